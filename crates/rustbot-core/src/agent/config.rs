@@ -1,8 +1,8 @@
 use crate::llm::LlmProvider;
+use crate::pruning::ContextPruning;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
-use std::process::Command;
 
 /// JSON-based agent configuration
 ///
@@ -27,6 +27,10 @@ pub struct JsonAgentConfig {
     #[serde(default = "default_version")]
     pub version: String,
 
+    /// File format version used by the migration runner (see `crate::migration`)
+    #[serde(default)]
+    pub schema_version: u32,
+
     /// Unique agent identifier (used as agent ID)
     pub name: String,
 
@@ -98,6 +102,13 @@ pub struct JsonAgentConfig {
     #[serde(rename = "mcpConfigFile")]
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub mcp_config_file: Option<String>,
+
+    /// How the chat history is reduced when it grows too long
+    ///
+    /// Example: `{"strategy": "summarize_then_drop", "maxMessages": 40}`
+    #[serde(rename = "contextPruning")]
+    #[serde(default)]
+    pub context_pruning: ContextPruning,
 }
 
 fn default_version() -> String {
@@ -185,10 +196,12 @@ impl JsonAgentConfig {
     /// - File I/O errors (file not found, permission denied)
     /// - JSON parsing errors (invalid syntax, missing required fields)
     pub fn from_file(path: &Path) -> Result<Self> {
-        let content = std::fs::read_to_string(path)
+        // Upgrades (and rewrites) files saved in an older format
+        let value = crate::migration::AGENT_CONFIG_SCHEMA
+            .load_file(path)
             .with_context(|| format!("Failed to read agent config from {:?}", path))?;
 
-        Self::from_json(&content)
+        serde_json::from_value(value)
             .with_context(|| format!("Failed to parse agent config from {:?}", path))
     }
 
@@ -198,20 +211,25 @@ impl JsonAgentConfig {
     /// - JSON parsing errors (invalid syntax, type mismatches)
     /// - Missing required fields (name, provider, model, instruction)
     pub fn from_json(json: &str) -> Result<Self> {
+        let (value, _) = crate::migration::AGENT_CONFIG_SCHEMA
+            .migrate_str(json)
+            .context("Failed to deserialize agent configuration")?;
         let config: JsonAgentConfig =
-            serde_json::from_str(json).context("Failed to deserialize agent configuration")?;
+            serde_json::from_value(value).context("Failed to deserialize agent configuration")?;
 
         Ok(config)
     }
 
-    /// Resolve environment variables in configuration strings
+    /// Resolve environment variables and secret references in configuration strings
     ///
-    /// Supports two syntaxes:
+    /// Supports these syntaxes:
     /// - `${VAR_NAME}` - Required variable (error if not found)
     /// - `${VAR_NAME:-default}` - Optional variable with fallback
+    /// - `op://`, `keychain://`, `vault://`, `aws-sm://` - Secret references
     ///
     /// # Errors
     /// - Environment variable not found (for required variables)
+    /// - Secret reference could not be read
     pub fn resolve_env_vars(&mut self) -> Result<()> {
         if let Some(api_key) = &self.api_key {
             self.api_key = Some(resolve_env_var(api_key)?);
@@ -354,158 +372,19 @@ impl JsonAgentConfig {
     }
 }
 
-/// Read a secret from 1Password using the CLI
+/// Resolve a secret reference or environment variable in a config value
 ///
-/// Supports the `op://vault/item/field` reference format used by 1Password CLI.
-///
-/// # Arguments
-/// * `reference` - 1Password secret reference (e.g., "op://Private/API Keys/credential")
-///
-/// # Returns
-/// * `Ok(String)` - The secret value from 1Password
-/// * `Err` - If `op` CLI is not installed, not authenticated, or reference is invalid
+/// Supports every reference `crate::services::resolve_secret` knows
+/// (`op://`, `${VAR}`, `${VAR:-default}`, `keychain://`, `vault://`,
+/// `aws-sm://`); plain values are returned as-is.
 ///
 /// # Example
 /// ```ignore
-/// let secret = read_1password_secret("op://Private/rustbot/api_key")?;
-/// ```
-///
-/// # Error Cases
-/// - `op` CLI not installed: Suggests installation via `brew install 1password-cli`
-/// - Not authenticated: Suggests running `op signin`
-/// - Invalid reference format: Must start with `op://`
-/// - Empty secret: 1Password returned empty value
-///
-/// # Requirements
-/// - 1Password CLI must be installed and available in PATH
-/// - User must be signed in: `op signin`
-/// - Secret reference must exist in user's 1Password account
-fn read_1password_secret(reference: &str) -> Result<String> {
-    // Validate reference format
-    if !reference.starts_with("op://") {
-        anyhow::bail!(
-            "Invalid 1Password reference format: '{}'. Must start with 'op://'",
-            reference
-        );
-    }
-
-    // Execute `op read` command
-    let output = Command::new("op")
-        .arg("read")
-        .arg(reference)
-        .output()
-        .with_context(|| {
-            format!(
-                "Failed to execute 1Password CLI. Is it installed?\n\
-                 Install: brew install 1password-cli\n\
-                 Reference: {}",
-                reference
-            )
-        })?;
-
-    // Check if command succeeded
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-
-        // Provide helpful error messages based on common failures
-        if stderr.contains("not currently signed in") || stderr.contains("signed out") {
-            anyhow::bail!(
-                "Not signed in to 1Password. Run: op signin\n\
-                 Reference: {}",
-                reference
-            );
-        } else if stderr.contains("isn't an item") || stderr.contains("not found") {
-            anyhow::bail!(
-                "1Password secret not found: {}\n\
-                 Error: {}",
-                reference,
-                stderr.trim()
-            );
-        } else {
-            anyhow::bail!(
-                "Failed to read 1Password secret: {}\n\
-                 Error: {}",
-                reference,
-                stderr.trim()
-            );
-        }
-    }
-
-    // Parse output
-    let secret = String::from_utf8(output.stdout)
-        .with_context(|| format!("1Password returned invalid UTF-8 for: {}", reference))?
-        .trim()
-        .to_string();
-
-    // Ensure secret is not empty
-    if secret.is_empty() {
-        anyhow::bail!(
-            "1Password secret is empty: {}",
-            reference
-        );
-    }
-
-    Ok(secret)
-}
-
-/// Resolve environment variable or 1Password secret reference
-///
-/// Supports three formats:
-/// 1. `op://vault/item/field` - 1Password secret reference
-/// 2. `${VAR}` - Environment variable (required)
-/// 3. `${VAR:-default}` - Environment variable with fallback default
-///
-/// # Arguments
-/// * `value` - The value to resolve
-///
-/// # Returns
-/// * Plain values are returned as-is
-/// * `op://` references are resolved via 1Password CLI
-/// * `${VAR}` references are resolved from environment
-///
-/// # Example
-/// ```ignore
-/// // 1Password secret
-/// let api_key = resolve_env_var("op://Private/rustbot/api_key")?;
-///
-/// // Environment variable
 /// let api_key = resolve_env_var("${OPENROUTER_API_KEY}")?;
-///
-/// // Environment variable with default
-/// let model = resolve_env_var("${MODEL:-anthropic/claude-sonnet-4}")?;
-///
-/// // Plain value
 /// let name = resolve_env_var("assistant")?; // Returns "assistant"
 /// ```
 fn resolve_env_var(value: &str) -> Result<String> {
-    // Check for 1Password secret reference first
-    if value.starts_with("op://") {
-        return read_1password_secret(value);
-    }
-
-    // Not an environment variable reference
-    if !value.starts_with("${") || !value.ends_with('}') {
-        return Ok(value.to_string());
-    }
-
-    // Extract variable expression
-    let var_expr = &value[2..value.len() - 1];
-
-    // Check for default value syntax: ${VAR:-default}
-    if let Some(pos) = var_expr.find(":-") {
-        let var_name = &var_expr[..pos];
-        let default_value = &var_expr[pos + 2..];
-
-        // Return env var if set, otherwise use default
-        match std::env::var(var_name) {
-            Ok(val) if !val.is_empty() => Ok(val),
-            _ => Ok(default_value.to_string()),
-        }
-    } else {
-        // No default, variable is required
-        std::env::var(var_expr)
-            .with_context(|| format!("Environment variable '{}' not found", var_expr))
-    }
+    Ok(crate::services::resolve_secret(value)?)
 }
 
 #[cfg(test)]
@@ -623,6 +502,7 @@ mod tests {
 
         let config = JsonAgentConfig {
             version: "1.0".to_string(),
+            schema_version: 1,
             name: "test".to_string(),
             description: String::new(),
             provider: LlmProvider::OpenRouter,
@@ -638,6 +518,7 @@ mod tests {
             metadata: None,
             mcp_extensions: Vec::new(),
             mcp_config_file: None,
+            context_pruning: ContextPruning::default(),
         };
 
         let result = config.validate();
@@ -654,6 +535,7 @@ mod tests {
 
         let config = JsonAgentConfig {
             version: "1.0".to_string(),
+            schema_version: 1,
             name: "test".to_string(),
             description: String::new(),
             provider: LlmProvider::OpenRouter,
@@ -673,6 +555,7 @@ mod tests {
             metadata: None,
             mcp_extensions: Vec::new(),
             mcp_config_file: None,
+            context_pruning: ContextPruning::default(),
         };
 
         let result = config.validate();
@@ -686,6 +569,7 @@ mod tests {
     fn test_ollama_no_api_key_required() {
         let config = JsonAgentConfig {
             version: "1.0".to_string(),
+            schema_version: 1,
             name: "test".to_string(),
             description: String::new(),
             provider: LlmProvider::Ollama,
@@ -701,6 +585,7 @@ mod tests {
             metadata: None,
             mcp_extensions: Vec::new(),
             mcp_config_file: None,
+            context_pruning: ContextPruning::default(),
         };
 
         // Ollama doesn't require API key, validation should pass
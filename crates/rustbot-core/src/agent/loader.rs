@@ -1,4 +1,5 @@
 use super::config::JsonAgentConfig;
+use super::validation::{self, BrokenAgent};
 use crate::agent::AgentConfig;
 use anyhow::{Context, Result};
 use std::path::{Path, PathBuf};
@@ -19,9 +20,10 @@ use std::path::{Path, PathBuf};
 /// - Flexibility: Filesystem-based vs. database-backed (filesystem simpler for local app)
 ///
 /// Error Handling: Individual agent load failures are logged but don't block other agents.
-/// This allows partial degradation rather than all-or-nothing failure.
+/// This allows partial degradation rather than all-or-nothing failure. `find_broken`
+/// lists the presets that were skipped, with the problems `validation` finds in them.
 ///
-/// Extension Points: Could add agent validation, dependency resolution, or database backend
+/// Extension Points: Could add dependency resolution or a database backend
 /// if agent count exceeds 100 or network deployment is needed.
 pub struct AgentLoader {
     /// Directories to search for agent JSON files
@@ -98,7 +100,25 @@ impl AgentLoader {
     /// - Directory read errors
     /// - Individual agent parse errors are logged but don't fail the entire load
     pub fn load_from_directory(&self, path: &Path) -> Result<Vec<AgentConfig>> {
+        self.scan_directory(path).map(|(agents, _)| agents)
+    }
+
+    /// Presets in the search paths that can't be loaded, and why
+    ///
+    /// Directories that don't exist or can't be read are skipped.
+    pub fn find_broken(&self) -> Vec<BrokenAgent> {
+        self.search_paths
+            .iter()
+            .filter(|path| path.exists())
+            .filter_map(|path| self.scan_directory(path).ok())
+            .flat_map(|(_, broken)| broken)
+            .collect()
+    }
+
+    /// Load the agents in a directory, collecting the presets that fail
+    fn scan_directory(&self, path: &Path) -> Result<(Vec<AgentConfig>, Vec<BrokenAgent>)> {
         let mut agents = Vec::new();
+        let mut broken = Vec::new();
 
         let entries = std::fs::read_dir(path)
             .with_context(|| format!("Failed to read agent directory: {:?}", path))?;
@@ -122,11 +142,12 @@ impl AgentLoader {
                 Err(e) => {
                     tracing::error!("Failed to load agent from {:?}: {}", entry_path, e);
                     // Continue processing other agents
+                    broken.push(validation::diagnose_preset(&entry_path, &e));
                 }
             }
         }
 
-        Ok(agents)
+        Ok((agents, broken))
     }
 
     /// Load a single agent from a JSON file
@@ -191,6 +212,7 @@ impl AgentLoader {
             web_search_enabled: json.capabilities.web_search,
             mcp_extensions: json.mcp_extensions,
             mcp_config_file: json.mcp_config_file,
+            context_pruning: json.context_pruning,
         })
     }
 }
@@ -329,6 +351,19 @@ mod tests {
         // Only valid agent should load
         assert_eq!(agents.len(), 1);
         assert_eq!(agents[0].id, "valid");
+
+        // The invalid one is listed with what's wrong with it
+        let mut loader = AgentLoader::new();
+        loader.search_paths = vec![temp_path.to_path_buf()];
+        let broken = loader.find_broken();
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].path, temp_path.join("invalid.json"));
+        let fields: Vec<_> = broken[0]
+            .issues
+            .iter()
+            .filter_map(|issue| issue.field.as_deref())
+            .collect();
+        assert!(fields.contains(&"provider") && fields.contains(&"instruction"));
     }
 
     #[test]
@@ -4,7 +4,9 @@
 // Module Organization:
 // - config.rs: JSON-based agent configuration with multi-provider LLM support
 // - loader.rs: Directory-based agent discovery and loading
+// - templates.rs: Role templates and preset generation for the New Agent wizard
 // - tools.rs: Tool definitions for OpenAI-compatible function calling
+// - validation.rs: Preset schema checks with field and line-level messages
 // - Core Agent and AgentConfig types defined in this file
 //
 // Design Decision: Hybrid module structure
@@ -16,10 +18,13 @@
 
 pub mod config;
 pub mod loader;
+pub mod templates;
 pub mod tools;
+pub mod validation;
 
 use crate::events::{AgentStatus, Event, EventBus, EventKind};
 use crate::llm::{LlmAdapter, LlmRequest, Message as LlmMessage, ToolCall};
+use crate::pruning::ContextPruning;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -30,6 +35,44 @@ pub use config::{AgentCapabilities, AgentMetadata, JsonAgentConfig, ModelParamet
 pub use loader::AgentLoader;
 pub use tools::{FunctionDefinition, FunctionParameters, ToolDefinition};
 
+/// Parts of the system message an agent with `config` sends, each with a
+/// title (see `Agent::system_sections`)
+///
+/// # Arguments
+/// * `system_instructions` - Instructions shared by every agent
+/// * `config` - Agent whose instructions and personality follow them
+pub fn system_sections_for(
+    system_instructions: &str,
+    config: &AgentConfig,
+) -> Vec<(&'static str, String)> {
+    let mut parts = Vec::new();
+
+    // Add system-level instructions (shared)
+    if !system_instructions.is_empty() {
+        parts.push(("System instructions", system_instructions.to_string()));
+    }
+
+    // Add agent-specific instructions
+    if !config.instructions.is_empty() {
+        parts.push((
+            "Agent instructions",
+            format!("## Agent Instructions\n\n{}", config.instructions),
+        ));
+    }
+
+    // Add agent personality (only if defined for this agent)
+    if let Some(personality) = &config.personality {
+        if !personality.is_empty() {
+            parts.push((
+                "Agent personality",
+                format!("## Agent Personality\n\n{}", personality),
+            ));
+        }
+    }
+
+    parts
+}
+
 /// Runtime configuration for an agent
 ///
 /// This is the configuration used by the Agent runtime. It can be created:
@@ -86,6 +129,10 @@ pub struct AgentConfig {
     /// Example: "assistant_mcp.json" loads from ~/.rustbot/mcp_configs/assistant_mcp.json
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub mcp_config_file: Option<String>,
+
+    /// How this agent's chat history is reduced when it grows too long
+    #[serde(default)]
+    pub context_pruning: ContextPruning,
 }
 
 impl AgentConfig {
@@ -102,6 +149,7 @@ impl AgentConfig {
             web_search_enabled: false,
             mcp_extensions: Vec::new(),
             mcp_config_file: None,
+            context_pruning: ContextPruning::default(),
         }
     }
 
@@ -118,6 +166,7 @@ impl AgentConfig {
             web_search_enabled: false,
             mcp_extensions: Vec::new(),
             mcp_config_file: None,
+            context_pruning: ContextPruning::default(),
         }
     }
 
@@ -208,6 +257,9 @@ pub struct Agent {
 
     /// Current status of the agent
     status: AgentStatus,
+
+    /// Model requested instead of the adapter default (`/model` in the chat)
+    model_override: Option<String>,
 }
 
 impl Agent {
@@ -229,6 +281,7 @@ impl Agent {
             runtime,
             system_instructions,
             status: AgentStatus::Idle,
+            model_override: None,
         }
     }
 
@@ -247,6 +300,62 @@ impl Agent {
         &self.status
     }
 
+    /// Model requested instead of the adapter default, if any
+    pub fn model_override(&self) -> Option<&str> {
+        self.model_override.as_deref()
+    }
+
+    /// Model for this agent's messages: the override, else the configured model
+    pub fn model(&self) -> &str {
+        self.model_override.as_deref().unwrap_or(&self.config.model)
+    }
+
+    /// Request another model for this agent's messages (None = adapter default)
+    pub fn set_model_override(&mut self, model: Option<String>) {
+        self.model_override = model;
+    }
+
+    /// Replace the instructions shared by every agent (a new instruction
+    /// profile); used from the next message on
+    pub fn set_system_instructions(&mut self, instructions: String) {
+        self.system_instructions = instructions;
+    }
+
+    /// How this agent's chat history is reduced (see `crate::pruning`)
+    pub fn context_pruning(&self) -> &ContextPruning {
+        &self.config.context_pruning
+    }
+
+    /// Fold dropped history into a running summary with this agent's model
+    ///
+    /// Used by the summarize-then-drop pruning strategy.
+    ///
+    /// # Arguments
+    /// * `previous` - Summary of turns dropped earlier, if any
+    /// * `dropped` - Messages just dropped from the history, oldest first
+    ///
+    /// # Errors
+    /// - The model request fails or returns an empty summary
+    pub async fn summarize(
+        &self,
+        previous: Option<&str>,
+        dropped: &[LlmMessage],
+    ) -> Result<String> {
+        let mut request = LlmRequest::new(crate::pruning::summary_request(previous, dropped));
+        request.model = self.model_override.clone();
+        let input_tokens = request_tokens(&request.messages);
+        let response = self.llm_adapter.complete_chat(request).await?;
+        record_request(
+            input_tokens,
+            crate::tokenizer::count_tokens(&response.content),
+        );
+        let summary = response.content.trim();
+        if summary.is_empty() {
+            anyhow::bail!("The model returned an empty summary");
+        }
+        Ok(summary.to_string())
+    }
+
     /// Update the agent's status and publish status change event
     fn set_status(&mut self, status: AgentStatus) {
         self.status = status.clone();
@@ -263,31 +372,21 @@ impl Agent {
         let _ = self.event_bus.publish(event);
     }
 
+    /// Parts of the system message, each with a title for the context inspector
+    ///
+    /// Empty parts are left out; joining the texts with blank lines gives the
+    /// system message sent to the model.
+    pub fn system_sections(&self) -> Vec<(&'static str, String)> {
+        system_sections_for(&self.system_instructions, &self.config)
+    }
+
     /// Build the complete system message for this agent
     fn build_system_message(&self) -> String {
-        let mut parts = Vec::new();
-
-        // Add system-level instructions (shared)
-        if !self.system_instructions.is_empty() {
-            parts.push(self.system_instructions.clone());
-        }
-
-        // Add agent-specific instructions
-        if !self.config.instructions.is_empty() {
-            parts.push(format!(
-                "## Agent Instructions\n\n{}",
-                self.config.instructions
-            ));
-        }
-
-        // Add agent personality (only if defined for this agent)
-        if let Some(personality) = &self.config.personality {
-            if !personality.is_empty() {
-                parts.push(format!("## Agent Personality\n\n{}", personality));
-            }
-        }
-
-        parts.join("\n\n")
+        self.system_sections()
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect::<Vec<_>>()
+            .join("\n\n")
     }
 
     /// Process a user message and generate a response
@@ -316,6 +415,7 @@ impl Agent {
 
         // Create request with web search if enabled for this agent
         let mut request = LlmRequest::new(api_messages);
+        request.model = self.model_override.clone();
         request.web_search = Some(self.config.web_search_enabled);
 
         // Update status to responding
@@ -383,23 +483,24 @@ impl Agent {
     /// 4. Finally stream the response
     ///
     /// # Arguments
-    /// * `user_message` - The message to process
+    /// * `user_message` - The user message to process (text and any attached images)
     /// * `context_messages` - Previous conversation messages for context
     /// * `tools` - Optional tool definitions (for primary agent delegation)
+    /// * `correlation_id` - Optional ID of the user turn, attached to every status event
     pub fn process_message_nonblocking(
         &self,
-        user_message: String,
+        user_message: LlmMessage,
         context_messages: Vec<LlmMessage>,
         tools: Option<Vec<ToolDefinition>>,
+        correlation_id: Option<String>,
     ) -> mpsc::UnboundedReceiver<Result<AgentResponse>> {
         let (result_tx, result_rx) = mpsc::unbounded_channel();
 
         // Clone everything we need for the async task
         let llm_adapter = Arc::clone(&self.llm_adapter);
-        let system_instructions = self.system_instructions.clone();
-        let config_instructions = self.config.instructions.clone();
-        let config_personality = self.config.personality.clone();
+        let system_content = self.build_system_message();
         let web_search_enabled = self.config.web_search_enabled;
+        let model_override = self.model_override.clone();
         let runtime = self.runtime.clone();
         let agent_id = self.config.id.clone();
         let event_bus = Arc::clone(&self.event_bus);
@@ -407,27 +508,9 @@ impl Agent {
         // Spawn async task
         runtime.spawn(async move {
             let agent_start = std::time::Instant::now();
-            tracing::debug!("⏱️  [AGENT] Processing started");
-
-            // Build system message
-            let mut parts = Vec::new();
-            if !system_instructions.is_empty() {
-                parts.push(system_instructions);
-            }
-            if !config_instructions.is_empty() {
-                parts.push(format!("## Agent Instructions\n\n{}", config_instructions));
-            }
-            // Only include personality if defined
-            if let Some(personality) = config_personality {
-                if !personality.is_empty() {
-                    parts.push(format!("## Agent Personality\n\n{}", personality));
-                }
-            }
-            let system_content = parts.join("\n\n");
-
             tracing::debug!(
-                "⏱️  [AGENT] System message built in {:?}",
-                agent_start.elapsed()
+                "⏱️  [AGENT] Processing started (correlation_id: {:?})",
+                correlation_id
             );
 
             // Build complete message history
@@ -436,10 +519,11 @@ impl Agent {
                 api_messages.push(LlmMessage::new("system", system_content));
             }
             api_messages.extend(context_messages);
-            api_messages.push(LlmMessage::new("user", user_message));
+            api_messages.push(user_message);
 
             // Create request with web search if enabled
             let mut request = LlmRequest::new(api_messages.clone());
+            request.model = model_override;
             request.web_search = Some(web_search_enabled);
 
             let result = if let Some(tool_defs) = tools {
@@ -473,8 +557,15 @@ impl Agent {
                                 response.content
                             };
 
-                            api_messages
-                                .push(LlmMessage::with_tool_calls(content, tool_calls.clone()));
+                            let assistant_msg =
+                                LlmMessage::with_tool_calls(content, tool_calls.clone());
+                            // The message was counted when it was sent; the tool
+                            // calls aren't part of the answer stored later
+                            record_request(
+                                0,
+                                crate::request_preview::message_tokens(&assistant_msg) as usize,
+                            );
+                            api_messages.push(assistant_msg);
 
                             Ok(AgentResponse::NeedsToolExecution {
                                 tool_calls,
@@ -496,7 +587,8 @@ impl Agent {
                                     status: AgentStatus::Responding,
                                 },
                             );
-                            let _ = event_bus.publish(event);
+                            let _ = event_bus
+                                .publish(event.with_correlation_id(correlation_id.clone()));
 
                             Ok(AgentResponse::StreamingResponse(rx))
                         }
@@ -527,7 +619,8 @@ impl Agent {
                                 status: AgentStatus::Responding,
                             },
                         );
-                        let _ = event_bus.publish(event);
+                        let _ =
+                            event_bus.publish(event.with_correlation_id(correlation_id.clone()));
 
                         Ok(AgentResponse::StreamingResponse(rx))
                     }
@@ -548,7 +641,7 @@ impl Agent {
                         status: AgentStatus::Error(e.to_string()),
                     },
                 );
-                let _ = event_bus.publish(event);
+                let _ = event_bus.publish(event.with_correlation_id(correlation_id.clone()));
             }
 
             let _ = result_tx.send(result);
@@ -559,15 +652,20 @@ impl Agent {
 
     /// Process a follow-up request with tool results
     /// Used after tools have been executed to get the final response
+    ///
+    /// `correlation_id` should match the one passed to `process_message_nonblocking`
+    /// so the follow-up status events stay grouped with the original turn.
     pub fn process_with_results(
         &self,
         messages_with_tool_results: Vec<LlmMessage>,
+        correlation_id: Option<String>,
     ) -> mpsc::UnboundedReceiver<Result<mpsc::UnboundedReceiver<String>>> {
         let (result_tx, result_rx) = mpsc::unbounded_channel();
 
         // Clone everything we need
         let llm_adapter = Arc::clone(&self.llm_adapter);
         let web_search_enabled = self.config.web_search_enabled;
+        let model_override = self.model_override.clone();
         let runtime = self.runtime.clone();
         let agent_id = self.config.id.clone();
         let event_bus = Arc::clone(&self.event_bus);
@@ -575,8 +673,12 @@ impl Agent {
         runtime.spawn(async move {
             // Create request with the updated message history (includes tool results)
             let mut request = LlmRequest::new(messages_with_tool_results);
+            request.model = model_override;
             request.web_search = Some(web_search_enabled);
 
+            // The answer is counted when it's stored
+            record_request(request_tokens(&request.messages), 0);
+
             let (tx, rx) = mpsc::unbounded_channel();
 
             let result = llm_adapter.stream_chat(request, tx).await;
@@ -592,7 +694,7 @@ impl Agent {
                             status: AgentStatus::Responding,
                         },
                     );
-                    let _ = event_bus.publish(event);
+                    let _ = event_bus.publish(event.with_correlation_id(correlation_id.clone()));
 
                     Ok(rx)
                 }
@@ -608,7 +710,7 @@ impl Agent {
                             status: AgentStatus::Error(e.to_string()),
                         },
                     );
-                    let _ = event_bus.publish(event);
+                    let _ = event_bus.publish(event.with_correlation_id(correlation_id.clone()));
 
                     Err(e)
                 }
@@ -621,6 +723,20 @@ impl Agent {
     }
 }
 
+/// Estimated tokens of a request's messages
+fn request_tokens(messages: &[LlmMessage]) -> usize {
+    messages
+        .iter()
+        .map(|message| crate::request_preview::message_tokens(message) as usize)
+        .sum()
+}
+
+/// Count a request other than the one that sends the user's message
+/// against the daily quota (see `crate::quota`)
+fn record_request(input_tokens: usize, output_tokens: usize) {
+    crate::quota::tracker().record_request(input_tokens, output_tokens);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -667,5 +783,15 @@ mod tests {
         assert!(system_msg.contains("System context here."));
         assert!(system_msg.contains("You are a helpful assistant."));
         assert!(system_msg.contains("Be friendly and concise."));
+
+        let titles: Vec<&str> = agent.system_sections().iter().map(|(t, _)| *t).collect();
+        assert_eq!(
+            titles,
+            vec![
+                "System instructions",
+                "Agent instructions",
+                "Agent personality"
+            ]
+        );
     }
 }
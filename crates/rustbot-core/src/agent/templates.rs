@@ -0,0 +1,281 @@
+// Role templates and preset generation for the "New Agent" wizard
+//
+// Design Decision: The wizard writes an ordinary preset file
+//
+// Rationale: Agents are defined by JSON files in agents/presets and
+// agents/custom, and creating one used to mean copying an existing file and
+// editing it by hand. The wizard collects the same fields step by step (name,
+// role template, model, capabilities, MCP extensions) and `AgentDraft`
+// turns them into a `JsonAgentConfig` saved to agents/custom, so the result
+// loads, migrates and can be edited exactly like a hand-written preset.
+//
+// Trade-offs:
+// - New agents are specialists (callable by the primary agent); making one
+//   primary still means editing its file
+// - Role templates are a built-in list; a template only pre-fills the
+//   instructions, which the user can change before saving
+
+use super::config::{AgentCapabilities, JsonAgentConfig, ModelParameters};
+use crate::llm::LlmProvider;
+use crate::pruning::ContextPruning;
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+
+/// A starting point for a new agent's instructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoleTemplate {
+    pub id: &'static str,
+    pub name: &'static str,
+
+    /// One line shown next to the template in the wizard
+    pub summary: &'static str,
+
+    /// Instructions the agent starts with
+    pub instruction: &'static str,
+
+    /// Whether the template needs web search to be useful
+    pub web_search: bool,
+}
+
+/// Built-in role templates, in the order the wizard lists them
+pub const ROLE_TEMPLATES: &[RoleTemplate] = &[
+    RoleTemplate {
+        id: "assistant",
+        name: "General assistant",
+        summary: "Answers questions and helps with everyday tasks",
+        instruction: "You are a helpful assistant. Answer clearly and concisely, and ask \
+                      for clarification when a request is ambiguous.",
+        web_search: false,
+    },
+    RoleTemplate {
+        id: "researcher",
+        name: "Researcher",
+        summary: "Looks things up on the web and cites sources",
+        instruction: "You are a research assistant. Search the web for current \
+                      information, compare several sources, and cite the sources you \
+                      used. Say so when sources disagree or information is uncertain.",
+        web_search: true,
+    },
+    RoleTemplate {
+        id: "coder",
+        name: "Programmer",
+        summary: "Writes, explains and reviews code",
+        instruction: "You are an experienced software engineer. Write correct, idiomatic \
+                      code with brief explanations. When reviewing code, point out bugs \
+                      first, then readability and performance issues.",
+        web_search: false,
+    },
+    RoleTemplate {
+        id: "writer",
+        name: "Writer",
+        summary: "Drafts and edits text",
+        instruction: "You are a skilled writer and editor. Match the tone the user asks \
+                      for, keep sentences short, and explain significant edits.",
+        web_search: false,
+    },
+    RoleTemplate {
+        id: "blank",
+        name: "Blank",
+        summary: "Write the instructions yourself",
+        instruction: "",
+        web_search: false,
+    },
+];
+
+/// Models offered when choosing an agent's model, as (id, label)
+pub const SUGGESTED_MODELS: &[(&str, &str)] = &[
+    ("openai/gpt-5.1-turbo", "GPT-5.1 Turbo"),
+    ("openai/gpt-4o", "GPT-4o"),
+    ("anthropic/claude-opus-4", "Claude Opus 4"),
+    ("anthropic/claude-sonnet-4.5", "Claude Sonnet 4.5"),
+    ("anthropic/claude-sonnet-4", "Claude Sonnet 4"),
+    ("openai/gpt-4", "GPT-4"),
+];
+
+/// Agent ID for a display name: lowercase ASCII letters, digits and `_`
+///
+/// "Research Helper!" becomes "research_helper".
+pub fn agent_id(name: &str) -> String {
+    let mut id = String::new();
+    for c in name.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            id.push(c.to_ascii_lowercase());
+        } else if !id.is_empty() && !id.ends_with('_') {
+            id.push('_');
+        }
+    }
+    id.trim_end_matches('_').to_string()
+}
+
+/// Everything the wizard has collected so far
+#[derive(Debug, Clone)]
+pub struct AgentDraft {
+    /// Display name as typed
+    pub name: String,
+
+    /// Index into `ROLE_TEMPLATES`
+    pub template: usize,
+    pub instruction: String,
+    pub model: String,
+    pub web_search: bool,
+    pub image_input: bool,
+
+    /// Installed MCP extension IDs to enable for the agent
+    pub mcp_extensions: Vec<String>,
+}
+
+impl Default for AgentDraft {
+    fn default() -> Self {
+        let mut draft = Self {
+            name: String::new(),
+            template: 0,
+            instruction: String::new(),
+            model: SUGGESTED_MODELS[0].0.to_string(),
+            web_search: false,
+            image_input: false,
+            mcp_extensions: Vec::new(),
+        };
+        draft.apply_template(0);
+        draft
+    }
+}
+
+impl AgentDraft {
+    /// Switch to a template, replacing the instructions and web search flag
+    pub fn apply_template(&mut self, index: usize) {
+        if let Some(template) = ROLE_TEMPLATES.get(index) {
+            self.template = index;
+            self.instruction = template.instruction.to_string();
+            self.web_search = template.web_search;
+        }
+    }
+
+    /// Check the draft against the agents that already exist
+    ///
+    /// # Errors
+    /// - Name has no letters or digits, or its ID is taken
+    /// - Instructions or model are empty
+    pub fn validate(&self, existing_ids: &[String]) -> Result<()> {
+        let id = agent_id(&self.name);
+        if id.is_empty() {
+            bail!("Give the agent a name with at least one letter or digit");
+        }
+        if existing_ids.contains(&id) {
+            bail!("An agent with the ID '{}' already exists", id);
+        }
+        if self.instruction.trim().is_empty() {
+            bail!("Describe what the agent should do");
+        }
+        if self.model.trim().is_empty() {
+            bail!("Choose a model");
+        }
+        Ok(())
+    }
+
+    /// The preset file contents for this draft
+    pub fn to_json_config(&self) -> JsonAgentConfig {
+        let description = match ROLE_TEMPLATES.get(self.template) {
+            Some(template) if template.id != "blank" => {
+                format!("{} ({})", self.name.trim(), template.name)
+            }
+            _ => self.name.trim().to_string(),
+        };
+        JsonAgentConfig {
+            version: "1.0".to_string(),
+            schema_version: crate::migration::AGENT_CONFIG_SCHEMA.current,
+            name: agent_id(&self.name),
+            description,
+            provider: LlmProvider::OpenRouter,
+            model: self.model.trim().to_string(),
+            api_key: None,
+            api_base: None,
+            instruction: self.instruction.trim().to_string(),
+            personality: None,
+            parameters: ModelParameters::default(),
+            capabilities: AgentCapabilities {
+                web_search: self.web_search,
+                image_input: self.image_input,
+                ..AgentCapabilities::default()
+            },
+            enabled: true,
+            is_primary: false,
+            metadata: None,
+            mcp_extensions: self.mcp_extensions.clone(),
+            mcp_config_file: None,
+            context_pruning: ContextPruning::default(),
+        }
+    }
+}
+
+/// Write a preset to `agents_dir/custom/<name>.json`
+///
+/// # Returns
+/// Path of the new file
+///
+/// # Errors
+/// - A file for this agent already exists
+/// - The directory or file can't be written
+pub fn save_custom_preset(agents_dir: &Path, config: &JsonAgentConfig) -> Result<PathBuf> {
+    let dir = agents_dir.join("custom");
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create agent directory {:?}", dir))?;
+
+    let path = dir.join(format!("{}.json", config.name));
+    if path.exists() {
+        bail!("{} already exists", path.display());
+    }
+    let json = serde_json::to_string_pretty(config).context("Failed to serialize agent preset")?;
+    std::fs::write(&path, json)
+        .with_context(|| format!("Failed to write agent preset to {:?}", path))?;
+
+    tracing::info!("🤖 Created agent preset {:?}", path);
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_agent_id() {
+        assert_eq!(agent_id("Research Helper!"), "research_helper");
+        assert_eq!(agent_id("  code--review 2 "), "code_review_2");
+        assert_eq!(agent_id("???"), "");
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut draft = AgentDraft {
+            name: "Researcher".to_string(),
+            ..AgentDraft::default()
+        };
+        assert!(draft.validate(&["assistant".to_string()]).is_ok());
+        assert!(draft.validate(&["researcher".to_string()]).is_err());
+
+        draft.apply_template(ROLE_TEMPLATES.len() - 1);
+        assert!(draft.validate(&[]).is_err());
+    }
+
+    #[test]
+    fn test_saved_preset_loads() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut draft = AgentDraft {
+            name: "Web Researcher".to_string(),
+            mcp_extensions: vec!["ai.exa/exa".to_string()],
+            ..AgentDraft::default()
+        };
+        draft.apply_template(1);
+
+        let path = save_custom_preset(temp_dir.path(), &draft.to_json_config()).unwrap();
+        assert!(path.ends_with("custom/web_researcher.json"));
+        assert!(save_custom_preset(temp_dir.path(), &draft.to_json_config()).is_err());
+
+        let config = JsonAgentConfig::from_file(&path).unwrap();
+        assert_eq!(config.name, "web_researcher");
+        assert_eq!(config.description, "Web Researcher (Researcher)");
+        assert!(config.capabilities.web_search);
+        assert!(!config.is_primary);
+        assert_eq!(config.mcp_extensions, vec!["ai.exa/exa"]);
+    }
+}
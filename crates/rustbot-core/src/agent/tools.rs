@@ -202,6 +202,7 @@ impl ToolDefinition {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::pruning::ContextPruning;
 
     #[test]
     fn test_web_search_tool_definition() {
@@ -216,6 +217,7 @@ mod tests {
             web_search_enabled: true,
             mcp_extensions: Vec::new(),
             mcp_config_file: None,
+            context_pruning: ContextPruning::default(),
         };
 
         let tool = ToolDefinition::from_agent(&agent);
@@ -244,6 +246,7 @@ mod tests {
             web_search_enabled: false,
             mcp_extensions: Vec::new(),
             mcp_config_file: None,
+            context_pruning: ContextPruning::default(),
         };
 
         let tool = ToolDefinition::from_agent(&agent);
@@ -269,6 +272,7 @@ mod tests {
             web_search_enabled: false,
             mcp_extensions: Vec::new(),
             mcp_config_file: None,
+            context_pruning: ContextPruning::default(),
         };
 
         // This should panic
@@ -289,6 +293,7 @@ mod tests {
             web_search_enabled: true,
             mcp_extensions: Vec::new(),
             mcp_config_file: None,
+            context_pruning: ContextPruning::default(),
         };
 
         // This should panic
@@ -309,6 +314,7 @@ mod tests {
                 web_search_enabled: false,
                 mcp_extensions: Vec::new(),
                 mcp_config_file: None,
+                context_pruning: ContextPruning::default(),
             },
             AgentConfig {
                 id: "web_search".to_string(),
@@ -321,6 +327,7 @@ mod tests {
                 web_search_enabled: true,
                 mcp_extensions: Vec::new(),
                 mcp_config_file: None,
+                context_pruning: ContextPruning::default(),
             },
             AgentConfig {
                 id: "code_helper".to_string(),
@@ -333,6 +340,7 @@ mod tests {
                 web_search_enabled: false,
                 mcp_extensions: Vec::new(),
                 mcp_config_file: None,
+                context_pruning: ContextPruning::default(),
             },
         ];
 
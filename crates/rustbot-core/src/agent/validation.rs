@@ -0,0 +1,469 @@
+// Validation of agent JSON presets
+//
+// Design Decision: Check the raw JSON against the preset schema before the
+// typed parse, and report every problem with the line it's on
+//
+// Rationale: serde stops at the first mismatch and reports it as "invalid
+// type: integer, expected a string" without saying which field, and the
+// loader used to log that and drop the agent, so a typo in a preset made the
+// agent silently disappear. Walking the JSON value first finds every missing
+// field, wrong type and out-of-range parameter at once, names the field by
+// its JSON key (`parameters.temperature`) and points at its line. The typed
+// parse and `JsonAgentConfig::validate` (API keys) still run afterwards for
+// whatever the schema can't see.
+//
+// Unknown keys are reported as warnings: serde ignores them, but they're
+// usually a misspelt field ("instructions", "is_primary") whose setting has
+// no effect.
+
+use super::config::JsonAgentConfig;
+use super::AgentConfig;
+use crate::llm::LlmProvider;
+use serde_json::Value;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A problem with a preset
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresetIssue {
+    /// JSON path of the field, e.g. `parameters.temperature` (None = the
+    /// whole preset)
+    pub field: Option<String>,
+    /// 1-based line the problem is on, when it can be found
+    pub line: Option<usize>,
+    pub message: String,
+    /// The preset still loads, but probably not as intended
+    pub warning: bool,
+}
+
+impl PresetIssue {
+    fn error(field: Option<&str>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.map(str::to_string),
+            line: None,
+            message: message.into(),
+            warning: false,
+        }
+    }
+
+    fn warning(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            warning: true,
+            ..Self::error(Some(field), message)
+        }
+    }
+}
+
+impl fmt::Display for PresetIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        if let Some(field) = &self.field {
+            write!(f, "{}: ", field)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// A preset file that couldn't be loaded, and why
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokenAgent {
+    pub path: PathBuf,
+    /// At least one of them is an error
+    pub issues: Vec<PresetIssue>,
+}
+
+/// Expected JSON type of a field
+#[derive(Clone, Copy)]
+enum Kind {
+    String,
+    /// A string or null
+    OptionalString,
+    Bool,
+    Number,
+    /// Whole number, at least 1
+    Count,
+    Object,
+    Strings,
+}
+
+impl Kind {
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            Kind::String => value.is_string(),
+            Kind::OptionalString => value.is_string() || value.is_null(),
+            Kind::Bool => value.is_boolean(),
+            Kind::Number => value.is_number(),
+            Kind::Count => value.as_u64().is_some_and(|n| n >= 1),
+            Kind::Object => value.is_object(),
+            Kind::Strings => value
+                .as_array()
+                .is_some_and(|items| items.iter().all(Value::is_string)),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Kind::String => "a string",
+            Kind::OptionalString => "a string or null",
+            Kind::Bool => "true or false",
+            Kind::Number => "a number",
+            Kind::Count => "a whole number of at least 1",
+            Kind::Object => "an object",
+            Kind::Strings => "a list of strings",
+        }
+    }
+}
+
+/// Top-level fields: key, type, required
+const FIELDS: &[(&str, Kind, bool)] = &[
+    ("version", Kind::String, false),
+    ("schema_version", Kind::Count, false),
+    ("name", Kind::String, true),
+    ("description", Kind::String, false),
+    ("provider", Kind::String, true),
+    ("model", Kind::String, true),
+    ("apiKey", Kind::OptionalString, false),
+    ("apiBase", Kind::OptionalString, false),
+    ("instruction", Kind::String, true),
+    ("personality", Kind::OptionalString, false),
+    ("parameters", Kind::Object, false),
+    ("capabilities", Kind::Object, false),
+    ("enabled", Kind::Bool, false),
+    ("isPrimary", Kind::Bool, false),
+    ("metadata", Kind::Object, false),
+    ("mcpExtensions", Kind::Strings, false),
+    ("mcpConfigFile", Kind::OptionalString, false),
+    ("contextPruning", Kind::Object, false),
+];
+
+/// Fields of the nested objects
+const NESTED_FIELDS: &[(&str, &[(&str, Kind)])] = &[
+    (
+        "parameters",
+        &[
+            ("temperature", Kind::Number),
+            ("maxTokens", Kind::Count),
+            ("topP", Kind::Number),
+        ],
+    ),
+    (
+        "capabilities",
+        &[
+            ("webSearch", Kind::Bool),
+            ("imageInput", Kind::Bool),
+            ("streaming", Kind::Bool),
+        ],
+    ),
+    (
+        "metadata",
+        &[
+            ("author", Kind::OptionalString),
+            ("created", Kind::OptionalString),
+            ("version", Kind::OptionalString),
+            ("tags", Kind::Strings),
+        ],
+    ),
+    (
+        "contextPruning",
+        &[("strategy", Kind::String), ("maxMessages", Kind::Count)],
+    ),
+];
+
+/// Every problem with a preset's JSON, in the order they appear
+///
+/// Checks the JSON syntax, the preset schema (required fields, types,
+/// providers, parameter ranges, unknown keys) and then what loading checks
+/// (secret references and API keys).
+///
+/// # Arguments
+/// * `json` - Content of the preset file
+///
+/// # Returns
+/// No issues if the preset loads cleanly
+pub fn validate_preset(json: &str) -> Vec<PresetIssue> {
+    if let Err(e) = serde_json::from_str::<Value>(json) {
+        return vec![PresetIssue {
+            line: Some(e.line()),
+            ..PresetIssue::error(None, format!("Invalid JSON: {}", syntax_message(&e)))
+        }];
+    }
+    let value = match crate::migration::AGENT_CONFIG_SCHEMA.migrate_str(json) {
+        Ok((migrated, _)) => migrated,
+        Err(e) => return vec![PresetIssue::error(None, format!("Can't upgrade: {}", e))],
+    };
+    let Some(fields) = value.as_object() else {
+        return vec![PresetIssue::error(None, "A preset must be a JSON object")];
+    };
+
+    let mut issues = Vec::new();
+    for (key, kind, required) in FIELDS {
+        match fields.get(*key) {
+            None if *required => issues.push(PresetIssue::error(Some(*key), "Missing")),
+            Some(field) if !kind.matches(field) => issues.push(PresetIssue::error(
+                Some(*key),
+                format!("Must be {}", kind.describe()),
+            )),
+            _ => {}
+        }
+    }
+    for key in fields.keys() {
+        if !FIELDS.iter().any(|(known, _, _)| known == key) {
+            issues.push(PresetIssue::warning(key, "Unknown field, ignored"));
+        }
+    }
+
+    for (parent, nested) in NESTED_FIELDS {
+        let Some(object) = fields.get(*parent).and_then(Value::as_object) else {
+            continue;
+        };
+        for (key, field) in object {
+            let path = format!("{}.{}", parent, key);
+            match nested.iter().find(|(known, _)| known == key) {
+                Some((_, kind)) if !kind.matches(field) => issues.push(PresetIssue::error(
+                    Some(path.as_str()),
+                    format!("Must be {}", kind.describe()),
+                )),
+                Some(_) => {}
+                None => issues.push(PresetIssue::warning(&path, "Unknown field, ignored")),
+            }
+        }
+    }
+    issues.extend(check_values(fields));
+
+    // Whatever the schema can't see, once it passes
+    if !issues.iter().any(|issue| !issue.warning) {
+        if let Err(e) = check_loads(value) {
+            issues.push(e);
+        }
+    }
+
+    for issue in &mut issues {
+        if issue.line.is_none() {
+            issue.line = issue
+                .field
+                .as_deref()
+                .and_then(|field| line_of(json, field));
+        }
+    }
+    issues.sort_by_key(|issue| issue.line.unwrap_or(usize::MAX));
+    issues
+}
+
+/// A preset that failed to load, with the problems `validate_preset` finds
+/// in it
+///
+/// # Arguments
+/// * `path` - The preset file
+/// * `error` - Why loading it failed, reported as is if validation finds
+///   nothing (e.g. the file can't be read)
+pub fn diagnose_preset(path: &Path, error: &anyhow::Error) -> BrokenAgent {
+    let mut issues = std::fs::read_to_string(path)
+        .map(|json| validate_preset(&json))
+        .unwrap_or_default();
+    if !has_errors(&issues) {
+        issues.push(PresetIssue::error(None, format!("{:#}", error)));
+    }
+    BrokenAgent {
+        path: path.to_path_buf(),
+        issues,
+    }
+}
+
+/// Problems with an agent edited in the app, named by their preset fields
+///
+/// Covers what the Agents view lets the user change; the rest was checked
+/// when the preset loaded.
+pub fn validate_agent(config: &AgentConfig) -> Vec<PresetIssue> {
+    let mut issues = Vec::new();
+    if config.name.trim().is_empty() {
+        issues.push(PresetIssue::error(Some("name"), "Can't be empty"));
+    }
+    if config.instructions.trim().is_empty() {
+        issues.push(PresetIssue::error(Some("instruction"), "Can't be empty"));
+    }
+    if config.model.trim().is_empty() {
+        issues.push(PresetIssue::error(Some("model"), "Can't be empty"));
+    }
+    if config.context_pruning.max_messages == Some(0) {
+        issues.push(PresetIssue::error(
+            Some("contextPruning.maxMessages"),
+            "Must be at least 1",
+        ));
+    }
+    if config
+        .personality
+        .as_deref()
+        .is_some_and(|personality| personality.trim().is_empty())
+    {
+        issues.push(PresetIssue::warning(
+            "personality",
+            "Blank; leave it out for no personality",
+        ));
+    }
+    issues
+}
+
+/// Whether any of the issues stops the preset from loading
+pub fn has_errors(issues: &[PresetIssue]) -> bool {
+    issues.iter().any(|issue| !issue.warning)
+}
+
+/// Values that have the right type but aren't allowed
+fn check_values(fields: &serde_json::Map<String, Value>) -> Vec<PresetIssue> {
+    let mut issues = Vec::new();
+    let text = |key: &str| fields.get(key).and_then(Value::as_str);
+
+    for key in ["name", "model", "instruction"] {
+        if text(key).is_some_and(|value| value.trim().is_empty()) {
+            issues.push(PresetIssue::error(Some(key), "Can't be empty"));
+        }
+    }
+    if let Some(name) = text("name") {
+        if name.contains(['/', '\\']) {
+            issues.push(PresetIssue::error(Some("name"), "Can't contain slashes"));
+        }
+    }
+    if let Some(provider) = text("provider") {
+        if serde_json::from_value::<LlmProvider>(Value::from(provider)).is_err() {
+            let known: Vec<String> = LlmProvider::ALL
+                .iter()
+                .map(|provider| serde_json::to_value(provider).unwrap_or_default())
+                .filter_map(|value| value.as_str().map(str::to_string))
+                .collect();
+            issues.push(PresetIssue::error(
+                Some("provider"),
+                format!("Unknown provider '{}' (use {})", provider, known.join(", ")),
+            ));
+        }
+    }
+
+    let parameter = |key: &str| fields.get("parameters")?.get(key)?.as_f64();
+    for (key, max) in [("temperature", 2.0), ("topP", 1.0)] {
+        if let Some(value) = parameter(key) {
+            if !(0.0..=max).contains(&value) {
+                issues.push(PresetIssue::error(
+                    Some(format!("parameters.{}", key).as_str()),
+                    format!("Must be between 0 and {}, got {}", max, value),
+                ));
+            }
+        }
+    }
+    if let Some(strategy) = fields
+        .get("contextPruning")
+        .and_then(|pruning| pruning.get("strategy"))
+        .filter(|strategy| strategy.is_string())
+    {
+        if serde_json::from_value::<crate::pruning::PruningStrategy>(strategy.clone()).is_err() {
+            issues.push(PresetIssue::error(
+                Some("contextPruning.strategy"),
+                format!(
+                    "Unknown strategy {} (use sliding_window, importance_weighted or \
+                     summarize_then_drop)",
+                    strategy
+                ),
+            ));
+        }
+    }
+    issues
+}
+
+/// The checks loading the preset runs: the typed parse, secret references
+/// and the provider's API key
+fn check_loads(value: Value) -> Result<(), PresetIssue> {
+    let mut config: JsonAgentConfig = serde_json::from_value(value)
+        .map_err(|e| PresetIssue::error(None, format!("Doesn't match the preset format: {}", e)))?;
+    config
+        .resolve_env_vars()
+        .map_err(|e| PresetIssue::error(Some("apiKey"), format!("{:#}", e)))?;
+    config
+        .validate()
+        .map_err(|e| PresetIssue::error(None, format!("{:#}", e)))
+}
+
+/// serde_json's message without the " at line 3 column 5" it appends
+fn syntax_message(error: &serde_json::Error) -> String {
+    let message = error.to_string();
+    match message.rfind(" at line ") {
+        Some(end) => message[..end].to_string(),
+        None => message,
+    }
+}
+
+/// Line a field's key is on, following a dotted path through the nesting
+///
+/// A plain text search rather than a parse with positions: the first
+/// `"key":` after the parent's key is the field in every preset written by
+/// hand or by Rustbot.
+fn line_of(json: &str, path: &str) -> Option<usize> {
+    let mut offset = 0;
+    for key in path.split('.') {
+        let quoted = format!("\"{}\"", key);
+        offset = json[offset..]
+            .match_indices(&quoted)
+            .map(|(at, _)| offset + at)
+            .find(|&at| json[at + quoted.len()..].trim_start().starts_with(':'))?;
+    }
+    Some(json[..offset].matches('\n').count() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_preset() {
+        let json = r#"{
+            "name": "local",
+            "provider": "ollama",
+            "model": "llama3",
+            "instruction": "Be helpful",
+            "personality": null,
+            "parameters": {"temperature": 0.5},
+            "contextPruning": {"strategy": "summarize_then_drop", "maxMessages": 40}
+        }"#;
+        assert_eq!(validate_preset(json), vec![]);
+    }
+
+    #[test]
+    fn test_issues_name_field_and_line() {
+        let json = r#"{
+  "name": "local",
+  "provider": "olama",
+  "model": 3,
+  "instructions": "Be helpful",
+  "parameters": {
+    "temperature": 3.5
+  }
+}"#;
+        let issues = validate_preset(json);
+        let summary: Vec<(Option<&str>, Option<usize>, bool)> = issues
+            .iter()
+            .map(|issue| (issue.field.as_deref(), issue.line, issue.warning))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Some("provider"), Some(3), false),
+                (Some("model"), Some(4), false),
+                (Some("instructions"), Some(5), true),
+                (Some("parameters.temperature"), Some(7), false),
+                (Some("instruction"), None, false),
+            ]
+        );
+        assert!(has_errors(&issues));
+        assert_eq!(
+            issues[0].to_string(),
+            "line 3: provider: Unknown provider 'olama' (use openrouter, openai, anthropic, ollama)"
+        );
+    }
+
+    #[test]
+    fn test_syntax_error_line() {
+        let issues = validate_preset("{\n  \"name\": \"local\",\n  \"model\" \"x\"\n}");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, Some(3));
+        assert!(issues[0].message.starts_with("Invalid JSON: expected `:`"));
+    }
+}
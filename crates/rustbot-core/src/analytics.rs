@@ -0,0 +1,389 @@
+// Opt-in local usage analytics
+//
+// Design Decision: Daily counters fed by an event bus subscriber, persisted
+// through the storage service
+//
+// Rationale: Users asked for insight into their own habits (how much they
+// chat, which agents and tools they lean on, how often things fail) without
+// any of it leaving the machine. The event bus already carries every user
+// message, finished tool call and agent error, so a subscriber counts them
+// into one bucket per local day. Counters instead of a raw log keep the file
+// small (a few hundred bytes a day) and hold no message content, tool
+// arguments or error text, only names and numbers.
+//
+// Configuration: Off by default; the user profile's `analytics` flag turns it
+// on, applied with `set_enabled` like the other process-wide settings.
+// Nothing is recorded while it's off, and `AnalyticsRecorder::clear` deletes
+// what was collected.
+//
+// Trade-offs:
+// - Counts are saved every `SAVE_INTERVAL`, so a crash loses the last few
+//   seconds of counts
+// - Days older than `RETENTION_DAYS` are dropped
+// - Token usage stays in `crate::usage`, which derives it from saved
+//   conversations
+
+use crate::events::{AgentStatus, Event, EventBus, EventKind};
+use crate::services::StorageService;
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// How often collected counts are written to storage
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Days of analytics kept
+pub const RETENTION_DAYS: i64 = 365;
+
+/// Turn analytics collection on or off
+pub fn set_enabled(enabled: bool) {
+    if ENABLED.swap(enabled, Ordering::SeqCst) != enabled {
+        tracing::info!(
+            "📊 Local analytics {}",
+            if enabled { "enabled" } else { "disabled" }
+        );
+    }
+}
+
+/// Whether analytics are being collected
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// Calls of one tool and how many of them failed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolActivity {
+    pub calls: u64,
+    pub errors: u64,
+}
+
+/// Counts for one local day
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DayActivity {
+    /// Messages sent by the user
+    #[serde(default)]
+    pub messages: u64,
+
+    /// Messages per agent ID
+    #[serde(default)]
+    pub agents: BTreeMap<String, u64>,
+
+    /// Finished calls per tool name
+    #[serde(default)]
+    pub tools: BTreeMap<String, ToolActivity>,
+
+    /// Turns that ended in an agent error
+    #[serde(default)]
+    pub errors: u64,
+}
+
+/// Totals over a range of days, busiest first
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActivitySummary {
+    pub messages: u64,
+    pub errors: u64,
+    pub agents: Vec<(String, u64)>,
+    pub tools: Vec<(String, ToolActivity)>,
+}
+
+impl ActivitySummary {
+    /// Share of messages whose turn ended in an error (None without messages)
+    pub fn error_rate(&self) -> Option<f64> {
+        (self.messages > 0).then(|| self.errors as f64 / self.messages as f64)
+    }
+}
+
+/// Collected analytics, one entry per day with activity
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageAnalytics {
+    #[serde(default)]
+    pub days: BTreeMap<NaiveDate, DayActivity>,
+}
+
+impl UsageAnalytics {
+    /// Count an event into the day it happened
+    ///
+    /// # Returns
+    /// true if the event was counted (user messages, finished tool calls and
+    /// agent errors are; everything else is ignored)
+    pub fn record(&mut self, event: &Event, date: NaiveDate) -> bool {
+        match &event.kind {
+            // Sent to an agent by the API; IPC and scripts publish requests
+            // addressed to "user" that the GUI sends on, counted then
+            EventKind::UserMessage(_) if event.source == "user" => {
+                let day = self.days.entry(date).or_default();
+                day.messages += 1;
+                *day.agents.entry(event.destination.clone()).or_default() += 1;
+            }
+            EventKind::ToolCall { call, .. } => {
+                let Some(result) = &call.result else {
+                    return false;
+                };
+                let day = self.days.entry(date).or_default();
+                let tool = day.tools.entry(call.name.clone()).or_default();
+                tool.calls += 1;
+                if result.is_err() {
+                    tool.errors += 1;
+                }
+            }
+            EventKind::AgentStatusChange {
+                status: AgentStatus::Error(_),
+                ..
+            } => self.days.entry(date).or_default().errors += 1,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Drop days before `oldest`
+    pub fn prune(&mut self, oldest: NaiveDate) {
+        self.days = self.days.split_off(&oldest);
+    }
+
+    /// Totals from `since` on
+    pub fn summary(&self, since: NaiveDate) -> ActivitySummary {
+        let mut summary = ActivitySummary::default();
+        let mut agents: BTreeMap<&str, u64> = BTreeMap::new();
+        let mut tools: BTreeMap<&str, ToolActivity> = BTreeMap::new();
+        for day in self.days.range(since..).map(|(_, day)| day) {
+            summary.messages += day.messages;
+            summary.errors += day.errors;
+            for (agent, count) in &day.agents {
+                *agents.entry(agent).or_default() += count;
+            }
+            for (name, activity) in &day.tools {
+                let tool = tools.entry(name).or_default();
+                tool.calls += activity.calls;
+                tool.errors += activity.errors;
+            }
+        }
+
+        summary.agents = agents
+            .into_iter()
+            .map(|(agent, count)| (agent.to_string(), count))
+            .collect();
+        summary
+            .agents
+            .sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        summary.tools = tools
+            .into_iter()
+            .map(|(name, activity)| (name.to_string(), activity))
+            .collect();
+        summary
+            .tools
+            .sort_by_key(|(_, activity)| std::cmp::Reverse(activity.calls));
+        summary
+    }
+}
+
+/// Collects analytics from the event bus in the background
+///
+/// Cheap to clone; clones share the collected counts.
+#[derive(Clone)]
+pub struct AnalyticsRecorder {
+    data: Arc<Mutex<UsageAnalytics>>,
+    storage: Arc<dyn StorageService>,
+}
+
+impl AnalyticsRecorder {
+    /// Load saved analytics and start counting events
+    ///
+    /// Events are only counted while collection is enabled (`set_enabled`).
+    ///
+    /// # Arguments
+    /// * `storage` - Where analytics are saved
+    /// * `event_bus` - Bus to count events from
+    /// * `handle` - Runtime to run the recorder on
+    pub fn spawn(
+        storage: Arc<dyn StorageService>,
+        event_bus: &EventBus,
+        handle: &tokio::runtime::Handle,
+    ) -> Self {
+        let recorder = Self {
+            data: Arc::new(Mutex::new(UsageAnalytics::default())),
+            storage,
+        };
+        let mut rx = event_bus.subscribe_named("analytics");
+        let this = recorder.clone();
+
+        handle.spawn(async move {
+            match this.storage.load_analytics().await {
+                Ok(saved) => {
+                    if let Ok(mut data) = this.data.lock() {
+                        // Keep anything counted while loading
+                        let counted = std::mem::replace(&mut *data, saved);
+                        data.days.extend(counted.days);
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to load analytics: {}", e),
+            }
+
+            let mut save = tokio::time::interval(SAVE_INTERVAL);
+            let mut dirty = false;
+            loop {
+                tokio::select! {
+                    event = rx.recv() => {
+                        let Some(event) = event else {
+                            break;
+                        };
+                        if is_enabled() {
+                            let date = event.timestamp.date_naive();
+                            if let Ok(mut data) = this.data.lock() {
+                                dirty |= data.record(&event, date);
+                            }
+                        }
+                    }
+                    _ = save.tick() => {
+                        if std::mem::take(&mut dirty) {
+                            this.save().await;
+                        }
+                    }
+                }
+            }
+            if dirty {
+                this.save().await;
+            }
+        });
+
+        recorder
+    }
+
+    /// Copy of the analytics collected so far
+    pub fn snapshot(&self) -> UsageAnalytics {
+        self.data
+            .lock()
+            .map(|data| data.clone())
+            .unwrap_or_default()
+    }
+
+    /// Delete all collected analytics
+    ///
+    /// # Errors
+    /// - The emptied analytics can't be saved
+    pub async fn clear(&self) -> crate::error::Result<()> {
+        if let Ok(mut data) = self.data.lock() {
+            *data = UsageAnalytics::default();
+        }
+        self.storage
+            .save_analytics(&UsageAnalytics::default())
+            .await
+    }
+
+    async fn save(&self) {
+        let oldest = Local::now().date_naive() - chrono::Duration::days(RETENTION_DAYS);
+        let analytics = match self.data.lock() {
+            Ok(mut data) => {
+                data.prune(oldest);
+                data.clone()
+            }
+            Err(_) => return,
+        };
+        if let Err(e) = self.storage.save_analytics(&analytics).await {
+            tracing::warn!("Failed to save analytics: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::ToolCallRecord;
+
+    fn tool_event(name: &str, result: Option<Result<String, String>>) -> Event {
+        Event::new(
+            "assistant".to_string(),
+            "broadcast".to_string(),
+            EventKind::ToolCall {
+                session_id: "main".to_string(),
+                call: ToolCallRecord {
+                    id: "call-1".to_string(),
+                    name: name.to_string(),
+                    arguments: serde_json::json!({}),
+                    result,
+                    duration: Duration::ZERO,
+                    warning: None,
+                },
+            },
+        )
+    }
+
+    #[test]
+    fn test_record_and_summarize() {
+        let monday = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+        let tuesday = NaiveDate::from_ymd_opt(2025, 3, 4).unwrap();
+        let mut analytics = UsageAnalytics::default();
+
+        let message = |agent: &str| {
+            Event::new(
+                "user".to_string(),
+                agent.to_string(),
+                EventKind::UserMessage("hi".to_string()),
+            )
+        };
+        assert!(analytics.record(&message("assistant"), monday));
+        assert!(analytics.record(&message("assistant"), tuesday));
+        assert!(analytics.record(&message("researcher"), tuesday));
+        let from_script = Event::new(
+            "script".to_string(),
+            "user".to_string(),
+            EventKind::UserMessage("hi".to_string()),
+        );
+        assert!(!analytics.record(&from_script, tuesday));
+
+        // Only finished tool calls count
+        assert!(!analytics.record(&tool_event("web_fetch", None), tuesday));
+        assert!(analytics.record(&tool_event("web_fetch", Some(Ok("page".into()))), tuesday));
+        assert!(analytics.record(&tool_event("web_fetch", Some(Err("404".into()))), tuesday));
+        assert!(analytics.record(&tool_event("calendar_list", Some(Ok("[]".into()))), monday));
+
+        let error = Event::new(
+            "assistant".to_string(),
+            "broadcast".to_string(),
+            EventKind::AgentStatusChange {
+                agent_id: "assistant".to_string(),
+                status: AgentStatus::Error("timeout".to_string()),
+            },
+        );
+        assert!(analytics.record(&error, tuesday));
+        let test = Event::new(
+            "a".to_string(),
+            "b".to_string(),
+            EventKind::Test("x".to_string()),
+        );
+        assert!(!analytics.record(&test, tuesday));
+
+        let all = analytics.summary(monday);
+        assert_eq!(all.messages, 3);
+        assert_eq!(all.errors, 1);
+        assert_eq!(all.agents[0], ("assistant".to_string(), 2));
+        assert_eq!(all.tools[0].0, "web_fetch");
+        assert_eq!(
+            all.tools[0].1,
+            ToolActivity {
+                calls: 2,
+                errors: 1
+            }
+        );
+        assert_eq!(all.error_rate(), Some(1.0 / 3.0));
+
+        let recent = analytics.summary(tuesday);
+        assert_eq!(recent.messages, 2);
+        assert_eq!(recent.tools.len(), 1);
+
+        analytics.prune(tuesday);
+        assert_eq!(analytics.days.len(), 1);
+        assert_eq!(UsageAnalytics::default().summary(monday).error_rate(), None);
+
+        // Round-trips through the saved JSON
+        let json = serde_json::to_string(&analytics).unwrap();
+        assert_eq!(
+            serde_json::from_str::<UsageAnalytics>(&json).unwrap(),
+            analytics
+        );
+    }
+}
@@ -0,0 +1,4052 @@
+// API layer for programmatic access to all Rustbot functionality
+// This provides the core interface that both UI and external code can use
+// Design principle: All functionality accessible programmatically
+
+use crate::agent::{Agent, AgentConfig, AgentResponse, ToolDefinition};
+#[cfg(feature = "calendar")]
+use crate::calendar::CalendarService;
+use crate::chat_stream::{self, ChatStream, StreamItem};
+use crate::conversation_export::{ConversationExport, ConversationFormat};
+#[cfg(feature = "email")]
+use crate::email::EmailService;
+use crate::error::RustbotError;
+use crate::events::{
+    new_correlation_id, AgentStatus, Event, EventBus, EventBusStats, EventKind, ToolCallRecord,
+};
+use crate::fs_consent::FsConsent;
+use crate::git_tools::GitTools;
+use crate::handoff::{self, Handoff};
+use crate::llm::{LlmAdapter, Message as LlmMessage, ToolCall};
+use crate::mcp::config::ToolOverride;
+use crate::mcp::error::McpError;
+use crate::mcp::extensions::ExtensionRegistry;
+use crate::mcp::manager::McpPluginManager;
+use crate::mcp::protocol::McpToolDefinition;
+#[cfg(feature = "native-plugins")]
+use crate::native_plugins::NativePluginHost;
+use crate::pruning::{self, PruningStrategy};
+use crate::request_preview::{self, RequestPreview};
+#[cfg(feature = "scripting")]
+use crate::scripting::ScriptHost;
+use crate::services::traits::{ConversationSession, SessionMessage};
+use crate::tool_executor::ToolExecutor;
+use crate::tool_toggles;
+use crate::untrusted;
+#[cfg(feature = "wasm-tools")]
+use crate::wasm_tools::WasmToolHost;
+use anyhow::{Context as AnyhowContext, Result};
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use tokio::sync::RwLock;
+use tokio::sync::{mpsc, Mutex, OwnedMutexGuard};
+
+/// Tool source identifier for routing execution
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolSource {
+    /// Tool is a native Rustbot agent
+    Agent { agent_id: String },
+
+    /// Tool is from an MCP plugin
+    Mcp { plugin_id: String },
+
+    /// Tool is registered by a user script
+    Script { script: String },
+
+    /// Tool is provided by the email connector
+    Email,
+
+    /// Tool is provided by the calendar connector
+    Calendar,
+
+    /// Tool is one of the git tools
+    Git,
+
+    /// Tool is a user's WebAssembly module
+    Wasm { tool: String },
+
+    /// Tool is provided by a native plugin
+    Native { plugin: String },
+}
+
+/// Registry entry for MCP tools
+#[derive(Debug, Clone)]
+struct McpToolRegistry {
+    /// MCP tool definition
+    definition: McpToolDefinition,
+
+    /// Source plugin ID
+    plugin_id: String,
+}
+
+/// Usage counters for one tool, kept for the lifetime of the API instance
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct ToolMetrics {
+    /// Number of times the tool was called
+    pub calls: u64,
+
+    /// Calls that returned an error
+    pub failures: u64,
+
+    /// Total time spent executing the tool, in milliseconds
+    pub total_duration_ms: u64,
+
+    /// When the tool was last called
+    pub last_called: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ToolMetrics {
+    /// Record one call
+    pub fn record(&mut self, duration: std::time::Duration, success: bool) {
+        self.calls += 1;
+        if !success {
+            self.failures += 1;
+        }
+        self.total_duration_ms += duration.as_millis() as u64;
+        self.last_called = Some(chrono::Utc::now());
+    }
+
+    /// Mean execution time in milliseconds (0 if never called)
+    pub fn average_duration_ms(&self) -> u64 {
+        self.total_duration_ms.checked_div(self.calls).unwrap_or(0)
+    }
+}
+
+/// Resident memory of the process in bytes
+#[cfg(feature = "memory-stats")]
+fn rss_bytes() -> Option<u64> {
+    memory_stats::memory_stats().map(|stats| stats.physical_mem as u64)
+}
+
+/// Resident memory of the process (not measured without `memory-stats`)
+#[cfg(not(feature = "memory-stats"))]
+fn rss_bytes() -> Option<u64> {
+    None
+}
+
+/// Resource and load figures for diagnostics (see `RustbotApi::metrics`)
+///
+/// A snapshot, cheap enough to take every frame. Frontends add their own
+/// figures (UI frame time, render caches) when showing it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiMetrics {
+    /// Resident memory of the process in bytes (None where the OS doesn't say,
+    /// or without the `memory-stats` feature)
+    pub rss_bytes: Option<u64>,
+
+    /// Event bus capacity, queue depth and drop counters
+    pub event_bus: EventBusStats,
+
+    /// Open chat sessions
+    pub sessions: usize,
+
+    /// LLM history messages held across all sessions
+    pub history_messages: usize,
+
+    /// Tools offered to agents (built-in and MCP)
+    pub tools: usize,
+
+    /// Tool calls made since the API was created
+    pub tool_calls: u64,
+}
+
+/// ID of the session every API instance starts with
+pub const DEFAULT_SESSION: &str = "main";
+
+/// One conversation: its own agent, LLM history and turn in flight
+///
+/// Frontends that show several chats at once (the GUI's tabs) keep one
+/// session per chat. Methods without a session argument act on the active
+/// session, so single-chat frontends never see sessions at all.
+#[derive(Debug, Clone)]
+pub struct ChatSession {
+    id: String,
+    agent_id: String,
+
+    /// Message history (for context)
+    history: VecDeque<LlmMessage>,
+
+    /// Messages the user pinned, sent with every request however far back
+    /// they are (history trimming doesn't touch them)
+    pinned: Vec<String>,
+
+    /// Correlation ID of this session's user turn in flight
+    correlation_id: Option<String>,
+
+    /// Summary of the turns dropped by summarize-then-drop pruning
+    summary: Option<String>,
+
+    /// Turns dropped since the summary was last updated
+    unsummarized: Vec<LlmMessage>,
+
+    /// Excerpts retrieved for the next message (see `crate::folder_index`)
+    retrieved: Option<String>,
+
+    /// How the session's agent got the conversation, if another agent
+    /// handed it over (see `crate::handoff`)
+    handoff: Option<Handoff>,
+
+    /// Tool sources this chat switched on or off, by source ID (see
+    /// `crate::tool_toggles`)
+    tool_overrides: HashMap<String, bool>,
+}
+
+impl ChatSession {
+    fn new(id: String, agent_id: String) -> Self {
+        Self {
+            id,
+            agent_id,
+            history: VecDeque::new(),
+            pinned: Vec::new(),
+            correlation_id: None,
+            summary: None,
+            unsummarized: Vec::new(),
+            retrieved: None,
+            handoff: None,
+            tool_overrides: HashMap::new(),
+        }
+    }
+
+    /// Session ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Agent that answers in this session
+    pub fn agent_id(&self) -> &str {
+        &self.agent_id
+    }
+
+    /// LLM history, oldest first
+    pub fn history(&self) -> Vec<LlmMessage> {
+        self.history.iter().cloned().collect()
+    }
+
+    /// Pinned messages kept in context
+    pub fn pinned(&self) -> &[String] {
+        &self.pinned
+    }
+
+    /// Summary of dropped turns sent ahead of the history, if any
+    pub fn summary(&self) -> Option<&str> {
+        self.summary.as_deref()
+    }
+
+    /// The handoff that gave the session to its agent, if any
+    pub fn handoff(&self) -> Option<&Handoff> {
+        self.handoff.as_ref()
+    }
+
+    /// Tool sources switched on or off in this session
+    pub fn tool_overrides(&self) -> &HashMap<String, bool> {
+        &self.tool_overrides
+    }
+
+    /// Give the conversation to another agent for the following turns
+    fn hand_off(&mut self, to: String, summary: String) -> Handoff {
+        let handoff = Handoff {
+            from: std::mem::replace(&mut self.agent_id, to.clone()),
+            to,
+            summary,
+        };
+        self.handoff = Some(handoff.clone());
+        handoff
+    }
+
+    /// Reduce the history to `max` messages with a pruning strategy
+    ///
+    /// Whole turns are dropped (see `crate::pruning`), so a tool result never
+    /// loses the assistant message that called it, which providers
+    /// (Anthropic in particular) reject. Turns dropped by summarize-then-drop
+    /// wait in `unsummarized` until the next message folds them into the
+    /// summary.
+    fn prune_history(&mut self, strategy: PruningStrategy, max: usize) {
+        let dropped = pruning::prune(&mut self.history, strategy, max);
+        if strategy == PruningStrategy::SummarizeThenDrop {
+            self.unsummarized.extend(dropped);
+        }
+    }
+}
+
+/// Where a turn stands once the agent has answered the message
+enum TurnStep {
+    /// The answer is streaming
+    Answer(mpsc::UnboundedReceiver<String>),
+
+    /// The agent called tools; run each, then ask for the answer
+    Tools(ToolTurn),
+}
+
+/// A turn waiting for its tool calls to run
+struct ToolTurn {
+    agent_id: String,
+    correlation_id: String,
+    tool_calls: Vec<ToolCall>,
+
+    /// The request so far, tool results appended as they come in
+    messages: Vec<LlmMessage>,
+
+    /// Typed stream of the turn, if it was sent with `send_message_stream_in`
+    sink: Option<mpsc::UnboundedSender<StreamItem>>,
+
+    started: std::time::Instant,
+}
+
+/// Introduces pinned messages to the model, ahead of the history
+const PINNED_PREAMBLE: &str = "The user pinned these messages from the conversation. \
+    Keep them in mind even if they no longer appear in the history below.";
+
+/// Core API for Rustbot functionality
+/// All user actions should have equivalent API methods here
+pub struct RustbotApi {
+    /// Event bus for pub/sub communication
+    event_bus: Arc<EventBus>,
+
+    /// Tokio runtime for async operations
+    runtime: Arc<Runtime>,
+
+    /// Registered agents
+    agents: Vec<Agent>,
+
+    /// Agent configurations (needed for building tool registry)
+    agent_configs: Vec<AgentConfig>,
+
+    /// Tool definitions for enabled specialist agents
+    /// These are automatically built from agent_configs
+    available_tools: Vec<ToolDefinition>,
+
+    /// MCP tools registry (thread-safe for async plugin operations)
+    /// Maps tool name (mcp:plugin_id:tool_name) to registry entry
+    mcp_tools: Arc<RwLock<HashMap<String, McpToolRegistry>>>,
+
+    /// MCP plugin manager (thread-safe for async operations)
+    /// Optional - only present if MCP support is enabled
+    mcp_manager: Option<Arc<Mutex<McpPluginManager>>>,
+
+    /// User script host providing script tools
+    /// Optional - only present if scripting is enabled
+    #[cfg(feature = "scripting")]
+    script_host: Option<Arc<ScriptHost>>,
+
+    /// Host running the user's WebAssembly tools
+    /// Optional - only present if the WebAssembly runtime is available
+    #[cfg(feature = "wasm-tools")]
+    wasm_tools: Option<Arc<WasmToolHost>>,
+
+    /// Native plugins the user enabled
+    /// Optional - only present where plugins are loaded (the desktop app)
+    #[cfg(feature = "native-plugins")]
+    native_plugins: Option<Arc<NativePluginHost>>,
+
+    /// Email connector providing read_inbox and send_email
+    /// Optional - only present if an email account is configured
+    #[cfg(feature = "email")]
+    email: Option<Arc<EmailService>>,
+
+    /// Filesystem grants checked before MCP tools touch a directory
+    /// Optional - without it tool calls run unchecked (headless modes)
+    fs_consent: Option<Arc<FsConsent>>,
+
+    /// Calendar connector providing calendar_list_events and calendar_create_event
+    /// Optional - only present if a calendar is configured
+    #[cfg(feature = "calendar")]
+    calendar: Option<Arc<CalendarService>>,
+
+    /// Git tools for the selected repository (git_status, git_diff, git_log,
+    /// git_commit)
+    /// Optional - only present while a repository is selected
+    git: Option<Arc<GitTools>>,
+
+    /// Extension registry for installed MCP services
+    /// Thread-safe for concurrent access
+    extension_registry: Arc<RwLock<ExtensionRegistry>>,
+
+    /// Open conversations (never empty)
+    sessions: Vec<ChatSession>,
+
+    /// Session used by the methods that don't take a session ID
+    active_session: String,
+
+    /// Maximum messages to keep in history
+    max_history_size: usize,
+
+    /// System context block sent ahead of every session's history
+    /// (see `crate::system_context`)
+    system_context: Option<String>,
+
+    /// Correlation ID of the most recent user turn in flight
+    /// Set by send_message and cleared once its final response is recorded
+    current_correlation_id: Option<String>,
+
+    /// Per-tool call counts and timings, keyed by tool name
+    tool_metrics: HashMap<String, ToolMetrics>,
+}
+
+impl RustbotApi {
+    /// Create a new API instance
+    pub fn new(event_bus: Arc<EventBus>, runtime: Arc<Runtime>, max_history_size: usize) -> Self {
+        // Load extension registry from default path
+        let registry_path = PathBuf::from(dirs::home_dir().unwrap_or_default())
+            .join(".rustbot")
+            .join("extensions")
+            .join("registry.json");
+
+        let extension_registry =
+            ExtensionRegistry::load(&registry_path).unwrap_or_else(|_| ExtensionRegistry::new());
+
+        Self {
+            event_bus,
+            runtime,
+            agents: Vec::new(),
+            agent_configs: Vec::new(),
+            available_tools: Vec::new(),
+            mcp_tools: Arc::new(RwLock::new(HashMap::new())),
+            mcp_manager: None, // MCP manager can be added later via set_mcp_manager()
+            #[cfg(feature = "scripting")]
+            script_host: None, // Script host can be added later via set_script_host()
+            #[cfg(feature = "wasm-tools")]
+            wasm_tools: None, // WASM tools can be added later via set_wasm_tools()
+            #[cfg(feature = "native-plugins")]
+            native_plugins: None, // Native plugins can be added later via set_native_plugins()
+            #[cfg(feature = "email")]
+            email: None, // Email connector can be added later via set_email_service()
+            #[cfg(feature = "calendar")]
+            calendar: None, // Calendar connector can be added later via set_calendar()
+            git: None,         // Git tools can be added later via set_git_tools()
+            fs_consent: None,  // Consent prompts can be added later via set_fs_consent()
+            extension_registry: Arc::new(RwLock::new(extension_registry)),
+            sessions: vec![ChatSession::new(
+                DEFAULT_SESSION.to_string(),
+                String::from("assistant"),
+            )],
+            active_session: DEFAULT_SESSION.to_string(),
+            max_history_size,
+            system_context: None,
+            current_correlation_id: None,
+            tool_metrics: HashMap::new(),
+        }
+    }
+
+    /// Get the correlation ID of the user turn currently in flight (if any)
+    pub fn current_correlation_id(&self) -> Option<&str> {
+        self.current_correlation_id.as_deref()
+    }
+
+    /// Set the MCP plugin manager
+    ///
+    /// Call this after creating RustbotApi to enable MCP plugin support.
+    /// The manager will be used for routing MCP tool calls.
+    ///
+    /// # Arguments
+    /// * `manager` - Initialized MCP plugin manager
+    pub fn set_mcp_manager(&mut self, manager: Arc<Mutex<McpPluginManager>>) {
+        self.mcp_manager = Some(manager);
+    }
+
+    /// Get reference to MCP manager (if configured)
+    pub fn mcp_manager(&self) -> Option<Arc<Mutex<McpPluginManager>>> {
+        self.mcp_manager.clone()
+    }
+
+    /// Set the user script host
+    ///
+    /// Tools registered by loaded scripts are offered to the primary agent
+    /// and routed to the host when called.
+    ///
+    /// # Arguments
+    /// * `host` - Script host (scripts are reloaded on the host directly)
+    #[cfg(feature = "scripting")]
+    pub fn set_script_host(&mut self, host: Arc<ScriptHost>) {
+        self.script_host = Some(host);
+    }
+
+    /// Set the WebAssembly tool host
+    ///
+    /// Tools from the modules that loaded are offered to the primary agent
+    /// and run in a sandbox when called.
+    ///
+    /// # Arguments
+    /// * `host` - WASM tool host (tools are reloaded on the host directly)
+    #[cfg(feature = "wasm-tools")]
+    pub fn set_wasm_tools(&mut self, host: Arc<WasmToolHost>) {
+        self.wasm_tools = Some(host);
+    }
+
+    /// Set the native plugin host
+    ///
+    /// Tools of the loaded plugins are offered to the primary agent and
+    /// routed to the plugin when called.
+    ///
+    /// # Arguments
+    /// * `host` - Plugin host (plugins are enabled on the host directly)
+    #[cfg(feature = "native-plugins")]
+    pub fn set_native_plugins(&mut self, host: Arc<NativePluginHost>) {
+        self.native_plugins = Some(host);
+    }
+
+    /// Set the email connector
+    ///
+    /// Its read_inbox and send_email tools are offered to the primary agent.
+    /// Sending asks the UI for approval, so only set this where a UI answers
+    /// `email::APPROVAL_METHOD` requests.
+    ///
+    /// # Arguments
+    /// * `email` - Connector for the configured account
+    #[cfg(feature = "email")]
+    pub fn set_email_service(&mut self, email: Arc<EmailService>) {
+        self.email = Some(email);
+    }
+
+    /// Set the filesystem consent checker
+    ///
+    /// MCP tool calls whose arguments name a directory the plugin hasn't been
+    /// granted ask the UI first, so only set this where a UI answers
+    /// `fs_consent::CONSENT_METHOD` requests.
+    ///
+    /// # Arguments
+    /// * `consent` - Grants and the consent round trip
+    pub fn set_fs_consent(&mut self, consent: Arc<FsConsent>) {
+        self.fs_consent = Some(consent);
+    }
+
+    /// Set or remove the calendar connector
+    ///
+    /// Its list and create tools are offered to the primary agent.
+    ///
+    /// # Arguments
+    /// * `calendar` - Connector for the configured calendar (None disconnects)
+    #[cfg(feature = "calendar")]
+    pub fn set_calendar(&mut self, calendar: Option<Arc<CalendarService>>) {
+        self.calendar = calendar;
+    }
+
+    /// Set or remove the git tools
+    ///
+    /// They are offered to the primary agent. git_commit waits for the UI to
+    /// answer `git_tools::APPROVAL_METHOD` requests.
+    ///
+    /// # Arguments
+    /// * `git` - Tools for the selected repository (None removes them)
+    pub fn set_git_tools(&mut self, git: Option<Arc<GitTools>>) {
+        self.git = git;
+    }
+
+    /// Register an MCP tool from a plugin
+    ///
+    /// Converts MCP tool definition to Rustbot tool format and adds to registry.
+    /// Tool names are namespaced as "mcp:{plugin_id}:{tool_name}" for uniqueness.
+    /// A name/description override in the plugin's config (see
+    /// `ToolOverride`) replaces the server's; calls still use its own name.
+    ///
+    /// # Arguments
+    /// * `tool` - MCP tool definition from plugin discovery
+    /// * `plugin_id` - ID of the source plugin
+    ///
+    /// # Returns
+    /// Ok if tool registered successfully, Err if tool already exists
+    ///
+    /// # Side Effects
+    /// - Adds tool to global tool registry
+    /// - Updates available_tools list
+    /// - Tool becomes visible to agents
+    pub async fn register_mcp_tool(
+        &mut self,
+        tool: McpToolDefinition,
+        plugin_id: String,
+    ) -> Result<()> {
+        // Name/description override from the plugin's config
+        let tool_override = match &self.mcp_manager {
+            Some(manager) => {
+                manager
+                    .lock()
+                    .await
+                    .tool_override(&plugin_id, &tool.name)
+                    .await
+            }
+            None => None,
+        };
+        let name = tool_override
+            .as_ref()
+            .and_then(|o| o.name.as_deref())
+            .unwrap_or(&tool.name);
+        let tool_name = format!("mcp:{}:{}", plugin_id, name);
+
+        tracing::debug!(
+            "Registering MCP tool: {} from plugin {}",
+            tool_name,
+            plugin_id
+        );
+
+        // Check for duplicates
+        {
+            let mcp_tools = self.mcp_tools.read().await;
+            if mcp_tools.contains_key(&tool_name) {
+                anyhow::bail!("MCP tool '{}' already registered", tool_name);
+            }
+        }
+
+        // Convert MCP tool to Rustbot ToolDefinition format
+        let rustbot_tool = Self::convert_mcp_tool_to_rustbot(
+            &tool,
+            &plugin_id,
+            &tool_name,
+            tool_override.as_ref(),
+        );
+
+        // Store in MCP registry
+        {
+            let mut mcp_tools = self.mcp_tools.write().await;
+            mcp_tools.insert(
+                tool_name.clone(),
+                McpToolRegistry {
+                    definition: tool,
+                    plugin_id: plugin_id.clone(),
+                },
+            );
+        }
+
+        // Add to available tools list
+        self.available_tools.push(rustbot_tool);
+
+        tracing::info!(
+            "MCP tool registered: {} (total tools: {})",
+            tool_name,
+            self.available_tools.len()
+        );
+
+        Ok(())
+    }
+
+    /// Unregister all MCP tools from a plugin
+    ///
+    /// Removes all tools associated with the specified plugin ID.
+    /// Call this when a plugin stops or is disabled.
+    ///
+    /// # Arguments
+    /// * `plugin_id` - ID of the plugin whose tools should be removed
+    ///
+    /// # Side Effects
+    /// - Removes all matching tools from registry
+    /// - Updates available_tools list
+    /// - Tools no longer visible to agents
+    pub async fn unregister_mcp_tools(&mut self, plugin_id: &str) -> Result<()> {
+        tracing::debug!("Unregistering MCP tools for plugin: {}", plugin_id);
+
+        let mut removed_count = 0;
+
+        // Remove from MCP registry and collect tool names
+        let removed_tools: Vec<String> = {
+            let mut mcp_tools = self.mcp_tools.write().await;
+            let to_remove: Vec<String> = mcp_tools
+                .iter()
+                .filter(|(_, entry)| entry.plugin_id == plugin_id)
+                .map(|(name, _)| name.clone())
+                .collect();
+
+            for tool_name in &to_remove {
+                mcp_tools.remove(tool_name);
+                removed_count += 1;
+            }
+
+            to_remove
+        };
+
+        // Remove from available tools list
+        self.available_tools
+            .retain(|tool| !removed_tools.contains(&tool.function.name));
+
+        tracing::info!(
+            "Unregistered {} MCP tools from plugin {} (remaining tools: {})",
+            removed_count,
+            plugin_id,
+            self.available_tools.len()
+        );
+
+        Ok(())
+    }
+
+    /// Get all available tools (agent, MCP, script, WASM, native plugin,
+    /// email, calendar and git tools)
+    ///
+    /// Returns a snapshot of all tools currently available to agents.
+    /// Includes native Rustbot agent tools, MCP plugin tools, tools
+    /// registered by user scripts, WebAssembly tools, native plugin tools,
+    /// the email and calendar connectors' tools and the git tools.
+    pub fn get_all_tools(&self) -> Vec<ToolDefinition> {
+        let mut tools = self.available_tools.clone();
+        #[cfg(feature = "scripting")]
+        if let Some(host) = &self.script_host {
+            tools.extend(host.tool_definitions());
+        }
+        #[cfg(feature = "wasm-tools")]
+        if let Some(host) = &self.wasm_tools {
+            tools.extend(host.tool_definitions());
+        }
+        #[cfg(feature = "native-plugins")]
+        if let Some(host) = &self.native_plugins {
+            tools.extend(host.tool_definitions());
+        }
+        #[cfg(feature = "email")]
+        if let Some(email) = &self.email {
+            tools.extend(email.tool_definitions());
+        }
+        #[cfg(feature = "calendar")]
+        if let Some(calendar) = &self.calendar {
+            tools.extend(calendar.tool_definitions());
+        }
+        if let Some(git) = &self.git {
+            tools.extend(git.tool_definitions());
+        }
+        tools
+    }
+
+    /// Check if a tool name is an MCP tool
+    ///
+    /// MCP tools are namespaced with "mcp:" prefix
+    fn is_mcp_tool(tool_name: &str) -> bool {
+        tool_name.starts_with("mcp:")
+    }
+
+    /// Parse MCP tool name into (plugin_id, tool_name)
+    ///
+    /// Expected format: "mcp:{plugin_id}:{tool_name}"
+    ///
+    /// # Returns
+    /// Ok((plugin_id, tool_name)) or Err if format is invalid
+    fn parse_mcp_tool_name(tool_name: &str) -> Result<(String, String)> {
+        let parts: Vec<&str> = tool_name.split(':').collect();
+        if parts.len() != 3 || parts[0] != "mcp" {
+            anyhow::bail!(
+                "Invalid MCP tool name format: '{}' (expected 'mcp:plugin_id:tool_name')",
+                tool_name
+            );
+        }
+
+        Ok((parts[1].to_string(), parts[2].to_string()))
+    }
+
+    /// Convert MCP tool definition to Rustbot tool format
+    ///
+    /// Creates a ToolDefinition compatible with Rustbot's agent system.
+    /// The inputSchema's properties and required fields become the parameters
+    /// (see `crate::mcp::schema::parameters`).
+    ///
+    /// # Arguments
+    /// * `mcp_tool` - MCP tool definition
+    /// * `plugin_id` - Source plugin ID
+    /// * `full_name` - Namespaced tool name (mcp:plugin_id:tool_name)
+    /// * `tool_override` - Override from the plugin's config; its description
+    ///   replaces the server's (the name is already in `full_name`)
+    ///
+    /// # Returns
+    /// ToolDefinition for use in agent tool lists
+    fn convert_mcp_tool_to_rustbot(
+        mcp_tool: &McpToolDefinition,
+        plugin_id: &str,
+        full_name: &str,
+        tool_override: Option<&ToolOverride>,
+    ) -> ToolDefinition {
+        use crate::agent::tools::FunctionDefinition;
+
+        let description = tool_override
+            .and_then(|o| o.description.clone())
+            .or_else(|| mcp_tool.description.clone())
+            .unwrap_or_else(|| format!("MCP tool {} from plugin {}", mcp_tool.name, plugin_id));
+        ToolDefinition {
+            tool_type: "function".to_string(),
+            function: FunctionDefinition {
+                name: full_name.to_string(),
+                description,
+                parameters: crate::mcp::schema::parameters(&mcp_tool.input_schema),
+            },
+        }
+    }
+
+    /// Build tool definitions from all enabled specialist agents
+    /// This should be called whenever agent configs change (enable/disable)
+    fn build_tool_definitions(&self) -> Vec<ToolDefinition> {
+        tracing::info!(
+            "🔍 [DEBUG] build_tool_definitions called with {} agent configs",
+            self.agent_configs.len()
+        );
+
+        // 🔍 DEBUG: Log which configs are enabled specialists
+        for config in &self.agent_configs {
+            if config.enabled && !config.is_primary {
+                tracing::info!(
+                    "🔍 [DEBUG] Enabled specialist: id='{}', name='{}'",
+                    config.id,
+                    config.name
+                );
+            }
+        }
+
+        let tools = ToolDefinition::from_agents(&self.agent_configs);
+        tracing::info!(
+            "🔍 [DEBUG] build_tool_definitions returning {} tools",
+            tools.len()
+        );
+        tools
+    }
+
+    /// Get tools from agent-specific enabled MCP extensions
+    ///
+    /// This loads tools from extensions that the agent has explicitly enabled
+    /// via the `mcpExtensions` field in their configuration.
+    ///
+    /// # Arguments
+    /// * `agent_config` - The agent configuration containing enabled extension IDs
+    ///
+    /// # Returns
+    /// Vector of tool definitions from the agent's enabled extensions
+    async fn get_agent_extension_tools(&self, agent_config: &AgentConfig) -> Vec<ToolDefinition> {
+        let extension_tools = Vec::new();
+
+        // If agent has no extensions enabled, return empty list
+        if agent_config.mcp_extensions.is_empty() {
+            tracing::debug!(
+                "Agent '{}' has no MCP extensions enabled",
+                agent_config.name
+            );
+            return extension_tools;
+        }
+
+        tracing::info!(
+            "Loading MCP extension tools for agent '{}': {:?}",
+            agent_config.name,
+            agent_config.mcp_extensions
+        );
+
+        // Get read lock on extension registry
+        let registry = self.extension_registry.read().await;
+
+        // For each enabled extension ID
+        for extension_id in &agent_config.mcp_extensions {
+            // Look up extension in registry
+            if let Some(_extension) = registry.get(extension_id) {
+                tracing::debug!(
+                    "Found extension '{}' for agent '{}'",
+                    extension_id,
+                    agent_config.name
+                );
+
+                // TODO: Load actual MCP tools from the extension
+                // This will require:
+                // 1. Starting the MCP server/service if not already running
+                // 2. Calling tools/list on the MCP service
+                // 3. Converting MCP tool definitions to Rustbot format
+                //
+                // For now, we'll add a placeholder noting the extension is enabled
+                // Full implementation will come in Phase 2 when MCP client is ready
+
+                tracing::info!(
+                    "Extension '{}' is enabled for agent '{}' but MCP tool loading not yet implemented",
+                    extension_id,
+                    agent_config.name
+                );
+            } else {
+                tracing::warn!(
+                    "Agent '{}' references extension '{}' which is not installed",
+                    agent_config.name,
+                    extension_id
+                );
+            }
+        }
+
+        extension_tools
+    }
+
+    /// Tools an agent can use, grouped by where they come from
+    ///
+    /// Every agent sees the same sources: the specialist agents (except
+    /// itself), its MCP extensions, MCP plugins, tools from user scripts,
+    /// WebAssembly modules and native plugins, the email/calendar connectors,
+    /// and the git tools. They start enabled for the primary agent and
+    /// disabled for specialists; a session's overrides switch them (see
+    /// `crate::tool_toggles`).
+    async fn tool_sources(&self, config: &AgentConfig) -> Vec<tool_toggles::ToolSource> {
+        let default_enabled = config.is_primary;
+        let mut sources = Vec::new();
+
+        // Specialist agents, then MCP plugin tools grouped by plugin
+        let (mcp, specialists): (Vec<_>, Vec<_>) = self
+            .available_tools
+            .iter()
+            .filter(|tool| tool.function.name != config.id)
+            .cloned()
+            .partition(|tool| tool.function.name.starts_with("mcp:"));
+        for tool in specialists {
+            let label = self
+                .agent_configs
+                .iter()
+                .find(|c| c.id == tool.function.name)
+                .map_or_else(|| tool.function.name.clone(), |c| c.name.clone());
+            sources.push(tool_toggles::ToolSource::new(
+                tool.function.name.clone(),
+                label,
+                default_enabled,
+                vec![tool],
+            ));
+        }
+        sources.extend(tool_toggles::group(mcp, default_enabled));
+
+        // Load agent-specific MCP extension tools
+        let extension_tools = self.get_agent_extension_tools(config).await;
+        if !extension_tools.is_empty() {
+            tracing::info!(
+                "🔍 [DEBUG] Adding {} extension tools for agent '{}'",
+                extension_tools.len(),
+                config.name
+            );
+            sources.extend(tool_toggles::group(extension_tools, default_enabled));
+        }
+
+        // Tools registered by user scripts
+        #[cfg(feature = "scripting")]
+        if let Some(host) = &self.script_host {
+            sources.extend(tool_toggles::group(
+                host.tool_definitions(),
+                default_enabled,
+            ));
+        }
+
+        // Sandboxed WebAssembly tools
+        #[cfg(feature = "wasm-tools")]
+        if let Some(host) = &self.wasm_tools {
+            sources.push(tool_toggles::ToolSource::new(
+                "wasm",
+                "WebAssembly tools",
+                default_enabled,
+                host.tool_definitions(),
+            ));
+        }
+
+        // Tools of enabled native plugins
+        #[cfg(feature = "native-plugins")]
+        if let Some(host) = &self.native_plugins {
+            sources.extend(tool_toggles::group(
+                host.tool_definitions(),
+                default_enabled,
+            ));
+        }
+
+        // Email connector tools (sending requires user approval)
+        #[cfg(feature = "email")]
+        if let Some(email) = &self.email {
+            sources.push(tool_toggles::ToolSource::new(
+                "email",
+                "Email",
+                default_enabled,
+                email.tool_definitions(),
+            ));
+        }
+
+        // Calendar connector tools
+        #[cfg(feature = "calendar")]
+        if let Some(calendar) = &self.calendar {
+            sources.push(tool_toggles::ToolSource::new(
+                "calendar",
+                "Calendar",
+                default_enabled,
+                calendar.tool_definitions(),
+            ));
+        }
+
+        // Git tools for the selected repository (commits need approval)
+        if let Some(git) = &self.git {
+            sources.push(tool_toggles::ToolSource::new(
+                "git",
+                "Git",
+                default_enabled,
+                git.tool_definitions(),
+            ));
+        }
+
+        sources.retain(|source| !source.tools.is_empty());
+        sources
+    }
+
+    /// Tools passed to an agent's requests
+    ///
+    /// The enabled sources of `tool_sources`; the primary agent also gets
+    /// the handoff tool. None if the agent gets no tools.
+    ///
+    /// # Arguments
+    /// * `agent_id` - Agent the request goes to
+    /// * `overrides` - The session's tool source overrides
+    async fn agent_tools(
+        &self,
+        agent_id: &str,
+        overrides: &HashMap<String, bool>,
+    ) -> Option<Vec<ToolDefinition>> {
+        tracing::info!(
+            "🔍 [DEBUG] Looking for agent config with id = '{}'",
+            agent_id
+        );
+
+        let agent_config = self.agent_configs.iter().find(|c| c.id == agent_id);
+
+        // 🔍 DEBUG: Log agent config lookup result
+        match agent_config {
+            Some(config) => {
+                tracing::info!(
+                    "🔍 [DEBUG] Found agent config: id='{}', isPrimary={}, enabled={}",
+                    config.id,
+                    config.is_primary,
+                    config.enabled
+                );
+            }
+            None => {
+                tracing::error!(
+                    "🔍 [DEBUG] CRITICAL: No agent config found for agent_id='{}'!",
+                    agent_id
+                );
+            }
+        }
+
+        let Some(config) = agent_config else {
+            tracing::warn!("🔍 [DEBUG] No agent config found, no tools will be passed");
+            return None;
+        };
+
+        let sources = self.tool_sources(config).await;
+        let mut tools = tool_toggles::enabled_tools(sources, overrides);
+        if config.is_primary {
+            // Handing the conversation to a specialist
+            tools.extend(handoff::tool_definition(&self.handoff_targets()));
+            tracing::info!("🔍 [DEBUG] Agent is PRIMARY, {} tools", tools.len());
+            Some(tools)
+        } else if tools.is_empty() {
+            // Specialist agents get tools only if the chat enabled some
+            tracing::info!("🔍 [DEBUG] Agent is NOT primary, no tools");
+            None
+        } else {
+            tracing::debug!(
+                "Specialist agent, {} tools enabled in this chat",
+                tools.len()
+            );
+            Some(tools)
+        }
+    }
+
+    /// Update the tool registry
+    /// Call this when agents are enabled/disabled to rebuild the available tools
+    pub fn update_tools(&mut self) {
+        tracing::info!("🔍 [DEBUG] update_tools called");
+        self.available_tools = self.build_tool_definitions();
+        tracing::info!(
+            "🔍 [DEBUG] Tool registry updated: {} tools available",
+            self.available_tools.len()
+        );
+
+        // 🔍 DEBUG: Log tool names
+        if !self.available_tools.is_empty() {
+            let tool_names: Vec<&str> = self
+                .available_tools
+                .iter()
+                .map(|t| t.function.name.as_str())
+                .collect();
+            tracing::info!("🔍 [DEBUG] Tools after update: {:?}", tool_names);
+        } else {
+            tracing::warn!("🔍 [DEBUG] WARNING: No tools available after update!");
+        }
+    }
+
+    /// Get the current list of available tools
+    pub fn available_tools(&self) -> &[ToolDefinition] {
+        &self.available_tools
+    }
+
+    /// Start automatic MCP tool registration via event bus
+    ///
+    /// Spawns a background task that listens for MCP plugin lifecycle events
+    /// and automatically registers/unregisters tools.
+    ///
+    /// Design Decision: Event-driven tool registration
+    ///
+    /// Rationale: Decouples API from MCP manager lifecycle. Plugins can start/stop
+    /// dynamically without manual registration calls. Makes system reactive and extensible.
+    ///
+    /// # Arguments
+    /// * `api` - Arc-wrapped API instance (for async task access)
+    ///
+    /// # Returns
+    /// JoinHandle for the background task (can be used for graceful shutdown)
+    ///
+    /// # Side Effects
+    /// - Spawns tokio task that runs for application lifetime
+    /// - Subscribes to event bus for MCP plugin events
+    /// - Automatically registers tools when plugins start
+    /// - Automatically unregisters tools when plugins stop
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let api = Arc::new(Mutex::new(RustbotApi::new(/*...*/)));
+    /// let task = RustbotApi::start_mcp_auto_registration(Arc::clone(&api));
+    /// // Auto-registration now active
+    /// ```
+    pub async fn start_mcp_auto_registration(
+        api: Arc<Mutex<RustbotApi>>,
+    ) -> tokio::task::JoinHandle<()> {
+        // Clone event bus Arc for the async task
+        let event_bus = {
+            let api_guard = api.lock().await;
+            Arc::clone(&api_guard.event_bus)
+        };
+
+        tokio::spawn(async move {
+            // Named subscriber: a lag burst must not end the registration loop
+            let mut rx = event_bus.subscribe_named("mcp_auto_registration");
+
+            tracing::info!("MCP auto-registration task started");
+
+            while let Some(event) = rx.recv().await {
+                if let EventKind::McpPluginEvent(plugin_event) = event.kind {
+                    match plugin_event {
+                        crate::events::McpPluginEvent::Started {
+                            plugin_id,
+                            tool_count,
+                        } => {
+                            tracing::info!(
+                                "Plugin '{}' started with {} tools, auto-registering...",
+                                plugin_id,
+                                tool_count
+                            );
+                            Self::register_plugin_tools(&api, &plugin_id).await;
+                        }
+                        crate::events::McpPluginEvent::Stopped { plugin_id } => {
+                            tracing::info!(
+                                "Plugin '{}' stopped, auto-unregistering tools...",
+                                plugin_id
+                            );
+
+                            let mut api_guard = api.lock().await;
+                            match api_guard.unregister_mcp_tools(&plugin_id).await {
+                                Ok(_) => {
+                                    tracing::info!(
+                                        "✓ Auto-unregistered tools for plugin '{}'",
+                                        plugin_id
+                                    );
+                                }
+                                Err(e) => {
+                                    tracing::error!(
+                                        "Failed to unregister tools for plugin '{}': {}",
+                                        plugin_id,
+                                        e
+                                    );
+                                }
+                            }
+                        }
+                        crate::events::McpPluginEvent::ToolsChanged {
+                            plugin_id,
+                            tool_count,
+                        } => {
+                            // E.g. a tool override was edited: register again
+                            tracing::info!(
+                                "Plugin '{}' tools changed ({} tools), re-registering...",
+                                plugin_id,
+                                tool_count
+                            );
+                            let unregistered =
+                                api.lock().await.unregister_mcp_tools(&plugin_id).await;
+                            if let Err(e) = unregistered {
+                                tracing::error!(
+                                    "Failed to unregister tools for plugin '{}': {}",
+                                    plugin_id,
+                                    e
+                                );
+                            }
+                            Self::register_plugin_tools(&api, &plugin_id).await;
+                        }
+                        _ => {
+                            // Other MCP events we don't handle yet
+                        }
+                    }
+                }
+            }
+
+            tracing::info!("MCP auto-registration task ended");
+        })
+    }
+
+    /// Register the tools of a running plugin (for the auto-registration
+    /// task), logging failures
+    async fn register_plugin_tools(api: &Mutex<RustbotApi>, plugin_id: &str) {
+        // Get tools from plugin via MCP manager
+        let tools_result = {
+            let api_guard = api.lock().await;
+            match &api_guard.mcp_manager {
+                Some(manager) => {
+                    let mgr = manager.lock().await;
+                    mgr.get_plugin_tools(plugin_id).await
+                }
+                None => {
+                    tracing::warn!("MCP manager not configured, cannot register tools");
+                    return;
+                }
+            }
+        };
+
+        match tools_result {
+            Ok(tools) => {
+                let mut registered_count = 0;
+                let mut failed_count = 0;
+
+                // Register each tool
+                for tool in tools {
+                    let mut api_guard = api.lock().await;
+                    match api_guard
+                        .register_mcp_tool(tool, plugin_id.to_string())
+                        .await
+                    {
+                        Ok(_) => registered_count += 1,
+                        Err(e) => {
+                            tracing::error!(
+                                "Failed to register tool from plugin '{}': {}",
+                                plugin_id,
+                                e
+                            );
+                            failed_count += 1;
+                        }
+                    }
+                }
+
+                tracing::info!(
+                    "✓ Auto-registered {} tools for plugin '{}' ({} failed)",
+                    registered_count,
+                    plugin_id,
+                    failed_count
+                );
+            }
+            Err(e) => {
+                tracing::error!("Failed to get tools from plugin '{}': {}", plugin_id, e);
+            }
+        }
+    }
+
+    /// Register an agent with the system
+    /// This makes the agent available for message processing
+    pub fn register_agent(&mut self, agent: Agent) {
+        self.agents.push(agent);
+    }
+
+    /// Create and register an agent while the app is running
+    ///
+    /// Used by the New Agent wizard. The config is added to the tool registry,
+    /// so an enabled specialist is callable by the primary agent right away.
+    ///
+    /// # Arguments
+    /// * `config` - Runtime config of the new agent
+    /// * `llm_adapter` - Adapter the agent sends requests through
+    /// * `system_instructions` - Instructions shared by all agents
+    ///
+    /// # Errors
+    /// - An agent with the same ID is already registered
+    pub fn add_agent_config(
+        &mut self,
+        config: AgentConfig,
+        llm_adapter: Arc<dyn LlmAdapter>,
+        system_instructions: String,
+    ) -> Result<()> {
+        if self.agents.iter().any(|agent| agent.id() == config.id) {
+            anyhow::bail!("Agent '{}' already exists", config.id);
+        }
+
+        self.agent_configs.push(config.clone());
+        self.register_agent(Agent::new(
+            config,
+            llm_adapter,
+            Arc::clone(&self.event_bus),
+            self.runtime.handle().clone(),
+            system_instructions,
+        ));
+        self.update_tools();
+        Ok(())
+    }
+
+    /// Replace the instructions shared by all agents, e.g. after switching
+    /// instruction profiles
+    ///
+    /// Takes effect from the next message; histories are kept.
+    pub fn set_system_instructions(&mut self, instructions: &str) {
+        for agent in &mut self.agents {
+            agent.set_system_instructions(instructions.to_string());
+        }
+    }
+
+    /// Get list of all registered agent IDs
+    pub fn list_agents(&self) -> Vec<String> {
+        self.agents.iter().map(|a| a.id().to_string()).collect()
+    }
+
+    /// Get the currently active agent ID (the active session's agent)
+    pub fn active_agent(&self) -> &str {
+        &self.active().agent_id
+    }
+
+    /// Switch to a different agent
+    /// Returns error if agent ID doesn't exist
+    pub fn switch_agent(&mut self, agent_id: &str) -> Result<()> {
+        let session_id = self.active_session.clone();
+        self.switch_agent_in(&session_id, agent_id)
+    }
+
+    /// Switch the agent of one session
+    ///
+    /// # Errors
+    /// - Unknown session or agent
+    pub fn switch_agent_in(&mut self, session_id: &str, agent_id: &str) -> Result<()> {
+        let index = self.session_index(session_id)?;
+        if !self.agents.iter().any(|a| a.id() == agent_id) {
+            anyhow::bail!("Agent '{}' not found", agent_id);
+        }
+
+        // Choosing another agent ends a handoff
+        if self.sessions[index].agent_id != agent_id {
+            self.sessions[index].handoff = None;
+        }
+        self.sessions[index].agent_id = agent_id.to_string();
+
+        // Publish agent switch event
+        let event = Event::new(
+            "system".to_string(),
+            "broadcast".to_string(),
+            EventKind::AgentStatusChange {
+                agent_id: agent_id.to_string(),
+                status: AgentStatus::Idle,
+            },
+        );
+        self.event_bus.publish(event)?;
+
+        Ok(())
+    }
+
+    /// Use another model for an agent until the API is rebuilt
+    ///
+    /// The change applies to every session talking to the agent.
+    ///
+    /// # Arguments
+    /// * `agent_id` - Agent to change
+    /// * `model` - OpenRouter model ID (e.g. `anthropic/claude-sonnet-4`), or
+    ///   None to go back to the default model
+    ///
+    /// # Errors
+    /// - Unknown agent
+    pub fn set_agent_model(&mut self, agent_id: &str, model: Option<String>) -> Result<()> {
+        let agent = self
+            .agents
+            .iter_mut()
+            .find(|a| a.id() == agent_id)
+            .with_context(|| format!("Agent '{}' not found", agent_id))?;
+        tracing::info!("🧠 Agent '{}' now uses model {:?}", agent_id, model);
+        agent.set_model_override(model);
+        Ok(())
+    }
+
+    /// Open conversations, in creation order
+    pub fn sessions(&self) -> &[ChatSession] {
+        &self.sessions
+    }
+
+    /// Look up a session by ID
+    pub fn session(&self, session_id: &str) -> Option<&ChatSession> {
+        self.sessions.iter().find(|s| s.id == session_id)
+    }
+
+    /// ID of the active session
+    pub fn active_session(&self) -> &str {
+        &self.active_session
+    }
+
+    /// Open a new, empty session with the active session's agent
+    ///
+    /// The caller picks the ID so it can use the session right away (e.g.
+    /// a UI tab queues its first message before this call has run). The
+    /// active session doesn't change; call `switch_session` for that.
+    ///
+    /// # Errors
+    /// - A session with this ID is already open
+    pub fn create_session(&mut self, session_id: &str) -> Result<()> {
+        if self.session(session_id).is_some() {
+            anyhow::bail!("Session '{}' already exists", session_id);
+        }
+
+        let agent_id = self.active().agent_id.clone();
+        tracing::info!("💬 Opening session '{}' (agent: {})", session_id, agent_id);
+        self.sessions
+            .push(ChatSession::new(session_id.to_string(), agent_id));
+        Ok(())
+    }
+
+    /// Make a session the target of the methods without a session ID
+    ///
+    /// # Errors
+    /// - Unknown session
+    pub fn switch_session(&mut self, session_id: &str) -> Result<()> {
+        self.session_index(session_id)?;
+        self.active_session = session_id.to_string();
+        Ok(())
+    }
+
+    /// Close a session and drop its history
+    ///
+    /// Closing the active session activates the one before it (or the next
+    /// one if it was first). A response still streaming for the session is
+    /// not recorded.
+    ///
+    /// # Errors
+    /// - Unknown session
+    /// - It's the last open session
+    pub fn close_session(&mut self, session_id: &str) -> Result<()> {
+        let index = self.session_index(session_id)?;
+        if self.sessions.len() == 1 {
+            anyhow::bail!("Can't close the last session");
+        }
+
+        let closed = self.sessions.remove(index);
+        tracing::info!(
+            "💬 Closed session '{}' ({} messages)",
+            closed.id,
+            closed.history.len()
+        );
+        if self.active_session == closed.id {
+            self.active_session = self.sessions[index.saturating_sub(1)].id.clone();
+        }
+        Ok(())
+    }
+
+    fn session_index(&self, session_id: &str) -> Result<usize> {
+        self.sessions
+            .iter()
+            .position(|s| s.id == session_id)
+            .with_context(|| format!("Session '{}' not found", session_id))
+    }
+
+    fn active(&self) -> &ChatSession {
+        self.session(&self.active_session)
+            .expect("the active session is never closed")
+    }
+
+    /// Messages sent ahead of a new message in the session at `index`: the
+    /// system context (if set), a system message with the pinned messages (if
+    /// any), one with the summary of dropped turns (if any), one with the
+    /// handoff summary (if any), one with retrieved excerpts (if any), then the
+    /// history (already pruned to whole turns, see `ChatSession::prune_history`)
+    fn context_messages(&self, index: usize) -> Vec<LlmMessage> {
+        let session = &self.sessions[index];
+        let mut messages = Vec::new();
+        if let Some(context) = &self.system_context {
+            messages.push(LlmMessage::new("system", context.clone()));
+        }
+        if !session.pinned.is_empty() {
+            let pinned: Vec<String> = session
+                .pinned
+                .iter()
+                .enumerate()
+                .map(|(i, text)| format!("{}. {}", i + 1, text))
+                .collect();
+            messages.push(LlmMessage::new(
+                "system",
+                format!("{}\n\n{}", PINNED_PREAMBLE, pinned.join("\n\n")),
+            ));
+        }
+        if let Some(summary) = &session.summary {
+            messages.push(pruning::summary_message(summary));
+        }
+        if let Some(handoff) = &session.handoff {
+            messages.push(handoff.context_message());
+        }
+        if let Some(retrieved) = &session.retrieved {
+            messages.push(LlmMessage::new("system", retrieved.clone()));
+        }
+        messages.extend(session.history.iter().cloned());
+        messages
+    }
+
+    /// Pruning strategy and history size of an agent's sessions
+    ///
+    /// The agent's `context_pruning` settings, with the API's
+    /// `max_history_size` when the agent doesn't set a size.
+    fn pruning_for(&self, agent_id: &str) -> (PruningStrategy, usize) {
+        let pruning = self
+            .agents
+            .iter()
+            .find(|a| a.id() == agent_id)
+            .map(|a| a.context_pruning().clone())
+            .unwrap_or_default();
+        (
+            pruning.strategy,
+            pruning.max_messages.unwrap_or(self.max_history_size),
+        )
+    }
+
+    /// Prune a session's history with its agent's strategy
+    fn prune_session(&mut self, index: usize) {
+        let (strategy, max) = self.pruning_for(&self.sessions[index].agent_id);
+        self.sessions[index].prune_history(strategy, max);
+    }
+
+    /// Fold turns dropped by summarize-then-drop into the session summary
+    ///
+    /// The agent's model writes the summary; if that fails, the first line of
+    /// each dropped user message is kept instead so nothing vanishes silently.
+    async fn update_summary(&mut self, index: usize) {
+        if self.sessions[index].unsummarized.is_empty() {
+            return;
+        }
+        let dropped = std::mem::take(&mut self.sessions[index].unsummarized);
+        let previous = self.sessions[index].summary.clone();
+        let agent_id = self.sessions[index].agent_id.clone();
+        tracing::info!(
+            "🗜️  Summarizing {} dropped messages of '{}'",
+            dropped.len(),
+            self.sessions[index].id
+        );
+
+        let result = match self.agents.iter().find(|a| a.id() == agent_id) {
+            Some(agent) => agent.summarize(previous.as_deref(), &dropped).await,
+            None => Err(anyhow::anyhow!("Agent '{}' not found", agent_id)),
+        };
+        let summary = result.unwrap_or_else(|e| {
+            tracing::warn!("Failed to summarize dropped history: {}", e);
+            pruning::fallback_summary(previous.as_deref(), &dropped)
+        });
+        self.sessions[index].summary = Some(summary);
+    }
+
+    /// Pre-flight check: make room for a new message in a session's context
+    ///
+    /// Counts the whole request (see `RequestPreview`) against the model's
+    /// context window. While it doesn't fit, the history is halved with the
+    /// agent's pruning strategy (summarize-then-drop folds the dropped turns
+    /// into the summary), so the provider never sees an oversized request.
+    ///
+    /// A message that can't fit even once everything droppable is gone is
+    /// rejected up front, and if trimming still falls short the history is
+    /// put back: a rejected message never costs the conversation.
+    ///
+    /// # Returns
+    /// Estimated tokens of the whole request, message included
+    ///
+    /// # Errors
+    /// - `RustbotError::ContextTooLong` when nothing more can be dropped: the
+    ///   message, pinned messages or instructions alone are too long
+    async fn make_room(
+        &mut self,
+        session_id: &str,
+        index: usize,
+        message: &LlmMessage,
+    ) -> Result<u32> {
+        let too_long = |needed: u32, model: &str, budget: u32| -> anyhow::Error {
+            RustbotError::ContextTooLong(format!(
+                "the message needs about {} tokens, but {} takes {} at most",
+                needed, model, budget
+            ))
+            .into()
+        };
+
+        // Pruning never drops the newest turn, so that, the message and the
+        // rest of the request are the least it can get down to
+        let preview = self.preview_request_in(session_id).await?;
+        let needed = preview.total_tokens() + request_preview::message_tokens(message);
+        let budget = preview.context_budget();
+        let history = &self.sessions[index].history;
+        let newest_turn = history.iter().rposition(|m| m.role == "user").unwrap_or(0);
+        let droppable: u32 = history
+            .iter()
+            .take(newest_turn)
+            .map(request_preview::message_tokens)
+            .sum();
+        if needed.saturating_sub(droppable) > budget {
+            return Err(too_long(needed, &preview.model, budget));
+        }
+
+        let session = &self.sessions[index];
+        let saved = (
+            session.history.clone(),
+            session.summary.clone(),
+            session.unsummarized.clone(),
+        );
+        loop {
+            let preview = self.preview_request_in(session_id).await?;
+            let needed = preview.total_tokens() + request_preview::message_tokens(message);
+            let budget = preview.context_budget();
+            if needed <= budget {
+                return Ok(needed);
+            }
+
+            let (strategy, _) = self.pruning_for(&preview.agent_id);
+            let before = self.sessions[index].history.len();
+            self.sessions[index].prune_history(strategy, before / 2);
+            if self.sessions[index].history.len() == before {
+                let session = &mut self.sessions[index];
+                (session.history, session.summary, session.unsummarized) = saved;
+                return Err(too_long(needed, &preview.model, budget));
+            }
+            tracing::info!(
+                "🗜️  '{}' needs {} of {} tokens; history trimmed from {} to {} messages",
+                session_id,
+                needed,
+                budget,
+                before,
+                self.sessions[index].history.len()
+            );
+            self.update_summary(index).await;
+        }
+    }
+
+    /// Set the system context block sent ahead of the history in every
+    /// session (None or an empty block stops sending it)
+    ///
+    /// Frontends render it with `crate::system_context` before each message
+    /// so the date and time stay current.
+    pub fn set_system_context(&mut self, context: Option<String>) {
+        self.system_context = context.filter(|context| !context.is_empty());
+    }
+
+    /// Set the pinned messages of a session, replacing the previous ones
+    ///
+    /// They go with every request in the session, independent of history
+    /// trimming. An empty list stops sending them.
+    ///
+    /// # Errors
+    /// - Unknown session
+    pub fn set_pinned_in(&mut self, session_id: &str, pinned: Vec<String>) -> Result<()> {
+        let index = self.session_index(session_id)?;
+        self.sessions[index].pinned = pinned;
+        Ok(())
+    }
+
+    /// Set the tool sources a session switched on or off, replacing the
+    /// previous overrides
+    ///
+    /// Sources not in `overrides` keep their default (see `tool_sources_in`).
+    ///
+    /// # Errors
+    /// - Unknown session
+    pub fn set_tool_overrides_in(
+        &mut self,
+        session_id: &str,
+        overrides: HashMap<String, bool>,
+    ) -> Result<()> {
+        let index = self.session_index(session_id)?;
+        self.sessions[index].tool_overrides = overrides;
+        Ok(())
+    }
+
+    /// Tool sources available to a session's agent, for a tool panel
+    ///
+    /// Whether each is on in the session is
+    /// `source.is_enabled(session.tool_overrides())`.
+    ///
+    /// # Errors
+    /// - Unknown session
+    pub async fn tool_sources_in(&self, session_id: &str) -> Result<Vec<tool_toggles::ToolSource>> {
+        let index = self.session_index(session_id)?;
+        let agent_id = &self.sessions[index].agent_id;
+        let Some(config) = self.agent_configs.iter().find(|c| c.id == *agent_id) else {
+            return Ok(Vec::new());
+        };
+        Ok(self.tool_sources(config).await)
+    }
+
+    /// Set the excerpts retrieved for a session's next message, replacing
+    /// the previous ones
+    ///
+    /// Frontends that answer questions about a folder (see
+    /// `crate::folder_index`) set them before each message; None stops
+    /// sending them.
+    ///
+    /// # Errors
+    /// - Unknown session
+    pub fn set_retrieved_in(&mut self, session_id: &str, retrieved: Option<String>) -> Result<()> {
+        let index = self.session_index(session_id)?;
+        self.sessions[index].retrieved = retrieved.filter(|r| !r.is_empty());
+        Ok(())
+    }
+
+    /// Send a user message and get a streaming response
+    /// This is the programmatic equivalent of typing a message in the UI
+    /// Returns a channel that will stream the agent's response chunks
+    pub async fn send_message(&mut self, message: &str) -> Result<mpsc::UnboundedReceiver<String>> {
+        let session_id = self.active_session.clone();
+        self.send_message_in(&session_id, message).await
+    }
+
+    /// Send a user message in one session (see `send_message`)
+    ///
+    /// # Errors
+    /// - Unknown session or agent
+    /// - Agent, tool or LLM failure
+    pub async fn send_message_in(
+        &mut self,
+        session_id: &str,
+        message: &str,
+    ) -> Result<mpsc::UnboundedReceiver<String>> {
+        self.send_message_with_images_in(session_id, message, Vec::new())
+            .await
+    }
+
+    /// Send a user message with attached images in one session
+    ///
+    /// # Arguments
+    /// * `session_id` - Session to send in
+    /// * `message` - Message text (may be empty when images are attached)
+    /// * `images` - Images as data URLs; the agent's model must accept image input
+    ///
+    /// # Errors
+    /// Same as `send_message_in`
+    pub async fn send_message_with_images_in(
+        &mut self,
+        session_id: &str,
+        message: &str,
+        images: Vec<String>,
+    ) -> Result<mpsc::UnboundedReceiver<String>> {
+        match self.start_turn(session_id, message, images, None).await? {
+            TurnStep::Answer(stream) => Ok(stream),
+            TurnStep::Tools(mut turn) => {
+                for idx in 0..turn.tool_calls.len() {
+                    self.run_tool_call(session_id, &mut turn, idx, None).await?;
+                }
+                self.finish_tool_turn(session_id, turn).await
+            }
+        }
+    }
+
+    /// Send a user message with attached images in one session of a shared API
+    ///
+    /// The same turn as `send_message_with_images_in`, but the API is unlocked
+    /// while a tool call waits for the user to allow file access (see
+    /// `crate::fs_consent`), so other tabs and frontends can use it for as long
+    /// as the prompt is open. The guard is released once the answer starts
+    /// streaming.
+    ///
+    /// # Arguments
+    /// * `api` - The API, locked by the caller (setup done under the same
+    ///   lock, such as switching the agent, applies to this turn)
+    /// * `session_id` - Session to send in
+    /// * `message` - Message text (may be empty when images are attached)
+    /// * `images` - Images as data URLs; the agent's model must accept image input
+    ///
+    /// # Errors
+    /// Same as `send_message_in`
+    pub async fn send_message_shared(
+        api: OwnedMutexGuard<Self>,
+        session_id: &str,
+        message: &str,
+        images: Vec<String>,
+    ) -> Result<mpsc::UnboundedReceiver<String>> {
+        Self::shared_turn(api, session_id, message, images, None).await
+    }
+
+    /// The turn of `send_message_shared`, passing tool calls to `sink`
+    async fn shared_turn(
+        mut api: OwnedMutexGuard<Self>,
+        session_id: &str,
+        message: &str,
+        images: Vec<String>,
+        sink: Option<mpsc::UnboundedSender<StreamItem>>,
+    ) -> Result<mpsc::UnboundedReceiver<String>> {
+        let mut turn = match api.start_turn(session_id, message, images, sink).await? {
+            TurnStep::Answer(stream) => return Ok(stream),
+            TurnStep::Tools(turn) => turn,
+        };
+        for idx in 0..turn.tool_calls.len() {
+            // Wait for a consent prompt without holding the API
+            let mut decided = None;
+            if let Some((consent, plugin, tool)) = api.consent_for(&turn.tool_calls[idx]).await {
+                let shared = Arc::clone(OwnedMutexGuard::mutex(&api));
+                drop(api);
+                let arguments = Some(turn.tool_calls[idx].arguments.clone());
+                let answer = consent
+                    .check_tool_call(&plugin, &tool, arguments.as_ref())
+                    .await;
+                api = shared.lock_owned().await;
+                decided = answer.transpose();
+            }
+            api.run_tool_call(session_id, &mut turn, idx, decided)
+                .await?;
+        }
+        api.finish_tool_turn(session_id, turn).await
+    }
+
+    /// Start a turn: send the message and wait for the agent's first answer
+    ///
+    /// # Arguments
+    /// * `sink` - Typed stream to pass the turn's tool calls to, if any
+    async fn start_turn(
+        &mut self,
+        session_id: &str,
+        message: &str,
+        images: Vec<String>,
+        sink: Option<mpsc::UnboundedSender<StreamItem>>,
+    ) -> Result<TurnStep> {
+        let start_time = std::time::Instant::now();
+        let index = self.session_index(session_id)?;
+        let agent_id = self.sessions[index].agent_id.clone();
+
+        // Daily limits of the profile (see `crate::quota`)
+        let quota = crate::quota::tracker();
+        quota.check()?;
+
+        // Every event produced while handling this message shares one correlation ID
+        let correlation_id = new_correlation_id();
+        self.current_correlation_id = Some(correlation_id.clone());
+        self.sessions[index].correlation_id = Some(correlation_id.clone());
+        tracing::debug!(
+            "⏱️  [PERF] send_message started (correlation_id: {})",
+            correlation_id
+        );
+
+        // 🔍 DEBUG: Check tool state at start of send_message
+        tracing::info!(
+            "🔍 [DEBUG] send_message called - available_tools.len() = {}, agent_configs.len() = {}, agent_id = '{}'",
+            self.available_tools.len(),
+            self.agent_configs.len(),
+            agent_id
+        );
+
+        // 🔍 DEBUG: Log all available tool names
+        if !self.available_tools.is_empty() {
+            let tool_names: Vec<&str> = self
+                .available_tools
+                .iter()
+                .map(|t| t.function.name.as_str())
+                .collect();
+            tracing::info!("🔍 [DEBUG] Available tools: {:?}", tool_names);
+        } else {
+            tracing::warn!("🔍 [DEBUG] WARNING: available_tools is EMPTY!");
+        }
+
+        // 🔍 DEBUG: Log all agent config IDs and their isPrimary status
+        for config in &self.agent_configs {
+            tracing::info!(
+                "🔍 [DEBUG] Agent config: id='{}', name='{}', isPrimary={}, enabled={}",
+                config.id,
+                config.name,
+                config.is_primary,
+                config.enabled
+            );
+        }
+
+        // Get context messages (pinned + summary + last N messages) - WITHOUT adding current
+        // message yet. The agent will receive the current message separately and add it to context
+        self.update_summary(index).await;
+        let user_msg = LlmMessage::new("user", message).with_images(images);
+        let request_tokens = self.make_room(session_id, index, &user_msg).await?;
+        // Counted and announced only once it's going out (a message that
+        // can't fit isn't), with everything sent along with it
+        quota.record_message(request_tokens as usize);
+        let _ = self.event_bus.publish(
+            Event::new(
+                "user".to_string(),
+                agent_id.clone(),
+                EventKind::UserMessage(message.to_string()),
+            )
+            .with_correlation_id(Some(correlation_id.clone())),
+        );
+        let context_messages = self.context_messages(index);
+
+        tracing::debug!("⏱️  [PERF] Context prepared in {:?}", start_time.elapsed());
+
+        // OPTIMIZATION: Publish immediate "thinking" status for better perceived performance
+        // This provides instant feedback to the user before we wait for the LLM response
+        let _ = self.event_bus.publish(
+            Event::new(
+                "system".to_string(),
+                "broadcast".to_string(),
+                EventKind::AgentStatusChange {
+                    agent_id: agent_id.clone(),
+                    status: AgentStatus::Thinking,
+                },
+            )
+            .with_correlation_id(Some(correlation_id.clone())),
+        );
+        tracing::debug!(
+            "⏱️  [PERF] Published thinking status at {:?}",
+            start_time.elapsed()
+        );
+
+        // Find active agent
+        let agent = self
+            .agents
+            .iter()
+            .find(|a| a.id() == agent_id)
+            .context("Active agent not found")?;
+
+        // Determine if we should pass tools (only for primary agent)
+        let tools = self
+            .agent_tools(&agent_id, &self.sessions[index].tool_overrides)
+            .await;
+
+        // Log tool count if tools are being passed
+        if let Some(ref tool_list) = tools {
+            tracing::debug!(
+                "Passing {} tools to primary agent: {:?}",
+                tool_list.len(),
+                tool_list
+                    .iter()
+                    .map(|t| &t.function.name)
+                    .collect::<Vec<_>>()
+            );
+        }
+
+        // DIAGNOSTIC: Log tool passing status at INFO level for debugging
+        if let Some(ref tool_list) = tools {
+            tracing::info!(
+                "🔧 [API] Passing {} tools to agent '{}': {:?}",
+                tool_list.len(),
+                agent_id,
+                tool_list
+                    .iter()
+                    .map(|t| &t.function.name)
+                    .collect::<Vec<_>>()
+            );
+        } else {
+            tracing::info!("🔧 [API] No tools passed to agent '{}'", agent_id);
+        }
+
+        // Process message through agent (non-blocking)
+        tracing::debug!(
+            "⏱️  [PERF] Starting agent processing at {:?}",
+            start_time.elapsed()
+        );
+        let mut result_rx = agent.process_message_nonblocking(
+            user_msg.clone(),
+            context_messages,
+            tools,
+            Some(correlation_id.clone()),
+        );
+
+        // Add user message to history AFTER sending to agent
+        // This ensures the next message will have this one as context
+        tracing::debug!(
+            "📝 [HISTORY] Adding USER message - content_len: {}, total_history: {}",
+            user_msg.content.len(),
+            self.sessions[index].history.len() + 1
+        );
+        self.sessions[index].history.push_back(user_msg);
+
+        // Trim history if needed
+        let (strategy, max) = self.pruning_for(&agent_id);
+        self.sessions[index].prune_history(strategy, max);
+
+        // Wait for the agent response and handle tool execution if needed
+        tracing::debug!(
+            "⏱️  [PERF] Waiting for agent response at {:?}",
+            start_time.elapsed()
+        );
+        let agent_response_result = result_rx.recv().await.context("No response from agent")?;
+
+        tracing::debug!(
+            "⏱️  [PERF] Agent response received at {:?}",
+            start_time.elapsed()
+        );
+
+        match agent_response_result {
+            Ok(AgentResponse::StreamingResponse(stream)) => {
+                // No tools needed - return stream directly
+                tracing::debug!(
+                    "⏱️  [PERF] Streaming response started at {:?}",
+                    start_time.elapsed()
+                );
+
+                // Publish responding status
+                let _ = self.event_bus.publish(
+                    Event::new(
+                        "system".to_string(),
+                        "broadcast".to_string(),
+                        EventKind::AgentStatusChange {
+                            agent_id: agent_id.clone(),
+                            status: AgentStatus::Responding,
+                        },
+                    )
+                    .with_correlation_id(Some(correlation_id.clone())),
+                );
+
+                Ok(TurnStep::Answer(stream))
+            }
+            Ok(AgentResponse::NeedsToolExecution {
+                tool_calls,
+                messages,
+            }) => {
+                tracing::info!(
+                    "Tool execution required: {} tools to execute",
+                    tool_calls.len()
+                );
+                tracing::debug!(
+                    "⏱️  [PERF] Tool execution phase started at {:?}",
+                    start_time.elapsed()
+                );
+
+                // CRITICAL FIX: Add the assistant message with tool calls to conversation history
+                // The messages array from the agent includes: [...context, user_msg, assistant_with_tool_calls]
+                // We need to add the assistant message to our history BEFORE adding tool results
+                if let Some(assistant_msg) = messages.iter().rev().find(|m| m.role == "assistant") {
+                    tracing::debug!("📝 [HISTORY] Adding ASSISTANT message with tool calls - content_len: {}, tool_calls: {}, total_history: {}",
+                        assistant_msg.content.len(),
+                        assistant_msg.tool_calls.as_ref().map(|tc| tc.len()).unwrap_or(0),
+                        self.sessions[index].history.len() + 1);
+
+                    // DEFENSIVE: Validate before adding
+                    if assistant_msg.content.is_empty() && assistant_msg.tool_calls.is_none() {
+                        tracing::error!("❌ [HISTORY] BLOCKED: Assistant message has EMPTY content AND no tool_calls!");
+                    } else {
+                        self.sessions[index]
+                            .history
+                            .push_back(assistant_msg.clone());
+                    }
+                }
+
+                Ok(TurnStep::Tools(ToolTurn {
+                    agent_id,
+                    correlation_id,
+                    tool_calls,
+                    messages,
+                    sink,
+                    started: start_time,
+                }))
+            }
+            Err(e) => {
+                // Error occurred during agent processing
+                Err(e)
+            }
+        }
+    }
+
+    /// Run tool call `idx` of a turn and add its result to the history
+    ///
+    /// # Arguments
+    /// * `decided` - The call's result if it was settled without running the
+    ///   tool (the user denied file access while the API was unlocked)
+    async fn run_tool_call(
+        &mut self,
+        session_id: &str,
+        turn: &mut ToolTurn,
+        idx: usize,
+        decided: Option<Result<String>>,
+    ) -> Result<()> {
+        let index = self.session_index(session_id)?;
+        let tool_call = &turn.tool_calls[idx];
+        tracing::info!(
+            "Executing tool {}/{}: {} (ID: {})",
+            idx + 1,
+            turn.tool_calls.len(),
+            tool_call.name,
+            tool_call.id
+        );
+
+        // Publish tool execution status
+        let event = Event::new(
+            turn.agent_id.clone(),
+            "broadcast".to_string(),
+            EventKind::AgentStatusChange {
+                agent_id: turn.agent_id.clone(),
+                status: AgentStatus::ExecutingTool(tool_call.name.clone()),
+            },
+        )
+        .with_correlation_id(Some(turn.correlation_id.clone()));
+        let _ = self.event_bus.publish(event);
+
+        // Tool call cards in the chat view
+        let mut record = ToolCallRecord {
+            id: tool_call.id.clone(),
+            name: tool_call.name.clone(),
+            arguments: tool_call.arguments.clone(),
+            result: None,
+            duration: std::time::Duration::ZERO,
+            warning: None,
+        };
+        self.publish_tool_call(turn, index, record.clone());
+
+        let tool_start = std::time::Instant::now();
+
+        // Execute the tool (delegates to specialist agent); a handoff changes
+        // the session, so it's handled here
+        let args_str = tool_call.arguments.to_string();
+        let result = if let Some(result) = decided {
+            result
+        } else if tool_call.name == handoff::TOOL_NAME {
+            match self.handoff_target(&args_str) {
+                Ok((to, summary)) => {
+                    let handoff = self.sessions[index].hand_off(to, summary);
+                    self.publish_handoff(index, &turn.correlation_id, &handoff);
+                    Ok(handoff.tool_result())
+                }
+                Err(e) => Err(e),
+            }
+        } else {
+            self.execute_tool(&tool_call.name, &args_str).await
+        };
+        self.tool_metrics
+            .entry(tool_call.name.clone())
+            .or_default()
+            .record(tool_start.elapsed(), result.is_ok());
+
+        // Tool output is untrusted: warn when it addresses the model
+        let warning = result
+            .as_ref()
+            .ok()
+            .and_then(|output| untrusted::scan(output));
+        if let Some(warning) = &warning {
+            tracing::warn!("⚠️  {} returned: {}", tool_call.name, warning);
+        }
+
+        record.duration = tool_start.elapsed();
+        record.warning = warning.as_ref().map(ToString::to_string);
+        record.result = Some(match &result {
+            Ok(output) => Ok(output.clone()),
+            Err(e) => Err(format!("{:#}", e)),
+        });
+        self.publish_tool_call(turn, index, record);
+        let result = result?;
+
+        tracing::info!(
+            "Tool {} completed in {:?}, result length: {} chars",
+            tool_call.name,
+            tool_start.elapsed(),
+            result.len()
+        );
+        tracing::debug!(
+            "⏱️  [PERF] Tool {}/{} completed at {:?} (took {:?})",
+            idx + 1,
+            turn.tool_calls.len(),
+            turn.started.elapsed(),
+            tool_start.elapsed()
+        );
+
+        // Labelled as untrusted data before the model sees it
+        let result = untrusted::wrap(&tool_call.name, &result, warning.as_ref());
+
+        // Add tool result to messages array for current request
+        let tool_call_id = tool_call.id.clone();
+        turn.messages.push(LlmMessage::tool_result(
+            tool_call_id.clone(),
+            result.clone(),
+        ));
+
+        // CRITICAL FIX: Add actual tool result content to conversation history
+        // (Previously stored placeholder "Tool executed", now stores actual result for better context)
+        tracing::debug!(
+            "📝 [HISTORY] Adding TOOL RESULT - tool_id: {}, result_len: {}, total_history: {}",
+            tool_call_id,
+            result.len(),
+            self.sessions[index].history.len() + 1
+        );
+
+        // DEFENSIVE: Validate tool result has content
+        if result.is_empty() {
+            tracing::warn!("⚠️  [HISTORY] Tool result for {} is EMPTY - adding anyway (required for conversation flow)", tool_call_id);
+        }
+
+        self.sessions[index]
+            .history
+            .push_back(LlmMessage::tool_result(tool_call_id, result));
+        Ok(())
+    }
+
+    /// Finish a turn whose tool calls have all run: send their results and
+    /// get the answer
+    async fn finish_tool_turn(
+        &mut self,
+        session_id: &str,
+        turn: ToolTurn,
+    ) -> Result<mpsc::UnboundedReceiver<String>> {
+        self.session_index(session_id)?;
+        let ToolTurn {
+            agent_id,
+            correlation_id,
+            messages,
+            started: start_time,
+            ..
+        } = turn;
+
+        // Make follow-up request with tool results to get final response
+        tracing::info!("All tools executed, requesting final response from agent");
+        tracing::debug!(
+            "⏱️  [PERF] All tools completed at {:?}, requesting final response",
+            start_time.elapsed()
+        );
+
+        // DEBUG: Log messages array to diagnose empty content error
+        tracing::debug!(
+            "Messages array before process_with_results ({} messages):",
+            messages.len()
+        );
+        for (idx, msg) in messages.iter().enumerate() {
+            tracing::debug!(
+                "  Message[{}]: role={}, content_len={}, has_tool_calls={}, has_tool_call_id={}",
+                idx,
+                msg.role,
+                msg.content.len(),
+                msg.tool_calls.is_some(),
+                msg.tool_call_id.is_some()
+            );
+        }
+
+        let agent = self
+            .agents
+            .iter()
+            .find(|a| a.id() == agent_id)
+            .context("Active agent not found")?;
+        let mut final_result_rx =
+            agent.process_with_results(messages, Some(correlation_id.clone()));
+
+        // Wait for the final streaming response
+        let final_stream = match final_result_rx.recv().await {
+            Some(Ok(stream)) => {
+                tracing::debug!(
+                    "⏱️  [PERF] Final streaming response started at {:?}",
+                    start_time.elapsed()
+                );
+                Ok(stream)
+            }
+            Some(Err(e)) => Err(e),
+            None => anyhow::bail!("No final response from agent"),
+        }?;
+
+        // Publish responding status for final response
+        let _ = self.event_bus.publish(
+            Event::new(
+                "system".to_string(),
+                "broadcast".to_string(),
+                EventKind::AgentStatusChange {
+                    agent_id: agent_id.clone(),
+                    status: AgentStatus::Responding,
+                },
+            )
+            .with_correlation_id(Some(correlation_id.clone())),
+        );
+
+        // Return the final stream
+        Ok(final_stream)
+    }
+
+    /// Send a user message in one session of a shared API and get a typed
+    /// stream of the turn
+    ///
+    /// Runs the turn of `send_message_shared` in a task and returns at once.
+    /// The stream carries each tool call as it starts and as it ends, the
+    /// answer text, an estimated usage report and a final `Done` with the whole
+    /// answer (see `crate::chat_stream`); a turn that fails ends with `Error`
+    /// instead. Record the answer with `add_assistant_response_in` as with
+    /// `send_message`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let guard = Arc::clone(&api).lock_owned().await;
+    /// let mut stream = RustbotApi::send_message_stream_in(guard, "default", "Hi", Vec::new());
+    /// while let Some(item) = stream.recv().await {
+    ///     match item {
+    ///         StreamItem::TextDelta(text) => print!("{}", text),
+    ///         StreamItem::Done(response) => {
+    ///             api.lock().await.add_assistant_response_in("default", response)?
+    ///         }
+    ///         StreamItem::Error(message) => eprintln!("{}", message),
+    ///         _ => {}
+    ///     }
+    /// }
+    /// ```
+    pub fn send_message_stream_in(
+        api: OwnedMutexGuard<Self>,
+        session_id: &str,
+        message: &str,
+        images: Vec<String>,
+    ) -> ChatStream {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let session_id = session_id.to_string();
+        let message = message.to_string();
+        tokio::spawn(async move {
+            let usage = match api.preview_request_in(&session_id).await {
+                Ok(preview) => chat_stream::Usage {
+                    input_tokens: preview.total_tokens()
+                        + request_preview::estimate_tokens(&message),
+                    model: preview.model,
+                    output_tokens: 0,
+                },
+                Err(e) => {
+                    let _ = tx.send(StreamItem::Error(format!("{:#}", e)));
+                    return;
+                }
+            };
+            let sink = Some(tx.clone());
+            match Self::shared_turn(api, &session_id, &message, images, sink).await {
+                Ok(text) => chat_stream::forward_text(text, tx, usage).await,
+                Err(e) => {
+                    let _ = tx.send(StreamItem::Error(format!("{:#}", e)));
+                }
+            }
+        });
+        rx
+    }
+
+    /// Send a message and wait for complete response (blocking)
+    /// This is useful for scripting scenarios where you want the full response
+    /// NOTE: This method is deprecated and may be removed in a future version.
+    /// Use send_message() with proper async handling instead.
+    #[deprecated(note = "Use async send_message() instead to avoid nested runtime issues")]
+    pub fn send_message_blocking(&mut self, message: &str) -> Result<String> {
+        // This is a simplified blocking version that doesn't support tool execution
+        // For full functionality with tool support, use the async send_message() method
+
+        let index = self.session_index(&self.active_session)?;
+        let agent_id = self.sessions[index].agent_id.clone();
+
+        let context_messages = self.context_messages(index);
+
+        let agent = self
+            .agents
+            .iter()
+            .find(|a| a.id() == agent_id)
+            .context("Active agent not found")?;
+
+        let user_msg = LlmMessage::new("user", message);
+        let mut result_rx = agent.process_message_nonblocking(
+            user_msg.clone(),
+            context_messages,
+            None, // No tools in blocking mode to keep it simple
+            None,
+        );
+
+        self.sessions[index].history.push_back(user_msg);
+
+        self.prune_session(index);
+
+        let mut stream_rx = self.runtime.block_on(async {
+            match result_rx.recv().await {
+                Some(Ok(AgentResponse::StreamingResponse(stream))) => Ok(stream),
+                Some(Ok(AgentResponse::NeedsToolExecution { .. })) => {
+                    anyhow::bail!("Tool execution not supported in blocking mode")
+                }
+                Some(Err(e)) => Err(e),
+                None => anyhow::bail!("No response received"),
+            }
+        })?;
+
+        let mut full_response = String::new();
+        self.runtime.block_on(async {
+            while let Some(chunk) = stream_rx.recv().await {
+                full_response.push_str(&chunk);
+            }
+        });
+
+        // CRITICAL: Only add assistant message if it has content
+        // Anthropic API rejects messages with empty content
+        if !full_response.is_empty() {
+            self.sessions[index]
+                .history
+                .push_back(LlmMessage::new("assistant", full_response.clone()));
+        } else {
+            tracing::warn!("⚠️  Skipping empty assistant message in history");
+        }
+
+        Ok(full_response)
+    }
+
+    /// Clear the message history
+    /// This is the programmatic equivalent of the "Clear" button
+    pub fn clear_history(&mut self) {
+        let session_id = self.active_session.clone();
+        if let Err(e) = self.clear_history_in(&session_id) {
+            tracing::warn!("Failed to clear history: {}", e);
+        }
+    }
+
+    /// Clear the message history of one session
+    ///
+    /// # Errors
+    /// - Unknown session
+    pub fn clear_history_in(&mut self, session_id: &str) -> Result<()> {
+        let index = self.session_index(session_id)?;
+        tracing::info!(
+            "🗑️  Clearing conversation history of '{}' ({} messages)",
+            session_id,
+            self.sessions[index].history.len()
+        );
+        self.sessions[index].history.clear();
+        self.sessions[index].pinned.clear();
+        self.sessions[index].summary = None;
+        self.sessions[index].unsummarized.clear();
+        self.sessions[index].retrieved = None;
+        self.sessions[index].handoff = None;
+        self.sessions[index].tool_overrides.clear();
+
+        // Publish clear conversation event to notify all subscribers
+        let event = Event::new(
+            "api".to_string(),
+            "broadcast".to_string(),
+            EventKind::SystemCommand(crate::events::SystemCommand::ClearConversation),
+        );
+
+        if let Err(e) = self.event_bus.publish(event) {
+            tracing::warn!("Failed to publish clear conversation event: {:?}", e);
+        }
+        Ok(())
+    }
+
+    /// Publish the state of a tool call for the session at `index`, and pass
+    /// it to the turn's typed stream if it has one
+    fn publish_tool_call(&self, turn: &ToolTurn, index: usize, call: ToolCallRecord) {
+        if let Some(sink) = &turn.sink {
+            let _ = sink.send(StreamItem::from_tool_call(&call));
+        }
+        let event = Event::new(
+            turn.agent_id.clone(),
+            "broadcast".to_string(),
+            EventKind::ToolCall {
+                session_id: self.sessions[index].id.clone(),
+                call,
+            },
+        )
+        .with_correlation_id(Some(turn.correlation_id.clone()));
+        let _ = self.event_bus.publish(event);
+    }
+
+    /// Specialists the primary agent can hand a conversation to, as (ID, name)
+    fn handoff_targets(&self) -> Vec<(String, String)> {
+        self.agent_configs
+            .iter()
+            .filter(|c| !c.is_primary && c.enabled)
+            .filter(|c| self.agents.iter().any(|a| a.id() == c.id))
+            .map(|c| (c.id.clone(), c.name.clone()))
+            .collect()
+    }
+
+    /// Check the arguments of a `handoff` call
+    ///
+    /// # Returns
+    /// The specialist to hand off to and the summary for it
+    ///
+    /// # Errors
+    /// - Invalid arguments, or the agent isn't an enabled specialist
+    fn handoff_target(&self, arguments: &str) -> Result<(String, String)> {
+        let (to, summary) = handoff::parse_arguments(arguments)?;
+        if !self.handoff_targets().iter().any(|(id, _)| *id == to) {
+            anyhow::bail!("'{}' isn't an enabled specialist agent", to);
+        }
+        Ok((to, summary))
+    }
+
+    /// Tell frontends who owns a session's conversation after a handoff
+    fn publish_handoff(&self, index: usize, correlation_id: &str, handoff: &Handoff) {
+        tracing::info!(
+            "🤝 Session '{}' handed from '{}' to '{}'",
+            self.sessions[index].id,
+            handoff.from,
+            handoff.to
+        );
+        let event = Event::new(
+            handoff.from.clone(),
+            "broadcast".to_string(),
+            EventKind::Handoff {
+                session_id: self.sessions[index].id.clone(),
+                handoff: handoff.clone(),
+            },
+        )
+        .with_correlation_id(Some(correlation_id.to_string()));
+        let _ = self.event_bus.publish(event);
+    }
+
+    /// What the next request in a session will contain, for the context inspector
+    ///
+    /// Uses the same system message, history and tools as `send_message_in`;
+    /// only the new user message is missing.
+    ///
+    /// # Errors
+    /// Unknown session, or the session's agent isn't registered
+    pub async fn preview_request_in(&self, session_id: &str) -> Result<RequestPreview> {
+        let index = self.session_index(session_id)?;
+        let session = &self.sessions[index];
+        let agent = self
+            .agents
+            .iter()
+            .find(|a| a.id() == session.agent_id)
+            .context("Active agent not found")?;
+
+        Ok(RequestPreview {
+            session_id: session_id.to_string(),
+            agent_id: session.agent_id.clone(),
+            model: agent.model().to_string(),
+            system: agent.system_sections(),
+            history: self.context_messages(index),
+            max_history: self.pruning_for(&session.agent_id).1,
+            tools: self
+                .agent_tools(&session.agent_id, &session.tool_overrides)
+                .await
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Call counts and timings for every tool used so far
+    pub fn tool_metrics(&self) -> &HashMap<String, ToolMetrics> {
+        &self.tool_metrics
+    }
+
+    /// Memory, event bus and history figures for a diagnostics view
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let metrics = api.metrics();
+    /// println!("{} events queued", metrics.event_bus.queued);
+    /// ```
+    pub fn metrics(&self) -> ApiMetrics {
+        ApiMetrics {
+            rss_bytes: rss_bytes(),
+            event_bus: self.event_bus.stats(),
+            sessions: self.sessions.len(),
+            history_messages: self.sessions.iter().map(|s| s.history.len()).sum(),
+            tools: self.available_tools.len(),
+            tool_calls: self.tool_metrics.values().map(|m| m.calls).sum(),
+        }
+    }
+
+    /// Get the current message history (of the active session)
+    pub fn get_history(&self) -> Vec<LlmMessage> {
+        self.active().history()
+    }
+
+    /// Replace the message history (e.g. when restoring a saved session)
+    ///
+    /// Only the most recent turns that fit in `max_history_size` messages
+    /// are kept.
+    pub fn restore_history(&mut self, messages: Vec<LlmMessage>) {
+        let session_id = self.active_session.clone();
+        if let Err(e) = self.restore_history_in(&session_id, messages) {
+            tracing::warn!("Failed to restore history: {}", e);
+        }
+    }
+
+    /// Replace the message history of one session (see `restore_history`)
+    ///
+    /// # Errors
+    /// - Unknown session
+    pub fn restore_history_in(
+        &mut self,
+        session_id: &str,
+        messages: Vec<LlmMessage>,
+    ) -> Result<()> {
+        let index = self.session_index(session_id)?;
+        tracing::info!(
+            "📂 Restoring conversation history of '{}' ({} messages)",
+            session_id,
+            messages.len()
+        );
+        self.sessions[index].history = messages.into();
+        self.prune_session(index);
+        Ok(())
+    }
+
+    /// Drop every history message after `index` (in the active session)
+    ///
+    /// Used to edit the conversation: keep messages `0..=index` and send a
+    /// new message from there.
+    pub fn truncate_after(&mut self, index: usize) {
+        let session_id = self.active_session.clone();
+        if let Err(e) = self.truncate_after_in(&session_id, index) {
+            tracing::warn!("Failed to truncate history: {}", e);
+        }
+    }
+
+    /// Drop every history message after `index` in one session
+    ///
+    /// # Errors
+    /// - Unknown session
+    pub fn truncate_after_in(&mut self, session_id: &str, index: usize) -> Result<()> {
+        let session = self.session_index(session_id)?;
+        let history = &mut self.sessions[session].history;
+        tracing::info!(
+            "✂️  Truncating history of '{}' after message {} ({} removed)",
+            session_id,
+            index,
+            history.len().saturating_sub(index + 1)
+        );
+        history.truncate(index + 1);
+        Ok(())
+    }
+
+    /// Remove a user message and everything after it, to resend it edited
+    ///
+    /// Frontends number messages differently from the LLM history (which also
+    /// holds tool calls and is trimmed to `max_history_size`), so the message
+    /// is identified by the number of user messages after it. If it was
+    /// already trimmed away, everything left came after it and the history
+    /// is cleared.
+    ///
+    /// # Arguments
+    /// * `session_id` - Session to edit
+    /// * `later_user_messages` - User messages after the one being removed
+    ///   (0 = the latest)
+    ///
+    /// # Errors
+    /// - Unknown session
+    pub fn rewind_in(&mut self, session_id: &str, later_user_messages: usize) -> Result<()> {
+        let session = self.session_index(session_id)?;
+        let position = self.sessions[session]
+            .history
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, m)| m.role == "user")
+            .nth(later_user_messages)
+            .map(|(position, _)| position);
+
+        match position {
+            Some(0) | None => {
+                self.sessions[session].history.clear();
+                Ok(())
+            }
+            Some(position) => self.truncate_after_in(session_id, position - 1),
+        }
+    }
+
+    /// Remove the latest user message if it never got an answer
+    ///
+    /// A failed request leaves the user message (and any tool calls made
+    /// for it) at the end of the history. Removing it before retrying keeps
+    /// the message from being sent twice.
+    ///
+    /// # Returns
+    /// true if a message was removed
+    ///
+    /// # Errors
+    /// - Unknown session
+    pub fn discard_unanswered_in(&mut self, session_id: &str) -> Result<bool> {
+        let session = self.session_index(session_id)?;
+        let history = &mut self.sessions[session].history;
+        let Some(position) = history.iter().rposition(|m| m.role == "user") else {
+            return Ok(false);
+        };
+        let answered = history
+            .iter()
+            .skip(position + 1)
+            .any(|m| m.role == "assistant" && m.tool_calls.is_none());
+        if answered {
+            return Ok(false);
+        }
+
+        tracing::info!(
+            "↩️  Discarding unanswered message in '{}' ({} messages removed)",
+            session_id,
+            history.len() - position
+        );
+        history.truncate(position);
+        Ok(true)
+    }
+
+    /// Export the current conversation (including tool calls) as a document
+    ///
+    /// Built from the in-memory LLM history, so only the most recent
+    /// `max_history_size` messages are included and message timestamps are
+    /// the export time. The UI exports its full session instead.
+    ///
+    /// # Errors
+    /// - Serialization failure (JSON format)
+    pub fn export_conversation(&self, format: ConversationFormat) -> Result<String> {
+        let active = self.active();
+        let mut session = ConversationSession::new(active.agent_id.clone());
+        session.history = active.history();
+        session.messages = active
+            .history
+            .iter()
+            .filter(|m| m.role == "user" || (m.role == "assistant" && m.tool_calls.is_none()))
+            .map(|m| SessionMessage {
+                role: m.role.clone(),
+                content: m.content.clone(),
+                timestamp: session.created_at,
+                input_tokens: None,
+                output_tokens: None,
+                agent_id: None,
+                model: None,
+                first_token_ms: None,
+                generation_ms: None,
+                pinned: false,
+                feedback: None,
+            })
+            .collect();
+        session.update_title();
+
+        ConversationExport::from_session(&session)
+            .render(format)
+            .map_err(|e| anyhow::anyhow!("Failed to export conversation: {}", e))
+    }
+
+    /// Get the status of an agent
+    pub fn agent_status(&self, agent_id: &str) -> Option<&AgentStatus> {
+        self.agents
+            .iter()
+            .find(|a| a.id() == agent_id)
+            .map(|a| a.status())
+    }
+
+    /// Get the status of the currently active agent
+    pub fn current_agent_status(&self) -> Option<&AgentStatus> {
+        self.agent_status(self.active_agent())
+    }
+
+    /// Publish a custom event to the event bus
+    /// This allows external code to participate in the event system
+    pub fn publish_event(&self, event: Event) -> Result<()> {
+        self.event_bus
+            .publish(event)
+            .map(|_| ())
+            .map_err(|e| anyhow::anyhow!("Failed to publish event: {:?}", e))
+    }
+
+    /// Subscribe to events from the event bus
+    /// Returns a receiver that will get all published events
+    pub fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<Event> {
+        self.event_bus.subscribe()
+    }
+
+    /// Add an assistant response to the message history
+    /// This should be called after receiving the complete response from streaming
+    pub fn add_assistant_response(&mut self, response: String) {
+        let session_id = self.active_session.clone();
+        if let Err(e) = self.add_assistant_response_in(&session_id, response) {
+            tracing::warn!("Failed to record assistant response: {}", e);
+        }
+    }
+
+    /// Add an assistant response to one session's history
+    ///
+    /// # Errors
+    /// - Unknown session (e.g. closed while the response streamed)
+    pub fn add_assistant_response_in(&mut self, session_id: &str, response: String) -> Result<()> {
+        let index = self.session_index(session_id)?;
+        let agent_id = self.sessions[index].agent_id.clone();
+        tracing::debug!(
+            "📝 [HISTORY] add_assistant_response called - response_len: {}, total_history: {}",
+            response.len(),
+            self.sessions[index].history.len()
+        );
+
+        // CRITICAL: Only add assistant message if it has content
+        // Anthropic API rejects messages with empty content
+        if !response.is_empty() {
+            tracing::debug!(
+                "📝 [HISTORY] Adding FINAL ASSISTANT response - content_len: {}, total_history: {}",
+                response.len(),
+                self.sessions[index].history.len() + 1
+            );
+            crate::quota::tracker().record_response(crate::tokenizer::count_tokens(&response));
+            self.sessions[index]
+                .history
+                .push_back(LlmMessage::new("assistant", response.clone()));
+        } else {
+            tracing::warn!(
+                "⚠️  [HISTORY] BLOCKED: Skipping empty assistant message in add_assistant_response"
+            );
+        }
+
+        // Close out the current turn so observers can group the final response
+        let correlation_id = self.sessions[index].correlation_id.take();
+        if self.current_correlation_id == correlation_id {
+            self.current_correlation_id = None;
+        }
+        let _ = self.event_bus.publish(
+            Event::new(
+                agent_id.clone(),
+                "user".to_string(),
+                EventKind::AgentMessage {
+                    agent_id: agent_id.clone(),
+                    content: response,
+                },
+            )
+            .with_correlation_id(correlation_id),
+        );
+
+        // Trim history if needed
+        self.prune_session(index);
+        Ok(())
+    }
+}
+
+/// Implement ToolExecutor for RustbotApi
+/// This allows agents to execute tool calls by delegating to specialist agents or MCP plugins
+#[async_trait]
+impl ToolExecutor for RustbotApi {
+    async fn execute_tool(&self, tool_name: &str, arguments: &str) -> Result<String> {
+        tracing::info!("Executing tool: {} with args: {}", tool_name, arguments);
+
+        // Check if this is an MCP tool
+        if Self::is_mcp_tool(tool_name) {
+            tracing::debug!("Routing to MCP tool: {}", tool_name);
+            return self.execute_mcp_tool(tool_name, arguments).await;
+        }
+
+        // Script tools run synchronously in the Rhai engine
+        #[cfg(feature = "scripting")]
+        if ScriptHost::is_script_tool(tool_name) {
+            tracing::debug!("Routing to script tool: {}", tool_name);
+            let host = self
+                .script_host
+                .clone()
+                .context("Script tool called but scripting is not enabled")?;
+            let (name, args) = (tool_name.to_string(), arguments.to_string());
+            let result =
+                tokio::task::spawn_blocking(move || host.call_tool(&name, &args)).await??;
+            return Ok(result);
+        }
+
+        // WASM tools run synchronously in a fresh sandboxed instance
+        #[cfg(feature = "wasm-tools")]
+        if WasmToolHost::is_wasm_tool(tool_name) {
+            tracing::debug!("Routing to WASM tool: {}", tool_name);
+            let host = self
+                .wasm_tools
+                .clone()
+                .context("WASM tool called but the WebAssembly runtime is unavailable")?;
+            let (name, args) = (tool_name.to_string(), arguments.to_string());
+            let result =
+                tokio::task::spawn_blocking(move || host.call_tool(&name, &args)).await??;
+            return Ok(result);
+        }
+
+        // Native plugin tools may block, so they get a blocking thread too
+        #[cfg(feature = "native-plugins")]
+        if NativePluginHost::is_native_tool(tool_name) {
+            tracing::debug!("Routing to native plugin tool: {}", tool_name);
+            let host = self
+                .native_plugins
+                .clone()
+                .context("Native plugin tool called but no plugins are loaded")?;
+            let (name, args) = (tool_name.to_string(), arguments.to_string());
+            let result =
+                tokio::task::spawn_blocking(move || host.call_tool(&name, &args)).await??;
+            return Ok(result);
+        }
+
+        // Email tools (only routed when a connector is configured, so agents
+        // with the same name still work without one)
+        #[cfg(feature = "email")]
+        if let Some(email) = self
+            .email
+            .as_ref()
+            .filter(|_| EmailService::is_email_tool(tool_name))
+        {
+            tracing::debug!("Routing to email tool: {}", tool_name);
+            return email.call_tool(tool_name, arguments).await;
+        }
+
+        // Calendar tools (same rule as email tools)
+        #[cfg(feature = "calendar")]
+        if let Some(calendar) = self
+            .calendar
+            .as_ref()
+            .filter(|_| CalendarService::is_calendar_tool(tool_name))
+        {
+            tracing::debug!("Routing to calendar tool: {}", tool_name);
+            return calendar.call_tool(tool_name, arguments).await;
+        }
+
+        // Git tools (same rule as email tools)
+        if let Some(git) = self
+            .git
+            .as_ref()
+            .filter(|_| GitTools::is_git_tool(tool_name))
+        {
+            tracing::debug!("Routing to git tool: {}", tool_name);
+            return git.call_tool(tool_name, arguments).await;
+        }
+
+        // Not an MCP tool - route to specialist agent
+        tracing::debug!("Routing to specialist agent: {}", tool_name);
+
+        // Find the specialist agent matching the tool name
+        let specialist_agent = self
+            .agents
+            .iter()
+            .find(|a| a.id() == tool_name)
+            .context(format!("Specialist agent '{}' not found", tool_name))?;
+
+        // Parse arguments JSON (could be used to construct a more specific prompt)
+        // For now, we'll just pass the arguments as the user message
+        let prompt = format!("Execute with arguments: {}", arguments);
+
+        // Execute the specialist agent with no context and no tools
+        let mut result_rx = specialist_agent.process_message_nonblocking(
+            LlmMessage::new("user", prompt),
+            vec![], // No conversation context for tool execution
+            None,   // Specialist agents don't get tools
+            self.current_correlation_id.clone(),
+        );
+
+        // Await and collect the result
+        let mut stream_rx = match result_rx.recv().await {
+            Some(Ok(AgentResponse::StreamingResponse(stream))) => Ok(stream),
+            Some(Ok(AgentResponse::NeedsToolExecution { .. })) => {
+                anyhow::bail!("Unexpected: Specialist agent requested tool execution")
+            }
+            Some(Err(e)) => Err(e),
+            None => anyhow::bail!("No response from specialist agent"),
+        }?;
+
+        // Collect all chunks into result
+        let mut result = String::new();
+        while let Some(chunk) = stream_rx.recv().await {
+            result.push_str(&chunk);
+        }
+
+        tracing::info!("Tool execution result: {}", result);
+        Ok(result)
+    }
+}
+
+impl RustbotApi {
+    /// Plugin and tool name on the server of an MCP tool; a tool renamed by
+    /// an override is called by its name on the server
+    async fn mcp_target(&self, tool_name: &str) -> Result<(String, String)> {
+        let (plugin_id, mut mcp_tool_name) = Self::parse_mcp_tool_name(tool_name)?;
+        if let Some(entry) = self.mcp_tools.read().await.get(tool_name) {
+            mcp_tool_name = entry.definition.name.clone();
+        }
+        Ok((plugin_id, mcp_tool_name))
+    }
+
+    /// The consent checker, plugin and tool to ask about before a tool call,
+    /// if it's an MCP call and file access prompts are set up
+    async fn consent_for(&self, call: &ToolCall) -> Option<(Arc<FsConsent>, String, String)> {
+        let consent = self.fs_consent.as_ref()?;
+        if !Self::is_mcp_tool(&call.name) {
+            return None;
+        }
+        let (plugin_id, tool) = self.mcp_target(&call.name).await.ok()?;
+        Some((Arc::clone(consent), plugin_id, tool))
+    }
+
+    /// Execute an MCP tool through the plugin manager
+    ///
+    /// Internal helper for routing MCP tool calls. Parses the tool name,
+    /// validates the plugin is running, and executes the tool.
+    ///
+    /// # Arguments
+    /// * `tool_name` - Namespaced tool name (mcp:plugin_id:tool_name)
+    /// * `arguments` - JSON-encoded tool arguments
+    ///
+    /// # Returns
+    /// Tool execution result as string, or error
+    ///
+    /// # Errors
+    /// - Invalid tool name format
+    /// - MCP manager not configured
+    /// - Plugin not running
+    /// - Tool execution failed
+    async fn execute_mcp_tool(&self, tool_name: &str, arguments: &str) -> Result<String> {
+        let (plugin_id, mcp_tool_name) = self.mcp_target(tool_name).await?;
+
+        tracing::debug!(
+            "Executing MCP tool '{}' on plugin '{}'",
+            mcp_tool_name,
+            plugin_id
+        );
+
+        // Get MCP manager
+        let manager = self
+            .mcp_manager
+            .as_ref()
+            .context("MCP plugin manager not configured")?;
+
+        // Parse arguments to JSON
+        let args_json: Option<serde_json::Value> = if arguments.trim().is_empty() {
+            None
+        } else {
+            Some(serde_json::from_str(arguments).context(format!(
+                "Invalid JSON arguments for MCP tool '{}': {}",
+                tool_name, arguments
+            ))?)
+        };
+
+        // Ask before the plugin touches a directory it hasn't been granted
+        if let Some(consent) = &self.fs_consent {
+            if let Some(denied) = consent
+                .check_tool_call(&plugin_id, &mcp_tool_name, args_json.as_ref())
+                .await?
+            {
+                return Ok(denied);
+            }
+        }
+
+        // Execute tool via manager
+        let mut manager_guard = manager.lock().await;
+        let result = manager_guard
+            .execute_tool(&plugin_id, &mcp_tool_name, args_json)
+            .await
+            .map_err(|e| match e {
+                // Not running or not answering: something the user can restart
+                McpError::PluginNotFound(message) | McpError::Transport(message) => {
+                    anyhow::Error::new(RustbotError::PluginDown {
+                        plugin: plugin_id.clone(),
+                        message,
+                    })
+                }
+                e => anyhow::Error::new(e),
+            })
+            .context(format!(
+                "MCP tool execution failed: plugin='{}', tool='{}'",
+                plugin_id, mcp_tool_name
+            ))?;
+
+        tracing::info!(
+            "MCP tool '{}' executed successfully, result length: {} chars",
+            tool_name,
+            result.len()
+        );
+
+        Ok(result)
+    }
+}
+
+/// Builder for creating RustbotApi instances with configuration
+pub struct RustbotApiBuilder {
+    event_bus: Option<Arc<EventBus>>,
+    runtime: Option<Arc<Runtime>>,
+    max_history_size: usize,
+    system_instructions: String,
+    llm_adapter: Option<Arc<dyn LlmAdapter>>,
+    agent_configs: Vec<AgentConfig>,
+}
+
+impl RustbotApiBuilder {
+    /// Create a new builder with default settings
+    pub fn new() -> Self {
+        Self {
+            event_bus: None,
+            runtime: None,
+            max_history_size: 20,
+            system_instructions: String::new(),
+            llm_adapter: None,
+            agent_configs: vec![AgentConfig::default_assistant()],
+        }
+    }
+
+    /// Set the event bus (optional - will create one if not provided)
+    pub fn event_bus(mut self, event_bus: Arc<EventBus>) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Set the tokio runtime (optional - will create one if not provided)
+    pub fn runtime(mut self, runtime: Arc<Runtime>) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    /// Set maximum history size
+    pub fn max_history_size(mut self, size: usize) -> Self {
+        self.max_history_size = size;
+        self
+    }
+
+    /// Set system-level instructions for all agents
+    pub fn system_instructions(mut self, instructions: String) -> Self {
+        self.system_instructions = instructions;
+        self
+    }
+
+    /// Set the LLM adapter for agents
+    pub fn llm_adapter(mut self, adapter: Arc<dyn LlmAdapter>) -> Self {
+        self.llm_adapter = Some(adapter);
+        self
+    }
+
+    /// Add an agent configuration
+    pub fn add_agent(mut self, config: AgentConfig) -> Self {
+        self.agent_configs.push(config);
+        self
+    }
+
+    /// Build the RustbotApi instance
+    pub fn build(self) -> Result<RustbotApi> {
+        let event_bus = self.event_bus.unwrap_or_else(|| Arc::new(EventBus::new()));
+        let runtime = self
+            .runtime
+            .unwrap_or_else(|| Arc::new(Runtime::new().unwrap()));
+
+        let llm_adapter = self.llm_adapter.context("LLM adapter must be provided")?;
+
+        let mut api = RustbotApi::new(
+            Arc::clone(&event_bus),
+            Arc::clone(&runtime),
+            self.max_history_size,
+        );
+
+        // Store agent configs for tool registry
+        api.agent_configs = self.agent_configs.clone();
+
+        // Create agents from configs
+        for config in self.agent_configs {
+            let agent = Agent::new(
+                config,
+                Arc::clone(&llm_adapter),
+                Arc::clone(&event_bus),
+                runtime.handle().clone(),
+                self.system_instructions.clone(),
+            );
+            api.register_agent(agent);
+        }
+
+        // Build initial tool registry from enabled specialist agents
+        api.update_tools();
+
+        Ok(api)
+    }
+}
+
+impl Default for RustbotApiBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::OpenRouterAdapter;
+    use std::sync::Once;
+
+    // Shared runtime for async tests to avoid drop issues
+    static INIT: Once = Once::new();
+    static mut TEST_RUNTIME: Option<Arc<Runtime>> = None;
+
+    fn get_test_runtime() -> Arc<Runtime> {
+        unsafe {
+            INIT.call_once(|| {
+                TEST_RUNTIME = Some(Arc::new(Runtime::new().unwrap()));
+            });
+            TEST_RUNTIME.clone().unwrap()
+        }
+    }
+
+    #[test]
+    fn test_tool_metrics_record() {
+        let mut metrics = ToolMetrics::default();
+        assert_eq!(metrics.average_duration_ms(), 0);
+
+        metrics.record(std::time::Duration::from_millis(100), true);
+        metrics.record(std::time::Duration::from_millis(300), false);
+        assert_eq!(metrics.calls, 2);
+        assert_eq!(metrics.failures, 1);
+        assert_eq!(metrics.average_duration_ms(), 200);
+        assert!(metrics.last_called.is_some());
+    }
+
+    #[test]
+    fn test_metrics() {
+        let event_bus = Arc::new(EventBus::new());
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), get_test_runtime(), 20);
+        api.restore_history(vec![
+            LlmMessage::new("user", "hi"),
+            LlmMessage::new("assistant", "hello"),
+        ]);
+
+        let metrics = api.metrics();
+        assert_eq!(metrics.sessions, 1);
+        assert_eq!(metrics.history_messages, 2);
+        assert_eq!(metrics.tool_calls, 0);
+        assert_eq!(metrics.event_bus, event_bus.stats());
+    }
+
+    #[test]
+    fn test_api_creation() {
+        let event_bus = Arc::new(EventBus::new());
+        let runtime = Arc::new(Runtime::new().unwrap());
+
+        let api = RustbotApi::new(Arc::clone(&event_bus), Arc::clone(&runtime), 20);
+
+        assert_eq!(api.active_agent(), "assistant");
+        assert_eq!(api.list_agents().len(), 0); // No agents registered yet
+    }
+
+    #[test]
+    fn test_restore_history_trims_to_max_size() {
+        let event_bus = Arc::new(EventBus::new());
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), get_test_runtime(), 2);
+
+        api.restore_history(vec![
+            LlmMessage::new("user", "one"),
+            LlmMessage::new("assistant", "two"),
+            LlmMessage::new("user", "three"),
+            LlmMessage::new("assistant", "four"),
+        ]);
+
+        let history = api.get_history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].content, "three");
+    }
+
+    #[test]
+    fn test_trimming_drops_whole_turns() {
+        let event_bus = Arc::new(EventBus::new());
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), get_test_runtime(), 3);
+
+        let call = LlmMessage::with_tool_calls(
+            String::new(),
+            vec![crate::llm::ToolCall {
+                id: "call_1".to_string(),
+                name: "search".to_string(),
+                arguments: serde_json::json!({}),
+            }],
+        );
+        let result = LlmMessage::tool_result("call_1".to_string(), "found it".to_string());
+
+        // Trimming message by message would keep the tool result without its call
+        api.restore_history(vec![
+            LlmMessage::new("user", "look it up"),
+            call,
+            result,
+            LlmMessage::new("assistant", "here it is"),
+            LlmMessage::new("user", "thanks"),
+            LlmMessage::new("assistant", "you're welcome"),
+        ]);
+        let history = api.get_history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].content, "thanks");
+
+        // The newest turn stays even when it alone is too long
+        let long_turn: Vec<LlmMessage> = std::iter::once(LlmMessage::new("user", "go"))
+            .chain((0..4).map(|i| LlmMessage::new("assistant", format!("step {}", i))))
+            .collect();
+        api.restore_history(long_turn);
+        assert_eq!(api.get_history().len(), 5);
+    }
+
+    #[test]
+    fn test_agent_pruning_settings() {
+        let event_bus = Arc::new(EventBus::new());
+        let runtime = get_test_runtime();
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), Arc::clone(&runtime), 20);
+        let mut config = AgentConfig::default_assistant();
+        config.context_pruning = pruning::ContextPruning {
+            strategy: PruningStrategy::SummarizeThenDrop,
+            max_messages: Some(4),
+        };
+        api.register_agent(Agent::new(
+            config,
+            Arc::new(OpenRouterAdapter::new("test-key".to_string())),
+            Arc::clone(&event_bus),
+            runtime.handle().clone(),
+            String::new(),
+        ));
+
+        api.restore_history(vec![
+            LlmMessage::new("user", "one"),
+            LlmMessage::new("assistant", "1"),
+            LlmMessage::new("user", "two"),
+            LlmMessage::new("assistant", "2"),
+            LlmMessage::new("user", "three"),
+            LlmMessage::new("assistant", "3"),
+        ]);
+
+        // The agent's size applies instead of the API's, and the dropped
+        // turns wait to be summarized with the next message
+        let history = api.get_history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].content, "three");
+        assert_eq!(api.active().unsummarized.len(), 4);
+    }
+
+    /// Adapter whose summaries always succeed (nothing is streamed)
+    struct SummaryAdapter;
+
+    #[async_trait]
+    impl LlmAdapter for SummaryAdapter {
+        async fn stream_chat(
+            &self,
+            _request: crate::llm::LlmRequest,
+            _tx: mpsc::UnboundedSender<String>,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        async fn complete_chat(
+            &self,
+            _request: crate::llm::LlmRequest,
+        ) -> Result<crate::llm::LlmResponse> {
+            Ok(crate::llm::LlmResponse {
+                content: "The user asked about Rust lifetimes.".to_string(),
+                tool_calls: None,
+                finish_reason: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "summary"
+        }
+    }
+
+    /// API whose assistant uses GPT-4 (8,192 tokens, 4,096 left for the
+    /// request) and keeps up to 100 messages, with 30 long ones in history
+    fn small_window_api(strategy: PruningStrategy) -> RustbotApi {
+        let event_bus = Arc::new(EventBus::new());
+        let runtime = get_test_runtime();
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), Arc::clone(&runtime), 100);
+        let mut config = AgentConfig::default_assistant();
+        config.model = "openai/gpt-4".to_string();
+        config.context_pruning = pruning::ContextPruning {
+            strategy,
+            max_messages: Some(100),
+        };
+        api.register_agent(Agent::new(
+            config,
+            Arc::new(SummaryAdapter),
+            event_bus,
+            runtime.handle().clone(),
+            String::new(),
+        ));
+
+        let history = (0..30)
+            .map(|i| {
+                let role = if i % 2 == 0 { "user" } else { "assistant" };
+                LlmMessage::new(role, format!("{} {}", i, "word ".repeat(400)))
+            })
+            .collect();
+        api.restore_history(history);
+        assert_eq!(api.get_history().len(), 30);
+        api
+    }
+
+    /// Whether the session's next request, with `message`, fits the window
+    async fn fits(api: &RustbotApi, message: &LlmMessage) -> bool {
+        let preview = api.preview_request_in(DEFAULT_SESSION).await.unwrap();
+        preview.total_tokens() + request_preview::message_tokens(message)
+            <= preview.context_budget()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_make_room_trims_history_until_it_fits() {
+        let mut api = small_window_api(PruningStrategy::SlidingWindow);
+        let message = LlmMessage::new("user", "And what about 'static?");
+        assert!(!fits(&api, &message).await);
+
+        api.make_room(DEFAULT_SESSION, 0, &message).await.unwrap();
+        let history = api.get_history();
+        assert!(!history.is_empty() && history.len() < 30);
+        assert!(fits(&api, &message).await);
+        // The newest turns are the ones kept
+        assert!(history.last().unwrap().content.starts_with("29 "));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_make_room_message_too_long() {
+        for strategy in [
+            PruningStrategy::SlidingWindow,
+            PruningStrategy::SummarizeThenDrop,
+        ] {
+            let mut api = small_window_api(strategy);
+            let contents = |api: &RustbotApi| {
+                let history = api.get_history();
+                history.into_iter().map(|m| m.content).collect::<Vec<_>>()
+            };
+            let history = contents(&api);
+            let message = LlmMessage::new("user", "word ".repeat(20_000));
+
+            let error = api
+                .make_room(DEFAULT_SESSION, 0, &message)
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<RustbotError>(),
+                Some(RustbotError::ContextTooLong(_))
+            ));
+            // Rejected before anything was dropped or summarized
+            assert_eq!(contents(&api), history);
+            assert_eq!(api.active().summary(), None);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_message_too_long_is_not_announced() {
+        let mut api = small_window_api(PruningStrategy::SlidingWindow);
+        let mut events = api.event_bus.subscribe();
+
+        let message = "word ".repeat(20_000);
+        assert!(api.send_message(&message).await.is_err());
+        while let Ok(event) = events.try_recv() {
+            assert!(!matches!(event.kind, EventKind::UserMessage(_)));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_make_room_summarize_then_drop_converges() {
+        let mut api = small_window_api(PruningStrategy::SummarizeThenDrop);
+        let message = LlmMessage::new("user", "And what about 'static?");
+
+        api.make_room(DEFAULT_SESSION, 0, &message).await.unwrap();
+        assert!(fits(&api, &message).await);
+        // The dropped turns live on in the summary, which is counted too
+        assert!(api.get_history().len() < 30);
+        assert_eq!(
+            api.active().summary(),
+            Some("The user asked about Rust lifetimes.")
+        );
+        assert!(api.active().unsummarized.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_typed_stream_ends_with_error() {
+        // No agent is registered, so the turn fails
+        let api = RustbotApi::new(Arc::new(EventBus::new()), get_test_runtime(), 20);
+        let api = Arc::new(Mutex::new(api));
+        let mut stream = RustbotApi::send_message_stream_in(
+            Arc::clone(&api).lock_owned().await,
+            DEFAULT_SESSION,
+            "Hi",
+            Vec::new(),
+        );
+
+        let mut items = Vec::new();
+        while let Some(item) = stream.recv().await {
+            items.push(item);
+        }
+        assert!(matches!(items.last(), Some(StreamItem::Error(_))));
+        // The API is free again once the turn is over
+        assert!(api.try_lock().is_ok());
+    }
+
+    #[test]
+    fn test_sessions_keep_separate_histories() {
+        let event_bus = Arc::new(EventBus::new());
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), get_test_runtime(), 20);
+        api.restore_history(vec![LlmMessage::new("user", "first chat")]);
+
+        let second = "second".to_string();
+        api.create_session(&second).unwrap();
+        assert!(api.create_session(&second).is_err());
+        assert_eq!(api.active_session(), DEFAULT_SESSION);
+        assert_eq!(api.session(&second).unwrap().agent_id(), "assistant");
+
+        api.restore_history_in(&second, vec![LlmMessage::new("user", "second chat")])
+            .unwrap();
+        api.add_assistant_response_in(&second, "reply".to_string())
+            .unwrap();
+        assert_eq!(api.get_history().len(), 1);
+
+        api.switch_session(&second).unwrap();
+        assert_eq!(api.get_history().len(), 2);
+        assert_eq!(api.get_history()[1].content, "reply");
+
+        api.clear_history_in(DEFAULT_SESSION).unwrap();
+        assert!(api.session(DEFAULT_SESSION).unwrap().history().is_empty());
+        assert_eq!(api.get_history().len(), 2);
+    }
+
+    #[test]
+    fn test_set_agent_model() {
+        let event_bus = Arc::new(EventBus::new());
+        let runtime = get_test_runtime();
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), Arc::clone(&runtime), 20);
+        api.register_agent(Agent::new(
+            AgentConfig::default_assistant(),
+            Arc::new(OpenRouterAdapter::new("test-key".to_string())),
+            Arc::clone(&event_bus),
+            runtime.handle().clone(),
+            String::new(),
+        ));
+
+        api.set_agent_model("assistant", Some("openai/gpt-4o-mini".to_string()))
+            .unwrap();
+        assert_eq!(api.agents[0].model_override(), Some("openai/gpt-4o-mini"));
+
+        api.set_agent_model("assistant", None).unwrap();
+        assert_eq!(api.agents[0].model_override(), None);
+
+        assert!(api.set_agent_model("missing", None).is_err());
+    }
+
+    #[test]
+    fn test_add_agent_config() {
+        let event_bus = Arc::new(EventBus::new());
+        let runtime = get_test_runtime();
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), Arc::clone(&runtime), 20);
+        let adapter: Arc<dyn LlmAdapter> = Arc::new(OpenRouterAdapter::new("test-key".to_string()));
+
+        let mut config = AgentConfig::default_assistant();
+        config.id = "researcher".to_string();
+        config.name = "researcher".to_string();
+        config.is_primary = false;
+        api.add_agent_config(config.clone(), Arc::clone(&adapter), String::new())
+            .unwrap();
+
+        assert_eq!(api.list_agents(), vec!["researcher"]);
+        assert!(api
+            .available_tools()
+            .iter()
+            .any(|tool| tool.function.name == "researcher"));
+        assert!(api
+            .add_agent_config(config, adapter, String::new())
+            .is_err());
+    }
+
+    #[test]
+    fn test_preview_request() {
+        let event_bus = Arc::new(EventBus::new());
+        let runtime = get_test_runtime();
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), Arc::clone(&runtime), 2);
+        let mut config = AgentConfig::default_assistant();
+        config.instructions = "Be brief.".to_string();
+        config.personality = None;
+        api.register_agent(Agent::new(
+            config,
+            Arc::new(OpenRouterAdapter::new("test-key".to_string())),
+            Arc::clone(&event_bus),
+            runtime.handle().clone(),
+            "Shared rules.".to_string(),
+        ));
+        api.restore_history(vec![
+            LlmMessage::new("user", "one"),
+            LlmMessage::new("assistant", "two"),
+            LlmMessage::new("user", "three"),
+            LlmMessage::new("assistant", "four"),
+        ]);
+
+        let preview = runtime
+            .block_on(api.preview_request_in(DEFAULT_SESSION))
+            .unwrap();
+        assert_eq!(preview.model, "openai/gpt-4o");
+        assert_eq!(preview.system.len(), 2);
+        assert_eq!(preview.system[0].1, "Shared rules.");
+        assert_eq!(preview.history.len(), 2);
+        assert_eq!(preview.history[0].content, "three");
+        assert!(preview.tools.is_empty());
+
+        assert!(runtime.block_on(api.preview_request_in("missing")).is_err());
+    }
+
+    #[test]
+    fn test_pinned_messages_survive_trimming() {
+        let event_bus = Arc::new(EventBus::new());
+        let runtime = get_test_runtime();
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), Arc::clone(&runtime), 2);
+        api.register_agent(Agent::new(
+            AgentConfig::default_assistant(),
+            Arc::new(OpenRouterAdapter::new("test-key".to_string())),
+            Arc::clone(&event_bus),
+            runtime.handle().clone(),
+            String::new(),
+        ));
+        api.restore_history(vec![
+            LlmMessage::new("user", "My project is called Falcon"),
+            LlmMessage::new("assistant", "two"),
+            LlmMessage::new("user", "three"),
+            LlmMessage::new("assistant", "four"),
+        ]);
+        api.set_pinned_in(
+            DEFAULT_SESSION,
+            vec!["My project is called Falcon".to_string()],
+        )
+        .unwrap();
+
+        let preview = runtime
+            .block_on(api.preview_request_in(DEFAULT_SESSION))
+            .unwrap();
+        assert_eq!(preview.history.len(), 3);
+        assert_eq!(preview.history[0].role, "system");
+        assert!(preview.history[0]
+            .content
+            .contains("1. My project is called Falcon"));
+        assert_eq!(preview.history[1].content, "three");
+
+        // The system context goes ahead of the pinned messages
+        api.set_system_context(Some("## System Context".to_string()));
+        let preview = runtime
+            .block_on(api.preview_request_in(DEFAULT_SESSION))
+            .unwrap();
+        assert_eq!(preview.history.len(), 4);
+        assert_eq!(preview.history[0].content, "## System Context");
+        api.set_system_context(Some(String::new()));
+        assert!(api.system_context.is_none());
+
+        // Retrieved excerpts go right before the history
+        api.set_retrieved_in(DEFAULT_SESSION, Some("src/main.rs:1-40".to_string()))
+            .unwrap();
+        let preview = runtime
+            .block_on(api.preview_request_in(DEFAULT_SESSION))
+            .unwrap();
+        assert_eq!(preview.history[1].content, "src/main.rs:1-40");
+        assert_eq!(preview.history[2].content, "three");
+
+        api.clear_history_in(DEFAULT_SESSION).unwrap();
+        assert!(api.session(DEFAULT_SESSION).unwrap().pinned().is_empty());
+        assert!(api.set_pinned_in("missing", Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_truncate_and_rewind() {
+        let event_bus = Arc::new(EventBus::new());
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), get_test_runtime(), 20);
+        let conversation = vec![
+            LlmMessage::new("user", "one"),
+            LlmMessage::new("assistant", "1"),
+            LlmMessage::new("user", "two"),
+            LlmMessage::tool_result("call-1".to_string(), "result".to_string()),
+            LlmMessage::new("assistant", "2"),
+            LlmMessage::new("user", "three"),
+            LlmMessage::new("assistant", "3"),
+        ];
+
+        api.restore_history(conversation.clone());
+        api.truncate_after(1);
+        assert_eq!(api.get_history().len(), 2);
+
+        // Edit "two": one user message ("three") comes after it
+        api.restore_history(conversation.clone());
+        api.rewind_in(DEFAULT_SESSION, 1).unwrap();
+        let history = api.get_history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].content, "1");
+
+        // Editing the first message, or one trimmed out of the history, clears it
+        api.restore_history(conversation.clone());
+        api.rewind_in(DEFAULT_SESSION, 2).unwrap();
+        assert!(api.get_history().is_empty());
+        api.restore_history(conversation);
+        api.rewind_in(DEFAULT_SESSION, 5).unwrap();
+        assert!(api.get_history().is_empty());
+    }
+
+    #[test]
+    fn test_discard_unanswered() {
+        let event_bus = Arc::new(EventBus::new());
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), get_test_runtime(), 20);
+        let answered = vec![
+            LlmMessage::new("user", "one"),
+            LlmMessage::new("assistant", "1"),
+        ];
+
+        api.restore_history(answered.clone());
+        assert!(!api.discard_unanswered_in(DEFAULT_SESSION).unwrap());
+        assert_eq!(api.get_history().len(), 2);
+
+        // Failed while calling a tool: the message and the tool call go
+        let mut failed = answered;
+        failed.push(LlmMessage::new("user", "two"));
+        failed.push(LlmMessage::tool_result(
+            "call-1".to_string(),
+            "result".to_string(),
+        ));
+        api.restore_history(failed);
+        assert!(api.discard_unanswered_in(DEFAULT_SESSION).unwrap());
+        let history = api.get_history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].content, "1");
+    }
+
+    #[test]
+    fn test_close_session() {
+        let event_bus = Arc::new(EventBus::new());
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), get_test_runtime(), 20);
+        assert!(api.close_session(DEFAULT_SESSION).is_err());
+
+        let second = "second".to_string();
+        api.create_session(&second).unwrap();
+        api.switch_session(&second).unwrap();
+        api.close_session(&second).unwrap();
+
+        assert_eq!(api.active_session(), DEFAULT_SESSION);
+        assert_eq!(api.sessions().len(), 1);
+        assert!(api.switch_session(&second).is_err());
+        assert!(api
+            .add_assistant_response_in(&second, "late".to_string())
+            .is_err());
+    }
+
+    #[test]
+    fn test_export_conversation_markdown() {
+        let event_bus = Arc::new(EventBus::new());
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), get_test_runtime(), 20);
+        api.restore_history(vec![
+            LlmMessage::new("user", "Summarize the README"),
+            LlmMessage::new("assistant", "It describes Rustbot."),
+        ]);
+
+        let markdown = api
+            .export_conversation(ConversationFormat::Markdown)
+            .unwrap();
+        assert!(markdown.starts_with("# Summarize the README"));
+        assert!(markdown.contains("It describes Rustbot."));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_mcp_tool_registration() {
+        let event_bus = Arc::new(EventBus::new());
+        let runtime = get_test_runtime();
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), Arc::clone(&runtime), 20);
+
+        // Create test MCP tool
+        let tool = McpToolDefinition {
+            name: "read_file".to_string(),
+            description: Some("Read a file".to_string()),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string" }
+                },
+                "required": ["path"]
+            }),
+        };
+
+        // Register tool
+        api.register_mcp_tool(tool.clone(), "filesystem".to_string())
+            .await
+            .unwrap();
+
+        // Verify tool appears in available tools
+        let tools = api.get_all_tools();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].function.name, "mcp:filesystem:read_file");
+        assert!(tools[0].function.description.contains("Read a file"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_mcp_tool_unregistration() {
+        let event_bus = Arc::new(EventBus::new());
+        let runtime = get_test_runtime();
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), Arc::clone(&runtime), 20);
+
+        // Register multiple tools from same plugin
+        let tool1 = McpToolDefinition {
+            name: "read_file".to_string(),
+            description: Some("Read a file".to_string()),
+            input_schema: serde_json::json!({"type": "object"}),
+        };
+
+        let tool2 = McpToolDefinition {
+            name: "write_file".to_string(),
+            description: Some("Write a file".to_string()),
+            input_schema: serde_json::json!({"type": "object"}),
+        };
+
+        api.register_mcp_tool(tool1, "filesystem".to_string())
+            .await
+            .unwrap();
+        api.register_mcp_tool(tool2, "filesystem".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(api.get_all_tools().len(), 2);
+
+        // Unregister all tools from plugin
+        api.unregister_mcp_tools("filesystem").await.unwrap();
+
+        assert_eq!(api.get_all_tools().len(), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_tool_overrides() {
+        let event_bus = Arc::new(EventBus::new());
+        let runtime = get_test_runtime();
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), Arc::clone(&runtime), 20);
+        let adapter: Arc<dyn LlmAdapter> = Arc::new(OpenRouterAdapter::new("test-key".to_string()));
+        api.add_agent_config(
+            AgentConfig::default_assistant(),
+            Arc::clone(&adapter),
+            String::new(),
+        )
+        .unwrap();
+        let mut researcher = AgentConfig::default_assistant();
+        researcher.id = "researcher".to_string();
+        researcher.is_primary = false;
+        api.add_agent_config(researcher, adapter, String::new())
+            .unwrap();
+        let tool = McpToolDefinition {
+            name: "read_file".to_string(),
+            description: Some("Read a file".to_string()),
+            input_schema: serde_json::json!({"type": "object"}),
+        };
+        api.register_mcp_tool(tool, "filesystem".to_string())
+            .await
+            .unwrap();
+
+        let tool_names = |preview: RequestPreview| -> Vec<String> {
+            preview.tools.into_iter().map(|t| t.function.name).collect()
+        };
+
+        // Everything is on for the primary agent
+        let sources = api.tool_sources_in(DEFAULT_SESSION).await.unwrap();
+        let ids: Vec<&str> = sources.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["researcher", "mcp:filesystem"]);
+        assert!(sources.iter().all(|s| s.default_enabled));
+
+        let overrides = HashMap::from([("mcp:filesystem".to_string(), false)]);
+        api.set_tool_overrides_in(DEFAULT_SESSION, overrides)
+            .unwrap();
+        let preview = api.preview_request_in(DEFAULT_SESSION).await.unwrap();
+        assert_eq!(tool_names(preview), ["researcher", handoff::TOOL_NAME]);
+
+        // A specialist gets only what the chat switched on
+        api.switch_agent_in(DEFAULT_SESSION, "researcher").unwrap();
+        let preview = api.preview_request_in(DEFAULT_SESSION).await.unwrap();
+        assert!(preview.tools.is_empty());
+        let overrides = HashMap::from([("mcp:filesystem".to_string(), true)]);
+        api.set_tool_overrides_in(DEFAULT_SESSION, overrides)
+            .unwrap();
+        let preview = api.preview_request_in(DEFAULT_SESSION).await.unwrap();
+        assert_eq!(tool_names(preview), ["mcp:filesystem:read_file"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_mcp_tool_duplicate_rejection() {
+        let event_bus = Arc::new(EventBus::new());
+        let runtime = get_test_runtime();
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), Arc::clone(&runtime), 20);
+
+        let tool = McpToolDefinition {
+            name: "read_file".to_string(),
+            description: Some("Read a file".to_string()),
+            input_schema: serde_json::json!({"type": "object"}),
+        };
+
+        // First registration should succeed
+        api.register_mcp_tool(tool.clone(), "filesystem".to_string())
+            .await
+            .unwrap();
+
+        // Second registration of same tool should fail
+        let result = api.register_mcp_tool(tool, "filesystem".to_string()).await;
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("already registered"));
+    }
+
+    #[tokio::test]
+    async fn test_consent_for() {
+        let event_bus = Arc::new(EventBus::new());
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), get_test_runtime(), 20);
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "mcp:filesystem:read_file".to_string(),
+            arguments: serde_json::json!({ "path": "/tmp/notes.txt" }),
+        };
+        // No prompts set up: nothing to wait for
+        assert!(api.consent_for(&call).await.is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        api.set_fs_consent(Arc::new(FsConsent::load(
+            dir.path().join("fs_grants.json"),
+            event_bus,
+        )));
+        let (_, plugin, tool) = api.consent_for(&call).await.unwrap();
+        assert_eq!(
+            (plugin.as_str(), tool.as_str()),
+            ("filesystem", "read_file")
+        );
+
+        let web_search = ToolCall {
+            name: "web_search".to_string(),
+            ..call
+        };
+        assert!(api.consent_for(&web_search).await.is_none());
+    }
+
+    #[test]
+    fn test_is_mcp_tool() {
+        assert!(RustbotApi::is_mcp_tool("mcp:filesystem:read_file"));
+        assert!(RustbotApi::is_mcp_tool("mcp:web:fetch"));
+        assert!(!RustbotApi::is_mcp_tool("web_search"));
+        assert!(!RustbotApi::is_mcp_tool("agent_tool"));
+    }
+
+    #[test]
+    fn test_parse_mcp_tool_name() {
+        // Valid format
+        let (plugin, tool) = RustbotApi::parse_mcp_tool_name("mcp:filesystem:read_file").unwrap();
+        assert_eq!(plugin, "filesystem");
+        assert_eq!(tool, "read_file");
+
+        // Invalid formats
+        assert!(RustbotApi::parse_mcp_tool_name("filesystem:read_file").is_err());
+        assert!(RustbotApi::parse_mcp_tool_name("mcp:filesystem").is_err());
+        assert!(RustbotApi::parse_mcp_tool_name("read_file").is_err());
+        assert!(RustbotApi::parse_mcp_tool_name("mcp:filesystem:read:file").is_err());
+        // Too many parts
+    }
+
+    #[test]
+    fn test_convert_mcp_tool_to_rustbot() {
+        let mcp_tool = McpToolDefinition {
+            name: "read_file".to_string(),
+            description: Some("Read contents of a file".to_string()),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "File path"
+                    }
+                },
+                "required": ["path"]
+            }),
+        };
+
+        let rustbot_tool = RustbotApi::convert_mcp_tool_to_rustbot(
+            &mcp_tool,
+            "filesystem",
+            "mcp:filesystem:read_file",
+            None,
+        );
+
+        assert_eq!(rustbot_tool.tool_type, "function");
+        assert_eq!(rustbot_tool.function.name, "mcp:filesystem:read_file");
+        assert_eq!(rustbot_tool.function.description, "Read contents of a file");
+        let parameters = &rustbot_tool.function.parameters;
+        assert_eq!(parameters.properties["path"]["type"], "string");
+        assert_eq!(parameters.required, ["path"]);
+
+        let tool_override = ToolOverride {
+            name: None,
+            description: Some("Read a UTF-8 text file".to_string()),
+        };
+        let rustbot_tool = RustbotApi::convert_mcp_tool_to_rustbot(
+            &mcp_tool,
+            "filesystem",
+            "mcp:filesystem:read_file",
+            Some(&tool_override),
+        );
+        assert_eq!(rustbot_tool.function.description, "Read a UTF-8 text file");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_mcp_tool_override_rename() {
+        use std::io::Write;
+
+        let mut config_file = tempfile::NamedTempFile::new().unwrap();
+        config_file
+            .write_all(
+                br#"{"mcp_plugins": {"local_servers": [{
+                    "id": "filesystem", "name": "Filesystem", "command": "npx",
+                    "tool_overrides": {"read_file": {"name": "read_text"}}
+                }]}}"#,
+            )
+            .unwrap();
+        let mut manager = McpPluginManager::new();
+        manager.load_config(config_file.path()).await.unwrap();
+
+        let event_bus = Arc::new(EventBus::new());
+        let runtime = get_test_runtime();
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), Arc::clone(&runtime), 20);
+        api.set_mcp_manager(Arc::new(Mutex::new(manager)));
+        let tool = McpToolDefinition {
+            name: "read_file".to_string(),
+            description: Some("Read a file".to_string()),
+            input_schema: serde_json::json!({"type": "object"}),
+        };
+        api.register_mcp_tool(tool, "filesystem".to_string())
+            .await
+            .unwrap();
+
+        let tools = api.get_all_tools();
+        assert_eq!(tools[0].function.name, "mcp:filesystem:read_text");
+        assert_eq!(tools[0].function.description, "Read a file");
+        // Calls go to the server's name
+        let registry = api.mcp_tools.read().await;
+        assert_eq!(
+            registry["mcp:filesystem:read_text"].definition.name,
+            "read_file"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_optional_integrations_not_set_up() {
+        // Holds with or without the optional features (`make check-core`
+        // runs it without any): integrations that aren't built in or not
+        // configured offer no tools, and their tool names don't route
+        let event_bus = Arc::new(EventBus::new());
+        let api = RustbotApi::new(event_bus, get_test_runtime(), 20);
+        assert!(api.get_all_tools().is_empty());
+        assert_eq!(api.metrics().tools, 0);
+        for tool in [
+            "script:notes:append",
+            "wasm:hash",
+            "read_inbox",
+            "calendar_list_events",
+        ] {
+            assert!(api.execute_tool(tool, "{}").await.is_err(), "{}", tool);
+        }
+    }
+
+    #[test]
+    fn test_convert_mcp_tool_without_description() {
+        let mcp_tool = McpToolDefinition {
+            name: "read_file".to_string(),
+            description: None, // No description provided
+            input_schema: serde_json::json!({"type": "object"}),
+        };
+
+        let rustbot_tool = RustbotApi::convert_mcp_tool_to_rustbot(
+            &mcp_tool,
+            "filesystem",
+            "mcp:filesystem:read_file",
+            None,
+        );
+
+        // Should generate a default description
+        assert!(rustbot_tool.function.description.contains("filesystem"));
+        assert!(rustbot_tool.function.description.contains("read_file"));
+    }
+
+    #[test]
+    fn test_agent_registration() {
+        let event_bus = Arc::new(EventBus::new());
+        let runtime = Arc::new(Runtime::new().unwrap());
+        let adapter: Arc<dyn LlmAdapter> = Arc::new(OpenRouterAdapter::new("test-key".to_string()));
+
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), Arc::clone(&runtime), 20);
+
+        let agent = Agent::new(
+            AgentConfig::default_assistant(),
+            adapter,
+            event_bus,
+            runtime.handle().clone(),
+            String::new(),
+        );
+
+        api.register_agent(agent);
+
+        assert_eq!(api.list_agents().len(), 1);
+        assert_eq!(api.list_agents()[0], "assistant");
+    }
+
+    #[test]
+    fn test_agent_switching() {
+        let event_bus = Arc::new(EventBus::new());
+        let runtime = Arc::new(Runtime::new().unwrap());
+        let adapter: Arc<dyn LlmAdapter> = Arc::new(OpenRouterAdapter::new("test-key".to_string()));
+
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), Arc::clone(&runtime), 20);
+
+        // Register two agents
+        let agent1 = Agent::new(
+            AgentConfig::default_assistant(),
+            Arc::clone(&adapter),
+            Arc::clone(&event_bus),
+            runtime.handle().clone(),
+            String::new(),
+        );
+        api.register_agent(agent1);
+
+        let mut config2 = AgentConfig::default_assistant();
+        config2.id = "researcher".to_string();
+        config2.name = "Researcher".to_string();
+
+        let agent2 = Agent::new(
+            config2,
+            adapter,
+            Arc::clone(&event_bus),
+            runtime.handle().clone(),
+            String::new(),
+        );
+        api.register_agent(agent2);
+
+        // Switch agents
+        assert_eq!(api.active_agent(), "assistant");
+        api.switch_agent("researcher").unwrap();
+        assert_eq!(api.active_agent(), "researcher");
+
+        // Try invalid agent
+        assert!(api.switch_agent("invalid").is_err());
+    }
+}
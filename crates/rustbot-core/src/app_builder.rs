@@ -37,8 +37,8 @@ use crate::error::{Result, RustbotError};
 use crate::events::EventBus;
 use crate::llm::{AdapterType, LlmAdapter};
 use crate::services::{
-    AgentService, ConfigService, DefaultAgentService, FileConfigService, FileStorageService,
-    FileSystem, RealFileSystem, StorageService,
+    AgentService, ConfigService, DefaultAgentService, EncryptionKey, FileConfigService,
+    FileStorageService, FileSystem, RealFileSystem, StorageService,
 };
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -51,11 +51,11 @@ use std::sync::Arc;
 /// # Examples
 ///
 /// ```no_run
-/// use rustbot::AppBuilder;
+/// use rustbot_core::AppBuilder;
 /// use std::path::PathBuf;
 ///
 /// #[tokio::main]
-/// async fn main() -> rustbot::Result<()> {
+/// async fn main() -> rustbot_core::Result<()> {
 ///     let deps = AppBuilder::new()
 ///         .with_api_key("sk-...".to_string())
 ///         .with_base_path(PathBuf::from("."))
@@ -85,6 +85,9 @@ pub struct AppBuilder {
     // Configuration paths
     base_path: PathBuf,
     system_instructions: String,
+
+    // Encryption at rest (None = plaintext storage)
+    storage_passphrase: Option<String>,
 }
 
 impl AppBuilder {
@@ -101,6 +104,7 @@ impl AppBuilder {
             llm_adapter: None,
             base_path: PathBuf::from("."),
             system_instructions: String::new(),
+            storage_passphrase: None,
         }
     }
 
@@ -122,6 +126,12 @@ impl AppBuilder {
         self
     }
 
+    /// Encrypt stored conversations and profile with a passphrase-derived key
+    pub fn with_storage_passphrase(mut self, passphrase: String) -> Self {
+        self.storage_passphrase = Some(passphrase);
+        self
+    }
+
     /// Use production dependencies (default)
     ///
     /// Creates real implementations of all services:
@@ -148,11 +158,22 @@ impl AppBuilder {
         // Create real filesystem
         let filesystem = Arc::new(RealFileSystem) as Arc<dyn FileSystem>;
 
-        // Create storage service
-        let storage = Arc::new(FileStorageService::new(
-            filesystem.clone(),
-            self.base_path.clone(),
-        )) as Arc<dyn StorageService>;
+        // Create storage service (encrypted when a passphrase is configured)
+        let storage = match &self.storage_passphrase {
+            Some(passphrase) => {
+                let key = EncryptionKey::for_storage_dir(&filesystem, &self.base_path, passphrase)
+                    .await?;
+                Arc::new(FileStorageService::encrypted(
+                    filesystem.clone(),
+                    self.base_path.clone(),
+                    key,
+                )) as Arc<dyn StorageService>
+            }
+            None => Arc::new(FileStorageService::new(
+                filesystem.clone(),
+                self.base_path.clone(),
+            )) as Arc<dyn StorageService>,
+        };
 
         // Create config service (loads from environment and files)
         let config = Arc::new(FileConfigService::load()?) as Arc<dyn ConfigService>;
@@ -162,7 +183,7 @@ impl AppBuilder {
             tokio::runtime::Runtime::new()
                 .map_err(|e| RustbotError::ApiError(format!("Failed to create runtime: {}", e)))?,
         );
-        let event_bus = Arc::new(EventBus::new());
+        let event_bus = Arc::new(EventBus::from_env());
 
         // Create LLM adapter
         let llm_adapter = Arc::from(crate::llm::create_adapter(
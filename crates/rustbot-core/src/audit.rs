@@ -0,0 +1,358 @@
+// Audit trail for security-relevant actions
+//
+// Design Decision: Hash-chained JSONL file written through one process-wide log
+//
+// Rationale: The event log records what agents and tools did, but not the
+// changes that decide what they are allowed to do: installing or removing an
+// extension, editing configuration, storing a key, granting a plugin access to
+// a folder. Those are recorded here instead, where each happens (the
+// marketplace, the settings screens, `DefaultSecretStore`, `FsConsent`),
+// through `record` on a log installed once at startup with `init`. Each entry
+// carries the SHA-256 of its content and of the entry before it, so editing,
+// removing or reordering an entry in the middle of the file breaks the chain
+// and `verify` reports where.
+//
+// Trade-offs:
+// - Tamper-evident, not tamper-proof: whoever can write the file can rewrite
+//   the whole chain, and dropping entries from the end leaves a valid chain
+//   (the entry count and head hash shown in the app make that noticeable)
+// - Details name what changed (extension, setting, secret name), never the
+//   values; they are scrubbed with `redact` as a second line of defence
+// - The file is never rotated; the expected volume is a few lines a day
+// - Without `init` (tests, embedding) `record` does nothing
+
+use crate::error::{Result, RustbotError};
+use crate::redact::redact;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// Hash the first entry links to
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+static GLOBAL: OnceLock<AuditLog> = OnceLock::new();
+
+/// Kind of security-relevant action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    ExtensionInstalled,
+    ExtensionUninstalled,
+    ConfigChanged,
+    SecretUpdated,
+    SecretDeleted,
+    AccessGranted,
+    AccessRevoked,
+}
+
+impl AuditAction {
+    /// Short label for the audit view
+    pub fn label(self) -> &'static str {
+        match self {
+            AuditAction::ExtensionInstalled => "Extension installed",
+            AuditAction::ExtensionUninstalled => "Extension uninstalled",
+            AuditAction::ConfigChanged => "Configuration changed",
+            AuditAction::SecretUpdated => "Secret updated",
+            AuditAction::SecretDeleted => "Secret deleted",
+            AuditAction::AccessGranted => "Access granted",
+            AuditAction::AccessRevoked => "Access revoked",
+        }
+    }
+}
+
+/// One line of the audit log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the chain, starting at 1
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub action: AuditAction,
+    pub detail: String,
+    /// Hash of the previous entry (`GENESIS_HASH` for the first)
+    pub prev_hash: String,
+    /// SHA-256 over this entry's fields and `prev_hash`, hex encoded
+    pub hash: String,
+}
+
+impl AuditEntry {
+    fn compute_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.prev_hash.as_bytes());
+        hasher.update(self.seq.to_string().as_bytes());
+        hasher.update(self.timestamp.to_rfc3339().as_bytes());
+        hasher.update(serde_json::to_string(&self.action).unwrap_or_default());
+        hasher.update(self.detail.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// Result of checking the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainStatus {
+    /// Every entry links to the one before it
+    Intact { entries: usize },
+
+    /// The chain breaks at this line (1-based)
+    Broken { line: usize, reason: String },
+}
+
+/// End of the chain, where the next entry is linked
+struct Head {
+    seq: u64,
+    hash: String,
+}
+
+/// Append-only, hash-chained audit log stored as JSONL
+///
+/// Usage:
+///     audit::init(AuditLog::open(AuditLog::default_path())?);
+///     audit::record(AuditAction::SecretUpdated, "OPENROUTER_API_KEY");
+pub struct AuditLog {
+    path: PathBuf,
+    head: Mutex<Head>,
+}
+
+impl AuditLog {
+    /// Default log location: ~/.rustbot/audit.jsonl
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("audit.jsonl")
+    }
+
+    /// Open a log, continuing the chain from its last entry
+    ///
+    /// # Errors
+    /// - The file exists but can't be read, or its last entry is malformed
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let mut head = Head {
+            seq: 0,
+            hash: GENESIS_HASH.to_string(),
+        };
+        if let Some(last) = read_lines(&path)?.last() {
+            let entry: AuditEntry = serde_json::from_str(last)?;
+            head = Head {
+                seq: entry.seq,
+                hash: entry.hash,
+            };
+        }
+        Ok(Self {
+            path,
+            head: Mutex::new(head),
+        })
+    }
+
+    /// Path of the log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry linked to the current end of the chain
+    ///
+    /// # Errors
+    /// - The file can't be written
+    pub fn append(&self, action: AuditAction, detail: &str) -> Result<AuditEntry> {
+        let mut head = self
+            .head
+            .lock()
+            .map_err(|_| RustbotError::StorageError("Audit log is poisoned".to_string()))?;
+
+        let mut entry = AuditEntry {
+            seq: head.seq + 1,
+            timestamp: Utc::now(),
+            action,
+            detail: redact(detail).into_owned(),
+            prev_hash: head.hash.clone(),
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+
+        head.seq = entry.seq;
+        head.hash = entry.hash.clone();
+        Ok(entry)
+    }
+
+    /// All entries, oldest first
+    ///
+    /// Malformed lines are skipped with a warning; `verify` reports them.
+    pub fn entries(&self) -> Result<Vec<AuditEntry>> {
+        Ok(read_lines(&self.path)?
+            .iter()
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    tracing::warn!("Skipping malformed audit log line: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Check that every entry is unchanged and linked to the one before it
+    ///
+    /// # Errors
+    /// - The file can't be read
+    pub fn verify(&self) -> Result<ChainStatus> {
+        let lines = read_lines(&self.path)?;
+        let mut prev_hash = GENESIS_HASH.to_string();
+        for (index, line) in lines.iter().enumerate() {
+            let broken = |reason: String| ChainStatus::Broken {
+                line: index + 1,
+                reason,
+            };
+            let entry: AuditEntry = match serde_json::from_str(line) {
+                Ok(entry) => entry,
+                Err(e) => return Ok(broken(format!("Unreadable entry: {}", e))),
+            };
+            if entry.seq != index as u64 + 1 {
+                return Ok(broken(format!(
+                    "Expected entry {}, found {}",
+                    index + 1,
+                    entry.seq
+                )));
+            }
+            if entry.prev_hash != prev_hash {
+                return Ok(broken("Not linked to the previous entry".to_string()));
+            }
+            if entry.hash != entry.compute_hash() {
+                return Ok(broken("Entry was modified".to_string()));
+            }
+            prev_hash = entry.hash;
+        }
+        Ok(ChainStatus::Intact {
+            entries: lines.len(),
+        })
+    }
+}
+
+/// Non-empty lines of the file (none if it doesn't exist)
+fn read_lines(path: &Path) -> Result<Vec<String>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut lines = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            lines.push(line);
+        }
+    }
+    Ok(lines)
+}
+
+/// Install the process-wide log `record` writes to
+///
+/// Only the first call takes effect.
+pub fn init(log: AuditLog) {
+    if GLOBAL.set(log).is_err() {
+        tracing::warn!("Audit log already initialized");
+    }
+}
+
+/// The process-wide log, if one was installed
+pub fn global() -> Option<&'static AuditLog> {
+    GLOBAL.get()
+}
+
+/// Record an action in the process-wide log
+///
+/// Failures are logged, not returned: an action that already happened
+/// shouldn't be reported as failed because the audit write didn't work.
+///
+/// # Arguments
+/// * `action` - What kind of change this was
+/// * `detail` - What changed (names, never secret values)
+pub fn record(action: AuditAction, detail: impl AsRef<str>) {
+    let Some(log) = GLOBAL.get() else {
+        return;
+    };
+    if let Err(e) = log.append(action, detail.as_ref()) {
+        tracing::warn!("Failed to write audit entry: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_continues_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+
+        let log = AuditLog::open(path.clone()).unwrap();
+        let first = log
+            .append(AuditAction::ExtensionInstalled, "filesystem")
+            .unwrap();
+        assert_eq!(first.prev_hash, GENESIS_HASH);
+
+        let log = AuditLog::open(path).unwrap();
+        let second = log
+            .append(AuditAction::SecretUpdated, "OPENROUTER_API_KEY")
+            .unwrap();
+        assert_eq!(second.seq, 2);
+        assert_eq!(second.prev_hash, first.hash);
+        assert_eq!(log.entries().unwrap(), vec![first, second]);
+        assert_eq!(log.verify().unwrap(), ChainStatus::Intact { entries: 2 });
+    }
+
+    #[test]
+    fn test_verify_detects_edits_and_removals() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let log = AuditLog::open(path.clone()).unwrap();
+        for detail in [
+            "Local-only mode on",
+            "System instructions",
+            "Local-only mode off",
+        ] {
+            log.append(AuditAction::ConfigChanged, detail).unwrap();
+        }
+        let original = std::fs::read_to_string(&path).unwrap();
+
+        std::fs::write(&path, original.replace("mode on", "mode 0n")).unwrap();
+        assert!(matches!(
+            log.verify().unwrap(),
+            ChainStatus::Broken { line: 1, .. }
+        ));
+
+        let without_second: Vec<&str> = original
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| *i != 1)
+            .map(|(_, line)| line)
+            .collect();
+        std::fs::write(&path, without_second.join("\n")).unwrap();
+        assert!(matches!(
+            log.verify().unwrap(),
+            ChainStatus::Broken { line: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn test_details_are_redacted() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = AuditLog::open(dir.path().join("audit.jsonl")).unwrap();
+        let entry = log
+            .append(
+                AuditAction::ConfigChanged,
+                "key sk-or-v1-0123456789abcdef0123",
+            )
+            .unwrap();
+        assert_eq!(entry.detail, "key [REDACTED]");
+    }
+}
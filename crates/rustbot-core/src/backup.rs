@@ -0,0 +1,552 @@
+// Scheduled backups of configuration and conversations
+//
+// Design Decision: Timestamped snapshot directories with a manifest
+//
+// Rationale: Saving system instructions already keeps a one-off
+// `backup_<timestamp>` copy, but agents, MCP configs, the profile and every
+// conversation had no safety net against a bad edit, a broken migration or a
+// corrupted file. A snapshot is a plain directory copy of each data source,
+// so backups can be inspected or restored by hand without Rustbot, and
+// encrypted data (see `services::encryption`) stays encrypted in the backup.
+//
+// Layout:
+//     ~/.rustbot/backups/<YYYYMMDD-HHMMSS>/
+//         manifest.json          BackupInfo (when, why, which items)
+//         files/<source name>    copy of each source file or directory
+//
+// Scheduling: A background task takes a snapshot when the newest one is
+// older than the configured interval (checked at startup and then on each
+// tick), and prunes everything beyond the newest `keep` snapshots.
+//
+// Restore: Takes a safety snapshot of the current state first, then replaces
+// each source listed in the manifest with its copy.
+//
+// Trade-offs:
+// - Full copies rather than incremental; conversation history is small JSON
+// - Running sessions keep in-memory state; a restart picks up restored files
+
+use crate::error::{Result, RustbotError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Manifest file name inside each snapshot
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Directory holding the copied sources inside each snapshot
+const FILES_DIR: &str = "files";
+
+/// How often the scheduler checks whether a snapshot is due
+const SCHEDULER_TICK: Duration = Duration::from_secs(15 * 60);
+
+/// Backup schedule and retention
+///
+/// File Format: JSON
+/// Location: ~/.rustbot/backup.json (optional; defaults apply when missing)
+///
+/// Example:
+///     { "enabled": true, "interval_hours": 12, "keep": 14 }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupConfig {
+    /// Whether scheduled backups run
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Hours between scheduled snapshots
+    #[serde(default = "default_interval_hours")]
+    pub interval_hours: u64,
+
+    /// Number of snapshots to keep (older ones are deleted)
+    #[serde(default = "default_keep")]
+    pub keep: usize,
+}
+
+fn default_true() -> bool {
+    true
+}
+fn default_interval_hours() -> u64 {
+    24
+}
+fn default_keep() -> usize {
+    7
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_hours: default_interval_hours(),
+            keep: default_keep(),
+        }
+    }
+}
+
+impl BackupConfig {
+    /// Default configuration file location (~/.rustbot/backup.json)
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("backup.json")
+    }
+
+    /// Load configuration from a file
+    ///
+    /// # Returns
+    /// Ok(None) if the file doesn't exist (use defaults), Ok(Some) if loaded
+    ///
+    /// # Errors
+    /// - File exists but cannot be read
+    /// - Invalid JSON
+    /// - Zero interval
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        let config: Self = serde_json::from_str(&content)?;
+        if config.interval_hours == 0 {
+            return Err(RustbotError::ConfigError(
+                "Backup interval_hours must be at least 1".to_string(),
+            ));
+        }
+        Ok(Some(config))
+    }
+
+    /// Interval between scheduled snapshots
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_hours * 60 * 60)
+    }
+}
+
+/// A file or directory included in snapshots
+#[derive(Debug, Clone)]
+pub struct BackupSource {
+    /// Unique name, used as the file name inside the snapshot
+    pub name: String,
+
+    /// Location on disk
+    pub path: PathBuf,
+}
+
+impl BackupSource {
+    pub fn new(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            path: path.into(),
+        }
+    }
+}
+
+/// Everything the app persists: configuration plus conversations
+///
+/// # Arguments
+/// * `data_dir` - Storage service base path (profile, sessions, stats)
+pub fn default_sources(data_dir: &Path) -> Vec<BackupSource> {
+    let mut sources = vec![
+        BackupSource::new("agents", "agents"),
+        BackupSource::new("mcp_config.json", "mcp_config.json"),
+    ];
+
+    if let Some(home) = dirs::home_dir() {
+        let rustbot = home.join(".rustbot");
+        sources.extend([
+            BackupSource::new("instructions", rustbot.join("instructions")),
+            BackupSource::new("mcp_configs", rustbot.join("mcp_configs")),
+            BackupSource::new("webhooks.json", rustbot.join("webhooks.json")),
+            BackupSource::new("hooks.json", rustbot.join("hooks.json")),
+        ]);
+    }
+
+    for name in [
+        "profile.json",
+        "system_prompts.json",
+        "token_stats.json",
+        "session_index.json",
+        "encryption.json",
+        "sessions",
+    ] {
+        sources.push(BackupSource::new(name, data_dir.join(name)));
+    }
+
+    sources
+}
+
+/// Metadata for one snapshot (stored as its manifest.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    /// Snapshot directory name
+    pub id: String,
+
+    /// When the snapshot was taken
+    pub created_at: DateTime<Utc>,
+
+    /// Why it was taken ("scheduled", "manual", "before restore of …")
+    pub reason: String,
+
+    /// Sources included (missing sources are skipped)
+    pub items: Vec<String>,
+
+    /// Total size of copied files in bytes
+    pub size_bytes: u64,
+}
+
+/// Creates, lists, prunes and restores snapshots
+pub struct BackupManager {
+    root: PathBuf,
+    keep: usize,
+    sources: Vec<BackupSource>,
+}
+
+impl BackupManager {
+    /// Create a manager
+    ///
+    /// # Arguments
+    /// * `root` - Directory holding the snapshots
+    /// * `keep` - Number of snapshots to retain (at least 1)
+    /// * `sources` - What to include in each snapshot
+    pub fn new(root: PathBuf, keep: usize, sources: Vec<BackupSource>) -> Self {
+        Self {
+            root,
+            keep: keep.max(1),
+            sources,
+        }
+    }
+
+    /// Default snapshot directory (~/.rustbot/backups)
+    pub fn default_root() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("backups")
+    }
+
+    /// Take a snapshot now and prune old ones
+    ///
+    /// # Errors
+    /// - Snapshot directory cannot be created or a source cannot be copied
+    pub fn create(&self, reason: &str) -> Result<BackupInfo> {
+        let info = self.snapshot(reason)?;
+        if let Err(e) = self.prune() {
+            tracing::warn!("Failed to prune old backups: {}", e);
+        }
+        Ok(info)
+    }
+
+    /// All snapshots, newest first (unreadable ones are skipped)
+    ///
+    /// # Errors
+    /// - Backup directory exists but cannot be listed
+    pub fn list(&self) -> Result<Vec<BackupInfo>> {
+        if !self.root.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut backups: Vec<BackupInfo> = std::fs::read_dir(&self.root)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let manifest = std::fs::read_to_string(entry.path().join(MANIFEST_FILE)).ok()?;
+                serde_json::from_str(&manifest).ok()
+            })
+            .collect();
+        backups.sort_by_key(|backup| std::cmp::Reverse(backup.created_at));
+        Ok(backups)
+    }
+
+    /// Delete snapshots beyond the retention limit
+    ///
+    /// # Returns
+    /// Number of snapshots deleted
+    ///
+    /// # Errors
+    /// - Listing or deleting fails
+    pub fn prune(&self) -> Result<usize> {
+        let expired: Vec<BackupInfo> = self.list()?.into_iter().skip(self.keep).collect();
+        for backup in &expired {
+            std::fs::remove_dir_all(self.root.join(&backup.id))?;
+            tracing::debug!("Pruned backup {}", backup.id);
+        }
+        Ok(expired.len())
+    }
+
+    /// Replace current data with a snapshot
+    ///
+    /// The current state is snapshotted first, so a restore can be undone.
+    ///
+    /// # Errors
+    /// - Unknown or invalid snapshot ID
+    /// - Safety snapshot or copy failure
+    pub fn restore(&self, id: &str) -> Result<BackupInfo> {
+        validate_id(id)?;
+        let dir = self.root.join(id);
+        let manifest = std::fs::read_to_string(dir.join(MANIFEST_FILE))
+            .map_err(|_| RustbotError::StorageError(format!("Backup '{}' not found", id)))?;
+        let info: BackupInfo = serde_json::from_str(&manifest)?;
+
+        // Not pruned until after the restore, so the chosen snapshot survives
+        let safety = self.snapshot(&format!("before restore of {}", id))?;
+        tracing::info!("💾 Saved current state as backup {}", safety.id);
+
+        for item in &info.items {
+            let Some(source) = self.sources.iter().find(|s| &s.name == item) else {
+                tracing::warn!("Skipping unknown backup item '{}'", item);
+                continue;
+            };
+            remove_path(&source.path)?;
+            copy_recursive(&dir.join(FILES_DIR).join(item), &source.path)?;
+        }
+
+        if let Err(e) = self.prune() {
+            tracing::warn!("Failed to prune old backups: {}", e);
+        }
+        tracing::info!("♻️  Restored backup {} ({} items)", id, info.items.len());
+        Ok(info)
+    }
+
+    /// Take snapshots on a schedule in the background
+    ///
+    /// A snapshot is taken right away if the newest one is older than
+    /// `interval` (or none exists), then whenever it becomes due again.
+    pub fn spawn_scheduler(
+        self: Arc<Self>,
+        interval: Duration,
+        handle: &tokio::runtime::Handle,
+    ) -> JoinHandle<()> {
+        let inner = handle.clone();
+        handle.spawn(async move {
+            let mut ticker = tokio::time::interval(SCHEDULER_TICK.min(interval));
+            loop {
+                ticker.tick().await;
+
+                let manager = Arc::clone(&self);
+                let result = inner
+                    .spawn_blocking(move || {
+                        if manager.is_due(interval)? {
+                            manager.create("scheduled").map(Some)
+                        } else {
+                            Ok(None)
+                        }
+                    })
+                    .await;
+
+                match result {
+                    Ok(Ok(Some(info))) => tracing::info!(
+                        "💾 Scheduled backup {} ({} bytes)",
+                        info.id,
+                        info.size_bytes
+                    ),
+                    Ok(Ok(None)) => {}
+                    Ok(Err(e)) => tracing::warn!("Scheduled backup failed: {}", e),
+                    Err(e) => tracing::warn!("Scheduled backup task panicked: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Whether the newest snapshot is older than `interval`
+    fn is_due(&self, interval: Duration) -> Result<bool> {
+        let Some(latest) = self.list()?.into_iter().next() else {
+            return Ok(true);
+        };
+        let age = Utc::now().signed_duration_since(latest.created_at);
+        Ok(age.to_std().map(|age| age >= interval).unwrap_or(false))
+    }
+
+    /// Copy all sources into a new snapshot directory (without pruning)
+    fn snapshot(&self, reason: &str) -> Result<BackupInfo> {
+        std::fs::create_dir_all(&self.root)?;
+
+        let created_at = Utc::now();
+        let base_id = created_at
+            .with_timezone(&chrono::Local)
+            .format("%Y%m%d-%H%M%S")
+            .to_string();
+        let mut id = base_id.clone();
+        let mut suffix = 1;
+        while self.root.join(&id).exists() {
+            suffix += 1;
+            id = format!("{}-{}", base_id, suffix);
+        }
+
+        // Copy into a temporary directory so a failed snapshot never shows up in the list
+        let partial = self.root.join(format!(".{}.partial", id));
+        let result = self.copy_sources(&partial.join(FILES_DIR));
+        let (items, size_bytes) = match result {
+            Ok(copied) => copied,
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(&partial);
+                return Err(e);
+            }
+        };
+
+        let info = BackupInfo {
+            id: id.clone(),
+            created_at,
+            reason: reason.to_string(),
+            items,
+            size_bytes,
+        };
+        std::fs::write(
+            partial.join(MANIFEST_FILE),
+            serde_json::to_string_pretty(&info)?,
+        )?;
+        std::fs::rename(&partial, self.root.join(&id))?;
+
+        Ok(info)
+    }
+
+    fn copy_sources(&self, files_dir: &Path) -> Result<(Vec<String>, u64)> {
+        std::fs::create_dir_all(files_dir)?;
+
+        let mut items = Vec::new();
+        let mut size = 0;
+        for source in &self.sources {
+            if !source.path.exists() {
+                continue;
+            }
+            size += copy_recursive(&source.path, &files_dir.join(&source.name))?;
+            items.push(source.name.clone());
+        }
+        Ok((items, size))
+    }
+}
+
+/// Snapshot IDs are directory names we generated; reject anything else
+fn validate_id(id: &str) -> Result<()> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(RustbotError::StorageError(format!(
+            "Invalid backup ID '{}'",
+            id
+        )));
+    }
+    Ok(())
+}
+
+/// Copy a file or directory tree, returning the number of bytes copied
+fn copy_recursive(from: &Path, to: &Path) -> Result<u64> {
+    if from.is_dir() {
+        std::fs::create_dir_all(to)?;
+        let mut size = 0;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            size += copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(size)
+    } else {
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(std::fs::copy(from, to)?)
+    }
+}
+
+fn remove_path(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        std::fs::remove_dir_all(path)?;
+    } else if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn manager(temp: &TempDir, keep: usize) -> BackupManager {
+        let data = temp.path().join("data");
+        BackupManager::new(
+            temp.path().join("backups"),
+            keep,
+            vec![
+                BackupSource::new("profile.json", data.join("profile.json")),
+                BackupSource::new("sessions", data.join("sessions")),
+                BackupSource::new("missing.json", data.join("missing.json")),
+            ],
+        )
+    }
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_create_copies_existing_sources() {
+        let temp = TempDir::new().unwrap();
+        write(&temp.path().join("data/profile.json"), "{}");
+        write(&temp.path().join("data/sessions/a.json"), "[1]");
+
+        let info = manager(&temp, 3).create("manual").unwrap();
+        assert_eq!(info.items, vec!["profile.json", "sessions"]);
+        assert_eq!(info.size_bytes, 5);
+
+        let copied = temp
+            .path()
+            .join("backups")
+            .join(&info.id)
+            .join("files/sessions/a.json");
+        assert_eq!(std::fs::read_to_string(copied).unwrap(), "[1]");
+    }
+
+    #[test]
+    fn test_prune_keeps_newest() {
+        let temp = TempDir::new().unwrap();
+        write(&temp.path().join("data/profile.json"), "{}");
+
+        let manager = manager(&temp, 2);
+        let ids: Vec<String> = (0..3)
+            .map(|_| manager.create("manual").unwrap().id)
+            .collect();
+
+        let remaining: Vec<String> = manager.list().unwrap().into_iter().map(|b| b.id).collect();
+        assert_eq!(remaining, vec![ids[2].clone(), ids[1].clone()]);
+    }
+
+    #[test]
+    fn test_restore_replaces_data_and_keeps_safety_copy() {
+        let temp = TempDir::new().unwrap();
+        write(&temp.path().join("data/profile.json"), "old");
+        write(&temp.path().join("data/sessions/a.json"), "a");
+
+        let manager = manager(&temp, 5);
+        let original = manager.create("manual").unwrap();
+
+        write(&temp.path().join("data/profile.json"), "new");
+        write(&temp.path().join("data/sessions/b.json"), "b");
+
+        manager.restore(&original.id).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("data/profile.json")).unwrap(),
+            "old"
+        );
+        assert!(!temp.path().join("data/sessions/b.json").exists());
+
+        let backups = manager.list().unwrap();
+        assert_eq!(backups.len(), 2);
+        assert!(backups[0].reason.starts_with("before restore"));
+    }
+
+    #[test]
+    fn test_restore_rejects_invalid_ids() {
+        let temp = TempDir::new().unwrap();
+        let manager = manager(&temp, 1);
+        assert!(manager.restore("../data").is_err());
+        assert!(manager.restore("20990101-000000").is_err());
+    }
+
+    #[test]
+    fn test_due_when_no_backups() {
+        let temp = TempDir::new().unwrap();
+        let manager = manager(&temp, 1);
+        assert!(manager.is_due(Duration::from_secs(3600)).unwrap());
+
+        manager.create("manual").unwrap();
+        assert!(!manager.is_due(Duration::from_secs(3600)).unwrap());
+    }
+}
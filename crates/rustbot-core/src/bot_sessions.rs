@@ -0,0 +1,350 @@
+// Per-conversation sessions for chat bot front ends (Discord, Telegram)
+//
+// Design Decision: One RustbotApi, one API session per chat channel
+//
+// Rationale: A bot talks to many channels at once. Each channel gets its own
+// RustbotApi session (history, agent, pinned context), opened on its first
+// message, so channels never see or overwrite each other's conversation and
+// the API's active session is left alone. Each channel also has a
+// ConversationSession (the same type the desktop app saves and the History
+// view lists), updated from the API session after every answer and saved.
+//
+// Session IDs: "{conversation key}-{timestamp}", e.g. "discord-123-456-20250101-…",
+// used for both the API session and the saved one. After a restart the most
+// recent session for a key is picked up again and its history restored.
+//
+// Trade-offs:
+// - Messages are processed one at a time across all channels (one API mutex)
+// - API sessions stay open (in memory) until the channel is reset
+// - Sessions are saved with import_sessions so they show up in History
+//   without replacing the chat the desktop app restores on startup
+
+use crate::api::RustbotApi;
+use crate::services::traits::{ConversationSession, SessionMessage};
+use crate::services::StorageService;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+
+/// Routes bot conversations through a shared RustbotApi
+pub struct BotSessions {
+    api: Arc<Mutex<RustbotApi>>,
+    storage: Arc<dyn StorageService>,
+
+    /// Agent for new conversations (None = the API's active agent)
+    default_agent: Option<String>,
+
+    /// Current session per conversation key
+    sessions: Mutex<HashMap<String, ConversationSession>>,
+}
+
+impl BotSessions {
+    /// # Arguments
+    /// * `api` - Shared API (also used for MCP tool registration)
+    /// * `storage` - Where sessions are saved
+    pub fn new(api: Arc<Mutex<RustbotApi>>, storage: Arc<dyn StorageService>) -> Self {
+        Self {
+            api,
+            storage,
+            default_agent: None,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Set the agent new conversations start with (builder style)
+    pub fn with_default_agent(mut self, agent: Option<String>) -> Self {
+        self.default_agent = agent;
+        self
+    }
+
+    /// IDs of all registered agents, sorted
+    pub async fn agents(&self) -> Vec<String> {
+        let mut agents = self.api.lock().await.list_agents();
+        agents.sort();
+        agents
+    }
+
+    /// Send a message in a conversation and return the full response
+    ///
+    /// The conversation's API session is opened on first use.
+    ///
+    /// # Arguments
+    /// * `key` - Conversation key (e.g. "discord-{guild}-{channel}")
+    /// * `agent` - Agent to answer (None keeps the session's agent)
+    /// * `message` - User message
+    /// * `chunks` - Optional channel that receives response chunks as they stream
+    ///
+    /// # Errors
+    /// - Unknown agent
+    /// - The request fails or the agent returns an empty response
+    pub async fn send(
+        &self,
+        key: &str,
+        agent: Option<&str>,
+        message: &str,
+        chunks: Option<mpsc::UnboundedSender<String>>,
+    ) -> Result<String> {
+        let mut api = self.api.lock().await;
+        let mut session = self.current_session(key, api.active_agent()).await;
+
+        if api.session(&session.id).is_none() {
+            api.create_session(&session.id)?;
+            api.restore_history_in(&session.id, session.history.clone())?;
+        }
+        match agent {
+            Some(agent) => api.switch_agent_in(&session.id, agent)?,
+            None => {
+                if let Err(e) = api.switch_agent_in(&session.id, &session.agent_id) {
+                    tracing::warn!("Session agent unavailable, using the default: {}", e);
+                }
+            }
+        }
+
+        let mut stream = api.send_message_in(&session.id, message).await?;
+        let mut response = String::new();
+        while let Some(chunk) = stream.recv().await {
+            response.push_str(&chunk);
+            if let Some(tx) = &chunks {
+                let _ = tx.send(chunk);
+            }
+        }
+        if response.is_empty() {
+            // Don't leave the unanswered message for the next request
+            api.discard_unanswered_in(&session.id)?;
+            anyhow::bail!("Agent returned an empty response");
+        }
+        api.add_assistant_response_in(&session.id, response.clone())?;
+
+        let now = chrono::Utc::now();
+        if let Some(api_session) = api.session(&session.id) {
+            session.agent_id = api_session.agent_id().to_string();
+            session.history = api_session.history();
+        }
+        session.updated_at = now;
+        for (role, content) in [("user", message), ("assistant", response.as_str())] {
+            session.messages.push(SessionMessage {
+                role: role.to_string(),
+                content: content.to_string(),
+                timestamp: now,
+                input_tokens: None,
+                output_tokens: None,
+                agent_id: None,
+                model: None,
+                first_token_ms: None,
+                generation_ms: None,
+                pinned: false,
+                feedback: None,
+            });
+        }
+        session.update_title();
+        drop(api);
+
+        if let Err(e) = self
+            .storage
+            .import_sessions(std::slice::from_ref(&session))
+            .await
+        {
+            tracing::warn!("Failed to save session '{}': {}", session.id, e);
+        }
+        self.sessions.lock().await.insert(key.to_string(), session);
+
+        Ok(response)
+    }
+
+    /// Change the agent that answers in a conversation
+    ///
+    /// # Errors
+    /// Unknown agent
+    pub async fn set_agent(&self, key: &str, agent: &str) -> Result<()> {
+        let active = {
+            let api = self.api.lock().await;
+            if !api.list_agents().iter().any(|id| id == agent) {
+                anyhow::bail!("Agent '{}' not found", agent);
+            }
+            api.active_agent().to_string()
+        };
+
+        let mut session = self.current_session(key, &active).await;
+        session.agent_id = agent.to_string();
+        self.sessions.lock().await.insert(key.to_string(), session);
+        Ok(())
+    }
+
+    /// Start a new conversation for a key (the old session stays in History)
+    ///
+    /// The old conversation's API session is closed.
+    pub async fn reset(&self, key: &str) {
+        let mut api = self.api.lock().await;
+        let agent_id = match &self.default_agent {
+            Some(agent) => agent.clone(),
+            None => api.active_agent().to_string(),
+        };
+        let mut sessions = self.sessions.lock().await;
+        let agent_id = match sessions.get(key) {
+            Some(old) => {
+                if api.session(&old.id).is_some() {
+                    if let Err(e) = api.close_session(&old.id) {
+                        tracing::warn!("Failed to close session '{}': {}", old.id, e);
+                    }
+                }
+                old.agent_id.clone()
+            }
+            None => agent_id,
+        };
+        sessions.insert(key.to_string(), new_session(key, agent_id));
+    }
+
+    /// The cached session for a key, the latest saved one, or a new one
+    async fn current_session(&self, key: &str, agent_id: &str) -> ConversationSession {
+        if let Some(session) = self.sessions.lock().await.get(key) {
+            return session.clone();
+        }
+
+        let prefix = format!("{}-", key);
+        let latest = match self.storage.list_sessions().await {
+            Ok(summaries) => summaries.into_iter().find(|s| s.id.starts_with(&prefix)),
+            Err(e) => {
+                tracing::warn!("Failed to list sessions: {}", e);
+                None
+            }
+        };
+        if let Some(summary) = latest {
+            match self.storage.load_session(&summary.id).await {
+                Ok(Some(session)) => return session,
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to load session '{}': {}", summary.id, e),
+            }
+        }
+
+        let agent_id = self.default_agent.as_deref().unwrap_or(agent_id);
+        new_session(key, agent_id.to_string())
+    }
+}
+
+fn new_session(key: &str, agent_id: String) -> ConversationSession {
+    let mut session = ConversationSession::new(agent_id);
+    session.id = format!("{}-{}", key, session.created_at.format("%Y%m%d-%H%M%S-%3f"));
+    session
+}
+
+/// Split a response into messages of at most `limit` characters
+///
+/// Cuts at the last line break before the limit when there is one, so
+/// paragraphs and code lines stay intact.
+pub fn split_message(text: &str, limit: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+
+    while rest.chars().count() > limit {
+        let byte_limit = rest
+            .char_indices()
+            .nth(limit)
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+        let cut = match rest[..byte_limit].rfind('\n') {
+            Some(i) if i > 0 => i,
+            _ => byte_limit,
+        };
+        parts.push(rest[..cut].to_string());
+        rest = rest[cut..].trim_start_matches('\n');
+    }
+    if !rest.is_empty() || parts.is_empty() {
+        parts.push(rest.to_string());
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentConfig};
+    use crate::events::EventBus;
+    use crate::llm::{LlmAdapter, LlmRequest, LlmResponse};
+    use crate::services::mocks::test_helpers::create_mock_storage;
+    use async_trait::async_trait;
+
+    /// Answers "echo: {last user message}"
+    struct EchoAdapter;
+
+    fn echo(request: &LlmRequest) -> String {
+        let last = request.messages.iter().rfind(|m| m.role == "user");
+        format!("echo: {}", last.map_or("", |m| m.content.as_str()))
+    }
+
+    #[async_trait]
+    impl LlmAdapter for EchoAdapter {
+        async fn stream_chat(
+            &self,
+            request: LlmRequest,
+            tx: mpsc::UnboundedSender<String>,
+        ) -> Result<()> {
+            let _ = tx.send(echo(&request));
+            Ok(())
+        }
+
+        async fn complete_chat(&self, request: LlmRequest) -> Result<LlmResponse> {
+            Ok(LlmResponse {
+                content: echo(&request),
+                tool_calls: None,
+                finish_reason: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "echo"
+        }
+    }
+
+    #[test]
+    fn test_channels_keep_separate_histories() {
+        let runtime = Arc::new(tokio::runtime::Runtime::new().unwrap());
+        let event_bus = Arc::new(EventBus::new());
+        let mut api = RustbotApi::new(Arc::clone(&event_bus), Arc::clone(&runtime), 20);
+        api.register_agent(Agent::new(
+            AgentConfig::default_assistant(),
+            Arc::new(EchoAdapter),
+            event_bus,
+            runtime.handle().clone(),
+            String::new(),
+        ));
+        let api = Arc::new(Mutex::new(api));
+        let sessions = BotSessions::new(Arc::clone(&api), Arc::new(create_mock_storage()));
+
+        runtime.block_on(async {
+            let a = sessions.send("chan-a", None, "one", None).await.unwrap();
+            let b = sessions.send("chan-b", None, "two", None).await.unwrap();
+            sessions.send("chan-a", None, "three", None).await.unwrap();
+            assert_eq!(a, "echo: one");
+            assert_eq!(b, "echo: two");
+
+            let api = api.lock().await;
+            let contents = |key: &str| {
+                let cached = sessions.sessions.try_lock().unwrap();
+                let history = api.session(&cached[key].id).unwrap().history();
+                history.into_iter().map(|m| m.content).collect::<Vec<_>>()
+            };
+            assert_eq!(
+                contents("chan-a"),
+                ["one", "echo: one", "three", "echo: three"]
+            );
+            assert_eq!(contents("chan-b"), ["two", "echo: two"]);
+
+            // The API's own session is untouched
+            assert!(api.get_history().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_split_message_prefers_line_breaks() {
+        assert_eq!(split_message("short", 10), vec!["short"]);
+        assert_eq!(split_message("", 10), vec![""]);
+
+        let parts = split_message("line one\nline two\nline three", 12);
+        assert_eq!(parts, vec!["line one", "line two", "line three"]);
+
+        let parts = split_message(&"é".repeat(25), 10);
+        assert_eq!(parts.len(), 3);
+        assert!(parts.iter().all(|p| p.chars().count() <= 10));
+    }
+}
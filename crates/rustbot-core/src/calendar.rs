@@ -0,0 +1,1353 @@
+// Calendar connector - list and create events through CalDAV or Google Calendar
+//
+// Design Decision: One tool pair over two small backends
+//
+// Rationale: The assistant only needs to answer "what's on my schedule" and to
+// put new events in the calendar. Both CalDAV (iCloud, Fastmail, Nextcloud,
+// Radicale, ...) and the Google Calendar REST API cover that with two
+// requests each, so a `CalendarBackend` trait with a list and a create method
+// is enough; the tools and their argument handling are shared.
+//
+// Configuration: ~/.rustbot/calendar.json holds the provider settings (edited
+// in Settings > Calendar). Credentials never go there - the CalDAV password,
+// the Google client secret and the Google refresh token are kept in the OS
+// keychain (see `services::secrets`).
+//
+// Google OAuth: `GoogleAuthFlow` runs the installed-app flow with PKCE and a
+// loopback redirect (http://127.0.0.1:<random port>), which needs no
+// registered redirect URL beyond the "Desktop app" client type.
+//
+// Tools (offered to the primary agent):
+//     calendar_list_events { start?, days? }                      default: today
+//     calendar_create_event { title, start, end?, location?, description? }
+//
+// Trade-offs:
+// - CalDAV responses are picked apart with a regex and a minimal iCalendar
+//   reader instead of full XML/iCalendar parsers; recurring events rely on
+//   the server expanding them (<c:expand>), which all common servers support
+// - Times with a TZID are shown as wall-clock time plus the zone name, no
+//   time zone database is bundled
+// - Created events carry only title, time, location and description
+
+use crate::agent::tools::{FunctionDefinition, FunctionParameters};
+use crate::agent::ToolDefinition;
+use crate::error::Result as RustbotResult;
+use crate::services::SecretStore;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+/// Keychain entry for the CalDAV password
+pub const CALDAV_PASSWORD_SECRET: &str = "RUSTBOT_CALDAV_PASSWORD";
+
+/// Keychain entry for the Google OAuth client secret
+pub const GOOGLE_CLIENT_SECRET: &str = "RUSTBOT_GOOGLE_CLIENT_SECRET";
+
+/// Keychain entry for the Google refresh token
+pub const GOOGLE_REFRESH_TOKEN_SECRET: &str = "RUSTBOT_GOOGLE_CALENDAR_TOKEN";
+
+/// Tool that lists events in a time range
+pub const LIST_EVENTS_TOOL: &str = "calendar_list_events";
+
+/// Tool that creates an event
+pub const CREATE_EVENT_TOOL: &str = "calendar_create_event";
+
+/// Longest range calendar_list_events accepts
+const MAX_LIST_DAYS: i64 = 31;
+
+/// Google endpoints
+const GOOGLE_AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_API_BASE: &str = "https://www.googleapis.com/calendar/v3";
+const GOOGLE_SCOPE: &str = "https://www.googleapis.com/auth/calendar.events";
+
+/// How long GoogleAuthFlow waits for the browser redirect
+const AUTH_TIMEOUT: Duration = Duration::from_secs(300);
+
+fn default_calendar_id() -> String {
+    "primary".to_string()
+}
+
+/// Calendar account configuration
+///
+/// File Format: JSON
+/// Location: ~/.rustbot/calendar.json
+///
+/// Examples:
+/// ```json
+/// { "provider": "caldav",
+///   "url": "https://caldav.fastmail.com/dav/calendars/user/me@example.com/Default/",
+///   "username": "me@example.com" }
+/// ```
+///
+/// ```json
+/// { "provider": "google", "client_id": "1234.apps.googleusercontent.com" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum CalendarConfig {
+    /// CalDAV calendar collection
+    CalDav {
+        /// URL of the calendar collection (not the principal)
+        url: String,
+        username: String,
+    },
+
+    /// Google Calendar via OAuth
+    Google {
+        /// OAuth client ID of a "Desktop app" client
+        client_id: String,
+
+        /// Calendar to use ("primary" = the account's main calendar)
+        #[serde(default = "default_calendar_id")]
+        calendar_id: String,
+    },
+}
+
+impl CalendarConfig {
+    /// Default config location: ~/.rustbot/calendar.json
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("calendar.json")
+    }
+
+    /// Load configuration from a file
+    ///
+    /// # Returns
+    /// Ok(None) if the file doesn't exist, Ok(Some) if loaded
+    ///
+    /// # Errors
+    /// - File exists but cannot be read
+    /// - Invalid JSON
+    pub fn load(path: &Path) -> RustbotResult<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// Write configuration to a file, creating parent directories
+    pub fn save(&self, path: &Path) -> RustbotResult<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Short description for logs and the settings view
+    pub fn label(&self) -> String {
+        match self {
+            Self::CalDav { username, .. } => format!("CalDAV ({})", username),
+            Self::Google { calendar_id, .. } => format!("Google Calendar ({})", calendar_id),
+        }
+    }
+}
+
+/// An event as returned by calendar_list_events
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CalendarEvent {
+    pub id: String,
+    pub title: String,
+
+    /// RFC 3339 in local time, a date for all-day events, or wall-clock time
+    /// followed by the zone name
+    pub start: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+    pub all_day: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Start or end of an event to create
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventTime {
+    /// All-day event date
+    Date(NaiveDate),
+    DateTime(DateTime<Utc>),
+}
+
+impl EventTime {
+    /// Parse an RFC 3339 timestamp, a local "YYYY-MM-DDTHH:MM[:SS]" time or
+    /// a "YYYY-MM-DD" date
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+            return Ok(Self::DateTime(time.with_timezone(&Utc)));
+        }
+        for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+            if let Ok(naive) = NaiveDateTime::parse_from_str(value, format) {
+                let local = Local
+                    .from_local_datetime(&naive)
+                    .earliest()
+                    .with_context(|| format!("'{}' does not exist in local time", value))?;
+                return Ok(Self::DateTime(local.with_timezone(&Utc)));
+            }
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            return Ok(Self::Date(date));
+        }
+        anyhow::bail!(
+            "Invalid time '{}' (use RFC 3339, YYYY-MM-DDTHH:MM or YYYY-MM-DD)",
+            value
+        )
+    }
+}
+
+/// An event to create (calendar_create_event arguments, parsed)
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewEvent {
+    pub title: String,
+    pub start: EventTime,
+    pub end: EventTime,
+    pub location: Option<String>,
+    pub description: Option<String>,
+}
+
+/// calendar_list_events arguments
+#[derive(Debug, Default, Deserialize)]
+struct ListArgs {
+    start: Option<String>,
+    days: Option<i64>,
+}
+
+/// calendar_create_event arguments
+#[derive(Debug, Deserialize)]
+struct CreateArgs {
+    title: String,
+    start: String,
+    end: Option<String>,
+    location: Option<String>,
+    description: Option<String>,
+}
+
+impl CreateArgs {
+    /// Parse times; end defaults to one hour (or one day) after start
+    fn into_event(self) -> Result<NewEvent> {
+        let start = EventTime::parse(&self.start)?;
+        let end = match (&self.end, start) {
+            (Some(end), _) => EventTime::parse(end)?,
+            (None, EventTime::Date(date)) => EventTime::Date(date + chrono::Duration::days(1)),
+            (None, EventTime::DateTime(time)) => {
+                EventTime::DateTime(time + chrono::Duration::hours(1))
+            }
+        };
+        let valid = match (start, end) {
+            (EventTime::Date(start), EventTime::Date(end)) => end > start,
+            (EventTime::DateTime(start), EventTime::DateTime(end)) => end > start,
+            _ => anyhow::bail!("start and end must both be dates or both be times"),
+        };
+        if !valid {
+            anyhow::bail!("end must be after start");
+        }
+
+        Ok(NewEvent {
+            title: self.title,
+            start,
+            end,
+            location: self.location.filter(|s| !s.is_empty()),
+            description: self.description.filter(|s| !s.is_empty()),
+        })
+    }
+}
+
+/// Provider-specific calendar access
+#[async_trait]
+pub trait CalendarBackend: Send + Sync {
+    /// Events overlapping [start, end), sorted by start
+    async fn list_events(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<CalendarEvent>>;
+
+    /// Create an event and return it as stored
+    async fn create_event(&self, event: &NewEvent) -> Result<CalendarEvent>;
+}
+
+/// Calendar tools for the configured account
+pub struct CalendarService {
+    label: String,
+    backend: Box<dyn CalendarBackend>,
+}
+
+impl CalendarService {
+    /// Wrap a backend
+    pub fn new(label: impl Into<String>, backend: Box<dyn CalendarBackend>) -> Self {
+        Self {
+            label: label.into(),
+            backend,
+        }
+    }
+
+    /// Build the service for a configuration, reading credentials from `secrets`
+    ///
+    /// # Errors
+    /// - A required secret is missing (CalDAV password, Google client secret
+    ///   or refresh token) or the secret store is unavailable
+    pub fn from_config(config: &CalendarConfig, secrets: &dyn SecretStore) -> Result<Self> {
+        let require = |name: &str| -> Result<String> {
+            secrets
+                .get(name)?
+                .with_context(|| format!("{} is not set", name))
+        };
+
+        let backend: Box<dyn CalendarBackend> = match config {
+            CalendarConfig::CalDav { url, username } => Box::new(CalDavBackend {
+                client: reqwest::Client::new(),
+                url: url.clone(),
+                username: username.clone(),
+                password: require(CALDAV_PASSWORD_SECRET)?,
+            }),
+            CalendarConfig::Google {
+                client_id,
+                calendar_id,
+            } => Box::new(GoogleBackend {
+                client: reqwest::Client::new(),
+                client_id: client_id.clone(),
+                client_secret: require(GOOGLE_CLIENT_SECRET)?,
+                refresh_token: require(GOOGLE_REFRESH_TOKEN_SECRET)?,
+                calendar_id: calendar_id.clone(),
+                access_token: Mutex::new(None),
+            }),
+        };
+        Ok(Self::new(config.label(), backend))
+    }
+
+    /// Account description (e.g. "Google Calendar (primary)")
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Check if a tool name belongs to the calendar connector
+    pub fn is_calendar_tool(tool_name: &str) -> bool {
+        tool_name == LIST_EVENTS_TOOL || tool_name == CREATE_EVENT_TOOL
+    }
+
+    /// Definitions of calendar_list_events and calendar_create_event
+    pub fn tool_definitions(&self) -> Vec<ToolDefinition> {
+        let now = Local::now().format("%Y-%m-%dT%H:%M%:z");
+        vec![
+            tool_definition(
+                LIST_EVENTS_TOOL,
+                &format!(
+                    "List events in the user's calendar ({}). Without arguments returns \
+                     today's events. The current time is {}.",
+                    self.label, now
+                ),
+                serde_json::json!({
+                    "start": {
+                        "type": "string",
+                        "description": "First day (YYYY-MM-DD) or time (RFC 3339), default today"
+                    },
+                    "days": {
+                        "type": "integer",
+                        "description": format!(
+                            "Number of days to cover (default 1, max {})",
+                            MAX_LIST_DAYS
+                        )
+                    }
+                }),
+                &[],
+            ),
+            tool_definition(
+                CREATE_EVENT_TOOL,
+                &format!(
+                    "Create an event in the user's calendar ({}). Use YYYY-MM-DD dates for \
+                     all-day events. The current time is {}.",
+                    self.label, now
+                ),
+                serde_json::json!({
+                    "title": { "type": "string", "description": "Event title" },
+                    "start": {
+                        "type": "string",
+                        "description": "Start as RFC 3339, local YYYY-MM-DDTHH:MM, or YYYY-MM-DD"
+                    },
+                    "end": {
+                        "type": "string",
+                        "description": "End in the same format (default: 1 hour, or 1 day)"
+                    },
+                    "location": { "type": "string", "description": "Where it takes place" },
+                    "description": { "type": "string", "description": "Notes" }
+                }),
+                &["title", "start"],
+            ),
+        ]
+    }
+
+    /// Run a calendar tool
+    ///
+    /// # Arguments
+    /// * `tool_name` - calendar_list_events or calendar_create_event
+    /// * `arguments` - JSON-encoded tool arguments
+    ///
+    /// # Errors
+    /// - Unknown tool or invalid arguments
+    /// - Request or authentication failures
+    pub async fn call_tool(&self, tool_name: &str, arguments: &str) -> Result<String> {
+        let arguments = if arguments.trim().is_empty() {
+            "{}"
+        } else {
+            arguments
+        };
+
+        match tool_name {
+            LIST_EVENTS_TOOL => {
+                let args: ListArgs = serde_json::from_str(arguments)
+                    .context("Invalid calendar_list_events arguments")?;
+                let start = match args.start.as_deref().map(EventTime::parse).transpose()? {
+                    Some(EventTime::DateTime(time)) => time,
+                    Some(EventTime::Date(date)) => local_midnight(date)?,
+                    None => local_midnight(Local::now().date_naive())?,
+                };
+                let days = args.days.unwrap_or(1).clamp(1, MAX_LIST_DAYS);
+                let end = start + chrono::Duration::days(days);
+
+                let events = self.backend.list_events(start, end).await?;
+                Ok(serde_json::to_string_pretty(&events)?)
+            }
+            CREATE_EVENT_TOOL => {
+                let args: CreateArgs = serde_json::from_str(arguments)
+                    .context("Invalid calendar_create_event arguments")?;
+                let event = self.backend.create_event(&args.into_event()?).await?;
+                tracing::info!("📅 Created calendar event '{}'", event.title);
+                Ok(serde_json::to_string_pretty(&event)?)
+            }
+            other => anyhow::bail!("Unknown calendar tool '{}'", other),
+        }
+    }
+}
+
+/// CalDAV calendar collection with basic auth
+struct CalDavBackend {
+    client: reqwest::Client,
+    url: String,
+    username: String,
+    password: String,
+}
+
+#[async_trait]
+impl CalendarBackend for CalDavBackend {
+    async fn list_events(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<CalendarEvent>> {
+        let range = format!(r#"start="{}" end="{}""#, ics_utc(start), ics_utc(end));
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><c:calendar-data><c:expand {range}/></c:calendar-data></d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VEVENT"><c:time-range {range}/></c:comp-filter>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#
+        );
+
+        crate::privacy::check_url(&self.url)?;
+        let response = self
+            .client
+            .request(reqwest::Method::from_bytes(b"REPORT")?, &self.url)
+            .basic_auth(&self.username, Some(&self.password))
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(body)
+            .send()
+            .await
+            .with_context(|| format!("CalDAV request to {} failed", self.url))?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            anyhow::bail!("CalDAV server returned {}", status);
+        }
+
+        let mut events: Vec<CalendarEvent> = calendar_data(&text)
+            .iter()
+            .flat_map(|ics| parse_ics_events(ics))
+            .collect();
+        events.sort_by(|a, b| a.start.cmp(&b.start));
+        Ok(events)
+    }
+
+    async fn create_event(&self, event: &NewEvent) -> Result<CalendarEvent> {
+        let uid = format!(
+            "{}-{}@rustbot",
+            Utc::now().format("%Y%m%dT%H%M%S"),
+            random_token(8)
+        );
+        let url = format!("{}/{}.ics", self.url.trim_end_matches('/'), uid);
+        crate::privacy::check_url(&url)?;
+
+        let response = self
+            .client
+            .put(&url)
+            .basic_auth(&self.username, Some(&self.password))
+            .header("Content-Type", "text/calendar; charset=utf-8")
+            .header("If-None-Match", "*")
+            .body(to_ics(&uid, event, Utc::now()))
+            .send()
+            .await
+            .with_context(|| format!("CalDAV request to {} failed", url))?;
+        if !response.status().is_success() {
+            anyhow::bail!("CalDAV server returned {}", response.status());
+        }
+
+        Ok(CalendarEvent {
+            id: uid,
+            title: event.title.clone(),
+            start: display_event_time(event.start),
+            end: Some(display_event_time(event.end)),
+            all_day: matches!(event.start, EventTime::Date(_)),
+            location: event.location.clone(),
+            description: event.description.clone(),
+        })
+    }
+}
+
+/// Google Calendar REST API with a stored refresh token
+struct GoogleBackend {
+    client: reqwest::Client,
+    client_id: String,
+    client_secret: String,
+    refresh_token: String,
+    calendar_id: String,
+
+    /// Cached access token and when it expires
+    access_token: Mutex<Option<(String, Instant)>>,
+}
+
+/// Token endpoint response
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: u64,
+    refresh_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleEvents {
+    #[serde(default)]
+    items: Vec<GoogleEvent>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleEvent {
+    #[serde(default, skip_serializing)]
+    id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    #[serde(default)]
+    start: GoogleTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    end: Option<GoogleTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleTime {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    date_time: Option<String>,
+}
+
+impl GoogleTime {
+    fn from_event_time(time: EventTime) -> Self {
+        match time {
+            EventTime::Date(date) => Self {
+                date: Some(date.format("%Y-%m-%d").to_string()),
+                date_time: None,
+            },
+            EventTime::DateTime(time) => Self {
+                date: None,
+                date_time: Some(time.to_rfc3339()),
+            },
+        }
+    }
+
+    fn display(&self) -> String {
+        match (&self.date_time, &self.date) {
+            (Some(time), _) => DateTime::parse_from_rfc3339(time)
+                .map(|t| t.with_timezone(&Local).to_rfc3339())
+                .unwrap_or_else(|_| time.clone()),
+            (None, Some(date)) => date.clone(),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+impl From<GoogleEvent> for CalendarEvent {
+    fn from(event: GoogleEvent) -> Self {
+        Self {
+            all_day: event.start.date.is_some() && event.start.date_time.is_none(),
+            start: event.start.display(),
+            end: event.end.as_ref().map(GoogleTime::display),
+            id: event.id,
+            title: event.summary.unwrap_or_default(),
+            location: event.location,
+            description: event.description,
+        }
+    }
+}
+
+impl GoogleBackend {
+    /// A valid access token, refreshed when missing or about to expire
+    async fn access_token(&self) -> Result<String> {
+        let mut cached = self.access_token.lock().await;
+        if let Some((token, expires)) = cached.as_ref() {
+            if Instant::now() < *expires {
+                return Ok(token.clone());
+            }
+        }
+
+        crate::privacy::check_url(GOOGLE_TOKEN_URL)?;
+        let response = self
+            .client
+            .post(GOOGLE_TOKEN_URL)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("refresh_token", self.refresh_token.as_str()),
+                ("grant_type", "refresh_token"),
+            ])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Google token refresh failed ({}), reconnect the calendar in Settings",
+                response.status()
+            );
+        }
+        let token: TokenResponse = response.json().await?;
+
+        // Refresh a minute early so a token never expires mid-request
+        let lifetime = Duration::from_secs(token.expires_in.saturating_sub(60));
+        *cached = Some((token.access_token.clone(), Instant::now() + lifetime));
+        Ok(token.access_token)
+    }
+
+    fn events_url(&self) -> String {
+        format!(
+            "{}/calendars/{}/events",
+            GOOGLE_API_BASE,
+            url_encode_path_segment(&self.calendar_id)
+        )
+    }
+}
+
+#[async_trait]
+impl CalendarBackend for GoogleBackend {
+    async fn list_events(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<CalendarEvent>> {
+        crate::privacy::check_url(GOOGLE_API_BASE)?;
+        let response = self
+            .client
+            .get(self.events_url())
+            .bearer_auth(self.access_token().await?)
+            .query(&[
+                ("timeMin", start.to_rfc3339()),
+                ("timeMax", end.to_rfc3339()),
+                ("singleEvents", "true".to_string()),
+                ("orderBy", "startTime".to_string()),
+                ("maxResults", "250".to_string()),
+            ])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("Google Calendar returned {}", response.status());
+        }
+
+        let events: GoogleEvents = response.json().await?;
+        Ok(events.items.into_iter().map(CalendarEvent::from).collect())
+    }
+
+    async fn create_event(&self, event: &NewEvent) -> Result<CalendarEvent> {
+        let body = GoogleEvent {
+            id: String::new(),
+            summary: Some(event.title.clone()),
+            start: GoogleTime::from_event_time(event.start),
+            end: Some(GoogleTime::from_event_time(event.end)),
+            location: event.location.clone(),
+            description: event.description.clone(),
+        };
+
+        crate::privacy::check_url(GOOGLE_API_BASE)?;
+        let response = self
+            .client
+            .post(self.events_url())
+            .bearer_auth(self.access_token().await?)
+            .json(&body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("Google Calendar returned {}", response.status());
+        }
+
+        let created: GoogleEvent = response.json().await?;
+        Ok(created.into())
+    }
+}
+
+/// Google OAuth installed-app flow (PKCE, loopback redirect)
+///
+/// Usage:
+///     let flow = GoogleAuthFlow::start(client_id, client_secret).await?;
+///     open_in_browser(flow.url());
+///     let refresh_token = flow.finish().await?;
+pub struct GoogleAuthFlow {
+    url: String,
+    listener: TcpListener,
+    redirect_uri: String,
+    client_id: String,
+    client_secret: String,
+    verifier: String,
+    state: String,
+}
+
+impl GoogleAuthFlow {
+    /// Bind the loopback listener and build the consent URL
+    ///
+    /// # Errors
+    /// - No local port available
+    pub async fn start(client_id: &str, client_secret: &str) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let redirect_uri = format!("http://127.0.0.1:{}", listener.local_addr()?.port());
+        let verifier = random_token(32);
+        let state = random_token(16);
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+
+        let url = reqwest::Url::parse_with_params(
+            GOOGLE_AUTH_URL,
+            &[
+                ("client_id", client_id),
+                ("redirect_uri", redirect_uri.as_str()),
+                ("response_type", "code"),
+                ("scope", GOOGLE_SCOPE),
+                ("access_type", "offline"),
+                ("prompt", "consent"),
+                ("code_challenge", challenge.as_str()),
+                ("code_challenge_method", "S256"),
+                ("state", state.as_str()),
+            ],
+        )?
+        .to_string();
+
+        Ok(Self {
+            url,
+            listener,
+            redirect_uri,
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            verifier,
+            state,
+        })
+    }
+
+    /// Consent page to open in the browser
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Wait for the redirect and exchange the code for a refresh token
+    ///
+    /// # Errors
+    /// - No redirect within five minutes
+    /// - The user denied access or the state does not match
+    /// - Google did not return a refresh token
+    pub async fn finish(self) -> Result<String> {
+        let code = tokio::time::timeout(AUTH_TIMEOUT, self.wait_for_code())
+            .await
+            .context("Timed out waiting for Google sign-in")??;
+
+        crate::privacy::check_url(GOOGLE_TOKEN_URL)?;
+        let response = reqwest::Client::new()
+            .post(GOOGLE_TOKEN_URL)
+            .form(&[
+                ("code", code.as_str()),
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("grant_type", "authorization_code"),
+                ("code_verifier", self.verifier.as_str()),
+            ])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("Google token exchange failed ({})", response.status());
+        }
+
+        let token: TokenResponse = response.json().await?;
+        token
+            .refresh_token
+            .context("Google did not return a refresh token")
+    }
+
+    /// Serve the loopback redirect until it carries a code or an error
+    async fn wait_for_code(&self) -> Result<String> {
+        loop {
+            let (mut stream, _) = self.listener.accept().await?;
+            let mut buffer = vec![0u8; 8192];
+            let read = stream.read(&mut buffer).await?;
+            let request = String::from_utf8_lossy(&buffer[..read]);
+
+            let result = match redirect_params(&request) {
+                Some(params) => params.into_result(&self.state),
+                // Not the redirect (e.g. /favicon.ico), keep waiting
+                None => {
+                    let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\n\r\n").await;
+                    continue;
+                }
+            };
+
+            let page = match &result {
+                Ok(_) => "Rustbot is connected to Google Calendar. You can close this tab.",
+                Err(_) => "Google Calendar was not connected. Return to Rustbot for details.",
+            };
+            let _ = stream
+                .write_all(
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\n\
+                         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        page.len(),
+                        page
+                    )
+                    .as_bytes(),
+                )
+                .await;
+            return result;
+        }
+    }
+}
+
+/// Query parameters of the OAuth redirect
+#[derive(Debug, Default, PartialEq)]
+struct RedirectParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+impl RedirectParams {
+    fn into_result(self, expected_state: &str) -> Result<String> {
+        if let Some(error) = self.error {
+            anyhow::bail!("Google sign-in failed: {}", error);
+        }
+        if self.state.as_deref() != Some(expected_state) {
+            anyhow::bail!("Google sign-in returned an unexpected state");
+        }
+        self.code.context("Google sign-in returned no code")
+    }
+}
+
+/// Parse "GET /?code=...&state=... HTTP/1.1"; None for other paths
+fn redirect_params(request: &str) -> Option<RedirectParams> {
+    let target = request.lines().next()?.split_whitespace().nth(1)?;
+    let url = reqwest::Url::parse(&format!("http://127.0.0.1{}", target)).ok()?;
+    if url.path() != "/" {
+        return None;
+    }
+
+    let mut params = RedirectParams::default();
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "code" => params.code = Some(value.into_owned()),
+            "state" => params.state = Some(value.into_owned()),
+            "error" => params.error = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+    if params == RedirectParams::default() {
+        return None;
+    }
+    Some(params)
+}
+
+/// Random URL-safe token from `bytes` random bytes
+fn random_token(bytes: usize) -> String {
+    let mut buffer = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buffer);
+    URL_SAFE_NO_PAD.encode(buffer)
+}
+
+/// Percent-encode a calendar ID for use in a URL path
+fn url_encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Start of a local day in UTC
+fn local_midnight(date: NaiveDate) -> Result<DateTime<Utc>> {
+    let midnight = date.and_hms_opt(0, 0, 0).context("Invalid date")?;
+    Ok(Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .context("Midnight does not exist in local time")?
+        .with_timezone(&Utc))
+}
+
+/// iCalendar UTC timestamp (e.g. 20250106T120000Z)
+fn ics_utc(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn display_event_time(time: EventTime) -> String {
+    match time {
+        EventTime::Date(date) => date.format("%Y-%m-%d").to_string(),
+        EventTime::DateTime(time) => time.with_timezone(&Local).to_rfc3339(),
+    }
+}
+
+/// Contents of every <calendar-data> element in a multistatus response
+fn calendar_data(xml: &str) -> Vec<String> {
+    let pattern =
+        regex::Regex::new(r"(?s)<(?:\w+:)?calendar-data[^>]*>(.*?)</(?:\w+:)?calendar-data>")
+            .expect("valid calendar-data pattern");
+    pattern
+        .captures_iter(xml)
+        .map(|captures| {
+            let data = captures[1].trim();
+            match data
+                .strip_prefix("<![CDATA[")
+                .and_then(|d| d.strip_suffix("]]>"))
+            {
+                Some(cdata) => cdata.to_string(),
+                None => xml_unescape(data),
+            }
+        })
+        .collect()
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&#xD;", "\r")
+        .replace("&amp;", "&")
+}
+
+/// Read the VEVENTs of an iCalendar document
+fn parse_ics_events(ics: &str) -> Vec<CalendarEvent> {
+    // Unfold continuation lines (RFC 5545 3.1)
+    let unfolded = ics
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut events = Vec::new();
+    let mut current: Option<CalendarEvent> = None;
+    let mut nested = 0; // VALARM and other components inside the event
+
+    for line in unfolded.lines() {
+        let Some((name_and_params, value)) = line.split_once(':') else {
+            continue;
+        };
+        let mut parts = name_and_params.split(';');
+        let name = parts.next().unwrap_or_default().to_ascii_uppercase();
+        let params: Vec<&str> = parts.collect();
+
+        let Some(event) = current.as_mut() else {
+            if name == "BEGIN" && value == "VEVENT" {
+                current = Some(CalendarEvent::default());
+            }
+            continue;
+        };
+
+        match name.as_str() {
+            "END" if value == "VEVENT" && nested == 0 => {
+                events.extend(current.take());
+            }
+            "BEGIN" => nested += 1,
+            "END" => nested -= 1,
+            _ if nested > 0 => {}
+            "UID" => event.id = value.to_string(),
+            "SUMMARY" => event.title = ics_unescape(value),
+            "LOCATION" => event.location = Some(ics_unescape(value)),
+            "DESCRIPTION" => event.description = Some(ics_unescape(value)),
+            "DTSTART" => {
+                let (start, all_day) = ics_time(value, &params);
+                event.start = start;
+                event.all_day = all_day;
+            }
+            "DTEND" => event.end = Some(ics_time(value, &params).0),
+            _ => {}
+        }
+    }
+    events
+}
+
+/// Convert an iCalendar DATE or DATE-TIME for display
+///
+/// # Returns
+/// (display string, is a date)
+fn ics_time(value: &str, params: &[&str]) -> (String, bool) {
+    if params.iter().any(|p| p.eq_ignore_ascii_case("VALUE=DATE")) || value.len() == 8 {
+        return match NaiveDate::parse_from_str(value, "%Y%m%d") {
+            Ok(date) => (date.format("%Y-%m-%d").to_string(), true),
+            Err(_) => (value.to_string(), true),
+        };
+    }
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S") {
+            return (
+                Utc.from_utc_datetime(&naive)
+                    .with_timezone(&Local)
+                    .to_rfc3339(),
+                false,
+            );
+        }
+    }
+
+    let Ok(naive) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") else {
+        return (value.to_string(), false);
+    };
+    let wall_clock = naive.format("%Y-%m-%dT%H:%M:%S").to_string();
+    match params.iter().find_map(|p| p.strip_prefix("TZID=")) {
+        Some(zone) => (
+            format!("{} ({})", wall_clock, zone.trim_matches('"')),
+            false,
+        ),
+        None => (wall_clock, false),
+    }
+}
+
+fn ics_unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn ics_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Serialize a new event as an iCalendar document
+fn to_ics(uid: &str, event: &NewEvent, now: DateTime<Utc>) -> String {
+    let time = |name: &str, time: EventTime| match time {
+        EventTime::Date(date) => format!("{};VALUE=DATE:{}", name, date.format("%Y%m%d")),
+        EventTime::DateTime(time) => format!("{}:{}", name, ics_utc(time)),
+    };
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Rustbot//Calendar//EN".to_string(),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", uid),
+        format!("DTSTAMP:{}", ics_utc(now)),
+        time("DTSTART", event.start),
+        time("DTEND", event.end),
+        format!("SUMMARY:{}", ics_escape(&event.title)),
+    ];
+    if let Some(location) = &event.location {
+        lines.push(format!("LOCATION:{}", ics_escape(location)));
+    }
+    if let Some(description) = &event.description {
+        lines.push(format!("DESCRIPTION:{}", ics_escape(description)));
+    }
+    lines.push("END:VEVENT".to_string());
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut ics = lines.join("\r\n");
+    ics.push_str("\r\n");
+    ics
+}
+
+fn tool_definition(
+    name: &str,
+    description: &str,
+    properties: serde_json::Value,
+    required: &[&str],
+) -> ToolDefinition {
+    ToolDefinition {
+        tool_type: "function".to_string(),
+        function: FunctionDefinition {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters: FunctionParameters {
+                param_type: "object".to_string(),
+                properties,
+                required: required.iter().map(|s| s.to_string()).collect(),
+            },
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Backend that records created events and returns a fixed list
+    struct FakeBackend {
+        created: std::sync::Mutex<Vec<NewEvent>>,
+    }
+
+    #[async_trait]
+    impl CalendarBackend for FakeBackend {
+        async fn list_events(
+            &self,
+            start: DateTime<Utc>,
+            end: DateTime<Utc>,
+        ) -> Result<Vec<CalendarEvent>> {
+            Ok(vec![CalendarEvent {
+                id: "1".to_string(),
+                title: format!("{} days", (end - start).num_days()),
+                ..Default::default()
+            }])
+        }
+
+        async fn create_event(&self, event: &NewEvent) -> Result<CalendarEvent> {
+            self.created.lock().unwrap().push(event.clone());
+            Ok(CalendarEvent {
+                id: "new".to_string(),
+                title: event.title.clone(),
+                ..Default::default()
+            })
+        }
+    }
+
+    #[test]
+    fn test_config_serde() {
+        let config: CalendarConfig =
+            serde_json::from_str(r#"{"provider": "google", "client_id": "abc"}"#).unwrap();
+        assert_eq!(
+            config,
+            CalendarConfig::Google {
+                client_id: "abc".to_string(),
+                calendar_id: "primary".to_string()
+            }
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("calendar.json");
+        assert!(CalendarConfig::load(&path).unwrap().is_none());
+        let config = CalendarConfig::CalDav {
+            url: "https://dav.example.com/cal/".to_string(),
+            username: "me".to_string(),
+        };
+        config.save(&path).unwrap();
+        assert_eq!(CalendarConfig::load(&path).unwrap(), Some(config));
+    }
+
+    #[test]
+    fn test_parse_ics_events() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+                   BEGIN:VEVENT\r\n\
+                   UID:a1\r\n\
+                   SUMMARY:Team sync\\, weekly\r\n\
+                   DTSTART;TZID=Europe/Berlin:20250106T100000\r\n\
+                   DTEND;TZID=Europe/Berlin:20250106T103000\r\n\
+                   DESCRIPTION:Agenda:\\nupdates and a very long line that is \r\n \
+                   folded\r\n\
+                   BEGIN:VALARM\r\n\
+                   DESCRIPTION:Reminder\r\n\
+                   END:VALARM\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VEVENT\r\n\
+                   UID:a2\r\n\
+                   SUMMARY:Holiday\r\n\
+                   DTSTART;VALUE=DATE:20250107\r\n\
+                   END:VEVENT\r\n\
+                   END:VCALENDAR\r\n";
+
+        let events = parse_ics_events(ics);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].id, "a1");
+        assert_eq!(events[0].title, "Team sync, weekly");
+        assert_eq!(events[0].start, "2025-01-06T10:00:00 (Europe/Berlin)");
+        assert_eq!(
+            events[0].description.as_deref(),
+            Some("Agenda:\nupdates and a very long line that is folded")
+        );
+        assert!(!events[0].all_day);
+        assert_eq!(events[1].start, "2025-01-07");
+        assert!(events[1].all_day);
+    }
+
+    #[test]
+    fn test_ics_roundtrip() {
+        let event = NewEvent {
+            title: "Lunch; with Bob".to_string(),
+            start: EventTime::parse("2025-01-06T12:00:00Z").unwrap(),
+            end: EventTime::parse("2025-01-06T13:00:00Z").unwrap(),
+            location: Some("Cafe, Main St".to_string()),
+            description: None,
+        };
+        let ics = to_ics("uid-1", &event, Utc::now());
+        assert!(ics.contains("DTSTART:20250106T120000Z\r\n"));
+
+        let parsed = parse_ics_events(&ics);
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].id, "uid-1");
+        assert_eq!(parsed[0].title, "Lunch; with Bob");
+        assert_eq!(parsed[0].location.as_deref(), Some("Cafe, Main St"));
+    }
+
+    #[test]
+    fn test_calendar_data_extraction() {
+        let xml = r#"<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+            <d:response><d:propstat><d:prop>
+              <cal:calendar-data>BEGIN:VCALENDAR&#13;
+SUMMARY:A &amp; B&#13;
+END:VCALENDAR</cal:calendar-data>
+            </d:prop></d:propstat></d:response>
+            <d:response><d:propstat><d:prop>
+              <calendar-data><![CDATA[BEGIN:VCALENDAR
+END:VCALENDAR]]></calendar-data>
+            </d:prop></d:propstat></d:response>
+        </d:multistatus>"#;
+
+        let data = calendar_data(xml);
+        assert_eq!(data.len(), 2);
+        assert!(data[0].contains("SUMMARY:A & B\r\n"));
+        assert!(data[1].starts_with("BEGIN:VCALENDAR\nEND"));
+    }
+
+    #[test]
+    fn test_event_time_parsing() {
+        assert_eq!(
+            EventTime::parse("2025-01-06").unwrap(),
+            EventTime::Date(NaiveDate::from_ymd_opt(2025, 1, 6).unwrap())
+        );
+        assert!(matches!(
+            EventTime::parse("2025-01-06T09:30").unwrap(),
+            EventTime::DateTime(_)
+        ));
+        assert!(EventTime::parse("next tuesday").is_err());
+
+        let args: CreateArgs =
+            serde_json::from_str(r#"{"title": "Offsite", "start": "2025-01-06"}"#).unwrap();
+        let event = args.into_event().unwrap();
+        assert_eq!(
+            event.end,
+            EventTime::Date(NaiveDate::from_ymd_opt(2025, 1, 7).unwrap())
+        );
+
+        let args: CreateArgs = serde_json::from_str(
+            r#"{"title": "x", "start": "2025-01-06T10:00:00Z", "end": "2025-01-06T09:00:00Z"}"#,
+        )
+        .unwrap();
+        assert!(args.into_event().is_err());
+    }
+
+    #[test]
+    fn test_google_event_conversion() {
+        let event: GoogleEvent = serde_json::from_str(
+            r#"{"id": "g1", "summary": "Dentist",
+                "start": {"date": "2025-01-06"}, "end": {"date": "2025-01-07"}}"#,
+        )
+        .unwrap();
+        let event = CalendarEvent::from(event);
+        assert_eq!(event.id, "g1");
+        assert_eq!(event.title, "Dentist");
+        assert_eq!(event.start, "2025-01-06");
+        assert!(event.all_day);
+
+        let body = serde_json::to_value(GoogleEvent {
+            summary: Some("x".to_string()),
+            start: GoogleTime::from_event_time(EventTime::parse("2025-01-06").unwrap()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"summary": "x", "start": {"date": "2025-01-06"}})
+        );
+    }
+
+    #[test]
+    fn test_redirect_params() {
+        let params =
+            redirect_params("GET /?state=s1&code=4%2Fabc HTTP/1.1\r\nHost: x\r\n\r\n").unwrap();
+        assert_eq!(params.into_result("s1").unwrap(), "4/abc");
+
+        let params = redirect_params("GET /?state=s1&code=c HTTP/1.1\r\n").unwrap();
+        assert!(params.into_result("other").is_err());
+
+        let params = redirect_params("GET /?error=access_denied HTTP/1.1\r\n").unwrap();
+        assert!(params.into_result("s1").is_err());
+
+        assert!(redirect_params("GET /favicon.ico HTTP/1.1\r\n").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tools() {
+        let service = CalendarService::new(
+            "Test",
+            Box::new(FakeBackend {
+                created: std::sync::Mutex::new(Vec::new()),
+            }),
+        );
+        assert!(CalendarService::is_calendar_tool(LIST_EVENTS_TOOL));
+        assert_eq!(service.tool_definitions().len(), 2);
+
+        let listed = service
+            .call_tool(LIST_EVENTS_TOOL, r#"{"start": "2025-01-06", "days": 90}"#)
+            .await
+            .unwrap();
+        assert!(listed.contains(&format!("{} days", MAX_LIST_DAYS)));
+
+        let created = service
+            .call_tool(
+                CREATE_EVENT_TOOL,
+                r#"{"title": "Call", "start": "2025-01-06T10:00:00Z"}"#,
+            )
+            .await
+            .unwrap();
+        assert!(created.contains("Call"));
+        assert!(service.call_tool(CREATE_EVENT_TOOL, "{}").await.is_err());
+    }
+}
@@ -0,0 +1,186 @@
+// Typed chat streams for library consumers and alternative frontends
+//
+// Design Decision: A channel of `StreamItem`s next to the plain text stream
+//
+// Rationale: `RustbotApi::send_message` returns the answer as bare text
+// chunks. Tool calls only show up as `ToolCall` events on the bus (matched to
+// the turn by session ID) and usage isn't reported at all, so a frontend that
+// wants tool cards or a token count has to subscribe to the bus and piece the
+// turn together itself. `send_message_stream_in` returns everything about one
+// turn on one channel, in order and as it happens: the tool calls as they
+// start and end, the answer text, an estimated usage report, then `Done` (or
+// `Error` if the turn failed).
+//
+// `send_message` is unchanged; the typed stream is built on the same turn, so
+// both send exactly the same request.
+//
+// Trade-offs:
+// - The turn runs in its own task on the shared API, so the stream is handed
+//   back before anything has happened. A consumer that drops its receiver
+//   stops the answer text, but tool calls already asked for still run
+// - Usage is estimated with `crate::tokenizer`, like the context meter, not
+//   taken from the provider's billing
+// - The channel is unbounded like the text stream
+// - As with `send_message`, the caller records the answer in the history
+//   (`add_assistant_response_in`, with the text from `Done`)
+
+use crate::events::ToolCallRecord;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Receiving end of a typed chat stream
+pub type ChatStream = mpsc::UnboundedReceiver<StreamItem>;
+
+/// One thing that happened while answering a message
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamItem {
+    /// The next piece of the answer text
+    TextDelta(String),
+
+    /// The agent called a tool
+    ToolCallStarted {
+        /// ID the model gave the call
+        id: String,
+        name: String,
+        arguments: serde_json::Value,
+    },
+
+    /// A tool call finished
+    ToolResult {
+        /// ID of the call (as in `ToolCallStarted`)
+        id: String,
+        name: String,
+        /// The tool's output, or its error message
+        result: Result<String, String>,
+        duration: Duration,
+    },
+
+    /// Estimated tokens of the turn, sent once after the answer text
+    UsageReport(Usage),
+
+    /// The answer is complete; carries its full text
+    Done(String),
+
+    /// The turn failed; carries the error, and is the last item like `Done`
+    Error(String),
+}
+
+/// Estimated token usage of one turn
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Usage {
+    /// Model the agent's requests went to
+    pub model: String,
+
+    /// Tokens sent: instructions, context, tools and the new message
+    pub input_tokens: u32,
+
+    /// Tokens of the answer text
+    pub output_tokens: u32,
+}
+
+impl StreamItem {
+    /// The item for a tool call record: started while it has no result,
+    /// finished once it has one
+    pub fn from_tool_call(call: &ToolCallRecord) -> Self {
+        match &call.result {
+            None => StreamItem::ToolCallStarted {
+                id: call.id.clone(),
+                name: call.name.clone(),
+                arguments: call.arguments.clone(),
+            },
+            Some(result) => StreamItem::ToolResult {
+                id: call.id.clone(),
+                name: call.name.clone(),
+                result: result.clone(),
+                duration: call.duration,
+            },
+        }
+    }
+}
+
+/// Forward a text stream as `TextDelta`s, then report usage and finish
+///
+/// Stops early, dropping `text` (which cancels the request), if the consumer
+/// drops its receiver.
+///
+/// # Arguments
+/// * `text` - Answer chunks from `send_message`
+/// * `items` - Typed stream to send to
+/// * `usage` - Usage of the request; `output_tokens` is counted here
+pub async fn forward_text(
+    mut text: mpsc::UnboundedReceiver<String>,
+    items: mpsc::UnboundedSender<StreamItem>,
+    mut usage: Usage,
+) {
+    let mut response = String::new();
+    while let Some(chunk) = text.recv().await {
+        response.push_str(&chunk);
+        if items.send(StreamItem::TextDelta(chunk)).is_err() {
+            return;
+        }
+    }
+    usage.output_tokens = crate::tokenizer::count_tokens(&response) as u32;
+    let _ = items.send(StreamItem::UsageReport(usage));
+    let _ = items.send(StreamItem::Done(response));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_tool_call() {
+        let mut call = ToolCallRecord {
+            id: "call_1".to_string(),
+            name: "web_search".to_string(),
+            arguments: serde_json::json!({ "query": "rust" }),
+            result: None,
+            duration: Duration::ZERO,
+            warning: None,
+        };
+        assert!(matches!(
+            StreamItem::from_tool_call(&call),
+            StreamItem::ToolCallStarted { ref name, .. } if name == "web_search"
+        ));
+
+        call.result = Some(Err("offline".to_string()));
+        call.duration = Duration::from_millis(20);
+        assert_eq!(
+            StreamItem::from_tool_call(&call),
+            StreamItem::ToolResult {
+                id: "call_1".to_string(),
+                name: "web_search".to_string(),
+                result: Err("offline".to_string()),
+                duration: Duration::from_millis(20),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forward_text() {
+        let (text_tx, text_rx) = mpsc::unbounded_channel();
+        let (items_tx, mut items_rx) = mpsc::unbounded_channel();
+        text_tx.send("Hel".to_string()).unwrap();
+        text_tx.send("lo".to_string()).unwrap();
+        drop(text_tx);
+
+        let usage = Usage {
+            model: "test/model".to_string(),
+            input_tokens: 12,
+            output_tokens: 0,
+        };
+        forward_text(text_rx, items_tx, usage).await;
+
+        let mut items = Vec::new();
+        while let Some(item) = items_rx.recv().await {
+            items.push(item);
+        }
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[0], StreamItem::TextDelta("Hel".to_string()));
+        assert!(matches!(
+            &items[2],
+            StreamItem::UsageReport(usage) if usage.input_tokens == 12 && usage.output_tokens > 0
+        ));
+        assert_eq!(items[3], StreamItem::Done("Hello".to_string()));
+    }
+}
@@ -0,0 +1,939 @@
+// Headless command-line interface
+//
+// Design Decision: Subcommands on the main binary, built on RustbotApi
+//
+// Rationale: Scripts, cron jobs and terminal users want answers from the same
+// agents, API key and system instructions as the desktop app without opening
+// a window. Running `rustbot` with no arguments still launches the GUI; a
+// subcommand skips eframe entirely and drives RustbotApi directly, the same
+// way the IPC socket does.
+//
+// Usage:
+//     rustbot ask "What's the capital of France?"
+//     rustbot ask --agent researcher --no-stream "Summarize RFC 9110"
+//     rustbot chat                 # interactive REPL (/help for commands)
+//     rustbot agents               # list agent IDs
+//     rustbot serve --bind 127.0.0.1:8787   # REST API (see `server`)
+//     rustbot discord              # Discord bot (see `discord`)
+//     rustbot telegram             # Telegram bridge (see `telegram`)
+//     rustbot rpc                  # JSON-RPC backend on stdio (see `rpc`)
+//     rustbot 'rustbot://chat?prompt=Hi'   # open a link in the app (see `deep_link`)
+//
+// Pipe mode: `-` in place of the prompt reads it from stdin; any words after
+// it become an instruction placed before the piped text:
+//     cat notes.md | rustbot --agent summarizer -
+//     git diff | rustbot - "Write a commit message for this diff"
+//     cat error.log | rustbot ask --json - "What failed?"
+//
+// Output: Response text goes to stdout (streamed as it arrives unless
+// --no-stream); logs and errors go to stderr so stdout can be piped.
+// --json prints one JSON object per response instead (see `JsonResponse`).
+//
+// Exit codes (for scripting):
+//     0  success
+//     1  request failed (network, provider or agent error)
+//     2  invalid arguments (reported by clap)
+//     3  configuration error (missing API key, unknown agent)
+//
+// Trade-offs:
+// - `ask`, `chat` and `serve` don't start MCP plugins, so agents run without
+//   plugin tools; the bot modes start enabled plugins from mcp_config.json
+// - CLI conversations are not saved to the History view
+
+use crate::agent::AgentConfig;
+use crate::api::{RustbotApi, RustbotApiBuilder};
+use crate::app_builder::AppDependencies;
+use crate::bot_sessions::BotSessions;
+use crate::deep_link;
+use crate::discord::{self, DiscordConfig};
+use crate::events::EventBus;
+use crate::mcp::manager::McpPluginManager;
+use crate::rpc;
+use crate::server::{self, ServerConfig};
+use crate::services::StorageService;
+use crate::settings_bundle::SettingsPaths;
+use crate::telegram::{self, TelegramConfig};
+use anyhow::{Context, Result};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{BufRead, IsTerminal, Read, Write};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Mutex;
+
+/// Exit code: success
+pub const EXIT_OK: i32 = 0;
+
+/// Exit code: the request failed
+pub const EXIT_FAILURE: i32 = 1;
+
+/// Exit code: missing API key, unknown agent or unreadable configuration
+pub const EXIT_CONFIG: i32 = 3;
+
+/// Same history window as the desktop app
+const MAX_HISTORY_SIZE: usize = 20;
+
+/// Prompt argument that means "read the prompt from stdin"
+pub const STDIN_PROMPT: &str = "-";
+
+/// Rustbot command line (no subcommand launches the desktop app)
+#[derive(Debug, Parser)]
+#[command(
+    name = "rustbot",
+    version,
+    about = "AI assistant powered by OpenRouter",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+
+    /// Pipe mode: `-` reads the prompt from stdin, optionally followed by an
+    /// instruction (same as `rustbot ask - ...`)
+    #[arg(value_name = "-")]
+    pub input: Vec<String>,
+
+    #[command(flatten)]
+    pub options: ChatOptions,
+
+    /// Log at info level to stderr (headless commands log warnings only)
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+}
+
+impl Cli {
+    /// A rustbot:// link passed by the OS to open in the desktop app
+    pub fn deep_link(&self) -> Option<&str> {
+        match self.input.as_slice() {
+            [link] if deep_link::is_deep_link(link) => Some(link),
+            _ => None,
+        }
+    }
+
+    /// Resolve the headless command to run, if any
+    ///
+    /// A top-level `-` is shorthand for `ask -`. No subcommand and no `-`
+    /// (or only a rustbot:// link) means the desktop app should start.
+    ///
+    /// # Errors
+    /// A clap usage error (exit code 2) for stray words or chat options
+    /// given without a command
+    pub fn into_command(self) -> std::result::Result<Option<CliCommand>, clap::Error> {
+        if self.command.is_some() {
+            return Ok(self.command);
+        }
+
+        if self.deep_link().is_some() && !self.options.is_set() {
+            return Ok(None);
+        }
+
+        match self.input.first().map(String::as_str) {
+            Some(STDIN_PROMPT) => Ok(Some(CliCommand::Ask {
+                prompt: self.input,
+                options: self.options,
+            })),
+            Some(other) => Err(Self::command().error(
+                ErrorKind::InvalidSubcommand,
+                format!(
+                    "unrecognized subcommand '{}' (use `rustbot ask ...`, or `-` to read the prompt from stdin)",
+                    other
+                ),
+            )),
+            None if self.options.is_set() => Err(Self::command().error(
+                ErrorKind::MissingRequiredArgument,
+                "--agent, --no-stream and --json need a prompt (`-` for stdin) or a subcommand",
+            )),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Headless subcommands
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    /// Ask a single question and print the answer
+    Ask {
+        /// The question (multiple words are joined with spaces)
+        #[arg(required = true, num_args = 1..)]
+        prompt: Vec<String>,
+
+        #[command(flatten)]
+        options: ChatOptions,
+    },
+
+    /// Interactive chat in the terminal
+    Chat {
+        #[command(flatten)]
+        options: ChatOptions,
+    },
+
+    /// List available agents
+    Agents,
+
+    /// Serve the REST API so other apps can use Rustbot as a backend
+    Serve {
+        /// Address to listen on (use 0.0.0.0:8787 to accept network connections)
+        #[arg(long, default_value = server::DEFAULT_BIND)]
+        bind: SocketAddr,
+
+        /// Bearer token clients must send (default: $RUSTBOT_SERVER_TOKEN,
+        /// otherwise a random token printed at startup)
+        #[arg(long)]
+        token: Option<String>,
+
+        /// Agent to start with (defaults to the primary agent)
+        #[arg(short, long)]
+        agent: Option<String>,
+    },
+
+    /// Run as a Discord bot (slash commands via the interactions endpoint)
+    Discord {
+        /// Address the interactions endpoint listens on
+        #[arg(long, default_value = discord::DEFAULT_BIND)]
+        bind: SocketAddr,
+
+        /// Bot configuration (default: ~/.rustbot/discord.json)
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+
+    /// Chat with your agents from Telegram (long polling, no public URL needed)
+    Telegram {
+        /// Bridge configuration (default: ~/.rustbot/telegram.json)
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+
+    /// Run as a JSON-RPC backend for other frontends (stdin/stdout by default)
+    Rpc {
+        /// Listen on a Unix socket instead of stdio
+        #[arg(long)]
+        socket: Option<PathBuf>,
+
+        /// Agent to start with (defaults to the primary agent)
+        #[arg(short, long)]
+        agent: Option<String>,
+    },
+}
+
+/// Options shared by `ask` and `chat`
+#[derive(Debug, Clone, Default, Args)]
+pub struct ChatOptions {
+    /// Agent to talk to (defaults to the primary agent)
+    #[arg(short, long)]
+    pub agent: Option<String>,
+
+    /// Print the response only once it is complete
+    #[arg(long)]
+    pub no_stream: bool,
+
+    /// Print each response as a JSON object (implies --no-stream)
+    #[arg(long)]
+    pub json: bool,
+}
+
+impl ChatOptions {
+    fn is_set(&self) -> bool {
+        self.agent.is_some() || self.no_stream || self.json
+    }
+}
+
+/// Structured result printed by `--json`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JsonResponse {
+    /// Agent that answered
+    pub agent: String,
+
+    /// Full response text (absent on error)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+
+    /// Error message (absent on success)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Tools the agent called while answering
+    pub tools: Vec<String>,
+
+    /// Wall-clock time from sending the message to the last chunk
+    pub duration_ms: u64,
+
+    /// Time from sending the message to the first chunk (absent if none came)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_token_ms: Option<u64>,
+}
+
+/// Commands available inside `rustbot chat`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplCommand {
+    /// Send the line as a message
+    Message(String),
+    /// Switch the active agent
+    Agent(String),
+    /// List agents
+    Agents,
+    /// Forget the conversation so far
+    Clear,
+    /// Show REPL commands
+    Help,
+    /// Leave the REPL
+    Exit,
+    /// Blank line
+    Empty,
+}
+
+impl ReplCommand {
+    /// Parse a line typed at the `chat` prompt
+    ///
+    /// Lines starting with `/` are commands; everything else is a message.
+    /// Unknown commands are sent as messages so paths like `/etc/hosts` work.
+    pub fn parse(line: &str) -> Self {
+        let line = line.trim();
+        if line.is_empty() {
+            return Self::Empty;
+        }
+
+        let (command, arg) = match line.split_once(char::is_whitespace) {
+            Some((command, arg)) => (command, arg.trim()),
+            None => (line, ""),
+        };
+        match command {
+            "/exit" | "/quit" => Self::Exit,
+            "/clear" => Self::Clear,
+            "/help" => Self::Help,
+            "/agents" => Self::Agents,
+            "/agent" if !arg.is_empty() => Self::Agent(arg.to_string()),
+            _ => Self::Message(line.to_string()),
+        }
+    }
+}
+
+const REPL_HELP: &str = "\
+Commands:
+  /agent <id>   switch agent
+  /agents       list agents
+  /clear        start a new conversation
+  /exit         quit (or Ctrl-D)";
+
+/// Run a headless command to completion
+///
+/// # Arguments
+/// * `command` - Parsed subcommand
+/// * `deps` - Application dependencies (runtime and LLM adapter required)
+///
+/// # Returns
+/// Process exit code (see module docs)
+pub fn run(command: CliCommand, deps: AppDependencies) -> i32 {
+    let Some(runtime) = deps.runtime.clone() else {
+        eprintln!("error: no async runtime available");
+        return EXIT_FAILURE;
+    };
+
+    let mut api = match build_api(&deps, &runtime) {
+        Ok(api) => api,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            return EXIT_CONFIG;
+        }
+    };
+
+    let storage = Arc::clone(&deps.storage);
+    let event_bus = Arc::clone(&deps.event_bus);
+    runtime.block_on(async move {
+        // Local-only mode, injection warnings and daily limits apply to headless commands too
+        match storage.load_user_profile().await {
+            Ok(profile) => profile.apply_process_settings(),
+            Err(e) => tracing::warn!("Failed to load user profile: {}", e),
+        }
+
+        match command {
+            CliCommand::Ask { prompt, options } => {
+                let prompt = match read_prompt(&prompt, std::io::stdin().lock()) {
+                    Ok(prompt) => prompt,
+                    Err(e) => {
+                        eprintln!("error: {:#}", e);
+                        return EXIT_FAILURE;
+                    }
+                };
+                ask(&mut api, &prompt, &options).await
+            }
+            CliCommand::Chat { options } => chat(&mut api, &options).await,
+            CliCommand::Agents => {
+                print_agents(&api);
+                EXIT_OK
+            }
+            CliCommand::Serve { bind, token, agent } => serve(api, bind, token, agent).await,
+            CliCommand::Discord { bind, config } => {
+                let api = share_with_mcp(api, event_bus).await;
+                discord_bot(BotSessions::new(api, storage), bind, config).await
+            }
+            CliCommand::Telegram { config } => {
+                let api = share_with_mcp(api, event_bus).await;
+                telegram_bot(api, storage, config).await
+            }
+            CliCommand::Rpc { socket, agent } => {
+                if let Some(agent) = &agent {
+                    if let Err(e) = api.switch_agent(agent) {
+                        eprintln!("error: {:#}", e);
+                        return EXIT_CONFIG;
+                    }
+                }
+                let api = share_with_mcp(api, Arc::clone(&event_bus)).await;
+                rpc_backend(api, event_bus, socket).await
+            }
+        }
+    })
+}
+
+/// Build a RustbotApi with the configured agents and system instructions
+fn build_api(deps: &AppDependencies, runtime: &Arc<tokio::runtime::Runtime>) -> Result<RustbotApi> {
+    let llm_adapter = deps
+        .llm_adapter
+        .as_ref()
+        .context("No LLM adapter configured (is OPENROUTER_API_KEY set?)")?;
+
+    let mut agent_configs = runtime
+        .block_on(deps.config.load_agent_configs())
+        .context("Failed to load agents")?;
+    if agent_configs.is_empty() {
+        agent_configs.push(AgentConfig::default_assistant());
+    }
+
+    let system_instructions = SettingsPaths::standard()
+        .system_instructions
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .unwrap_or_default();
+
+    let mut builder = RustbotApiBuilder::new()
+        .event_bus(Arc::clone(&deps.event_bus))
+        .runtime(Arc::clone(runtime))
+        .llm_adapter(Arc::clone(llm_adapter))
+        .max_history_size(MAX_HISTORY_SIZE)
+        .system_instructions(system_instructions);
+    for config in agent_configs {
+        builder = builder.add_agent(config);
+    }
+    builder.build()
+}
+
+/// Build the prompt from `ask` arguments
+///
+/// A leading `-` reads the prompt from `stdin`; words after it are an
+/// instruction placed before the piped text. Otherwise the words are joined.
+///
+/// # Errors
+/// - stdin cannot be read or is not UTF-8
+/// - stdin is empty
+pub fn read_prompt(words: &[String], mut stdin: impl Read) -> Result<String> {
+    let Some((first, instruction)) = words.split_first() else {
+        return Ok(String::new());
+    };
+    if first != STDIN_PROMPT {
+        return Ok(words.join(" "));
+    }
+
+    let mut piped = String::new();
+    stdin
+        .read_to_string(&mut piped)
+        .context("Failed to read prompt from stdin")?;
+    if piped.trim().is_empty() {
+        anyhow::bail!("No input on stdin");
+    }
+
+    let instruction = instruction.join(" ");
+    if instruction.is_empty() {
+        Ok(piped)
+    } else {
+        Ok(format!("{}\n\n{}", instruction, piped))
+    }
+}
+
+async fn ask(api: &mut RustbotApi, prompt: &str, options: &ChatOptions) -> i32 {
+    if let Some(agent) = &options.agent {
+        if let Err(e) = api.switch_agent(agent) {
+            eprintln!("error: {:#}", e);
+            return EXIT_CONFIG;
+        }
+    }
+
+    match respond(api, prompt, options).await {
+        Ok(()) => EXIT_OK,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+async fn chat(api: &mut RustbotApi, options: &ChatOptions) -> i32 {
+    if let Some(agent) = &options.agent {
+        if let Err(e) = api.switch_agent(agent) {
+            eprintln!("error: {:#}", e);
+            return EXIT_CONFIG;
+        }
+    }
+
+    let interactive = std::io::stdin().is_terminal();
+    if interactive {
+        eprintln!(
+            "Chatting with '{}'. Type /help for commands, /exit to quit.",
+            api.active_agent()
+        );
+    }
+
+    // Read stdin on a blocking thread so streaming keeps running on the runtime
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut failed = false;
+    loop {
+        if interactive {
+            eprint!("{}> ", api.active_agent());
+            let _ = std::io::stderr().flush();
+        }
+        let Some(line) = rx.recv().await else { break };
+
+        match ReplCommand::parse(&line) {
+            ReplCommand::Empty => {}
+            ReplCommand::Exit => break,
+            ReplCommand::Help => eprintln!("{}", REPL_HELP),
+            ReplCommand::Agents => print_agents(api),
+            ReplCommand::Clear => {
+                api.clear_history();
+                eprintln!("Started a new conversation");
+            }
+            ReplCommand::Agent(id) => match api.switch_agent(&id) {
+                Ok(()) => eprintln!("Switched to '{}'", id),
+                Err(e) => eprintln!("error: {:#}", e),
+            },
+            ReplCommand::Message(message) => {
+                if let Err(e) = respond(api, &message, options).await {
+                    eprintln!("error: {:#}", e);
+                    failed = true;
+                }
+            }
+        }
+    }
+
+    // In a script, any failed message fails the run
+    if failed && !interactive {
+        EXIT_FAILURE
+    } else {
+        EXIT_OK
+    }
+}
+
+async fn serve(
+    mut api: RustbotApi,
+    bind: SocketAddr,
+    token: Option<String>,
+    agent: Option<String>,
+) -> i32 {
+    if let Some(agent) = &agent {
+        if let Err(e) = api.switch_agent(agent) {
+            eprintln!("error: {:#}", e);
+            return EXIT_CONFIG;
+        }
+    }
+
+    let token = match token.or_else(|| std::env::var(server::TOKEN_ENV_VAR).ok()) {
+        Some(token) if !token.is_empty() => token,
+        _ => {
+            let token = server::generate_token();
+            eprintln!("API token: {}", token);
+            token
+        }
+    };
+
+    eprintln!(
+        "Serving Rustbot REST API on http://{} (Ctrl-C to stop)",
+        bind
+    );
+    match server::serve(api, ServerConfig { bind, token }).await {
+        Ok(()) => EXIT_OK,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Serve the JSON-RPC protocol on stdio or a Unix socket
+async fn rpc_backend(
+    api: Arc<Mutex<RustbotApi>>,
+    event_bus: Arc<EventBus>,
+    socket: Option<PathBuf>,
+) -> i32 {
+    let result = match socket {
+        Some(path) => {
+            eprintln!(
+                "Serving Rustbot JSON-RPC on {} (Ctrl-C to stop)",
+                path.display()
+            );
+            rpc::serve_unix(&path, api, event_bus).await
+        }
+        None => {
+            let stdio = tokio::io::join(tokio::io::stdin(), tokio::io::stdout());
+            rpc::serve_connection(stdio, api, event_bus).await
+        }
+    };
+    match result {
+        Ok(()) => EXIT_OK,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Share the API between bot tasks and start enabled MCP plugins
+///
+/// Plugin tools are registered with the API as plugins come up. Failures are
+/// logged; the bot still runs with agent tools only.
+async fn share_with_mcp(api: RustbotApi, event_bus: Arc<EventBus>) -> Arc<Mutex<RustbotApi>> {
+    let api = Arc::new(Mutex::new(api));
+
+    let config_path = SettingsPaths::standard().mcp_config;
+    if !config_path.exists() {
+        return api;
+    }
+
+    let mut manager = McpPluginManager::with_event_bus(Some(event_bus));
+    if let Err(e) = manager.load_config(&config_path).await {
+        tracing::warn!("Failed to load MCP configuration: {}", e);
+        return api;
+    }
+    let manager = Arc::new(Mutex::new(manager));
+    api.lock().await.set_mcp_manager(Arc::clone(&manager));
+    RustbotApi::start_mcp_auto_registration(Arc::clone(&api)).await;
+
+    // Enabled plugins start concurrently
+    for (id, result) in manager.lock().await.start_enabled().await {
+        if let Err(e) = result {
+            tracing::warn!("Failed to start MCP plugin '{}': {}", id, e);
+        }
+    }
+    api
+}
+
+async fn discord_bot(sessions: BotSessions, bind: SocketAddr, config: Option<PathBuf>) -> i32 {
+    let path = config.unwrap_or_else(DiscordConfig::default_path);
+    let config = match DiscordConfig::load(&path) {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            eprintln!("error: no Discord configuration at {}", path.display());
+            return EXIT_CONFIG;
+        }
+        Err(e) => {
+            eprintln!("error: {}: {}", path.display(), e);
+            return EXIT_CONFIG;
+        }
+    };
+
+    eprintln!(
+        "Discord interactions endpoint on http://{}/interactions (Ctrl-C to stop)",
+        bind
+    );
+    match discord::serve(config, sessions, bind).await {
+        Ok(()) => EXIT_OK,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+async fn telegram_bot(
+    api: Arc<Mutex<RustbotApi>>,
+    storage: Arc<dyn StorageService>,
+    config: Option<PathBuf>,
+) -> i32 {
+    let path = config.unwrap_or_else(TelegramConfig::default_path);
+    let config = match TelegramConfig::load(&path) {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            eprintln!("error: no Telegram configuration at {}", path.display());
+            return EXIT_CONFIG;
+        }
+        Err(e) => {
+            eprintln!("error: {}: {}", path.display(), e);
+            return EXIT_CONFIG;
+        }
+    };
+    let token = match std::env::var(telegram::BOT_TOKEN_ENV_VAR) {
+        Ok(token) if !token.is_empty() => token,
+        _ => {
+            eprintln!("error: {} is not set", telegram::BOT_TOKEN_ENV_VAR);
+            return EXIT_CONFIG;
+        }
+    };
+
+    let sessions = BotSessions::new(api, storage).with_default_agent(config.default_agent.clone());
+    eprintln!("Telegram bridge running (Ctrl-C to stop)");
+    match telegram::run(config, &token, sessions).await {
+        Ok(()) => EXIT_OK,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Send a message and write the reply to stdout
+///
+/// With `--json` the reply (or the error) is written as one JSON line; the
+/// error is still returned so the exit code reflects it.
+async fn respond(api: &mut RustbotApi, message: &str, options: &ChatOptions) -> Result<()> {
+    if !options.json {
+        return respond_text(api, message, options.no_stream).await;
+    }
+
+    let calls_before = tool_calls(api);
+    let started = Instant::now();
+    let mut first_token = None;
+    let result = collect_response(api, message, &mut first_token).await;
+
+    let calls_after = tool_calls(api);
+    let mut tools: Vec<String> = calls_after
+        .iter()
+        .filter(|(name, calls)| calls_before.get(*name).copied().unwrap_or(0) < **calls)
+        .map(|(name, _)| name.clone())
+        .collect();
+    tools.sort();
+
+    let output = JsonResponse {
+        agent: api.active_agent().to_string(),
+        response: result.as_ref().ok().cloned(),
+        error: result.as_ref().err().map(|e| format!("{:#}", e)),
+        tools,
+        duration_ms: started.elapsed().as_millis() as u64,
+        first_token_ms: first_token.map(|at| at.duration_since(started).as_millis() as u64),
+    };
+
+    let mut stdout = std::io::stdout();
+    serde_json::to_writer(&mut stdout, &output)?;
+    stdout.write_all(b"\n")?;
+    stdout.flush()?;
+
+    let response = result?;
+    api.add_assistant_response(response);
+    Ok(())
+}
+
+/// Send a message and wait for the complete reply
+///
+/// `first_token` is set to when the first chunk arrived.
+async fn collect_response(
+    api: &mut RustbotApi,
+    message: &str,
+    first_token: &mut Option<Instant>,
+) -> Result<String> {
+    let mut stream = api.send_message(message).await?;
+
+    let mut response = String::new();
+    while let Some(chunk) = stream.recv().await {
+        first_token.get_or_insert_with(Instant::now);
+        response.push_str(&chunk);
+    }
+
+    if response.is_empty() {
+        anyhow::bail!("Agent returned an empty response");
+    }
+    Ok(response)
+}
+
+/// Per-tool call counts, to work out which tools a response used
+fn tool_calls(api: &RustbotApi) -> HashMap<String, u64> {
+    api.tool_metrics()
+        .iter()
+        .map(|(name, metrics)| (name.clone(), metrics.calls))
+        .collect()
+}
+
+/// Send a message and write the reply to stdout as plain text
+async fn respond_text(api: &mut RustbotApi, message: &str, no_stream: bool) -> Result<()> {
+    let mut stream = api.send_message(message).await?;
+
+    let mut stdout = std::io::stdout();
+    let mut response = String::new();
+    while let Some(chunk) = stream.recv().await {
+        if !no_stream {
+            stdout.write_all(chunk.as_bytes())?;
+            stdout.flush()?;
+        }
+        response.push_str(&chunk);
+    }
+
+    if no_stream {
+        stdout.write_all(response.as_bytes())?;
+    }
+    if !response.is_empty() && !response.ends_with('\n') {
+        stdout.write_all(b"\n")?;
+    }
+    stdout.flush()?;
+
+    if response.is_empty() {
+        anyhow::bail!("Agent returned an empty response");
+    }
+    api.add_assistant_response(response);
+    Ok(())
+}
+
+fn print_agents(api: &RustbotApi) {
+    let active = api.active_agent();
+    let mut agents = api.list_agents();
+    agents.sort();
+    for id in agents {
+        let marker = if id == active { "*" } else { " " };
+        println!("{} {}", marker, id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_subcommand_launches_gui() {
+        let cli = Cli::try_parse_from(["rustbot"]).unwrap();
+        assert!(cli.command.is_none());
+    }
+
+    #[test]
+    fn test_ask_joins_prompt_and_parses_flags() {
+        let cli = Cli::try_parse_from([
+            "rustbot",
+            "ask",
+            "--agent",
+            "researcher",
+            "--no-stream",
+            "hello",
+            "there",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Some(CliCommand::Ask { prompt, options }) => {
+                assert_eq!(prompt.join(" "), "hello there");
+                assert_eq!(options.agent.as_deref(), Some("researcher"));
+                assert!(options.no_stream);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_serve_defaults_to_loopback() {
+        let cli = Cli::try_parse_from(["rustbot", "serve"]).unwrap();
+        match cli.command {
+            Some(CliCommand::Serve { bind, token, .. }) => {
+                assert!(bind.ip().is_loopback());
+                assert!(token.is_none());
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_ask_requires_prompt() {
+        assert!(Cli::try_parse_from(["rustbot", "ask"]).is_err());
+    }
+
+    #[test]
+    fn test_top_level_dash_is_pipe_mode() {
+        let cli = Cli::try_parse_from(["rustbot", "--agent", "summarizer", "--json", "-"]).unwrap();
+
+        match cli.into_command().unwrap() {
+            Some(CliCommand::Ask { prompt, options }) => {
+                assert_eq!(prompt, vec!["-"]);
+                assert_eq!(options.agent.as_deref(), Some("summarizer"));
+                assert!(options.json);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_top_level_rejects_stray_words_and_lone_options() {
+        let cli = Cli::try_parse_from(["rustbot", "hello"]).unwrap();
+        assert!(cli.into_command().is_err());
+
+        let cli = Cli::try_parse_from(["rustbot", "--json"]).unwrap();
+        assert!(cli.into_command().is_err());
+
+        let cli = Cli::try_parse_from(["rustbot"]).unwrap();
+        assert!(cli.into_command().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_deep_link_launches_gui() {
+        let cli = Cli::try_parse_from(["rustbot", "rustbot://chat?prompt=hi"]).unwrap();
+        assert_eq!(cli.deep_link(), Some("rustbot://chat?prompt=hi"));
+        assert!(cli.into_command().unwrap().is_none());
+
+        let cli = Cli::try_parse_from(["rustbot", "ask", "rustbot://chat"]).unwrap();
+        assert_eq!(cli.deep_link(), None);
+    }
+
+    #[test]
+    fn test_read_prompt_from_stdin() {
+        let words = |w: &[&str]| w.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let prompt = read_prompt(&words(&["-"]), "notes\n".as_bytes()).unwrap();
+        assert_eq!(prompt, "notes\n");
+
+        let prompt = read_prompt(&words(&["-", "Summarize", "this"]), "notes".as_bytes()).unwrap();
+        assert_eq!(prompt, "Summarize this\n\nnotes");
+
+        // stdin is only read for a leading `-`
+        let prompt = read_prompt(&words(&["what", "is", "-"]), "ignored".as_bytes()).unwrap();
+        assert_eq!(prompt, "what is -");
+
+        assert!(read_prompt(&words(&["-"]), "  \n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_json_response_omits_missing_fields() {
+        let output = JsonResponse {
+            agent: "assistant".to_string(),
+            response: Some("hi".to_string()),
+            error: None,
+            tools: vec![],
+            duration_ms: 12,
+            first_token_ms: None,
+        };
+        let json = serde_json::to_value(&output).unwrap();
+        assert_eq!(json["response"], "hi");
+        assert!(json.get("error").is_none());
+        assert!(json.get("first_token_ms").is_none());
+    }
+
+    #[test]
+    fn test_repl_commands() {
+        assert_eq!(ReplCommand::parse("  "), ReplCommand::Empty);
+        assert_eq!(ReplCommand::parse("/quit"), ReplCommand::Exit);
+        assert_eq!(
+            ReplCommand::parse("/agent researcher"),
+            ReplCommand::Agent("researcher".to_string())
+        );
+        assert_eq!(
+            ReplCommand::parse("what is /etc/hosts?"),
+            ReplCommand::Message("what is /etc/hosts?".to_string())
+        );
+        assert_eq!(
+            ReplCommand::parse("/etc/hosts"),
+            ReplCommand::Message("/etc/hosts".to_string())
+        );
+    }
+}
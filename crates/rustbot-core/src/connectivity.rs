@@ -0,0 +1,169 @@
+// Offline mode: connectivity detection and graceful degradation
+//
+// Design Decision: One process-wide online flag, refreshed by a cheap probe
+// and checked before features that only work with internet access
+//
+// Rationale: Without a connection every hosted request fails slowly and with
+// a confusing error. The GUI probes the provider every `PROBE_INTERVAL` (and
+// right after a request fails) and records the result with `set_online`.
+// While offline:
+// - Features in `OFFLINE_FEATURES` refuse up front with `Offline`, like the
+//   local-only gate in `crate::privacy`, so the chat keeps diagram and
+//   formula source as code and the marketplace says why it's empty
+// - The chat adapter (`crate::llm::OfflineFallbackAdapter`) answers with the
+//   configured local model instead, without web search
+// - With no local model, the GUI queues outgoing messages and sends them
+//   once the probe succeeds again
+//
+// Configuration: The local model is a user profile setting (an Ollama model
+// name, served on localhost); `UserProfile::apply_process_settings` applies
+// it with `set_local_model`.
+//
+// Trade-offs:
+// - The probe only proves the provider is reachable; a single failing host
+//   (mermaid.ink, the MCP registry) still surfaces its own error
+// - Under local-only mode the probe is skipped and Rustbot counts as online:
+//   the privacy gate already explains what's disabled
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::time::Duration;
+use thiserror::Error;
+
+static ONLINE: AtomicBool = AtomicBool::new(true);
+static LOCAL_MODEL: RwLock<Option<String>> = RwLock::new(None);
+
+/// Endpoint the probe connects to (any HTTP response counts as online)
+pub const PROBE_URL: &str = "https://openrouter.ai";
+
+/// How long the probe waits for a response
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the GUI probes connectivity
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Feature names for `check_online`
+pub const MARKETPLACE: &str = "The marketplace";
+pub const DIAGRAMS: &str = "Diagram rendering (mermaid.ink)";
+pub const FORMULAS: &str = "Formula typesetting";
+pub const WEB_SEARCH: &str = "Web search";
+pub const GISTS: &str = "Sharing to a gist";
+
+/// Features that are off while offline, with what the user sees instead
+pub const OFFLINE_FEATURES: &[(&str, &str)] = &[
+    (MARKETPLACE, "the registry can't be browsed"),
+    (DIAGRAMS, "diagrams show as code"),
+    (FORMULAS, "formulas show as TeX"),
+    (WEB_SEARCH, "agents answer without searching"),
+    (GISTS, "conversations can still be shared as a file"),
+];
+
+/// A feature refused because Rustbot is offline
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{feature} is unavailable until Rustbot is back online")]
+pub struct Offline {
+    /// Feature that needs the connection (one of `OFFLINE_FEATURES`)
+    pub feature: &'static str,
+}
+
+/// Whether the last probe reached the provider
+pub fn is_online() -> bool {
+    ONLINE.load(Ordering::SeqCst)
+}
+
+/// Record the result of a connectivity probe
+///
+/// # Returns
+/// true if the state changed
+pub fn set_online(online: bool) -> bool {
+    let changed = ONLINE.swap(online, Ordering::SeqCst) != online;
+    if changed {
+        if online {
+            tracing::info!("🌐 Back online");
+        } else {
+            tracing::warn!("📴 Offline: the provider can't be reached");
+        }
+    }
+    changed
+}
+
+/// Check that a feature needing internet access may run
+///
+/// # Errors
+/// - Rustbot is offline
+pub fn check_online(feature: &'static str) -> Result<(), Offline> {
+    if is_online() {
+        Ok(())
+    } else {
+        Err(Offline { feature })
+    }
+}
+
+/// Set the local model that answers while offline (None or empty = queue
+/// messages instead)
+pub fn set_local_model(model: Option<String>) {
+    if let Ok(mut local) = LOCAL_MODEL.write() {
+        *local = model
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty());
+    }
+}
+
+/// The local model that answers while offline, if one is configured
+pub fn local_model() -> Option<String> {
+    LOCAL_MODEL.read().ok().and_then(|model| model.clone())
+}
+
+/// Whether chat messages can be answered right now: online, or offline
+/// with a local model
+pub fn can_chat() -> bool {
+    is_online() || local_model().is_some()
+}
+
+/// Probe the provider and record whether it's reachable
+///
+/// # Returns
+/// Whether Rustbot is online now
+pub async fn refresh() -> bool {
+    if crate::privacy::check_url(PROBE_URL).is_err() {
+        set_online(true);
+        return true;
+    }
+    let online = crate::llm::shared_client()
+        .head(PROBE_URL)
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+        .is_ok();
+    set_online(online);
+    online
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One test: the state is process-wide, so parallel tests would race
+    #[test]
+    fn test_offline_state() {
+        set_local_model(None);
+        set_online(true);
+        assert!(check_online(DIAGRAMS).is_ok());
+        assert!(can_chat());
+
+        assert!(set_online(false));
+        assert!(!set_online(false));
+        let err = check_online(MARKETPLACE).unwrap_err();
+        assert_eq!(err.feature, MARKETPLACE);
+        assert!(!can_chat());
+
+        set_local_model(Some("  ".to_string()));
+        assert_eq!(local_model(), None);
+        set_local_model(Some(" llama3.2 ".to_string()));
+        assert_eq!(local_model().as_deref(), Some("llama3.2"));
+        assert!(can_chat());
+
+        set_local_model(None);
+        assert!(set_online(true));
+    }
+}
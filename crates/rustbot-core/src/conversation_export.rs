@@ -0,0 +1,547 @@
+// Conversation export to Markdown, HTML and JSON
+//
+// Design Decision: Export from a ConversationSession, interleaving tool activity
+//
+// Rationale: The chat view shows what the user saw (including diagrams that
+// were rendered to embedded images), while the LLM history holds the tool
+// calls and tool results behind each answer. A useful export needs both, so
+// the exporter walks the displayed messages and slots each turn's tool
+// activity in front of the assistant reply it led to.
+//
+// Turn Alignment:
+// - History is split into turns at each user message
+// - History is trimmed from the front, so turns are matched to displayed
+//   user messages counting from the end
+//
+// Trade-offs:
+// - HTML output is a self-contained page with pre-wrapped text rather than a
+//   full markdown rendering; embedded images (diagrams) become <img> tags and
+//   fenced code blocks are highlighted with inline colors (no scripts or
+//   stylesheets to fetch, so the file can be shared as is); without the
+//   `highlight` feature code blocks are plain <pre> text
+// - JSON is a stable, documented structure rather than the raw session file
+//
+// Extension Points: Add PDF output or per-message filtering here.
+
+use crate::error::{Result, RustbotError};
+use crate::llm::Message as LlmMessage;
+use crate::markdown::{split_code_blocks, MarkdownSegment};
+use crate::services::traits::{ConversationSession, MessageFeedback, Rating, SessionMessage};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+#[cfg(feature = "highlight")]
+use std::sync::OnceLock;
+#[cfg(feature = "highlight")]
+use syntect::highlighting::ThemeSet;
+#[cfg(feature = "highlight")]
+use syntect::html::highlighted_html_for_string;
+#[cfg(feature = "highlight")]
+use syntect::parsing::SyntaxSet;
+
+/// Output format for conversation exports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConversationFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+impl ConversationFormat {
+    /// File extension (without the dot)
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+            Self::Json => "json",
+        }
+    }
+
+    /// Human-readable name for UI selectors
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Markdown => "Markdown",
+            Self::Html => "HTML",
+            Self::Json => "JSON",
+        }
+    }
+}
+
+/// One item in the exported transcript
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExportEntry {
+    /// A user or assistant message as displayed
+    Message {
+        role: String,
+        content: String,
+        timestamp: DateTime<Utc>,
+        /// The user's rating (assistant messages)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        feedback: Option<MessageFeedback>,
+    },
+    /// A tool the assistant invoked
+    ToolCall {
+        id: String,
+        name: String,
+        arguments: serde_json::Value,
+    },
+    /// The result returned to the assistant
+    ToolResult {
+        tool_call_id: Option<String>,
+        content: String,
+    },
+}
+
+/// Exported conversation (also the JSON output schema)
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationExport {
+    pub title: String,
+    pub agent_id: String,
+    pub created_at: DateTime<Utc>,
+    pub exported_at: DateTime<Utc>,
+    pub entries: Vec<ExportEntry>,
+}
+
+impl ConversationExport {
+    /// Build the transcript from a session
+    pub fn from_session(session: &ConversationSession) -> Self {
+        let turns = tool_activity_by_turn(&session.history);
+        let user_count = session.messages.iter().filter(|m| m.role == "user").count();
+        // Turns missing from the (trimmed) history belong to the oldest messages
+        let skipped = user_count.saturating_sub(turns.len());
+
+        let mut entries = Vec::new();
+        let mut user_index = 0;
+        for message in &session.messages {
+            if message.role == "user" {
+                entries.push(message_entry(message));
+                if user_index >= skipped {
+                    entries.extend(turns[user_index - skipped].iter().cloned());
+                }
+                user_index += 1;
+            } else {
+                entries.push(message_entry(message));
+            }
+        }
+
+        Self {
+            title: if session.title.is_empty() {
+                "Conversation".to_string()
+            } else {
+                session.title.clone()
+            },
+            agent_id: session.agent_id.clone(),
+            created_at: session.created_at,
+            exported_at: Utc::now(),
+            entries,
+        }
+    }
+
+    /// Render the transcript in the requested format
+    ///
+    /// # Errors
+    /// - JSON serialization failure
+    pub fn render(&self, format: ConversationFormat) -> Result<String> {
+        match format {
+            ConversationFormat::Markdown => Ok(self.to_markdown()),
+            ConversationFormat::Html => Ok(self.to_html()),
+            ConversationFormat::Json => serde_json::to_string_pretty(self).map_err(|e| {
+                RustbotError::StorageError(format!("Failed to serialize conversation: {}", e))
+            }),
+        }
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# {}\n\n_Agent: {} · Started {} · Exported {}_\n\n",
+            self.title,
+            self.agent_id,
+            format_time(self.created_at),
+            format_time(self.exported_at)
+        );
+
+        for entry in &self.entries {
+            match entry {
+                ExportEntry::Message {
+                    role,
+                    content,
+                    timestamp,
+                    feedback,
+                } => {
+                    out.push_str(&format!(
+                        "## {} ({})\n\n{}\n\n",
+                        role_label(role),
+                        format_time(*timestamp),
+                        content
+                    ));
+                    if let Some(feedback) = feedback {
+                        out.push_str(&format!("> {}\n\n", feedback_label(feedback)));
+                    }
+                }
+                ExportEntry::ToolCall {
+                    name, arguments, ..
+                } => {
+                    out.push_str(&format!(
+                        "**🔧 Tool call:** `{}`\n\n```json\n{}\n```\n\n",
+                        name,
+                        serde_json::to_string_pretty(arguments).unwrap_or_default()
+                    ));
+                }
+                ExportEntry::ToolResult { content, .. } => {
+                    out.push_str(&format!("**Tool result:**\n\n```\n{}\n```\n\n", content));
+                }
+            }
+        }
+
+        out
+    }
+
+    fn to_html(&self) -> String {
+        let mut body = String::new();
+
+        for entry in &self.entries {
+            match entry {
+                ExportEntry::Message {
+                    role,
+                    content,
+                    timestamp,
+                    feedback,
+                } => {
+                    let feedback = feedback
+                        .as_ref()
+                        .map(|f| {
+                            format!(
+                                "<p class=\"feedback\">{}</p>\n",
+                                escape_html(&feedback_label(f))
+                            )
+                        })
+                        .unwrap_or_default();
+                    body.push_str(&format!(
+                        "<section class=\"message {}\">\n<h2>{} <small>{}</small></h2>\n<div class=\"content\">{}</div>\n{}</section>\n",
+                        escape_html(role),
+                        role_label(role),
+                        format_time(*timestamp),
+                        content_to_html(content),
+                        feedback
+                    ));
+                }
+                ExportEntry::ToolCall {
+                    name, arguments, ..
+                } => {
+                    body.push_str(&format!(
+                        "<section class=\"tool\">\n<h3>🔧 Tool call: <code>{}</code></h3>\n<pre>{}</pre>\n</section>\n",
+                        escape_html(name),
+                        escape_html(&serde_json::to_string_pretty(arguments).unwrap_or_default())
+                    ));
+                }
+                ExportEntry::ToolResult { content, .. } => {
+                    body.push_str(&format!(
+                        "<section class=\"tool\">\n<h3>Tool result</h3>\n<pre>{}</pre>\n</section>\n",
+                        escape_html(content)
+                    ));
+                }
+            }
+        }
+
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n{style}\n</style>\n</head>\n<body>\n<h1>{title}</h1>\n<p class=\"meta\">Agent: {agent} · Started {started} · Exported {exported}</p>\n{body}</body>\n</html>\n",
+            title = escape_html(&self.title),
+            style = HTML_STYLE,
+            agent = escape_html(&self.agent_id),
+            started = format_time(self.created_at),
+            exported = format_time(self.exported_at),
+            body = body
+        )
+    }
+}
+
+/// Minimal stylesheet for HTML exports
+const HTML_STYLE: &str = "body { font-family: sans-serif; max-width: 900px; margin: 2em auto; }
+.meta, small { color: #777; font-weight: normal; }
+.message { border-bottom: 1px solid #ddd; padding: 0.5em 0; }
+.content { white-space: pre-wrap; }
+.content img { max-width: 100%; }
+.content pre { padding: 0.5em 1em; overflow-x: auto; }
+.tool { background: #f6f6f6; padding: 0.5em 1em; margin: 0.5em 0; }
+.feedback { color: #555; font-style: italic; }
+pre { white-space: pre-wrap; }";
+
+fn message_entry(message: &SessionMessage) -> ExportEntry {
+    ExportEntry::Message {
+        role: message.role.clone(),
+        content: message.content.clone(),
+        timestamp: message.timestamp,
+        feedback: message.feedback.clone(),
+    }
+}
+
+/// "👍 Rated helpful: comment" or "👎 Rated unhelpful"
+fn feedback_label(feedback: &MessageFeedback) -> String {
+    let rating = match feedback.rating {
+        Rating::Up => "👍 Rated helpful",
+        Rating::Down => "👎 Rated unhelpful",
+    };
+    if feedback.comment.trim().is_empty() {
+        rating.to_string()
+    } else {
+        format!("{}: {}", rating, feedback.comment.trim())
+    }
+}
+
+/// Collect tool calls and results for each user turn in the LLM history
+fn tool_activity_by_turn(history: &[LlmMessage]) -> Vec<Vec<ExportEntry>> {
+    let mut turns: Vec<Vec<ExportEntry>> = Vec::new();
+
+    for message in history {
+        if message.role == "user" {
+            turns.push(Vec::new());
+            continue;
+        }
+        let Some(turn) = turns.last_mut() else {
+            // Tool activity whose user message was trimmed away
+            continue;
+        };
+
+        if let Some(tool_calls) = &message.tool_calls {
+            turn.extend(tool_calls.iter().map(|call| ExportEntry::ToolCall {
+                id: call.id.clone(),
+                name: call.name.clone(),
+                arguments: call.arguments.clone(),
+            }));
+        } else if message.role == "tool" {
+            turn.push(ExportEntry::ToolResult {
+                tool_call_id: message.tool_call_id.clone(),
+                content: message.content.clone(),
+            });
+        }
+    }
+
+    turns
+}
+
+fn role_label(role: &str) -> &str {
+    match role {
+        "user" => "You",
+        "assistant" => "Assistant",
+        other => other,
+    }
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Message content as HTML: prose is escaped, fenced code blocks are
+/// highlighted
+fn content_to_html(content: &str) -> String {
+    split_code_blocks(content)
+        .into_iter()
+        .map(|segment| match segment {
+            MarkdownSegment::Text(text) => prose_to_html(text),
+            MarkdownSegment::Code { language, code, .. } => highlight_code(code, language),
+        })
+        .collect()
+}
+
+/// Code block with inline highlighting colors (plain text for unknown
+/// languages)
+#[cfg(feature = "highlight")]
+fn highlight_code(code: &str, language: Option<&str>) -> String {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+
+    let syntaxes = SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines);
+    let theme = &THEMES.get_or_init(ThemeSet::load_defaults).themes["InspiredGitHub"];
+    let syntax = language
+        .and_then(|language| syntaxes.find_syntax_by_token(language))
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+    highlighted_html_for_string(code, syntaxes, syntax, theme)
+        .unwrap_or_else(|_| format!("<pre>{}</pre>", escape_html(code)))
+}
+
+/// Code block as plain text (built without `highlight`)
+#[cfg(not(feature = "highlight"))]
+fn highlight_code(code: &str, _language: Option<&str>) -> String {
+    format!("<pre>{}</pre>", escape_html(code))
+}
+
+/// Escape prose, turning embedded data-URL images into <img> tags
+fn prose_to_html(content: &str) -> String {
+    let image_pattern = Regex::new(r#"!\[([^\]]*)\]\((data:image/[^;]+;base64,[A-Za-z0-9+/=]+)\)"#)
+        .expect("Invalid regex pattern");
+
+    let mut html = String::new();
+    let mut last = 0;
+    for cap in image_pattern.captures_iter(content) {
+        let whole = cap.get(0).expect("capture 0 always present");
+        html.push_str(&escape_html(&content[last..whole.start()]));
+        html.push_str(&format!(
+            "<img alt=\"{}\" src=\"{}\">",
+            escape_html(&cap[1]),
+            &cap[2]
+        ));
+        last = whole.end();
+    }
+    html.push_str(&escape_html(&content[last..]));
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ToolCall;
+
+    fn message(role: &str, content: &str) -> SessionMessage {
+        SessionMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: Utc::now(),
+            input_tokens: None,
+            output_tokens: None,
+            agent_id: None,
+            model: None,
+            first_token_ms: None,
+            generation_ms: None,
+            pinned: false,
+            feedback: None,
+        }
+    }
+
+    fn session_with_tool_call() -> ConversationSession {
+        let mut session = ConversationSession::new("assistant");
+        session.messages = vec![
+            message("user", "old question"),
+            message("assistant", "old answer"),
+            message("user", "What's the weather?"),
+            message("assistant", "Sunny ![chart](data:image/png;base64,AAAA)"),
+        ];
+        session.update_title();
+        // Oldest turn was trimmed from the LLM history
+        session.history = vec![
+            LlmMessage::new("user", "What's the weather?"),
+            LlmMessage::with_tool_calls(
+                String::new(),
+                vec![ToolCall {
+                    id: "call_1".to_string(),
+                    name: "web_search".to_string(),
+                    arguments: serde_json::json!({"query": "weather"}),
+                }],
+            ),
+            LlmMessage::tool_result("call_1".to_string(), "72°F".to_string()),
+            LlmMessage::new("assistant", "Sunny"),
+        ];
+        session
+    }
+
+    #[test]
+    fn test_tool_activity_aligned_with_latest_turn() {
+        let export = ConversationExport::from_session(&session_with_tool_call());
+
+        assert_eq!(export.entries.len(), 6);
+        assert!(matches!(
+            &export.entries[1],
+            ExportEntry::Message { content, .. } if content == "old answer"
+        ));
+        assert!(matches!(
+            &export.entries[3],
+            ExportEntry::ToolCall { name, .. } if name == "web_search"
+        ));
+        assert!(matches!(
+            &export.entries[4],
+            ExportEntry::ToolResult { content, .. } if content == "72°F"
+        ));
+    }
+
+    #[test]
+    fn test_markdown_includes_tool_calls() {
+        let markdown = ConversationExport::from_session(&session_with_tool_call())
+            .render(ConversationFormat::Markdown)
+            .unwrap();
+
+        assert!(markdown.starts_with("# old question"));
+        assert!(markdown.contains("**🔧 Tool call:** `web_search`"));
+        assert!(markdown.contains("\"query\": \"weather\""));
+    }
+
+    #[test]
+    fn test_html_escapes_content_and_keeps_images() {
+        let mut session = session_with_tool_call();
+        session
+            .messages
+            .push(message("user", "<script>alert(1)</script>"));
+
+        let html = ConversationExport::from_session(&session)
+            .render(ConversationFormat::Html)
+            .unwrap();
+
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("<img alt=\"chart\" src=\"data:image/png;base64,AAAA\">"));
+    }
+
+    #[test]
+    #[cfg(feature = "highlight")]
+    fn test_html_highlights_code_blocks() {
+        let mut session = session_with_tool_call();
+        session.messages.push(message(
+            "assistant",
+            "Try this:\n\n```rust\nfn main() {}\n```\n\nand <b>this</b>:\n\n```\na < b\n```\n",
+        ));
+
+        let html = ConversationExport::from_session(&session)
+            .render(ConversationFormat::Html)
+            .unwrap();
+
+        assert!(html.contains("Try this:"));
+        assert!(html.contains("and &lt;b&gt;this&lt;/b&gt;:"));
+        // The keyword starts a styled span (syntect may keep the space after
+        // it, or more of the line, in the same span)
+        let keyword = regex::Regex::new(r#"<span style="[^"]+">fn\b"#).unwrap();
+        assert!(keyword.is_match(&html));
+        // Unknown language: plain but still escaped
+        assert!(html.contains("a &lt; b"));
+    }
+
+    #[test]
+    fn test_json_export_schema() {
+        let json = ConversationExport::from_session(&session_with_tool_call())
+            .render(ConversationFormat::Json)
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["agent_id"], "assistant");
+        assert_eq!(value["entries"][3]["type"], "tool_call");
+        assert_eq!(value["entries"][3]["arguments"]["query"], "weather");
+        assert!(value["entries"][1].get("feedback").is_none());
+    }
+
+    #[test]
+    fn test_exports_include_feedback() {
+        let mut session = session_with_tool_call();
+        let mut feedback = MessageFeedback::new(Rating::Down);
+        feedback.comment = "Forgot the units".to_string();
+        session.messages[3].feedback = Some(feedback);
+        let export = ConversationExport::from_session(&session);
+
+        let markdown = export.render(ConversationFormat::Markdown).unwrap();
+        assert!(markdown.contains("> 👎 Rated unhelpful: Forgot the units"));
+
+        let json = export.render(ConversationFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["entries"][5]["feedback"]["rating"], "down");
+        assert_eq!(
+            value["entries"][5]["feedback"]["comment"],
+            "Forgot the units"
+        );
+    }
+}
@@ -0,0 +1,348 @@
+// Import conversations from ChatGPT and Claude data exports
+//
+// Design Decision: Lenient parsing over serde_json::Value
+//
+// Rationale: Neither vendor documents their export schema and both have
+// changed it over time (extra content types, null timestamps, new fields).
+// Walking a Value and keeping only what we understand — text from user and
+// assistant turns — imports old and new exports alike instead of failing on
+// the first unexpected field.
+//
+// Supported Formats (the `conversations.json` inside each export archive):
+// - OpenAI: array of conversations with a `mapping` message tree; the active
+//   branch is recovered by walking `parent` links back from `current_node`
+// - Anthropic: array of conversations with a flat `chat_messages` list
+//
+// Trade-offs:
+// - Attachments, images and tool activity are skipped (text only)
+// - Edited/regenerated ChatGPT branches other than the active one are dropped
+//
+// Extension Points: Add importers for other tools by recognizing their
+// conversation shape in `import_value`.
+
+use crate::error::{Result, RustbotError};
+use crate::llm::Message as LlmMessage;
+use crate::services::traits::{ConversationSession, SessionMessage};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::path::Path;
+
+/// Source application of an export file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    ChatGpt,
+    Claude,
+}
+
+impl ImportSource {
+    /// Short name used in session IDs and agent labels
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ChatGpt => "chatgpt",
+            Self::Claude => "claude",
+        }
+    }
+}
+
+/// Read an export file and convert every conversation into a session
+///
+/// # Errors
+/// - File cannot be read or is not JSON
+/// - Content is not a recognized ChatGPT or Claude export
+pub fn import_file(path: &Path) -> Result<Vec<ConversationSession>> {
+    let content = std::fs::read_to_string(path)?;
+    let value: Value = serde_json::from_str(&content)?;
+    import_value(&value)
+}
+
+/// Convert a parsed export into sessions
+///
+/// Conversations without any text messages are skipped.
+///
+/// # Errors
+/// - Not an array of conversations in a recognized format
+pub fn import_value(value: &Value) -> Result<Vec<ConversationSession>> {
+    let conversations = value.as_array().ok_or_else(|| {
+        RustbotError::StorageError("Expected an array of conversations".to_string())
+    })?;
+
+    let mut sessions = Vec::new();
+    for conversation in conversations {
+        let session = if conversation.get("mapping").is_some() {
+            import_chatgpt(conversation)
+        } else if conversation.get("chat_messages").is_some() {
+            import_claude(conversation)
+        } else {
+            return Err(RustbotError::StorageError(
+                "Unrecognized export format (expected ChatGPT or Claude conversations.json)"
+                    .to_string(),
+            ));
+        };
+
+        if let Some(session) = session {
+            sessions.push(session);
+        }
+    }
+
+    Ok(sessions)
+}
+
+/// Convert one ChatGPT conversation (message tree) into a session
+fn import_chatgpt(conversation: &Value) -> Option<ConversationSession> {
+    let mapping = conversation.get("mapping")?.as_object()?;
+    let created_at = unix_time(conversation.get("create_time")).unwrap_or_else(Utc::now);
+
+    // Walk the active branch from the current node back to the root
+    let mut node_id = conversation
+        .get("current_node")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let mut branch = Vec::new();
+    while let Some(id) = node_id {
+        let Some(node) = mapping.get(&id) else {
+            break;
+        };
+        branch.push(node);
+        node_id = node
+            .get("parent")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        if branch.len() > mapping.len() {
+            // Malformed export with a parent cycle
+            break;
+        }
+    }
+    branch.reverse();
+
+    let mut messages = Vec::new();
+    for node in branch {
+        let Some(message) = node.get("message") else {
+            continue;
+        };
+        let role = message
+            .pointer("/author/role")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if role != "user" && role != "assistant" {
+            continue;
+        }
+
+        let text = message
+            .pointer("/content/parts")
+            .and_then(Value::as_array)
+            .map(|parts| {
+                parts
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default();
+
+        let timestamp = unix_time(message.get("create_time")).unwrap_or(created_at);
+        messages.push((role.to_string(), text, timestamp));
+    }
+
+    build_session(
+        ImportSource::ChatGpt,
+        conversation.get("id").and_then(Value::as_str),
+        conversation.get("title").and_then(Value::as_str),
+        created_at,
+        unix_time(conversation.get("update_time")),
+        messages,
+    )
+}
+
+/// Convert one Claude conversation (flat message list) into a session
+fn import_claude(conversation: &Value) -> Option<ConversationSession> {
+    let created_at = iso_time(conversation.get("created_at")).unwrap_or_else(Utc::now);
+
+    let mut messages = Vec::new();
+    for message in conversation.get("chat_messages")?.as_array()? {
+        let role = match message.get("sender").and_then(Value::as_str) {
+            Some("human") => "user",
+            Some("assistant") => "assistant",
+            _ => continue,
+        };
+
+        // Newer exports split text into typed content blocks
+        let text = match message.get("content").and_then(Value::as_array) {
+            Some(blocks) if !blocks.is_empty() => blocks
+                .iter()
+                .filter(|b| b.get("type").and_then(Value::as_str) == Some("text"))
+                .filter_map(|b| b.get("text").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => message
+                .get("text")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+        };
+
+        let timestamp = iso_time(message.get("created_at")).unwrap_or(created_at);
+        messages.push((role.to_string(), text, timestamp));
+    }
+
+    build_session(
+        ImportSource::Claude,
+        conversation.get("uuid").and_then(Value::as_str),
+        conversation.get("name").and_then(Value::as_str),
+        created_at,
+        iso_time(conversation.get("updated_at")),
+        messages,
+    )
+}
+
+/// Assemble a session from (role, text, timestamp) triples
+fn build_session(
+    source: ImportSource,
+    original_id: Option<&str>,
+    title: Option<&str>,
+    created_at: DateTime<Utc>,
+    updated_at: Option<DateTime<Utc>>,
+    messages: Vec<(String, String, DateTime<Utc>)>,
+) -> Option<ConversationSession> {
+    let messages: Vec<(String, String, DateTime<Utc>)> = messages
+        .into_iter()
+        .filter(|(_, text, _)| !text.trim().is_empty())
+        .collect();
+    if messages.is_empty() {
+        return None;
+    }
+
+    let mut session = ConversationSession::new(format!("imported-{}", source.as_str()));
+    // Stable ID so importing the same export twice overwrites instead of duplicating
+    let original_id = original_id
+        .map(sanitize_id)
+        .unwrap_or_else(|| created_at.timestamp_millis().to_string());
+    session.id = format!("import-{}-{}", source.as_str(), original_id);
+    session.title = title.unwrap_or_default().trim().to_string();
+    session.created_at = created_at;
+    session.updated_at = updated_at
+        .or_else(|| messages.last().map(|(_, _, t)| *t))
+        .unwrap_or(created_at);
+
+    for (role, text, timestamp) in messages {
+        session
+            .history
+            .push(LlmMessage::new(role.clone(), text.clone()));
+        session.messages.push(SessionMessage {
+            role,
+            content: text,
+            timestamp,
+            input_tokens: None,
+            output_tokens: None,
+            agent_id: None,
+            model: None,
+            first_token_ms: None,
+            generation_ms: None,
+            pinned: false,
+            feedback: None,
+        });
+    }
+    session.update_title();
+
+    Some(session)
+}
+
+/// Keep only characters allowed in session IDs
+fn sanitize_id(id: &str) -> String {
+    id.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect()
+}
+
+/// Parse a Unix timestamp in (fractional) seconds
+fn unix_time(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    let seconds = value?.as_f64()?;
+    DateTime::from_timestamp_millis((seconds * 1000.0) as i64)
+}
+
+/// Parse an RFC 3339 timestamp
+fn iso_time(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value?.as_str()?)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_import_chatgpt_follows_active_branch() {
+        let export = json!([{
+            "id": "abc-123",
+            "title": "Rust lifetimes",
+            "create_time": 1700000000.5,
+            "update_time": 1700000100.0,
+            "current_node": "n3",
+            "mapping": {
+                "root": {"id": "root", "message": null, "parent": null},
+                "n1": {"id": "n1", "parent": "root", "message": {
+                    "author": {"role": "user"},
+                    "create_time": 1700000001.0,
+                    "content": {"content_type": "text", "parts": ["What is 'a?"]}
+                }},
+                "n2-old": {"id": "n2-old", "parent": "n1", "message": {
+                    "author": {"role": "assistant"},
+                    "content": {"content_type": "text", "parts": ["Discarded draft"]}
+                }},
+                "n2": {"id": "n2", "parent": "n1", "message": {
+                    "author": {"role": "system"},
+                    "content": {"content_type": "text", "parts": ["hidden"]}
+                }},
+                "n3": {"id": "n3", "parent": "n2", "message": {
+                    "author": {"role": "assistant"},
+                    "content": {"content_type": "text", "parts": ["A lifetime."]}
+                }}
+            }
+        }]);
+
+        let sessions = import_value(&export).unwrap();
+        assert_eq!(sessions.len(), 1);
+
+        let session = &sessions[0];
+        assert_eq!(session.id, "import-chatgpt-abc-123");
+        assert_eq!(session.title, "Rust lifetimes");
+        assert_eq!(session.agent_id, "imported-chatgpt");
+        assert_eq!(session.messages.len(), 2);
+        assert_eq!(session.messages[0].content, "What is 'a?");
+        assert_eq!(session.messages[1].content, "A lifetime.");
+        assert_eq!(session.history.len(), 2);
+    }
+
+    #[test]
+    fn test_import_claude_content_blocks_and_text() {
+        let export = json!([{
+            "uuid": "c0ffee",
+            "name": "",
+            "created_at": "2024-05-01T10:00:00Z",
+            "updated_at": "2024-05-01T10:05:00Z",
+            "chat_messages": [
+                {"sender": "human", "text": "Hello Claude", "created_at": "2024-05-01T10:00:00Z"},
+                {"sender": "assistant", "text": "", "created_at": "2024-05-01T10:00:05Z",
+                 "content": [{"type": "text", "text": "Hi!"}, {"type": "tool_use", "name": "x"}]}
+            ]
+        }]);
+
+        let sessions = import_value(&export).unwrap();
+        let session = &sessions[0];
+        assert_eq!(session.id, "import-claude-c0ffee");
+        // Untitled conversations fall back to the first user message
+        assert_eq!(session.title, "Hello Claude");
+        assert_eq!(session.messages[1].content, "Hi!");
+        assert_eq!(session.messages[1].role, "assistant");
+    }
+
+    #[test]
+    fn test_import_skips_empty_and_rejects_unknown() {
+        let empty = json!([{"uuid": "x", "chat_messages": []}]);
+        assert!(import_value(&empty).unwrap().is_empty());
+
+        assert!(import_value(&json!([{"foo": "bar"}])).is_err());
+        assert!(import_value(&json!({"conversations": []})).is_err());
+    }
+}
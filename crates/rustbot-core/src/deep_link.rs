@@ -0,0 +1,311 @@
+// rustbot:// deep links for launchers and automation tools
+//
+// Design Decision: A custom URL scheme that opens the chat with a prompt
+//
+// Rationale: Shortcuts, Alfred, Raycast and plain HTML links can all open
+// URLs, but none of them can drive the IPC socket. A link like
+//     rustbot://chat?prompt=Summarize%20my%20clipboard&agent=researcher
+// brings the app to the front, switches agent and pre-fills the message box;
+// adding `send=1` asks in the app whether to send it (see `DeepLink::steps`).
+//
+// Delivery:
+// - Linux/Windows: the OS starts `rustbot <url>`. If an instance is already
+//   running, the new process forwards the link over the IPC socket
+//   (`open_link` command) and exits; otherwise it launches the app with the
+//   link applied after startup. Windows has no IPC socket yet, so each link
+//   opens a new window there.
+// - macOS: the OS sends a kAEGetURL Apple Event to the running bundle instead
+//   of passing arguments; `install_url_event_handler` queues those links for
+//   the UI to pick up with `take_pending`.
+//
+// Registration: the app bundle declares the scheme (`osx_url_schemes` in
+// Cargo.toml) and Linux desktops use assets/rustbot.desktop
+// (MimeType=x-scheme-handler/rustbot).
+//
+// Trade-offs:
+// - Any web page can offer a rustbot:// link, and browsers only ask once
+//   whether to open the app. A link therefore never sends by itself: it can
+//   fill in the message box, and `send=1` only brings up a confirmation, so
+//   a prompt (and the tool calls it leads to) runs only when the user says so
+// - Unknown query parameters are ignored so older builds accept newer links
+
+use anyhow::{bail, Context, Result};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// URL scheme registered with the OS
+pub const SCHEME: &str = "rustbot";
+
+/// Event bus request method the UI answers to open a link (params: DeepLink)
+pub const OPEN_METHOD: &str = "open_deep_link";
+
+/// A parsed rustbot:// link
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeepLink {
+    /// Text for the message box
+    pub prompt: Option<String>,
+
+    /// Agent to switch to before the prompt is used
+    pub agent: Option<String>,
+
+    /// Offer to send the prompt (the user still confirms in the app)
+    pub send: bool,
+}
+
+/// One thing the app does to open a link, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkStep {
+    /// Make this agent the chat's agent
+    SwitchAgent(String),
+
+    /// Put this text into the message box
+    Prefill(String),
+
+    /// Ask the user whether to send the pre-filled message
+    ConfirmSend,
+}
+
+impl DeepLink {
+    /// Parse a rustbot:// URL
+    ///
+    /// Supported forms:
+    /// - `rustbot://chat?prompt=...&agent=...&send=1`
+    /// - `rustbot://open` (or just `rustbot://`) to bring the app to the front
+    ///
+    /// # Errors
+    /// - Not a valid URL or not the rustbot scheme
+    /// - Unknown action (host)
+    /// - `send` requested without a prompt
+    pub fn parse(url: &str) -> Result<Self> {
+        let url = Url::parse(url.trim()).context("Invalid link")?;
+        if url.scheme() != SCHEME {
+            bail!("Not a {}:// link", SCHEME);
+        }
+
+        // `rustbot://chat?...` puts the action in the host, `rustbot:chat?...`
+        // in the path
+        let action = url
+            .host_str()
+            .unwrap_or_else(|| url.path())
+            .trim_matches('/')
+            .to_lowercase();
+
+        match action.as_str() {
+            "" | "open" => Ok(Self::default()),
+            "chat" => {
+                let mut link = Self::default();
+                for (key, value) in url.query_pairs() {
+                    match key.as_ref() {
+                        "prompt" => link.prompt = non_empty(&value),
+                        "agent" => link.agent = non_empty(&value),
+                        "send" => link.send = is_truthy(&value),
+                        _ => {}
+                    }
+                }
+                if link.send && link.prompt.is_none() {
+                    bail!("send=1 needs a prompt");
+                }
+                Ok(link)
+            }
+            other => bail!("Unknown link action '{}'", other),
+        }
+    }
+
+    /// What opening the link does, in order
+    ///
+    /// There is deliberately no step that sends: with `send` the prompt is
+    /// pre-filled and the user is asked, and only their confirmation sends.
+    pub fn steps(&self) -> Vec<LinkStep> {
+        let mut steps = Vec::new();
+        if let Some(agent) = &self.agent {
+            steps.push(LinkStep::SwitchAgent(agent.clone()));
+        }
+        if let Some(prompt) = &self.prompt {
+            steps.push(LinkStep::Prefill(prompt.clone()));
+            if self.send {
+                steps.push(LinkStep::ConfirmSend);
+            }
+        }
+        steps
+    }
+}
+
+/// Whether a command-line argument is a rustbot:// link
+pub fn is_deep_link(arg: &str) -> bool {
+    arg.get(..SCHEME.len() + 1)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}:", SCHEME)))
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn is_truthy(value: &str) -> bool {
+    matches!(
+        value.trim().to_lowercase().as_str(),
+        "" | "1" | "true" | "yes"
+    )
+}
+
+/// Links delivered by the OS while the app runs, waiting for the UI
+static PENDING: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Queue a link URL for the UI (see `take_pending`)
+pub fn queue_link(url: String) {
+    if let Ok(mut pending) = PENDING.lock() {
+        pending.push(url);
+    }
+}
+
+/// Take all queued link URLs, oldest first
+pub fn take_pending() -> Vec<String> {
+    PENDING
+        .lock()
+        .map(|mut pending| std::mem::take(&mut *pending))
+        .unwrap_or_default()
+}
+
+/// Receive rustbot:// links sent as Apple Events (macOS)
+///
+/// Must be called on the main thread. The handler queues each URL with
+/// `queue_link`. Calling this again replaces the previous handler, so the app
+/// registers once before the event loop starts (to catch the link that
+/// launched it) and again once it's running, in case AppKit installed its own
+/// handler while finishing launch.
+#[cfg(all(target_os = "macos", feature = "apple-events"))]
+pub fn install_url_event_handler() {
+    apple_events::install();
+}
+
+/// Other platforms receive links as command-line arguments (as does macOS
+/// without the `apple-events` feature)
+#[cfg(not(all(target_os = "macos", feature = "apple-events")))]
+pub fn install_url_event_handler() {}
+
+#[cfg(all(target_os = "macos", feature = "apple-events"))]
+mod apple_events {
+    use objc2::rc::Retained;
+    use objc2::runtime::{AnyObject, NSObject};
+    use objc2::{declare_class, msg_send_id, mutability, sel, ClassType, DeclaredClass};
+    use objc2_foundation::{NSAppleEventDescriptor, NSAppleEventManager};
+
+    // Four-character codes from <CoreServices/AE/AppleEvents.h>
+    const INTERNET_EVENT_CLASS: u32 = u32::from_be_bytes(*b"GURL");
+    const GET_URL_EVENT_ID: u32 = u32::from_be_bytes(*b"GURL");
+    const DIRECT_OBJECT_KEYWORD: u32 = u32::from_be_bytes(*b"----");
+
+    declare_class!(
+        struct UrlEventHandler;
+
+        unsafe impl ClassType for UrlEventHandler {
+            type Super = NSObject;
+            type Mutability = mutability::InteriorMutable;
+            const NAME: &'static str = "RustbotUrlEventHandler";
+        }
+
+        impl DeclaredClass for UrlEventHandler {}
+
+        unsafe impl UrlEventHandler {
+            #[method(handleGetURLEvent:withReplyEvent:)]
+            fn handle_get_url(
+                &self,
+                event: &NSAppleEventDescriptor,
+                _reply: &NSAppleEventDescriptor,
+            ) {
+                let url = unsafe { event.paramDescriptorForKeyword(DIRECT_OBJECT_KEYWORD) }
+                    .and_then(|descriptor| unsafe { descriptor.stringValue() });
+                if let Some(url) = url {
+                    tracing::info!("🔗 Received link via Apple Event");
+                    super::queue_link(url.to_string());
+                }
+            }
+        }
+    );
+
+    pub fn install() {
+        let handler: Retained<UrlEventHandler> =
+            unsafe { msg_send_id![UrlEventHandler::alloc(), init] };
+        let target: &AnyObject = &handler;
+        unsafe {
+            NSAppleEventManager::sharedAppleEventManager()
+                .setEventHandler_andSelector_forEventClass_andEventID(
+                    target,
+                    sel!(handleGetURLEvent:withReplyEvent:),
+                    INTERNET_EVENT_CLASS,
+                    GET_URL_EVENT_ID,
+                );
+        }
+        // The event manager doesn't retain its handlers
+        std::mem::forget(handler);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chat_link() {
+        let link = DeepLink::parse("rustbot://chat?prompt=What%27s+new%3F&agent=researcher&send=1")
+            .unwrap();
+        assert_eq!(link.prompt.as_deref(), Some("What's new?"));
+        assert_eq!(link.agent.as_deref(), Some("researcher"));
+        assert!(link.send);
+
+        let link = DeepLink::parse("rustbot:chat?prompt=hi&utm_source=x").unwrap();
+        assert_eq!(link.prompt.as_deref(), Some("hi"));
+        assert!(!link.send);
+    }
+
+    #[test]
+    fn test_send_link_only_asks() {
+        let link = DeepLink::parse("rustbot://chat?prompt=Email%20my%20files&agent=writer&send=1")
+            .unwrap();
+        assert_eq!(
+            link.steps(),
+            [
+                LinkStep::SwitchAgent("writer".to_string()),
+                LinkStep::Prefill("Email my files".to_string()),
+                LinkStep::ConfirmSend,
+            ]
+        );
+        // Without a prompt there's nothing to fill in or send
+        assert!(DeepLink::parse("rustbot://open")
+            .unwrap()
+            .steps()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_parse_open_and_errors() {
+        assert_eq!(DeepLink::parse("rustbot://").unwrap(), DeepLink::default());
+        assert_eq!(
+            DeepLink::parse("rustbot://open").unwrap(),
+            DeepLink::default()
+        );
+
+        assert!(DeepLink::parse("https://chat?prompt=hi").is_err());
+        assert!(DeepLink::parse("rustbot://delete-everything").is_err());
+        assert!(DeepLink::parse("rustbot://chat?send=1").is_err());
+        assert!(DeepLink::parse("rustbot://chat?prompt=hi&send=no")
+            .map(|link| !link.send)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_is_deep_link() {
+        assert!(is_deep_link("rustbot://chat"));
+        assert!(is_deep_link("RustBot:chat"));
+        assert!(!is_deep_link("rustbotx"));
+        assert!(!is_deep_link("-"));
+    }
+
+    #[test]
+    fn test_pending_queue() {
+        queue_link("rustbot://open".to_string());
+        assert!(take_pending().contains(&"rustbot://open".to_string()));
+        assert!(take_pending().is_empty());
+    }
+}
@@ -0,0 +1,696 @@
+// Discord bot mode (`rustbot discord`)
+//
+// Design Decision: HTTP interactions endpoint instead of a gateway connection
+//
+// Rationale: Slash commands can be delivered to an HTTPS endpoint as signed
+// webhooks, which fits the axum server we already run for `rustbot serve` and
+// needs no websocket client or long-lived gateway session. Replies are
+// deferred (Discord allows 3 seconds for the first response) and then streamed
+// by repeatedly editing the original response as chunks arrive.
+//
+// Setup:
+// 1. Create an application at https://discord.com/developers/applications
+// 2. Put its ID and public key in ~/.rustbot/discord.json (see `DiscordConfig`)
+// 3. Export DISCORD_BOT_TOKEN so slash commands are registered on startup
+// 4. Run `rustbot discord` and expose it over HTTPS (reverse proxy or tunnel),
+//    then set "Interactions Endpoint URL" to https://<host>/interactions
+//
+// Slash commands:
+//     /ask prompt:<text>   ask the channel's agent (streams the answer)
+//     /reset               start a new conversation in this channel
+//     /agents              list available agents
+//
+// Conversations are per channel and saved like desktop chats (see
+// `bot_sessions`), so they appear in the History view.
+//
+// Security:
+// - Every request is verified against the application's Ed25519 public key
+// - Guilds can be limited to specific channels
+//
+// Trade-offs:
+// - Edits are rate limited to one per `edit_interval_ms`
+// - Responses over 2000 characters continue in follow-up messages
+
+use crate::bot_sessions::{split_message, BotSessions};
+use crate::error::{Result as RustbotResult, RustbotError};
+use anyhow::{Context, Result};
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+/// Default listen address for the interactions endpoint
+pub const DEFAULT_BIND: &str = "127.0.0.1:8788";
+
+/// Environment variable holding the bot token (used to register commands)
+pub const BOT_TOKEN_ENV_VAR: &str = "DISCORD_BOT_TOKEN";
+
+/// Discord REST API base URL
+const API_BASE: &str = "https://discord.com/api/v10";
+
+/// Maximum message length accepted by Discord
+pub const MAX_MESSAGE_LEN: usize = 2000;
+
+/// Interaction types
+const INTERACTION_PING: u8 = 1;
+const INTERACTION_COMMAND: u8 = 2;
+
+/// Interaction response types
+const RESPONSE_PONG: u8 = 1;
+const RESPONSE_MESSAGE: u8 = 4;
+const RESPONSE_DEFERRED: u8 = 5;
+
+/// Message flag: only visible to the user who ran the command
+const FLAG_EPHEMERAL: u32 = 1 << 6;
+
+fn default_edit_interval_ms() -> u64 {
+    1000
+}
+
+/// Discord bot configuration
+///
+/// File Format: JSON
+/// Location: ~/.rustbot/discord.json
+///
+/// Example:
+///     {
+///       "application_id": "123456789012345678",
+///       "public_key": "<64 hex characters from the developer portal>",
+///       "default_agent": "assistant",
+///       "guilds": {
+///         "987654321098765432": {
+///           "agent": "researcher",
+///           "channels": ["111111111111111111"]
+///         }
+///       }
+///     }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscordConfig {
+    /// Application (client) ID
+    pub application_id: String,
+
+    /// Application public key (hex), used to verify interactions
+    pub public_key: String,
+
+    /// Agent for DMs and servers without their own setting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_agent: Option<String>,
+
+    /// Per-server settings keyed by guild ID
+    #[serde(default)]
+    pub guilds: HashMap<String, GuildConfig>,
+
+    /// Minimum time between streaming edits of a response
+    #[serde(default = "default_edit_interval_ms")]
+    pub edit_interval_ms: u64,
+}
+
+/// Settings for one Discord server
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GuildConfig {
+    /// Agent that answers in this server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+
+    /// Channel IDs the bot answers in (empty = all channels)
+    #[serde(default)]
+    pub channels: Vec<String>,
+}
+
+impl DiscordConfig {
+    /// Default config location: ~/.rustbot/discord.json
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("discord.json")
+    }
+
+    /// Load configuration from a file
+    ///
+    /// # Returns
+    /// Ok(None) if the file doesn't exist, Ok(Some) if loaded
+    ///
+    /// # Errors
+    /// - File exists but cannot be read
+    /// - Invalid JSON
+    /// - Public key is not a valid Ed25519 key
+    pub fn load(path: &Path) -> RustbotResult<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        let config: Self = serde_json::from_str(&content)?;
+        config.verifying_key()?;
+        Ok(Some(config))
+    }
+
+    /// Parse the application public key
+    pub fn verifying_key(&self) -> RustbotResult<VerifyingKey> {
+        let bytes: [u8; 32] = decode_hex(&self.public_key)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                RustbotError::ConfigError(
+                    "Discord public_key must be 64 hex characters".to_string(),
+                )
+            })?;
+        VerifyingKey::from_bytes(&bytes)
+            .map_err(|e| RustbotError::ConfigError(format!("Invalid Discord public_key: {}", e)))
+    }
+
+    /// Agent that answers in a guild (None = API default)
+    pub fn agent_for(&self, guild_id: Option<&str>) -> Option<&str> {
+        guild_id
+            .and_then(|id| self.guilds.get(id))
+            .and_then(|guild| guild.agent.as_deref())
+            .or(self.default_agent.as_deref())
+    }
+
+    /// Whether the bot may answer in a channel
+    pub fn channel_allowed(&self, guild_id: Option<&str>, channel_id: Option<&str>) -> bool {
+        match guild_id.and_then(|id| self.guilds.get(id)) {
+            Some(guild) if !guild.channels.is_empty() => {
+                channel_id.is_some_and(|channel| guild.channels.iter().any(|c| c == channel))
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Incoming interaction (only the fields we use)
+#[derive(Debug, Deserialize)]
+struct Interaction {
+    #[serde(rename = "type")]
+    kind: u8,
+    #[serde(default)]
+    token: String,
+    guild_id: Option<String>,
+    channel_id: Option<String>,
+    data: Option<CommandData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandData {
+    name: String,
+    #[serde(default)]
+    options: Vec<CommandOption>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandOption {
+    name: String,
+    value: serde_json::Value,
+}
+
+impl Interaction {
+    /// String value of a command option
+    fn option(&self, name: &str) -> Option<&str> {
+        self.data
+            .as_ref()?
+            .options
+            .iter()
+            .find(|o| o.name == name)?
+            .value
+            .as_str()
+    }
+
+    /// Conversation key: one conversation per channel
+    fn conversation_key(&self) -> String {
+        format!(
+            "discord-{}-{}",
+            self.guild_id.as_deref().unwrap_or("dm"),
+            self.channel_id.as_deref().unwrap_or("unknown")
+        )
+    }
+}
+
+/// Slash command definitions registered with Discord
+pub fn command_definitions() -> serde_json::Value {
+    serde_json::json!([
+        {
+            "name": "ask",
+            "description": "Ask Rustbot a question",
+            "options": [{
+                "type": 3,
+                "name": "prompt",
+                "description": "Your question",
+                "required": true
+            }]
+        },
+        { "name": "reset", "description": "Start a new conversation in this channel" },
+        { "name": "agents", "description": "List available agents" }
+    ])
+}
+
+/// Minimal Discord REST client for command registration and replies
+#[derive(Clone)]
+pub struct DiscordClient {
+    http: reqwest::Client,
+    application_id: String,
+}
+
+impl DiscordClient {
+    pub fn new(application_id: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            application_id: application_id.to_string(),
+        }
+    }
+
+    /// Register (overwrite) the global slash commands
+    pub async fn register_commands(&self, bot_token: &str) -> Result<()> {
+        crate::privacy::check_url(API_BASE)?;
+        self.http
+            .put(format!(
+                "{}/applications/{}/commands",
+                API_BASE, self.application_id
+            ))
+            .header("Authorization", format!("Bot {}", bot_token))
+            .json(&command_definitions())
+            .send()
+            .await?
+            .error_for_status()
+            .context("Discord rejected the command registration")?;
+        Ok(())
+    }
+
+    /// Replace the content of the deferred response
+    pub async fn edit_original(&self, interaction_token: &str, content: &str) -> Result<()> {
+        crate::privacy::check_url(API_BASE)?;
+        self.http
+            .patch(format!(
+                "{}/webhooks/{}/{}/messages/@original",
+                API_BASE, self.application_id, interaction_token
+            ))
+            .json(&serde_json::json!({ "content": content }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Post an additional message after the original response
+    pub async fn follow_up(&self, interaction_token: &str, content: &str) -> Result<()> {
+        crate::privacy::check_url(API_BASE)?;
+        self.http
+            .post(format!(
+                "{}/webhooks/{}/{}",
+                API_BASE, self.application_id, interaction_token
+            ))
+            .json(&serde_json::json!({ "content": content }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Shared state for the interactions handler
+#[derive(Clone)]
+pub struct DiscordState {
+    config: Arc<DiscordConfig>,
+    verifying_key: VerifyingKey,
+    sessions: Arc<BotSessions>,
+    client: DiscordClient,
+}
+
+impl DiscordState {
+    /// # Errors
+    /// Invalid public key in the configuration
+    pub fn new(config: DiscordConfig, sessions: BotSessions) -> RustbotResult<Self> {
+        Ok(Self {
+            verifying_key: config.verifying_key()?,
+            client: DiscordClient::new(&config.application_id),
+            config: Arc::new(config),
+            sessions: Arc::new(sessions),
+        })
+    }
+}
+
+/// Build the router (exposed for tests and embedding)
+pub fn router(state: DiscordState) -> Router {
+    Router::new()
+        .route("/interactions", post(interactions))
+        .with_state(state)
+}
+
+/// Register slash commands and serve the interactions endpoint until Ctrl-C
+///
+/// # Errors
+/// - Invalid configuration
+/// - Address cannot be bound
+/// - Server fails while running
+pub async fn serve(config: DiscordConfig, sessions: BotSessions, bind: SocketAddr) -> Result<()> {
+    let state = DiscordState::new(config, sessions)?;
+
+    match std::env::var(BOT_TOKEN_ENV_VAR) {
+        Ok(token) if !token.is_empty() => match state.client.register_commands(&token).await {
+            Ok(()) => tracing::info!("Registered Discord slash commands"),
+            Err(e) => tracing::warn!("Failed to register Discord slash commands: {:#}", e),
+        },
+        _ => tracing::warn!(
+            "{} not set, slash commands were not registered",
+            BOT_TOKEN_ENV_VAR
+        ),
+    }
+
+    let listener = tokio::net::TcpListener::bind(bind)
+        .await
+        .with_context(|| format!("Failed to bind {}", bind))?;
+    tracing::info!(
+        "🤖 Discord interactions endpoint on http://{}/interactions",
+        bind
+    );
+
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+            tracing::info!("Shutting down Discord bot");
+        })
+        .await
+        .context("Discord interactions server failed")
+}
+
+/// Verify the Ed25519 signature Discord puts on every interaction
+pub fn verify_signature(key: &VerifyingKey, headers: &HeaderMap, body: &[u8]) -> bool {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let (Some(signature), Some(timestamp)) = (
+        header("x-signature-ed25519"),
+        header("x-signature-timestamp"),
+    ) else {
+        return false;
+    };
+    let Some(signature) = decode_hex(signature).and_then(|b| <[u8; 64]>::try_from(b).ok()) else {
+        return false;
+    };
+
+    let mut message = timestamp.as_bytes().to_vec();
+    message.extend_from_slice(body);
+    key.verify(&message, &Signature::from_bytes(&signature))
+        .is_ok()
+}
+
+async fn interactions(
+    State(state): State<DiscordState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if !verify_signature(&state.verifying_key, &headers, &body) {
+        return (StatusCode::UNAUTHORIZED, "invalid request signature").into_response();
+    }
+
+    let interaction: Interaction = match serde_json::from_slice(&body) {
+        Ok(interaction) => interaction,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    match interaction.kind {
+        INTERACTION_PING => Json(serde_json::json!({ "type": RESPONSE_PONG })).into_response(),
+        INTERACTION_COMMAND => Json(handle_command(&state, interaction).await).into_response(),
+        other => (
+            StatusCode::BAD_REQUEST,
+            format!("unsupported interaction type {}", other),
+        )
+            .into_response(),
+    }
+}
+
+/// Run a slash command and return the initial interaction response
+async fn handle_command(state: &DiscordState, interaction: Interaction) -> serde_json::Value {
+    let guild_id = interaction.guild_id.as_deref();
+    if !state
+        .config
+        .channel_allowed(guild_id, interaction.channel_id.as_deref())
+    {
+        return ephemeral("Rustbot isn't enabled in this channel.");
+    }
+
+    let command = interaction
+        .data
+        .as_ref()
+        .map(|d| d.name.as_str())
+        .unwrap_or_default();
+    match command {
+        "ask" => {
+            let Some(prompt) = interaction.option("prompt").map(str::to_string) else {
+                return ephemeral("Missing prompt.");
+            };
+            let agent = state.config.agent_for(guild_id).map(str::to_string);
+            let key = interaction.conversation_key();
+            let token = interaction.token.clone();
+
+            let state = state.clone();
+            tokio::spawn(async move {
+                answer(state, key, agent, prompt, token).await;
+            });
+            serde_json::json!({ "type": RESPONSE_DEFERRED })
+        }
+        "reset" => {
+            state.sessions.reset(&interaction.conversation_key()).await;
+            ephemeral("Started a new conversation in this channel.")
+        }
+        "agents" => {
+            let active = state.config.agent_for(guild_id);
+            let lines: Vec<String> = state
+                .sessions
+                .agents()
+                .await
+                .into_iter()
+                .map(|id| {
+                    let marker = if Some(id.as_str()) == active {
+                        " (this server)"
+                    } else {
+                        ""
+                    };
+                    format!("• {}{}", id, marker)
+                })
+                .collect();
+            if lines.is_empty() {
+                return ephemeral("No agents are configured.");
+            }
+            ephemeral(&lines.join("\n"))
+        }
+        other => ephemeral(&format!("Unknown command /{}", other)),
+    }
+}
+
+/// Send the prompt and stream the answer into the deferred response
+async fn answer(
+    state: DiscordState,
+    key: String,
+    agent: Option<String>,
+    prompt: String,
+    token: String,
+) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let interval = Duration::from_millis(state.config.edit_interval_ms);
+    let editor = tokio::spawn(stream_edits(
+        state.client.clone(),
+        token.clone(),
+        rx,
+        interval,
+    ));
+
+    let result = state
+        .sessions
+        .send(&key, agent.as_deref(), &prompt, Some(tx))
+        .await;
+    let _ = editor.await;
+
+    let parts = match result {
+        Ok(response) => split_message(&response, MAX_MESSAGE_LEN),
+        Err(e) => {
+            tracing::warn!("Discord request in {} failed: {:#}", key, e);
+            vec![format!("⚠️ {:#}", e)]
+        }
+    };
+
+    let mut parts = parts.into_iter();
+    if let Some(first) = parts.next() {
+        if let Err(e) = state.client.edit_original(&token, &first).await {
+            tracing::warn!("Failed to update Discord response: {:#}", e);
+        }
+    }
+    for part in parts {
+        if let Err(e) = state.client.follow_up(&token, &part).await {
+            tracing::warn!("Failed to send Discord follow-up: {:#}", e);
+            break;
+        }
+    }
+}
+
+/// Edit the response with the text so far, at most once per interval
+async fn stream_edits(
+    client: DiscordClient,
+    token: String,
+    mut chunks: mpsc::UnboundedReceiver<String>,
+    interval: Duration,
+) {
+    let mut text = String::new();
+    let mut dirty = false;
+    let mut next_edit = Instant::now() + interval;
+
+    loop {
+        tokio::select! {
+            chunk = chunks.recv() => match chunk {
+                Some(chunk) => {
+                    text.push_str(&chunk);
+                    dirty = true;
+                }
+                None => break,
+            },
+            _ = tokio::time::sleep_until(next_edit), if dirty => {
+                if let Err(e) = client.edit_original(&token, &preview(&text)).await {
+                    tracing::debug!("Streaming edit failed: {:#}", e);
+                }
+                dirty = false;
+                next_edit = Instant::now() + interval;
+            }
+        }
+    }
+}
+
+/// Text shown while streaming: the start of the response plus a cursor
+fn preview(text: &str) -> String {
+    let limit = MAX_MESSAGE_LEN - 2;
+    let mut preview: String = text.chars().take(limit).collect();
+    preview.push_str(" ▌");
+    preview
+}
+
+fn ephemeral(content: &str) -> serde_json::Value {
+    serde_json::json!({
+        "type": RESPONSE_MESSAGE,
+        "data": { "content": content, "flags": FLAG_EPHEMERAL }
+    })
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::RustbotApi;
+    use crate::events::EventBus;
+    use crate::services::mocks::test_helpers::create_mock_storage;
+    use axum::body::Body;
+    use axum::http::Request as HttpRequest;
+    use ed25519_dalek::{Signer, SigningKey};
+    use tower::ServiceExt;
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[7u8; 32])
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn config() -> DiscordConfig {
+        serde_json::from_value(serde_json::json!({
+            "application_id": "1",
+            "public_key": hex(signing_key().verifying_key().as_bytes()),
+            "default_agent": "assistant",
+            "guilds": {
+                "10": { "agent": "researcher", "channels": ["20"] }
+            }
+        }))
+        .unwrap()
+    }
+
+    fn call(body: &str, sign_with: Option<&SigningKey>) -> (StatusCode, serde_json::Value) {
+        let runtime = Arc::new(tokio::runtime::Runtime::new().unwrap());
+        let api = RustbotApi::new(Arc::new(EventBus::new()), Arc::clone(&runtime), 20);
+        let sessions = BotSessions::new(
+            Arc::new(tokio::sync::Mutex::new(api)),
+            Arc::new(create_mock_storage()),
+        );
+        let app = router(DiscordState::new(config(), sessions).unwrap());
+
+        let timestamp = "1700000000";
+        let mut request =
+            HttpRequest::post("/interactions").header("x-signature-timestamp", timestamp);
+        if let Some(key) = sign_with {
+            let signature = key.sign(format!("{}{}", timestamp, body).as_bytes());
+            request = request.header("x-signature-ed25519", hex(&signature.to_bytes()));
+        }
+        let request = request.body(Body::from(body.to_string())).unwrap();
+
+        runtime.block_on(async {
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+            (status, body)
+        })
+    }
+
+    #[test]
+    fn test_ping_with_valid_signature() {
+        let (status, body) = call(r#"{"type": 1}"#, Some(&signing_key()));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["type"], RESPONSE_PONG);
+    }
+
+    #[test]
+    fn test_rejects_bad_or_missing_signature() {
+        let other = SigningKey::from_bytes(&[9u8; 32]);
+        assert_eq!(
+            call(r#"{"type": 1}"#, Some(&other)).0,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(call(r#"{"type": 1}"#, None).0, StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_command_in_disallowed_channel_is_ephemeral() {
+        let body = r#"{"type": 2, "token": "t", "guild_id": "10", "channel_id": "99",
+                       "data": {"name": "ask", "options": [{"name": "prompt", "type": 3, "value": "hi"}]}}"#;
+        let (status, body) = call(body, Some(&signing_key()));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["type"], RESPONSE_MESSAGE);
+        assert_eq!(body["data"]["flags"], FLAG_EPHEMERAL);
+    }
+
+    #[test]
+    fn test_per_guild_agent_and_channels() {
+        let config = config();
+        assert_eq!(config.agent_for(Some("10")), Some("researcher"));
+        assert_eq!(config.agent_for(Some("11")), Some("assistant"));
+        assert_eq!(config.agent_for(None), Some("assistant"));
+
+        assert!(config.channel_allowed(Some("10"), Some("20")));
+        assert!(!config.channel_allowed(Some("10"), Some("21")));
+        assert!(config.channel_allowed(Some("11"), Some("21")));
+        assert!(config.channel_allowed(None, Some("dm")));
+    }
+
+    #[test]
+    fn test_invalid_public_key_is_rejected() {
+        let mut config = config();
+        config.public_key = "abc".to_string();
+        assert!(config.verifying_key().is_err());
+    }
+}
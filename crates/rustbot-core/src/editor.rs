@@ -0,0 +1,280 @@
+// Editor companion: code context in, patches out
+//
+// Design Decision: Editor plugins send context over JSON-RPC, replies carry
+// parsed patches
+//
+// Rationale: Editor plugins (see editors/neovim) already drive `rustbot rpc`.
+// They know the current file and selection; the agent doesn't. The plugin
+// sends that context with `editor.context`, asks with `editor.ask`, and gets
+// back the response plus any code changes pulled out of it, so applying a
+// suggestion is one command instead of copy and paste:
+// - ```diff blocks become `CodePatch`es (unified diffs, applied with
+//   `git apply` or `patch -p1` from the project root)
+// - With a selection, a single other code block becomes the `replacement`
+//   for the selected lines
+//
+// Trade-offs:
+// - Large files are truncated (`MAX_FILE_CHARS`) to keep requests affordable;
+//   the selection is always sent in full
+// - Patches are parsed from Markdown, so a model that ignores the format
+//   instructions yields a plain answer with no patches
+
+use serde::{Deserialize, Serialize};
+
+/// File content beyond this many characters is cut off
+pub const MAX_FILE_CHARS: usize = 24_000;
+
+/// What the editor is looking at
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EditorContext {
+    /// Current file, relative to `root` when possible
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// Editor language ID (e.g. "rust", "lua")
+    #[serde(default)]
+    pub language: Option<String>,
+
+    /// Project root (where patches are applied)
+    #[serde(default)]
+    pub root: Option<String>,
+
+    /// Full text of the current file
+    #[serde(default)]
+    pub content: Option<String>,
+
+    /// Selected text
+    #[serde(default)]
+    pub selection: Option<Selection>,
+}
+
+/// A selected line range (1-based, inclusive)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Selection {
+    pub text: String,
+    pub start_line: u32,
+    pub end_line: u32,
+}
+
+/// A unified diff from the response
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CodePatch {
+    /// Target file from the `+++` header (without the `b/` prefix)
+    pub path: Option<String>,
+
+    /// The diff text
+    pub diff: String,
+}
+
+/// Code changes found in a response
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EditorEdits {
+    pub patches: Vec<CodePatch>,
+
+    /// New text for the selection (only when the request had one)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+}
+
+impl EditorContext {
+    /// Whether there's anything to tell the agent
+    pub fn is_empty(&self) -> bool {
+        self.path.is_none() && self.content.is_none() && self.selection.is_none()
+    }
+
+    /// The message sent to the agent: context, request and reply format
+    pub fn build_prompt(&self, request: &str) -> String {
+        if self.is_empty() {
+            return request.to_string();
+        }
+
+        let language = self.language.as_deref().unwrap_or("");
+        let mut prompt = String::from("I'm working in my code editor.\n");
+
+        if let Some(path) = &self.path {
+            match &self.language {
+                Some(language) => {
+                    prompt.push_str(&format!("Current file: {} ({})\n", path, language))
+                }
+                None => prompt.push_str(&format!("Current file: {}\n", path)),
+            }
+        }
+        if let Some(content) = &self.content {
+            let (content, truncated) = truncate(content, MAX_FILE_CHARS);
+            prompt.push_str(&format!(
+                "\nFile content:\n```{}\n{}\n```\n",
+                language, content
+            ));
+            if truncated {
+                prompt.push_str(&format!(
+                    "(truncated after {} characters)\n",
+                    MAX_FILE_CHARS
+                ));
+            }
+        }
+        if let Some(selection) = &self.selection {
+            prompt.push_str(&format!(
+                "\nSelected lines {}-{}:\n```{}\n{}\n```\n",
+                selection.start_line, selection.end_line, language, selection.text
+            ));
+        }
+
+        prompt.push_str(&format!("\n{}\n\n", request.trim()));
+        prompt.push_str(
+            "If you change code, show each change as a unified diff in a ```diff block \
+             with paths relative to the project root",
+        );
+        if self.selection.is_some() {
+            prompt.push_str(
+                ", or, to change only the selected lines, reply with exactly one code block \
+                 containing their new text",
+            );
+        }
+        prompt.push('.');
+        prompt
+    }
+
+    /// Pull patches (and a selection replacement) out of a response
+    pub fn extract_edits(&self, response: &str) -> EditorEdits {
+        let blocks = code_blocks(response);
+        let patches: Vec<CodePatch> = blocks
+            .iter()
+            .filter(|(lang, _)| lang == "diff" || lang == "patch")
+            .map(|(_, body)| CodePatch {
+                path: diff_target(body),
+                diff: format!("{}\n", body),
+            })
+            .collect();
+
+        let replacement = match (&self.selection, patches.is_empty()) {
+            (Some(_), true) => match blocks.as_slice() {
+                [(_, body)] => Some(body.clone()),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        EditorEdits {
+            patches,
+            replacement,
+        }
+    }
+}
+
+/// Fenced code blocks as (language, body)
+fn code_blocks(text: &str) -> Vec<(String, String)> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(String, Vec<&str>)> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match current.take() {
+            None => {
+                if let Some(info) = trimmed.strip_prefix("```") {
+                    let lang = info.split_whitespace().next().unwrap_or("");
+                    current = Some((lang.to_lowercase(), Vec::new()));
+                }
+            }
+            Some((lang, body)) if trimmed.trim_end() == "```" => {
+                blocks.push((lang, body.join("\n")));
+            }
+            Some((lang, mut body)) => {
+                body.push(line);
+                current = Some((lang, body));
+            }
+        }
+    }
+    blocks
+}
+
+/// File a diff applies to, from its `+++ b/path` header
+fn diff_target(diff: &str) -> Option<String> {
+    let header = diff.lines().find_map(|line| line.strip_prefix("+++ "))?;
+    let path = header.split('\t').next()?.trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix("b/").unwrap_or(path).to_string())
+}
+
+/// Cut text to at most `max` characters
+fn truncate(text: &str, max: usize) -> (&str, bool) {
+    match text.char_indices().nth(max) {
+        Some((index, _)) => (&text[..index], true),
+        None => (text, false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selection_context() -> EditorContext {
+        EditorContext {
+            path: Some("src/lib.rs".to_string()),
+            language: Some("rust".to_string()),
+            selection: Some(Selection {
+                text: "fn add(a: i32, b: i32) -> i32 { a - b }".to_string(),
+                start_line: 3,
+                end_line: 3,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_prompt_includes_context() {
+        let prompt = selection_context().build_prompt("Fix the bug");
+        assert!(prompt.contains("Current file: src/lib.rs (rust)"));
+        assert!(prompt.contains("Selected lines 3-3:\n```rust\nfn add"));
+        assert!(prompt.contains("Fix the bug"));
+        assert!(prompt.contains("exactly one code block"));
+
+        assert_eq!(EditorContext::default().build_prompt("hi"), "hi");
+    }
+
+    #[test]
+    fn test_large_files_are_truncated() {
+        let context = EditorContext {
+            content: Some("é".repeat(MAX_FILE_CHARS + 10)),
+            ..Default::default()
+        };
+        let prompt = context.build_prompt("Summarize");
+        assert!(prompt.contains("(truncated after"));
+        assert_eq!(prompt.matches('é').count(), MAX_FILE_CHARS);
+    }
+
+    #[test]
+    fn test_extract_patches() {
+        let response = concat!(
+            "Here you go:\n\n```diff\n",
+            "--- a/src/lib.rs\n",
+            "+++ b/src/lib.rs\n",
+            "@@ -3 +3 @@\n",
+            "-fn add(a: i32, b: i32) -> i32 { a - b }\n",
+            "+fn add(a: i32, b: i32) -> i32 { a + b }\n",
+            "```\n",
+        );
+        let edits = selection_context().extract_edits(response);
+
+        assert_eq!(edits.patches.len(), 1);
+        assert_eq!(edits.patches[0].path.as_deref(), Some("src/lib.rs"));
+        assert!(edits.patches[0].diff.ends_with("{ a + b }\n"));
+        assert_eq!(edits.replacement, None);
+    }
+
+    #[test]
+    fn test_extract_selection_replacement() {
+        let response = "```rust\nfn add(a: i32, b: i32) -> i32 { a + b }\n```";
+        let edits = selection_context().extract_edits(response);
+        assert!(edits.patches.is_empty());
+        assert_eq!(
+            edits.replacement.as_deref(),
+            Some("fn add(a: i32, b: i32) -> i32 { a + b }")
+        );
+
+        // Without a selection a code block is just part of the answer
+        let edits = EditorContext::default().extract_edits(response);
+        assert_eq!(edits, EditorEdits::default());
+    }
+}
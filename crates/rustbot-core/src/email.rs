@@ -0,0 +1,674 @@
+// Email connector - read an IMAP inbox and send replies over SMTP as agent tools
+//
+// Design Decision: Built-in tools with a per-send approval round trip to the UI
+//
+// Rationale: Reading mail is harmless, but an agent sending mail on the user's
+// behalf is not something to leave to the model. Every `send_email` call asks
+// the desktop app for approval over the event bus (`EventBus::request` to
+// "user" with method `APPROVAL_METHOD`); the UI shows the draft and answers
+// true or false. Nothing is sent without an explicit yes, and an unanswered
+// request counts as a no.
+//
+// Setup:
+// 1. Describe the account in ~/.rustbot/email.json (see `EmailConfig`)
+// 2. Store the password (or app password) in the keychain or environment as
+//    RUSTBOT_EMAIL_PASSWORD
+//
+// Tools (offered to the primary agent):
+//     read_inbox { limit?, unread_only? }             newest messages first
+//     send_email { to, subject, body, in_reply_to? }  asks the user first
+//
+// Trade-offs:
+// - IMAP uses the blocking `imap` crate on a blocking thread; one connection
+//   per call keeps the code simple at the cost of a TLS handshake each time
+// - Messages are fetched with BODY.PEEK[] so reading never marks them as seen
+// - Only the plain text part is returned, truncated to `max_body_chars`
+// - Port 465 uses implicit TLS, any other SMTP port uses STARTTLS
+//
+// Extension Points:
+// - Let the user edit the draft in the approval dialog
+// - Add search and folder listing tools
+
+use crate::agent::tools::{FunctionDefinition, FunctionParameters};
+use crate::agent::ToolDefinition;
+use crate::error::Result as RustbotResult;
+use crate::events::{EventBus, EventError};
+use anyhow::{Context, Result};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use mail_parser::MessageParser;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Name of the account password secret (keychain entry or environment variable)
+pub const PASSWORD_SECRET: &str = "RUSTBOT_EMAIL_PASSWORD";
+
+/// Tool that lists recent inbox messages
+pub const READ_INBOX_TOOL: &str = "read_inbox";
+
+/// Tool that sends an email after the user approves it
+pub const SEND_EMAIL_TOOL: &str = "send_email";
+
+/// Event bus request method the UI answers with true (send) or false
+pub const APPROVAL_METHOD: &str = "approve_email";
+
+/// How long a send waits for the user before giving up
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Default and maximum number of messages returned by read_inbox
+const DEFAULT_INBOX_LIMIT: usize = 10;
+const MAX_INBOX_LIMIT: usize = 50;
+
+fn default_imap_port() -> u16 {
+    993
+}
+
+fn default_smtp_port() -> u16 {
+    465
+}
+
+fn default_mailbox() -> String {
+    "INBOX".to_string()
+}
+
+fn default_max_body_chars() -> usize {
+    4000
+}
+
+/// Email account configuration
+///
+/// File Format: JSON
+/// Location: ~/.rustbot/email.json
+///
+/// Example:
+///     {
+///       "imap_host": "imap.fastmail.com",
+///       "smtp_host": "smtp.fastmail.com",
+///       "username": "me@example.com",
+///       "from": "Me <me@example.com>"
+///     }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailConfig {
+    /// IMAP server (TLS)
+    pub imap_host: String,
+
+    #[serde(default = "default_imap_port")]
+    pub imap_port: u16,
+
+    /// SMTP server
+    pub smtp_host: String,
+
+    /// 465 for implicit TLS, 587 for STARTTLS
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+
+    /// Login for both servers
+    pub username: String,
+
+    /// Sender address (defaults to the username)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+
+    /// Mailbox read by read_inbox
+    #[serde(default = "default_mailbox")]
+    pub mailbox: String,
+
+    /// Message bodies longer than this are truncated
+    #[serde(default = "default_max_body_chars")]
+    pub max_body_chars: usize,
+}
+
+impl EmailConfig {
+    /// Default config location: ~/.rustbot/email.json
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("email.json")
+    }
+
+    /// Load configuration from a file
+    ///
+    /// # Returns
+    /// Ok(None) if the file doesn't exist, Ok(Some) if loaded
+    ///
+    /// # Errors
+    /// - File exists but cannot be read
+    /// - Invalid JSON
+    pub fn load(path: &Path) -> RustbotResult<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// Address outgoing mail is sent from
+    pub fn from_address(&self) -> &str {
+        self.from.as_deref().unwrap_or(&self.username)
+    }
+}
+
+/// A message returned by read_inbox
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InboxMessage {
+    pub uid: u32,
+    pub from: String,
+    pub subject: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+
+    /// Message-ID header, pass as `in_reply_to` to answer this message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+
+    pub unread: bool,
+    pub body: String,
+}
+
+/// An email the agent wants to send (send_email arguments)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmailDraft {
+    /// Comma-separated recipient addresses
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+
+    /// Message-ID of the message being answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_reply_to: Option<String>,
+}
+
+impl EmailDraft {
+    /// Recipient addresses, trimmed
+    pub fn recipients(&self) -> impl Iterator<Item = &str> {
+        self.to.split(',').map(str::trim).filter(|s| !s.is_empty())
+    }
+}
+
+/// read_inbox arguments
+#[derive(Debug, Default, Deserialize)]
+struct ReadInboxArgs {
+    limit: Option<usize>,
+    #[serde(default)]
+    unread_only: bool,
+}
+
+/// IMAP/SMTP connector for one account
+pub struct EmailService {
+    config: EmailConfig,
+    password: String,
+    event_bus: Arc<EventBus>,
+}
+
+impl EmailService {
+    /// # Arguments
+    /// * `config` - Account settings
+    /// * `password` - Password for both IMAP and SMTP
+    /// * `event_bus` - Bus used to ask the UI for send approval
+    pub fn new(config: EmailConfig, password: String, event_bus: Arc<EventBus>) -> Self {
+        Self {
+            config,
+            password,
+            event_bus,
+        }
+    }
+
+    /// Account settings
+    pub fn config(&self) -> &EmailConfig {
+        &self.config
+    }
+
+    /// Check if a tool name belongs to the email connector
+    pub fn is_email_tool(tool_name: &str) -> bool {
+        tool_name == READ_INBOX_TOOL || tool_name == SEND_EMAIL_TOOL
+    }
+
+    /// Definitions of read_inbox and send_email
+    pub fn tool_definitions(&self) -> Vec<ToolDefinition> {
+        vec![
+            tool_definition(
+                READ_INBOX_TOOL,
+                &format!(
+                    "Read the newest messages in the user's email inbox ({}), newest first. \
+                     Returns sender, subject, date, Message-ID and the plain text body.",
+                    self.config.username
+                ),
+                serde_json::json!({
+                    "limit": {
+                        "type": "integer",
+                        "description": format!(
+                            "Number of messages (default {}, max {})",
+                            DEFAULT_INBOX_LIMIT, MAX_INBOX_LIMIT
+                        )
+                    },
+                    "unread_only": {
+                        "type": "boolean",
+                        "description": "Only return unread messages"
+                    }
+                }),
+                &[],
+            ),
+            tool_definition(
+                SEND_EMAIL_TOOL,
+                &format!(
+                    "Send an email from {}. The user reviews every email and must approve it \
+                     before it is sent; tell them what you are about to send.",
+                    self.config.from_address()
+                ),
+                serde_json::json!({
+                    "to": {
+                        "type": "string",
+                        "description": "Recipient address(es), comma-separated"
+                    },
+                    "subject": { "type": "string", "description": "Subject line" },
+                    "body": { "type": "string", "description": "Plain text body" },
+                    "in_reply_to": {
+                        "type": "string",
+                        "description": "Message-ID of the email being answered (from read_inbox)"
+                    }
+                }),
+                &["to", "subject", "body"],
+            ),
+        ]
+    }
+
+    /// Run an email tool
+    ///
+    /// # Arguments
+    /// * `tool_name` - read_inbox or send_email
+    /// * `arguments` - JSON-encoded tool arguments
+    ///
+    /// # Errors
+    /// - Unknown tool or invalid arguments
+    /// - Connection, login or send failures
+    pub async fn call_tool(&self, tool_name: &str, arguments: &str) -> Result<String> {
+        let arguments = if arguments.trim().is_empty() {
+            "{}"
+        } else {
+            arguments
+        };
+
+        match tool_name {
+            READ_INBOX_TOOL => {
+                let args: ReadInboxArgs =
+                    serde_json::from_str(arguments).context("Invalid read_inbox arguments")?;
+                let limit = args
+                    .limit
+                    .unwrap_or(DEFAULT_INBOX_LIMIT)
+                    .clamp(1, MAX_INBOX_LIMIT);
+                let messages = self.read_inbox(limit, args.unread_only).await?;
+                Ok(serde_json::to_string_pretty(&messages)?)
+            }
+            SEND_EMAIL_TOOL => {
+                let draft: EmailDraft =
+                    serde_json::from_str(arguments).context("Invalid send_email arguments")?;
+                self.send(&draft).await
+            }
+            other => anyhow::bail!("Unknown email tool '{}'", other),
+        }
+    }
+
+    /// Fetch the newest messages from the configured mailbox
+    ///
+    /// # Errors
+    /// - Connection or login failure
+    /// - Mailbox does not exist
+    pub async fn read_inbox(&self, limit: usize, unread_only: bool) -> Result<Vec<InboxMessage>> {
+        let config = self.config.clone();
+        let password = self.password.clone();
+        tokio::task::spawn_blocking(move || fetch_messages(&config, &password, limit, unread_only))
+            .await?
+    }
+
+    /// Send a draft once the user approves it
+    ///
+    /// # Returns
+    /// A short result for the agent: sent, or declined by the user
+    ///
+    /// # Errors
+    /// - Invalid addresses
+    /// - SMTP connection, login or delivery failure
+    pub async fn send(&self, draft: &EmailDraft) -> Result<String> {
+        let message = build_message(self.config.from_address(), draft)?;
+
+        crate::privacy::check_host(&self.config.smtp_host, self.config.smtp_port)?;
+        if !self.request_approval(draft).await? {
+            tracing::info!("📧 Email to {} declined by the user", draft.to);
+            return Ok(format!(
+                "The user declined to send this email to {}. It was not sent.",
+                draft.to
+            ));
+        }
+
+        let host = self.config.smtp_host.as_str();
+        let transport = if self.config.smtp_port == 465 {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(host)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?
+        };
+        transport
+            .port(self.config.smtp_port)
+            .credentials(Credentials::new(
+                self.config.username.clone(),
+                self.password.clone(),
+            ))
+            .build()
+            .send(message)
+            .await
+            .with_context(|| format!("Failed to send email via {}", host))?;
+
+        tracing::info!("📧 Email sent to {}", draft.to);
+        Ok(format!("Email sent to {}", draft.to))
+    }
+
+    /// Ask the UI whether a draft may be sent
+    ///
+    /// # Returns
+    /// true only if the user approved; no answer within the timeout is a no
+    async fn request_approval(&self, draft: &EmailDraft) -> Result<bool> {
+        match self
+            .event_bus
+            .request::<bool>(
+                "email",
+                "user",
+                APPROVAL_METHOD,
+                serde_json::to_value(draft)?,
+                APPROVAL_TIMEOUT,
+            )
+            .await
+        {
+            Ok(approved) => Ok(approved),
+            Err(EventError::Timeout) => {
+                tracing::warn!("📧 No approval for email to {} - not sent", draft.to);
+                Ok(false)
+            }
+            Err(e) => Err(anyhow::anyhow!("Could not ask for approval: {}", e)),
+        }
+    }
+}
+
+/// Build the outgoing message, validating all addresses
+fn build_message(from: &str, draft: &EmailDraft) -> Result<lettre::Message> {
+    let from: Mailbox = from
+        .parse()
+        .with_context(|| format!("Invalid sender address '{}'", from))?;
+    let mut builder = lettre::Message::builder()
+        .from(from)
+        .subject(draft.subject.as_str())
+        .header(ContentType::TEXT_PLAIN);
+
+    let mut recipients = 0;
+    for to in draft.recipients() {
+        let mailbox: Mailbox = to
+            .parse()
+            .with_context(|| format!("Invalid recipient address '{}'", to))?;
+        builder = builder.to(mailbox);
+        recipients += 1;
+    }
+    if recipients == 0 {
+        anyhow::bail!("No recipient given");
+    }
+
+    if let Some(id) = &draft.in_reply_to {
+        // read_inbox reports Message-IDs without the angle brackets
+        let id = if id.starts_with('<') {
+            id.clone()
+        } else {
+            format!("<{}>", id)
+        };
+        builder = builder.in_reply_to(id.clone()).references(id);
+    }
+
+    builder
+        .body(draft.body.clone())
+        .context("Failed to build email")
+}
+
+/// Fetch messages over IMAP (blocking)
+fn fetch_messages(
+    config: &EmailConfig,
+    password: &str,
+    limit: usize,
+    unread_only: bool,
+) -> Result<Vec<InboxMessage>> {
+    crate::privacy::check_host(&config.imap_host, config.imap_port)?;
+    let tls = native_tls::TlsConnector::new()?;
+    let client = imap::connect(
+        (config.imap_host.as_str(), config.imap_port),
+        &config.imap_host,
+        &tls,
+    )
+    .with_context(|| format!("Failed to connect to {}", config.imap_host))?;
+    let mut session = client
+        .login(&config.username, password)
+        .map_err(|(e, _)| e)
+        .context("IMAP login failed")?;
+
+    session
+        .select(&config.mailbox)
+        .with_context(|| format!("Failed to open mailbox '{}'", config.mailbox))?;
+    let unseen: HashSet<u32> = session.uid_search("UNSEEN")?;
+    let mut uids: Vec<u32> = if unread_only {
+        unseen.iter().copied().collect()
+    } else {
+        session.uid_search("ALL")?.into_iter().collect()
+    };
+    uids.sort_unstable_by(|a, b| b.cmp(a));
+    uids.truncate(limit);
+
+    let mut messages = Vec::new();
+    if !uids.is_empty() {
+        let set = uids
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        for fetch in session.uid_fetch(&set, "(UID BODY.PEEK[])")?.iter() {
+            let (Some(uid), Some(raw)) = (fetch.uid, fetch.body()) else {
+                continue;
+            };
+            if let Some(message) =
+                parse_message(uid, raw, unseen.contains(&uid), config.max_body_chars)
+            {
+                messages.push(message);
+            }
+        }
+    }
+
+    if let Err(e) = session.logout() {
+        tracing::debug!("IMAP logout failed: {}", e);
+    }
+
+    messages.sort_by_key(|message| std::cmp::Reverse(message.uid));
+    Ok(messages)
+}
+
+/// Parse a raw RFC 822 message into an inbox entry
+fn parse_message(
+    uid: u32,
+    raw: &[u8],
+    unread: bool,
+    max_body_chars: usize,
+) -> Option<InboxMessage> {
+    let message = MessageParser::default().parse(raw)?;
+
+    let from = message
+        .from()
+        .and_then(|address| address.first())
+        .map(|addr| match (addr.name(), addr.address()) {
+            (Some(name), Some(address)) => format!("{} <{}>", name, address),
+            (None, Some(address)) => address.to_string(),
+            (Some(name), None) => name.to_string(),
+            (None, None) => String::new(),
+        })
+        .unwrap_or_default();
+
+    let body = message
+        .body_text(0)
+        .map(|text| text.trim().to_string())
+        .unwrap_or_default();
+    let body = if body.chars().count() > max_body_chars {
+        let mut truncated: String = body.chars().take(max_body_chars).collect();
+        truncated.push_str("\n[…truncated]");
+        truncated
+    } else {
+        body
+    };
+
+    Some(InboxMessage {
+        uid,
+        from,
+        subject: message.subject().unwrap_or_default().to_string(),
+        date: message.date().map(|date| date.to_rfc3339()),
+        message_id: message.message_id().map(str::to_string),
+        unread,
+        body,
+    })
+}
+
+fn tool_definition(
+    name: &str,
+    description: &str,
+    properties: serde_json::Value,
+    required: &[&str],
+) -> ToolDefinition {
+    ToolDefinition {
+        tool_type: "function".to_string(),
+        function: FunctionDefinition {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters: FunctionParameters {
+                param_type: "object".to_string(),
+                properties,
+                required: required.iter().map(|s| s.to_string()).collect(),
+            },
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventKind;
+
+    fn config() -> EmailConfig {
+        serde_json::from_str(
+            r#"{"imap_host": "imap.example.com", "smtp_host": "smtp.example.com",
+                "username": "me@example.com"}"#,
+        )
+        .unwrap()
+    }
+
+    fn draft() -> EmailDraft {
+        EmailDraft {
+            to: "a@example.com, Bob <b@example.com>".to_string(),
+            subject: "Hello".to_string(),
+            body: "Hi there".to_string(),
+            in_reply_to: Some("abc@example.com".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config = config();
+        assert_eq!(config.imap_port, 993);
+        assert_eq!(config.smtp_port, 465);
+        assert_eq!(config.mailbox, "INBOX");
+        assert_eq!(config.from_address(), "me@example.com");
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(EmailConfig::load(&dir.path().join("email.json"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_parse_message() {
+        let raw = b"From: Alice Example <alice@example.com>\r\n\
+                    To: me@example.com\r\n\
+                    Subject: Lunch?\r\n\
+                    Date: Mon, 6 Jan 2025 12:00:00 +0000\r\n\
+                    Message-ID: <lunch@example.com>\r\n\
+                    Content-Type: text/plain; charset=utf-8\r\n\
+                    \r\n\
+                    Are you free tomorrow?\r\n";
+
+        let message = parse_message(7, raw, true, 100).unwrap();
+        assert_eq!(message.uid, 7);
+        assert_eq!(message.from, "Alice Example <alice@example.com>");
+        assert_eq!(message.subject, "Lunch?");
+        assert_eq!(message.message_id.as_deref(), Some("lunch@example.com"));
+        assert!(message.unread);
+        assert_eq!(message.body, "Are you free tomorrow?");
+
+        let message = parse_message(7, raw, false, 7).unwrap();
+        assert!(message.body.starts_with("Are you\n"));
+        assert!(message.body.ends_with("[…truncated]"));
+    }
+
+    #[test]
+    fn test_build_message_validates_addresses() {
+        let message = build_message("me@example.com", &draft()).unwrap();
+        let formatted = String::from_utf8(message.formatted()).unwrap();
+        assert!(formatted.contains("Subject: Hello"));
+        assert!(formatted.contains("In-Reply-To: <abc@example.com>"));
+
+        let mut bad = draft();
+        bad.to = "not an address".to_string();
+        assert!(build_message("me@example.com", &bad).is_err());
+
+        bad.to = " , ".to_string();
+        assert!(build_message("me@example.com", &bad).is_err());
+    }
+
+    #[test]
+    fn test_tool_definitions() {
+        let service = EmailService::new(config(), String::new(), Arc::new(EventBus::new()));
+        let names: Vec<_> = service
+            .tool_definitions()
+            .into_iter()
+            .map(|t| t.function.name)
+            .collect();
+        assert_eq!(names, vec![READ_INBOX_TOOL, SEND_EMAIL_TOOL]);
+        assert!(EmailService::is_email_tool("send_email"));
+        assert!(!EmailService::is_email_tool("mcp:mail:send_email"));
+    }
+
+    #[tokio::test]
+    async fn test_declined_email_is_not_sent() {
+        let bus = Arc::new(EventBus::new());
+        let service = EmailService::new(config(), String::new(), Arc::clone(&bus));
+
+        // Stand-in for the UI: decline every approval request
+        let mut rx = bus.subscribe();
+        let responder = Arc::clone(&bus);
+        tokio::spawn(async move {
+            while let Ok(event) = rx.recv().await {
+                if let EventKind::Request { method, params } = &event.kind {
+                    assert_eq!(method, APPROVAL_METHOD);
+                    assert_eq!(params["subject"], "Hello");
+                    responder
+                        .respond(&event, "user", Ok(serde_json::json!(false)))
+                        .unwrap();
+                }
+            }
+        });
+
+        let arguments = serde_json::to_string(&draft()).unwrap();
+        let result = service
+            .call_tool(SEND_EMAIL_TOOL, &arguments)
+            .await
+            .unwrap();
+        assert!(result.contains("declined"));
+    }
+}
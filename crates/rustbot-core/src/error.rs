@@ -0,0 +1,346 @@
+// Centralized error handling using thiserror for type-safe error management
+//
+// Design Decision: Unified error type with context
+//
+// Rationale: Instead of using Box<dyn Error> throughout, we define specific
+// error variants that map to different failure modes in the application.
+// This enables pattern matching, better error messages, and type safety.
+//
+// Trade-offs:
+// - Type Safety: Specific error types vs. generic Box<dyn Error>
+// - Ergonomics: thiserror auto-derives Display and Error trait
+// - Conversion: #[from] attribute handles automatic conversions from std errors
+//
+// Alternatives Considered:
+// 1. Keep Box<dyn Error>: Rejected for lack of type safety and match-ability
+// 2. anyhow::Error everywhere: Rejected because we want type-safe error variants
+// 3. Custom Error enum without thiserror: Rejected due to boilerplate
+//
+// Extension Points: Add new error variants as needed for specific failure modes
+//
+// Failures the user can act on (rate limits, a context that's too long, a
+// rejected key, a plugin that's down, timeouts) get their own variants with
+// a `remediation()` hint. They still travel through `anyhow` inside the agent
+// pipeline; `RustbotError::find` recovers them at the edge so the chat can say
+// "shorten the conversation" instead of echoing a provider's error body.
+
+use thiserror::Error;
+
+/// Main error type for Rustbot application
+///
+/// This enum covers all error conditions that can occur in the application.
+/// Each variant provides contextual information about what went wrong.
+///
+/// Usage:
+///     fn load_file() -> Result<String> {
+///         let content = std::fs::read_to_string(path)
+///             .map_err(|e| RustbotError::StorageError(
+///                 format!("Failed to read config: {}", e)
+///             ))?;
+///         Ok(content)
+///     }
+///
+/// Error Handling Strategy:
+/// - IO errors: Automatically converted via #[from] IoError variant
+/// - Serde errors: Automatically converted via #[from] SerdeError variant
+/// - HTTP errors: Automatically converted via #[from] ReqwestError variant
+/// - Application errors: Use specific variants (AgentNotFound, ConfigError, etc.)
+/// - Provider HTTP errors: `RustbotError::from_provider_response`
+#[derive(Debug, Error)]
+pub enum RustbotError {
+    /// Agent with specified ID not found in registry
+    ///
+    /// This occurs when trying to switch to or access a non-existent agent.
+    /// The string contains the agent ID that was not found.
+    #[error("Agent not found: {0}")]
+    AgentNotFound(String),
+
+    /// API-level error with context
+    ///
+    /// Generic API errors that don't fit other categories.
+    /// Contains a descriptive message about what failed.
+    #[error("API error: {0}")]
+    ApiError(String),
+
+    /// LLM adapter or communication error
+    ///
+    /// Errors from LLM providers (OpenRouter, Claude API, etc.)
+    /// Includes connection failures, rate limits, and API errors.
+    #[error("LLM error: {0}")]
+    LlmError(String),
+
+    /// Configuration loading or validation error
+    ///
+    /// Failures in reading or parsing configuration files,
+    /// environment variables, or validation of config values.
+    #[error("Configuration error: {0}")]
+    ConfigError(String),
+
+    /// Storage/persistence error
+    ///
+    /// File system operations, database errors, or serialization failures
+    /// when reading/writing persistent data.
+    #[error("Storage error: {0}")]
+    StorageError(String),
+
+    /// Event bus communication error
+    ///
+    /// Failures in the pub/sub event system, including channel errors
+    /// and event delivery failures.
+    #[error("Event bus error: {0}")]
+    EventError(String),
+
+    /// IO operation failed (file, network, etc.)
+    ///
+    /// Wraps std::io::Error with automatic conversion via #[from].
+    /// This covers file operations, network I/O, and system calls.
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// JSON serialization/deserialization failed
+    ///
+    /// Wraps serde_json::Error with automatic conversion.
+    /// Occurs when parsing or generating JSON data.
+    #[error("JSON serialization error: {0}")]
+    SerdeError(#[from] serde_json::Error),
+
+    /// HTTP request failed
+    ///
+    /// Wraps reqwest::Error with automatic conversion.
+    /// Network errors, HTTP errors, and request building failures.
+    #[error("HTTP request error: {0}")]
+    ReqwestError(#[from] reqwest::Error),
+
+    /// Environment variable not found or invalid
+    ///
+    /// Missing required environment variables (API keys, etc.)
+    /// or values that fail validation.
+    #[error("Environment error: {0}")]
+    EnvError(String),
+
+    /// Directory or path error
+    ///
+    /// Failures related to path operations: invalid paths,
+    /// missing directories, or permission issues.
+    #[error("Path error: {0}")]
+    PathError(String),
+
+    /// The provider is rate limiting requests (HTTP 429)
+    ///
+    /// Carries how long the provider asked to wait, if it said.
+    #[error(transparent)]
+    RateLimited(#[from] crate::llm::RateLimited),
+
+    /// The request doesn't fit the model's context window
+    ///
+    /// Contains the provider's explanation.
+    #[error("Context too long: {0}")]
+    ContextTooLong(String),
+
+    /// The provider rejected the API key (HTTP 401/403)
+    ///
+    /// Contains the provider's explanation.
+    #[error("Invalid API key: {0}")]
+    InvalidApiKey(String),
+
+    /// An MCP plugin isn't running or stopped answering
+    #[error("Plugin '{plugin}' is unavailable: {message}")]
+    PluginDown { plugin: String, message: String },
+
+    /// A request took longer than allowed
+    ///
+    /// Contains what timed out.
+    #[error("Timed out: {0}")]
+    Timeout(String),
+}
+
+/// Phrases providers use when a request exceeds the context window
+const CONTEXT_TOO_LONG_PHRASES: &[&str] = &[
+    "context length",
+    "context_length",
+    "context window",
+    "prompt is too long",
+    "too many tokens",
+];
+
+impl RustbotError {
+    /// Error for a provider's non-success HTTP response
+    ///
+    /// Picks the variant from the status and, for context overflows that
+    /// come back as a plain 400, the error message. The message is the JSON
+    /// `error.message` when the body has one, else the body itself.
+    ///
+    /// # Arguments
+    /// * `provider` - Provider name for the message
+    /// * `status` - Response status
+    /// * `body` - Response body
+    pub fn from_provider_response(provider: &str, status: reqwest::StatusCode, body: &str) -> Self {
+        use reqwest::StatusCode;
+
+        let message = serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|json| {
+                json.pointer("/error/message")
+                    .and_then(|m| m.as_str())
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| body.trim().to_string());
+        let message = if message.is_empty() {
+            status
+                .canonical_reason()
+                .unwrap_or("no details")
+                .to_string()
+        } else {
+            message
+        };
+        let lower = message.to_lowercase();
+
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::InvalidApiKey(message),
+            StatusCode::PAYLOAD_TOO_LARGE => Self::ContextTooLong(message),
+            StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => {
+                Self::Timeout(format!("{} ({})", provider, message))
+            }
+            _ if CONTEXT_TOO_LONG_PHRASES.iter().any(|p| lower.contains(p)) => {
+                Self::ContextTooLong(message)
+            }
+            _ => Self::LlmError(format!("{} answered {}: {}", provider, status, message)),
+        }
+    }
+
+    /// What the user can do about the error, for errors with a specific fix
+    pub fn remediation(&self) -> Option<&'static str> {
+        match self {
+            Self::RateLimited(_) => Some("Wait a moment, then send the message again."),
+            Self::ContextTooLong(_) => Some(
+                "Shorten the context: start a new chat, remove long messages or attachments, \
+                 or switch to a model with a larger context window.",
+            ),
+            Self::InvalidApiKey(_) => Some(
+                "Check your API key: it may be mistyped, revoked or out of credit. Update \
+                 OPENROUTER_API_KEY in .env.local or the system keychain and restart.",
+            ),
+            Self::PluginDown { .. } => {
+                Some("Check the plugin under Extensions and restart it, or disable its tools.")
+            }
+            Self::Timeout(_) => Some(
+                "Check your connection and try again; a shorter request or another model may \
+                 answer faster.",
+            ),
+            _ => None,
+        }
+    }
+
+    /// The `RustbotError` in an `anyhow` error or the errors it wraps
+    pub fn find(err: &anyhow::Error) -> Option<&RustbotError> {
+        err.chain()
+            .find_map(|cause| cause.downcast_ref::<RustbotError>())
+    }
+}
+
+/// Type alias for Result with RustbotError
+///
+/// Use this instead of std::result::Result<T, RustbotError> for convenience.
+///
+/// Example:
+///     fn load_config() -> Result<Config> {
+///         // ...
+///     }
+pub type Result<T> = std::result::Result<T, RustbotError>;
+
+// Conversion from anyhow::Error for gradual migration
+//
+// This allows existing code using anyhow to interoperate with new
+// thiserror-based code during the transition period. A `RustbotError`
+// that went through `anyhow` comes back as itself.
+impl From<anyhow::Error> for RustbotError {
+    fn from(err: anyhow::Error) -> Self {
+        err.downcast::<RustbotError>()
+            .unwrap_or_else(|err| RustbotError::ApiError(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_display() {
+        let err = RustbotError::AgentNotFound("researcher".to_string());
+        assert_eq!(err.to_string(), "Agent not found: researcher");
+
+        let err = RustbotError::StorageError("Failed to save".to_string());
+        assert_eq!(err.to_string(), "Storage error: Failed to save");
+    }
+
+    #[test]
+    fn test_io_error_conversion() {
+        let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "file not found");
+        let rustbot_err: RustbotError = io_err.into();
+
+        match rustbot_err {
+            RustbotError::IoError(_) => {} // Success
+            _ => panic!("Expected IoError variant"),
+        }
+    }
+
+    #[test]
+    fn test_result_type_alias() {
+        fn returns_error() -> Result<i32> {
+            Err(RustbotError::ConfigError("test error".to_string()))
+        }
+
+        let result = returns_error();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_provider_response() {
+        use reqwest::StatusCode;
+
+        let err = RustbotError::from_provider_response(
+            "OpenRouter",
+            StatusCode::UNAUTHORIZED,
+            r#"{"error":{"message":"No auth credentials found","code":401}}"#,
+        );
+        assert!(matches!(&err, RustbotError::InvalidApiKey(m) if m == "No auth credentials found"));
+
+        let err = RustbotError::from_provider_response(
+            "OpenRouter",
+            StatusCode::BAD_REQUEST,
+            r#"{"error":{"message":"This endpoint's maximum context length is 8192 tokens."}}"#,
+        );
+        assert!(matches!(err, RustbotError::ContextTooLong(_)));
+        assert!(err.remediation().unwrap().contains("Shorten"));
+
+        let err =
+            RustbotError::from_provider_response("OpenRouter", StatusCode::GATEWAY_TIMEOUT, "");
+        assert!(matches!(err, RustbotError::Timeout(_)));
+
+        let err = RustbotError::from_provider_response(
+            "OpenRouter",
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "upstream failed",
+        );
+        assert!(matches!(err, RustbotError::LlmError(_)));
+        assert_eq!(err.remediation(), None);
+    }
+
+    #[test]
+    fn test_find_through_anyhow() {
+        let err = anyhow::Error::new(RustbotError::PluginDown {
+            plugin: "filesystem".to_string(),
+            message: "not running".to_string(),
+        })
+        .context("MCP tool execution failed");
+        assert!(matches!(
+            RustbotError::find(&err),
+            Some(RustbotError::PluginDown { .. })
+        ));
+        assert!(RustbotError::find(&anyhow::anyhow!("plain")).is_none());
+
+        // Converting back keeps the variant
+        let err: RustbotError = anyhow::Error::new(RustbotError::Timeout("x".into())).into();
+        assert!(matches!(err, RustbotError::Timeout(_)));
+    }
+}
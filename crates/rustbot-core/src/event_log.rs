@@ -0,0 +1,482 @@
+// Persistent event log with JSONL/CSV export
+//
+// Design Decision: Append-only JSONL file fed by an event bus subscriber
+//
+// Rationale: The in-memory event history only keeps the last 50 events for
+// the sidebar visualizer. For offline analysis users need a durable record
+// they can slice by time range and open in jq, pandas or a spreadsheet.
+// JSONL is append-friendly (no rewrite on each event) and trivially parsed.
+//
+// Trade-offs:
+// - Single file + one rotated backup vs daily files: Simple range queries,
+//   bounded disk usage (MAX_LOG_BYTES per file)
+// - Full scan on export: Fine for the expected volume (a few MB)
+// - RPC request/response traffic is not recorded (high volume, low value)
+// - Details are scrubbed of secrets (see `redact`) before they are written or
+//   exported, so a key quoted in a tool error never reaches the file
+// - The file is not encrypted; with encryption at rest on, the app turns off
+//   message bodies so user and agent messages are logged by length only
+//
+// Extension Points:
+// - Index by correlation ID for turn-level exports
+// - Additional export formats (Parquet, SQLite)
+
+use crate::error::{Result, RustbotError};
+use crate::events::{AgentStatus, Event, EventBus, EventKind, McpPluginEvent};
+use crate::redact::redact;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tokio::task::JoinHandle;
+
+/// Rotate the log once it grows beyond this size
+const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Serializable snapshot of a bus event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub timestamp: DateTime<Local>,
+    pub source: String,
+    pub destination: String,
+    pub kind: String,
+    pub detail: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+impl EventRecord {
+    /// Build a record from a bus event
+    pub fn from_event(event: &Event) -> Self {
+        let (kind, detail) = match &event.kind {
+            EventKind::UserMessage(content) => ("UserMessage", content.clone()),
+            EventKind::AgentMessage { agent_id, content } => {
+                ("AgentMessage", format!("{}: {}", agent_id, content))
+            }
+            EventKind::AgentStatusChange { agent_id, status } => {
+                let status = match status {
+                    AgentStatus::Idle => "Idle".to_string(),
+                    AgentStatus::Thinking => "Thinking".to_string(),
+                    AgentStatus::Responding => "Responding".to_string(),
+                    AgentStatus::ExecutingTool(tool) => format!("ExecutingTool({})", tool),
+                    AgentStatus::Error(msg) => format!("Error({})", msg),
+                };
+                ("StatusChange", format!("{}: {}", agent_id, status))
+            }
+            EventKind::ToolCall { call, .. } => (
+                "ToolCall",
+                match &call.result {
+                    None => format!("{} started", call.name),
+                    Some(Ok(_)) => format!("{} ok ({:?})", call.name, call.duration),
+                    Some(Err(e)) => format!("{} failed: {}", call.name, e),
+                },
+            ),
+            EventKind::Handoff { handoff, .. } => {
+                ("Handoff", format!("{} -> {}", handoff.from, handoff.to))
+            }
+            EventKind::SystemCommand(cmd) => ("SystemCommand", format!("{:?}", cmd)),
+            EventKind::McpPluginEvent(plugin_event) => {
+                let detail = match plugin_event {
+                    McpPluginEvent::Error { plugin_id, message } => {
+                        format!("{}: Error({})", plugin_id, message)
+                    }
+                    other => format!("{:?}", other),
+                };
+                ("McpPlugin", detail)
+            }
+            EventKind::SubscriberLagged {
+                subscriber,
+                skipped,
+            } => (
+                "SubscriberLagged",
+                format!("{} dropped {} events", subscriber, skipped),
+            ),
+            EventKind::Request { method, .. } => ("Request", method.clone()),
+            EventKind::Response { result } => (
+                "Response",
+                match result {
+                    Ok(_) => "ok".to_string(),
+                    Err(e) => format!("error: {}", e),
+                },
+            ),
+            EventKind::Test(msg) => ("Test", msg.clone()),
+        };
+
+        Self {
+            timestamp: event.timestamp,
+            source: event.source.clone(),
+            destination: event.destination.clone(),
+            kind: kind.to_string(),
+            // Tool errors and messages can quote keys; keep them off disk
+            detail: redact(&detail).into_owned(),
+            correlation_id: event.correlation_id.clone(),
+        }
+    }
+
+    /// Build a record that keeps message text out: user and agent messages
+    /// are logged by length only
+    pub fn from_event_without_bodies(event: &Event) -> Self {
+        let omitted = |content: &str| format!("({} chars, not logged)", content.chars().count());
+        let mut record = Self::from_event(event);
+        match &event.kind {
+            EventKind::UserMessage(content) => record.detail = omitted(content),
+            EventKind::AgentMessage { agent_id, content } => {
+                record.detail = format!("{}: {}", agent_id, omitted(content));
+            }
+            _ => {}
+        }
+        record
+    }
+}
+
+/// Export file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Jsonl,
+    Csv,
+}
+
+impl ExportFormat {
+    /// File extension (without the dot)
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Append-only event log stored as JSONL
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    path: PathBuf,
+
+    /// Record the text of user and agent messages
+    message_bodies: bool,
+}
+
+impl EventLog {
+    /// Create a log at the given path (parent directories are created on first write)
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            message_bodies: true,
+        }
+    }
+
+    /// Set whether the recorder logs message text (builder style); off, user
+    /// and agent messages are logged by length only
+    pub fn with_message_bodies(mut self, message_bodies: bool) -> Self {
+        self.message_bodies = message_bodies;
+        self
+    }
+
+    /// Default log location: ~/.rustbot/events.jsonl
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("events.jsonl")
+    }
+
+    /// Path of the active log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path of the rotated (previous) log file
+    fn rotated_path(&self) -> PathBuf {
+        self.path.with_extension("jsonl.1")
+    }
+
+    /// Append a single record, rotating the file if it is too large
+    pub fn append(&self, record: &EventRecord) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        if let Ok(metadata) = std::fs::metadata(&self.path) {
+            if metadata.len() >= MAX_LOG_BYTES {
+                std::fs::rename(&self.path, self.rotated_path())?;
+            }
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    }
+
+    /// Load records within an optional time range (inclusive), oldest first
+    ///
+    /// Reads the rotated file followed by the active file. Malformed lines
+    /// are skipped with a warning rather than failing the whole export.
+    pub fn load_range(
+        &self,
+        from: Option<DateTime<Local>>,
+        to: Option<DateTime<Local>>,
+    ) -> Result<Vec<EventRecord>> {
+        let mut records = Vec::new();
+
+        for path in [self.rotated_path(), self.path.clone()] {
+            if !path.exists() {
+                continue;
+            }
+
+            let reader = BufReader::new(File::open(&path)?);
+            for line in reader.lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<EventRecord>(&line) {
+                    Ok(record) => {
+                        let before_start = matches!(from, Some(f) if record.timestamp < f);
+                        let after_end = matches!(to, Some(t) if record.timestamp > t);
+                        if !before_start && !after_end {
+                            records.push(record);
+                        }
+                    }
+                    Err(e) => tracing::warn!("Skipping malformed event log line: {}", e),
+                }
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Export records within a time range to a file
+    ///
+    /// # Returns
+    /// Number of records written
+    pub fn export(
+        &self,
+        from: Option<DateTime<Local>>,
+        to: Option<DateTime<Local>>,
+        format: ExportFormat,
+        output: &Path,
+    ) -> Result<usize> {
+        let mut records = self.load_range(from, to)?;
+        // Also covers lines logged before redaction was added
+        for record in &mut records {
+            if let Cow::Owned(redacted) = redact(&record.detail) {
+                record.detail = redacted;
+            }
+        }
+        if let Some(parent) = output.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut file = File::create(output)?;
+        write_records(&mut file, &records, format)?;
+        Ok(records.len())
+    }
+
+    /// Record every event published on the bus in the background
+    ///
+    /// RPC `Request`/`Response` events are skipped. Write failures are logged
+    /// once per failure and don't stop the recorder.
+    pub fn spawn_recorder(
+        self,
+        event_bus: &EventBus,
+        handle: &tokio::runtime::Handle,
+    ) -> JoinHandle<()> {
+        let mut rx = event_bus.subscribe_named("event_log");
+
+        handle.spawn(async move {
+            while let Some(event) = rx.recv().await {
+                if matches!(
+                    event.kind,
+                    EventKind::Request { .. } | EventKind::Response { .. }
+                ) {
+                    continue;
+                }
+                let log = self.clone();
+                let record = if log.message_bodies {
+                    EventRecord::from_event(&event)
+                } else {
+                    EventRecord::from_event_without_bodies(&event)
+                };
+                let result = tokio::task::spawn_blocking(move || log.append(&record)).await;
+                match result {
+                    Ok(Err(e)) => tracing::warn!("Failed to persist event: {}", e),
+                    Err(e) => tracing::warn!("Event log task failed: {}", e),
+                    Ok(Ok(())) => {}
+                }
+            }
+        })
+    }
+}
+
+/// Serialize records in the requested format
+pub fn write_records<W: Write>(
+    writer: &mut W,
+    records: &[EventRecord],
+    format: ExportFormat,
+) -> Result<()> {
+    match format {
+        ExportFormat::Jsonl => {
+            for record in records {
+                writeln!(writer, "{}", serde_json::to_string(record)?)?;
+            }
+        }
+        ExportFormat::Csv => {
+            writeln!(
+                writer,
+                "timestamp,source,destination,kind,detail,correlation_id"
+            )?;
+            for record in records {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{},{}",
+                    csv_field(&record.timestamp.to_rfc3339()),
+                    csv_field(&record.source),
+                    csv_field(&record.destination),
+                    csv_field(&record.kind),
+                    csv_field(&record.detail),
+                    csv_field(record.correlation_id.as_deref().unwrap_or("")),
+                )?;
+            }
+        }
+    }
+    writer
+        .flush()
+        .map_err(|e| RustbotError::StorageError(format!("Failed to flush export: {}", e)))
+}
+
+/// Quote a CSV field when it contains separators, quotes or newlines (RFC 4180)
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn record_at(timestamp: DateTime<Local>, detail: &str) -> EventRecord {
+        EventRecord {
+            timestamp,
+            source: "user".to_string(),
+            destination: "assistant".to_string(),
+            kind: "UserMessage".to_string(),
+            detail: detail.to_string(),
+            correlation_id: None,
+        }
+    }
+
+    #[test]
+    fn test_record_from_event() {
+        let event = Event::new(
+            "assistant".to_string(),
+            "broadcast".to_string(),
+            EventKind::AgentStatusChange {
+                agent_id: "assistant".to_string(),
+                status: AgentStatus::ExecutingTool("web_search".to_string()),
+            },
+        )
+        .with_correlation_id(Some("turn-1-0".to_string()));
+
+        let record = EventRecord::from_event(&event);
+        assert_eq!(record.kind, "StatusChange");
+        assert_eq!(record.detail, "assistant: ExecutingTool(web_search)");
+        assert_eq!(record.correlation_id.as_deref(), Some("turn-1-0"));
+    }
+
+    #[test]
+    fn test_record_detail_is_redacted() {
+        let event = Event::new(
+            "user".to_string(),
+            "assistant".to_string(),
+            EventKind::UserMessage("my key is sk-or-v1-abcdefghijklmnop1234".to_string()),
+        );
+        let record = EventRecord::from_event(&event);
+        assert_eq!(record.detail, "my key is [REDACTED]");
+    }
+
+    #[test]
+    fn test_record_without_bodies() {
+        let event = Event::new(
+            "assistant".to_string(),
+            "user".to_string(),
+            EventKind::AgentMessage {
+                agent_id: "assistant".to_string(),
+                content: "Your diagnosis is…".to_string(),
+            },
+        );
+        let record = EventRecord::from_event_without_bodies(&event);
+        assert_eq!(record.kind, "AgentMessage");
+        assert_eq!(record.detail, "assistant: (18 chars, not logged)");
+    }
+
+    #[test]
+    fn test_append_and_load_range() {
+        let dir = TempDir::new().unwrap();
+        let log = EventLog::new(dir.path().join("events.jsonl"));
+
+        let now = Local::now();
+        let old = now - chrono::Duration::hours(2);
+        log.append(&record_at(old, "old")).unwrap();
+        log.append(&record_at(now, "new")).unwrap();
+
+        let all = log.load_range(None, None).unwrap();
+        assert_eq!(all.len(), 2);
+
+        let recent = log
+            .load_range(Some(now - chrono::Duration::hours(1)), None)
+            .unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].detail, "new");
+    }
+
+    #[test]
+    fn test_csv_escaping() {
+        let mut output = Vec::new();
+        let record = record_at(Local::now(), "hello, \"world\"\nbye");
+        write_records(&mut output, &[record], ExportFormat::Csv).unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            "timestamp,source,destination,kind,detail,correlation_id"
+        );
+        assert!(text.contains("\"hello, \"\"world\"\"\nbye\""));
+    }
+
+    #[test]
+    fn test_export_jsonl_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let log = EventLog::new(dir.path().join("events.jsonl"));
+        log.append(&record_at(Local::now(), "one")).unwrap();
+        log.append(&record_at(Local::now(), "two")).unwrap();
+
+        let output = dir.path().join("exports").join("out.jsonl");
+        let written = log
+            .export(None, None, ExportFormat::Jsonl, &output)
+            .unwrap();
+        assert_eq!(written, 2);
+
+        let exported = EventLog::new(output).load_range(None, None).unwrap();
+        assert_eq!(exported[1].detail, "two");
+    }
+
+    #[test]
+    fn test_malformed_lines_are_skipped() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("events.jsonl");
+        std::fs::write(&path, "not json\n").unwrap();
+
+        let log = EventLog::new(path);
+        log.append(&record_at(Local::now(), "valid")).unwrap();
+        assert_eq!(log.load_range(None, None).unwrap().len(), 1);
+    }
+}
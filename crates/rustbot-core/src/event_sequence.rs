@@ -0,0 +1,207 @@
+// Event flow as a sequence diagram - lanes, turn grouping and Mermaid output
+//
+// Design Decision: Lay out lanes and groups here, draw them in the front end
+//
+// Rationale: The Event Flow view shows each component that publishes on the
+// event bus (user, api, agents, mcp_manager) as a swimlane, with one arrow per
+// event and events grouped by the correlation ID of the turn that caused them.
+// Choosing lane order and grouping is plain data work, so it lives in core
+// with tests; the GUI only paints the result. The same steps can be written
+// as a Mermaid `sequenceDiagram` and rendered by the existing mermaid pipeline.
+//
+// Trade-offs: Broadcast events have no single receiver; they are drawn as a
+// note on the sender's lane rather than an arrow to every lane.
+
+/// Destination of events sent to every subscriber
+pub const BROADCAST: &str = "broadcast";
+
+/// One event in a sequence: who sent what to whom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceStep {
+    pub source: String,
+    pub destination: String,
+    /// Short label drawn on the arrow (usually the event kind)
+    pub label: String,
+}
+
+impl SequenceStep {
+    pub fn is_broadcast(&self) -> bool {
+        self.destination == BROADCAST
+    }
+}
+
+/// Lane order: the user on the left, then the API, agents, and MCP last
+fn lane_rank(name: &str) -> u8 {
+    match name {
+        "user" => 0,
+        "api" | "system" => 1,
+        "mcp_manager" => 3,
+        _ => 2,
+    }
+}
+
+/// Participants of a sequence, one per swimlane, in display order
+///
+/// Lanes are ordered user, api/system, agents, mcp_manager; lanes of the same
+/// rank keep the order in which they first appear. `broadcast` is not a lane.
+pub fn participants(steps: &[SequenceStep]) -> Vec<String> {
+    let mut lanes: Vec<String> = Vec::new();
+    for step in steps {
+        for name in [&step.source, &step.destination] {
+            if name != BROADCAST && !lanes.contains(name) {
+                lanes.push(name.clone());
+            }
+        }
+    }
+    // Stable sort keeps first-appearance order within a rank
+    lanes.sort_by_key(|name| lane_rank(name));
+    lanes
+}
+
+/// Group items by correlation ID, in order of each group's first item
+///
+/// Items without a correlation ID are collected in a single `None` group.
+///
+/// # Arguments
+/// * `items` - Items in time order
+/// * `correlation_id` - Reads an item's correlation ID
+pub fn group_by_correlation<T>(
+    items: impl IntoIterator<Item = T>,
+    correlation_id: impl Fn(&T) -> Option<&str>,
+) -> Vec<(Option<String>, Vec<T>)> {
+    let mut groups: Vec<(Option<String>, Vec<T>)> = Vec::new();
+    for item in items {
+        let id = correlation_id(&item).map(str::to_string);
+        match groups.iter_mut().find(|(group_id, _)| *group_id == id) {
+            Some((_, group)) => group.push(item),
+            None => groups.push((id, vec![item])),
+        }
+    }
+    groups
+}
+
+/// Write steps as a Mermaid `sequenceDiagram`
+///
+/// Participants get generated aliases (`P0`, `P1`, ...) so names with dashes
+/// or dots stay valid; labels are cleaned of characters Mermaid treats as
+/// syntax.
+pub fn to_mermaid(steps: &[SequenceStep]) -> String {
+    let lanes = participants(steps);
+    let alias = |name: &str| {
+        lanes
+            .iter()
+            .position(|lane| lane == name)
+            .map(|i| format!("P{}", i))
+            .unwrap_or_default()
+    };
+
+    let mut diagram = String::from("sequenceDiagram\n");
+    for (i, lane) in lanes.iter().enumerate() {
+        diagram.push_str(&format!(
+            "    participant P{} as {}\n",
+            i,
+            clean_label(lane)
+        ));
+    }
+    for step in steps {
+        let label = clean_label(&step.label);
+        if step.is_broadcast() {
+            diagram.push_str(&format!(
+                "    Note over {}: {} (broadcast)\n",
+                alias(&step.source),
+                label
+            ));
+        } else {
+            diagram.push_str(&format!(
+                "    {}->>{}: {}\n",
+                alias(&step.source),
+                alias(&step.destination),
+                label
+            ));
+        }
+    }
+    diagram
+}
+
+/// Text safe to use as a Mermaid participant name or message label
+fn clean_label(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\n' | '\r' | ';' | '#' | ':' => ' ',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(source: &str, destination: &str, label: &str) -> SequenceStep {
+        SequenceStep {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            label: label.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_participants_order() {
+        let steps = vec![
+            step("mcp_manager", BROADCAST, "McpPlugin"),
+            step("researcher", "user", "AgentMessage"),
+            step("user", "assistant", "UserMessage"),
+            step("api", BROADCAST, "Request"),
+        ];
+        assert_eq!(
+            participants(&steps),
+            vec!["user", "api", "researcher", "assistant", "mcp_manager"]
+        );
+    }
+
+    #[test]
+    fn test_group_by_correlation() {
+        let items = vec![
+            ("a", Some("turn-1")),
+            ("b", None),
+            ("c", Some("turn-2")),
+            ("d", Some("turn-1")),
+            ("e", None),
+        ];
+        let groups = group_by_correlation(items, |(_, id)| *id);
+
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].0.as_deref(), Some("turn-1"));
+        assert_eq!(
+            groups[0]
+                .1
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>(),
+            vec!["a", "d"]
+        );
+        assert_eq!(groups[1].0, None);
+        assert_eq!(groups[1].1.len(), 2);
+        assert_eq!(groups[2].0.as_deref(), Some("turn-2"));
+    }
+
+    #[test]
+    fn test_to_mermaid() {
+        let steps = vec![
+            step("user", "assistant", "UserMessage"),
+            step("api", BROADCAST, "Request"),
+            step("assistant", "user", "Agent: done; ok"),
+        ];
+        let diagram = to_mermaid(&steps);
+
+        assert!(diagram.starts_with("sequenceDiagram\n"));
+        assert!(diagram.contains("participant P0 as user\n"));
+        assert!(diagram.contains("participant P1 as api\n"));
+        assert!(diagram.contains("participant P2 as assistant\n"));
+        assert!(diagram.contains("P0->>P2: UserMessage\n"));
+        assert!(diagram.contains("Note over P1: Request (broadcast)\n"));
+        assert!(diagram.contains("P2->>P0: Agent  done  ok\n"));
+    }
+}
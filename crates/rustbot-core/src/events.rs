@@ -0,0 +1,813 @@
+// Event system for asynchronous communication between components
+// Implements event bus pattern using tokio broadcast channels
+
+use crate::handoff::Handoff;
+use chrono;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Maximum capacity for the event broadcast channel
+const EVENT_CHANNEL_CAPACITY: usize = 1000;
+
+/// Environment variable overriding the event channel capacity
+pub const EVENT_CAPACITY_ENV_VAR: &str = "RUSTBOT_EVENT_BUS_CAPACITY";
+
+/// Monotonic counter used to keep correlation IDs unique within a process
+static CORRELATION_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Main event structure containing all information about an event
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub source: String,
+    pub destination: String,
+    pub kind: EventKind,
+    pub timestamp: chrono::DateTime<chrono::Local>,
+    /// Groups every event that belongs to a single user turn
+    /// (user message → status changes → tool execution → final response)
+    pub correlation_id: Option<String>,
+}
+
+impl Event {
+    /// Create a new event
+    pub fn new(source: String, destination: String, kind: EventKind) -> Self {
+        Self {
+            source,
+            destination,
+            kind,
+            timestamp: chrono::Local::now(),
+            correlation_id: None,
+        }
+    }
+
+    /// Attach a correlation ID to this event (builder style)
+    ///
+    /// Passing `None` leaves the event uncorrelated, which lets callers
+    /// forward an optional ID without branching.
+    pub fn with_correlation_id(mut self, correlation_id: Option<String>) -> Self {
+        self.correlation_id = correlation_id;
+        self
+    }
+
+    /// Check if this event is targeted to a specific destination
+    pub fn is_for(&self, target: &str) -> bool {
+        self.destination == "broadcast" || self.destination == target
+    }
+}
+
+/// Generate a new correlation ID for a user turn
+///
+/// Format: `turn-<unix millis>-<counter>`. The counter guarantees uniqueness
+/// within the process even when two turns start in the same millisecond.
+pub fn new_correlation_id() -> String {
+    let seq = CORRELATION_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("turn-{}-{}", chrono::Utc::now().timestamp_millis(), seq)
+}
+
+/// Types of events that can be sent through the event bus
+#[derive(Debug, Clone)]
+pub enum EventKind {
+    /// Message from user to agent
+    UserMessage(String),
+
+    /// Message from agent to user
+    AgentMessage { agent_id: String, content: String },
+
+    /// Agent status update
+    AgentStatusChange {
+        agent_id: String,
+        status: AgentStatus,
+    },
+
+    /// Tool call made by an agent, published when it starts and again with
+    /// the result when it finishes
+    ToolCall {
+        /// API session whose turn made the call (see `RustbotApi::sessions`)
+        session_id: String,
+        call: ToolCallRecord,
+    },
+
+    /// An agent handed a session's conversation to another one (see
+    /// `crate::handoff`)
+    Handoff {
+        /// API session that changed hands
+        session_id: String,
+        handoff: Handoff,
+    },
+
+    /// System command (clear conversation, save state, etc.)
+    SystemCommand(SystemCommand),
+
+    /// MCP plugin lifecycle events (Phase 3)
+    McpPluginEvent(McpPluginEvent),
+
+    /// A subscriber fell behind and missed events (see `EventSubscriber`)
+    SubscriberLagged { subscriber: String, skipped: u64 },
+
+    /// RPC request addressed to a component (see `EventBus::request`)
+    ///
+    /// The reply is a `Response` event carrying the same correlation ID.
+    Request {
+        method: String,
+        params: serde_json::Value,
+    },
+
+    /// RPC reply to a `Request` (Err carries a human-readable message)
+    Response {
+        result: Result<serde_json::Value, String>,
+    },
+
+    /// Test event for initial implementation
+    Test(String),
+}
+
+/// A tool call and, once it has finished, its outcome
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCallRecord {
+    /// ID the model gave the call
+    pub id: String,
+    pub name: String,
+    pub arguments: serde_json::Value,
+    /// None while the tool runs; Err holds the error message
+    pub result: Option<Result<String, String>>,
+    /// Time the tool took (zero while it runs)
+    pub duration: Duration,
+    /// Set when the result looks like a prompt injection (see `crate::untrusted`)
+    pub warning: Option<String>,
+}
+
+/// MCP Plugin events for lifecycle and state changes
+///
+/// These events allow UI and other components to react to plugin state changes,
+/// errors, and health status updates.
+#[derive(Debug, Clone)]
+pub enum McpPluginEvent {
+    /// Plugin successfully started
+    Started {
+        plugin_id: String,
+        tool_count: usize,
+    },
+
+    /// Plugin successfully stopped
+    Stopped { plugin_id: String },
+
+    /// Plugin encountered an error
+    Error { plugin_id: String, message: String },
+
+    /// Plugin tools changed (after reload or initialization)
+    ToolsChanged {
+        plugin_id: String,
+        tool_count: usize,
+    },
+
+    /// Plugin health status update
+    HealthStatus {
+        plugin_id: String,
+        status: PluginHealthStatus,
+    },
+
+    /// Plugin restart attempted
+    RestartAttempt {
+        plugin_id: String,
+        attempt: u32,
+        max_retries: u32,
+    },
+
+    /// Configuration reloaded
+    ConfigReloaded {
+        plugins_added: Vec<String>,
+        plugins_removed: Vec<String>,
+        plugins_updated: Vec<String>,
+    },
+}
+
+/// Health status for MCP plugins
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginHealthStatus {
+    /// Plugin is healthy and responding
+    Healthy,
+
+    /// Plugin process exists but not responding to requests
+    Unresponsive,
+
+    /// Plugin process has died
+    Dead,
+}
+
+/// Agent status states
+#[derive(Debug, Clone)]
+pub enum AgentStatus {
+    Idle,
+    Thinking,
+    Responding,
+    ExecutingTool(String), // Tool name being executed
+    Error(String),
+}
+
+/// System-level commands
+#[derive(Debug, Clone)]
+pub enum SystemCommand {
+    ClearConversation,
+    SaveState,
+    LoadState,
+    ReloadConfig,
+}
+
+/// Counters shared by the bus and its named subscribers
+#[derive(Debug, Default)]
+struct EventBusMetrics {
+    published: AtomicU64,
+    dropped: AtomicU64,
+    lag_incidents: AtomicU64,
+}
+
+/// Point-in-time snapshot of event bus health
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventBusStats {
+    /// Channel capacity (events buffered per subscriber)
+    pub capacity: usize,
+    /// Currently active subscribers
+    pub subscribers: usize,
+    /// Events successfully published
+    pub published: u64,
+    /// Events missed by lagging subscribers (summed over subscribers)
+    pub dropped: u64,
+    /// Number of times a subscriber reported lag
+    pub lag_incidents: u64,
+    /// Events still waiting for the slowest subscriber (queue depth)
+    pub queued: usize,
+}
+
+/// Event bus for publishing and subscribing to events
+pub struct EventBus {
+    tx: broadcast::Sender<Event>,
+    capacity: usize,
+    metrics: Arc<EventBusMetrics>,
+}
+
+impl EventBus {
+    /// Create a new event bus with default capacity
+    pub fn new() -> Self {
+        Self::with_capacity(EVENT_CHANNEL_CAPACITY)
+    }
+
+    /// Create a new event bus with custom capacity
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (tx, _rx) = broadcast::channel(capacity);
+        Self {
+            tx,
+            capacity,
+            metrics: Arc::new(EventBusMetrics::default()),
+        }
+    }
+
+    /// Create an event bus sized from `RUSTBOT_EVENT_BUS_CAPACITY`
+    ///
+    /// Falls back to the default capacity when the variable is unset, zero
+    /// or not a number.
+    pub fn from_env() -> Self {
+        let capacity = match std::env::var(EVENT_CAPACITY_ENV_VAR) {
+            Ok(value) => match value.trim().parse::<usize>() {
+                Ok(capacity) if capacity > 0 => capacity,
+                _ => {
+                    tracing::warn!(
+                        "Invalid {}='{}', using default capacity {}",
+                        EVENT_CAPACITY_ENV_VAR,
+                        value,
+                        EVENT_CHANNEL_CAPACITY
+                    );
+                    EVENT_CHANNEL_CAPACITY
+                }
+            },
+            Err(_) => EVENT_CHANNEL_CAPACITY,
+        };
+        Self::with_capacity(capacity)
+    }
+
+    /// Subscribe to events - returns a receiver
+    ///
+    /// Callers handle `RecvError::Lagged` themselves. Prefer `subscribe_named`
+    /// for long-lived consumers so lag is reported and counted.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.tx.subscribe()
+    }
+
+    /// Subscribe with lag detection
+    ///
+    /// The returned subscriber skips past missed events, records them in the
+    /// bus metrics and publishes a `SubscriberLagged` warning event.
+    ///
+    /// # Arguments
+    /// * `name` - Subscriber name used in logs and warning events
+    pub fn subscribe_named(&self, name: &str) -> EventSubscriber {
+        EventSubscriber {
+            name: name.to_string(),
+            rx: self.tx.subscribe(),
+            tx: self.tx.clone(),
+            metrics: Arc::clone(&self.metrics),
+            pending_lag: None,
+        }
+    }
+
+    /// Publish an event to all subscribers
+    pub fn publish(&self, event: Event) -> Result<usize, EventError> {
+        let delivered = self.tx.send(event).map_err(|_| EventError::SendFailed)?;
+        self.metrics.published.fetch_add(1, Ordering::Relaxed);
+        Ok(delivered)
+    }
+
+    /// Get channel capacity and drop counters
+    pub fn stats(&self) -> EventBusStats {
+        EventBusStats {
+            capacity: self.capacity,
+            subscribers: self.tx.receiver_count(),
+            published: self.metrics.published.load(Ordering::Relaxed),
+            dropped: self.metrics.dropped.load(Ordering::Relaxed),
+            lag_incidents: self.metrics.lag_incidents.load(Ordering::Relaxed),
+            queued: self.tx.len(),
+        }
+    }
+
+    /// Get a clone of the sender for publishing from async tasks
+    pub fn sender(&self) -> broadcast::Sender<Event> {
+        self.tx.clone()
+    }
+
+    /// Get the number of active subscribers
+    pub fn subscriber_count(&self) -> usize {
+        self.tx.receiver_count()
+    }
+
+    /// Send an RPC request and wait for the typed reply
+    ///
+    /// Publishes a `Request` event to `destination` with a fresh correlation ID,
+    /// then waits for the matching `Response`. The reply subscription is created
+    /// before publishing so a fast responder can't be missed.
+    ///
+    /// # Arguments
+    /// * `source` - Name of the requesting component (the reply is addressed to it)
+    /// * `destination` - Component expected to answer (e.g. "mcp_manager")
+    /// * `method` - Method name understood by the responder
+    /// * `params` - JSON parameters
+    /// * `timeout` - Maximum time to wait for the reply
+    ///
+    /// # Errors
+    /// - `EventError::Timeout` if nobody answers in time
+    /// - `EventError::RequestFailed` if the responder returned an error
+    ///   or the reply couldn't be deserialized into `T`
+    pub async fn request<T: DeserializeOwned>(
+        &self,
+        source: &str,
+        destination: &str,
+        method: &str,
+        params: serde_json::Value,
+        timeout: Duration,
+    ) -> Result<T, EventError> {
+        let correlation_id = new_correlation_id();
+        let mut rx = self.subscribe();
+
+        self.publish(
+            Event::new(
+                source.to_string(),
+                destination.to_string(),
+                EventKind::Request {
+                    method: method.to_string(),
+                    params,
+                },
+            )
+            .with_correlation_id(Some(correlation_id.clone())),
+        )?;
+
+        let wait_for_reply = async {
+            loop {
+                match rx.recv().await {
+                    Ok(event) if event.correlation_id.as_deref() == Some(&correlation_id) => {
+                        if let EventKind::Response { result } = event.kind {
+                            return result;
+                        }
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => {
+                        return Err(EventError::ChannelClosed.to_string());
+                    }
+                }
+            }
+        };
+
+        let value = tokio::time::timeout(timeout, wait_for_reply)
+            .await
+            .map_err(|_| EventError::Timeout)?
+            .map_err(EventError::RequestFailed)?;
+
+        serde_json::from_value(value).map_err(|e| EventError::RequestFailed(e.to_string()))
+    }
+
+    /// Reply to a `Request` event
+    ///
+    /// The response is addressed to the request's source and carries its
+    /// correlation ID so `request()` can match it.
+    pub fn respond(
+        &self,
+        request: &Event,
+        responder: &str,
+        result: Result<serde_json::Value, String>,
+    ) -> Result<usize, EventError> {
+        self.publish(
+            Event::new(
+                responder.to_string(),
+                request.source.clone(),
+                EventKind::Response { result },
+            )
+            .with_correlation_id(request.correlation_id.clone()),
+        )
+    }
+}
+
+/// Event receiver that detects and reports lag
+///
+/// Wraps a broadcast receiver: when the subscriber falls more than the channel
+/// capacity behind, tokio drops the oldest events. Instead of surfacing that
+/// as an error (which ends naive `while let Ok(..)` loops), the subscriber
+/// counts the drop, publishes a `SubscriberLagged` event and keeps receiving.
+///
+/// The warning is published only after the next event has been read: at that
+/// point the slot it evicts has already been consumed, so reporting lag can't
+/// make this subscriber lag again.
+pub struct EventSubscriber {
+    name: String,
+    rx: broadcast::Receiver<Event>,
+    tx: broadcast::Sender<Event>,
+    metrics: Arc<EventBusMetrics>,
+    pending_lag: Option<u64>,
+}
+
+impl EventSubscriber {
+    /// Wait for the next event; returns None once the bus is closed
+    pub async fn recv(&mut self) -> Option<Event> {
+        loop {
+            match self.rx.recv().await {
+                Ok(event) => {
+                    self.flush_lag_warning();
+                    return Some(event);
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => self.record_lag(skipped),
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Get the next event without waiting; returns None if none is pending
+    pub fn try_recv(&mut self) -> Option<Event> {
+        loop {
+            match self.rx.try_recv() {
+                Ok(event) => {
+                    self.flush_lag_warning();
+                    return Some(event);
+                }
+                Err(broadcast::error::TryRecvError::Lagged(skipped)) => self.record_lag(skipped),
+                Err(_) => return None,
+            }
+        }
+    }
+
+    /// Subscriber name (as passed to `subscribe_named`)
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn record_lag(&mut self, skipped: u64) {
+        self.metrics.dropped.fetch_add(skipped, Ordering::Relaxed);
+        self.metrics.lag_incidents.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!(
+            "⚠️  Event subscriber '{}' lagged, {} events dropped",
+            self.name,
+            skipped
+        );
+        *self.pending_lag.get_or_insert(0) += skipped;
+    }
+
+    fn flush_lag_warning(&mut self) {
+        let Some(skipped) = self.pending_lag.take() else {
+            return;
+        };
+
+        let warning = Event::new(
+            "event_bus".to_string(),
+            "broadcast".to_string(),
+            EventKind::SubscriberLagged {
+                subscriber: self.name.clone(),
+                skipped,
+            },
+        );
+        if self.tx.send(warning).is_ok() {
+            self.metrics.published.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Errors that can occur during event operations
+#[derive(Debug, Clone)]
+pub enum EventError {
+    SendFailed,
+    ReceiveFailed,
+    ChannelClosed,
+    Timeout,
+    RequestFailed(String),
+}
+
+impl fmt::Display for EventError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventError::SendFailed => write!(f, "Failed to send event"),
+            EventError::ReceiveFailed => write!(f, "Failed to receive event"),
+            EventError::ChannelClosed => write!(f, "Event channel closed"),
+            EventError::Timeout => write!(f, "Timed out waiting for reply"),
+            EventError::RequestFailed(msg) => write!(f, "Request failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for EventError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_creation() {
+        let event = Event::new(
+            "user".to_string(),
+            "agent".to_string(),
+            EventKind::UserMessage("Hello".to_string()),
+        );
+
+        assert_eq!(event.source, "user");
+        assert_eq!(event.destination, "agent");
+        assert!(matches!(event.kind, EventKind::UserMessage(_)));
+        assert!(event.correlation_id.is_none());
+    }
+
+    #[test]
+    fn test_event_correlation_id() {
+        let id = new_correlation_id();
+        let event = Event::new(
+            "user".to_string(),
+            "agent".to_string(),
+            EventKind::UserMessage("Hello".to_string()),
+        )
+        .with_correlation_id(Some(id.clone()));
+
+        assert_eq!(event.correlation_id.as_deref(), Some(id.as_str()));
+        assert!(id.starts_with("turn-"));
+        assert_ne!(id, new_correlation_id());
+    }
+
+    #[test]
+    fn test_correlation_id_survives_bus() {
+        let bus = EventBus::new();
+        let mut rx = bus.subscribe();
+
+        let event = Event::new(
+            "agent".to_string(),
+            "broadcast".to_string(),
+            EventKind::Test("status".to_string()),
+        )
+        .with_correlation_id(Some("turn-1-0".to_string()));
+        bus.publish(event).unwrap();
+
+        let received = rx.try_recv().unwrap();
+        assert_eq!(received.correlation_id.as_deref(), Some("turn-1-0"));
+    }
+
+    #[test]
+    fn test_event_targeting() {
+        let targeted = Event::new(
+            "user".to_string(),
+            "agent1".to_string(),
+            EventKind::Test("test".to_string()),
+        );
+
+        assert!(targeted.is_for("agent1"));
+        assert!(!targeted.is_for("agent2"));
+
+        let broadcast = Event::new(
+            "system".to_string(),
+            "broadcast".to_string(),
+            EventKind::Test("test".to_string()),
+        );
+
+        assert!(broadcast.is_for("agent1"));
+        assert!(broadcast.is_for("agent2"));
+        assert!(broadcast.is_for("anyone"));
+    }
+
+    #[test]
+    fn test_event_bus_creation() {
+        let bus = EventBus::new();
+        assert_eq!(bus.subscriber_count(), 0);
+    }
+
+    #[test]
+    fn test_event_bus_subscription() {
+        let bus = EventBus::new();
+        let _rx1 = bus.subscribe();
+        let _rx2 = bus.subscribe();
+
+        assert_eq!(bus.subscriber_count(), 2);
+    }
+
+    #[test]
+    fn test_event_bus_publish_receive() {
+        let bus = EventBus::new();
+        let mut rx = bus.subscribe();
+
+        let event = Event::new(
+            "test".to_string(),
+            "dest".to_string(),
+            EventKind::Test("message".to_string()),
+        );
+
+        let result = bus.publish(event.clone());
+        assert!(result.is_ok());
+
+        let received = rx.try_recv();
+        assert!(received.is_ok());
+
+        let received_event = received.unwrap();
+        assert_eq!(received_event.source, "test");
+        assert_eq!(received_event.destination, "dest");
+    }
+
+    #[test]
+    fn test_event_bus_multiple_subscribers() {
+        let bus = EventBus::new();
+        let mut rx1 = bus.subscribe();
+        let mut rx2 = bus.subscribe();
+
+        let event = Event::new(
+            "sender".to_string(),
+            "broadcast".to_string(),
+            EventKind::Test("broadcast message".to_string()),
+        );
+
+        bus.publish(event).unwrap();
+
+        // Both subscribers should receive the event
+        assert!(rx1.try_recv().is_ok());
+        assert!(rx2.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_request_reply() {
+        let bus = std::sync::Arc::new(EventBus::new());
+        let mut server_rx = bus.subscribe();
+
+        let server_bus = std::sync::Arc::clone(&bus);
+        tokio::spawn(async move {
+            while let Ok(event) = server_rx.recv().await {
+                if let EventKind::Request { method, params } = &event.kind {
+                    if event.is_for("calculator") && method == "add" {
+                        let sum = params["a"].as_i64().unwrap() + params["b"].as_i64().unwrap();
+                        let _ = server_bus.respond(&event, "calculator", Ok(sum.into()));
+                    }
+                }
+            }
+        });
+
+        let sum: i64 = bus
+            .request(
+                "test",
+                "calculator",
+                "add",
+                serde_json::json!({"a": 2, "b": 3}),
+                Duration::from_secs(1),
+            )
+            .await
+            .unwrap();
+        assert_eq!(sum, 5);
+    }
+
+    #[tokio::test]
+    async fn test_request_error_reply() {
+        let bus = std::sync::Arc::new(EventBus::new());
+        let mut server_rx = bus.subscribe();
+
+        let server_bus = std::sync::Arc::clone(&bus);
+        tokio::spawn(async move {
+            while let Ok(event) = server_rx.recv().await {
+                if matches!(event.kind, EventKind::Request { .. }) {
+                    let _ = server_bus.respond(&event, "server", Err("unknown method".into()));
+                }
+            }
+        });
+
+        let result: Result<serde_json::Value, _> = bus
+            .request(
+                "test",
+                "server",
+                "nope",
+                serde_json::Value::Null,
+                Duration::from_secs(1),
+            )
+            .await;
+        assert!(matches!(result, Err(EventError::RequestFailed(msg)) if msg == "unknown method"));
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let bus = EventBus::new();
+
+        let result: Result<serde_json::Value, _> = bus
+            .request(
+                "test",
+                "nobody",
+                "ping",
+                serde_json::Value::Null,
+                Duration::from_millis(20),
+            )
+            .await;
+        assert!(matches!(result, Err(EventError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn test_named_subscriber_reports_lag() {
+        let bus = EventBus::with_capacity(2);
+        let mut slow = bus.subscribe_named("slow");
+        let mut observer = bus.subscribe();
+
+        for i in 0..5 {
+            bus.publish(Event::new(
+                "test".to_string(),
+                "broadcast".to_string(),
+                EventKind::Test(i.to_string()),
+            ))
+            .unwrap();
+        }
+
+        // Slow subscriber skips dropped events and keeps receiving
+        let event = slow.recv().await.unwrap();
+        assert!(matches!(event.kind, EventKind::Test(_)));
+
+        let stats = bus.stats();
+        assert_eq!(stats.capacity, 2);
+        assert_eq!(stats.dropped, 3);
+        assert_eq!(stats.lag_incidents, 1);
+
+        // A warning event is published for other observers
+        let mut saw_warning = false;
+        loop {
+            match observer.try_recv() {
+                Ok(event) => {
+                    if let EventKind::SubscriberLagged {
+                        subscriber,
+                        skipped,
+                    } = event.kind
+                    {
+                        assert_eq!(subscriber, "slow");
+                        assert_eq!(skipped, 3);
+                        saw_warning = true;
+                    }
+                }
+                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        assert!(saw_warning);
+    }
+
+    #[test]
+    fn test_stats_count_published() {
+        let bus = EventBus::new();
+        let _rx = bus.subscribe();
+        bus.publish(Event::new(
+            "a".to_string(),
+            "b".to_string(),
+            EventKind::Test("x".to_string()),
+        ))
+        .unwrap();
+
+        let stats = bus.stats();
+        assert_eq!(stats.published, 1);
+        assert_eq!(stats.dropped, 0);
+        assert_eq!(stats.subscribers, 1);
+        // Not yet received by `_rx`
+        assert_eq!(stats.queued, 1);
+    }
+
+    #[test]
+    fn test_try_recv_empty() {
+        let bus = EventBus::new();
+        let mut sub = bus.subscribe_named("ui");
+        assert!(sub.try_recv().is_none());
+        assert_eq!(sub.name(), "ui");
+    }
+}
@@ -0,0 +1,179 @@
+// Rated answers across saved conversations, for evaluating agents
+//
+// Design Decision: Ratings live on the session messages; this module only
+// collects them
+//
+// Rationale: A 👍/👎 (and optional comment) is stored with the answer it
+// rates, so it survives restores, shows up in conversation exports and goes
+// away with the conversation. People tuning an agent's prompt want the
+// ratings in one place instead: `collect` walks the saved sessions and pairs
+// each rated answer with the user message it answered, the agent and model,
+// and `to_jsonl` writes one record per line for evaluation scripts.
+// `summarize` counts ratings per agent and model to compare prompt or model
+// changes at a glance.
+//
+// Trade-offs:
+// - The prompt is the displayed user message; tool calls behind the answer
+//   are in the conversation export, not here
+// - Messages saved before agent/model attribution use the session's agent and
+//   `usage::UNKNOWN_MODEL`
+
+use crate::services::{ConversationSession, Rating};
+use crate::usage::UNKNOWN_MODEL;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// One rated answer, as written to the JSONL export
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeedbackRecord {
+    pub session_id: String,
+    pub session_title: String,
+    pub agent_id: String,
+    pub model: String,
+
+    /// User message the answer replied to (empty if there was none)
+    pub prompt: String,
+    pub response: String,
+    pub rating: Rating,
+    pub comment: String,
+
+    /// When the answer was requested
+    pub answered_at: DateTime<Utc>,
+    pub rated_at: DateTime<Utc>,
+}
+
+/// Rating counts for one agent and model
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeedbackSummary {
+    pub up: usize,
+    pub down: usize,
+}
+
+impl FeedbackSummary {
+    /// Share of positive ratings, from 0.0 to 1.0 (None without ratings)
+    pub fn approval(&self) -> Option<f32> {
+        let total = self.up + self.down;
+        (total > 0).then(|| self.up as f32 / total as f32)
+    }
+}
+
+/// Every rated answer in the sessions, oldest first
+pub fn collect(sessions: &[ConversationSession]) -> Vec<FeedbackRecord> {
+    let mut records = Vec::new();
+    for session in sessions {
+        let mut prompt = "";
+        for message in &session.messages {
+            if message.role == "user" {
+                prompt = &message.content;
+                continue;
+            }
+            let Some(feedback) = &message.feedback else {
+                continue;
+            };
+            records.push(FeedbackRecord {
+                session_id: session.id.clone(),
+                session_title: session.title.clone(),
+                agent_id: message
+                    .agent_id
+                    .clone()
+                    .unwrap_or_else(|| session.agent_id.clone()),
+                model: message
+                    .model
+                    .clone()
+                    .unwrap_or_else(|| UNKNOWN_MODEL.to_string()),
+                prompt: prompt.to_string(),
+                response: message.content.clone(),
+                rating: feedback.rating,
+                comment: feedback.comment.clone(),
+                answered_at: message.timestamp,
+                rated_at: feedback.rated_at,
+            });
+        }
+    }
+    records.sort_by_key(|r| r.answered_at);
+    records
+}
+
+/// Rating counts per (agent, model)
+pub fn summarize(records: &[FeedbackRecord]) -> BTreeMap<(String, String), FeedbackSummary> {
+    let mut summary: BTreeMap<(String, String), FeedbackSummary> = BTreeMap::new();
+    for record in records {
+        let entry = summary
+            .entry((record.agent_id.clone(), record.model.clone()))
+            .or_default();
+        match record.rating {
+            Rating::Up => entry.up += 1,
+            Rating::Down => entry.down += 1,
+        }
+    }
+    summary
+}
+
+/// One JSON object per line
+///
+/// # Errors
+/// - Serialization failure
+pub fn to_jsonl(records: &[FeedbackRecord]) -> Result<String> {
+    let mut out = String::new();
+    for record in records {
+        out.push_str(&serde_json::to_string(record).context("Failed to serialize feedback")?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{MessageFeedback, SessionMessage};
+
+    fn message(role: &str, content: &str, feedback: Option<Rating>) -> SessionMessage {
+        SessionMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: Utc::now(),
+            input_tokens: None,
+            output_tokens: None,
+            agent_id: None,
+            model: Some("openai/gpt-4o".to_string()),
+            first_token_ms: None,
+            generation_ms: None,
+            pinned: false,
+            feedback: feedback.map(MessageFeedback::new),
+        }
+    }
+
+    #[test]
+    fn test_collect_pairs_answers_with_prompts() {
+        let mut session = ConversationSession::new("assistant");
+        session.messages = vec![
+            message("user", "What is 2+2?", None),
+            message("assistant", "4", Some(Rating::Up)),
+            message("user", "And 3+3?", None),
+            message("assistant", "5", Some(Rating::Down)),
+            message("user", "Thanks", None),
+            message("assistant", "You're welcome", None),
+        ];
+
+        let records = collect(&[session]);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].prompt, "What is 2+2?");
+        assert_eq!(records[1].prompt, "And 3+3?");
+        assert_eq!(records[1].rating, Rating::Down);
+        assert_eq!(records[1].agent_id, "assistant");
+
+        let summary = summarize(&records);
+        let counts = &summary[&("assistant".to_string(), "openai/gpt-4o".to_string())];
+        assert_eq!((counts.up, counts.down), (1, 1));
+        assert_eq!(counts.approval(), Some(0.5));
+        assert_eq!(FeedbackSummary::default().approval(), None);
+
+        let jsonl = to_jsonl(&records).unwrap();
+        assert_eq!(jsonl.lines().count(), 2);
+        let first: serde_json::Value = serde_json::from_str(jsonl.lines().next().unwrap()).unwrap();
+        assert_eq!(first["rating"], "up");
+        assert_eq!(first["response"], "4");
+    }
+}
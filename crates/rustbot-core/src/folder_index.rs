@@ -0,0 +1,542 @@
+// Folder Q&A - a keyword index over a directory's text files
+//
+// Design Decision: Files are cut into fixed line ranges and searched with
+// TF-IDF over the same terms as the History search; the best excerpts go to
+// the model with their paths and line numbers
+//
+// Rationale: "Chat with folder" answers questions about a codebase or a folder
+// of notes without the user pasting files. Retrieval has to work offline and
+// with every provider, so it uses no embedding model: a question's words are
+// looked up in an index kept per folder, and the excerpts that match best are
+// sent with the question (`RustbotApi::set_retrieved_in`). Each excerpt is
+// labelled `path:start-end`, and the model is asked to cite those labels, so
+// answers point at the lines they rely on.
+//
+// Building walks the folder like git does: `.gitignore` files (in the root and
+// in subdirectories) are respected, `.git` is skipped, and binary or very
+// large files are left out. Refreshing only re-reads files whose modification
+// time changed.
+//
+// Storage: the active project's index directory (see
+// `ProjectStore::index_dir`) for its folder, otherwise
+// ~/.rustbot/folder_indexes/<hash of the path>.json
+//
+// Trade-offs:
+// - Keyword matching misses synonyms ("car" won't find "vehicle"); identifiers
+//   and distinctive words, the usual questions about code, work well
+// - .gitignore support covers the common syntax (`*`, `**`, `?`, `!`,
+//   trailing `/`, leading `/`), not git's global or info/exclude files
+// - The index is rebuilt on request, not watched
+
+use crate::error::{Result, RustbotError};
+use crate::services::session_index::tokenize;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Lines per excerpt
+const CHUNK_LINES: usize = 40;
+
+/// Files larger than this are left out (bytes)
+const MAX_FILE_BYTES: u64 = 512 * 1024;
+
+/// Stop indexing after this many files
+const MAX_FILES: usize = 5_000;
+
+/// Excerpts sent with a question
+pub const DEFAULT_EXCERPTS: usize = 6;
+
+/// Tells the model how to use the excerpts
+const EXCERPTS_PREAMBLE: &str = "Excerpts from the folder the user is asking about, found by \
+    searching it for the question. Answer from them, and cite the files and lines you use as \
+    `path:start-end` exactly as labelled. If the excerpts don't contain the answer, say so \
+    instead of guessing.";
+
+/// A file in the index
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexedFile {
+    /// Path relative to the folder, with `/` separators
+    pub path: String,
+
+    /// Modification time (seconds since the epoch) when indexed
+    pub modified: u64,
+}
+
+/// A line range of a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chunk {
+    /// Index into `FolderIndex::files`
+    pub file: usize,
+
+    /// First and last line, 1-based and inclusive
+    pub start_line: usize,
+    pub end_line: usize,
+
+    pub text: String,
+}
+
+/// An excerpt matching a question
+#[derive(Debug, Clone, PartialEq)]
+pub struct Excerpt {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+    pub score: f32,
+}
+
+impl Excerpt {
+    /// Citation label, e.g. "src/main.rs:41-80"
+    pub fn label(&self) -> String {
+        format!("{}:{}-{}", self.path, self.start_line, self.end_line)
+    }
+}
+
+/// Index of one folder
+///
+/// Usage:
+///     let index = FolderIndex::build(&root, None)?;
+///     index.save(&FolderIndex::default_path(&root))?;
+///     let excerpts = index.search("where is the config loaded", DEFAULT_EXCERPTS);
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FolderIndex {
+    pub root: PathBuf,
+    pub built_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub files: Vec<IndexedFile>,
+    pub chunks: Vec<Chunk>,
+
+    /// Term counts per chunk, rebuilt after loading
+    #[serde(skip)]
+    terms: Vec<HashMap<String, u32>>,
+}
+
+impl FolderIndex {
+    /// Where the index of a folder outside a project is kept:
+    /// ~/.rustbot/folder_indexes/<hash>.json
+    pub fn default_path(root: &Path) -> PathBuf {
+        let hash = format!("{:x}", Sha256::digest(root.to_string_lossy().as_bytes()));
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("folder_indexes")
+            .join(format!("{}.json", &hash[..16]))
+    }
+
+    /// Index a folder
+    ///
+    /// # Arguments
+    /// * `root` - Folder to index
+    /// * `previous` - Earlier index of the same folder; files whose
+    ///   modification time is unchanged are taken from it instead of re-read
+    ///
+    /// # Errors
+    /// - `root` is not a readable directory
+    pub fn build(root: &Path, previous: Option<&FolderIndex>) -> Result<Self> {
+        if !root.is_dir() {
+            return Err(RustbotError::ConfigError(format!(
+                "{} is not a folder",
+                root.display()
+            )));
+        }
+
+        let mut reusable: HashMap<&str, (u64, Vec<&Chunk>)> = HashMap::new();
+        if let Some(previous) = previous.filter(|p| p.root == root) {
+            for chunk in &previous.chunks {
+                let file = &previous.files[chunk.file];
+                reusable
+                    .entry(file.path.as_str())
+                    .or_insert_with(|| (file.modified, Vec::new()))
+                    .1
+                    .push(chunk);
+            }
+        }
+
+        let mut paths = Vec::new();
+        walk(root, "", &IgnoreRules::default(), &mut paths)?;
+        if paths.len() > MAX_FILES {
+            tracing::warn!(
+                "📂 {} has {} files; indexing the first {}",
+                root.display(),
+                paths.len(),
+                MAX_FILES
+            );
+            paths.truncate(MAX_FILES);
+        }
+
+        let mut index = Self {
+            root: root.to_path_buf(),
+            built_at: Some(chrono::Utc::now()),
+            ..Self::default()
+        };
+        for (path, modified) in paths {
+            let file = index.files.len();
+            match reusable.get(path.as_str()) {
+                Some((indexed, chunks)) if *indexed == modified => {
+                    index.chunks.extend(chunks.iter().map(|chunk| Chunk {
+                        file,
+                        ..(*chunk).clone()
+                    }));
+                }
+                _ => match read_text(&root.join(&path)) {
+                    Some(text) => index.chunks.extend(split(file, &text)),
+                    None => continue,
+                },
+            }
+            index.files.push(IndexedFile { path, modified });
+        }
+        index.count_terms();
+        Ok(index)
+    }
+
+    /// Load an index written by `save`
+    ///
+    /// # Returns
+    /// Ok(None) if the file doesn't exist
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        let mut index: Self = serde_json::from_str(&content)?;
+        index.count_terms();
+        Ok(Some(index))
+    }
+
+    /// Write the index, creating parent directories
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn count_terms(&mut self) {
+        self.terms = self
+            .chunks
+            .iter()
+            .map(|chunk| {
+                let mut counts = HashMap::new();
+                // The path counts too, so "config loader" finds config/loader.rs
+                let path = &self.files[chunk.file].path;
+                for term in tokenize(path).chain(tokenize(&chunk.text)) {
+                    *counts.entry(term).or_insert(0) += 1;
+                }
+                counts
+            })
+            .collect();
+    }
+
+    /// The excerpts that match a question best, best first
+    ///
+    /// Scored with TF-IDF: words that are rare in the folder weigh more than
+    /// words found everywhere.
+    pub fn search(&self, question: &str, limit: usize) -> Vec<Excerpt> {
+        let mut query: Vec<String> = tokenize(question).collect();
+        query.sort();
+        query.dedup();
+
+        let chunks = self.chunks.len() as f32;
+        let weights: Vec<(String, f32)> = query
+            .into_iter()
+            .filter_map(|term| {
+                let containing = self.terms.iter().filter(|t| t.contains_key(&term)).count();
+                (containing > 0).then(|| (term, (chunks / containing as f32).ln() + 1.0))
+            })
+            .collect();
+        if weights.is_empty() {
+            return Vec::new();
+        }
+
+        let mut scored: Vec<(usize, f32)> = self
+            .terms
+            .iter()
+            .enumerate()
+            .filter_map(|(i, counts)| {
+                let score: f32 = weights
+                    .iter()
+                    .filter_map(|(term, weight)| {
+                        counts.get(term).map(|&n| (1.0 + (n as f32).ln()) * weight)
+                    })
+                    .sum();
+                (score > 0.0).then_some((i, score))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(i, score)| {
+                let chunk = &self.chunks[i];
+                Excerpt {
+                    path: self.files[chunk.file].path.clone(),
+                    start_line: chunk.start_line,
+                    end_line: chunk.end_line,
+                    text: chunk.text.clone(),
+                    score,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The excerpts as a message for the model, or None if there are none
+pub fn excerpts_message(excerpts: &[Excerpt]) -> Option<String> {
+    if excerpts.is_empty() {
+        return None;
+    }
+    let blocks: Vec<String> = excerpts
+        .iter()
+        .map(|e| format!("{}\n```\n{}\n```", e.label(), e.text.trim_end()))
+        .collect();
+    Some(format!("{}\n\n{}", EXCERPTS_PREAMBLE, blocks.join("\n\n")))
+}
+
+/// Cut a file into `CHUNK_LINES` line ranges
+fn split(file: usize, text: &str) -> Vec<Chunk> {
+    let lines: Vec<&str> = text.lines().collect();
+    lines
+        .chunks(CHUNK_LINES)
+        .enumerate()
+        .filter(|(_, lines)| lines.iter().any(|l| !l.trim().is_empty()))
+        .map(|(n, lines)| Chunk {
+            file,
+            start_line: n * CHUNK_LINES + 1,
+            end_line: n * CHUNK_LINES + lines.len(),
+            text: lines.join("\n"),
+        })
+        .collect()
+}
+
+/// A file's text, or None if it is too large, binary or unreadable
+fn read_text(path: &Path) -> Option<String> {
+    let bytes = std::fs::read(path).ok()?;
+    if bytes.len() as u64 > MAX_FILE_BYTES || bytes.iter().take(8_000).any(|&b| b == 0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Collect the files under `dir` that aren't ignored, with their
+/// modification times
+///
+/// # Arguments
+/// * `dir` - Directory to read
+/// * `relative` - `dir` relative to the root ("" for the root)
+/// * `inherited` - Rules from the .gitignore files above `dir`
+fn walk(
+    dir: &Path,
+    relative: &str,
+    inherited: &IgnoreRules,
+    files: &mut Vec<(String, u64)>,
+) -> Result<()> {
+    let mut rules = inherited.clone();
+    if let Ok(content) = std::fs::read_to_string(dir.join(".gitignore")) {
+        rules.add(relative, &content);
+    }
+
+    let mut entries: Vec<_> = std::fs::read_dir(dir)?.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name == ".git" {
+            continue;
+        }
+        let path = if relative.is_empty() {
+            name
+        } else {
+            format!("{}/{}", relative, name)
+        };
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        // Symlinks are skipped so a link can't lead out of the folder
+        if file_type.is_symlink() || rules.is_ignored(&path, file_type.is_dir()) {
+            continue;
+        }
+        if file_type.is_dir() {
+            walk(&entry.path(), &path, &rules, files)?;
+        } else if file_type.is_file() {
+            let modified = entry
+                .metadata()
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs());
+            files.push((path, modified));
+        }
+    }
+    Ok(())
+}
+
+/// One .gitignore line
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    /// Directory of the .gitignore, relative to the root ("" for the root)
+    base: String,
+    pattern: Regex,
+    negate: bool,
+    dir_only: bool,
+}
+
+/// Patterns from the .gitignore files that apply to a directory
+#[derive(Debug, Clone, Default)]
+struct IgnoreRules {
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreRules {
+    /// Add the lines of the .gitignore in `base`
+    fn add(&mut self, base: &str, content: &str) {
+        for line in content.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negate, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            // A slash anywhere but the end anchors the pattern to `base`
+            let anchored = line.contains('/');
+            let line = line.trim_start_matches('/');
+            let prefix = if anchored { "^" } else { "^(?:.*/)?" };
+            match Regex::new(&format!("{}{}$", prefix, glob_to_regex(line))) {
+                Ok(pattern) => self.rules.push(IgnoreRule {
+                    base: base.to_string(),
+                    pattern,
+                    negate,
+                    dir_only,
+                }),
+                Err(e) => tracing::debug!("Skipping .gitignore pattern {:?}: {}", line, e),
+            }
+        }
+    }
+
+    /// Whether a path (relative to the root) is ignored; the last matching
+    /// rule wins
+    fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let within = if rule.base.is_empty() {
+                Some(path)
+            } else {
+                path.strip_prefix(rule.base.as_str())
+                    .and_then(|rest| rest.strip_prefix('/'))
+            };
+            if within.is_some_and(|within| rule.pattern.is_match(within)) {
+                ignored = !rule.negate;
+            }
+        }
+        ignored
+    }
+}
+
+/// Translate a gitignore glob into a regex (without anchors)
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::new();
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    // "**/" matches zero or more directories
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => regex.push('['),
+            ']' => regex.push(']'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_gitignore_rules() {
+        let mut rules = IgnoreRules::default();
+        rules.add(
+            "",
+            "# build output\ntarget/\n*.log\n!keep.log\n/secrets.txt\ndocs/**/*.pdf",
+        );
+        rules.add("web", "node_modules");
+
+        assert!(rules.is_ignored("target", true));
+        assert!(!rules.is_ignored("target", false));
+        assert!(rules.is_ignored("logs/app.log", false));
+        assert!(!rules.is_ignored("logs/keep.log", false));
+        assert!(rules.is_ignored("secrets.txt", false));
+        assert!(!rules.is_ignored("config/secrets.txt", false));
+        assert!(rules.is_ignored("docs/a/b/manual.pdf", false));
+        assert!(rules.is_ignored("web/node_modules", true));
+        assert!(!rules.is_ignored("node_modules", true));
+    }
+
+    #[test]
+    fn test_build_search_and_refresh() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root, ".gitignore", "build/\n");
+        write(root, "build/out.txt", "the config loader, compiled");
+        write(
+            root,
+            "README.md",
+            "# Falcon\n\nA small tool for tracking birds.",
+        );
+        let mut loader = vec!["use std::fs;"; 45];
+        loader.push("fn load_config(path: &Path) -> Config { /* reads falcon.toml */ }");
+        write(root, "src/config/loader.rs", &loader.join("\n"));
+        write(root, "logo.png", "\u{0}PNG");
+
+        let index = FolderIndex::build(root, None).unwrap();
+        let paths: Vec<&str> = index.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![".gitignore", "README.md", "src/config/loader.rs"]
+        );
+
+        let excerpts = index.search("Where is the config loaded?", 3);
+        assert_eq!(excerpts[0].label(), "src/config/loader.rs:41-46");
+        assert!(excerpts[0].text.contains("fn load_config"));
+        assert!(index.search("zebra", 3).is_empty());
+
+        let message = excerpts_message(&excerpts).unwrap();
+        assert!(message.contains("src/config/loader.rs:41-46\n```\n"));
+        assert!(excerpts_message(&[]).is_none());
+
+        // Saved and loaded indexes search the same, and unchanged files are reused
+        let storage = tempfile::tempdir().unwrap();
+        let path = storage.path().join("index/folder.json");
+        index.save(&path).unwrap();
+        let loaded = FolderIndex::load(&path).unwrap().unwrap();
+        assert_eq!(loaded.search("falcon birds", 1)[0].path, "README.md");
+        let refreshed = FolderIndex::build(root, Some(&loaded)).unwrap();
+        assert_eq!(refreshed.chunks, index.chunks);
+    }
+}
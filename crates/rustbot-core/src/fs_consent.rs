@@ -0,0 +1,537 @@
+// Consent prompts for plugins and tools that touch the filesystem
+//
+// Design Decision: Ask once per plugin and directory, remember the answer at
+// the scope the user picks
+//
+// Rationale: MCP servers such as the filesystem plugin read and write wherever
+// the model points them, so a prompt injection in a web page can ask for
+// ~/.ssh as easily as for the project the user meant. Before a tool call whose
+// arguments name a path in a directory its plugin hasn't been granted, the API
+// asks the UI over the event bus (method `CONSENT_METHOD`, the same round trip
+// as email approvals) and waits. The user allows access for this session,
+// always, or denies. "Always" grants are stored in ~/.rustbot/fs_grants.json
+// and listed in Settings, where any grant can be revoked.
+//
+// Scope:
+// - A grant covers a directory and everything below it
+// - Write access includes read access
+// - Paths are found in tool arguments as absolute or `~/` strings; the access
+//   is write when the tool name suggests a change (write, edit, move, ...)
+//   and read otherwise
+// - `..` is resolved before comparing, so a granted directory can't be left
+//   through a path that starts inside it
+//
+// Trade-offs:
+// - Path detection is heuristic: relative paths and paths inside free text
+//   aren't seen, and neither are the directories a plugin is started with
+// - Symlinks aren't resolved; a link inside a granted directory is trusted
+// - Only set where a UI answers; headless modes have no one to ask, and an
+//   unanswered request counts as a denial
+
+use crate::audit::{self, AuditAction};
+use crate::error::Result as RustbotResult;
+use crate::events::{EventBus, EventError};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Event bus request method the UI answers with a `ConsentAnswer`
+pub const CONSENT_METHOD: &str = "approve_fs_access";
+
+/// How long to wait for the user before treating the request as denied
+const CONSENT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Tool name fragments that mean the tool changes files
+const WRITE_HINTS: &[&str] = &[
+    "write", "edit", "create", "move", "rename", "delete", "remove", "mkdir", "append", "save",
+    "patch",
+];
+
+/// Kind of access a tool call needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FsAccess {
+    Read,
+    Write,
+}
+
+impl FsAccess {
+    /// Access a tool needs, guessed from its name
+    pub fn for_tool(tool_name: &str) -> Self {
+        let name = tool_name.to_ascii_lowercase();
+        if WRITE_HINTS.iter().any(|hint| name.contains(hint)) {
+            FsAccess::Write
+        } else {
+            FsAccess::Read
+        }
+    }
+
+    /// How the access is described to the user
+    pub fn label(self) -> &'static str {
+        match self {
+            FsAccess::Read => "read",
+            FsAccess::Write => "read and write",
+        }
+    }
+}
+
+/// The user's answer to a consent request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentAnswer {
+    Deny,
+
+    /// Allow until Rustbot exits
+    Session,
+
+    /// Allow and remember the grant
+    Always,
+}
+
+/// Access to a directory (and everything below it) granted to a plugin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FsGrant {
+    /// MCP plugin ID
+    pub plugin: String,
+    pub directory: PathBuf,
+    pub access: FsAccess,
+    pub granted_at: DateTime<Utc>,
+}
+
+impl FsGrant {
+    /// "read access to ~/Projects for filesystem"
+    pub fn describe(&self) -> String {
+        format!(
+            "{} access to {} for {}",
+            self.access.label(),
+            display_path(&self.directory),
+            self.plugin
+        )
+    }
+
+    fn covers(&self, plugin: &str, directory: &Path, access: FsAccess) -> bool {
+        self.plugin == plugin && directory.starts_with(&self.directory) && self.access >= access
+    }
+}
+
+/// What the UI is asked to allow
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsentRequest {
+    pub plugin: String,
+    pub tool: String,
+    pub directory: PathBuf,
+    pub access: FsAccess,
+}
+
+impl ConsentRequest {
+    /// One-line question for the consent dialog
+    pub fn prompt(&self) -> String {
+        format!(
+            "Allow {} access to {} for {}?",
+            self.access.label(),
+            display_path(&self.directory),
+            self.plugin
+        )
+    }
+}
+
+/// On-disk format of the remembered grants
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct GrantFile {
+    grants: Vec<FsGrant>,
+}
+
+/// Filesystem grants and the consent round trip to the UI
+///
+/// Usage:
+///     let consent = Arc::new(FsConsent::load(FsConsent::default_path(), event_bus));
+///     api.set_fs_consent(Arc::clone(&consent));
+///     // Before running a tool:
+///     if let Some(denied) = consent.check_tool_call("filesystem", "read_file", args).await? {
+///         return Ok(denied);
+///     }
+pub struct FsConsent {
+    path: PathBuf,
+    event_bus: Arc<EventBus>,
+    saved: Mutex<Vec<FsGrant>>,
+    session: Mutex<Vec<FsGrant>>,
+}
+
+impl FsConsent {
+    /// Default grants file: ~/.rustbot/fs_grants.json
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("fs_grants.json")
+    }
+
+    /// Load remembered grants (none if the file is missing or unreadable)
+    ///
+    /// # Arguments
+    /// * `path` - Grants file, written when a grant is remembered or revoked
+    /// * `event_bus` - Bus the UI answers `CONSENT_METHOD` requests on
+    pub fn load(path: PathBuf, event_bus: Arc<EventBus>) -> Self {
+        let saved = if path.exists() {
+            std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| Ok(serde_json::from_str::<GrantFile>(&content)?))
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Failed to load filesystem grants: {}", e);
+                    GrantFile::default()
+                })
+                .grants
+        } else {
+            Vec::new()
+        };
+        Self {
+            path,
+            event_bus,
+            saved: Mutex::new(saved),
+            session: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Remembered grants, oldest first
+    pub fn saved_grants(&self) -> Vec<FsGrant> {
+        self.saved.lock().map(|g| g.clone()).unwrap_or_default()
+    }
+
+    /// Grants that end when Rustbot exits, oldest first
+    pub fn session_grants(&self) -> Vec<FsGrant> {
+        self.session.lock().map(|g| g.clone()).unwrap_or_default()
+    }
+
+    /// Whether `plugin` may access `directory`
+    pub fn is_granted(&self, plugin: &str, directory: &Path, access: FsAccess) -> bool {
+        let covered = |grants: &Mutex<Vec<FsGrant>>| {
+            grants
+                .lock()
+                .map(|g| {
+                    g.iter()
+                        .any(|grant| grant.covers(plugin, directory, access))
+                })
+                .unwrap_or(false)
+        };
+        covered(&self.session) || covered(&self.saved)
+    }
+
+    /// Withdraw a grant (remembered or session)
+    ///
+    /// # Errors
+    /// - The grants file can't be written
+    pub fn revoke(&self, grant: &FsGrant) -> RustbotResult<()> {
+        if let Ok(mut session) = self.session.lock() {
+            session.retain(|g| g != grant);
+        }
+        let mut saved = self.saved.lock().map_err(|_| {
+            crate::error::RustbotError::StorageError("Grant list is poisoned".to_string())
+        })?;
+        let before = saved.len();
+        saved.retain(|g| g != grant);
+        if saved.len() != before {
+            self.write(&saved)?;
+        }
+        tracing::info!("📂 Revoked {}", grant.describe());
+        audit::record(AuditAction::AccessRevoked, grant.describe());
+        Ok(())
+    }
+
+    /// Ask for consent where a tool call needs it
+    ///
+    /// Every directory named in `arguments` that `plugin` hasn't been granted
+    /// is put to the user, one at a time.
+    ///
+    /// # Returns
+    /// None if the call may run, or the message to return to the model
+    /// instead of running it
+    ///
+    /// # Errors
+    /// - The request couldn't be sent over the event bus
+    pub async fn check_tool_call(
+        &self,
+        plugin: &str,
+        tool: &str,
+        arguments: Option<&Value>,
+    ) -> Result<Option<String>> {
+        let Some(arguments) = arguments else {
+            return Ok(None);
+        };
+        let access = FsAccess::for_tool(tool);
+
+        let mut directories: Vec<PathBuf> = paths_in(arguments)
+            .iter()
+            .map(|path| scope_directory(path))
+            .collect();
+        directories.sort();
+        directories.dedup();
+
+        for directory in directories {
+            if self.is_granted(plugin, &directory, access) {
+                continue;
+            }
+            let request = ConsentRequest {
+                plugin: plugin.to_string(),
+                tool: tool.to_string(),
+                directory,
+                access,
+            };
+            let answer = self.ask(&request).await?;
+            if answer == ConsentAnswer::Deny {
+                tracing::info!("📂 Denied: {}", request.prompt());
+                return Ok(Some(format!(
+                    "The user denied {} access to {} for {}. The tool was not run.",
+                    access.label(),
+                    request.directory.display(),
+                    plugin
+                )));
+            }
+            self.record(request, answer);
+        }
+        Ok(None)
+    }
+
+    async fn ask(&self, request: &ConsentRequest) -> Result<ConsentAnswer> {
+        match self
+            .event_bus
+            .request::<ConsentAnswer>(
+                "fs_consent",
+                "user",
+                CONSENT_METHOD,
+                serde_json::to_value(request)?,
+                CONSENT_TIMEOUT,
+            )
+            .await
+        {
+            Ok(answer) => Ok(answer),
+            Err(EventError::Timeout) => {
+                tracing::warn!("📂 No answer to \"{}\" - denied", request.prompt());
+                Ok(ConsentAnswer::Deny)
+            }
+            Err(e) => Err(anyhow::anyhow!("Could not ask for file access: {}", e)),
+        }
+    }
+
+    fn record(&self, request: ConsentRequest, answer: ConsentAnswer) {
+        let grant = FsGrant {
+            plugin: request.plugin,
+            directory: request.directory,
+            access: request.access,
+            granted_at: Utc::now(),
+        };
+        let scope = if answer == ConsentAnswer::Always {
+            "always"
+        } else {
+            "this session"
+        };
+        tracing::info!("📂 Granted {} ({})", grant.describe(), scope);
+        audit::record(
+            AuditAction::AccessGranted,
+            format!("{} ({})", grant.describe(), scope),
+        );
+        let target = if answer == ConsentAnswer::Always {
+            &self.saved
+        } else {
+            &self.session
+        };
+        let Ok(mut grants) = target.lock() else {
+            return;
+        };
+        grants.push(grant);
+        if answer == ConsentAnswer::Always {
+            if let Err(e) = self.write(&grants) {
+                tracing::warn!("Failed to save filesystem grant: {}", e);
+            }
+        }
+    }
+
+    fn write(&self, grants: &[FsGrant]) -> RustbotResult<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = GrantFile {
+            grants: grants.to_vec(),
+        };
+        std::fs::write(&self.path, serde_json::to_string_pretty(&file)?)?;
+        Ok(())
+    }
+}
+
+/// Absolute and `~/` paths among the string values of tool arguments
+pub fn paths_in(arguments: &Value) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    collect_paths(arguments, &mut paths);
+    paths
+}
+
+fn collect_paths(value: &Value, paths: &mut Vec<PathBuf>) {
+    match value {
+        Value::String(text) => {
+            let text = text.trim();
+            if text.contains('\n') {
+                return;
+            }
+            let path = match text.strip_prefix("~/") {
+                Some(rest) => dirs::home_dir().map(|home| home.join(rest)),
+                None if text == "~" => dirs::home_dir(),
+                None if Path::new(text).is_absolute() => Some(PathBuf::from(text)),
+                None => None,
+            };
+            paths.extend(path.map(|path| normalize(&path)));
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_paths(item, paths);
+            }
+        }
+        Value::Object(fields) => {
+            for field in fields.values() {
+                collect_paths(field, paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Resolve `.` and `..` without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Directory a grant for `path` covers: the path itself if it is a
+/// directory, otherwise its parent
+fn scope_directory(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        return path.to_path_buf();
+    }
+    path.parent().unwrap_or(path).to_path_buf()
+}
+
+/// Path with the home directory shown as `~`
+pub fn display_path(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventKind;
+
+    #[test]
+    fn test_access_for_tool() {
+        assert_eq!(FsAccess::for_tool("read_file"), FsAccess::Read);
+        assert_eq!(FsAccess::for_tool("list_directory"), FsAccess::Read);
+        assert_eq!(FsAccess::for_tool("write_file"), FsAccess::Write);
+        assert_eq!(FsAccess::for_tool("move_file"), FsAccess::Write);
+        assert_eq!(FsAccess::for_tool("CreateDirectory"), FsAccess::Write);
+    }
+
+    #[test]
+    fn test_paths_in_arguments() {
+        let arguments = serde_json::json!({
+            "path": "/srv/project/src/main.rs",
+            "query": "fn main",
+            "paths": ["/etc/hosts", "relative/file.txt"],
+            "options": {"destination": "/srv/project/../secret/out.txt"},
+        });
+        let mut paths = paths_in(&arguments);
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/etc/hosts"),
+                PathBuf::from("/srv/project/src/main.rs"),
+                PathBuf::from("/srv/secret/out.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_grants_cover_subdirectories_and_weaker_access() {
+        let grant = FsGrant {
+            plugin: "filesystem".to_string(),
+            directory: PathBuf::from("/srv/project"),
+            access: FsAccess::Write,
+            granted_at: Utc::now(),
+        };
+        let covered = |plugin, dir, access| grant.covers(plugin, Path::new(dir), access);
+        assert!(covered("filesystem", "/srv/project/src", FsAccess::Read));
+        assert!(covered("filesystem", "/srv/project", FsAccess::Write));
+        assert!(!covered("filesystem", "/srv/projects", FsAccess::Read));
+        assert!(!covered("filesystem", "/srv", FsAccess::Read));
+        assert!(!covered("other", "/srv/project", FsAccess::Read));
+
+        let read_only = FsGrant {
+            access: FsAccess::Read,
+            ..grant.clone()
+        };
+        assert!(!read_only.covers("filesystem", Path::new("/srv/project"), FsAccess::Write));
+    }
+
+    #[tokio::test]
+    async fn test_consent_round_trip_and_revoke() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fs_grants.json");
+        let bus = Arc::new(EventBus::new());
+        let consent = FsConsent::load(path.clone(), Arc::clone(&bus));
+
+        // Stand-in for the UI: always allow reads, deny writes
+        let mut rx = bus.subscribe();
+        let responder = Arc::clone(&bus);
+        tokio::spawn(async move {
+            while let Ok(event) = rx.recv().await {
+                if let EventKind::Request { method, params } = &event.kind {
+                    assert_eq!(method, CONSENT_METHOD);
+                    let request: ConsentRequest = serde_json::from_value(params.clone()).unwrap();
+                    let answer = match request.access {
+                        FsAccess::Read => ConsentAnswer::Always,
+                        FsAccess::Write => ConsentAnswer::Deny,
+                    };
+                    responder
+                        .respond(&event, "user", Ok(serde_json::to_value(answer).unwrap()))
+                        .unwrap();
+                }
+            }
+        });
+
+        let read = serde_json::json!({"path": "/srv/project/notes.md"});
+        let allowed = consent
+            .check_tool_call("filesystem", "read_file", Some(&read))
+            .await
+            .unwrap();
+        assert_eq!(allowed, None);
+        assert!(consent.is_granted("filesystem", Path::new("/srv/project"), FsAccess::Read));
+
+        let denied = consent
+            .check_tool_call("filesystem", "write_file", Some(&read))
+            .await
+            .unwrap();
+        assert!(denied.unwrap().contains("denied read and write access"));
+
+        // The remembered grant survives a restart until it is revoked
+        let reloaded = FsConsent::load(path.clone(), Arc::clone(&bus));
+        let grants = reloaded.saved_grants();
+        assert_eq!(grants.len(), 1);
+        reloaded.revoke(&grants[0]).unwrap();
+        let reloaded = FsConsent::load(path, bus);
+        assert!(reloaded.saved_grants().is_empty());
+    }
+}
@@ -0,0 +1,513 @@
+// Git tools - read a repository's status, diff and log, and commit on approval
+//
+// Design Decision: Built-in tools bound to one selected repository, read-only
+// unless commits are allowed, and every commit confirmed by the user
+//
+// Rationale: Coding questions usually start with "what did I change?", and
+// pasting diffs into the chat is tedious. The tools run the `git` command line
+// in the repository the user selected (the active project's folder), never in
+// a path the model picks, so an agent can't wander into other repositories.
+// Looking is harmless; writing is not, so `git_commit` is only offered when
+// the project allows commits, and each call asks the desktop app for approval
+// over the event bus (`EventBus::request` to "user" with method
+// `APPROVAL_METHOD`), like sending email. An unanswered request counts as a
+// no.
+//
+// Tools (offered to the primary agent):
+//     git_status {}                      branch and changed files
+//     git_diff { staged?, path? }        working tree or staged changes
+//     git_log { limit?, path? }          recent commits, newest first
+//     git_commit { message }             commits what is staged, asks first
+//
+// `commit_message_prompt` builds the "draft a commit message" request the
+// chat's /commit command puts into the input.
+//
+// Trade-offs:
+// - Needs a `git` executable on the PATH; there is no libgit2 fallback
+// - Output is cut at `MAX_OUTPUT_CHARS`, so very large diffs are incomplete
+// - The agent can't stage files; the user decides what goes into a commit
+
+use crate::agent::tools::{FunctionDefinition, FunctionParameters};
+use crate::agent::ToolDefinition;
+use crate::events::{EventBus, EventError};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Tool that shows the branch and changed files
+pub const STATUS_TOOL: &str = "git_status";
+
+/// Tool that shows working tree or staged changes
+pub const DIFF_TOOL: &str = "git_diff";
+
+/// Tool that lists recent commits
+pub const LOG_TOOL: &str = "git_log";
+
+/// Tool that commits the staged changes after the user approves
+pub const COMMIT_TOOL: &str = "git_commit";
+
+/// Event bus request method the UI answers with true (commit) or false
+pub const APPROVAL_METHOD: &str = "approve_git_commit";
+
+/// How long a commit waits for the user before giving up
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How long a git command may run
+const GIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest tool result; the rest of the output is dropped
+const MAX_OUTPUT_CHARS: usize = 20_000;
+
+/// Default and maximum number of commits returned by git_log
+const DEFAULT_LOG_LIMIT: usize = 10;
+const MAX_LOG_LIMIT: usize = 50;
+
+/// Whether `path` is the top of a git working tree
+pub fn is_repository(path: &Path) -> bool {
+    path.join(".git").exists()
+}
+
+/// git_diff arguments
+#[derive(Debug, Default, Deserialize)]
+struct DiffArgs {
+    #[serde(default)]
+    staged: bool,
+    path: Option<String>,
+}
+
+/// git_log arguments
+#[derive(Debug, Default, Deserialize)]
+struct LogArgs {
+    limit: Option<usize>,
+    path: Option<String>,
+}
+
+/// git_commit arguments
+#[derive(Debug, Deserialize)]
+struct CommitArgs {
+    message: String,
+}
+
+/// A commit waiting for the user's approval
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitDraft {
+    /// Repository the commit goes into
+    pub repository: PathBuf,
+
+    /// Commit message proposed by the agent
+    pub message: String,
+
+    /// `git diff --staged --stat` of what will be committed
+    pub changes: String,
+}
+
+/// Git tools for one repository
+pub struct GitTools {
+    repository: PathBuf,
+    allow_commits: bool,
+    event_bus: Arc<EventBus>,
+}
+
+impl GitTools {
+    /// # Arguments
+    /// * `repository` - Working tree the tools run in
+    /// * `allow_commits` - Offer git_commit (each commit still needs approval)
+    /// * `event_bus` - Bus used to ask the UI for commit approval
+    pub fn new(repository: PathBuf, allow_commits: bool, event_bus: Arc<EventBus>) -> Self {
+        Self {
+            repository,
+            allow_commits,
+            event_bus,
+        }
+    }
+
+    /// Working tree the tools run in
+    pub fn repository(&self) -> &Path {
+        &self.repository
+    }
+
+    /// Check if a tool name belongs to the git tools
+    pub fn is_git_tool(tool_name: &str) -> bool {
+        [STATUS_TOOL, DIFF_TOOL, LOG_TOOL, COMMIT_TOOL].contains(&tool_name)
+    }
+
+    /// Definitions of the read tools, plus git_commit if commits are allowed
+    pub fn tool_definitions(&self) -> Vec<ToolDefinition> {
+        let repository = self.repository.display();
+        let path_property = serde_json::json!({
+            "type": "string",
+            "description": "Only this file or directory, relative to the repository root"
+        });
+        let mut tools = vec![
+            tool_definition(
+                STATUS_TOOL,
+                &format!(
+                    "Show the current branch and the changed, staged and untracked files in \
+                     the git repository at {}.",
+                    repository
+                ),
+                serde_json::json!({}),
+                &[],
+            ),
+            tool_definition(
+                DIFF_TOOL,
+                &format!(
+                    "Show the uncommitted changes in the git repository at {} as a unified \
+                     diff.",
+                    repository
+                ),
+                serde_json::json!({
+                    "staged": {
+                        "type": "boolean",
+                        "description": "Show the staged changes instead of the unstaged ones"
+                    },
+                    "path": path_property
+                }),
+                &[],
+            ),
+            tool_definition(
+                LOG_TOOL,
+                &format!(
+                    "List recent commits in the git repository at {}, newest first, with \
+                     hash, author, date and subject.",
+                    repository
+                ),
+                serde_json::json!({
+                    "limit": {
+                        "type": "integer",
+                        "description": format!(
+                            "Number of commits (default {}, max {})",
+                            DEFAULT_LOG_LIMIT, MAX_LOG_LIMIT
+                        )
+                    },
+                    "path": path_property
+                }),
+                &[],
+            ),
+        ];
+        if self.allow_commits {
+            tools.push(tool_definition(
+                COMMIT_TOOL,
+                &format!(
+                    "Commit the staged changes in the git repository at {}. The user reviews \
+                     every commit and must approve it; tell them what you are about to commit.",
+                    repository
+                ),
+                serde_json::json!({
+                    "message": {
+                        "type": "string",
+                        "description": "Commit message: a short subject line, a blank line, \
+                                        then details if needed"
+                    }
+                }),
+                &["message"],
+            ));
+        }
+        tools
+    }
+
+    /// Run a git tool
+    ///
+    /// # Arguments
+    /// * `tool_name` - git_status, git_diff, git_log or git_commit
+    /// * `arguments` - JSON-encoded tool arguments
+    ///
+    /// # Errors
+    /// - Unknown tool, invalid arguments, or git_commit while commits are off
+    /// - git is missing, fails or times out
+    pub async fn call_tool(&self, tool_name: &str, arguments: &str) -> Result<String> {
+        let arguments = if arguments.trim().is_empty() {
+            "{}"
+        } else {
+            arguments
+        };
+
+        match tool_name {
+            STATUS_TOOL => self.status().await,
+            DIFF_TOOL => {
+                let args: DiffArgs =
+                    serde_json::from_str(arguments).context("Invalid git_diff arguments")?;
+                let diff = self.diff(args.staged, args.path.as_deref()).await?;
+                Ok(if diff.trim().is_empty() {
+                    "No changes".to_string()
+                } else {
+                    diff
+                })
+            }
+            LOG_TOOL => {
+                let args: LogArgs =
+                    serde_json::from_str(arguments).context("Invalid git_log arguments")?;
+                let limit = args
+                    .limit
+                    .unwrap_or(DEFAULT_LOG_LIMIT)
+                    .clamp(1, MAX_LOG_LIMIT);
+                self.log(limit, args.path.as_deref()).await
+            }
+            COMMIT_TOOL => {
+                anyhow::ensure!(
+                    self.allow_commits,
+                    "Commits are turned off for this repository"
+                );
+                let args: CommitArgs =
+                    serde_json::from_str(arguments).context("Invalid git_commit arguments")?;
+                self.commit(&args.message).await
+            }
+            other => anyhow::bail!("Unknown git tool '{}'", other),
+        }
+    }
+
+    /// Branch and changed files (`git status --short --branch`)
+    pub async fn status(&self) -> Result<String> {
+        self.git(&["status", "--short", "--branch"]).await
+    }
+
+    /// Unstaged or staged changes, optionally for one path
+    pub async fn diff(&self, staged: bool, path: Option<&str>) -> Result<String> {
+        let mut args = vec!["diff"];
+        if staged {
+            args.push("--staged");
+        }
+        push_path(&mut args, path)?;
+        self.git(&args).await
+    }
+
+    /// The newest `limit` commits, optionally touching one path
+    pub async fn log(&self, limit: usize, path: Option<&str>) -> Result<String> {
+        let count = format!("-{}", limit);
+        let mut args = vec![
+            "log",
+            count.as_str(),
+            "--date=short",
+            "--format=%h %ad %an: %s",
+        ];
+        push_path(&mut args, path)?;
+        self.git(&args).await
+    }
+
+    /// Summary and full diff of the staged changes
+    ///
+    /// # Returns
+    /// (`--stat` summary, diff); both empty if nothing is staged
+    pub async fn staged_changes(&self) -> Result<(String, String)> {
+        let stat = self.git(&["diff", "--staged", "--stat"]).await?;
+        let diff = self.git(&["diff", "--staged"]).await?;
+        Ok((stat, diff))
+    }
+
+    /// Commit the staged changes once the user approves
+    ///
+    /// # Returns
+    /// A short result for the agent: committed, nothing staged, or declined
+    ///
+    /// # Errors
+    /// - Empty message
+    /// - git fails (e.g. a commit hook rejects the commit)
+    pub async fn commit(&self, message: &str) -> Result<String> {
+        let message = message.trim();
+        anyhow::ensure!(!message.is_empty(), "The commit message is empty");
+
+        let changes = self.git(&["diff", "--staged", "--stat"]).await?;
+        if changes.trim().is_empty() {
+            return Ok("Nothing is staged, so there is nothing to commit. \
+                       Ask the user to stage the changes first."
+                .to_string());
+        }
+
+        let draft = CommitDraft {
+            repository: self.repository.clone(),
+            message: message.to_string(),
+            changes,
+        };
+        if !self.request_approval(&draft).await? {
+            tracing::info!(
+                "🌿 Commit in {} declined by the user",
+                draft.repository.display()
+            );
+            return Ok("The user declined this commit. Nothing was committed.".to_string());
+        }
+
+        self.git(&["commit", "--quiet", "-m", message]).await?;
+        let commit = self.git(&["log", "-1", "--format=%h %s"]).await?;
+        tracing::info!(
+            "🌿 Committed {} in {}",
+            commit.trim(),
+            draft.repository.display()
+        );
+        Ok(format!("Committed {}", commit.trim()))
+    }
+
+    /// Ask the UI whether a commit may be made
+    ///
+    /// # Returns
+    /// true only if the user approved; no answer within the timeout is a no
+    async fn request_approval(&self, draft: &CommitDraft) -> Result<bool> {
+        match self
+            .event_bus
+            .request::<bool>(
+                "git",
+                "user",
+                APPROVAL_METHOD,
+                serde_json::to_value(draft)?,
+                APPROVAL_TIMEOUT,
+            )
+            .await
+        {
+            Ok(approved) => Ok(approved),
+            Err(EventError::Timeout) => {
+                tracing::warn!("🌿 No approval for commit - not committed");
+                Ok(false)
+            }
+            Err(e) => Err(anyhow::anyhow!("Could not ask for approval: {}", e)),
+        }
+    }
+
+    /// Run git in the repository and return its output
+    async fn git(&self, args: &[&str]) -> Result<String> {
+        let child = tokio::process::Command::new("git")
+            .arg("--no-pager")
+            .arg("-C")
+            .arg(&self.repository)
+            .args(args)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Could not run git; is it installed?")?;
+
+        let output = tokio::time::timeout(GIT_TIMEOUT, child.wait_with_output())
+            .await
+            .map_err(|_| anyhow::anyhow!("git {} timed out", args[0]))??;
+        if !output.status.success() {
+            anyhow::bail!(
+                "git {} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(truncate(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+/// Add a pathspec after `--` so it can't be read as an option
+///
+/// # Errors
+/// - The path is absolute or leaves the repository
+fn push_path<'a>(args: &mut Vec<&'a str>, path: Option<&'a str>) -> Result<()> {
+    let Some(path) = path.map(str::trim).filter(|p| !p.is_empty()) else {
+        return Ok(());
+    };
+    let relative = Path::new(path);
+    anyhow::ensure!(
+        relative.is_relative()
+            && !relative
+                .components()
+                .any(|c| matches!(c, std::path::Component::ParentDir)),
+        "Path must be inside the repository: {}",
+        path
+    );
+    args.extend(["--", path]);
+    Ok(())
+}
+
+/// Cut output to `MAX_OUTPUT_CHARS`, saying so
+fn truncate(output: &str) -> String {
+    if output.chars().count() <= MAX_OUTPUT_CHARS {
+        return output.to_string();
+    }
+    let kept: String = output.chars().take(MAX_OUTPUT_CHARS).collect();
+    format!("{}\n[output truncated]", kept)
+}
+
+/// Request for a commit message describing the staged changes
+///
+/// # Arguments
+/// * `stat` - `git diff --staged --stat`
+/// * `diff` - `git diff --staged`
+pub fn commit_message_prompt(stat: &str, diff: &str) -> String {
+    format!(
+        "Draft a commit message for these staged changes: a subject line of at most 72 \
+         characters in the imperative mood, a blank line, then a short explanation of what \
+         changed and why if it isn't obvious.\n\n```\n{}\n```\n\n```diff\n{}\n```",
+        stat.trim_end(),
+        diff.trim_end()
+    )
+}
+
+fn tool_definition(
+    name: &str,
+    description: &str,
+    properties: serde_json::Value,
+    required: &[&str],
+) -> ToolDefinition {
+    ToolDefinition {
+        tool_type: "function".to_string(),
+        function: FunctionDefinition {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters: FunctionParameters {
+                param_type: "object".to_string(),
+                properties,
+                required: required.iter().map(|s| s.to_string()).collect(),
+            },
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_names(tools: &GitTools) -> Vec<String> {
+        tools
+            .tool_definitions()
+            .into_iter()
+            .map(|t| t.function.name)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_commits_are_off_by_default() {
+        let bus = Arc::new(EventBus::new());
+        let read_only = GitTools::new(PathBuf::from("/tmp/repo"), false, Arc::clone(&bus));
+        assert_eq!(
+            tool_names(&read_only),
+            vec![STATUS_TOOL, DIFF_TOOL, LOG_TOOL]
+        );
+        let refused = read_only
+            .call_tool(COMMIT_TOOL, r#"{"message": "Fix it"}"#)
+            .await;
+        assert!(refused.unwrap_err().to_string().contains("turned off"));
+
+        let writable = GitTools::new(PathBuf::from("/tmp/repo"), true, bus);
+        assert!(tool_names(&writable).contains(&COMMIT_TOOL.to_string()));
+        assert!(GitTools::is_git_tool("git_log"));
+        assert!(!GitTools::is_git_tool("mcp:git:git_log"));
+    }
+
+    #[test]
+    fn test_paths_stay_in_the_repository() {
+        let mut args = vec!["diff"];
+        push_path(&mut args, Some("src/main.rs")).unwrap();
+        assert_eq!(args, vec!["diff", "--", "src/main.rs"]);
+
+        let mut args = vec!["diff"];
+        push_path(&mut args, Some(" ")).unwrap();
+        assert_eq!(args, vec!["diff"]);
+
+        assert!(push_path(&mut Vec::new(), Some("../other/secret.txt")).is_err());
+        assert!(push_path(&mut Vec::new(), Some("/etc/passwd")).is_err());
+    }
+
+    #[test]
+    fn test_commit_message_prompt() {
+        let prompt = commit_message_prompt(" src/lib.rs | 2 +-\n", "-old line\n+new line\n");
+        assert!(prompt.starts_with("Draft a commit message"));
+        assert!(prompt.contains("```\n src/lib.rs | 2 +-\n```"));
+        assert!(prompt.ends_with("```diff\n-old line\n+new line\n```"));
+    }
+}
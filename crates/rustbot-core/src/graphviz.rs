@@ -0,0 +1,157 @@
+// Graphviz DOT diagrams in chat answers, laid out locally as SVG
+//
+// Design Decision: layout-rs in process, next to mermaid.ink for mermaid
+//
+// Rationale: Models draw graphs (dependencies, state machines, call trees) in
+// DOT as often as in mermaid. layout-rs parses DOT and lays it out in pure
+// Rust, so no Graphviz install or network call is needed. `embed_graphs`
+// replaces ```dot (or ```graphviz) blocks with embedded SVG images exactly
+// like `mermaid::embed_diagrams`, so the chat shows, copies, zooms and
+// exports them with the same code.
+//
+// Trade-offs:
+// - layout-rs covers the common subset of DOT (nodes, edges, labels, shapes,
+//   colors, clusters); graphs it can't parse stay as code blocks
+// - Dark mode swaps black and white in the output; other colors set in the
+//   DOT source are kept as written
+
+use crate::markdown::{split_code_blocks, MarkdownSegment};
+use crate::mermaid::DiagramTheme;
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use layout::backends::svg::SVGWriter;
+use layout::gv::{DotParser, GraphBuilder};
+
+/// Code block languages treated as DOT
+const LANGUAGES: &[&str] = &["dot", "graphviz"];
+
+/// Colors swapped for dark mode: (as layout-rs writes them, replacement)
+const DARK_COLORS: &[(&str, &str)] = &[
+    ("\"black\"", "\"#e0e0e0\""),
+    ("\"#000000\"", "\"#e0e0e0\""),
+    ("\"#000000ff\"", "\"#e0e0e0ff\""),
+    ("\"white\"", "\"#2b2b2b\""),
+    ("\"#ffffff\"", "\"#2b2b2b\""),
+    ("\"#ffffffff\"", "\"#2b2b2bff\""),
+];
+
+/// DOT code blocks in markdown
+///
+/// # Returns
+/// (start, end, code) with the byte range covering the fences, like
+/// `mermaid::extract_mermaid_blocks`; unclosed blocks are skipped
+pub fn extract_dot_blocks(markdown: &str) -> Vec<(usize, usize, String)> {
+    let mut blocks = Vec::new();
+    for segment in split_code_blocks(markdown) {
+        let MarkdownSegment::Code {
+            language: Some(language),
+            code,
+            open: false,
+        } = segment
+        else {
+            continue;
+        };
+        if !LANGUAGES.contains(&language.to_ascii_lowercase().as_str()) {
+            continue;
+        }
+        // Code borrows from `markdown`; the fences are the lines around it
+        let code_start = code.as_ptr() as usize - markdown.as_ptr() as usize;
+        let code_end = code_start + code.len();
+        let start = markdown[..code_start - 1].rfind('\n').map_or(0, |i| i + 1);
+        let end = markdown[code_end..]
+            .find('\n')
+            .map_or(markdown.len(), |i| code_end + i);
+        blocks.push((start, end, code.to_string()));
+    }
+    blocks
+}
+
+/// Lay out a DOT graph as SVG
+///
+/// # Errors
+/// - The DOT doesn't parse, or layout-rs fails on it
+pub fn render_to_svg(dot: &str, theme: DiagramTheme) -> Result<Vec<u8>> {
+    let mut parser = DotParser::new(dot);
+    let graph = parser
+        .process()
+        .map_err(|e| anyhow!("Invalid DOT: {}", e))?;
+
+    // layout-rs panics on some inputs it doesn't support; a bad graph in an
+    // answer must not take the app down
+    let svg = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let mut builder = GraphBuilder::new();
+        builder.visit_graph(&graph);
+        let mut visual = builder.get();
+        let mut writer = SVGWriter::new();
+        visual.do_it(false, false, false, &mut writer);
+        writer.finalize()
+    }))
+    .map_err(|_| anyhow!("Graph layout failed"))?;
+
+    let svg = match theme {
+        DiagramTheme::Light => svg,
+        DiagramTheme::Dark => DARK_COLORS
+            .iter()
+            .fold(svg, |svg, (from, to)| svg.replace(from, to)),
+    };
+    Ok(svg.into_bytes())
+}
+
+/// Replace the DOT blocks in markdown with embedded SVG images
+///
+/// Each block becomes `![Graphviz Diagram](data:image/svg+xml;base64,...)`.
+/// Blocks that fail to render stay as code blocks.
+///
+/// # Returns
+/// The new markdown, or None if no graph was rendered
+pub fn embed_graphs(markdown: &str, theme: DiagramTheme) -> Option<String> {
+    let blocks = extract_dot_blocks(markdown);
+    let mut result = markdown.to_string();
+    let mut rendered = 0;
+
+    // Process blocks in reverse order to maintain correct indices
+    for (start, end, code) in blocks.iter().rev() {
+        match render_to_svg(code, theme) {
+            Ok(svg) => {
+                let data_url = format!("data:image/svg+xml;base64,{}", BASE64.encode(&svg));
+                result.replace_range(*start..*end, &format!("![Graphviz Diagram]({})", data_url));
+                rendered += 1;
+            }
+            Err(e) => tracing::warn!("Failed to render DOT graph: {}", e),
+        }
+    }
+
+    (rendered > 0).then_some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_dot_blocks() {
+        let markdown = "Graph:\n```dot\ndigraph { a -> b }\n```\nand\n```graphviz\ngraph {}\n```\n\
+                        ```rust\nfn main() {}\n```\n```dot\nunclosed";
+        let blocks = extract_dot_blocks(markdown);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(
+            &markdown[blocks[0].0..blocks[0].1],
+            "```dot\ndigraph { a -> b }\n```"
+        );
+        assert_eq!(blocks[0].2, "digraph { a -> b }\n");
+        assert_eq!(blocks[1].2, "graph {}\n");
+    }
+
+    #[test]
+    fn test_embed_graphs() {
+        let markdown = "Before\n```dot\ndigraph { a -> b }\n```\nAfter";
+        let embedded = embed_graphs(markdown, DiagramTheme::Light).unwrap();
+        assert!(embedded.starts_with("Before\n![Graphviz Diagram](data:image/svg+xml;base64,"));
+        assert!(embedded.ends_with(")\nAfter"));
+
+        let svg = render_to_svg("digraph { a -> b }", DiagramTheme::Dark).unwrap();
+        assert!(String::from_utf8(svg).unwrap().contains("<svg"));
+
+        assert!(embed_graphs("```dot\nnot dot at all {\n```\n", DiagramTheme::Light).is_none());
+    }
+}
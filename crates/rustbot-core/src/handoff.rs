@@ -0,0 +1,177 @@
+// Agent handoff - the primary agent passes the conversation to a specialist
+//
+// Design Decision: A built-in `handoff` tool that changes the session's agent
+//
+// Rationale: Specialists are normally called as tools: the primary agent asks
+// one question and relays the answer. That's wasteful when the rest of the
+// conversation belongs to the specialist (a long research session, a writing
+// task). With `handoff` the primary agent names the specialist and writes a
+// summary of what it needs to know; `RustbotApi` makes the specialist the
+// session's agent, so it answers the following turns directly, and sends the
+// summary ahead of the history as a system message. A `Handoff` event tells
+// the frontends who owns the chat now.
+//
+// Tool (offered to the primary agent while a specialist is enabled):
+//     handoff { agent, summary }     agent ID from the enum, context for it
+//
+// Handing back is the user's choice: switching the agent (or the chat's
+// "Back to ..." button) ends the handoff.
+//
+// Trade-offs:
+// - Specialists never get the handoff tool, so they can't hand off in turn
+// - The summary is the primary agent's; nothing checks it's complete (the
+//   history is still sent, so little is lost if it isn't)
+
+use crate::agent::tools::{FunctionDefinition, FunctionParameters};
+use crate::agent::ToolDefinition;
+use crate::llm::Message as LlmMessage;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// Tool that hands the conversation to a specialist
+pub const TOOL_NAME: &str = "handoff";
+
+/// A conversation passed from one agent to another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Handoff {
+    /// Agent that handed the conversation over
+    pub from: String,
+
+    /// Agent that answers from now on
+    pub to: String,
+
+    /// What the new agent needs to know, written by the previous one
+    pub summary: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct HandoffArgs {
+    agent: String,
+    #[serde(default)]
+    summary: String,
+}
+
+/// The `handoff` tool, or None if there's no specialist to hand off to
+///
+/// # Arguments
+/// * `specialists` - (ID, description) of the agents that can take over
+pub fn tool_definition(specialists: &[(String, String)]) -> Option<ToolDefinition> {
+    if specialists.is_empty() {
+        return None;
+    }
+    let agents: Vec<String> = specialists
+        .iter()
+        .map(|(id, description)| format!("- {}: {}", id, description))
+        .collect();
+    Some(ToolDefinition {
+        tool_type: "function".to_string(),
+        function: FunctionDefinition {
+            name: TOOL_NAME.to_string(),
+            description: format!(
+                "Hand the rest of this conversation to a specialist agent, who answers the \
+                 user directly from the next message on. Use this only when the user's \
+                 task belongs to the specialist for more than one question; to ask a \
+                 single question, call the specialist's own tool instead. After handing \
+                 off, tell the user who takes over.\n\nSpecialists:\n{}",
+                agents.join("\n")
+            ),
+            parameters: FunctionParameters {
+                param_type: "object".to_string(),
+                properties: serde_json::json!({
+                    "agent": {
+                        "type": "string",
+                        "enum": specialists.iter().map(|(id, _)| id).collect::<Vec<_>>(),
+                        "description": "ID of the specialist that takes over"
+                    },
+                    "summary": {
+                        "type": "string",
+                        "description": "What the specialist needs to know: the user's goal, \
+                            decisions made so far, constraints and open questions"
+                    }
+                }),
+                required: vec!["agent".to_string(), "summary".to_string()],
+            },
+        },
+    })
+}
+
+/// Read the arguments of a `handoff` call
+///
+/// # Returns
+/// The agent to hand off to and the summary for it
+///
+/// # Errors
+/// - The arguments aren't JSON with an `agent`
+pub fn parse_arguments(arguments: &str) -> Result<(String, String)> {
+    let args: HandoffArgs =
+        serde_json::from_str(arguments).context("Invalid arguments for the handoff tool")?;
+    Ok((
+        args.agent.trim().to_string(),
+        args.summary.trim().to_string(),
+    ))
+}
+
+impl Handoff {
+    /// The system message sent to the new agent ahead of the history
+    pub fn context_message(&self) -> LlmMessage {
+        let summary = if self.summary.is_empty() {
+            "(no summary given; see the conversation below)"
+        } else {
+            &self.summary
+        };
+        LlmMessage::new(
+            "system",
+            format!(
+                "The agent '{}' handed this conversation to you; answer the user's next \
+                 messages directly. Its summary of the conversation so far:\n\n{}",
+                self.from, summary
+            ),
+        )
+    }
+
+    /// Tool result telling the previous agent the handoff happened
+    pub fn tool_result(&self) -> String {
+        format!(
+            "The conversation now belongs to '{}', who will answer the user's next \
+             message. Tell the user briefly that '{}' takes over from here.",
+            self.to, self.to
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handoff_tool() {
+        assert!(tool_definition(&[]).is_none());
+
+        let specialists = vec![("writer".to_string(), "Drafts and edits text".to_string())];
+        let tool = tool_definition(&specialists).unwrap();
+        assert_eq!(tool.function.name, TOOL_NAME);
+        assert!(tool.function.description.contains("- writer: Drafts"));
+        assert_eq!(
+            tool.function.parameters.properties["agent"]["enum"],
+            serde_json::json!(["writer"])
+        );
+
+        let (agent, summary) =
+            parse_arguments(r#"{"agent": " writer ", "summary": "Blog post on Rust"}"#).unwrap();
+        assert_eq!(
+            (agent.as_str(), summary.as_str()),
+            ("writer", "Blog post on Rust")
+        );
+        assert!(parse_arguments(r#"{"summary": "x"}"#).is_err());
+
+        let handoff = Handoff {
+            from: "assistant".to_string(),
+            to: "writer".to_string(),
+            summary: summary.clone(),
+        };
+        let message = handoff.context_message();
+        assert_eq!(message.role, "system");
+        assert!(message.content.contains("'assistant'"));
+        assert!(message.content.contains("Blog post on Rust"));
+    }
+}
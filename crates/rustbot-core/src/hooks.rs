@@ -0,0 +1,621 @@
+// Scriptable event hooks - run user commands in response to events
+//
+// Design Decision: Declarative hook config + background runner on the event bus
+//
+// Rationale: Users want lightweight automation ("notify-send when a response
+// completes", "log agent errors to a file") without writing Rust. Hooks are
+// declared in ~/.rustbot/hooks.json and executed by a runner that subscribes
+// to the event bus, mirroring the webhook sink.
+//
+// Sandboxing:
+// - Commands are spawned directly (no shell), so event data can't inject syntax
+// - Environment is cleared; only PATH, HOME and RUSTBOT_* event vars are passed
+// - stdin is closed, output is captured and logged (truncated)
+// - Each run has a timeout and the child is killed when it expires
+//
+// Rate Limiting:
+// - Per-hook minimum interval between runs (extra triggers are dropped)
+// - Global cap on concurrently running hook processes
+//
+// Trade-offs:
+// - No shell means no pipes/redirection; users can still call `sh -c` explicitly
+// - Dropped (not queued) triggers keep bursts from piling up processes
+//
+// Extension Points:
+// - Filter hooks by agent ID or plugin ID
+// - Pass the full event as JSON on stdin
+
+use crate::error::{Result, RustbotError};
+use crate::events::{AgentStatus, Event, EventBus, EventKind, McpPluginEvent, PluginHealthStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+
+/// Maximum number of hook processes running at once
+const MAX_CONCURRENT_HOOKS: usize = 4;
+
+/// Maximum captured output logged per hook run (bytes)
+const MAX_LOGGED_OUTPUT: usize = 1024;
+
+/// Top-level hook configuration
+///
+/// File Format: JSON
+/// Location: ~/.rustbot/hooks.json
+///
+/// Example:
+///     {
+///       "hooks": [
+///         {
+///           "id": "notify",
+///           "on": "response_complete",
+///           "command": "notify-send",
+///           "args": ["Rustbot", "{{message}}"],
+///           "min_interval_secs": 5
+///         }
+///       ]
+///     }
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HookConfig {
+    #[serde(default)]
+    pub hooks: Vec<HookDefinition>,
+}
+
+/// A single hook: trigger + command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookDefinition {
+    /// Unique identifier (used in logs and rate limiting)
+    pub id: String,
+
+    /// Event trigger
+    pub on: HookTrigger,
+
+    /// Executable to run (resolved via PATH)
+    pub command: String,
+
+    /// Arguments; support {{kind}}, {{source}}, {{message}} and {{correlation_id}}
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Timeout in seconds before the process is killed
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+
+    /// Minimum seconds between two runs of this hook
+    #[serde(default)]
+    pub min_interval_secs: u64,
+
+    /// Optional working directory (defaults to the system temp dir)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<PathBuf>,
+
+    /// Whether this hook is active
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+fn default_true() -> bool {
+    true
+}
+
+/// Events that can trigger a hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookTrigger {
+    /// User sent a message
+    UserMessage,
+    /// Agent finished a response
+    ResponseComplete,
+    /// Agent transitioned to an error status
+    AgentError,
+    /// Agent started executing a tool
+    ToolExecution,
+    /// MCP plugin reported an error or was found dead
+    PluginCrash,
+}
+
+impl HookTrigger {
+    /// Map a bus event to the trigger it fires, if any
+    pub fn classify(event: &Event) -> Option<Self> {
+        match &event.kind {
+            EventKind::UserMessage(_) => Some(Self::UserMessage),
+            EventKind::AgentMessage { .. } => Some(Self::ResponseComplete),
+            EventKind::AgentStatusChange {
+                status: AgentStatus::Error(_),
+                ..
+            } => Some(Self::AgentError),
+            EventKind::AgentStatusChange {
+                status: AgentStatus::ExecutingTool(_),
+                ..
+            } => Some(Self::ToolExecution),
+            EventKind::McpPluginEvent(McpPluginEvent::Error { .. })
+            | EventKind::McpPluginEvent(McpPluginEvent::HealthStatus {
+                status: PluginHealthStatus::Dead,
+                ..
+            }) => Some(Self::PluginCrash),
+            _ => None,
+        }
+    }
+
+    /// Stable string name exposed to hook processes
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UserMessage => "user_message",
+            Self::ResponseComplete => "response_complete",
+            Self::AgentError => "agent_error",
+            Self::ToolExecution => "tool_execution",
+            Self::PluginCrash => "plugin_crash",
+        }
+    }
+}
+
+/// Short human-readable description of an event for hook arguments, with
+/// secrets redacted since it's handed to another process
+fn event_message(event: &Event) -> String {
+    let message = match &event.kind {
+        EventKind::UserMessage(content) => content.clone(),
+        EventKind::AgentMessage { content, .. } => content.clone(),
+        EventKind::AgentStatusChange {
+            status: AgentStatus::Error(msg),
+            ..
+        } => msg.clone(),
+        EventKind::AgentStatusChange {
+            status: AgentStatus::ExecutingTool(tool),
+            ..
+        } => tool.clone(),
+        EventKind::McpPluginEvent(McpPluginEvent::Error { plugin_id, message }) => {
+            format!("{}: {}", plugin_id, message)
+        }
+        EventKind::McpPluginEvent(McpPluginEvent::HealthStatus { plugin_id, .. }) => {
+            format!("{}: dead", plugin_id)
+        }
+        other => format!("{:?}", other),
+    };
+    crate::redact::redact(&message).into_owned()
+}
+
+impl HookDefinition {
+    /// Substitute event placeholders into the argument list
+    ///
+    /// Each argument is filled in one pass, so placeholders inside the event's
+    /// text aren't expanded.
+    pub fn render_args(&self, trigger: HookTrigger, event: &Event) -> Vec<String> {
+        let message = event_message(event);
+        let correlation_id = event.correlation_id.clone().unwrap_or_default();
+
+        self.args
+            .iter()
+            .map(|arg| {
+                crate::prompt_library::fill_placeholders(arg, |name| match name {
+                    "kind" => Some(trigger.as_str().to_string()),
+                    "source" => Some(event.source.clone()),
+                    "message" => Some(message.clone()),
+                    "correlation_id" => Some(correlation_id.clone()),
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+}
+
+impl HookConfig {
+    /// Default config location: ~/.rustbot/hooks.json
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("hooks.json")
+    }
+
+    /// Load configuration from a file
+    ///
+    /// # Returns
+    /// Ok(None) if the file doesn't exist (hooks disabled), Ok(Some) if loaded
+    ///
+    /// # Errors
+    /// - File exists but cannot be read
+    /// - Invalid JSON
+    /// - Validation failure (duplicate IDs, empty command)
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        let config: Self = serde_json::from_str(&content)?;
+        config.validate()?;
+        Ok(Some(config))
+    }
+
+    /// Validate hook definitions
+    pub fn validate(&self) -> Result<()> {
+        let mut seen = std::collections::HashSet::new();
+        for hook in &self.hooks {
+            if !seen.insert(hook.id.as_str()) {
+                return Err(RustbotError::ConfigError(format!(
+                    "Duplicate hook id '{}'",
+                    hook.id
+                )));
+            }
+            if hook.command.trim().is_empty() {
+                return Err(RustbotError::ConfigError(format!(
+                    "Hook '{}' has an empty command",
+                    hook.id
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Executes configured hooks when matching events are published
+pub struct HookRunner {
+    config: HookConfig,
+    last_run: Arc<Mutex<HashMap<String, Instant>>>,
+    permits: Arc<Semaphore>,
+}
+
+impl HookRunner {
+    /// Create a runner for the given configuration
+    pub fn new(config: HookConfig) -> Self {
+        Self {
+            config,
+            last_run: Arc::new(Mutex::new(HashMap::new())),
+            permits: Arc::new(Semaphore::new(MAX_CONCURRENT_HOOKS)),
+        }
+    }
+
+    /// Subscribe to the event bus and run matching hooks in the background
+    pub fn spawn(self, event_bus: &EventBus, handle: &tokio::runtime::Handle) -> JoinHandle<()> {
+        let mut rx = event_bus.subscribe_named("hooks");
+        let spawn_handle = handle.clone();
+
+        handle.spawn(async move {
+            tracing::info!("Hook runner started ({} hooks)", self.config.hooks.len());
+
+            while let Some(event) = rx.recv().await {
+                self.dispatch(&event, &spawn_handle);
+            }
+
+            tracing::info!("Hook runner stopped");
+        })
+    }
+
+    /// Start every hook that matches the event and passes rate limiting
+    ///
+    /// The concurrency permit is taken before the cooldown is checked, so a
+    /// hook skipped because too many are running can fire on the next event.
+    fn dispatch(&self, event: &Event, handle: &tokio::runtime::Handle) {
+        let Some(trigger) = HookTrigger::classify(event) else {
+            return;
+        };
+
+        for hook in self
+            .config
+            .hooks
+            .iter()
+            .filter(|h| h.enabled && h.on == trigger)
+        {
+            let Ok(permit) = Arc::clone(&self.permits).try_acquire_owned() else {
+                tracing::warn!(
+                    "Hook '{}' skipped: {} hooks already running",
+                    hook.id,
+                    MAX_CONCURRENT_HOOKS
+                );
+                continue;
+            };
+
+            if !self.try_acquire_slot(hook) {
+                tracing::debug!("Hook '{}' rate limited, skipping", hook.id);
+                continue;
+            }
+
+            let hook = hook.clone();
+            let args = hook.render_args(trigger, event);
+            let env = hook_env(trigger, event);
+            handle.spawn(async move {
+                if let Err(e) = run_hook(&hook, args, env).await {
+                    tracing::warn!("Hook '{}' failed: {}", hook.id, e);
+                }
+                drop(permit);
+            });
+        }
+    }
+
+    /// Check the per-hook minimum interval, recording the run if it passes
+    ///
+    /// Only called once the hook is certain to run.
+    fn try_acquire_slot(&self, hook: &HookDefinition) -> bool {
+        let mut last_run = self.last_run.lock().unwrap();
+        let now = Instant::now();
+        let interval = Duration::from_secs(hook.min_interval_secs);
+
+        if let Some(previous) = last_run.get(&hook.id) {
+            if now.duration_since(*previous) < interval {
+                return false;
+            }
+        }
+
+        last_run.insert(hook.id.clone(), now);
+        true
+    }
+}
+
+/// Environment variables passed to hook processes
+fn hook_env(trigger: HookTrigger, event: &Event) -> Vec<(String, String)> {
+    let mut env = Vec::new();
+
+    for key in ["PATH", "HOME"] {
+        if let Ok(value) = std::env::var(key) {
+            env.push((key.to_string(), value));
+        }
+    }
+
+    env.push((
+        "RUSTBOT_EVENT_KIND".to_string(),
+        trigger.as_str().to_string(),
+    ));
+    env.push(("RUSTBOT_EVENT_SOURCE".to_string(), event.source.clone()));
+    env.push(("RUSTBOT_EVENT_MESSAGE".to_string(), event_message(event)));
+    env.push((
+        "RUSTBOT_CORRELATION_ID".to_string(),
+        event.correlation_id.clone().unwrap_or_default(),
+    ));
+    env
+}
+
+/// Spawn the hook process in a restricted environment and wait for it
+async fn run_hook(
+    hook: &HookDefinition,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+) -> Result<()> {
+    let working_dir = hook.working_dir.clone().unwrap_or_else(std::env::temp_dir);
+
+    let child = tokio::process::Command::new(&hook.command)
+        .args(&args)
+        .env_clear()
+        .envs(env)
+        .current_dir(working_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let output = tokio::time::timeout(Duration::from_secs(hook.timeout), child.wait_with_output())
+        .await
+        .map_err(|_| RustbotError::ApiError(format!("timed out after {}s", hook.timeout)))??;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    tracing::debug!(
+        "Hook '{}' exited with {} (stdout: {:?}, stderr: {:?})",
+        hook.id,
+        output.status,
+        truncate(&stdout),
+        truncate(&stderr)
+    );
+
+    if !output.status.success() {
+        return Err(RustbotError::ApiError(format!(
+            "exited with {}",
+            output.status
+        )));
+    }
+
+    Ok(())
+}
+
+/// Truncate captured output for logging
+fn truncate(output: &str) -> &str {
+    if output.len() <= MAX_LOGGED_OUTPUT {
+        return output;
+    }
+    let mut end = MAX_LOGGED_OUTPUT;
+    while !output.is_char_boundary(end) {
+        end -= 1;
+    }
+    &output[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(id: &str, on: HookTrigger) -> HookDefinition {
+        HookDefinition {
+            id: id.to_string(),
+            on,
+            command: "true".to_string(),
+            args: vec![],
+            timeout: 5,
+            min_interval_secs: 60,
+            working_dir: None,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_classify_triggers() {
+        let response = Event::new(
+            "assistant".to_string(),
+            "user".to_string(),
+            EventKind::AgentMessage {
+                agent_id: "assistant".to_string(),
+                content: "done".to_string(),
+            },
+        );
+        assert_eq!(
+            HookTrigger::classify(&response),
+            Some(HookTrigger::ResponseComplete)
+        );
+
+        let error = Event::new(
+            "assistant".to_string(),
+            "broadcast".to_string(),
+            EventKind::AgentStatusChange {
+                agent_id: "assistant".to_string(),
+                status: AgentStatus::Error("boom".to_string()),
+            },
+        );
+        assert_eq!(HookTrigger::classify(&error), Some(HookTrigger::AgentError));
+
+        let test = Event::new(
+            "a".to_string(),
+            "b".to_string(),
+            EventKind::Test("x".to_string()),
+        );
+        assert_eq!(HookTrigger::classify(&test), None);
+    }
+
+    #[test]
+    fn test_render_args() {
+        let mut def = hook("notify", HookTrigger::UserMessage);
+        def.args = vec!["Rustbot".to_string(), "{{kind}}: {{message}}".to_string()];
+
+        let event = Event::new(
+            "user".to_string(),
+            "assistant".to_string(),
+            EventKind::UserMessage("hi; rm -rf /".to_string()),
+        );
+
+        // Arguments are passed verbatim (no shell), so content is inert
+        assert_eq!(
+            def.render_args(HookTrigger::UserMessage, &event),
+            vec!["Rustbot", "user_message: hi; rm -rf /"]
+        );
+    }
+
+    #[test]
+    fn test_render_args_fills_placeholders_once() {
+        let mut def = hook("notify", HookTrigger::AgentError);
+        def.args = vec!["{{message}}".to_string(), "{{correlation_id}}".to_string()];
+
+        let event = Event::new(
+            "assistant".to_string(),
+            "broadcast".to_string(),
+            EventKind::AgentStatusChange {
+                agent_id: "assistant".to_string(),
+                status: AgentStatus::Error(
+                    "{{correlation_id}} with key sk-or-v1-0123456789abcdef0123".to_string(),
+                ),
+            },
+        )
+        .with_correlation_id(Some("turn-1-0".to_string()));
+
+        assert_eq!(
+            def.render_args(HookTrigger::AgentError, &event),
+            vec!["{{correlation_id}} with key [REDACTED]", "turn-1-0"]
+        );
+        let env = hook_env(HookTrigger::AgentError, &event);
+        assert!(env.contains(&(
+            "RUSTBOT_EVENT_MESSAGE".to_string(),
+            "{{correlation_id}} with key [REDACTED]".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_rate_limiting() {
+        let def = hook("limited", HookTrigger::AgentError);
+        let runner = HookRunner::new(HookConfig {
+            hooks: vec![def.clone()],
+        });
+
+        assert!(runner.try_acquire_slot(&def));
+        assert!(!runner.try_acquire_slot(&def));
+
+        let mut unlimited = def.clone();
+        unlimited.id = "unlimited".to_string();
+        unlimited.min_interval_secs = 0;
+        assert!(runner.try_acquire_slot(&unlimited));
+        assert!(runner.try_acquire_slot(&unlimited));
+    }
+
+    #[tokio::test]
+    async fn test_busy_hook_keeps_its_cooldown() {
+        let def = hook("busy", HookTrigger::UserMessage);
+        let runner = HookRunner::new(HookConfig {
+            hooks: vec![def.clone()],
+        });
+        let event = Event::new(
+            "user".to_string(),
+            "assistant".to_string(),
+            EventKind::UserMessage("hi".to_string()),
+        );
+        let handle = tokio::runtime::Handle::current();
+
+        // All slots taken: the hook is skipped without starting its cooldown
+        let running = Arc::clone(&runner.permits)
+            .try_acquire_many_owned(MAX_CONCURRENT_HOOKS as u32)
+            .unwrap();
+        runner.dispatch(&event, &handle);
+        assert!(!runner.last_run.lock().unwrap().contains_key("busy"));
+
+        drop(running);
+        runner.dispatch(&event, &handle);
+        assert!(runner.last_run.lock().unwrap().contains_key("busy"));
+    }
+
+    #[test]
+    fn test_validate_rejects_duplicates_and_empty_commands() {
+        let config = HookConfig {
+            hooks: vec![
+                hook("a", HookTrigger::AgentError),
+                hook("a", HookTrigger::PluginCrash),
+            ],
+        };
+        assert!(config.validate().is_err());
+
+        let mut empty = hook("b", HookTrigger::AgentError);
+        empty.command = "  ".to_string();
+        assert!(HookConfig { hooks: vec![empty] }.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config: HookConfig = serde_json::from_str(
+            r#"{"hooks": [{"id": "n", "on": "response_complete", "command": "notify-send"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.hooks[0].on, HookTrigger::ResponseComplete);
+        assert_eq!(config.hooks[0].timeout, 10);
+        assert!(config.hooks[0].enabled);
+    }
+
+    #[test]
+    fn test_truncate_respects_char_boundaries() {
+        let long = "é".repeat(MAX_LOGGED_OUTPUT);
+        let truncated = truncate(&long);
+        assert!(truncated.len() <= MAX_LOGGED_OUTPUT);
+        assert!(truncate("short") == "short");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_hook_sandboxed_env() {
+        let mut def = hook("env", HookTrigger::UserMessage);
+        def.command = "sh".to_string();
+        def.args = vec![
+            "-c".to_string(),
+            "test \"$RUSTBOT_EVENT_KIND\" = user_message && test -z \"$CARGO\"".to_string(),
+        ];
+
+        let event = Event::new(
+            "user".to_string(),
+            "assistant".to_string(),
+            EventKind::UserMessage("hi".to_string()),
+        );
+        let env = hook_env(HookTrigger::UserMessage, &event);
+
+        assert!(run_hook(&def, def.args.clone(), env).await.is_ok());
+    }
+}
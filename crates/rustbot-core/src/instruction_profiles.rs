@@ -0,0 +1,293 @@
+// Named sets of system instructions ("Coding", "Writing", "Minimal", ...)
+//
+// Design Decision: One directory per profile next to the original `system`
+// directory, and the active profile's name in a plain text file
+//
+// Rationale: System instructions used to live in a single file,
+// ~/.rustbot/instructions/system/current, with timestamped backups beside
+// it. Each profile gets the same layout under its own name, so saving a
+// profile keeps its own backups, and the original file becomes the
+// "Default" profile without a migration. The active profile is remembered in
+// ~/.rustbot/instructions/active so the CLI and settings export follow the
+// profile chosen in the app.
+//
+// Trade-offs:
+// - Profile names are directory names: no slashes, and not "system" or
+//   "Default" (taken by the default profile)
+// - Profiles aren't watched; edits made outside the app are picked up on the
+//   next switch or restart
+
+use crate::error::{Result, RustbotError};
+use std::path::{Path, PathBuf};
+
+/// Profile stored in the original `system` directory
+pub const DEFAULT_PROFILE: &str = "Default";
+
+/// Directory of the default profile
+const DEFAULT_DIR: &str = "system";
+
+/// File in a profile directory with its instructions
+const CURRENT_FILE: &str = "current";
+
+/// File naming the active profile
+const ACTIVE_FILE: &str = "active";
+
+/// Starting points offered when creating a profile: name and instructions
+pub const STARTER_PROFILES: &[(&str, &str)] = &[
+    (
+        "Coding",
+        "You are an experienced software engineer pairing with the user. \
+Answer with working code first and keep explanations short. Prefer the \
+language, libraries and style already used in the code you're shown. Point \
+out bugs, edge cases and security problems you notice, and say when you're \
+unsure whether an API exists instead of guessing.",
+    ),
+    (
+        "Writing",
+        "You are a careful editor and writing partner. Keep the user's voice and \
+meaning; suggest changes rather than rewriting everything. Prefer plain \
+words, short sentences and active voice. When asked for a draft, match the \
+requested tone and length, and ask about the audience if it isn't clear.",
+    ),
+    (
+        "Minimal",
+        "Answer as briefly as possible. No preamble, no summaries, no follow-up \
+questions unless the request can't be answered without one.",
+    ),
+];
+
+/// Check a name for a new profile
+///
+/// # Errors
+/// Why the name can't be used
+pub fn validate_name(name: &str) -> std::result::Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Enter a name for the profile".to_string());
+    }
+    if name.eq_ignore_ascii_case(DEFAULT_PROFILE) || name.eq_ignore_ascii_case(DEFAULT_DIR) {
+        return Err(format!("'{}' is the default profile", name));
+    }
+    if name.starts_with('.')
+        || name.eq_ignore_ascii_case(ACTIVE_FILE)
+        || name.contains(['/', '\\', ':'])
+    {
+        return Err(format!("'{}' can't be used as a profile name", name));
+    }
+    Ok(())
+}
+
+/// The instruction profiles in a directory
+#[derive(Debug, Clone)]
+pub struct InstructionProfiles {
+    dir: PathBuf,
+}
+
+impl InstructionProfiles {
+    /// Profiles stored in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Profiles in ~/.rustbot/instructions
+    pub fn standard() -> Self {
+        Self::new(
+            dirs::home_dir()
+                .unwrap_or_default()
+                .join(".rustbot")
+                .join("instructions"),
+        )
+    }
+
+    /// File holding a profile's instructions
+    pub fn path_of(&self, name: &str) -> PathBuf {
+        let dir = if name == DEFAULT_PROFILE {
+            DEFAULT_DIR
+        } else {
+            name
+        };
+        self.dir.join(dir).join(CURRENT_FILE)
+    }
+
+    /// Names of the saved profiles, the default profile first
+    ///
+    /// The default profile is listed even before it's saved.
+    pub fn list(&self) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.path().join(CURRENT_FILE).is_file())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| name != DEFAULT_DIR)
+            .collect();
+        names.sort_by_key(|name| name.to_lowercase());
+        names.insert(0, DEFAULT_PROFILE.to_string());
+        names
+    }
+
+    /// Whether a profile has been saved (the default profile always exists)
+    pub fn exists(&self, name: &str) -> bool {
+        name == DEFAULT_PROFILE || self.path_of(name).is_file()
+    }
+
+    /// Name of the active profile
+    ///
+    /// The default profile if none was chosen or the chosen one was deleted.
+    pub fn active(&self) -> String {
+        std::fs::read_to_string(self.dir.join(ACTIVE_FILE))
+            .map(|name| name.trim().to_string())
+            .ok()
+            .filter(|name| !name.is_empty() && self.exists(name))
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+    }
+
+    /// Make a profile the active one
+    ///
+    /// # Errors
+    /// - The profile doesn't exist
+    /// - The choice can't be saved
+    pub fn set_active(&self, name: &str) -> Result<()> {
+        if !self.exists(name) {
+            return Err(RustbotError::ConfigError(format!(
+                "No instruction profile named '{}'",
+                name
+            )));
+        }
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.dir.join(ACTIVE_FILE), name)?;
+        Ok(())
+    }
+
+    /// A profile's instructions
+    ///
+    /// # Returns
+    /// Ok(None) if the profile hasn't been saved
+    ///
+    /// # Errors
+    /// - The file exists but can't be read
+    pub fn load(&self, name: &str) -> Result<Option<String>> {
+        let path = self.path_of(name);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(std::fs::read_to_string(path)?))
+    }
+
+    /// Save a profile's instructions, keeping the previous version as a
+    /// timestamped backup
+    ///
+    /// # Errors
+    /// - The directory, backup or file can't be written
+    pub fn save(&self, name: &str, instructions: &str) -> Result<()> {
+        let path = self.path_of(name);
+        let dir = profile_dir(&path);
+        std::fs::create_dir_all(dir)?;
+
+        if path.exists() {
+            let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+            std::fs::copy(&path, dir.join(format!("backup_{}", timestamp)))?;
+        }
+        std::fs::write(&path, instructions)?;
+        Ok(())
+    }
+
+    /// Create a profile
+    ///
+    /// # Errors
+    /// - The name is invalid or taken
+    /// - The profile can't be written
+    pub fn create(&self, name: &str, instructions: &str) -> Result<()> {
+        let name = name.trim();
+        validate_name(name).map_err(RustbotError::ConfigError)?;
+        if self
+            .list()
+            .iter()
+            .any(|existing| existing.eq_ignore_ascii_case(name))
+        {
+            return Err(RustbotError::ConfigError(format!(
+                "A profile named '{}' already exists",
+                name
+            )));
+        }
+        self.save(name, instructions)
+    }
+
+    /// Delete a profile with its backups
+    ///
+    /// The default profile becomes active if the deleted one was.
+    ///
+    /// # Errors
+    /// - It's the default profile
+    /// - The directory can't be removed
+    pub fn delete(&self, name: &str) -> Result<()> {
+        if name == DEFAULT_PROFILE {
+            return Err(RustbotError::ConfigError(
+                "The default profile can't be deleted".to_string(),
+            ));
+        }
+        // Never follow a name like ".." out of the profiles directory
+        validate_name(name).map_err(RustbotError::ConfigError)?;
+        let was_active = self.active() == name;
+        std::fs::remove_dir_all(profile_dir(&self.path_of(name)))?;
+        if was_active {
+            self.set_active(DEFAULT_PROFILE)?;
+        }
+        Ok(())
+    }
+}
+
+/// Directory of a profile from the path of its instructions file
+fn profile_dir(path: &Path) -> &Path {
+    path.parent().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_profiles() {
+        let temp = TempDir::new().unwrap();
+        let profiles = InstructionProfiles::new(temp.path());
+        assert_eq!(profiles.list(), vec![DEFAULT_PROFILE]);
+        assert_eq!(profiles.active(), DEFAULT_PROFILE);
+        assert_eq!(profiles.load(DEFAULT_PROFILE).unwrap(), None);
+
+        // The default profile is the original system/current file
+        profiles.save(DEFAULT_PROFILE, "Be helpful").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("system").join("current")).unwrap(),
+            "Be helpful"
+        );
+
+        profiles.create("Writing", "Be clear").unwrap();
+        profiles.create("coding", "Show code").unwrap();
+        assert!(profiles.create("writing", "Again").is_err());
+        assert!(profiles.create("system", "Taken").is_err());
+        assert!(profiles.create("../escape", "No").is_err());
+        assert_eq!(profiles.list(), vec![DEFAULT_PROFILE, "coding", "Writing"]);
+
+        profiles.set_active("Writing").unwrap();
+        assert_eq!(profiles.active(), "Writing");
+        assert!(profiles.set_active("Missing").is_err());
+        assert_eq!(
+            profiles.load("Writing").unwrap().as_deref(),
+            Some("Be clear")
+        );
+
+        // Saving again keeps a backup
+        profiles.save("Writing", "Be concise").unwrap();
+        let files = std::fs::read_dir(temp.path().join("Writing"))
+            .unwrap()
+            .count();
+        assert_eq!(files, 2);
+
+        profiles.delete("Writing").unwrap();
+        assert_eq!(profiles.active(), DEFAULT_PROFILE);
+        assert_eq!(profiles.list(), vec![DEFAULT_PROFILE, "coding"]);
+        assert!(profiles.delete(DEFAULT_PROFILE).is_err());
+        assert!(profiles.delete("..").is_err());
+    }
+}
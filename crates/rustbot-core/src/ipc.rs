@@ -0,0 +1,608 @@
+// Local IPC control socket for driving the running app from scripts
+//
+// Design Decision: Newline-delimited JSON over a Unix domain socket
+//
+// Rationale: Window-manager bindings, shell scripts and editor plugins need a
+// way to poke the running instance ("ask about the clipboard", "switch to the
+// researcher agent") without a second process owning its own API keys and
+// history. A local socket with one JSON object per line is trivial to drive
+// from `socat`, `nc -U` or any language.
+//
+// Protocol:
+//     → {"command": "send_message", "message": "Hello"}
+//     ← {"type": "chunk", "text": "Hi"}
+//     ← {"type": "chunk", "text": " there"}
+//     ← {"type": "done"}
+//
+//     → {"command": "switch_agent", "agent_id": "researcher"}
+//     ← {"type": "ok"}
+//
+//     → {"command": "open_link", "url": "rustbot://chat?prompt=Hi"}
+//     ← {"type": "ok"}
+//
+// Security:
+// - Socket lives in ~/.rustbot and is chmod 600 (owner only); it is bound in a
+//   private 0700 directory and only moved into place once restricted
+// - No network exposure; same trust boundary as the user account
+//
+// Trade-offs:
+// - Unix only for now; Windows named pipes are a future extension
+// - `send_message` talks to the API directly, so the reply is streamed to the
+//   caller; pass `"show_in_ui": true` to route the message through the chat
+//   view instead (no streamed reply)
+
+use crate::api::RustbotApi;
+use crate::deep_link::{self, DeepLink};
+use crate::error::Result;
+use crate::events::{Event, EventBus, EventKind, SystemCommand};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+
+/// How long `open_link` waits for the UI to apply a link
+const OPEN_LINK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Command sent by an IPC client (one JSON object per line)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum IpcCommand {
+    /// Send a user message to the active agent
+    SendMessage {
+        message: String,
+        /// Route through the chat view instead of streaming back to the client
+        #[serde(default)]
+        show_in_ui: bool,
+    },
+    /// Switch the active agent
+    SwitchAgent { agent_id: String },
+    /// Reload agent and MCP configuration
+    ReloadConfig,
+    /// List registered agents
+    ListAgents,
+    /// Report active agent and history size
+    Status,
+    /// Open a rustbot:// link in the chat view (see `deep_link`)
+    OpenLink { url: String },
+}
+
+/// Reply written back to the client (one JSON object per line)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpcResponse {
+    /// Streaming response fragment
+    Chunk { text: String },
+    /// End of a streamed response
+    Done,
+    /// Command succeeded with no payload
+    Ok,
+    /// Command was handed to the UI
+    Accepted,
+    /// Registered agents
+    Agents { agents: Vec<String>, active: String },
+    /// Current state
+    Status {
+        active_agent: String,
+        history_len: usize,
+    },
+    /// Command failed
+    Error { message: String },
+}
+
+/// Default socket location: ~/.rustbot/rustbot.sock
+pub fn default_socket_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".rustbot")
+        .join("rustbot.sock")
+}
+
+/// Serve one client connection until it disconnects
+///
+/// Generic over the stream type so it can be driven by in-memory pipes in tests.
+pub async fn handle_connection<S>(
+    stream: S,
+    api: Arc<Mutex<RustbotApi>>,
+    event_bus: Arc<EventBus>,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<IpcCommand>(&line) {
+            Ok(command) => {
+                tracing::info!("IPC command: {:?}", command);
+                execute_command(command, &api, &event_bus, &mut writer).await?;
+            }
+            Err(e) => {
+                write_response(
+                    &mut writer,
+                    &IpcResponse::Error {
+                        message: format!("Invalid command: {}", e),
+                    },
+                )
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Execute a single command and write its response(s)
+async fn execute_command<W>(
+    command: IpcCommand,
+    api: &Arc<Mutex<RustbotApi>>,
+    event_bus: &EventBus,
+    writer: &mut W,
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    match command {
+        IpcCommand::SendMessage {
+            message,
+            show_in_ui: true,
+        } => {
+            // The UI handles UserMessage events addressed to "user" as typed input
+            let event = Event::new(
+                "ipc".to_string(),
+                "user".to_string(),
+                EventKind::UserMessage(message),
+            );
+            let response = match event_bus.publish(event) {
+                Ok(_) => IpcResponse::Accepted,
+                Err(e) => IpcResponse::Error {
+                    message: e.to_string(),
+                },
+            };
+            write_response(writer, &response).await
+        }
+        IpcCommand::SendMessage { message, .. } => {
+            let mut api_guard = api.lock().await;
+            let mut stream = match api_guard.send_message(&message).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    return write_response(
+                        writer,
+                        &IpcResponse::Error {
+                            message: e.to_string(),
+                        },
+                    )
+                    .await;
+                }
+            };
+
+            // Keep the API locked until the reply is recorded, even if the client disconnects
+            let mut full_response = String::new();
+            let mut write_result = Ok(());
+            while let Some(chunk) = stream.recv().await {
+                full_response.push_str(&chunk);
+                if write_result.is_ok() {
+                    write_result =
+                        write_response(writer, &IpcResponse::Chunk { text: chunk }).await;
+                }
+            }
+            api_guard.add_assistant_response(full_response);
+            drop(api_guard);
+
+            write_result?;
+            write_response(writer, &IpcResponse::Done).await
+        }
+        IpcCommand::SwitchAgent { agent_id } => {
+            let response = match api.lock().await.switch_agent(&agent_id) {
+                Ok(()) => IpcResponse::Ok,
+                Err(e) => IpcResponse::Error {
+                    message: e.to_string(),
+                },
+            };
+            write_response(writer, &response).await
+        }
+        IpcCommand::ReloadConfig => {
+            let event = Event::new(
+                "ipc".to_string(),
+                "broadcast".to_string(),
+                EventKind::SystemCommand(SystemCommand::ReloadConfig),
+            );
+            let response = match event_bus.publish(event) {
+                Ok(_) => IpcResponse::Ok,
+                Err(e) => IpcResponse::Error {
+                    message: e.to_string(),
+                },
+            };
+            write_response(writer, &response).await
+        }
+        IpcCommand::ListAgents => {
+            let api_guard = api.lock().await;
+            let response = IpcResponse::Agents {
+                agents: api_guard.list_agents(),
+                active: api_guard.active_agent().to_string(),
+            };
+            drop(api_guard);
+            write_response(writer, &response).await
+        }
+        IpcCommand::Status => {
+            let api_guard = api.lock().await;
+            let response = IpcResponse::Status {
+                active_agent: api_guard.active_agent().to_string(),
+                history_len: api_guard.get_history().len(),
+            };
+            drop(api_guard);
+            write_response(writer, &response).await
+        }
+        IpcCommand::OpenLink { url } => {
+            let response = match open_link(&url, event_bus).await {
+                Ok(()) => IpcResponse::Ok,
+                Err(message) => IpcResponse::Error { message },
+            };
+            write_response(writer, &response).await
+        }
+    }
+}
+
+/// Validate a link and wait for the UI to apply it
+async fn open_link(url: &str, event_bus: &EventBus) -> std::result::Result<(), String> {
+    let link = DeepLink::parse(url).map_err(|e| e.to_string())?;
+    let params = serde_json::to_value(&link).map_err(|e| e.to_string())?;
+    event_bus
+        .request::<serde_json::Value>(
+            "ipc",
+            "user",
+            deep_link::OPEN_METHOD,
+            params,
+            OPEN_LINK_TIMEOUT,
+        )
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Serialize a response as a single JSON line
+async fn write_response<W>(writer: &mut W, response: &IpcResponse) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut line = serde_json::to_string(response)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Running control socket (see `start_control_socket`)
+///
+/// The listener lives as long as the app; when the API is rebuilt (config
+/// reload) it is handed over with `set_api` instead of rebinding the socket.
+pub struct ControlSocket {
+    task: JoinHandle<()>,
+    api: watch::Sender<Arc<Mutex<RustbotApi>>>,
+}
+
+impl ControlSocket {
+    /// Serve new connections from another API
+    ///
+    /// Connections already open keep the API they started with.
+    pub fn set_api(&self, api: Arc<Mutex<RustbotApi>>) {
+        self.api.send_replace(api);
+    }
+
+    /// Stop accepting connections
+    pub fn stop(self) {
+        self.task.abort();
+    }
+}
+
+/// Bind the control socket and accept connections in the background
+///
+/// An existing socket file is only replaced when it is stale (left by a
+/// previous run, so connecting is refused); a live one belongs to another
+/// instance, which keeps it.
+///
+/// # Errors
+/// - Another instance is already listening on the socket
+/// - Parent directory cannot be created
+/// - Socket cannot be bound
+#[cfg(unix)]
+pub fn start_control_socket(
+    path: PathBuf,
+    api: Arc<Mutex<RustbotApi>>,
+    event_bus: Arc<EventBus>,
+    handle: &tokio::runtime::Handle,
+) -> Result<ControlSocket> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    match std::os::unix::net::UnixStream::connect(&path) {
+        Ok(_) => {
+            return Err(crate::error::RustbotError::ConfigError(format!(
+                "Rustbot is already running (control socket {})",
+                path.display()
+            )));
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+            tracing::info!("Removing stale control socket {}", path.display());
+            std::fs::remove_file(&path)?;
+        }
+        // Nothing there yet; anything else surfaces when the socket is moved into place
+        Err(_) => {}
+    }
+
+    // The socket starts out with the umask's permissions, so bind it where
+    // nobody else can reach it until it is restricted
+    let staging = staging_dir(&path);
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    std::fs::DirBuilder::new().mode(0o700).create(&staging)?;
+    let staged_path = staging.join("rustbot.sock");
+    let bound = {
+        let _guard = handle.enter();
+        tokio::net::UnixListener::bind(&staged_path)
+    }
+    .and_then(|listener| {
+        std::fs::set_permissions(&staged_path, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::rename(&staged_path, &path)?;
+        Ok(listener)
+    });
+    std::fs::remove_dir_all(&staging)?;
+    let listener = bound?;
+    tracing::info!("IPC control socket listening on {}", path.display());
+
+    let (api_tx, api_rx) = watch::channel(api);
+    let connection_handle = handle.clone();
+    let task = handle.spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _addr)) => {
+                    let api = Arc::clone(&api_rx.borrow());
+                    let event_bus = Arc::clone(&event_bus);
+                    connection_handle.spawn(async move {
+                        if let Err(e) = handle_connection(stream, api, event_bus).await {
+                            tracing::warn!("IPC connection error: {}", e);
+                        }
+                    });
+                }
+                Err(e) => {
+                    tracing::error!("IPC accept failed: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+    Ok(ControlSocket { task, api: api_tx })
+}
+
+/// Private directory next to `path` the socket is bound in (one per process)
+#[cfg(unix)]
+fn staging_dir(path: &std::path::Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}", name, std::process::id()))
+}
+
+/// Send one command to a running instance and return its first reply
+///
+/// Used to hand work to the app that already owns the window, e.g. a
+/// rustbot:// link opened while Rustbot is running.
+///
+/// # Errors
+/// - Nothing is listening on the socket (no running instance)
+/// - The connection closes without a reply
+#[cfg(unix)]
+pub async fn send_command(path: &std::path::Path, command: &IpcCommand) -> Result<IpcResponse> {
+    let stream = tokio::net::UnixStream::connect(path).await?;
+    let (reader, mut writer) = tokio::io::split(stream);
+
+    let mut line = serde_json::to_string(command)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    writer.flush().await?;
+
+    let reply = BufReader::new(reader).lines().next_line().await?;
+    let reply = reply.ok_or_else(|| {
+        crate::error::RustbotError::ConfigError("IPC connection closed without a reply".to_string())
+    })?;
+    Ok(serde_json::from_str(&reply)?)
+}
+
+/// Control socket is not yet available on this platform
+#[cfg(not(unix))]
+pub async fn send_command(_path: &std::path::Path, _command: &IpcCommand) -> Result<IpcResponse> {
+    Err(crate::error::RustbotError::ConfigError(
+        "IPC control socket is only supported on Unix platforms".to_string(),
+    ))
+}
+
+/// Control socket is not yet available on this platform
+#[cfg(not(unix))]
+pub fn start_control_socket(
+    _path: PathBuf,
+    _api: Arc<Mutex<RustbotApi>>,
+    _event_bus: Arc<EventBus>,
+    _handle: &tokio::runtime::Handle,
+) -> Result<ControlSocket> {
+    Err(crate::error::RustbotError::ConfigError(
+        "IPC control socket is only supported on Unix platforms".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::OnceLock;
+    use tokio::runtime::Runtime;
+
+    // Shared runtime owned by the API under test (never dropped inside a test)
+    fn test_runtime() -> Arc<Runtime> {
+        static RUNTIME: OnceLock<Arc<Runtime>> = OnceLock::new();
+        Arc::clone(RUNTIME.get_or_init(|| Arc::new(Runtime::new().unwrap())))
+    }
+
+    async fn roundtrip(input: &str) -> (Vec<serde_json::Value>, Arc<EventBus>) {
+        let event_bus = Arc::new(EventBus::new());
+        let api = Arc::new(Mutex::new(RustbotApi::new(
+            Arc::clone(&event_bus),
+            test_runtime(),
+            20,
+        )));
+
+        let (client, server) = tokio::io::duplex(4096);
+        let bus = Arc::clone(&event_bus);
+        let server_task = tokio::spawn(handle_connection(server, api, bus));
+
+        let (client_read, mut client_write) = tokio::io::split(client);
+        client_write.write_all(input.as_bytes()).await.unwrap();
+        client_write.shutdown().await.unwrap();
+
+        let mut lines = BufReader::new(client_read).lines();
+        let mut responses = Vec::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            responses.push(serde_json::from_str(&line).unwrap());
+        }
+        server_task.await.unwrap().unwrap();
+
+        (responses, event_bus)
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        let cmd: IpcCommand =
+            serde_json::from_str(r#"{"command": "send_message", "message": "hi"}"#).unwrap();
+        assert_eq!(
+            cmd,
+            IpcCommand::SendMessage {
+                message: "hi".to_string(),
+                show_in_ui: false
+            }
+        );
+
+        let cmd: IpcCommand = serde_json::from_str(r#"{"command": "reload_config"}"#).unwrap();
+        assert_eq!(cmd, IpcCommand::ReloadConfig);
+    }
+
+    #[test]
+    fn test_response_serialization() {
+        let json = serde_json::to_string(&IpcResponse::Chunk {
+            text: "hi".to_string(),
+        })
+        .unwrap();
+        assert_eq!(json, r#"{"type":"chunk","text":"hi"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_list_agents_and_status() {
+        let (responses, _) =
+            roundtrip("{\"command\": \"list_agents\"}\n{\"command\": \"status\"}\n").await;
+
+        assert_eq!(responses[0]["type"], "agents");
+        assert_eq!(responses[0]["active"], "assistant");
+        assert_eq!(responses[1]["type"], "status");
+        assert_eq!(responses[1]["history_len"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_command_and_unknown_agent() {
+        let (responses, _) =
+            roundtrip("not json\n{\"command\": \"switch_agent\", \"agent_id\": \"missing\"}\n")
+                .await;
+
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["type"], "error");
+        assert_eq!(responses[1]["type"], "error");
+    }
+
+    #[tokio::test]
+    async fn test_reload_config_publishes_system_command() {
+        let event_bus = Arc::new(EventBus::new());
+        let mut rx = event_bus.subscribe();
+        let api = Arc::new(Mutex::new(RustbotApi::new(
+            Arc::clone(&event_bus),
+            test_runtime(),
+            20,
+        )));
+
+        let mut output = Vec::new();
+        execute_command(IpcCommand::ReloadConfig, &api, &event_bus, &mut output)
+            .await
+            .unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "{\"type\":\"ok\"}\n");
+        let event = rx.try_recv().unwrap();
+        assert!(matches!(
+            event.kind,
+            EventKind::SystemCommand(SystemCommand::ReloadConfig)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_open_link_waits_for_ui() {
+        let event_bus = Arc::new(EventBus::new());
+        let mut rx = event_bus.subscribe();
+        let responder = Arc::clone(&event_bus);
+        tokio::spawn(async move {
+            while let Ok(event) = rx.recv().await {
+                if let EventKind::Request { ref params, .. } = event.kind {
+                    assert_eq!(params["prompt"], "hi");
+                    responder
+                        .respond(&event, "user", Ok(serde_json::Value::Null))
+                        .unwrap();
+                }
+            }
+        });
+
+        assert_eq!(
+            open_link("rustbot://chat?prompt=hi", &event_bus).await,
+            Ok(())
+        );
+        assert!(open_link("rustbot://nope", &event_bus).await.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_second_instance_keeps_live_socket() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rustbot.sock");
+        let event_bus = Arc::new(EventBus::new());
+        let api = Arc::new(Mutex::new(RustbotApi::new(
+            Arc::clone(&event_bus),
+            test_runtime(),
+            20,
+        )));
+        let handle = test_runtime().handle().clone();
+
+        // Stale socket left by a crashed run is replaced
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        let first = start_control_socket(
+            path.clone(),
+            Arc::clone(&api),
+            Arc::clone(&event_bus),
+            &handle,
+        )
+        .unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(!staging_dir(&path).exists());
+
+        // A live one is not taken over
+        let second = start_control_socket(path.clone(), api, event_bus, &handle);
+        assert!(second.is_err());
+        assert!(std::os::unix::net::UnixStream::connect(&path).is_ok());
+        first.stop();
+    }
+}
@@ -0,0 +1,106 @@
+// Rustbot core library
+// Agents, LLM adapters, MCP plugins, events, storage and the programmatic API,
+// with no GUI dependencies. This can be:
+// - Used programmatically from Rust code
+// - Called from scripts and tests
+// - Integrated into other applications (servers, bots, editor plugins)
+//
+// The desktop app (the `rustbot` crate at the workspace root) is a thin egui
+// front end over this crate.
+//
+// Integrations with heavy dependencies are behind cargo features, so an
+// embedder only builds what it uses: `cli`, `server`, `discord`, `telegram`,
+// `scripting` (Rhai), `wasm-tools` (wasmtime), `native-plugins`, `email`,
+// `calendar`, `highlight` (syntect, for HTML exports), `graphviz`,
+// `tiktoken`, `memory-stats` and `apple-events` (rustbot:// links on macOS).
+// `full` enables all of them; the desktop app does. Without a feature its
+// module is absent and the API simply offers no tools from it.
+
+pub mod agent;
+pub mod analytics; // Opt-in local usage analytics (messages, agents, tools, errors)
+pub mod api;
+pub mod app_builder; // Builder pattern for dependency injection
+pub mod audit; // Hash-chained audit trail of security-relevant actions
+pub mod backup; // Scheduled config and conversation backups
+pub mod bot_sessions; // Per-channel conversations for chat bots
+#[cfg(feature = "calendar")]
+pub mod calendar; // CalDAV/Google Calendar connector exposed as agent tools
+pub mod chat_stream; // Typed items of a chat turn: text, tool calls, usage, done
+#[cfg(feature = "cli")]
+pub mod cli; // Headless `rustbot ask` / `rustbot chat` commands
+pub mod connectivity; // Offline detection and the features disabled until back online
+pub mod conversation_export; // Markdown/HTML/JSON conversation export
+pub mod conversation_import; // ChatGPT/Claude export importers
+pub mod deep_link; // rustbot:// URL scheme handling
+#[cfg(feature = "discord")]
+pub mod discord; // Discord bot mode (`rustbot discord`)
+pub mod editor; // Editor context and code patches for editor plugins
+#[cfg(feature = "email")]
+pub mod email; // IMAP/SMTP connector exposed as agent tools
+pub mod error;
+pub mod event_log; // Persistent event log with JSONL/CSV export
+pub mod event_sequence; // Event flow lanes, turn grouping and Mermaid output
+pub mod events;
+pub mod feedback; // Answer ratings collected from saved conversations
+pub mod folder_index; // Folder Q&A: keyword index of a directory with cited excerpts
+pub mod fs_consent; // Consent prompts and revocable grants for filesystem access
+pub mod git_tools; // Git status/diff/log tools and approval-gated commits
+#[cfg(feature = "graphviz")]
+pub mod graphviz; // Graphviz DOT diagrams laid out as SVG
+pub mod handoff; // Primary agent handing the conversation to a specialist
+pub mod hooks; // User-defined commands triggered by events
+pub mod instruction_profiles; // Named sets of system instructions, one of them active
+pub mod ipc; // Local control socket for external scripts
+pub mod llm;
+pub mod markdown; // Splitting chat markdown into prose and code blocks
+pub mod math; // TeX formulas in answers typeset to SVG
+pub mod mcp; // MCP (Model Context Protocol) plugin system
+pub mod mermaid; // Mermaid diagram rendering
+pub mod migration; // Schema versioning for config and profile files
+#[cfg(feature = "native-plugins")]
+pub mod native_plugins; // Native plugins loaded from dynamic libraries (rustbot-plugin SDK)
+pub mod privacy; // Local-only mode: no network egress but loopback and allowed hosts
+pub mod projects; // Named workspaces: folder, preferred agents and conversations
+pub mod prompt_library; // Reusable prompt templates with {{variables}}
+pub mod prompt_test; // A/B tests of system instructions with optional LLM-judge scores
+pub mod pruning; // Strategies for reducing chat history to fit the context
+pub mod quota; // Daily message and spending limits per profile
+pub mod redact; // Secret scrubbing for log output and exported traces
+pub mod request_preview; // Context inspector: what the next model request contains
+pub mod rpc; // JSON-RPC backend protocol (`rustbot rpc`)
+#[cfg(feature = "scripting")]
+pub mod scripting; // Rhai user scripts (event hooks and custom tools)
+#[cfg(feature = "server")]
+pub mod server; // REST API for `rustbot serve`
+pub mod services; // Service layer for dependency injection (Phase 1 - additive)
+pub mod settings_bundle; // Settings export/import for machine migration
+pub mod share; // Conversations shared as a self-contained HTML page or a gist
+pub mod system_context; // Configurable date/machine/user facts sent ahead of the conversation
+#[cfg(feature = "telegram")]
+pub mod telegram; // Telegram bridge (`rustbot telegram`)
+pub mod theme; // Light/dark/system and user color palettes
+pub mod tokenizer; // Token counts (tiktoken) and model context windows
+pub mod tool_executor;
+pub mod tool_toggles; // Per-conversation on/off switches for tool sources
+pub mod untrusted; // Untrusted-content markers and injection warnings for tool results
+pub mod usage; // Token usage per day/week by agent and model
+#[cfg(feature = "wasm-tools")]
+pub mod wasm_tools; // Sandboxed WebAssembly tools from ~/.rustbot/tools
+pub mod webhooks; // Optional webhook sink for external monitoring
+
+// Re-export commonly used types for convenience
+pub use agent::{Agent, AgentConfig, AgentLoader, JsonAgentConfig};
+pub use api::{RustbotApi, RustbotApiBuilder};
+pub use app_builder::{AppBuilder, AppDependencies};
+pub use chat_stream::{ChatStream, StreamItem};
+pub use error::{Result, RustbotError};
+pub use events::{AgentStatus, Event, EventBus, EventKind};
+pub use llm::{LlmAdapter, LlmProvider, LlmRequest, Message as LlmMessage};
+
+// Re-export service layer types (Phase 1 - new dependency injection layer)
+// Note: These are additive and don't affect existing code paths.
+// Services can be used for new code or gradual migration of existing code.
+pub use services::{
+    AgentService, ConfigService, DefaultAgentService, FileConfigService, FileStorageService,
+    FileSystem, RealFileSystem, StorageService,
+};
@@ -0,0 +1,462 @@
+// Anthropic Messages API adapter
+//
+// Design Decision: A native adapter instead of an OpenAI-compatible endpoint
+//
+// Rationale: OpenAI and Ollama accept the chat completions format the
+// OpenRouter adapter already speaks, so they reuse it with their own URL.
+// Anthropic only offers its Messages API: the system prompt is a top-level
+// field, tool calls and results are content blocks, images are base64
+// sources and streaming sends typed events. This adapter converts the
+// unified `LlmRequest` to that format and the answers back.
+//
+// Model IDs: Agent configs use OpenRouter IDs (`anthropic/claude-sonnet-4.5`).
+// The vendor prefix is dropped and version dots become dashes, which gives
+// Anthropic's alias for the same model (`claude-sonnet-4-5`).
+//
+// Trade-offs:
+// - Web search is an OpenRouter plugin and is ignored here
+// - `max_tokens` is required by Anthropic; requests without one get
+//   `DEFAULT_MAX_TOKENS`
+
+use super::http::shared_client;
+use super::key_check::ANTHROPIC_VERSION;
+use super::rate_limit::RateLimited;
+use super::types::*;
+use super::LlmAdapter;
+use crate::error::RustbotError;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::Client;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+/// Model used when the request names none
+const DEFAULT_MODEL: &str = "claude-sonnet-4-5";
+
+/// Answer length limit for requests that don't set one
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+pub struct AnthropicAdapter {
+    client: Client,
+    api_key: String,
+    url: String,
+}
+
+impl AnthropicAdapter {
+    pub fn new(api_key: String) -> Self {
+        Self::with_url(api_key, LlmProvider::Anthropic.chat_url())
+    }
+
+    /// Adapter for another Messages API endpoint (a proxy or a test server)
+    pub fn with_url(api_key: String, url: impl Into<String>) -> Self {
+        Self {
+            client: shared_client(),
+            api_key,
+            url: url.into(),
+        }
+    }
+
+    /// Send a request
+    ///
+    /// # Errors
+    /// Typed like the OpenRouter adapter's: `RateLimited` on 429, `Timeout`,
+    /// `InvalidApiKey` and `ContextTooLong`; other error statuses are `LlmError`.
+    async fn send_request(&self, body: &Value) -> Result<reqwest::Response> {
+        crate::privacy::check_url(&self.url)?;
+        let response = self
+            .client
+            .post(&self.url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("Content-Type", "application/json")
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    anyhow::Error::new(RustbotError::Timeout(format!(
+                        "No answer from {}",
+                        self.url
+                    )))
+                } else {
+                    anyhow::Error::new(e).context("Failed to send request to Anthropic")
+                }
+            })?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let limited = RateLimited::from_headers(response.headers());
+            let body = response.text().await.unwrap_or_default();
+            tracing::warn!(
+                "⏳ Rate limited (retry after {:?}): {}",
+                limited.retry_after,
+                body
+            );
+            return Err(RustbotError::RateLimited(limited).into());
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            tracing::warn!("Anthropic API error {}: {}", status, body);
+            return Err(RustbotError::from_provider_response("Anthropic", status, &body).into());
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl LlmAdapter for AnthropicAdapter {
+    async fn stream_chat(
+        &self,
+        request: LlmRequest,
+        tx: mpsc::UnboundedSender<String>,
+    ) -> Result<()> {
+        let body = request_body(request, true)?;
+        let response = self.send_request(&body).await?;
+
+        let mut stream = response.bytes_stream();
+        let mut buffer = String::new();
+        loop {
+            // Stop reading as soon as the receiver is gone (the user stopped generation)
+            let chunk = tokio::select! {
+                chunk = stream.next() => chunk,
+                _ = tx.closed() => {
+                    tracing::info!("⏹️  [LLM] Stream cancelled by the receiver");
+                    return Ok(());
+                }
+            };
+            let Some(chunk) = chunk else {
+                break;
+            };
+            let chunk = chunk.context("Failed to read chunk from stream")?;
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+
+            // Events are separated by a blank line; only the data line matters
+            while let Some(pos) = buffer.find("\n\n") {
+                let event = buffer[..pos].to_string();
+                buffer = buffer[pos + 2..].to_string();
+
+                for data in event.lines().filter_map(|line| line.strip_prefix("data: ")) {
+                    match stream_event(data)? {
+                        StreamEvent::Text(text) => {
+                            if tx.send(text).is_err() {
+                                return Ok(()); // Receiver dropped
+                            }
+                        }
+                        StreamEvent::Stop => return Ok(()),
+                        StreamEvent::Other => {}
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn complete_chat(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let body = request_body(request, false)?;
+        let response = self.send_request(&body).await?;
+        let text = response.text().await?;
+        tracing::debug!("Anthropic raw response: {}", text);
+        parse_completion(&text)
+    }
+
+    fn name(&self) -> &str {
+        "Anthropic"
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.url
+    }
+}
+
+/// Anthropic's ID for an OpenRouter model ID
+fn model_id(model: &str) -> String {
+    model
+        .strip_prefix("anthropic/")
+        .unwrap_or(model)
+        .replace('.', "-")
+}
+
+/// The Messages API body for a request
+///
+/// System messages are joined into the top-level `system` field; tool
+/// results become `tool_result` blocks in a user turn, merged when several
+/// follow one another.
+fn request_body(request: LlmRequest, stream: bool) -> Result<Value> {
+    let mut system = Vec::new();
+    let mut messages: Vec<Value> = Vec::new();
+
+    for (idx, message) in request.messages.iter().enumerate() {
+        match message.role.as_str() {
+            "system" => system.push(message.content.clone()),
+            "tool" => {
+                let tool_use_id = message
+                    .tool_call_id
+                    .as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("tool message missing tool_call_id"))?;
+                let block = json!({
+                    "type": "tool_result",
+                    "tool_use_id": tool_use_id,
+                    "content": message.content,
+                });
+                match messages.last_mut() {
+                    Some(last) if is_tool_results(last) => {
+                        if let Some(blocks) = last["content"].as_array_mut() {
+                            blocks.push(block);
+                        }
+                    }
+                    _ => messages.push(json!({ "role": "user", "content": [block] })),
+                }
+            }
+            "assistant" => {
+                let mut blocks = Vec::new();
+                if !message.content.is_empty() {
+                    blocks.push(json!({ "type": "text", "text": message.content }));
+                }
+                for call in message.tool_calls.iter().flatten() {
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.name,
+                        "input": call.arguments,
+                    }));
+                }
+                if blocks.is_empty() {
+                    anyhow::bail!("Assistant message {} has empty content", idx);
+                }
+                messages.push(json!({ "role": "assistant", "content": blocks }));
+            }
+            _ => {
+                let mut blocks: Vec<Value> = message.images.iter().map(|url| image(url)).collect();
+                if !message.content.is_empty() {
+                    blocks.push(json!({ "type": "text", "text": message.content }));
+                }
+                if blocks.is_empty() {
+                    anyhow::bail!("Message {} (role: {}) has empty content", idx, message.role);
+                }
+                messages.push(json!({ "role": "user", "content": blocks }));
+            }
+        }
+    }
+
+    let model = request.model.as_deref().unwrap_or(DEFAULT_MODEL);
+    let mut body = json!({
+        "model": model_id(model),
+        "max_tokens": request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        "messages": messages,
+        "stream": stream,
+    });
+    if !system.is_empty() {
+        body["system"] = Value::String(system.join("\n\n"));
+    }
+    if let Some(temperature) = request.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    if let Some(tools) = request.tools.filter(|tools| !tools.is_empty()) {
+        body["tools"] = tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "name": tool.function.name,
+                    "description": tool.function.description,
+                    "input_schema": tool.function.parameters,
+                })
+            })
+            .collect();
+        // "none" and "auto" mean the same in both APIs; "required" is "any"
+        if let Some(choice) = request.tool_choice.as_deref() {
+            let choice = match choice {
+                "required" => "any",
+                other => other,
+            };
+            body["tool_choice"] = json!({ "type": choice });
+        }
+    }
+    Ok(body)
+}
+
+/// Whether a turn holds only tool results (so the next one can join it)
+fn is_tool_results(message: &Value) -> bool {
+    message["role"] == "user"
+        && message["content"]
+            .as_array()
+            .is_some_and(|blocks| blocks.iter().all(|b| b["type"] == "tool_result"))
+}
+
+/// An image block from a data URL (`data:image/png;base64,...`) or a web URL
+fn image(url: &str) -> Value {
+    let inline = url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(";base64,"));
+    match inline {
+        Some((media_type, data)) => json!({
+            "type": "image",
+            "source": { "type": "base64", "media_type": media_type, "data": data },
+        }),
+        None => json!({ "type": "image", "source": { "type": "url", "url": url } }),
+    }
+}
+
+/// What a streamed event means for the answer
+#[derive(Debug, PartialEq)]
+enum StreamEvent {
+    Text(String),
+    Stop,
+    Other,
+}
+
+/// Read the data of one streamed event
+///
+/// # Errors
+/// - The stream reports an error (e.g. overloaded)
+fn stream_event(data: &str) -> Result<StreamEvent> {
+    let Ok(event) = serde_json::from_str::<Value>(data) else {
+        tracing::warn!("Failed to parse stream event: {}", data);
+        return Ok(StreamEvent::Other);
+    };
+    match event["type"].as_str() {
+        Some("content_block_delta") if event["delta"]["type"] == "text_delta" => {
+            Ok(StreamEvent::Text(
+                event["delta"]["text"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            ))
+        }
+        Some("message_stop") => Ok(StreamEvent::Stop),
+        Some("error") => Err(RustbotError::LlmError(format!(
+            "Anthropic stream failed: {}",
+            event["error"]["message"]
+                .as_str()
+                .unwrap_or("unknown error")
+        ))
+        .into()),
+        _ => Ok(StreamEvent::Other),
+    }
+}
+
+/// Convert a Messages API answer to the unified response
+///
+/// Stop reasons are reported in the chat completions vocabulary
+/// (`tool_use` is `tool_calls`, `end_turn` is `stop`).
+fn parse_completion(text: &str) -> Result<LlmResponse> {
+    let answer: Value = serde_json::from_str(text)
+        .map_err(|e| anyhow::anyhow!("error decoding response body: {}", e))?;
+    let blocks = answer["content"]
+        .as_array()
+        .context("No content in response")?;
+
+    let content = blocks
+        .iter()
+        .filter(|block| block["type"] == "text")
+        .filter_map(|block| block["text"].as_str())
+        .collect::<Vec<_>>()
+        .join("");
+    let tool_calls: Vec<ToolCall> = blocks
+        .iter()
+        .filter(|block| block["type"] == "tool_use")
+        .map(|block| ToolCall {
+            id: block["id"].as_str().unwrap_or_default().to_string(),
+            name: block["name"].as_str().unwrap_or_default().to_string(),
+            arguments: block["input"].clone(),
+        })
+        .collect();
+    let finish_reason = answer["stop_reason"].as_str().map(|reason| {
+        match reason {
+            "tool_use" => "tool_calls",
+            "end_turn" | "stop_sequence" => "stop",
+            "max_tokens" => "length",
+            other => other,
+        }
+        .to_string()
+    });
+
+    Ok(LlmResponse {
+        content,
+        tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+        finish_reason,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_id() {
+        assert_eq!(model_id("anthropic/claude-sonnet-4.5"), "claude-sonnet-4-5");
+        assert_eq!(
+            model_id("claude-3-5-haiku-latest"),
+            "claude-3-5-haiku-latest"
+        );
+    }
+
+    #[test]
+    fn test_request_body() {
+        let call = ToolCall {
+            id: "toolu_1".to_string(),
+            name: "calculator".to_string(),
+            arguments: json!({"expression": "2+2"}),
+        };
+        let mut request = LlmRequest::new(vec![
+            Message::new("system", "Be brief."),
+            Message::new("user", "What is 2+2?")
+                .with_images(vec!["data:image/png;base64,AAAA".to_string()]),
+            Message::with_tool_calls(String::new(), vec![call.clone(), call]),
+            Message::tool_result("toolu_1".to_string(), "4".to_string()),
+            Message::tool_result("toolu_1".to_string(), "4".to_string()),
+        ])
+        .with_model("anthropic/claude-sonnet-4.5".to_string())
+        .with_tool_choice("required".to_string());
+        request.tools = Some(Vec::new());
+
+        let body = request_body(request, true).unwrap();
+        assert_eq!(body["model"], "claude-sonnet-4-5");
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(body["max_tokens"], DEFAULT_MAX_TOKENS);
+        assert!(body.get("tools").is_none());
+
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[0]["content"][0]["source"]["media_type"],
+            "image/png"
+        );
+        assert_eq!(messages[0]["content"][1]["text"], "What is 2+2?");
+        assert_eq!(messages[1]["content"][0]["type"], "tool_use");
+        // Consecutive tool results share one user turn
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_completion_with_tool_use() {
+        let response = parse_completion(
+            r#"{"content":[{"type":"text","text":"Let me check."},
+                {"type":"tool_use","id":"toolu_1","name":"weather","input":{"city":"NYC"}}],
+                "stop_reason":"tool_use"}"#,
+        )
+        .unwrap();
+        assert_eq!(response.content, "Let me check.");
+        assert_eq!(response.finish_reason.as_deref(), Some("tool_calls"));
+        let calls = response.tool_calls.unwrap();
+        assert_eq!(calls[0].name, "weather");
+        assert_eq!(calls[0].arguments["city"], "NYC");
+    }
+
+    #[test]
+    fn test_stream_event() {
+        let delta =
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#;
+        assert_eq!(
+            stream_event(delta).unwrap(),
+            StreamEvent::Text("Hi".to_string())
+        );
+        assert_eq!(
+            stream_event(r#"{"type":"message_stop"}"#).unwrap(),
+            StreamEvent::Stop
+        );
+        assert!(stream_event(r#"{"type":"error","error":{"message":"Overloaded"}}"#).is_err());
+    }
+}
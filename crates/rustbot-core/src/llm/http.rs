@@ -0,0 +1,52 @@
+// Shared HTTP client for provider requests
+//
+// Design Decision: One process-wide reqwest client, built on first use
+//
+// Rationale: Each adapter used to build its own `Client`, and the agent
+// service builds a fresh adapter for every agent it loads. Every client has
+// its own connection pool, so messages to the same provider often paid for a
+// new TCP connection and TLS handshake. One shared client keeps connections
+// open between messages (keep-alive, HTTP/2 where the provider offers it via
+// ALPN), which takes the handshake off the time to first token.
+//
+// Trade-offs: Settings are global. Per-request options (timeouts, headers)
+// still go on the request builder, so callers don't need their own client.
+
+use reqwest::Client;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// How long an idle pooled connection stays open
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// TCP keep-alive probe interval, so idle connections survive NAT timeouts
+const TCP_KEEPALIVE: Duration = Duration::from_secs(30);
+
+/// Time allowed to establish a connection (the response itself can take longer)
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// The shared HTTP client
+///
+/// `Client` is a handle to a shared pool, so the returned clone is cheap and
+/// every clone reuses the same open connections.
+pub fn shared_client() -> Client {
+    CLIENT
+        .get_or_init(|| {
+            Client::builder()
+                .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+                .tcp_keepalive(TCP_KEEPALIVE)
+                .connect_timeout(CONNECT_TIMEOUT)
+                .http2_adaptive_window(true)
+                .build()
+                .unwrap_or_else(|e| {
+                    tracing::warn!(
+                        "⚠️  Couldn't configure the HTTP client, using defaults: {}",
+                        e
+                    );
+                    Client::new()
+                })
+        })
+        .clone()
+}
@@ -0,0 +1,121 @@
+// API key validation with a live call to the provider
+//
+// Design Decision: Ask the provider's cheapest authenticated endpoint
+//
+// Rationale: The setup wizard used to accept any non-empty string, so a
+// mistyped key only showed up as an error on the first message. Each provider
+// has a read-only endpoint that needs a valid key and costs no tokens
+// (OpenRouter's key info, the model lists of OpenAI and Anthropic); calling it
+// before finishing setup catches typos and revoked keys. Ollama has no keys,
+// so its check only confirms the local server answers.
+//
+// Trade-offs: A network outage looks like a failed check. The wizard shows
+// the error text so the user can tell "key rejected" from "can't connect".
+
+use super::http::shared_client;
+use super::types::LlmProvider;
+use anyhow::{bail, Context, Result};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use std::time::Duration;
+
+/// How long a check may take before it counts as failed
+const CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Anthropic API version sent with the check (and by `AnthropicAdapter`)
+pub(super) const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Build the request that checks a key against a provider
+///
+/// # Arguments
+/// * `client` - HTTP client to build the request with
+/// * `provider` - Provider the key belongs to
+/// * `api_base` - Provider API base URL (usually `provider.default_api_base()`)
+/// * `api_key` - Key to check (ignored for Ollama)
+pub fn validation_request(
+    client: &Client,
+    provider: LlmProvider,
+    api_base: &str,
+    api_key: &str,
+) -> RequestBuilder {
+    let base = api_base.trim_end_matches('/');
+    match provider {
+        LlmProvider::OpenRouter => client.get(format!("{}/key", base)).bearer_auth(api_key),
+        LlmProvider::OpenAI => client.get(format!("{}/models", base)).bearer_auth(api_key),
+        LlmProvider::Anthropic => client
+            .get(format!("{}/models", base))
+            .header("x-api-key", api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION),
+        LlmProvider::Ollama => client.get(format!("{}/api/tags", base)),
+    }
+    .timeout(CHECK_TIMEOUT)
+}
+
+/// Check that a provider accepts an API key
+///
+/// # Errors
+/// - The provider can't be reached
+/// - The provider rejects the key or answers with an error status
+pub async fn validate_api_key(provider: LlmProvider, api_key: &str) -> Result<()> {
+    let client = shared_client();
+    crate::privacy::check_url(provider.default_api_base())?;
+    let response = validation_request(&client, provider, provider.default_api_base(), api_key)
+        .send()
+        .await
+        .with_context(|| format!("Couldn't reach {}", provider.display_name()))?;
+
+    match response.status() {
+        status if status.is_success() => {
+            tracing::info!("🔑 {} accepted the API key", provider.display_name());
+            Ok(())
+        }
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            bail!("{} rejected the API key", provider.display_name())
+        }
+        status => bail!(
+            "{} answered the key check with {}",
+            provider.display_name(),
+            status
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(provider: LlmProvider) -> reqwest::Request {
+        validation_request(
+            &Client::new(),
+            provider,
+            provider.default_api_base(),
+            "sk-test",
+        )
+        .build()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validation_requests() {
+        let openrouter = build(LlmProvider::OpenRouter);
+        assert_eq!(
+            openrouter.url().as_str(),
+            "https://openrouter.ai/api/v1/key"
+        );
+        assert_eq!(openrouter.headers()["authorization"], "Bearer sk-test");
+
+        let openai = build(LlmProvider::OpenAI);
+        assert_eq!(openai.url().as_str(), "https://api.openai.com/v1/models");
+
+        let anthropic = build(LlmProvider::Anthropic);
+        assert_eq!(
+            anthropic.url().as_str(),
+            "https://api.anthropic.com/v1/models"
+        );
+        assert_eq!(anthropic.headers()["x-api-key"], "sk-test");
+        assert!(anthropic.headers().get("authorization").is_none());
+
+        let ollama = build(LlmProvider::Ollama);
+        assert_eq!(ollama.url().as_str(), "http://localhost:11434/api/tags");
+        assert!(ollama.headers().is_empty());
+    }
+}
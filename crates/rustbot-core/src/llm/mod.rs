@@ -0,0 +1,101 @@
+mod anthropic;
+mod http;
+mod key_check;
+mod offline;
+mod openrouter;
+mod rate_limit;
+mod types;
+
+pub use anthropic::AnthropicAdapter;
+pub use http::shared_client;
+pub use key_check::{validate_api_key, validation_request};
+pub use offline::OfflineFallbackAdapter;
+pub use openrouter::OpenRouterAdapter;
+pub use rate_limit::RateLimited;
+pub use types::*;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+/// Unified LLM interface that all adapters must implement
+/// Supports streaming, structured responses, and tool calls
+#[async_trait]
+pub trait LlmAdapter: Send + Sync {
+    /// Stream a chat completion response
+    /// Sends chunks of text through the provided channel as they arrive
+    ///
+    /// Closing or dropping the receiver cancels the request: implementations
+    /// stop reading the response and return `Ok(())`.
+    async fn stream_chat(
+        &self,
+        request: LlmRequest,
+        tx: mpsc::UnboundedSender<String>,
+    ) -> Result<()>;
+
+    /// Get a complete chat response (non-streaming)
+    /// Useful for tool calls and structured outputs
+    async fn complete_chat(&self, request: LlmRequest) -> Result<LlmResponse>;
+
+    /// Get the adapter name for logging/debugging
+    fn name(&self) -> &str;
+
+    /// URL requests are sent to (empty for adapters that don't use HTTP)
+    fn endpoint(&self) -> &str {
+        ""
+    }
+}
+
+/// Factory function to create the appropriate LLM adapter
+///
+/// OpenAI and Ollama speak the chat completions format, so they use the
+/// OpenRouter adapter pointed at their own endpoint. The adapter answers with
+/// the configured local model while Rustbot is offline (see
+/// `OfflineFallbackAdapter`).
+pub fn create_adapter(adapter_type: AdapterType, api_key: String) -> Box<dyn LlmAdapter> {
+    let online: Box<dyn LlmAdapter> = match adapter_type {
+        AdapterType::OpenRouter => Box::new(OpenRouterAdapter::new(api_key)),
+        AdapterType::OpenAI => Box::new(OpenRouterAdapter::for_provider(
+            LlmProvider::OpenAI,
+            api_key,
+        )),
+        AdapterType::Anthropic => Box::new(AnthropicAdapter::new(api_key)),
+        AdapterType::Ollama => Box::new(OpenRouterAdapter::for_provider(
+            LlmProvider::Ollama,
+            api_key,
+        )),
+    };
+    Box::new(OfflineFallbackAdapter::new(online))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adapter_for_each_provider() {
+        let endpoints = [
+            (
+                LlmProvider::OpenRouter,
+                "https://openrouter.ai/api/v1/chat/completions",
+            ),
+            (
+                LlmProvider::OpenAI,
+                "https://api.openai.com/v1/chat/completions",
+            ),
+            (
+                LlmProvider::Anthropic,
+                "https://api.anthropic.com/v1/messages",
+            ),
+            (
+                LlmProvider::Ollama,
+                "http://localhost:11434/v1/chat/completions",
+            ),
+        ];
+        for (provider, endpoint) in endpoints {
+            let adapter = create_adapter(provider.into(), "test-key".to_string());
+            assert_eq!(adapter.endpoint(), endpoint);
+            assert_eq!(adapter.name(), provider.display_name());
+        }
+    }
+}
@@ -0,0 +1,84 @@
+// Local model fallback while offline
+//
+// Design Decision: Wrap the hosted adapter and decide per request
+//
+// Rationale: Agents hold their adapter for their whole life, and rebuilding
+// them when the connection drops would lose in-flight state. The wrapper
+// checks `crate::connectivity` on every request instead: online, it passes
+// the request through untouched; offline with a local model configured, it
+// sends it to Ollama's OpenAI-compatible endpoint with that model and
+// without web search (an OpenRouter plugin Ollama doesn't have).
+//
+// Trade-offs: Offline with no local model, requests still go to the hosted
+// adapter and fail there; the GUI queues messages before they get this far.
+
+use super::openrouter::OpenRouterAdapter;
+use super::types::{LlmProvider, LlmRequest, LlmResponse};
+use super::LlmAdapter;
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+/// Adapter that answers with the local model while Rustbot is offline
+pub struct OfflineFallbackAdapter {
+    online: Box<dyn LlmAdapter>,
+    local: Box<dyn LlmAdapter>,
+}
+
+impl OfflineFallbackAdapter {
+    /// Fall back to the local Ollama server
+    ///
+    /// # Arguments
+    /// * `online` - Adapter for the hosted provider
+    pub fn new(online: Box<dyn LlmAdapter>) -> Self {
+        Self {
+            online,
+            local: Box::new(OpenRouterAdapter::with_url(
+                String::new(),
+                LlmProvider::Ollama.chat_url(),
+            )),
+        }
+    }
+
+    /// Adapter for this request, with the request rewritten for the local
+    /// model if that's the one answering
+    fn route(&self, mut request: LlmRequest) -> (&dyn LlmAdapter, LlmRequest) {
+        if crate::connectivity::is_online() {
+            return (self.online.as_ref(), request);
+        }
+        match crate::connectivity::local_model() {
+            Some(model) => {
+                tracing::debug!("📴 Offline: answering with local model {}", model);
+                request.model = Some(model);
+                request.web_search = None;
+                (self.local.as_ref(), request)
+            }
+            None => (self.online.as_ref(), request),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmAdapter for OfflineFallbackAdapter {
+    async fn stream_chat(
+        &self,
+        request: LlmRequest,
+        tx: mpsc::UnboundedSender<String>,
+    ) -> Result<()> {
+        let (adapter, request) = self.route(request);
+        adapter.stream_chat(request, tx).await
+    }
+
+    async fn complete_chat(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let (adapter, request) = self.route(request);
+        adapter.complete_chat(request).await
+    }
+
+    fn name(&self) -> &str {
+        self.online.name()
+    }
+
+    fn endpoint(&self) -> &str {
+        self.online.endpoint()
+    }
+}
@@ -1,6 +1,9 @@
+use super::http::shared_client;
+use super::rate_limit::RateLimited;
 use super::types::*;
 use super::LlmAdapter;
 use crate::agent::ToolDefinition;
+use crate::error::RustbotError;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use futures::StreamExt;
@@ -8,31 +11,118 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
-const OPENROUTER_API_URL: &str = "https://openrouter.ai/api/v1/chat/completions";
 const DEFAULT_MODEL: &str = "openai/gpt-4o";
 
 pub struct OpenRouterAdapter {
     client: Client,
     api_key: String,
+    url: String,
+    provider: LlmProvider,
 }
 
 impl OpenRouterAdapter {
     pub fn new(api_key: String) -> Self {
+        Self::with_url(api_key, LlmProvider::OpenRouter.chat_url())
+    }
+
+    /// Adapter for another OpenAI-compatible chat completions endpoint, such
+    /// as Ollama's (`http://localhost:11434/v1/chat/completions`)
+    pub fn with_url(api_key: String, url: impl Into<String>) -> Self {
         Self {
-            client: Client::new(),
+            client: shared_client(),
             api_key,
+            url: url.into(),
+            provider: LlmProvider::OpenRouter,
+        }
+    }
+
+    /// Adapter for a provider's own chat completions endpoint (OpenAI or
+    /// Ollama)
+    ///
+    /// Model IDs are OpenRouter's (`openai/gpt-4o`), so the vendor prefix is
+    /// dropped; Ollama answers with the local model from Preferences when one
+    /// is set. Web search is an OpenRouter plugin and is left out.
+    pub fn for_provider(provider: LlmProvider, api_key: String) -> Self {
+        Self {
+            provider,
+            ..Self::with_url(api_key, provider.chat_url())
+        }
+    }
+
+    /// Model ID the endpoint understands
+    fn model_id(&self, model: Option<String>) -> String {
+        let model = model.unwrap_or_else(|| DEFAULT_MODEL.to_string());
+        if self.provider == LlmProvider::OpenRouter {
+            return model;
+        }
+        let local = (self.provider == LlmProvider::Ollama)
+            .then(crate::connectivity::local_model)
+            .flatten();
+        local.unwrap_or_else(|| match model.split_once('/') {
+            Some((_, id)) => id.to_string(),
+            None => model,
+        })
+    }
+
+    /// Web search plugin, if requested and the endpoint is OpenRouter
+    ///
+    /// OpenRouter expects plugins array: [{"id": "web", "max_results": 5}]
+    fn plugins(&self, web_search: Option<bool>) -> Option<Vec<WebPlugin>> {
+        if web_search == Some(true) && self.provider == LlmProvider::OpenRouter {
+            Some(vec![WebPlugin {
+                id: "web".to_string(), // Required value for web search
+                max_results: Some(5),  // Default is 5 results per search
+            }])
+        } else {
+            None
         }
     }
 
+    /// Send a request
+    ///
+    /// # Errors
+    /// Failures the user can act on are typed (see `RustbotError`):
+    /// `RateLimited` on 429, `Timeout`, `InvalidApiKey` and `ContextTooLong`;
+    /// other error statuses are `LlmError`.
     async fn send_request(&self, request: &ApiRequest) -> Result<reqwest::Response> {
-        self.client
-            .post(OPENROUTER_API_URL)
+        crate::privacy::check_url(&self.url)?;
+        let response = self
+            .client
+            .post(&self.url)
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("Content-Type", "application/json")
             .json(request)
             .send()
             .await
-            .context("Failed to send request to OpenRouter")
+            .map_err(|e| {
+                if e.is_timeout() {
+                    anyhow::Error::new(RustbotError::Timeout(format!(
+                        "No answer from {}",
+                        self.url
+                    )))
+                } else {
+                    anyhow::Error::new(e)
+                        .context(format!("Failed to send request to {}", self.name()))
+                }
+            })?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let limited = RateLimited::from_headers(response.headers());
+            let body = response.text().await.unwrap_or_default();
+            tracing::warn!(
+                "⏳ Rate limited (retry after {:?}): {}",
+                limited.retry_after,
+                body
+            );
+            return Err(RustbotError::RateLimited(limited).into());
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            tracing::warn!("{} API error {}: {}", self.name(), status, body);
+            return Err(RustbotError::from_provider_response(self.name(), status, &body).into());
+        }
+        Ok(response)
     }
 }
 
@@ -46,7 +136,7 @@ impl LlmAdapter for OpenRouterAdapter {
         let start_time = std::time::Instant::now();
         tracing::debug!("⏱️  [LLM] stream_chat starting");
 
-        let model = request.model.unwrap_or_else(|| DEFAULT_MODEL.to_string());
+        let model = self.model_id(request.model);
         let is_anthropic = model.starts_with("anthropic/claude");
 
         // Convert messages to appropriate format based on model
@@ -58,15 +148,7 @@ impl LlmAdapter for OpenRouterAdapter {
         };
 
         // Configure web search if enabled (OpenRouter-specific feature)
-        // OpenRouter expects plugins array: [{"id": "web", "max_results": 5}]
-        let plugins = if request.web_search == Some(true) {
-            Some(vec![WebPlugin {
-                id: "web".to_string(), // Required value for web search
-                max_results: Some(5),  // Default is 5 results per search
-            }])
-        } else {
-            None
-        };
+        let plugins = self.plugins(request.web_search);
 
         let api_request = ApiRequest {
             model,
@@ -104,22 +186,29 @@ impl LlmAdapter for OpenRouterAdapter {
             start_time.elapsed()
         );
         let response = self.send_request(&api_request).await?;
+        let headers_at = start_time.elapsed();
         tracing::debug!(
             "⏱️  [LLM] Stream response headers received at {:?}",
-            start_time.elapsed()
+            headers_at
         );
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("OpenRouter API error {}: {}", status, error_text);
-        }
-
         let mut stream = response.bytes_stream();
         let mut buffer = String::new();
         let mut first_chunk = true;
-
-        while let Some(chunk) = stream.next().await {
+        let mut first_content = true;
+
+        loop {
+            // Stop reading as soon as the receiver is gone (the user stopped generation)
+            let chunk = tokio::select! {
+                chunk = stream.next() => chunk,
+                _ = tx.closed() => {
+                    tracing::info!("⏹️  [LLM] Stream cancelled by the receiver");
+                    return Ok(());
+                }
+            };
+            let Some(chunk) = chunk else {
+                break;
+            };
             if first_chunk {
                 tracing::debug!(
                     "⏱️  [LLM] First chunk received at {:?}",
@@ -155,9 +244,15 @@ impl LlmAdapter for OpenRouterAdapter {
                                     if let Some(delta) = &choice.delta {
                                         // Handle content streaming
                                         if let Some(content) = &delta.content {
-                                            if first_chunk {
-                                                tracing::debug!("⏱️  [LLM] First content sent to channel at {:?}", start_time.elapsed());
-                                                first_chunk = false;
+                                            if first_content {
+                                                // Time to first token, split into waiting
+                                                // for the response and for the model
+                                                tracing::info!(
+                                                    "⏱️  [LLM] First token after {:?} (headers after {:?})",
+                                                    start_time.elapsed(),
+                                                    headers_at
+                                                );
+                                                first_content = false;
                                             }
                                             if tx.send(content.clone()).is_err() {
                                                 return Ok(()); // Receiver dropped
@@ -189,7 +284,7 @@ impl LlmAdapter for OpenRouterAdapter {
         let start_time = std::time::Instant::now();
         tracing::debug!("⏱️  [LLM] complete_chat starting");
 
-        let model = request.model.unwrap_or_else(|| DEFAULT_MODEL.to_string());
+        let model = self.model_id(request.model);
         let is_anthropic = model.starts_with("anthropic/claude");
 
         // Convert messages to appropriate format based on model
@@ -200,15 +295,7 @@ impl LlmAdapter for OpenRouterAdapter {
         };
 
         // Configure web search if enabled (OpenRouter-specific feature)
-        // OpenRouter expects plugins array: [{"id": "web", "max_results": 5}]
-        let plugins = if request.web_search == Some(true) {
-            Some(vec![WebPlugin {
-                id: "web".to_string(), // Required value for web search
-                max_results: Some(5),  // Default is 5 results per search
-            }])
-        } else {
-            None
-        };
+        let plugins = self.plugins(request.web_search);
 
         let api_request = ApiRequest {
             model,
@@ -243,12 +330,6 @@ impl LlmAdapter for OpenRouterAdapter {
         let response = self.send_request(&api_request).await?;
         tracing::debug!("⏱️  [LLM] Response received at {:?}", start_time.elapsed());
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("OpenRouter API error {}: {}", status, error_text);
-        }
-
         // Get response text for debugging
         let response_text = response.text().await?;
         tracing::debug!("⏱️  [LLM] Response body read at {:?}", start_time.elapsed());
@@ -309,7 +390,11 @@ impl LlmAdapter for OpenRouterAdapter {
     }
 
     fn name(&self) -> &str {
-        "OpenRouter"
+        self.provider.display_name()
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.url
     }
 }
 
@@ -345,6 +430,31 @@ struct ApiRequest {
     provider: Option<ProviderConfig>,
 }
 
+/// Message content for the API: plain text, or content parts when images are attached
+///
+/// OpenRouter accepts OpenAI-style `image_url` parts for every vision model
+/// (it converts them for Anthropic and Google), so both serializers share this.
+fn content_value(message: &Message) -> serde_json::Value {
+    if message.images.is_empty() {
+        return serde_json::Value::String(message.content.clone());
+    }
+
+    let mut parts = Vec::with_capacity(message.images.len() + 1);
+    if !message.content.is_empty() {
+        parts.push(serde_json::json!({
+            "type": "text",
+            "text": message.content
+        }));
+    }
+    for url in &message.images {
+        parts.push(serde_json::json!({
+            "type": "image_url",
+            "image_url": { "url": url }
+        }));
+    }
+    serde_json::Value::Array(parts)
+}
+
 /// Serialize messages for OpenAI models (GPT-4o, o1, etc.)
 ///
 /// OpenAI format uses standard message structure with:
@@ -357,7 +467,7 @@ fn serialize_messages_for_openai_value(messages: &[Message]) -> Result<Vec<serde
             // Convert our internal Message format to OpenAI API format
             let mut json = serde_json::json!({
                 "role": msg.role,
-                "content": msg.content,
+                "content": content_value(msg),
             });
 
             // Add tool_calls if present (for assistant messages)
@@ -465,13 +575,13 @@ fn serialize_messages_for_anthropic_value(messages: &[Message]) -> Result<Vec<se
                 })
             }
             _ => {
-                // User, system, or other messages
-                if message.content.is_empty() {
+                // User, system, or other messages (images alone are valid content)
+                if message.content.is_empty() && message.images.is_empty() {
                     anyhow::bail!("Message {} (role: {}) has empty content", idx, message.role);
                 }
                 serde_json::json!({
                     "role": message.role,
-                    "content": message.content
+                    "content": content_value(message)
                 })
             }
         };
@@ -966,6 +1076,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_images_become_content_parts() {
+        let png = "data:image/png;base64,iVBORw0KGgo=".to_string();
+        let messages = vec![
+            Message::new("user", "What is in this picture?").with_images(vec![png.clone()]),
+            Message::new("user", "").with_images(vec![png.clone()]),
+            Message::new("assistant", "A cat."),
+        ];
+
+        for json in [
+            serialize_messages_for_openai_value(&messages).unwrap(),
+            serialize_messages_for_anthropic_value(&messages).unwrap(),
+        ] {
+            let parts = json[0]["content"].as_array().unwrap();
+            assert_eq!(parts.len(), 2);
+            assert_eq!(parts[0]["type"], "text");
+            assert_eq!(parts[0]["text"], "What is in this picture?");
+            assert_eq!(parts[1]["type"], "image_url");
+            assert_eq!(parts[1]["image_url"]["url"], png);
+
+            // No empty text part when only an image was sent
+            let parts = json[1]["content"].as_array().unwrap();
+            assert_eq!(parts.len(), 1);
+            assert_eq!(parts[0]["type"], "image_url");
+
+            // Messages without images keep plain string content
+            assert_eq!(json[2]["content"], "A cat.");
+        }
+    }
+
     #[test]
     fn test_deserialize_openrouter_response_with_tools() {
         // Simulated OpenRouter response with tool calls
@@ -1270,4 +1410,44 @@ mod tests {
         assert_eq!(calls[0].id, "call_good");
         assert_eq!(calls[0].name, "valid_tool");
     }
+
+    #[tokio::test]
+    async fn test_stream_stops_when_receiver_dropped() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Server that sends one chunk and then keeps the stream open
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "http://{}/v1/chat/completions",
+            listener.local_addr().unwrap()
+        );
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0u8; 8192];
+            let _ = stream.read(&mut buffer).await;
+            let chunk = "data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
+                 Transfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n",
+                chunk.len(),
+                chunk
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+        });
+
+        let adapter = OpenRouterAdapter::with_url("test-key".to_string(), url);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let request = LlmRequest::new(vec![Message::new("user", "Hi")]);
+        let task = tokio::spawn(async move { adapter.stream_chat(request, tx).await });
+
+        assert_eq!(rx.recv().await.as_deref(), Some("Hello"));
+        drop(rx);
+
+        let result = tokio::time::timeout(std::time::Duration::from_secs(5), task)
+            .await
+            .expect("stream_chat kept reading after the receiver was dropped")
+            .unwrap();
+        assert!(result.is_ok());
+    }
 }
@@ -0,0 +1,85 @@
+// Provider rate limits (HTTP 429)
+//
+// Design Decision: A typed error carrying how long to wait
+//
+// Rationale: A 429 isn't a failure the user should have to deal with; the
+// request succeeds if sent again later. Adapters turn the response into
+// `RustbotError::RateLimited` with the wait the provider asked for, and
+// callers find it with `RustbotError::find`: the GUI retries the message after
+// the wait and holds queued messages until then.
+//
+// The wait comes from `Retry-After` (seconds) or, as OpenRouter sends it,
+// `X-RateLimit-Reset` (Unix time in milliseconds). Providers that send
+// neither leave it to the caller's backoff.
+
+use reqwest::header::HeaderMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// The provider refused a request because too many were sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("The provider is rate limiting requests")]
+pub struct RateLimited {
+    /// How long the provider asked to wait, if it said
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimited {
+    /// Read the wait from the headers of a 429 response
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+        let retry_after = header("retry-after")
+            .and_then(|value| value.trim().parse::<f64>().ok())
+            .or_else(|| {
+                let reset_ms = header("x-ratelimit-reset")?.trim().parse::<u64>().ok()?;
+                let now_ms = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .ok()?
+                    .as_millis();
+                Some(reset_ms.saturating_sub(now_ms as u64) as f64 / 1000.0)
+            })
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok());
+
+        Self { retry_after }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_retry_after_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(RateLimited::from_headers(&headers).retry_after, None);
+
+        headers.insert("retry-after", HeaderValue::from_static("12"));
+        assert_eq!(
+            RateLimited::from_headers(&headers).retry_after,
+            Some(Duration::from_secs(12))
+        );
+
+        // HTTP dates aren't used by the providers; they fall back to the reset time
+        headers.insert(
+            "retry-after",
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        let reset = HeaderValue::from_str(&(now_ms + 30_000).to_string()).unwrap();
+        headers.insert("x-ratelimit-reset", reset);
+        let wait = RateLimited::from_headers(&headers).retry_after.unwrap();
+        assert!(wait > Duration::from_secs(25) && wait <= Duration::from_secs(30));
+
+        // A reset in the past means go ahead
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("1000"));
+        assert_eq!(
+            RateLimited::from_headers(&headers).retry_after,
+            Some(Duration::ZERO)
+        );
+    }
+}
@@ -2,16 +2,27 @@ use crate::agent::ToolDefinition;
 use serde::{Deserialize, Serialize};
 
 /// Type of LLM adapter to use
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AdapterType {
     OpenRouter,
-    // Future options:
-    // Anthropic,
-    // OpenAI,
+    OpenAI,
+    Anthropic,
+    Ollama,
+}
+
+impl From<LlmProvider> for AdapterType {
+    fn from(provider: LlmProvider) -> Self {
+        match provider {
+            LlmProvider::OpenRouter => AdapterType::OpenRouter,
+            LlmProvider::OpenAI => AdapterType::OpenAI,
+            LlmProvider::Anthropic => AdapterType::Anthropic,
+            LlmProvider::Ollama => AdapterType::Ollama,
+        }
+    }
 }
 
 /// LLM provider enumeration for JSON configuration
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum LlmProvider {
     OpenRouter,
@@ -21,6 +32,34 @@ pub enum LlmProvider {
 }
 
 impl LlmProvider {
+    /// Every provider, in the order the setup wizard lists them
+    pub const ALL: [LlmProvider; 4] = [
+        LlmProvider::OpenRouter,
+        LlmProvider::OpenAI,
+        LlmProvider::Anthropic,
+        LlmProvider::Ollama,
+    ];
+
+    /// Name shown to users
+    pub fn display_name(&self) -> &str {
+        match self {
+            LlmProvider::OpenRouter => "OpenRouter",
+            LlmProvider::OpenAI => "OpenAI",
+            LlmProvider::Anthropic => "Anthropic",
+            LlmProvider::Ollama => "Ollama",
+        }
+    }
+
+    /// Page where users create an API key (None for local Ollama)
+    pub fn key_url(&self) -> Option<&str> {
+        match self {
+            LlmProvider::OpenRouter => Some("https://openrouter.ai/keys"),
+            LlmProvider::OpenAI => Some("https://platform.openai.com/api-keys"),
+            LlmProvider::Anthropic => Some("https://console.anthropic.com/settings/keys"),
+            LlmProvider::Ollama => None,
+        }
+    }
+
     /// Get the default API base URL for this provider
     pub fn default_api_base(&self) -> &str {
         match self {
@@ -31,6 +70,18 @@ impl LlmProvider {
         }
     }
 
+    /// Endpoint chat requests are sent to: chat completions, or Anthropic's
+    /// Messages API
+    pub fn chat_url(&self) -> String {
+        match self {
+            LlmProvider::OpenRouter | LlmProvider::OpenAI => {
+                format!("{}/chat/completions", self.default_api_base())
+            }
+            LlmProvider::Anthropic => format!("{}/messages", self.default_api_base()),
+            LlmProvider::Ollama => format!("{}/v1/chat/completions", self.default_api_base()),
+        }
+    }
+
     /// Get the default environment variable name for this provider's API key
     pub fn default_env_var(&self) -> &str {
         match self {
@@ -123,6 +174,10 @@ pub struct Message {
     /// For assistant messages: tool calls requested by the assistant
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub tool_calls: Option<Vec<ToolCall>>,
+
+    /// For user messages: attached images as data URLs (`data:image/png;base64,...`)
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub images: Vec<String>,
 }
 
 impl Message {
@@ -133,6 +188,7 @@ impl Message {
             content: content.into(),
             tool_call_id: None,
             tool_calls: None,
+            images: Vec::new(),
         }
     }
 
@@ -143,6 +199,7 @@ impl Message {
             content,
             tool_call_id: Some(tool_call_id),
             tool_calls: None,
+            images: Vec::new(),
         }
     }
 
@@ -153,8 +210,15 @@ impl Message {
             content,
             tool_call_id: None,
             tool_calls: Some(tool_calls),
+            images: Vec::new(),
         }
     }
+
+    /// Attach images (data URLs) to a user message for vision models
+    pub fn with_images(mut self, images: Vec<String>) -> Self {
+        self.images = images;
+        self
+    }
 }
 
 /// A tool call requested by the LLM
@@ -0,0 +1,401 @@
+// Splitting chat markdown into prose and fenced code blocks
+//
+// Design Decision: Front ends render code blocks themselves
+//
+// Rationale: Markdown viewers draw fenced code as plain monospace. Splitting
+// the message lets a front end highlight each block and give it a language
+// label and a copy button, while the prose in between still goes through the
+// regular markdown renderer.
+//
+// Trade-offs: Only top-level fences (indented at most three spaces) are split
+// out; fences nested in lists or quotes stay with the surrounding prose and
+// render the old way.
+//
+// Streaming: `stable_prefix_len` tells a front end how much of a growing
+// answer is settled, so it only re-processes the rest as chunks arrive.
+//
+// Tables: `extract_tables` finds GFM pipe tables in prose so a front end can
+// scroll wide ones sideways and offer them as CSV (`MarkdownTable::to_csv`).
+// Cells keep their inline markdown (`**bold**` stays as written in the CSV).
+
+use crate::event_log::csv_field;
+
+/// A piece of a markdown message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkdownSegment<'a> {
+    /// Markdown without top-level fenced code blocks
+    Text(&'a str),
+    /// Body of a fenced code block
+    Code {
+        /// First word of the info string (`rust` in "```rust"), if any
+        language: Option<&'a str>,
+        /// Code without the fences; includes the trailing newline
+        code: &'a str,
+        /// No closing fence yet (e.g. the answer is still streaming)
+        open: bool,
+    },
+}
+
+/// Parse a fence line: marker character, fence length and the info string after it
+fn fence(line: &str) -> Option<(char, usize, &str)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let rest = &line[indent..];
+    let marker = rest.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = rest.len() - rest.trim_start_matches(marker).len();
+    if length < 3 {
+        return None;
+    }
+    Some((marker, length, &rest[length..]))
+}
+
+/// Split markdown into prose and fenced code blocks, in order
+///
+/// An unclosed fence runs to the end of the text, like CommonMark.
+///
+/// # Returns
+/// Segments borrowing from `markdown`; empty prose between blocks is dropped
+pub fn split_code_blocks(markdown: &str) -> Vec<MarkdownSegment<'_>> {
+    let mut segments = Vec::new();
+    let mut text_start = 0;
+    let mut offset = 0;
+    // (marker, length, language, start of the code)
+    let mut open_fence: Option<(char, usize, Option<&str>, usize)> = None;
+
+    for line in markdown.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let content = line.trim_end_matches(['\n', '\r']);
+
+        match open_fence {
+            None => {
+                let Some((marker, length, info)) = fence(content) else {
+                    continue;
+                };
+                // Backtick fences can't have backticks in the info string
+                if marker == '`' && info.contains('`') {
+                    continue;
+                }
+                if line_start > text_start {
+                    segments.push(MarkdownSegment::Text(&markdown[text_start..line_start]));
+                }
+                let language = info.split_whitespace().next();
+                open_fence = Some((marker, length, language, offset));
+            }
+            Some((marker, length, language, code_start)) => {
+                let closes = fence(content).is_some_and(|(m, l, info)| {
+                    m == marker && l >= length && info.trim().is_empty()
+                });
+                if closes {
+                    segments.push(MarkdownSegment::Code {
+                        language,
+                        code: &markdown[code_start..line_start],
+                        open: false,
+                    });
+                    open_fence = None;
+                    text_start = offset;
+                }
+            }
+        }
+    }
+
+    match open_fence {
+        Some((_, _, language, code_start)) => segments.push(MarkdownSegment::Code {
+            language,
+            code: &markdown[code_start..],
+            open: true,
+        }),
+        None if text_start < markdown.len() => {
+            segments.push(MarkdownSegment::Text(&markdown[text_start..]))
+        }
+        None => {}
+    }
+    segments
+}
+
+/// Length of the start of a growing message that appending can't change
+///
+/// The prefix ends after the last blank line outside code blocks, or after
+/// the last closing fence line, whichever is later. Text appended to the
+/// message starts a new block after it, so the prefix can be processed once
+/// and only the rest again while an answer streams. (Link reference
+/// definitions in the appended text are the exception; chat answers rarely
+/// use them.)
+pub fn stable_prefix_len(markdown: &str) -> usize {
+    let mut stable = 0;
+    for segment in split_code_blocks(markdown) {
+        match segment {
+            MarkdownSegment::Text(text) => {
+                // Segments borrow from `markdown`, so the pointer gives the offset
+                let start = text.as_ptr() as usize - markdown.as_ptr() as usize;
+                if let Some(pos) = text.rfind("\n\n") {
+                    stable = start + pos + 2;
+                }
+            }
+            MarkdownSegment::Code {
+                code, open: false, ..
+            } => {
+                // After the closing fence, once its line is complete
+                let end = code.as_ptr() as usize - markdown.as_ptr() as usize + code.len();
+                if let Some(pos) = markdown[end..].find('\n') {
+                    stable = end + pos + 1;
+                }
+            }
+            MarkdownSegment::Code { open: true, .. } => {}
+        }
+    }
+    stable
+}
+
+/// A GFM pipe table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkdownTable {
+    /// Byte range of the table, through the newline after its last row
+    pub start: usize,
+    pub end: usize,
+    pub headers: Vec<String>,
+
+    /// Rows padded or cut to the number of headers, like GFM renders them
+    pub rows: Vec<Vec<String>>,
+}
+
+impl MarkdownTable {
+    /// Header and rows as CSV (RFC 4180 quoting)
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        for row in std::iter::once(&self.headers).chain(&self.rows) {
+            let fields: Vec<String> = row.iter().map(|cell| csv_field(cell)).collect();
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Cells of a table row: outer pipes dropped, `\|` kept as a literal pipe
+fn table_cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => line,
+    };
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cell.push('|');
+                chars.next();
+            }
+            '|' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            c => cell.push(c),
+        }
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+/// Whether a line is a table's delimiter row, e.g. `|---|:--:|`
+fn is_delimiter_row(line: &str) -> bool {
+    line.contains('-')
+        && table_cells(line).iter().all(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+        })
+}
+
+/// Tables outside fenced code blocks, in order
+///
+/// A table is a header row, a delimiter row with the same number of cells,
+/// and the rows after it up to a blank line or a line without a pipe.
+pub fn extract_tables(markdown: &str) -> Vec<MarkdownTable> {
+    let mut tables = Vec::new();
+    for segment in split_code_blocks(markdown) {
+        let MarkdownSegment::Text(text) = segment else {
+            continue;
+        };
+        // Segments borrow from `markdown`, so the pointer gives the offset
+        let base = text.as_ptr() as usize - markdown.as_ptr() as usize;
+
+        // (start, end through the newline, content)
+        let mut lines = Vec::new();
+        let mut offset = base;
+        for line in text.split_inclusive('\n') {
+            lines.push((
+                offset,
+                offset + line.len(),
+                line.trim_end_matches(['\n', '\r']),
+            ));
+            offset += line.len();
+        }
+
+        let is_row = |line: &str| {
+            let indent = line.len() - line.trim_start_matches(' ').len();
+            indent < 4 && line.contains('|')
+        };
+        let mut i = 0;
+        while i + 1 < lines.len() {
+            let (start, _, header) = lines[i];
+            let (_, _, delimiter) = lines[i + 1];
+            let headers = table_cells(header);
+            if !is_row(header)
+                || !is_delimiter_row(delimiter)
+                || table_cells(delimiter).len() != headers.len()
+            {
+                i += 1;
+                continue;
+            }
+
+            let mut end = lines[i + 1].1;
+            let mut rows = Vec::new();
+            i += 2;
+            while i < lines.len() && is_row(lines[i].2) && !lines[i].2.trim().is_empty() {
+                let mut row = table_cells(lines[i].2);
+                row.resize(headers.len(), String::new());
+                rows.push(row);
+                end = lines[i].1;
+                i += 1;
+            }
+            tables.push(MarkdownTable {
+                start,
+                end,
+                headers,
+                rows,
+            });
+        }
+    }
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_text_and_code() {
+        let markdown = "Try this:\n\n```rust\nfn main() {}\n```\nThen run it.\n";
+        assert_eq!(
+            split_code_blocks(markdown),
+            vec![
+                MarkdownSegment::Text("Try this:\n\n"),
+                MarkdownSegment::Code {
+                    language: Some("rust"),
+                    code: "fn main() {}\n",
+                    open: false,
+                },
+                MarkdownSegment::Text("Then run it.\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plain_text_is_one_segment() {
+        assert_eq!(
+            split_code_blocks("Just `inline` code"),
+            vec![MarkdownSegment::Text("Just `inline` code")]
+        );
+        assert!(split_code_blocks("").is_empty());
+    }
+
+    #[test]
+    fn test_fence_rules() {
+        // Longer and tilde fences, info string words after the language
+        let markdown = "~~~~python title=x\n```\nnot a close\n~~~~\n";
+        assert_eq!(
+            split_code_blocks(markdown),
+            vec![MarkdownSegment::Code {
+                language: Some("python"),
+                code: "```\nnot a close\n",
+                open: false,
+            }]
+        );
+
+        // Four spaces of indentation is an indented code block, not a fence
+        assert_eq!(
+            split_code_blocks("    ```\n    x\n"),
+            vec![MarkdownSegment::Text("    ```\n    x\n")]
+        );
+    }
+
+    #[test]
+    fn test_unclosed_fence_while_streaming() {
+        assert_eq!(
+            split_code_blocks("Here:\n```js\nconsole.log(1)"),
+            vec![
+                MarkdownSegment::Text("Here:\n"),
+                MarkdownSegment::Code {
+                    language: Some("js"),
+                    code: "console.log(1)",
+                    open: true,
+                },
+            ]
+        );
+        assert_eq!(
+            split_code_blocks("```"),
+            vec![MarkdownSegment::Code {
+                language: None,
+                code: "",
+                open: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_stable_prefix_len() {
+        let markdown = "Intro\n\nStill streaming";
+        assert_eq!(&markdown[..stable_prefix_len(markdown)], "Intro\n\n");
+
+        let markdown = "Intro\n\n```rust\nfn main() {}\n```\nAfter";
+        assert_eq!(
+            &markdown[stable_prefix_len(markdown)..],
+            "After",
+            "a closed code block is settled"
+        );
+
+        // Blank lines inside an open block, or a fence line still being
+        // written, don't count
+        let markdown = "Intro\n\n```\na\n\nb";
+        assert_eq!(&markdown[stable_prefix_len(markdown)..], "```\na\n\nb");
+        let markdown = "Intro\n\n```\ncode\n```";
+        assert_eq!(&markdown[stable_prefix_len(markdown)..], "```\ncode\n```");
+        assert_eq!(stable_prefix_len("One line"), 0);
+    }
+
+    #[test]
+    fn test_extract_tables() {
+        let markdown = "Results:\n\n| Name | Score, % |\n|:-----|-----:|\n| Ada | 95 |\n\
+                        | \"Bob\" \\| co | 80 | extra |\n| Eve |\n\nDone.\n\
+                        ```\n| a | b |\n|---|---|\n```\n";
+        let tables = extract_tables(markdown);
+        assert_eq!(tables.len(), 1);
+
+        let table = &tables[0];
+        assert_eq!(table.headers, ["Name", "Score, %"]);
+        assert_eq!(
+            table.rows,
+            [
+                vec!["Ada", "95"],
+                vec!["\"Bob\" | co", "80"],
+                vec!["Eve", ""],
+            ]
+        );
+        assert!(markdown[table.start..].starts_with("| Name |"));
+        assert!(markdown[..table.end].ends_with("| Eve |\n"));
+        assert_eq!(
+            table.to_csv(),
+            "Name,\"Score, %\"\nAda,95\n\"\"\"Bob\"\" | co\",80\nEve,\n"
+        );
+    }
+
+    #[test]
+    fn test_not_a_table() {
+        assert!(extract_tables("a | b\nno delimiter\n").is_empty());
+        assert!(extract_tables("| a | b |\n|---|\n").is_empty());
+        assert!(extract_tables("").is_empty());
+    }
+}
@@ -0,0 +1,379 @@
+// TeX math in chat answers, typeset to SVG
+//
+// Design Decision: Typeset with the CodeCogs LaTeX service, like mermaid.ink
+// for diagrams
+//
+// Rationale: Models write formulas as TeX (`$...$`, `$$...$$`, `\(...\)`,
+// `\[...\]`), which the markdown viewer shows as raw source. A TeX engine is
+// a large dependency for the GUI; latex.codecogs.com turns a formula into an
+// SVG with the glyphs as paths, which egui's SVG loader draws without any
+// fonts. `embed_math` replaces each formula with an embedded SVG image, the
+// same way `mermaid::embed_diagrams` does for diagrams, so saved
+// conversations keep the typeset formulas.
+//
+// Trade-offs:
+// - Needs network access; formulas that fail to render stay as TeX (as do
+//   all uncached formulas in local-only mode, see `crate::privacy`)
+// - Inline formulas are images in the text flow, aligned to the line rather
+//   than the text baseline
+// - `$` is only math when it can't be currency: the opening `$` must not be
+//   followed by a space, the closing one not preceded by a space or followed
+//   by a digit, and both on one line ("$5 and $10" stays text)
+
+use crate::mermaid::DiagramTheme;
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Typesetting service; the TeX goes URL-encoded after the `?`
+const RENDER_URL: &str = "https://latex.codecogs.com/svg.image?";
+
+/// Resolution the service typesets at, roughly matching the chat's text size
+const RENDER_DPI: u32 = 110;
+
+/// A formula in markdown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MathSpan {
+    /// Byte range of the formula including its delimiters
+    pub start: usize,
+    pub end: usize,
+    pub tex: String,
+
+    /// `$$...$$` or `\[...\]` (a formula on its own line)
+    pub display: bool,
+}
+
+/// Formulas outside code blocks and code spans, in order
+pub fn extract_math(markdown: &str) -> Vec<MathSpan> {
+    let mut spans = Vec::new();
+    for segment in crate::markdown::split_code_blocks(markdown) {
+        if let crate::markdown::MarkdownSegment::Text(text) = segment {
+            // Segments borrow from `markdown`, so the pointer gives the offset
+            let base = text.as_ptr() as usize - markdown.as_ptr() as usize;
+            scan(text, base, &mut spans);
+        }
+    }
+    spans
+}
+
+/// Find formulas in prose; `base` is the prose's offset in the message
+fn scan(text: &str, base: usize, spans: &mut Vec<MathSpan>) {
+    let bytes = text.as_bytes();
+    let mut span = |start: usize, end: usize, tex: &str, display: bool| {
+        let tex = tex.trim();
+        if !tex.is_empty() {
+            spans.push(MathSpan {
+                start: base + start,
+                end: base + end,
+                tex: tex.to_string(),
+                display,
+            });
+        }
+    };
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            // Code span: skip to the closing run of backticks
+            b'`' => {
+                let run = bytes[i..].iter().take_while(|b| **b == b'`').count();
+                let ticks = &text[i..i + run];
+                i += run;
+                if let Some(pos) = text[i..].find(ticks) {
+                    i += pos + run;
+                }
+                continue;
+            }
+            b'\\' => {
+                let close = match bytes.get(i + 1) {
+                    Some(b'[') => Some(("\\]", true)),
+                    Some(b'(') => Some(("\\)", false)),
+                    _ => None,
+                };
+                if let Some((close, display)) = close {
+                    if let Some(pos) = text[i + 2..].find(close) {
+                        let end = i + 2 + pos + close.len();
+                        span(i, end, &text[i + 2..i + 2 + pos], display);
+                        i = end;
+                        continue;
+                    }
+                }
+                // Escaped character, e.g. \$
+                i += 2;
+                continue;
+            }
+            b'$' if bytes.get(i + 1) == Some(&b'$') => {
+                if let Some(pos) = text[i + 2..].find("$$") {
+                    let end = i + 2 + pos + 2;
+                    span(i, end, &text[i + 2..i + 2 + pos], true);
+                    i = end;
+                } else {
+                    i += 2;
+                }
+                continue;
+            }
+            b'$' => {
+                if let Some(close) = inline_dollar_end(bytes, i) {
+                    span(i, close + 1, &text[i + 1..close], false);
+                    i = close + 1;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// Index of the `$` closing an inline formula opened at `open`, if any
+fn inline_dollar_end(bytes: &[u8], open: usize) -> Option<usize> {
+    let first = *bytes.get(open + 1)?;
+    if first.is_ascii_whitespace() || first == b'$' {
+        return None;
+    }
+    let mut i = open + 2;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' => return None,
+            b'\\' => i += 1,
+            b'$' => {
+                let before = bytes[i - 1];
+                let after = bytes.get(i + 1).copied().unwrap_or(b' ');
+                if !before.is_ascii_whitespace() && !after.is_ascii_digit() {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// TeX sent to the service: resolution, color for the theme and, for display
+/// formulas, display style
+pub fn render_source(tex: &str, display: bool, theme: DiagramTheme) -> String {
+    let mut source = format!("\\dpi{{{}}}", RENDER_DPI);
+    if theme == DiagramTheme::Dark {
+        source.push_str("\\color{white}");
+    }
+    if display {
+        source.push_str("\\displaystyle ");
+    }
+    source.push_str(tex);
+    source
+}
+
+/// Percent-encode everything except RFC 3986 unreserved characters
+fn url_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len() * 3);
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Image alt text for a formula: the TeX in its delimiters
+///
+/// Brackets become parentheses and line breaks spaces, so the alt text can't
+/// end the markdown image early. Chat front ends use the `$` to tell
+/// formulas from diagrams.
+pub fn alt_text(span: &MathSpan) -> String {
+    let tex: String = span
+        .tex
+        .chars()
+        .map(|c| match c {
+            '[' => '(',
+            ']' => ')',
+            '\n' | '\r' => ' ',
+            c => c,
+        })
+        .collect();
+    if span.display {
+        format!("$${}$$", tex)
+    } else {
+        format!("${}$", tex)
+    }
+}
+
+/// Formula typesetter with an in-memory cache
+pub struct MathRenderer {
+    client: reqwest::Client,
+    cache: HashMap<String, Vec<u8>>,
+}
+
+impl MathRenderer {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            client,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Typeset a formula as SVG
+    ///
+    /// # Errors
+    /// - The service can't be reached or answers with an error status
+    /// - The answer isn't SVG (e.g. the TeX doesn't parse)
+    pub async fn render_to_svg(
+        &mut self,
+        tex: &str,
+        display: bool,
+        theme: DiagramTheme,
+    ) -> Result<Vec<u8>> {
+        let source = render_source(tex, display, theme);
+        if let Some(cached) = self.cache.get(&source) {
+            return Ok(cached.clone());
+        }
+
+        let url = format!("{}{}", RENDER_URL, url_encode(&source));
+        crate::privacy::check_url(&url)?;
+        crate::connectivity::check_online(crate::connectivity::FORMULAS)?;
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .context("Couldn't reach the math typesetting service")?;
+        if !response.status().is_success() {
+            bail!("Math typesetting returned status {}", response.status());
+        }
+        let svg = response
+            .bytes()
+            .await
+            .context("Failed to read typeset formula")?
+            .to_vec();
+        let head = String::from_utf8_lossy(&svg[..svg.len().min(100)]).to_string();
+        if !head.trim_start().starts_with("<?xml") && !head.trim_start().starts_with("<svg") {
+            bail!("Math typesetting did not return SVG");
+        }
+
+        tracing::debug!("✓ Typeset formula: {} bytes", svg.len());
+        self.cache.insert(source, svg.clone());
+        Ok(svg)
+    }
+
+    /// Number of cached formulas
+    pub fn cache_size(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Total size of the cached SVGs in bytes
+    pub fn cache_memory_bytes(&self) -> usize {
+        self.cache.values().map(|v| v.len()).sum()
+    }
+}
+
+impl Default for MathRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Replace the formulas in markdown with embedded SVG images
+///
+/// Each formula becomes `![$tex$](data:image/svg+xml;base64,...)` (see
+/// `alt_text`). Formulas that fail to render stay as TeX.
+///
+/// # Returns
+/// The new markdown, or None if no formula was rendered
+pub async fn embed_math(
+    renderer: &Mutex<MathRenderer>,
+    markdown: &str,
+    theme: DiagramTheme,
+) -> Option<String> {
+    let spans = extract_math(markdown);
+    if spans.is_empty() {
+        return None;
+    }
+
+    let mut renderer = renderer.lock().await;
+    let mut result = markdown.to_string();
+    let mut rendered = 0;
+
+    // Replace from the end so earlier offsets stay valid
+    for span in spans.iter().rev() {
+        match renderer.render_to_svg(&span.tex, span.display, theme).await {
+            Ok(svg) => {
+                let image = format!(
+                    "![{}](data:image/svg+xml;base64,{})",
+                    alt_text(span),
+                    BASE64.encode(&svg)
+                );
+                result.replace_range(span.start..span.end, &image);
+                rendered += 1;
+            }
+            Err(e)
+                if e.is::<crate::privacy::EgressBlocked>()
+                    || e.is::<crate::connectivity::Offline>() =>
+            {
+                tracing::debug!("Formula not typeset: {}", e)
+            }
+            Err(e) => tracing::warn!("Failed to typeset formula: {:#}", e),
+        }
+    }
+
+    (rendered > 0).then_some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tex(markdown: &str) -> Vec<(String, bool)> {
+        extract_math(markdown)
+            .into_iter()
+            .map(|span| (span.tex, span.display))
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_math() {
+        let markdown = "Euler: $e^{i\\pi} + 1 = 0$ and\n$$\n\\int_0^1 x\\,dx\n$$\n\
+                        also \\(a^2\\) and \\[b^2\\].";
+        assert_eq!(
+            tex(markdown),
+            [
+                ("e^{i\\pi} + 1 = 0".to_string(), false),
+                ("\\int_0^1 x\\,dx".to_string(), true),
+                ("a^2".to_string(), false),
+                ("b^2".to_string(), true),
+            ]
+        );
+
+        let spans = extract_math(markdown);
+        assert_eq!(
+            &markdown[spans[0].start..spans[0].end],
+            "$e^{i\\pi} + 1 = 0$"
+        );
+        assert_eq!(alt_text(&spans[1]), "$$\\int_0^1 x\\,dx$$");
+    }
+
+    #[test]
+    fn test_not_math() {
+        assert!(tex("It costs $5 and $10, or $ 3 $.").is_empty());
+        assert!(tex("Escaped \\$x\\$ stays").is_empty());
+        assert!(tex("Code `$x$` and\n```sh\necho $HOME$PATH\n```\n").is_empty());
+        assert!(tex("Open $x\nacross lines$").is_empty());
+    }
+
+    #[test]
+    fn test_render_source() {
+        assert_eq!(
+            render_source("x^2", true, DiagramTheme::Dark),
+            "\\dpi{110}\\color{white}\\displaystyle x^2"
+        );
+        assert_eq!(url_encode("\\frac{a}{b} c"), "%5Cfrac%7Ba%7D%7Bb%7D%20c");
+    }
+}
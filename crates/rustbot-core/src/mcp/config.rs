@@ -24,10 +24,10 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::process::Command;
 
 use super::error::{McpError, Result};
 use super::extensions::McpConfigEntry;
+use crate::error::RustbotError;
 
 /// Top-level MCP plugin configuration
 ///
@@ -46,6 +46,10 @@ use super::extensions::McpConfigEntry;
 ///     }
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpConfig {
+    /// File format version used by the migration runner (see `crate::migration`)
+    #[serde(default)]
+    pub schema_version: u32,
+
     pub mcp_plugins: McpPlugins,
 }
 
@@ -136,6 +140,11 @@ pub struct LocalServerConfig {
     /// Optional working directory for process
     #[serde(skip_serializing_if = "Option::is_none")]
     pub working_dir: Option<PathBuf>,
+
+    /// Name/description overrides for the plugin's tools, keyed by the
+    /// tool's name on the server (see `ToolOverride`)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tool_overrides: HashMap<String, ToolOverride>,
 }
 
 /// Configuration for a cloud MCP service (HTTP transport)
@@ -184,6 +193,59 @@ pub struct CloudServiceConfig {
     /// Timeout in seconds for HTTP requests
     #[serde(default = "default_timeout")]
     pub timeout: u64,
+
+    /// Name/description overrides for the service's tools, keyed by the
+    /// tool's name on the server (see `ToolOverride`)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tool_overrides: HashMap<String, ToolOverride>,
+}
+
+/// Replacement name and/or description for one of a plugin's tools
+///
+/// Some servers ship tool descriptions that confuse the model. Overrides are
+/// applied when the tool is registered for the agents (see
+/// `RustbotApi::register_mcp_tool`); calls still go to the server under the
+/// tool's own name.
+///
+/// Example:
+///     "tool_overrides": {
+///       "read_file": {
+///         "name": "read_text_file",
+///         "description": "Read a UTF-8 text file inside the allowed directory"
+///       }
+///     }
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolOverride {
+    /// Name shown to the agents instead of the server's (no `:`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Description shown to the agents instead of the server's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl ToolOverride {
+    /// Whether it leaves the tool as the server describes it
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.description.is_none()
+    }
+
+    /// Check the replacement name
+    ///
+    /// Tools are registered as `mcp:{plugin}:{name}`, so the name can't be
+    /// empty or contain `:`.
+    fn validate(&self, plugin_id: &str, tool_name: &str) -> Result<()> {
+        match &self.name {
+            Some(name) if name.trim().is_empty() || name.contains(':') => {
+                Err(McpError::Config(format!(
+                    "Plugin '{}': invalid name '{}' for tool '{}' (must be non-empty, without ':')",
+                    plugin_id, name, tool_name
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 /// Authentication configuration for cloud services
@@ -308,6 +370,7 @@ impl McpConfig {
 
             // Create empty config for new agent
             let config = McpConfig {
+                schema_version: crate::migration::MCP_CONFIG_SCHEMA.current,
                 mcp_plugins: McpPlugins {
                     local_servers: vec![],
                     cloud_services: vec![],
@@ -328,8 +391,15 @@ impl McpConfig {
     /// Example:
     ///     let config = McpConfig::load_from_file("mcp_config.json")?;
     pub fn load_from_file(path: &Path) -> Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        let config: McpConfig = serde_json::from_str(&content)?;
+        // Upgrades (and rewrites) files saved in an older format
+        let value = crate::migration::MCP_CONFIG_SCHEMA
+            .load_file(path)
+            .map_err(|e| match e {
+                RustbotError::IoError(e) => McpError::Io(e),
+                RustbotError::SerdeError(e) => McpError::Json(e),
+                other => McpError::Config(other.to_string()),
+            })?;
+        let config: McpConfig = serde_json::from_value(value)?;
         config.validate()?;
         Ok(config)
     }
@@ -341,6 +411,7 @@ impl McpConfig {
     /// 2. No empty plugin IDs or names
     /// 3. Local servers must have valid commands
     /// 4. Cloud services must have valid URLs
+    /// 5. Tool override names must be non-empty and without `:`
     ///
     /// Error Cases:
     /// - Duplicate IDs: Returns Config error with duplicate ID
@@ -373,6 +444,9 @@ impl McpConfig {
                     server.id
                 )));
             }
+            for (tool, tool_override) in &server.tool_overrides {
+                tool_override.validate(&server.id, tool)?;
+            }
         }
 
         // Validate cloud services
@@ -396,6 +470,9 @@ impl McpConfig {
                     service.id
                 )));
             }
+            for (tool, tool_override) in &service.tool_overrides {
+                tool_override.validate(&service.id, tool)?;
+            }
         }
 
         Ok(())
@@ -448,6 +525,73 @@ impl McpConfig {
         Ok(())
     }
 
+    /// Tool overrides of a plugin, None for an unknown plugin
+    pub fn tool_overrides(&self, plugin_id: &str) -> Option<&HashMap<String, ToolOverride>> {
+        self.mcp_plugins
+            .local_servers
+            .iter()
+            .find(|s| s.id == plugin_id)
+            .map(|s| &s.tool_overrides)
+            .or_else(|| {
+                self.mcp_plugins
+                    .cloud_services
+                    .iter()
+                    .find(|s| s.id == plugin_id)
+                    .map(|s| &s.tool_overrides)
+            })
+    }
+
+    /// Set the override of one of a plugin's tools; an empty override
+    /// removes it
+    ///
+    /// Blank names and descriptions count as not overridden.
+    ///
+    /// # Errors
+    /// - Unknown plugin
+    /// - The name is invalid (see `validate`)
+    pub fn set_tool_override(
+        &mut self,
+        plugin_id: &str,
+        tool_name: &str,
+        tool_override: ToolOverride,
+    ) -> Result<()> {
+        let blank_to_none = |value: Option<String>| {
+            value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        let tool_override = ToolOverride {
+            name: blank_to_none(tool_override.name),
+            description: blank_to_none(tool_override.description),
+        };
+        tool_override.validate(plugin_id, tool_name)?;
+
+        let overrides = if let Some(server) = self
+            .mcp_plugins
+            .local_servers
+            .iter_mut()
+            .find(|s| s.id == plugin_id)
+        {
+            &mut server.tool_overrides
+        } else if let Some(service) = self
+            .mcp_plugins
+            .cloud_services
+            .iter_mut()
+            .find(|s| s.id == plugin_id)
+        {
+            &mut service.tool_overrides
+        } else {
+            return Err(McpError::PluginNotFound(plugin_id.to_string()));
+        };
+
+        if tool_override.is_empty() {
+            overrides.remove(tool_name);
+        } else {
+            overrides.insert(tool_name.to_string(), tool_override);
+        }
+        Ok(())
+    }
+
     /// Remove an extension from configuration by ID
     ///
     /// Searches both local_servers and cloud_services for the given ID
@@ -479,168 +623,33 @@ impl McpConfig {
     }
 }
 
-/// Read a secret from 1Password using the CLI
-///
-/// Supports the `op://vault/item/field` reference format used by 1Password CLI.
-///
-/// # Arguments
-/// * `reference` - 1Password secret reference (e.g., "op://Private/API Keys/credential")
-///
-/// # Returns
-/// * `Ok(String)` - The secret value from 1Password
-/// * `Err` - If `op` CLI is not installed, not authenticated, or reference is invalid
-///
-/// # Example
-/// ```ignore
-/// let secret = read_1password_secret("op://Private/rustbot/api_key")?;
-/// ```
-///
-/// # Error Cases
-/// - `op` CLI not installed: Suggests installation via `brew install 1password-cli`
-/// - Not authenticated: Suggests running `op signin`
-/// - Invalid reference format: Must start with `op://`
-/// - Empty secret: 1Password returned empty value
-///
-/// # Requirements
-/// - 1Password CLI must be installed and available in PATH
-/// - User must be signed in: `op signin`
-/// - Secret reference must exist in user's 1Password account
-fn read_1password_secret(reference: &str) -> Result<String> {
-    // Validate reference format
-    if !reference.starts_with("op://") {
-        return Err(McpError::Config(format!(
-            "Invalid 1Password reference format: '{}'. Must start with 'op://'",
-            reference
-        )));
-    }
-
-    // Execute `op read` command
-    let output = Command::new("op")
-        .arg("read")
-        .arg(reference)
-        .output()
-        .map_err(|e| {
-            McpError::Config(format!(
-                "Failed to execute 1Password CLI: {}\n\
-                 Install: brew install 1password-cli\n\
-                 Reference: {}",
-                e, reference
-            ))
-        })?;
-
-    // Check if command succeeded
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-
-        // Provide helpful error messages based on common failures
-        if stderr.contains("not currently signed in") || stderr.contains("signed out") {
-            return Err(McpError::Config(format!(
-                "Not signed in to 1Password. Run: op signin\n\
-                 Reference: {}",
-                reference
-            )));
-        } else if stderr.contains("isn't an item") || stderr.contains("not found") {
-            return Err(McpError::Config(format!(
-                "1Password secret not found: {}\n\
-                 Error: {}",
-                reference,
-                stderr.trim()
-            )));
-        } else {
-            return Err(McpError::Config(format!(
-                "Failed to read 1Password secret: {}\n\
-                 Error: {}",
-                reference,
-                stderr.trim()
-            )));
-        }
-    }
-
-    // Parse output
-    let secret = String::from_utf8(output.stdout)
-        .map_err(|e| {
-            McpError::Config(format!(
-                "1Password returned invalid UTF-8 for: {}\nError: {}",
-                reference, e
-            ))
-        })?
-        .trim()
-        .to_string();
-
-    // Ensure secret is not empty
-    if secret.is_empty() {
-        return Err(McpError::Config(format!(
-            "1Password secret is empty: {}",
-            reference
-        )));
-    }
-
-    Ok(secret)
-}
-
-/// Resolve environment variable or 1Password secret reference
+/// Resolve a secret reference or environment variable in a config value
 ///
-/// Supports four formats:
+/// Supports every reference `crate::services::resolve_secret` knows:
 /// 1. `op://vault/item/field` - 1Password secret reference
-/// 2. `${VAR}` - Environment variable (required)
-/// 3. `${VAR:-default}` - Environment variable with fallback default
-/// 4. Plain values - Returned as-is
-///
-/// # Arguments
-/// * `value` - The value to resolve
-///
-/// # Returns
-/// * Plain values are returned as-is
-/// * `op://` references are resolved via 1Password CLI
-/// * `${VAR}` references are resolved from environment
+/// 2. `${VAR}` / `${VAR:-default}` / `env://VAR` - Environment variable
+/// 3. `keychain://NAME` - OS keychain entry
+/// 4. `vault://mount/path#field` - HashiCorp Vault
+/// 5. `aws-sm://secret-id[#json-key]` - AWS Secrets Manager
+/// 6. Plain values - Returned as-is
 ///
 /// # Example
 /// ```ignore
 /// // 1Password secret
 /// let api_key = resolve_env_var("op://Private/rustbot/api_key")?;
 ///
-/// // Environment variable
-/// let api_key = resolve_env_var("${OPENROUTER_API_KEY}")?;
-///
 /// // Environment variable with default
 /// let model = resolve_env_var("${MODEL:-anthropic/claude-sonnet-4}")?;
-///
-/// // Plain value
-/// let name = resolve_env_var("assistant")?; // Returns "assistant"
 /// ```
 ///
 /// # Error Cases
 /// - Variable not found: Returns Config error with variable name
-/// - 1Password CLI errors: Returns Config error with helpful message
+/// - Secret manager errors: Returns Config error with helpful message
 pub fn resolve_env_var(value: &str) -> Result<String> {
-    // Check for 1Password secret reference first
-    if value.starts_with("op://") {
-        return read_1password_secret(value);
-    }
-
-    // Not an environment variable reference
-    if !value.starts_with("${") || !value.ends_with("}") {
-        return Ok(value.to_string());
-    }
-
-    // Extract variable expression
-    let var_expr = &value[2..value.len() - 1];
-
-    // Check for default value syntax: ${VAR:-default}
-    if let Some(pos) = var_expr.find(":-") {
-        let var_name = &var_expr[..pos];
-        let default_value = &var_expr[pos + 2..];
-
-        // Return env var if set, otherwise use default
-        match std::env::var(var_name) {
-            Ok(val) if !val.is_empty() => Ok(val),
-            _ => Ok(default_value.to_string()),
-        }
-    } else {
-        // No default, variable is required
-        std::env::var(var_expr)
-            .map_err(|_| McpError::Config(format!("Environment variable not found: {}", var_expr)))
-    }
+    crate::services::resolve_secret(value).map_err(|e| match e {
+        RustbotError::ConfigError(message) => McpError::Config(message),
+        other => McpError::Config(other.to_string()),
+    })
 }
 
 /// Configuration file watcher for hot-reload capability
@@ -765,6 +774,7 @@ mod tests {
     #[test]
     fn test_config_serialization() {
         let config = McpConfig {
+            schema_version: 1,
             mcp_plugins: McpPlugins {
                 local_servers: vec![LocalServerConfig {
                     id: "test".to_string(),
@@ -779,6 +789,7 @@ mod tests {
                     health_check_interval: Some(30),
                     timeout: 60,
                     working_dir: None,
+                    tool_overrides: HashMap::new(),
                 }],
                 cloud_services: vec![],
             },
@@ -794,6 +805,7 @@ mod tests {
     #[test]
     fn test_duplicate_id_validation() {
         let config = McpConfig {
+            schema_version: 1,
             mcp_plugins: McpPlugins {
                 local_servers: vec![
                     LocalServerConfig {
@@ -809,6 +821,7 @@ mod tests {
                         health_check_interval: None,
                         timeout: 60,
                         working_dir: None,
+                        tool_overrides: HashMap::new(),
                     },
                     LocalServerConfig {
                         id: "duplicate".to_string(),
@@ -823,6 +836,7 @@ mod tests {
                         health_check_interval: None,
                         timeout: 60,
                         working_dir: None,
+                        tool_overrides: HashMap::new(),
                     },
                 ],
                 cloud_services: vec![],
@@ -837,6 +851,62 @@ mod tests {
             .contains("Duplicate plugin ID"));
     }
 
+    #[test]
+    fn test_tool_overrides() {
+        let json = r#"{
+            "mcp_plugins": {
+                "local_servers": [{
+                    "id": "filesystem",
+                    "name": "Filesystem",
+                    "command": "npx",
+                    "tool_overrides": {
+                        "read_file": { "description": "Read a text file" }
+                    }
+                }]
+            }
+        }"#;
+        let mut config: McpConfig = serde_json::from_str(json).unwrap();
+        config.validate().unwrap();
+        assert_eq!(
+            config.tool_overrides("filesystem").unwrap()["read_file"].description,
+            Some("Read a text file".to_string())
+        );
+
+        let renamed = ToolOverride {
+            name: Some(" read_text ".to_string()),
+            description: Some("  ".to_string()),
+        };
+        config
+            .set_tool_override("filesystem", "read_file", renamed)
+            .unwrap();
+        assert_eq!(
+            config.tool_overrides("filesystem").unwrap()["read_file"],
+            ToolOverride {
+                name: Some("read_text".to_string()),
+                description: None,
+            }
+        );
+
+        let invalid = ToolOverride {
+            name: Some("fs:read".to_string()),
+            description: None,
+        };
+        assert!(config
+            .set_tool_override("filesystem", "read_file", invalid)
+            .is_err());
+        assert!(config
+            .set_tool_override("missing", "read_file", ToolOverride::default())
+            .is_err());
+
+        config
+            .set_tool_override("filesystem", "read_file", ToolOverride::default())
+            .unwrap();
+        assert!(config.tool_overrides("filesystem").unwrap().is_empty());
+        assert!(!serde_json::to_string(&config)
+            .unwrap()
+            .contains("tool_overrides"));
+    }
+
     #[test]
     fn test_env_var_resolution() {
         env::set_var("TEST_VAR", "test_value");
@@ -249,6 +249,7 @@ impl ExtensionInstaller {
             max_retries: Some(3), // Default 3 retries
             health_check_interval: None,
             timeout: 30, // Default 30s timeout
+            tool_overrides: HashMap::new(),
         };
 
         Ok((
@@ -296,6 +297,7 @@ impl ExtensionInstaller {
             health_check_interval: None,
             timeout: 30,
             working_dir: None,
+            tool_overrides: HashMap::new(),
         };
 
         Ok((InstallationType::Local, McpConfigEntry::LocalServer(config)))
@@ -373,6 +375,7 @@ mod tests {
                 health_check_interval: None,
                 timeout: 30,
                 working_dir: None,
+                tool_overrides: HashMap::new(),
             }),
             metadata: InstallationMetadata {
                 version: "1.0.0".to_string(),
@@ -27,15 +27,16 @@
 //! - Phase 4: Add auto-restart with exponential backoff
 //! - Phase 5: Add event bus integration for status updates
 
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
 
 use super::client::McpClient;
-use super::config::McpConfig;
+use super::config::{McpConfig, ToolOverride};
 use super::error::{McpError, Result};
 use super::plugin::{PluginMetadata, PluginState, PluginType, ToolInfo};
 use super::protocol::McpToolDefinition;
@@ -82,6 +83,9 @@ pub struct McpPluginManager {
     /// Configuration (shared for hot-reload capability)
     config: Arc<RwLock<McpConfig>>,
 
+    /// File the configuration was loaded from; tool overrides are saved there
+    config_path: Arc<RwLock<Option<PathBuf>>>,
+
     /// Plugin metadata registry
     plugins: Arc<RwLock<HashMap<String, PluginMetadata>>>,
 
@@ -115,11 +119,13 @@ impl McpPluginManager {
     pub fn with_event_bus(event_bus: Option<Arc<EventBus>>) -> Self {
         Self {
             config: Arc::new(RwLock::new(McpConfig {
+                schema_version: crate::migration::MCP_CONFIG_SCHEMA.current,
                 mcp_plugins: super::config::McpPlugins {
                     local_servers: Vec::new(),
                     cloud_services: Vec::new(),
                 },
             })),
+            config_path: Arc::new(RwLock::new(None)),
             plugins: Arc::new(RwLock::new(HashMap::new())),
             running_plugins: Arc::new(RwLock::new(HashMap::new())),
             event_bus,
@@ -159,6 +165,7 @@ impl McpPluginManager {
 
         // Store configuration
         *self.config.write().await = config.clone();
+        *self.config_path.write().await = Some(config_path.to_path_buf());
 
         // Initialize plugin metadata (but don't start yet - Phase 1)
         let mut plugins = self.plugins.write().await;
@@ -263,7 +270,7 @@ impl McpPluginManager {
     /// assert_eq!(plugin.state, PluginState::Running);
     /// println!("Tools: {}", plugin.tools.len());
     /// ```
-    pub async fn start_plugin(&mut self, id: &str) -> Result<()> {
+    pub async fn start_plugin(&self, id: &str) -> Result<()> {
         // Check if already running
         {
             let running = self.running_plugins.read().await;
@@ -389,6 +396,37 @@ impl McpPluginManager {
         Ok(())
     }
 
+    /// Start every enabled local server at once
+    ///
+    /// Servers start concurrently, so launching with several plugins takes
+    /// as long as the slowest handshake rather than the sum of all of them.
+    /// A server that fails is left in the Error state; the others still
+    /// start.
+    ///
+    /// Returns: (plugin id, result) for each enabled server, in config order
+    ///
+    /// Example:
+    /// ```rust,ignore
+    /// manager.load_config(path).await?;
+    /// let results = manager.start_enabled().await;
+    /// let started = results.iter().filter(|(_, r)| r.is_ok()).count();
+    /// ```
+    pub async fn start_enabled(&self) -> Vec<(String, Result<()>)> {
+        let ids: Vec<String> = self
+            .config
+            .read()
+            .await
+            .mcp_plugins
+            .local_servers
+            .iter()
+            .filter(|server| server.enabled)
+            .map(|server| server.id.clone())
+            .collect();
+
+        let results = futures::future::join_all(ids.iter().map(|id| self.start_plugin(id))).await;
+        ids.into_iter().zip(results).collect()
+    }
+
     /// Stop a plugin (Phase 2 implementation)
     ///
     /// Gracefully shuts down the plugin process and cleans up resources.
@@ -447,6 +485,37 @@ impl McpPluginManager {
         Ok(())
     }
 
+    /// Stop every running plugin and the health monitor
+    ///
+    /// Called when the app exits so plugin processes don't outlive it as
+    /// orphans.
+    ///
+    /// Returns: (plugin id, result) for each plugin that was running
+    ///
+    /// Side Effects:
+    /// - Aborts the health monitor, so it can't restart a plugin being stopped
+    /// - Each running plugin goes through `stop_plugin`
+    ///
+    /// Example:
+    /// ```rust,ignore
+    /// for (id, result) in manager.stop_all().await {
+    ///     if let Err(e) = result {
+    ///         tracing::warn!("Failed to stop {}: {}", id, e);
+    ///     }
+    /// }
+    /// ```
+    pub async fn stop_all(&mut self) -> Vec<(String, Result<()>)> {
+        self.stop_health_monitoring().await;
+
+        let ids: Vec<String> = self.running_plugins.read().await.keys().cloned().collect();
+        let mut results = Vec::with_capacity(ids.len());
+        for id in ids {
+            let result = self.stop_plugin(&id).await;
+            results.push((id, result));
+        }
+        results
+    }
+
     /// Execute a tool from a running plugin
     ///
     /// Calls a tool on an active plugin and returns the result.
@@ -716,6 +785,65 @@ impl McpPluginManager {
         Ok(tools)
     }
 
+    /// Name/description override of one of a plugin's tools, if any
+    ///
+    /// # Arguments
+    /// * `plugin_id` - Plugin providing the tool
+    /// * `tool_name` - The tool's name on the server
+    pub async fn tool_override(&self, plugin_id: &str, tool_name: &str) -> Option<ToolOverride> {
+        self.config
+            .read()
+            .await
+            .tool_overrides(plugin_id)?
+            .get(tool_name)
+            .cloned()
+    }
+
+    /// Set or clear (with an empty override) the name/description override
+    /// of one of a plugin's tools
+    ///
+    /// Saves the configuration file it was loaded from. While the plugin
+    /// runs, publishes `ToolsChanged` so the API registers its tools again
+    /// with the new override.
+    ///
+    /// # Errors
+    /// - Unknown plugin or invalid name (see `McpConfig::set_tool_override`)
+    /// - The configuration file can't be written
+    pub async fn set_tool_override(
+        &self,
+        plugin_id: &str,
+        tool_name: &str,
+        tool_override: ToolOverride,
+    ) -> Result<()> {
+        let overrides = {
+            let mut config = self.config.write().await;
+            config.set_tool_override(plugin_id, tool_name, tool_override)?;
+            if let Some(path) = self.config_path.read().await.as_deref() {
+                config.save_to_file(path)?;
+            }
+            config
+                .tool_overrides(plugin_id)
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        let tool_count = {
+            let mut plugins = self.plugins.write().await;
+            let Some(plugin) = plugins.get_mut(plugin_id) else {
+                return Ok(());
+            };
+            plugin.tool_overrides = overrides;
+            plugin.is_running().then_some(plugin.tools.len())
+        };
+        if let Some(tool_count) = tool_count {
+            self.emit_event(McpPluginEvent::ToolsChanged {
+                plugin_id: plugin_id.to_string(),
+                tool_count,
+            });
+        }
+        Ok(())
+    }
+
     // ========================================================================
     // Phase 3: Auto-Restart and Health Monitoring
     // ========================================================================
@@ -1001,13 +1129,76 @@ impl McpPluginManager {
             tracing::info!("Health monitoring stopped");
         }
     }
+
+    // ========================================================================
+    // Event bus RPC
+    // ========================================================================
+
+    /// Answer `Request` events addressed to "mcp_manager"
+    ///
+    /// Lets components query plugin state via `EventBus::request` instead of
+    /// holding an `Arc<Mutex<McpPluginManager>>`. Only read-only queries are
+    /// exposed; lifecycle operations still go through the manager directly.
+    ///
+    /// Supported methods:
+    /// - `list_plugins` → `Vec<PluginInfo>`
+    /// - `plugin_tools` (`{"plugin_id": "..."}`) → `Vec<McpToolDefinition>`
+    ///
+    /// # Returns
+    /// Task handle, or None if the manager has no event bus
+    pub fn serve_requests(&self, handle: &tokio::runtime::Handle) -> Option<JoinHandle<()>> {
+        let event_bus = Arc::clone(self.event_bus.as_ref()?);
+        let mut rx = event_bus.subscribe_named("mcp_manager");
+        let manager = self.clone();
+
+        Some(handle.spawn(async move {
+            while let Some(event) = rx.recv().await {
+                let EventKind::Request { method, params } = &event.kind else {
+                    continue;
+                };
+                if event.destination != "mcp_manager" {
+                    continue;
+                }
+
+                let result = manager.handle_request(method, params).await;
+                if let Err(e) = event_bus.respond(&event, "mcp_manager", result) {
+                    tracing::warn!("Failed to publish MCP request reply: {}", e);
+                }
+            }
+        }))
+    }
+
+    /// Dispatch a single RPC request
+    async fn handle_request(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> std::result::Result<serde_json::Value, String> {
+        match method {
+            "list_plugins" => {
+                serde_json::to_value(self.list_plugins().await).map_err(|e| e.to_string())
+            }
+            "plugin_tools" => {
+                let plugin_id = params
+                    .get("plugin_id")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| "missing 'plugin_id' parameter".to_string())?;
+                let tools = self
+                    .get_plugin_tools(plugin_id)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                serde_json::to_value(tools).map_err(|e| e.to_string())
+            }
+            other => Err(format!("Unknown method '{}'", other)),
+        }
+    }
 }
 
 /// Lightweight plugin information for UI lists
 ///
 /// This struct provides essential information without cloning large
 /// tool/resource lists. Use this for displaying plugin lists in UI.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginInfo {
     pub id: String,
     pub name: String,
@@ -1063,6 +1254,47 @@ mod tests {
         assert_eq!(plugin.state, PluginState::Stopped);
     }
 
+    #[tokio::test]
+    async fn test_set_tool_override() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let config_json = r#"{
+            "mcp_plugins": {
+                "local_servers": [
+                    { "id": "test", "name": "Test Server", "command": "echo" }
+                ]
+            }
+        }"#;
+        temp_file.write_all(config_json.as_bytes()).unwrap();
+        temp_file.flush().unwrap();
+
+        let mut manager = McpPluginManager::new();
+        manager.load_config(temp_file.path()).await.unwrap();
+        let tool_override = ToolOverride {
+            name: None,
+            description: Some("Echo the input back".to_string()),
+        };
+        manager
+            .set_tool_override("test", "echo", tool_override.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            manager.tool_override("test", "echo").await,
+            Some(tool_override.clone())
+        );
+        let plugin = manager.get_plugin("test").await.unwrap();
+        assert_eq!(plugin.tool_overrides["echo"], tool_override);
+
+        // Saved to the file it was loaded from
+        let saved = McpConfig::load_from_file(temp_file.path()).unwrap();
+        assert!(saved.tool_overrides("test").unwrap().contains_key("echo"));
+
+        assert!(manager
+            .set_tool_override("missing", "echo", ToolOverride::default())
+            .await
+            .is_err());
+    }
+
     #[tokio::test]
     async fn test_list_plugins() {
         let mut temp_file = NamedTempFile::new().unwrap();
@@ -1139,8 +1371,51 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_start_plugin_not_found() {
+    async fn test_start_enabled_skips_disabled_servers() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let config_json = r#"{
+            "mcp_plugins": {
+                "local_servers": [
+                    {
+                        "id": "missing",
+                        "name": "Missing Server",
+                        "command": "/nonexistent/rustbot-test-server",
+                        "args": [],
+                        "enabled": true
+                    },
+                    {
+                        "id": "disabled",
+                        "name": "Disabled Server",
+                        "command": "/nonexistent/rustbot-test-server",
+                        "args": [],
+                        "enabled": false
+                    }
+                ],
+                "cloud_services": []
+            }
+        }"#;
+        temp_file.write_all(config_json.as_bytes()).unwrap();
+        temp_file.flush().unwrap();
+
         let mut manager = McpPluginManager::new();
+        manager.load_config(temp_file.path()).await.unwrap();
+
+        // Only the enabled server is attempted; its failure is reported
+        let results = manager.start_enabled().await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, "missing");
+        assert!(results[0].1.is_err());
+        let plugin = manager.get_plugin("missing").await.unwrap();
+        assert!(matches!(plugin.state, PluginState::Error { .. }));
+        assert_eq!(
+            manager.get_plugin("disabled").await.unwrap().state,
+            PluginState::Disabled
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_plugin_not_found() {
+        let manager = McpPluginManager::new();
 
         // Starting non-existent plugin should fail
         let result = manager.start_plugin("nonexistent").await;
@@ -1176,6 +1451,13 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_stop_all_without_running_plugins() {
+        let mut manager = McpPluginManager::new();
+        assert!(manager.stop_all().await.is_empty());
+        assert!(manager.health_monitor_handle.read().await.is_none());
+    }
+
     // ========================================================================
     // Phase 3 Tests: Auto-Restart, Health Monitoring, Config Reload
     // ========================================================================
@@ -1379,6 +1661,50 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_serve_requests_list_plugins() {
+        let event_bus = Arc::new(EventBus::new());
+        let mut manager = McpPluginManager::with_event_bus(Some(Arc::clone(&event_bus)));
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file
+            .write_all(
+                br#"{"mcp_plugins": {"local_servers": [
+                    {"id": "fs", "name": "Filesystem", "command": "echo", "enabled": false}
+                ]}}"#,
+            )
+            .unwrap();
+        manager.load_config(temp_file.path()).await.unwrap();
+
+        let _server = manager
+            .serve_requests(&tokio::runtime::Handle::current())
+            .unwrap();
+
+        let plugins: Vec<PluginInfo> = event_bus
+            .request(
+                "test",
+                "mcp_manager",
+                "list_plugins",
+                serde_json::Value::Null,
+                Duration::from_secs(1),
+            )
+            .await
+            .unwrap();
+        assert_eq!(plugins.len(), 1);
+        assert_eq!(plugins[0].id, "fs");
+
+        let unknown: std::result::Result<serde_json::Value, _> = event_bus
+            .request(
+                "test",
+                "mcp_manager",
+                "bogus",
+                serde_json::Value::Null,
+                Duration::from_secs(1),
+            )
+            .await;
+        assert!(unknown.is_err());
+    }
+
     #[tokio::test]
     async fn test_reload_config_add_plugin() {
         let mut temp_file = NamedTempFile::new().unwrap();
@@ -61,6 +61,17 @@ impl MarketplaceClient {
         }
     }
 
+    /// Check that the registry can be contacted
+    ///
+    /// # Errors
+    /// - Local-only mode is on and the registry isn't an allowed endpoint
+    /// - Rustbot is offline
+    pub fn check_allowed(&self) -> Result<(), MarketplaceError> {
+        crate::privacy::check_url(&self.base_url)?;
+        crate::connectivity::check_online(crate::connectivity::MARKETPLACE)?;
+        Ok(())
+    }
+
     /// List all servers with pagination
     ///
     /// # Arguments
@@ -89,6 +100,7 @@ impl MarketplaceClient {
             "{}/servers?limit={}&offset={}",
             self.base_url, limit, offset
         );
+        self.check_allowed()?;
         let response = self.http_client.get(&url).send().await?;
 
         // Check for HTTP errors before parsing
@@ -123,6 +135,7 @@ impl MarketplaceClient {
         limit: usize,
     ) -> Result<McpRegistry, MarketplaceError> {
         let url = format!("{}/servers?search={}&limit={}", self.base_url, query, limit);
+        self.check_allowed()?;
         let response = self.http_client.get(&url).send().await?;
 
         // Check for HTTP errors before parsing
@@ -336,6 +349,8 @@ pub struct RegistryMetadata {
 ///
 /// - `NetworkError`: Retry with exponential backoff (UI responsibility)
 /// - `ParseError`: Log error and show generic failure message
+/// - `Blocked`: Local-only mode is on; nothing to retry
+/// - `Offline`: No connection; retry once back online
 #[derive(Debug)]
 pub enum MarketplaceError {
     /// HTTP request failed (network error, DNS failure, timeout, etc.)
@@ -343,6 +358,12 @@ pub enum MarketplaceError {
 
     /// JSON parsing failed (malformed response)
     ParseError(serde_json::Error),
+
+    /// Local-only mode keeps the registry from being contacted
+    Blocked(crate::privacy::EgressBlocked),
+
+    /// The registry can't be reached while offline
+    Offline(crate::connectivity::Offline),
 }
 
 impl From<reqwest::Error> for MarketplaceError {
@@ -351,6 +372,18 @@ impl From<reqwest::Error> for MarketplaceError {
     }
 }
 
+impl From<crate::privacy::EgressBlocked> for MarketplaceError {
+    fn from(err: crate::privacy::EgressBlocked) -> Self {
+        MarketplaceError::Blocked(err)
+    }
+}
+
+impl From<crate::connectivity::Offline> for MarketplaceError {
+    fn from(err: crate::connectivity::Offline) -> Self {
+        MarketplaceError::Offline(err)
+    }
+}
+
 impl From<serde_json::Error> for MarketplaceError {
     fn from(err: serde_json::Error) -> Self {
         MarketplaceError::ParseError(err)
@@ -362,6 +395,8 @@ impl std::fmt::Display for MarketplaceError {
         match self {
             MarketplaceError::NetworkError(e) => write!(f, "Network error: {}", e),
             MarketplaceError::ParseError(e) => write!(f, "Failed to parse response: {}", e),
+            MarketplaceError::Blocked(e) => write!(f, "{}", e),
+            MarketplaceError::Offline(e) => write!(f, "{}", e),
         }
     }
 }
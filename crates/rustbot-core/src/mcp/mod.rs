@@ -67,7 +67,7 @@
 //! # Usage Example
 //!
 //! ```rust,ignore
-//! use rustbot::mcp::{McpPluginManager, McpConfig};
+//! use rustbot_core::mcp::{McpPluginManager, McpConfig};
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -120,12 +120,14 @@ pub mod manager;
 pub mod marketplace; // Marketplace API client for MCP Registry
 pub mod plugin;
 pub mod protocol; // Phase 2: MCP protocol types
+pub mod schema; // Tool input schemas as display rows and function parameters
 pub mod stdio; // Phase 2: stdio transport implementation
 pub mod transport; // Phase 2: Transport layer (stdio, HTTP) // Extension system for downloadable MCP services
 
 // Re-export commonly used types for convenience
 pub use config::{
     resolve_env_var, AuthConfig, CloudServiceConfig, LocalServerConfig, McpConfig, McpPlugins,
+    ToolOverride,
 };
 
 pub use plugin::{
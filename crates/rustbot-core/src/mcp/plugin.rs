@@ -23,9 +23,10 @@
 //! - Add state transition history for debugging
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::SystemTime;
 
-use super::config::{CloudServiceConfig, LocalServerConfig};
+use super::config::{CloudServiceConfig, LocalServerConfig, ToolOverride};
 
 /// Plugin lifecycle state
 ///
@@ -177,6 +178,13 @@ pub struct PluginMetadata {
     /// Loaded from config file, defaults to 5
     #[serde(default = "default_max_retries")]
     pub max_retries: u32,
+
+    /// Name/description overrides for the tools, from the config
+    ///
+    /// `tools` lists the tools as the server describes them; the overrides
+    /// are applied when they are registered for the agents.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tool_overrides: HashMap<String, ToolOverride>,
 }
 
 // Default value for max_retries
@@ -287,6 +295,7 @@ impl PluginMetadata {
             restart_count: 0,
             last_restart: None,
             max_retries: config.max_retries.unwrap_or(5),
+            tool_overrides: config.tool_overrides.clone(),
         }
     }
 
@@ -308,6 +317,7 @@ impl PluginMetadata {
             restart_count: 0,
             last_restart: None,
             max_retries: config.max_retries.unwrap_or(5),
+            tool_overrides: config.tool_overrides.clone(),
         }
     }
 
@@ -364,6 +374,7 @@ mod tests {
             health_check_interval: Some(30),
             timeout: 60,
             working_dir: None,
+            tool_overrides: std::collections::HashMap::new(),
         };
 
         let metadata = PluginMetadata::new_local_server(&config);
@@ -389,6 +400,7 @@ mod tests {
             restart_count: 0,
             last_restart: None,
             max_retries: 5,
+            tool_overrides: HashMap::new(),
         };
 
         assert!(metadata.is_running());
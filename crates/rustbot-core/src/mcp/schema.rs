@@ -0,0 +1,398 @@
+//! Tool input schemas flattened into rows for display, and turned into
+//! function parameters for the model
+//!
+//! Design Decision: One row per parameter, nested parameters indented
+//!
+//! Rationale: MCP tools describe their arguments with JSON Schema, which is
+//! hard to read raw once objects nest or types combine. The extension detail
+//! page shows a table instead: each property becomes a `SchemaField` with a
+//! dotted path, a short type ("array of string", "string | null"), whether
+//! it is required, and its description, enum values and default. Properties
+//! of nested objects (and of objects inside arrays, as `name[].field`)
+//! follow their parent with a larger `depth`.
+//!
+//! `parameters` converts the same schema into the `FunctionParameters` sent
+//! with the tool: the top-level `properties` and `required` list, with nested
+//! constraints (their own `required`, enums, item schemas) kept as they are.
+//! References to the schema's own `$defs`/`definitions` are inlined, since
+//! only the properties are sent and the definitions would be left behind.
+//!
+//! Trade-offs:
+//! - `$ref` is shown by name, not resolved; the raw schema is shown next to
+//!   the table for anything the rows leave out
+//! - Nesting stops at `MAX_DEPTH` so self-referencing schemas can't recurse
+//!   forever; a reference nested deeper than that is left as it is
+//! - Top-level `allOf`/`anyOf` and other keywords outside `properties` aren't
+//!   carried over to the parameters
+
+use crate::agent::tools::FunctionParameters;
+use serde_json::{Map, Value};
+
+/// Deepest nesting level turned into rows
+const MAX_DEPTH: usize = 8;
+
+/// One parameter of a tool's input schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaField {
+    /// Dotted path, e.g. "options.depth" or "files[].path"
+    pub path: String,
+
+    /// Property name (last part of the path)
+    pub name: String,
+
+    /// Nesting level, 0 for top-level parameters
+    pub depth: usize,
+
+    /// Short type description, e.g. "integer" or "array of string"
+    pub type_name: String,
+    pub required: bool,
+    pub description: Option<String>,
+
+    /// Allowed values, as JSON
+    pub enum_values: Vec<String>,
+
+    /// Default value, as JSON
+    pub default: Option<String>,
+}
+
+/// Parameters of a tool input schema, in display order
+///
+/// Returns nothing for schemas without properties (tools that take no
+/// arguments, or schemas this function can't read).
+pub fn fields(schema: &Value) -> Vec<SchemaField> {
+    let mut fields = Vec::new();
+    collect(schema, "", 0, &mut fields);
+    fields
+}
+
+/// Function parameters for a tool input schema
+///
+/// Required names that aren't properties are dropped; providers reject
+/// them. Schemas without properties give a tool that takes no arguments.
+pub fn parameters(schema: &Value) -> FunctionParameters {
+    let properties = match schema.get("properties") {
+        Some(Value::Object(properties)) => properties
+            .iter()
+            .map(|(name, property)| (name.clone(), inline_refs(property, schema, 0)))
+            .collect(),
+        _ => Map::new(),
+    };
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(Value::as_str)
+                .filter(|name| properties.contains_key(*name))
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    FunctionParameters {
+        param_type: "object".to_string(),
+        properties: Value::Object(properties),
+        required,
+    }
+}
+
+/// Short description of a schema's type
+pub fn type_name(schema: &Value) -> String {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return reference
+            .rsplit('/')
+            .next()
+            .unwrap_or(reference)
+            .to_string();
+    }
+    for key in ["anyOf", "oneOf"] {
+        if let Some(options) = schema.get(key).and_then(Value::as_array) {
+            return options
+                .iter()
+                .map(type_name)
+                .collect::<Vec<_>>()
+                .join(" | ");
+        }
+    }
+
+    let types: Vec<String> = match schema.get("type") {
+        Some(Value::String(name)) => vec![name.clone()],
+        Some(Value::Array(names)) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .map(String::from)
+            .collect(),
+        _ if schema.get("enum").is_some() => vec!["enum".to_string()],
+        _ if schema.get("properties").is_some() => vec!["object".to_string()],
+        _ => vec!["any".to_string()],
+    };
+    types
+        .into_iter()
+        .map(|name| match (name.as_str(), schema.get("items")) {
+            ("array", Some(items)) => format!("array of {}", type_name(items)),
+            _ => name,
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// Replace local `$ref`s (`#/...`) in `value` with what they point to in `root`
+///
+/// Keywords next to a `$ref` (a description, say) win over the target's.
+fn inline_refs(value: &Value, root: &Value, depth: usize) -> Value {
+    match value {
+        Value::Object(object) => {
+            let target = object
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|reference| reference.strip_prefix('#'))
+                .filter(|_| depth < MAX_DEPTH)
+                .and_then(|pointer| root.pointer(pointer));
+            match target {
+                Some(target) => {
+                    let mut merged = match inline_refs(target, root, depth + 1) {
+                        Value::Object(target) => target,
+                        other => return other,
+                    };
+                    for (key, field) in object.iter().filter(|(key, _)| *key != "$ref") {
+                        merged.insert(key.clone(), inline_refs(field, root, depth + 1));
+                    }
+                    Value::Object(merged)
+                }
+                None => Value::Object(
+                    object
+                        .iter()
+                        .map(|(key, field)| (key.clone(), inline_refs(field, root, depth)))
+                        .collect(),
+                ),
+            }
+        }
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| inline_refs(value, root, depth))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn collect(schema: &Value, prefix: &str, depth: usize, fields: &mut Vec<SchemaField>) {
+    if depth >= MAX_DEPTH {
+        return;
+    }
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return;
+    };
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    for (name, property) in properties {
+        let path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        fields.push(SchemaField {
+            path: path.clone(),
+            name: name.clone(),
+            depth,
+            type_name: type_name(property),
+            required: required.contains(&name.as_str()),
+            description: property
+                .get("description")
+                .and_then(Value::as_str)
+                .map(String::from),
+            enum_values: property
+                .get("enum")
+                .and_then(Value::as_array)
+                .map(|values| values.iter().map(Value::to_string).collect())
+                .unwrap_or_default(),
+            default: property.get("default").map(Value::to_string),
+        });
+
+        // Nested objects, directly or as array items
+        collect(property, &path, depth + 1, fields);
+        if let Some(items) = property.get("items") {
+            collect(items, &format!("{}[]", path), depth + 1, fields);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fields() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "Search terms" },
+                "limit": { "type": ["integer", "null"], "default": 10 },
+                "sort": { "enum": ["date", "relevance"] },
+                "filters": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": { "field": { "type": "string" } },
+                        "required": ["field"]
+                    }
+                },
+                "options": {
+                    "type": "object",
+                    "properties": { "depth": { "$ref": "#/$defs/Depth" } }
+                }
+            },
+            "required": ["query"]
+        });
+
+        // Property order is serde_json's (sorted, or as written with
+        // preserve_order); nested fields always follow their parent
+        let fields = fields(&schema);
+        let mut paths: Vec<&str> = fields.iter().map(|f| f.path.as_str()).collect();
+        let position = |path: &str| paths.iter().position(|p| *p == path).unwrap();
+        assert_eq!(position("filters[].field"), position("filters") + 1);
+        assert_eq!(position("options.depth"), position("options") + 1);
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                "filters",
+                "filters[].field",
+                "limit",
+                "options",
+                "options.depth",
+                "query",
+                "sort"
+            ]
+        );
+
+        let by_path = |path: &str| fields.iter().find(|f| f.path == path).unwrap();
+        assert_eq!(by_path("filters").type_name, "array of object");
+        assert_eq!(by_path("filters[].field").depth, 1);
+        assert!(by_path("filters[].field").required);
+        assert_eq!(by_path("limit").type_name, "integer | null");
+        assert_eq!(by_path("limit").default.as_deref(), Some("10"));
+        assert_eq!(by_path("options.depth").type_name, "Depth");
+        assert!(by_path("query").required);
+        assert_eq!(
+            by_path("query").description.as_deref(),
+            Some("Search terms")
+        );
+        assert_eq!(by_path("sort").type_name, "enum");
+        assert_eq!(by_path("sort").enum_values, ["\"date\"", "\"relevance\""]);
+    }
+
+    #[test]
+    fn test_schema_without_properties() {
+        assert!(fields(&json!({ "type": "object" })).is_empty());
+        assert!(fields(&json!(null)).is_empty());
+        assert_eq!(type_name(&json!({})), "any");
+    }
+
+    #[test]
+    fn test_parameters() {
+        // edit_file from @modelcontextprotocol/server-filesystem
+        let edit_file = json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string" },
+                "edits": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "oldText": {
+                                "type": "string",
+                                "description": "Text to search for - must match exactly"
+                            },
+                            "newText": { "type": "string", "description": "Text to replace with" }
+                        },
+                        "required": ["oldText", "newText"],
+                        "additionalProperties": false
+                    }
+                },
+                "dryRun": {
+                    "type": "boolean",
+                    "default": false,
+                    "description": "Preview changes using git-style diff format"
+                }
+            },
+            "required": ["path", "edits"],
+            "additionalProperties": false,
+            "$schema": "http://json-schema.org/draft-07/schema#"
+        });
+        let params = parameters(&edit_file);
+        assert_eq!(params.param_type, "object");
+        assert_eq!(params.required, ["path", "edits"]);
+        // The whole schema isn't nested under `properties`
+        assert!(params.properties.get("$schema").is_none());
+        assert_eq!(
+            params.properties["edits"]["items"]["required"],
+            json!(["oldText", "newText"])
+        );
+
+        // A FastMCP (pydantic) tool taking an enum and a nested model
+        let create_task = json!({
+            "$defs": {
+                "Priority": { "enum": ["low", "high"], "title": "Priority", "type": "string" },
+                "Assignee": {
+                    "properties": {
+                        "name": { "title": "Name", "type": "string" },
+                        "priority": { "$ref": "#/$defs/Priority" }
+                    },
+                    "required": ["name"],
+                    "title": "Assignee",
+                    "type": "object"
+                }
+            },
+            "properties": {
+                "title": { "title": "Title", "type": "string" },
+                "priority": { "$ref": "#/$defs/Priority", "default": "low" },
+                "assignee": { "anyOf": [{ "$ref": "#/$defs/Assignee" }, { "type": "null" }] }
+            },
+            "required": ["title", "due"],
+            "title": "create_taskArguments",
+            "type": "object"
+        });
+        let params = parameters(&create_task);
+        assert_eq!(params.required, ["title"]);
+        assert_eq!(
+            params.properties["priority"],
+            json!({
+                "enum": ["low", "high"],
+                "title": "Priority",
+                "type": "string",
+                "default": "low"
+            })
+        );
+        let assignee = &params.properties["assignee"]["anyOf"][0];
+        assert_eq!(assignee["required"], json!(["name"]));
+        assert_eq!(
+            assignee["properties"]["priority"]["enum"],
+            json!(["low", "high"])
+        );
+
+        // Self-referencing schemas stop resolving instead of recursing forever
+        let tree = json!({
+            "$defs": {
+                "Node": {
+                    "type": "object",
+                    "properties": { "child": { "$ref": "#/$defs/Node" } }
+                }
+            },
+            "properties": { "root": { "$ref": "#/$defs/Node" } }
+        });
+        assert!(parameters(&tree).properties["root"]["properties"]["child"].is_object());
+
+        let empty = parameters(&json!({ "type": "object" }));
+        assert_eq!(empty.properties, json!({}));
+        assert!(empty.required.is_empty());
+    }
+}
@@ -370,6 +370,7 @@ mod tests {
             health_check_interval: None,
             timeout: 60,
             working_dir: None,
+            tool_overrides: HashMap::new(),
         };
 
         let transport = StdioTransport::new(config);
@@ -391,6 +392,7 @@ mod tests {
             health_check_interval: None,
             timeout: 60,
             working_dir: None,
+            tool_overrides: HashMap::new(),
         };
 
         let transport = StdioTransport::new(config);
@@ -419,6 +421,7 @@ mod tests {
             health_check_interval: None,
             timeout: 60,
             working_dir: None,
+            tool_overrides: HashMap::new(),
         };
 
         let mut transport = StdioTransport::new(config);
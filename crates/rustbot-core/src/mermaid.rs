@@ -21,10 +21,27 @@
 //
 // Extension Points: The MermaidRenderer trait could support multiple backends
 // if local rendering becomes necessary (check network connectivity, fallback to local).
+//
+// Output format: The chat shows diagrams as SVG (`render_to_svg`), drawn by
+// egui's SVG loader so they stay sharp at any size. mermaid.ink's SVG puts
+// labels in <foreignObject> HTML, which usvg (behind egui's loader) skips, so
+// the diagram source gets an init directive turning HTML labels off and
+// picking the mermaid theme that matches the UI (`DiagramTheme`).
+// `render_to_png` (the lossy JPEG from /img/) remains for callers that need
+// a raster image.
+//
+// Local-only and offline mode: Both renderers fail with `MermaidError::Blocked`
+// or `MermaidError::Offline` before any request, so the chat keeps showing the
+// diagram source as code.
+//
+// Threading: `embed_diagrams` awaits mermaid.ink, so the GUI runs it as a
+// background task when an answer completes and swaps the rendered markdown in
+// when it arrives; the code blocks show until then.
 
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use std::collections::HashMap;
 use std::time::Duration;
+use tokio::sync::Mutex;
 
 /// Result type for mermaid operations
 type Result<T> = std::result::Result<T, MermaidError>;
@@ -43,6 +60,59 @@ pub enum MermaidError {
 
     #[error("Timeout while rendering diagram")]
     Timeout,
+
+    /// Local-only mode keeps diagram source off mermaid.ink
+    #[error(transparent)]
+    Blocked(#[from] crate::privacy::EgressBlocked),
+
+    /// mermaid.ink can't be reached while offline
+    #[error(transparent)]
+    Offline(#[from] crate::connectivity::Offline),
+}
+
+/// Mermaid theme to draw a diagram in, following the UI's light or dark mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagramTheme {
+    Light,
+    Dark,
+}
+
+impl DiagramTheme {
+    /// Name of the built-in mermaid theme
+    pub fn mermaid_name(self) -> &'static str {
+        match self {
+            DiagramTheme::Light => "default",
+            DiagramTheme::Dark => "dark",
+        }
+    }
+}
+
+/// Diagram source with an init directive for the theme and plain-text labels
+///
+/// Diagrams that already start with their own `%%{init: ...}%%` directive are
+/// left alone.
+pub fn themed_source(mermaid_code: &str, theme: DiagramTheme) -> String {
+    if mermaid_code.trim_start().starts_with("%%{") {
+        return mermaid_code.to_string();
+    }
+    format!(
+        "%%{{init: {{\"theme\": \"{}\", \"htmlLabels\": false, \
+         \"flowchart\": {{\"htmlLabels\": false}}}}}}%%\n{}",
+        theme.mermaid_name(),
+        mermaid_code
+    )
+}
+
+/// Work around mermaid.ink output that makes usvg skip elements
+///
+/// mermaid.ink generates <rect> elements with empty or "0" width/height,
+/// which usvg drops (losing labels).
+fn fix_svg(svg: &str) -> String {
+    svg.replace(r#"width="0""#, r#"width="0.1""#)
+        .replace(r#"height="0""#, r#"height="0.1""#)
+        // Also fix empty width/height attributes
+        .replace(r#"width="""#, r#"width="1""#)
+        .replace(r#"height="""#, r#"height="1""#)
 }
 
 /// Mermaid diagram renderer with caching
@@ -92,9 +162,10 @@ impl MermaidRenderer {
     ///
     /// This method:
     /// 1. Checks cache first for previously rendered diagrams
-    /// 2. Base64 encodes the mermaid code if not cached
+    /// 2. Adds the theme directive (see `themed_source`) and base64 encodes
+    ///    the mermaid code if not cached
     /// 3. Calls the mermaid.ink API: https://mermaid.ink/svg/{encoded}
-    /// 4. Caches and returns the SVG result
+    /// 4. Fixes attributes usvg can't handle, caches and returns the SVG
     ///
     /// Error Handling:
     /// 1. NetworkError: Retry logic not implemented (fails fast)
@@ -108,6 +179,7 @@ impl MermaidRenderer {
     ///
     /// # Arguments
     /// * `mermaid_code` - The mermaid diagram code to render
+    /// * `theme` - Light or dark diagram colors
     ///
     /// # Returns
     /// * `Ok(Vec<u8>)` - SVG image data on success
@@ -116,17 +188,22 @@ impl MermaidRenderer {
     /// # Example
     /// ```ignore
     /// let renderer = MermaidRenderer::new();
-    /// let svg = renderer.render_to_svg("graph TD\n  A-->B").await?;
+    /// let svg = renderer.render_to_svg("graph TD\n  A-->B", DiagramTheme::Dark).await?;
     /// ```
-    pub async fn render_to_svg(&mut self, mermaid_code: &str) -> Result<Vec<u8>> {
-        // Check cache first (O(1) lookup)
-        if let Some(cached) = self.cache.get(mermaid_code) {
+    pub async fn render_to_svg(
+        &mut self,
+        mermaid_code: &str,
+        theme: DiagramTheme,
+    ) -> Result<Vec<u8>> {
+        // Check cache first (O(1) lookup) - the theme is part of the key
+        let cache_key = format!("svg:{}:{}", theme.mermaid_name(), mermaid_code);
+        if let Some(cached) = self.cache.get(&cache_key) {
             return Ok(cached.clone());
         }
 
         // Base64 encode the mermaid code
         // Note: URL-safe encoding to handle special characters in diagram code
-        let encoded = BASE64.encode(mermaid_code.as_bytes());
+        let encoded = BASE64.encode(themed_source(mermaid_code, theme).as_bytes());
 
         // Call mermaid.ink API
         let url = format!("https://mermaid.ink/svg/{}", encoded);
@@ -137,6 +214,8 @@ impl MermaidRenderer {
         );
 
         // Make request with timeout (5 seconds)
+        crate::privacy::check_url(&url)?;
+        crate::connectivity::check_online(crate::connectivity::DIAGRAMS)?;
         let response = self.client.get(&url).send().await?;
 
         // Check if request succeeded
@@ -175,9 +254,11 @@ impl MermaidRenderer {
 
         tracing::info!("✓ Rendered mermaid diagram: {} bytes", svg_bytes.len());
 
+        // Pre-process SVG to fix mermaid.ink issues that cause usvg to skip elements
+        let svg_bytes = fix_svg(&String::from_utf8_lossy(&svg_bytes)).into_bytes();
+
         // Cache the result
-        self.cache
-            .insert(mermaid_code.to_string(), svg_bytes.clone());
+        self.cache.insert(cache_key, svg_bytes.clone());
 
         Ok(svg_bytes)
     }
@@ -216,6 +297,8 @@ impl MermaidRenderer {
         );
 
         // Make request with timeout (5 seconds)
+        crate::privacy::check_url(&url)?;
+        crate::connectivity::check_online(crate::connectivity::DIAGRAMS)?;
         let response = self.client.get(&url).send().await?;
 
         // Check for success
@@ -287,54 +370,6 @@ impl MermaidRenderer {
     pub fn cache_memory_bytes(&self) -> usize {
         self.cache.values().map(|v| v.len()).sum()
     }
-
-    /// Convert SVG bytes to PNG with transparent background
-    ///
-    /// Uses resvg to render SVG to PNG format which is compatible with
-    /// egui_commonmark's embedded_image feature.
-    ///
-    /// # Arguments
-    /// * `svg_bytes` - SVG image data
-    ///
-    /// # Returns
-    /// * `Ok(Vec<u8>)` - PNG image data with transparency
-    /// * `Err(MermaidError)` - Conversion error
-    fn svg_to_png(svg_bytes: &[u8]) -> Result<Vec<u8>> {
-        // Pre-process SVG to fix mermaid.ink issues that cause usvg to skip elements
-        // Issue: mermaid.ink generates <rect> elements with empty or "0" width/height
-        // which causes usvg to skip them (losing labels)
-        let svg_str = String::from_utf8_lossy(svg_bytes);
-
-        // Fix invalid rect attributes by replacing width="0" and height="0" with small values
-        let fixed_svg = svg_str
-            .replace(r#"width="0""#, r#"width="0.1""#)
-            .replace(r#"height="0""#, r#"height="0.1""#)
-            // Also fix empty width/height attributes
-            .replace(r#"width="""#, r#"width="1""#)
-            .replace(r#"height="""#, r#"height="1""#);
-
-        // Parse SVG
-        let options = usvg::Options::default();
-        let tree = usvg::Tree::from_data(fixed_svg.as_bytes(), &options)
-            .map_err(|e| MermaidError::EncodingError(format!("Failed to parse SVG: {}", e)))?;
-
-        // Get SVG dimensions
-        let size = tree.size();
-
-        // Create pixmap with transparent background
-        let mut pixmap = tiny_skia::Pixmap::new(size.width() as u32, size.height() as u32)
-            .ok_or_else(|| MermaidError::EncodingError("Failed to create pixmap".to_string()))?;
-
-        // Render SVG to pixmap
-        resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
-
-        // Encode as PNG
-        let png_data = pixmap
-            .encode_png()
-            .map_err(|e| MermaidError::EncodingError(format!("Failed to encode PNG: {}", e)))?;
-
-        Ok(png_data)
-    }
 }
 
 impl Default for MermaidRenderer {
@@ -343,6 +378,46 @@ impl Default for MermaidRenderer {
     }
 }
 
+/// Replace the mermaid blocks in markdown with embedded SVG images
+///
+/// Each block becomes `![Mermaid Diagram](data:image/svg+xml;base64,...)`.
+/// Blocks that fail to render stay as code blocks (graceful degradation).
+///
+/// # Returns
+/// The new markdown, or None if no diagram was rendered
+pub async fn embed_diagrams(
+    renderer: &Mutex<MermaidRenderer>,
+    markdown: &str,
+    theme: DiagramTheme,
+) -> Option<String> {
+    let blocks = extract_mermaid_blocks(markdown);
+    if blocks.is_empty() {
+        return None;
+    }
+
+    let mut renderer = renderer.lock().await;
+    let mut result = markdown.to_string();
+    let mut rendered = 0;
+
+    // Process blocks in reverse order to maintain correct indices
+    for (start, end, code) in blocks.iter().rev() {
+        match renderer.render_to_svg(code, theme).await {
+            Ok(svg) => {
+                let data_url = format!("data:image/svg+xml;base64,{}", BASE64.encode(&svg));
+                result.replace_range(*start..*end, &format!("![Mermaid Diagram]({})", data_url));
+                rendered += 1;
+                tracing::debug!("✓ Rendered mermaid diagram ({} bytes SVG)", svg.len());
+            }
+            Err(e @ (MermaidError::Blocked(_) | MermaidError::Offline(_))) => {
+                tracing::debug!("Diagram not rendered: {}", e)
+            }
+            Err(e) => tracing::warn!("Failed to render mermaid diagram: {}", e),
+        }
+    }
+
+    (rendered > 0).then_some(result)
+}
+
 /// Extract mermaid code blocks from markdown text
 ///
 /// Searches for code blocks with language identifier "mermaid" and extracts their content.
@@ -464,58 +539,29 @@ More text"#;
         assert_eq!(blocks[0].2.trim(), "");
     }
 
-    #[test]
-    fn test_svg_to_png_conversion() {
-        // Simple SVG test
-        let svg_data = r#"<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
-            <rect x="10" y="10" width="80" height="80" fill="red"/>
-        </svg>"#;
-
-        let result = MermaidRenderer::svg_to_png(svg_data.as_bytes());
-
-        assert!(result.is_ok(), "SVG to PNG conversion should succeed");
-
-        let png_bytes = result.unwrap();
-        assert!(!png_bytes.is_empty(), "PNG data should not be empty");
-
-        // Verify PNG signature (89 50 4E 47 0D 0A 1A 0A)
-        assert_eq!(png_bytes[0], 0x89, "PNG signature byte 1");
-        assert_eq!(png_bytes[1], 0x50, "PNG signature byte 2 (P)");
-        assert_eq!(png_bytes[2], 0x4E, "PNG signature byte 3 (N)");
-        assert_eq!(png_bytes[3], 0x47, "PNG signature byte 4 (G)");
-    }
-
-    #[test]
-    fn test_svg_to_png_transparent_background() {
-        // SVG with transparent background
-        let svg_data = r#"<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
-            <circle cx="50" cy="50" r="40" fill="blue" opacity="0.5"/>
-        </svg>"#;
-
-        let result = MermaidRenderer::svg_to_png(svg_data.as_bytes());
-        assert!(result.is_ok(), "SVG with transparency should convert");
-
-        let png_bytes = result.unwrap();
-        // PNG should support transparency
-        assert!(!png_bytes.is_empty());
-    }
-
-    #[test]
-    fn test_svg_to_png_invalid_svg() {
-        let invalid_svg = b"This is not SVG data";
-
-        let result = MermaidRenderer::svg_to_png(invalid_svg);
-        assert!(result.is_err(), "Invalid SVG should return error");
+    #[tokio::test]
+    async fn test_embed_diagrams_without_blocks() {
+        let renderer = Mutex::new(MermaidRenderer::new());
+        let markdown = "No diagrams here\n```rust\nfn main() {}\n```\n";
+        assert_eq!(
+            embed_diagrams(&renderer, markdown, DiagramTheme::Light).await,
+            None
+        );
     }
 
     #[test]
-    fn test_svg_to_png_empty_input() {
-        let result = MermaidRenderer::svg_to_png(b"");
-        assert!(result.is_err(), "Empty input should return error");
+    fn test_themed_source() {
+        let themed = themed_source("graph TD\n  A-->B", DiagramTheme::Dark);
+        assert!(themed.starts_with("%%{init: {\"theme\": \"dark\", \"htmlLabels\": false"));
+        assert!(themed.ends_with("}}%%\ngraph TD\n  A-->B"));
+
+        // The diagram's own directive wins
+        let custom = "%%{init: {\"theme\": \"forest\"}}%%\ngraph TD\n  A-->B";
+        assert_eq!(themed_source(custom, DiagramTheme::Light), custom);
     }
 
     #[test]
-    fn test_svg_to_png_fixes_invalid_rect_attributes() {
+    fn test_fix_svg_replaces_invalid_rect_attributes() {
         // SVG with invalid rect attributes (like mermaid.ink generates)
         let svg_data = r#"<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
             <rect x="10" y="10" width="0" height="0" fill="red"/>
@@ -523,17 +569,12 @@ More text"#;
             <text x="50" y="50">Label</text>
         </svg>"#;
 
-        let result = MermaidRenderer::svg_to_png(svg_data.as_bytes());
-
-        // Should succeed despite invalid attributes
-        assert!(result.is_ok(), "Should handle invalid rect attributes");
+        let fixed = fix_svg(svg_data);
 
-        let png_bytes = result.unwrap();
-        assert!(!png_bytes.is_empty(), "PNG should not be empty");
-        assert_eq!(
-            &png_bytes[0..4],
-            &[0x89, 0x50, 0x4E, 0x47],
-            "Should have PNG signature"
-        );
+        assert!(fixed.contains(r#"width="0.1" height="0.1""#));
+        assert!(fixed.contains(r#"width="1" height="1""#));
+        assert!(!fixed.contains(r#"width="0""#));
+        assert!(!fixed.contains(r#"width="""#));
+        assert!(fixed.contains(r#"<text x="50" y="50">Label</text>"#));
     }
 }
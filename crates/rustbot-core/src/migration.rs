@@ -0,0 +1,308 @@
+// Schema versioning and migrations for on-disk config files
+//
+// Design Decision: Versioned JSON documents upgraded step by step on load
+//
+// Rationale: Agent presets, mcp_config.json and the user profile are
+// hand-edited and long-lived. Without a version stamp, a future format change
+// either fails to parse old files or — worse — silently drops fields through
+// `#[serde(default)]`. Each file now carries an integer `schema_version`, and
+// a migrator rewrites the raw JSON one version at a time (0 → 1 → 2 …) before
+// it is deserialized into the typed struct.
+//
+// Rules:
+// - Files without `schema_version` are version 0 (written before versioning)
+// - Each migration upgrades exactly one version; the runner chains them
+// - Files newer than the running app are rejected instead of being rewritten
+//   and losing data the old app doesn't understand
+// - Upgraded files are written back with a `.v<N>.bak` copy of the original
+//
+// Adding a format change:
+// 1. Bump `current` on the file's migrator below
+// 2. Append a `Migration { from: <old current>, .. }` that edits the JSON
+// 3. Update the typed struct to the new shape
+//
+// Trade-offs: Migrations operate on untyped `serde_json::Value`, so they are
+// checked by tests rather than the compiler — in exchange old struct
+// definitions don't need to be kept around.
+
+use crate::error::{Result, RustbotError};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// JSON field holding a file's schema version
+pub const SCHEMA_VERSION_FIELD: &str = "schema_version";
+
+/// One upgrade step from version `from` to `from + 1`
+pub struct Migration {
+    /// Version this migration upgrades from
+    pub from: u32,
+
+    /// What changed, for logs
+    pub description: &'static str,
+
+    /// Edit the raw JSON in place (the runner updates `schema_version`)
+    pub apply: fn(&mut Value) -> Result<()>,
+}
+
+/// Migration runner for one kind of config file
+pub struct SchemaMigrator {
+    /// File kind, for logs and errors (e.g. "agent config")
+    pub name: &'static str,
+
+    /// Version written by this build of Rustbot
+    pub current: u32,
+
+    migrations: &'static [Migration],
+}
+
+/// Agent preset/custom JSON files (`agents/**/*.json`)
+pub static AGENT_CONFIG_SCHEMA: SchemaMigrator = SchemaMigrator::new(
+    "agent config",
+    1,
+    &[Migration {
+        from: 0,
+        description: "start tracking schema_version",
+        apply: no_changes,
+    }],
+);
+
+/// MCP plugin configuration (`mcp_config.json` and per-agent configs)
+pub static MCP_CONFIG_SCHEMA: SchemaMigrator = SchemaMigrator::new(
+    "MCP config",
+    1,
+    &[Migration {
+        from: 0,
+        description: "start tracking schema_version",
+        apply: no_changes,
+    }],
+);
+
+/// User profile (`profile.json`)
+pub static USER_PROFILE_SCHEMA: SchemaMigrator = SchemaMigrator::new(
+    "user profile",
+    1,
+    &[Migration {
+        from: 0,
+        description: "start tracking schema_version",
+        apply: no_changes,
+    }],
+);
+
+/// Migration for formats whose only change is gaining a version stamp
+fn no_changes(_: &mut Value) -> Result<()> {
+    Ok(())
+}
+
+impl SchemaMigrator {
+    /// Create a migrator (used for the statics above)
+    pub const fn new(name: &'static str, current: u32, migrations: &'static [Migration]) -> Self {
+        Self {
+            name,
+            current,
+            migrations,
+        }
+    }
+
+    /// Schema version of a JSON document (0 when absent)
+    pub fn version_of(value: &Value) -> u32 {
+        value
+            .get(SCHEMA_VERSION_FIELD)
+            .and_then(Value::as_u64)
+            .map(|v| v as u32)
+            .unwrap_or(0)
+    }
+
+    /// Upgrade a JSON document to the current version in place
+    ///
+    /// # Returns
+    /// Whether the document changed (and should be written back)
+    ///
+    /// # Errors
+    /// - Document is not a JSON object
+    /// - Document is newer than this build supports
+    /// - A migration step is missing or fails
+    pub fn migrate(&self, value: &mut Value) -> Result<bool> {
+        let mut version = Self::version_of(value);
+        if version > self.current {
+            return Err(RustbotError::ConfigError(format!(
+                "{} schema version {} is newer than supported version {} (upgrade Rustbot)",
+                self.name, version, self.current
+            )));
+        }
+        if version == self.current {
+            return Ok(false);
+        }
+        if !value.is_object() {
+            return Err(RustbotError::ConfigError(format!(
+                "{} must be a JSON object",
+                self.name
+            )));
+        }
+
+        while version < self.current {
+            let migration = self
+                .migrations
+                .iter()
+                .find(|m| m.from == version)
+                .ok_or_else(|| {
+                    RustbotError::ConfigError(format!(
+                        "No migration for {} from schema version {}",
+                        self.name, version
+                    ))
+                })?;
+
+            (migration.apply)(value)?;
+            version += 1;
+            value[SCHEMA_VERSION_FIELD] = Value::from(version);
+            tracing::debug!(
+                "Migrated {} to schema version {}: {}",
+                self.name,
+                version,
+                migration.description
+            );
+        }
+
+        Ok(true)
+    }
+
+    /// Parse and upgrade a JSON string
+    ///
+    /// # Errors
+    /// - Invalid JSON
+    /// - Migration failure (see `migrate`)
+    pub fn migrate_str(&self, content: &str) -> Result<(Value, bool)> {
+        let mut value: Value = serde_json::from_str(content)?;
+        let changed = self.migrate(&mut value)?;
+        Ok((value, changed))
+    }
+
+    /// Read a file and upgrade it, writing the new version back to disk
+    ///
+    /// The original is kept next to it as `<file>.v<N>.bak`. If the file
+    /// can't be written (e.g. read-only install), the upgraded document is
+    /// still returned and the warning logged; the upgrade reruns next load.
+    ///
+    /// # Errors
+    /// - File cannot be read
+    /// - Invalid JSON or migration failure
+    pub fn load_file(&self, path: &Path) -> Result<Value> {
+        let content = std::fs::read_to_string(path)?;
+        let mut value: Value = serde_json::from_str(&content)?;
+        let old_version = Self::version_of(&value);
+
+        if self.migrate(&mut value)? {
+            if let Err(e) = Self::write_back(path, &content, old_version, &value) {
+                tracing::warn!("Could not save upgraded {} {:?}: {}", self.name, path, e);
+            } else {
+                tracing::info!(
+                    "⬆️  Upgraded {} {:?} from schema version {} to {}",
+                    self.name,
+                    path,
+                    old_version,
+                    self.current
+                );
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn write_back(path: &Path, original: &str, old_version: u32, value: &Value) -> Result<()> {
+        std::fs::write(backup_path(path, old_version), original)?;
+        std::fs::write(path, serde_json::to_string_pretty(value)?)?;
+        Ok(())
+    }
+}
+
+/// Where the pre-migration copy of a file is kept (`config.json` → `config.json.v0.bak`)
+pub fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn rename_field(value: &mut Value) -> Result<()> {
+        let object = value.as_object_mut().unwrap();
+        if let Some(old) = object.remove("old_name") {
+            object.insert("new_name".to_string(), old);
+        }
+        Ok(())
+    }
+
+    static TEST_SCHEMA: SchemaMigrator = SchemaMigrator::new(
+        "test file",
+        2,
+        &[
+            Migration {
+                from: 0,
+                description: "start tracking schema_version",
+                apply: no_changes,
+            },
+            Migration {
+                from: 1,
+                description: "rename old_name to new_name",
+                apply: rename_field,
+            },
+        ],
+    );
+
+    #[test]
+    fn test_migrate_chains_steps_from_unversioned() {
+        let mut value = json!({"old_name": "x"});
+        assert!(TEST_SCHEMA.migrate(&mut value).unwrap());
+        assert_eq!(value, json!({"new_name": "x", "schema_version": 2}));
+
+        // Already current: untouched
+        assert!(!TEST_SCHEMA.migrate(&mut value).unwrap());
+    }
+
+    #[test]
+    fn test_migrate_starts_at_file_version() {
+        let mut value = json!({"schema_version": 1, "old_name": "x"});
+        TEST_SCHEMA.migrate(&mut value).unwrap();
+        assert_eq!(value["new_name"], "x");
+    }
+
+    #[test]
+    fn test_newer_file_is_rejected() {
+        let mut value = json!({"schema_version": 3});
+        assert!(TEST_SCHEMA.migrate(&mut value).is_err());
+        assert_eq!(value, json!({"schema_version": 3}));
+    }
+
+    #[test]
+    fn test_load_file_writes_back_with_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.json");
+        std::fs::write(&path, r#"{"old_name": "x"}"#).unwrap();
+
+        let value = TEST_SCHEMA.load_file(&path).unwrap();
+        assert_eq!(value["schema_version"], 2);
+
+        let on_disk: Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(on_disk, value);
+
+        let backup = std::fs::read_to_string(temp_dir.path().join("config.json.v0.bak")).unwrap();
+        assert_eq!(backup, r#"{"old_name": "x"}"#);
+    }
+
+    #[test]
+    fn test_builtin_schemas_upgrade_unversioned_files() {
+        for schema in [
+            &AGENT_CONFIG_SCHEMA,
+            &MCP_CONFIG_SCHEMA,
+            &USER_PROFILE_SCHEMA,
+        ] {
+            let mut value = json!({});
+            schema.migrate(&mut value).unwrap();
+            assert_eq!(SchemaMigrator::version_of(&value), schema.current);
+        }
+    }
+}
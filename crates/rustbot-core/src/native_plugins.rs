@@ -0,0 +1,607 @@
+// Native plugins - dynamic libraries built with the rustbot-plugin SDK
+//
+// Design Decision: Libraries in ~/.rustbot/native_plugins are listed but only
+// loaded once the user enables them; each one's tools, event subscriptions
+// and settings panel come from the manifest it returns over the C ABI
+// (see crates/rustbot-plugin)
+//
+// Rationale: MCP servers are separate processes speaking JSON-RPC, which is
+// heavy for a small tool or an event listener that wants to live in-process.
+// Native plugins are as cheap as a function call, but they are arbitrary code
+// with the user's permissions, so nothing is loaded until it is enabled in
+// Settings > Native Plugins.
+//
+// Configuration: ~/.rustbot/native_plugins/plugins.json
+//     { "enabled": ["counter"], "settings": { "counter": { "prefix": "> " } } }
+//
+// Trade-offs:
+// - Libraries stay loaded until the app quits; disabling a plugin stops its
+//   tools and events, and replacing the file takes a restart
+// - Plugins are named after the file (`libcounter.so` is "counter"), not the
+//   manifest, so the name is known before anything is loaded
+// - Tools are namespaced "native:{plugin}:{tool}" like script and MCP tools
+// - Events are delivered one at a time on a blocking thread, like script hooks
+
+use crate::agent::tools::{FunctionDefinition, FunctionParameters};
+use crate::agent::ToolDefinition;
+use crate::error::{Result, RustbotError};
+use crate::events::{AgentStatus, Event, EventBus, EventKind, McpPluginEvent, PluginHealthStatus};
+use rustbot_plugin::{
+    EntryFn, Manifest, PluginEvent, PluginVTable, SettingSpec, ToolResult, ABI_VERSION,
+    ENTRY_SYMBOL,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+
+pub use rustbot_plugin::{SettingKind, ToolSpec};
+
+/// Prefix for native plugin tool names
+const NATIVE_TOOL_PREFIX: &str = "native:";
+
+/// Persisted plugin settings
+///
+/// File Format: JSON
+/// Location: ~/.rustbot/native_plugins/plugins.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NativePluginsConfig {
+    /// Plugin names (file stems without "lib") that may be loaded
+    #[serde(default)]
+    pub enabled: Vec<String>,
+
+    /// Settings values by plugin
+    #[serde(default)]
+    pub settings: BTreeMap<String, serde_json::Map<String, serde_json::Value>>,
+}
+
+impl NativePluginsConfig {
+    /// Load settings from a file
+    ///
+    /// # Returns
+    /// Ok(None) if the file doesn't exist (no plugins enabled)
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// Write settings to a file, creating the parent directory if needed
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Status of one plugin library, for the plugin manager view
+#[derive(Debug, Clone)]
+pub struct NativePluginInfo {
+    /// Plugin name (file stem without "lib")
+    pub name: String,
+
+    /// Path to the library
+    pub path: PathBuf,
+
+    /// Whether the user enabled it
+    pub enabled: bool,
+
+    /// Manifest, once loaded
+    pub manifest: Option<Manifest>,
+
+    /// Current settings values (defaults filled in)
+    pub values: serde_json::Map<String, serde_json::Value>,
+
+    /// Load error
+    pub error: Option<String>,
+}
+
+/// A loaded library's function table
+///
+/// The library is leaked when loaded, so the table stays valid for the rest
+/// of the process.
+#[derive(Clone, Copy)]
+struct Library {
+    vtable: &'static PluginVTable,
+}
+
+impl Library {
+    /// Open a library and check its ABI version
+    fn open(path: &Path) -> std::result::Result<Self, String> {
+        // SAFETY: loading runs the library's initializers; the user enabled
+        // this plugin knowing it is native code
+        let library = unsafe { libloading::Library::new(path) }.map_err(|e| e.to_string())?;
+        // SAFETY: the entry symbol has the SDK's signature
+        let vtable = unsafe {
+            let entry = library
+                .get::<EntryFn>(ENTRY_SYMBOL)
+                .map_err(|e| format!("Not a Rustbot plugin: {}", e))?;
+            entry()
+        };
+        if vtable.is_null() {
+            return Err("Plugin returned no function table".to_string());
+        }
+        // SAFETY: the version is the first field of every table version
+        let version = unsafe { (*vtable).abi_version };
+        if version != ABI_VERSION {
+            return Err(format!(
+                "Built for plugin ABI {}, this Rustbot supports {}",
+                version, ABI_VERSION
+            ));
+        }
+        // Never unloaded: the table and any threads the plugin started live
+        // in the library's code
+        std::mem::forget(library);
+        // SAFETY: non-null, checked version, and the library is never unloaded
+        Ok(Self {
+            vtable: unsafe { &*vtable },
+        })
+    }
+
+    /// Take ownership of a string the plugin returned
+    fn take(&self, text: *mut c_char) -> String {
+        if text.is_null() {
+            return String::new();
+        }
+        // SAFETY: the plugin returns NUL-terminated strings from CString
+        let owned = unsafe { CStr::from_ptr(text) }
+            .to_string_lossy()
+            .into_owned();
+        (self.vtable.free_string)(text);
+        owned
+    }
+
+    fn manifest(&self) -> std::result::Result<Manifest, String> {
+        let json = self.take((self.vtable.manifest)());
+        serde_json::from_str(&json).map_err(|e| format!("Invalid manifest: {}", e))
+    }
+
+    fn configure(&self, values: &serde_json::Map<String, serde_json::Value>) {
+        let json = c_string(&serde_json::Value::Object(values.clone()).to_string());
+        (self.vtable.configure)(json.as_ptr());
+    }
+
+    fn call_tool(&self, name: &str, arguments: &str) -> std::result::Result<String, String> {
+        let (name, arguments) = (c_string(name), c_string(arguments));
+        let json = self.take((self.vtable.call_tool)(name.as_ptr(), arguments.as_ptr()));
+        match serde_json::from_str(&json) {
+            Ok(ToolResult::Ok(text)) => Ok(text),
+            Ok(ToolResult::Error(message)) => Err(message),
+            Err(e) => Err(format!("Invalid tool result: {}", e)),
+        }
+    }
+
+    fn on_event(&self, event: &PluginEvent) {
+        if let Ok(json) = serde_json::to_string(event) {
+            let json = c_string(&json);
+            (self.vtable.on_event)(json.as_ptr());
+        }
+    }
+}
+
+fn c_string(text: &str) -> CString {
+    CString::new(text.replace('\0', "")).unwrap_or_default()
+}
+
+/// A plugin file, with its library if it is enabled and loaded
+struct LoadedPlugin {
+    info: NativePluginInfo,
+    library: Option<Library>,
+}
+
+/// Loads enabled native plugins and routes tool calls and events to them
+///
+/// Shared as `Arc<NativePluginHost>` between the event bus task, the API (for
+/// tool calls) and the plugin manager view.
+pub struct NativePluginHost {
+    dir: PathBuf,
+    plugins: Mutex<Vec<LoadedPlugin>>,
+
+    /// Libraries opened so far by path (kept across reloads)
+    opened: Mutex<HashMap<PathBuf, Library>>,
+}
+
+impl NativePluginHost {
+    /// Default plugins directory: ~/.rustbot/native_plugins
+    pub fn default_dir() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("native_plugins")
+    }
+
+    /// Create a host for the plugins in `dir`
+    ///
+    /// Nothing is loaded until `reload` is called.
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            plugins: Mutex::new(Vec::new()),
+            opened: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Plugins directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn config_path(&self) -> PathBuf {
+        self.dir.join("plugins.json")
+    }
+
+    /// List the libraries in the directory and load the enabled ones
+    ///
+    /// Creates the directory if it doesn't exist. A plugin that fails to load
+    /// is kept in the list with its error.
+    ///
+    /// # Errors
+    /// - The directory cannot be created or read
+    /// - plugins.json exists but is invalid
+    pub fn reload(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let config = NativePluginsConfig::load(&self.config_path())?.unwrap_or_default();
+
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path.extension().and_then(|e| e.to_str())
+                        == Some(std::env::consts::DLL_EXTENSION)
+            })
+            .collect();
+        paths.sort();
+
+        let plugins: Vec<LoadedPlugin> = paths
+            .into_iter()
+            .map(|path| self.load_plugin(path, &config))
+            .collect();
+        tracing::info!(
+            "🔩 Loaded {} of {} native plugins from {}",
+            plugins.iter().filter(|p| p.library.is_some()).count(),
+            plugins.len(),
+            self.dir.display()
+        );
+
+        *self.plugins.lock().unwrap() = plugins;
+        Ok(())
+    }
+
+    fn load_plugin(&self, path: PathBuf, config: &NativePluginsConfig) -> LoadedPlugin {
+        let stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        let name = stem.strip_prefix("lib").unwrap_or(stem).to_string();
+        let enabled = config.enabled.contains(&name);
+        let mut plugin = LoadedPlugin {
+            info: NativePluginInfo {
+                name,
+                path,
+                enabled,
+                manifest: None,
+                values: serde_json::Map::new(),
+                error: None,
+            },
+            library: None,
+        };
+        if !enabled {
+            return plugin;
+        }
+
+        let opened = self.opened.lock().unwrap().get(&plugin.info.path).copied();
+        let library = match opened {
+            Some(library) => Ok(library),
+            None => Library::open(&plugin.info.path).inspect(|library| {
+                self.opened
+                    .lock()
+                    .unwrap()
+                    .insert(plugin.info.path.clone(), *library);
+            }),
+        };
+        let loaded = library.and_then(|library| Ok((library, library.manifest()?)));
+        match loaded {
+            Ok((library, manifest)) => {
+                plugin.info.values =
+                    with_defaults(&manifest.settings, config.settings.get(&plugin.info.name));
+                library.configure(&plugin.info.values);
+                plugin.info.manifest = Some(manifest);
+                plugin.library = Some(library);
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "🔩 Native plugin '{}' failed to load: {}",
+                    plugin.info.name,
+                    e
+                );
+                plugin.info.error = Some(e);
+            }
+        }
+        plugin
+    }
+
+    /// Status of every plugin, in file name order
+    pub fn list(&self) -> Vec<NativePluginInfo> {
+        self.plugins
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|p| p.info.clone())
+            .collect()
+    }
+
+    /// Enable or disable a plugin, persist the choice and reload
+    ///
+    /// # Errors
+    /// - plugins.json cannot be read or written
+    /// - The reload fails
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> Result<()> {
+        let path = self.config_path();
+        let mut config = NativePluginsConfig::load(&path)?.unwrap_or_default();
+        config.enabled.retain(|n| n != name);
+        if enabled {
+            config.enabled.push(name.to_string());
+        }
+        config.save(&path)?;
+        self.reload()
+    }
+
+    /// Save a plugin's settings and pass them to it
+    ///
+    /// # Errors
+    /// - The plugin is not loaded
+    /// - plugins.json cannot be read or written
+    pub fn save_settings(
+        &self,
+        name: &str,
+        values: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<()> {
+        let mut plugins = self.plugins.lock().unwrap();
+        let plugin = plugins
+            .iter_mut()
+            .find(|p| p.info.name == name && p.library.is_some())
+            .ok_or_else(|| {
+                RustbotError::ConfigError(format!("Native plugin '{}' is not loaded", name))
+            })?;
+
+        let path = self.config_path();
+        let mut config = NativePluginsConfig::load(&path)?.unwrap_or_default();
+        config.settings.insert(name.to_string(), values.clone());
+        config.save(&path)?;
+
+        if let Some(library) = plugin.library {
+            library.configure(&values);
+        }
+        plugin.info.values = values;
+        Ok(())
+    }
+
+    /// Tool definitions for every tool of the loaded plugins
+    pub fn tool_definitions(&self) -> Vec<ToolDefinition> {
+        let plugins = self.plugins.lock().unwrap();
+        plugins
+            .iter()
+            .filter(|p| p.library.is_some())
+            .filter_map(|p| Some((p.info.name.as_str(), p.info.manifest.as_ref()?)))
+            .flat_map(|(plugin, manifest)| {
+                manifest.tools.iter().map(move |tool| ToolDefinition {
+                    tool_type: "function".to_string(),
+                    function: FunctionDefinition {
+                        name: tool_name(plugin, &tool.name),
+                        description: tool.description.clone(),
+                        parameters: FunctionParameters {
+                            param_type: "object".to_string(),
+                            properties: tool.properties.clone(),
+                            required: tool.required.clone(),
+                        },
+                    },
+                })
+            })
+            .collect()
+    }
+
+    /// Check if a tool name belongs to a native plugin
+    pub fn is_native_tool(tool_name: &str) -> bool {
+        tool_name.starts_with(NATIVE_TOOL_PREFIX)
+    }
+
+    /// Run a plugin tool
+    ///
+    /// # Arguments
+    /// * `tool_name` - Namespaced name ("native:{plugin}:{tool}")
+    /// * `arguments` - JSON-encoded arguments
+    ///
+    /// # Errors
+    /// - Unknown tool or plugin not loaded
+    /// - The plugin reports an error (or panicked)
+    pub fn call_tool(&self, tool_name: &str, arguments: &str) -> Result<String> {
+        let (plugin_name, name) = tool_name
+            .strip_prefix(NATIVE_TOOL_PREFIX)
+            .and_then(|rest| rest.split_once(':'))
+            .ok_or_else(|| {
+                RustbotError::ConfigError(format!("Invalid native tool name '{}'", tool_name))
+            })?;
+        let library = self
+            .plugins
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|p| p.info.name == plugin_name)
+            .and_then(|p| p.library)
+            .ok_or_else(|| {
+                RustbotError::ConfigError(format!("Native plugin '{}' is not loaded", plugin_name))
+            })?;
+
+        library
+            .call_tool(name, arguments)
+            .map_err(RustbotError::ApiError)
+    }
+
+    /// Deliver an event to the loaded plugins subscribed to its kind
+    pub fn dispatch(&self, event: &Event) {
+        let Some(event) = plugin_event(event) else {
+            return;
+        };
+        let subscribers: Vec<Library> = self
+            .plugins
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|p| {
+                p.info
+                    .manifest
+                    .as_ref()
+                    .is_some_and(|m| m.events.iter().any(|e| e == event.kind()))
+            })
+            .filter_map(|p| p.library)
+            .collect();
+        for library in subscribers {
+            library.on_event(&event);
+        }
+    }
+
+    /// Subscribe to the event bus and deliver events in the background
+    ///
+    /// Events are delivered one at a time on a blocking thread, in order.
+    pub fn spawn(
+        self: Arc<Self>,
+        event_bus: &EventBus,
+        handle: &tokio::runtime::Handle,
+    ) -> JoinHandle<()> {
+        let mut rx = event_bus.subscribe_named("native_plugins");
+
+        handle.spawn(async move {
+            while let Some(event) = rx.recv().await {
+                let host = Arc::clone(&self);
+                if let Err(e) = tokio::task::spawn_blocking(move || host.dispatch(&event)).await {
+                    tracing::error!("🔩 Native plugin event handler panicked: {}", e);
+                }
+            }
+        })
+    }
+}
+
+fn tool_name(plugin: &str, tool: &str) -> String {
+    format!("{}{}:{}", NATIVE_TOOL_PREFIX, plugin, tool)
+}
+
+/// Saved values for a plugin's settings, with defaults for the missing ones
+fn with_defaults(
+    specs: &[SettingSpec],
+    saved: Option<&serde_json::Map<String, serde_json::Value>>,
+) -> serde_json::Map<String, serde_json::Value> {
+    specs
+        .iter()
+        .map(|spec| {
+            let value = saved
+                .and_then(|saved| saved.get(&spec.key))
+                .cloned()
+                .unwrap_or_else(|| spec.default.clone());
+            (spec.key.clone(), value)
+        })
+        .collect()
+}
+
+/// Map a bus event to the event plugins receive
+fn plugin_event(event: &Event) -> Option<PluginEvent> {
+    Some(match &event.kind {
+        EventKind::UserMessage(text) => PluginEvent::UserMessage { text: text.clone() },
+        EventKind::AgentMessage { agent_id, content } => PluginEvent::Response {
+            agent_id: agent_id.clone(),
+            text: content.clone(),
+        },
+        EventKind::AgentStatusChange {
+            agent_id,
+            status: AgentStatus::Error(message),
+        } => PluginEvent::AgentError {
+            agent_id: agent_id.clone(),
+            message: message.clone(),
+        },
+        EventKind::AgentStatusChange {
+            agent_id,
+            status: AgentStatus::ExecutingTool(tool),
+        } => PluginEvent::Tool {
+            agent_id: agent_id.clone(),
+            tool: tool.clone(),
+        },
+        EventKind::McpPluginEvent(McpPluginEvent::Error { plugin_id, message }) => {
+            PluginEvent::PluginCrash {
+                plugin_id: plugin_id.clone(),
+                message: message.clone(),
+            }
+        }
+        EventKind::McpPluginEvent(McpPluginEvent::HealthStatus {
+            plugin_id,
+            status: PluginHealthStatus::Dead,
+            ..
+        }) => PluginEvent::PluginCrash {
+            plugin_id: plugin_id.clone(),
+            message: "dead".to_string(),
+        },
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_plugins_are_listed_but_not_loaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = format!("libcounter.{}", std::env::consts::DLL_EXTENSION);
+        // Not a real library: loading it would fail
+        std::fs::write(dir.path().join(&file), b"not a library").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), b"ignored").unwrap();
+
+        let host = NativePluginHost::new(dir.path().to_path_buf());
+        host.reload().unwrap();
+        let plugins = host.list();
+        assert_eq!(plugins.len(), 1);
+        assert_eq!(plugins[0].name, "counter");
+        assert!(!plugins[0].enabled);
+        assert!(plugins[0].error.is_none());
+        assert!(host.tool_definitions().is_empty());
+
+        host.set_enabled("counter", true).unwrap();
+        let plugins = host.list();
+        assert!(plugins[0].enabled);
+        assert!(plugins[0].error.is_some());
+        assert!(host.call_tool("native:counter:count", "{}").is_err());
+        assert!(NativePluginHost::is_native_tool("native:counter:count"));
+    }
+
+    #[test]
+    fn test_settings_defaults_and_events() {
+        let specs = vec![
+            SettingSpec::new("prefix", "Prefix", SettingKind::Text, "> ".into()),
+            SettingSpec::new("loud", "Loud", SettingKind::Toggle, false.into()),
+        ];
+        let saved: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(r#"{"loud": true, "stale": 1}"#).unwrap();
+        let values = with_defaults(&specs, Some(&saved));
+        assert_eq!(
+            serde_json::Value::Object(values),
+            serde_json::json!({"prefix": "> ", "loud": true})
+        );
+
+        let event = Event::new(
+            "user".to_string(),
+            "broadcast".to_string(),
+            EventKind::UserMessage("hi".to_string()),
+        );
+        assert_eq!(
+            plugin_event(&event),
+            Some(PluginEvent::UserMessage {
+                text: "hi".to_string()
+            })
+        );
+    }
+}
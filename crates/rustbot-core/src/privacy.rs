@@ -0,0 +1,157 @@
+// Local-only (privacy) mode
+//
+// Design Decision: One process-wide egress gate, checked before every
+// outgoing connection
+//
+// Rationale: Users working with sensitive material want a guarantee that
+// nothing leaves the machine: no chat requests to hosted providers, no
+// diagram or formula source sent to mermaid.ink or the math renderer, no
+// marketplace, webhook or bot traffic. Each of those modules builds its own
+// request, so each asks `check_url` (or `check_host` for IMAP/SMTP) first and
+// fails with `EgressBlocked` instead of connecting. The mode is a user
+// profile setting; the app state actor and the headless commands apply it
+// with `configure` as soon as the profile is loaded.
+//
+// Allowed endpoints:
+// - Loopback (`localhost`, `127.0.0.0/8`, `::1`) never leaves the machine and
+//   is always allowed, so a local Ollama or MCP server keeps working
+// - Hosts the user lists explicitly (`host` or `host:port`), e.g. a model
+//   server elsewhere on the LAN
+//
+// Trade-offs:
+// - New network code has to call `check_url`; the gate isn't enforced inside
+//   reqwest itself
+// - Subprocesses (stdio MCP plugins, the `op`/`vault`/`aws` CLIs) do their
+//   own networking and aren't covered; stdio plugins that need the network
+//   are the user's choice to enable
+
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use thiserror::Error;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static ALLOWED_HOSTS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// A request refused because local-only mode is on
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Local-only mode is on: {host} is not an allowed endpoint")]
+pub struct EgressBlocked {
+    /// Host the request was for (or the URL, if it had no host)
+    pub host: String,
+}
+
+/// Turn local-only mode on or off and set the allowed endpoints
+///
+/// # Arguments
+/// * `enabled` - Block egress to anything but loopback and `allowed_hosts`
+/// * `allowed_hosts` - Extra endpoints as `host` or `host:port`
+pub fn configure(enabled: bool, allowed_hosts: &[String]) {
+    if let Ok(mut hosts) = ALLOWED_HOSTS.write() {
+        *hosts = allowed_hosts
+            .iter()
+            .map(|host| host.trim().to_ascii_lowercase())
+            .filter(|host| !host.is_empty())
+            .collect();
+    }
+    if ENABLED.swap(enabled, Ordering::SeqCst) != enabled {
+        tracing::info!(
+            "🔒 Local-only mode {}",
+            if enabled { "enabled" } else { "disabled" }
+        );
+    }
+}
+
+/// Whether local-only mode is on
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// Check that a request to `url` may leave the machine
+///
+/// # Errors
+/// - Local-only mode is on and the URL's host isn't loopback or allowed
+///   (URLs that don't parse are refused too)
+pub fn check_url(url: &str) -> Result<(), EgressBlocked> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    let blocked = || EgressBlocked {
+        host: url.to_string(),
+    };
+    let parsed = reqwest::Url::parse(url).map_err(|_| blocked())?;
+    let host = parsed.host_str().ok_or_else(blocked)?;
+    check_endpoint(host, parsed.port_or_known_default())
+}
+
+/// Check that a connection to `host` (IMAP, SMTP, ...) may leave the machine
+///
+/// # Errors
+/// - Local-only mode is on and the host isn't loopback or allowed
+pub fn check_host(host: &str, port: u16) -> Result<(), EgressBlocked> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    check_endpoint(host, Some(port))
+}
+
+fn check_endpoint(host: &str, port: Option<u16>) -> Result<(), EgressBlocked> {
+    let host = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase();
+    if is_loopback(&host) {
+        return Ok(());
+    }
+    let allowed = ALLOWED_HOSTS.read().map(|hosts| {
+        hosts.iter().any(|entry| match entry.rsplit_once(':') {
+            Some((name, entry_port)) if !name.contains(':') => {
+                name == host && port.map(|p| p.to_string()).as_deref() == Some(entry_port)
+            }
+            _ => *entry == host,
+        })
+    });
+    if allowed.unwrap_or(false) {
+        Ok(())
+    } else {
+        Err(EgressBlocked { host })
+    }
+}
+
+fn is_loopback(host: &str) -> bool {
+    host == "localhost"
+        || host.ends_with(".localhost")
+        || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One test: the mode is process-wide, so parallel tests would race
+    #[test]
+    fn test_local_only_mode() {
+        configure(false, &[]);
+        assert!(check_url("https://openrouter.ai/api/v1/chat/completions").is_ok());
+
+        configure(
+            true,
+            &["models.lan:8080".to_string(), " GPU-Box ".to_string()],
+        );
+        assert!(is_enabled());
+        let err = check_url("https://mermaid.ink/svg/abc").unwrap_err();
+        assert_eq!(err.host, "mermaid.ink");
+        assert!(check_url("http://localhost:11434/api/tags").is_ok());
+        assert!(check_url("http://127.0.0.1:3000/mcp").is_ok());
+        assert!(check_url("http://[::1]:8080/").is_ok());
+        assert!(check_url("http://models.lan:8080/v1").is_ok());
+        assert!(check_url("http://models.lan:9090/v1").is_err());
+        assert!(check_url("https://gpu-box/v1").is_ok());
+        assert!(check_url("not a url").is_err());
+        assert!(check_host("imap.example.com", 993).is_err());
+        assert!(check_host("localhost", 1143).is_ok());
+
+        configure(false, &[]);
+        assert!(check_host("imap.example.com", 993).is_ok());
+    }
+}
@@ -0,0 +1,231 @@
+// Projects - named workspaces bundling a folder, agents and conversations
+//
+// Design Decision: One directory per project under ~/.rustbot/projects, with
+// the settings in `project.json` and room next to it for the project's
+// knowledge index
+//
+// Rationale: Users who work on several things at once (a thesis, a side
+// project, the family budget) want each to keep its own files, preferred
+// agents and chat history. A project names that bundle; switching projects
+// switches the whole context: the folder is mentioned in the system context
+// (and is the repository the git tools work in), the first preferred agent
+// starts new chats, and the history only lists the project's conversations
+// (sessions record the project they were started in).
+// Keeping each project in its own directory gives features that work on the
+// folder (file tools, folder Q&A) an obvious place for their data, see
+// `ProjectStore::index_dir`.
+//
+// Configuration: ~/.rustbot/projects/<id>/project.json (edited in
+// Settings > Projects); the active project is in the user profile
+//
+// Trade-offs:
+// - Conversations belong to at most one project; moving one means editing the
+//   session file
+// - Deleting a project keeps its conversations; they show up again when no
+//   project is active
+
+use crate::agent::templates::agent_id;
+use crate::error::{Result, RustbotError};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// File holding a project's settings inside its directory
+const PROJECT_FILE: &str = "project.json";
+
+/// A named workspace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Project {
+    /// Identifier derived from the name (also the directory name)
+    pub id: String,
+
+    /// Display name
+    pub name: String,
+
+    /// Folder the project is about
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder: Option<PathBuf>,
+
+    /// Agent IDs offered in this project; the first one starts new chats
+    #[serde(default)]
+    pub preferred_agents: Vec<String>,
+
+    /// Let agents commit in the folder's git repository (each commit is
+    /// still approved by the user, see `crate::git_tools`)
+    #[serde(default)]
+    pub allow_git_commits: bool,
+
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Project {
+    /// A new project with an ID derived from `name`
+    pub fn new(name: &str) -> Self {
+        Self {
+            id: agent_id(name),
+            name: name.trim().to_string(),
+            folder: None,
+            preferred_agents: Vec::new(),
+            allow_git_commits: false,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    /// Agent that new chats in this project start with
+    pub fn default_agent(&self) -> Option<&str> {
+        self.preferred_agents.first().map(String::as_str)
+    }
+}
+
+/// Projects saved on disk
+///
+/// Usage:
+///     let store = ProjectStore::new(ProjectStore::default_dir());
+///     let project = store.create("Thesis")?;
+///     for project in store.list()? { ... }
+pub struct ProjectStore {
+    dir: PathBuf,
+}
+
+impl ProjectStore {
+    /// Default location: ~/.rustbot/projects
+    pub fn default_dir() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("projects")
+    }
+
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Directory a project's files live in
+    pub fn project_dir(&self, id: &str) -> PathBuf {
+        self.dir.join(id)
+    }
+
+    /// Where features working on the project folder keep their index
+    pub fn index_dir(&self, id: &str) -> PathBuf {
+        self.project_dir(id).join("index")
+    }
+
+    /// All projects, sorted by name
+    ///
+    /// Directories without a readable `project.json` are skipped with a
+    /// warning.
+    ///
+    /// # Errors
+    /// - The projects directory exists but cannot be read
+    pub fn list(&self) -> Result<Vec<Project>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut projects = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path().join(PROJECT_FILE);
+            if !path.exists() {
+                continue;
+            }
+            match load(&path) {
+                Ok(project) => projects.push(project),
+                Err(e) => tracing::warn!("Skipping project {}: {}", path.display(), e),
+            }
+        }
+        projects.sort_by_key(|p| p.name.to_lowercase());
+        Ok(projects)
+    }
+
+    /// Load a project by ID
+    ///
+    /// # Returns
+    /// Ok(None) if there is no such project
+    pub fn get(&self, id: &str) -> Result<Option<Project>> {
+        let path = self.project_dir(id).join(PROJECT_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        load(&path).map(Some)
+    }
+
+    /// Write a project, creating its directory
+    pub fn save(&self, project: &Project) -> Result<()> {
+        let dir = self.project_dir(&project.id);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(
+            dir.join(PROJECT_FILE),
+            serde_json::to_string_pretty(project)?,
+        )?;
+        Ok(())
+    }
+
+    /// Create and save a new project
+    ///
+    /// # Errors
+    /// - The name has no letters or digits to build an ID from
+    /// - A project with the same ID exists
+    pub fn create(&self, name: &str) -> Result<Project> {
+        let project = Project::new(name);
+        if project.id.is_empty() {
+            return Err(RustbotError::ConfigError(
+                "Project name needs at least one letter or digit".to_string(),
+            ));
+        }
+        if self.project_dir(&project.id).join(PROJECT_FILE).exists() {
+            return Err(RustbotError::ConfigError(format!(
+                "A project named '{}' already exists",
+                project.name
+            )));
+        }
+        self.save(&project)?;
+        Ok(project)
+    }
+
+    /// Delete a project and its index (its conversations are kept)
+    pub fn delete(&self, id: &str) -> Result<()> {
+        let dir = self.project_dir(id);
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+}
+
+fn load(path: &Path) -> Result<Project> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_list_and_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ProjectStore::new(dir.path().to_path_buf());
+        assert!(store.list().unwrap().is_empty());
+
+        let mut thesis = store.create("My Thesis").unwrap();
+        assert_eq!(thesis.id, "my_thesis");
+        thesis.folder = Some(PathBuf::from("/home/ana/thesis"));
+        thesis.preferred_agents = vec!["researcher".to_string(), "assistant".to_string()];
+        store.save(&thesis).unwrap();
+        store.create("Budget").unwrap();
+
+        assert!(store.create("my thesis").is_err());
+        assert!(store.create("!!").is_err());
+
+        let projects = store.list().unwrap();
+        assert_eq!(
+            projects.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(),
+            vec!["Budget", "My Thesis"]
+        );
+        let loaded = store.get("my_thesis").unwrap().unwrap();
+        assert_eq!(loaded, thesis);
+        assert_eq!(loaded.default_agent(), Some("researcher"));
+
+        store.delete("my_thesis").unwrap();
+        assert!(store.get("my_thesis").unwrap().is_none());
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,234 @@
+// Prompt library - reusable prompt templates with {{variables}}
+//
+// Design Decision: Plain text bodies with `{{name}}` placeholders
+//
+// Rationale: Users mostly keep a handful of prompts they retype often ("review
+// this diff", "translate to {{language}}"). Double-brace placeholders are easy
+// to type and read, and need no template engine: a front end asks for each
+// variable's value and the filled text goes into the chat input, where it can
+// still be edited before sending.
+//
+// Configuration: ~/.rustbot/prompts.json (edited in Settings > Prompts)
+//
+// Trade-offs: No conditionals, loops or escaping; a literal `{{x}}` in a
+// prompt is always treated as a variable.
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// A saved prompt
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    /// Name used by the picker and `/prompt <name>`
+    pub name: String,
+
+    /// Short explanation shown in the picker
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+
+    /// Prompt text with `{{variable}}` placeholders
+    pub body: String,
+}
+
+impl PromptTemplate {
+    /// Names of the variables in the body, in order of first use
+    pub fn variables(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for (_, name) in placeholders(&self.body) {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+        names
+    }
+
+    /// Body with each placeholder replaced by its value
+    ///
+    /// Placeholders without a value are left as they are, so they stay
+    /// visible in the chat input.
+    pub fn fill(&self, values: &HashMap<String, String>) -> String {
+        fill_placeholders(&self.body, |name| values.get(name).cloned())
+    }
+}
+
+/// Replace each `{{name}}` placeholder in `body` with its value, in one pass
+///
+/// Values are inserted as they are: a placeholder inside a value is not
+/// filled in again. Placeholders `value` returns None for are left as they
+/// are.
+pub(crate) fn fill_placeholders(body: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut filled = String::with_capacity(body.len());
+    let mut last = 0;
+    for (range, name) in placeholders(body) {
+        if let Some(value) = value(name) {
+            filled.push_str(&body[last..range.start]);
+            filled.push_str(&value);
+            last = range.end;
+        }
+    }
+    filled.push_str(&body[last..]);
+    filled
+}
+
+/// Placeholders in a body: byte range of `{{ name }}` and the trimmed name
+fn placeholders(body: &str) -> Vec<(std::ops::Range<usize>, &str)> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = body[offset..].find("{{").map(|i| offset + i) {
+        let Some(end) = body[start + 2..].find("}}").map(|i| start + 2 + i) else {
+            break;
+        };
+        let name = body[start + 2..end].trim();
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == ' ');
+        if valid {
+            found.push((start..end + 2, name));
+            offset = end + 2;
+        } else {
+            offset = start + 2;
+        }
+    }
+    found
+}
+
+/// The user's saved prompts
+///
+/// File Format: JSON
+/// Location: ~/.rustbot/prompts.json
+///
+/// Example:
+///     { "prompts": [
+///         { "name": "translate",
+///           "description": "Translate text",
+///           "body": "Translate this into {{language}}:\n\n{{text}}" } ] }
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptLibrary {
+    #[serde(default)]
+    pub prompts: Vec<PromptTemplate>,
+}
+
+impl PromptLibrary {
+    /// Default library location: ~/.rustbot/prompts.json
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("prompts.json")
+    }
+
+    /// Load the library from a file
+    ///
+    /// # Returns
+    /// Ok(None) if the file doesn't exist, Ok(Some) if loaded
+    ///
+    /// # Errors
+    /// - File exists but cannot be read
+    /// - Invalid JSON
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// Write the library to a file, creating parent directories
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Find a prompt by name, ignoring case
+    pub fn get(&self, name: &str) -> Option<&PromptTemplate> {
+        self.prompts
+            .iter()
+            .find(|p| p.name.trim().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Check that every prompt has a name and no two share one
+    ///
+    /// # Errors
+    /// Describes the first problem found
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        for (i, prompt) in self.prompts.iter().enumerate() {
+            if prompt.name.trim().is_empty() {
+                return Err(format!("Prompt {} has no name", i + 1));
+            }
+            if self.prompts[..i]
+                .iter()
+                .any(|p| p.name.trim().eq_ignore_ascii_case(prompt.name.trim()))
+            {
+                return Err(format!("More than one prompt is named '{}'", prompt.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(body: &str) -> PromptTemplate {
+        PromptTemplate {
+            name: "test".to_string(),
+            description: String::new(),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_variables_in_order_without_duplicates() {
+        let prompt = template("Translate {{ text }} into {{language}}. Keep {{text}} short.");
+        assert_eq!(prompt.variables(), vec!["text", "language"]);
+        assert!(template("No variables, just {braces} and {{}}")
+            .variables()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_fill_replaces_known_variables() {
+        let prompt = template("Hi {{name}}, meet {{ other }} and {{name}}.");
+        let values = HashMap::from([("name".to_string(), "Ann".to_string())]);
+        assert_eq!(prompt.fill(&values), "Hi Ann, meet {{ other }} and Ann.");
+
+        // Unclosed braces are left alone
+        assert_eq!(template("x {{name").fill(&values), "x {{name");
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prompts.json");
+        assert_eq!(PromptLibrary::load(&path).unwrap(), None);
+
+        let library = PromptLibrary {
+            prompts: vec![template("Summarize {{text}}")],
+        };
+        library.save(&path).unwrap();
+        assert_eq!(PromptLibrary::load(&path).unwrap(), Some(library.clone()));
+        assert!(library.get(" TEST ").is_some());
+    }
+
+    #[test]
+    fn test_validate_names() {
+        let mut library = PromptLibrary {
+            prompts: vec![template("a"), template("b")],
+        };
+        assert!(library.validate().is_err());
+
+        library.prompts[1].name = "other".to_string();
+        assert!(library.validate().is_ok());
+
+        library.prompts[0].name = "  ".to_string();
+        assert!(library.validate().is_err());
+    }
+}
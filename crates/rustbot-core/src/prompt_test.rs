@@ -0,0 +1,547 @@
+// A/B testing of system instructions
+//
+// Design Decision: Send a fixed prompt set to two variants, optionally have a
+// model judge each pair, and report the answers side by side with a diff
+//
+// Rationale: Changing an agent's instructions is guesswork without seeing
+// how the answers change. A variant is a system message and a model, built
+// from an agent configuration (`PromptVariant::from_agent`), so the same
+// harness compares two drafts of one agent's instructions or two agents.
+// Each prompt is a single-turn request without tools: the comparison shows
+// what the instructions do, not what a tool happened to return.
+//
+// Judging: With a judge, a third request scores both answers from 1 to 10
+// against the user's criteria. The answers are shown in swapped order for
+// every other prompt so a model's preference for the first answer evens out
+// over the set.
+//
+// Trade-offs:
+// - Prompts run one after another (both variants of a prompt in parallel) to
+//   stay clear of provider rate limits
+// - The word diff is skipped for answers too long to compare cheaply; both
+//   are still shown in full
+// - Every request (both answers and the judge's) counts against the daily
+//   quota (see `crate::quota`); a run stops at the prompt where a limit is
+//   reached and keeps the answers it has
+
+use crate::agent::AgentConfig;
+use crate::llm::{LlmAdapter, LlmRequest, LlmResponse, Message as LlmMessage};
+use serde::Deserialize;
+use std::ops::Range;
+
+/// Separator between prompts in a prompt set (a line of its own)
+pub const PROMPT_SEPARATOR: &str = "---";
+
+/// Longest answers (in words, multiplied) that get a word diff
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// One side of a comparison
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptVariant {
+    /// Name in the report, e.g. "A: assistant"
+    pub label: String,
+    /// System message sent ahead of each prompt
+    pub system: String,
+    /// Model answering (None = the adapter's default)
+    pub model: Option<String>,
+}
+
+impl PromptVariant {
+    /// The system message and model an agent would use
+    ///
+    /// # Arguments
+    /// * `label` - Name in the report
+    /// * `system_instructions` - Instructions shared by every agent
+    /// * `config` - Agent (with edited instructions, to test a draft)
+    pub fn from_agent(label: &str, system_instructions: &str, config: &AgentConfig) -> Self {
+        let system = crate::agent::system_sections_for(system_instructions, config)
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        Self {
+            label: label.to_string(),
+            system,
+            model: Some(config.model.clone()).filter(|model| !model.is_empty()),
+        }
+    }
+}
+
+/// Model that scores the answers, and what it scores them on
+#[derive(Debug, Clone, PartialEq)]
+pub struct Judge {
+    /// Model judging (None = the adapter's default)
+    pub model: Option<String>,
+    /// What makes an answer good, e.g. "accuracy and brevity"
+    pub criteria: String,
+}
+
+/// A prompt set to run against two variants
+#[derive(Debug, Clone)]
+pub struct PromptTest {
+    pub prompts: Vec<String>,
+    pub variants: [PromptVariant; 2],
+    pub judge: Option<Judge>,
+}
+
+/// Scores the judge gave one prompt's answers
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verdict {
+    /// Score of each variant's answer, 1 to 10
+    pub scores: [u8; 2],
+    /// The judge's one-sentence explanation
+    pub reason: String,
+}
+
+/// Both variants' answers to one prompt (Err holds the error message)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseResult {
+    pub prompt: String,
+    pub answers: [Result<String, String>; 2],
+    /// None without a judge or when an answer failed
+    pub verdict: Option<Result<Verdict, String>>,
+}
+
+/// Results of a prompt test
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestReport {
+    /// Variant labels
+    pub labels: [String; 2],
+    pub cases: Vec<CaseResult>,
+
+    /// Why the run stopped before the last prompt (a daily limit was reached)
+    pub stopped: Option<String>,
+}
+
+/// Judged wins, ties and average scores over a report
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TestSummary {
+    pub wins: [usize; 2],
+    pub ties: usize,
+    /// Average score per variant (None if nothing was judged)
+    pub mean_scores: [Option<f64>; 2],
+    /// Prompts with a failed answer
+    pub failures: usize,
+}
+
+impl TestReport {
+    /// Count wins and average the judge's scores
+    pub fn summary(&self) -> TestSummary {
+        let mut summary = TestSummary::default();
+        let mut totals = [0u32; 2];
+        let mut judged = 0u32;
+        for case in &self.cases {
+            if case.answers.iter().any(Result::is_err) {
+                summary.failures += 1;
+            }
+            let Some(Ok(verdict)) = &case.verdict else {
+                continue;
+            };
+            judged += 1;
+            totals[0] += u32::from(verdict.scores[0]);
+            totals[1] += u32::from(verdict.scores[1]);
+            match verdict.scores[0].cmp(&verdict.scores[1]) {
+                std::cmp::Ordering::Greater => summary.wins[0] += 1,
+                std::cmp::Ordering::Less => summary.wins[1] += 1,
+                std::cmp::Ordering::Equal => summary.ties += 1,
+            }
+        }
+        if judged > 0 {
+            summary.mean_scores = totals.map(|total| Some(f64::from(total) / f64::from(judged)));
+        }
+        summary
+    }
+
+    /// The report as markdown, for saving or pasting into notes
+    pub fn to_markdown(&self) -> String {
+        let summary = self.summary();
+        let mut out = format!(
+            "# Prompt test: {} vs {}\n\n",
+            self.labels[0], self.labels[1]
+        );
+        if let [Some(a), Some(b)] = summary.mean_scores {
+            out.push_str(&format!("| | {} | {} |\n", self.labels[0], self.labels[1]));
+            out.push_str("|---|---|---|\n");
+            out.push_str(&format!(
+                "| Wins | {} | {} |\n",
+                summary.wins[0], summary.wins[1]
+            ));
+            out.push_str(&format!("| Average score | {:.1} | {:.1} |\n\n", a, b));
+            out.push_str(&format!("Ties: {}\n\n", summary.ties));
+        }
+        if let Some(stopped) = &self.stopped {
+            out.push_str(&format!("_Stopped early: {}_\n\n", stopped));
+        }
+
+        for (i, case) in self.cases.iter().enumerate() {
+            out.push_str(&format!("## Prompt {}\n\n{}\n\n", i + 1, case.prompt));
+            for (label, answer) in self.labels.iter().zip(&case.answers) {
+                match answer {
+                    Ok(answer) => out.push_str(&format!("### {}\n\n{}\n\n", label, answer)),
+                    Err(e) => out.push_str(&format!("### {}\n\n_Failed: {}_\n\n", label, e)),
+                }
+            }
+            match &case.verdict {
+                Some(Ok(verdict)) => out.push_str(&format!(
+                    "> Judge: {} / {}: {}\n\n",
+                    verdict.scores[0], verdict.scores[1], verdict.reason
+                )),
+                Some(Err(e)) => out.push_str(&format!("> Judge failed: {}\n\n", e)),
+                None => {}
+            }
+        }
+        out
+    }
+}
+
+/// Split a prompt set into prompts at lines holding only `PROMPT_SEPARATOR`
+pub fn parse_prompts(text: &str) -> Vec<String> {
+    let mut prompts = vec![String::new()];
+    for line in text.lines() {
+        if line.trim() == PROMPT_SEPARATOR {
+            prompts.push(String::new());
+        } else if let Some(prompt) = prompts.last_mut() {
+            prompt.push_str(line);
+            prompt.push('\n');
+        }
+    }
+    prompts
+        .into_iter()
+        .map(|prompt| prompt.trim().to_string())
+        .filter(|prompt| !prompt.is_empty())
+        .collect()
+}
+
+/// Run every prompt against both variants, judging each pair if asked
+///
+/// # Arguments
+/// * `adapter` - Adapter the requests go through
+/// * `test` - Prompts, variants and judge
+/// * `progress` - Called with the number of prompts done after each one
+pub async fn run(
+    adapter: &dyn LlmAdapter,
+    test: &PromptTest,
+    progress: &(dyn Fn(usize) + Send + Sync),
+) -> TestReport {
+    let mut cases = Vec::with_capacity(test.prompts.len());
+    let mut stopped = None;
+    for (i, prompt) in test.prompts.iter().enumerate() {
+        if let Err(e) = crate::quota::tracker().check() {
+            stopped = Some(e.to_string());
+            break;
+        }
+        let (a, b) = futures::join!(
+            answer(adapter, &test.variants[0], prompt),
+            answer(adapter, &test.variants[1], prompt)
+        );
+        let verdict = match (&test.judge, &a, &b) {
+            (Some(judge), Ok(a), Ok(b)) => Some(
+                judge_answers(adapter, judge, prompt, [a, b], i % 2 == 1)
+                    .await
+                    .map_err(|e| format!("{:#}", e)),
+            ),
+            _ => None,
+        };
+        cases.push(CaseResult {
+            prompt: prompt.clone(),
+            answers: [a, b],
+            verdict,
+        });
+        progress(i + 1);
+    }
+
+    TestReport {
+        labels: test.variants.clone().map(|variant| variant.label),
+        cases,
+        stopped,
+    }
+}
+
+/// Send a request, counted against the daily quota like a chat message
+///
+/// # Errors
+/// - A daily limit is used up, or the request fails
+async fn complete_counted(
+    adapter: &dyn LlmAdapter,
+    request: LlmRequest,
+) -> anyhow::Result<LlmResponse> {
+    let quota = crate::quota::tracker();
+    quota.check()?;
+    let input_tokens: u32 = request
+        .messages
+        .iter()
+        .map(crate::request_preview::message_tokens)
+        .sum();
+    let response = adapter.complete_chat(request).await?;
+    quota.record_message(input_tokens as usize);
+    quota.record_response(crate::tokenizer::count_tokens(&response.content));
+    Ok(response)
+}
+
+/// One variant's answer to a prompt
+async fn answer(
+    adapter: &dyn LlmAdapter,
+    variant: &PromptVariant,
+    prompt: &str,
+) -> Result<String, String> {
+    let mut messages = Vec::new();
+    if !variant.system.is_empty() {
+        messages.push(LlmMessage::new("system", variant.system.clone()));
+    }
+    messages.push(LlmMessage::new("user", prompt));
+    let mut request = LlmRequest::new(messages);
+    request.model = variant.model.clone();
+
+    complete_counted(adapter, request)
+        .await
+        .map(|response| response.content.trim().to_string())
+        .map_err(|e| format!("{:#}", e))
+}
+
+/// Scores the judge replies with
+#[derive(Deserialize)]
+struct JudgeReply {
+    first: f64,
+    second: f64,
+    #[serde(default)]
+    reason: String,
+}
+
+/// Have the judge score a pair of answers
+///
+/// # Arguments
+/// * `swapped` - Show the second variant's answer first
+///
+/// # Errors
+/// - The request fails or the reply has no scores
+async fn judge_answers(
+    adapter: &dyn LlmAdapter,
+    judge: &Judge,
+    prompt: &str,
+    answers: [&String; 2],
+    swapped: bool,
+) -> anyhow::Result<Verdict> {
+    let (first, second) = if swapped {
+        (answers[1], answers[0])
+    } else {
+        (answers[0], answers[1])
+    };
+    let criteria = if judge.criteria.trim().is_empty() {
+        "helpfulness, accuracy and clarity"
+    } else {
+        judge.criteria.trim()
+    };
+    let system = format!(
+        "You compare two answers to the same prompt. Score each from 1 (poor) to 10 \
+         (excellent) on {}. Reply with JSON only, in this form: \
+         {{\"first\": <score>, \"second\": <score>, \"reason\": \"<one sentence>\"}}",
+        criteria
+    );
+    let user = format!(
+        "Prompt:\n{}\n\nFirst answer:\n{}\n\nSecond answer:\n{}",
+        prompt, first, second
+    );
+    let mut request = LlmRequest::new(vec![
+        LlmMessage::new("system", system),
+        LlmMessage::new("user", user),
+    ]);
+    request.model = judge.model.clone();
+    request.temperature = Some(0.0);
+
+    let response = complete_counted(adapter, request).await?;
+    parse_verdict(&response.content, swapped)
+}
+
+/// Read the judge's JSON reply, mapping the scores back to the variants
+///
+/// # Errors
+/// - No JSON object with `first` and `second` scores in the reply
+fn parse_verdict(reply: &str, swapped: bool) -> anyhow::Result<Verdict> {
+    let json = reply
+        .find('{')
+        .zip(reply.rfind('}'))
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| &reply[start..=end])
+        .ok_or_else(|| anyhow::anyhow!("The judge didn't reply with scores"))?;
+    let reply: JudgeReply = serde_json::from_str(json)
+        .map_err(|e| anyhow::anyhow!("The judge's scores couldn't be read: {}", e))?;
+
+    let score = |value: f64| value.round().clamp(1.0, 10.0) as u8;
+    let (first, second) = (score(reply.first), score(reply.second));
+    Ok(Verdict {
+        scores: if swapped {
+            [second, first]
+        } else {
+            [first, second]
+        },
+        reason: reply.reason.trim().to_string(),
+    })
+}
+
+/// A run of words in a diff between two answers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffSpan<'a> {
+    /// In both answers
+    Same(&'a str),
+    /// Only in the first answer
+    Removed(&'a str),
+    /// Only in the second answer
+    Added(&'a str),
+}
+
+/// Word diff from `a` to `b`, with runs of the same kind merged
+///
+/// Answers too long to compare return one `Removed` and one `Added` span.
+pub fn word_diff<'a>(a: &'a str, b: &'a str) -> Vec<DiffSpan<'a>> {
+    // Byte range of each word, with the whitespace after it
+    let words = |text: &str| -> Vec<Range<usize>> {
+        let mut start = 0;
+        text.split_inclusive(char::is_whitespace)
+            .map(|word| {
+                let range = start..start + word.len();
+                start = range.end;
+                range
+            })
+            .collect()
+    };
+    let (a_words, b_words) = (words(a), words(b));
+    let (n, m) = (a_words.len(), b_words.len());
+    if n.saturating_mul(m) > MAX_DIFF_CELLS {
+        return vec![DiffSpan::Removed(a), DiffSpan::Added(b)];
+    }
+    let same = |i: usize, j: usize| a[a_words[i].clone()] == b[b_words[j].clone()];
+
+    // Longest common subsequence lengths of the suffixes
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if same(i, j) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // Walk the table, growing the last span while the kind stays the same
+    // (Same and Removed ranges are in `a`, Added ranges in `b`)
+    #[derive(Clone, Copy, PartialEq)]
+    enum Kind {
+        Same,
+        Removed,
+        Added,
+    }
+    let mut ranges: Vec<(Kind, Range<usize>)> = Vec::new();
+    let mut push = |kind: Kind, range: &Range<usize>| match ranges.last_mut() {
+        Some((last_kind, last)) if *last_kind == kind && last.end == range.start => {
+            last.end = range.end;
+        }
+        _ => ranges.push((kind, range.clone())),
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && same(i, j) {
+            push(Kind::Same, &a_words[i]);
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            push(Kind::Added, &b_words[j]);
+            j += 1;
+        } else {
+            push(Kind::Removed, &a_words[i]);
+            i += 1;
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(kind, range)| match kind {
+            Kind::Same => DiffSpan::Same(&a[range]),
+            Kind::Removed => DiffSpan::Removed(&a[range]),
+            Kind::Added => DiffSpan::Added(&b[range]),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prompts() {
+        let prompts = parse_prompts("Summarize this\nin one line\n---\n\n---\n  Translate: hi  \n");
+        assert_eq!(
+            prompts,
+            vec!["Summarize this\nin one line", "Translate: hi"]
+        );
+    }
+
+    #[test]
+    fn test_parse_verdict() {
+        let verdict = parse_verdict(
+            "Sure! {\"first\": 8, \"second\": 4.6, \"reason\": \"More precise.\"}",
+            false,
+        )
+        .unwrap();
+        assert_eq!(verdict.scores, [8, 5]);
+        assert_eq!(verdict.reason, "More precise.");
+
+        // Shown swapped: the first answer was variant B's
+        let verdict = parse_verdict("{\"first\": 12, \"second\": 0}", true).unwrap();
+        assert_eq!(verdict.scores, [1, 10]);
+
+        assert!(parse_verdict("I prefer the first one", false).is_err());
+    }
+
+    #[test]
+    fn test_word_diff() {
+        let diff = word_diff("The quick brown fox", "The slow brown fox jumps");
+        assert_eq!(
+            diff,
+            vec![
+                DiffSpan::Same("The "),
+                DiffSpan::Added("slow "),
+                DiffSpan::Removed("quick "),
+                DiffSpan::Same("brown "),
+                DiffSpan::Added("fox jumps"),
+                DiffSpan::Removed("fox"),
+            ]
+        );
+        assert_eq!(word_diff("same", "same"), vec![DiffSpan::Same("same")]);
+    }
+
+    #[test]
+    fn test_summary() {
+        let verdict = |a, b| {
+            Some(Ok(Verdict {
+                scores: [a, b],
+                reason: String::new(),
+            }))
+        };
+        let case = |verdict| CaseResult {
+            prompt: "p".to_string(),
+            answers: [Ok("a".to_string()), Ok("b".to_string())],
+            verdict,
+        };
+        let mut report = TestReport {
+            labels: ["A".to_string(), "B".to_string()],
+            cases: vec![
+                case(verdict(8, 6)),
+                case(verdict(5, 5)),
+                case(verdict(9, 4)),
+            ],
+            stopped: None,
+        };
+        report.cases.push(CaseResult {
+            answers: [Ok("a".to_string()), Err("timeout".to_string())],
+            ..case(None)
+        });
+
+        let summary = report.summary();
+        assert_eq!(summary.wins, [2, 0]);
+        assert_eq!(summary.ties, 1);
+        assert_eq!(summary.mean_scores, [Some(22.0 / 3.0), Some(5.0)]);
+        assert_eq!(summary.failures, 1);
+        assert!(report.to_markdown().contains("| Wins | 2 | 0 |"));
+    }
+}
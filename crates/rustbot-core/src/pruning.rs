@@ -0,0 +1,379 @@
+// Context pruning strategies
+//
+// Design Decision: Each agent chooses how its chat history is cut down once it
+// outgrows the limit; `RustbotApi` applies that choice after every turn
+//
+// Rationale: Always keeping the last 20 messages works for quick questions but
+// loses whatever was set up at the start of a long session (a name, a
+// constraint, the code being worked on). The strategies trade cost against
+// what survives:
+// - Sliding window: drop the oldest turns (the previous fixed behaviour, and
+//   the default)
+// - Importance-weighted: drop the turns that look least important first,
+//   scored by `turn_importance` (age, things the user asked to remember, code,
+//   tool results, throwaway replies)
+// - Summarize-then-drop: when the history is full, drop its older half and
+//   have the agent's model fold it into a running summary that is sent ahead
+//   of the history
+//
+// Every strategy drops whole turns (a user message and everything up to the
+// next one), so a tool result never loses the assistant message that called
+// it, and the newest turn always stays.
+//
+// Agent presets select a strategy with `"contextPruning": {"strategy":
+// "importance_weighted", "maxMessages": 40}`; without `maxMessages` the API's
+// `max_history_size` applies.
+//
+// Trade-offs:
+// - Importance scores are keyword and shape heuristics, not a model judgement
+// - Summaries cost one extra model call each time the history fills up, and
+//   live in the session only (a restored conversation starts without one)
+
+use crate::llm::Message as LlmMessage;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// Newest turns importance-weighted pruning never drops
+const KEEP_RECENT_TURNS: usize = 2;
+
+/// Longest summary kept when the model can't be asked for one (characters)
+const FALLBACK_SUMMARY_CHARS: usize = 2_000;
+
+/// Phrases that mark a turn the user wants kept
+const IMPORTANT_PHRASES: &[&str] = &[
+    "remember",
+    "important",
+    "always",
+    "never",
+    "don't forget",
+    "keep in mind",
+    "note that",
+    "my name",
+    "i prefer",
+];
+
+/// Introduces the summary of dropped turns to the model
+const SUMMARY_PREAMBLE: &str = "Summary of the earlier part of this conversation, \
+    which is no longer in the history below:";
+
+/// Instructions for the model writing the summary
+const SUMMARY_INSTRUCTIONS: &str = "Summarize the conversation below for your own later \
+    reference. Keep facts about the user, decisions, constraints, names, numbers and open \
+    questions; leave out pleasantries. If an earlier summary is given, merge it in. Answer \
+    with the summary only, as short bullet points.";
+
+/// How the history is reduced when it grows past the limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PruningStrategy {
+    /// Drop the oldest turns
+    #[default]
+    SlidingWindow,
+
+    /// Drop the least important turns first, keeping the newest
+    ImportanceWeighted,
+
+    /// Fold the older half into a summary when the history is full
+    SummarizeThenDrop,
+}
+
+impl PruningStrategy {
+    /// Every strategy, in the order settings list them
+    pub const ALL: [PruningStrategy; 3] = [
+        PruningStrategy::SlidingWindow,
+        PruningStrategy::ImportanceWeighted,
+        PruningStrategy::SummarizeThenDrop,
+    ];
+
+    /// Name shown in settings
+    pub fn label(self) -> &'static str {
+        match self {
+            PruningStrategy::SlidingWindow => "Sliding window",
+            PruningStrategy::ImportanceWeighted => "Importance-weighted",
+            PruningStrategy::SummarizeThenDrop => "Summarize, then drop",
+        }
+    }
+}
+
+/// An agent's pruning settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextPruning {
+    #[serde(default)]
+    pub strategy: PruningStrategy,
+
+    /// History size in messages (None = the API's `max_history_size`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_messages: Option<usize>,
+}
+
+/// Reduce `history` to at most `max` messages with `strategy`
+///
+/// # Returns
+/// The dropped messages, oldest first (empty if the history fit)
+pub fn prune(
+    history: &mut VecDeque<LlmMessage>,
+    strategy: PruningStrategy,
+    max: usize,
+) -> Vec<LlmMessage> {
+    if history.len() <= max {
+        return Vec::new();
+    }
+    match strategy {
+        PruningStrategy::SlidingWindow => drop_oldest(history, max),
+        // Dropping half at once keeps summary calls rare
+        PruningStrategy::SummarizeThenDrop => drop_oldest(history, max / 2),
+        PruningStrategy::ImportanceWeighted => drop_least_important(history, max),
+    }
+}
+
+/// Index ranges of the turns in `history`
+///
+/// A new turn starts at every user message after the first message; anything
+/// before the first user message belongs to the first turn.
+fn turns(history: &VecDeque<LlmMessage>) -> Vec<Range<usize>> {
+    let mut starts: Vec<usize> = history
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, m)| m.role == "user")
+        .map(|(i, _)| i)
+        .collect();
+    starts.insert(0, 0);
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, &start)| start..starts.get(n + 1).copied().unwrap_or(history.len()))
+        .filter(|range| !range.is_empty())
+        .collect()
+}
+
+fn drop_oldest(history: &mut VecDeque<LlmMessage>, max: usize) -> Vec<LlmMessage> {
+    let turns = turns(history);
+    let mut len = history.len();
+    let mut cut = 0;
+    // Never the newest turn
+    for turn in &turns[..turns.len().saturating_sub(1)] {
+        if len <= max {
+            break;
+        }
+        len -= turn.len();
+        cut = turn.end;
+    }
+    history.drain(..cut).collect()
+}
+
+fn drop_least_important(history: &mut VecDeque<LlmMessage>, max: usize) -> Vec<LlmMessage> {
+    let turns = turns(history);
+    let candidates = turns.len().saturating_sub(KEEP_RECENT_TURNS);
+    let messages: Vec<LlmMessage> = history.drain(..).collect();
+
+    let mut ranked: Vec<(usize, f32)> = (0..candidates)
+        .map(|n| {
+            let score = turn_importance(&messages[turns[n].clone()], n, turns.len());
+            (n, score)
+        })
+        .collect();
+    // Lowest score first; the older turn goes first on a tie
+    ranked.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+
+    let mut dropped_turns = vec![false; turns.len()];
+    let mut len = messages.len();
+    for (n, _) in ranked {
+        if len <= max {
+            break;
+        }
+        dropped_turns[n] = true;
+        len -= turns[n].len();
+    }
+
+    let mut dropped = Vec::new();
+    for (n, turn) in turns.iter().enumerate() {
+        let turn_messages = messages[turn.clone()].iter().cloned();
+        if dropped_turns[n] {
+            dropped.extend(turn_messages);
+        } else {
+            history.extend(turn_messages);
+        }
+    }
+    dropped
+}
+
+/// How much a turn is worth keeping; higher is more important
+///
+/// # Arguments
+/// * `turn` - The turn's messages, user message first
+/// * `position` - Index of the turn in the history (0 = oldest)
+/// * `count` - Number of turns in the history
+pub fn turn_importance(turn: &[LlmMessage], position: usize, count: usize) -> f32 {
+    // Newer turns are more likely to matter for the next answer
+    let mut score = (position + 1) as f32 / count.max(1) as f32;
+
+    let user = turn
+        .iter()
+        .find(|m| m.role == "user")
+        .map(|m| m.content.to_lowercase())
+        .unwrap_or_default();
+    if IMPORTANT_PHRASES.iter().any(|phrase| user.contains(phrase)) {
+        score += 1.0;
+    }
+    if turn.iter().any(|m| m.content.contains("```")) {
+        score += 0.5;
+    }
+    if turn
+        .iter()
+        .any(|m| m.role == "tool" || m.tool_calls.is_some())
+    {
+        score += 0.5;
+    }
+    if !turn.iter().all(|m| m.images.is_empty()) {
+        score += 0.5;
+    }
+    // "thanks", "ok", "go on": nothing to lose
+    if user.trim().chars().count() < 20 {
+        score -= 0.5;
+    }
+    score
+}
+
+/// Messages asking the model to fold `dropped` into `previous`
+pub fn summary_request(previous: Option<&str>, dropped: &[LlmMessage]) -> Vec<LlmMessage> {
+    let mut transcript = String::new();
+    if let Some(previous) = previous {
+        transcript.push_str(&format!("Earlier summary:\n{}\n\n", previous));
+    }
+    transcript.push_str("Conversation:\n");
+    for message in dropped {
+        if message.content.trim().is_empty() {
+            continue;
+        }
+        transcript.push_str(&format!("{}: {}\n", message.role, message.content.trim()));
+    }
+    vec![
+        LlmMessage::new("system", SUMMARY_INSTRUCTIONS),
+        LlmMessage::new("user", transcript),
+    ]
+}
+
+/// Summary used when the model can't write one: the earlier summary plus the
+/// first line of each dropped user message, cut to a fixed length
+pub fn fallback_summary(previous: Option<&str>, dropped: &[LlmMessage]) -> String {
+    let mut lines: Vec<String> = previous
+        .map(|previous| vec![previous.to_string()])
+        .unwrap_or_default();
+    lines.extend(
+        dropped
+            .iter()
+            .filter(|m| m.role == "user")
+            .filter_map(|m| m.content.lines().find(|line| !line.trim().is_empty()))
+            .map(|line| format!("- The user said: {}", line.trim())),
+    );
+    let summary = lines.join("\n");
+    // Keep the newest part when it's too long
+    let skip = summary
+        .chars()
+        .count()
+        .saturating_sub(FALLBACK_SUMMARY_CHARS);
+    summary.chars().skip(skip).collect()
+}
+
+/// The summary as a message to send ahead of the history
+pub fn summary_message(summary: &str) -> LlmMessage {
+    LlmMessage::new("system", format!("{}\n\n{}", SUMMARY_PREAMBLE, summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(contents: &[(&str, &str)]) -> VecDeque<LlmMessage> {
+        contents
+            .iter()
+            .map(|(role, content)| LlmMessage::new(*role, *content))
+            .collect()
+    }
+
+    fn contents(messages: &VecDeque<LlmMessage>) -> Vec<&str> {
+        messages.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sliding_window_and_summarize_drop_oldest() {
+        let turns = [
+            ("user", "one"),
+            ("assistant", "1"),
+            ("user", "two"),
+            ("assistant", "2"),
+            ("user", "three"),
+            ("assistant", "3"),
+            ("user", "four"),
+            ("assistant", "4"),
+        ];
+
+        let mut sliding = history(&turns);
+        let dropped = prune(&mut sliding, PruningStrategy::SlidingWindow, 6);
+        assert_eq!(
+            contents(&sliding),
+            vec!["two", "2", "three", "3", "four", "4"]
+        );
+        assert_eq!(dropped.len(), 2);
+
+        // Summarize-then-drop clears room for several turns at once
+        let mut summarized = history(&turns);
+        let dropped = prune(&mut summarized, PruningStrategy::SummarizeThenDrop, 6);
+        assert_eq!(contents(&summarized), vec!["four", "4"]);
+        assert_eq!(dropped.len(), 6);
+        assert!(prune(&mut summarized, PruningStrategy::SummarizeThenDrop, 6).is_empty());
+    }
+
+    #[test]
+    fn test_importance_weighted_keeps_what_matters() {
+        let mut messages = history(&[
+            (
+                "user",
+                "Please remember that my deadline is Friday the 14th",
+            ),
+            ("assistant", "Noted."),
+            ("user", "thanks"),
+            ("assistant", "You're welcome!"),
+            ("user", "What's a good name for a bakery in Lisbon?"),
+            ("assistant", "Pão Quente"),
+            ("user", "ok"),
+            ("assistant", "Anything else?"),
+            ("user", "Plan my week around the deadline"),
+            ("assistant", "Here's a plan..."),
+        ]);
+        let dropped = prune(&mut messages, PruningStrategy::ImportanceWeighted, 6);
+
+        assert_eq!(
+            dropped
+                .iter()
+                .map(|m| m.content.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "thanks",
+                "You're welcome!",
+                "What's a good name for a bakery in Lisbon?",
+                "Pão Quente"
+            ]
+        );
+        // The remembered fact stays, order is kept, the newest turns are untouched
+        assert_eq!(
+            contents(&messages)[0],
+            "Please remember that my deadline is Friday the 14th"
+        );
+        assert_eq!(contents(&messages)[5], "Here's a plan...");
+    }
+
+    #[test]
+    fn test_fallback_summary() {
+        let dropped: Vec<LlmMessage> = history(&[
+            ("user", "My name is Ana\nand I like tea"),
+            ("assistant", "Hi Ana"),
+        ])
+        .into();
+        let summary = fallback_summary(Some("- Earlier notes"), &dropped);
+        assert_eq!(summary, "- Earlier notes\n- The user said: My name is Ana");
+    }
+}
@@ -0,0 +1,356 @@
+// Daily usage quotas
+//
+// Design Decision: Limits live in the user profile, counters in a small file
+// next to it, and one process-wide tracker checks them before every message
+//
+// Rationale: On a shared family machine, or for a child's account, whoever
+// manages the computer wants to cap how much Rustbot is used: a number of
+// messages per day and a daily spending limit. Each OS account has its own
+// ~/.rustbot and so its own profile and limits. The limits are applied with
+// `configure` wherever the profile is (like local-only mode), and
+// `RustbotApi` calls `check` and `record_message` when a message is sent and
+// `record_response` when its answer is stored, and agents call
+// `record_request` for the other requests of a turn (history summaries, tool
+// calls and the follow-up that sends their results), so the GUI, the headless
+// commands and the bots are all covered. The settings can be locked with a
+// PIN so the limited user can't raise them.
+//
+// Trade-offs:
+// - Cost is an estimate from token counts at a fixed price (the same one the
+//   token counter in the chat view uses), not the provider's bill
+// - Usage is only counted while a limit is set
+// - Input cost counts the whole request (history, instructions, tools) as
+//   estimated before sending
+// - Only the user's messages count toward the message limit; the extra
+//   requests of a turn count toward the spending limit
+// - Counters are a plain file; someone who can edit ~/.rustbot can reset them.
+//   This is a guard rail for a managed account, not a security boundary
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, RwLock};
+use thiserror::Error;
+
+/// Price per million input tokens used for estimates (USD)
+pub const INPUT_COST_PER_MILLION: f64 = 3.0;
+
+/// Price per million output tokens used for estimates (USD)
+pub const OUTPUT_COST_PER_MILLION: f64 = 15.0;
+
+static TRACKER: OnceLock<QuotaTracker> = OnceLock::new();
+
+/// Estimated cost of a number of tokens, in USD
+pub fn estimate_cost(input_tokens: u64, output_tokens: u64) -> f64 {
+    (input_tokens as f64 / 1_000_000.0) * INPUT_COST_PER_MILLION
+        + (output_tokens as f64 / 1_000_000.0) * OUTPUT_COST_PER_MILLION
+}
+
+/// Daily limits for one profile (None = unlimited)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageQuota {
+    #[serde(default)]
+    pub messages_per_day: Option<u32>,
+
+    /// Estimated spend per day in USD
+    #[serde(default)]
+    pub cost_per_day: Option<f64>,
+
+    /// Salted SHA-256 of the PIN that unlocks these settings, as
+    /// "salt:hash" (None = not locked)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin_hash: Option<String>,
+}
+
+impl UsageQuota {
+    /// Whether any limit is set
+    pub fn is_limited(&self) -> bool {
+        self.messages_per_day.is_some() || self.cost_per_day.is_some()
+    }
+
+    /// Whether changing the limits needs a PIN
+    pub fn is_locked(&self) -> bool {
+        self.pin_hash.is_some()
+    }
+
+    /// Lock the settings with a PIN, or unlock them for good with None
+    pub fn set_pin(&mut self, pin: Option<&str>) {
+        self.pin_hash = pin.map(|pin| {
+            let mut salt = [0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            let salt: String = salt.iter().map(|b| format!("{:02x}", b)).collect();
+            format!("{}:{}", salt, hash_pin(&salt, pin))
+        });
+    }
+
+    /// Whether `pin` unlocks the settings (always true when not locked)
+    pub fn check_pin(&self, pin: &str) -> bool {
+        match self.pin_hash.as_deref() {
+            Some(stored) => stored
+                .split_once(':')
+                .is_some_and(|(salt, hash)| hash == hash_pin(salt, pin)),
+            None => true,
+        }
+    }
+}
+
+fn hash_pin(salt: &str, pin: &str) -> String {
+    format!("{:x}", Sha256::digest(format!("{}{}", salt, pin.trim())))
+}
+
+/// A message refused because a daily limit is used up
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum QuotaExceeded {
+    #[error("Daily message limit reached ({limit} messages). It resets at midnight.")]
+    Messages { limit: u32 },
+
+    #[error("Daily spending limit reached (${limit:.2}). It resets at midnight.")]
+    Cost { limit: f64 },
+}
+
+/// What has been used today
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DailyUsage {
+    /// Local date (YYYY-MM-DD) the counters belong to
+    pub date: String,
+    pub messages: u32,
+    /// Estimated spend in USD
+    pub cost: f64,
+}
+
+impl DailyUsage {
+    fn roll_over(&mut self) {
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        if self.date != today {
+            *self = Self {
+                date: today,
+                ..Self::default()
+            };
+        }
+    }
+}
+
+/// Limits and today's counters
+///
+/// Usage:
+///     let tracker = QuotaTracker::load(QuotaTracker::default_path());
+///     tracker.set_limits(profile.quota.clone());
+///     tracker.check()?;
+///     tracker.record_message(input_tokens);
+pub struct QuotaTracker {
+    path: PathBuf,
+    limits: RwLock<UsageQuota>,
+    usage: Mutex<DailyUsage>,
+}
+
+impl QuotaTracker {
+    /// Default counters file: ~/.rustbot/quota_usage.json
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("quota_usage.json")
+    }
+
+    /// Load today's counters (zero if the file is missing or from another day)
+    pub fn load(path: PathBuf) -> Self {
+        let mut usage: DailyUsage = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        usage.roll_over();
+        Self {
+            path,
+            limits: RwLock::new(UsageQuota::default()),
+            usage: Mutex::new(usage),
+        }
+    }
+
+    /// Replace the limits
+    pub fn set_limits(&self, limits: UsageQuota) {
+        if let Ok(mut current) = self.limits.write() {
+            *current = limits;
+        }
+    }
+
+    /// Current limits
+    pub fn limits(&self) -> UsageQuota {
+        self.limits.read().map(|l| l.clone()).unwrap_or_default()
+    }
+
+    /// What has been used today
+    pub fn today(&self) -> DailyUsage {
+        self.usage
+            .lock()
+            .map(|mut usage| {
+                usage.roll_over();
+                usage.clone()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Check that another message is allowed today
+    ///
+    /// # Errors
+    /// - The message or spending limit is used up
+    pub fn check(&self) -> Result<(), QuotaExceeded> {
+        let limits = self.limits();
+        let usage = self.today();
+        if let Some(limit) = limits.messages_per_day {
+            if usage.messages >= limit {
+                return Err(QuotaExceeded::Messages { limit });
+            }
+        }
+        if let Some(limit) = limits.cost_per_day {
+            if usage.cost >= limit {
+                return Err(QuotaExceeded::Cost { limit });
+            }
+        }
+        Ok(())
+    }
+
+    /// Count a sent message and the estimated cost of its request
+    ///
+    /// `input_tokens` is the whole request: history, instructions and tool
+    /// definitions as well as the message.
+    pub fn record_message(&self, input_tokens: usize) {
+        self.record(1, estimate_cost(input_tokens as u64, 0));
+    }
+
+    /// Count the estimated cost of an answer
+    pub fn record_response(&self, output_tokens: usize) {
+        self.record(0, estimate_cost(0, output_tokens as u64));
+    }
+
+    /// Count the estimated cost of a request of a turn other than the one
+    /// that sends the message (a summary, the follow-up with tool results)
+    pub fn record_request(&self, input_tokens: usize, output_tokens: usize) {
+        self.record(0, estimate_cost(input_tokens as u64, output_tokens as u64));
+    }
+
+    fn record(&self, messages: u32, cost: f64) {
+        if !self.limits().is_limited() {
+            return;
+        }
+        let Ok(mut usage) = self.usage.lock() else {
+            return;
+        };
+        usage.roll_over();
+        usage.messages += messages;
+        usage.cost += cost;
+        if let Err(e) = save(&self.path, &usage) {
+            tracing::warn!("Failed to save quota usage: {}", e);
+        }
+    }
+}
+
+fn save(path: &Path, usage: &DailyUsage) -> crate::error::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(usage)?)?;
+    Ok(())
+}
+
+/// The process-wide tracker, with counters from `QuotaTracker::default_path`
+pub fn tracker() -> &'static QuotaTracker {
+    TRACKER.get_or_init(|| QuotaTracker::load(QuotaTracker::default_path()))
+}
+
+/// Apply a profile's limits to this process
+pub fn configure(limits: &UsageQuota) {
+    let tracker = tracker();
+    if tracker.limits() != *limits && limits.is_limited() {
+        tracing::info!(
+            "⏳ Daily limits: {} messages, {} spend",
+            limits
+                .messages_per_day
+                .map_or("unlimited".to_string(), |n| n.to_string()),
+            limits
+                .cost_per_day
+                .map_or("unlimited".to_string(), |c| format!("${:.2}", c))
+        );
+    }
+    tracker.set_limits(limits.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_are_enforced_and_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("quota_usage.json");
+        let tracker = QuotaTracker::load(path.clone());
+
+        // Nothing is counted without a limit
+        tracker.record_message(100);
+        assert_eq!(tracker.today().messages, 0);
+
+        tracker.set_limits(UsageQuota {
+            messages_per_day: Some(2),
+            ..UsageQuota::default()
+        });
+        for _ in 0..2 {
+            tracker.check().unwrap();
+            tracker.record_message(10);
+        }
+        assert_eq!(tracker.check(), Err(QuotaExceeded::Messages { limit: 2 }));
+
+        // A restart doesn't reset the day's counters
+        let reloaded = QuotaTracker::load(path);
+        assert_eq!(reloaded.today().messages, 2);
+    }
+
+    #[test]
+    fn test_cost_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let tracker = QuotaTracker::load(dir.path().join("quota_usage.json"));
+        tracker.set_limits(UsageQuota {
+            cost_per_day: Some(0.01),
+            ..UsageQuota::default()
+        });
+        tracker.record_message(1_000);
+        tracker.check().unwrap();
+        // 1000 output tokens at $15 per million: $0.015
+        tracker.record_response(1_000);
+        assert!(matches!(tracker.check(), Err(QuotaExceeded::Cost { .. })));
+    }
+
+    #[test]
+    fn test_extra_requests_count_toward_cost_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let tracker = QuotaTracker::load(dir.path().join("quota_usage.json"));
+        tracker.set_limits(UsageQuota {
+            messages_per_day: Some(1),
+            cost_per_day: Some(0.01),
+            ..UsageQuota::default()
+        });
+        // 1000 input tokens at $3 and 500 output tokens at $15 per million
+        tracker.record_request(1_000, 500);
+        let today = tracker.today();
+        assert_eq!(today.messages, 0);
+        assert!((today.cost - 0.0105).abs() < 1e-9);
+        assert!(matches!(tracker.check(), Err(QuotaExceeded::Cost { .. })));
+    }
+
+    #[test]
+    fn test_pin() {
+        let mut quota = UsageQuota::default();
+        assert!(quota.check_pin("anything"));
+        quota.set_pin(Some("1234"));
+        assert!(quota.is_locked());
+        assert!(quota.check_pin(" 1234 "));
+        assert!(!quota.check_pin("4321"));
+        assert!(!serde_json::to_string(&quota).unwrap().contains("1234"));
+
+        // Salted: the same PIN hashes differently each time
+        let mut other = UsageQuota::default();
+        other.set_pin(Some("1234"));
+        assert_ne!(quota.pin_hash, other.pin_hash);
+    }
+}
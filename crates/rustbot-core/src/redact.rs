@@ -0,0 +1,189 @@
+// Secret redaction for log output and exported traces
+//
+// Design Decision: One process-wide scrubber applied where text leaves the
+// process (the tracing writer, the event log), not at each log call
+//
+// Rationale: Debug logging prints whole requests, MCP plugin configs and
+// provider error bodies, and the event log records tool arguments and
+// results. Any of these can carry an API key, a bearer token or a value
+// resolved from 1Password. Guarding every `tracing!` call doesn't scale, so
+// text is scrubbed on its way to stderr and to disk instead:
+// - Known key shapes (OpenRouter, OpenAI, Anthropic, GitHub, Slack tokens)
+// - `Authorization`/`x-api-key` header values and `Bearer` tokens
+// - Exact values registered at runtime: secrets resolved from references
+//   (`op://`, `vault://`, ...) and the values of secret-looking environment
+//   variables (`*_API_KEY`, `*_TOKEN`, `*_SECRET`, `*_PASSWORD`)
+//
+// Trade-offs:
+// - Secrets of an unknown shape that were never registered still get
+//   through; registering them is the fix
+// - Registered values shorter than `MIN_SECRET_LEN` are ignored, so a short
+//   password doesn't blank out ordinary words
+// - Every line written pays for a few regex scans (only noticeable with
+//   trace-level logging)
+
+use regex::Regex;
+use std::borrow::Cow;
+use std::io::Write;
+use std::sync::{OnceLock, RwLock};
+
+/// What a redacted secret is replaced with
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Registered values shorter than this are not redacted
+const MIN_SECRET_LEN: usize = 8;
+
+/// Environment variable name endings that mark the value as a secret
+const SECRET_ENV_SUFFIXES: &[&str] = &["_API_KEY", "_TOKEN", "_SECRET", "_PASSWORD"];
+
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Key shapes, and header values where the secret follows a name
+fn patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            // sk-or-v1-…, sk-ant-…, sk-proj-… and plain OpenAI keys
+            (r"\bsk-[A-Za-z0-9_-]{16,}", REDACTED),
+            (r"\b(?:ghp|gho|ghs|github_pat)_[A-Za-z0-9_]{20,}", REDACTED),
+            (r"\bxox[abprs]-[A-Za-z0-9-]{10,}", REDACTED),
+            (r"(?i)\b(bearer)\s+[A-Za-z0-9._~+/=-]{8,}", "$1 [REDACTED]"),
+            (
+                r#"(?i)\b(authorization|x-api-key|api[_-]?key)("?\s*[:=]\s*"?)[^\s",}]{8,}"#,
+                "$1$2[REDACTED]",
+            ),
+        ]
+        .into_iter()
+        .map(|(pattern, replacement)| {
+            (
+                Regex::new(pattern).expect("redaction pattern is valid"),
+                replacement,
+            )
+        })
+        .collect()
+    })
+}
+
+/// Redact this exact value from now on (e.g. a secret resolved from `op://`)
+pub fn register_secret(value: &str) {
+    let value = value.trim();
+    if value.len() < MIN_SECRET_LEN {
+        return;
+    }
+    if let Ok(mut secrets) = SECRETS.write() {
+        if !secrets.iter().any(|known| known == value) {
+            secrets.push(value.to_string());
+            // Longest first, so a secret containing another is replaced whole
+            secrets.sort_by_key(|known| std::cmp::Reverse(known.len()));
+        }
+    }
+}
+
+/// Register the values of secret-looking environment variables
+///
+/// Call once at startup, after `.env` files are loaded.
+pub fn register_env_secrets() {
+    for (name, value) in std::env::vars() {
+        let upper = name.to_ascii_uppercase();
+        if SECRET_ENV_SUFFIXES
+            .iter()
+            .any(|suffix| upper.ends_with(suffix))
+            && !crate::services::is_secret_reference(&value)
+        {
+            register_secret(&value);
+        }
+    }
+}
+
+/// Text with every known secret replaced by `REDACTED`
+///
+/// Borrows the input when there was nothing to redact.
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let mut result = Cow::Borrowed(text);
+    if let Ok(secrets) = SECRETS.read() {
+        for secret in secrets.iter() {
+            if result.contains(secret.as_str()) {
+                result = Cow::Owned(result.replace(secret.as_str(), REDACTED));
+            }
+        }
+    }
+    for (pattern, replacement) in patterns() {
+        let replaced = match pattern.replace_all(&result, *replacement) {
+            Cow::Owned(replaced) => Some(replaced),
+            Cow::Borrowed(_) => None,
+        };
+        if let Some(replaced) = replaced {
+            result = Cow::Owned(replaced);
+        }
+    }
+    result
+}
+
+/// Writer that redacts everything passing through it
+///
+/// Wraps the log output, e.g. with tracing-subscriber:
+/// `.with_writer(|| RedactingWriter::new(std::io::stderr()))`. The fmt layer
+/// writes each event in one call, so secrets aren't split across writes.
+pub struct RedactingWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> RedactingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.inner.write_all(redact(&text).as_bytes())?;
+        // All of `buf` was consumed, even if fewer bytes went out
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_key_shapes_and_headers() {
+        let text = redact("key sk-or-v1-0123456789abcdef0123 sent");
+        assert_eq!(text, "key [REDACTED] sent");
+
+        let text = redact("Authorization: Bearer abc.def-ghi_jkl");
+        assert!(!text.contains("abc.def"));
+        assert!(text.starts_with("Authorization: "));
+
+        let text = redact(r#"{"x-api-key": "anthropic-secret-value"}"#);
+        assert_eq!(text, r#"{"x-api-key": "[REDACTED]"}"#);
+
+        // Nothing to redact: no copy
+        assert!(matches!(redact("plain log line"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_registered_secrets() {
+        register_secret("resolved-from-1password");
+        register_secret("short");
+        let text = redact("token=resolved-from-1password, pin short");
+        assert_eq!(text, "token=[REDACTED], pin short");
+    }
+
+    #[test]
+    fn test_redacting_writer() {
+        let mut writer = RedactingWriter::new(Vec::new());
+        writer
+            .write_all(b"request with sk-ant-REDACTED\n")
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(writer.inner).unwrap(),
+            "request with [REDACTED]\n"
+        );
+    }
+}
@@ -0,0 +1,147 @@
+// Context inspector - what the next request to the model will contain
+//
+// Design Decision: Build the preview from the same pieces as the real request
+//
+// Rationale: When an answer goes wrong it helps to see exactly what the model
+// was given. `RustbotApi::preview_request_in` assembles the preview from the
+// agent's system message sections, the session history as trimmed to the
+// history limit, and the tool list, using the same calls as `send_message`,
+// so the inspector can't drift from what is actually sent.
+//
+// `send_message_in` uses the same preview as a pre-flight check: a request
+// that won't fit the model's context window (less `RESPONSE_RESERVE` for the
+// answer) is trimmed before it's sent rather than rejected by the provider.
+//
+// Trade-offs:
+// - Token counts use the same tokenizer as the context meter (o200k_base),
+//   which only approximates other providers' tokenizers
+// - Tool definitions are counted by the size of their JSON schema
+// - Attached images are not counted
+
+use crate::agent::ToolDefinition;
+use crate::llm::Message as LlmMessage;
+use crate::tokenizer;
+
+/// Tokens of the context window left free for the answer
+pub const RESPONSE_RESERVE: u32 = 4_096;
+
+/// The request the next message in a session will produce, minus the message
+#[derive(Debug, Clone)]
+pub struct RequestPreview {
+    pub session_id: String,
+    pub agent_id: String,
+
+    /// Model the agent's requests go to
+    pub model: String,
+
+    /// Parts of the system message as (title, text), in order
+    pub system: Vec<(&'static str, String)>,
+
+    /// Conversation history sent before the new message, after a system
+    /// message with the pinned messages if the session has any
+    pub history: Vec<LlmMessage>,
+
+    /// Most messages kept in the history; older ones are dropped
+    pub max_history: usize,
+
+    /// Tool definitions offered to the model (empty for specialist agents)
+    pub tools: Vec<ToolDefinition>,
+}
+
+impl RequestPreview {
+    pub fn system_tokens(&self) -> u32 {
+        self.system
+            .iter()
+            .map(|(_, text)| estimate_tokens(text))
+            .sum()
+    }
+
+    pub fn history_tokens(&self) -> u32 {
+        self.history.iter().map(message_tokens).sum()
+    }
+
+    pub fn tools_tokens(&self) -> u32 {
+        self.tools.iter().map(tool_tokens).sum()
+    }
+
+    /// Estimated tokens of the whole request except the new message
+    pub fn total_tokens(&self) -> u32 {
+        self.system_tokens() + self.history_tokens() + self.tools_tokens()
+    }
+
+    /// Tokens the request may take: the model's context window less
+    /// `RESPONSE_RESERVE`
+    pub fn context_budget(&self) -> u32 {
+        tokenizer::context_window(&self.model).saturating_sub(RESPONSE_RESERVE)
+    }
+}
+
+/// Token count of a text, as in the context meter
+pub fn estimate_tokens(text: &str) -> u32 {
+    tokenizer::count_tokens(text) as u32
+}
+
+/// Estimated tokens of a message, including the tool calls it carries
+pub fn message_tokens(message: &LlmMessage) -> u32 {
+    let tool_calls = message
+        .tool_calls
+        .as_ref()
+        .map(|calls| estimate_tokens(&serde_json::to_string(calls).unwrap_or_default()))
+        .unwrap_or(0);
+    estimate_tokens(&message.content) + tool_calls
+}
+
+/// Estimated tokens of a tool definition, from its JSON form
+pub fn tool_tokens(tool: &ToolDefinition) -> u32 {
+    estimate_tokens(&serde_json::to_string(tool).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ToolCall;
+
+    #[test]
+    fn test_token_totals() {
+        let preview = RequestPreview {
+            session_id: "default".to_string(),
+            agent_id: "assistant".to_string(),
+            model: "test/model".to_string(),
+            system: vec![(
+                "System instructions",
+                "You are a helpful assistant.".to_string(),
+            )],
+            history: vec![
+                LlmMessage::new("user", "hello"),
+                LlmMessage::new("assistant", ""),
+            ],
+            max_history: 20,
+            tools: Vec::new(),
+        };
+
+        assert!(preview.system_tokens() > 0);
+        assert_eq!(preview.history_tokens(), estimate_tokens("hello"));
+        assert_eq!(preview.tools_tokens(), 0);
+        assert_eq!(
+            preview.total_tokens(),
+            preview.system_tokens() + preview.history_tokens()
+        );
+        assert_eq!(
+            preview.context_budget(),
+            tokenizer::DEFAULT_CONTEXT_WINDOW - RESPONSE_RESERVE
+        );
+    }
+
+    #[test]
+    fn test_message_tokens_count_tool_calls() {
+        let plain = LlmMessage::new("assistant", "Looking it up");
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "web_search".to_string(),
+            arguments: serde_json::json!({ "query": "rust" }),
+        };
+        let with_call = LlmMessage::with_tool_calls("Looking it up".to_string(), vec![call]);
+
+        assert!(message_tokens(&with_call) > message_tokens(&plain));
+    }
+}
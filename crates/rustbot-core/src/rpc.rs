@@ -0,0 +1,833 @@
+// JSON-RPC 2.0 control protocol for alternative frontends (`rustbot rpc`)
+//
+// Design Decision: JSON-RPC 2.0, one message per line, over stdio or a socket
+//
+// Rationale: The IPC socket is a handful of ad-hoc commands for scripts and
+// the REST API is request/response only. A TUI, web frontend or editor plugin
+// needs the whole RustbotApi surface, streamed replies and status updates
+// from one long-lived backend process. JSON-RPC 2.0 has client libraries in
+// every language (and is what editors already speak for LSP), so a frontend
+// spawns `rustbot rpc` and talks to its stdin/stdout, or connects to
+// `rustbot rpc --socket PATH`.
+//
+// Protocol (version `PROTOCOL_VERSION`; new methods and fields may be added,
+// existing ones keep their meaning):
+//     → {"jsonrpc": "2.0", "id": 1, "method": "initialize"}
+//     ← {"jsonrpc": "2.0", "id": 1, "result": {"protocol_version": "1.2", …}}
+//     → {"jsonrpc": "2.0", "id": 2, "method": "chat.send",
+//        "params": {"message": "Hi", "stream": true}}
+//     ← {"jsonrpc": "2.0", "method": "chat.chunk", "params": {"id": 2, "text": "Hel"}}
+//     ← {"jsonrpc": "2.0", "method": "chat.chunk", "params": {"id": 2, "text": "lo"}}
+//     ← {"jsonrpc": "2.0", "id": 2, "result": {"agent": "assistant", "response": "Hello"}}
+//
+// Methods:
+//     initialize                         server info and method list
+//     agents.list                        {agents: [{id, active}], active}
+//     agents.switch    {agent_id}        {active}
+//     agents.status    {agent_id?}       {agent_id, status}
+//     chat.send        {message, agent?, stream?}   {agent, response}
+//     history.get                        {messages}
+//     history.clear                      null
+//     history.restore  {messages}        {count}
+//     history.export   {format}          {format, content}  (markdown|html|json)
+//     tools.list                         {tools}
+//     tools.metrics                      {tools}
+//     editor.context   {path?, language?, root?, content?, selection?}   null
+//     editor.ask       {message, agent?, stream?, context?}
+//                      {agent, response, patches, replacement?}  (see `editor`)
+//     events.subscribe / events.unsubscribe   toggles `agent.status` notifications
+//     shutdown                           null, then the connection closes
+//
+// Notifications sent by the server:
+//     chat.chunk       {id, text}        streamed fragment of request `id`
+//     chat.tool        {id, call_id, name, arguments}           tool call started
+//                      {id, call_id, name, output|error, duration_ms}   finished
+//     chat.usage       {id, model, input_tokens, output_tokens}  estimated usage
+//     agent.status     {agent_id, status}
+//
+// Errors use the standard JSON-RPC codes plus `REQUEST_FAILED` (the agent or
+// provider failed) and `NOT_FOUND` (unknown agent).
+//
+// Trade-offs:
+// - Requests on one connection run in order; a long `chat.send` delays the
+//   next request (notifications still flow)
+// - All connections share one conversation, like the REST API
+
+use crate::api::{RustbotApi, ToolMetrics};
+use crate::chat_stream::StreamItem;
+use crate::conversation_export::ConversationFormat;
+use crate::editor::EditorContext;
+use crate::events::{AgentStatus, EventBus, EventKind, EventSubscriber};
+use crate::llm::Message as LlmMessage;
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, Mutex};
+
+/// Protocol version reported by `initialize`
+pub const PROTOCOL_VERSION: &str = "1.2";
+
+/// Invalid JSON
+pub const PARSE_ERROR: i64 = -32700;
+
+/// Not a valid JSON-RPC request object
+pub const INVALID_REQUEST: i64 = -32600;
+
+/// Unknown method
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Missing or malformed params
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// The agent or LLM provider failed
+pub const REQUEST_FAILED: i64 = -32000;
+
+/// Unknown agent
+pub const NOT_FOUND: i64 = -32001;
+
+/// Methods understood by the server (reported by `initialize`)
+pub const METHODS: &[&str] = &[
+    "initialize",
+    "agents.list",
+    "agents.switch",
+    "agents.status",
+    "chat.send",
+    "history.get",
+    "history.clear",
+    "history.restore",
+    "history.export",
+    "tools.list",
+    "tools.metrics",
+    "editor.context",
+    "editor.ask",
+    "events.subscribe",
+    "events.unsubscribe",
+    "shutdown",
+];
+
+/// Notifications the server may send
+pub const NOTIFICATIONS: &[&str] = &["chat.chunk", "chat.tool", "chat.usage", "agent.status"];
+
+/// An incoming request or notification (no `id`)
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// JSON-RPC error object
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl ToString) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SendParams {
+    message: String,
+    #[serde(default)]
+    agent: Option<String>,
+    #[serde(default)]
+    stream: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct EditorAskParams {
+    message: String,
+    #[serde(default)]
+    agent: Option<String>,
+    #[serde(default)]
+    stream: bool,
+    /// Overrides the context set with `editor.context` for this request
+    #[serde(default)]
+    context: Option<EditorContext>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AgentParams {
+    agent_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusParams {
+    #[serde(default)]
+    agent_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RestoreParams {
+    messages: Vec<LlmMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportParams {
+    format: String,
+}
+
+/// Serve one client until it disconnects or sends `shutdown`
+///
+/// Generic over the stream type: stdio (`tokio::io::join(stdin, stdout)`),
+/// Unix sockets and in-memory pipes in tests.
+///
+/// # Errors
+/// Reading from or writing to the stream fails
+pub async fn serve_connection<S>(
+    stream: S,
+    api: Arc<Mutex<RustbotApi>>,
+    event_bus: Arc<EventBus>,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (reader, writer) = tokio::io::split(stream);
+    let (out_tx, out_rx) = mpsc::unbounded_channel();
+    let writer_task = tokio::spawn(write_lines(writer, out_rx));
+
+    let subscribed = Arc::new(AtomicBool::new(false));
+    let events_task = tokio::spawn(forward_events(
+        event_bus.subscribe_named("rpc"),
+        Arc::clone(&subscribed),
+        out_tx.clone(),
+    ));
+
+    let mut connection = Connection {
+        api,
+        out: out_tx,
+        subscribed,
+        editor: EditorContext::default(),
+    };
+    let mut lines = BufReader::new(reader).lines();
+    let result = async {
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (reply, shutdown) = connection.handle_line(&line).await;
+            if let Some(reply) = reply {
+                connection.send(reply);
+            }
+            if shutdown {
+                tracing::info!("RPC client requested shutdown");
+                break;
+            }
+        }
+        Ok::<_, anyhow::Error>(())
+    }
+    .await;
+
+    // The writer finishes once every sender is gone
+    events_task.abort();
+    let _ = events_task.await;
+    drop(connection);
+    writer_task.await??;
+    result
+}
+
+/// Accept connections on a Unix socket until Ctrl-C
+///
+/// A stale socket file from a previous run is removed before binding.
+///
+/// # Errors
+/// The socket cannot be bound
+#[cfg(unix)]
+pub async fn serve_unix(
+    path: &std::path::Path,
+    api: Arc<Mutex<RustbotApi>>,
+    event_bus: Arc<EventBus>,
+) -> Result<()> {
+    use anyhow::Context;
+    use std::os::unix::fs::PermissionsExt;
+
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("Failed to bind {}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    tracing::info!("🔌 JSON-RPC listening on {}", path.display());
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _addr) = accepted?;
+                let api = Arc::clone(&api);
+                let event_bus = Arc::clone(&event_bus);
+                tokio::spawn(async move {
+                    if let Err(e) = serve_connection(stream, api, event_bus).await {
+                        tracing::warn!("RPC connection error: {}", e);
+                    }
+                });
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    let _ = std::fs::remove_file(path);
+    Ok(())
+}
+
+/// Unix sockets are not available on this platform (use stdio)
+#[cfg(not(unix))]
+pub async fn serve_unix(
+    _path: &std::path::Path,
+    _api: Arc<Mutex<RustbotApi>>,
+    _event_bus: Arc<EventBus>,
+) -> Result<()> {
+    anyhow::bail!("--socket is only supported on Unix platforms; use stdio instead")
+}
+
+/// Per-connection state
+struct Connection {
+    api: Arc<Mutex<RustbotApi>>,
+    out: mpsc::UnboundedSender<String>,
+    subscribed: Arc<AtomicBool>,
+
+    /// Set by `editor.context`, used by `editor.ask`
+    editor: EditorContext,
+}
+
+impl Connection {
+    /// Queue a message for the client
+    fn send(&self, message: Value) {
+        let _ = self.out.send(message.to_string());
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// Handle one line (a request, notification or batch)
+    ///
+    /// Returns the reply, if any, and whether the client asked to shut down.
+    async fn handle_line(&mut self, line: &str) -> (Option<Value>, bool) {
+        let value: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(e) => {
+                let error = RpcError::new(PARSE_ERROR, format!("Parse error: {}", e));
+                return (Some(error_response(Value::Null, error)), false);
+            }
+        };
+
+        match value {
+            Value::Array(batch) if batch.is_empty() => {
+                let error = RpcError::new(INVALID_REQUEST, "Empty batch");
+                (Some(error_response(Value::Null, error)), false)
+            }
+            Value::Array(batch) => {
+                let mut replies = Vec::new();
+                let mut shutdown = false;
+                for message in batch {
+                    let (reply, stop) = self.handle_message(message).await;
+                    replies.extend(reply);
+                    shutdown |= stop;
+                }
+                let reply = (!replies.is_empty()).then_some(Value::Array(replies));
+                (reply, shutdown)
+            }
+            message => self.handle_message(message).await,
+        }
+    }
+
+    async fn handle_message(&mut self, message: Value) -> (Option<Value>, bool) {
+        let request = match serde_json::from_value::<Request>(message) {
+            Ok(request) if request.jsonrpc == "2.0" => request,
+            _ => {
+                let error = RpcError::new(INVALID_REQUEST, "Invalid JSON-RPC 2.0 request");
+                return (Some(error_response(Value::Null, error)), false);
+            }
+        };
+
+        tracing::debug!("RPC {} (id: {:?})", request.method, request.id);
+        let shutdown = request.method == "shutdown";
+        let result = self.call(&request).await;
+
+        let reply = request.id.map(|id| match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => error_response(id, error),
+        });
+        (reply, shutdown)
+    }
+
+    /// Run a method and return its result
+    async fn call(&mut self, request: &Request) -> std::result::Result<Value, RpcError> {
+        match request.method.as_str() {
+            "initialize" => Ok(json!({
+                "protocol_version": PROTOCOL_VERSION,
+                "server": { "name": "rustbot", "version": env!("CARGO_PKG_VERSION") },
+                "methods": METHODS,
+                "notifications": NOTIFICATIONS,
+            })),
+            "agents.list" => {
+                let api = self.api.lock().await;
+                let active = api.active_agent().to_string();
+                let mut ids = api.list_agents();
+                ids.sort();
+                let agents: Vec<Value> = ids
+                    .into_iter()
+                    .map(|id| json!({ "active": id == active, "id": id }))
+                    .collect();
+                Ok(json!({ "agents": agents, "active": active }))
+            }
+            "agents.switch" => {
+                let params: AgentParams = params(&request.params)?;
+                let mut api = self.api.lock().await;
+                api.switch_agent(&params.agent_id)
+                    .map_err(|e| RpcError::new(NOT_FOUND, format!("{:#}", e)))?;
+                Ok(json!({ "active": api.active_agent() }))
+            }
+            "agents.status" => {
+                let params: StatusParams = params(&request.params)?;
+                let api = self.api.lock().await;
+                let agent_id = params
+                    .agent_id
+                    .unwrap_or_else(|| api.active_agent().to_string());
+                let status = api.agent_status(&agent_id).ok_or_else(|| {
+                    RpcError::new(NOT_FOUND, format!("Agent '{}' not found", agent_id))
+                })?;
+                Ok(json!({ "agent_id": agent_id, "status": status_json(status) }))
+            }
+            "chat.send" => {
+                let params: SendParams = params(&request.params)?;
+                require_message(&params.message)?;
+                let id = request.id.clone().unwrap_or(Value::Null);
+                let (agent, response) = self
+                    .chat_send(&params.message, params.agent.as_deref(), params.stream, &id)
+                    .await?;
+                Ok(json!({ "agent": agent, "response": response }))
+            }
+            "editor.context" => {
+                // null (or no params) clears the context
+                self.editor = match &request.params {
+                    Value::Null => EditorContext::default(),
+                    other => params(other)?,
+                };
+                Ok(Value::Null)
+            }
+            "editor.ask" => {
+                let params: EditorAskParams = params(&request.params)?;
+                require_message(&params.message)?;
+                let context = params.context.as_ref().unwrap_or(&self.editor);
+                let prompt = context.build_prompt(&params.message);
+                let id = request.id.clone().unwrap_or(Value::Null);
+                let (agent, response) = self
+                    .chat_send(&prompt, params.agent.as_deref(), params.stream, &id)
+                    .await?;
+                let edits = context.extract_edits(&response);
+                Ok(json!({
+                    "agent": agent,
+                    "response": response,
+                    "patches": edits.patches,
+                    "replacement": edits.replacement,
+                }))
+            }
+            "history.get" => {
+                let messages = self.api.lock().await.get_history();
+                Ok(json!({ "messages": messages }))
+            }
+            "history.clear" => {
+                self.api.lock().await.clear_history();
+                Ok(Value::Null)
+            }
+            "history.restore" => {
+                let params: RestoreParams = params(&request.params)?;
+                let mut api = self.api.lock().await;
+                api.restore_history(params.messages);
+                Ok(json!({ "count": api.get_history().len() }))
+            }
+            "history.export" => {
+                let params: ExportParams = params(&request.params)?;
+                let format = parse_format(&params.format).ok_or_else(|| {
+                    RpcError::new(
+                        INVALID_PARAMS,
+                        format!("Unknown format '{}' (markdown, html, json)", params.format),
+                    )
+                })?;
+                let content = self
+                    .api
+                    .lock()
+                    .await
+                    .export_conversation(format)
+                    .map_err(|e| RpcError::new(REQUEST_FAILED, format!("{:#}", e)))?;
+                Ok(json!({ "format": format.extension(), "content": content }))
+            }
+            "tools.list" => {
+                let tools = self.api.lock().await.available_tools().to_vec();
+                Ok(json!({ "tools": tools }))
+            }
+            "tools.metrics" => {
+                let tools: BTreeMap<String, ToolMetrics> = self
+                    .api
+                    .lock()
+                    .await
+                    .tool_metrics()
+                    .iter()
+                    .map(|(name, metrics)| (name.clone(), metrics.clone()))
+                    .collect();
+                Ok(json!({ "tools": tools }))
+            }
+            "events.subscribe" => {
+                self.subscribed.store(true, Ordering::Relaxed);
+                Ok(Value::Null)
+            }
+            "events.unsubscribe" => {
+                self.subscribed.store(false, Ordering::Relaxed);
+                Ok(Value::Null)
+            }
+            "shutdown" => Ok(Value::Null),
+            other => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Method '{}' not found", other),
+            )),
+        }
+    }
+
+    /// Send a message, streaming `chat.chunk`, `chat.tool` and `chat.usage`
+    /// notifications when asked to
+    ///
+    /// Returns the agent that answered and the full response.
+    async fn chat_send(
+        &self,
+        message: &str,
+        agent: Option<&str>,
+        stream: bool,
+        id: &Value,
+    ) -> std::result::Result<(String, String), RpcError> {
+        let mut api = Arc::clone(&self.api).lock_owned().await;
+        if let Some(agent) = agent {
+            api.switch_agent(agent)
+                .map_err(|e| RpcError::new(NOT_FOUND, format!("{:#}", e)))?;
+        }
+        let agent = api.active_agent().to_string();
+
+        let session = api.active_session().to_string();
+        let mut items = RustbotApi::send_message_stream_in(api, &session, message, Vec::new());
+
+        let mut response = String::new();
+        while let Some(item) = items.recv().await {
+            match item {
+                StreamItem::Done(text) => response = text,
+                StreamItem::Error(message) => {
+                    return Err(RpcError::new(REQUEST_FAILED, message));
+                }
+                item if stream => {
+                    if let Some((method, params)) = item_notification(id, &item) {
+                        self.notify(method, params);
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.api
+            .lock()
+            .await
+            .add_assistant_response_in(&session, response.clone())
+            .map_err(|e| RpcError::new(NOT_FOUND, format!("{:#}", e)))?;
+
+        Ok((agent, response))
+    }
+}
+
+fn require_message(message: &str) -> std::result::Result<(), RpcError> {
+    if message.trim().is_empty() {
+        return Err(RpcError::new(INVALID_PARAMS, "message must not be empty"));
+    }
+    Ok(())
+}
+
+/// Deserialize method params (missing params count as `{}`)
+fn params<T: DeserializeOwned>(params: &Value) -> std::result::Result<T, RpcError> {
+    let params = match params {
+        Value::Null => json!({}),
+        other => other.clone(),
+    };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e))
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+fn parse_format(name: &str) -> Option<ConversationFormat> {
+    match name.to_lowercase().as_str() {
+        "markdown" | "md" => Some(ConversationFormat::Markdown),
+        "html" => Some(ConversationFormat::Html),
+        "json" => Some(ConversationFormat::Json),
+        _ => None,
+    }
+}
+
+/// Notification for an item of the typed stream of request `id` (None for
+/// `Done`, whose text is the request's result, and `Error`, which fails it)
+fn item_notification(id: &Value, item: &StreamItem) -> Option<(&'static str, Value)> {
+    match item {
+        StreamItem::TextDelta(text) => Some(("chat.chunk", json!({ "id": id, "text": text }))),
+        StreamItem::ToolCallStarted {
+            id: call_id,
+            name,
+            arguments,
+        } => Some((
+            "chat.tool",
+            json!({ "id": id, "call_id": call_id, "name": name, "arguments": arguments }),
+        )),
+        StreamItem::ToolResult {
+            id: call_id,
+            name,
+            result,
+            duration,
+        } => {
+            let mut params = json!({
+                "id": id,
+                "call_id": call_id,
+                "name": name,
+                "duration_ms": duration.as_millis() as u64,
+            });
+            match result {
+                Ok(output) => params["output"] = json!(output),
+                Err(error) => params["error"] = json!(error),
+            }
+            Some(("chat.tool", params))
+        }
+        StreamItem::UsageReport(usage) => Some((
+            "chat.usage",
+            json!({
+                "id": id,
+                "model": usage.model,
+                "input_tokens": usage.input_tokens,
+                "output_tokens": usage.output_tokens,
+            }),
+        )),
+        StreamItem::Done(_) | StreamItem::Error(_) => None,
+    }
+}
+
+/// Agent status as sent to clients, e.g. {"state": "executing_tool", "tool": "web_search"}
+fn status_json(status: &AgentStatus) -> Value {
+    match status {
+        AgentStatus::Idle => json!({ "state": "idle" }),
+        AgentStatus::Thinking => json!({ "state": "thinking" }),
+        AgentStatus::Responding => json!({ "state": "responding" }),
+        AgentStatus::ExecutingTool(tool) => json!({ "state": "executing_tool", "tool": tool }),
+        AgentStatus::Error(message) => json!({ "state": "error", "message": message }),
+    }
+}
+
+/// Forward agent status changes while the client is subscribed
+async fn forward_events(
+    mut events: EventSubscriber,
+    subscribed: Arc<AtomicBool>,
+    out: mpsc::UnboundedSender<String>,
+) {
+    while let Some(event) = events.recv().await {
+        if !subscribed.load(Ordering::Relaxed) {
+            continue;
+        }
+        if let EventKind::AgentStatusChange { agent_id, status } = event.kind {
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": "agent.status",
+                "params": { "agent_id": agent_id, "status": status_json(&status) },
+            });
+            if out.send(notification.to_string()).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Write queued messages, one per line
+async fn write_lines<W>(mut writer: W, mut lines: mpsc::UnboundedReceiver<String>) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    while let Some(mut line) = lines.recv().await {
+        line.push('\n');
+        writer.write_all(line.as_bytes()).await?;
+        writer.flush().await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::OnceLock;
+    use tokio::runtime::Runtime;
+
+    // Shared runtime owned by the API under test (never dropped inside a test)
+    fn test_runtime() -> Arc<Runtime> {
+        static RUNTIME: OnceLock<Arc<Runtime>> = OnceLock::new();
+        Arc::clone(RUNTIME.get_or_init(|| Arc::new(Runtime::new().unwrap())))
+    }
+
+    async fn roundtrip(input: &str) -> Vec<Value> {
+        let event_bus = Arc::new(EventBus::new());
+        let api = Arc::new(Mutex::new(RustbotApi::new(
+            Arc::clone(&event_bus),
+            test_runtime(),
+            20,
+        )));
+
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let server_task = tokio::spawn(serve_connection(server, api, event_bus));
+
+        let (client_read, mut client_write) = tokio::io::split(client);
+        client_write.write_all(input.as_bytes()).await.unwrap();
+        client_write.shutdown().await.unwrap();
+
+        let mut lines = BufReader::new(client_read).lines();
+        let mut replies = Vec::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            replies.push(serde_json::from_str(&line).unwrap());
+        }
+        server_task.await.unwrap().unwrap();
+        replies
+    }
+
+    #[tokio::test]
+    async fn test_initialize_and_agents() {
+        let replies = roundtrip(concat!(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "initialize"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "agents.list"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "agents.status"}"#,
+            "\n",
+        ))
+        .await;
+
+        assert_eq!(replies[0]["id"], 1);
+        assert_eq!(replies[0]["result"]["protocol_version"], PROTOCOL_VERSION);
+        assert_eq!(replies[1]["result"]["active"], "assistant");
+        // The test API has no agents registered
+        assert_eq!(replies[2]["error"]["code"], NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let replies = roundtrip(concat!(
+            "not json\n",
+            r#"{"id": 1, "method": "initialize"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "nope"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "agents.switch"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "agents.switch","#,
+            r#" "params": {"agent_id": "missing"}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 5, "method": "chat.send", "params": {"message": " "}}"#,
+            "\n",
+        ))
+        .await;
+
+        let codes: Vec<i64> = replies
+            .iter()
+            .map(|r| r["error"]["code"].as_i64().unwrap())
+            .collect();
+        assert_eq!(
+            codes,
+            vec![
+                PARSE_ERROR,
+                INVALID_REQUEST,
+                METHOD_NOT_FOUND,
+                INVALID_PARAMS,
+                NOT_FOUND,
+                INVALID_PARAMS
+            ]
+        );
+        assert_eq!(replies[0]["id"], Value::Null);
+        assert_eq!(replies[4]["id"], 4);
+    }
+
+    #[tokio::test]
+    async fn test_editor_context() {
+        let replies = roundtrip(concat!(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "editor.context","#,
+            r#" "params": {"path": "src/lib.rs","#,
+            r#" "selection": {"text": "x", "start_line": 1, "end_line": 1}}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "editor.context","#,
+            r#" "params": {"selection": 3}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "editor.ask", "params": {"message": ""}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "editor.context"}"#,
+            "\n",
+        ))
+        .await;
+
+        assert_eq!(replies[0]["result"], Value::Null);
+        assert_eq!(replies[1]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(replies[2]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(replies[3]["result"], Value::Null);
+    }
+
+    #[test]
+    fn test_item_notifications() {
+        let id = json!(7);
+        let (method, params) = item_notification(
+            &id,
+            &StreamItem::ToolResult {
+                id: "call_1".to_string(),
+                name: "web_search".to_string(),
+                result: Err("offline".to_string()),
+                duration: std::time::Duration::from_millis(40),
+            },
+        )
+        .unwrap();
+        assert_eq!(method, "chat.tool");
+        assert_eq!(params["id"], 7);
+        assert_eq!(params["error"], "offline");
+        assert_eq!(params["duration_ms"], 40);
+        assert!(params.get("output").is_none());
+
+        let (method, _) = item_notification(&id, &StreamItem::TextDelta("Hi".to_string())).unwrap();
+        assert_eq!(method, "chat.chunk");
+        assert!(item_notification(&id, &StreamItem::Done("Hi".to_string())).is_none());
+        assert!(item_notification(&id, &StreamItem::Error("offline".to_string())).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_notifications_batches_and_shutdown() {
+        let replies = roundtrip(concat!(
+            r#"{"jsonrpc": "2.0", "method": "history.clear"}"#,
+            "\n",
+            r#"[{"jsonrpc": "2.0", "id": 1, "method": "history.get"},"#,
+            r#" {"jsonrpc": "2.0", "method": "events.subscribe"},"#,
+            r#" {"jsonrpc": "2.0", "id": 2, "method": "history.export","#,
+            r#" "params": {"format": "markdown"}}]"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "shutdown"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "initialize"}"#,
+            "\n",
+        ))
+        .await;
+
+        // No reply to the notification, one array for the batch, nothing after shutdown
+        assert_eq!(replies.len(), 2);
+        let batch = replies[0].as_array().unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch[0]["result"]["messages"], json!([]));
+        assert_eq!(batch[1]["result"]["format"], "md");
+        assert_eq!(replies[1]["id"], 3);
+    }
+}
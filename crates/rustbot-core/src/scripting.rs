@@ -0,0 +1,790 @@
+// Embedded Rhai scripting for user automation
+//
+// Design Decision: Rhai scripts loaded from ~/.rustbot/scripts/*.rhai
+//
+// Rationale: Shell hooks (hooks.rs) cover "run a command when X happens", but
+// anything stateful or conditional quickly outgrows a command line. Rhai is a
+// pure-Rust, sandboxed-by-default language with no file, network or process
+// access unless the host registers it, so scripts only see the API surface we
+// expose here.
+//
+// Script API:
+// - send_message(text)                         send a message as if typed (returns bool)
+// - register_tool(name, description, fn)       expose a script function as an agent tool
+// - register_tool(name, description, fn, params)  same, with a map of string parameters
+// - print(text) / debug(value)                 write to the Rustbot log
+//
+// Event hooks (define any of these functions in a script):
+// - on_user_message(text)
+// - on_response(agent_id, text)
+// - on_agent_error(agent_id, message)
+// - on_tool(agent_id, tool_name)
+// - on_plugin_crash(plugin_id, message)
+//
+// Example:
+//     register_tool("word_count", "Count words in a text", "word_count",
+//                   #{ text: "Text to count" });
+//
+//     fn word_count(args) { args.text.split(" ").len().to_string() }
+//
+//     fn on_agent_error(agent, message) {
+//         print(`agent ${agent} failed: ${message}`);
+//     }
+//
+// Safety:
+// - Module imports are disabled; scripts can't load other files
+// - Operation, call depth and collection size limits stop runaway scripts
+// - Events published by scripts are not fed back into script hooks, so a hook
+//   calling send_message can't loop on itself
+//
+// Trade-offs:
+// - Scripts run one at a time on a blocking thread; a slow hook delays the next
+// - Script tools are namespaced "script:{script}:{tool}" like MCP tools
+// - send_message goes through the UI (like IPC show_in_ui), so it has no
+//   effect in headless mode
+//
+// Extension Points:
+// - Expose agent switching and conversation history to scripts
+// - Per-script permissions for future host functions (HTTP, files)
+
+use crate::agent::tools::{FunctionDefinition, FunctionParameters};
+use crate::agent::ToolDefinition;
+use crate::error::{Result, RustbotError};
+use crate::events::{AgentStatus, Event, EventBus, EventKind, McpPluginEvent, PluginHealthStatus};
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+
+/// Event source used for events published by scripts
+pub const SCRIPT_EVENT_SOURCE: &str = "script";
+
+/// Prefix for tool names registered by scripts
+const SCRIPT_TOOL_PREFIX: &str = "script:";
+
+/// Script file extension
+const SCRIPT_EXTENSION: &str = "rhai";
+
+/// Maximum Rhai operations per call before the script is aborted
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Maximum function call depth
+const MAX_CALL_LEVELS: usize = 64;
+
+/// Maximum length of a string value (bytes)
+const MAX_STRING_SIZE: usize = 1024 * 1024;
+
+/// Maximum number of elements in an array or map
+const MAX_COLLECTION_SIZE: usize = 10_000;
+
+/// Hook functions a script may define, with their arity
+const HOOK_FUNCTIONS: &[(&str, usize)] = &[
+    ("on_user_message", 1),
+    ("on_response", 2),
+    ("on_agent_error", 2),
+    ("on_tool", 2),
+    ("on_plugin_crash", 2),
+];
+
+/// Persisted script settings
+///
+/// File Format: JSON
+/// Location: ~/.rustbot/scripts/scripts.json
+///
+/// Example:
+///     { "disabled": ["experimental"] }
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScriptsConfig {
+    /// Script names (file stems) that should not be loaded
+    #[serde(default)]
+    pub disabled: Vec<String>,
+}
+
+impl ScriptsConfig {
+    /// Load settings from a file
+    ///
+    /// # Returns
+    /// Ok(None) if the file doesn't exist (all scripts enabled), Ok(Some) if loaded
+    ///
+    /// # Errors
+    /// - File exists but cannot be read
+    /// - Invalid JSON
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// Write settings to a file, creating the parent directory if needed
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// A tool exposed by a script
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptTool {
+    /// Tool name as registered by the script (without namespace)
+    pub name: String,
+
+    /// Description shown to the model
+    pub description: String,
+
+    /// Script function called with the tool arguments as a map
+    pub function: String,
+
+    /// Parameter names and descriptions (all strings, all required)
+    pub params: Vec<(String, String)>,
+}
+
+/// Status of one script file, for the script manager view
+#[derive(Debug, Clone)]
+pub struct ScriptInfo {
+    /// Script name (file stem)
+    pub name: String,
+
+    /// Path to the .rhai file
+    pub path: PathBuf,
+
+    /// Whether the script is enabled in scripts.json
+    pub enabled: bool,
+
+    /// Compile or load error, or the most recent runtime error
+    pub error: Option<String>,
+
+    /// Hook functions the script defines
+    pub hooks: Vec<String>,
+
+    /// Namespaced names of the tools the script registered
+    pub tools: Vec<String>,
+}
+
+/// A script file with its compiled AST (if it loaded)
+struct LoadedScript {
+    info: ScriptInfo,
+    ast: Option<AST>,
+    tools: Vec<ScriptTool>,
+}
+
+/// Loads user scripts and runs their hooks and tools
+///
+/// Shared as `Arc<ScriptHost>` between the event bus task, the API (for
+/// tool calls) and the script manager view.
+pub struct ScriptHost {
+    dir: PathBuf,
+    engine: Engine,
+    scripts: Mutex<Vec<LoadedScript>>,
+
+    /// Tools registered by the script currently being loaded
+    pending_tools: Arc<Mutex<Vec<ScriptTool>>>,
+}
+
+impl ScriptHost {
+    /// Default scripts directory: ~/.rustbot/scripts
+    pub fn default_dir() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("scripts")
+    }
+
+    /// Create a host for the scripts in `dir`
+    ///
+    /// No scripts are loaded until `reload` is called.
+    ///
+    /// # Arguments
+    /// * `dir` - Directory containing .rhai files and scripts.json
+    /// * `event_bus` - Bus that `send_message` publishes to
+    pub fn new(dir: PathBuf, event_bus: Arc<EventBus>) -> Self {
+        let pending_tools = Arc::new(Mutex::new(Vec::new()));
+        let engine = build_engine(event_bus, Arc::clone(&pending_tools));
+
+        Self {
+            dir,
+            engine,
+            scripts: Mutex::new(Vec::new()),
+            pending_tools,
+        }
+    }
+
+    /// Scripts directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn config_path(&self) -> PathBuf {
+        self.dir.join("scripts.json")
+    }
+
+    /// (Re)load every script in the directory
+    ///
+    /// Creates the directory if it doesn't exist. A script that fails to
+    /// compile or run is kept in the list with its error; the others still load.
+    ///
+    /// # Errors
+    /// - The directory cannot be created or read
+    /// - scripts.json exists but is invalid
+    pub fn reload(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let config = ScriptsConfig::load(&self.config_path())?.unwrap_or_default();
+
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path.extension().and_then(|e| e.to_str()) == Some(SCRIPT_EXTENSION)
+            })
+            .collect();
+        paths.sort();
+
+        let loaded: Vec<LoadedScript> = paths
+            .into_iter()
+            .map(|path| {
+                let name = path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or_default()
+                    .to_string();
+                let enabled = !config.disabled.contains(&name);
+                self.load_script(name, path, enabled)
+            })
+            .collect();
+
+        let tool_count: usize = loaded.iter().map(|s| s.tools.len()).sum();
+        tracing::info!(
+            "📜 Loaded {} scripts ({} tools) from {}",
+            loaded.len(),
+            tool_count,
+            self.dir.display()
+        );
+
+        *self.scripts.lock().unwrap() = loaded;
+        Ok(())
+    }
+
+    /// Compile a script and run its top level (which registers tools)
+    fn load_script(&self, name: String, path: PathBuf, enabled: bool) -> LoadedScript {
+        let mut script = LoadedScript {
+            info: ScriptInfo {
+                name,
+                path,
+                enabled,
+                error: None,
+                hooks: Vec::new(),
+                tools: Vec::new(),
+            },
+            ast: None,
+            tools: Vec::new(),
+        };
+
+        if !enabled {
+            return script;
+        }
+
+        let source = match std::fs::read_to_string(&script.info.path) {
+            Ok(source) => source,
+            Err(e) => {
+                script.info.error = Some(format!("Failed to read script: {}", e));
+                return script;
+            }
+        };
+
+        let mut ast = match self.engine.compile(&source) {
+            Ok(ast) => ast,
+            Err(e) => {
+                tracing::warn!("📜 Script '{}' failed to compile: {}", script.info.name, e);
+                script.info.error = Some(e.to_string());
+                return script;
+            }
+        };
+        ast.set_source(script.info.name.clone());
+
+        self.pending_tools.lock().unwrap().clear();
+        let run_result = self.engine.run_ast(&ast);
+        let tools = std::mem::take(&mut *self.pending_tools.lock().unwrap());
+
+        if let Err(e) = run_result {
+            tracing::warn!("📜 Script '{}' failed to load: {}", script.info.name, e);
+            script.info.error = Some(e.to_string());
+            return script;
+        }
+
+        script.info.hooks = HOOK_FUNCTIONS
+            .iter()
+            .filter(|(hook, arity)| has_function(&ast, hook, *arity))
+            .map(|(hook, _)| hook.to_string())
+            .collect();
+
+        for tool in &tools {
+            if !has_function(&ast, &tool.function, 1) {
+                script.info.error = Some(format!(
+                    "Tool '{}' refers to missing function {}(args)",
+                    tool.name, tool.function
+                ));
+                return script;
+            }
+        }
+
+        script.info.tools = tools
+            .iter()
+            .map(|t| tool_name(&script.info.name, &t.name))
+            .collect();
+        script.tools = tools;
+        script.ast = Some(ast);
+        script
+    }
+
+    /// Status of every script, in file name order
+    pub fn list(&self) -> Vec<ScriptInfo> {
+        self.scripts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|s| s.info.clone())
+            .collect()
+    }
+
+    /// Enable or disable a script, persist the choice and reload
+    ///
+    /// # Errors
+    /// - scripts.json cannot be read or written
+    /// - The reload fails
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> Result<()> {
+        let path = self.config_path();
+        let mut config = ScriptsConfig::load(&path)?.unwrap_or_default();
+        config.disabled.retain(|n| n != name);
+        if !enabled {
+            config.disabled.push(name.to_string());
+        }
+        config.save(&path)?;
+        self.reload()
+    }
+
+    /// Tool definitions for every tool registered by enabled scripts
+    pub fn tool_definitions(&self) -> Vec<ToolDefinition> {
+        let scripts = self.scripts.lock().unwrap();
+        scripts
+            .iter()
+            .filter(|s| s.ast.is_some())
+            .flat_map(|s| {
+                s.tools
+                    .iter()
+                    .map(move |tool| tool_definition(&s.info.name, tool))
+            })
+            .collect()
+    }
+
+    /// Check if a tool name belongs to a script
+    pub fn is_script_tool(tool_name: &str) -> bool {
+        tool_name.starts_with(SCRIPT_TOOL_PREFIX)
+    }
+
+    /// Run a script tool
+    ///
+    /// # Arguments
+    /// * `tool_name` - Namespaced name ("script:{script}:{tool}")
+    /// * `arguments` - JSON-encoded arguments, passed to the script as a map
+    ///
+    /// # Returns
+    /// The function's return value; strings as-is, anything else as JSON
+    ///
+    /// # Errors
+    /// - Unknown tool or script not loaded
+    /// - Arguments are not valid JSON
+    /// - The script function fails or exceeds its limits
+    pub fn call_tool(&self, tool_name: &str, arguments: &str) -> Result<String> {
+        let (script_name, name) = tool_name
+            .strip_prefix(SCRIPT_TOOL_PREFIX)
+            .and_then(|rest| rest.split_once(':'))
+            .ok_or_else(|| {
+                RustbotError::ConfigError(format!("Invalid script tool name '{}'", tool_name))
+            })?;
+
+        let args: serde_json::Value = if arguments.trim().is_empty() {
+            serde_json::Value::Object(Default::default())
+        } else {
+            serde_json::from_str(arguments)?
+        };
+        let args = rhai::serde::to_dynamic(args)
+            .map_err(|e| RustbotError::ApiError(format!("Invalid tool arguments: {}", e)))?;
+
+        let mut scripts = self.scripts.lock().unwrap();
+        let script = scripts
+            .iter_mut()
+            .find(|s| s.info.name == script_name && s.ast.is_some())
+            .ok_or_else(|| {
+                RustbotError::ConfigError(format!("Script '{}' is not loaded", script_name))
+            })?;
+        let function = script
+            .tools
+            .iter()
+            .find(|t| t.name == name)
+            .map(|t| t.function.clone())
+            .ok_or_else(|| {
+                RustbotError::ConfigError(format!("Unknown script tool '{}'", tool_name))
+            })?;
+
+        let result = call_function(&self.engine, script, &function, vec![args])
+            .map_err(RustbotError::ApiError)?;
+
+        if result.is_string() {
+            return Ok(result.into_string().unwrap_or_default());
+        }
+        let value: serde_json::Value = rhai::serde::from_dynamic(&result)
+            .map_err(|e| RustbotError::ApiError(format!("Unsupported tool result: {}", e)))?;
+        Ok(value.to_string())
+    }
+
+    /// Run the matching hook function in every script that defines it
+    ///
+    /// Events published by scripts themselves are ignored.
+    pub fn dispatch(&self, event: &Event) {
+        if event.source == SCRIPT_EVENT_SOURCE {
+            return;
+        }
+        let Some((hook, args)) = hook_call(event) else {
+            return;
+        };
+
+        let mut scripts = self.scripts.lock().unwrap();
+        for script in scripts
+            .iter_mut()
+            .filter(|s| s.info.hooks.iter().any(|h| h == hook))
+        {
+            if let Err(e) = call_function(&self.engine, script, hook, args.clone()) {
+                tracing::warn!("📜 Script '{}' {} failed: {}", script.info.name, hook, e);
+            }
+        }
+    }
+
+    /// Subscribe to the event bus and run script hooks in the background
+    ///
+    /// Hooks run one event at a time on a blocking thread, in event order.
+    pub fn spawn(
+        self: Arc<Self>,
+        event_bus: &EventBus,
+        handle: &tokio::runtime::Handle,
+    ) -> JoinHandle<()> {
+        let mut rx = event_bus.subscribe_named("scripts");
+
+        handle.spawn(async move {
+            tracing::info!("📜 Script host started");
+
+            while let Some(event) = rx.recv().await {
+                let host = Arc::clone(&self);
+                if let Err(e) = tokio::task::spawn_blocking(move || host.dispatch(&event)).await {
+                    tracing::error!("📜 Script hook panicked: {}", e);
+                }
+            }
+
+            tracing::info!("📜 Script host stopped");
+        })
+    }
+}
+
+/// Build the sandboxed engine with the script API registered
+fn build_engine(event_bus: Arc<EventBus>, pending_tools: Arc<Mutex<Vec<ScriptTool>>>) -> Engine {
+    let mut engine = Engine::new();
+
+    engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_COLLECTION_SIZE);
+    engine.set_max_map_size(MAX_COLLECTION_SIZE);
+
+    engine.on_print(|text| tracing::info!("📜 [script] {}", text));
+    engine.on_debug(|text, source, pos| {
+        tracing::debug!("📜 [script {}:{}] {}", source.unwrap_or("?"), pos, text)
+    });
+
+    engine.register_fn("send_message", move |text: &str| -> bool {
+        let event = Event::new(
+            SCRIPT_EVENT_SOURCE.to_string(),
+            "user".to_string(),
+            EventKind::UserMessage(text.to_string()),
+        );
+        event_bus.publish(event).is_ok()
+    });
+
+    let pending = Arc::clone(&pending_tools);
+    engine.register_fn(
+        "register_tool",
+        move |name: &str, description: &str, function: &str| {
+            push_tool(&pending, name, description, function, Map::new())
+        },
+    );
+
+    let pending = pending_tools;
+    engine.register_fn(
+        "register_tool",
+        move |name: &str, description: &str, function: &str, params: Map| {
+            push_tool(&pending, name, description, function, params)
+        },
+    );
+
+    engine
+}
+
+/// Validate and queue a tool registered by the script being loaded
+fn push_tool(
+    pending: &Mutex<Vec<ScriptTool>>,
+    name: &str,
+    description: &str,
+    function: &str,
+    params: Map,
+) -> std::result::Result<(), Box<rhai::EvalAltResult>> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(format!(
+            "Invalid tool name '{}' (use letters, digits, '_' and '-')",
+            name
+        )
+        .into());
+    }
+
+    let mut pending = pending.lock().unwrap();
+    if pending.iter().any(|t| t.name == name) {
+        return Err(format!("Tool '{}' registered twice", name).into());
+    }
+
+    pending.push(ScriptTool {
+        name: name.to_string(),
+        description: description.to_string(),
+        function: function.to_string(),
+        params: params
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    });
+    Ok(())
+}
+
+/// Call a script function, recording any error on the script
+fn call_function(
+    engine: &Engine,
+    script: &mut LoadedScript,
+    function: &str,
+    args: Vec<Dynamic>,
+) -> std::result::Result<Dynamic, String> {
+    let Some(ast) = &script.ast else {
+        return Err(format!("Script '{}' is not loaded", script.info.name));
+    };
+
+    // The top level already ran at load time; don't re-register tools
+    let options = CallFnOptions::new().eval_ast(false);
+    let result = engine
+        .call_fn_with_options::<Dynamic>(options, &mut Scope::new(), ast, function, args)
+        .map_err(|e| e.to_string());
+
+    if let Err(e) = &result {
+        script.info.error = Some(e.clone());
+    }
+    result
+}
+
+fn has_function(ast: &AST, name: &str, arity: usize) -> bool {
+    ast.iter_functions()
+        .any(|f| f.name == name && f.params.len() == arity)
+}
+
+fn tool_name(script: &str, tool: &str) -> String {
+    format!("{}{}:{}", SCRIPT_TOOL_PREFIX, script, tool)
+}
+
+fn tool_definition(script: &str, tool: &ScriptTool) -> ToolDefinition {
+    let properties: serde_json::Map<String, serde_json::Value> = tool
+        .params
+        .iter()
+        .map(|(name, description)| {
+            (
+                name.clone(),
+                serde_json::json!({ "type": "string", "description": description }),
+            )
+        })
+        .collect();
+
+    ToolDefinition {
+        tool_type: "function".to_string(),
+        function: FunctionDefinition {
+            name: tool_name(script, &tool.name),
+            description: tool.description.clone(),
+            parameters: FunctionParameters {
+                param_type: "object".to_string(),
+                properties: serde_json::Value::Object(properties),
+                required: tool.params.iter().map(|(name, _)| name.clone()).collect(),
+            },
+        },
+    }
+}
+
+/// Map a bus event to the hook function it triggers and its arguments
+fn hook_call(event: &Event) -> Option<(&'static str, Vec<Dynamic>)> {
+    let (hook, args): (&'static str, Vec<String>) = match &event.kind {
+        EventKind::UserMessage(text) => ("on_user_message", vec![text.clone()]),
+        EventKind::AgentMessage { agent_id, content } => {
+            ("on_response", vec![agent_id.clone(), content.clone()])
+        }
+        EventKind::AgentStatusChange {
+            agent_id,
+            status: AgentStatus::Error(message),
+        } => ("on_agent_error", vec![agent_id.clone(), message.clone()]),
+        EventKind::AgentStatusChange {
+            agent_id,
+            status: AgentStatus::ExecutingTool(tool),
+        } => ("on_tool", vec![agent_id.clone(), tool.clone()]),
+        EventKind::McpPluginEvent(McpPluginEvent::Error { plugin_id, message }) => {
+            ("on_plugin_crash", vec![plugin_id.clone(), message.clone()])
+        }
+        EventKind::McpPluginEvent(McpPluginEvent::HealthStatus {
+            plugin_id,
+            status: PluginHealthStatus::Dead,
+            ..
+        }) => (
+            "on_plugin_crash",
+            vec![plugin_id.clone(), "dead".to_string()],
+        ),
+        _ => return None,
+    };
+
+    Some((hook, args.into_iter().map(Dynamic::from).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn host_with(scripts: &[(&str, &str)]) -> (TempDir, Arc<EventBus>, ScriptHost) {
+        let dir = TempDir::new().unwrap();
+        for (name, source) in scripts {
+            std::fs::write(dir.path().join(format!("{}.rhai", name)), source).unwrap();
+        }
+        let bus = Arc::new(EventBus::new());
+        let host = ScriptHost::new(dir.path().to_path_buf(), Arc::clone(&bus));
+        host.reload().unwrap();
+        (dir, bus, host)
+    }
+
+    const WORD_COUNT: &str = r#"
+        register_tool("word_count", "Count words", "count", #{ text: "Text to count" });
+        fn count(args) { args.text.split(" ").len() }
+        fn on_user_message(text) { }
+    "#;
+
+    #[test]
+    fn test_load_lists_hooks_and_tools() {
+        let (_dir, _bus, host) = host_with(&[("words", WORD_COUNT)]);
+
+        let scripts = host.list();
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(scripts[0].name, "words");
+        assert!(scripts[0].error.is_none());
+        assert_eq!(scripts[0].hooks, vec!["on_user_message"]);
+        assert_eq!(scripts[0].tools, vec!["script:words:word_count"]);
+
+        let tools = host.tool_definitions();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].function.parameters.required, vec!["text"]);
+    }
+
+    #[test]
+    fn test_call_tool_passes_arguments() {
+        let (_dir, _bus, host) = host_with(&[("words", WORD_COUNT)]);
+
+        let result = host
+            .call_tool("script:words:word_count", r#"{"text": "one two three"}"#)
+            .unwrap();
+        assert_eq!(result, "3");
+
+        assert!(host.call_tool("script:words:missing", "{}").is_err());
+        assert!(host.call_tool("script:other:word_count", "{}").is_err());
+    }
+
+    #[test]
+    fn test_hook_send_message_publishes_without_feedback() {
+        let (_dir, bus, host) = host_with(&[(
+            "echo",
+            r#"fn on_user_message(text) { send_message("echo: " + text); }"#,
+        )]);
+        let mut rx = bus.subscribe();
+
+        host.dispatch(&Event::new(
+            "ui".to_string(),
+            "agent".to_string(),
+            EventKind::UserMessage("hi".to_string()),
+        ));
+
+        let event = rx.try_recv().unwrap();
+        assert_eq!(event.source, SCRIPT_EVENT_SOURCE);
+        assert!(matches!(event.kind, EventKind::UserMessage(ref t) if t == "echo: hi"));
+
+        // The script's own message must not trigger the hook again
+        host.dispatch(&event);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_broken_script_does_not_block_others() {
+        let (_dir, _bus, host) = host_with(&[("broken", "fn oops( {"), ("words", WORD_COUNT)]);
+
+        let scripts = host.list();
+        assert_eq!(scripts.len(), 2);
+        assert!(scripts[0].error.is_some());
+        assert!(scripts[1].error.is_none());
+        assert_eq!(host.tool_definitions().len(), 1);
+    }
+
+    #[test]
+    fn test_runaway_script_is_stopped() {
+        let (_dir, _bus, host) = host_with(&[(
+            "spin",
+            r#"
+                register_tool("spin", "Never returns", "spin");
+                fn spin(args) { loop { } }
+            "#,
+        )]);
+
+        assert!(host.call_tool("script:spin:spin", "{}").is_err());
+        assert!(host.list()[0].error.is_some());
+    }
+
+    #[test]
+    fn test_disable_persists_and_unloads() {
+        let (dir, bus, host) = host_with(&[("words", WORD_COUNT)]);
+
+        host.set_enabled("words", false).unwrap();
+        assert!(!host.list()[0].enabled);
+        assert!(host.tool_definitions().is_empty());
+
+        // A fresh host honours scripts.json
+        let fresh = ScriptHost::new(dir.path().to_path_buf(), bus);
+        fresh.reload().unwrap();
+        assert!(!fresh.list()[0].enabled);
+
+        host.set_enabled("words", true).unwrap();
+        assert_eq!(host.tool_definitions().len(), 1);
+    }
+
+    #[test]
+    fn test_tool_with_missing_function_is_rejected() {
+        let (_dir, _bus, host) = host_with(&[(
+            "bad",
+            r#"register_tool("ghost", "No function", "nowhere");"#,
+        )]);
+
+        assert!(host.list()[0].error.is_some());
+        assert!(host.tool_definitions().is_empty());
+    }
+}
@@ -0,0 +1,368 @@
+// HTTP REST API server (`rustbot serve`)
+//
+// Design Decision: Small axum router over a shared RustbotApi
+//
+// Rationale: The IPC socket only reaches processes on the same machine that
+// can speak newline-delimited JSON. Editors, home automation and web apps
+// expect plain HTTP + JSON, so `rustbot serve` exposes the same RustbotApi
+// operations as REST endpoints. Requests are serialized through one mutex,
+// matching the single-conversation model of the desktop app.
+//
+// Endpoints (all except /v1/health require `Authorization: Bearer <token>`):
+//     GET    /v1/health          {"status": "ok", "version": "…"}
+//     GET    /v1/agents          {"agents": [{"id": "…", "active": true}]}
+//     POST   /v1/messages        {"message": "…", "agent"?: "…", "stream"?: bool}
+//                                → {"agent": "…", "response": "…"}
+//                                → or text/event-stream of `chunk` events + `done`
+//     GET    /v1/history         {"messages": [{"role": "…", "content": "…"}]}
+//     DELETE /v1/history         clears the conversation
+//     GET    /v1/tools/metrics   {"tools": {"name": {"calls": 3, …}}}
+//
+// Security:
+// - Binds to 127.0.0.1 by default; binding elsewhere logs a warning since
+//   traffic is plain HTTP (put a TLS proxy in front for network use)
+// - Token compared in constant time; a random one is generated if none given
+//
+// Trade-offs:
+// - One conversation shared by all clients; `agent` switches it for everyone
+// - Requests queue behind the in-flight message instead of running in parallel
+
+use crate::api::{RustbotApi, ToolMetrics};
+use crate::llm::Message as LlmMessage;
+use anyhow::{Context, Result};
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event as SseEvent, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+
+/// Default listen address (loopback only)
+pub const DEFAULT_BIND: &str = "127.0.0.1:8787";
+
+/// Environment variable holding the bearer token
+pub const TOKEN_ENV_VAR: &str = "RUSTBOT_SERVER_TOKEN";
+
+/// Server settings
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Address to listen on
+    pub bind: SocketAddr,
+
+    /// Bearer token clients must present
+    pub token: String,
+}
+
+/// Shared state for request handlers
+#[derive(Clone)]
+pub struct ServerState {
+    api: Arc<Mutex<RustbotApi>>,
+    token: Arc<str>,
+}
+
+impl ServerState {
+    pub fn new(api: RustbotApi, token: &str) -> Self {
+        Self {
+            api: Arc::new(Mutex::new(api)),
+            token: Arc::from(token),
+        }
+    }
+}
+
+/// Generate a random 256-bit token (hex encoded)
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Build the router (exposed for tests and embedding)
+pub fn router(state: ServerState) -> Router {
+    let protected = Router::new()
+        .route("/v1/agents", get(list_agents))
+        .route("/v1/messages", post(send_message))
+        .route("/v1/history", get(get_history).delete(clear_history))
+        .route("/v1/tools/metrics", get(tool_metrics))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_token));
+
+    Router::new()
+        .route("/v1/health", get(health))
+        .merge(protected)
+        .with_state(state)
+}
+
+/// Serve the REST API until Ctrl-C
+///
+/// # Errors
+/// - Address cannot be bound
+/// - Server fails while running
+pub async fn serve(api: RustbotApi, config: ServerConfig) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(config.bind)
+        .await
+        .with_context(|| format!("Failed to bind {}", config.bind))?;
+
+    if !config.bind.ip().is_loopback() {
+        tracing::warn!(
+            "REST API listening on {} over plain HTTP; use a TLS proxy for network access",
+            config.bind
+        );
+    }
+    tracing::info!("🌐 REST API listening on http://{}", config.bind);
+
+    axum::serve(listener, router(ServerState::new(api, &config.token)))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+            tracing::info!("Shutting down REST API");
+        })
+        .await
+        .context("REST API server failed")
+}
+
+/// JSON error body with an HTTP status
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl ToString) -> Self {
+        Self {
+            status,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(serde_json::json!({ "error": self.message }));
+        (self.status, body).into_response()
+    }
+}
+
+async fn require_token(State(state): State<ServerState>, request: Request, next: Next) -> Response {
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), state.token.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => ApiError::new(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token")
+            .into_response(),
+    }
+}
+
+/// Compare without short-circuiting so response timing doesn't leak the token
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn health() -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+    }))
+}
+
+#[derive(Serialize)]
+struct AgentEntry {
+    id: String,
+    active: bool,
+}
+
+async fn list_agents(State(state): State<ServerState>) -> Json<serde_json::Value> {
+    let api = state.api.lock().await;
+    let active = api.active_agent().to_string();
+    let mut agents: Vec<AgentEntry> = api
+        .list_agents()
+        .into_iter()
+        .map(|id| AgentEntry {
+            active: id == active,
+            id,
+        })
+        .collect();
+    agents.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Json(serde_json::json!({ "agents": agents }))
+}
+
+/// Body of `POST /v1/messages`
+#[derive(Debug, Deserialize)]
+pub struct MessageRequest {
+    /// User message
+    pub message: String,
+
+    /// Switch to this agent first (stays active for later requests)
+    #[serde(default)]
+    pub agent: Option<String>,
+
+    /// Stream the reply as server-sent events
+    #[serde(default)]
+    pub stream: bool,
+}
+
+/// Reply to a non-streaming `POST /v1/messages`
+#[derive(Debug, Serialize)]
+pub struct MessageResponse {
+    pub agent: String,
+    pub response: String,
+}
+
+async fn send_message(
+    State(state): State<ServerState>,
+    Json(request): Json<MessageRequest>,
+) -> std::result::Result<Response, ApiError> {
+    if request.message.trim().is_empty() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "message must not be empty",
+        ));
+    }
+
+    let mut api = Arc::clone(&state.api).lock_owned().await;
+    if let Some(agent) = &request.agent {
+        api.switch_agent(agent)
+            .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, e))?;
+    }
+    let agent = api.active_agent().to_string();
+
+    let mut stream = api
+        .send_message(&request.message)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::BAD_GATEWAY, format!("{:#}", e)))?;
+
+    if !request.stream {
+        let mut response = String::new();
+        while let Some(chunk) = stream.recv().await {
+            response.push_str(&chunk);
+        }
+        api.add_assistant_response(response.clone());
+        return Ok(Json(MessageResponse { agent, response }).into_response());
+    }
+
+    // Keep the API locked until the reply is recorded, even if the client disconnects
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut response = String::new();
+        while let Some(chunk) = stream.recv().await {
+            response.push_str(&chunk);
+            let _ = tx.send(SseEvent::default().event("chunk").data(chunk));
+        }
+        api.add_assistant_response(response);
+        let _ = tx.send(SseEvent::default().event("done").data(agent));
+    });
+
+    let events = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv()
+            .await
+            .map(|event| (Ok::<_, Infallible>(event), rx))
+    });
+    Ok(Sse::new(events).into_response())
+}
+
+async fn get_history(State(state): State<ServerState>) -> Json<serde_json::Value> {
+    let messages: Vec<LlmMessage> = state.api.lock().await.get_history();
+    Json(serde_json::json!({ "messages": messages }))
+}
+
+async fn clear_history(State(state): State<ServerState>) -> StatusCode {
+    state.api.lock().await.clear_history();
+    StatusCode::NO_CONTENT
+}
+
+async fn tool_metrics(State(state): State<ServerState>) -> Json<serde_json::Value> {
+    let tools: BTreeMap<String, ToolMetrics> = state
+        .api
+        .lock()
+        .await
+        .tool_metrics()
+        .iter()
+        .map(|(name, metrics)| (name.clone(), metrics.clone()))
+        .collect();
+    Json(serde_json::json!({ "tools": tools }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventBus;
+    use axum::body::Body;
+    use axum::http::Request as HttpRequest;
+    use tower::ServiceExt;
+
+    fn call(request: HttpRequest<Body>) -> (StatusCode, serde_json::Value) {
+        let runtime = Arc::new(tokio::runtime::Runtime::new().unwrap());
+        let api = RustbotApi::new(Arc::new(EventBus::new()), Arc::clone(&runtime), 20);
+        let app = router(ServerState::new(api, "secret"));
+
+        runtime.block_on(async {
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+            (status, body)
+        })
+    }
+
+    fn get(uri: &str, token: Option<&str>) -> HttpRequest<Body> {
+        let mut builder = HttpRequest::get(uri);
+        if let Some(token) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn test_health_needs_no_token() {
+        let (status, body) = call(get("/v1/health", None));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+    }
+
+    #[test]
+    fn test_endpoints_require_token() {
+        assert_eq!(call(get("/v1/agents", None)).0, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            call(get("/v1/history", Some("wrong"))).0,
+            StatusCode::UNAUTHORIZED
+        );
+
+        let (status, body) = call(get("/v1/history", Some("secret")));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["messages"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_empty_message_is_rejected() {
+        let request = HttpRequest::post("/v1/messages")
+            .header(header::AUTHORIZATION, "Bearer secret")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"message": "  "}"#))
+            .unwrap();
+        assert_eq!(call(request).0, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_generated_tokens_are_unique() {
+        let token = generate_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_token());
+        assert!(constant_time_eq(token.as_bytes(), token.as_bytes()));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+    }
+}
@@ -19,7 +19,7 @@ use super::traits::{AgentService, ConfigService};
 use crate::agent::Agent;
 use crate::error::{Result, RustbotError};
 use crate::events::EventBus;
-use crate::llm::OpenRouterAdapter;
+use crate::llm::{create_adapter, AdapterType, LlmAdapter};
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -92,7 +92,8 @@ impl DefaultAgentService {
 
         for config in agent_configs {
             // Create LLM adapter (currently using OpenRouter for all)
-            let llm_adapter = Arc::new(OpenRouterAdapter::new(api_key.clone()));
+            let llm_adapter: Arc<dyn LlmAdapter> =
+                Arc::from(create_adapter(AdapterType::OpenRouter, api_key.clone()));
 
             // Create agent
             let agent = Arc::new(Agent::new(
@@ -196,6 +197,7 @@ impl AgentService for DefaultAgentService {
 mod tests {
     use super::*;
     use crate::agent::AgentConfig;
+    use crate::pruning::ContextPruning;
     use crate::services::traits::ConfigService;
     use std::path::PathBuf;
 
@@ -248,6 +250,7 @@ mod tests {
             web_search_enabled: false,
             mcp_extensions: vec![],
             mcp_config_file: None,
+            context_pruning: ContextPruning::default(),
         }
     }
 
@@ -0,0 +1,277 @@
+// Background owner of persisted app state: agents, user profile, last session
+//
+// Design Decision: Actor task with message passing
+//
+// Rationale: The GUI used to call `runtime.block_on` for agent configs, the
+// user profile and the restored session: at startup, on every system context
+// build, and on config reload. Each call stalled the egui thread on disk I/O
+// (and decryption with encryption at rest). The actor owns those loads and
+// saves instead. The UI sends a request and polls the returned oneshot
+// receiver each frame, and reads the latest profile from a watch channel
+// without waiting. Profile changes are applied in order by the one task, so
+// a theme change and a text size change can't overwrite each other the way
+// concurrent load-modify-save tasks could.
+//
+// The startup loads (profile, last session, token stats, recovery state,
+// agents) run concurrently; the slowest of them decides when the chat can
+// show.
+//
+// Trade-offs:
+// - Until `startup` answers, the UI only has defaults (the splash screen
+//   covers this)
+// - Load failures are logged and replaced with defaults, as before; the
+//   actor never returns errors to the UI
+
+use super::traits::{
+    ConfigService, ConversationSession, RecoveryState, StorageService, TokenStats, UserProfile,
+};
+use crate::agent::AgentConfig;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot, watch};
+
+/// Change applied to the saved user profile
+pub type ProfileUpdate = Box<dyn FnOnce(&mut UserProfile) + Send>;
+
+/// Everything the UI needs before it can show the chat
+#[derive(Debug, Clone)]
+pub struct StartupState {
+    /// Configured agents, or just the default assistant if none load
+    pub agent_configs: Vec<AgentConfig>,
+
+    /// Conversation open when the app last exited
+    pub last_session: Option<ConversationSession>,
+    pub profile: UserProfile,
+    pub token_stats: TokenStats,
+
+    /// Draft and interrupted answer left by a crash or force-quit
+    pub recovery: Option<RecoveryState>,
+}
+
+enum Request {
+    Startup(oneshot::Sender<StartupState>),
+    LoadAgents(oneshot::Sender<Vec<AgentConfig>>),
+    UpdateProfile(ProfileUpdate),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Handle for talking to the app state actor
+///
+/// Usage:
+///     let app_state = AppStateActor::spawn(storage, config, runtime.handle());
+///     let startup_rx = app_state.startup(); // poll with try_recv each frame
+///     app_state.update_profile(|profile| profile.theme = "dark".to_string());
+pub struct AppStateActor {
+    tx: mpsc::UnboundedSender<Request>,
+    profile: watch::Receiver<UserProfile>,
+}
+
+impl AppStateActor {
+    /// Start the actor task
+    ///
+    /// # Arguments
+    /// * `storage` - Where the profile, sessions and stats are persisted
+    /// * `config` - Source of agent configurations
+    /// * `handle` - Runtime to run the actor on
+    pub fn spawn(
+        storage: Arc<dyn StorageService>,
+        config: Arc<dyn ConfigService>,
+        handle: &tokio::runtime::Handle,
+    ) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (profile_tx, profile) = watch::channel(UserProfile::default());
+        handle.spawn(run(storage, config, profile_tx, rx));
+        Self { tx, profile }
+    }
+
+    /// Load the agents, last session, profile, token stats and recovery state
+    pub fn startup(&self) -> oneshot::Receiver<StartupState> {
+        let (tx, rx) = oneshot::channel();
+        self.send(Request::Startup(tx));
+        rx
+    }
+
+    /// Load the agent configurations again (e.g. after editing them)
+    pub fn load_agents(&self) -> oneshot::Receiver<Vec<AgentConfig>> {
+        let (tx, rx) = oneshot::channel();
+        self.send(Request::LoadAgents(tx));
+        rx
+    }
+
+    /// Change the saved user profile; updates are applied in order
+    pub fn update_profile(&self, update: impl FnOnce(&mut UserProfile) + Send + 'static) {
+        self.send(Request::UpdateProfile(Box::new(update)));
+    }
+
+    /// Answers once every request sent before it has been handled (e.g. to
+    /// wait for queued profile saves before exiting)
+    pub fn flush(&self) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        self.send(Request::Flush(tx));
+        rx
+    }
+
+    /// Latest known profile (the default until `startup` has loaded it)
+    pub fn profile(&self) -> UserProfile {
+        self.profile.borrow().clone()
+    }
+
+    fn send(&self, request: Request) {
+        if self.tx.send(request).is_err() {
+            tracing::warn!("App state actor stopped; request dropped");
+        }
+    }
+}
+
+async fn run(
+    storage: Arc<dyn StorageService>,
+    config: Arc<dyn ConfigService>,
+    profile_tx: watch::Sender<UserProfile>,
+    mut rx: mpsc::UnboundedReceiver<Request>,
+) {
+    while let Some(request) = rx.recv().await {
+        match request {
+            Request::Startup(reply) => {
+                let (profile, last_session, token_stats, recovery, agent_configs) = tokio::join!(
+                    storage.load_user_profile(),
+                    storage.load_last_session(),
+                    storage.load_token_stats(),
+                    storage.load_recovery(),
+                    load_agents(config.as_ref()),
+                );
+                let profile = profile.unwrap_or_else(|e| {
+                    tracing::warn!("Failed to load user profile: {}", e);
+                    UserProfile::default()
+                });
+                profile.apply_process_settings();
+                profile_tx.send_replace(profile.clone());
+
+                let last_session = last_session.unwrap_or_else(|e| {
+                    tracing::warn!("Failed to restore last session: {}", e);
+                    None
+                });
+                let mut token_stats = token_stats.unwrap_or_else(|e| {
+                    tracing::warn!("Failed to load token stats: {}", e);
+                    TokenStats::default()
+                });
+                token_stats
+                    .reset_daily_if_stale(&chrono::Local::now().format("%Y-%m-%d").to_string());
+                let recovery = recovery
+                    .unwrap_or_else(|e| {
+                        tracing::warn!("Failed to load recovery state: {}", e);
+                        None
+                    })
+                    .filter(|state| !state.is_empty());
+
+                let _ = reply.send(StartupState {
+                    agent_configs,
+                    last_session,
+                    profile,
+                    token_stats,
+                    recovery,
+                });
+            }
+            Request::LoadAgents(reply) => {
+                let _ = reply.send(load_agents(config.as_ref()).await);
+            }
+            Request::UpdateProfile(update) => {
+                // Start from the saved profile so fields changed elsewhere
+                // (e.g. the CLI) aren't reverted
+                let mut profile = match storage.load_user_profile().await {
+                    Ok(profile) => profile,
+                    Err(e) => {
+                        tracing::warn!("Failed to load user profile: {}", e);
+                        profile_tx.borrow().clone()
+                    }
+                };
+                update(&mut profile);
+                profile.apply_process_settings();
+                if let Err(e) = storage.save_user_profile(&profile).await {
+                    tracing::error!("Failed to save user profile: {}", e);
+                }
+                profile_tx.send_replace(profile);
+            }
+            Request::Flush(reply) => {
+                let _ = reply.send(());
+            }
+        }
+    }
+}
+
+/// Agent configs, falling back to the default assistant
+async fn load_agents(config: &dyn ConfigService) -> Vec<AgentConfig> {
+    let agent_configs = config.load_agent_configs().await.unwrap_or_else(|e| {
+        tracing::warn!("Failed to load agents from config service: {}", e);
+        Vec::new()
+    });
+    if agent_configs.is_empty() {
+        tracing::info!("No agents loaded, using default assistant");
+        return vec![AgentConfig::default_assistant()];
+    }
+    agent_configs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::traits::{MockConfigService, MockStorageService};
+
+    #[tokio::test]
+    async fn test_startup_falls_back_to_defaults() {
+        let mut storage = MockStorageService::new();
+        storage
+            .expect_load_user_profile()
+            .returning(|| Ok(UserProfile::default()));
+        storage.expect_load_last_session().returning(|| Ok(None));
+        storage
+            .expect_load_token_stats()
+            .returning(|| Ok(TokenStats::default()));
+        storage.expect_load_recovery().returning(|| Ok(None));
+        let mut config = MockConfigService::new();
+        config
+            .expect_load_agent_configs()
+            .returning(|| Ok(Vec::new()));
+
+        let actor = AppStateActor::spawn(
+            Arc::new(storage),
+            Arc::new(config),
+            &tokio::runtime::Handle::current(),
+        );
+        let state = actor.startup().await.unwrap();
+        assert_eq!(state.agent_configs.len(), 1);
+        assert_eq!(state.agent_configs[0].id, "assistant");
+        assert!(state.last_session.is_none());
+        assert!(state.recovery.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_profile_updates_apply_in_order() {
+        let saved = Arc::new(std::sync::Mutex::new(UserProfile::default()));
+        let mut storage = MockStorageService::new();
+        let load = Arc::clone(&saved);
+        storage
+            .expect_load_user_profile()
+            .returning(move || Ok(load.lock().unwrap().clone()));
+        let save = Arc::clone(&saved);
+        storage
+            .expect_save_user_profile()
+            .returning(move |profile| {
+                *save.lock().unwrap() = profile.clone();
+                Ok(())
+            });
+
+        let actor = AppStateActor::spawn(
+            Arc::new(storage),
+            Arc::new(MockConfigService::new()),
+            &tokio::runtime::Handle::current(),
+        );
+        actor.update_profile(|profile| profile.theme = "dark".to_string());
+        actor.update_profile(|profile| profile.font_size = 18.0);
+
+        // Requests are handled in order, so both updates are saved by now
+        actor.flush().await.unwrap();
+        let saved = saved.lock().unwrap().clone();
+        assert_eq!(saved.theme, "dark");
+        assert_eq!(saved.font_size, 18.0);
+        assert_eq!(actor.profile().theme, "dark");
+    }
+}
@@ -0,0 +1,350 @@
+// Encryption at rest for stored application data
+//
+// Design Decision: Encrypting FileSystem decorator under FileStorageService
+//
+// Rationale: Conversations and the user profile can contain sensitive data,
+// and they sit in plain JSON next to the app. Wrapping the FileSystem port
+// (rather than each StorageService method) encrypts every file the storage
+// service writes — sessions, search index, profile, prompts — without
+// touching serialization code, and new storage types are covered for free.
+//
+// Cryptography:
+// - XChaCha20-Poly1305 (AEAD) with a random 192-bit nonce per write
+// - Key derived from a passphrase with Argon2id and a per-directory salt
+// - A verifier blob detects a wrong passphrase up front instead of failing
+//   on the first file read
+//
+// File Format: "RBENC1:" + base64(nonce || ciphertext)
+//
+// Migration: Reads of files without the prefix return the plaintext as-is,
+// so enabling encryption on an existing data directory works; each file is
+// encrypted the next time it is saved.
+//
+// Trade-offs:
+// - Directory listings and file names are not hidden
+// - Forgetting the passphrase means the data is unrecoverable (by design)
+
+use super::traits::FileSystem;
+use crate::error::{Result, RustbotError};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Environment variable holding the storage passphrase (encryption is off when unset)
+pub const PASSPHRASE_ENV_VAR: &str = "RUSTBOT_STORAGE_PASSPHRASE";
+
+/// Prefix marking an encrypted file
+const ENCRYPTED_PREFIX: &str = "RBENC1:";
+
+/// Nonce length for XChaCha20-Poly1305
+const NONCE_LEN: usize = 24;
+
+/// Known plaintext used to verify the passphrase
+const VERIFIER_PLAINTEXT: &str = "rustbot-storage-key";
+
+/// Key parameters file stored (unencrypted) in the data directory
+const KEY_FILE_NAME: &str = "encryption.json";
+
+/// 256-bit storage encryption key
+#[derive(Clone)]
+pub struct EncryptionKey([u8; 32]);
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncryptionKey(<redacted>)")
+    }
+}
+
+impl EncryptionKey {
+    /// Use raw key bytes (e.g. a key kept in the OS keychain)
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Generate a random key
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        Self(bytes)
+    }
+
+    /// Decode a base64-encoded key
+    ///
+    /// # Errors
+    /// - Invalid base64 or wrong length
+    pub fn from_base64(encoded: &str) -> Result<Self> {
+        let bytes = BASE64
+            .decode(encoded.trim())
+            .map_err(|e| RustbotError::ConfigError(format!("Invalid encryption key: {}", e)))?;
+        let bytes: [u8; 32] = bytes.try_into().map_err(|_| {
+            RustbotError::ConfigError("Encryption key must be 32 bytes".to_string())
+        })?;
+        Ok(Self(bytes))
+    }
+
+    /// Encode the key as base64 (for storing in a keychain)
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(self.0)
+    }
+
+    /// Derive a key from a passphrase with Argon2id
+    ///
+    /// # Errors
+    /// - Key derivation failure (e.g. salt too short)
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Result<Self> {
+        let mut key = [0u8; 32];
+        argon2::Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| RustbotError::ConfigError(format!("Key derivation failed: {}", e)))?;
+        Ok(Self(key))
+    }
+
+    /// Derive the key for a data directory, creating its key file on first use
+    ///
+    /// # Errors
+    /// - Key file cannot be read or written
+    /// - Passphrase does not match the one the directory was set up with
+    pub async fn for_storage_dir(
+        fs: &Arc<dyn FileSystem>,
+        base_path: &Path,
+        passphrase: &str,
+    ) -> Result<Self> {
+        let path = base_path.join(KEY_FILE_NAME);
+
+        if fs.exists(&path).await {
+            let content = fs.read_to_string(&path).await?;
+            let key_file: KeyFile = serde_json::from_str(&content)?;
+            let salt = BASE64.decode(&key_file.salt).map_err(|e| {
+                RustbotError::StorageError(format!("Corrupt encryption key file: {}", e))
+            })?;
+
+            let key = Self::from_passphrase(passphrase, &salt)?;
+            match key.decrypt(&key_file.verifier) {
+                Ok(plaintext) if plaintext == VERIFIER_PLAINTEXT => Ok(key),
+                _ => Err(RustbotError::ConfigError(
+                    "Incorrect storage passphrase".to_string(),
+                )),
+            }
+        } else {
+            let mut salt = [0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            let key = Self::from_passphrase(passphrase, &salt)?;
+
+            let key_file = KeyFile {
+                version: 1,
+                kdf: "argon2id".to_string(),
+                salt: BASE64.encode(salt),
+                verifier: key.encrypt(VERIFIER_PLAINTEXT)?,
+            };
+            if !fs.exists(base_path).await {
+                fs.create_dir_all(base_path).await?;
+            }
+            fs.write(&path, &serde_json::to_string_pretty(&key_file)?)
+                .await?;
+
+            tracing::info!("🔐 Storage encryption enabled for {}", base_path.display());
+            Ok(key)
+        }
+    }
+
+    /// Encrypt text into the on-disk format
+    ///
+    /// # Errors
+    /// - Encryption failure
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&self.0));
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| RustbotError::StorageError("Encryption failed".to_string()))?;
+
+        let mut blob = nonce.to_vec();
+        blob.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, BASE64.encode(blob)))
+    }
+
+    /// Decrypt text from the on-disk format
+    ///
+    /// # Errors
+    /// - Not in the encrypted format
+    /// - Wrong key or tampered data (authentication failure)
+    pub fn decrypt(&self, encrypted: &str) -> Result<String> {
+        let encoded = encrypted
+            .trim()
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .ok_or_else(|| RustbotError::StorageError("Data is not encrypted".to_string()))?;
+        let blob = BASE64
+            .decode(encoded)
+            .map_err(|e| RustbotError::StorageError(format!("Corrupt encrypted data: {}", e)))?;
+        if blob.len() < NONCE_LEN {
+            return Err(RustbotError::StorageError(
+                "Corrupt encrypted data: too short".to_string(),
+            ));
+        }
+
+        let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&self.0));
+        let plaintext = cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                RustbotError::StorageError(
+                    "Decryption failed (wrong key or corrupted file)".to_string(),
+                )
+            })?;
+
+        String::from_utf8(plaintext)
+            .map_err(|e| RustbotError::StorageError(format!("Decrypted data is not UTF-8: {}", e)))
+    }
+}
+
+/// Whether file content is in the encrypted format
+pub fn is_encrypted(content: &str) -> bool {
+    content.trim_start().starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Storage passphrase from `PASSPHRASE_ENV_VAR` (None = encryption off)
+pub fn passphrase_from_env() -> Option<String> {
+    std::env::var(PASSPHRASE_ENV_VAR)
+        .ok()
+        .filter(|passphrase| !passphrase.is_empty())
+}
+
+/// Key derivation parameters persisted next to the encrypted data
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyFile {
+    version: u32,
+    kdf: String,
+    salt: String,
+    verifier: String,
+}
+
+/// FileSystem decorator that encrypts file contents on write and decrypts on read
+///
+/// Usage:
+///     let key = EncryptionKey::for_storage_dir(&fs, &base, passphrase).await?;
+///     let storage = FileStorageService::encrypted(fs, base, key);
+pub struct EncryptedFileSystem {
+    inner: Arc<dyn FileSystem>,
+    key: EncryptionKey,
+}
+
+impl EncryptedFileSystem {
+    /// Wrap a filesystem with the given key
+    pub fn new(inner: Arc<dyn FileSystem>, key: EncryptionKey) -> Self {
+        Self { inner, key }
+    }
+}
+
+#[async_trait]
+impl FileSystem for EncryptedFileSystem {
+    async fn read_to_string(&self, path: &Path) -> Result<String> {
+        let content = self.inner.read_to_string(path).await?;
+        if is_encrypted(&content) {
+            self.key.decrypt(&content)
+        } else {
+            // Plaintext from before encryption was enabled
+            Ok(content)
+        }
+    }
+
+    async fn write(&self, path: &Path, content: &str) -> Result<()> {
+        let encrypted = self.key.encrypt(content)?;
+        self.inner.write(path, &encrypted).await
+    }
+
+    async fn exists(&self, path: &Path) -> bool {
+        self.inner.exists(path).await
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> Result<()> {
+        self.inner.create_dir_all(path).await
+    }
+
+    async fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        self.inner.read_dir(path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::RealFileSystem;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_encrypt_roundtrip_and_tamper_detection() {
+        let key = EncryptionKey::generate();
+        let encrypted = key.encrypt("{\"name\": \"Ada\"}").unwrap();
+
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("Ada"));
+        assert_eq!(key.decrypt(&encrypted).unwrap(), "{\"name\": \"Ada\"}");
+
+        // Same plaintext encrypts differently each time (random nonce)
+        assert_ne!(encrypted, key.encrypt("{\"name\": \"Ada\"}").unwrap());
+
+        let other = EncryptionKey::generate();
+        assert!(other.decrypt(&encrypted).is_err());
+
+        let mut tampered = encrypted.clone();
+        tampered.pop();
+        tampered.push(if encrypted.ends_with('A') { 'B' } else { 'A' });
+        assert!(key.decrypt(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_key_base64_roundtrip() {
+        let key = EncryptionKey::generate();
+        let restored = EncryptionKey::from_base64(&key.to_base64()).unwrap();
+        assert_eq!(key.0, restored.0);
+        assert!(EncryptionKey::from_base64("c2hvcnQ=").is_err());
+        assert_eq!(format!("{:?}", key), "EncryptionKey(<redacted>)");
+    }
+
+    #[tokio::test]
+    async fn test_passphrase_verified_against_key_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let fs = Arc::new(RealFileSystem) as Arc<dyn FileSystem>;
+
+        let key = EncryptionKey::for_storage_dir(&fs, temp_dir.path(), "correct horse")
+            .await
+            .unwrap();
+        let again = EncryptionKey::for_storage_dir(&fs, temp_dir.path(), "correct horse")
+            .await
+            .unwrap();
+        assert_eq!(key.0, again.0);
+
+        assert!(
+            EncryptionKey::for_storage_dir(&fs, temp_dir.path(), "wrong")
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_filesystem_reads_legacy_plaintext() {
+        let temp_dir = TempDir::new().unwrap();
+        let inner = Arc::new(RealFileSystem) as Arc<dyn FileSystem>;
+        let fs = EncryptedFileSystem::new(Arc::clone(&inner), EncryptionKey::generate());
+
+        let legacy = temp_dir.path().join("legacy.json");
+        inner.write(&legacy, "{\"plain\": true}").await.unwrap();
+        assert_eq!(
+            fs.read_to_string(&legacy).await.unwrap(),
+            "{\"plain\": true}"
+        );
+
+        let secret = temp_dir.path().join("secret.json");
+        fs.write(&secret, "{\"secret\": 42}").await.unwrap();
+        assert!(is_encrypted(&inner.read_to_string(&secret).await.unwrap()));
+        assert_eq!(
+            fs.read_to_string(&secret).await.unwrap(),
+            "{\"secret\": 42}"
+        );
+    }
+}
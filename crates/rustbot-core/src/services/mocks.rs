@@ -15,6 +15,7 @@
 pub mod test_helpers {
     use super::super::traits::*;
     use crate::agent::AgentConfig;
+    use crate::pruning::ContextPruning;
     use mockall::predicate::*;
     use std::path::PathBuf;
 
@@ -68,6 +69,11 @@ pub mod test_helpers {
     /// - save_token_stats() succeeds
     /// - load_system_prompts() returns default SystemPrompts
     /// - save_system_prompts() succeeds
+    /// - load_last_session() returns None (nothing to restore)
+    /// - save_session(), import_sessions() and clear_last_session() succeed
+    /// - list_sessions() and search_sessions() return no sessions
+    /// - load_recovery() returns None; save_recovery() and clear_recovery() succeed
+    /// - load_analytics() returns empty analytics; save_analytics() succeeds
     pub fn create_mock_storage() -> MockStorageService {
         let mut mock = MockStorageService::new();
 
@@ -83,6 +89,32 @@ pub mod test_helpers {
 
         mock.expect_save_system_prompts().returning(|_| Ok(()));
 
+        // Default: no previous session
+        mock.expect_load_last_session().returning(|| Ok(None));
+
+        mock.expect_save_session().returning(|_| Ok(()));
+
+        mock.expect_import_sessions()
+            .returning(|sessions| Ok(sessions.len()));
+
+        mock.expect_clear_last_session().returning(|| Ok(()));
+
+        mock.expect_list_sessions().returning(|| Ok(vec![]));
+
+        mock.expect_search_sessions().returning(|_| Ok(vec![]));
+
+        // Default: clean exit last time
+        mock.expect_load_recovery().returning(|| Ok(None));
+
+        mock.expect_save_recovery().returning(|_| Ok(()));
+
+        mock.expect_clear_recovery().returning(|| Ok(()));
+
+        mock.expect_load_analytics()
+            .returning(|| Ok(crate::analytics::UsageAnalytics::default()));
+
+        mock.expect_save_analytics().returning(|_| Ok(()));
+
         mock
     }
 
@@ -139,6 +171,7 @@ pub mod test_helpers {
                 web_search_enabled: false,
                 mcp_extensions: vec![],
                 mcp_config_file: None,
+                context_pruning: ContextPruning::default(),
             },
             AgentConfig {
                 id: "agent2".to_string(),
@@ -151,6 +184,7 @@ pub mod test_helpers {
                 web_search_enabled: false,
                 mcp_extensions: vec![],
                 mcp_config_file: None,
+                context_pruning: ContextPruning::default(),
             },
         ];
 
@@ -185,6 +219,7 @@ pub mod test_helpers {
             web_search_enabled: false,
             mcp_extensions: vec![],
             mcp_config_file: None,
+            context_pruning: ContextPruning::default(),
         }
     }
 
@@ -195,6 +230,7 @@ pub mod test_helpers {
             total_output_tokens: output,
             total_cost: cost,
             last_updated: chrono::Utc::now(),
+            ..Default::default()
         }
     }
 
@@ -34,18 +34,32 @@
 // (database, cache, message queue, etc.)
 
 pub mod agents;
+pub mod app_state;
 pub mod config;
+pub mod encryption;
 pub mod filesystem;
 #[cfg(test)]
 pub mod integration_tests;
 #[cfg(test)]
 pub mod mocks;
+pub mod secret_resolver;
+pub mod secrets;
+pub mod session_index;
 pub mod storage;
 pub mod traits;
+pub mod write_behind;
 
 // Re-export commonly used types
 pub use agents::DefaultAgentService;
+pub use app_state::{AppStateActor, StartupState};
 pub use config::FileConfigService;
+pub use encryption::{EncryptedFileSystem, EncryptionKey};
 pub use filesystem::RealFileSystem;
+pub use secret_resolver::{is_secret_reference, resolve_secret, SecretResolver, SecretResolvers};
+pub use secrets::{DefaultSecretStore, EnvFileSecretStore, KeychainSecretStore};
 pub use storage::FileStorageService;
-pub use traits::{AgentService, ConfigService, FileSystem, StorageService};
+pub use traits::{
+    AgentService, ConfigService, ConversationSession, FileSystem, MessageFeedback, Rating,
+    RecoveryState, SecretStore, SessionMessage, SessionSummary, StorageService, TokenStats,
+};
+pub use write_behind::{WriteBehind, WriteQueue};
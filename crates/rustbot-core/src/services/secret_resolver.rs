@@ -0,0 +1,503 @@
+// Secret references in configuration values
+//
+// Design Decision: One `SecretResolver` trait, one registry used everywhere
+//
+// Rationale: API keys and MCP plugin env vars used to be resolved by three
+// copies of the same 1Password code (GUI startup, agent configs, MCP
+// configs), each with its own idea of which syntaxes exist. Teams keep
+// secrets in different places, so every backend is now a resolver behind a
+// common trait and all callers go through `resolve_secret`:
+// - `op://vault/item/field` - 1Password CLI
+// - `${VAR}`, `${VAR:-default}`, `env://VAR` - process environment
+// - `keychain://NAME` - OS keychain (Rustbot's service, like the setup wizard)
+// - `vault://mount/path#field` - HashiCorp Vault CLI (`VAULT_ADDR`/`VAULT_TOKEN`)
+// - `aws-sm://secret-id` or `aws-sm://secret-id#json-key` - AWS Secrets
+//   Manager via the AWS CLI (its usual credential chain and region)
+// Anything else is a plain value and is returned as-is.
+//
+// Values resolved from a secret store are registered with `crate::redact`,
+// so they never show up in logs.
+//
+// Trade-offs:
+// - Vault and AWS go through their CLIs like 1Password does, rather than
+//   through SDKs: no extra dependencies, auth is whatever the user already
+//   set up, and resolution stays synchronous (it runs while configs load)
+// - Resolution blocks; it only happens at startup and when a plugin starts
+
+use super::secrets::KeychainSecretStore;
+use super::traits::SecretStore;
+use crate::error::{Result, RustbotError};
+use std::process::Command;
+use std::sync::{Arc, OnceLock};
+
+/// Resolves one kind of secret reference
+///
+/// Usage:
+///     struct Custom;
+///     impl SecretResolver for Custom {
+///         fn handles(&self, value: &str) -> bool { value.starts_with("custom://") }
+///         fn resolve(&self, reference: &str) -> Result<String> { ... }
+///     }
+///     let mut resolvers = SecretResolvers::system();
+///     resolvers.register(Arc::new(Custom));
+pub trait SecretResolver: Send + Sync {
+    /// Whether `value` is a reference this resolver understands
+    fn handles(&self, value: &str) -> bool;
+
+    /// Resolve a reference accepted by `handles`
+    ///
+    /// # Errors
+    /// - Backend unavailable, not signed in, or the secret doesn't exist
+    fn resolve(&self, reference: &str) -> Result<String>;
+
+    /// Whether resolved values are secrets to keep out of logs
+    fn is_secret(&self) -> bool {
+        true
+    }
+}
+
+/// Ordered set of resolvers; the first one that handles a value resolves it
+pub struct SecretResolvers {
+    resolvers: Vec<Arc<dyn SecretResolver>>,
+}
+
+impl SecretResolvers {
+    /// Create a registry from the given resolvers
+    pub fn new(resolvers: Vec<Arc<dyn SecretResolver>>) -> Self {
+        Self { resolvers }
+    }
+
+    /// Every built-in backend
+    pub fn system() -> Self {
+        Self::new(vec![
+            Arc::new(OnePasswordResolver),
+            Arc::new(EnvResolver),
+            Arc::new(KeychainResolver::default()),
+            Arc::new(VaultResolver),
+            Arc::new(AwsSecretsManagerResolver),
+        ])
+    }
+
+    /// Add a resolver, checked after the existing ones
+    pub fn register(&mut self, resolver: Arc<dyn SecretResolver>) {
+        self.resolvers.push(resolver);
+    }
+
+    /// Whether `value` is a reference rather than a plain value
+    pub fn is_reference(&self, value: &str) -> bool {
+        self.resolvers.iter().any(|r| r.handles(value))
+    }
+
+    /// Resolve a reference, or return a plain value as-is
+    ///
+    /// # Errors
+    /// - The matching resolver failed (see `SecretResolver::resolve`)
+    pub fn resolve(&self, value: &str) -> Result<String> {
+        let Some(resolver) = self.resolvers.iter().find(|r| r.handles(value)) else {
+            return Ok(value.to_string());
+        };
+        let resolved = resolver.resolve(value)?;
+        if resolver.is_secret() {
+            crate::redact::register_secret(&resolved);
+        }
+        Ok(resolved)
+    }
+}
+
+fn system_resolvers() -> &'static SecretResolvers {
+    static RESOLVERS: OnceLock<SecretResolvers> = OnceLock::new();
+    RESOLVERS.get_or_init(SecretResolvers::system)
+}
+
+/// Resolve a configuration value with the built-in resolvers
+///
+/// # Example
+/// ```rust,ignore
+/// let api_key = resolve_secret("op://Private/rustbot/api_key")?;
+/// let token = resolve_secret("vault://secret/rustbot#github_token")?;
+/// let model = resolve_secret("${MODEL:-anthropic/claude-sonnet-4}")?;
+/// let name = resolve_secret("assistant")?; // Returns "assistant"
+/// ```
+///
+/// # Errors
+/// - Referenced secret or environment variable can't be read
+pub fn resolve_secret(value: &str) -> Result<String> {
+    system_resolvers().resolve(value)
+}
+
+/// Whether `value` is a secret reference for one of the built-in resolvers
+pub fn is_secret_reference(value: &str) -> bool {
+    system_resolvers().is_reference(value)
+}
+
+/// A secret manager's command-line tool and how it reports common failures
+struct SecretCli {
+    program: &'static str,
+    name: &'static str,
+    install: &'static str,
+    sign_in: &'static str,
+    signed_out: &'static [&'static str],
+    not_found: &'static [&'static str],
+}
+
+impl SecretCli {
+    /// Run the tool and return its trimmed output
+    fn read(&self, args: &[&str], reference: &str) -> Result<String> {
+        let output = Command::new(self.program)
+            .args(args)
+            .output()
+            .map_err(|e| {
+                config_error(format!(
+                    "Failed to execute {} CLI ({}). Is it installed?\n\
+                     Install: {}\n\
+                     Reference: {}",
+                    self.name, e, self.install, reference
+                ))
+            })?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let stderr = stderr.trim();
+            let message = if self.signed_out.iter().any(|s| stderr.contains(s)) {
+                format!(
+                    "Not signed in to {}. Run: {}\nReference: {}",
+                    self.name, self.sign_in, reference
+                )
+            } else if self.not_found.iter().any(|s| stderr.contains(s)) {
+                format!(
+                    "{} secret not found: {}\nError: {}",
+                    self.name, reference, stderr
+                )
+            } else {
+                format!(
+                    "Failed to read {} secret: {}\nError: {}",
+                    self.name, reference, stderr
+                )
+            };
+            return Err(config_error(message));
+        }
+
+        let secret = String::from_utf8(output.stdout)
+            .map_err(|e| {
+                config_error(format!(
+                    "{} returned invalid UTF-8 for: {}\nError: {}",
+                    self.name, reference, e
+                ))
+            })?
+            .trim()
+            .to_string();
+        non_empty(secret, self.name, reference)
+    }
+}
+
+fn config_error(message: String) -> RustbotError {
+    RustbotError::ConfigError(message)
+}
+
+fn non_empty(secret: String, backend: &str, reference: &str) -> Result<String> {
+    if secret.is_empty() {
+        return Err(config_error(format!(
+            "{} secret is empty: {}",
+            backend, reference
+        )));
+    }
+    Ok(secret)
+}
+
+/// Split `path#field` into the path and the optional field
+fn split_field(reference: &str) -> (&str, Option<&str>) {
+    match reference.split_once('#') {
+        Some((path, field)) if !field.is_empty() => (path, Some(field)),
+        Some((path, _)) => (path, None),
+        None => (reference, None),
+    }
+}
+
+/// 1Password references: `op://vault/item/field`
+///
+/// Requires the 1Password CLI, signed in with `op signin`.
+pub struct OnePasswordResolver;
+
+const ONE_PASSWORD_CLI: SecretCli = SecretCli {
+    program: "op",
+    name: "1Password",
+    install: "brew install 1password-cli",
+    sign_in: "op signin",
+    signed_out: &["not currently signed in", "signed out"],
+    not_found: &["isn't an item", "not found"],
+};
+
+impl SecretResolver for OnePasswordResolver {
+    fn handles(&self, value: &str) -> bool {
+        value.starts_with("op://")
+    }
+
+    fn resolve(&self, reference: &str) -> Result<String> {
+        ONE_PASSWORD_CLI.read(&["read", reference], reference)
+    }
+}
+
+/// Environment references: `${VAR}`, `${VAR:-default}` and `env://VAR`
+///
+/// Values aren't registered for redaction here: a default can be any
+/// ordinary setting (a model name), and secret-looking variables are already
+/// registered at startup by `redact::register_env_secrets`.
+pub struct EnvResolver;
+
+impl SecretResolver for EnvResolver {
+    fn handles(&self, value: &str) -> bool {
+        value.starts_with("env://") || (value.starts_with("${") && value.ends_with('}'))
+    }
+
+    fn resolve(&self, reference: &str) -> Result<String> {
+        let var_expr = match reference.strip_prefix("env://") {
+            Some(name) => name,
+            None => &reference[2..reference.len() - 1],
+        };
+
+        // ${VAR:-default} falls back when the variable is unset or empty
+        if let Some((var_name, default_value)) = var_expr.split_once(":-") {
+            return match std::env::var(var_name) {
+                Ok(value) if !value.is_empty() => Ok(value),
+                _ => Ok(default_value.to_string()),
+            };
+        }
+
+        std::env::var(var_expr)
+            .map_err(|_| config_error(format!("Environment variable not found: {}", var_expr)))
+    }
+
+    fn is_secret(&self) -> bool {
+        false
+    }
+}
+
+/// OS keychain references: `keychain://NAME`
+///
+/// Reads the entry the setup wizard and `DefaultSecretStore` write, so
+/// `keychain://OPENAI_API_KEY` works in agent and MCP configs.
+#[derive(Default)]
+pub struct KeychainResolver {
+    store: KeychainSecretStore,
+}
+
+impl SecretResolver for KeychainResolver {
+    fn handles(&self, value: &str) -> bool {
+        value.starts_with("keychain://")
+    }
+
+    fn resolve(&self, reference: &str) -> Result<String> {
+        let name = &reference["keychain://".len()..];
+        match self.store.get(name)? {
+            Some(secret) => non_empty(secret, "Keychain", reference),
+            None => Err(config_error(format!(
+                "Keychain secret not found: {}",
+                reference
+            ))),
+        }
+    }
+}
+
+/// HashiCorp Vault references: `vault://mount/path#field`
+///
+/// Runs `vault kv get -field=<field> mount/path`, so the server and token come
+/// from `VAULT_ADDR` and `VAULT_TOKEN` (or `vault login`).
+pub struct VaultResolver;
+
+const VAULT_CLI: SecretCli = SecretCli {
+    program: "vault",
+    name: "Vault",
+    install: "https://developer.hashicorp.com/vault/install",
+    sign_in: "vault login (or set VAULT_TOKEN)",
+    signed_out: &["permission denied", "missing client token"],
+    not_found: &["No value found"],
+};
+
+impl SecretResolver for VaultResolver {
+    fn handles(&self, value: &str) -> bool {
+        value.starts_with("vault://")
+    }
+
+    fn resolve(&self, reference: &str) -> Result<String> {
+        let (path, field) = split_field(&reference["vault://".len()..]);
+        let Some(field) = field else {
+            return Err(config_error(format!(
+                "Vault reference needs a field: {}. Use vault://mount/path#field",
+                reference
+            )));
+        };
+        let field_arg = format!("-field={}", field);
+        VAULT_CLI.read(&["kv", "get", &field_arg, path], reference)
+    }
+}
+
+/// AWS Secrets Manager references: `aws-sm://secret-id[#json-key]`
+///
+/// Reads the secret string with the AWS CLI (its usual credential chain and
+/// region). With `#json-key`, the secret is parsed as a JSON object and that
+/// key's value is returned, the way the AWS console stores key/value secrets.
+pub struct AwsSecretsManagerResolver;
+
+const AWS_CLI: SecretCli = SecretCli {
+    program: "aws",
+    name: "AWS Secrets Manager",
+    install: "https://aws.amazon.com/cli/",
+    sign_in: "aws configure (or aws sso login)",
+    signed_out: &[
+        "Unable to locate credentials",
+        "ExpiredToken",
+        "token has expired",
+    ],
+    not_found: &["ResourceNotFoundException"],
+};
+
+impl SecretResolver for AwsSecretsManagerResolver {
+    fn handles(&self, value: &str) -> bool {
+        value.starts_with("aws-sm://")
+    }
+
+    fn resolve(&self, reference: &str) -> Result<String> {
+        let (secret_id, key) = split_field(&reference["aws-sm://".len()..]);
+        let secret = AWS_CLI.read(
+            &[
+                "secretsmanager",
+                "get-secret-value",
+                "--secret-id",
+                secret_id,
+                "--query",
+                "SecretString",
+                "--output",
+                "text",
+            ],
+            reference,
+        )?;
+        match key {
+            Some(key) => json_field(&secret, key, reference),
+            None => Ok(secret),
+        }
+    }
+}
+
+/// One string value from a JSON object secret
+fn json_field(secret: &str, key: &str, reference: &str) -> Result<String> {
+    let value: serde_json::Value = serde_json::from_str(secret).map_err(|_| {
+        config_error(format!(
+            "Secret is not a JSON object, can't read key '{}': {}",
+            key, reference
+        ))
+    })?;
+    match value.get(key).and_then(|v| v.as_str()) {
+        Some(field) => non_empty(field.to_string(), "AWS Secrets Manager", reference),
+        None => Err(config_error(format!(
+            "Key '{}' not found in secret: {}",
+            key, reference
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_plain_values_pass_through() {
+        let resolvers = SecretResolvers::system();
+        assert_eq!(resolvers.resolve("assistant").unwrap(), "assistant");
+        assert_eq!(resolvers.resolve("${UNCLOSED").unwrap(), "${UNCLOSED");
+        assert!(!resolvers.is_reference("sk-or-v1-plain"));
+        for reference in [
+            "op://Private/rustbot/key",
+            "${KEY}",
+            "env://KEY",
+            "keychain://KEY",
+            "vault://secret/app#key",
+            "aws-sm://prod/app#key",
+        ] {
+            assert!(resolvers.is_reference(reference), "{}", reference);
+        }
+    }
+
+    #[test]
+    fn test_env_references() {
+        std::env::set_var("RUSTBOT_RESOLVER_TEST_VAR", "from-env");
+        let resolvers = SecretResolvers::system();
+        assert_eq!(
+            resolvers.resolve("${RUSTBOT_RESOLVER_TEST_VAR}").unwrap(),
+            "from-env"
+        );
+        assert_eq!(
+            resolvers
+                .resolve("env://RUSTBOT_RESOLVER_TEST_VAR")
+                .unwrap(),
+            "from-env"
+        );
+        assert_eq!(
+            resolvers
+                .resolve("${RUSTBOT_RESOLVER_MISSING:-fallback}")
+                .unwrap(),
+            "fallback"
+        );
+        let err = resolvers
+            .resolve("${RUSTBOT_RESOLVER_MISSING}")
+            .unwrap_err();
+        assert!(err.to_string().contains("Environment variable not found"));
+        std::env::remove_var("RUSTBOT_RESOLVER_TEST_VAR");
+    }
+
+    struct CountingResolver(AtomicUsize);
+
+    impl SecretResolver for CountingResolver {
+        fn handles(&self, value: &str) -> bool {
+            value.starts_with("test://")
+        }
+
+        fn resolve(&self, _reference: &str) -> Result<String> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok("custom-resolved-secret".to_string())
+        }
+    }
+
+    #[test]
+    fn test_registered_resolver_is_used_and_redacted() {
+        let custom = Arc::new(CountingResolver(AtomicUsize::new(0)));
+        let mut resolvers = SecretResolvers::new(Vec::new());
+        resolvers.register(custom.clone());
+
+        assert_eq!(
+            resolvers.resolve("test://anything").unwrap(),
+            "custom-resolved-secret"
+        );
+        assert_eq!(custom.0.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            crate::redact::redact("key custom-resolved-secret"),
+            "key [REDACTED]"
+        );
+    }
+
+    #[test]
+    fn test_reference_fields() {
+        assert_eq!(
+            split_field("secret/app#token"),
+            ("secret/app", Some("token"))
+        );
+        assert_eq!(split_field("prod/app#"), ("prod/app", None));
+        assert_eq!(split_field("prod/app"), ("prod/app", None));
+
+        let err = VaultResolver.resolve("vault://secret/app").unwrap_err();
+        assert!(err.to_string().contains("needs a field"));
+    }
+
+    #[test]
+    fn test_json_field() {
+        let secret = r#"{"api_key": "value-from-aws", "empty": ""}"#;
+        let reference = "aws-sm://prod/app#api_key";
+        assert_eq!(
+            json_field(secret, "api_key", reference).unwrap(),
+            "value-from-aws"
+        );
+        assert!(json_field(secret, "missing", reference).is_err());
+        assert!(json_field(secret, "empty", reference).is_err());
+        assert!(json_field("not json", "api_key", reference).is_err());
+    }
+}
@@ -0,0 +1,473 @@
+// Secret storage for API keys
+//
+// Design Decision: OS keychain first, environment/.env.local as fallback
+//
+// Rationale: The setup wizard used to write the OpenRouter key to a plaintext
+// `.env.local` in the working directory, where it is easy to commit or leak.
+// The OS keychain (macOS Keychain, Windows Credential Manager, Secret Service
+// on Linux) encrypts secrets with the user's login and is the expected place
+// for desktop apps to keep credentials. Secret Service needs libdbus, so on
+// Linux it is behind the `secret-service` feature; without it keys go to the
+// kernel keyring, which forgets them at logout.
+//
+// Fallback: Headless Linux boxes and CI often have no keyring daemon, and
+// many users deliberately manage keys through their shell or 1Password
+// references. When the keychain is unavailable or has no entry, secrets are
+// read from the process environment and `.env.local` as before. The setup
+// wizard doesn't use that fallback: it writes with `set_in_keychain` and
+// reports the error instead of leaving a plaintext key in the working
+// directory.
+//
+// Migration: On startup `migrate()` moves plain keys found in `.env.local`
+// into the keychain and removes them from the file. Secret references
+// (`op://`, `vault://`, ...) are not secrets themselves and stay where they
+// are.
+//
+// Trade-offs:
+// - Keychain access is blocking; it only happens at startup and on save
+// - Some platforms prompt the user the first time the keychain is accessed
+
+use super::secret_resolver::is_secret_reference;
+use super::traits::SecretStore;
+use crate::audit::{self, AuditAction};
+use crate::error::{Result, RustbotError};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Name of the OpenRouter API key secret (also its environment variable)
+pub const API_KEY_NAME: &str = "OPENROUTER_API_KEY";
+
+/// Secrets moved from `.env.local` into the keychain on startup: the API key
+/// of every provider the setup wizard offers
+pub const MIGRATED_SECRETS: &[&str] = &[API_KEY_NAME, "OPENAI_API_KEY", "ANTHROPIC_API_KEY"];
+
+/// Keychain service name under which Rustbot secrets are stored
+const KEYCHAIN_SERVICE: &str = "rustbot";
+
+/// Default env file written by earlier versions of the setup wizard
+const ENV_FILE: &str = ".env.local";
+
+/// Secret store backed by the OS keychain
+pub struct KeychainSecretStore {
+    service: String,
+}
+
+impl KeychainSecretStore {
+    /// Create a store using the given keychain service name
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+        }
+    }
+
+    fn entry(&self, name: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(&self.service, name)
+            .map_err(|e| RustbotError::ConfigError(format!("Keychain unavailable: {}", e)))
+    }
+}
+
+impl Default for KeychainSecretStore {
+    fn default() -> Self {
+        Self::new(KEYCHAIN_SERVICE)
+    }
+}
+
+impl SecretStore for KeychainSecretStore {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        match self.entry(name)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(RustbotError::ConfigError(format!(
+                "Failed to read {} from keychain: {}",
+                name, e
+            ))),
+        }
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        self.entry(name)?.set_password(value).map_err(|e| {
+            RustbotError::ConfigError(format!("Failed to store {} in keychain: {}", name, e))
+        })
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        match self.entry(name)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(RustbotError::ConfigError(format!(
+                "Failed to delete {} from keychain: {}",
+                name, e
+            ))),
+        }
+    }
+}
+
+/// Secret store backed by environment variables and a dotenv file
+///
+/// Reads check the process environment first, then the file. Writes only
+/// touch the file.
+pub struct EnvFileSecretStore {
+    path: PathBuf,
+}
+
+impl EnvFileSecretStore {
+    /// Create a store reading and writing the given dotenv file
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Read a secret from the file only (ignoring the process environment)
+    ///
+    /// # Errors
+    /// - File exists but cannot be read
+    pub fn get_from_file(&self, name: &str) -> Result<Option<String>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&self.path)?;
+        Ok(content
+            .lines()
+            .filter_map(parse_env_line)
+            .filter(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+            .next_back())
+    }
+
+    /// Rewrite the file, replacing (or dropping) every line for `name`
+    fn rewrite(&self, name: &str, value: Option<&str>) -> Result<()> {
+        let content = if self.path.exists() {
+            std::fs::read_to_string(&self.path)?
+        } else if value.is_none() {
+            return Ok(());
+        } else {
+            String::new()
+        };
+
+        let mut lines: Vec<String> = content
+            .lines()
+            .filter(|line| !matches!(parse_env_line(line), Some((key, _)) if key == name))
+            .map(str::to_string)
+            .collect();
+        if let Some(value) = value {
+            lines.push(format!("{}={}", name, value));
+        }
+
+        let mut output = lines.join("\n");
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        std::fs::write(&self.path, output)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(0o600))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl SecretStore for EnvFileSecretStore {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        match std::env::var(name) {
+            Ok(value) if !value.is_empty() => Ok(Some(value)),
+            _ => self.get_from_file(name),
+        }
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        self.rewrite(name, Some(value))
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        self.rewrite(name, None)
+    }
+}
+
+/// Parse a `NAME=value` dotenv line (comments and blanks yield `None`)
+fn parse_env_line(line: &str) -> Option<(&str, String)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let (key, value) = line.split_once('=')?;
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value);
+    Some((key.trim(), value.to_string()))
+}
+
+/// Keychain-first secret store with environment/.env.local fallback
+///
+/// Usage:
+///     let secrets = DefaultSecretStore::system();
+///     secrets.migrate(MIGRATED_SECRETS);
+///     let api_key = secrets.get(API_KEY_NAME)?;
+pub struct DefaultSecretStore {
+    keychain: Arc<dyn SecretStore>,
+    env: EnvFileSecretStore,
+}
+
+impl DefaultSecretStore {
+    /// Create a store from a keychain backend and an env file fallback
+    pub fn new(keychain: Arc<dyn SecretStore>, env: EnvFileSecretStore) -> Self {
+        Self { keychain, env }
+    }
+
+    /// OS keychain with `.env.local` in the working directory as fallback
+    pub fn system() -> Self {
+        Self::new(
+            Arc::new(KeychainSecretStore::default()),
+            EnvFileSecretStore::new(ENV_FILE),
+        )
+    }
+
+    /// Store a secret in the keychain only, never in the env file
+    ///
+    /// For callers that would rather fail than write a plaintext copy to
+    /// `.env.local` in the working directory (the setup wizard). An older copy
+    /// in the env file is removed.
+    ///
+    /// # Errors
+    /// - Keychain unavailable or the write failed
+    pub fn set_in_keychain(&self, name: &str, value: &str) -> Result<()> {
+        self.keychain.set(name, value)?;
+        if let Err(e) = self.env.delete(name) {
+            tracing::warn!("Failed to remove {} from env file: {}", name, e);
+        }
+        audit::record(AuditAction::SecretUpdated, format!("{} (keychain)", name));
+        Ok(())
+    }
+
+    /// Move plain secrets from the env file into the keychain
+    ///
+    /// A value in the file replaces any keychain entry, since the user put it
+    /// there most recently. The file entry is only removed once the keychain
+    /// write succeeds, so nothing is lost if the keychain is unavailable.
+    ///
+    /// # Returns
+    /// Number of secrets migrated
+    pub fn migrate(&self, names: &[&str]) -> usize {
+        let mut migrated = 0;
+        for name in names {
+            let value = match self.env.get_from_file(name) {
+                Ok(Some(value)) if !value.is_empty() && !is_secret_reference(&value) => value,
+                Ok(_) => continue,
+                Err(e) => {
+                    tracing::warn!("Failed to read {} from env file: {}", name, e);
+                    continue;
+                }
+            };
+
+            if let Err(e) = self.keychain.set(name, &value) {
+                tracing::warn!("Keeping {} in env file (keychain unavailable): {}", name, e);
+                continue;
+            }
+            match self.env.delete(name) {
+                Ok(()) => {
+                    tracing::info!("🔑 Moved {} from env file to the OS keychain", name);
+                    migrated += 1;
+                }
+                Err(e) => tracing::warn!(
+                    "Stored {} in keychain but could not remove it from env file: {}",
+                    name,
+                    e
+                ),
+            }
+        }
+        migrated
+    }
+}
+
+impl SecretStore for DefaultSecretStore {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        match self.keychain.get(name) {
+            Ok(Some(value)) => return Ok(Some(value)),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Keychain lookup failed, using environment: {}", e),
+        }
+        self.env.get(name)
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        match self.keychain.set(name, value) {
+            Ok(()) => {
+                // Don't leave an older plaintext copy behind
+                if let Err(e) = self.env.delete(name) {
+                    tracing::warn!("Failed to remove {} from env file: {}", name, e);
+                }
+                audit::record(AuditAction::SecretUpdated, format!("{} (keychain)", name));
+                Ok(())
+            }
+            Err(e) => {
+                tracing::warn!("Keychain unavailable, writing {} to env file: {}", name, e);
+                self.env.set(name, value)?;
+                audit::record(AuditAction::SecretUpdated, format!("{} (env file)", name));
+                Ok(())
+            }
+        }
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        if let Err(e) = self.keychain.delete(name) {
+            tracing::warn!("Failed to delete {} from keychain: {}", name, e);
+        }
+        self.env.delete(name)?;
+        audit::record(AuditAction::SecretDeleted, name);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::traits::MockSecretStore;
+    use mockall::predicate::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_env_file_set_replace_delete() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".env.local");
+        std::fs::write(&path, "# comment\nMODEL=gpt\nRUSTBOT_TEST_ENV_FILE=old\n").unwrap();
+
+        let store = EnvFileSecretStore::new(&path);
+        assert_eq!(
+            store.get("RUSTBOT_TEST_ENV_FILE").unwrap().as_deref(),
+            Some("old")
+        );
+
+        store.set("RUSTBOT_TEST_ENV_FILE", "new").unwrap();
+        assert_eq!(
+            store.get("RUSTBOT_TEST_ENV_FILE").unwrap().as_deref(),
+            Some("new")
+        );
+
+        store.delete("RUSTBOT_TEST_ENV_FILE").unwrap();
+        assert_eq!(store.get("RUSTBOT_TEST_ENV_FILE").unwrap(), None);
+
+        // Unrelated lines are preserved
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "# comment\nMODEL=gpt\n");
+    }
+
+    #[test]
+    fn test_parse_env_line() {
+        assert_eq!(parse_env_line("A=1"), Some(("A", "1".to_string())));
+        assert_eq!(
+            parse_env_line("export B=\"two\""),
+            Some(("B", "two".to_string()))
+        );
+        assert_eq!(parse_env_line("# C=3"), None);
+        assert_eq!(parse_env_line(""), None);
+    }
+
+    #[test]
+    fn test_migrate_moves_plain_key_to_keychain() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".env.local");
+        std::fs::write(
+            &path,
+            "RUSTBOT_TEST_MIGRATE=sk-or-123\nRUSTBOT_TEST_REF=op://vault/item/key\n",
+        )
+        .unwrap();
+
+        let mut keychain = MockSecretStore::new();
+        keychain
+            .expect_set()
+            .with(eq("RUSTBOT_TEST_MIGRATE"), eq("sk-or-123"))
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let store = DefaultSecretStore::new(Arc::new(keychain), EnvFileSecretStore::new(&path));
+        let migrated = store.migrate(&["RUSTBOT_TEST_MIGRATE", "RUSTBOT_TEST_REF"]);
+
+        assert_eq!(migrated, 1);
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "RUSTBOT_TEST_REF=op://vault/item/key\n");
+    }
+
+    #[test]
+    fn test_keychain_failure_falls_back_to_env_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".env.local");
+
+        let mut keychain = MockSecretStore::new();
+        keychain
+            .expect_set()
+            .returning(|_, _| Err(RustbotError::ConfigError("no keyring".to_string())));
+        keychain
+            .expect_get()
+            .returning(|_| Err(RustbotError::ConfigError("no keyring".to_string())));
+
+        let store = DefaultSecretStore::new(Arc::new(keychain), EnvFileSecretStore::new(&path));
+        store.set("RUSTBOT_TEST_FALLBACK", "sk-or-456").unwrap();
+        assert_eq!(
+            store.get("RUSTBOT_TEST_FALLBACK").unwrap().as_deref(),
+            Some("sk-or-456")
+        );
+
+        // Migration leaves the key in place when the keychain can't take it
+        assert_eq!(store.migrate(&["RUSTBOT_TEST_FALLBACK"]), 0);
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("RUSTBOT_TEST_FALLBACK=sk-or-456"));
+    }
+
+    #[test]
+    fn test_set_in_keychain_never_writes_env_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".env.local");
+        std::fs::write(
+            &path,
+            "RUSTBOT_TEST_STRICT=old
+",
+        )
+        .unwrap();
+
+        let mut broken = MockSecretStore::new();
+        broken
+            .expect_set()
+            .returning(|_, _| Err(RustbotError::ConfigError("no keyring".to_string())));
+        let store = DefaultSecretStore::new(Arc::new(broken), EnvFileSecretStore::new(&path));
+        assert!(store.set_in_keychain("RUSTBOT_TEST_STRICT", "new").is_err());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "RUSTBOT_TEST_STRICT=old\n"
+        );
+
+        // A successful write removes the plaintext copy
+        let mut keychain = MockSecretStore::new();
+        keychain
+            .expect_set()
+            .with(eq("RUSTBOT_TEST_STRICT"), eq("new"))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let store = DefaultSecretStore::new(Arc::new(keychain), EnvFileSecretStore::new(&path));
+        store.set_in_keychain("RUSTBOT_TEST_STRICT", "new").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+    }
+
+    #[test]
+    fn test_keychain_value_takes_precedence() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".env.local");
+        std::fs::write(&path, "RUSTBOT_TEST_PRECEDENCE=from-file\n").unwrap();
+
+        let mut keychain = MockSecretStore::new();
+        keychain
+            .expect_get()
+            .with(eq("RUSTBOT_TEST_PRECEDENCE"))
+            .returning(|_| Ok(Some("from-keychain".to_string())));
+
+        let store = DefaultSecretStore::new(Arc::new(keychain), EnvFileSecretStore::new(&path));
+        assert_eq!(
+            store.get("RUSTBOT_TEST_PRECEDENCE").unwrap().as_deref(),
+            Some("from-keychain")
+        );
+    }
+}
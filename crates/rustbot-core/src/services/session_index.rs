@@ -0,0 +1,241 @@
+// Full-text index over persisted conversation sessions
+//
+// Design Decision: In-process inverted index persisted as JSON
+//
+// Rationale: The History view needs "find the chat where I asked about X"
+// across hundreds of sessions without opening every session file on each
+// keystroke. An inverted index (term → sessions containing it) answers that
+// with a BTreeMap range lookup, and fits the existing JSON-file storage
+// without pulling in a database or search engine dependency.
+//
+// Matching:
+// - Content is lowercased and split on non-alphanumeric characters
+// - Every query term must match (AND), as a prefix of an indexed term
+// - Results are ranked by total term frequency, then by recency
+//
+// Trade-offs:
+// - Whole index is loaded into memory (fine for a single user's history)
+// - No stemming or phrase queries; prefix matching covers most lookups
+//
+// Extension Points: Swap for SQLite FTS5 behind the same StorageService
+// methods if history grows beyond what fits comfortably in memory.
+
+use super::traits::{ConversationSession, SessionSummary};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Ignore terms shorter than this (mostly noise like "a", "I")
+const MIN_TERM_CHARS: usize = 2;
+
+/// Inverted index from terms to the sessions containing them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionIndex {
+    /// Summary of every indexed session, keyed by session ID
+    sessions: HashMap<String, SessionSummary>,
+
+    /// term → (session ID → occurrences)
+    postings: BTreeMap<String, HashMap<String, u32>>,
+}
+
+/// Split text into lowercase index terms
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_TERM_CHARS)
+        .map(|word| word.to_lowercase())
+}
+
+impl SessionIndex {
+    /// Create an empty index
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of indexed sessions
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Whether the index has no sessions
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// Add or re-index a session
+    pub fn upsert(&mut self, session: &ConversationSession) {
+        self.remove(&session.id);
+
+        let mut counts: HashMap<String, u32> = HashMap::new();
+        let texts = std::iter::once(session.title.as_str())
+            .chain(session.messages.iter().map(|m| m.content.as_str()));
+        for text in texts {
+            for term in tokenize(text) {
+                *counts.entry(term).or_insert(0) += 1;
+            }
+        }
+
+        for (term, count) in counts {
+            self.postings
+                .entry(term)
+                .or_default()
+                .insert(session.id.clone(), count);
+        }
+        self.sessions
+            .insert(session.id.clone(), SessionSummary::from(session));
+    }
+
+    /// Remove a session from the index
+    pub fn remove(&mut self, id: &str) {
+        if self.sessions.remove(id).is_none() {
+            return;
+        }
+        self.postings.retain(|_, sessions| {
+            sessions.remove(id);
+            !sessions.is_empty()
+        });
+    }
+
+    /// All sessions, most recently updated first
+    pub fn list(&self) -> Vec<SessionSummary> {
+        let mut sessions: Vec<SessionSummary> = self.sessions.values().cloned().collect();
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+        sessions
+    }
+
+    /// Find sessions containing every query term (prefix match)
+    ///
+    /// An empty query returns all sessions, like `list()`.
+    pub fn search(&self, query: &str) -> Vec<SessionSummary> {
+        let terms: Vec<String> = tokenize(query).collect();
+        if terms.is_empty() {
+            return self.list();
+        }
+
+        let mut scores: Option<HashMap<&str, u32>> = None;
+        for term in &terms {
+            // Collect every session matching this term as a prefix
+            let mut matches: HashMap<&str, u32> = HashMap::new();
+            for (_, sessions) in self
+                .postings
+                .range(term.clone()..)
+                .take_while(|(indexed, _)| indexed.starts_with(term.as_str()))
+            {
+                for (id, count) in sessions {
+                    *matches.entry(id.as_str()).or_insert(0) += count;
+                }
+            }
+
+            // Intersect with the sessions matching previous terms
+            scores = Some(match scores {
+                None => matches,
+                Some(previous) => previous
+                    .into_iter()
+                    .filter_map(|(id, score)| matches.get(id).map(|count| (id, score + count)))
+                    .collect(),
+            });
+        }
+
+        let mut ranked: Vec<(&SessionSummary, u32)> = scores
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(id, score)| self.sessions.get(id).map(|summary| (summary, score)))
+            .collect();
+        ranked.sort_by(|(a, a_score), (b, b_score)| {
+            b_score
+                .cmp(a_score)
+                .then_with(|| b.updated_at.cmp(&a.updated_at))
+        });
+
+        ranked
+            .into_iter()
+            .map(|(summary, _)| summary.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::traits::SessionMessage;
+
+    fn session(id: &str, contents: &[&str]) -> ConversationSession {
+        let mut session = ConversationSession::new("assistant");
+        session.id = id.to_string();
+        session.messages = contents
+            .iter()
+            .map(|content| SessionMessage {
+                role: "user".to_string(),
+                content: content.to_string(),
+                timestamp: chrono::Utc::now(),
+                input_tokens: None,
+                output_tokens: None,
+                agent_id: None,
+                model: None,
+                first_token_ms: None,
+                generation_ms: None,
+                pinned: false,
+                feedback: None,
+            })
+            .collect();
+        session.update_title();
+        session
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let terms: Vec<String> = tokenize("Rust's borrow-checker, a FOO!").collect();
+        assert_eq!(terms, vec!["rust", "borrow", "checker", "foo"]);
+    }
+
+    #[test]
+    fn test_search_requires_all_terms_and_matches_prefixes() {
+        let mut index = SessionIndex::new();
+        index.upsert(&session("a", &["How do I configure tokio runtimes?"]));
+        index.upsert(&session("b", &["Explain the tokio select macro"]));
+
+        let ids: Vec<String> = index.search("tok").into_iter().map(|s| s.id).collect();
+        assert_eq!(ids.len(), 2);
+
+        let ids: Vec<String> = index
+            .search("tokio config")
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids, vec!["a"]);
+
+        assert!(index.search("python").is_empty());
+    }
+
+    #[test]
+    fn test_ranking_by_frequency() {
+        let mut index = SessionIndex::new();
+        index.upsert(&session("once", &["mermaid diagram"]));
+        index.upsert(&session("twice", &["mermaid", "another mermaid chart"]));
+
+        let ids: Vec<String> = index.search("mermaid").into_iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec!["twice", "once"]);
+    }
+
+    #[test]
+    fn test_upsert_replaces_old_terms() {
+        let mut index = SessionIndex::new();
+        index.upsert(&session("a", &["original topic"]));
+        index.upsert(&session("a", &["different subject"]));
+
+        assert_eq!(index.len(), 1);
+        assert!(index.search("original").is_empty());
+        assert_eq!(index.search("subject").len(), 1);
+
+        index.remove("a");
+        assert!(index.is_empty());
+        assert!(index.search("subject").is_empty());
+    }
+
+    #[test]
+    fn test_empty_query_lists_all() {
+        let mut index = SessionIndex::new();
+        index.upsert(&session("a", &["one"]));
+        index.upsert(&session("b", &["two"]));
+
+        assert_eq!(index.search("  ").len(), 2);
+    }
+}
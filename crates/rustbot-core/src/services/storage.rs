@@ -16,11 +16,19 @@
 // Extension Points: Can switch to SQLite or cloud storage by implementing
 // StorageService trait with a different adapter (no business logic changes).
 
-use super::traits::{FileSystem, StorageService, SystemPrompts, TokenStats, UserProfile};
+use super::encryption::{EncryptedFileSystem, EncryptionKey};
+use super::session_index::SessionIndex;
+use super::traits::{
+    ConversationSession, FileSystem, RecoveryState, SessionSummary, StorageService, SystemPrompts,
+    TokenStats, UserProfile,
+};
+use crate::analytics::UsageAnalytics;
 use crate::error::{Result, RustbotError};
+use crate::migration::{backup_path, SchemaMigrator, USER_PROFILE_SCHEMA};
 use async_trait::async_trait;
 use std::path::PathBuf;
 use std::sync::Arc;
+use tokio::sync::Mutex;
 
 /// File-based storage service using JSON serialization
 ///
@@ -41,6 +49,9 @@ pub struct FileStorageService {
 
     /// Base directory for storing data files
     base_path: PathBuf,
+
+    /// Session search index, loaded (or rebuilt) on first use
+    session_index: Mutex<Option<SessionIndex>>,
 }
 
 impl FileStorageService {
@@ -52,7 +63,23 @@ impl FileStorageService {
     ///
     /// The base directory is created if it doesn't exist on first write.
     pub fn new(fs: Arc<dyn FileSystem>, base_path: PathBuf) -> Self {
-        Self { fs, base_path }
+        Self {
+            fs,
+            base_path,
+            session_index: Mutex::new(None),
+        }
+    }
+
+    /// Create a storage service that encrypts every file it writes
+    ///
+    /// Existing plaintext files stay readable and are encrypted on next save.
+    ///
+    /// # Arguments
+    /// * `fs` - Underlying filesystem
+    /// * `base_path` - Directory to store data files
+    /// * `key` - Storage key (see `EncryptionKey::for_storage_dir`)
+    pub fn encrypted(fs: Arc<dyn FileSystem>, base_path: PathBuf, key: EncryptionKey) -> Self {
+        Self::new(Arc::new(EncryptedFileSystem::new(fs, key)), base_path)
     }
 
     /// Get path to token stats file
@@ -70,6 +97,116 @@ impl FileStorageService {
         self.base_path.join("profile.json")
     }
 
+    /// Get path to the conversation sessions directory
+    fn sessions_dir(&self) -> PathBuf {
+        self.base_path.join("sessions")
+    }
+
+    /// Get path to a session file, rejecting IDs that would escape the directory
+    fn session_path(&self, id: &str) -> Result<PathBuf> {
+        let valid = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(RustbotError::StorageError(format!(
+                "Invalid session id '{}'",
+                id
+            )));
+        }
+        Ok(self.sessions_dir().join(format!("{}.json", id)))
+    }
+
+    /// Get path to the file holding the ID of the most recent session
+    fn last_session_path(&self) -> PathBuf {
+        self.sessions_dir().join("last_session")
+    }
+
+    /// Get path to the draft and interrupted answer saved for crash recovery
+    fn recovery_path(&self) -> PathBuf {
+        self.base_path.join("recovery.json")
+    }
+
+    /// Get path to the opt-in usage analytics
+    fn analytics_path(&self) -> PathBuf {
+        self.base_path.join("analytics.json")
+    }
+
+    /// Get path to the session search index
+    fn session_index_path(&self) -> PathBuf {
+        self.base_path.join("session_index.json")
+    }
+
+    /// Load the session index from disk, rebuilding it from session files
+    /// if it is missing or unreadable
+    async fn load_session_index(&self) -> Result<SessionIndex> {
+        let path = self.session_index_path();
+        if self.fs.exists(&path).await {
+            let content = self.fs.read_to_string(&path).await?;
+            match serde_json::from_str(&content) {
+                Ok(index) => return Ok(index),
+                Err(e) => tracing::warn!("Session index corrupt, rebuilding: {}", e),
+            }
+        }
+
+        let mut index = SessionIndex::new();
+        let dir = self.sessions_dir();
+        if self.fs.exists(&dir).await {
+            for path in self.fs.read_dir(&dir).await? {
+                if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                    continue;
+                }
+                let content = self.fs.read_to_string(&path).await?;
+                match serde_json::from_str::<ConversationSession>(&content) {
+                    Ok(session) => index.upsert(&session),
+                    Err(e) => tracing::warn!("Skipping unreadable session {:?}: {}", path, e),
+                }
+            }
+        }
+
+        tracing::info!("Rebuilt session index ({} sessions)", index.len());
+        self.save_session_index(&index).await?;
+        Ok(index)
+    }
+
+    /// Write the session index to disk
+    async fn save_session_index(&self, index: &SessionIndex) -> Result<()> {
+        self.ensure_base_dir().await?;
+
+        let content = serde_json::to_string(index).map_err(|e| {
+            RustbotError::StorageError(format!("Failed to serialize session index: {}", e))
+        })?;
+
+        self.fs.write(&self.session_index_path(), &content).await
+    }
+
+    /// Write a session to its own file
+    async fn write_session_file(&self, session: &ConversationSession) -> Result<()> {
+        let path = self.session_path(&session.id)?;
+        self.ensure_sessions_dir().await?;
+
+        let content = serde_json::to_string_pretty(session).map_err(|e| {
+            RustbotError::StorageError(format!("Failed to serialize session: {}", e))
+        })?;
+
+        self.fs.write(&path, &content).await
+    }
+
+    /// Keep the search index in step with the session files
+    async fn index_sessions(&self, sessions: &[ConversationSession]) -> Result<()> {
+        let mut guard = self.session_index.lock().await;
+        if guard.is_none() {
+            *guard = Some(self.load_session_index().await?);
+        }
+        if let Some(index) = guard.as_mut() {
+            for session in sessions {
+                index.upsert(session);
+            }
+            self.save_session_index(index).await?;
+        }
+        Ok(())
+    }
+
     /// Ensure base directory exists
     async fn ensure_base_dir(&self) -> Result<()> {
         if !self.fs.exists(&self.base_path).await {
@@ -77,6 +214,15 @@ impl FileStorageService {
         }
         Ok(())
     }
+
+    /// Ensure sessions directory exists
+    async fn ensure_sessions_dir(&self) -> Result<()> {
+        let dir = self.sessions_dir();
+        if !self.fs.exists(&dir).await {
+            self.fs.create_dir_all(&dir).await?;
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -144,8 +290,26 @@ impl StorageService for FileStorageService {
         }
 
         let content = self.fs.read_to_string(&path).await?;
+        let mut value: serde_json::Value = serde_json::from_str(&content).map_err(|e| {
+            RustbotError::StorageError(format!("Failed to deserialize user profile: {}", e))
+        })?;
 
-        serde_json::from_str(&content).map_err(|e| {
+        // Upgrade profiles saved in an older format, keeping the original alongside
+        let old_version = SchemaMigrator::version_of(&value);
+        if USER_PROFILE_SCHEMA.migrate(&mut value)? {
+            self.fs
+                .write(&backup_path(&path, old_version), &content)
+                .await?;
+            self.fs
+                .write(&path, &serde_json::to_string_pretty(&value)?)
+                .await?;
+            tracing::info!(
+                "⬆️  Upgraded user profile to schema version {}",
+                USER_PROFILE_SCHEMA.current
+            );
+        }
+
+        serde_json::from_value(value).map_err(|e| {
             RustbotError::StorageError(format!("Failed to deserialize user profile: {}", e))
         })
     }
@@ -161,13 +325,153 @@ impl StorageService for FileStorageService {
         self.fs.write(&path, &content).await?;
         Ok(())
     }
+
+    async fn save_session(&self, session: &ConversationSession) -> Result<()> {
+        self.write_session_file(session).await?;
+        self.fs
+            .write(&self.last_session_path(), &session.id)
+            .await?;
+
+        self.index_sessions(std::slice::from_ref(session)).await
+    }
+
+    async fn import_sessions(&self, sessions: &[ConversationSession]) -> Result<usize> {
+        for session in sessions {
+            self.write_session_file(session).await?;
+        }
+
+        self.index_sessions(sessions).await?;
+        Ok(sessions.len())
+    }
+
+    async fn load_session(&self, id: &str) -> Result<Option<ConversationSession>> {
+        let path = self.session_path(id)?;
+
+        if !self.fs.exists(&path).await {
+            return Ok(None);
+        }
+
+        let content = self.fs.read_to_string(&path).await?;
+
+        serde_json::from_str(&content).map(Some).map_err(|e| {
+            RustbotError::StorageError(format!("Failed to deserialize session '{}': {}", id, e))
+        })
+    }
+
+    async fn load_last_session(&self) -> Result<Option<ConversationSession>> {
+        let pointer = self.last_session_path();
+
+        if !self.fs.exists(&pointer).await {
+            // First run: nothing to restore
+            return Ok(None);
+        }
+
+        let id = self.fs.read_to_string(&pointer).await?;
+        let id = id.trim();
+        if id.is_empty() {
+            // Cleared by the user
+            return Ok(None);
+        }
+
+        self.load_session(id).await
+    }
+
+    async fn clear_last_session(&self) -> Result<()> {
+        let pointer = self.last_session_path();
+
+        if !self.fs.exists(&pointer).await {
+            return Ok(());
+        }
+
+        self.fs.write(&pointer, "").await?;
+        Ok(())
+    }
+
+    async fn list_sessions(&self) -> Result<Vec<SessionSummary>> {
+        self.search_sessions("").await
+    }
+
+    async fn search_sessions(&self, query: &str) -> Result<Vec<SessionSummary>> {
+        let mut guard = self.session_index.lock().await;
+        if guard.is_none() {
+            *guard = Some(self.load_session_index().await?);
+        }
+        Ok(guard
+            .as_ref()
+            .map(|index| index.search(query))
+            .unwrap_or_default())
+    }
+
+    async fn load_recovery(&self) -> Result<Option<RecoveryState>> {
+        let path = self.recovery_path();
+
+        if !self.fs.exists(&path).await {
+            return Ok(None);
+        }
+
+        let content = self.fs.read_to_string(&path).await?;
+        if content.trim().is_empty() {
+            // Cleared after a clean exit
+            return Ok(None);
+        }
+
+        serde_json::from_str(&content).map(Some).map_err(|e| {
+            RustbotError::StorageError(format!("Failed to deserialize recovery state: {}", e))
+        })
+    }
+
+    async fn save_recovery(&self, state: &RecoveryState) -> Result<()> {
+        self.ensure_base_dir().await?;
+
+        let content = serde_json::to_string_pretty(state).map_err(|e| {
+            RustbotError::StorageError(format!("Failed to serialize recovery state: {}", e))
+        })?;
+
+        self.fs.write(&self.recovery_path(), &content).await?;
+        Ok(())
+    }
+
+    async fn clear_recovery(&self) -> Result<()> {
+        let path = self.recovery_path();
+
+        if !self.fs.exists(&path).await {
+            return Ok(());
+        }
+
+        self.fs.write(&path, "").await?;
+        Ok(())
+    }
+
+    async fn load_analytics(&self) -> Result<UsageAnalytics> {
+        let path = self.analytics_path();
+
+        if !self.fs.exists(&path).await {
+            return Ok(UsageAnalytics::default());
+        }
+
+        let content = self.fs.read_to_string(&path).await?;
+        serde_json::from_str(&content).map_err(|e| {
+            RustbotError::StorageError(format!("Failed to deserialize analytics: {}", e))
+        })
+    }
+
+    async fn save_analytics(&self, analytics: &UsageAnalytics) -> Result<()> {
+        self.ensure_base_dir().await?;
+
+        let content = serde_json::to_string_pretty(analytics).map_err(|e| {
+            RustbotError::StorageError(format!("Failed to serialize analytics: {}", e))
+        })?;
+
+        self.fs.write(&self.analytics_path(), &content).await?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::services::mocks::test_helpers::*;
-    use crate::services::traits::MockFileSystem;
+    use crate::services::traits::{MockFileSystem, SessionMessage};
     use crate::services::RealFileSystem;
     use mockall::predicate::*;
     use tempfile::TempDir;
@@ -261,6 +565,198 @@ mod tests {
         assert!(fs.exists(&nested_path).await);
     }
 
+    #[tokio::test]
+    async fn test_save_and_restore_last_session() {
+        let temp_dir = TempDir::new().unwrap();
+        let fs = Arc::new(RealFileSystem);
+        let storage = FileStorageService::new(fs, temp_dir.path().to_path_buf());
+
+        // Nothing to restore on first run
+        assert!(storage.load_last_session().await.unwrap().is_none());
+
+        let mut session = ConversationSession::new("assistant");
+        session.messages.push(SessionMessage {
+            role: "user".to_string(),
+            content: "Hello".to_string(),
+            timestamp: chrono::Utc::now(),
+            input_tokens: Some(2),
+            output_tokens: None,
+            agent_id: None,
+            model: None,
+            first_token_ms: None,
+            generation_ms: None,
+            pinned: false,
+            feedback: None,
+        });
+        session
+            .history
+            .push(crate::llm::Message::new("user", "Hello"));
+        storage.save_session(&session).await.unwrap();
+
+        let restored = storage.load_last_session().await.unwrap().unwrap();
+        assert_eq!(restored.id, session.id);
+        assert_eq!(restored.messages, session.messages);
+        assert_eq!(restored.history.len(), 1);
+
+        // Clearing forgets the pointer but keeps the session itself
+        storage.clear_last_session().await.unwrap();
+        assert!(storage.load_last_session().await.unwrap().is_none());
+        assert!(storage.load_session(&session.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_save_and_clear_recovery() {
+        let temp_dir = TempDir::new().unwrap();
+        let fs = Arc::new(RealFileSystem);
+        let storage = FileStorageService::new(fs, temp_dir.path().to_path_buf());
+
+        assert!(storage.load_recovery().await.unwrap().is_none());
+
+        let state = RecoveryState {
+            session_id: "session-1".to_string(),
+            draft: "Half a thought".to_string(),
+            interrupted: None,
+            saved_at: chrono::Utc::now(),
+        };
+        storage.save_recovery(&state).await.unwrap();
+        assert_eq!(storage.load_recovery().await.unwrap(), Some(state));
+
+        storage.clear_recovery().await.unwrap();
+        assert!(storage.load_recovery().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load_analytics() {
+        let temp_dir = TempDir::new().unwrap();
+        let fs = Arc::new(RealFileSystem);
+        let storage = FileStorageService::new(fs, temp_dir.path().to_path_buf());
+
+        assert_eq!(
+            storage.load_analytics().await.unwrap(),
+            UsageAnalytics::default()
+        );
+
+        let mut analytics = UsageAnalytics::default();
+        let day = chrono::NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+        analytics.days.entry(day).or_default().messages = 4;
+        storage.save_analytics(&analytics).await.unwrap();
+        assert_eq!(storage.load_analytics().await.unwrap(), analytics);
+    }
+
+    #[tokio::test]
+    async fn test_search_sessions_and_rebuild_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let fs = Arc::new(RealFileSystem);
+        let storage = FileStorageService::new(fs.clone(), temp_dir.path().to_path_buf());
+
+        for (i, content) in ["Deploying with docker compose", "Tuning tokio runtimes"]
+            .iter()
+            .enumerate()
+        {
+            let mut session = ConversationSession::new("assistant");
+            session.id = format!("session-{}", i);
+            session.messages.push(SessionMessage {
+                role: "user".to_string(),
+                content: content.to_string(),
+                timestamp: chrono::Utc::now(),
+                input_tokens: None,
+                output_tokens: None,
+                agent_id: None,
+                model: None,
+                first_token_ms: None,
+                generation_ms: None,
+                pinned: false,
+                feedback: None,
+            });
+            session.update_title();
+            storage.save_session(&session).await.unwrap();
+        }
+
+        assert_eq!(storage.list_sessions().await.unwrap().len(), 2);
+        let hits = storage.search_sessions("docker").await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].title, "Deploying with docker compose");
+
+        // A fresh service without the index file rebuilds it from sessions
+        std::fs::remove_file(temp_dir.path().join("session_index.json")).unwrap();
+        let reopened = FileStorageService::new(fs, temp_dir.path().to_path_buf());
+        assert_eq!(reopened.search_sessions("tokio").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_import_sessions_keeps_last_session() {
+        let temp_dir = TempDir::new().unwrap();
+        let fs = Arc::new(RealFileSystem);
+        let storage = FileStorageService::new(fs, temp_dir.path().to_path_buf());
+
+        let current = ConversationSession::new("assistant");
+        storage.save_session(&current).await.unwrap();
+
+        let mut imported = ConversationSession::new("imported-chatgpt");
+        imported.id = "import-chatgpt-1".to_string();
+        imported.title = "Imported chat".to_string();
+        assert_eq!(storage.import_sessions(&[imported]).await.unwrap(), 1);
+
+        let last = storage.load_last_session().await.unwrap().unwrap();
+        assert_eq!(last.id, current.id);
+        assert_eq!(storage.search_sessions("imported").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_storage_hides_profile() {
+        let temp_dir = TempDir::new().unwrap();
+        let fs = Arc::new(RealFileSystem);
+        let key = EncryptionKey::generate();
+        let storage =
+            FileStorageService::encrypted(fs.clone(), temp_dir.path().to_path_buf(), key.clone());
+
+        let profile = UserProfile {
+            name: "Ada Lovelace".to_string(),
+            ..UserProfile::default()
+        };
+        storage.save_user_profile(&profile).await.unwrap();
+
+        let raw = std::fs::read_to_string(temp_dir.path().join("profile.json")).unwrap();
+        assert!(!raw.contains("Ada"));
+
+        let reopened = FileStorageService::encrypted(fs, temp_dir.path().to_path_buf(), key);
+        assert_eq!(
+            reopened.load_user_profile().await.unwrap().name,
+            "Ada Lovelace"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_legacy_profile_is_upgraded_on_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let legacy = r#"{"name": "Ada", "email": "", "timezone": null, "location": null}"#;
+        std::fs::write(temp_dir.path().join("profile.json"), legacy).unwrap();
+
+        let storage =
+            FileStorageService::new(Arc::new(RealFileSystem), temp_dir.path().to_path_buf());
+        let profile = storage.load_user_profile().await.unwrap();
+        assert_eq!(profile.name, "Ada");
+        assert_eq!(profile.schema_version, USER_PROFILE_SCHEMA.current);
+
+        let upgraded = std::fs::read_to_string(temp_dir.path().join("profile.json")).unwrap();
+        assert!(upgraded.contains("schema_version"));
+        let backup = std::fs::read_to_string(temp_dir.path().join("profile.json.v0.bak")).unwrap();
+        assert_eq!(backup, legacy);
+    }
+
+    #[tokio::test]
+    async fn test_session_id_path_traversal_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let fs = Arc::new(RealFileSystem);
+        let storage = FileStorageService::new(fs, temp_dir.path().to_path_buf());
+
+        assert!(storage.load_session("../profile").await.is_err());
+
+        let mut session = ConversationSession::new("assistant");
+        session.id = "a/b".to_string();
+        assert!(storage.save_session(&session).await.is_err());
+    }
+
     // ===== UNIT TESTS (using mocks) =====
 
     #[tokio::test]
@@ -0,0 +1,915 @@
+// Core trait definitions for service layer dependency injection
+//
+// Design Decision: Trait-based abstractions for infrastructure concerns
+//
+// Rationale: Traits provide compile-time polymorphism in Rust, enabling:
+// 1. Zero-cost abstractions (no runtime overhead vs. direct calls)
+// 2. Type-safe mocking (trait objects with Arc<dyn Trait>)
+// 3. Explicit contracts for infrastructure dependencies
+// 4. Send + Sync bounds for async/concurrent safety
+//
+// All traits are marked Send + Sync to work with tokio's async runtime,
+// which requires thread-safe types for spawning tasks across threads.
+
+use crate::agent::{Agent, AgentConfig};
+use crate::error::Result;
+use async_trait::async_trait;
+#[cfg(test)]
+use mockall::automock;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Filesystem abstraction for file I/O operations
+///
+/// Provides async file operations to enable dependency injection and testing.
+/// All operations use tokio::fs under the hood for non-blocking I/O.
+///
+/// Design: Simple, focused interface covering common file operations.
+/// More specialized operations can be added as needed.
+///
+/// Usage:
+///     let fs: Arc<dyn FileSystem> = Arc::new(RealFileSystem);
+///     let content = fs.read_to_string(Path::new("config.json")).await?;
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait FileSystem: Send + Sync {
+    /// Read entire file contents as a UTF-8 string
+    ///
+    /// # Errors
+    /// - File not found
+    /// - Permission denied
+    /// - Invalid UTF-8 encoding
+    async fn read_to_string(&self, path: &Path) -> Result<String>;
+
+    /// Write string content to a file (creates or overwrites)
+    ///
+    /// # Errors
+    /// - Permission denied
+    /// - Disk full
+    /// - Invalid path
+    async fn write(&self, path: &Path, content: &str) -> Result<()>;
+
+    /// Check if a path exists (file or directory)
+    ///
+    /// Returns false on permission errors (cannot distinguish from non-existence)
+    async fn exists(&self, path: &Path) -> bool;
+
+    /// Create directory and all parent directories (like mkdir -p)
+    ///
+    /// # Errors
+    /// - Permission denied
+    /// - Disk full
+    /// - Invalid path
+    async fn create_dir_all(&self, path: &Path) -> Result<()>;
+
+    /// Read directory entries, returning file paths
+    ///
+    /// # Errors
+    /// - Directory not found
+    /// - Permission denied
+    async fn read_dir(&self, path: &Path) -> Result<Vec<std::path::PathBuf>>;
+}
+
+/// Storage service for application data persistence
+///
+/// High-level interface for reading/writing structured application data.
+/// Abstracts serialization format and storage location from business logic.
+///
+/// Design: Domain-specific storage operations (not generic read/write).
+/// Each method handles a specific data type with appropriate serialization.
+///
+/// Usage:
+///     let storage: Arc<dyn StorageService> = Arc::new(FileStorageService::new(...));
+///     let stats = storage.load_token_stats().await?;
+///     storage.save_token_stats(&updated_stats).await?;
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait StorageService: Send + Sync {
+    /// Load token usage statistics from persistent storage
+    ///
+    /// Returns default/empty stats if file doesn't exist (first run).
+    ///
+    /// # Errors
+    /// - Deserialization errors (corrupt data)
+    /// - Permission errors
+    async fn load_token_stats(&self) -> Result<TokenStats>;
+
+    /// Save token usage statistics to persistent storage
+    ///
+    /// # Errors
+    /// - Serialization errors
+    /// - Write errors (disk full, permissions)
+    async fn save_token_stats(&self, stats: &TokenStats) -> Result<()>;
+
+    /// Load system prompts from persistent storage
+    ///
+    /// Returns default prompts if file doesn't exist.
+    ///
+    /// # Errors
+    /// - Deserialization errors
+    /// - Permission errors
+    async fn load_system_prompts(&self) -> Result<SystemPrompts>;
+
+    /// Save system prompts to persistent storage
+    ///
+    /// # Errors
+    /// - Serialization errors
+    /// - Write errors
+    async fn save_system_prompts(&self, prompts: &SystemPrompts) -> Result<()>;
+
+    /// Load user profile from persistent storage
+    ///
+    /// Returns default profile if file doesn't exist.
+    ///
+    /// # Errors
+    /// - Deserialization errors
+    /// - Permission errors
+    async fn load_user_profile(&self) -> Result<UserProfile>;
+
+    /// Save user profile to persistent storage
+    ///
+    /// # Errors
+    /// - Serialization errors
+    /// - Write errors
+    async fn save_user_profile(&self, profile: &UserProfile) -> Result<()>;
+
+    /// Save a conversation session and mark it as the most recent one
+    ///
+    /// # Errors
+    /// - Invalid session ID (must be a plain file name)
+    /// - Serialization errors
+    /// - Write errors
+    async fn save_session(&self, session: &ConversationSession) -> Result<()>;
+
+    /// Save sessions from another application without changing which
+    /// session is restored on startup
+    ///
+    /// Sessions with an existing ID are overwritten.
+    ///
+    /// # Returns
+    /// Number of sessions saved
+    ///
+    /// # Errors
+    /// - Invalid session ID
+    /// - Serialization or write errors
+    async fn import_sessions(&self, sessions: &[ConversationSession]) -> Result<usize>;
+
+    /// Load a conversation session by ID
+    ///
+    /// Returns None if no session with this ID exists.
+    ///
+    /// # Errors
+    /// - Invalid session ID
+    /// - Deserialization errors
+    /// - Permission errors
+    async fn load_session(&self, id: &str) -> Result<Option<ConversationSession>>;
+
+    /// Load the most recently saved session (restored on startup)
+    ///
+    /// Returns None on first run or after clear_last_session().
+    ///
+    /// # Errors
+    /// - Deserialization errors
+    /// - Permission errors
+    async fn load_last_session(&self) -> Result<Option<ConversationSession>>;
+
+    /// Forget the most recent session so the next startup begins empty
+    ///
+    /// The session itself stays on disk.
+    ///
+    /// # Errors
+    /// - Write errors
+    async fn clear_last_session(&self) -> Result<()>;
+
+    /// List all saved sessions, most recently updated first
+    ///
+    /// # Errors
+    /// - Index or session files cannot be read
+    async fn list_sessions(&self) -> Result<Vec<SessionSummary>>;
+
+    /// Full-text search over session titles and message content
+    ///
+    /// Every query term must match (as a word prefix). Results are ranked by
+    /// relevance; an empty query behaves like list_sessions().
+    ///
+    /// # Errors
+    /// - Index or session files cannot be read
+    async fn search_sessions(&self, query: &str) -> Result<Vec<SessionSummary>>;
+
+    /// Load the draft and interrupted answer saved while the app last ran
+    ///
+    /// Returns None on first run or after clear_recovery().
+    ///
+    /// # Errors
+    /// - Deserialization errors
+    /// - Permission errors
+    async fn load_recovery(&self) -> Result<Option<RecoveryState>>;
+
+    /// Save the current draft and streaming answer, replacing the last ones
+    ///
+    /// # Errors
+    /// - Serialization errors
+    /// - Write errors
+    async fn save_recovery(&self, state: &RecoveryState) -> Result<()>;
+
+    /// Forget the saved recovery state (after a clean exit or once the user
+    /// has restored or dismissed it)
+    ///
+    /// # Errors
+    /// - Write errors
+    async fn clear_recovery(&self) -> Result<()>;
+    /// Load the opt-in usage analytics (see `crate::analytics`)
+    ///
+    /// Returns empty analytics if none were collected yet.
+    ///
+    /// # Errors
+    /// - Deserialization errors
+    /// - Permission errors
+    async fn load_analytics(&self) -> Result<crate::analytics::UsageAnalytics>;
+
+    /// Save the usage analytics, replacing the last ones
+    ///
+    /// # Errors
+    /// - Serialization errors
+    /// - Write errors
+    async fn save_analytics(&self, analytics: &crate::analytics::UsageAnalytics) -> Result<()>;
+}
+
+/// Configuration service for application settings
+///
+/// Centralized access to configuration from environment variables and config files.
+/// Handles validation and provides type-safe accessors.
+///
+/// Design: Configuration is loaded once at startup and cached in memory.
+/// This avoids repeated file/env reads and provides consistent config during runtime.
+///
+/// Usage:
+///     let config: Arc<dyn ConfigService> = Arc::new(FileConfigService::load()?);
+///     let api_key = config.get_api_key()?;
+///     let agents_dir = config.get_agents_dir();
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait ConfigService: Send + Sync {
+    /// Load all agent configurations from configured directory
+    ///
+    /// # Errors
+    /// - Directory read errors
+    /// - JSON parse errors
+    /// - Validation errors
+    async fn load_agent_configs(&self) -> Result<Vec<AgentConfig>>;
+
+    /// Save an agent configuration to the agent directory
+    ///
+    /// # Errors
+    /// - Serialization errors
+    /// - Write errors
+    async fn save_agent_config(&self, config: &AgentConfig) -> Result<()>;
+
+    /// Get the currently active agent ID
+    ///
+    /// # Errors
+    /// - Config file not found or corrupt
+    async fn get_active_agent_id(&self) -> Result<String>;
+
+    /// Set the active agent ID
+    ///
+    /// # Errors
+    /// - Write errors
+    async fn set_active_agent_id(&self, id: &str) -> Result<()>;
+
+    /// Get agents directory path
+    fn get_agents_dir(&self) -> std::path::PathBuf;
+
+    /// Get API key (from config or environment)
+    ///
+    /// # Errors
+    /// - API key not configured
+    fn get_api_key(&self) -> Result<String>;
+
+    /// Get default model identifier
+    fn get_model(&self) -> String;
+}
+
+/// Secret storage for credentials such as API keys
+///
+/// Synchronous because OS keychain APIs are blocking and secrets are only
+/// read or written at startup and from the setup wizard.
+///
+/// Usage:
+///     let secrets = DefaultSecretStore::system();
+///     secrets.set("OPENROUTER_API_KEY", "sk-or-...")?;
+///     let key = secrets.get("OPENROUTER_API_KEY")?;
+#[cfg_attr(test, automock)]
+pub trait SecretStore: Send + Sync {
+    /// Read a secret, `None` if it is not stored
+    ///
+    /// # Errors
+    /// - Backing store unavailable (e.g. keychain locked or no keyring daemon)
+    fn get(&self, name: &str) -> Result<Option<String>>;
+
+    /// Store or replace a secret
+    ///
+    /// # Errors
+    /// - Backing store unavailable or write denied
+    fn set(&self, name: &str, value: &str) -> Result<()>;
+
+    /// Remove a secret (succeeds if it was not stored)
+    ///
+    /// # Errors
+    /// - Backing store unavailable or write denied
+    fn delete(&self, name: &str) -> Result<()>;
+}
+
+/// Agent service for managing the agent registry
+///
+/// Provides access to loaded agents and handles agent switching.
+/// Maintains the registry of available agents and current selection.
+///
+/// Design: Centralized agent registry with lazy loading.
+/// Agents are loaded from config on service creation and cached in memory.
+///
+/// Usage:
+///     let service: Arc<dyn AgentService> = DefaultAgentService::new(config).await?;
+///     let agent = service.get_agent("researcher").await?;
+///     let all_agents = service.list_agents();
+///     service.switch_agent("writer").await?;
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait AgentService: Send + Sync {
+    /// Get agent by ID
+    ///
+    /// # Errors
+    /// - Agent not found
+    /// - Agent disabled
+    async fn get_agent(&self, id: &str) -> Result<Arc<Agent>>;
+
+    /// List all available agent IDs
+    fn list_agents(&self) -> Vec<String>;
+
+    /// Switch to a different agent
+    ///
+    /// # Errors
+    /// - Agent not found
+    /// - Agent disabled
+    async fn switch_agent(&mut self, id: &str) -> Result<()>;
+
+    /// Get the current active agent
+    fn current_agent(&self) -> Arc<Agent>;
+}
+
+// Placeholder types for StorageService
+// These should be moved to appropriate modules once storage is implemented
+
+/// Token usage statistics
+///
+/// Tracks cumulative token usage and costs across all conversations.
+/// Used for budget monitoring and usage analytics.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TokenStats {
+    /// Total input tokens consumed
+    pub total_input_tokens: u64,
+
+    /// Total output tokens generated
+    pub total_output_tokens: u64,
+
+    /// Total cost in USD
+    pub total_cost: f64,
+
+    /// Last updated timestamp
+    pub last_updated: chrono::DateTime<chrono::Utc>,
+
+    /// Input tokens consumed on `last_reset_date`
+    #[serde(default)]
+    pub daily_input_tokens: u64,
+
+    /// Output tokens generated on `last_reset_date`
+    #[serde(default)]
+    pub daily_output_tokens: u64,
+
+    /// Local date (YYYY-MM-DD) the daily counters belong to
+    #[serde(default)]
+    pub last_reset_date: String,
+}
+
+impl Default for TokenStats {
+    fn default() -> Self {
+        Self {
+            total_input_tokens: 0,
+            total_output_tokens: 0,
+            total_cost: 0.0,
+            last_updated: chrono::Utc::now(),
+            daily_input_tokens: 0,
+            daily_output_tokens: 0,
+            last_reset_date: String::new(),
+        }
+    }
+}
+
+impl TokenStats {
+    /// Add usage to both the daily and total counters
+    pub fn record(&mut self, input_tokens: u64, output_tokens: u64, cost: f64) {
+        self.daily_input_tokens += input_tokens;
+        self.daily_output_tokens += output_tokens;
+        self.total_input_tokens += input_tokens;
+        self.total_output_tokens += output_tokens;
+        self.total_cost += cost;
+        self.last_updated = chrono::Utc::now();
+    }
+
+    /// Zero the daily counters if they belong to a different day
+    ///
+    /// # Arguments
+    /// * `today` - Local date as YYYY-MM-DD
+    pub fn reset_daily_if_stale(&mut self, today: &str) {
+        if self.last_reset_date != today {
+            self.daily_input_tokens = 0;
+            self.daily_output_tokens = 0;
+            self.last_reset_date = today.to_string();
+        }
+    }
+}
+
+/// User profile information for personalization
+///
+/// Stores user details that can be used to personalize AI responses.
+/// This information is provided as context in system prompts.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct UserProfile {
+    /// User's full name
+    pub name: String,
+
+    /// User's email address
+    pub email: String,
+
+    /// User's timezone (e.g., "America/Los_Angeles")
+    pub timezone: Option<String>,
+
+    /// User's location (e.g., "San Francisco, CA")
+    pub location: Option<String>,
+
+    /// UI theme: "light", "dark", "system" or the name of a user theme
+    /// (see `crate::theme`)
+    #[serde(default = "default_theme")]
+    pub theme: String,
+
+    /// Provider chosen in the setup wizard; its key is in the keychain under
+    /// the provider's environment variable name
+    #[serde(default = "default_provider")]
+    pub provider: crate::llm::LlmProvider,
+
+    /// Notify when an answer finishes while the window is in the background
+    #[serde(default = "default_notifications")]
+    pub notifications: bool,
+
+    /// Body text size in points; other text styles scale with it
+    /// (see `crate::theme::FontSizes`)
+    #[serde(default = "default_font_size")]
+    pub font_size: f32,
+
+    /// Zoom factor for the whole interface, 1.0 = 100%
+    #[serde(default = "default_ui_scale")]
+    pub ui_scale: f32,
+
+    /// Local-only mode: no network egress except loopback and
+    /// `allowed_hosts` (see `crate::privacy`)
+    #[serde(default)]
+    pub privacy_mode: bool,
+
+    /// Endpoints reachable in local-only mode, as `host` or `host:port`
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+
+    /// Ollama model that answers while offline; None queues messages until
+    /// back online (see `crate::connectivity`)
+    #[serde(default)]
+    pub offline_model: Option<String>,
+
+    /// Collect usage analytics on this machine (see `crate::analytics`)
+    #[serde(default)]
+    pub analytics: bool,
+
+    /// Check tool results for prompt injections and warn about them
+    /// (see `crate::untrusted`)
+    #[serde(default = "default_injection_warnings")]
+    pub injection_warnings: bool,
+
+    /// Daily message and spending limits (see `crate::quota`)
+    #[serde(default)]
+    pub quota: crate::quota::UsageQuota,
+
+    /// Fields of the system context sent to the model, and the user's own
+    /// entries (see `crate::system_context`)
+    #[serde(default)]
+    pub system_context: crate::system_context::SystemContextSettings,
+
+    /// ID of the project the user is working in (see `crate::projects`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_project: Option<String>,
+
+    /// File format version used by the migration runner (see `crate::migration`)
+    #[serde(default)]
+    pub schema_version: u32,
+}
+
+fn default_theme() -> String {
+    "light".to_string()
+}
+
+fn default_provider() -> crate::llm::LlmProvider {
+    crate::llm::LlmProvider::OpenRouter
+}
+
+fn default_notifications() -> bool {
+    true
+}
+
+fn default_injection_warnings() -> bool {
+    true
+}
+
+fn default_font_size() -> f32 {
+    crate::theme::DEFAULT_FONT_SIZE
+}
+
+fn default_ui_scale() -> f32 {
+    crate::theme::DEFAULT_UI_SCALE
+}
+
+impl UserProfile {
+    /// Apply the settings kept in process-wide state: local-only mode, the
+    /// offline model, analytics, injection warnings and daily limits
+    pub fn apply_process_settings(&self) {
+        crate::privacy::configure(self.privacy_mode, &self.allowed_hosts);
+        crate::connectivity::set_local_model(self.offline_model.clone());
+        crate::analytics::set_enabled(self.analytics);
+        crate::untrusted::set_scan_enabled(self.injection_warnings);
+        crate::quota::configure(&self.quota);
+    }
+}
+
+impl Default for UserProfile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            email: String::new(),
+            timezone: None,
+            location: None,
+            theme: default_theme(),
+            provider: default_provider(),
+            notifications: default_notifications(),
+            font_size: default_font_size(),
+            ui_scale: default_ui_scale(),
+            privacy_mode: false,
+            allowed_hosts: Vec::new(),
+            offline_model: None,
+            analytics: false,
+            injection_warnings: default_injection_warnings(),
+            quota: crate::quota::UsageQuota::default(),
+            system_context: crate::system_context::SystemContextSettings::default(),
+            active_project: None,
+            schema_version: crate::migration::USER_PROFILE_SCHEMA.current,
+        }
+    }
+}
+
+/// System-level prompts shared across agents
+///
+/// Contains common instructions and context that all agents inherit.
+/// Allows customization of base behavior without modifying agent configs.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SystemPrompts {
+    /// Base system prompt for all agents
+    pub base_prompt: String,
+
+    /// Additional context or instructions
+    pub context: Option<String>,
+}
+
+impl Default for SystemPrompts {
+    fn default() -> Self {
+        Self {
+            base_prompt: String::new(),
+            context: None,
+        }
+    }
+}
+
+/// Maximum length of a session title derived from the first user message
+const SESSION_TITLE_MAX_CHARS: usize = 60;
+
+/// A persisted conversation
+///
+/// Holds both what the user saw (messages with timestamps and token counts)
+/// and the LLM context (history, including tool calls) so a restored session
+/// can be continued exactly where it left off.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ConversationSession {
+    /// Unique session identifier (also the file name)
+    pub id: String,
+
+    /// Short title (first user message, truncated)
+    pub title: String,
+
+    /// Agent active when the session was last saved
+    pub agent_id: String,
+
+    /// When the session was started
+    pub created_at: chrono::DateTime<chrono::Utc>,
+
+    /// When the session was last saved
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+
+    /// Messages as displayed in the chat view
+    #[serde(default)]
+    pub messages: Vec<SessionMessage>,
+
+    /// LLM conversation history (user, assistant and tool messages)
+    #[serde(default)]
+    pub history: Vec<crate::llm::Message>,
+
+    /// Send pinned messages with every request, so they stay in the model's
+    /// context after older history is trimmed
+    #[serde(default)]
+    pub keep_pinned_in_context: bool,
+
+    /// Project the session was started in (see `crate::projects`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+}
+
+impl ConversationSession {
+    /// Start a new, empty session
+    pub fn new(agent_id: impl Into<String>) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            id: format!("session-{}", now.format("%Y%m%d-%H%M%S-%3f")),
+            title: String::new(),
+            agent_id: agent_id.into(),
+            created_at: now,
+            updated_at: now,
+            messages: Vec::new(),
+            history: Vec::new(),
+            keep_pinned_in_context: false,
+            project: None,
+        }
+    }
+
+    /// Contents of the pinned messages, oldest first
+    pub fn pinned_contents(&self) -> Vec<String> {
+        self.messages
+            .iter()
+            .filter(|m| m.pinned)
+            .map(|m| m.content.clone())
+            .collect()
+    }
+
+    /// Derive the title from the first user message if not set yet
+    pub fn update_title(&mut self) {
+        if !self.title.is_empty() {
+            return;
+        }
+        if let Some(first) = self.messages.iter().find(|m| m.role == "user") {
+            let line = first.content.lines().next().unwrap_or_default().trim();
+            let mut title: String = line.chars().take(SESSION_TITLE_MAX_CHARS).collect();
+            if line.chars().count() > SESSION_TITLE_MAX_CHARS {
+                title.push('…');
+            }
+            self.title = title;
+        }
+    }
+}
+
+/// Lightweight description of a session for listings (no message bodies)
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SessionSummary {
+    pub id: String,
+    pub title: String,
+    pub agent_id: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub message_count: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+}
+
+impl From<&ConversationSession> for SessionSummary {
+    fn from(session: &ConversationSession) -> Self {
+        Self {
+            id: session.id.clone(),
+            title: session.title.clone(),
+            agent_id: session.agent_id.clone(),
+            created_at: session.created_at,
+            updated_at: session.updated_at,
+            message_count: session.messages.len(),
+            project: session.project.clone(),
+        }
+    }
+}
+
+/// A single displayed message within a session
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SessionMessage {
+    /// "user" or "assistant"
+    pub role: String,
+
+    /// Message content (markdown)
+    pub content: String,
+
+    /// When the message was added
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+
+    /// Estimated input tokens (user messages)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_tokens: Option<u32>,
+
+    /// Estimated output tokens (assistant messages)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_tokens: Option<u32>,
+
+    /// Agent the message was sent to or answered by (usage reports)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+
+    /// Model the agent used (usage reports)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// Milliseconds from sending until the first streamed text (assistant messages)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_token_ms: Option<u64>,
+
+    /// Milliseconds from sending until the answer was complete (assistant messages)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation_ms: Option<u64>,
+
+    /// Pinned by the user (listed in the chat's Pinned panel)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+
+    /// The user's rating of the answer (assistant messages)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feedback: Option<MessageFeedback>,
+}
+
+/// Thumbs up or down on an answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rating {
+    Up,
+    Down,
+}
+
+/// A user's rating of an assistant message, with an optional comment
+///
+/// Collected to evaluate prompt and model changes (see `crate::feedback`).
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MessageFeedback {
+    pub rating: Rating,
+
+    /// What was good or wrong about the answer
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub comment: String,
+
+    /// When the rating was last changed
+    pub rated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl MessageFeedback {
+    pub fn new(rating: Rating) -> Self {
+        Self {
+            rating,
+            comment: String::new(),
+            rated_at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// What was unsent or still streaming, saved periodically while the app runs
+///
+/// Cleared on a clean exit, so finding one at startup means the app crashed
+/// or was force-quit and the user can be offered to restore it.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RecoveryState {
+    /// Session the draft and interrupted answer belong to
+    pub session_id: String,
+
+    /// Unsent text in the message box
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub draft: String,
+
+    /// Messages as displayed while an answer was streaming, ending with the
+    /// prompt and the answer so far
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interrupted: Option<Vec<SessionMessage>>,
+
+    /// When the state was saved
+    pub saved_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl RecoveryState {
+    /// Nothing worth offering to restore
+    pub fn is_empty(&self) -> bool {
+        self.draft.trim().is_empty() && self.interrupted.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_stats_default() {
+        let stats = TokenStats::default();
+        assert_eq!(stats.total_input_tokens, 0);
+        assert_eq!(stats.total_output_tokens, 0);
+        assert_eq!(stats.total_cost, 0.0);
+    }
+
+    #[test]
+    fn test_token_stats_daily_rollover() {
+        let mut stats = TokenStats::default();
+        stats.reset_daily_if_stale("2025-01-01");
+        stats.record(100, 50, 0.01);
+        stats.reset_daily_if_stale("2025-01-01");
+        assert_eq!(stats.daily_input_tokens, 100);
+
+        stats.reset_daily_if_stale("2025-01-02");
+        assert_eq!(stats.daily_input_tokens, 0);
+        assert_eq!(stats.daily_output_tokens, 0);
+        assert_eq!(stats.total_input_tokens, 100);
+        assert_eq!(stats.total_output_tokens, 50);
+    }
+
+    #[test]
+    fn test_system_prompts_default() {
+        let prompts = SystemPrompts::default();
+        assert_eq!(prompts.base_prompt, "");
+        assert_eq!(prompts.context, None);
+    }
+
+    #[test]
+    fn test_session_title_from_first_user_message() {
+        let mut session = ConversationSession::new("assistant");
+        session.messages.push(SessionMessage {
+            role: "user".to_string(),
+            content: format!("{}\nsecond line", "a".repeat(80)),
+            timestamp: chrono::Utc::now(),
+            input_tokens: Some(20),
+            output_tokens: None,
+            agent_id: None,
+            model: None,
+            first_token_ms: None,
+            generation_ms: None,
+            pinned: false,
+            feedback: None,
+        });
+
+        session.update_title();
+        assert_eq!(session.title.chars().count(), SESSION_TITLE_MAX_CHARS + 1);
+        assert!(session.title.ends_with('…'));
+        assert!(session.id.starts_with("session-"));
+    }
+
+    #[test]
+    fn test_session_message_latency_is_optional() {
+        // Saved before latency was recorded
+        let json = r#"{"role":"assistant","content":"hi","timestamp":"2025-03-01T12:00:00Z"}"#;
+        let mut message: SessionMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(message.first_token_ms, None);
+
+        message.first_token_ms = Some(850);
+        message.generation_ms = Some(4200);
+        let json = serde_json::to_string(&message).unwrap();
+        let restored: SessionMessage = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.first_token_ms, Some(850));
+        assert_eq!(restored.generation_ms, Some(4200));
+        assert!(!restored.pinned);
+        assert!(!json.contains("pinned"));
+    }
+
+    #[test]
+    fn test_pinned_messages() {
+        let mut session = ConversationSession::new("assistant");
+        for (content, pinned) in [("one", true), ("two", false), ("three", true)] {
+            session.messages.push(SessionMessage {
+                role: "user".to_string(),
+                content: content.to_string(),
+                timestamp: chrono::Utc::now(),
+                input_tokens: None,
+                output_tokens: None,
+                agent_id: None,
+                model: None,
+                first_token_ms: None,
+                generation_ms: None,
+                pinned,
+                feedback: None,
+            });
+        }
+        assert_eq!(session.pinned_contents(), vec!["one", "three"]);
+
+        let json = serde_json::to_string(&session).unwrap();
+        let restored: ConversationSession = serde_json::from_str(&json).unwrap();
+        assert!(restored.messages[0].pinned);
+        assert!(!restored.keep_pinned_in_context);
+    }
+}
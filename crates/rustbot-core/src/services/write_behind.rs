@@ -0,0 +1,255 @@
+// Debounced background persistence for token statistics and conversations
+//
+// Design Decision: Write-behind queue with a quiet-period debounce
+//
+// Rationale: Token stats change on every message sent and every response
+// finished, and used to be written synchronously from the UI thread each
+// time. Conversations were saved by a separate task per change, so two quick
+// saves of the same session could finish out of order and leave the older
+// snapshot on disk. The UI now hands the latest snapshots to this queue and
+// returns immediately; one background task saves them through the
+// StorageService once updates have been quiet for `delay`. Snapshots are
+// coalesced: only the newest token stats and the newest version of each
+// session are written, in the order they were last queued (so the session
+// queued last is also the one marked most recent).
+//
+// Trade-offs:
+// - A crash within `delay` of the last update loses that update (the
+//   recovery state covers drafts and streaming answers)
+// - `flush` (or dropping every handle) saves what is pending, so a normal
+//   exit persists everything as long as the runtime is still running
+
+use super::traits::{ConversationSession, StorageService, TokenStats};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Default quiet period before pending writes are saved
+pub const WRITE_BEHIND_DELAY: Duration = Duration::from_secs(2);
+
+enum Write {
+    TokenStats(TokenStats),
+    Session(Box<ConversationSession>),
+}
+
+/// Handle for queueing saves, cheap to clone into background tasks
+#[derive(Clone)]
+pub struct WriteQueue {
+    tx: mpsc::UnboundedSender<Write>,
+}
+
+impl WriteQueue {
+    /// Queue a token stats snapshot (replaces any pending snapshot)
+    pub fn save_token_stats(&self, stats: TokenStats) {
+        self.send(Write::TokenStats(stats));
+    }
+
+    /// Queue a session snapshot (replaces a pending snapshot of the same session)
+    pub fn save_session(&self, session: ConversationSession) {
+        self.send(Write::Session(Box::new(session)));
+    }
+
+    fn send(&self, write: Write) {
+        if self.tx.send(write).is_err() {
+            tracing::warn!("Write-behind queue stopped; update not saved");
+        }
+    }
+}
+
+/// Background writer owning the queue's task
+///
+/// Usage:
+///     let writer = WriteBehind::spawn(storage, WRITE_BEHIND_DELAY, runtime.handle());
+///     writer.queue().save_token_stats(stats.clone()); // never blocks
+///     let queue = writer.queue().clone(); // for background tasks
+pub struct WriteBehind {
+    queue: WriteQueue,
+    task: JoinHandle<()>,
+}
+
+impl WriteBehind {
+    /// Start the background writer
+    ///
+    /// # Arguments
+    /// * `storage` - Where stats and sessions are persisted
+    /// * `delay` - Quiet period after the last update before saving
+    /// * `handle` - Runtime to run the writer task on
+    pub fn spawn(
+        storage: Arc<dyn StorageService>,
+        delay: Duration,
+        handle: &tokio::runtime::Handle,
+    ) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let task = handle.spawn(run(storage, delay, rx));
+        Self {
+            queue: WriteQueue { tx },
+            task,
+        }
+    }
+
+    /// Handle for queueing saves
+    pub fn queue(&self) -> &WriteQueue {
+        &self.queue
+    }
+
+    /// Save everything pending now and stop the writer
+    ///
+    /// Waits for clones of the queue handed to other tasks to be dropped, so
+    /// finish those first.
+    pub async fn flush(self) {
+        let Self { queue, task } = self;
+        drop(queue);
+        if let Err(e) = task.await {
+            tracing::warn!("Write-behind queue failed: {}", e);
+        }
+    }
+}
+
+/// Writes collected during one burst, newest snapshot of each
+#[derive(Default)]
+struct Pending {
+    token_stats: Option<TokenStats>,
+    sessions: Vec<ConversationSession>,
+}
+
+impl Pending {
+    fn add(&mut self, write: Write) {
+        match write {
+            Write::TokenStats(stats) => self.token_stats = Some(stats),
+            Write::Session(session) => {
+                // The newer snapshot moves to the end, so it is saved last
+                self.sessions.retain(|pending| pending.id != session.id);
+                self.sessions.push(*session);
+            }
+        }
+    }
+
+    async fn save(self, storage: &dyn StorageService) {
+        if let Some(stats) = self.token_stats {
+            if let Err(e) = storage.save_token_stats(&stats).await {
+                tracing::warn!("Failed to save token stats: {}", e);
+            }
+        }
+        for session in self.sessions {
+            if let Err(e) = storage.save_session(&session).await {
+                tracing::warn!("Failed to save session '{}': {}", session.id, e);
+            }
+        }
+    }
+}
+
+async fn run(
+    storage: Arc<dyn StorageService>,
+    delay: Duration,
+    mut rx: mpsc::UnboundedReceiver<Write>,
+) {
+    // Wait for the first update of each burst
+    while let Some(first) = rx.recv().await {
+        let mut pending = Pending::default();
+        pending.add(first);
+
+        // Keep taking newer snapshots until updates go quiet
+        let closed = loop {
+            match tokio::time::timeout(delay, rx.recv()).await {
+                Ok(Some(newer)) => pending.add(newer),
+                Ok(None) => break true,
+                Err(_) => break false,
+            }
+        };
+
+        pending.save(storage.as_ref()).await;
+        if closed {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::traits::MockStorageService;
+
+    #[tokio::test]
+    async fn test_burst_is_coalesced_into_one_save() {
+        let mut storage = MockStorageService::new();
+        storage
+            .expect_save_token_stats()
+            .withf(|stats| stats.total_input_tokens == 3)
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let writer = WriteBehind::spawn(
+            Arc::new(storage),
+            Duration::from_millis(50),
+            &tokio::runtime::Handle::current(),
+        );
+        for tokens in 1..=3 {
+            let stats = TokenStats {
+                total_input_tokens: tokens,
+                ..Default::default()
+            };
+            writer.queue().save_token_stats(stats);
+        }
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        writer.flush().await;
+    }
+
+    #[tokio::test]
+    async fn test_flush_saves_pending_stats() {
+        let mut storage = MockStorageService::new();
+        storage
+            .expect_save_token_stats()
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let writer = WriteBehind::spawn(
+            Arc::new(storage),
+            Duration::from_secs(60),
+            &tokio::runtime::Handle::current(),
+        );
+        writer.queue().save_token_stats(TokenStats::default());
+        writer.flush().await;
+    }
+
+    #[tokio::test]
+    async fn test_sessions_keep_newest_snapshot_in_queue_order() {
+        let saved = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut storage = MockStorageService::new();
+        let log = Arc::clone(&saved);
+        storage
+            .expect_save_session()
+            .times(2)
+            .returning(move |session| {
+                log.lock()
+                    .unwrap()
+                    .push((session.id.clone(), session.title.clone()));
+                Ok(())
+            });
+
+        let writer = WriteBehind::spawn(
+            Arc::new(storage),
+            Duration::from_secs(60),
+            &tokio::runtime::Handle::current(),
+        );
+        let session = |id: &str, title: &str| {
+            let mut session = ConversationSession::new("assistant".to_string());
+            session.id = id.to_string();
+            session.title = title.to_string();
+            session
+        };
+        writer.queue().save_session(session("a", "first"));
+        writer.queue().save_session(session("b", "other"));
+        writer.queue().save_session(session("a", "second"));
+        writer.flush().await;
+
+        assert_eq!(
+            *saved.lock().unwrap(),
+            vec![
+                ("b".to_string(), "other".to_string()),
+                ("a".to_string(), "second".to_string()),
+            ]
+        );
+    }
+}
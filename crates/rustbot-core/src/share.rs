@@ -0,0 +1,232 @@
+// Conversation sharing: a self-contained HTML page or a GitHub gist
+//
+// Design Decision: Share the HTML export, optionally redacted first
+//
+// Rationale: The HTML export already inlines diagrams and formulas as images
+// and highlights code blocks with inline colors, so a single file opens
+// anywhere without fetching anything. Sharing builds that page; a gist is
+// the same page uploaded with one click so there is a link to send.
+//
+// Redaction: Conversations pasted from a terminal or a config file often
+// carry things that shouldn't be published. The optional pass runs every
+// message, tool argument and tool result through `crate::redact` (API keys,
+// tokens, registered secrets) and also replaces email addresses and the
+// user's home directory, which give away who wrote the conversation.
+//
+// Configuration: Gists are created with a GitHub token that has the `gist`
+// scope, read from the keychain (or environment) as `GITHUB_TOKEN_SECRET`.
+//
+// Trade-offs:
+// - Gists are secret (unlisted) unless the user asks for a public one;
+//   anyone with the link can still read a secret gist
+// - Redaction is pattern based: a secret of an unknown shape that was never
+//   registered is published as is
+// - Uploading is refused in local-only mode and while offline
+
+use crate::conversation_export::{ConversationExport, ConversationFormat, ExportEntry};
+use crate::error::Result;
+use crate::redact::REDACTED;
+use crate::services::traits::ConversationSession;
+use regex::Regex;
+use serde::Deserialize;
+use std::sync::OnceLock;
+use thiserror::Error;
+
+/// Keychain entry (and environment variable) holding the GitHub token
+pub const GITHUB_TOKEN_SECRET: &str = "GITHUB_TOKEN";
+
+/// GitHub endpoint gists are created at
+pub const GIST_API_URL: &str = "https://api.github.com/gists";
+
+/// A gist that couldn't be created
+#[derive(Debug, Error)]
+pub enum ShareError {
+    #[error(transparent)]
+    Blocked(#[from] crate::privacy::EgressBlocked),
+
+    #[error(transparent)]
+    Offline(#[from] crate::connectivity::Offline),
+
+    #[error("No GitHub token: add one with the gist scope to share to a gist")]
+    NoToken,
+
+    #[error("Couldn't reach GitHub: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("GitHub refused the gist ({status}): {message}")]
+    Rejected { status: u16, message: String },
+
+    #[error("Couldn't render the page: {0}")]
+    Render(#[from] crate::error::RustbotError),
+}
+
+/// The conversation as a shareable HTML page
+///
+/// # Arguments
+/// * `session` - Conversation to share, with its tool history
+/// * `redact` - Remove secrets, email addresses and the home directory first
+///
+/// # Errors
+/// - Rendering failure
+pub fn share_page(session: &ConversationSession, redact: bool) -> Result<String> {
+    let mut export = ConversationExport::from_session(session);
+    if redact {
+        redact_export(&mut export);
+    }
+    export.render(ConversationFormat::Html)
+}
+
+/// Upload a page as a gist
+///
+/// # Arguments
+/// * `token` - GitHub token with the `gist` scope (empty = `NoToken`)
+/// * `file_name` - Name of the file in the gist
+/// * `content` - File content
+/// * `public` - List the gist on the user's profile (otherwise secret)
+///
+/// # Returns
+/// Address of the new gist
+///
+/// # Errors
+/// - Local-only mode or offline
+/// - No token, GitHub unreachable or the gist refused
+pub async fn upload_gist(
+    token: &str,
+    file_name: &str,
+    content: &str,
+    public: bool,
+) -> std::result::Result<String, ShareError> {
+    crate::privacy::check_url(GIST_API_URL)?;
+    crate::connectivity::check_online(crate::connectivity::GISTS)?;
+    if token.trim().is_empty() {
+        return Err(ShareError::NoToken);
+    }
+
+    let body = serde_json::json!({
+        "description": "Conversation shared from Rustbot",
+        "public": public,
+        "files": { file_name: { "content": content } },
+    });
+    let response = crate::llm::shared_client()
+        .post(GIST_API_URL)
+        .bearer_auth(token.trim())
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "rustbot")
+        .json(&body)
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let message = response
+            .json::<serde_json::Value>()
+            .await
+            .ok()
+            .and_then(|body| body["message"].as_str().map(str::to_string))
+            .unwrap_or_else(|| status.to_string());
+        return Err(ShareError::Rejected {
+            status: status.as_u16(),
+            message,
+        });
+    }
+
+    #[derive(Deserialize)]
+    struct Gist {
+        html_url: String,
+    }
+    let gist: Gist = response.json().await?;
+    tracing::info!("🔗 Shared conversation as {}", gist.html_url);
+    Ok(gist.html_url)
+}
+
+/// Redact every message, tool argument and tool result
+fn redact_export(export: &mut ConversationExport) {
+    export.title = redact_text(&export.title);
+    for entry in &mut export.entries {
+        match entry {
+            ExportEntry::Message { content, .. } | ExportEntry::ToolResult { content, .. } => {
+                *content = redact_text(content);
+            }
+            ExportEntry::ToolCall { arguments, .. } => redact_json(arguments),
+        }
+    }
+}
+
+/// Redact the strings in a JSON value
+fn redact_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(text) => *text = redact_text(text),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_json),
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+/// Secrets, email addresses and the home directory removed from text
+fn redact_text(text: &str) -> String {
+    static EMAIL: OnceLock<Regex> = OnceLock::new();
+    let email = EMAIL.get_or_init(|| {
+        Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").expect("Invalid regex")
+    });
+
+    let mut text = crate::redact::redact(text).into_owned();
+    if let Some(home) = dirs::home_dir() {
+        let home = home.display().to_string();
+        // "/" as home would replace every slash
+        if home.len() > 1 {
+            text = text.replace(&home, "~");
+        }
+    }
+    email.replace_all(&text, REDACTED).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::traits::SessionMessage;
+
+    #[test]
+    fn test_share_page_redacts() {
+        let home = dirs::home_dir().unwrap_or_default().display().to_string();
+        let mut session = ConversationSession::new("assistant");
+        session.messages.push(SessionMessage {
+            role: "user".to_string(),
+            content: format!(
+                "Why does {}/project fail with key sk-or-v1-abcdefghijklmnop1234? \
+                 Mail me at ada@example.com",
+                home
+            ),
+            timestamp: chrono::Utc::now(),
+            input_tokens: None,
+            output_tokens: None,
+            agent_id: None,
+            model: None,
+            first_token_ms: None,
+            generation_ms: None,
+            pinned: false,
+            feedback: None,
+        });
+
+        let page = share_page(&session, true).unwrap();
+        assert!(!page.contains("sk-or-v1-abcdefghijklmnop1234"));
+        assert!(!page.contains("ada@example.com"));
+        assert!(page.contains("~/project"));
+        assert!(page.contains(REDACTED));
+
+        let page = share_page(&session, false).unwrap();
+        assert!(page.contains("ada@example.com"));
+    }
+
+    #[test]
+    fn test_redact_tool_arguments() {
+        let mut arguments = serde_json::json!({
+            "to": ["ada@example.com"],
+            "headers": { "Authorization": "Bearer abcdefghijklmnop" },
+            "count": 2,
+        });
+        redact_json(&mut arguments);
+        assert_eq!(arguments["to"][0], REDACTED);
+        assert!(!arguments.to_string().contains("abcdefghijklmnop"));
+        assert_eq!(arguments["count"], 2);
+    }
+}
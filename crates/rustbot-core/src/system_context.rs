@@ -0,0 +1,233 @@
+// System context block sent ahead of the conversation
+//
+// Design Decision: The user profile decides which facts about the machine and
+// the user go into the "System Context" block; `SystemContext::render`
+// assembles it from those settings
+//
+// Rationale: Telling the model the date, the platform or the user's name makes
+// answers fit ("what's on today", shell commands for the right OS), but the
+// hostname, account name and email identify the person and machine to
+// whichever provider answers. Each field can be left out in Preferences, and
+// users can add their own entries instead (a project, a role, a preferred
+// language). The GUI renders the block before every message and hands it to
+// `RustbotApi::set_system_context`, so the model sees the current time.
+//
+// Trade-offs:
+// - Every field defaults to on, matching what was sent before the settings
+//   existed; users opt out rather than in
+// - Custom entries are sent verbatim; they are not checked for secrets
+
+use crate::services::traits::UserProfile;
+use serde::{Deserialize, Serialize};
+
+/// Which fields the system context includes, plus the user's own entries
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SystemContextSettings {
+    pub date_time: bool,
+    pub model: bool,
+    pub application: bool,
+    pub operating_system: bool,
+    pub hostname: bool,
+    pub username: bool,
+
+    /// Name, email, timezone and location from the user profile
+    pub user_profile: bool,
+
+    /// Extra entries, sent in order after the built-in fields
+    pub custom: Vec<ContextEntry>,
+}
+
+impl Default for SystemContextSettings {
+    fn default() -> Self {
+        Self {
+            date_time: true,
+            model: true,
+            application: true,
+            operating_system: true,
+            hostname: true,
+            username: true,
+            user_profile: true,
+            custom: Vec::new(),
+        }
+    }
+}
+
+impl SystemContextSettings {
+    /// The built-in fields with their labels, for settings checkboxes
+    pub fn fields_mut(&mut self) -> [(&'static str, &mut bool); 7] {
+        [
+            ("Date and time", &mut self.date_time),
+            ("Model", &mut self.model),
+            ("Rustbot version", &mut self.application),
+            ("Operating system", &mut self.operating_system),
+            ("Hostname", &mut self.hostname),
+            ("System user name", &mut self.username),
+            ("Name, email, timezone and location", &mut self.user_profile),
+        ]
+    }
+}
+
+/// A key/value line the user added to the system context
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextEntry {
+    pub key: String,
+    pub value: String,
+}
+
+/// Facts available for the system context at the time of a request
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemContext {
+    /// e.g. "2025-11-20 14:03:11 +01:00 (Thursday)"
+    pub date_time: String,
+    pub model: String,
+    pub application: String,
+    /// e.g. "macos (aarch64)"
+    pub operating_system: String,
+    pub hostname: String,
+    pub username: String,
+    /// Profile lines as (label, value)
+    pub profile: Vec<(&'static str, String)>,
+}
+
+impl SystemContext {
+    /// Collect the facts about this machine and user right now
+    ///
+    /// # Arguments
+    /// * `model` - Model that will answer
+    /// * `application` - App name and version, e.g. "Rustbot v0.4.0"
+    /// * `profile` - The user profile
+    pub fn current(model: &str, application: &str, profile: &UserProfile) -> Self {
+        let now = chrono::Local::now();
+        let mut profile_lines = Vec::new();
+        if !profile.name.is_empty() {
+            profile_lines.push(("User Name", profile.name.clone()));
+        }
+        if !profile.email.is_empty() {
+            profile_lines.push(("User Email", profile.email.clone()));
+        }
+        // Timezone and location are only shown alongside a name or email
+        if !profile_lines.is_empty() {
+            if let Some(timezone) = &profile.timezone {
+                profile_lines.push(("User Timezone", timezone.clone()));
+            }
+            if let Some(location) = &profile.location {
+                profile_lines.push(("User Location", location.clone()));
+            }
+        }
+
+        Self {
+            date_time: format!(
+                "{} ({})",
+                now.format("%Y-%m-%d %H:%M:%S %Z"),
+                now.format("%A")
+            ),
+            model: model.to_string(),
+            application: application.to_string(),
+            operating_system: format!("{} ({})", std::env::consts::OS, std::env::consts::ARCH),
+            hostname: std::env::var("HOSTNAME")
+                .or_else(|_| std::env::var("COMPUTERNAME"))
+                .unwrap_or_else(|_| "unknown".to_string()),
+            username: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "unknown".to_string()),
+            profile: profile_lines,
+        }
+    }
+
+    /// The "System Context" block with the fields `settings` allows
+    ///
+    /// # Returns
+    /// The markdown block, or an empty string if every field is off and there
+    /// are no custom entries
+    pub fn render(&self, settings: &SystemContextSettings) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        let mut field = |enabled: bool, label: &str, value: &str| {
+            if enabled {
+                lines.push(format!("**{}**: {}", label, value));
+            }
+        };
+        field(settings.date_time, "Current Date & Time", &self.date_time);
+        field(settings.model, "LLM Model", &self.model);
+        field(settings.application, "Application", &self.application);
+        field(
+            settings.operating_system,
+            "Operating System",
+            &self.operating_system,
+        );
+        field(settings.hostname, "Hostname", &self.hostname);
+        field(settings.username, "User", &self.username);
+        for (label, value) in &self.profile {
+            field(settings.user_profile, label, value);
+        }
+        for entry in &settings.custom {
+            let (key, value) = (entry.key.trim(), entry.value.trim());
+            if !key.is_empty() && !value.is_empty() {
+                field(true, key, value);
+            }
+        }
+
+        if lines.is_empty() {
+            return String::new();
+        }
+        format!(
+            "## System Context\n\n{}\n\nThis information is provided automatically to give you \
+             context about the current system environment.",
+            lines.join("\n")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> SystemContext {
+        SystemContext {
+            date_time: "2025-11-20 14:03:11 +01:00 (Thursday)".to_string(),
+            model: "anthropic/claude-sonnet-4.5".to_string(),
+            application: "Rustbot v0.4.0".to_string(),
+            operating_system: "linux (x86_64)".to_string(),
+            hostname: "ana-laptop".to_string(),
+            username: "ana".to_string(),
+            profile: vec![("User Name", "Ana Silva".to_string())],
+        }
+    }
+
+    #[test]
+    fn test_render_respects_settings() {
+        let all = context().render(&SystemContextSettings::default());
+        assert!(all.starts_with("## System Context\n\n**Current Date & Time**: 2025-11-20"));
+        assert!(all.contains("**Hostname**: ana-laptop\n**User**: ana\n**User Name**: Ana Silva"));
+
+        let mut settings = SystemContextSettings {
+            hostname: false,
+            username: false,
+            user_profile: false,
+            ..SystemContextSettings::default()
+        };
+        settings.custom.push(ContextEntry {
+            key: "Project".to_string(),
+            value: " Rustbot ".to_string(),
+        });
+        settings.custom.push(ContextEntry::default());
+        let private = context().render(&settings);
+        assert!(!private.contains("ana"));
+        assert!(private.contains("**Operating System**: linux (x86_64)\n**Project**: Rustbot\n"));
+
+        for (_, enabled) in settings.fields_mut() {
+            *enabled = false;
+        }
+        settings.custom.clear();
+        assert_eq!(context().render(&settings), "");
+    }
+
+    #[test]
+    fn test_missing_settings_default_to_on() {
+        let settings: SystemContextSettings =
+            serde_json::from_str(r#"{"hostname": false}"#).unwrap();
+        assert!(!settings.hostname);
+        assert!(settings.username);
+        assert!(settings.custom.is_empty());
+    }
+}
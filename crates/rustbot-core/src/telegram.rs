@@ -0,0 +1,540 @@
+// Telegram bot bridge (`rustbot telegram`)
+//
+// Design Decision: Long polling with getUpdates
+//
+// Rationale: Polling needs no public URL, TLS certificate or open port, so the
+// bridge can run on a laptop behind NAT and still be reachable from a phone.
+// Each Telegram chat is its own conversation (see `bot_sessions`), answered
+// by the configured agents with the same tools as the desktop app, including
+// tools from enabled MCP plugins.
+//
+// Setup:
+// 1. Create a bot with @BotFather and export TELEGRAM_BOT_TOKEN
+// 2. List the Telegram user IDs allowed to chat in ~/.rustbot/telegram.json
+//    (message the bot once; it replies with your ID if you're not listed)
+// 3. Run `rustbot telegram`
+//
+// Chat commands:
+//     /start          greeting and help
+//     /agents         list agents
+//     /agent <id>     switch the agent for this chat
+//     /reset          start a new conversation
+//     anything else   sent to the agent (the reply streams via message edits)
+//
+// Security:
+// - Only users in `allowed_users` are answered; an empty list allows nobody
+// - The bot token is read from the environment, never from the config file
+//
+// Trade-offs:
+// - Replies are plain text (no parse_mode) so model Markdown can't break sends
+// - Responses over 4096 characters continue in additional messages
+
+use crate::bot_sessions::{split_message, BotSessions};
+use crate::error::Result as RustbotResult;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+/// Environment variable holding the bot token from @BotFather
+pub const BOT_TOKEN_ENV_VAR: &str = "TELEGRAM_BOT_TOKEN";
+
+/// Maximum message length accepted by Telegram
+pub const MAX_MESSAGE_LEN: usize = 4096;
+
+/// Delay before polling again after a failed getUpdates call
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+const HELP: &str = "\
+Send a message to chat with Rustbot.
+
+/agents - list agents
+/agent <id> - switch agent for this chat
+/reset - start a new conversation";
+
+fn default_poll_timeout_secs() -> u64 {
+    30
+}
+
+fn default_edit_interval_ms() -> u64 {
+    1500
+}
+
+/// Telegram bridge configuration
+///
+/// File Format: JSON
+/// Location: ~/.rustbot/telegram.json
+///
+/// Example:
+///     {
+///       "allowed_users": [123456789],
+///       "default_agent": "assistant"
+///     }
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TelegramConfig {
+    /// Telegram user IDs allowed to use the bot
+    #[serde(default)]
+    pub allowed_users: Vec<i64>,
+
+    /// Agent for new chats (defaults to the primary agent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_agent: Option<String>,
+
+    /// Long-polling timeout for getUpdates
+    #[serde(default = "default_poll_timeout_secs")]
+    pub poll_timeout_secs: u64,
+
+    /// Minimum time between streaming edits of a reply
+    #[serde(default = "default_edit_interval_ms")]
+    pub edit_interval_ms: u64,
+}
+
+impl TelegramConfig {
+    /// Default config location: ~/.rustbot/telegram.json
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("telegram.json")
+    }
+
+    /// Load configuration from a file
+    ///
+    /// # Returns
+    /// Ok(None) if the file doesn't exist, Ok(Some) if loaded
+    ///
+    /// # Errors
+    /// - File exists but cannot be read
+    /// - Invalid JSON
+    pub fn load(path: &Path) -> RustbotResult<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// Whether a Telegram user may chat with the bot
+    pub fn is_allowed(&self, user_id: i64) -> bool {
+        self.allowed_users.contains(&user_id)
+    }
+}
+
+/// Telegram Bot API response envelope
+#[derive(Debug, Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+/// One update from getUpdates (only the fields we use)
+#[derive(Debug, Clone, Deserialize)]
+pub struct Update {
+    pub update_id: i64,
+    pub message: Option<Message>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Message {
+    pub message_id: i64,
+    pub chat: Chat,
+    pub from: Option<User>,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Chat {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct User {
+    pub id: i64,
+}
+
+/// Commands understood in a chat
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatCommand {
+    Start,
+    Agents,
+    Agent(String),
+    Reset,
+    Message(String),
+}
+
+impl ChatCommand {
+    /// Parse an incoming text message
+    ///
+    /// Commands may carry the bot's username (`/reset@my_bot`), as Telegram
+    /// adds it in group chats. Unknown commands are sent as messages.
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        let (command, arg) = match text.split_once(char::is_whitespace) {
+            Some((command, arg)) => (command, arg.trim()),
+            None => (text, ""),
+        };
+        let command = command.split('@').next().unwrap_or_default();
+
+        match command {
+            "/start" | "/help" => Self::Start,
+            "/agents" => Self::Agents,
+            "/agent" if !arg.is_empty() => Self::Agent(arg.to_string()),
+            "/reset" => Self::Reset,
+            _ => Self::Message(text.to_string()),
+        }
+    }
+}
+
+/// Minimal Telegram Bot API client
+#[derive(Clone)]
+pub struct TelegramClient {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl TelegramClient {
+    pub fn new(bot_token: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: format!("https://api.telegram.org/bot{}", bot_token),
+        }
+    }
+
+    async fn call<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+        timeout: Duration,
+    ) -> Result<T> {
+        crate::privacy::check_url(&self.base_url)?;
+        let response: ApiResponse<T> = self
+            .http
+            .post(format!("{}/{}", self.base_url, method))
+            .timeout(timeout)
+            .json(&params)
+            .send()
+            .await
+            // The URL contains the bot token; keep it out of error messages
+            .map_err(reqwest::Error::without_url)
+            .with_context(|| format!("Telegram {} request failed", method))?
+            .json()
+            .await
+            .map_err(reqwest::Error::without_url)
+            .with_context(|| format!("Invalid Telegram {} response", method))?;
+
+        match response.result {
+            Some(result) if response.ok => Ok(result),
+            _ => anyhow::bail!(
+                "Telegram {} failed: {}",
+                method,
+                response.description.unwrap_or_default()
+            ),
+        }
+    }
+
+    /// Wait up to `timeout_secs` for updates after `offset`
+    pub async fn get_updates(&self, offset: i64, timeout_secs: u64) -> Result<Vec<Update>> {
+        self.call(
+            "getUpdates",
+            serde_json::json!({
+                "offset": offset,
+                "timeout": timeout_secs,
+                "allowed_updates": ["message"]
+            }),
+            Duration::from_secs(timeout_secs + 10),
+        )
+        .await
+    }
+
+    /// Send a text message and return its ID
+    pub async fn send_message(&self, chat_id: i64, text: &str) -> Result<i64> {
+        let message: Message = self
+            .call(
+                "sendMessage",
+                serde_json::json!({ "chat_id": chat_id, "text": text }),
+                Duration::from_secs(30),
+            )
+            .await?;
+        Ok(message.message_id)
+    }
+
+    /// Replace the text of a message the bot sent
+    pub async fn edit_message(&self, chat_id: i64, message_id: i64, text: &str) -> Result<()> {
+        self.call::<serde_json::Value>(
+            "editMessageText",
+            serde_json::json!({ "chat_id": chat_id, "message_id": message_id, "text": text }),
+            Duration::from_secs(30),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// Poll for messages and answer them until Ctrl-C
+///
+/// # Arguments
+/// * `config` - Bridge configuration (allowlist, default agent)
+/// * `bot_token` - Token from @BotFather
+/// * `sessions` - Conversations backed by the shared API
+///
+/// # Errors
+/// Only if the bot token is rejected on startup; network errors while
+/// polling are logged and retried.
+pub async fn run(config: TelegramConfig, bot_token: &str, sessions: BotSessions) -> Result<()> {
+    let client = TelegramClient::new(bot_token);
+    let config = Arc::new(config);
+    let sessions = Arc::new(sessions);
+
+    // Fail fast on a bad token instead of retrying forever
+    client
+        .call::<serde_json::Value>("getMe", serde_json::json!({}), Duration::from_secs(30))
+        .await
+        .context("Telegram rejected the bot token")?;
+
+    if config.allowed_users.is_empty() {
+        tracing::warn!("No allowed_users configured; the bot will not answer anyone");
+    }
+    tracing::info!("🤖 Telegram bridge polling for messages");
+
+    let mut offset = 0;
+    loop {
+        let updates = tokio::select! {
+            updates = client.get_updates(offset, config.poll_timeout_secs) => updates,
+            _ = tokio::signal::ctrl_c() => {
+                tracing::info!("Shutting down Telegram bridge");
+                return Ok(());
+            }
+        };
+
+        let updates = match updates {
+            Ok(updates) => updates,
+            Err(e) => {
+                tracing::warn!("{:#}; retrying in {}s", e, RETRY_DELAY.as_secs());
+                tokio::time::sleep(RETRY_DELAY).await;
+                continue;
+            }
+        };
+
+        for update in updates {
+            offset = offset.max(update.update_id + 1);
+            let Some(message) = update.message else {
+                continue;
+            };
+
+            let (client, config, sessions) =
+                (client.clone(), Arc::clone(&config), Arc::clone(&sessions));
+            tokio::spawn(async move {
+                handle_message(&client, &config, &sessions, message).await;
+            });
+        }
+    }
+}
+
+/// Answer one incoming message
+async fn handle_message(
+    client: &TelegramClient,
+    config: &TelegramConfig,
+    sessions: &BotSessions,
+    message: Message,
+) {
+    let chat_id = message.chat.id;
+    let (Some(user), Some(text)) = (message.from, message.text) else {
+        return;
+    };
+
+    if !config.is_allowed(user.id) {
+        tracing::warn!(
+            "Ignoring Telegram message from user {} (not allowed)",
+            user.id
+        );
+        reply(
+            client,
+            chat_id,
+            &format!(
+                "You're not allowed to use this bot. Your Telegram user ID is {}.",
+                user.id
+            ),
+        )
+        .await;
+        return;
+    }
+
+    let key = format!("telegram-{}", chat_id);
+    match ChatCommand::parse(&text) {
+        ChatCommand::Start => reply(client, chat_id, HELP).await,
+        ChatCommand::Agents => {
+            let agents = sessions.agents().await;
+            let text = if agents.is_empty() {
+                "No agents are configured.".to_string()
+            } else {
+                agents
+                    .iter()
+                    .map(|id| format!("• {}", id))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            reply(client, chat_id, &text).await;
+        }
+        ChatCommand::Agent(agent) => {
+            let text = match sessions.set_agent(&key, &agent).await {
+                Ok(()) => format!("Switched to '{}'", agent),
+                Err(e) => format!("⚠️ {:#}", e),
+            };
+            reply(client, chat_id, &text).await;
+        }
+        ChatCommand::Reset => {
+            sessions.reset(&key).await;
+            reply(client, chat_id, "Started a new conversation.").await;
+        }
+        ChatCommand::Message(text) => answer(client, config, sessions, chat_id, &key, &text).await,
+    }
+}
+
+/// Send the message to the agent and stream the reply into a placeholder
+async fn answer(
+    client: &TelegramClient,
+    config: &TelegramConfig,
+    sessions: &BotSessions,
+    chat_id: i64,
+    key: &str,
+    text: &str,
+) {
+    let placeholder = match client.send_message(chat_id, "…").await {
+        Ok(id) => id,
+        Err(e) => {
+            tracing::warn!("Failed to send Telegram reply: {:#}", e);
+            return;
+        }
+    };
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let interval = Duration::from_millis(config.edit_interval_ms);
+    let editor = tokio::spawn(stream_edits(
+        client.clone(),
+        chat_id,
+        placeholder,
+        rx,
+        interval,
+    ));
+
+    let result = sessions.send(key, None, text, Some(tx)).await;
+    let _ = editor.await;
+
+    let parts = match result {
+        Ok(response) => split_message(&response, MAX_MESSAGE_LEN),
+        Err(e) => {
+            tracing::warn!("Telegram request in {} failed: {:#}", key, e);
+            vec![format!("⚠️ {:#}", e)]
+        }
+    };
+
+    let mut parts = parts.into_iter();
+    if let Some(first) = parts.next() {
+        if let Err(e) = client.edit_message(chat_id, placeholder, &first).await {
+            tracing::debug!("Final Telegram edit failed: {:#}", e);
+        }
+    }
+    for part in parts {
+        if let Err(e) = client.send_message(chat_id, &part).await {
+            tracing::warn!("Failed to send Telegram reply: {:#}", e);
+            break;
+        }
+    }
+}
+
+/// Edit the placeholder with the text so far, at most once per interval
+async fn stream_edits(
+    client: TelegramClient,
+    chat_id: i64,
+    message_id: i64,
+    mut chunks: mpsc::UnboundedReceiver<String>,
+    interval: Duration,
+) {
+    let mut text = String::new();
+    let mut dirty = false;
+    let mut next_edit = Instant::now() + interval;
+
+    loop {
+        tokio::select! {
+            chunk = chunks.recv() => match chunk {
+                Some(chunk) => {
+                    text.push_str(&chunk);
+                    dirty = true;
+                }
+                None => break,
+            },
+            _ = tokio::time::sleep_until(next_edit), if dirty => {
+                let preview: String = text.chars().take(MAX_MESSAGE_LEN - 2).collect();
+                let preview = format!("{} ▌", preview);
+                if let Err(e) = client.edit_message(chat_id, message_id, &preview).await {
+                    tracing::debug!("Streaming edit failed: {:#}", e);
+                }
+                dirty = false;
+                next_edit = Instant::now() + interval;
+            }
+        }
+    }
+}
+
+async fn reply(client: &TelegramClient, chat_id: i64, text: &str) {
+    if let Err(e) = client.send_message(chat_id, text).await {
+        tracing::warn!("Failed to send Telegram reply: {:#}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(ChatCommand::parse("/start"), ChatCommand::Start);
+        assert_eq!(ChatCommand::parse("/reset@rustbot_bot"), ChatCommand::Reset);
+        assert_eq!(
+            ChatCommand::parse("/agent  researcher "),
+            ChatCommand::Agent("researcher".to_string())
+        );
+        assert_eq!(
+            ChatCommand::parse("/agent"),
+            ChatCommand::Message("/agent".to_string())
+        );
+        assert_eq!(
+            ChatCommand::parse("what's in /etc/hosts?"),
+            ChatCommand::Message("what's in /etc/hosts?".to_string())
+        );
+    }
+
+    #[test]
+    fn test_allowlist_defaults_to_nobody() {
+        let config: TelegramConfig = serde_json::from_str("{}").unwrap();
+        assert!(!config.is_allowed(42));
+        assert_eq!(config.poll_timeout_secs, 30);
+
+        let config: TelegramConfig = serde_json::from_str(r#"{"allowed_users": [42]}"#).unwrap();
+        assert!(config.is_allowed(42));
+        assert!(!config.is_allowed(43));
+    }
+
+    #[test]
+    fn test_parse_updates() {
+        let body = r#"{"ok": true, "result": [
+            {"update_id": 7, "message": {"message_id": 1, "chat": {"id": -100, "type": "group"},
+             "from": {"id": 42, "is_bot": false, "first_name": "A"}, "text": "hi"}},
+            {"update_id": 8, "edited_message": {}}
+        ]}"#;
+        let response: ApiResponse<Vec<Update>> = serde_json::from_str(body).unwrap();
+        let updates = response.result.unwrap();
+
+        assert_eq!(updates.len(), 2);
+        let message = updates[0].message.as_ref().unwrap();
+        assert_eq!(message.chat.id, -100);
+        assert_eq!(message.from.as_ref().unwrap().id, 42);
+        assert_eq!(message.text.as_deref(), Some("hi"));
+        assert!(updates[1].message.is_none());
+    }
+}
@@ -0,0 +1,521 @@
+// Color themes: built-in light/dark palettes and user palettes from TOML
+//
+// Design Decision: Palettes are plain data here; the desktop app maps them
+// onto egui visuals (src/ui/theme.rs)
+//
+// Rationale: Views used to pick colors inline (a gray for hints, a green for
+// success…), tuned for the light theme only, so dark mode left dim text on
+// dark panels. A palette names each role once — background, accent, muted
+// text, error — and every view asks for the role instead of an RGB value.
+// Keeping the data in core lets tests cover parsing without a GUI.
+//
+// User themes: ~/.rustbot/themes/*.toml, for example
+//     name = "Solarized Dark"
+//     base = "dark"              # colors left out come from this palette
+//     background = "#002b36"
+//     accent = "#268bd2"
+//
+// The profile's `theme` field holds "light", "dark", "system" (follow the OS),
+// "high-contrast" or the name of a user theme. The high-contrast palette is
+// white and yellow on black, with every text role at least 7:1 against the
+// background (WCAG AAA), and makes the app draw thicker outlines.
+//
+// Text size is separate from the palette: the profile's `font_size` sets the
+// body text size (the other text styles scale with it, see `FontSizes`) and
+// `ui_scale` zooms the whole interface, spacing included.
+//
+// Trade-offs:
+// - A missing or broken user theme falls back to the light palette
+// - Palettes can't set fonts; size and scale are the same for every theme
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::path::{Path, PathBuf};
+
+/// Theme name for the built-in light palette
+pub const LIGHT: &str = "light";
+
+/// Theme name for the built-in dark palette
+pub const DARK: &str = "dark";
+
+/// Theme name that follows the OS light/dark setting
+pub const SYSTEM: &str = "system";
+
+/// Theme name for the built-in high-contrast palette
+pub const HIGH_CONTRAST: &str = "high-contrast";
+
+/// Body text size when the profile doesn't set one, in points
+pub const DEFAULT_FONT_SIZE: f32 = 16.0;
+
+/// Body text size limits for the Preferences slider
+pub const MIN_FONT_SIZE: f32 = 10.0;
+pub const MAX_FONT_SIZE: f32 = 28.0;
+
+/// Interface zoom when the profile doesn't set one
+pub const DEFAULT_UI_SCALE: f32 = 1.0;
+
+/// Interface zoom limits for the Preferences slider
+pub const MIN_UI_SCALE: f32 = 0.75;
+pub const MAX_UI_SCALE: f32 = 2.0;
+
+/// Sizes of each text style for a body text size, in points
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontSizes {
+    pub heading: f32,
+    pub body: f32,
+    pub button: f32,
+    pub small: f32,
+    pub monospace: f32,
+}
+
+impl FontSizes {
+    /// Text style sizes for a body size, keeping the default proportions
+    /// (heading 24, body and buttons 16, small and code 14)
+    ///
+    /// Out-of-range sizes are clamped to `MIN_FONT_SIZE..=MAX_FONT_SIZE`.
+    pub fn for_body(body: f32) -> Self {
+        let body = if body.is_finite() {
+            body.clamp(MIN_FONT_SIZE, MAX_FONT_SIZE)
+        } else {
+            DEFAULT_FONT_SIZE
+        };
+        let scaled = |size: f32| (size * body / DEFAULT_FONT_SIZE).round();
+        Self {
+            heading: scaled(24.0),
+            body,
+            button: body,
+            small: scaled(14.0),
+            monospace: scaled(14.0),
+        }
+    }
+}
+
+/// Interface zoom clamped to `MIN_UI_SCALE..=MAX_UI_SCALE`
+pub fn clamp_ui_scale(scale: f32) -> f32 {
+    if scale.is_finite() {
+        scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE)
+    } else {
+        DEFAULT_UI_SCALE
+    }
+}
+
+/// An sRGB color, written as "#rrggbb" in theme files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    /// Parse "#rrggbb" (the # is optional)
+    pub fn parse(value: &str) -> Option<Self> {
+        let hex = value.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Self(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    /// Contrast ratio with another color, from 1.0 (same) to 21.0
+    /// (black on white), as defined by WCAG 2
+    pub fn contrast_ratio(self, other: Rgb) -> f32 {
+        let (a, b) = (self.luminance(), other.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// WCAG relative luminance
+    fn luminance(self) -> f32 {
+        let linear = |channel: u8| {
+            let c = channel as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(self.0) + 0.7152 * linear(self.1) + 0.0722 * linear(self.2)
+    }
+}
+
+impl Serialize for Rgb {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2))
+    }
+}
+
+impl<'de> Deserialize<'de> for Rgb {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Self::parse(&value).ok_or_else(|| {
+            serde::de::Error::custom(format!("invalid color '{}' (expected #rrggbb)", value))
+        })
+    }
+}
+
+/// Colors for each UI role
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Palette {
+    pub name: String,
+
+    /// Start from egui's dark visuals (affects colors not listed here)
+    pub dark: bool,
+
+    /// Draw thicker widget outlines and focus rings
+    pub high_contrast: bool,
+
+    /// Side and central panels
+    pub background: Rgb,
+    /// Windows and popups
+    pub surface: Rgb,
+    /// Text fields and code blocks
+    pub input: Rgb,
+    /// Group frames and other non-interactive widgets
+    pub frame: Rgb,
+    /// Buttons and other interactive widgets
+    pub widget: Rgb,
+    pub widget_hover: Rgb,
+    pub border: Rgb,
+
+    pub text: Rgb,
+    /// Hints, timestamps and secondary labels
+    pub muted: Rgb,
+    /// Placeholders and disabled items
+    pub subtle: Rgb,
+
+    /// Selection, active widgets and links
+    pub accent: Rgb,
+    /// "You" label in the chat
+    pub user: Rgb,
+    /// "Assistant" label in the chat
+    pub assistant: Rgb,
+    pub success: Rgb,
+    pub warning: Rgb,
+    pub error: Rgb,
+}
+
+impl Palette {
+    /// Built-in light palette
+    pub fn light() -> Self {
+        Self {
+            name: LIGHT.to_string(),
+            dark: false,
+            high_contrast: false,
+            background: Rgb(248, 248, 250),
+            surface: Rgb(255, 255, 255),
+            input: Rgb(250, 250, 252),
+            frame: Rgb(245, 245, 247),
+            widget: Rgb(240, 240, 242),
+            widget_hover: Rgb(230, 230, 235),
+            border: Rgb(210, 210, 215),
+            text: Rgb(50, 50, 50),
+            muted: Rgb(120, 120, 120),
+            subtle: Rgb(150, 150, 150),
+            accent: Rgb(60, 120, 220),
+            user: Rgb(45, 100, 200),
+            assistant: Rgb(60, 150, 60),
+            success: Rgb(60, 150, 60),
+            warning: Rgb(200, 150, 50),
+            error: Rgb(200, 60, 60),
+        }
+    }
+
+    /// Built-in dark palette (deep grays rather than black)
+    pub fn dark() -> Self {
+        Self {
+            name: DARK.to_string(),
+            dark: true,
+            high_contrast: false,
+            background: Rgb(30, 30, 35),
+            surface: Rgb(25, 25, 28),
+            input: Rgb(20, 20, 23),
+            frame: Rgb(40, 40, 45),
+            widget: Rgb(45, 45, 50),
+            widget_hover: Rgb(55, 55, 65),
+            border: Rgb(60, 60, 65),
+            text: Rgb(230, 230, 230),
+            muted: Rgb(160, 160, 165),
+            subtle: Rgb(120, 120, 125),
+            accent: Rgb(100, 160, 255),
+            user: Rgb(110, 160, 240),
+            assistant: Rgb(100, 190, 110),
+            success: Rgb(100, 190, 110),
+            warning: Rgb(230, 180, 80),
+            error: Rgb(235, 100, 100),
+        }
+    }
+
+    /// Built-in high-contrast palette: white and yellow on black
+    pub fn high_contrast() -> Self {
+        Self {
+            name: HIGH_CONTRAST.to_string(),
+            dark: true,
+            high_contrast: true,
+            background: Rgb(0, 0, 0),
+            surface: Rgb(0, 0, 0),
+            input: Rgb(0, 0, 0),
+            frame: Rgb(0, 0, 0),
+            widget: Rgb(25, 25, 25),
+            widget_hover: Rgb(70, 70, 0),
+            border: Rgb(255, 255, 255),
+            text: Rgb(255, 255, 255),
+            muted: Rgb(235, 235, 235),
+            subtle: Rgb(200, 200, 200),
+            accent: Rgb(255, 220, 0),
+            user: Rgb(0, 220, 255),
+            assistant: Rgb(120, 255, 120),
+            success: Rgb(120, 255, 120),
+            warning: Rgb(255, 200, 0),
+            error: Rgb(255, 120, 120),
+        }
+    }
+
+    /// Parse a user theme file
+    ///
+    /// # Errors
+    /// Invalid TOML, a missing `name`, an unknown `base` or a malformed color
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let file: PaletteFile = toml::from_str(text)?;
+        Ok(file.resolve())
+    }
+}
+
+/// Which palette a user theme starts from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Base {
+    #[default]
+    Light,
+    Dark,
+    #[serde(rename = "high-contrast")]
+    HighContrast,
+}
+
+/// A theme file: a name, a base and any colors to override
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PaletteFile {
+    name: String,
+    #[serde(default)]
+    base: Base,
+    background: Option<Rgb>,
+    surface: Option<Rgb>,
+    input: Option<Rgb>,
+    frame: Option<Rgb>,
+    widget: Option<Rgb>,
+    widget_hover: Option<Rgb>,
+    border: Option<Rgb>,
+    text: Option<Rgb>,
+    muted: Option<Rgb>,
+    subtle: Option<Rgb>,
+    accent: Option<Rgb>,
+    user: Option<Rgb>,
+    assistant: Option<Rgb>,
+    success: Option<Rgb>,
+    warning: Option<Rgb>,
+    error: Option<Rgb>,
+}
+
+impl PaletteFile {
+    fn resolve(self) -> Palette {
+        let base = match self.base {
+            Base::Light => Palette::light(),
+            Base::Dark => Palette::dark(),
+            Base::HighContrast => Palette::high_contrast(),
+        };
+        Palette {
+            name: self.name,
+            dark: base.dark,
+            high_contrast: base.high_contrast,
+            background: self.background.unwrap_or(base.background),
+            surface: self.surface.unwrap_or(base.surface),
+            input: self.input.unwrap_or(base.input),
+            frame: self.frame.unwrap_or(base.frame),
+            widget: self.widget.unwrap_or(base.widget),
+            widget_hover: self.widget_hover.unwrap_or(base.widget_hover),
+            border: self.border.unwrap_or(base.border),
+            text: self.text.unwrap_or(base.text),
+            muted: self.muted.unwrap_or(base.muted),
+            subtle: self.subtle.unwrap_or(base.subtle),
+            accent: self.accent.unwrap_or(base.accent),
+            user: self.user.unwrap_or(base.user),
+            assistant: self.assistant.unwrap_or(base.assistant),
+            success: self.success.unwrap_or(base.success),
+            warning: self.warning.unwrap_or(base.warning),
+            error: self.error.unwrap_or(base.error),
+        }
+    }
+}
+
+/// Default location of user themes: ~/.rustbot/themes
+pub fn themes_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".rustbot")
+        .join("themes")
+}
+
+/// Load every *.toml theme in a directory, sorted by name
+///
+/// Broken files are logged and skipped, as are themes named like a
+/// built-in ("light", "dark", "system", "high-contrast").
+pub fn load_palettes(dir: &Path) -> Vec<Palette> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut palettes: Vec<Palette> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .filter_map(|path| {
+            let palette = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))
+                .and_then(|text| Palette::from_toml(&text));
+            match palette {
+                Ok(palette) if is_builtin(&palette.name) => {
+                    tracing::warn!(
+                        "Theme {} uses the reserved name '{}', skipping",
+                        path.display(),
+                        palette.name
+                    );
+                    None
+                }
+                Ok(palette) => Some(palette),
+                Err(e) => {
+                    tracing::warn!("Invalid theme {}: {:#}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect();
+    palettes.sort_by_key(|palette| palette.name.to_lowercase());
+    palettes
+}
+
+fn is_builtin(name: &str) -> bool {
+    [LIGHT, DARK, SYSTEM, HIGH_CONTRAST]
+        .iter()
+        .any(|builtin| name.eq_ignore_ascii_case(builtin))
+}
+
+/// The palette for a theme preference
+///
+/// # Arguments
+/// * `theme` - Profile value: "light", "dark", "system", "high-contrast" or
+///   a user theme name
+/// * `system_dark` - Whether the OS is in dark mode (used for "system")
+/// * `custom` - User themes from `load_palettes`
+pub fn resolve(theme: &str, system_dark: bool, custom: &[Palette]) -> Palette {
+    match theme {
+        DARK => Palette::dark(),
+        HIGH_CONTRAST => Palette::high_contrast(),
+        SYSTEM if system_dark => Palette::dark(),
+        LIGHT | SYSTEM => Palette::light(),
+        name => custom
+            .iter()
+            .find(|palette| palette.name == name)
+            .cloned()
+            .unwrap_or_else(Palette::light),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_colors() {
+        assert_eq!(Rgb::parse("#268bd2"), Some(Rgb(0x26, 0x8b, 0xd2)));
+        assert_eq!(Rgb::parse("FFFFFF"), Some(Rgb(255, 255, 255)));
+        assert_eq!(Rgb::parse("#fff"), None);
+        assert_eq!(Rgb::parse("#gg0000"), None);
+        assert_eq!(Rgb::parse("#ééé"), None);
+    }
+
+    #[test]
+    fn test_user_theme_overrides_base() {
+        let palette = Palette::from_toml(
+            r##"
+                name = "Solarized Dark"
+                base = "dark"
+                background = "#002b36"
+                accent = "#268bd2"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(palette.name, "Solarized Dark");
+        assert!(palette.dark);
+        assert_eq!(palette.background, Rgb(0x00, 0x2b, 0x36));
+        assert_eq!(palette.accent, Rgb(0x26, 0x8b, 0xd2));
+        assert_eq!(palette.text, Palette::dark().text);
+    }
+
+    #[test]
+    fn test_invalid_user_themes() {
+        assert!(Palette::from_toml(r##"base = "dark""##).is_err());
+        assert!(Palette::from_toml("name = \"x\"\nbase = \"sepia\"").is_err());
+        assert!(Palette::from_toml("name = \"x\"\naccent = \"blue\"").is_err());
+        assert!(Palette::from_toml("name = \"x\"\naccnet = \"#000000\"").is_err());
+    }
+
+    #[test]
+    fn test_load_and_resolve() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.toml"), "name = \"Paper\"").unwrap();
+        std::fs::write(dir.path().join("a.toml"), "name = \"dark\"").unwrap();
+        std::fs::write(dir.path().join("c.toml"), "not toml [").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "name = \"Ignored\"").unwrap();
+
+        let custom = load_palettes(dir.path());
+        assert_eq!(custom.len(), 1);
+        assert_eq!(custom[0].name, "Paper");
+
+        assert_eq!(resolve(SYSTEM, true, &custom), Palette::dark());
+        assert_eq!(resolve(SYSTEM, false, &custom), Palette::light());
+        assert_eq!(resolve("Paper", true, &custom).name, "Paper");
+        assert_eq!(resolve("Missing", true, &custom), Palette::light());
+    }
+
+    #[test]
+    fn test_high_contrast_palette() {
+        assert!((Rgb(0, 0, 0).contrast_ratio(Rgb(255, 255, 255)) - 21.0).abs() < 0.01);
+        assert_eq!(Rgb(90, 90, 90).contrast_ratio(Rgb(90, 90, 90)), 1.0);
+
+        let palette = resolve(HIGH_CONTRAST, false, &[]);
+        assert!(palette.high_contrast);
+        let text_roles = [
+            palette.text,
+            palette.muted,
+            palette.subtle,
+            palette.accent,
+            palette.user,
+            palette.assistant,
+            palette.success,
+            palette.warning,
+            palette.error,
+        ];
+        for role in text_roles {
+            assert!(role.contrast_ratio(palette.background) >= 7.0, "{:?}", role);
+        }
+
+        let custom = Palette::from_toml("name = \"Night\"\nbase = \"high-contrast\"").unwrap();
+        assert!(custom.high_contrast);
+        assert!(!Palette::dark().high_contrast);
+    }
+
+    #[test]
+    fn test_font_sizes() {
+        let default = FontSizes::for_body(DEFAULT_FONT_SIZE);
+        assert_eq!(default.heading, 24.0);
+        assert_eq!(default.body, 16.0);
+        assert_eq!(default.small, 14.0);
+
+        let large = FontSizes::for_body(20.0);
+        assert_eq!(large.heading, 30.0);
+        assert_eq!(large.monospace, 18.0);
+
+        assert_eq!(FontSizes::for_body(2.0).body, MIN_FONT_SIZE);
+        assert_eq!(FontSizes::for_body(f32::NAN).body, DEFAULT_FONT_SIZE);
+        assert_eq!(clamp_ui_scale(5.0), MAX_UI_SCALE);
+        assert_eq!(clamp_ui_scale(f32::INFINITY), DEFAULT_UI_SCALE);
+    }
+}
@@ -0,0 +1,124 @@
+// Token counting with a real tokenizer, and model context window sizes
+//
+// Design Decision: tiktoken's o200k_base encoding for every model
+//
+// Rationale: The chat input shows a live token count and warns before a
+// message would overflow the model's context window. Counting characters
+// (~4 per token) is off by 30% or more for code and non-English text. The
+// exact tokenizer isn't available offline for every provider (Anthropic's
+// isn't public), but o200k_base (GPT-4o and later) stays within a few percent
+// for other modern models, which is close enough for a warning.
+//
+// The encoding takes a moment to build, so it is loaded once on first use and
+// shared; `warm_up` lets the GUI do that off the UI thread at startup.
+//
+// Trade-offs: Counts for non-OpenAI models are approximations. Context window
+// sizes come from a built-in table of model families; unknown models get
+// `DEFAULT_CONTEXT_WINDOW`. Builds without the `tiktoken` feature always
+// estimate (~4 characters per token).
+
+#[cfg(feature = "tiktoken")]
+use std::sync::OnceLock;
+#[cfg(feature = "tiktoken")]
+use tiktoken_rs::CoreBPE;
+
+/// Context window assumed for models missing from the table
+pub const DEFAULT_CONTEXT_WINDOW: u32 = 128_000;
+
+#[cfg(feature = "tiktoken")]
+static ENCODING: OnceLock<Option<CoreBPE>> = OnceLock::new();
+
+#[cfg(feature = "tiktoken")]
+fn encoding() -> Option<&'static CoreBPE> {
+    ENCODING
+        .get_or_init(|| match tiktoken_rs::o200k_base() {
+            Ok(bpe) => Some(bpe),
+            Err(e) => {
+                tracing::warn!("⚠️  Couldn't load tokenizer, estimating tokens: {}", e);
+                None
+            }
+        })
+        .as_ref()
+}
+
+/// Load the tokenizer now so the first count doesn't pause the caller
+pub fn warm_up() {
+    #[cfg(feature = "tiktoken")]
+    let _ = encoding();
+}
+
+/// Number of tokens in `text`
+///
+/// Falls back to ~4 characters per token if the tokenizer couldn't be loaded
+/// (or isn't built in).
+pub fn count_tokens(text: &str) -> usize {
+    if text.is_empty() {
+        return 0;
+    }
+    #[cfg(feature = "tiktoken")]
+    if let Some(bpe) = encoding() {
+        return bpe.encode_ordinary(text).len();
+    }
+    text.len().div_ceil(4)
+}
+
+/// Context window of a model, in tokens
+///
+/// Accepts OpenRouter-style ids (`anthropic/claude-sonnet-4`) and bare model
+/// names.
+pub fn context_window(model: &str) -> u32 {
+    let name = model
+        .rsplit('/')
+        .next()
+        .unwrap_or(model)
+        .to_ascii_lowercase();
+    let windows: &[(&str, u32)] = &[
+        ("claude", 200_000),
+        ("gpt-5", 400_000),
+        ("gpt-4.1", 1_047_576),
+        ("gpt-4o", 128_000),
+        ("gpt-4-turbo", 128_000),
+        ("gpt-4", 8_192),
+        ("gpt-3.5", 16_385),
+        ("o1", 200_000),
+        ("o3", 200_000),
+        ("o4", 200_000),
+        ("gemini", 1_048_576),
+        ("grok", 131_072),
+        ("llama", 128_000),
+        ("mistral", 128_000),
+        ("deepseek", 128_000),
+    ];
+    windows
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map(|(_, window)| *window)
+        .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_tokens() {
+        assert_eq!(count_tokens(""), 0);
+        #[cfg(feature = "tiktoken")]
+        assert_eq!(count_tokens("hello"), 1);
+        #[cfg(not(feature = "tiktoken"))]
+        assert_eq!(count_tokens("hello"), 2);
+        assert!(count_tokens("The quick brown fox jumps over the lazy dog") < 15);
+        // Special-token text is counted as plain text
+        assert!(count_tokens("<|endoftext|>") > 1);
+    }
+
+    #[test]
+    fn test_context_window() {
+        assert_eq!(context_window("anthropic/claude-sonnet-4.5"), 200_000);
+        assert_eq!(context_window("openai/gpt-4o-mini"), 128_000);
+        assert_eq!(context_window("gpt-4.1"), 1_047_576);
+        assert_eq!(context_window("openai/gpt-4"), 8_192);
+        assert_eq!(context_window("google/Gemini-2.5-Pro"), 1_048_576);
+        assert_eq!(context_window("someone/new-model"), DEFAULT_CONTEXT_WINDOW);
+    }
+}
@@ -0,0 +1,164 @@
+// Per-conversation tool toggles
+//
+// Design Decision: Chats switch whole tool sources on and off
+//
+// Rationale: Which tools an agent gets is global: the primary agent gets every
+// enabled specialist, running plugin, script, connector and the git tools;
+// specialists get none. Trying the filesystem plugin in one chat, or keeping
+// the git tools out of another, meant editing agent configs and undoing it
+// afterwards. Instead the tools are grouped by where they come from (one
+// specialist, one MCP server, one script, the email connector, ...) and each
+// session keeps overrides for the sources it switched. A source that isn't
+// overridden keeps its default: on for the primary agent, off for
+// specialists.
+//
+// Trade-offs:
+// - Overrides are per source, not per tool; a plugin's tools come as a set
+// - The frontend owns the overrides (like pinned messages) and sets them on
+//   the session before each message; they aren't saved with the conversation
+// - Overrides name sources that may disappear (a plugin stops); they're kept
+//   and apply again when it comes back
+
+use crate::agent::ToolDefinition;
+use std::collections::HashMap;
+
+/// Tools that are switched on and off together
+#[derive(Debug, Clone)]
+pub struct ToolSource {
+    /// Stable ID, e.g. `mcp:filesystem`, `email` or a specialist's agent ID
+    pub id: String,
+
+    /// Name shown in the tool panel
+    pub label: String,
+
+    /// Whether the source is on in a chat that didn't switch it
+    pub default_enabled: bool,
+
+    /// The source's tools
+    pub tools: Vec<ToolDefinition>,
+}
+
+impl ToolSource {
+    pub fn new(
+        id: impl Into<String>,
+        label: impl Into<String>,
+        default_enabled: bool,
+        tools: Vec<ToolDefinition>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            label: label.into(),
+            default_enabled,
+            tools,
+        }
+    }
+
+    /// Whether the source is on, given a session's overrides
+    pub fn is_enabled(&self, overrides: &HashMap<String, bool>) -> bool {
+        overrides
+            .get(&self.id)
+            .copied()
+            .unwrap_or(self.default_enabled)
+    }
+}
+
+/// Source ID and label of a namespaced tool name
+///
+/// `mcp:filesystem:read_file` belongs to `mcp:filesystem` ("filesystem
+/// (MCP)"); likewise `script:` and `native:` tools. Other names have no
+/// source of their own.
+pub fn source_of(tool_name: &str) -> Option<(String, String)> {
+    let (kind, rest) = tool_name.split_once(':')?;
+    let (plugin, _) = rest.split_once(':')?;
+    let kind_label = match kind {
+        "mcp" => "MCP",
+        "script" => "script",
+        "native" => "plugin",
+        _ => return None,
+    };
+    Some((
+        format!("{}:{}", kind, plugin),
+        format!("{} ({})", plugin, kind_label),
+    ))
+}
+
+/// Group tools by `source_of`, in the order their sources first appear
+///
+/// A tool without a namespaced source becomes a source of its own.
+pub fn group(tools: Vec<ToolDefinition>, default_enabled: bool) -> Vec<ToolSource> {
+    let mut sources: Vec<ToolSource> = Vec::new();
+    for tool in tools {
+        let (id, label) = source_of(&tool.function.name)
+            .unwrap_or_else(|| (tool.function.name.clone(), tool.function.name.clone()));
+        match sources.iter_mut().find(|source| source.id == id) {
+            Some(source) => source.tools.push(tool),
+            None => sources.push(ToolSource::new(id, label, default_enabled, vec![tool])),
+        }
+    }
+    sources
+}
+
+/// The tools of the sources that are on, in source order
+pub fn enabled_tools(
+    sources: Vec<ToolSource>,
+    overrides: &HashMap<String, bool>,
+) -> Vec<ToolDefinition> {
+    sources
+        .into_iter()
+        .filter(|source| source.is_enabled(overrides))
+        .flat_map(|source| source.tools)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::tools::{FunctionDefinition, FunctionParameters};
+
+    fn tool(name: &str) -> ToolDefinition {
+        ToolDefinition {
+            tool_type: "function".to_string(),
+            function: FunctionDefinition {
+                name: name.to_string(),
+                description: String::new(),
+                parameters: FunctionParameters {
+                    param_type: "object".to_string(),
+                    properties: serde_json::json!({}),
+                    required: Vec::new(),
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn test_group_and_toggle() {
+        let sources = group(
+            vec![
+                tool("mcp:filesystem:read_file"),
+                tool("web_search"),
+                tool("mcp:filesystem:write_file"),
+                tool("script:notes:append"),
+            ],
+            true,
+        );
+        let ids: Vec<&str> = sources.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["mcp:filesystem", "web_search", "script:notes"]);
+        assert_eq!(sources[0].label, "filesystem (MCP)");
+        assert_eq!(sources[0].tools.len(), 2);
+        assert_eq!(source_of("wasm:hash"), None);
+
+        let mut overrides = HashMap::new();
+        overrides.insert("mcp:filesystem".to_string(), false);
+        let names: Vec<String> = enabled_tools(sources.clone(), &overrides)
+            .into_iter()
+            .map(|t| t.function.name)
+            .collect();
+        assert_eq!(names, ["web_search", "script:notes:append"]);
+
+        // Off by default (a specialist's sources), on when the chat says so
+        let off = group(vec![tool("mcp:filesystem:read_file")], false);
+        assert!(enabled_tools(off.clone(), &HashMap::new()).is_empty());
+        overrides.insert("mcp:filesystem".to_string(), true);
+        assert_eq!(enabled_tools(off, &overrides).len(), 1);
+    }
+}
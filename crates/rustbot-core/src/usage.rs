@@ -0,0 +1,184 @@
+// Token usage per day or week, by agent and model
+//
+// Design Decision: Aggregate the saved conversations instead of keeping a log
+//
+// Rationale: Every saved session message already carries its estimated token
+// count, and new messages also record the agent and model that handled them.
+// Summing those gives a usage history, including conversations from before the
+// Usage view existed, with nothing extra to write while chatting.
+//
+// Trade-offs:
+// - Token counts are the chat view's estimates, not the provider's billing
+// - Messages saved before agent/model attribution are counted under the
+//   session's agent and an "unknown" model
+// - Deleting a conversation removes its usage from the history
+
+use crate::services::ConversationSession;
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use std::collections::BTreeMap;
+
+/// Model name for messages saved without one
+pub const UNKNOWN_MODEL: &str = "unknown";
+
+/// Length of the periods usage is grouped into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsagePeriod {
+    Day,
+    /// Monday to Sunday
+    Week,
+}
+
+impl UsagePeriod {
+    /// First day of the period containing `date`
+    pub fn start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => date,
+            Self::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        }
+    }
+}
+
+/// Tokens used in one period by one agent and model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageEntry {
+    /// First day of the period (local time)
+    pub period: NaiveDate,
+    pub agent_id: String,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Sum the token counts of saved sessions per period, agent and model
+///
+/// # Arguments
+/// * `sessions` - Saved conversations
+/// * `period` - Day or week buckets
+/// * `since` - Ignore messages from before this local date
+///
+/// # Returns
+/// Entries sorted by period, then agent and model; messages without token
+/// counts are skipped
+pub fn aggregate(
+    sessions: &[ConversationSession],
+    period: UsagePeriod,
+    since: Option<NaiveDate>,
+) -> Vec<UsageEntry> {
+    let mut totals: BTreeMap<(NaiveDate, String, String), (u64, u64)> = BTreeMap::new();
+
+    for session in sessions {
+        for message in &session.messages {
+            if message.input_tokens.is_none() && message.output_tokens.is_none() {
+                continue;
+            }
+            let date = message.timestamp.with_timezone(&Local).date_naive();
+            if since.is_some_and(|since| date < since) {
+                continue;
+            }
+
+            let key = (
+                period.start(date),
+                message
+                    .agent_id
+                    .clone()
+                    .unwrap_or_else(|| session.agent_id.clone()),
+                message
+                    .model
+                    .clone()
+                    .unwrap_or_else(|| UNKNOWN_MODEL.to_string()),
+            );
+            let total = totals.entry(key).or_default();
+            total.0 += message.input_tokens.unwrap_or(0) as u64;
+            total.1 += message.output_tokens.unwrap_or(0) as u64;
+        }
+    }
+
+    totals
+        .into_iter()
+        .map(
+            |((period, agent_id, model), (input_tokens, output_tokens))| UsageEntry {
+                period,
+                agent_id,
+                model,
+                input_tokens,
+                output_tokens,
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::SessionMessage;
+    use chrono::TimeZone;
+
+    fn message(
+        day: u32,
+        input_tokens: Option<u32>,
+        output_tokens: Option<u32>,
+        model: Option<&str>,
+    ) -> SessionMessage {
+        SessionMessage {
+            role: if input_tokens.is_some() {
+                "user".to_string()
+            } else {
+                "assistant".to_string()
+            },
+            content: String::new(),
+            timestamp: Local
+                .with_ymd_and_hms(2025, 3, day, 12, 0, 0)
+                .unwrap()
+                .with_timezone(&chrono::Utc),
+            input_tokens,
+            output_tokens,
+            agent_id: model.map(|_| "researcher".to_string()),
+            model: model.map(str::to_string),
+            first_token_ms: None,
+            generation_ms: None,
+            pinned: false,
+            feedback: None,
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, day).unwrap()
+    }
+
+    #[test]
+    fn test_week_starts_on_monday() {
+        // 2025-03-05 is a Wednesday
+        assert_eq!(UsagePeriod::Week.start(date(5)), date(3));
+        assert_eq!(UsagePeriod::Week.start(date(3)), date(3));
+        assert_eq!(UsagePeriod::Day.start(date(5)), date(5));
+    }
+
+    #[test]
+    fn test_aggregate_by_period_agent_and_model() {
+        let mut session = ConversationSession::new("assistant");
+        session.messages = vec![
+            message(3, Some(10), None, Some("openai/gpt-4o")),
+            message(3, None, Some(100), Some("openai/gpt-4o")),
+            message(4, Some(5), None, Some("openai/gpt-4o")),
+            // Saved before attribution: session agent, unknown model
+            message(4, None, Some(50), None),
+            // No token counts (imported)
+            message(4, None, None, None),
+        ];
+
+        let daily = aggregate(std::slice::from_ref(&session), UsagePeriod::Day, None);
+        assert_eq!(daily.len(), 3);
+        assert_eq!(daily[0].period, date(3));
+        assert_eq!((daily[0].input_tokens, daily[0].output_tokens), (10, 100));
+        assert_eq!(daily[1].agent_id, "assistant");
+        assert_eq!(daily[1].model, UNKNOWN_MODEL);
+        assert_eq!(daily[2].model, "openai/gpt-4o");
+
+        let weekly = aggregate(&[session.clone()], UsagePeriod::Week, None);
+        let gpt = weekly.iter().find(|e| e.model == "openai/gpt-4o").unwrap();
+        assert_eq!((gpt.input_tokens, gpt.output_tokens), (15, 100));
+
+        let recent = aggregate(&[session], UsagePeriod::Day, Some(date(4)));
+        assert!(recent.iter().all(|e| e.period == date(4)));
+    }
+}
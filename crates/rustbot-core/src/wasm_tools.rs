@@ -0,0 +1,769 @@
+// Sandboxed WebAssembly tools
+//
+// Design Decision: Each `.wasm` module in ~/.rustbot/tools with a TOML
+// manifest next to it (`weather.wasm` + `weather.toml`) becomes an agent tool,
+// run in wasmtime with only the host functions its manifest grants
+//
+// Rationale: Rhai scripts (scripting.rs) are fine for glue, but tools written
+// in Rust, Go, C or AssemblyScript need a compiled runtime. WebAssembly has no
+// ambient authority: a module can only call the functions the host links in,
+// so there is no WASI here. The manifest declares what a tool may do (log,
+// read files under some folders, GET from some hosts); modules importing
+// anything else fail to load, and every call is checked against the grant
+// again. Each call gets a fresh instance with fuel and memory limits, so a
+// tool can't keep state between calls or run away.
+//
+// Manifest (weather.toml):
+//     description = "Current weather for a city"
+//
+//     [parameters.city]
+//     type = "string"                # default "string"
+//     description = "City name"
+//     optional = false               # default false
+//
+//     [capabilities]
+//     log = true
+//     read_paths = ["~/notes"]
+//     http_hosts = ["api.open-meteo.com"]
+//
+// Module interface:
+// - exports `memory`, `alloc(len: i32) -> i32` and
+//   `run(ptr: i32, len: i32) -> i64`; `run` gets the JSON arguments as UTF-8
+//   and returns the result's `(ptr << 32) | len`
+// - imports from "rustbot", each needing its capability:
+//   `log(ptr, len)` (log), `read_file(ptr, len) -> i64` (read_paths) and
+//   `http_get(ptr, len) -> i64` (http_hosts); the i64 results are packed like
+//   `run`'s (the host writes into memory from `alloc`), or -1 if the request
+//   was refused or failed (the reason goes to the Rustbot log)
+//
+// Trade-offs:
+// - Tools are namespaced "wasm:{name}" like script and MCP tools
+// - Modules are compiled on load, so reloading many large tools takes a moment
+// - http_get follows no redirects (a redirect could leave the granted hosts)
+//   and respects local-only mode
+// - Calls are synchronous; the API runs them on a blocking thread
+
+use crate::agent::tools::{FunctionDefinition, FunctionParameters};
+use crate::agent::ToolDefinition;
+use crate::error::{Result, RustbotError};
+use anyhow::{anyhow, bail, Context as _};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use wasmtime::{
+    Caller, Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+/// Prefix for WASM tool names
+const WASM_TOOL_PREFIX: &str = "wasm:";
+
+/// Module the host functions are imported from
+const HOST_MODULE: &str = "rustbot";
+
+/// Module file extension
+const MODULE_EXTENSION: &str = "wasm";
+
+/// Instructions (roughly) a call may execute before it is stopped
+const MAX_FUEL: u64 = 100_000_000;
+
+/// Linear memory a call may grow to (bytes)
+const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Largest argument, result, file or response passed across (bytes)
+const MAX_TRANSFER_BYTES: usize = 1024 * 1024;
+
+/// Timeout for http_get
+const HTTP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(20);
+
+/// A tool's manifest (`<name>.toml`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolManifest {
+    /// Description shown to the model
+    pub description: String,
+
+    /// Parameters by name
+    #[serde(default)]
+    pub parameters: BTreeMap<String, ParameterSpec>,
+
+    /// What the tool may do besides computing
+    #[serde(default)]
+    pub capabilities: Capabilities,
+}
+
+/// One tool parameter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParameterSpec {
+    /// JSON schema type ("string", "number", "boolean", ...)
+    #[serde(rename = "type", default = "default_parameter_type")]
+    pub param_type: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default)]
+    pub optional: bool,
+}
+
+fn default_parameter_type() -> String {
+    "string".to_string()
+}
+
+/// Host functions a tool is granted
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// `log`: write to the Rustbot log
+    #[serde(default)]
+    pub log: bool,
+
+    /// `read_file`: read files under these folders (`~` is the home folder)
+    #[serde(default)]
+    pub read_paths: Vec<String>,
+
+    /// `http_get`: GET from these hosts
+    #[serde(default)]
+    pub http_hosts: Vec<String>,
+}
+
+impl Capabilities {
+    /// Human-readable grants, for the tool manager view
+    pub fn summary(&self) -> Vec<String> {
+        let mut grants = Vec::new();
+        if self.log {
+            grants.push("log".to_string());
+        }
+        if !self.read_paths.is_empty() {
+            grants.push(format!("read {}", self.read_paths.join(", ")));
+        }
+        if !self.http_hosts.is_empty() {
+            grants.push(format!("GET {}", self.http_hosts.join(", ")));
+        }
+        grants
+    }
+
+    /// Whether the host function `name` is granted
+    fn grants(&self, name: &str) -> bool {
+        match name {
+            "log" => self.log,
+            "read_file" => !self.read_paths.is_empty(),
+            "http_get" => !self.http_hosts.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+/// Status of one tool module, for the tool manager view
+#[derive(Debug, Clone)]
+pub struct WasmToolInfo {
+    /// Tool name (file stem)
+    pub name: String,
+
+    /// Path to the .wasm file
+    pub path: PathBuf,
+
+    /// Description from the manifest
+    pub description: String,
+
+    /// Granted capabilities (see `Capabilities::summary`)
+    pub capabilities: Vec<String>,
+
+    /// Manifest, compile or capability error
+    pub error: Option<String>,
+}
+
+/// A module with its manifest and compiled code (if it loaded)
+struct LoadedTool {
+    info: WasmToolInfo,
+    manifest: ToolManifest,
+    module: Option<Module>,
+}
+
+/// Loads WASM tools and runs their calls
+///
+/// Shared as `Arc<WasmToolHost>` between the API (for tool calls) and the
+/// tool manager view.
+pub struct WasmToolHost {
+    dir: PathBuf,
+    engine: Engine,
+    tools: Mutex<Vec<LoadedTool>>,
+}
+
+impl WasmToolHost {
+    /// Default tools directory: ~/.rustbot/tools
+    pub fn default_dir() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("tools")
+    }
+
+    /// Create a host for the tools in `dir`
+    ///
+    /// No tools are loaded until `reload` is called.
+    ///
+    /// # Errors
+    /// - The WebAssembly engine can't be created on this platform
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(|e| {
+            RustbotError::ConfigError(format!("WebAssembly runtime unavailable: {:#}", e))
+        })?;
+
+        Ok(Self {
+            dir,
+            engine,
+            tools: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Tools directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// (Re)load every module in the directory
+    ///
+    /// Creates the directory if it doesn't exist. A module whose manifest is
+    /// missing or invalid, that fails to compile, or that imports functions
+    /// its manifest doesn't grant is kept in the list with its error.
+    ///
+    /// # Errors
+    /// - The directory cannot be created or read
+    pub fn reload(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path.extension().and_then(|e| e.to_str()) == Some(MODULE_EXTENSION)
+            })
+            .collect();
+        paths.sort();
+
+        let loaded: Vec<LoadedTool> = paths.into_iter().map(|p| self.load_tool(p)).collect();
+        tracing::info!(
+            "🧩 Loaded {} of {} WASM tools from {}",
+            loaded.iter().filter(|t| t.module.is_some()).count(),
+            loaded.len(),
+            self.dir.display()
+        );
+
+        *self.tools.lock().unwrap() = loaded;
+        Ok(())
+    }
+
+    /// Read a module's manifest, compile it and check its imports
+    fn load_tool(&self, path: PathBuf) -> LoadedTool {
+        let name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_string();
+        let mut tool = LoadedTool {
+            info: WasmToolInfo {
+                name,
+                path,
+                description: String::new(),
+                capabilities: Vec::new(),
+                error: None,
+            },
+            manifest: ToolManifest::default(),
+            module: None,
+        };
+
+        let manifest_path = tool.info.path.with_extension("toml");
+        let manifest = std::fs::read_to_string(&manifest_path)
+            .map_err(|e| format!("Failed to read {}: {}", manifest_path.display(), e))
+            .and_then(|content| {
+                toml::from_str::<ToolManifest>(&content)
+                    .map_err(|e| format!("Invalid manifest: {}", e))
+            });
+        match manifest {
+            Ok(manifest) => tool.manifest = manifest,
+            Err(e) => {
+                tool.info.error = Some(e);
+                return tool;
+            }
+        }
+        tool.info.description = tool.manifest.description.clone();
+        tool.info.capabilities = tool.manifest.capabilities.summary();
+
+        match self.compile(&tool.info.path, &tool.manifest.capabilities) {
+            Ok(module) => tool.module = Some(module),
+            Err(e) => {
+                tracing::warn!("🧩 WASM tool '{}' failed to load: {:#}", tool.info.name, e);
+                tool.info.error = Some(format!("{:#}", e));
+            }
+        }
+        tool
+    }
+
+    fn compile(&self, path: &Path, capabilities: &Capabilities) -> anyhow::Result<Module> {
+        let module = Module::from_file(&self.engine, path)?;
+        for import in module.imports() {
+            if import.module() != HOST_MODULE {
+                bail!(
+                    "imports {}.{}; only \"{}\" host functions are available",
+                    import.module(),
+                    import.name(),
+                    HOST_MODULE
+                );
+            }
+            if !capabilities.grants(import.name()) {
+                bail!(
+                    "imports {}.{}, which its manifest doesn't grant",
+                    HOST_MODULE,
+                    import.name()
+                );
+            }
+        }
+        Ok(module)
+    }
+
+    /// Status of every module, in file name order
+    pub fn list(&self) -> Vec<WasmToolInfo> {
+        self.tools
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|t| t.info.clone())
+            .collect()
+    }
+
+    /// Tool definitions for every module that loaded
+    pub fn tool_definitions(&self) -> Vec<ToolDefinition> {
+        self.tools
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|t| t.module.is_some())
+            .map(|t| tool_definition(&t.info.name, &t.manifest))
+            .collect()
+    }
+
+    /// Check if a tool name belongs to a WASM tool
+    pub fn is_wasm_tool(tool_name: &str) -> bool {
+        tool_name.starts_with(WASM_TOOL_PREFIX)
+    }
+
+    /// Run a WASM tool in a fresh instance
+    ///
+    /// # Arguments
+    /// * `tool_name` - Namespaced name ("wasm:{name}")
+    /// * `arguments` - JSON-encoded arguments, passed to `run` as-is
+    ///
+    /// # Returns
+    /// The text the module returned
+    ///
+    /// # Errors
+    /// - Unknown tool or module not loaded
+    /// - The module traps, runs out of fuel or memory, or returns invalid UTF-8
+    pub fn call_tool(&self, tool_name: &str, arguments: &str) -> Result<String> {
+        let name = tool_name.strip_prefix(WASM_TOOL_PREFIX).ok_or_else(|| {
+            RustbotError::ConfigError(format!("Invalid WASM tool name '{}'", tool_name))
+        })?;
+        let (module, capabilities) = {
+            let tools = self.tools.lock().unwrap();
+            let tool = tools.iter().find(|t| t.info.name == name).ok_or_else(|| {
+                RustbotError::ConfigError(format!("Unknown WASM tool '{}'", tool_name))
+            })?;
+            let module = tool.module.clone().ok_or_else(|| {
+                RustbotError::ConfigError(format!("WASM tool '{}' is not loaded", name))
+            })?;
+            (module, tool.manifest.capabilities.clone())
+        };
+
+        let arguments = if arguments.trim().is_empty() {
+            "{}"
+        } else {
+            arguments
+        };
+        run(&self.engine, &module, name, &capabilities, arguments)
+            .map_err(|e| RustbotError::ApiError(format!("WASM tool '{}' failed: {:#}", name, e)))
+    }
+}
+
+/// Per-call state available to host functions
+struct HostState {
+    limits: StoreLimits,
+    tool: String,
+
+    /// Canonical folders `read_file` may read under
+    read_paths: Vec<PathBuf>,
+    http_hosts: Vec<String>,
+
+    /// Runtime for `http_get` (None outside a Tokio runtime)
+    runtime: Option<tokio::runtime::Handle>,
+}
+
+fn run(
+    engine: &Engine,
+    module: &Module,
+    tool: &str,
+    capabilities: &Capabilities,
+    input: &str,
+) -> anyhow::Result<String> {
+    let mut linker = Linker::new(engine);
+    link_host_functions(&mut linker, capabilities)?;
+
+    let state = HostState {
+        limits: StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY_BYTES)
+            .instances(1)
+            .build(),
+        tool: tool.to_string(),
+        read_paths: capabilities
+            .read_paths
+            .iter()
+            .filter_map(|p| std::fs::canonicalize(expand_home(p)).ok())
+            .collect(),
+        http_hosts: capabilities.http_hosts.clone(),
+        runtime: tokio::runtime::Handle::try_current().ok(),
+    };
+    let mut store = Store::new(engine, state);
+    store.limiter(|state| &mut state.limits);
+    store.set_fuel(MAX_FUEL)?;
+
+    let instance = linker.instantiate(&mut store, module)?;
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .context("module doesn't export memory")?;
+    let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+    let run = instance.get_typed_func::<(i32, i32), i64>(&mut store, "run")?;
+
+    let len = transfer_len(input.len())?;
+    let ptr = alloc.call(&mut store, len)?;
+    memory.write(&mut store, ptr as u32 as usize, input.as_bytes())?;
+    let (ptr, len) = unpack(run.call(&mut store, (ptr, len))?);
+    read_string(&memory, &store, ptr, len)
+}
+
+/// Define the host functions the capabilities grant (and no others)
+fn link_host_functions(
+    linker: &mut Linker<HostState>,
+    capabilities: &Capabilities,
+) -> anyhow::Result<()> {
+    if capabilities.grants("log") {
+        linker.func_wrap(
+            HOST_MODULE,
+            "log",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> anyhow::Result<()> {
+                let memory = guest_memory(&mut caller)?;
+                let text = read_string(&memory, &caller, ptr, len)?;
+                tracing::info!("🧩 {}: {}", caller.data().tool, text);
+                Ok(())
+            },
+        )?;
+    }
+    if capabilities.grants("read_file") {
+        linker.func_wrap(
+            HOST_MODULE,
+            "read_file",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> anyhow::Result<i64> {
+                let memory = guest_memory(&mut caller)?;
+                let path = read_string(&memory, &caller, ptr, len)?;
+                match read_file(caller.data(), &path) {
+                    Ok(content) => write_result(&mut caller, content.as_bytes()),
+                    Err(e) => {
+                        tracing::warn!("🧩 {}: read_file refused: {:#}", caller.data().tool, e);
+                        Ok(-1)
+                    }
+                }
+            },
+        )?;
+    }
+    if capabilities.grants("http_get") {
+        linker.func_wrap(
+            HOST_MODULE,
+            "http_get",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> anyhow::Result<i64> {
+                let memory = guest_memory(&mut caller)?;
+                let url = read_string(&memory, &caller, ptr, len)?;
+                match http_get(caller.data(), &url) {
+                    Ok(body) => write_result(&mut caller, body.as_bytes()),
+                    Err(e) => {
+                        tracing::warn!("🧩 {}: http_get failed: {:#}", caller.data().tool, e);
+                        Ok(-1)
+                    }
+                }
+            },
+        )?;
+    }
+    Ok(())
+}
+
+fn read_file(state: &HostState, path: &str) -> anyhow::Result<String> {
+    let path = std::fs::canonicalize(expand_home(path))?;
+    if !state.read_paths.iter().any(|dir| path.starts_with(dir)) {
+        bail!("{} is outside the granted read_paths", path.display());
+    }
+    if std::fs::metadata(&path)?.len() > MAX_TRANSFER_BYTES as u64 {
+        bail!("{} is too large", path.display());
+    }
+    Ok(std::fs::read_to_string(path)?)
+}
+
+fn http_get(state: &HostState, url: &str) -> anyhow::Result<String> {
+    let parsed = reqwest::Url::parse(url)?;
+    let host = parsed.host_str().unwrap_or_default();
+    if !state
+        .http_hosts
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    {
+        bail!("{} is not in the granted http_hosts", host);
+    }
+    crate::privacy::check_url(url)?;
+    let runtime = state
+        .runtime
+        .as_ref()
+        .context("no async runtime for HTTP requests")?;
+
+    let mut body = runtime.block_on(async {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(HTTP_TIMEOUT)
+            .build()?;
+        client
+            .get(parsed)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+    })?;
+    if body.len() > MAX_TRANSFER_BYTES {
+        let mut end = MAX_TRANSFER_BYTES;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        body.truncate(end);
+    }
+    Ok(body)
+}
+
+fn guest_memory(caller: &mut Caller<'_, HostState>) -> anyhow::Result<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .context("module doesn't export memory")
+}
+
+/// Copy bytes into memory from the module's `alloc`, returning them packed
+fn write_result(caller: &mut Caller<'_, HostState>, bytes: &[u8]) -> anyhow::Result<i64> {
+    let alloc = caller
+        .get_export("alloc")
+        .and_then(|export| export.into_func())
+        .context("module doesn't export alloc")?
+        .typed::<i32, i32>(&*caller)?;
+    let len = transfer_len(bytes.len())?;
+    let ptr = alloc.call(&mut *caller, len)?;
+    guest_memory(caller)?.write(&mut *caller, ptr as u32 as usize, bytes)?;
+    Ok(pack(ptr, len))
+}
+
+fn read_string(
+    memory: &Memory,
+    store: impl wasmtime::AsContext,
+    ptr: i32,
+    len: i32,
+) -> anyhow::Result<String> {
+    let len = len as u32 as usize;
+    if len > MAX_TRANSFER_BYTES {
+        bail!(
+            "{} bytes is more than the {} byte limit",
+            len,
+            MAX_TRANSFER_BYTES
+        );
+    }
+    let mut bytes = vec![0; len];
+    memory.read(store, ptr as u32 as usize, &mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| anyhow!("text is not valid UTF-8"))
+}
+
+fn transfer_len(len: usize) -> anyhow::Result<i32> {
+    if len > MAX_TRANSFER_BYTES {
+        bail!(
+            "{} bytes is more than the {} byte limit",
+            len,
+            MAX_TRANSFER_BYTES
+        );
+    }
+    Ok(len as i32)
+}
+
+fn pack(ptr: i32, len: i32) -> i64 {
+    ((ptr as u32 as i64) << 32) | len as u32 as i64
+}
+
+fn unpack(value: i64) -> (i32, i32) {
+    ((value >> 32) as i32, value as i32)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+fn tool_definition(name: &str, manifest: &ToolManifest) -> ToolDefinition {
+    let properties: serde_json::Map<String, serde_json::Value> = manifest
+        .parameters
+        .iter()
+        .map(|(param, spec)| {
+            (
+                param.clone(),
+                serde_json::json!({ "type": spec.param_type, "description": spec.description }),
+            )
+        })
+        .collect();
+
+    ToolDefinition {
+        tool_type: "function".to_string(),
+        function: FunctionDefinition {
+            name: format!("{}{}", WASM_TOOL_PREFIX, name),
+            description: manifest.description.clone(),
+            parameters: FunctionParameters {
+                param_type: "object".to_string(),
+                properties: serde_json::Value::Object(properties),
+                required: manifest
+                    .parameters
+                    .iter()
+                    .filter(|(_, spec)| !spec.optional)
+                    .map(|(param, _)| param.clone())
+                    .collect(),
+            },
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bump allocator shared by the test modules
+    const ALLOC: &str = r#"
+        (memory (export "memory") 1)
+        (global $next (mut i32) (i32.const 1024))
+        (func (export "alloc") (param $len i32) (result i32)
+          (local $ptr i32)
+          (local.set $ptr (global.get $next))
+          (global.set $next (i32.add (global.get $next) (local.get $len)))
+          (local.get $ptr))
+    "#;
+
+    fn write_tool(dir: &Path, name: &str, body: &str, manifest: &str) {
+        let module = format!("(module {} {})", body, ALLOC);
+        std::fs::write(dir.join(format!("{}.wasm", name)), module).unwrap();
+        std::fs::write(dir.join(format!("{}.toml", name)), manifest).unwrap();
+    }
+
+    #[test]
+    fn test_load_and_call() {
+        let dir = tempfile::tempdir().unwrap();
+        write_tool(
+            dir.path(),
+            "echo",
+            r#"(func (export "run") (param $ptr i32) (param $len i32) (result i64)
+                 (i64.or (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                         (i64.extend_i32_u (local.get $len))))"#,
+            "description = \"Echo the arguments\"\n\n[parameters.text]\ndescription = \"Text\"\n",
+        );
+        write_tool(
+            dir.path(),
+            "spin",
+            r#"(func (export "run") (param i32 i32) (result i64)
+                 (loop $forever (br $forever))
+                 (i64.const 0))"#,
+            "description = \"Never returns\"",
+        );
+        // Imports a host function its manifest doesn't grant, and WASI
+        write_tool(
+            dir.path(),
+            "sneaky",
+            r#"(import "rustbot" "read_file" (func (param i32 i32) (result i64)))"#,
+            "description = \"Reads files\"",
+        );
+        write_tool(
+            dir.path(),
+            "wasi",
+            r#"(import "wasi_snapshot_preview1" "fd_write"
+                 (func (param i32 i32 i32 i32) (result i32)))"#,
+            "description = \"Writes to stdout\"",
+        );
+        std::fs::write(dir.path().join("orphan.wasm"), "(module)").unwrap();
+
+        let host = WasmToolHost::new(dir.path().to_path_buf()).unwrap();
+        host.reload().unwrap();
+
+        let tools = host.list();
+        let errors: Vec<(&str, bool)> = tools
+            .iter()
+            .map(|t| (t.name.as_str(), t.error.is_some()))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                ("echo", false),
+                ("orphan", true),
+                ("sneaky", true),
+                ("spin", false),
+                ("wasi", true)
+            ]
+        );
+        assert!(tools[2]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("rustbot.read_file"));
+
+        let definitions = host.tool_definitions();
+        assert_eq!(definitions.len(), 2);
+        assert_eq!(definitions[0].function.name, "wasm:echo");
+        assert_eq!(definitions[0].function.parameters.required, vec!["text"]);
+        assert!(WasmToolHost::is_wasm_tool("wasm:echo"));
+
+        let args = r#"{"text":"hi"}"#;
+        assert_eq!(host.call_tool("wasm:echo", args).unwrap(), args);
+        // Runs out of fuel instead of hanging
+        assert!(host.call_tool("wasm:spin", "{}").is_err());
+        assert!(host.call_tool("wasm:sneaky", "{}").is_err());
+        assert!(host.call_tool("wasm:missing", "{}").is_err());
+    }
+
+    #[test]
+    fn test_read_file_is_limited_to_granted_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = tempfile::tempdir().unwrap();
+        std::fs::write(notes.path().join("todo.txt"), "buy milk").unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "hunter2").unwrap();
+
+        // Reads the file named by its whole input
+        write_tool(
+            dir.path(),
+            "cat",
+            r#"(import "rustbot" "read_file" (func $read_file (param i32 i32) (result i64)))
+               (func (export "run") (param $ptr i32) (param $len i32) (result i64)
+                 (call $read_file (local.get $ptr) (local.get $len)))"#,
+            &format!(
+                "description = \"Read a note\"\n\n[capabilities]\nread_paths = [{:?}]\n",
+                notes.path().display().to_string()
+            ),
+        );
+        let host = WasmToolHost::new(dir.path().to_path_buf()).unwrap();
+        host.reload().unwrap();
+        assert_eq!(host.list()[0].capabilities.len(), 1);
+
+        let todo = notes.path().join("todo.txt").display().to_string();
+        assert_eq!(host.call_tool("wasm:cat", &todo).unwrap(), "buy milk");
+
+        // A refused read returns -1, which unpacks to an invalid range
+        let secret = outside.path().join("secret.txt").display().to_string();
+        assert!(host.call_tool("wasm:cat", &secret).is_err());
+        let escape = notes.path().join("../").display().to_string();
+        assert!(host.call_tool("wasm:cat", &escape).is_err());
+    }
+}
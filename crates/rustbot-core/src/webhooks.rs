@@ -0,0 +1,542 @@
+// Webhook event sink - forwards selected events to external HTTP endpoints
+//
+// Design Decision: Optional event bus subscriber with its own delivery task
+//
+// Rationale: External monitoring and automation (Slack relays, n8n, Zapier,
+// custom dashboards) only need a small subset of events: agent errors, tool
+// executions and plugin crashes. A dedicated subscriber keeps the core event
+// flow untouched - if no webhooks are configured nothing is spawned.
+//
+// Trade-offs:
+// - Fire-and-forget vs guaranteed delivery: Retries with backoff, but failed
+//   deliveries are logged and dropped (no persistent outbox)
+// - Templating: Simple {{placeholder}} substitution instead of a template engine
+//   dependency - enough for JSON/form payloads
+// - Sequential delivery per event: Simple ordering guarantees, slow endpoints
+//   delay later deliveries (mitigated by request timeout)
+//
+// Extension Points:
+// - Add more WebhookEventKind variants as new event types are needed
+// - Add HMAC signing of payloads
+// - Persist failed deliveries for later replay
+
+use crate::error::{Result, RustbotError};
+use crate::events::{AgentStatus, Event, EventBus, EventKind, McpPluginEvent, PluginHealthStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Default number of delivery retries after the first attempt
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Default HTTP timeout for a single delivery attempt (seconds)
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// Base delay for exponential retry backoff (milliseconds)
+const RETRY_BASE_DELAY_MS: u64 = 500;
+
+/// Top-level webhook configuration
+///
+/// File Format: JSON
+/// Location: ~/.rustbot/webhooks.json
+///
+/// Example:
+///     {
+///       "webhooks": [
+///         {
+///           "id": "slack",
+///           "url": "https://hooks.slack.com/services/...",
+///           "events": ["agent_error", "plugin_crash"],
+///           "template": "{\"text\": \"[{{kind}}] {{source}}: {{message}}\"}"
+///         }
+///       ]
+///     }
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    #[serde(default)]
+    pub webhooks: Vec<WebhookTarget>,
+}
+
+/// A single webhook endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookTarget {
+    /// Unique identifier (used in logs)
+    pub id: String,
+
+    /// Endpoint URL that receives POST requests
+    pub url: String,
+
+    /// Event kinds forwarded to this endpoint (empty = all supported kinds)
+    #[serde(default)]
+    pub events: Vec<WebhookEventKind>,
+
+    /// Optional body template with {{placeholder}} substitution
+    ///
+    /// Available placeholders: kind, source, destination, message,
+    /// timestamp, correlation_id. When omitted, a JSON payload is sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+
+    /// Extra HTTP headers (values support ${ENV_VAR} substitution)
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+
+    /// Number of retries after a failed delivery
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+
+    /// Per-attempt timeout in seconds
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+
+    /// Whether this webhook is active
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_max_retries() -> u32 {
+    DEFAULT_MAX_RETRIES
+}
+fn default_timeout() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+fn default_true() -> bool {
+    true
+}
+
+/// Event categories that can be forwarded to webhooks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    /// Agent transitioned to an error status
+    AgentError,
+    /// Agent started executing a tool
+    ToolExecution,
+    /// MCP plugin reported an error or was found dead
+    PluginCrash,
+}
+
+impl WebhookEventKind {
+    /// Classify a bus event, returning None for events webhooks don't handle
+    pub fn classify(event: &Event) -> Option<Self> {
+        match &event.kind {
+            EventKind::AgentStatusChange {
+                status: AgentStatus::Error(_),
+                ..
+            } => Some(Self::AgentError),
+            EventKind::AgentStatusChange {
+                status: AgentStatus::ExecutingTool(_),
+                ..
+            } => Some(Self::ToolExecution),
+            EventKind::McpPluginEvent(McpPluginEvent::Error { .. })
+            | EventKind::McpPluginEvent(McpPluginEvent::HealthStatus {
+                status: PluginHealthStatus::Dead,
+                ..
+            }) => Some(Self::PluginCrash),
+            _ => None,
+        }
+    }
+
+    /// Stable string name used in payloads and templates
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AgentError => "agent_error",
+            Self::ToolExecution => "tool_execution",
+            Self::PluginCrash => "plugin_crash",
+        }
+    }
+}
+
+/// Flattened view of an event used for payload rendering
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookPayload {
+    pub kind: String,
+    pub source: String,
+    pub destination: String,
+    pub message: String,
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+impl WebhookPayload {
+    /// Build a payload from a classified event
+    pub fn from_event(kind: WebhookEventKind, event: &Event) -> Self {
+        let message = match &event.kind {
+            EventKind::AgentStatusChange {
+                agent_id,
+                status: AgentStatus::Error(msg),
+            } => format!("Agent '{}' error: {}", agent_id, msg),
+            EventKind::AgentStatusChange {
+                agent_id,
+                status: AgentStatus::ExecutingTool(tool),
+            } => format!("Agent '{}' executing tool '{}'", agent_id, tool),
+            EventKind::McpPluginEvent(McpPluginEvent::Error { plugin_id, message }) => {
+                format!("Plugin '{}' error: {}", plugin_id, message)
+            }
+            EventKind::McpPluginEvent(McpPluginEvent::HealthStatus { plugin_id, .. }) => {
+                format!("Plugin '{}' is dead", plugin_id)
+            }
+            other => format!("{:?}", other),
+        };
+
+        Self {
+            kind: kind.as_str().to_string(),
+            source: event.source.clone(),
+            destination: event.destination.clone(),
+            // Goes to a third party: no secrets from error text
+            message: crate::redact::redact(&message).into_owned(),
+            timestamp: event.timestamp.to_rfc3339(),
+            correlation_id: event.correlation_id.clone(),
+        }
+    }
+
+    /// Render the request body, using the template if provided
+    ///
+    /// Placeholder values are JSON-escaped so templates producing JSON stay
+    /// valid, and filled in one pass: a placeholder inside a value (such as an
+    /// error message) stays as it is.
+    pub fn render(&self, template: Option<&str>) -> String {
+        let Some(template) = template else {
+            return serde_json::to_string(self).unwrap_or_default();
+        };
+
+        let escape = |value: &str| {
+            let quoted = serde_json::to_string(value).unwrap_or_default();
+            quoted[1..quoted.len() - 1].to_string()
+        };
+
+        crate::prompt_library::fill_placeholders(template, |name| {
+            let value = match name {
+                "kind" => &self.kind,
+                "source" => &self.source,
+                "destination" => &self.destination,
+                "message" => &self.message,
+                "timestamp" => &self.timestamp,
+                "correlation_id" => self.correlation_id.as_deref().unwrap_or(""),
+                _ => return None,
+            };
+            Some(escape(value))
+        })
+    }
+}
+
+impl WebhookTarget {
+    /// Whether this target wants events of the given kind
+    pub fn accepts(&self, kind: WebhookEventKind) -> bool {
+        self.enabled && (self.events.is_empty() || self.events.contains(&kind))
+    }
+}
+
+impl WebhookConfig {
+    /// Default config location: ~/.rustbot/webhooks.json
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("webhooks.json")
+    }
+
+    /// Load configuration from a file
+    ///
+    /// # Returns
+    /// Ok(None) if the file doesn't exist (webhooks disabled), Ok(Some) if loaded
+    ///
+    /// # Errors
+    /// - File exists but cannot be read
+    /// - Invalid JSON
+    /// - Validation failure (empty or non-HTTP URL, duplicate IDs)
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        let config: Self = serde_json::from_str(&content)?;
+        config.validate()?;
+        Ok(Some(config))
+    }
+
+    /// Validate webhook definitions
+    pub fn validate(&self) -> Result<()> {
+        let mut seen = std::collections::HashSet::new();
+        for target in &self.webhooks {
+            if !seen.insert(target.id.as_str()) {
+                return Err(RustbotError::ConfigError(format!(
+                    "Duplicate webhook id '{}'",
+                    target.id
+                )));
+            }
+            if !(target.url.starts_with("http://") || target.url.starts_with("https://")) {
+                return Err(RustbotError::ConfigError(format!(
+                    "Webhook '{}' has invalid URL '{}'",
+                    target.id, target.url
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Delivers matching events to configured webhook endpoints
+pub struct WebhookSink {
+    client: reqwest::Client,
+    config: WebhookConfig,
+}
+
+impl WebhookSink {
+    /// Create a sink for the given configuration
+    pub fn new(config: WebhookConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config,
+        }
+    }
+
+    /// Subscribe to the event bus and deliver matching events in the background
+    ///
+    /// The task ends when the event bus is dropped. Lag is reported by the
+    /// named subscriber rather than terminating the task.
+    pub fn spawn(self, event_bus: &EventBus, handle: &tokio::runtime::Handle) -> JoinHandle<()> {
+        let mut rx = event_bus.subscribe_named("webhooks");
+
+        handle.spawn(async move {
+            tracing::info!(
+                "Webhook sink started ({} targets)",
+                self.config.webhooks.len()
+            );
+
+            while let Some(event) = rx.recv().await {
+                self.handle_event(&event).await;
+            }
+
+            tracing::info!("Webhook sink stopped");
+        })
+    }
+
+    /// Deliver a single event to every interested target
+    async fn handle_event(&self, event: &Event) {
+        let Some(kind) = WebhookEventKind::classify(event) else {
+            return;
+        };
+
+        let payload = WebhookPayload::from_event(kind, event);
+        for target in self.config.webhooks.iter().filter(|t| t.accepts(kind)) {
+            if let Err(e) = crate::privacy::check_url(&target.url) {
+                tracing::debug!("Webhook '{}' skipped: {}", target.id, e);
+                continue;
+            }
+            let body = payload.render(target.template.as_deref());
+            if let Err(e) = self.deliver(target, body).await {
+                tracing::warn!("Webhook '{}' delivery failed: {}", target.id, e);
+            }
+        }
+    }
+
+    /// POST a body to a target with exponential backoff retries
+    async fn deliver(&self, target: &WebhookTarget, body: String) -> Result<()> {
+        let mut attempt = 0;
+
+        loop {
+            let mut request = self
+                .client
+                .post(&target.url)
+                .timeout(Duration::from_secs(target.timeout))
+                .header("Content-Type", "application/json")
+                .body(body.clone());
+
+            for (name, value) in &target.headers {
+                request = request.header(name, resolve_env_vars(value));
+            }
+
+            let outcome = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => format!("HTTP {}", response.status()),
+                Err(e) => e.to_string(),
+            };
+
+            if attempt >= target.max_retries {
+                return Err(RustbotError::ApiError(format!(
+                    "giving up after {} attempts: {}",
+                    attempt + 1,
+                    outcome
+                )));
+            }
+
+            let delay =
+                Duration::from_millis(RETRY_BASE_DELAY_MS.saturating_mul(1 << attempt.min(6)));
+            tracing::debug!(
+                "Webhook '{}' attempt {} failed ({}), retrying in {:?}",
+                target.id,
+                attempt + 1,
+                outcome,
+                delay
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Replace ${VAR} references with environment variable values
+///
+/// Unknown variables are replaced with an empty string.
+fn resolve_env_vars(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        match rest[start + 2..].find('}') {
+            Some(end) => {
+                let name = &rest[start + 2..start + 2 + end];
+                result.push_str(&std::env::var(name).unwrap_or_default());
+                rest = &rest[start + 2 + end + 1..];
+            }
+            None => {
+                result.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status_event(status: AgentStatus) -> Event {
+        Event::new(
+            "assistant".to_string(),
+            "broadcast".to_string(),
+            EventKind::AgentStatusChange {
+                agent_id: "assistant".to_string(),
+                status,
+            },
+        )
+    }
+
+    #[test]
+    fn test_classify_events() {
+        assert_eq!(
+            WebhookEventKind::classify(&status_event(AgentStatus::Error("boom".into()))),
+            Some(WebhookEventKind::AgentError)
+        );
+        assert_eq!(
+            WebhookEventKind::classify(&status_event(AgentStatus::ExecutingTool("web".into()))),
+            Some(WebhookEventKind::ToolExecution)
+        );
+        assert_eq!(
+            WebhookEventKind::classify(&status_event(AgentStatus::Thinking)),
+            None
+        );
+
+        let crash = Event::new(
+            "mcp_manager".to_string(),
+            "broadcast".to_string(),
+            EventKind::McpPluginEvent(McpPluginEvent::HealthStatus {
+                plugin_id: "fs".to_string(),
+                status: PluginHealthStatus::Dead,
+            }),
+        );
+        assert_eq!(
+            WebhookEventKind::classify(&crash),
+            Some(WebhookEventKind::PluginCrash)
+        );
+    }
+
+    #[test]
+    fn test_render_template_escapes_values() {
+        let event = status_event(AgentStatus::Error("bad \"quote\"".into()))
+            .with_correlation_id(Some("turn-1-0".to_string()));
+        let payload = WebhookPayload::from_event(WebhookEventKind::AgentError, &event);
+
+        let body = payload.render(Some(
+            r#"{"text": "{{kind}}: {{message}} ({{correlation_id}})"}"#,
+        ));
+        let parsed: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            parsed["text"],
+            "agent_error: Agent 'assistant' error: bad \"quote\" (turn-1-0)"
+        );
+    }
+
+    #[test]
+    fn test_render_fills_placeholders_once() {
+        let event = status_event(AgentStatus::Error(
+            "bad value {{timestamp}} for key sk-or-v1-0123456789abcdef0123".into(),
+        ));
+        let payload = WebhookPayload::from_event(WebhookEventKind::AgentError, &event);
+
+        let body = payload.render(Some(r#"{"text": "{{message}}"}"#));
+        let parsed: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            parsed["text"],
+            "Agent 'assistant' error: bad value {{timestamp}} for key [REDACTED]"
+        );
+    }
+
+    #[test]
+    fn test_render_default_payload() {
+        let event = status_event(AgentStatus::ExecutingTool("web_search".into()));
+        let payload = WebhookPayload::from_event(WebhookEventKind::ToolExecution, &event);
+
+        let parsed: serde_json::Value = serde_json::from_str(&payload.render(None)).unwrap();
+        assert_eq!(parsed["kind"], "tool_execution");
+        assert_eq!(parsed["source"], "assistant");
+        assert!(parsed.get("correlation_id").is_none());
+    }
+
+    #[test]
+    fn test_target_filtering() {
+        let config: WebhookConfig = serde_json::from_str(
+            r#"{"webhooks": [{"id": "a", "url": "https://example.com", "events": ["plugin_crash"]}]}"#,
+        )
+        .unwrap();
+        let target = &config.webhooks[0];
+
+        assert!(target.accepts(WebhookEventKind::PluginCrash));
+        assert!(!target.accepts(WebhookEventKind::AgentError));
+        assert_eq!(target.max_retries, DEFAULT_MAX_RETRIES);
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_config() {
+        let config: WebhookConfig =
+            serde_json::from_str(r#"{"webhooks": [{"id": "a", "url": "ftp://example.com"}]}"#)
+                .unwrap();
+        assert!(config.validate().is_err());
+
+        let config: WebhookConfig = serde_json::from_str(
+            r#"{"webhooks": [
+                {"id": "a", "url": "https://example.com"},
+                {"id": "a", "url": "https://example.org"}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_load_missing_file_disables_webhooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = WebhookConfig::load(&dir.path().join("webhooks.json")).unwrap();
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_resolve_env_vars() {
+        std::env::set_var("RUSTBOT_WEBHOOK_TEST_TOKEN", "secret");
+        assert_eq!(
+            resolve_env_vars("Bearer ${RUSTBOT_WEBHOOK_TEST_TOKEN}"),
+            "Bearer secret"
+        );
+        assert_eq!(resolve_env_vars("no vars"), "no vars");
+        assert_eq!(resolve_env_vars("${UNCLOSED"), "${UNCLOSED");
+    }
+}
@@ -0,0 +1,521 @@
+// Rustbot plugin SDK
+// Build native extensions that Rustbot loads from ~/.rustbot/native_plugins:
+// agent tools, event listeners and settings panels, without the separate
+// process an MCP server needs.
+//
+// Design Decision: A stable C ABI (one `rustbot_plugin_entry` symbol
+// returning a table of `extern "C"` functions) with JSON strings crossing it
+//
+// Rationale: Rust has no stable ABI, so a plugin built with another compiler
+// version can't share Rust types with the host. C strings and function
+// pointers are stable; JSON keeps the table small while the manifest, tool
+// arguments and events grow. Plugins implement `Plugin` and call
+// `export_plugin!`; the macro generates the table and catches panics so they
+// never unwind into the host.
+//
+// Example (crate-type = ["cdylib"]):
+//     use rustbot_plugin::{export_plugin, Manifest, Plugin, ToolSpec};
+//     use serde_json::Value;
+//
+//     #[derive(Default)]
+//     struct Shout;
+//
+//     impl Plugin for Shout {
+//         fn manifest(&self) -> Manifest {
+//             Manifest::new("shout", env!("CARGO_PKG_VERSION"), "Upper-cases text")
+//                 .tool(ToolSpec::new("shout", "Upper-case a text").param("text", "Text"))
+//         }
+//
+//         fn call_tool(&mut self, _name: &str, args: &Value) -> Result<String, String> {
+//             Ok(args["text"].as_str().unwrap_or_default().to_uppercase())
+//         }
+//     }
+//
+//     export_plugin!(Shout, Shout::default());
+//
+// Trade-offs:
+// - Plugins run in the Rustbot process with the user's permissions; they are
+//   off until the user enables them
+// - Libraries are never unloaded (a plugin's threads could outlive it);
+//   updating one takes a restart
+// - Settings panels are declared (`SettingSpec`) and drawn by Rustbot, so
+//   plugins don't depend on the GUI toolkit
+
+use serde::{Deserialize, Serialize};
+use std::os::raw::c_char;
+
+/// ABI version of `PluginVTable`; the host refuses plugins built for another
+pub const ABI_VERSION: u32 = 1;
+
+/// Symbol every plugin exports (see `export_plugin!`)
+pub const ENTRY_SYMBOL: &[u8] = b"rustbot_plugin_entry\0";
+
+/// Signature of the entry symbol
+pub type EntryFn = unsafe extern "C" fn() -> *const PluginVTable;
+
+/// Functions a plugin exposes to the host
+///
+/// Strings passed in are owned by the host and only borrowed; strings
+/// returned are owned by the plugin and handed back through `free_string`.
+#[repr(C)]
+pub struct PluginVTable {
+    pub abi_version: u32,
+
+    /// The `Manifest` as JSON
+    pub manifest: extern "C" fn() -> *mut c_char,
+
+    /// Run a tool: (name, JSON arguments) -> `ToolResult` as JSON
+    pub call_tool: extern "C" fn(*const c_char, *const c_char) -> *mut c_char,
+
+    /// Deliver a `PluginEvent` (JSON) the manifest subscribed to
+    pub on_event: extern "C" fn(*const c_char),
+
+    /// Apply the user's settings (JSON object keyed by `SettingSpec::key`)
+    pub configure: extern "C" fn(*const c_char),
+
+    /// Free a string returned by the plugin
+    pub free_string: extern "C" fn(*mut c_char),
+}
+
+/// What a plugin provides
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+
+    #[serde(default)]
+    pub tools: Vec<ToolSpec>,
+
+    /// Event kinds to receive (see `PluginEvent::kind`)
+    #[serde(default)]
+    pub events: Vec<String>,
+
+    /// Fields of the plugin's settings panel
+    #[serde(default)]
+    pub settings: Vec<SettingSpec>,
+}
+
+impl Manifest {
+    pub fn new(name: &str, version: &str, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            description: description.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Add a tool
+    pub fn tool(mut self, tool: ToolSpec) -> Self {
+        self.tools.push(tool);
+        self
+    }
+
+    /// Subscribe to an event kind ("user_message", "response", ...)
+    pub fn event(mut self, kind: &str) -> Self {
+        self.events.push(kind.to_string());
+        self
+    }
+
+    /// Add a settings field
+    pub fn setting(mut self, setting: SettingSpec) -> Self {
+        self.settings.push(setting);
+        self
+    }
+}
+
+/// A tool offered to the assistant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+
+    /// JSON schema properties of the arguments object
+    #[serde(default = "empty_object")]
+    pub properties: serde_json::Value,
+
+    /// Required argument names
+    #[serde(default)]
+    pub required: Vec<String>,
+}
+
+fn empty_object() -> serde_json::Value {
+    serde_json::Value::Object(Default::default())
+}
+
+impl ToolSpec {
+    pub fn new(name: &str, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            properties: empty_object(),
+            required: Vec::new(),
+        }
+    }
+
+    /// Add a required string parameter
+    pub fn param(mut self, name: &str, description: &str) -> Self {
+        self.required.push(name.to_string());
+        self.optional_param(name, description)
+    }
+
+    /// Add an optional string parameter
+    pub fn optional_param(mut self, name: &str, description: &str) -> Self {
+        if let Some(properties) = self.properties.as_object_mut() {
+            properties.insert(
+                name.to_string(),
+                serde_json::json!({ "type": "string", "description": description }),
+            );
+        }
+        self
+    }
+}
+
+/// A field in the plugin's settings panel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SettingSpec {
+    pub key: String,
+    pub label: String,
+
+    #[serde(default)]
+    pub help: String,
+
+    pub kind: SettingKind,
+
+    /// Value until the user changes it
+    #[serde(default)]
+    pub default: serde_json::Value,
+}
+
+impl SettingSpec {
+    pub fn new(key: &str, label: &str, kind: SettingKind, default: serde_json::Value) -> Self {
+        Self {
+            key: key.to_string(),
+            label: label.to_string(),
+            help: String::new(),
+            kind,
+            default,
+        }
+    }
+
+    /// Hover text for the field
+    pub fn help(mut self, help: &str) -> Self {
+        self.help = help.to_string();
+        self
+    }
+}
+
+/// How a setting is edited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingKind {
+    Text,
+    Toggle,
+    Number,
+}
+
+/// An event delivered to plugins that subscribed to its kind
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PluginEvent {
+    UserMessage { text: String },
+    Response { agent_id: String, text: String },
+    AgentError { agent_id: String, message: String },
+    Tool { agent_id: String, tool: String },
+    PluginCrash { plugin_id: String, message: String },
+}
+
+impl PluginEvent {
+    /// Kind name used in `Manifest::events`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PluginEvent::UserMessage { .. } => "user_message",
+            PluginEvent::Response { .. } => "response",
+            PluginEvent::AgentError { .. } => "agent_error",
+            PluginEvent::Tool { .. } => "tool",
+            PluginEvent::PluginCrash { .. } => "plugin_crash",
+        }
+    }
+}
+
+/// Result of `PluginVTable::call_tool`: `{"ok": "..."}` or `{"error": "..."}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolResult {
+    Ok(String),
+    Error(String),
+}
+
+/// A native plugin
+///
+/// One instance lives for the whole session; calls are serialized.
+pub trait Plugin: Send + 'static {
+    /// Name, tools, event subscriptions and settings
+    fn manifest(&self) -> Manifest;
+
+    /// Run one of the manifest's tools
+    ///
+    /// # Returns
+    /// The text given to the assistant, or an error message
+    fn call_tool(&mut self, name: &str, arguments: &serde_json::Value) -> Result<String, String>;
+
+    /// React to an event the manifest subscribed to
+    fn on_event(&mut self, _event: &PluginEvent) {}
+
+    /// Apply settings; called after loading and whenever the user saves
+    fn configure(&mut self, _settings: &serde_json::Map<String, serde_json::Value>) {}
+}
+
+/// Export a `Plugin` type as the library's `rustbot_plugin_entry`
+///
+/// # Arguments
+/// * `$plugin` - Type implementing `Plugin`
+/// * `$init` - Expression creating it (run on first use)
+#[macro_export]
+macro_rules! export_plugin {
+    ($plugin:ty, $init:expr) => {
+        fn __rustbot_plugin() -> &'static ::std::sync::Mutex<$plugin> {
+            static PLUGIN: ::std::sync::OnceLock<::std::sync::Mutex<$plugin>> =
+                ::std::sync::OnceLock::new();
+            PLUGIN.get_or_init(|| ::std::sync::Mutex::new($init))
+        }
+
+        extern "C" fn __rustbot_manifest() -> *mut ::std::os::raw::c_char {
+            $crate::ffi::manifest(__rustbot_plugin())
+        }
+
+        extern "C" fn __rustbot_call_tool(
+            name: *const ::std::os::raw::c_char,
+            arguments: *const ::std::os::raw::c_char,
+        ) -> *mut ::std::os::raw::c_char {
+            unsafe { $crate::ffi::call_tool(__rustbot_plugin(), name, arguments) }
+        }
+
+        extern "C" fn __rustbot_on_event(event: *const ::std::os::raw::c_char) {
+            unsafe { $crate::ffi::on_event(__rustbot_plugin(), event) }
+        }
+
+        extern "C" fn __rustbot_configure(settings: *const ::std::os::raw::c_char) {
+            unsafe { $crate::ffi::configure(__rustbot_plugin(), settings) }
+        }
+
+        extern "C" fn __rustbot_free_string(text: *mut ::std::os::raw::c_char) {
+            unsafe { $crate::ffi::free_string(text) }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn rustbot_plugin_entry() -> *const $crate::PluginVTable {
+            static VTABLE: $crate::PluginVTable = $crate::PluginVTable {
+                abi_version: $crate::ABI_VERSION,
+                manifest: __rustbot_manifest,
+                call_tool: __rustbot_call_tool,
+                on_event: __rustbot_on_event,
+                configure: __rustbot_configure,
+                free_string: __rustbot_free_string,
+            };
+            &VTABLE
+        }
+    };
+}
+
+/// Glue used by `export_plugin!`; not part of the stable API
+#[doc(hidden)]
+pub mod ffi {
+    use super::{Plugin, PluginEvent, ToolResult};
+    use std::ffi::{CStr, CString};
+    use std::os::raw::c_char;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::sync::{Mutex, MutexGuard};
+
+    fn lock<P>(plugin: &Mutex<P>) -> MutexGuard<'_, P> {
+        // A panic in an earlier call doesn't disable the plugin
+        plugin
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn to_c_string(text: String) -> *mut c_char {
+        // Interior NULs can't cross the ABI; drop them
+        CString::new(text.replace('\0', ""))
+            .unwrap_or_default()
+            .into_raw()
+    }
+
+    /// # Safety
+    /// `text` must be null or a valid NUL-terminated string
+    unsafe fn from_c_str(text: *const c_char) -> String {
+        if text.is_null() {
+            return String::new();
+        }
+        CStr::from_ptr(text).to_string_lossy().into_owned()
+    }
+
+    pub fn manifest<P: Plugin>(plugin: &Mutex<P>) -> *mut c_char {
+        let json = catch_unwind(AssertUnwindSafe(|| {
+            serde_json::to_string(&lock(plugin).manifest()).unwrap_or_default()
+        }));
+        to_c_string(json.unwrap_or_default())
+    }
+
+    /// # Safety
+    /// `name` and `arguments` must be null or valid NUL-terminated strings
+    pub unsafe fn call_tool<P: Plugin>(
+        plugin: &Mutex<P>,
+        name: *const c_char,
+        arguments: *const c_char,
+    ) -> *mut c_char {
+        let (name, arguments) = (from_c_str(name), from_c_str(arguments));
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let arguments: serde_json::Value = if arguments.trim().is_empty() {
+                serde_json::Value::Object(Default::default())
+            } else {
+                serde_json::from_str(&arguments)
+                    .map_err(|e| format!("Invalid tool arguments: {}", e))?
+            };
+            lock(plugin).call_tool(&name, &arguments)
+        }));
+        let result = match result {
+            Ok(Ok(text)) => ToolResult::Ok(text),
+            Ok(Err(message)) => ToolResult::Error(message),
+            Err(_) => ToolResult::Error(format!("Tool '{}' panicked", name)),
+        };
+        to_c_string(serde_json::to_string(&result).unwrap_or_default())
+    }
+
+    /// # Safety
+    /// `event` must be null or a valid NUL-terminated string
+    pub unsafe fn on_event<P: Plugin>(plugin: &Mutex<P>, event: *const c_char) {
+        let Ok(event) = serde_json::from_str::<PluginEvent>(&from_c_str(event)) else {
+            return;
+        };
+        let _ = catch_unwind(AssertUnwindSafe(|| lock(plugin).on_event(&event)));
+    }
+
+    /// # Safety
+    /// `settings` must be null or a valid NUL-terminated string
+    pub unsafe fn configure<P: Plugin>(plugin: &Mutex<P>, settings: *const c_char) {
+        let settings = serde_json::from_str(&from_c_str(settings)).unwrap_or_default();
+        let _ = catch_unwind(AssertUnwindSafe(|| lock(plugin).configure(&settings)));
+    }
+
+    /// # Safety
+    /// `text` must be null or a string returned by this plugin, freed once
+    pub unsafe fn free_string(text: *mut c_char) {
+        if !text.is_null() {
+            drop(CString::from_raw(text));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::{CStr, CString};
+
+    #[derive(Default)]
+    struct Counter {
+        seen: usize,
+        prefix: String,
+    }
+
+    impl Plugin for Counter {
+        fn manifest(&self) -> Manifest {
+            Manifest::new("counter", "1.0.0", "Counts user messages")
+                .tool(
+                    ToolSpec::new("count", "Messages seen so far").optional_param("label", "Label"),
+                )
+                .event("user_message")
+                .setting(SettingSpec::new(
+                    "prefix",
+                    "Prefix",
+                    SettingKind::Text,
+                    serde_json::json!(""),
+                ))
+        }
+
+        fn call_tool(
+            &mut self,
+            name: &str,
+            arguments: &serde_json::Value,
+        ) -> Result<String, String> {
+            match name {
+                "count" => Ok(format!(
+                    "{}{} {}",
+                    self.prefix,
+                    arguments["label"].as_str().unwrap_or("messages"),
+                    self.seen
+                )),
+                "panic" => panic!("boom"),
+                _ => Err(format!("Unknown tool {}", name)),
+            }
+        }
+
+        fn on_event(&mut self, event: &PluginEvent) {
+            if let PluginEvent::UserMessage { .. } = event {
+                self.seen += 1;
+            }
+        }
+
+        fn configure(&mut self, settings: &serde_json::Map<String, serde_json::Value>) {
+            if let Some(prefix) = settings.get("prefix").and_then(|p| p.as_str()) {
+                self.prefix = prefix.to_string();
+            }
+        }
+    }
+
+    export_plugin!(Counter, Counter::default());
+
+    fn take(vtable: &PluginVTable, text: *mut c_char) -> String {
+        let owned = unsafe { CStr::from_ptr(text) }
+            .to_string_lossy()
+            .into_owned();
+        (vtable.free_string)(text);
+        owned
+    }
+
+    #[test]
+    fn test_vtable_round_trip() {
+        let vtable = unsafe { &*rustbot_plugin_entry() };
+        assert_eq!(vtable.abi_version, ABI_VERSION);
+
+        let manifest: Manifest = serde_json::from_str(&take(vtable, (vtable.manifest)())).unwrap();
+        assert_eq!(manifest.name, "counter");
+        assert_eq!(manifest.tools[0].required, Vec::<String>::new());
+        assert_eq!(manifest.events, vec!["user_message"]);
+
+        let event = serde_json::to_string(&PluginEvent::UserMessage {
+            text: "hi".to_string(),
+        })
+        .unwrap();
+        assert!(event.contains(r#""type":"user_message""#));
+        let event = CString::new(event).unwrap();
+        (vtable.on_event)(event.as_ptr());
+        let settings = CString::new(r#"{"prefix":"> "}"#).unwrap();
+        (vtable.configure)(settings.as_ptr());
+
+        let call = |name: &str, arguments: &str| -> ToolResult {
+            let (name, arguments) = (
+                CString::new(name).unwrap(),
+                CString::new(arguments).unwrap(),
+            );
+            let result = take(
+                vtable,
+                (vtable.call_tool)(name.as_ptr(), arguments.as_ptr()),
+            );
+            serde_json::from_str(&result).unwrap()
+        };
+        assert_eq!(
+            call("count", r#"{"label":"seen"}"#),
+            ToolResult::Ok("> seen 1".to_string())
+        );
+        assert!(matches!(call("count", "not json"), ToolResult::Error(_)));
+        assert!(matches!(call("missing", ""), ToolResult::Error(_)));
+        // A panic is reported as an error and the plugin keeps working
+        assert_eq!(
+            call("panic", ""),
+            ToolResult::Error("Tool 'panic' panicked".to_string())
+        );
+        assert!(matches!(call("count", ""), ToolResult::Ok(_)));
+    }
+}
@@ -0,0 +1,200 @@
+-- Rustbot companion for Neovim
+--
+-- Talks JSON-RPC to a `rustbot rpc` child process (see
+-- crates/rustbot-core/src/rpc.rs and editor.rs). The current file and
+-- selection are sent with each question; the answer streams into a scratch
+-- window, and :RustbotApply applies the suggested change.
+
+local M = {}
+
+M.config = {
+  -- Command that starts the backend
+  cmd = { "rustbot", "rpc" },
+  -- Agent to ask (nil = the backend's active agent)
+  agent = nil,
+  -- Send the whole file when nothing is selected
+  send_file = true,
+}
+
+local job = nil
+local next_id = 0
+local pending = {} -- request id -> { callback, on_chunk }
+local partial = ""
+local last = nil -- { bufnr, selection, result }
+local response_buf = nil
+
+local function notify(message, level)
+  vim.notify("rustbot: " .. message, level or vim.log.levels.INFO)
+end
+
+local function handle_message(message)
+  if message.method == "chat.chunk" then
+    local request = pending[message.params.id]
+    if request and request.on_chunk then
+      request.on_chunk(message.params.text)
+    end
+  elseif message.id ~= nil then
+    local request = pending[message.id]
+    pending[message.id] = nil
+    if request then
+      request.callback(message.error, message.result)
+    end
+  end
+end
+
+local function on_stdout(_, data)
+  -- Neovim splits output on newlines; the last item is an unfinished line
+  data[1] = partial .. data[1]
+  partial = table.remove(data)
+  for _, line in ipairs(data) do
+    if line ~= "" then
+      local ok, message = pcall(vim.json.decode, line)
+      if ok then
+        handle_message(message)
+      end
+    end
+  end
+end
+
+local function start()
+  if job then
+    return true
+  end
+  job = vim.fn.jobstart(M.config.cmd, {
+    on_stdout = on_stdout,
+    on_exit = function(_, code)
+      job = nil
+      partial = ""
+      for _, request in pairs(pending) do
+        request.callback({ message = "backend exited (" .. code .. ")" }, nil)
+      end
+      pending = {}
+    end,
+  })
+  if job <= 0 then
+    job = nil
+    notify("failed to start " .. table.concat(M.config.cmd, " "), vim.log.levels.ERROR)
+    return false
+  end
+  return true
+end
+
+--- Send a JSON-RPC request to the backend
+function M.request(method, params, callback, on_chunk)
+  if not start() then
+    return
+  end
+  next_id = next_id + 1
+  pending[next_id] = { callback = callback or function() end, on_chunk = on_chunk }
+  local message = { jsonrpc = "2.0", id = next_id, method = method, params = params }
+  vim.fn.chansend(job, vim.json.encode(message) .. "\n")
+end
+
+--- Editor context for the current buffer (and line range, if given)
+local function buffer_context(bufnr, line1, line2)
+  local path = vim.api.nvim_buf_get_name(bufnr)
+  local root = vim.fn.getcwd()
+  local context = {
+    path = path ~= "" and vim.fn.fnamemodify(path, ":.") or nil,
+    language = vim.bo[bufnr].filetype ~= "" and vim.bo[bufnr].filetype or nil,
+    root = root,
+  }
+  if line1 then
+    local lines = vim.api.nvim_buf_get_lines(bufnr, line1 - 1, line2, false)
+    context.selection = { text = table.concat(lines, "\n"), start_line = line1, end_line = line2 }
+  elseif M.config.send_file then
+    context.content = table.concat(vim.api.nvim_buf_get_lines(bufnr, 0, -1, false), "\n")
+  end
+  return context
+end
+
+local function show_response()
+  if not (response_buf and vim.api.nvim_buf_is_valid(response_buf)) then
+    response_buf = vim.api.nvim_create_buf(false, true)
+    vim.bo[response_buf].filetype = "markdown"
+    vim.api.nvim_buf_set_name(response_buf, "rustbot://response")
+  end
+  if vim.fn.bufwinid(response_buf) == -1 then
+    vim.cmd("botright vsplit")
+    vim.api.nvim_win_set_buf(0, response_buf)
+    vim.cmd("wincmd p")
+  end
+  vim.api.nvim_buf_set_lines(response_buf, 0, -1, false, { "" })
+  return response_buf
+end
+
+local function append(buf, text)
+  local last_line = vim.api.nvim_buf_line_count(buf) - 1
+  local current = vim.api.nvim_buf_get_lines(buf, last_line, last_line + 1, false)[1] or ""
+  local lines = vim.split(current .. text, "\n", { plain = true })
+  vim.api.nvim_buf_set_lines(buf, last_line, last_line + 1, false, lines)
+end
+
+--- Ask about the current buffer; `line1`/`line2` limit it to a selection
+function M.ask(question, line1, line2)
+  if question == nil or question == "" then
+    question = vim.fn.input("Rustbot: ")
+    if question == "" then
+      return
+    end
+  end
+
+  local bufnr = vim.api.nvim_get_current_buf()
+  local context = buffer_context(bufnr, line1, line2)
+  local buf = show_response()
+
+  M.request("editor.ask", {
+    message = question,
+    agent = M.config.agent,
+    stream = true,
+    context = context,
+  }, function(err, result)
+    if err then
+      notify(err.message, vim.log.levels.ERROR)
+      return
+    end
+    last = { bufnr = bufnr, root = context.root, selection = context.selection, result = result }
+    local changes = #result.patches + (result.replacement ~= vim.NIL and result.replacement and 1 or 0)
+    if changes > 0 then
+      notify("suggested change ready, :RustbotApply to apply it")
+    end
+  end, function(text)
+    append(buf, text)
+  end)
+end
+
+--- Apply the change from the last answer
+function M.apply()
+  if not last then
+    notify("nothing to apply", vim.log.levels.WARN)
+    return
+  end
+  local result = last.result
+
+  if result.replacement and result.replacement ~= vim.NIL and last.selection then
+    local lines = vim.split(result.replacement, "\n", { plain = true })
+    vim.api.nvim_buf_set_lines(last.bufnr, last.selection.start_line - 1, last.selection.end_line, false, lines)
+    notify("replaced lines " .. last.selection.start_line .. "-" .. last.selection.end_line)
+    return
+  end
+
+  if #result.patches == 0 then
+    notify("the last answer has no patch", vim.log.levels.WARN)
+    return
+  end
+  for _, patch in ipairs(result.patches) do
+    local output = vim.fn.system({ "git", "-C", last.root, "apply", "--recount", "-" }, patch.diff)
+    if vim.v.shell_error ~= 0 then
+      notify("patch failed: " .. output, vim.log.levels.ERROR)
+      return
+    end
+  end
+  vim.cmd("checktime")
+  notify("applied " .. #result.patches .. " patch(es)")
+end
+
+function M.setup(opts)
+  M.config = vim.tbl_deep_extend("force", M.config, opts or {})
+end
+
+return M
@@ -0,0 +1,19 @@
+if vim.g.loaded_rustbot then
+  return
+end
+vim.g.loaded_rustbot = true
+
+-- :RustbotAsk [question]        ask about the current file
+-- :'<,'>RustbotAsk [question]   ask about the selected lines
+vim.api.nvim_create_user_command("RustbotAsk", function(opts)
+  local rustbot = require("rustbot")
+  if opts.range > 0 then
+    rustbot.ask(opts.args, opts.line1, opts.line2)
+  else
+    rustbot.ask(opts.args)
+  end
+end, { nargs = "*", range = true, desc = "Ask Rustbot about the current file or selection" })
+
+vim.api.nvim_create_user_command("RustbotApply", function()
+  require("rustbot").apply()
+end, { desc = "Apply the change suggested in Rustbot's last answer" })
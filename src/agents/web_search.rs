@@ -2,6 +2,7 @@
 // This agent specializes in using web search to find current, relevant information
 
 use crate::agent::AgentConfig;
+use crate::pruning::ContextPruning;
 
 /// Create a web search specialist agent
 ///
@@ -53,6 +54,7 @@ Format your responses clearly with:
         web_search_enabled: true,
         mcp_extensions: Vec::new(), // No MCP extensions by default
         mcp_config_file: None,      // Use global config by default
+        context_pruning: ContextPruning::default(),
     }
 }
 
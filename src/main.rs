@@ -1,144 +1,115 @@
-mod agent;
 mod agents;
-mod api;
-mod app_builder;
-mod error;
-mod events;
-mod llm;
-mod mcp;
-mod mermaid;
-mod services;
-mod tool_executor;
 mod ui;
 mod version;
 
+// Core functionality lives in the rustbot-core crate; importing the modules at
+// the crate root keeps `crate::agent::...` paths working in the GUI modules
+use rustbot_core::{
+    agent, analytics, api, app_builder, audit, backup, calendar, cli, connectivity,
+    conversation_export, conversation_import, deep_link, email, error, event_log, event_sequence,
+    events, feedback, folder_index, fs_consent, git_tools, graphviz, handoff, hooks,
+    instruction_profiles, ipc, llm, math, mcp, mermaid, migration, native_plugins, privacy,
+    projects, prompt_library, prompt_test, pruning, quota, redact, request_preview, scripting,
+    services, settings_bundle, share, system_context, theme, tokenizer, tool_toggles, usage,
+    wasm_tools, webhooks,
+};
+
 use agent::AgentConfig;
 use api::RustbotApi;
 use app_builder::{AppBuilder, AppDependencies};
 use eframe::egui;
-use egui_commonmark::CommonMarkCache;
 use egui_phosphor::regular as icons;
 use error::{Result, RustbotError};
 use events::{Event, EventBus, EventKind, SystemCommand};
-use llm::{create_adapter, AdapterType, LlmAdapter};
+use llm::{create_adapter, LlmAdapter};
 use mcp::manager::McpPluginManager;
-use std::collections::VecDeque;
+use services::SecretStore;
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
-use std::process::Command;
 use std::sync::Arc;
-use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio::sync::{mpsc, Mutex};
+use ui::accessibility::AccessibleLabel;
 use ui::icon::create_window_icon;
+use ui::theme::colors as theme_colors;
 use ui::{
-    AppView, ChatMessage, ContextTracker, ExtensionsView, MessageRole, PluginsView, SettingsView,
-    SystemPrompts, TokenStats, VisualEvent,
+    AppView, ChatMessage, ContextTracker, ExtensionsView, LegacyTokenStats, MessageRole,
+    PluginsView, SettingsView, SystemPrompts, VisualEvent,
 };
 
-/// Read a secret from 1Password using the CLI
-///
-/// # Arguments
-/// * `reference` - 1Password secret reference (format: `op://vault/item/field`)
-///
-/// # Returns
-/// * `Ok(String)` - The secret value
-/// * `Err(anyhow::Error)` - If reading fails
+/// Hand a rustbot:// link to an already running instance over the IPC socket
 ///
-/// # Errors
-/// - 1Password CLI not installed
-/// - Not signed in to 1Password
-/// - Secret reference not found
-/// - Invalid reference format
-fn read_1password_secret(reference: &str) -> anyhow::Result<String> {
-    use anyhow::Context;
-
-    // Validate reference format
-    if !reference.starts_with("op://") {
-        anyhow::bail!(
-            "Invalid 1Password reference format: '{}'. Must start with 'op://'",
-            reference
-        );
-    }
-
-    // Execute `op read` command
-    let output = Command::new("op")
-        .arg("read")
-        .arg(reference)
-        .output()
-        .with_context(|| {
-            format!(
-                "Failed to execute 1Password CLI. Is it installed?\n\
-                 Install: brew install 1password-cli\n\
-                 Reference: {}",
-                reference
-            )
-        })?;
-
-    // Check if command succeeded
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
+/// Returns false when no instance is listening, so this process should open
+/// the app itself.
+fn forward_deep_link(url: &str) -> bool {
+    let command = ipc::IpcCommand::OpenLink {
+        url: url.to_string(),
+    };
+    let reply = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime.block_on(ipc::send_command(&ipc::default_socket_path(), &command)),
+        Err(e) => {
+            tracing::warn!("Failed to create runtime for link forwarding: {}", e);
+            return false;
+        }
+    };
 
-        // Provide helpful error messages based on common failures
-        if stderr.contains("not currently signed in") || stderr.contains("signed out") {
-            anyhow::bail!(
-                "Not signed in to 1Password. Run: op signin\n\
-                 Reference: {}",
-                reference
-            );
-        } else if stderr.contains("isn't an item") || stderr.contains("not found") {
-            anyhow::bail!(
-                "1Password secret not found: {}\n\
-                 Error: {}",
-                reference,
-                stderr.trim()
-            );
-        } else {
-            anyhow::bail!(
-                "Failed to read 1Password secret: {}\n\
-                 Error: {}",
-                reference,
-                stderr.trim()
-            );
+    match reply {
+        Ok(ipc::IpcResponse::Error { message }) => {
+            tracing::warn!("Running instance rejected the link: {}", message);
+            true
+        }
+        Ok(_) => {
+            tracing::info!("🔗 Link handed to the running instance");
+            true
+        }
+        Err(e) => {
+            tracing::debug!("No running instance ({}), opening the link here", e);
+            false
         }
     }
+}
 
-    // Parse output
-    let secret = String::from_utf8(output.stdout)
-        .with_context(|| format!("1Password returned invalid UTF-8 for: {}", reference))?
-        .trim()
-        .to_string();
+fn main() -> std::result::Result<(), eframe::Error> {
+    use clap::Parser;
+    let args = cli::Cli::parse();
+    let verbose = args.verbose;
+    let deep_link_url = args.deep_link().map(str::to_string);
+    let command = args.into_command().unwrap_or_else(|e| e.exit());
 
-    // Ensure secret is not empty
-    if secret.is_empty() {
-        anyhow::bail!("1Password secret is empty: {}", reference);
+    // Initialize tracing for logging
+    // Headless commands log to stderr (warnings only unless --verbose) so
+    // stdout carries just the response. Log lines are scrubbed of API keys
+    // and other secrets (see `redact`).
+    if command.is_some() {
+        tracing_subscriber::fmt()
+            .with_writer(|| redact::RedactingWriter::new(std::io::stderr()))
+            .with_max_level(if verbose {
+                tracing::Level::INFO
+            } else {
+                tracing::Level::WARN
+            })
+            .init();
+    } else {
+        tracing_subscriber::fmt()
+            .with_writer(|| redact::RedactingWriter::new(std::io::stdout()))
+            .init();
     }
 
-    Ok(secret)
-}
-
-/// Resolve API key from environment variable or 1Password reference
-///
-/// Supports two formats:
-/// 1. `op://vault/item/field` - 1Password secret reference
-/// 2. Plain API key - Returned as-is
-///
-/// # Arguments
-/// * `value` - The environment variable value to resolve
-///
-/// # Returns
-/// * `Ok(String)` - The resolved API key
-/// * `Err(anyhow::Error)` - If resolution fails
-fn resolve_api_key(value: &str) -> anyhow::Result<String> {
-    // If it's a 1Password reference, resolve it
-    if value.starts_with("op://") {
-        return read_1password_secret(value);
+    // Extension installs, settings, keys and grants are recorded in the audit log
+    match audit::AuditLog::open(audit::AuditLog::default_path()) {
+        Ok(log) => audit::init(log),
+        Err(e) => tracing::warn!("Audit log disabled: {}", e),
     }
 
-    // Otherwise return as-is (plain API key)
-    Ok(value.to_string())
-}
-
-fn main() -> std::result::Result<(), eframe::Error> {
-    // Initialize tracing for logging
-    tracing_subscriber::fmt::init();
+    // rustbot:// link from the OS: a running instance takes it over, otherwise
+    // it's applied once this instance has started
+    let mut startup_link = None;
+    if let Some(url) = deep_link_url {
+        match deep_link::DeepLink::parse(&url) {
+            Ok(_) if forward_deep_link(&url) => return Ok(()),
+            Ok(link) => startup_link = Some(link),
+            Err(e) => tracing::warn!("Ignoring invalid link: {}", e),
+        }
+    }
 
     // Load .env.local file - try multiple locations for robustness
     // First try current directory, then executable directory
@@ -157,13 +128,17 @@ fn main() -> std::result::Result<(), eframe::Error> {
         tracing::warn!(".env.local file not found - will need OPENROUTER_API_KEY from environment");
     }
 
+    // Move plaintext keys from .env.local into the OS keychain (no-op once migrated)
+    let secrets = services::DefaultSecretStore::system();
+    secrets.migrate(services::secrets::MIGRATED_SECRETS);
+
     // Get API key with proper error handling to avoid panic in FFI boundary
     // If not found, we'll show setup wizard instead of exiting
-    // Also resolve 1Password references (op://...) if present
-    let api_key = match std::env::var("OPENROUTER_API_KEY") {
-        Ok(key_ref) => {
-            // Try to resolve the key (handles both plain keys and 1Password references)
-            match resolve_api_key(&key_ref) {
+    // Also resolve secret references (op://, keychain://, vault://, aws-sm://)
+    let api_key = match secrets.get(services::secrets::API_KEY_NAME) {
+        Ok(Some(key_ref)) => {
+            // Try to resolve the key (handles both plain keys and secret references)
+            match services::resolve_secret(&key_ref) {
                 Ok(resolved_key) => {
                     tracing::info!("✓ API key loaded successfully");
                     resolved_key
@@ -176,32 +151,65 @@ fn main() -> std::result::Result<(), eframe::Error> {
                     eprintln!("\nPossible solutions:");
                     eprintln!("  - If using 1Password: Ensure 1Password CLI is installed (brew install 1password-cli)");
                     eprintln!("  - If using 1Password: Sign in with 'op signin'");
-                    eprintln!("  - If using 1Password: Verify the reference is correct (op://vault/item/field)");
+                    eprintln!(
+                        "  - If using Vault or AWS: Sign in with 'vault login' or 'aws sso login'"
+                    );
+                    eprintln!("  - Verify the reference is correct (e.g. op://vault/item/field)");
                     eprintln!("  - Or set a plain API key in .env.local");
                     eprintln!("\nWill show setup wizard to configure API key...\n");
                     String::new() // Empty string triggers setup wizard
                 }
             }
         }
-        Err(_) => {
+        Ok(None) | Err(_) => {
             tracing::warn!("OPENROUTER_API_KEY not found - will show setup wizard");
             String::new()
         }
     };
 
-    // Build dependencies using AppBuilder
-    let deps = tokio::runtime::Runtime::new()
-        .expect("Failed to create runtime")
-        .block_on(async {
-            AppBuilder::new()
-                .with_api_key(api_key.clone())
-                .with_base_path(std::path::PathBuf::from("."))
-                .with_production_deps()
-                .await
-                .expect("Failed to build dependencies")
-                .build()
-                .expect("Failed to finalize dependencies")
-        });
+    // Headless commands can't show the setup wizard
+    if command.is_some() && api_key.is_empty() {
+        eprintln!("error: OPENROUTER_API_KEY is not configured (run rustbot once to set it up)");
+        std::process::exit(cli::EXIT_CONFIG);
+    }
+
+    // Config and agent files resolve ${OPENROUTER_API_KEY} from the environment,
+    // so expose a keychain-stored key there too
+    if !api_key.is_empty() {
+        std::env::set_var(services::secrets::API_KEY_NAME, &api_key);
+    }
+
+    // Same for the other providers' keys (agent configs with a `provider`)
+    for provider in llm::LlmProvider::ALL {
+        let name = provider.default_env_var();
+        if name.is_empty() || name == services::secrets::API_KEY_NAME || std::env::var(name).is_ok()
+        {
+            continue;
+        }
+        if let Ok(Some(key_ref)) = secrets.get(name) {
+            match services::resolve_secret(&key_ref) {
+                Ok(key) => std::env::set_var(name, key),
+                Err(e) => tracing::warn!("Failed to resolve {}: {}", name, e),
+            }
+        }
+    }
+    redact::register_env_secrets();
+
+    let deps = build_dependencies(&api_key);
+
+    if let Some(command) = command {
+        match deps {
+            Ok(deps) => std::process::exit(cli::run(command, deps)),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(cli::EXIT_CONFIG);
+            }
+        }
+    }
+
+    // macOS delivers links as Apple Events; register before the event loop
+    // starts so the link that launched the app isn't missed
+    deep_link::install_url_event_handler();
 
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
@@ -257,49 +265,400 @@ fn main() -> std::result::Result<(), eframe::Error> {
             // Apply fonts
             cc.egui_ctx.set_fonts(fonts);
 
-            Ok(Box::new(RustbotApp::new(deps, api_key)))
+            // Re-register now that AppKit has finished launching
+            deep_link::install_url_event_handler();
+
+            let mut shell = AppShell::start(&cc.egui_ctx, deps, api_key);
+            if let AppShell::Running(app) = &mut shell {
+                app.pending_deep_link = startup_link;
+            }
+            Ok(Box::new(shell))
         }),
     )
 }
 
+/// Build the service layer with AppBuilder
+///
+/// # Errors
+/// - The async runtime can't be created
+/// - A service fails to initialize (e.g. no API key, wrong storage passphrase)
+fn build_dependencies(api_key: &str) -> Result<AppDependencies> {
+    tokio::runtime::Runtime::new()?.block_on(async {
+        let mut builder = AppBuilder::new()
+            .with_api_key(api_key.to_string())
+            .with_base_path(std::path::PathBuf::from("."));
+        // Optional encryption at rest for conversations and profile
+        if let Some(passphrase) = services::encryption::passphrase_from_env() {
+            builder = builder.with_storage_passphrase(passphrase);
+        }
+        builder.with_production_deps().await?.build()
+    })
+}
+
+/// Top-level app: the chat, or the error screen if Rustbot couldn't start
+enum AppShell {
+    Running(Box<RustbotApp>),
+    Failed(ui::ErrorScreen),
+}
+
+impl AppShell {
+    /// Start the chat on the dependencies, or show why that failed
+    fn start(ctx: &egui::Context, deps: Result<AppDependencies>, api_key: String) -> Self {
+        match deps.and_then(|deps| RustbotApp::start(ctx, deps, api_key)) {
+            Ok(app) => Self::Running(Box::new(app)),
+            Err(e) => {
+                tracing::error!("Startup failed: {}", e);
+                Self::Failed(ui::ErrorScreen::new(e))
+            }
+        }
+    }
+
+    /// Build the dependencies again and start over (after a fix)
+    fn retry(ctx: &egui::Context) -> Self {
+        let api_key = std::env::var(services::secrets::API_KEY_NAME).unwrap_or_default();
+        Self::start(ctx, build_dependencies(&api_key), api_key)
+    }
+}
+
+impl eframe::App for AppShell {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if let Self::Running(app) = self {
+            app.shutdown();
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        match self {
+            Self::Running(app) => {
+                let started = std::time::Instant::now();
+                app.update(ctx, frame);
+                app.frame_times.record(started.elapsed());
+                if let Some(e) = app.startup_error.take() {
+                    app.shutdown();
+                    *self = Self::Failed(ui::ErrorScreen::new(e));
+                }
+            }
+            Self::Failed(screen) => match screen.show(ctx) {
+                Some(ui::ErrorScreenAction::Retry) => *self = Self::retry(ctx),
+                Some(ui::ErrorScreenAction::SaveApiKey(key)) => {
+                    // Stored like the setup wizard does: keychain, plus this
+                    // process's environment
+                    let name = services::secrets::API_KEY_NAME;
+                    match services::DefaultSecretStore::system().set_in_keychain(name, &key) {
+                        Ok(()) => {
+                            std::env::set_var(name, &key);
+                            redact::register_secret(&key);
+                            *self = Self::retry(ctx);
+                        }
+                        Err(e) => screen.set_fix_error(format!(
+                            "Couldn't save the key to the system keychain ({}). Set {} in your \
+                             environment instead.",
+                            e, name
+                        )),
+                    }
+                }
+                Some(ui::ErrorScreenAction::Quit) => {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+                None => {}
+            },
+        }
+    }
+}
+
+/// Loaded settings bundle and what importing it changed
+type SettingsImport = (
+    settings_bundle::SettingsBundle,
+    settings_bundle::ImportSummary,
+);
+
 struct RustbotApp {
     // Injected dependencies (service layer)
     deps: AppDependencies,
+    runtime: Arc<tokio::runtime::Runtime>, // deps.runtime, checked in `new`
+    llm_provider: llm::LlmProvider,        // Provider deps.llm_adapter talks to
 
     // Core API for all functionality - wrapped in Arc<Mutex> for thread safety
     api: Arc<Mutex<RustbotApi>>,
 
     // UI state
     message_input: String,
+    input_token_cache: (String, u32), // Last counted input and its token count
+    pending_images: Vec<ui::ImageAttachment>, // Pasted, sent with the next message
+    command_feedback: Option<(String, bool)>, // Slash command output (message, is_error)
     messages: Vec<ChatMessage>,
+    editing_message: Option<(usize, String)>, // (index in messages, edited text)
+    feedback_comment: Option<(usize, String)>, // (index in messages, comment being written)
+    retry: ui::RetryState,                    // Retries of the failed latest message
+    pending_diagrams: Vec<ui::PendingDiagrams>, // Answers with diagrams still rendering
+    outbox: Vec<ui::QueuedMessage>,           // Waiting to be sent, oldest first
+    regenerate_open: bool,                    // Options shown under the latest answer
+    regenerate_agent: Option<String>,         // Agent picked there (None = tab's agent)
+    session: services::ConversationSession,   // Persisted copy of the current chat
+    api_session: String,                      // RustbotApi session behind the visible tab
+    handoff: Option<handoff::Handoff>,        // Set while a specialist owns the chat
+    tool_overrides: HashMap<String, bool>,    // Tool sources switched on/off in this chat
+
+    // Chat tabs; the visible tab's state is in the fields above (see ChatTab)
+    tabs: Vec<ui::ChatTab>,
+    active_tab: usize,
+    next_tab_id: u64,
+
+    // Conversation export dialog state
+    conversation_export_open: bool,
+    conversation_export_format: conversation_export::ConversationFormat,
+    conversation_export_path: String,
+    conversation_export_message: Option<(String, bool)>, // (message, is_error)
+    // Conversation export in progress: (path, result)
+    conversation_export_rx: Option<tokio::sync::oneshot::Receiver<(PathBuf, Result<()>)>>,
+
+    // Share dialog state (HTML page or gist)
+    share_open: bool,
+    share_redact: bool,
+    share_public: bool,
+    share_path: String,
+    share_has_token: bool,     // A GitHub token is stored
+    share_token_input: String, // Token typed in the dialog, saved to the keychain on upload
+    share_rx:
+        Option<tokio::sync::oneshot::Receiver<std::result::Result<String, share::ShareError>>>,
+    share_save_rx: Option<tokio::sync::oneshot::Receiver<Result<PathBuf>>>, // Page being saved
+    share_url: Option<String>,
+    share_message: Option<(String, bool)>, // (message, is_error)
+
+    // Diagram opened from a message, shown zoomable over the window
+    diagram_viewer: Option<ui::DiagramViewer>,
+
+    // Context inspector state
+    context_inspector_open: bool,
+    context_preview: Option<request_preview::RequestPreview>,
+    context_preview_at: usize, // Chat messages when the preview was built
+    // Preview being built: (chat messages when asked, preview or None while a turn holds the API)
+    context_preview_rx: Option<(
+        usize,
+        tokio::sync::oneshot::Receiver<Option<anyhow::Result<request_preview::RequestPreview>>>,
+    )>,
+    context_preview_message: Option<(String, bool)>, // (message, is_error)
+
+    // History view state
+    history_query: String,
+    history_results: Vec<services::SessionSummary>,
+    // Search in progress for `history_results`; a newer query replaces it
+    history_rx: Option<tokio::sync::oneshot::Receiver<Result<Vec<services::SessionSummary>>>>,
+    // Saved session being loaded by `restore_session`: (id, result)
+    restore_rx: Option<
+        tokio::sync::oneshot::Receiver<(String, Result<Option<services::ConversationSession>>)>,
+    >,
+    history_import_path: String,
+    history_import_message: Option<(String, bool)>, // (message, is_error)
+    // Conversation import in progress; yields the number imported
+    history_import_rx: Option<tokio::sync::oneshot::Receiver<Result<usize>>>,
+    feedback_export_message: Option<(String, bool)>, // (message, is_error)
+    // Ratings export in progress: (rating count, file written)
+    feedback_export_rx: Option<tokio::sync::oneshot::Receiver<anyhow::Result<(usize, PathBuf)>>>,
+
+    // Usage view state
+    usage_period: usage::UsagePeriod,
+    usage_by_model: bool, // break down by model (true) or agent (false)
+    usage_metric: ui::UsageMetric,
+    usage_entries: Vec<usage::UsageEntry>,
+    // Saved conversations being loaded for `usage_entries`
+    usage_rx: Option<tokio::sync::oneshot::Receiver<Result<Vec<services::ConversationSession>>>>,
+    usage_activity: bool, // show collected analytics (true) or token usage (false)
+    usage_analytics: analytics::UsageAnalytics, // Copy shown in the Activity section
+
+    // Settings export/import (Preferences view)
+    settings_bundle_path: String,
+    settings_bundle_message: Option<(String, bool)>, // (message, is_error)
+    // Settings bundle export in progress: (path, result)
+    settings_export_rx: Option<tokio::sync::oneshot::Receiver<(PathBuf, Result<()>)>>,
+    // Settings bundle import in progress: (path, result)
+    settings_import_rx: Option<tokio::sync::oneshot::Receiver<(PathBuf, Result<SettingsImport>)>>,
+
+    // Backups (Settings > Backups)
+    backup_manager: Arc<backup::BackupManager>,
+    backups: Vec<backup::BackupInfo>,
+    backup_message: Option<(String, bool)>, // (message, is_error)
+    // Backup or restore in progress: (ID being restored, None for a new backup; result)
+    backup_rx: Option<tokio::sync::oneshot::Receiver<(Option<String>, Result<backup::BackupInfo>)>>,
+
+    // User scripts (Settings > Scripts)
+    script_host: Arc<scripting::ScriptHost>,
+    scripts: Vec<scripting::ScriptInfo>,
+    script_message: Option<(String, bool)>, // (message, is_error)
+
+    // Sandboxed WebAssembly tools (Settings > WASM Tools); None if the
+    // runtime couldn't start
+    wasm_tools: Option<Arc<wasm_tools::WasmToolHost>>,
+    wasm_tool_list: Vec<wasm_tools::WasmToolInfo>,
+    wasm_tool_message: Option<(String, bool)>, // (message, is_error)
+
+    // Native plugins (Settings > Native Plugins), with settings being edited
+    native_plugins: Arc<native_plugins::NativePluginHost>,
+    native_plugin_list: Vec<native_plugins::NativePluginInfo>,
+    native_plugin_message: Option<(String, bool)>, // (message, is_error)
+
+    // Email connector and the drafts waiting for approval (oldest first)
+    email: Option<Arc<email::EmailService>>,
+    email_approvals: VecDeque<(Event, email::EmailDraft)>,
+
+    // Git tools for the active project's repository and the commits waiting
+    // for approval (oldest first)
+    git_tools: Option<Arc<git_tools::GitTools>>,
+    git_approvals: VecDeque<(Event, git_tools::CommitDraft)>,
+    // /commit reading the staged changes: the prompt, or what went wrong
+    commit_draft_rx: Option<tokio::sync::oneshot::Receiver<std::result::Result<String, String>>>,
+
+    // Filesystem grants and the consent requests waiting for an answer (oldest first)
+    fs_consent: Arc<fs_consent::FsConsent>,
+    fs_consent_requests: VecDeque<(Event, fs_consent::ConsentRequest)>,
+
+    // Audit log as last loaded in Settings > Audit Log, and whether its chain is intact
+    audit_entries: Vec<audit::AuditEntry>,
+    audit_status: Option<std::result::Result<audit::ChainStatus, String>>,
+
+    // Calendar connector (Settings > Calendar)
+    calendar: Option<Arc<calendar::CalendarService>>,
+    calendar_form: ui::CalendarForm,
+    calendar_message: Option<(String, bool)>, // (message, is_error)
+    calendar_auth_rx: Option<tokio::sync::oneshot::Receiver<anyhow::Result<String>>>,
+
+    // Projects (Settings > Projects) and the one the user is working in
+    projects: Vec<projects::Project>,
+    active_project: Option<String>,
+    new_project_name: String,
+    project_message: Option<(String, bool)>, // (message, is_error)
+
+    // Chat with folder: the folder, its index and whether questions are
+    // answered from it
+    folder_panel_open: bool,
+    folder_chat_path: String,
+    folder_chat_enabled: bool,
+    folder_index: Option<Arc<folder_index::FolderIndex>>,
+    folder_index_rx: Option<tokio::sync::oneshot::Receiver<Result<folder_index::FolderIndex>>>,
+    // Saved index being loaded: (folder, index if one was saved)
+    folder_index_load_rx: Option<
+        tokio::sync::oneshot::Receiver<(PathBuf, Result<Option<folder_index::FolderIndex>>)>,
+    >,
+    folder_index_message: Option<(String, bool)>, // (message, is_error)
+
+    // Tool toggles above the input and the sources they list (those of the
+    // visible tab's agent)
+    tool_panel_open: bool,
+    tool_sources: Vec<tool_toggles::ToolSource>,
+    // Tool sources being listed (None while a turn holds the API)
+    tool_sources_rx: Option<
+        tokio::sync::oneshot::Receiver<Option<anyhow::Result<Vec<tool_toggles::ToolSource>>>>,
+    >,
+
+    // Prompt library (Settings > Prompts), its picker and variable form
+    prompt_library: prompt_library::PromptLibrary,
+    prompt_message: Option<(String, bool)>, // (message, is_error)
+    prompt_picker_open: bool,
+    prompt_form: Option<ui::PromptForm>,
+    focus_input_requested: bool, // Cmd+L: focus the message input on the next frame
+    pinned_panel_open: bool,     // Pinned messages panel beside the chat
+    scroll_to_message: Option<usize>, // Message to bring into view (Pinned panel "Jump to")
+
+    // rustbot:// link passed on the command line, applied after startup
+    pending_deep_link: Option<deep_link::DeepLink>,
+    // Prompt a `send=1` link filled in, waiting for the user to confirm sending
+    link_send_confirm: Option<String>,
     response_rx: Option<mpsc::UnboundedReceiver<String>>,
     current_response: String,
     is_waiting: bool,
     spinner_rotation: f32,
-    token_stats: TokenStats,
+    token_stats: services::TokenStats,
+    writer: Option<services::WriteBehind>, // Debounced stats and session saves; flushed on exit
+    // Recovery saves, and history lookups ahead of session saves; awaited on exit
+    pending_saves: tokio::task::JoinSet<()>,
+
+    // Agents, profile and last session are loaded and saved by this actor so
+    // the UI thread never waits on storage
+    app_state: services::AppStateActor,
+    startup_rx: Option<tokio::sync::oneshot::Receiver<services::StartupState>>,
+    startup_error: Option<RustbotError>, // Shown on the error screen (see `AppShell`)
+    agent_reload_rx: Option<tokio::sync::oneshot::Receiver<Vec<AgentConfig>>>,
+
+    // Crash recovery: what an unclean exit left behind, offered once the chat
+    // shows, and the draft and streamed length last saved (see `save_recovery`)
+    recovery_offer: Option<services::RecoveryState>,
+    recovery_saved: Option<(String, Option<usize>)>,
+    recovery_checked: std::time::Instant,
     context_tracker: ContextTracker,
     sidebar_open: bool,
     current_view: AppView,
     settings_view: SettingsView,
     system_prompts: SystemPrompts,
+    // Instruction profile the system prompts belong to, and all saved profiles
+    instruction_profile: String,
+    instruction_profiles: Vec<String>,
+    // New profile fields; the starter is an index in STARTER_PROFILES (None = copy)
+    new_profile_name: String,
+    new_profile_starter: Option<usize>,
+    profile_message: Option<(String, bool)>,
     current_activity: Option<String>, // Track current agent activity
-    dark_mode: bool,                  // Theme toggle state
+    theme: String,                    // "light", "dark", "system" or a user theme name
+    custom_themes: Vec<theme::Palette>, // User palettes from ~/.rustbot/themes
+    applied_palette: Option<theme::Palette>,
+    font_size: f32,                        // Body text size in points
+    ui_scale: f32,                         // Zoom factor for the whole interface
+    applied_text_size: Option<(f32, f32)>, // (font_size, ui_scale) last applied
+    notifications_enabled: bool,           // Notify when a background answer finishes
+    privacy_mode: bool,                    // Local-only mode (see rustbot_core::privacy)
+    allowed_hosts_input: String,           // Local-only allowed endpoints, comma separated
+    offline_model: String,                 // Ollama model used while offline (empty = queue)
+    analytics_enabled: bool,               // Local usage analytics (see rustbot_core::analytics)
+    connectivity_rx: Option<tokio::sync::oneshot::Receiver<bool>>, // Probe in flight
+    connectivity_checked: Option<std::time::Instant>, // When the last probe started
+    rate_limited_until: Option<std::time::Instant>, // Provider asked every tab to wait (429)
+    injection_warnings: bool,              // Flag tool results that address the model
+    quota_form: ui::QuotaForm,             // Settings > Preferences > Daily Limits
+    prompt_test_form: ui::PromptTestForm,  // Settings > A/B Tests
+    // Fields sent as system context (Settings > Preferences > System Context)
+    system_context: system_context::SystemContextSettings,
 
     // Event visualization
-    event_rx: broadcast::Receiver<Event>,
+    event_rx: events::EventSubscriber,
     event_history: VecDeque<VisualEvent>,
     show_event_visualizer: bool,
+    event_sequence_zoom: f32,
+    event_sequence_diagram: Option<(Option<String>, String)>, // (turn, rendered markdown)
+    event_sequence_render: Option<tokio::sync::oneshot::Receiver<Option<String>>>,
+
+    // Persisted event log and export state
+    event_log: event_log::EventLog,
+    event_export_range: ui::EventExportRange,
+    event_export_format: event_log::ExportFormat,
+    event_export_message: Option<(String, bool)>, // (message, is_error)
+
+    // Opt-in usage analytics counted from the event bus (Usage view)
+    analytics: analytics::AnalyticsRecorder,
+
+    // Local control socket (handed the new API on config reload)
+    ipc_server: Option<ipc::ControlSocket>,
 
     // Agent UI state
     agent_configs: Vec<AgentConfig>,
     selected_agent_index: Option<usize>,
-
-    // Pending agent result receiver
-    pending_agent_result:
-        Option<mpsc::UnboundedReceiver<anyhow::Result<mpsc::UnboundedReceiver<String>>>>,
+    agent_wizard: Option<ui::AgentWizard>, // Open New Agent wizard
+    // Presets that failed to load, one of them possibly open for fixing
+    broken_agents: Vec<agent::validation::BrokenAgent>,
+    preset_editor: Option<ui::PresetEditor>,
+    // Result of Validate in the agent editor
+    agent_issues: Option<Vec<agent::validation::PresetIssue>>,
+
+    // Pending agent result receiver and the task producing it (aborted by Stop)
+    pending_agent_result: Option<ui::AgentResultReceiver>,
+    turn_task: Option<tokio::task::AbortHandle>,
+    // When the turn started, and whether it came from outside the window
+    // (scripts, IPC); decides whether finishing it sends a notification
+    turn_started: Option<(std::time::Instant, bool)>,
 
     // MCP Plugin Manager and UI
     mcp_manager: Arc<Mutex<McpPluginManager>>,
+    // Enabled plugins starting in the background: (started, enabled) once done
+    mcp_startup_rx: Option<tokio::sync::oneshot::Receiver<(usize, usize)>>,
+    mcp_startup: Option<(usize, usize)>,
     plugins_view: Option<PluginsView>,
     extensions_marketplace_view: Option<ui::MarketplaceView>,
     extensions_view: ExtensionsView,
@@ -314,10 +673,16 @@ struct RustbotApp {
     uninstall_message: Option<(String, bool)>,        // (message, is_error)
 
     // Markdown rendering
-    markdown_cache: CommonMarkCache,
+    markdown_cache: ui::markdown::MarkdownCache,
+    message_heights: ui::MessageHeights, // Lets the chat skip drawing off-screen messages
 
     // Mermaid diagram rendering
     mermaid_renderer: Arc<Mutex<mermaid::MermaidRenderer>>,
+    math_renderer: Arc<Mutex<math::MathRenderer>>,
+
+    // Diagnostics panel (Events view)
+    frame_times: ui::FrameTimes,
+    api_metrics: Option<api::ApiMetrics>, // Last snapshot, kept while the API is busy
 
     // Splash screen state
     show_splash: bool,
@@ -328,7 +693,10 @@ struct RustbotApp {
     setup_wizard_step: SetupWizardStep,
     setup_name: String,
     setup_email: String,
+    setup_provider: llm::LlmProvider,
     setup_api_key: String,
+    setup_key_check_rx: Option<tokio::sync::oneshot::Receiver<anyhow::Result<()>>>,
+    setup_key_message: Option<(String, bool)>, // (message, is_error)
 }
 
 /// Setup wizard flow steps
@@ -337,86 +705,288 @@ enum SetupWizardStep {
     Welcome,
     EnterName,
     EnterEmail,
+    ChooseProvider,
     EnterApiKey,
     Complete,
 }
 
 impl RustbotApp {
-    fn new(deps: AppDependencies, api_key: String) -> Self {
-        // Get runtime from dependencies (required)
-        let runtime = deps
-            .runtime
-            .as_ref()
-            .expect("Runtime is required for RustbotApp");
+    /// Create the app and hook it up to the window
+    ///
+    /// # Errors
+    /// - See `new`
+    fn start(ctx: &egui::Context, deps: AppDependencies, api_key: String) -> Result<Self> {
+        let mut app = Self::new(deps, api_key)?;
+
+        // Wake the UI for events published while the window is idle
+        // (e.g. IPC requests such as forwarded rustbot:// links)
+        let ctx = ctx.clone();
+        let mut wake_rx = app.deps.event_bus.subscribe_named("ui-waker");
+        app.runtime.spawn(async move {
+            while let Some(event) = wake_rx.recv().await {
+                if event.is_for("user") {
+                    ctx.request_repaint();
+                }
+            }
+        });
 
-        // Load persisted state (UI-specific types, not from service layer)
-        // Note: TokenStats and SystemPrompts are UI-specific types with different
-        // structure from the service layer types, so we handle them directly
-        let token_stats = Self::load_token_stats().unwrap_or_default();
-        let system_prompts = Self::load_system_prompts().unwrap_or_default();
+        app.update_context_tracker();
+        Ok(app)
+    }
 
-        // Subscribe to event bus
-        let event_rx = deps.event_bus.subscribe();
+    /// Set up the app on the given dependencies
+    ///
+    /// # Errors
+    /// - The dependencies have no runtime or LLM adapter
+    /// - The API can't be built
+    fn new(deps: AppDependencies, api_key: String) -> Result<Self> {
+        // Runtime and LLM adapter are required
+        let runtime =
+            Arc::clone(deps.runtime.as_ref().ok_or_else(|| {
+                RustbotError::ConfigError("No async runtime configured".to_string())
+            })?);
+        let llm_adapter =
+            Arc::clone(deps.llm_adapter.as_ref().ok_or_else(|| {
+                RustbotError::ConfigError("No LLM adapter configured".to_string())
+            })?);
+
+        // Build the tokenizer tables while the rest of startup runs
+        std::thread::spawn(tokenizer::warm_up);
+
+        // Agents, the last session, the profile and token stats load in the
+        // background while the splash screen shows (see `apply_startup`)
+        let app_state = services::AppStateActor::spawn(
+            Arc::clone(&deps.storage),
+            Arc::clone(&deps.config),
+            runtime.handle(),
+        );
+        let startup_rx = app_state.startup();
 
-        // Get LLM adapter from dependencies (required)
-        let llm_adapter = deps
-            .llm_adapter
-            .as_ref()
-            .expect("LLM adapter is required for RustbotApp");
+        let writer = services::WriteBehind::spawn(
+            Arc::clone(&deps.storage),
+            services::write_behind::WRITE_BEHIND_DELAY,
+            runtime.handle(),
+        );
 
-        // Load agents from config service
-        let mut agent_configs = runtime.block_on(async {
-            deps.config.load_agent_configs().await.unwrap_or_else(|e| {
-                tracing::warn!("Failed to load agents from config service: {}", e);
-                vec![]
-            })
-        });
+        // Note: SystemPrompts is a UI-specific type with a different structure
+        // from the service layer type, so we handle it directly
+        let system_prompts = Self::load_system_prompts().unwrap_or_default();
+        let instruction_profiles = instruction_profiles::InstructionProfiles::standard();
 
-        // If no agents loaded, fall back to default assistant
-        if agent_configs.is_empty() {
-            tracing::info!("No agents loaded, using default assistant");
-            agent_configs.push(AgentConfig::default_assistant());
-        }
+        // Subscribe to event bus
+        let event_rx = deps.event_bus.subscribe_named("ui");
+
+        // Start with the default assistant; the configured agents replace it
+        // once the app state actor has loaded them
+        let agent_configs = vec![AgentConfig::default_assistant()];
 
-        // Build the API using RustbotApiBuilder with all loaded agents
+        // Build the API using RustbotApiBuilder
         let mut api_builder = api::RustbotApiBuilder::new()
             .event_bus(Arc::clone(&deps.event_bus))
-            .runtime(Arc::clone(runtime))
-            .llm_adapter(Arc::clone(llm_adapter))
+            .runtime(Arc::clone(&runtime))
+            .llm_adapter(llm_adapter)
             .max_history_size(20)
             .system_instructions(system_prompts.system_instructions.clone());
 
-        // Add all loaded agents
         for agent_config in &agent_configs {
             api_builder = api_builder.add_agent(agent_config.clone());
         }
 
-        let api = api_builder.build().expect("Failed to build RustbotApi");
+        let mut api = api_builder.build()?;
+
+        let session = services::ConversationSession::new(api.active_agent());
 
         // Initialize MCP plugin manager with event bus
-        let mcp_manager = Arc::new(Mutex::new(McpPluginManager::with_event_bus(Some(
-            Arc::clone(&deps.event_bus),
-        ))));
+        let mcp_manager = McpPluginManager::with_event_bus(Some(Arc::clone(&deps.event_bus)));
 
-        // Load MCP configuration if available
+        // Answer plugin queries arriving over the event bus
+        mcp_manager.serve_requests(runtime.handle());
+        let mcp_manager = Arc::new(Mutex::new(mcp_manager));
+
+        // Load MCP configuration if available and start the enabled plugins,
+        // alongside the app state loading above
         let mcp_config_path = std::path::Path::new("mcp_config.json");
-        if mcp_config_path.exists() {
+        let mcp_startup_rx = if mcp_config_path.exists() {
             let mgr = Arc::clone(&mcp_manager);
-            runtime.block_on(async move {
-                if let Ok(mut manager) = mgr.try_lock() {
-                    match manager.load_config(mcp_config_path).await {
-                        Ok(_) => {
-                            tracing::info!("✓ Loaded MCP configuration from mcp_config.json");
-                        }
-                        Err(e) => {
-                            tracing::warn!("Failed to load MCP configuration: {}", e);
-                        }
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            runtime.spawn(async move {
+                let mut mgr = mgr.lock().await;
+                match mgr.load_config(mcp_config_path).await {
+                    Ok(_) => {
+                        tracing::info!("✓ Loaded MCP configuration from mcp_config.json");
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to load MCP configuration: {}", e);
                     }
                 }
+                let results = mgr.start_enabled().await;
+                let started = results.iter().filter(|(_, result)| result.is_ok()).count();
+                for (id, result) in &results {
+                    if let Err(e) = result {
+                        tracing::warn!("Failed to start MCP plugin '{}': {}", id, e);
+                    }
+                }
+                let _ = tx.send((started, results.len()));
             });
+            Some(rx)
         } else {
             tracing::info!("No mcp_config.json found, MCP plugins disabled");
+            None
+        };
+
+        // Start webhook sink if ~/.rustbot/webhooks.json is present
+        match webhooks::WebhookConfig::load(&webhooks::WebhookConfig::default_path()) {
+            Ok(Some(config)) => {
+                webhooks::WebhookSink::new(config).spawn(&deps.event_bus, runtime.handle());
+            }
+            Ok(None) => {
+                tracing::debug!("No webhooks.json found, webhook sink disabled");
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load webhook configuration: {}", e);
+            }
+        }
+
+        // Persist all events for later export from the Events view (the log
+        // is plain JSONL, so with encryption at rest message text stays out)
+        let event_log = event_log::EventLog::new(event_log::EventLog::default_path())
+            .with_message_bodies(services::encryption::passphrase_from_env().is_none());
+        event_log
+            .clone()
+            .spawn_recorder(&deps.event_bus, runtime.handle());
+
+        // Count usage analytics while the user has them turned on
+        let analytics = analytics::AnalyticsRecorder::spawn(
+            Arc::clone(&deps.storage),
+            &deps.event_bus,
+            runtime.handle(),
+        );
+
+        // Start hook runner if ~/.rustbot/hooks.json is present
+        match hooks::HookConfig::load(&hooks::HookConfig::default_path()) {
+            Ok(Some(config)) => {
+                hooks::HookRunner::new(config).spawn(&deps.event_bus, runtime.handle());
+            }
+            Ok(None) => {
+                tracing::debug!("No hooks.json found, event hooks disabled");
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load hook configuration: {}", e);
+            }
+        }
+
+        // Load user scripts from ~/.rustbot/scripts and run their event hooks
+        let script_host = Arc::new(scripting::ScriptHost::new(
+            scripting::ScriptHost::default_dir(),
+            Arc::clone(&deps.event_bus),
+        ));
+        if let Err(e) = script_host.reload() {
+            tracing::warn!("Failed to load user scripts: {}", e);
+        }
+        Arc::clone(&script_host).spawn(&deps.event_bus, runtime.handle());
+        api.set_script_host(Arc::clone(&script_host));
+        let scripts = script_host.list();
+
+        // Load WebAssembly tools from ~/.rustbot/tools
+        let wasm_dir = wasm_tools::WasmToolHost::default_dir();
+        let wasm_tools = match wasm_tools::WasmToolHost::new(wasm_dir) {
+            Ok(host) => {
+                if let Err(e) = host.reload() {
+                    tracing::warn!("Failed to load WASM tools: {}", e);
+                }
+                let host = Arc::new(host);
+                api.set_wasm_tools(Arc::clone(&host));
+                Some(host)
+            }
+            Err(e) => {
+                tracing::warn!("WASM tools unavailable: {}", e);
+                None
+            }
+        };
+        let wasm_tool_list = wasm_tools.as_ref().map(|h| h.list()).unwrap_or_default();
+
+        // Load the native plugins the user enabled and deliver events to them
+        let native_plugins = Arc::new(native_plugins::NativePluginHost::new(
+            native_plugins::NativePluginHost::default_dir(),
+        ));
+        if let Err(e) = native_plugins.reload() {
+            tracing::warn!("Failed to load native plugins: {}", e);
+        }
+        Arc::clone(&native_plugins).spawn(&deps.event_bus, runtime.handle());
+        api.set_native_plugins(Arc::clone(&native_plugins));
+        let native_plugin_list = native_plugins.list();
+
+        // Email tools if ~/.rustbot/email.json is present (sends need approval)
+        let email = Self::load_email_service(&deps.event_bus);
+        if let Some(email) = &email {
+            api.set_email_service(Arc::clone(email));
+        }
+
+        // Plugins ask before touching a directory (grants in ~/.rustbot/fs_grants.json)
+        let fs_consent = Arc::new(fs_consent::FsConsent::load(
+            fs_consent::FsConsent::default_path(),
+            Arc::clone(&deps.event_bus),
+        ));
+        api.set_fs_consent(Arc::clone(&fs_consent));
+
+        // Calendar tools if ~/.rustbot/calendar.json is present
+        let calendar_config =
+            match calendar::CalendarConfig::load(&calendar::CalendarConfig::default_path()) {
+                Ok(config) => config,
+                Err(e) => {
+                    tracing::warn!("Failed to load calendar configuration: {}", e);
+                    None
+                }
+            };
+        let calendar =
+            calendar_config
+                .as_ref()
+                .and_then(|config| match Self::connect_calendar(config) {
+                    Ok(calendar) => Some(calendar),
+                    Err(e) => {
+                        tracing::warn!("Calendar tools disabled: {:#}", e);
+                        None
+                    }
+                });
+        api.set_calendar(calendar.clone());
+
+        // Saved prompt templates (~/.rustbot/prompts.json)
+        let prompt_library = match prompt_library::PromptLibrary::load(
+            &prompt_library::PromptLibrary::default_path(),
+        ) {
+            Ok(library) => library.unwrap_or_default(),
+            Err(e) => {
+                tracing::warn!("Failed to load prompt library: {}", e);
+                prompt_library::PromptLibrary::default()
+            }
+        };
+
+        // Projects (~/.rustbot/projects)
+        let project_list = projects::ProjectStore::new(projects::ProjectStore::default_dir())
+            .list()
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to load projects: {}", e);
+                Vec::new()
+            });
+
+        // Scheduled snapshots of config and conversations (~/.rustbot/backup.json)
+        let backup_config = match backup::BackupConfig::load(&backup::BackupConfig::default_path())
+        {
+            Ok(config) => config.unwrap_or_default(),
+            Err(e) => {
+                tracing::warn!("Failed to load backup configuration, using defaults: {}", e);
+                backup::BackupConfig::default()
+            }
+        };
+        let backup_manager = Arc::new(backup::BackupManager::new(
+            backup::BackupManager::default_root(),
+            backup_config.keep,
+            backup::default_sources(std::path::Path::new(".")),
+        ));
+        if backup_config.enabled {
+            Arc::clone(&backup_manager).spawn_scheduler(backup_config.interval(), runtime.handle());
         }
+        let backups = backup_manager.list().unwrap_or_default();
 
         // Create plugins view with runtime handle
         let plugins_view = Some(PluginsView::new(
@@ -430,41 +1000,203 @@ impl RustbotApp {
         // Create mermaid renderer
         let mermaid_renderer = Arc::new(Mutex::new(mermaid::MermaidRenderer::new()));
 
-        // Check if this is first run (no profile exists and/or no API key in env)
-        // Also load theme preference
-        let (profile_exists, dark_mode) = runtime.block_on(async {
-            let profile = deps.storage.load_user_profile().await.unwrap_or_default();
-            let exists = !profile.name.is_empty() || !profile.email.is_empty();
-            let dark = profile.theme == "dark";
-            (exists, dark)
-        });
+        // Preferences until the saved profile arrives with the startup state
+        let profile = services::traits::UserProfile::default();
 
-        let setup_wizard_active = !profile_exists || api_key.is_empty();
+        // Accept commands from external scripts on ~/.rustbot/rustbot.sock
+        let api = Arc::new(Mutex::new(api));
+        let ipc_server = Self::start_ipc_server(&api, &deps.event_bus, runtime.handle());
 
-        Self {
+        Ok(Self {
             deps,
-            api: Arc::new(Mutex::new(api)),
+            runtime,
+            llm_provider: llm::LlmProvider::OpenRouter, // See `build_dependencies`
+            api,
             message_input: String::new(),
+            pending_images: Vec::new(),
+            command_feedback: None,
             messages: Vec::new(),
+            input_token_cache: (String::new(), 0),
+            editing_message: None,
+            feedback_comment: None,
+            retry: ui::RetryState::default(),
+            pending_diagrams: Vec::new(),
+            outbox: Vec::new(),
+            regenerate_open: false,
+            regenerate_agent: None,
+            session,
+            api_session: api::DEFAULT_SESSION.to_string(),
+            handoff: None,
+            tool_overrides: HashMap::new(),
+            tabs: vec![ui::ChatTab::new(String::new(), "")],
+            active_tab: 0,
+            next_tab_id: 1,
+            conversation_export_open: false,
+            conversation_export_format: conversation_export::ConversationFormat::Markdown,
+            conversation_export_path: String::new(),
+            conversation_export_message: None,
+            conversation_export_rx: None,
+            share_open: false,
+            share_redact: true,
+            share_public: false,
+            share_path: String::new(),
+            share_has_token: false,
+            share_token_input: String::new(),
+            share_rx: None,
+            share_save_rx: None,
+            share_url: None,
+            share_message: None,
+            diagram_viewer: None,
+            context_inspector_open: false,
+            context_preview: None,
+            context_preview_at: 0,
+            context_preview_rx: None,
+            context_preview_message: None,
+            history_query: String::new(),
+            history_results: Vec::new(),
+            history_rx: None,
+            restore_rx: None,
+            history_import_path: String::new(),
+            history_import_message: None,
+            history_import_rx: None,
+            feedback_export_message: None,
+            feedback_export_rx: None,
+            usage_period: usage::UsagePeriod::Day,
+            usage_by_model: true,
+            usage_metric: ui::UsageMetric::InputTokens,
+            usage_entries: Vec::new(),
+            usage_rx: None,
+            usage_activity: false,
+            usage_analytics: analytics::UsageAnalytics::default(),
+            settings_bundle_path: dirs::home_dir()
+                .unwrap_or_default()
+                .join(".rustbot")
+                .join("exports")
+                .join("rustbot-settings.json")
+                .display()
+                .to_string(),
+            settings_bundle_message: None,
+            settings_export_rx: None,
+            settings_import_rx: None,
+            backup_manager,
+            backups,
+            backup_message: None,
+            backup_rx: None,
+            script_host,
+            scripts,
+            script_message: None,
+            wasm_tools,
+            wasm_tool_list,
+            wasm_tool_message: None,
+            native_plugins,
+            native_plugin_list,
+            native_plugin_message: None,
+            email,
+            email_approvals: VecDeque::new(),
+            git_tools: None,
+            git_approvals: VecDeque::new(),
+            commit_draft_rx: None,
+            fs_consent,
+            fs_consent_requests: VecDeque::new(),
+            audit_entries: Vec::new(),
+            audit_status: None,
+            calendar,
+            calendar_form: ui::CalendarForm::from_config(calendar_config.as_ref()),
+            calendar_message: None,
+            calendar_auth_rx: None,
+            active_project: profile
+                .active_project
+                .clone()
+                .filter(|id| project_list.iter().any(|p| p.id == *id)),
+            projects: project_list,
+            new_project_name: String::new(),
+            project_message: None,
+            folder_panel_open: false,
+            folder_chat_path: String::new(),
+            folder_chat_enabled: false,
+            folder_index: None,
+            folder_index_rx: None,
+            folder_index_load_rx: None,
+            folder_index_message: None,
+            prompt_library,
+            prompt_message: None,
+            prompt_picker_open: false,
+            prompt_form: None,
+            tool_panel_open: false,
+            tool_sources: Vec::new(),
+            tool_sources_rx: None,
+            focus_input_requested: false,
+            pinned_panel_open: false,
+            scroll_to_message: None,
+            pending_deep_link: None,
+            link_send_confirm: None,
             response_rx: None,
             current_response: String::new(),
             is_waiting: false,
             spinner_rotation: 0.0,
-            token_stats: Self::check_and_reset_daily_stats(token_stats),
+            token_stats: services::TokenStats::default(),
+            writer: Some(writer),
+            pending_saves: tokio::task::JoinSet::new(),
+            app_state,
+            startup_rx: Some(startup_rx),
+            startup_error: None,
+            agent_reload_rx: None,
+            recovery_offer: None,
+            recovery_saved: None,
+            recovery_checked: std::time::Instant::now(),
             context_tracker: ContextTracker::default(),
             sidebar_open: true, // Start with sidebar open
             current_view: AppView::Chat,
             settings_view: SettingsView::Agents, // Start with Agents view to show loaded agents
             system_prompts,
+            instruction_profile: instruction_profiles.active(),
+            instruction_profiles: instruction_profiles.list(),
+            new_profile_name: String::new(),
+            new_profile_starter: None,
+            profile_message: None,
             current_activity: None,
-            dark_mode,
+            theme: profile.theme,
+            font_size: profile.font_size,
+            ui_scale: profile.ui_scale,
+            applied_text_size: None,
+            notifications_enabled: profile.notifications,
+            privacy_mode: profile.privacy_mode,
+            allowed_hosts_input: profile.allowed_hosts.join(", "),
+            offline_model: profile.offline_model.clone().unwrap_or_default(),
+            analytics_enabled: profile.analytics,
+            connectivity_rx: None,
+            connectivity_checked: None,
+            rate_limited_until: None,
+            injection_warnings: profile.injection_warnings,
+            quota_form: ui::QuotaForm::from_quota(&profile.quota),
+            prompt_test_form: ui::PromptTestForm::default(),
+            system_context: profile.system_context.clone(),
+            custom_themes: theme::load_palettes(&theme::themes_dir()),
+            applied_palette: None,
             event_rx,
             agent_configs: agent_configs.clone(),
             selected_agent_index: None,
-            event_history: VecDeque::with_capacity(50),
+            agent_wizard: None,
+            broken_agents: agent::AgentLoader::new().find_broken(),
+            preset_editor: None,
+            agent_issues: None,
+            event_history: VecDeque::with_capacity(200),
             show_event_visualizer: true, // Start with visualizer open for debugging
+            event_sequence_zoom: 1.0,
+            event_sequence_diagram: None,
+            event_sequence_render: None,
+            event_log,
+            event_export_range: ui::EventExportRange::default(),
+            event_export_format: event_log::ExportFormat::Jsonl,
+            event_export_message: None,
+            analytics,
+            ipc_server,
             pending_agent_result: None,
+            turn_task: None,
+            turn_started: None,
             mcp_manager,
+            mcp_startup_rx,
+            mcp_startup: None,
             plugins_view,
             extensions_marketplace_view,
             extensions_view: ExtensionsView::default(),
@@ -473,291 +1205,403 @@ impl RustbotApp {
             installed_extensions_filter: ui::InstallTypeFilter::default(),
             uninstall_confirmation: None,
             uninstall_message: None,
-            markdown_cache: CommonMarkCache::default(),
+            markdown_cache: ui::markdown::MarkdownCache::default(),
+            message_heights: ui::MessageHeights::default(),
             mermaid_renderer,
+            math_renderer: Arc::new(Mutex::new(math::MathRenderer::new())),
+            frame_times: ui::FrameTimes::default(),
+            api_metrics: None,
             show_splash: true,
             splash_start_time: Some(std::time::Instant::now()),
-            setup_wizard_active,
+            setup_wizard_active: false,
             setup_wizard_step: SetupWizardStep::Welcome,
             setup_name: String::new(),
             setup_email: String::new(),
+            setup_provider: profile.provider,
             setup_api_key: api_key.clone(),
-        }
+            setup_key_check_rx: None,
+            setup_key_message: None,
+        })
     }
 
-    /// Apply light theme with professional color scheme
+    /// Apply the preferred theme if it changed since the last frame
     ///
-    /// Design: Clean, bright interface optimized for extended use
-    /// - Soft backgrounds to reduce eye strain
-    /// - High contrast for readability
-    /// - Consistent spacing and Roboto fonts
-    fn apply_light_theme(&self, ctx: &egui::Context) {
-        let mut style = (*ctx.style()).clone();
-
-        // Font sizes (same for both themes)
-        style.text_styles = [
-            (
-                egui::TextStyle::Heading,
-                egui::FontId::new(24.0, egui::FontFamily::Proportional),
-            ),
-            (
-                egui::TextStyle::Body,
-                egui::FontId::new(16.0, egui::FontFamily::Proportional),
-            ),
-            (
-                egui::TextStyle::Button,
-                egui::FontId::new(16.0, egui::FontFamily::Proportional),
-            ),
-            (
-                egui::TextStyle::Small,
-                egui::FontId::new(14.0, egui::FontFamily::Proportional),
-            ),
-            (
-                egui::TextStyle::Monospace,
-                egui::FontId::new(14.0, egui::FontFamily::Proportional),
-            ),
-        ]
-        .into();
-
-        // Light color scheme
-        let mut visuals = egui::Visuals::light();
-        visuals.widgets.noninteractive.bg_fill = egui::Color32::from_rgb(245, 245, 247);
-        visuals.widgets.inactive.bg_fill = egui::Color32::from_rgb(240, 240, 242);
-        visuals.widgets.hovered.bg_fill = egui::Color32::from_rgb(230, 230, 235);
-        visuals.widgets.active.bg_fill = egui::Color32::from_rgb(60, 120, 220);
-        visuals.selection.bg_fill = egui::Color32::from_rgba_premultiplied(60, 120, 220, 80);
-        visuals.extreme_bg_color = egui::Color32::from_rgb(250, 250, 252);
-        visuals.panel_fill = egui::Color32::from_rgb(248, 248, 250);
-        visuals.window_fill = egui::Color32::from_rgb(255, 255, 255);
-
-        style.visuals = visuals;
-        ctx.set_style(style);
-    }
-
-    /// Apply dark theme with professional color scheme
-    ///
-    /// Design: Comfortable dark interface for low-light environments
-    /// - Deep grays instead of pure black to reduce eye strain
-    /// - Carefully chosen accent colors for visibility
-    /// - Same spacing and fonts as light theme for consistency
-    fn apply_dark_theme(&self, ctx: &egui::Context) {
-        let mut style = (*ctx.style()).clone();
-
-        // Font sizes (same for both themes)
-        style.text_styles = [
-            (
-                egui::TextStyle::Heading,
-                egui::FontId::new(24.0, egui::FontFamily::Proportional),
-            ),
-            (
-                egui::TextStyle::Body,
-                egui::FontId::new(16.0, egui::FontFamily::Proportional),
-            ),
-            (
-                egui::TextStyle::Button,
-                egui::FontId::new(16.0, egui::FontFamily::Proportional),
-            ),
-            (
-                egui::TextStyle::Small,
-                egui::FontId::new(14.0, egui::FontFamily::Proportional),
-            ),
-            (
-                egui::TextStyle::Monospace,
-                egui::FontId::new(14.0, egui::FontFamily::Proportional),
-            ),
-        ]
-        .into();
+    /// "system" follows the OS setting reported by the window, so switching
+    /// the OS between light and dark takes effect immediately.
+    fn apply_theme(&mut self, ctx: &egui::Context) {
+        let system_dark = ctx.system_theme() == Some(egui::Theme::Dark);
+        let palette = theme::resolve(&self.theme, system_dark, &self.custom_themes);
+        if self.applied_palette.as_ref() != Some(&palette) {
+            ui::theme::apply(ctx, &palette);
+            self.applied_palette = Some(palette);
+        }
 
-        // Dark color scheme
-        let mut visuals = egui::Visuals::dark();
+        // Font size and scale apply live while the Preferences controls move
+        let text_size = (self.font_size, self.ui_scale);
+        if self.applied_text_size != Some(text_size) {
+            ui::theme::apply_text_size(ctx, self.font_size, self.ui_scale);
+            self.applied_text_size = Some(text_size);
 
-        // Text colors
-        visuals.override_text_color = Some(egui::Color32::from_rgb(230, 230, 230));
+            // Measured heights depend on the text size, even at the same width
+            self.message_heights.clear();
+            for tab in &mut self.tabs {
+                tab.message_heights.clear();
+            }
+        }
+    }
 
-        // Widget backgrounds
-        visuals.widgets.noninteractive.bg_fill = egui::Color32::from_rgb(40, 40, 45);
-        visuals.widgets.inactive.bg_fill = egui::Color32::from_rgb(45, 45, 50);
-        visuals.widgets.hovered.bg_fill = egui::Color32::from_rgb(55, 55, 65);
-        visuals.widgets.active.bg_fill = egui::Color32::from_rgb(100, 160, 255);
+    /// Save the font size and interface scale to the user profile
+    fn save_text_size(&self) {
+        let (font_size, ui_scale) = (self.font_size, self.ui_scale);
+        self.app_state.update_profile(move |profile| {
+            profile.font_size = font_size;
+            profile.ui_scale = ui_scale;
+        });
+    }
 
-        // Selection color
-        visuals.selection.bg_fill = egui::Color32::from_rgba_premultiplied(100, 160, 255, 80);
+    /// Allowed endpoints as entered in Settings, one per comma
+    fn allowed_hosts(&self) -> Vec<String> {
+        self.allowed_hosts_input
+            .split(',')
+            .map(str::trim)
+            .filter(|host| !host.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
 
-        // Panel and window backgrounds
-        visuals.panel_fill = egui::Color32::from_rgb(30, 30, 35);
-        visuals.window_fill = egui::Color32::from_rgb(25, 25, 28);
-        visuals.extreme_bg_color = egui::Color32::from_rgb(20, 20, 23);
+    /// Apply local-only mode now and save it to the user profile
+    fn save_privacy_mode(&self) {
+        let (enabled, hosts) = (self.privacy_mode, self.allowed_hosts());
+        privacy::configure(enabled, &hosts);
+        audit::record(
+            audit::AuditAction::ConfigChanged,
+            if enabled {
+                format!("Local-only mode on (allowed: {})", hosts.join(", "))
+            } else {
+                "Local-only mode off".to_string()
+            },
+        );
+        self.app_state.update_profile(move |profile| {
+            profile.privacy_mode = enabled;
+            profile.allowed_hosts = hosts;
+        });
+    }
 
-        // Border and separator colors
-        visuals.widgets.noninteractive.bg_stroke.color = egui::Color32::from_rgb(60, 60, 65);
-        visuals.window_stroke.color = egui::Color32::from_rgb(50, 50, 55);
+    /// Ollama model for offline use as entered in Settings (None if empty)
+    fn offline_model(&self) -> Option<String> {
+        Some(self.offline_model.trim().to_string()).filter(|model| !model.is_empty())
+    }
 
-        style.visuals = visuals;
-        ctx.set_style(style);
+    /// Apply the offline model now and save it to the user profile
+    fn save_offline_model(&self) {
+        let model = self.offline_model();
+        connectivity::set_local_model(model.clone());
+        self.app_state
+            .update_profile(move |profile| profile.offline_model = model);
     }
 
-    fn get_instructions_dir() -> Result<PathBuf> {
-        let home_dir = std::env::var("HOME")
-            .or_else(|_| std::env::var("USERPROFILE"))
-            .map_err(|_| {
-                RustbotError::EnvError(
-                    "Could not determine home directory: HOME or USERPROFILE not set".to_string(),
-                )
-            })?;
+    /// Probe connectivity every `connectivity::PROBE_INTERVAL`
+    ///
+    /// The probe runs in the background and records the result in
+    /// `rustbot_core::connectivity`; a finished probe repaints so the offline
+    /// badge and queued messages follow.
+    fn poll_connectivity(&mut self, ctx: &egui::Context) {
+        if let Some(rx) = &mut self.connectivity_rx {
+            match rx.try_recv() {
+                Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+                _ => {
+                    self.connectivity_rx = None;
+                    ctx.request_repaint();
+                }
+            }
+        }
+
+        let elapsed = self.connectivity_checked.map(|at| at.elapsed());
+        if let Some(wait) = elapsed.and_then(|e| connectivity::PROBE_INTERVAL.checked_sub(e)) {
+            ctx.request_repaint_after(wait);
+            return;
+        }
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn(async move {
+            let _ = tx.send(connectivity::refresh().await);
+        });
+        self.connectivity_rx = Some(rx);
+        self.connectivity_checked = Some(std::time::Instant::now());
+    }
 
-        let mut dir = PathBuf::from(home_dir);
-        dir.push(".rustbot");
-        dir.push("instructions");
+    /// Whether the provider asked to wait before the next request
+    fn rate_limited(&self) -> bool {
+        self.rate_limited_until
+            .is_some_and(|until| until > std::time::Instant::now())
+    }
 
-        // Create directory if it doesn't exist
-        if !dir.exists() {
-            std::fs::create_dir_all(&dir).map_err(|e| {
-                RustbotError::StorageError(format!(
-                    "Failed to create instructions directory: {}",
-                    e
-                ))
-            })?;
+    /// Wait out a 429 from the provider, then retry the failed message
+    ///
+    /// The wait applies to every tab: retries and queued messages hold until
+    /// it's over. After `MAX_RATE_LIMIT_RETRIES` in a row the message stays
+    /// failed for the user to retry.
+    ///
+    /// # Returns
+    /// The wait, or None if the message won't be retried automatically
+    fn wait_out_rate_limit(&mut self, limited: &llm::RateLimited) -> Option<std::time::Duration> {
+        let wait = limited
+            .retry_after
+            .unwrap_or_else(|| self.retry.delay())
+            .max(ui::types::MIN_RATE_LIMIT_WAIT);
+        let until = std::time::Instant::now() + wait;
+        self.rate_limited_until = Some(self.rate_limited_until.map_or(until, |u| u.max(until)));
+        if self.retry.attempts >= ui::types::MAX_RATE_LIMIT_RETRIES {
+            return None;
         }
+        self.retry.attempts += 1;
+        self.retry.scheduled = Some(until);
+        tracing::info!(
+            "⏳ Rate limited: retry {} in {:?}",
+            self.retry.attempts,
+            wait
+        );
+        Some(wait)
+    }
 
-        Ok(dir)
+    /// Send the oldest queued message once nothing is in the way: no answer
+    /// streaming or retry pending in this tab, no rate limit wait, and
+    /// online (or offline with a local model)
+    fn poll_outbox(&mut self, ctx: &egui::Context) {
+        if self.outbox.is_empty() || self.is_waiting || self.retry.scheduled.is_some() {
+            return;
+        }
+        if let Some(until) = self.rate_limited_until {
+            let now = std::time::Instant::now();
+            if until > now {
+                ctx.request_repaint_after(until - now);
+                return;
+            }
+        }
+        if !connectivity::can_chat() {
+            return;
+        }
+        let queued = self.outbox.remove(0);
+        tracing::info!("📤 Sending a queued message ({} left)", self.outbox.len());
+        self.retry = ui::RetryState::default();
+        self.submit_message(queued.text, queued.images, None);
     }
 
+    /// Instructions of the active instruction profile
     fn load_system_prompts() -> Result<SystemPrompts> {
-        let mut dir = Self::get_instructions_dir()?;
-
-        // Load system instructions
-        dir.push("system");
-        dir.push("current");
-        let system_instructions = if dir.exists() {
-            std::fs::read_to_string(&dir).map_err(|e| {
-                RustbotError::StorageError(format!(
-                    "Failed to read system instructions from {:?}: {}",
-                    dir, e
-                ))
-            })?
-        } else {
-            String::new()
-        };
+        let profiles = instruction_profiles::InstructionProfiles::standard();
+        let system_instructions = profiles.load(&profiles.active())?.unwrap_or_default();
 
         Ok(SystemPrompts {
             system_instructions,
         })
     }
 
+    /// Save the instructions to the current profile (keeping a backup of the
+    /// previous version) and use them from the next message on
     fn save_system_prompts(&self) -> Result<()> {
-        let base_dir = Self::get_instructions_dir()?;
-
-        // Save system instructions
-        let mut system_dir = base_dir.clone();
-        system_dir.push("system");
-        std::fs::create_dir_all(&system_dir).map_err(|e| {
-            RustbotError::StorageError(format!("Failed to create system directory: {}", e))
-        })?;
-
-        let system_current = system_dir.join("current");
-
-        // Create backup if current exists
-        if system_current.exists() {
-            let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
-            let backup_path = system_dir.join(format!("backup_{}", timestamp));
-            std::fs::copy(&system_current, &backup_path).map_err(|e| {
-                RustbotError::StorageError(format!(
-                    "Failed to create backup of system instructions: {}",
-                    e
-                ))
-            })?;
-        }
-
-        // Write new current
-        std::fs::write(&system_current, &self.system_prompts.system_instructions).map_err(|e| {
-            RustbotError::StorageError(format!("Failed to write system instructions: {}", e))
-        })?;
+        instruction_profiles::InstructionProfiles::standard().save(
+            &self.instruction_profile,
+            &self.system_prompts.system_instructions,
+        )?;
+        audit::record(
+            audit::AuditAction::ConfigChanged,
+            format!("System instructions ({})", self.instruction_profile),
+        );
+        self.apply_system_instructions();
 
         Ok(())
     }
 
-    fn get_stats_file_path() -> PathBuf {
-        let mut path = PathBuf::from(".");
-        path.push("rustbot_stats.json");
-        path
+    /// Hand the system instructions to every agent in the API
+    fn apply_system_instructions(&self) {
+        let api = Arc::clone(&self.api);
+        let instructions = self.system_prompts.system_instructions.clone();
+        self.runtime.spawn(async move {
+            api.lock().await.set_system_instructions(&instructions);
+        });
     }
 
-    fn load_token_stats() -> Result<TokenStats> {
-        let path = Self::get_stats_file_path();
-        if !path.exists() {
-            return Ok(TokenStats::default());
-        }
-
-        let content = std::fs::read_to_string(&path).map_err(|e| {
-            RustbotError::StorageError(format!("Failed to read token stats from {:?}: {}", path, e))
-        })?;
-
-        let stats: TokenStats = serde_json::from_str(&content).map_err(|e| {
-            RustbotError::StorageError(format!("Failed to parse token stats JSON: {}", e))
-        })?;
-
-        Ok(stats)
+    /// Re-read the saved instruction profiles and which one is active
+    fn refresh_instruction_profiles(&mut self) {
+        let profiles = instruction_profiles::InstructionProfiles::standard();
+        self.instruction_profiles = profiles.list();
+        self.instruction_profile = profiles.active();
     }
 
-    fn save_token_stats(&self) -> Result<()> {
-        let path = Self::get_stats_file_path();
-        let content = serde_json::to_string_pretty(&self.token_stats).map_err(|e| {
-            RustbotError::StorageError(format!("Failed to serialize token stats: {}", e))
-        })?;
-
-        std::fs::write(&path, content).map_err(|e| {
-            RustbotError::StorageError(format!("Failed to write token stats to {:?}: {}", path, e))
-        })?;
-
-        Ok(())
+    /// Make another instruction profile active and send its instructions
+    /// from the next message on
+    ///
+    /// Unsaved edits to the current profile's instructions are dropped.
+    fn switch_instruction_profile(&mut self, name: &str) {
+        let profiles = instruction_profiles::InstructionProfiles::standard();
+        let loaded = profiles.set_active(name).and_then(|()| profiles.load(name));
+        match loaded {
+            Ok(instructions) => {
+                self.system_prompts.system_instructions = instructions.unwrap_or_default();
+                self.instruction_profile = name.to_string();
+                self.apply_system_instructions();
+                tracing::info!("📝 Switched to the {} instruction profile", name);
+                self.profile_message = None;
+            }
+            Err(e) => {
+                self.command_feedback =
+                    Some((format!("Couldn't switch instructions: {}", e), true));
+            }
+        }
     }
 
-    fn check_and_reset_daily_stats(mut stats: TokenStats) -> TokenStats {
-        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
-
-        if stats.last_reset_date != today {
-            stats.daily_input = 0;
-            stats.daily_output = 0;
-            stats.last_reset_date = today;
+    /// Create a profile from the New profile fields and switch to it
+    ///
+    /// It starts from the chosen starter, or a copy of the current
+    /// instructions.
+    fn create_instruction_profile(&mut self) {
+        let name = self.new_profile_name.trim().to_string();
+        let instructions = match self.new_profile_starter {
+            Some(i) => instruction_profiles::STARTER_PROFILES[i].1.to_string(),
+            None => self.system_prompts.system_instructions.clone(),
+        };
+        let profiles = instruction_profiles::InstructionProfiles::standard();
+        if let Err(e) = profiles.create(&name, &instructions) {
+            self.profile_message = Some((e.to_string(), true));
+            return;
         }
-
-        stats
+        audit::record(
+            audit::AuditAction::ConfigChanged,
+            format!("Instruction profile '{}' created", name),
+        );
+        self.instruction_profiles = profiles.list();
+        self.new_profile_name.clear();
+        self.new_profile_starter = None;
+        self.switch_instruction_profile(&name);
+        self.profile_message = Some((format!("Created the {} profile", name), false));
     }
 
-    fn estimate_tokens(&self, text: &str) -> u32 {
-        // Rough estimation: ~4 characters per token
-        ((text.len() as f32) / 4.0).ceil() as u32
+    /// Delete the current instruction profile and go back to the default one
+    fn delete_instruction_profile(&mut self) {
+        let name = self.instruction_profile.clone();
+        let profiles = instruction_profiles::InstructionProfiles::standard();
+        if let Err(e) = profiles.delete(&name) {
+            self.profile_message = Some((format!("Couldn't delete the profile: {}", e), true));
+            return;
+        }
+        audit::record(
+            audit::AuditAction::ConfigChanged,
+            format!("Instruction profile '{}' deleted", name),
+        );
+        self.instruction_profiles = profiles.list();
+        self.switch_instruction_profile(instruction_profiles::DEFAULT_PROFILE);
+        self.profile_message = Some((format!("Deleted the {} profile", name), false));
     }
 
-    fn calculate_cost(&self, input_tokens: u32, output_tokens: u32) -> f64 {
-        // Claude Sonnet 4.5 pricing via OpenRouter
-        // Input: $3.00 per million tokens
-        // Output: $15.00 per million tokens
-        const INPUT_COST_PER_MILLION: f64 = 3.0;
-        const OUTPUT_COST_PER_MILLION: f64 = 15.0;
-
-        let input_cost = (input_tokens as f64 / 1_000_000.0) * INPUT_COST_PER_MILLION;
-        let output_cost = (output_tokens as f64 / 1_000_000.0) * OUTPUT_COST_PER_MILLION;
-
-        input_cost + output_cost
+    /// Location of token stats written before they moved into the storage service
+    fn legacy_stats_file_path() -> PathBuf {
+        PathBuf::from(".").join("rustbot_stats.json")
     }
 
-    fn generate_system_context(&self) -> String {
-        // Get current date and time
-        let now = chrono::Local::now();
-        let datetime = now.format("%Y-%m-%d %H:%M:%S %Z").to_string();
-        let day_of_week = now.format("%A").to_string();
-
-        // Get system information
-        let os = std::env::consts::OS;
-        let arch = std::env::consts::ARCH;
-        let hostname = std::env::var("HOSTNAME")
-            .or_else(|_| std::env::var("COMPUTERNAME"))
-            .unwrap_or_else(|_| "unknown".to_string());
-        let user = std::env::var("USER")
-            .or_else(|_| std::env::var("USERNAME"))
-            .unwrap_or_else(|_| "unknown".to_string());
+    /// Move stats from `rustbot_stats.json` into the storage service (one-time)
+    ///
+    /// The stats are saved through the stats writer, like any other update.
+    /// Daily counters are reset if they belong to a previous day.
+    ///
+    /// # Returns
+    /// The migrated stats, or None if there was nothing to migrate
+    fn migrate_legacy_token_stats(&self) -> Option<services::TokenStats> {
+        let path = Self::legacy_stats_file_path();
+        let content = std::fs::read_to_string(&path).ok()?;
+        let legacy: LegacyTokenStats = match serde_json::from_str(&content) {
+            Ok(legacy) => legacy,
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable {:?}: {}", path, e);
+                return None;
+            }
+        };
+
+        // Cost wasn't stored in the old format; the UI derives it from token counts
+        let mut stats = services::TokenStats {
+            total_input_tokens: legacy.total_input as u64,
+            total_output_tokens: legacy.total_output as u64,
+            daily_input_tokens: legacy.daily_input as u64,
+            daily_output_tokens: legacy.daily_output as u64,
+            last_reset_date: legacy.last_reset_date,
+            ..Default::default()
+        };
+
+        stats.reset_daily_if_stale(&chrono::Local::now().format("%Y-%m-%d").to_string());
+        self.save_token_stats_snapshot(stats.clone());
+
+        let mut migrated = path.clone().into_os_string();
+        migrated.push(".migrated");
+        if let Err(e) = std::fs::rename(&path, &migrated) {
+            tracing::warn!("Failed to rename {:?} after migration: {}", path, e);
+        }
+        tracing::info!("📊 Migrated token stats from {:?}", path);
+
+        Some(stats)
+    }
+
+    /// Queue the current stats for a debounced background save
+    fn save_token_stats(&self) {
+        self.save_token_stats_snapshot(self.token_stats.clone());
+    }
+
+    fn save_token_stats_snapshot(&self, stats: services::TokenStats) {
+        match &self.writer {
+            Some(writer) => writer.queue().save_token_stats(stats),
+            None => tracing::debug!("Token stats changed after shutdown or a restore; not saved"),
+        }
+    }
+
+    /// Queue for background session saves, None after shutdown or a backup
+    /// restore
+    fn write_queue(&self) -> Option<services::WriteQueue> {
+        let queue = self.writer.as_ref().map(|writer| writer.queue().clone());
+        if queue.is_none() {
+            tracing::debug!("Session changed after shutdown or a restore; not saved");
+        }
+        queue
+    }
+
+    fn estimate_tokens(&self, text: &str) -> u32 {
+        tokenizer::count_tokens(text) as u32
+    }
+
+    /// Recount the context meter from the chat and size it to the tab's model
+    fn update_context_tracker(&mut self) {
+        let system_content_tokens = self.estimate_tokens(&self.generate_system_context());
+        let conversation_total_tokens: u32 = self
+            .messages
+            .iter()
+            .map(|msg| self.estimate_tokens(&msg.content))
+            .sum();
+        self.context_tracker
+            .update_counts(system_content_tokens, conversation_total_tokens);
 
+        if let Some(config) = self
+            .agent_configs
+            .iter()
+            .find(|c| c.id == self.session.agent_id)
+        {
+            self.context_tracker.max_tokens = tokenizer::context_window(&config.model);
+        }
+    }
+
+    /// Tokens in the chat input, recounted only when the text changes
+    fn input_token_count(&mut self) -> u32 {
+        if self.input_token_cache.0 != self.message_input {
+            let count = self.estimate_tokens(&self.message_input);
+            self.input_token_cache = (self.message_input.clone(), count);
+        }
+        self.input_token_cache.1
+    }
+
+    fn calculate_cost(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        // Claude Sonnet 4.5 pricing via OpenRouter, shared with the daily limits
+        quota::estimate_cost(input_tokens, output_tokens)
+    }
+
+    /// The system context block, with the fields chosen in Settings >
+    /// Preferences > System Context
+    fn generate_system_context(&self) -> String {
         // Get the primary agent's model
         let model = self
             .agent_configs
@@ -766,61 +1610,39 @@ impl RustbotApp {
             .map(|config| config.model.as_str())
             .unwrap_or("unknown");
 
-        // Load user profile synchronously (blocking on async)
-        let profile = if let Some(runtime) = &self.deps.runtime {
-            runtime.block_on(async {
-                self.deps
-                    .storage
-                    .load_user_profile()
-                    .await
-                    .unwrap_or_default()
-            })
-        } else {
-            services::traits::UserProfile::default()
-        };
-
-        // Build user profile section if available
-        let user_profile_section = if !profile.name.is_empty() || !profile.email.is_empty() {
-            let mut section = String::new();
-            if !profile.name.is_empty() {
-                section.push_str(&format!("\n**User Name**: {}", profile.name));
-            }
-            if !profile.email.is_empty() {
-                section.push_str(&format!("\n**User Email**: {}", profile.email));
-            }
-            if let Some(ref timezone) = profile.timezone {
-                section.push_str(&format!("\n**User Timezone**: {}", timezone));
-            }
-            if let Some(ref location) = profile.location {
-                section.push_str(&format!("\n**User Location**: {}", location));
+        // Latest profile known to the app state actor (no storage access)
+        let profile = self.app_state.profile();
+
+        // The active project comes first among the custom entries
+        let mut settings = self.system_context.clone();
+        if let Some(project) = self.current_project() {
+            let mut entries = vec![system_context::ContextEntry {
+                key: "Project".to_string(),
+                value: project.name.clone(),
+            }];
+            if let Some(folder) = &project.folder {
+                entries.push(system_context::ContextEntry {
+                    key: "Project Folder".to_string(),
+                    value: folder.display().to_string(),
+                });
             }
-            section
-        } else {
-            String::new()
-        };
-
-        // Build the system context
-        format!(
-            r#"## System Context
-
-**Current Date & Time**: {} ({})
-**LLM Model**: {}
-**Application**: Rustbot v{}
-**Operating System**: {} ({})
-**Hostname**: {}
-**User**: {}{}
+            settings.custom.splice(0..0, entries);
+        }
 
-This information is provided automatically to give you context about the current system environment."#,
-            datetime,
-            day_of_week,
+        system_context::SystemContext::current(
             model,
-            version::version_string(),
-            os,
-            arch,
-            hostname,
-            user,
-            user_profile_section
+            &format!("Rustbot {}", version::version_string()),
+            &profile,
         )
+        .render(&settings)
+    }
+
+    /// Save the System Context settings to the user profile
+    fn save_system_context(&mut self) {
+        let settings = self.system_context.clone();
+        self.app_state
+            .update_profile(move |profile| profile.system_context = settings);
+        self.update_context_tracker();
     }
 
     fn clear_conversation(&mut self) {
@@ -832,294 +1654,3340 @@ This information is provided automatically to give you context about the current
 
         // Clear UI state
         self.messages.clear();
+        self.editing_message = None;
+        self.feedback_comment = None;
+        self.retry = ui::RetryState::default();
+        self.pending_diagrams.clear();
+        self.regenerate_open = false;
+        self.message_heights.clear();
         self.current_response.clear();
         self.context_tracker.update_counts(0, 0);
 
-        // Clear event flow display
-        self.event_history.clear();
+        // Clear event flow display
+        self.event_history.clear();
+        self.event_sequence_diagram = None;
+        self.event_sequence_render = None;
+
+        // Start a fresh session; the previous one stays on disk
+        self.session = services::ConversationSession::new(self.session.agent_id.clone());
+        self.handoff = None;
+        self.tool_overrides.clear();
+
+        // Clear API conversation history and publish event
+        let api = Arc::clone(&self.api);
+        let storage = Arc::clone(&self.deps.storage);
+        let runtime = &self.runtime;
+        let api_session = self.api_session.clone();
+        runtime.spawn(async move {
+            let mut api_guard = api.lock().await;
+            if let Err(e) = api_guard.clear_history_in(&api_session) {
+                tracing::warn!("Failed to clear history: {}", e);
+            }
+            drop(api_guard);
+
+            if let Err(e) = storage.clear_last_session().await {
+                tracing::warn!("Failed to clear last session: {}", e);
+            }
+        });
+    }
+
+    /// Reload agent configs; the API is rebuilt once they arrive
+    /// (see `poll_agent_reload`)
+    fn reload_config(&mut self) {
+        tracing::info!("🔄 Reloading Rustbot configuration...");
+        self.agent_reload_rx = Some(self.app_state.load_agents());
+    }
+
+    /// Finish a config reload once the app state actor has the agents
+    fn poll_agent_reload(&mut self) {
+        let Some(rx) = &mut self.agent_reload_rx else {
+            return;
+        };
+        let agent_configs = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => {
+                tracing::warn!("Config reload failed: {}", e);
+                self.agent_reload_rx = None;
+                return;
+            }
+            Ok(agent_configs) => agent_configs,
+        };
+        self.agent_reload_rx = None;
+
+        tracing::info!("📋 Reloaded {} agents", agent_configs.len());
+        for config in &agent_configs {
+            tracing::info!(
+                "   - {} (primary: {}, enabled: {})",
+                config.id,
+                config.is_primary,
+                config.enabled
+            );
+        }
+
+        let api = match self.build_api(&agent_configs) {
+            Ok(api) => api,
+            Err(e) => {
+                tracing::error!("Config reload failed: {}", e);
+                self.command_feedback = Some((format!("Couldn't reload the agents: {}", e), true));
+                return;
+            }
+        };
+        self.install_api(api, agent_configs);
+        self.broken_agents = agent::AgentLoader::new().find_broken();
+
+        // The rebuilt API only has its default session, so other tabs are closed
+        self.tabs = vec![ui::ChatTab::new(String::new(), "")];
+        self.active_tab = 0;
+        self.api_session = api::DEFAULT_SESSION.to_string();
+        self.message_input.clear();
+        self.pending_images.clear();
+        self.pending_agent_result = None;
+        self.turn_task = None;
+        self.turn_started = None;
+        self.response_rx = None;
+        self.is_waiting = false;
+
+        // Clear conversation on reload
+        self.clear_conversation();
+
+        tracing::info!("✅ Configuration reloaded successfully");
+    }
+
+    /// LLM adapter from the dependencies
+    ///
+    /// # Errors
+    /// - No adapter configured
+    fn llm_adapter(&self) -> Result<&Arc<dyn LlmAdapter>> {
+        self.deps
+            .llm_adapter
+            .as_ref()
+            .ok_or_else(|| RustbotError::ConfigError("No LLM adapter configured".to_string()))
+    }
+
+    /// Send requests to another provider from now on
+    ///
+    /// Agents hold the adapter they were built with, so reload or rebuild
+    /// the API afterwards.
+    fn use_provider(&mut self, provider: llm::LlmProvider, api_key: String) {
+        self.deps.llm_adapter = Some(Arc::from(create_adapter(provider.into(), api_key)));
+        self.llm_provider = provider;
+    }
+
+    /// Build an API with the given agents and the app's tool services
+    ///
+    /// # Errors
+    /// - No LLM adapter configured
+    /// - An agent config is invalid
+    fn build_api(&self, agent_configs: &[AgentConfig]) -> Result<RustbotApi> {
+        let runtime = &self.runtime;
+        let llm_adapter = self.llm_adapter()?;
+
+        let mut api_builder = api::RustbotApiBuilder::new()
+            .event_bus(Arc::clone(&self.deps.event_bus))
+            .runtime(Arc::clone(runtime))
+            .llm_adapter(Arc::clone(llm_adapter))
+            .max_history_size(20)
+            .system_instructions(self.system_prompts.system_instructions.clone());
+
+        for agent_config in agent_configs {
+            api_builder = api_builder.add_agent(agent_config.clone());
+        }
+
+        let mut api = api_builder.build()?;
+        api.set_script_host(Arc::clone(&self.script_host));
+        if let Some(host) = &self.wasm_tools {
+            api.set_wasm_tools(Arc::clone(host));
+        }
+        api.set_native_plugins(Arc::clone(&self.native_plugins));
+        if let Some(email) = &self.email {
+            api.set_email_service(Arc::clone(email));
+        }
+        api.set_fs_consent(Arc::clone(&self.fs_consent));
+        api.set_calendar(self.calendar.clone());
+        api.set_git_tools(self.git_tools.clone());
+        Ok(api)
+    }
+
+    /// Replace the API, resubscribing to events and pointing the control
+    /// socket at the new one
+    ///
+    /// The socket keeps its listener: rebinding right after aborting the old
+    /// one would find it still accepting and fail as "already running".
+    fn install_api(&mut self, api: RustbotApi, agent_configs: Vec<AgentConfig>) {
+        let runtime = &self.runtime;
+
+        // Subscribe to fresh event bus events
+        self.event_rx = self.deps.event_bus.subscribe_named("ui");
+        self.api = Arc::new(Mutex::new(api));
+
+        // Point the control socket at the rebuilt API
+        match &self.ipc_server {
+            Some(server) => server.set_api(Arc::clone(&self.api)),
+            None => {
+                self.ipc_server =
+                    Self::start_ipc_server(&self.api, &self.deps.event_bus, runtime.handle());
+            }
+        }
+        self.agent_configs = agent_configs;
+    }
+
+    /// Apply what the app state actor loaded at startup: agents, the last
+    /// conversation, preferences and token stats
+    ///
+    /// # Errors
+    /// - The API can't be built with the loaded agents
+    fn apply_startup(&mut self, state: services::StartupState) -> Result<()> {
+        let services::StartupState {
+            agent_configs,
+            last_session,
+            profile,
+            token_stats,
+            recovery,
+        } = state;
+
+        // The dependencies start out with OpenRouter
+        if profile.provider != self.llm_provider {
+            let api_key = std::env::var(profile.provider.default_env_var()).unwrap_or_default();
+            self.use_provider(profile.provider, api_key);
+        }
+        let mut api = self.build_api(&agent_configs)?;
+
+        // Restore the previous conversation so a restart doesn't lose the chat
+        match last_session {
+            Some(session) => {
+                tracing::info!(
+                    "📂 Restored session '{}' ({} messages)",
+                    session.id,
+                    session.messages.len()
+                );
+                if let Err(e) = api.switch_agent(&session.agent_id) {
+                    tracing::warn!("Restored session agent unavailable: {}", e);
+                }
+                api.restore_history(session.history.clone());
+                self.messages = session
+                    .messages
+                    .iter()
+                    .map(Self::chat_message_from_session)
+                    .collect();
+                self.session = session;
+            }
+            None => self.session = services::ConversationSession::new(api.active_agent()),
+        }
+        self.install_api(api, agent_configs);
+        self.update_context_tracker();
+
+        self.token_stats = self.migrate_legacy_token_stats().unwrap_or(token_stats);
+
+        // First run if no profile exists or the provider requests go to has no key
+        let profile_exists = !profile.name.is_empty() || !profile.email.is_empty();
+        let provider_ready = !self.llm_provider.requires_api_key()
+            || std::env::var(self.llm_provider.default_env_var()).is_ok_and(|key| !key.is_empty());
+        self.setup_wizard_active = !profile_exists || !provider_ready;
+        self.setup_provider = profile.provider;
+
+        self.theme = profile.theme;
+        self.font_size = profile.font_size;
+        self.ui_scale = profile.ui_scale;
+        self.notifications_enabled = profile.notifications;
+        self.privacy_mode = profile.privacy_mode;
+        self.allowed_hosts_input = profile.allowed_hosts.join(", ");
+        self.offline_model = profile.offline_model.clone().unwrap_or_default();
+        self.analytics_enabled = profile.analytics;
+        self.injection_warnings = profile.injection_warnings;
+        self.quota_form = ui::QuotaForm::from_quota(&profile.quota);
+        self.system_context = profile.system_context.clone();
+        self.active_project = profile
+            .active_project
+            .clone()
+            .filter(|id| self.projects.iter().any(|p| p.id == *id));
+        self.apply_git_tools();
+
+        if let Some(recovery) = &recovery {
+            tracing::info!(
+                "🩹 Found unsaved work from {} (last exit wasn't clean)",
+                recovery.saved_at
+            );
+        }
+        self.recovery_offer = recovery;
+        Ok(())
+    }
+
+    /// Take the startup state once the app state actor has loaded it
+    fn poll_startup(&mut self) {
+        let Some(rx) = &mut self.startup_rx else {
+            return;
+        };
+        match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => {}
+            Err(e) => {
+                tracing::warn!("Startup state unavailable: {}", e);
+                self.startup_rx = None;
+            }
+            Ok(state) => {
+                self.startup_rx = None;
+                if let Err(e) = self.apply_startup(state) {
+                    tracing::error!("Startup failed: {}", e);
+                    self.startup_error = Some(e);
+                }
+            }
+        }
+    }
+
+    /// Save the draft and any streaming answer every `RECOVERY_SAVE_INTERVAL`
+    /// so a crash or force-quit doesn't lose them
+    ///
+    /// Only the visible tab is covered. Nothing is saved while a previous
+    /// recovery offer is pending, so it isn't overwritten before the user
+    /// has answered it.
+    fn save_recovery(&mut self, ctx: &egui::Context) {
+        if self.recovery_offer.is_some() || self.startup_rx.is_some() {
+            return;
+        }
+        let streamed = self.is_waiting.then_some(self.current_response.len());
+        let unchanged = match &self.recovery_saved {
+            Some((draft, saved)) => *draft == self.message_input && *saved == streamed,
+            None => self.message_input.trim().is_empty() && streamed.is_none(),
+        };
+        if unchanged {
+            return;
+        }
+        // Come back once the interval is up even if nothing else repaints
+        let since = self.recovery_checked.elapsed();
+        if since < ui::types::RECOVERY_SAVE_INTERVAL {
+            ctx.request_repaint_after(ui::types::RECOVERY_SAVE_INTERVAL - since);
+            return;
+        }
+        self.recovery_checked = std::time::Instant::now();
+        let key = (self.message_input.clone(), streamed);
+        if key.0.trim().is_empty() && streamed.is_none() {
+            // Nothing left to lose (sent, and the answer saved with the session)
+            self.clear_recovery();
+            return;
+        }
+
+        let state = services::RecoveryState {
+            session_id: self.session.id.clone(),
+            draft: key.0.clone(),
+            interrupted: streamed.map(|_| self.snapshot_session().messages),
+            saved_at: chrono::Utc::now(),
+        };
+        let storage = Arc::clone(&self.deps.storage);
+        let runtime = &self.runtime;
+        self.pending_saves.spawn_on(
+            async move {
+                if let Err(e) = storage.save_recovery(&state).await {
+                    tracing::warn!("Failed to save recovery state: {}", e);
+                }
+            },
+            runtime.handle(),
+        );
+        self.recovery_saved = Some(key);
+    }
+
+    /// Put back what an unclean exit left behind: the unsent draft, and the
+    /// conversation up to the answer that was streaming
+    ///
+    /// The interrupted answer is kept as far as it got and added to the LLM
+    /// history, so the model sees it when the conversation continues.
+    fn restore_recovery(&mut self) {
+        let Some(state) = self.recovery_offer.take() else {
+            return;
+        };
+
+        if !state.draft.trim().is_empty() {
+            if self.message_input.trim().is_empty() {
+                self.message_input = state.draft;
+            } else {
+                self.message_input = format!("{}\n\n{}", state.draft, self.message_input);
+            }
+        }
+
+        if let Some(messages) = state.interrupted {
+            // A conversation that never finished a turn was never saved, so
+            // it isn't the restored one; it continues under its own ID
+            let same_session = state.session_id == self.session.id;
+            let known = if same_session {
+                self.session.messages.len().min(messages.len())
+            } else {
+                self.session = services::ConversationSession::new(self.session.agent_id.clone());
+                self.session.id = state.session_id;
+                0
+            };
+            let added: Vec<llm::Message> = messages[known..]
+                .iter()
+                .map(|m| llm::Message::new(m.role.clone(), m.content.clone()))
+                .collect();
+
+            tracing::info!(
+                "🩹 Restored interrupted conversation '{}' ({} messages)",
+                self.session.id,
+                messages.len()
+            );
+            self.messages = messages
+                .iter()
+                .map(Self::chat_message_from_session)
+                .collect();
+            self.message_heights.clear();
+            self.update_context_tracker();
+
+            let mut session = self.snapshot_session();
+            let api = Arc::clone(&self.api);
+            let queue = self.write_queue();
+            let api_session = self.api_session.clone();
+            let runtime = &self.runtime;
+            self.pending_saves.spawn_on(
+                async move {
+                    let mut api_guard = api.lock().await;
+                    let mut history = match api_guard.session(&api_session) {
+                        Some(api_session) if same_session => api_session.history(),
+                        _ => Vec::new(),
+                    };
+                    history.extend(added);
+                    if let Err(e) = api_guard.restore_history_in(&api_session, history.clone()) {
+                        tracing::warn!("Failed to restore history: {}", e);
+                    }
+                    drop(api_guard);
+
+                    session.history = history;
+                    if let Some(queue) = queue {
+                        queue.save_session(session);
+                    }
+                },
+                runtime.handle(),
+            );
+        }
+
+        self.clear_recovery();
+    }
+
+    /// Forget the recovery state, after restoring or dismissing it
+    fn clear_recovery(&mut self) {
+        self.recovery_offer = None;
+        self.recovery_saved = None;
+        let storage = Arc::clone(&self.deps.storage);
+        let runtime = &self.runtime;
+        self.pending_saves.spawn_on(
+            async move {
+                if let Err(e) = storage.clear_recovery().await {
+                    tracing::warn!("Failed to clear recovery state: {}", e);
+                }
+            },
+            runtime.handle(),
+        );
+    }
+
+    /// Open the New Agent wizard, listing the installed MCP extensions
+    fn open_agent_wizard(&mut self) {
+        let registry_path = dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(".rustbot")
+            .join("extensions")
+            .join("registry.json");
+        let extensions = mcp::extensions::ExtensionRegistry::load(&registry_path)
+            .map(|registry| {
+                registry
+                    .list()
+                    .into_iter()
+                    .map(|extension| (extension.id.clone(), extension.name.clone()))
+                    .collect()
+            })
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to load extension registry: {}", e);
+                Vec::new()
+            });
+
+        self.agent_wizard = Some(ui::AgentWizard {
+            step: ui::AgentWizardStep::Name,
+            draft: agent::templates::AgentDraft::default(),
+            extensions,
+            error: None,
+        });
+    }
+
+    /// Write the preset being fixed and reload the agents
+    ///
+    /// The editor stays open with the error if the file can't be written.
+    fn save_preset_editor(&mut self) {
+        let Some(editor) = &mut self.preset_editor else {
+            return;
+        };
+        if let Err(e) = std::fs::write(&editor.path, &editor.json) {
+            editor.error = Some(format!("Couldn't save the preset: {}", e));
+            return;
+        }
+        tracing::info!("🤖 Fixed agent preset {:?}", editor.path);
+        audit::record(
+            audit::AuditAction::ConfigChanged,
+            format!("Agent preset {} edited", editor.path.display()),
+        );
+        self.preset_editor = None;
+        self.reload_config();
+    }
+
+    /// Save a wizard draft as a preset in agents/custom and register the agent
+    ///
+    /// # Returns
+    /// ID of the new agent
+    ///
+    /// # Errors
+    /// - The draft is invalid or its ID is taken
+    /// - A response is still streaming (the API is busy)
+    /// - The preset can't be written or loaded
+    fn create_agent(&mut self, draft: &agent::templates::AgentDraft) -> anyhow::Result<String> {
+        let existing: Vec<String> = self.agent_configs.iter().map(|c| c.id.clone()).collect();
+        draft.validate(&existing)?;
+
+        let mut api = self.api.try_lock().map_err(|_| {
+            anyhow::anyhow!("Rustbot is still answering; try again when it finishes")
+        })?;
+        let path = agent::templates::save_custom_preset(
+            &self.deps.config.get_agents_dir(),
+            &draft.to_json_config(),
+        )?;
+        let config = match agent::loader::AgentLoader::new().load_agent(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                // Don't leave a preset behind that blocks the next attempt
+                let _ = std::fs::remove_file(&path);
+                return Err(e);
+            }
+        };
+        let llm_adapter = self.llm_adapter()?;
+        api.add_agent_config(
+            config.clone(),
+            Arc::clone(llm_adapter),
+            self.system_prompts.system_instructions.clone(),
+        )?;
+        drop(api);
+
+        tracing::info!("🤖 Registered new agent '{}'", config.id);
+        audit::record(
+            audit::AuditAction::ConfigChanged,
+            format!("Agent '{}' created", config.id),
+        );
+        let id = config.id.clone();
+        self.agent_configs.push(config);
+        Ok(id)
+    }
+
+    /// Start the local IPC control socket
+    ///
+    /// Failure is logged and the app keeps running without external control.
+    fn start_ipc_server(
+        api: &Arc<Mutex<RustbotApi>>,
+        event_bus: &Arc<EventBus>,
+        handle: &tokio::runtime::Handle,
+    ) -> Option<ipc::ControlSocket> {
+        match ipc::start_control_socket(
+            ipc::default_socket_path(),
+            Arc::clone(api),
+            Arc::clone(event_bus),
+            handle,
+        ) {
+            Ok(server) => Some(server),
+            Err(e) => {
+                tracing::warn!("IPC control socket unavailable: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Create the email connector from ~/.rustbot/email.json
+    ///
+    /// Returns None (with a log message) when no account is configured or the
+    /// password secret is missing.
+    fn load_email_service(event_bus: &Arc<EventBus>) -> Option<Arc<email::EmailService>> {
+        let config = match email::EmailConfig::load(&email::EmailConfig::default_path()) {
+            Ok(Some(config)) => config,
+            Ok(None) => {
+                tracing::debug!("No email.json found, email tools disabled");
+                return None;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load email configuration: {}", e);
+                return None;
+            }
+        };
+
+        match services::DefaultSecretStore::system().get(email::PASSWORD_SECRET) {
+            Ok(Some(password)) => Some(Arc::new(email::EmailService::new(
+                config,
+                password,
+                Arc::clone(event_bus),
+            ))),
+            Ok(None) => {
+                tracing::warn!(
+                    "email.json found but {} is not set, email tools disabled",
+                    email::PASSWORD_SECRET
+                );
+                None
+            }
+            Err(e) => {
+                tracing::warn!("Failed to read email password: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Create the calendar connector, reading credentials from the keychain
+    fn connect_calendar(
+        config: &calendar::CalendarConfig,
+    ) -> anyhow::Result<Arc<calendar::CalendarService>> {
+        let secrets = services::DefaultSecretStore::system();
+        Ok(Arc::new(calendar::CalendarService::from_config(
+            config, &secrets,
+        )?))
+    }
+
+    /// Use a calendar connector (or none) in the app and the API
+    ///
+    /// The API gets it in the background, after any turn in progress.
+    fn apply_calendar(&mut self, calendar: Option<Arc<calendar::CalendarService>>) {
+        self.calendar = calendar.clone();
+
+        let api = Arc::clone(&self.api);
+        self.runtime
+            .spawn(async move { api.lock().await.set_calendar(calendar) });
+    }
+
+    /// Save Settings > Calendar and reconnect
+    ///
+    /// The secret typed into the form goes to the keychain, everything else
+    /// to ~/.rustbot/calendar.json.
+    fn save_calendar_settings(&mut self) {
+        let config = self.calendar_form.to_config();
+        let (secret_name, secret) = self.calendar_form.secret();
+
+        let saved = (|| -> Result<()> {
+            if !secret.is_empty() {
+                services::DefaultSecretStore::system().set(secret_name, secret)?;
+            }
+            config.save(&calendar::CalendarConfig::default_path())
+        })();
+        if let Err(e) = saved {
+            self.calendar_message =
+                Some((format!("Failed to save calendar settings: {}", e), true));
+            return;
+        }
+        audit::record(audit::AuditAction::ConfigChanged, "Calendar settings");
+        self.calendar_form.caldav_password.clear();
+        self.calendar_form.google_client_secret.clear();
+
+        match Self::connect_calendar(&config) {
+            Ok(calendar) => {
+                self.calendar_message = Some((format!("Connected to {}", calendar.label()), false));
+                self.apply_calendar(Some(calendar));
+            }
+            Err(e) => {
+                self.calendar_message = Some((format!("Saved, but not connected: {:#}", e), true));
+                self.apply_calendar(None);
+            }
+        }
+    }
+
+    /// Start Google sign-in in the browser; finished by poll_google_sign_in
+    fn start_google_sign_in(&mut self, ctx: &egui::Context) {
+        let calendar::CalendarConfig::Google { client_id, .. } = self.calendar_form.to_config()
+        else {
+            return;
+        };
+        let client_secret = if self.calendar_form.google_client_secret.is_empty() {
+            services::DefaultSecretStore::system()
+                .get(calendar::GOOGLE_CLIENT_SECRET)
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        } else {
+            self.calendar_form.google_client_secret.clone()
+        };
+        if client_id.is_empty() || client_secret.is_empty() {
+            self.calendar_message = Some((
+                "Enter the client ID and client secret first".to_string(),
+                true,
+            ));
+            return;
+        }
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let result = async {
+                let flow = calendar::GoogleAuthFlow::start(&client_id, &client_secret).await?;
+                ctx.open_url(egui::OpenUrl::new_tab(flow.url()));
+                flow.finish().await
+            }
+            .await;
+            let _ = tx.send(result);
+            ctx.request_repaint();
+        });
+        self.calendar_auth_rx = Some(rx);
+        self.calendar_message = Some((
+            "Waiting for Google sign-in in your browser…".to_string(),
+            false,
+        ));
+    }
+
+    /// Store the refresh token once Google sign-in completes
+    fn poll_google_sign_in(&mut self) {
+        let Some(rx) = &mut self.calendar_auth_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => Err(anyhow::Error::from(e)),
+            Ok(result) => result,
+        };
+        self.calendar_auth_rx = None;
+
+        let stored = result.and_then(|token| {
+            services::DefaultSecretStore::system()
+                .set(calendar::GOOGLE_REFRESH_TOKEN_SECRET, &token)
+                .map_err(anyhow::Error::from)
+        });
+        match stored {
+            Ok(()) => self.save_calendar_settings(),
+            Err(e) => {
+                self.calendar_message = Some((format!("Google sign-in failed: {:#}", e), true));
+            }
+        }
+    }
+
+    /// Remove the calendar configuration and its keychain entries
+    fn disconnect_calendar(&mut self) {
+        let path = calendar::CalendarConfig::default_path();
+        let secrets = services::DefaultSecretStore::system();
+        let removed = (|| -> Result<()> {
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+            for name in [
+                calendar::CALDAV_PASSWORD_SECRET,
+                calendar::GOOGLE_CLIENT_SECRET,
+                calendar::GOOGLE_REFRESH_TOKEN_SECRET,
+            ] {
+                secrets.delete(name)?;
+            }
+            Ok(())
+        })();
+
+        self.apply_calendar(None);
+        self.calendar_form = ui::CalendarForm::default();
+        self.calendar_message = Some(match removed {
+            Ok(()) => ("Calendar disconnected".to_string(), false),
+            Err(e) => (format!("Failed to remove calendar settings: {}", e), true),
+        });
+    }
+
+    /// Open the export dialog with a fresh default file name
+    ///
+    /// Defaults to ~/.rustbot/exports/conversation-<timestamp>.<ext>
+    fn open_conversation_export_dialog(&mut self) {
+        self.conversation_export_path = dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("exports")
+            .join(format!(
+                "conversation-{}.{}",
+                chrono::Local::now().format("%Y%m%d-%H%M%S"),
+                self.conversation_export_format.extension()
+            ))
+            .display()
+            .to_string();
+        self.conversation_export_message = None;
+        self.conversation_export_open = true;
+    }
+
+    /// Open the context inspector with a fresh preview of the next request
+    fn open_context_inspector(&mut self) {
+        self.context_inspector_open = true;
+        self.refresh_context_preview();
+    }
+
+    /// Rebuild the context inspector's preview of the next request
+    ///
+    /// Built in the background (`poll_context_preview` takes it). Skipped
+    /// while a turn holds the API; the previous preview stays and the
+    /// inspector says why it wasn't updated.
+    fn refresh_context_preview(&mut self) {
+        if self.context_preview_rx.is_some() {
+            return;
+        }
+        let api = Arc::clone(&self.api);
+        let api_session = self.api_session.clone();
+        let pinned = self.pinned_context();
+        let overrides = self.tool_overrides.clone();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn(async move {
+            let Ok(mut api) = api.try_lock() else {
+                let _ = tx.send(None);
+                return;
+            };
+            if let Err(e) = api.set_pinned_in(&api_session, pinned) {
+                tracing::warn!("Failed to update pinned messages: {}", e);
+            }
+            if let Err(e) = api.set_tool_overrides_in(&api_session, overrides) {
+                tracing::warn!("Failed to update tool toggles: {}", e);
+            }
+            let _ = tx.send(Some(api.preview_request_in(&api_session).await));
+        });
+        self.context_preview_rx = Some((self.messages.len(), rx));
+    }
+
+    /// Show the context preview once it is built
+    fn poll_context_preview(&mut self) {
+        let Some((messages, rx)) = &mut self.context_preview_rx else {
+            return;
+        };
+        let messages = *messages;
+        let result = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => Some(Err(e.into())),
+            Ok(result) => result,
+        };
+        self.context_preview_rx = None;
+
+        match result {
+            Some(Ok(preview)) => {
+                self.context_preview = Some(preview);
+                self.context_preview_at = messages;
+                self.context_preview_message = None;
+            }
+            Some(Err(e)) => {
+                self.context_preview_message =
+                    Some((format!("Couldn't build the preview: {}", e), true));
+            }
+            None => {
+                self.context_preview_message = Some((
+                    "An answer is in progress; refresh when it's done".to_string(),
+                    true,
+                ));
+            }
+        }
+    }
+
+    /// Reload the tool panel's sources for the visible tab's agent
+    ///
+    /// Listed in the background (`poll_tool_sources` takes them). Skipped
+    /// while a turn holds the API; the previous list stays.
+    fn refresh_tool_sources(&mut self) {
+        let api = Arc::clone(&self.api);
+        let api_session = self.api_session.clone();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn(async move {
+            let Ok(api) = api.try_lock() else {
+                let _ = tx.send(None);
+                return;
+            };
+            let _ = tx.send(Some(api.tool_sources_in(&api_session).await));
+        });
+        self.tool_sources_rx = Some(rx);
+    }
+
+    /// Show the tool panel's sources once they are listed
+    fn poll_tool_sources(&mut self) {
+        let Some(rx) = &mut self.tool_sources_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => Some(Err(e.into())),
+            Ok(result) => result,
+        };
+        self.tool_sources_rx = None;
+
+        match result {
+            Some(Ok(sources)) => self.tool_sources = sources,
+            Some(Err(e)) => tracing::warn!("Failed to list tool sources: {}", e),
+            None => {}
+        }
+    }
+
+    /// Write the current session, including tool calls from the API history
+    /// and rendered diagrams from the chat view, to a file
+    ///
+    /// Runs in the background; `poll_conversation_export` reports the outcome
+    /// in `conversation_export_message`.
+    fn export_conversation_to_file(
+        &mut self,
+        path: PathBuf,
+        format: conversation_export::ConversationFormat,
+    ) {
+        let session = self.session_with_history();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn(async move {
+            let session = session.await;
+            let result = (|| {
+                let content = conversation_export::ConversationExport::from_session(&session)
+                    .render(format)?;
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, content)?;
+
+                tracing::info!("📤 Exported conversation to {}", path.display());
+                Ok(())
+            })();
+            let _ = tx.send((path, result));
+        });
+        self.conversation_export_rx = Some(rx);
+    }
+
+    /// Report a finished conversation export
+    fn poll_conversation_export(&mut self) {
+        let Some(rx) = &mut self.conversation_export_rx else {
+            return;
+        };
+        let (path, result) = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => {
+                self.conversation_export_rx = None;
+                self.conversation_export_message = Some((format!("Export failed: {}", e), true));
+                return;
+            }
+            Ok(received) => received,
+        };
+        self.conversation_export_rx = None;
+
+        self.conversation_export_message = Some(match result {
+            Ok(()) => (format!("Saved to {}", path.display()), false),
+            Err(e) => (format!("Export failed: {}", e), true),
+        });
+    }
+
+    /// The current session with the tool calls from the API history, for
+    /// exporting and sharing
+    ///
+    /// The history is read once no turn holds the API (a turn in any tab
+    /// does), so await the result in the background rather than on the UI
+    /// thread.
+    fn session_with_history(
+        &mut self,
+    ) -> impl std::future::Future<Output = services::ConversationSession> + Send + 'static {
+        let mut session = self.snapshot_session();
+        let api = Arc::clone(&self.api);
+        let api_session = self.api_session.clone();
+        async move {
+            if let Some(api_session) = api.lock().await.session(&api_session) {
+                session.history = api_session.history();
+            }
+            session
+        }
+    }
+
+    /// Open the share dialog with a fresh default file name
+    ///
+    /// Defaults to ~/.rustbot/exports/shared-<timestamp>.html
+    fn open_share_dialog(&mut self) {
+        self.share_path = dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("exports")
+            .join(format!(
+                "shared-{}.html",
+                chrono::Local::now().format("%Y%m%d-%H%M%S")
+            ))
+            .display()
+            .to_string();
+        self.share_has_token = services::DefaultSecretStore::system()
+            .get(share::GITHUB_TOKEN_SECRET)
+            .ok()
+            .flatten()
+            .is_some_and(|token| !token.is_empty());
+        self.share_url = None;
+        self.share_message = None;
+        self.share_open = true;
+    }
+
+    /// Write the shareable page of the current session to the share path
+    ///
+    /// Runs in the background; `poll_share` reports the file written.
+    fn save_shared_page(&mut self) {
+        let path = PathBuf::from(self.share_path.trim());
+        let redact = self.share_redact;
+        let session = self.session_with_history();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn(async move {
+            let session = session.await;
+            let result = (|| {
+                let page = share::share_page(&session, redact)?;
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, page)?;
+
+                tracing::info!("🔗 Saved shareable page to {}", path.display());
+                Ok(path)
+            })();
+            let _ = tx.send(result);
+        });
+        self.share_save_rx = Some(rx);
+    }
+
+    /// Upload the shareable page as a gist in the background; finished by
+    /// poll_share
+    ///
+    /// A token typed in the dialog is saved to the keychain first.
+    fn start_gist_upload(&mut self, ctx: &egui::Context) {
+        if self.share_rx.is_some() {
+            return;
+        }
+        let secrets = services::DefaultSecretStore::system();
+        let typed = self.share_token_input.trim().to_string();
+        if !typed.is_empty() {
+            if let Err(e) = secrets.set(share::GITHUB_TOKEN_SECRET, &typed) {
+                self.share_message = Some((format!("Couldn't save the token: {}", e), true));
+                return;
+            }
+            audit::record(audit::AuditAction::ConfigChanged, "GitHub token for gists");
+            self.share_token_input.clear();
+            self.share_has_token = true;
+        }
+        let token = match secrets.get(share::GITHUB_TOKEN_SECRET) {
+            Ok(token) => token.unwrap_or_default(),
+            Err(e) => {
+                self.share_message = Some((format!("Couldn't read the token: {}", e), true));
+                return;
+            }
+        };
+        let session = self.session_with_history();
+        let redact = self.share_redact;
+        let public = self.share_public;
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let result = match share::share_page(&session.await, redact) {
+                Ok(page) => share::upload_gist(&token, "conversation.html", &page, public).await,
+                Err(e) => Err(e.into()),
+            };
+            let _ = tx.send(result);
+            ctx.request_repaint();
+        });
+        self.share_rx = Some(rx);
+        self.share_url = None;
+        self.share_message = Some(("Uploading…".to_string(), false));
+    }
+
+    /// Show the saved page or the gist address once it is done
+    fn poll_share(&mut self) {
+        if let Some(rx) = &mut self.share_save_rx {
+            match rx.try_recv() {
+                Err(tokio::sync::oneshot::error::TryRecvError::Empty) => {}
+                Err(e) => {
+                    self.share_save_rx = None;
+                    self.share_message = Some((format!("Sharing failed: {}", e), true));
+                }
+                Ok(result) => {
+                    self.share_save_rx = None;
+                    self.share_message = Some(match result {
+                        Ok(path) => (format!("Saved to {}", path.display()), false),
+                        Err(e) => (format!("Sharing failed: {}", e), true),
+                    });
+                }
+            }
+        }
+
+        let Some(rx) = &mut self.share_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => {
+                self.share_rx = None;
+                self.share_message = Some((format!("Sharing failed: {}", e), true));
+                return;
+            }
+            Ok(result) => result,
+        };
+        self.share_rx = None;
+        match result {
+            Ok(url) => {
+                self.share_message = Some(("Shared as a gist".to_string(), false));
+                self.share_url = Some(url);
+            }
+            Err(e) => {
+                // A rejected token has to be entered again
+                if matches!(e, share::ShareError::Rejected { status: 401, .. }) {
+                    self.share_has_token = false;
+                }
+                self.share_message = Some((format!("Sharing failed: {}", e), true));
+            }
+        }
+    }
+
+    /// Re-run the History view search with the current query
+    ///
+    /// The search runs in the background and `poll_history` shows the results.
+    fn refresh_history(&mut self) {
+        let storage = Arc::clone(&self.deps.storage);
+        let query = self.history_query.clone();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn(async move {
+            let _ = tx.send(storage.search_sessions(&query).await);
+        });
+        self.history_rx = Some(rx);
+    }
+
+    /// Show the History view search results once they arrive
+    ///
+    /// While a project is active only its conversations are listed.
+    fn poll_history(&mut self) {
+        let Some(rx) = &mut self.history_rx else {
+            return;
+        };
+        self.history_results = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => {
+                tracing::warn!("Failed to search session history: {}", e);
+                Vec::new()
+            }
+            Ok(result) => result.unwrap_or_else(|e| {
+                tracing::warn!("Failed to search session history: {}", e);
+                Vec::new()
+            }),
+        };
+        self.history_rx = None;
+
+        if let Some(project) = &self.active_project {
+            self.history_results
+                .retain(|summary| summary.project.as_ref() == Some(project));
+        }
+    }
+
+    /// Reload the Usage view from the saved conversations and the collected
+    /// analytics
+    ///
+    /// The conversations load in the background (`poll_usage` fills in the
+    /// entries); covers the last 30 days, or the last 12 weeks in weekly mode.
+    fn refresh_usage(&mut self) {
+        let storage = Arc::clone(&self.deps.storage);
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn(async move {
+            let _ = tx.send(Self::load_saved_sessions(storage).await);
+        });
+        self.usage_rx = Some(rx);
+        self.usage_analytics = self.analytics.snapshot();
+    }
+
+    /// Aggregate the Usage view entries once the saved conversations are loaded
+    fn poll_usage(&mut self) {
+        let Some(rx) = &mut self.usage_rx else {
+            return;
+        };
+        let sessions = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => {
+                tracing::warn!("Failed to load sessions for usage: {}", e);
+                Vec::new()
+            }
+            Ok(result) => result.unwrap_or_else(|e| {
+                tracing::warn!("Failed to load sessions for usage: {}", e);
+                Vec::new()
+            }),
+        };
+        self.usage_rx = None;
+
+        let since = self.usage_since();
+        self.usage_entries = usage::aggregate(&sessions, self.usage_period, Some(since));
+    }
+
+    /// Turn usage analytics on or off now and save it to the user profile
+    fn save_analytics_enabled(&self) {
+        let enabled = self.analytics_enabled;
+        analytics::set_enabled(enabled);
+        self.app_state
+            .update_profile(move |profile| profile.analytics = enabled);
+    }
+
+    /// Delete the collected usage analytics
+    fn clear_analytics(&mut self) {
+        self.usage_analytics = analytics::UsageAnalytics::default();
+        let recorder = self.analytics.clone();
+        let runtime = &self.runtime;
+        self.pending_saves.spawn_on(
+            async move {
+                if let Err(e) = recorder.clear().await {
+                    tracing::warn!("Failed to clear analytics: {}", e);
+                }
+            },
+            runtime.handle(),
+        );
+    }
+
+    /// Every saved conversation, with messages
+    ///
+    /// Reads every session file, so run it off the UI thread.
+    ///
+    /// # Errors
+    /// - Session listing or a session file can't be read
+    async fn load_saved_sessions(
+        storage: Arc<dyn services::StorageService>,
+    ) -> Result<Vec<services::ConversationSession>> {
+        let summaries = storage.list_sessions().await?;
+        let mut sessions = Vec::with_capacity(summaries.len());
+        for summary in summaries {
+            if let Some(session) = storage.load_session(&summary.id).await? {
+                sessions.push(session);
+            }
+        }
+        Ok(sessions)
+    }
+
+    /// Write every rated answer in the saved conversations to
+    /// ~/.rustbot/exports/feedback-<timestamp>.jsonl
+    ///
+    /// The sessions load in the background; `poll_feedback_export` reports
+    /// the outcome in `feedback_export_message`.
+    fn export_feedback(&mut self) {
+        let storage = Arc::clone(&self.deps.storage);
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn(async move {
+            let result = async {
+                let records = feedback::collect(&Self::load_saved_sessions(storage).await?);
+                let dir = dirs::home_dir()
+                    .unwrap_or_default()
+                    .join(".rustbot")
+                    .join("exports");
+                std::fs::create_dir_all(&dir)?;
+                let path = dir.join(format!(
+                    "feedback-{}.jsonl",
+                    chrono::Local::now().format("%Y%m%d-%H%M%S")
+                ));
+                std::fs::write(&path, feedback::to_jsonl(&records)?)?;
+
+                tracing::info!("📤 Exported {} ratings to {:?}", records.len(), path);
+                Ok::<_, anyhow::Error>((records.len(), path))
+            }
+            .await;
+            let _ = tx.send(result);
+        });
+        self.feedback_export_rx = Some(rx);
+    }
+
+    /// Report a finished ratings export
+    fn poll_feedback_export(&mut self) {
+        let Some(rx) = &mut self.feedback_export_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => Err(e.into()),
+            Ok(result) => result,
+        };
+        self.feedback_export_rx = None;
+
+        self.feedback_export_message = Some(match result {
+            Ok((count, path)) => (
+                format!("Exported {} ratings to {}", count, path.display()),
+                false,
+            ),
+            Err(e) => (format!("Export failed: {}", e), true),
+        });
+    }
+
+    /// Write a table from an answer to ~/.rustbot/exports/table-<timestamp>.csv
+    ///
+    /// # Returns
+    /// Path of the new file
+    fn save_table(&self, csv: &str) -> anyhow::Result<PathBuf> {
+        let dir = dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("exports");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!(
+            "table-{}.csv",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        std::fs::write(&path, csv)?;
+
+        tracing::info!("📤 Saved table to {:?}", path);
+        Ok(path)
+    }
+
+    /// Run the A/B test in Settings in the background; finished by
+    /// poll_prompt_test
+    fn start_prompt_test(&mut self, ctx: &egui::Context) {
+        let form = &mut self.prompt_test_form;
+        if form.rx.is_some() {
+            return;
+        }
+        let test = match form.to_test(
+            &self.system_prompts.system_instructions,
+            &self.agent_configs,
+        ) {
+            Ok(test) => test,
+            Err(message) => {
+                form.message = Some((message, true));
+                return;
+            }
+        };
+        let adapter = match self.deps.llm_adapter.as_ref() {
+            Some(adapter) => Arc::clone(adapter),
+            None => {
+                form.message = Some(("No LLM adapter configured".to_string(), true));
+                return;
+            }
+        };
+
+        let done = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        form.progress = Some((Arc::clone(&done), test.prompts.len()));
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let progress = |count: usize| {
+                done.store(count, std::sync::atomic::Ordering::Relaxed);
+                ctx.request_repaint();
+            };
+            let report = prompt_test::run(adapter.as_ref(), &test, &progress).await;
+            let _ = tx.send(report);
+            ctx.request_repaint();
+        });
+        form.rx = Some(rx);
+        form.message = None;
+    }
+
+    /// Show the A/B test report once every prompt has been answered
+    fn poll_prompt_test(&mut self) {
+        let form = &mut self.prompt_test_form;
+        let Some(rx) = &mut form.rx else {
+            return;
+        };
+        let result = rx.try_recv();
+        if matches!(
+            result,
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty)
+        ) {
+            return;
+        }
+        form.rx = None;
+        form.progress = None;
+        match result {
+            Ok(report) => {
+                tracing::info!("🧪 A/B test finished: {} prompts", report.cases.len());
+                if let Some(stopped) = &report.stopped {
+                    form.message = Some((format!("The test stopped early: {}", stopped), true));
+                }
+                form.report = Some(report);
+                form.selected_case = 0;
+            }
+            Err(e) => form.message = Some((format!("The test stopped: {}", e), true)),
+        }
+    }
+
+    /// Write the A/B test report to
+    /// ~/.rustbot/exports/prompt-test-<timestamp>.md
+    ///
+    /// # Returns
+    /// Path of the new file
+    fn save_prompt_test_report(&self) -> anyhow::Result<PathBuf> {
+        let Some(report) = &self.prompt_test_form.report else {
+            anyhow::bail!("No report to save");
+        };
+        let dir = dirs::home_dir()
+            .unwrap_or_default()
+            .join(".rustbot")
+            .join("exports");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!(
+            "prompt-test-{}.md",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        std::fs::write(&path, report.to_markdown())?;
+
+        tracing::info!("📤 Saved A/B test report to {:?}", path);
+        Ok(path)
+    }
+
+    /// Act on a click in rendered markdown: open a diagram or save a table
+    fn handle_markdown_action(&mut self, action: ui::markdown::MarkdownAction) {
+        match action {
+            ui::markdown::MarkdownAction::OpenDiagram(uri) => {
+                self.diagram_viewer = Some(ui::DiagramViewer::new(uri));
+            }
+            ui::markdown::MarkdownAction::SaveTable(csv) => {
+                self.command_feedback = Some(match self.save_table(&csv) {
+                    Ok(path) => (format!("Saved the table to {}", path.display()), false),
+                    Err(e) => (format!("Couldn't save the table: {}", e), true),
+                });
+            }
+        }
+    }
+
+    /// First day shown in the Usage view
+    fn usage_since(&self) -> chrono::NaiveDate {
+        let today = chrono::Local::now().date_naive();
+        match self.usage_period {
+            usage::UsagePeriod::Day => today - chrono::Duration::days(29),
+            usage::UsagePeriod::Week => {
+                usage::UsagePeriod::Week.start(today) - chrono::Duration::weeks(11)
+            }
+        }
+    }
+
+    /// Import a ChatGPT or Claude `conversations.json` into session history
+    ///
+    /// Runs in the background; `poll_history_import` reports the outcome in
+    /// `history_import_message`.
+    fn import_conversations(&mut self, path: PathBuf) {
+        let storage = Arc::clone(&self.deps.storage);
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn(async move {
+            let result = async {
+                let sessions = conversation_import::import_file(&path)?;
+                let count = storage.import_sessions(&sessions).await?;
+
+                tracing::info!(
+                    "📥 Imported {} conversations from {}",
+                    count,
+                    path.display()
+                );
+                Ok::<_, RustbotError>(count)
+            }
+            .await;
+            let _ = tx.send(result);
+        });
+        self.history_import_rx = Some(rx);
+    }
+
+    /// Report a finished conversation import and refresh the History view
+    fn poll_history_import(&mut self) {
+        let Some(rx) = &mut self.history_import_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => {
+                self.history_import_rx = None;
+                self.history_import_message = Some((format!("Import failed: {}", e), true));
+                return;
+            }
+            Ok(result) => result,
+        };
+        self.history_import_rx = None;
+
+        self.history_import_message = Some(match result {
+            Ok(count) => (format!("Imported {} conversations", count), false),
+            Err(e) => (format!("Import failed: {}", e), true),
+        });
+        self.refresh_history();
+    }
+
+    /// Export agents, MCP config, system instructions and preferences to a bundle file
+    ///
+    /// Runs in the background; `poll_settings_bundle` reports the outcome in
+    /// `settings_bundle_message`.
+    fn export_settings_bundle(&mut self, path: PathBuf) {
+        let storage = Arc::clone(&self.deps.storage);
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn(async move {
+            let result = async {
+                let bundle = settings_bundle::SettingsBundle::collect(
+                    &settings_bundle::SettingsPaths::standard(),
+                    storage.as_ref(),
+                )
+                .await?;
+                bundle.save(&path)?;
+
+                tracing::info!(
+                    "📤 Exported settings ({} agents) to {}",
+                    bundle.agents.len(),
+                    path.display()
+                );
+                Ok::<_, RustbotError>(())
+            }
+            .await;
+            let _ = tx.send((path, result));
+        });
+        self.settings_export_rx = Some(rx);
+    }
+
+    /// Apply a settings bundle and reload agents so the changes take effect
+    ///
+    /// The bundle is read and written in the background; `poll_settings_bundle`
+    /// reloads the app state and reports the outcome.
+    fn import_settings_bundle(&mut self, path: PathBuf) {
+        let storage = Arc::clone(&self.deps.storage);
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn(async move {
+            let result = async {
+                let bundle = settings_bundle::SettingsBundle::load(&path)?;
+                let summary = bundle
+                    .apply(
+                        &settings_bundle::SettingsPaths::standard(),
+                        storage.as_ref(),
+                    )
+                    .await?;
+                Ok::<_, RustbotError>((bundle, summary))
+            }
+            .await;
+            let _ = tx.send((path, result));
+        });
+        self.settings_import_rx = Some(rx);
+    }
+
+    /// Report a finished settings bundle export or import
+    ///
+    /// After an import, the theme, system instructions and agents are
+    /// reloaded so the changes take effect.
+    fn poll_settings_bundle(&mut self) {
+        if let Some(rx) = &mut self.settings_export_rx {
+            match rx.try_recv() {
+                Err(tokio::sync::oneshot::error::TryRecvError::Empty) => {}
+                Err(e) => {
+                    self.settings_export_rx = None;
+                    self.settings_bundle_message = Some((format!("Export failed: {}", e), true));
+                }
+                Ok((path, result)) => {
+                    self.settings_export_rx = None;
+                    self.settings_bundle_message = Some(match result {
+                        Ok(()) => (format!("Saved to {}", path.display()), false),
+                        Err(e) => (format!("Export failed: {}", e), true),
+                    });
+                }
+            }
+        }
+
+        let Some(rx) = &mut self.settings_import_rx else {
+            return;
+        };
+        let (path, result) = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => {
+                self.settings_import_rx = None;
+                self.settings_bundle_message = Some((format!("Import failed: {}", e), true));
+                return;
+            }
+            Ok(received) => received,
+        };
+        self.settings_import_rx = None;
+
+        let (bundle, summary) = match result {
+            Ok(imported) => imported,
+            Err(e) => {
+                self.settings_bundle_message = Some((format!("Import failed: {}", e), true));
+                return;
+            }
+        };
+        if let Some(preferences) = &bundle.preferences {
+            self.theme = preferences.theme.clone();
+        }
+        if summary.system_instructions {
+            self.system_prompts = Self::load_system_prompts().unwrap_or_default();
+            self.refresh_instruction_profiles();
+        }
+        self.reload_config();
+        audit::record(
+            audit::AuditAction::ConfigChanged,
+            format!("Settings imported from {}", path.display()),
+        );
+        self.settings_bundle_message = Some((summary.describe(), false));
+    }
+
+    /// Take a backup now in the background
+    ///
+    /// `poll_backup` reports the outcome and refreshes the list in
+    /// Settings > Backups.
+    fn create_backup(&mut self) {
+        let manager = Arc::clone(&self.backup_manager);
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn(async move {
+            let result = tokio::task::spawn_blocking(move || manager.create("manual"))
+                .await
+                .unwrap_or_else(|e| Err(RustbotError::StorageError(e.to_string())));
+            let _ = tx.send((None, result));
+        });
+        self.backup_rx = Some(rx);
+    }
+
+    /// Restore a backup in the background
+    ///
+    /// Saves still queued are written first, and the session writer is
+    /// stopped for good: later saves (including the one on exit) would
+    /// overwrite the restored conversations, which the open chat and history
+    /// only pick up after a restart. `poll_backup` reloads the settings.
+    fn restore_backup(&mut self, id: &str) {
+        let writer = self.writer.take();
+        let manager = Arc::clone(&self.backup_manager);
+        let id = id.to_string();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn(async move {
+            if let Some(writer) = writer {
+                writer.flush().await;
+            }
+            let restore_id = id.clone();
+            let result = tokio::task::spawn_blocking(move || manager.restore(&restore_id))
+                .await
+                .unwrap_or_else(|e| Err(RustbotError::StorageError(e.to_string())));
+            let _ = tx.send((Some(id), result));
+        });
+        self.backup_rx = Some(rx);
+    }
+
+    /// Report a finished backup or restore
+    ///
+    /// After a restore, system instructions and agents are reloaded. A failed
+    /// restore starts a new session writer so changes are saved again.
+    fn poll_backup(&mut self) {
+        let Some(rx) = &mut self.backup_rx else {
+            return;
+        };
+        let (restore_id, result) = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => (None, Err(RustbotError::StorageError(e.to_string()))),
+            Ok(received) => received,
+        };
+        self.backup_rx = None;
+
+        self.backup_message = Some(match (restore_id, result) {
+            (None, Ok(info)) => (format!("Created backup {}", info.id), false),
+            (None, Err(e)) => (format!("Backup failed: {}", e), true),
+            (Some(id), Ok(_)) => {
+                self.system_prompts = Self::load_system_prompts().unwrap_or_default();
+                self.refresh_instruction_profiles();
+                self.reload_config();
+                audit::record(
+                    audit::AuditAction::ConfigChanged,
+                    format!("Backup {} restored", id),
+                );
+                (
+                    format!(
+                        "Restored backup {}. Restart Rustbot to reload conversations; \
+                         changes made until then aren't saved.",
+                        id
+                    ),
+                    false,
+                )
+            }
+            (Some(_), Err(e)) => {
+                if self.writer.is_none() {
+                    self.writer = Some(services::WriteBehind::spawn(
+                        Arc::clone(&self.deps.storage),
+                        services::write_behind::WRITE_BEHIND_DELAY,
+                        self.runtime.handle(),
+                    ));
+                }
+                (format!("Restore failed: {}", e), true)
+            }
+        });
+        self.backups = self.backup_manager.list().unwrap_or_default();
+    }
+
+    /// Apply the limits in Settings > Preferences > Daily Limits and save them
+    /// to the user profile
+    fn save_quota(&mut self) {
+        let limits = match self.quota_form.to_quota(&quota::tracker().limits()) {
+            Ok(limits) => limits,
+            Err(message) => {
+                self.quota_form.message = Some((message, true));
+                return;
+            }
+        };
+        quota::configure(&limits);
+        audit::record(
+            audit::AuditAction::ConfigChanged,
+            format!(
+                "Daily limits: {} messages, {} spend{}",
+                limits
+                    .messages_per_day
+                    .map_or("unlimited".to_string(), |n| n.to_string()),
+                limits
+                    .cost_per_day
+                    .map_or("unlimited".to_string(), |c| format!("${:.2}", c)),
+                if limits.is_locked() { " (locked)" } else { "" }
+            ),
+        );
+        self.quota_form = ui::QuotaForm::from_quota(&limits);
+        self.quota_form.message = Some(("Daily limits saved".to_string(), false));
+        self.app_state
+            .update_profile(move |profile| profile.quota = limits);
+    }
+
+    /// Read the audit log and check its chain for Settings > Audit Log
+    fn refresh_audit_log(&mut self) {
+        let Some(log) = audit::global() else {
+            self.audit_entries.clear();
+            self.audit_status = Some(Err("The audit log couldn't be opened".to_string()));
+            return;
+        };
+        self.audit_entries = log.entries().unwrap_or_default();
+        self.audit_status = Some(log.verify().map_err(|e| e.to_string()));
+    }
+
+    /// Reload every script from disk and refresh Settings > Scripts
+    fn reload_scripts(&mut self) {
+        self.script_message = Some(match self.script_host.reload() {
+            Ok(()) => (
+                format!("Reloaded {} scripts", self.script_host.list().len()),
+                false,
+            ),
+            Err(e) => (format!("Failed to reload scripts: {}", e), true),
+        });
+        self.scripts = self.script_host.list();
+    }
+
+    /// Reload every WASM tool from disk and refresh Settings > WASM Tools
+    fn reload_wasm_tools(&mut self) {
+        let Some(host) = &self.wasm_tools else {
+            return;
+        };
+        self.wasm_tool_message = Some(match host.reload() {
+            Ok(()) => (format!("Reloaded {} tools", host.list().len()), false),
+            Err(e) => (format!("Failed to reload tools: {}", e), true),
+        });
+        self.wasm_tool_list = host.list();
+    }
+
+    /// Enable or disable a native plugin (persisted in plugins.json)
+    ///
+    /// Enabling loads the library right away.
+    fn set_native_plugin_enabled(&mut self, name: &str, enabled: bool) {
+        match self.native_plugins.set_enabled(name, enabled) {
+            Ok(()) => audit::record(
+                audit::AuditAction::ConfigChanged,
+                format!(
+                    "Native plugin '{}' {}",
+                    name,
+                    if enabled { "enabled" } else { "disabled" }
+                ),
+            ),
+            Err(e) => {
+                self.native_plugin_message =
+                    Some((format!("Failed to update '{}': {}", name, e), true));
+            }
+        }
+        self.native_plugin_list = self.native_plugins.list();
+    }
+
+    /// Save the settings edited in a native plugin's panel
+    fn save_native_plugin_settings(&mut self, index: usize) {
+        let Some(plugin) = self.native_plugin_list.get(index) else {
+            return;
+        };
+        self.native_plugin_message = Some(
+            match self
+                .native_plugins
+                .save_settings(&plugin.name, plugin.values.clone())
+            {
+                Ok(()) => (format!("Saved settings for {}", plugin.name), false),
+                Err(e) => (format!("Failed to save settings: {}", e), true),
+            },
+        );
+    }
+
+    /// Enable or disable a script (persisted in scripts.json)
+    fn set_script_enabled(&mut self, name: &str, enabled: bool) {
+        match self.script_host.set_enabled(name, enabled) {
+            Ok(()) => audit::record(
+                audit::AuditAction::ConfigChanged,
+                format!(
+                    "Script '{}' {}",
+                    name,
+                    if enabled { "enabled" } else { "disabled" }
+                ),
+            ),
+            Err(e) => {
+                self.script_message = Some((format!("Failed to update '{}': {}", name, e), true));
+            }
+        }
+        self.scripts = self.script_host.list();
+    }
+
+    /// Replace the current chat with a saved session
+    ///
+    /// The session loads in the background; `poll_restore_session` swaps it
+    /// in and makes it the one reopened on next startup.
+    fn restore_session(&mut self, id: &str) {
+        let storage = Arc::clone(&self.deps.storage);
+        let id = id.to_string();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn(async move {
+            let result = storage.load_session(&id).await;
+            let _ = tx.send((id, result));
+        });
+        self.restore_rx = Some(rx);
+    }
+
+    /// Swap in the session loaded by `restore_session` once it arrives
+    fn poll_restore_session(&mut self) {
+        let Some(rx) = &mut self.restore_rx else {
+            return;
+        };
+        let result = rx.try_recv();
+        if matches!(
+            result,
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty)
+        ) {
+            return;
+        }
+        self.restore_rx = None;
+
+        let session = match result {
+            Ok((_, Ok(Some(session)))) => session,
+            Ok((id, Ok(None))) => {
+                tracing::warn!("Session '{}' no longer exists", id);
+                return;
+            }
+            Ok((id, Err(e))) => {
+                tracing::warn!("Failed to load session '{}': {}", id, e);
+                return;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load session: {}", e);
+                return;
+            }
+        };
+        let runtime = Arc::clone(&self.runtime);
+
+        tracing::info!(
+            "📂 Restoring session '{}' ({} messages)",
+            session.id,
+            session.messages.len()
+        );
+
+        // The API takes the history in the background, after any turn in
+        // progress
+        let api = Arc::clone(&self.api);
+        let restored = session.clone();
+        let api_session = self.api_session.clone();
+        runtime.spawn(async move {
+            let mut api_guard = api.lock().await;
+            if let Err(e) = api_guard.switch_agent_in(&api_session, &restored.agent_id) {
+                tracing::warn!("Restored session agent unavailable: {}", e);
+            }
+            if let Err(e) = api_guard.restore_history_in(&api_session, restored.history.clone()) {
+                tracing::warn!("Failed to restore history: {}", e);
+            }
+        });
+        // Saving marks it as the session reopened on next startup
+        if let Some(queue) = self.write_queue() {
+            queue.save_session(session.clone());
+        }
+
+        self.messages = session
+            .messages
+            .iter()
+            .map(Self::chat_message_from_session)
+            .collect();
+        self.session = session;
+        self.current_response.clear();
+        self.editing_message = None;
+        self.feedback_comment = None;
+        self.retry = ui::RetryState::default();
+        self.pending_diagrams.clear();
+        self.regenerate_open = false;
+        self.message_heights.clear();
+        self.current_view = AppView::Chat;
+        self.update_context_tracker();
+    }
+
+    /// Convert a persisted session message back into a chat view message
+    fn chat_message_from_session(message: &services::SessionMessage) -> ChatMessage {
+        let role = if message.role == "user" {
+            MessageRole::User
+        } else {
+            MessageRole::Assistant
+        };
+
+        ChatMessage {
+            role,
+            content: message.content.clone(),
+            input_tokens: message.input_tokens,
+            output_tokens: message.output_tokens,
+            embedded_images: Self::extract_image_data_urls(&message.content),
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+            timestamp: Some(message.timestamp),
+            first_token_ms: message.first_token_ms,
+            generation_ms: message.generation_ms,
+            pinned: message.pinned,
+            feedback: message.feedback.clone(),
+            failed: false,
+        }
+    }
+
+    /// Sync the session with the chat view and return a copy to persist
+    ///
+    /// Messages already in the session keep their original timestamps and
+    /// agent; new ones are attributed to the tab's current agent and model.
+    fn snapshot_session(&mut self) -> services::ConversationSession {
+        let now = chrono::Utc::now();
+        let previous = std::mem::take(&mut self.session.messages);
+        // A conversation belongs to the project it was started in
+        if previous.is_empty() && self.session.project.is_none() {
+            self.session.project = self.active_project.clone();
+        }
+        let agent_id = self.session.agent_id.clone();
+        let model = self
+            .agent_configs
+            .iter()
+            .find(|c| c.id == agent_id)
+            .map(|c| c.model.clone());
+
+        self.session.messages = self
+            .messages
+            .iter()
+            .enumerate()
+            .map(|(i, msg)| services::SessionMessage {
+                role: match msg.role {
+                    MessageRole::User => "user".to_string(),
+                    MessageRole::Assistant => "assistant".to_string(),
+                },
+                content: msg.content.clone(),
+                timestamp: msg
+                    .timestamp
+                    .or(previous.get(i).map(|m| m.timestamp))
+                    .unwrap_or(now),
+                input_tokens: msg.input_tokens,
+                output_tokens: msg.output_tokens,
+                agent_id: match previous.get(i) {
+                    Some(m) => m.agent_id.clone(),
+                    None => Some(agent_id.clone()),
+                },
+                model: match previous.get(i) {
+                    Some(m) => m.model.clone(),
+                    None => model.clone(),
+                },
+                first_token_ms: msg.first_token_ms,
+                generation_ms: msg.generation_ms,
+                pinned: msg.pinned,
+                feedback: msg.feedback.clone(),
+            })
+            .collect();
+        self.session.updated_at = now;
+        self.session.update_title();
+
+        self.session.clone()
+    }
+
+    /// Save the visible tab's session in the background, with the LLM
+    /// history from the API (for changes outside a turn, such as pins)
+    fn save_session(&mut self) {
+        let Some(queue) = self.write_queue() else {
+            return;
+        };
+        let mut session = self.snapshot_session();
+        let api = Arc::clone(&self.api);
+        let api_session = self.api_session.clone();
+        let runtime = &self.runtime;
+        self.pending_saves.spawn_on(
+            async move {
+                if let Some(api_session) = api.lock().await.session(&api_session) {
+                    session.history = api_session.history();
+                }
+                queue.save_session(session);
+            },
+            runtime.handle(),
+        );
+    }
+
+    /// Pin or unpin a message of the visible tab and save the change
+    fn toggle_pin(&mut self, index: usize) {
+        let Some(message) = self.messages.get_mut(index) else {
+            return;
+        };
+        message.pinned = !message.pinned;
+        tracing::info!(
+            "📌 Message {} {}",
+            index,
+            if message.pinned { "pinned" } else { "unpinned" }
+        );
+        self.save_session();
+    }
+
+    /// Rate an answer in the visible tab and offer to add a comment
+    ///
+    /// Choosing the rating the answer already has removes it.
+    fn rate_message(&mut self, index: usize, rating: services::Rating) {
+        let Some(message) = self.messages.get_mut(index) else {
+            return;
+        };
+        match &mut message.feedback {
+            Some(feedback) if feedback.rating == rating => {
+                message.feedback = None;
+                self.feedback_comment = None;
+            }
+            Some(feedback) => {
+                feedback.rating = rating;
+                feedback.rated_at = chrono::Utc::now();
+                self.feedback_comment = Some((index, feedback.comment.clone()));
+            }
+            None => {
+                message.feedback = Some(services::MessageFeedback::new(rating));
+                self.feedback_comment = Some((index, String::new()));
+            }
+        }
+        tracing::info!("👍 Message {} rated {:?}", index, rating);
+        self.save_session();
+    }
+
+    /// Save the comment being written for a rated answer
+    fn save_feedback_comment(&mut self) {
+        let Some((index, comment)) = self.feedback_comment.take() else {
+            return;
+        };
+        if let Some(feedback) = self
+            .messages
+            .get_mut(index)
+            .and_then(|m| m.feedback.as_mut())
+        {
+            feedback.comment = comment.trim().to_string();
+            feedback.rated_at = chrono::Utc::now();
+            self.save_session();
+        }
+    }
+
+    /// Pinned messages to send with the next request (none unless the tab
+    /// keeps them in context)
+    fn pinned_context(&self) -> Vec<String> {
+        if !self.session.keep_pinned_in_context {
+            return Vec::new();
+        }
+        self.messages
+            .iter()
+            .filter(|m| m.pinned && !m.content.is_empty())
+            .map(|m| m.content.clone())
+            .collect()
+    }
+
+    /// Extract all base64 image data URLs from markdown content
+    ///
+    /// This helper function finds all embedded images in the format:
+    /// ![alt](data:image/svg+xml;base64,...)
+    ///
+    /// # Arguments
+    /// * `markdown` - The markdown content to search
+    ///
+    /// # Returns
+    /// Vector of base64-encoded image data URLs
+    fn extract_image_data_urls(markdown: &str) -> Vec<String> {
+        use regex::Regex;
+        let mut images = Vec::new();
+
+        // Match data URL images: ![...](data:image/...;base64,...)
+        let pattern = Regex::new(r#"!\[([^\]]*)\]\((data:image/[^;]+;base64,[A-Za-z0-9+/=]+)\)"#)
+            .expect("Invalid regex pattern");
+
+        for cap in pattern.captures_iter(markdown) {
+            // Typeset formulas (alt text "$tex$", see math::alt_text) aren't diagrams
+            if cap[1].starts_with('$') {
+                continue;
+            }
+            if let Some(data_url) = cap.get(2) {
+                images.push(data_url.as_str().to_string());
+            }
+        }
+
+        images
+    }
+
+    /// Whether markdown has diagrams or formulas for `render_diagrams`
+    fn has_diagrams(markdown: &str) -> bool {
+        !graphviz::extract_dot_blocks(markdown).is_empty()
+            || !mermaid::extract_mermaid_blocks(markdown).is_empty()
+            || !math::extract_math(markdown).is_empty()
+    }
+
+    /// Render the DOT and mermaid blocks and TeX formulas of some markdown on
+    /// a background task
+    ///
+    /// The source shows until the result arrives; a diagram or formula that
+    /// fails to render stays as it was. The UI is repainted when the task is
+    /// done.
+    ///
+    /// # Arguments
+    /// * `ctx` - Repainted when the diagrams are ready
+    /// * `markdown` - Markdown with diagrams or formulas
+    /// * `theme` - Diagram colors, matching the UI's dark or light mode
+    ///
+    /// # Returns
+    /// Receives the markdown with diagrams and formulas embedded as SVG
+    /// images, or None if there was nothing to render
+    fn render_diagrams(
+        &self,
+        ctx: &egui::Context,
+        markdown: &str,
+        theme: mermaid::DiagramTheme,
+    ) -> tokio::sync::oneshot::Receiver<Option<String>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let renderer = Arc::clone(&self.mermaid_renderer);
+        let math_renderer = Arc::clone(&self.math_renderer);
+        let markdown = markdown.to_string();
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let mut markdown = markdown;
+            let mut changed = false;
+
+            // DOT layout is CPU work, kept off the async workers
+            let source = markdown.clone();
+            let graphs =
+                tokio::task::spawn_blocking(move || graphviz::embed_graphs(&source, theme));
+            if let Ok(Some(embedded)) = graphs.await {
+                markdown = embedded;
+                changed = true;
+            }
+            if let Some(embedded) = mermaid::embed_diagrams(&renderer, &markdown, theme).await {
+                markdown = embedded;
+                changed = true;
+            }
+            if let Some(embedded) = math::embed_math(&math_renderer, &markdown, theme).await {
+                markdown = embedded;
+                changed = true;
+            }
+
+            let _ = tx.send(changed.then_some(markdown));
+            ctx.request_repaint();
+        });
+        rx
+    }
+
+    /// Put diagrams that finished rendering into the visible tab's answers
+    /// and save the session with them
+    fn poll_diagrams(&mut self) {
+        use tokio::sync::oneshot::error::TryRecvError;
+
+        let mut changed = false;
+        let messages = &mut self.messages;
+        self.pending_diagrams.retain_mut(|pending| {
+            let content = match pending.rx.try_recv() {
+                Err(TryRecvError::Empty) => return true,
+                Ok(Some(content)) => content,
+                Ok(None) | Err(TryRecvError::Closed) => return false,
+            };
+            // Edited, regenerated or cleared while rendering
+            if let Some(message) = messages
+                .get_mut(pending.index)
+                .filter(|m| m.content == pending.source)
+            {
+                message.embedded_images = Self::extract_image_data_urls(&content);
+                message.content = content;
+                changed = true;
+            }
+            false
+        });
+
+        if changed {
+            self.save_session();
+        }
+    }
+
+    fn send_message(&mut self, _ctx: &egui::Context) {
+        if self.message_input.trim().is_empty() && self.pending_images.is_empty() {
+            return;
+        }
+
+        if let Some(command) = ui::SlashCommand::parse(&self.message_input) {
+            // Commands act on the conversation as it is, so not mid-answer
+            if self.is_waiting {
+                return;
+            }
+            self.message_input.clear();
+            self.run_slash_command(command);
+            return;
+        }
+        self.command_feedback = None;
+
+        let message = std::mem::take(&mut self.message_input);
+        let images = std::mem::take(&mut self.pending_images);
+
+        // Hold the message, behind any already waiting, while an answer is
+        // coming or due for a retry, the provider is rate limiting, or
+        // Rustbot is offline with no local model
+        if self.is_waiting
+            || self.retry.scheduled.is_some()
+            || self.rate_limited()
+            || !connectivity::can_chat()
+            || !self.outbox.is_empty()
+        {
+            self.outbox.push(ui::QueuedMessage {
+                text: message,
+                images,
+            });
+            return;
+        }
+
+        self.retry = ui::RetryState::default();
+        self.submit_message(message, images, None);
+    }
+
+    /// Run a slash command typed in the chat input
+    ///
+    /// Agent and model changes apply to the visible tab's API session;
+    /// the outcome is shown under the input box.
+    fn run_slash_command(&mut self, command: ui::SlashCommand) {
+        tracing::info!("⌨️  Slash command: {:?}", command);
+        let api = Arc::clone(&self.api);
+        let api_session = self.api_session.clone();
+        let runtime = Arc::clone(&self.runtime);
+
+        self.command_feedback = match command {
+            ui::SlashCommand::Clear => {
+                self.clear_conversation();
+                None
+            }
+            ui::SlashCommand::Export => {
+                self.open_conversation_export_dialog();
+                None
+            }
+            ui::SlashCommand::Share => {
+                self.open_share_dialog();
+                None
+            }
+            ui::SlashCommand::Context => {
+                self.open_context_inspector();
+                None
+            }
+            ui::SlashCommand::Commit => self.draft_commit_message(),
+            ui::SlashCommand::Help => Some((ui::SlashCommand::help(), false)),
+            ui::SlashCommand::Prompt(name) => {
+                if self.use_prompt(&name) {
+                    None
+                } else {
+                    Some((format!("Unknown prompt '{}'", name), true))
+                }
+            }
+            ui::SlashCommand::Agent(id) => {
+                match self.agent_configs.iter().find(|c| c.id == id && c.enabled) {
+                    Some(config) => {
+                        let feedback = format!("Switched to {}", config.name);
+                        self.session.agent_id = id.clone();
+                        runtime.spawn(async move {
+                            let mut api_guard = api.lock().await;
+                            if let Err(e) = api_guard.switch_agent_in(&api_session, &id) {
+                                tracing::warn!("Failed to switch agent: {}", e);
+                            }
+                        });
+                        Some((feedback, false))
+                    }
+                    None => Some((format!("Unknown agent '{}'", id), true)),
+                }
+            }
+            ui::SlashCommand::Model(model) => {
+                let feedback = match &model {
+                    Some(model) => format!("Using {} until the configuration is reloaded", model),
+                    None => "Using the default model".to_string(),
+                };
+                runtime.spawn(async move {
+                    let mut api_guard = api.lock().await;
+                    let Some(agent_id) = api_guard
+                        .session(&api_session)
+                        .map(|s| s.agent_id().to_string())
+                    else {
+                        return;
+                    };
+                    if let Err(e) = api_guard.set_agent_model(&agent_id, model) {
+                        tracing::warn!("Failed to change model: {}", e);
+                    }
+                });
+                Some((feedback, false))
+            }
+        };
+    }
+
+    /// Attach the clipboard image (if there is one) to the next message
+    fn paste_clipboard_image(&mut self, ctx: &egui::Context) {
+        match ui::attachments::paste_image(ctx) {
+            Ok(Some(image)) => self.pending_images.push(image),
+            Ok(None) => tracing::debug!("📋 No image on the clipboard"),
+            Err(e) => tracing::warn!("⚠️  Couldn't read the clipboard image: {}", e),
+        }
+    }
+
+    /// Read the staged changes of the project's repository in the
+    /// background; `poll_commit_draft` puts a request for a commit message
+    /// with them into the chat input
+    ///
+    /// # Returns
+    /// Feedback for the command line
+    fn draft_commit_message(&mut self) -> Option<(String, bool)> {
+        let Some(git) = self.git_tools.clone() else {
+            return Some((
+                "/commit needs an active project whose folder is a git repository".to_string(),
+                true,
+            ));
+        };
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn(async move {
+            let result = match git.staged_changes().await {
+                Ok((stat, _)) if stat.trim().is_empty() => Err(format!(
+                    "Nothing is staged in {}",
+                    git.repository().display()
+                )),
+                Ok((stat, diff)) => Ok(git_tools::commit_message_prompt(&stat, &diff)),
+                Err(e) => Err(e.to_string()),
+            };
+            let _ = tx.send(result);
+        });
+        self.commit_draft_rx = Some(rx);
+        Some(("Reading the staged changes…".to_string(), false))
+    }
+
+    /// Put the commit message request into the chat input once the staged
+    /// changes have been read
+    fn poll_commit_draft(&mut self) {
+        let Some(rx) = &mut self.commit_draft_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => Err(format!("Couldn't read the staged changes: {}", e)),
+            Ok(result) => result,
+        };
+        self.commit_draft_rx = None;
+        self.command_feedback = match result {
+            Ok(prompt) => {
+                self.insert_prompt_text(prompt);
+                None
+            }
+            Err(message) => Some((message, true)),
+        };
+    }
+
+    /// Put a library prompt into the chat input
+    ///
+    /// Prompts with variables open the form above the input first.
+    ///
+    /// # Returns
+    /// false if there is no prompt with that name
+    fn use_prompt(&mut self, name: &str) -> bool {
+        let Some(template) = self.prompt_library.get(name).cloned() else {
+            return false;
+        };
+        self.prompt_picker_open = false;
+        let form = ui::PromptForm::new(template);
+        if form.values.is_empty() {
+            self.insert_prompt_text(form.text());
+        } else {
+            self.prompt_form = Some(form);
+        }
+        true
+    }
+
+    /// Insert filled-in prompt text, after any text already typed
+    fn insert_prompt_text(&mut self, text: String) {
+        if self.message_input.trim().is_empty() {
+            self.message_input = text;
+        } else {
+            self.message_input = format!("{}\n\n{}", self.message_input.trim_end(), text);
+        }
+    }
+
+    /// Save Settings > Prompts to ~/.rustbot/prompts.json
+    fn save_prompt_library(&mut self) {
+        if let Err(e) = self.prompt_library.validate() {
+            self.prompt_message = Some((e, true));
+            return;
+        }
+        self.prompt_message = Some(
+            match self
+                .prompt_library
+                .save(&prompt_library::PromptLibrary::default_path())
+            {
+                Ok(()) => ("Prompts saved".to_string(), false),
+                Err(e) => (format!("Failed to save prompts: {}", e), true),
+            },
+        );
+    }
+
+    /// The project the user is working in, if any
+    fn current_project(&self) -> Option<&projects::Project> {
+        let id = self.active_project.as_ref()?;
+        self.projects.iter().find(|p| p.id == *id)
+    }
+
+    /// Switch to a project, or to no project with None
+    ///
+    /// The visible tab starts a new conversation with the project's first
+    /// preferred agent, and History only lists the project's conversations.
+    /// Other tabs keep their conversations.
+    fn switch_project(&mut self, id: Option<String>) {
+        if self.is_waiting || id == self.active_project {
+            return;
+        }
+        tracing::info!(
+            "📁 Switching to project {}",
+            id.as_deref().unwrap_or("(none)")
+        );
+        self.active_project = id.clone();
+        self.app_state
+            .update_profile(move |profile| profile.active_project = id);
+        self.clear_conversation();
+
+        let agent = self
+            .current_project()
+            .and_then(|project| project.default_agent())
+            .filter(|agent| {
+                self.agent_configs
+                    .iter()
+                    .any(|c| c.id == *agent && c.enabled)
+            })
+            .map(str::to_string);
+        if let Some(agent) = agent {
+            self.session.agent_id = agent.clone();
+            let api = Arc::clone(&self.api);
+            let api_session = self.api_session.clone();
+            self.runtime.spawn(async move {
+                let mut api_guard = api.lock().await;
+                if let Err(e) = api_guard.switch_agent_in(&api_session, &agent) {
+                    tracing::warn!("Failed to switch to the project's agent: {}", e);
+                }
+            });
+        }
+
+        self.apply_git_tools();
+        self.refresh_history();
+        self.update_context_tracker();
+    }
+
+    /// Offer the git tools for the active project's folder if it is a git
+    /// repository, in the app and the API
+    fn apply_git_tools(&mut self) {
+        let git = self.current_project().and_then(|project| {
+            let folder = project
+                .folder
+                .as_ref()
+                .filter(|f| git_tools::is_repository(f))?;
+            Some(Arc::new(git_tools::GitTools::new(
+                folder.clone(),
+                project.allow_git_commits,
+                Arc::clone(&self.deps.event_bus),
+            )))
+        });
+        if let Some(git) = &git {
+            tracing::info!("🌿 Git tools on for {}", git.repository().display());
+        }
+        self.git_tools = git.clone();
+
+        // The API gets them in the background, after any turn in progress
+        let api = Arc::clone(&self.api);
+        self.runtime
+            .spawn(async move { api.lock().await.set_git_tools(git) });
+    }
+
+    /// Create a project named after Settings > Projects' name field
+    fn create_project(&mut self) {
+        let store = projects::ProjectStore::new(projects::ProjectStore::default_dir());
+        self.project_message = Some(match store.create(&self.new_project_name) {
+            Ok(project) => {
+                let message = format!("Created project {}", project.name);
+                self.projects.push(project);
+                self.projects.sort_by_key(|p| p.name.to_lowercase());
+                self.new_project_name.clear();
+                (message, false)
+            }
+            Err(e) => (e.to_string(), true),
+        });
+    }
+
+    /// Save the edits to a project in Settings > Projects
+    fn save_project(&mut self, index: usize) {
+        let Some(project) = self.projects.get(index) else {
+            return;
+        };
+        if project.name.trim().is_empty() {
+            self.project_message = Some(("Project name can't be empty".to_string(), true));
+            return;
+        }
+        let store = projects::ProjectStore::new(projects::ProjectStore::default_dir());
+        self.project_message = Some(match store.save(project) {
+            Ok(()) => (format!("Saved project {}", project.name), false),
+            Err(e) => (format!("Failed to save project: {}", e), true),
+        });
+        if Some(&project.id) == self.active_project.as_ref() {
+            self.apply_git_tools();
+            self.update_context_tracker();
+        }
+    }
+
+    /// Delete a project; its conversations are kept
+    fn delete_project(&mut self, index: usize) {
+        if index >= self.projects.len() {
+            return;
+        }
+        let id = self.projects[index].id.clone();
+        let store = projects::ProjectStore::new(projects::ProjectStore::default_dir());
+        if let Err(e) = store.delete(&id) {
+            self.project_message = Some((format!("Failed to delete project: {}", e), true));
+            return;
+        }
+        let project = self.projects.remove(index);
+        // The current chat stays open; it just no longer belongs to a project
+        if self.active_project.as_ref() == Some(&id) {
+            self.active_project = None;
+            self.app_state
+                .update_profile(|profile| profile.active_project = None);
+            self.apply_git_tools();
+            self.refresh_history();
+            self.update_context_tracker();
+        }
+        self.project_message = Some((format!("Deleted project {}", project.name), false));
+    }
+
+    /// Open or close the Chat with folder panel
+    ///
+    /// Opening it starts on the active project's folder if no folder is set,
+    /// and loads that folder's saved index.
+    fn toggle_folder_panel(&mut self) {
+        self.folder_panel_open = !self.folder_panel_open;
+        if !self.folder_panel_open || self.folder_index.is_some() {
+            return;
+        }
+        if self.folder_chat_path.trim().is_empty() {
+            if let Some(folder) = self.current_project().and_then(|p| p.folder.clone()) {
+                self.folder_chat_path = folder.display().to_string();
+            }
+        }
+        self.load_folder_index();
+    }
+
+    /// Where the index of a folder is kept: the active project's index
+    /// directory for the project folder, otherwise the default location
+    fn folder_index_path(&self, root: &std::path::Path) -> PathBuf {
+        match self.current_project() {
+            Some(project) if project.folder.as_deref() == Some(root) => {
+                projects::ProjectStore::new(projects::ProjectStore::default_dir())
+                    .index_dir(&project.id)
+                    .join("folder.json")
+            }
+            _ => folder_index::FolderIndex::default_path(root),
+        }
+    }
+
+    /// Load the saved index of the Chat with folder path, if it was built before
+    ///
+    /// Runs in the background; `poll_folder_index` takes the index.
+    fn load_folder_index(&mut self) {
+        let root = PathBuf::from(self.folder_chat_path.trim());
+        if root.as_os_str().is_empty() {
+            return;
+        }
+        let path = self.folder_index_path(&root);
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.runtime.spawn_blocking(move || {
+            let _ = tx.send((root, folder_index::FolderIndex::load(&path)));
+        });
+        self.folder_index_load_rx = Some(rx);
+    }
+
+    /// Build the index of the Chat with folder path in the background, or
+    /// refresh it (only changed files are read again)
+    fn build_folder_index(&mut self, ctx: &egui::Context) {
+        if self.folder_index_rx.is_some() {
+            return;
+        }
+        let root = PathBuf::from(self.folder_chat_path.trim());
+        if !root.is_dir() {
+            self.folder_index_message = Some((format!("{} is not a folder", root.display()), true));
+            return;
+        }
+        let path = self.folder_index_path(&root);
+        let previous = self.folder_index.clone().filter(|index| index.root == root);
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let ctx = ctx.clone();
+        self.runtime.spawn_blocking(move || {
+            // A saved index that can't be read is rebuilt from scratch
+            let previous = previous.or_else(|| {
+                folder_index::FolderIndex::load(&path)
+                    .ok()
+                    .flatten()
+                    .map(Arc::new)
+            });
+            let result = folder_index::FolderIndex::build(&root, previous.as_deref())
+                .and_then(|index| index.save(&path).map(|()| index));
+            let _ = tx.send(result);
+            ctx.request_repaint();
+        });
+        self.folder_index_rx = Some(rx);
+        self.folder_index_message = Some(("Indexing…".to_string(), false));
+    }
+
+    /// Take the folder index once loading or building finishes
+    fn poll_folder_index(&mut self) {
+        self.poll_folder_index_load();
+
+        let Some(rx) = &mut self.folder_index_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => Err(RustbotError::StorageError(e.to_string())),
+            Ok(result) => result,
+        };
+        self.folder_index_rx = None;
+        self.folder_index_message = Some(match result {
+            Ok(index) => {
+                tracing::info!(
+                    "📚 Indexed {} files in {}",
+                    index.files.len(),
+                    index.root.display()
+                );
+                let message = format!(
+                    "Indexed {} files ({} excerpts)",
+                    index.files.len(),
+                    index.chunks.len()
+                );
+                self.folder_index = Some(Arc::new(index));
+                self.folder_chat_enabled = true;
+                (message, false)
+            }
+            Err(e) => (format!("Indexing failed: {}", e), true),
+        });
+    }
+
+    /// Take a loaded saved index, unless the folder changed or an index was
+    /// built meanwhile
+    fn poll_folder_index_load(&mut self) {
+        let Some(rx) = &mut self.folder_index_load_rx else {
+            return;
+        };
+        let (root, result) = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => (
+                PathBuf::from(self.folder_chat_path.trim()),
+                Err(RustbotError::StorageError(e.to_string())),
+            ),
+            Ok(received) => received,
+        };
+        self.folder_index_load_rx = None;
+        let folder = std::path::Path::new(self.folder_chat_path.trim());
+        if root != folder || self.folder_index.is_some() {
+            return;
+        }
+        match result {
+            Ok(index) => self.folder_index = index.map(Arc::new),
+            Err(e) => {
+                self.folder_index_message =
+                    Some((format!("Failed to load the folder index: {}", e), true));
+            }
+        }
+    }
+
+    /// Excerpts of the indexed folder matching a question, when Chat with
+    /// folder is on
+    fn folder_excerpts(&self, question: &str) -> Option<String> {
+        if !self.folder_chat_enabled {
+            return None;
+        }
+        let index = self.folder_index.as_ref()?;
+        folder_index::excerpts_message(&index.search(question, folder_index::DEFAULT_EXCERPTS))
+    }
+
+    /// Replace a user message and everything after it, then send the new text
+    ///
+    /// The API history is rewound to just before the message, so the agent
+    /// answers the edited text without seeing the old exchange.
+    fn resend_edited_message(&mut self, index: usize, content: String) {
+        if self.is_waiting {
+            return;
+        }
+        let Some(original) = self.messages.get(index) else {
+            return;
+        };
+        if original.role != MessageRole::User
+            || (content.trim().is_empty() && original.images.is_empty())
+        {
+            return;
+        }
+        let images = original.images.clone();
+
+        let later_user_messages = self.messages[index + 1..]
+            .iter()
+            .filter(|m| m.role == MessageRole::User)
+            .count();
+        tracing::info!(
+            "✏️  Resending edited message {} ({} later messages dropped)",
+            index,
+            self.messages.len() - index - 1
+        );
+        self.messages.truncate(index);
+        self.submit_message(content, images, Some(later_user_messages));
+    }
+
+    /// Show a user message in the chat and send it to the agent
+    ///
+    /// # Arguments
+    /// * `message` - Text to send
+    /// * `images` - Pasted images sent along with the text
+    /// * `rewind` - Rewind the API history first (user messages after the
+    ///   edited one, see `RustbotApi::rewind_in`)
+    fn submit_message(
+        &mut self,
+        message: String,
+        images: Vec<ui::ImageAttachment>,
+        rewind: Option<usize>,
+    ) {
+        let image_urls = images.iter().map(|i| i.data_url.clone()).collect();
+
+        // Add user message to UI
+        self.messages.push(ChatMessage {
+            role: MessageRole::User,
+            content: message.clone(),
+            input_tokens: Some(self.estimate_tokens(&message)),
+            output_tokens: None,
+            embedded_images: Vec::new(), // User messages don't have embedded images
+            images,
+            tool_calls: Vec::new(),
+            timestamp: Some(chrono::Utc::now()),
+            first_token_ms: None,
+            generation_ms: None,
+            pinned: false,
+            feedback: None,
+            failed: false,
+        });
+
+        self.start_turn(message, image_urls, rewind, None);
+    }
+
+    /// Ask again for the latest answer, optionally with another agent
+    ///
+    /// # Arguments
+    /// * `agent` - Agent to answer (None = the tab's agent); it stays
+    ///   selected for the tab afterwards
+    /// * `keep_previous` - Keep the old answer above the new one instead of
+    ///   replacing it (only the new one stays in the agent's context)
+    fn regenerate_response(&mut self, agent: Option<String>, keep_previous: bool) {
+        if self.is_waiting {
+            return;
+        }
+        let Some(user_index) = self
+            .messages
+            .iter()
+            .rposition(|m| m.role == MessageRole::User)
+        else {
+            return;
+        };
+
+        tracing::info!(
+            "🔁 Regenerating response (agent: {:?}, keep previous: {})",
+            agent,
+            keep_previous
+        );
+        let prompt = self.messages[user_index].content.clone();
+        let images = self.messages[user_index]
+            .images
+            .iter()
+            .map(|i| i.data_url.clone())
+            .collect();
+        if !keep_previous {
+            self.messages.truncate(user_index + 1);
+        }
+        self.start_turn(prompt, images, Some(0), agent);
+    }
+
+    /// Send the user message behind a failed answer again
+    ///
+    /// The first retry goes out right away and each further one waits twice
+    /// as long (see `RetryState::delay`); `poll_retry` sends it when due.
+    fn retry_failed_message(&mut self) {
+        if self.is_waiting || !self.messages.last().is_some_and(|m| m.failed) {
+            return;
+        }
+        let delay = self.retry.delay();
+        self.retry.attempts += 1;
+        self.retry.scheduled = Some(std::time::Instant::now() + delay);
+        tracing::info!(
+            "🔁 Retry {} of the failed message in {:?}",
+            self.retry.attempts,
+            delay
+        );
+    }
+
+    /// Send a scheduled retry once its wait is over
+    ///
+    /// The failed attempt left the user message in the API history; it is
+    /// removed first so the retry sees the same context as the first try.
+    fn poll_retry(&mut self, ctx: &egui::Context) {
+        let Some(scheduled) = self.retry.scheduled else {
+            return;
+        };
+        let scheduled = self
+            .rate_limited_until
+            .map_or(scheduled, |u| u.max(scheduled));
+        let now = std::time::Instant::now();
+        if scheduled > now {
+            ctx.request_repaint_after(scheduled - now);
+            return;
+        }
+        let Ok(mut api) = self.api.try_lock() else {
+            ctx.request_repaint();
+            return;
+        };
+        if let Err(e) = api.discard_unanswered_in(&self.api_session) {
+            tracing::warn!("Failed to discard the failed message: {}", e);
+        }
+        drop(api);
+        self.retry.scheduled = None;
+
+        let Some(user_index) = self
+            .messages
+            .iter()
+            .rposition(|m| m.role == MessageRole::User)
+        else {
+            return;
+        };
+        let prompt = self.messages[user_index].content.clone();
+        let images = self.messages[user_index]
+            .images
+            .iter()
+            .map(|i| i.data_url.clone())
+            .collect();
+        self.messages.truncate(user_index + 1);
+        self.start_turn(prompt, images, None, None);
+    }
+
+    /// Send a message to the agent and stream the answer into a new chat entry
+    ///
+    /// # Arguments
+    /// * `message` - Text to send (already shown in the chat)
+    /// * `images` - Attached images as data URLs
+    /// * `rewind` - Rewind the API history first (user messages after the
+    ///   edited one, see `RustbotApi::rewind_in`)
+    /// * `agent` - Switch the tab's agent first
+    fn start_turn(
+        &mut self,
+        message: String,
+        images: Vec<String>,
+        rewind: Option<usize>,
+        agent: Option<String>,
+    ) {
+        // Calculate input tokens early
+        let input_tokens = self.estimate_tokens(&message);
+        let cost = self.calculate_cost(input_tokens as u64, 0);
+        self.token_stats.record(input_tokens as u64, 0, cost);
+        self.save_token_stats();
+
+        // Add placeholder for assistant response
+        self.messages.push(ChatMessage {
+            role: MessageRole::Assistant,
+            content: String::new(),
+            input_tokens: None,
+            output_tokens: None,
+            embedded_images: Vec::new(), // Will be populated when content is set
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+            timestamp: Some(chrono::Utc::now()),
+            first_token_ms: None,
+            generation_ms: None,
+            pinned: false,
+            feedback: None,
+            failed: false,
+        });
+
+        self.is_waiting = true;
+        self.current_response.clear();
+
+        // Update context tracker
+        self.update_context_tracker();
+
+        // Call send_message - we use a channel to communicate the result back
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.pending_agent_result = Some(rx);
+
+        if let Some(agent) = &agent {
+            self.session.agent_id = agent.clone();
+        }
+
+        // Spawn async task using tokio runtime
+        // This is the proper way to call async code from sync UI thread
+        let api = Arc::clone(&self.api);
+        let api_session = self.api_session.clone();
+        let pinned = self.pinned_context();
+        let tool_overrides = self.tool_overrides.clone();
+        let retrieved = self.folder_excerpts(&message);
+        let context = self.generate_system_context();
+        let runtime = &self.runtime;
+        let task = runtime.spawn(async move {
+            // Lock the API for the setup and the turn; the turn lets go of it
+            // while a file access prompt is open
+            let mut api_guard = api.lock_owned().await;
+            api_guard.set_system_context(Some(context));
+            if let Err(e) = api_guard.set_pinned_in(&api_session, pinned) {
+                let _ = tx.send(Err(e));
+                return;
+            }
+            if let Err(e) = api_guard.set_retrieved_in(&api_session, retrieved) {
+                let _ = tx.send(Err(e));
+                return;
+            }
+            if let Err(e) = api_guard.set_tool_overrides_in(&api_session, tool_overrides) {
+                let _ = tx.send(Err(e));
+                return;
+            }
+            if let Some(agent) = agent {
+                if let Err(e) = api_guard.switch_agent_in(&api_session, &agent) {
+                    let _ = tx.send(Err(e));
+                    return;
+                }
+            }
+            if let Some(later_user_messages) = rewind {
+                if let Err(e) = api_guard.rewind_in(&api_session, later_user_messages) {
+                    let _ = tx.send(Err(e));
+                    return;
+                }
+            }
+            let result =
+                RustbotApi::send_message_shared(api_guard, &api_session, &message, images).await;
+            let _ = tx.send(result);
+        });
+        self.turn_task = Some(task.abort_handle());
+        self.turn_started = Some((std::time::Instant::now(), false));
+    }
+
+    /// Stop the answer being generated, keeping the part that has arrived
+    ///
+    /// Before streaming starts the request task is aborted and the turn is
+    /// rolled back: the unanswered user message leaves the API history (so the
+    /// next request doesn't send two user turns in a row) and goes back into
+    /// the input box. While streaming, closing the receiver cancels the LLM
+    /// request and `poll_response` finalizes the partial answer like a
+    /// complete one (history, token usage).
+    fn stop_generation(&mut self) {
+        if !self.is_waiting {
+            return;
+        }
+        tracing::info!("⏹️  Stopping generation");
+
+        if let Some(task) = self.turn_task.take() {
+            task.abort();
+        }
+        self.turn_started = None;
+        self.pending_agent_result = None;
+        if let Some(rx) = &mut self.response_rx {
+            rx.close();
+        }
+
+        // Nothing arrived yet: roll the turn back instead of finalizing
+        if self.current_response.is_empty() {
+            self.response_rx = None;
+            self.is_waiting = false;
+            if self
+                .messages
+                .last()
+                .is_some_and(|m| m.role == MessageRole::Assistant && m.content.is_empty())
+            {
+                self.messages.pop();
+            }
+            if self
+                .messages
+                .last()
+                .is_some_and(|m| m.role == MessageRole::User)
+            {
+                if let Some(message) = self.messages.pop() {
+                    if self.message_input.trim().is_empty() {
+                        self.message_input = message.content;
+                    }
+                    if self.pending_images.is_empty() {
+                        self.pending_images = message.images;
+                    }
+                }
+            }
+
+            // The aborted task releases the API lock before this runs; if it
+            // never got as far as adding the message, nothing is removed
+            let api = Arc::clone(&self.api);
+            let api_session = self.api_session.clone();
+            self.runtime.spawn(async move {
+                if let Err(e) = api.lock().await.discard_unanswered_in(&api_session) {
+                    tracing::warn!("Failed to roll back the stopped message: {}", e);
+                }
+            });
+        }
+    }
+
+    /// Add or update a tool call card on the answer being generated
+    ///
+    /// The card goes on the latest message of the tab whose API session made
+    /// the call; the finished call replaces the running one with the same ID.
+    fn show_tool_call(&mut self, session_id: &str, call: events::ToolCallRecord) {
+        let messages = if session_id == self.api_session {
+            &mut self.messages
+        } else if let Some(tab) = self.tabs.iter_mut().find(|t| t.api_session == session_id) {
+            &mut tab.messages
+        } else {
+            return;
+        };
+        let Some(message) = messages
+            .last_mut()
+            .filter(|m| m.role == MessageRole::Assistant)
+        else {
+            return;
+        };
+        match message.tool_calls.iter_mut().find(|c| c.id == call.id) {
+            Some(card) => *card = call,
+            None => message.tool_calls.push(call),
+        }
+    }
+
+    /// Make the specialist a tab's agent after the primary agent handed it
+    /// the conversation (the API session already changed agents)
+    fn show_handoff(&mut self, session_id: &str, handoff: handoff::Handoff) {
+        if session_id == self.api_session {
+            self.session.agent_id = handoff.to.clone();
+            self.handoff = Some(handoff);
+            self.update_context_tracker();
+        } else if let Some(tab) = self.tabs.iter_mut().find(|t| t.api_session == session_id) {
+            tab.session.agent_id = handoff.to.clone();
+            tab.handoff = Some(handoff);
+        }
+    }
+
+    /// The handoff that gave the visible tab to its agent, while that agent
+    /// still owns the chat
+    fn active_handoff(&self) -> Option<&handoff::Handoff> {
+        self.handoff
+            .as_ref()
+            .filter(|handoff| handoff.to == self.session.agent_id)
+    }
 
-        // Clear API conversation history and publish event
+    /// Give the visible tab back to the agent that handed it off
+    fn end_handoff(&mut self) {
+        let Some(handoff) = self.handoff.take() else {
+            return;
+        };
+        self.session.agent_id = handoff.from.clone();
+        self.update_context_tracker();
         let api = Arc::clone(&self.api);
-        let runtime = self
-            .deps
-            .runtime
-            .as_ref()
-            .expect("Runtime is required for RustbotApp");
-        runtime.spawn(async move {
+        let api_session = self.api_session.clone();
+        self.runtime.spawn(async move {
             let mut api_guard = api.lock().await;
-            api_guard.clear_history();
+            if let Err(e) = api_guard.switch_agent_in(&api_session, &handoff.from) {
+                tracing::warn!("Failed to hand the chat back: {}", e);
+            }
         });
     }
 
-    fn reload_config(&mut self) {
-        tracing::info!("🔄 Reloading Rustbot configuration...");
-
-        // Get runtime from dependencies
-        let runtime = self
-            .deps
-            .runtime
-            .as_ref()
-            .expect("Runtime is required for RustbotApp");
-
-        // Reload agents from config service
-        let agent_configs = runtime.block_on(async {
-            self.deps
-                .config
-                .load_agent_configs()
-                .await
-                .unwrap_or_else(|e| {
-                    tracing::warn!("Failed to load agents from config service: {}", e);
-                    vec![AgentConfig::default_assistant()]
-                })
-        });
+    /// Exchange the visible chat state with the tab stored at `index`
+    ///
+    /// The slot of the visible tab holds an empty placeholder; swapping twice
+    /// restores the previous state, which lets `update` poll background tabs
+    /// with the same code as the visible one.
+    fn swap_tab(&mut self, index: usize) {
+        let tab = &mut self.tabs[index];
+        std::mem::swap(&mut self.api_session, &mut tab.api_session);
+        std::mem::swap(&mut self.message_input, &mut tab.message_input);
+        std::mem::swap(&mut self.pending_images, &mut tab.pending_images);
+        std::mem::swap(&mut self.messages, &mut tab.messages);
+        std::mem::swap(&mut self.session, &mut tab.session);
+        std::mem::swap(&mut self.context_tracker, &mut tab.context_tracker);
+        std::mem::swap(
+            &mut self.pending_agent_result,
+            &mut tab.pending_agent_result,
+        );
+        std::mem::swap(&mut self.turn_task, &mut tab.turn_task);
+        std::mem::swap(&mut self.turn_started, &mut tab.turn_started);
+        std::mem::swap(&mut self.response_rx, &mut tab.response_rx);
+        std::mem::swap(&mut self.current_response, &mut tab.current_response);
+        std::mem::swap(&mut self.is_waiting, &mut tab.is_waiting);
+        std::mem::swap(&mut self.editing_message, &mut tab.editing_message);
+        std::mem::swap(&mut self.regenerate_open, &mut tab.regenerate_open);
+        std::mem::swap(&mut self.message_heights, &mut tab.message_heights);
+        std::mem::swap(&mut self.feedback_comment, &mut tab.feedback_comment);
+        std::mem::swap(&mut self.retry, &mut tab.retry);
+        std::mem::swap(&mut self.pending_diagrams, &mut tab.pending_diagrams);
+        std::mem::swap(&mut self.outbox, &mut tab.outbox);
+        std::mem::swap(&mut self.handoff, &mut tab.handoff);
+        std::mem::swap(&mut self.tool_overrides, &mut tab.tool_overrides);
+    }
 
-        tracing::info!("📋 Reloaded {} agents", agent_configs.len());
-        for config in &agent_configs {
-            tracing::info!(
-                "   - {} (primary: {}, enabled: {})",
-                config.id,
-                config.is_primary,
-                config.enabled
-            );
+    /// Show another chat tab
+    ///
+    /// The API's active session follows, so the control socket and scripts
+    /// talk to the conversation on screen.
+    fn select_tab(&mut self, index: usize) {
+        if index == self.active_tab || index >= self.tabs.len() {
+            return;
         }
-
-        // Get LLM adapter from dependencies
-        let llm_adapter = self
-            .deps
-            .llm_adapter
-            .as_ref()
-            .expect("LLM adapter is required for RustbotApp");
-
-        // Subscribe to fresh event bus events
-        let event_rx = self.deps.event_bus.subscribe();
-
-        // Rebuild the API with reloaded agents
-        let mut api_builder = api::RustbotApiBuilder::new()
-            .event_bus(Arc::clone(&self.deps.event_bus))
-            .runtime(Arc::clone(runtime))
-            .llm_adapter(Arc::clone(llm_adapter))
-            .max_history_size(20)
-            .system_instructions(self.system_prompts.system_instructions.clone());
-
-        for agent_config in &agent_configs {
-            api_builder = api_builder.add_agent(agent_config.clone());
+        self.swap_tab(self.active_tab);
+        self.swap_tab(index);
+        self.active_tab = index;
+        self.current_view = AppView::Chat;
+        if self.tool_panel_open {
+            self.refresh_tool_sources();
         }
 
-        let api = api_builder.build().expect("Failed to rebuild RustbotApi");
+        let api = Arc::clone(&self.api);
+        let api_session = self.api_session.clone();
+        let runtime = &self.runtime;
+        runtime.spawn(async move {
+            if let Err(e) = api.lock().await.switch_session(&api_session) {
+                tracing::warn!("Failed to switch API session: {}", e);
+            }
+        });
+    }
 
-        // Update app state with new components
-        self.api = Arc::new(Mutex::new(api));
-        self.event_rx = event_rx;
-        self.agent_configs = agent_configs;
+    /// Open an empty chat tab (with the current agent) and show it
+    fn new_tab(&mut self) {
+        let api_session = format!("tab-{}", self.next_tab_id);
+        self.next_tab_id += 1;
 
-        // Clear conversation on reload
-        self.clear_conversation();
+        // Created in the background; the API mutex is fair, so a message sent
+        // from the new tab is queued behind this
+        let api = Arc::clone(&self.api);
+        let id = api_session.clone();
+        let runtime = &self.runtime;
+        runtime.spawn(async move {
+            if let Err(e) = api.lock().await.create_session(&id) {
+                tracing::warn!("Failed to create API session: {}", e);
+            }
+        });
 
-        tracing::info!("✅ Configuration reloaded successfully");
+        tracing::info!("💬 New chat tab '{}'", api_session);
+        self.tabs
+            .push(ui::ChatTab::new(api_session, &self.session.agent_id));
+        self.select_tab(self.tabs.len() - 1);
     }
 
-    /// Extract all base64 image data URLs from markdown content
-    ///
-    /// This helper function finds all embedded images in the format:
-    /// ![alt](data:image/jpeg;base64,...)
-    ///
-    /// # Arguments
-    /// * `markdown` - The markdown content to search
+    /// Close a chat tab and its API session (the last tab stays open)
     ///
-    /// # Returns
-    /// Vector of base64-encoded image data URLs
-    fn extract_image_data_urls(markdown: &str) -> Vec<String> {
-        use regex::Regex;
-        let mut images = Vec::new();
-
-        // Match data URL images: ![...](data:image/...;base64,...)
-        let pattern = Regex::new(r#"!\[[^\]]*\]\((data:image/[^;]+;base64,[A-Za-z0-9+/=]+)\)"#)
-            .expect("Invalid regex pattern");
+    /// A response still streaming in the tab is dropped; completed turns are
+    /// already saved and can be reopened from History.
+    fn close_tab(&mut self, index: usize) {
+        if self.tabs.len() < 2 || index >= self.tabs.len() {
+            return;
+        }
+        if index == self.active_tab {
+            self.select_tab(if index > 0 { index - 1 } else { 1 });
+        }
 
-        for cap in pattern.captures_iter(markdown) {
-            if let Some(data_url) = cap.get(1) {
-                images.push(data_url.as_str().to_string());
-            }
+        let tab = self.tabs.remove(index);
+        if index < self.active_tab {
+            self.active_tab -= 1;
         }
+        tracing::info!("💬 Closed chat tab '{}'", tab.api_session);
 
-        images
+        let api = Arc::clone(&self.api);
+        let runtime = &self.runtime;
+        runtime.spawn(async move {
+            if let Err(e) = api.lock().await.close_session(&tab.api_session) {
+                tracing::warn!("Failed to close API session: {}", e);
+            }
+        });
     }
 
-    /// Preprocess markdown content to render mermaid diagrams
-    ///
-    /// This method detects mermaid code blocks and replaces them with embedded SVG data.
-    /// The replacement happens inline in the markdown, converting:
-    /// ```mermaid
-    /// graph TD
-    ///   A-->B
-    /// ```
-    ///
-    /// Into an embedded SVG image that egui_commonmark can display.
-    ///
-    /// # Arguments
-    /// * `markdown` - The original markdown content with mermaid blocks
+    /// Receive the agent result and response chunks for the visible tab
     ///
-    /// # Returns
-    /// Preprocessed markdown with mermaid diagrams replaced by SVG embeds
-    fn preprocess_mermaid(&self, markdown: &str) -> String {
-        // First, validate any existing base64 SVG images in the markdown
-        // This handles cases where the LLM already generated base64 SVG images
-        let mut result = markdown.to_string();
+    /// Background tabs are polled by swapping them in (see `swap_tab`).
+    fn poll_response(&mut self, ctx: &egui::Context) {
+        // Check for pending agent result (from non-blocking async task)
+        if let Some(result_rx) = &mut self.pending_agent_result {
+            match result_rx.try_recv() {
+                Ok(result) => {
+                    tracing::info!("Received agent processing result");
+                    // Agent processing completed, handle result
+                    match result {
+                        Ok(rx) => {
+                            tracing::info!("Agent processing succeeded, starting stream");
+                            // Successfully got response stream, start receiving chunks
+                            self.response_rx = Some(rx);
+                            self.pending_agent_result = None; // Clear the pending result
+                            ctx.request_repaint();
+                        }
+                        Err(e) => {
+                            // Error occurred during agent processing
+                            tracing::error!("Failed to process message through agent: {}", e);
+                            self.is_waiting = false;
+                            // Probe again right away in case the connection is gone
+                            self.connectivity_checked = None;
 
-        // Look for base64 SVG images: ![...](data:image/svg+xml;base64,...)
-        use regex::Regex;
-        let base64_img_pattern =
-            Regex::new(r#"!\[([^\]]*)\]\(data:image/svg\+xml;base64,([A-Za-z0-9+/=]+)\)"#).unwrap();
-
-        // Validate and fix any base64 SVG images
-        for cap in base64_img_pattern.captures_iter(markdown) {
-            if let (Some(alt_text), Some(base64_data)) = (cap.get(1), cap.get(2)) {
-                use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-                // Try to decode the base64 to verify it's valid
-                if let Ok(svg_bytes) = BASE64.decode(base64_data.as_str()) {
-                    // Verify it's actually SVG
-                    if let Ok(svg_str) = std::str::from_utf8(&svg_bytes[..svg_bytes.len().min(100)])
-                    {
-                        if svg_str.trim_start().starts_with("<?xml")
-                            || svg_str.trim_start().starts_with("<svg")
-                        {
-                            tracing::debug!(
-                                "✓ Found valid base64 SVG image in markdown ({} bytes)",
-                                svg_bytes.len()
-                            );
-                            // It's valid, keep it as-is
-                            continue;
+                            // A 429 is retried after the wait the provider asked for
+                            let content = if let Some(limit) =
+                                e.downcast_ref::<quota::QuotaExceeded>()
+                            {
+                                format!(
+                                    "⏳ {}\n\nThe limits for this account are set in \
+                                     Settings > Preferences.",
+                                    limit
+                                )
+                            } else if let Some(RustbotError::RateLimited(limited)) =
+                                RustbotError::find(&e)
+                            {
+                                match self.wait_out_rate_limit(limited) {
+                                    Some(wait) => format!(
+                                        "⏳ {}\n\nRetrying in {} s; queued messages follow.",
+                                        limited,
+                                        wait.as_secs().max(1)
+                                    ),
+                                    None => {
+                                        format!("⏳ {}\n\nPlease try again in a moment.", limited)
+                                    }
+                                }
+                            } else if let Some((err, fix)) = RustbotError::find(&e)
+                                .and_then(|err| Some((err, err.remediation()?)))
+                            {
+                                // A failure the user can fix: say how
+                                format!("⚠️ {}\n\n{}", err, fix)
+                            } else {
+                                format!(
+                                    "⚠️ Error: {}\n\nPlease try again or check your connection.",
+                                    e
+                                )
+                            };
+
+                            // Add error message visible to user
+                            if let Some(last_msg) = self.messages.last_mut() {
+                                last_msg.content = content;
+                                last_msg.failed = true;
+                            }
+
+                            self.pending_agent_result = None; // Clear the pending result
+                            ctx.request_repaint();
                         }
                     }
                 }
-                tracing::warn!("Found invalid base64 SVG image, will remove it");
-            }
-        }
+                Err(mpsc::error::TryRecvError::Empty) => {
+                    // Still waiting for result, check again next frame
+                    ctx.request_repaint_after(ui::types::STREAM_FRAME_INTERVAL);
+                }
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    tracing::error!("Agent result channel disconnected unexpectedly");
+                    self.is_waiting = false;
+                    self.pending_agent_result = None;
 
-        // Then, extract and render mermaid code blocks
-        let blocks = mermaid::extract_mermaid_blocks(&result);
+                    // Add error message
+                    if let Some(last_msg) = self.messages.last_mut() {
+                        last_msg.content =
+                            "⚠️ Error: Agent processing failed unexpectedly.\n\nPlease try again."
+                                .to_string();
+                        last_msg.failed = true;
+                    }
 
-        if blocks.is_empty() {
-            return result;
+                    ctx.request_repaint();
+                }
+            }
         }
 
-        let renderer = Arc::clone(&self.mermaid_renderer);
-        let runtime = Arc::clone(
-            self.deps
-                .runtime
-                .as_ref()
-                .expect("Runtime is required for RustbotApp"),
-        );
+        // Check for streaming responses
+        if let Some(rx) = &mut self.response_rx {
+            // Take every chunk that arrived since the last frame at once
+            let first_chunk = self.current_response.is_empty();
+            let received = ui::types::drain_stream(rx, &mut self.current_response);
 
-        // Process blocks in reverse order to maintain correct indices
-        for (start, end, code) in blocks.iter().rev() {
-            // Try to render the diagram as PNG (better compatibility with egui_commonmark)
-            let png_result = runtime.block_on(async {
-                if let Ok(mut r) = renderer.try_lock() {
-                    r.render_to_png(code).await
-                } else {
-                    Err(mermaid::MermaidError::EncodingError(
-                        "Renderer locked".to_string(),
-                    ))
+            // Update the last message (assistant response) once per frame
+            if received {
+                if let Some(last_msg) = self.messages.last_mut() {
+                    last_msg.content.clone_from(&self.current_response);
+                    if first_chunk {
+                        last_msg.first_token_ms = self
+                            .turn_started
+                            .map(|(started, _)| started.elapsed().as_millis() as u64);
+                    }
                 }
-            });
+            }
 
-            match png_result {
-                Ok(image_bytes) => {
-                    // Convert image bytes to base64 data URL
-                    // Note: mermaid.ink/img/ returns JPEG (with labels) instead of PNG (transparent)
-                    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-                    let img_base64 = BASE64.encode(&image_bytes);
-                    let data_url = format!("data:image/jpeg;base64,{}", img_base64);
+            // The next batch is drawn on the next paced frame
+            ctx.request_repaint_after(ui::types::STREAM_FRAME_INTERVAL);
 
-                    // Replace mermaid block with image markdown
-                    // Note: egui_commonmark supports image syntax: ![alt](url)
-                    let replacement = format!("![Mermaid Diagram]({})", data_url);
+            // Check if stream is done
+            if rx.is_closed() && !self.current_response.is_empty() {
+                // Calculate output tokens for the completed response
+                let output_tokens = self.estimate_tokens(&self.current_response);
+                let cost = self.calculate_cost(0, output_tokens as u64);
+                self.token_stats.record(0, output_tokens as u64, cost);
 
-                    result.replace_range(*start..*end, &replacement);
+                // Save stats after updating
+                self.save_token_stats();
+
+                let generation_ms = self
+                    .turn_started
+                    .map(|(started, _)| started.elapsed().as_millis() as u64);
+
+                // Answer finished while the user was in another window
+                if let Some((started, background)) = self.turn_started.take() {
+                    let focused = ctx.input(|i| i.viewport().focused).unwrap_or(true);
+                    if self.notifications_enabled
+                        && !focused
+                        && (background || started.elapsed() >= ui::notifications::MIN_DURATION)
+                    {
+                        ui::notifications::notify(
+                            ctx,
+                            &format!("Rustbot: {}", ui::ChatTab::title(&self.messages)),
+                            &ui::notifications::preview(&self.current_response),
+                        );
+                    }
+                }
 
-                    tracing::debug!(
-                        "✓ Rendered mermaid diagram ({} bytes JPEG)",
-                        image_bytes.len()
-                    );
+                // Update the last message with token count and final content
+                if let Some(last_msg) = self.messages.last_mut() {
+                    last_msg.output_tokens = Some(output_tokens);
+                    last_msg.generation_ms = generation_ms;
+                    last_msg.content = self.current_response.clone();
+                    // Extract embedded image data URLs for easy access
+                    last_msg.embedded_images = Self::extract_image_data_urls(&last_msg.content);
                 }
-                Err(e) => {
-                    // On error, leave the code block as-is (graceful degradation)
-                    tracing::warn!("Failed to render mermaid diagram: {}", e);
-                    // Optionally, we could add an error message:
-                    // let error_msg = format!("```\nError rendering diagram: {}\n```", e);
-                    // result.replace_range(*start..*end, &error_msg);
+                self.update_context_tracker();
+
+                // Diagrams and formulas render in the background and replace
+                // their source when ready (see poll_diagrams)
+                if Self::has_diagrams(&self.current_response) {
+                    let theme = ui::markdown::diagram_theme(&ctx.style().visuals);
+                    let rx = self.render_diagrams(ctx, &self.current_response, theme);
+                    self.pending_diagrams.push(ui::PendingDiagrams {
+                        index: self.messages.len().saturating_sub(1),
+                        source: self.current_response.clone(),
+                        rx,
+                    });
                 }
+
+                // Add assistant response to API's message history
+                // This ensures the next message will have this response as context
+                let api = Arc::clone(&self.api);
+                let response = self.current_response.clone();
+                let mut session = self.snapshot_session();
+                let queue = self.write_queue();
+                let runtime = &self.runtime;
+                let api_session = self.api_session.clone();
+                self.pending_saves.spawn_on(
+                    async move {
+                        let mut api_guard = api.lock().await;
+                        if let Err(e) = api_guard.add_assistant_response_in(&api_session, response)
+                        {
+                            // The tab was closed while the response streamed
+                            tracing::debug!("Response not recorded: {}", e);
+                            return;
+                        }
+
+                        // Persist the completed turn, including the LLM context
+                        if let Some(api_session) = api_guard.session(&api_session) {
+                            session.history = api_session.history();
+                            session.agent_id = api_session.agent_id().to_string();
+                        }
+                        drop(api_guard);
+
+                        if let Some(queue) = queue {
+                            queue.save_session(session);
+                        }
+                    },
+                    runtime.handle(),
+                );
+
+                self.response_rx = None;
+                self.current_response.clear();
+                self.is_waiting = false;
+                self.retry = ui::RetryState::default();
             }
         }
-
-        result
     }
 
-    fn send_message(&mut self, _ctx: &egui::Context) {
-        if self.message_input.trim().is_empty() || self.is_waiting {
+    /// Record the MCP plugin startup result once the background start is done
+    fn poll_mcp_startup(&mut self) {
+        let Some(rx) = &mut self.mcp_startup_rx else {
             return;
+        };
+        match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => {}
+            Err(e) => {
+                tracing::warn!("MCP plugin startup unavailable: {}", e);
+                self.mcp_startup_rx = None;
+            }
+            Ok((started, enabled)) => {
+                tracing::info!("🔌 Started {} of {} MCP plugins", started, enabled);
+                self.mcp_startup_rx = None;
+                self.mcp_startup = Some((started, enabled));
+            }
         }
-
-        // Calculate input tokens early
-        let input_tokens = self.estimate_tokens(&self.message_input);
-        self.token_stats.daily_input += input_tokens;
-        self.token_stats.total_input += input_tokens;
-        let _ = self.save_token_stats();
-
-        // Add user message to UI
-        self.messages.push(ChatMessage {
-            role: MessageRole::User,
-            content: self.message_input.clone(),
-            input_tokens: Some(input_tokens),
-            output_tokens: None,
-            embedded_images: Vec::new(), // User messages don't have embedded images
-        });
-
-        // Add placeholder for assistant response
-        self.messages.push(ChatMessage {
-            role: MessageRole::Assistant,
-            content: String::new(),
-            input_tokens: None,
-            output_tokens: None,
-            embedded_images: Vec::new(), // Will be populated when content is set
-        });
-
-        self.is_waiting = true;
-        self.current_response.clear();
-
-        // Update context tracker
-        let system_content_tokens = self.estimate_tokens(&self.generate_system_context());
-        let conversation_total_tokens: u32 = self
-            .messages
-            .iter()
-            .map(|msg| self.estimate_tokens(&msg.content))
-            .sum();
-        self.context_tracker
-            .update_counts(system_content_tokens, conversation_total_tokens);
-
-        // Call send_message - we use a channel to communicate the result back
-        let message = self.message_input.clone();
-        let (tx, rx) = mpsc::unbounded_channel();
-        self.pending_agent_result = Some(rx);
-
-        // Spawn async task using tokio runtime
-        // This is the proper way to call async code from sync UI thread
-        let api = Arc::clone(&self.api);
-        let runtime = self
-            .deps
-            .runtime
-            .as_ref()
-            .expect("Runtime is required for RustbotApp");
-        runtime.spawn(async move {
-            // Lock the API, call send_message, then release lock
-            let mut api_guard = api.lock().await;
-            let result = api_guard.send_message(&message).await;
-            let _ = tx.send(result);
-        });
-
-        // Clear input after processing
-        self.message_input.clear();
     }
 
     /// Render fullscreen splash screen with logo
@@ -1142,8 +5010,35 @@ This information is provided automatically to give you context about the current
 
                 ui.add_space(40.0);
 
-                // Loading animation
-                ui.spinner();
+                // Startup steps; only the app state has to finish before the
+                // chat shows, plugins keep starting behind it
+                let mut steps = vec![(
+                    "Agents, profile and conversation".to_string(),
+                    self.startup_rx.is_none(),
+                )];
+                if let Some((started, enabled)) = self.mcp_startup {
+                    steps.push((
+                        format!("Started {} of {} MCP plugins", started, enabled),
+                        true,
+                    ));
+                } else if self.mcp_startup_rx.is_some() {
+                    steps.push(("Starting MCP plugins".to_string(), false));
+                }
+                let done = steps.iter().filter(|(_, done)| *done).count();
+                ui.add(
+                    egui::ProgressBar::new(done as f32 / steps.len() as f32)
+                        .desired_width(240.0)
+                        .animate(done < steps.len()),
+                );
+                ui.add_space(10.0);
+                for (label, done) in steps {
+                    let icon = if done {
+                        icons::CHECK_CIRCLE
+                    } else {
+                        icons::CIRCLE_NOTCH
+                    };
+                    ui.label(format!("{} {}", icon, label));
+                }
             });
         });
     }
@@ -1202,15 +5097,63 @@ This information is provided automatically to give you context about the current
                         ui.label("Optional, but helps with context.");
                         ui.add_space(20.0);
 
-                        ui.horizontal(|ui| {
-                            ui.label("Email:");
-                            ui.text_edit_singleline(&mut self.setup_email);
-                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Email:");
+                            ui.text_edit_singleline(&mut self.setup_email);
+                        });
+
+                        ui.add_space(20.0);
+                        ui.horizontal(|ui| {
+                            if ui.button("Back").clicked() {
+                                self.setup_wizard_step = SetupWizardStep::EnterName;
+                            }
+                            if ui.button("Next").clicked() {
+                                self.setup_wizard_step = SetupWizardStep::ChooseProvider;
+                            }
+                        });
+                    }
+
+                    SetupWizardStep::ChooseProvider => {
+                        ui.heading("Choose a provider");
+                        ui.add_space(10.0);
+                        ui.label("Where should Rustbot send your messages?");
+                        ui.add_space(20.0);
+
+                        for provider in llm::LlmProvider::ALL {
+                            let description = match provider {
+                                llm::LlmProvider::OpenRouter => {
+                                    "One key for models from many providers (recommended)"
+                                }
+                                llm::LlmProvider::OpenAI => "GPT models with an OpenAI key",
+                                llm::LlmProvider::Anthropic => {
+                                    "Claude models with an Anthropic key"
+                                }
+                                llm::LlmProvider::Ollama => "Local models, no key needed",
+                            };
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .radio(self.setup_provider == provider, provider.display_name())
+                                    .clicked()
+                                    && self.setup_provider != provider
+                                {
+                                    self.setup_provider = provider;
+                                    // Offer a key already in the environment
+                                    self.setup_api_key = std::env::var(provider.default_env_var())
+                                        .unwrap_or_default();
+                                    self.setup_key_message = None;
+                                }
+                                ui.label(
+                                    egui::RichText::new(description)
+                                        .small()
+                                        .color(theme_colors(ui.ctx()).muted),
+                                );
+                            });
+                        }
 
                         ui.add_space(20.0);
                         ui.horizontal(|ui| {
                             if ui.button("Back").clicked() {
-                                self.setup_wizard_step = SetupWizardStep::EnterName;
+                                self.setup_wizard_step = SetupWizardStep::EnterEmail;
                             }
                             if ui.button("Next").clicked() {
                                 self.setup_wizard_step = SetupWizardStep::EnterApiKey;
@@ -1219,30 +5162,74 @@ This information is provided automatically to give you context about the current
                     }
 
                     SetupWizardStep::EnterApiKey => {
-                        ui.heading("OpenRouter API Key");
-                        ui.add_space(10.0);
-                        ui.label("Get your free API key from:");
-                        ui.hyperlink("https://openrouter.ai/keys");
-                        ui.add_space(20.0);
+                        let provider = self.setup_provider;
+                        let checking = self.setup_key_check_rx.is_some();
 
-                        ui.horizontal(|ui| {
-                            ui.label("API Key:");
-                            ui.add(
-                                egui::TextEdit::singleline(&mut self.setup_api_key).password(true),
+                        if let Some(url) = provider.key_url() {
+                            ui.heading(format!("{} API Key", provider.display_name()));
+                            ui.add_space(10.0);
+                            ui.label("Get your API key from:");
+                            ui.hyperlink(url);
+                            ui.add_space(20.0);
+
+                            ui.horizontal(|ui| {
+                                ui.label("API Key:");
+                                let field = ui.add_enabled(
+                                    !checking,
+                                    egui::TextEdit::singleline(&mut self.setup_api_key)
+                                        .password(true),
+                                );
+                                if field.changed() {
+                                    self.setup_key_message = None;
+                                }
+                            });
+                            ui.label(
+                                egui::RichText::new("Stored in your system keychain")
+                                    .small()
+                                    .color(theme_colors(ui.ctx()).muted),
                             );
-                        });
+                        } else {
+                            ui.heading(provider.display_name());
+                            ui.add_space(10.0);
+                            ui.label(format!(
+                                "Ollama runs on your computer and needs no key. Rustbot will check \
+                                 that it is running at {}.",
+                                provider.default_api_base()
+                            ));
+                        }
+
+                        if let Some((message, is_error)) = &self.setup_key_message {
+                            let color = if *is_error {
+                                theme_colors(ui.ctx()).error
+                            } else {
+                                theme_colors(ui.ctx()).success
+                            };
+                            ui.add_space(10.0);
+                            ui.label(egui::RichText::new(message).color(color));
+                        }
 
                         ui.add_space(20.0);
                         ui.horizontal(|ui| {
-                            if ui.button("Back").clicked() {
-                                self.setup_wizard_step = SetupWizardStep::EnterEmail;
+                            if ui
+                                .add_enabled(!checking, egui::Button::new("Back"))
+                                .clicked()
+                            {
+                                self.setup_wizard_step = SetupWizardStep::ChooseProvider;
                             }
-                            if ui.button("Finish Setup").clicked()
-                                && !self.setup_api_key.trim().is_empty()
+                            let has_key = !provider.requires_api_key()
+                                || !self.setup_api_key.trim().is_empty();
+                            if ui
+                                .add_enabled(
+                                    has_key && !checking,
+                                    egui::Button::new("Check Key & Finish"),
+                                )
+                                .clicked()
                             {
-                                self.save_setup_wizard_results();
-                                self.setup_wizard_step = SetupWizardStep::Complete;
-                                self.setup_wizard_active = false;
+                                self.start_setup_key_check(ui.ctx());
+                            }
+                            if checking {
+                                ui.spinner();
+                                ui.label("Checking…");
                             }
                         });
                     }
@@ -1260,8 +5247,74 @@ This information is provided automatically to give you context about the current
             });
     }
 
+    /// Check the wizard's API key with a live call to the chosen provider
+    fn start_setup_key_check(&mut self, ctx: &egui::Context) {
+        let provider = self.setup_provider;
+        let api_key = self.setup_api_key.trim().to_string();
+        let runtime = &self.runtime;
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let ctx = ctx.clone();
+        runtime.spawn(async move {
+            let _ = tx.send(llm::validate_api_key(provider, &api_key).await);
+            ctx.request_repaint();
+        });
+        self.setup_key_check_rx = Some(rx);
+        self.setup_key_message = None;
+    }
+
+    /// Finish setup once the provider accepts the key
+    fn poll_setup_key_check(&mut self) {
+        let Some(rx) = &mut self.setup_key_check_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => return,
+            Err(e) => Err(anyhow::Error::from(e)),
+            Ok(result) => result,
+        };
+        self.setup_key_check_rx = None;
+
+        let saved = result.and_then(|()| Ok(self.save_setup_wizard_results()?));
+        match saved {
+            Ok(()) => {
+                self.setup_wizard_step = SetupWizardStep::Complete;
+                self.setup_wizard_active = false;
+            }
+            Err(e) => self.setup_key_message = Some((format!("{:#}", e), true)),
+        }
+    }
+
     /// Save setup wizard results to storage
-    fn save_setup_wizard_results(&self) {
+    ///
+    /// The API key goes to the OS keychain only, under the provider's
+    /// environment variable name, and is exposed in this process's
+    /// environment so agent configs resolve it right away.
+    ///
+    /// # Errors
+    /// - Keychain unavailable (the key is not written anywhere else)
+    fn save_setup_wizard_results(&mut self) -> Result<()> {
+        let provider = self.setup_provider;
+        let api_key = self.setup_api_key.trim().to_string();
+        let key_name = provider.default_env_var();
+        if provider.requires_api_key() {
+            services::DefaultSecretStore::system()
+                .set_in_keychain(key_name, &api_key)
+                .map_err(|e| {
+                    RustbotError::ConfigError(format!(
+                        "Couldn't save the key to the system keychain ({}). Set {} in your \
+                         environment instead.",
+                        e, key_name
+                    ))
+                })?;
+            std::env::set_var(key_name, &api_key);
+            redact::register_secret(&api_key);
+        }
+
+        // Send requests to the chosen provider with the new key
+        self.use_provider(provider, api_key);
+        self.reload_config();
+
         // Save user profile
         let profile = services::traits::UserProfile {
             name: self.setup_name.clone(),
@@ -1269,27 +5322,75 @@ This information is provided automatically to give you context about the current
             timezone: None,
             location: None,
             theme: "light".to_string(), // Default to light theme
+            provider,
+            notifications: true,
+            font_size: self.font_size,
+            ui_scale: self.ui_scale,
+            privacy_mode: self.privacy_mode,
+            allowed_hosts: self.allowed_hosts(),
+            offline_model: self.offline_model(),
+            analytics: self.analytics_enabled,
+            injection_warnings: self.injection_warnings,
+            quota: quota::tracker().limits(),
+            system_context: self.system_context.clone(),
+            active_project: self.active_project.clone(),
+            schema_version: migration::USER_PROFILE_SCHEMA.current,
         };
 
-        let storage = Arc::clone(&self.deps.storage);
-        if let Some(runtime) = &self.deps.runtime {
-            runtime.spawn(async move {
-                let _ = storage.save_user_profile(&profile).await;
-            });
+        self.app_state.update_profile(move |saved| *saved = profile);
+
+        Ok(())
+    }
+
+    /// Apply a rustbot:// link: switch agent, pre-fill the prompt and, for
+    /// `send=1`, ask whether to send it (links never send by themselves)
+    ///
+    /// # Errors
+    /// - Unknown agent
+    /// - A response is still streaming (the agent switch needs the API)
+    fn open_deep_link(
+        &mut self,
+        ctx: &egui::Context,
+        link: deep_link::DeepLink,
+    ) -> std::result::Result<(), String> {
+        tracing::info!(
+            "🔗 Opening link (agent: {:?}, send: {})",
+            link.agent,
+            link.send
+        );
+        self.current_view = AppView::Chat;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+
+        if self.is_waiting && link.agent.is_some() {
+            return Err("Rustbot is still answering the previous message".to_string());
         }
 
-        // Save API key to .env.local
-        let env_path = std::path::PathBuf::from(".env.local");
-        let env_content = format!("OPENROUTER_API_KEY={}", self.setup_api_key);
-        let _ = std::fs::write(&env_path, env_content);
+        for step in link.steps() {
+            match step {
+                deep_link::LinkStep::SwitchAgent(agent) => {
+                    let Ok(mut api) = self.api.try_lock() else {
+                        return Err("Rustbot is busy; open the link again in a moment".to_string());
+                    };
+                    api.switch_agent_in(&self.api_session, &agent)
+                        .map_err(|e| e.to_string())?;
+                    drop(api);
+                    self.session.agent_id = agent;
+                }
+                deep_link::LinkStep::Prefill(prompt) => self.message_input = prompt,
+                deep_link::LinkStep::ConfirmSend => {
+                    self.link_send_confirm = Some(self.message_input.clone());
+                }
+            }
+        }
+        Ok(())
     }
 
     fn handle_user_message_event(&mut self, _ctx: &egui::Context, content: String) {
         // Calculate input tokens
         let input_tokens = self.estimate_tokens(&content);
-        self.token_stats.daily_input += input_tokens;
-        self.token_stats.total_input += input_tokens;
-        let _ = self.save_token_stats();
+        let cost = self.calculate_cost(input_tokens as u64, 0);
+        self.token_stats.record(input_tokens as u64, 0, cost);
+        self.save_token_stats();
 
         // Add user message to UI
         self.messages.push(ChatMessage {
@@ -1298,6 +5399,14 @@ This information is provided automatically to give you context about the current
             input_tokens: Some(input_tokens),
             output_tokens: None,
             embedded_images: Vec::new(), // User messages don't have embedded images
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+            timestamp: Some(chrono::Utc::now()),
+            first_token_ms: None,
+            generation_ms: None,
+            pinned: false,
+            feedback: None,
+            failed: false,
         });
 
         // Add placeholder for assistant response
@@ -1307,20 +5416,21 @@ This information is provided automatically to give you context about the current
             input_tokens: None,
             output_tokens: None,
             embedded_images: Vec::new(), // Will be populated when content is set
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+            timestamp: Some(chrono::Utc::now()),
+            first_token_ms: None,
+            generation_ms: None,
+            pinned: false,
+            feedback: None,
+            failed: false,
         });
 
         self.is_waiting = true;
         self.current_response.clear();
 
         // Update context tracker
-        let system_content_tokens = self.estimate_tokens(&self.generate_system_context());
-        let conversation_total_tokens: u32 = self
-            .messages
-            .iter()
-            .map(|msg| self.estimate_tokens(&msg.content))
-            .sum();
-        self.context_tracker
-            .update_counts(system_content_tokens, conversation_total_tokens);
+        self.update_context_tracker();
 
         // Spawn async task to send message
         let (tx, rx) = mpsc::unbounded_channel();
@@ -1328,26 +5438,120 @@ This information is provided automatically to give you context about the current
 
         // Spawn async task using tokio runtime
         let api = Arc::clone(&self.api);
-        let runtime = self
-            .deps
-            .runtime
-            .as_ref()
-            .expect("Runtime is required for RustbotApp");
-        runtime.spawn(async move {
-            // Lock the API, call send_message, then release lock
-            let mut api_guard = api.lock().await;
-            let result = api_guard.send_message(&content).await;
+        let api_session = self.api_session.clone();
+        let pinned = self.pinned_context();
+        let tool_overrides = self.tool_overrides.clone();
+        let retrieved = self.folder_excerpts(&content);
+        let context = self.generate_system_context();
+        let runtime = &self.runtime;
+        let task = runtime.spawn(async move {
+            // Lock the API for the setup and the turn; the turn lets go of it
+            // while a file access prompt is open
+            let mut api_guard = api.lock_owned().await;
+            api_guard.set_system_context(Some(context));
+            if let Err(e) = api_guard.set_pinned_in(&api_session, pinned) {
+                let _ = tx.send(Err(e));
+                return;
+            }
+            if let Err(e) = api_guard.set_retrieved_in(&api_session, retrieved) {
+                let _ = tx.send(Err(e));
+                return;
+            }
+            if let Err(e) = api_guard.set_tool_overrides_in(&api_session, tool_overrides) {
+                let _ = tx.send(Err(e));
+                return;
+            }
+            let result =
+                RustbotApi::send_message_shared(api_guard, &api_session, &content, Vec::new())
+                    .await;
             let _ = tx.send(result);
         });
+        self.turn_task = Some(task.abort_handle());
+        self.turn_started = Some((std::time::Instant::now(), true));
+    }
+
+    /// Persist state and stop plugins before the window closes
+    ///
+    /// Waits up to `SHUTDOWN_TIMEOUT` for session saves still running, then
+    /// saves the visible conversation, flushes pending token stats and
+    /// profile changes, and stops MCP plugin processes so they aren't left
+    /// running as orphans. The crash recovery state is cleared last, unless
+    /// the user hasn't answered an offer to restore it yet. After a backup
+    /// restore there is no writer, so the restored conversations are kept.
+    fn shutdown(&mut self) {
+        tracing::info!("👋 Shutting down");
+        let runtime = Arc::clone(&self.runtime);
+
+        if let Some(server) = self.ipc_server.take() {
+            server.stop();
+        }
+
+        // A partial answer is kept as streamed so far
+        if !self.messages.is_empty() {
+            self.save_session();
+        }
+        let mut pending_saves = std::mem::take(&mut self.pending_saves);
+        let writer = self.writer.take();
+        let profile_flushed = self.app_state.flush();
+        let mcp_manager = Arc::clone(&self.mcp_manager);
+        let storage = Arc::clone(&self.deps.storage);
+        let clear_recovery = self.recovery_offer.is_none();
+
+        let finished = runtime.block_on(tokio::time::timeout(ui::types::SHUTDOWN_TIMEOUT, async {
+            let stop_plugins = async {
+                for (id, result) in mcp_manager.lock().await.stop_all().await {
+                    if let Err(e) = result {
+                        tracing::warn!("Failed to stop MCP plugin '{}': {}", id, e);
+                    }
+                }
+            };
+            let profile_saved = async {
+                if profile_flushed.await.is_err() {
+                    tracing::warn!("App state actor stopped before saving the profile");
+                }
+            };
+            let save_state = async {
+                // Tasks still looking up history hold the queue open
+                while pending_saves.join_next().await.is_some() {}
+                if let Some(writer) = writer {
+                    writer.flush().await;
+                }
+                // After any recovery save still queued above
+                if clear_recovery {
+                    if let Err(e) = storage.clear_recovery().await {
+                        tracing::warn!("Failed to clear recovery state: {}", e);
+                    }
+                }
+            };
+            tokio::join!(stop_plugins, profile_saved, save_state);
+        }));
+        if finished.is_err() {
+            tracing::warn!(
+                "Shutdown took longer than {:?}; some state may not be saved",
+                ui::types::SHUTDOWN_TIMEOUT
+            );
+        }
     }
 }
 
 impl eframe::App for RustbotApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Check splash screen timer (show for 2 seconds)
+        // Results from the app state actor
+        self.poll_startup();
+        self.poll_agent_reload();
+        while self.pending_saves.try_join_next().is_some() {}
+        self.save_recovery(ctx);
+        if self.agent_reload_rx.is_some() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+        }
+
+        self.poll_mcp_startup();
+
+        // Show the splash screen until the startup state has loaded (and for
+        // at least SPLASH_MIN_DURATION)
         if self.show_splash {
             if let Some(start) = self.splash_start_time {
-                if start.elapsed().as_secs() < 2 {
+                if start.elapsed() < ui::types::SPLASH_MIN_DURATION || self.startup_rx.is_some() {
                     self.render_splash_screen(ctx);
                     ctx.request_repaint();
                     return;
@@ -1364,20 +5568,40 @@ impl eframe::App for RustbotApp {
             return;
         }
 
+        // rustbot:// links from the command line and macOS Apple Events
+        if let Some(link) = self.pending_deep_link.take() {
+            if let Err(e) = self.open_deep_link(ctx, link) {
+                tracing::warn!("Failed to open link: {}", e);
+            }
+        }
+        for url in deep_link::take_pending() {
+            let result = deep_link::DeepLink::parse(&url)
+                .map_err(|e| e.to_string())
+                .and_then(|link| self.open_deep_link(ctx, link));
+            if let Err(e) = result {
+                tracing::warn!("Failed to open link: {}", e);
+            }
+        }
+
         // Process events from the event bus
         // Use a flag to track if we processed any events
         let mut events_processed = false;
 
-        while let Ok(event) = self.event_rx.try_recv() {
+        while let Some(event) = self.event_rx.try_recv() {
             events_processed = true;
 
-            // Track event for visualization (keep last 50 events)
+            // Track event for visualization (keep last 200 events)
             let event_kind_str = match &event.kind {
                 EventKind::UserMessage(_) => "UserMessage".to_string(),
                 EventKind::AgentMessage { .. } => "AgentMessage".to_string(),
+                EventKind::ToolCall { .. } => "ToolCall".to_string(),
+                EventKind::Handoff { .. } => "Handoff".to_string(),
                 EventKind::AgentStatusChange { .. } => "StatusChange".to_string(),
                 EventKind::SystemCommand(_) => "SystemCommand".to_string(),
                 EventKind::McpPluginEvent(_) => "McpPlugin".to_string(),
+                EventKind::SubscriberLagged { .. } => "Lagged".to_string(),
+                EventKind::Request { .. } => "Request".to_string(),
+                EventKind::Response { .. } => "Response".to_string(),
                 EventKind::Test(_) => "Test".to_string(),
             };
 
@@ -1387,10 +5611,11 @@ impl eframe::App for RustbotApp {
                 destination: event.destination.clone(),
                 kind: event_kind_str,
                 timestamp: event.timestamp,
+                correlation_id: event.correlation_id.clone(),
             });
 
-            // Keep only last 50 events - pop from front (O(1) operation)
-            if self.event_history.len() > 50 {
+            // Keep only last 200 events - pop from front (O(1) operation)
+            if self.event_history.len() > 200 {
                 self.event_history.pop_front();
             }
 
@@ -1405,8 +5630,22 @@ impl eframe::App for RustbotApp {
                         tracing::info!("Received agent message from {}: {}", agent_id, content);
                         // Agent messages are already handled in streaming
                     }
+                    EventKind::ToolCall { session_id, call } => {
+                        self.show_tool_call(&session_id, call);
+                    }
+                    EventKind::Handoff {
+                        session_id,
+                        handoff,
+                    } => {
+                        self.show_handoff(&session_id, handoff);
+                    }
                     EventKind::AgentStatusChange { agent_id, status } => {
-                        tracing::info!("Agent {} status changed to {:?}", agent_id, status);
+                        tracing::info!(
+                            "Agent {} status changed to {:?} (correlation_id: {:?})",
+                            agent_id,
+                            status,
+                            event.correlation_id
+                        );
 
                         // Update current activity based on agent status
                         use events::AgentStatus;
@@ -1438,6 +5677,10 @@ impl eframe::App for RustbotApp {
                             SystemCommand::LoadState => {
                                 tracing::info!("Load state command received");
                             }
+                            SystemCommand::ReloadConfig => {
+                                tracing::info!("Reload config command received");
+                                self.reload_config();
+                            }
                         }
                     }
                     EventKind::McpPluginEvent(plugin_event) => {
@@ -1448,6 +5691,80 @@ impl eframe::App for RustbotApp {
                             plugins_view.handle_mcp_event(&plugin_event);
                         }
                     }
+                    EventKind::SubscriberLagged {
+                        subscriber,
+                        skipped,
+                    } => {
+                        tracing::warn!(
+                            "Event subscriber '{}' dropped {} events",
+                            subscriber,
+                            skipped
+                        );
+                    }
+                    EventKind::Request {
+                        ref method,
+                        ref params,
+                    } if method == email::APPROVAL_METHOD => {
+                        // Shown by render_email_approval_dialog, answered on click
+                        match serde_json::from_value::<email::EmailDraft>(params.clone()) {
+                            Ok(draft) => self.email_approvals.push_back((event.clone(), draft)),
+                            Err(e) => {
+                                let _ = self.deps.event_bus.respond(
+                                    &event,
+                                    "user",
+                                    Err(format!("Invalid email draft: {}", e)),
+                                );
+                            }
+                        }
+                    }
+                    EventKind::Request {
+                        ref method,
+                        ref params,
+                    } if method == git_tools::APPROVAL_METHOD => {
+                        // Shown by render_git_approval_dialog, answered on click
+                        match serde_json::from_value::<git_tools::CommitDraft>(params.clone()) {
+                            Ok(draft) => self.git_approvals.push_back((event.clone(), draft)),
+                            Err(e) => {
+                                let _ = self.deps.event_bus.respond(
+                                    &event,
+                                    "user",
+                                    Err(format!("Invalid commit: {}", e)),
+                                );
+                            }
+                        }
+                    }
+                    EventKind::Request {
+                        ref method,
+                        ref params,
+                    } if method == fs_consent::CONSENT_METHOD => {
+                        // Shown by render_fs_consent_dialog, answered on click
+                        match serde_json::from_value::<fs_consent::ConsentRequest>(params.clone()) {
+                            Ok(request) => {
+                                self.fs_consent_requests.push_back((event.clone(), request))
+                            }
+                            Err(e) => {
+                                let _ = self.deps.event_bus.respond(
+                                    &event,
+                                    "user",
+                                    Err(format!("Invalid file access request: {}", e)),
+                                );
+                            }
+                        }
+                    }
+                    EventKind::Request {
+                        ref method,
+                        ref params,
+                    } if method == deep_link::OPEN_METHOD => {
+                        // Forwarded over IPC; the sender waits for the outcome
+                        let result = serde_json::from_value::<deep_link::DeepLink>(params.clone())
+                            .map_err(|e| format!("Invalid link: {}", e))
+                            .and_then(|link| self.open_deep_link(ctx, link))
+                            .map(|()| serde_json::Value::Null);
+                        let _ = self.deps.event_bus.respond(&event, "user", result);
+                    }
+                    EventKind::Request { .. } | EventKind::Response { .. } => {
+                        // RPC traffic is handled by EventBus::request callers
+                    }
                     EventKind::Test(msg) => {
                         tracing::info!("Test event received: {}", msg);
                     }
@@ -1455,16 +5772,53 @@ impl eframe::App for RustbotApp {
             }
         }
 
+        // Finish Google Calendar sign-in once the browser redirect arrives
+        self.poll_google_sign_in();
+        self.poll_setup_key_check();
+        self.poll_folder_index();
+        self.poll_connectivity(ctx);
+        self.poll_share();
+        self.poll_conversation_export();
+        self.poll_commit_draft();
+        self.poll_usage();
+        self.poll_history();
+        self.poll_restore_session();
+        self.poll_settings_bundle();
+        self.poll_backup();
+        self.poll_history_import();
+        self.poll_feedback_export();
+        self.poll_context_preview();
+        self.poll_tool_sources();
+        if self.commit_draft_rx.is_some()
+            || self.usage_rx.is_some()
+            || self.history_rx.is_some()
+            || self.restore_rx.is_some()
+            || self.settings_export_rx.is_some()
+            || self.settings_import_rx.is_some()
+            || self.backup_rx.is_some()
+            || self.conversation_export_rx.is_some()
+            || self.share_save_rx.is_some()
+            || self.history_import_rx.is_some()
+            || self.feedback_export_rx.is_some()
+            || self.folder_index_load_rx.is_some()
+            || self.context_preview_rx.is_some()
+            || self.tool_sources_rx.is_some()
+        {
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+        }
+        self.poll_prompt_test();
+
         // Request immediate repaint if we processed any events
         // This ensures the event visualizer updates immediately
         if events_processed {
             ctx.request_repaint();
         }
 
-        // Update spinner rotation when waiting
+        // Update spinner rotation when waiting; frames are paced while a
+        // response streams, so the spinner turns by elapsed time
         if self.is_waiting {
-            self.spinner_rotation += 0.1;
-            ctx.request_repaint();
+            self.spinner_rotation += ui::types::SPINNER_SPEED * ctx.input(|i| i.stable_dt);
+            ctx.request_repaint_after(ui::types::STREAM_FRAME_INTERVAL);
         }
 
         // Handle keyboard shortcuts
@@ -1478,121 +5832,65 @@ impl eframe::App for RustbotApp {
             if i.modifiers.command && i.key_pressed(egui::Key::R) {
                 self.reload_config();
             }
-        });
-
-        // Check for pending agent result (from non-blocking async task)
-        if let Some(result_rx) = &mut self.pending_agent_result {
-            match result_rx.try_recv() {
-                Ok(result) => {
-                    tracing::info!("Received agent processing result");
-                    // Agent processing completed, handle result
-                    match result {
-                        Ok(rx) => {
-                            tracing::info!("Agent processing succeeded, starting stream");
-                            // Successfully got response stream, start receiving chunks
-                            self.response_rx = Some(rx);
-                            self.pending_agent_result = None; // Clear the pending result
-                            ctx.request_repaint();
-                        }
-                        Err(e) => {
-                            // Error occurred during agent processing
-                            tracing::error!("Failed to process message through agent: {}", e);
-                            self.is_waiting = false;
-
-                            // Add error message visible to user
-                            if let Some(last_msg) = self.messages.last_mut() {
-                                last_msg.content = format!(
-                                    "⚠️ Error: {}\n\nPlease try again or check your connection.",
-                                    e
-                                );
-                            }
-
-                            self.pending_agent_result = None; // Clear the pending result
-                            ctx.request_repaint();
-                        }
-                    }
-                }
-                Err(mpsc::error::TryRecvError::Empty) => {
-                    // Still waiting for result, request repaint to check again
-                    ctx.request_repaint();
-                }
-                Err(mpsc::error::TryRecvError::Disconnected) => {
-                    tracing::error!("Agent result channel disconnected unexpectedly");
-                    self.is_waiting = false;
-                    self.pending_agent_result = None;
-
-                    // Add error message
-                    if let Some(last_msg) = self.messages.last_mut() {
-                        last_msg.content =
-                            "⚠️ Error: Agent processing failed unexpectedly.\n\nPlease try again."
-                                .to_string();
-                    }
 
-                    ctx.request_repaint();
-                }
+            // Cmd+T / Cmd+W to open and close chat tabs
+            if i.modifiers.command && i.key_pressed(egui::Key::T) {
+                self.new_tab();
             }
-        }
-
-        // Check for streaming responses
-        if let Some(rx) = &mut self.response_rx {
-            while let Ok(chunk) = rx.try_recv() {
-                self.current_response.push_str(&chunk);
-
-                // Update the last message (assistant response)
-                if let Some(last_msg) = self.messages.last_mut() {
-                    last_msg.content = self.current_response.clone();
-                }
-
-                ctx.request_repaint(); // Request repaint for each chunk
+            if i.modifiers.command && i.key_pressed(egui::Key::W) {
+                self.close_tab(self.active_tab);
             }
 
-            // Check if stream is done
-            if rx.is_closed() && !self.current_response.is_empty() {
-                // Calculate output tokens for the completed response
-                let output_tokens = self.estimate_tokens(&self.current_response);
-                self.token_stats.daily_output += output_tokens;
-                self.token_stats.total_output += output_tokens;
-
-                // Save stats after updating
-                let _ = self.save_token_stats();
-
-                // Preprocess mermaid diagrams in the response once when content is finalized
-                let preprocessed_content = self.preprocess_mermaid(&self.current_response);
-
-                // Update the last message with token count and preprocessed content
-                if let Some(last_msg) = self.messages.last_mut() {
-                    last_msg.output_tokens = Some(output_tokens);
-                    last_msg.content = preprocessed_content.clone();
-                    // Extract embedded image data URLs for easy access
-                    last_msg.embedded_images = Self::extract_image_data_urls(&preprocessed_content);
+            // Cmd+1 ... Cmd+9 to show a chat tab by position
+            let number_keys = [
+                egui::Key::Num1,
+                egui::Key::Num2,
+                egui::Key::Num3,
+                egui::Key::Num4,
+                egui::Key::Num5,
+                egui::Key::Num6,
+                egui::Key::Num7,
+                egui::Key::Num8,
+                egui::Key::Num9,
+            ];
+            for (index, key) in number_keys.into_iter().enumerate() {
+                if i.modifiers.command && i.key_pressed(key) {
+                    self.select_tab(index);
                 }
+            }
 
-                // Add assistant response to API's message history
-                // This ensures the next message will have this response as context
-                let api = Arc::clone(&self.api);
-                let response = self.current_response.clone();
-                let runtime = self
-                    .deps
-                    .runtime
-                    .as_ref()
-                    .expect("Runtime is required for RustbotApp");
-                runtime.spawn(async move {
-                    let mut api_guard = api.lock().await;
-                    api_guard.add_assistant_response(response);
-                });
+            // Cmd+L to jump to the message input from anywhere
+            if i.modifiers.command && i.key_pressed(egui::Key::L) {
+                self.current_view = AppView::Chat;
+                self.focus_input_requested = true;
+            }
+        });
 
-                self.response_rx = None;
-                self.current_response.clear();
-                self.is_waiting = false;
+        // Poll agent results, streams, retries and diagrams, including background tabs
+        for index in 0..self.tabs.len() {
+            let tab = &self.tabs[index];
+            let busy = tab.is_waiting
+                || tab.retry.scheduled.is_some()
+                || !tab.pending_diagrams.is_empty()
+                || (!tab.outbox.is_empty() && connectivity::can_chat());
+            if index != self.active_tab && busy {
+                self.swap_tab(index);
+                self.poll_retry(ctx);
+                self.poll_response(ctx);
+                self.poll_diagrams();
+                self.poll_outbox(ctx);
+                self.swap_tab(index);
+                // Paced like the visible tab's stream
+                ctx.request_repaint_after(ui::types::STREAM_FRAME_INTERVAL);
             }
         }
+        self.poll_retry(ctx);
+        self.poll_response(ctx);
+        self.poll_diagrams();
+        self.poll_outbox(ctx);
 
         // Apply theme based on user preference
-        if self.dark_mode {
-            self.apply_dark_theme(ctx);
-        } else {
-            self.apply_light_theme(ctx);
-        }
+        self.apply_theme(ctx);
 
         // Sidebar panel
         if self.sidebar_open {
@@ -1607,7 +5905,11 @@ impl eframe::App for RustbotApp {
                             ui.with_layout(
                                 egui::Layout::right_to_left(egui::Align::Center),
                                 |ui| {
-                                    if ui.button(icons::CARET_LEFT).clicked() {
+                                    if ui
+                                        .button(icons::CARET_LEFT)
+                                        .accessible_label("Hide menu")
+                                        .clicked()
+                                    {
                                         self.sidebar_open = false;
                                     }
                                 },
@@ -1655,6 +5957,34 @@ impl eframe::App for RustbotApp {
 
                         ui.add_space(5.0);
 
+                        // History button
+                        ui.horizontal(|ui| {
+                            let history_button = ui.selectable_label(
+                                self.current_view == AppView::History,
+                                format!("{} History", icons::CLOCK_COUNTER_CLOCKWISE),
+                            );
+                            if history_button.clicked() {
+                                self.current_view = AppView::History;
+                                self.refresh_history();
+                            }
+                        });
+
+                        ui.add_space(5.0);
+
+                        // Usage dashboard
+                        ui.horizontal(|ui| {
+                            let usage_button = ui.selectable_label(
+                                self.current_view == AppView::Usage,
+                                format!("{} Usage", icons::CHART_BAR),
+                            );
+                            if usage_button.clicked() {
+                                self.current_view = AppView::Usage;
+                                self.refresh_usage();
+                            }
+                        });
+
+                        ui.add_space(5.0);
+
                         // Extensions button (was Marketplace)
                         ui.horizontal(|ui| {
                             let extensions_button = ui.add(egui::SelectableLabel::new(
@@ -1679,7 +6009,7 @@ impl eframe::App for RustbotApp {
                             ui.label(
                                 egui::RichText::new("⌘R")
                                     .size(12.0)
-                                    .color(egui::Color32::from_rgb(120, 120, 120)),
+                                    .color(theme_colors(ui.ctx()).muted),
                             );
                         });
 
@@ -1714,89 +6044,37 @@ impl eframe::App for RustbotApp {
                                 .max_height(300.0)
                                 .auto_shrink([false; 2])
                                 .show(ui, |ui| {
-                                    if self.event_history.is_empty() {
-                                        ui.label(
-                                            egui::RichText::new("No events yet")
-                                                .size(11.0)
-                                                .color(egui::Color32::from_rgb(120, 120, 120)),
-                                        );
-                                    } else {
-                                        // Show most recent events first
-                                        for event in self.event_history.iter().rev().take(10) {
-                                            ui.group(|ui| {
-                                                ui.set_width(ui.available_width());
-
-                                                // Event kind with color coding
-                                                let (color, icon) = match event.kind.as_str() {
-                                                    "UserMessage" => (
-                                                        egui::Color32::from_rgb(100, 150, 255),
-                                                        "📤",
-                                                    ),
-                                                    "AgentMessage" => (
-                                                        egui::Color32::from_rgb(100, 255, 150),
-                                                        "📥",
-                                                    ),
-                                                    "StatusChange" => (
-                                                        egui::Color32::from_rgb(255, 200, 100),
-                                                        "🔄",
-                                                    ),
-                                                    "SystemCommand" => (
-                                                        egui::Color32::from_rgb(255, 100, 100),
-                                                        "⚙️",
-                                                    ),
-                                                    _ => (
-                                                        egui::Color32::from_rgb(150, 150, 150),
-                                                        "📨",
-                                                    ),
-                                                };
-
-                                                ui.horizontal(|ui| {
-                                                    ui.label(egui::RichText::new(icon).size(10.0));
-                                                    ui.label(
-                                                        egui::RichText::new(&event.kind)
-                                                            .size(10.0)
-                                                            .color(color),
-                                                    );
-                                                });
-
-                                                // Source → Destination
-                                                ui.label(
-                                                    egui::RichText::new(format!(
-                                                        "{} → {}",
-                                                        event.source, event.destination
-                                                    ))
-                                                    .size(9.0)
-                                                    .color(egui::Color32::from_rgb(150, 150, 150)),
-                                                );
-
-                                                // Timestamp
-                                                ui.label(
-                                                    egui::RichText::new(
-                                                        event
-                                                            .timestamp
-                                                            .format("%H:%M:%S")
-                                                            .to_string(),
-                                                    )
-                                                    .size(8.0)
-                                                    .color(egui::Color32::from_rgb(100, 100, 100)),
-                                                );
-                                            });
-                                            ui.add_space(3.0);
-                                        }
-                                    }
+                                    // Latest few turns; the Events view shows more
+                                    self.render_event_sequence(ui, 3, false);
                                 });
+
+                            if ui.small_button("Open in Events view").clicked() {
+                                self.current_view = AppView::Events;
+                            }
                         }
                     });
                 });
         }
 
+        // Pinned messages of the visible chat, beside it
+        if self.current_view == AppView::Chat && self.pinned_panel_open {
+            self.render_pinned_panel(ctx);
+        }
+        if self.current_view == AppView::Chat && self.folder_panel_open {
+            self.render_folder_panel(ctx);
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.vertical(|ui| {
                 // Header at top with toggle button and version info
                 ui.horizontal(|ui| {
                     // Sidebar toggle button (hamburger menu)
                     if !self.sidebar_open {
-                        if ui.button(icons::LIST).clicked() {
+                        if ui
+                            .button(icons::LIST)
+                            .accessible_label("Show menu")
+                            .clicked()
+                        {
                             self.sidebar_open = true;
                         }
                     }
@@ -1806,7 +6084,7 @@ impl eframe::App for RustbotApp {
                     ui.label(
                         egui::RichText::new(version::version_string())
                             .size(14.0)
-                            .color(egui::Color32::from_rgb(120, 120, 120)),
+                            .color(theme_colors(ui.ctx()).muted),
                     );
                 });
                 ui.separator();
@@ -1816,9 +6094,47 @@ impl eframe::App for RustbotApp {
                     AppView::Chat => self.render_chat_view(ui, ctx),
                     AppView::Settings => self.render_settings_view(ui),
                     AppView::Events => self.render_events_view(ui),
+                    AppView::History => self.render_history_view(ui),
+                    AppView::Usage => self.render_usage_view(ui),
                     AppView::Extensions => self.render_extensions_view(ui, ctx),
                 }
             });
         });
+
+        // Emails an agent wants to send wait for an explicit yes or no
+        if !self.email_approvals.is_empty() {
+            self.render_email_approval_dialog(ctx);
+        }
+
+        // Agents' commits wait for an explicit yes or no
+        if !self.git_approvals.is_empty() {
+            self.render_git_approval_dialog(ctx);
+        }
+
+        // Plugins wait for consent before touching a new directory
+        if !self.fs_consent_requests.is_empty() {
+            self.render_fs_consent_dialog(ctx);
+        }
+
+        // A rustbot:// link's prompt is only sent once the user confirms
+        if self.link_send_confirm.is_some() {
+            self.render_link_send_dialog(ctx);
+        }
+
+        if self.context_inspector_open {
+            self.render_context_inspector(ctx);
+        }
+
+        if let Some(viewer) = &mut self.diagram_viewer {
+            if !viewer.show(ctx) {
+                self.diagram_viewer = None;
+            }
+        }
+
+        if self.agent_wizard.is_some() {
+            self.render_agent_wizard(ctx);
+        }
+
+        self.render_chat_windows(ctx);
     }
 }
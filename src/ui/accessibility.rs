@@ -0,0 +1,74 @@
+// Screen-reader names for widgets and keyboard focus on chat messages
+//
+// Design Decision: Name icon-only widgets where they are created
+//
+// Rationale: egui exposes widgets to screen readers through AccessKit, using
+// the widget's text as its name. For buttons that only show a Phosphor icon
+// that text is a private-use glyph, so VoiceOver/Narrator/Orca announce
+// nothing useful. `AccessibleLabel::accessible_label` replaces the name and
+// sets the same text as the tooltip, so sighted and screen-reader users see
+// the same description.
+//
+// Chat messages are plain text and can't take focus on their own; the view
+// adds a focus stop on each message header (see `message_focus`) so Tab walks
+// through the conversation and the screen reader reads each message.
+//
+// Trade-offs: Names are English only, like the rest of the UI.
+
+use eframe::egui;
+
+/// Give a widget a screen-reader name and a matching tooltip
+pub trait AccessibleLabel {
+    fn accessible_label(self, label: &str) -> Self;
+}
+
+impl AccessibleLabel for egui::Response {
+    fn accessible_label(self, label: &str) -> Self {
+        let enabled = self.enabled();
+        self.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Button, enabled, label));
+        self.on_hover_text(label)
+    }
+}
+
+/// Id of the focus stop on a chat message header
+///
+/// `list` is the id of the `Ui` the messages are drawn in.
+pub fn message_focus_id(list: egui::Id, index: usize) -> egui::Id {
+    list.with(("message_focus", index))
+}
+
+/// Make a message header a keyboard focus stop that reads out the message
+///
+/// # Arguments
+/// * `ui` - Ui the header was drawn in
+/// * `header` - Rect of the header label
+/// * `id` - From `message_focus_id`
+/// * `author` - "You" or "Assistant"
+/// * `content` - Message text read by the screen reader
+pub fn message_focus(
+    ui: &egui::Ui,
+    header: egui::Rect,
+    id: egui::Id,
+    author: &str,
+    content: &str,
+) -> egui::Response {
+    let response = ui.interact(header, id, egui::Sense::focusable_noninteractive());
+    response.widget_info(|| {
+        egui::WidgetInfo::labeled(
+            egui::WidgetType::Label,
+            true,
+            format!("{}: {}", author, content),
+        )
+    });
+    response
+}
+
+/// Outline drawn around the message that has keyboard focus
+pub fn focus_ring(ui: &egui::Ui, rect: egui::Rect, color: egui::Color32) {
+    ui.painter().rect_stroke(
+        rect.expand(2.0),
+        4.0,
+        egui::Stroke::new(2.0, color),
+        egui::StrokeKind::Outside,
+    );
+}
@@ -0,0 +1,94 @@
+// Images pasted from the clipboard into the chat
+//
+// Design Decision: Read the clipboard with arboard instead of egui events
+//
+// Rationale: egui only forwards pasted text (`Event::Paste`), and eframe
+// swallows the paste shortcut when the clipboard holds no text at all. The
+// chat input therefore looks for an image on the system clipboard whenever a
+// paste happens, and offers a button for image-only clipboards.
+//
+// Trade-offs: Images are re-encoded as PNG and scaled down to MAX_DIMENSION
+// so a large screenshot doesn't blow up the request (and every later one,
+// since the image stays in the conversation history).
+
+use base64::Engine;
+use eframe::egui;
+
+/// Longest side of an image sent to the model, in pixels
+const MAX_DIMENSION: u32 = 2048;
+
+/// Longest side of a thumbnail in the chat, in points
+const THUMBNAIL_SIZE: f32 = 64.0;
+
+/// Image attached to a user message
+#[derive(Clone)]
+pub struct ImageAttachment {
+    /// PNG data URL sent to the model
+    pub data_url: String,
+    /// Texture for thumbnails
+    pub texture: egui::TextureHandle,
+}
+
+impl ImageAttachment {
+    /// Draw a thumbnail (the image size shows on hover)
+    pub fn thumbnail(&self, ui: &mut egui::Ui) -> egui::Response {
+        let size = self.texture.size_vec2();
+        let scale = (THUMBNAIL_SIZE / size.x.max(size.y)).min(1.0);
+        ui.add(egui::Image::new((self.texture.id(), size * scale)))
+            .on_hover_text(format!("{} × {}", size.x, size.y))
+    }
+}
+
+/// Read an image from the system clipboard
+///
+/// # Returns
+/// `Ok(None)` when the clipboard holds no image
+///
+/// # Errors
+/// The clipboard can't be opened or the image can't be encoded
+pub fn paste_image(ctx: &egui::Context) -> Result<Option<ImageAttachment>, String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    let data = match clipboard.get_image() {
+        Ok(data) => data,
+        Err(arboard::Error::ContentNotAvailable) => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    let image = image::RgbaImage::from_raw(
+        data.width as u32,
+        data.height as u32,
+        data.bytes.into_owned(),
+    )
+    .ok_or("Clipboard image has an unexpected size")?;
+    let image = if image.width().max(image.height()) > MAX_DIMENSION {
+        image::DynamicImage::ImageRgba8(image)
+            .thumbnail(MAX_DIMENSION, MAX_DIMENSION)
+            .to_rgba8()
+    } else {
+        image
+    };
+
+    let mut png = Vec::new();
+    image
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    let data_url = format!(
+        "data:image/png;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(&png)
+    );
+
+    let size = [image.width() as usize, image.height() as usize];
+    let texture = ctx.load_texture(
+        "pasted_image",
+        egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw()),
+        egui::TextureOptions::LINEAR,
+    );
+
+    tracing::info!(
+        "🖼️  Pasted image {}×{} ({} KB)",
+        size[0],
+        size[1],
+        png.len() / 1024
+    );
+    Ok(Some(ImageAttachment { data_url, texture }))
+}
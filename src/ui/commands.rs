@@ -0,0 +1,139 @@
+// Slash commands typed in the chat input
+//
+// Parsed before a message is sent, like the `rustbot chat` REPL commands
+// (`rustbot_core::cli::ReplCommand`). Unknown commands and commands missing
+// their argument are sent as messages, so paths like `/etc/hosts` still work.
+
+/// A command run instead of sending the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlashCommand {
+    /// Start a new conversation in this tab
+    Clear,
+    /// Switch this tab's agent
+    Agent(String),
+    /// Use another model for this tab's agent (None = default model)
+    Model(Option<String>),
+    /// Insert a prompt from the library into the input
+    Prompt(String),
+    /// Open the conversation export dialog
+    Export,
+    /// Open the share dialog (HTML page or gist)
+    Share,
+    /// Open the context inspector
+    Context,
+    /// Ask the agent to draft a commit message for the staged changes
+    Commit,
+    /// List the commands
+    Help,
+}
+
+/// Name, argument and description of a command, for the autocomplete popup
+pub struct CommandInfo {
+    pub name: &'static str,
+    pub args: &'static str,
+    pub description: &'static str,
+}
+
+impl CommandInfo {
+    /// Name with its argument placeholder, e.g. "/agent <id>"
+    pub fn usage(&self) -> String {
+        if self.args.is_empty() {
+            self.name.to_string()
+        } else {
+            format!("{} {}", self.name, self.args)
+        }
+    }
+}
+
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        name: "/clear",
+        args: "",
+        description: "Start a new conversation",
+    },
+    CommandInfo {
+        name: "/agent",
+        args: "<id>",
+        description: "Switch this tab's agent",
+    },
+    CommandInfo {
+        name: "/model",
+        args: "[id]",
+        description: "Use another model for the agent (no id = default)",
+    },
+    CommandInfo {
+        name: "/prompt",
+        args: "<name>",
+        description: "Insert a saved prompt",
+    },
+    CommandInfo {
+        name: "/export",
+        args: "",
+        description: "Export the conversation",
+    },
+    CommandInfo {
+        name: "/share",
+        args: "",
+        description: "Share the conversation as a page or a gist",
+    },
+    CommandInfo {
+        name: "/context",
+        args: "",
+        description: "Inspect what the next message will send",
+    },
+    CommandInfo {
+        name: "/commit",
+        args: "",
+        description: "Draft a commit message for the staged changes",
+    },
+    CommandInfo {
+        name: "/help",
+        args: "",
+        description: "List commands",
+    },
+];
+
+impl SlashCommand {
+    /// Parse the chat input; None means send it as a message
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let (command, arg) = match input.split_once(char::is_whitespace) {
+            Some((command, arg)) => (command, arg.trim()),
+            None => (input, ""),
+        };
+        match command {
+            "/clear" if arg.is_empty() => Some(Self::Clear),
+            "/agent" if !arg.is_empty() => Some(Self::Agent(arg.to_string())),
+            "/model" => Some(Self::Model((!arg.is_empty()).then(|| arg.to_string()))),
+            "/prompt" if !arg.is_empty() => Some(Self::Prompt(arg.to_string())),
+            "/export" if arg.is_empty() => Some(Self::Export),
+            "/share" if arg.is_empty() => Some(Self::Share),
+            "/context" if arg.is_empty() => Some(Self::Context),
+            "/commit" if arg.is_empty() => Some(Self::Commit),
+            "/help" if arg.is_empty() => Some(Self::Help),
+            _ => None,
+        }
+    }
+
+    /// Help text listing every command
+    pub fn help() -> String {
+        COMMANDS
+            .iter()
+            .map(|c| format!("{} — {}", c.usage(), c.description))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Commands whose name starts with the partially typed input
+///
+/// Empty unless the input is a single word starting with `/`.
+pub fn completions(input: &str) -> Vec<&'static CommandInfo> {
+    if !input.starts_with('/') || input.contains(char::is_whitespace) {
+        return Vec::new();
+    }
+    COMMANDS
+        .iter()
+        .filter(|c| c.name.starts_with(input))
+        .collect()
+}
@@ -0,0 +1,172 @@
+// Full-window viewer for rendered diagrams, with zoom and pan
+//
+// Design Decision: Reuse the image the chat already loaded
+//
+// Rationale: Large mermaid diagrams shrink to the message width and become
+// unreadable. Clicking one opens this modal with the same `bytes://` URI
+// `markdown::show` registered, so nothing is decoded or fetched again. The
+// SVG is rasterized at a power-of-two scale at or above the zoom level, so it
+// stays sharp when zoomed in without creating a texture for every zoom step.
+//
+// Controls: Ctrl+scroll or pinch zooms around the pointer, dragging or
+// scrolling pans, "Fit" scales the whole diagram into the window and Escape
+// or a click outside closes the viewer.
+
+use crate::ui::accessibility::AccessibleLabel;
+use crate::ui::theme::colors as theme_colors;
+use eframe::egui;
+use egui_phosphor::regular as icons;
+
+/// Zoom limits, relative to the diagram's own size
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 8.0;
+
+/// Zoom factor of the zoom buttons
+const ZOOM_STEP: f32 = 1.25;
+
+/// An open diagram and how it is zoomed and panned
+#[derive(Debug, Clone)]
+pub struct DiagramViewer {
+    /// Image URI, as registered by `markdown::show`
+    uri: String,
+    zoom: f32,
+
+    /// Offset of the diagram's center from the view's center, in points
+    pan: egui::Vec2,
+
+    /// Fit the diagram into the view on the next frame
+    fit: bool,
+}
+
+impl DiagramViewer {
+    /// Open a diagram, fitted to the window
+    pub fn new(uri: String) -> Self {
+        Self {
+            uri,
+            zoom: 1.0,
+            pan: egui::Vec2::ZERO,
+            fit: true,
+        }
+    }
+
+    /// Draw the viewer
+    ///
+    /// # Returns
+    /// false once the user closed it
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut open = true;
+        let screen = ctx.screen_rect();
+
+        let modal = egui::Modal::new(egui::Id::new("diagram_viewer")).show(ctx, |ui| {
+            let colors = theme_colors(ui.ctx());
+            ui.set_width(screen.width() * 0.85);
+
+            ui.horizontal(|ui| {
+                if ui
+                    .button(icons::MAGNIFYING_GLASS_MINUS)
+                    .accessible_label("Zoom out")
+                    .clicked()
+                {
+                    self.zoom = (self.zoom / ZOOM_STEP).max(MIN_ZOOM);
+                }
+                ui.label(format!("{:.0}%", self.zoom * 100.0));
+                if ui
+                    .button(icons::MAGNIFYING_GLASS_PLUS)
+                    .accessible_label("Zoom in")
+                    .clicked()
+                {
+                    self.zoom = (self.zoom * ZOOM_STEP).min(MAX_ZOOM);
+                }
+                if ui
+                    .button(format!("{} Fit", icons::ARROWS_IN))
+                    .on_hover_text("Fit the diagram into the window")
+                    .clicked()
+                {
+                    self.fit = true;
+                }
+                if ui.button("1:1").on_hover_text("Actual size").clicked() {
+                    self.zoom = 1.0;
+                    self.pan = egui::Vec2::ZERO;
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button(icons::X).accessible_label("Close").clicked() {
+                        open = false;
+                    }
+                    ui.label(
+                        egui::RichText::new("Ctrl+scroll to zoom, drag to pan")
+                            .small()
+                            .color(colors.muted),
+                    );
+                });
+            });
+            ui.add_space(4.0);
+
+            let (rect, response) = ui.allocate_exact_size(
+                egui::vec2(ui.available_width(), screen.height() * 0.75),
+                egui::Sense::drag(),
+            );
+            ui.painter().rect_filled(rect, 6.0, colors.input);
+            self.draw(ui, rect, &response);
+        });
+
+        if modal.should_close() {
+            open = false;
+        }
+        open
+    }
+
+    /// Handle zoom/pan input and paint the diagram into `rect`
+    fn draw(&mut self, ui: &mut egui::Ui, rect: egui::Rect, response: &egui::Response) {
+        // Rasterize at the next power of two so zooming in stays sharp
+        let raster = 2f32.powf(self.zoom.log2().ceil()).clamp(1.0, MAX_ZOOM);
+        let image = egui::Image::new(self.uri.as_str()).fit_to_original_size(raster);
+        let texture = match image.load_for_size(ui.ctx(), rect.size()) {
+            Ok(egui::load::TexturePoll::Ready { texture }) => texture,
+            Ok(egui::load::TexturePoll::Pending { .. }) => {
+                ui.put(rect, egui::Spinner::new());
+                return;
+            }
+            Err(e) => {
+                ui.put(
+                    rect,
+                    egui::Label::new(format!("Couldn't load the diagram: {}", e)),
+                );
+                return;
+            }
+        };
+        // Size at zoom 1.0, in points
+        let size = texture.size / (raster * ui.ctx().pixels_per_point());
+
+        if self.fit {
+            self.fit = false;
+            self.zoom = (rect.width() / size.x)
+                .min(rect.height() / size.y)
+                .clamp(MIN_ZOOM, MAX_ZOOM);
+            self.pan = egui::Vec2::ZERO;
+        }
+
+        if response.hovered() {
+            let (zoom_delta, scroll) = ui.input(|i| (i.zoom_delta(), i.smooth_scroll_delta));
+            if zoom_delta != 1.0 {
+                // Keep the point under the pointer in place
+                let zoom = (self.zoom * zoom_delta).clamp(MIN_ZOOM, MAX_ZOOM);
+                if let Some(pointer) = response.hover_pos() {
+                    let from_center = pointer - rect.center();
+                    self.pan = from_center - (from_center - self.pan) * (zoom / self.zoom);
+                }
+                self.zoom = zoom;
+            } else {
+                self.pan += scroll;
+            }
+        }
+        self.pan += response.drag_delta();
+
+        let image_rect = egui::Rect::from_center_size(rect.center() + self.pan, size * self.zoom);
+        ui.painter().with_clip_rect(rect).image(
+            texture.id,
+            image_rect,
+            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+            egui::Color32::WHITE,
+        );
+    }
+}
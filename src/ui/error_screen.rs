@@ -0,0 +1,128 @@
+// Full-window screen shown when Rustbot can't start
+//
+// Design Decision: Show startup failures in the window instead of panicking
+//
+// Rationale: Building the dependencies or the API used to `expect` success,
+// so a missing API key, a wrong storage passphrase or an unreadable config
+// closed the app before any window appeared, with the reason only on stderr.
+// Those failures now come back as `RustbotError`s and this screen shows
+// them, with a retry and, where there is one, the fix: a missing API key can
+// be entered right here (it goes to the keychain like the setup wizard's).
+//
+// Trade-offs:
+// - Only failures before the chat shows land here; errors afterwards are
+//   still reported inline (command feedback, failed messages)
+
+use crate::error::RustbotError;
+use crate::services;
+use crate::ui::theme::colors as theme_colors;
+use eframe::egui;
+use egui_phosphor::regular as icons;
+
+/// What the user chose on the error screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorScreenAction {
+    /// Try starting again
+    Retry,
+
+    /// Save this API key, then try starting again
+    SaveApiKey(String),
+    Quit,
+}
+
+/// A startup failure and the fix offered for it
+pub struct ErrorScreen {
+    error: String,
+
+    /// The failure is the missing OpenRouter key, so offer to enter it
+    needs_api_key: bool,
+    api_key_input: String,
+
+    /// Why the last fix attempt failed (e.g. keychain unavailable)
+    fix_error: Option<String>,
+}
+
+impl ErrorScreen {
+    pub fn new(error: RustbotError) -> Self {
+        let needs_api_key = matches!(
+            &error,
+            RustbotError::EnvError(message) if message.contains(services::secrets::API_KEY_NAME)
+        );
+        Self {
+            error: error.to_string(),
+            needs_api_key,
+            api_key_input: String::new(),
+            fix_error: None,
+        }
+    }
+
+    /// Report that applying a fix failed; the screen stays open
+    pub fn set_fix_error(&mut self, message: String) {
+        self.fix_error = Some(message);
+    }
+
+    /// Draw the screen
+    ///
+    /// # Returns
+    /// The action the user chose this frame, if any
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<ErrorScreenAction> {
+        let mut action = None;
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let colors = theme_colors(ui.ctx());
+            ui.vertical_centered(|ui| {
+                ui.add_space(ui.available_height() * 0.2);
+                ui.label(
+                    egui::RichText::new(icons::WARNING_CIRCLE)
+                        .size(48.0)
+                        .color(colors.error),
+                );
+                ui.add_space(10.0);
+                ui.heading("Rustbot couldn't start");
+                ui.add_space(10.0);
+                ui.label(egui::RichText::new(&self.error).color(colors.muted));
+                ui.add_space(20.0);
+
+                if self.needs_api_key {
+                    ui.label("Enter your OpenRouter API key to continue:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.api_key_input)
+                            .password(true)
+                            .hint_text("sk-or-...")
+                            .desired_width(320.0),
+                    );
+                    ui.add_space(10.0);
+                }
+                if let Some(fix_error) = &self.fix_error {
+                    ui.label(egui::RichText::new(fix_error).color(colors.error));
+                    ui.add_space(10.0);
+                }
+
+                ui.horizontal(|ui| {
+                    // Center the buttons under the text
+                    ui.add_space((ui.available_width() - 300.0).max(0.0) / 2.0);
+                    let key = self.api_key_input.trim();
+                    if self.needs_api_key {
+                        if ui
+                            .add_enabled(!key.is_empty(), egui::Button::new("Save Key and Start"))
+                            .clicked()
+                        {
+                            action = Some(ErrorScreenAction::SaveApiKey(key.to_string()));
+                        }
+                    } else if ui
+                        .button(format!("{} Retry", icons::ARROW_CLOCKWISE))
+                        .clicked()
+                    {
+                        action = Some(ErrorScreenAction::Retry);
+                    }
+                    if ui.button(format!("{} Copy Details", icons::COPY)).clicked() {
+                        ui.ctx().copy_text(self.error.clone());
+                    }
+                    if ui.button("Quit").clicked() {
+                        action = Some(ErrorScreenAction::Quit);
+                    }
+                });
+            });
+        });
+        action
+    }
+}
@@ -0,0 +1,407 @@
+// Chat markdown rendering with highlighted code blocks
+//
+// Prose goes through egui_commonmark; fenced code blocks (split out by
+// `rustbot_core::markdown`) are drawn here with syntect highlighting, a
+// language label and a copy button.
+//
+// Rendered mermaid diagrams and typeset formulas arrive as
+// `data:image/svg+xml` images. egui picks an image loader by the URI's
+// extension, so `svg_uri` registers each one as `bytes://…svg` for
+// egui_extras' SVG loader. Images on their own line are drawn here rather
+// than by egui_commonmark so they can be clicked to open `DiagramViewer`.
+//
+// Tables (found by `rustbot_core::markdown::extract_tables`) get their own
+// horizontal scroll area, so wide ones don't squeeze the message, and a
+// "Download CSV" button.
+//
+// Splitting a message into blocks (code, prose, images, tables) and decoding
+// its images is done once and kept in `MarkdownCache`. While an answer
+// streams only the part after `rustbot_core::markdown::stable_prefix_len`
+// is split again; egui_commonmark still lays out the prose every frame.
+
+use crate::ui::accessibility::AccessibleLabel;
+use crate::ui::theme::colors as theme_colors;
+use eframe::egui;
+use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
+use egui_extras::syntax_highlighting::{self, CodeTheme};
+use egui_phosphor::regular as icons;
+use regex::Regex;
+use rustbot_core::markdown::{
+    extract_tables, split_code_blocks, stable_prefix_len, MarkdownSegment,
+};
+use rustbot_core::mermaid::DiagramTheme;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::OnceLock;
+
+/// Something clicked in a message that the app handles
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkdownAction {
+    /// Open an embedded diagram in `DiagramViewer` (its image URI)
+    OpenDiagram(String),
+
+    /// Save a table (as CSV)
+    SaveTable(String),
+}
+
+/// Messages held before entries not drawn this frame are dropped
+const MAX_CACHED_MESSAGES: usize = 256;
+
+/// egui_commonmark's cache plus each message split into the blocks `show`
+/// draws
+#[derive(Default)]
+pub struct MarkdownCache {
+    commonmark: CommonMarkCache,
+    messages: HashMap<egui::Id, Prepared>,
+}
+
+impl MarkdownCache {
+    /// Number of messages held
+    pub fn cached_messages(&self) -> usize {
+        self.messages.len()
+    }
+}
+
+/// A message's blocks, split at `stable_prefix_len` so a streaming answer
+/// only re-processes its tail
+struct Prepared {
+    /// Markdown the blocks were made from
+    source: String,
+    stable_len: usize,
+    stable: Vec<Block>,
+    tail: Vec<Block>,
+
+    /// Frame the message was last drawn in
+    last_pass: u64,
+}
+
+/// A piece of a message, ready to draw
+enum Block {
+    /// Markdown for egui_commonmark, inline SVG images already pointed at
+    /// their `bytes://` URIs
+    Prose(String),
+    Table {
+        source: String,
+        csv: String,
+    },
+
+    /// Image on a line of its own (diagram or display formula)
+    Image {
+        uri: String,
+        alt: String,
+    },
+    Code {
+        language: Option<String>,
+        code: String,
+    },
+}
+
+/// Render a chat message's markdown
+///
+/// `id` identifies the message across frames: while its markdown only grows
+/// (a streaming answer), the part before `stable_prefix_len` isn't split,
+/// searched for images and tables or rewritten again.
+///
+/// # Returns
+/// What the user clicked, if anything
+pub fn show(
+    ui: &mut egui::Ui,
+    cache: &mut MarkdownCache,
+    id: egui::Id,
+    markdown: &str,
+) -> Option<MarkdownAction> {
+    let pass = ui.ctx().cumulative_pass_nr();
+    if !cache.messages.contains_key(&id) && cache.messages.len() >= MAX_CACHED_MESSAGES {
+        cache
+            .messages
+            .retain(|_, prepared| prepared.last_pass == pass);
+    }
+
+    let prepared = cache
+        .messages
+        .entry(id)
+        .or_insert_with(|| Prepared::new(ui.ctx(), markdown));
+    prepared.update(ui.ctx(), markdown);
+    prepared.last_pass = pass;
+
+    let mut clicked = None;
+    let mut blocks = prepared.stable.iter().chain(&prepared.tail).peekable();
+    while let Some(block) = blocks.next() {
+        // The last stable prose and the first tail prose are one paragraph
+        // run in the message; render them together so spacing matches
+        if let (Block::Prose(text), Some(Block::Prose(next))) = (block, blocks.peek()) {
+            let joined = format!("{}{}", text, next);
+            blocks.next();
+            commonmark(ui, &mut cache.commonmark, &joined);
+            continue;
+        }
+        if let Some(action) = draw(ui, &mut cache.commonmark, block) {
+            clicked = Some(action);
+        }
+    }
+    clicked
+}
+
+impl Prepared {
+    fn new(ctx: &egui::Context, markdown: &str) -> Self {
+        let stable_len = stable_prefix_len(markdown);
+        Self {
+            source: markdown.to_string(),
+            stable_len,
+            stable: prepare(ctx, &markdown[..stable_len]),
+            tail: prepare(ctx, &markdown[stable_len..]),
+            last_pass: 0,
+        }
+    }
+
+    /// Catch up with the message's current markdown
+    fn update(&mut self, ctx: &egui::Context, markdown: &str) {
+        if self.source == markdown {
+            return;
+        }
+        // Edited or replaced (e.g. diagrams rendered): start over
+        if !markdown.starts_with(&self.source[..self.stable_len]) {
+            *self = Self::new(ctx, markdown);
+            return;
+        }
+
+        let stable_len = self.stable_len + stable_prefix_len(&markdown[self.stable_len..]);
+        if stable_len > self.stable_len {
+            let settled = prepare(ctx, &markdown[self.stable_len..stable_len]);
+            push_blocks(&mut self.stable, settled);
+            self.stable_len = stable_len;
+        }
+        self.tail = prepare(ctx, &markdown[stable_len..]);
+        self.source.clear();
+        self.source.push_str(markdown);
+    }
+}
+
+/// Append blocks, joining prose that continues the last block
+fn push_blocks(blocks: &mut Vec<Block>, more: Vec<Block>) {
+    for block in more {
+        match (blocks.last_mut(), block) {
+            (Some(Block::Prose(text)), Block::Prose(next)) => text.push_str(&next),
+            (_, block) => blocks.push(block),
+        }
+    }
+}
+
+/// Split markdown into the blocks `show` draws
+fn prepare(ctx: &egui::Context, markdown: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    for segment in split_code_blocks(markdown) {
+        match segment {
+            MarkdownSegment::Text(text) => prepare_text(ctx, text, &mut blocks),
+            MarkdownSegment::Code { language, code, .. } => blocks.push(Block::Code {
+                language: language.map(String::from),
+                code: code.to_string(),
+            }),
+        }
+    }
+    blocks
+}
+
+/// Pattern of an embedded SVG image: alt text and base64 data
+fn svg_image_pattern() -> &'static Regex {
+    static SVG_IMAGE: OnceLock<Regex> = OnceLock::new();
+    SVG_IMAGE.get_or_init(|| {
+        Regex::new(r"!\[([^\]]*)\]\(data:image/svg\+xml;base64,([A-Za-z0-9+/=]+)\)")
+            .expect("Invalid regex")
+    })
+}
+
+/// Prose with embedded SVG images
+///
+/// Images on a line of their own (diagrams, display formulas) become image
+/// blocks that can be clicked; images inside a sentence (inline formulas)
+/// stay in the text flow.
+fn prepare_text(ctx: &egui::Context, text: &str, blocks: &mut Vec<Block>) {
+    if !text.contains("data:image/svg+xml;base64,") {
+        prepare_prose(ctx, text, blocks);
+        return;
+    }
+
+    let mut prose_start = 0;
+    for caps in svg_image_pattern().captures_iter(text) {
+        let image = caps.get(0).expect("Capture 0 is the whole match");
+        let before = text[..image.start()].trim_end_matches([' ', '\t']);
+        let after = text[image.end()..].trim_start_matches([' ', '\t', '\r']);
+        let own_line = (before.is_empty() || before.ends_with('\n'))
+            && (after.is_empty() || after.starts_with('\n'));
+        if !own_line {
+            continue;
+        }
+        let Some(uri) = svg_uri(ctx, &caps[2]) else {
+            continue;
+        };
+        prepare_prose(ctx, &text[prose_start..image.start()], blocks);
+        prose_start = image.end();
+
+        let alt = if caps[1].is_empty() {
+            "Diagram"
+        } else {
+            &caps[1]
+        };
+        blocks.push(Block::Image {
+            uri,
+            alt: alt.to_string(),
+        });
+    }
+    prepare_prose(ctx, &text[prose_start..], blocks);
+}
+
+/// Markdown prose, with its tables as separate blocks
+fn prepare_prose(ctx: &egui::Context, text: &str, blocks: &mut Vec<Block>) {
+    let mut rest = 0;
+    for table in extract_tables(text) {
+        push_blocks(
+            blocks,
+            vec![Block::Prose(inline_images(ctx, &text[rest..table.start]))],
+        );
+        rest = table.end;
+        blocks.push(Block::Table {
+            source: inline_images(ctx, &text[table.start..table.end]),
+            csv: table.to_csv(),
+        });
+    }
+    push_blocks(
+        blocks,
+        vec![Block::Prose(inline_images(ctx, &text[rest..]))],
+    );
+}
+
+/// Point inline SVG images at egui's SVG loader
+fn inline_images(ctx: &egui::Context, text: &str) -> String {
+    svg_image_pattern()
+        .replace_all(text, |caps: &regex::Captures| {
+            match svg_uri(ctx, &caps[2]) {
+                Some(uri) => format!("![{}]({})", &caps[1], uri),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Draw one block
+fn draw(ui: &mut egui::Ui, cache: &mut CommonMarkCache, block: &Block) -> Option<MarkdownAction> {
+    match block {
+        Block::Prose(text) => commonmark(ui, cache, text),
+        Block::Table { source, csv } => {
+            // Wide tables scroll sideways and offer a CSV download
+            egui::ScrollArea::horizontal()
+                .id_salt(("markdown_table", egui::Id::new(source)))
+                .show(ui, |ui| commonmark(ui, cache, source));
+            if ui
+                .small_button(format!("{} Download CSV", icons::DOWNLOAD_SIMPLE))
+                .on_hover_text("Save the table as a CSV file")
+                .clicked()
+            {
+                return Some(MarkdownAction::SaveTable(csv.clone()));
+            }
+        }
+        Block::Image { uri, alt } => {
+            let response = ui
+                .add(
+                    egui::Image::new(uri.as_str())
+                        .fit_to_original_size(1.0)
+                        .max_width(ui.available_width())
+                        .sense(egui::Sense::click()),
+                )
+                .on_hover_cursor(egui::CursorIcon::ZoomIn)
+                .accessible_label(&format!("{} (click to zoom)", alt));
+            if response.clicked() {
+                return Some(MarkdownAction::OpenDiagram(uri.clone()));
+            }
+        }
+        Block::Code { language, code } => code_block(ui, language.as_deref(), code),
+    }
+    None
+}
+
+/// Prose through egui_commonmark
+fn commonmark(ui: &mut egui::Ui, cache: &mut CommonMarkCache, text: &str) {
+    if text.trim().is_empty() {
+        return;
+    }
+    CommonMarkViewer::new().show(ui, cache, text);
+}
+
+/// A fenced code block: header with language and copy button, then the code
+pub fn code_block(ui: &mut egui::Ui, language: Option<&str>, code: &str) {
+    let colors = theme_colors(ui.ctx());
+    let code = code.strip_suffix('\n').unwrap_or(code);
+
+    ui.add_space(4.0);
+    egui::Frame::new()
+        .fill(colors.input)
+        .stroke(egui::Stroke::new(1.0, colors.border))
+        .corner_radius(6.0)
+        .inner_margin(egui::Margin::same(8))
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new(language.unwrap_or("text"))
+                        .small()
+                        .color(colors.muted),
+                );
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui
+                        .small_button(icons::COPY)
+                        .accessible_label("Copy code")
+                        .clicked()
+                    {
+                        ui.ctx().copy_text(code.to_string());
+                    }
+                });
+            });
+            ui.add_space(2.0);
+
+            // highlight() memoizes the layout, so this is cheap on later frames
+            let theme = CodeTheme::from_style(ui.style());
+            let job = syntax_highlighting::highlight(
+                ui.ctx(),
+                ui.style(),
+                &theme,
+                code,
+                language.unwrap_or("text"),
+            );
+            ui.add(egui::Label::new(job).wrap().selectable(true));
+        });
+    ui.add_space(4.0);
+}
+
+/// Mermaid theme matching the UI's dark or light mode
+pub fn diagram_theme(visuals: &egui::Visuals) -> DiagramTheme {
+    if visuals.dark_mode {
+        DiagramTheme::Dark
+    } else {
+        DiagramTheme::Light
+    }
+}
+
+/// `bytes://` URI egui's SVG loader accepts for a base64 SVG
+///
+/// Each image is decoded and handed to egui once; later frames only look up
+/// the URI. None if the data isn't valid base64.
+fn svg_uri(ctx: &egui::Context, data: &str) -> Option<String> {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    let uri = format!("bytes://diagram-{:016x}.svg", hasher.finish());
+
+    let id = egui::Id::new(&uri);
+    if ctx.data(|d| d.get_temp::<()>(id)).is_none() {
+        use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+        match BASE64.decode(data) {
+            Ok(bytes) => ctx.include_bytes(uri.clone(), bytes),
+            Err(e) => {
+                tracing::warn!("Invalid embedded SVG image: {}", e);
+                return None;
+            }
+        }
+        ctx.data_mut(|d| d.insert_temp(id, ()));
+    }
+    Some(uri)
+}
@@ -44,9 +44,11 @@ use std::sync::Arc;
 use tokio::runtime::Handle;
 use tokio::sync::mpsc;
 
+use crate::audit::{self, AuditAction};
 use crate::mcp::config::McpConfig;
 use crate::mcp::extensions::{ExtensionInstaller, ExtensionRegistry, InstalledExtension};
-use crate::mcp::marketplace::{MarketplaceClient, McpRegistry, McpServerWrapper};
+use crate::mcp::marketplace::{MarketplaceClient, MarketplaceError, McpRegistry, McpServerWrapper};
+use crate::ui::theme::colors as theme_colors;
 
 /// Async task result for server list fetch
 enum FetchResult {
@@ -198,6 +200,17 @@ impl MarketplaceView {
     /// Spawns async task to fetch servers from API based on current search/filter state.
     /// Results are sent back via `fetch_tx` channel and processed in `update()`.
     pub fn refresh_servers(&mut self) {
+        // Don't even start a fetch while local-only mode or being offline
+        // keeps the registry out of reach
+        if let Err(e) = self.client.check_allowed() {
+            self.is_loading = false;
+            self.error_message = Some(match e {
+                MarketplaceError::Offline(e) => e.to_string(),
+                e => format!("The marketplace is unavailable. {}", e),
+            });
+            return;
+        }
+
         self.is_loading = true;
         self.error_message = None;
 
@@ -459,7 +472,7 @@ impl MarketplaceView {
 
         if let Some(error) = &self.error_message {
             ui.colored_label(
-                egui::Color32::RED,
+                theme_colors(ui.ctx()).error,
                 format!("{} Error", icons::WARNING_CIRCLE),
             );
             ui.label(error);
@@ -476,7 +489,7 @@ impl MarketplaceView {
             ui.label(
                 egui::RichText::new("(latest versions only)")
                     .size(11.0)
-                    .color(egui::Color32::from_rgb(120, 120, 120))
+                    .color(theme_colors(ui.ctx()).muted)
             )
             .on_hover_text("Multiple versions of the same server are deduplicated. Only the latest stable release is shown.");
         });
@@ -535,7 +548,7 @@ impl MarketplaceView {
                     ui.label(
                         egui::RichText::new(&server.description)
                             .size(11.0)
-                            .color(egui::Color32::from_rgb(120, 120, 120)),
+                            .color(theme_colors(ui.ctx()).muted),
                     );
 
                     ui.horizontal(|ui| {
@@ -546,7 +559,7 @@ impl MarketplaceView {
                         if is_official {
                             ui.label(
                                 egui::RichText::new(format!("{} Official", icons::SEAL_CHECK))
-                                    .color(egui::Color32::from_rgb(60, 150, 60)),
+                                    .color(theme_colors(ui.ctx()).success),
                             );
                         }
 
@@ -555,7 +568,7 @@ impl MarketplaceView {
                             ui.label(
                                 egui::RichText::new(format!("v{}", server.version))
                                     .size(11.0)
-                                    .color(egui::Color32::from_rgb(100, 100, 100)),
+                                    .color(theme_colors(ui.ctx()).muted),
                             );
                         }
                     });
@@ -624,7 +637,7 @@ impl MarketplaceView {
                             "{} Official Anthropic Server",
                             icons::SEAL_CHECK
                         ))
-                        .color(egui::Color32::from_rgb(60, 150, 60)),
+                        .color(theme_colors(ui.ctx()).success),
                     );
                 }
 
@@ -665,7 +678,7 @@ impl MarketplaceView {
                                     ui.label(
                                         egui::RichText::new(format!("  {}", env_var.description))
                                             .size(11.0)
-                                            .color(egui::Color32::from_rgb(120, 120, 120)),
+                                            .color(theme_colors(ui.ctx()).muted),
                                     );
                                 }
                             }
@@ -689,9 +702,9 @@ impl MarketplaceView {
                 // Installation status message
                 if let Some((message, is_error)) = &self.install_message {
                     let color = if *is_error {
-                        egui::Color32::from_rgb(200, 80, 80)
+                        theme_colors(ui.ctx()).error
                     } else {
-                        egui::Color32::from_rgb(60, 150, 60)
+                        theme_colors(ui.ctx()).success
                     };
                     ui.label(egui::RichText::new(message).color(color));
                     ui.add_space(10.0);
@@ -780,7 +793,7 @@ impl MarketplaceView {
                 ui.label(
                     egui::RichText::new("Paste this into your mcp_config.json file")
                         .size(11.0)
-                        .color(egui::Color32::from_rgb(120, 120, 120)),
+                        .color(theme_colors(ui.ctx()).muted),
                 );
             }
         } else {
@@ -792,7 +805,7 @@ impl MarketplaceView {
                         icons::ARROW_LEFT
                     ))
                     .size(14.0)
-                    .color(egui::Color32::from_rgb(120, 120, 120)),
+                    .color(theme_colors(ui.ctx()).muted),
                 );
             });
         }
@@ -1046,6 +1059,11 @@ impl MarketplaceView {
                 // Save registry
                 match self.extension_registry.save(&self.registry_path) {
                     Ok(_) => {
+                        audit::record(
+                            AuditAction::ExtensionInstalled,
+                            format!("{} ({})", server.name, extension_clone.id),
+                        );
+
                         // Update appropriate MCP config based on selected agent
                         let config_result = if let Some(ref agent_id) = self.selected_agent {
                             self.update_agent_mcp_config(agent_id, &extension_clone)
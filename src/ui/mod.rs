@@ -1,17 +1,34 @@
 // UI module for Rustbot
 // Contains all UI-related types, utilities, and views
 
+pub mod accessibility;
+pub mod attachments;
+pub mod commands;
+pub mod diagram_viewer;
+pub mod error_screen;
 pub mod icon;
+pub mod markdown;
 pub mod marketplace;
+pub mod notifications;
 pub mod plugins;
+pub mod sequence_view;
+pub mod theme;
+pub mod tool_cards;
 pub mod types;
 pub mod views;
 
 // Re-export commonly used types for convenience
 pub use types::{
-    AppView, ChatMessage, ContextTracker, ExtensionsView, InstallTypeFilter, MessageRole,
-    SettingsView, SystemPrompts, TokenStats, VisualEvent,
+    AgentResultReceiver, AgentWizard, AgentWizardStep, AppView, CalendarForm, ChatMessage, ChatTab,
+    ContextTracker, EventExportRange, ExtensionsView, FrameTimes, InstallTypeFilter,
+    LegacyTokenStats, MessageHeights, MessageRole, PendingDiagrams, PresetEditor, PromptForm,
+    PromptTestForm, QueuedMessage, QuotaForm, RetryState, SettingsView, SystemPrompts, UsageMetric,
+    VisualEvent,
 };
 
+pub use attachments::ImageAttachment;
+pub use commands::SlashCommand;
+pub use diagram_viewer::DiagramViewer;
+pub use error_screen::{ErrorScreen, ErrorScreenAction};
 pub use marketplace::MarketplaceView;
 pub use plugins::PluginsView;
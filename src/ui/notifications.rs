@@ -0,0 +1,64 @@
+// Desktop notifications for answers that finish in the background
+//
+// Design Decision: notify-rust on a short-lived thread per notification
+//
+// Rationale: notify-rust talks to the native notification service on each
+// platform (D-Bus on Linux/BSD, Notification Center on macOS, toasts on
+// Windows). Showing one can block briefly, and waiting for a click blocks
+// until the notification closes, so neither may run on the UI thread.
+//
+// Trade-offs: Click-to-focus is wired up where notify-rust reports actions
+// (Linux/BSD). On macOS and Windows a click activates the app through the
+// OS instead, which already brings the window forward.
+
+use eframe::egui;
+use std::time::Duration;
+
+/// Answers typed in the window only notify if they took at least this long
+pub const MIN_DURATION: Duration = Duration::from_secs(10);
+
+/// Characters of the answer shown in the notification body
+const PREVIEW_CHARS: usize = 140;
+
+/// First characters of a response on one line, for the notification body
+pub fn preview(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut preview: String = text.chars().take(PREVIEW_CHARS).collect();
+    if text.chars().count() > PREVIEW_CHARS {
+        preview.push('…');
+    }
+    preview
+}
+
+/// Show a notification; clicking it focuses the Rustbot window
+pub fn notify(ctx: &egui::Context, title: &str, body: &str) {
+    let ctx = ctx.clone();
+    let title = title.to_string();
+    let body = body.to_string();
+    std::thread::spawn(move || {
+        let mut notification = notify_rust::Notification::new();
+        notification.summary(&title).body(&body).appname("Rustbot");
+
+        #[cfg(all(unix, not(target_os = "macos")))]
+        {
+            notification.action("default", "Open");
+            match notification.show() {
+                Ok(handle) => handle.wait_for_action(|action| {
+                    if action == "default" {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                        ctx.request_repaint();
+                    }
+                }),
+                Err(e) => tracing::warn!("⚠️  Couldn't show notification: {}", e),
+            }
+        }
+
+        #[cfg(not(all(unix, not(target_os = "macos"))))]
+        {
+            let _ = &ctx;
+            if let Err(e) = notification.show() {
+                tracing::warn!("⚠️  Couldn't show notification: {}", e);
+            }
+        }
+    });
+}
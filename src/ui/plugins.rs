@@ -4,10 +4,13 @@
 //!
 //! Comprehensive UI for managing MCP plugins in Rustbot.
 //!
-//! Design Decision: Two-column layout with real-time updates
+//! Design Decision: Plugin list with a detail page per plugin
 //!
-//! Rationale: Plugin list in left column provides overview, details panel
-//! on right shows comprehensive information. Event-driven updates ensure
+//! Rationale: The list gives an overview; clicking a plugin opens its detail
+//! page with full information, every tool with a browsable view of its JSON
+//! input schema (and its name/description override, editable there), and the
+//! resources and prompts the plugin offers. The list
+//! is fetched from the manager in the background every few seconds, so the
 //! UI stays in sync with plugin state changes without manual refresh.
 //!
 //! Trade-offs:
@@ -16,8 +19,8 @@
 //! - Mutable access: Async spawn for operations to avoid blocking UI
 //!
 //! UI Components:
-//! 1. Plugin List (left): Status, name, tool count
-//! 2. Plugin Details (right): Full info, control buttons
+//! 1. Plugin List: Status, name, tool count
+//! 2. Plugin Details: Full info, control buttons, tools, resources, prompts
 //! 3. Global Controls (toolbar): Reload config
 //!
 //! Note: Recent Events moved to dedicated Events view (see render_events_only())
@@ -27,11 +30,15 @@ use egui_phosphor::regular as icons;
 use std::collections::VecDeque;
 use std::sync::Arc;
 use tokio::runtime::Handle;
-use tokio::sync::Mutex;
+use tokio::sync::{oneshot, Mutex};
 
 use crate::events::{Event, EventBus, EventKind, McpPluginEvent, PluginHealthStatus};
+use crate::mcp::config::ToolOverride;
 use crate::mcp::manager::McpPluginManager;
-use crate::mcp::plugin::{PluginMetadata, PluginState};
+use crate::mcp::plugin::{PluginMetadata, PluginState, ToolInfo};
+use crate::mcp::schema;
+use crate::ui::markdown;
+use crate::ui::theme::colors as theme_colors;
 
 /// Extensions (local) management view
 ///
@@ -53,6 +60,9 @@ pub struct PluginsView {
     /// Cached plugin list (updated via refresh)
     plugins: Vec<PluginMetadata>,
 
+    /// Plugin list being fetched in the background
+    refresh_rx: Option<oneshot::Receiver<Vec<PluginMetadata>>>,
+
     /// Currently selected plugin ID for detail view
     selected_plugin: Option<String>,
 
@@ -64,6 +74,17 @@ pub struct PluginsView {
 
     /// Auto-refresh interval (seconds)
     refresh_interval: u64,
+
+    /// Tool override being edited on the detail page
+    override_draft: Option<OverrideDraft>,
+}
+
+/// Name and description override of one tool, as typed on the detail page
+struct OverrideDraft {
+    plugin_id: String,
+    tool: String,
+    name: String,
+    description: String,
 }
 
 impl PluginsView {
@@ -77,10 +98,12 @@ impl PluginsView {
             mcp_manager,
             runtime,
             plugins: Vec::new(),
+            refresh_rx: None,
             selected_plugin: None,
             recent_events: VecDeque::with_capacity(50),
             last_refresh: std::time::Instant::now(),
             refresh_interval: 2, // 2 seconds
+            override_draft: None,
         }
     }
 
@@ -89,28 +112,33 @@ impl PluginsView {
     /// Fetches current plugin state asynchronously and updates cache.
     /// Called automatically on refresh interval or manually via button.
     pub async fn refresh_plugins(&mut self) {
-        let manager = self.mcp_manager.lock().await;
-
-        // Get all plugins using the public list_plugins method
-        let plugin_infos = manager.list_plugins().await;
+        self.plugins = load_plugins(&self.mcp_manager).await;
+    }
 
-        // Convert PluginInfo to PluginMetadata by fetching full details
-        self.plugins.clear();
-        for info in plugin_infos {
-            if let Some(metadata) = manager.get_plugin(&info.id).await {
-                self.plugins.push(metadata);
-            }
-        }
+    /// Open the detail page of a plugin
+    pub fn select_plugin(&mut self, plugin_id: &str) {
+        self.selected_plugin = Some(plugin_id.to_string());
     }
 
     /// Main render method
     ///
     /// Draws the complete plugins UI with:
     /// - Header with title
-    /// - Two-column layout (list | details)
-    /// - Recent events collapsible section
+    /// - Plugin list, or the selected plugin's detail page
     /// - Global controls
     pub fn render(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        // Plugin list fetched in the background
+        if let Some(rx) = &mut self.refresh_rx {
+            match rx.try_recv() {
+                Ok(plugins) => {
+                    self.plugins = plugins;
+                    self.refresh_rx = None;
+                }
+                Err(oneshot::error::TryRecvError::Empty) => {}
+                Err(oneshot::error::TryRecvError::Closed) => self.refresh_rx = None,
+            }
+        }
+
         // Header
         ui.horizontal(|ui| {
             ui.heading(format!("{} Local Extensions", icons::PUZZLE_PIECE));
@@ -148,22 +176,15 @@ impl PluginsView {
         egui::ScrollArea::vertical()
             .auto_shrink([false; 2])
             .show(ui, |ui| {
-                // Two-column layout
-                ui.columns(2, |columns| {
-                    // Left: Plugin List
-                    columns[0].vertical(|ui| {
-                        self.render_plugin_list(ui, ctx);
-                    });
-
-                    // Right: Plugin Details
-                    columns[1].vertical(|ui| {
-                        self.render_plugin_details(ui, ctx);
-                    });
-                });
+                if self.selected_plugin.is_some() {
+                    self.render_plugin_details(ui, ctx);
+                } else {
+                    self.render_plugin_list(ui, ctx);
+                }
             });
     }
 
-    /// Render plugin list (left column)
+    /// Render plugin list
     ///
     /// Shows all plugins as selectable cards with:
     /// - Status indicator (colored dot)
@@ -179,12 +200,12 @@ impl PluginsView {
                 ui.label(
                     egui::RichText::new("No plugins configured")
                         .size(12.0)
-                        .color(egui::Color32::from_rgb(120, 120, 120)),
+                        .color(theme_colors(ui.ctx()).muted),
                 );
                 ui.label(
                     egui::RichText::new("Create mcp_config.json to add plugins")
                         .size(11.0)
-                        .color(egui::Color32::from_rgb(150, 150, 150)),
+                        .color(theme_colors(ui.ctx()).subtle),
                 );
             });
             return;
@@ -212,7 +233,7 @@ impl PluginsView {
                             ui.label(
                                 egui::RichText::new(format!("{} tools", plugin.tools.len()))
                                     .size(11.0)
-                                    .color(egui::Color32::from_rgb(100, 100, 100)),
+                                    .color(theme_colors(ui.ctx()).muted),
                             );
                         });
                     }
@@ -225,7 +246,7 @@ impl PluginsView {
                     ui.label(
                         egui::RichText::new(state_text)
                             .size(11.0)
-                            .color(egui::Color32::from_rgb(120, 120, 120)),
+                            .color(theme_colors(ui.ctx()).muted),
                     );
                 });
             });
@@ -240,7 +261,7 @@ impl PluginsView {
                 ui.painter().rect_stroke(
                     response.response.rect,
                     egui::Rounding::same(2),
-                    egui::Stroke::new(2.0, egui::Color32::from_rgb(60, 120, 220)),
+                    egui::Stroke::new(2.0, theme_colors(ui.ctx()).accent),
                     egui::epaint::StrokeKind::Outside,
                 );
             }
@@ -249,185 +270,274 @@ impl PluginsView {
         }
     }
 
-    /// Render plugin details (right column)
+    /// Render the detail page of the selected plugin
     ///
     /// Shows comprehensive information about selected plugin:
     /// - Plugin name and ID
     /// - Current state with visual indicator
     /// - Restart count (if any)
-    /// - Tools list with descriptions
     /// - Control buttons (Start/Stop/Restart)
+    /// - Tools with their input schemas
+    /// - Resources and prompts (when the plugin lists any)
     fn render_plugin_details(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
-        if let Some(plugin_id) = &self.selected_plugin {
-            if let Some(plugin) = self.plugins.iter().find(|p| &p.id == plugin_id) {
-                // Plugin header
-                ui.heading(&plugin.name);
+        if ui
+            .button(format!("{} All Extensions", icons::ARROW_LEFT))
+            .clicked()
+        {
+            self.selected_plugin = None;
+            return;
+        }
+        ui.add_space(10.0);
+
+        let Some(plugin_id) = &self.selected_plugin else {
+            return;
+        };
+        let mut save_override = None;
+        if let Some(plugin) = self.plugins.iter().find(|p| &p.id == plugin_id) {
+            // Plugin header
+            ui.heading(&plugin.name);
+            ui.label(
+                egui::RichText::new(format!("ID: {}", plugin.id))
+                    .size(11.0)
+                    .color(theme_colors(ui.ctx()).muted),
+            );
+
+            ui.add_space(5.0);
+
+            // Description (if available)
+            if let Some(desc) = &plugin.description {
                 ui.label(
-                    egui::RichText::new(format!("ID: {}", plugin.id))
-                        .size(11.0)
-                        .color(egui::Color32::from_rgb(120, 120, 120)),
+                    egui::RichText::new(desc)
+                        .size(12.0)
+                        .color(theme_colors(ui.ctx()).text),
                 );
+                ui.add_space(5.0);
+            }
+
+            ui.separator();
+
+            // Status section
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new("Status:").strong());
+                let (status_icon, color) = get_status_icon_and_color(&plugin.state);
+                ui.colored_label(color, status_icon);
+                let state_text = get_state_text(&plugin.state);
+                ui.colored_label(color, state_text);
+            });
+
+            // Error message (if in error state)
+            if let Some(error_msg) = plugin.error_message() {
+                ui.add_space(5.0);
+                ui.group(|ui| {
+                    ui.set_min_width(ui.available_width());
+                    ui.colored_label(
+                        theme_colors(ui.ctx()).error,
+                        format!("{} Error:", icons::WARNING),
+                    );
+                    ui.label(
+                        egui::RichText::new(error_msg)
+                            .size(11.0)
+                            .color(theme_colors(ui.ctx()).error),
+                    );
+                });
+            }
 
+            // Restart info (if plugin has been restarted)
+            if plugin.restart_count > 0 {
                 ui.add_space(5.0);
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{} Restarts: {}/{}",
+                        icons::ARROW_CLOCKWISE,
+                        plugin.restart_count,
+                        plugin.max_retries
+                    ))
+                    .size(11.0)
+                    .color(theme_colors(ui.ctx()).warning),
+                );
+            }
 
-                // Description (if available)
-                if let Some(desc) = &plugin.description {
+            ui.add_space(10.0);
+            // Control buttons
+            ui.horizontal(|ui| match &plugin.state {
+                PluginState::Running => {
+                    if ui
+                        .button(format!("{} Stop", icons::STOP))
+                        .on_hover_text("Stop this plugin")
+                        .clicked()
+                    {
+                        self.stop_plugin(plugin_id, ctx);
+                    }
+
+                    if ui
+                        .button(format!("{} Restart", icons::ARROW_CLOCKWISE))
+                        .on_hover_text("Restart this plugin")
+                        .clicked()
+                    {
+                        self.restart_plugin(plugin_id, ctx);
+                    }
+                }
+                PluginState::Stopped | PluginState::Disabled | PluginState::Error { .. } => {
+                    if ui
+                        .button(format!("{} Start", icons::PLAY))
+                        .on_hover_text("Start this plugin")
+                        .clicked()
+                    {
+                        self.start_plugin(plugin_id, ctx);
+                    }
+                }
+                PluginState::Starting | PluginState::Initializing | PluginState::Stopping => {
                     ui.label(
-                        egui::RichText::new(desc)
-                            .size(12.0)
-                            .color(egui::Color32::from_rgb(80, 80, 80)),
+                        egui::RichText::new("Operation in progress...")
+                            .size(11.0)
+                            .color(theme_colors(ui.ctx()).subtle),
                     );
-                    ui.add_space(5.0);
                 }
+            });
 
-                ui.separator();
+            ui.add_space(10.0);
+            ui.separator();
 
-                // Status section
-                ui.horizontal(|ui| {
-                    ui.label(egui::RichText::new("Status:").strong());
-                    let (status_icon, color) = get_status_icon_and_color(&plugin.state);
-                    ui.colored_label(color, status_icon);
-                    let state_text = get_state_text(&plugin.state);
-                    ui.colored_label(color, state_text);
-                });
+            // Tools section
+            ui.label(
+                egui::RichText::new(format!("{} Tools ({})", icons::WRENCH, plugin.tools.len()))
+                    .strong(),
+            );
+            ui.add_space(5.0);
 
-                // Error message (if in error state)
-                if let Some(error_msg) = plugin.error_message() {
-                    ui.add_space(5.0);
-                    ui.group(|ui| {
-                        ui.set_min_width(ui.available_width());
-                        ui.colored_label(
-                            egui::Color32::from_rgb(200, 60, 60),
-                            format!("{} Error:", icons::WARNING),
-                        );
-                        ui.label(
-                            egui::RichText::new(error_msg)
-                                .size(11.0)
-                                .color(egui::Color32::from_rgb(150, 50, 50)),
-                        );
+            if plugin.tools.is_empty() {
+                let message = if plugin.state == PluginState::Running {
+                    "No tools available"
+                } else {
+                    "Start the plugin to see its tools"
+                };
+                ui.label(
+                    egui::RichText::new(message)
+                        .size(11.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                );
+            }
+            for tool in &plugin.tools {
+                let tool_override = plugin.tool_overrides.get(&tool.name);
+                if render_tool(ui, &plugin.id, tool, tool_override) {
+                    self.override_draft = Some(OverrideDraft {
+                        plugin_id: plugin.id.clone(),
+                        tool: tool.name.clone(),
+                        name: tool_override
+                            .and_then(|o| o.name.clone())
+                            .unwrap_or_default(),
+                        description: tool_override
+                            .and_then(|o| o.description.clone())
+                            .unwrap_or_default(),
                     });
                 }
 
-                // Restart info (if plugin has been restarted)
-                if plugin.restart_count > 0 {
-                    ui.add_space(5.0);
-                    ui.label(
-                        egui::RichText::new(format!(
-                            "{} Restarts: {}/{}",
-                            icons::ARROW_CLOCKWISE,
-                            plugin.restart_count,
-                            plugin.max_retries
-                        ))
-                        .size(11.0)
-                        .color(egui::Color32::from_rgb(200, 150, 50)),
-                    );
+                let draft = self
+                    .override_draft
+                    .as_mut()
+                    .filter(|d| d.plugin_id == plugin.id && d.tool == tool.name);
+                if let Some(draft) = draft {
+                    match render_override_form(ui, draft, tool) {
+                        Some(true) => save_override = self.override_draft.take(),
+                        Some(false) => self.override_draft = None,
+                        None => {}
+                    }
                 }
+            }
 
+            // Resources section (only when the plugin lists any)
+            if !plugin.resources.is_empty() {
                 ui.add_space(10.0);
-                ui.separator();
-
-                // Tools section
                 ui.label(
                     egui::RichText::new(format!(
-                        "{} Tools ({})",
-                        icons::WRENCH,
-                        plugin.tools.len()
+                        "{} Resources ({})",
+                        icons::FILE_TEXT,
+                        plugin.resources.len()
                     ))
                     .strong(),
                 );
                 ui.add_space(5.0);
-
-                if plugin.tools.is_empty() {
-                    ui.label(
-                        egui::RichText::new("No tools available")
-                            .size(11.0)
-                            .color(egui::Color32::from_rgb(120, 120, 120)),
-                    );
-                } else {
-                    egui::ScrollArea::vertical()
-                        .max_height(200.0)
-                        .show(ui, |ui| {
-                            for tool in &plugin.tools {
-                                ui.horizontal(|ui| {
-                                    ui.label("•");
-                                    ui.label(egui::RichText::new(&tool.name).strong().size(12.0));
-                                });
-
-                                if let Some(desc) = &tool.description {
-                                    ui.horizontal(|ui| {
-                                        ui.add_space(15.0);
-                                        ui.label(
-                                            egui::RichText::new(desc)
-                                                .size(11.0)
-                                                .color(egui::Color32::from_rgb(100, 100, 100)),
-                                        );
-                                    });
-                                }
-                                ui.add_space(3.0);
-                            }
-                        });
+                for resource in &plugin.resources {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(egui::RichText::new(&resource.name).strong().size(12.0));
+                        ui.label(egui::RichText::new(&resource.uri).monospace().size(11.0));
+                        if let Some(mime_type) = &resource.mime_type {
+                            ui.label(
+                                egui::RichText::new(mime_type)
+                                    .size(11.0)
+                                    .color(theme_colors(ui.ctx()).subtle),
+                            );
+                        }
+                    });
+                    if let Some(desc) = &resource.description {
+                        ui.label(
+                            egui::RichText::new(desc)
+                                .size(11.0)
+                                .color(theme_colors(ui.ctx()).muted),
+                        );
+                    }
+                    ui.add_space(3.0);
                 }
+            }
 
+            // Prompts section (only when the plugin lists any)
+            if !plugin.prompts.is_empty() {
                 ui.add_space(10.0);
-                ui.separator();
-
-                // Control buttons
-                ui.horizontal(|ui| match &plugin.state {
-                    PluginState::Running => {
-                        if ui
-                            .button(format!("{} Stop", icons::STOP))
-                            .on_hover_text("Stop this plugin")
-                            .clicked()
-                        {
-                            self.stop_plugin(plugin_id, ctx);
-                        }
-
-                        if ui
-                            .button(format!("{} Restart", icons::ARROW_CLOCKWISE))
-                            .on_hover_text("Restart this plugin")
-                            .clicked()
-                        {
-                            self.restart_plugin(plugin_id, ctx);
-                        }
-                    }
-                    PluginState::Stopped | PluginState::Disabled | PluginState::Error { .. } => {
-                        if ui
-                            .button(format!("{} Start", icons::PLAY))
-                            .on_hover_text("Start this plugin")
-                            .clicked()
-                        {
-                            self.start_plugin(plugin_id, ctx);
-                        }
-                    }
-                    PluginState::Starting | PluginState::Initializing | PluginState::Stopping => {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{} Prompts ({})",
+                        icons::CHAT_TEXT,
+                        plugin.prompts.len()
+                    ))
+                    .strong(),
+                );
+                ui.add_space(5.0);
+                for prompt in &plugin.prompts {
+                    ui.label(egui::RichText::new(&prompt.name).strong().size(12.0));
+                    if let Some(desc) = &prompt.description {
                         ui.label(
-                            egui::RichText::new("Operation in progress...")
+                            egui::RichText::new(desc)
                                 .size(11.0)
-                                .color(egui::Color32::from_rgb(150, 150, 150)),
+                                .color(theme_colors(ui.ctx()).muted),
                         );
                     }
-                });
-            } else {
-                // Selected plugin not found (might have been removed)
-                ui.vertical_centered(|ui| {
-                    ui.add_space(50.0);
-                    ui.label(
-                        egui::RichText::new("Plugin not found")
-                            .color(egui::Color32::from_rgb(150, 150, 150)),
-                    );
-                });
-                self.selected_plugin = None; // Clear invalid selection
+                    for argument in &prompt.arguments {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.add_space(15.0);
+                            ui.label(egui::RichText::new(&argument.name).monospace().size(11.0));
+                            if argument.required {
+                                ui.label(
+                                    egui::RichText::new("required")
+                                        .size(10.0)
+                                        .color(theme_colors(ui.ctx()).warning),
+                                );
+                            }
+                            if let Some(desc) = &argument.description {
+                                ui.label(
+                                    egui::RichText::new(desc)
+                                        .size(11.0)
+                                        .color(theme_colors(ui.ctx()).muted),
+                                );
+                            }
+                        });
+                    }
+                    ui.add_space(3.0);
+                }
             }
         } else {
-            // No plugin selected
+            // Selected plugin not found (might have been removed, or the
+            // list hasn't been fetched yet)
             ui.vertical_centered(|ui| {
                 ui.add_space(50.0);
                 ui.label(
-                    egui::RichText::new("← Select a plugin to view details")
-                        .color(egui::Color32::from_rgb(120, 120, 120)),
+                    egui::RichText::new("Plugin not found").color(theme_colors(ui.ctx()).subtle),
                 );
             });
         }
+
+        if let Some(draft) = save_override {
+            self.save_tool_override(draft, ctx);
+        }
     }
 
     /// Render only the events panel (for standalone Events view)
@@ -446,7 +556,7 @@ impl PluginsView {
             ui.label(
                 egui::RichText::new("No recent events")
                     .size(11.0)
-                    .color(egui::Color32::from_rgb(120, 120, 120)),
+                    .color(theme_colors(ui.ctx()).muted),
             );
         } else {
             egui::ScrollArea::vertical()
@@ -459,7 +569,7 @@ impl PluginsView {
                             ui.label(
                                 egui::RichText::new(event)
                                     .size(11.0)
-                                    .color(egui::Color32::from_rgb(80, 80, 80)),
+                                    .color(theme_colors(ui.ctx()).text),
                             );
                         });
                     }
@@ -494,12 +604,18 @@ impl PluginsView {
     // Plugin Control Actions (async spawned to avoid blocking UI)
     // ========================================================================
 
-    /// Trigger manual refresh
-    fn trigger_refresh(&self, ctx: &egui::Context) {
-        let ctx_clone = ctx.clone();
+    /// Fetch the plugin list in the background; `render` picks it up
+    fn trigger_refresh(&mut self, ctx: &egui::Context) {
+        if self.refresh_rx.is_some() {
+            return;
+        }
+        let (tx, rx) = oneshot::channel();
+        self.refresh_rx = Some(rx);
 
+        let manager = Arc::clone(&self.mcp_manager);
+        let ctx_clone = ctx.clone();
         self.runtime.spawn(async move {
-            // Refresh will happen on next render
+            let _ = tx.send(load_plugins(&manager).await);
             ctx_clone.request_repaint();
         });
     }
@@ -511,7 +627,7 @@ impl PluginsView {
         let ctx_clone = ctx.clone();
 
         self.runtime.spawn(async move {
-            let mut mgr = manager.lock().await;
+            let mgr = manager.lock().await;
             match mgr.start_plugin(&id).await {
                 Ok(_) => {
                     tracing::info!("Plugin '{}' started successfully", id);
@@ -558,7 +674,7 @@ impl PluginsView {
         self.runtime.spawn(async move {
             tokio::time::sleep(std::time::Duration::from_millis(500)).await;
 
-            let mut mgr = manager.lock().await;
+            let mgr = manager.lock().await;
             match mgr.start_plugin(&id).await {
                 Ok(_) => {
                     tracing::info!("Plugin '{}' restarted successfully", id);
@@ -571,6 +687,45 @@ impl PluginsView {
         });
     }
 
+    /// Save a tool's name/description override to the plugin's config
+    ///
+    /// A running plugin's tools are registered again with it (the manager
+    /// publishes `ToolsChanged`); blank fields restore the server's text.
+    fn save_tool_override(&mut self, draft: OverrideDraft, ctx: &egui::Context) {
+        let manager = Arc::clone(&self.mcp_manager);
+        let ctx_clone = ctx.clone();
+        let tool_override = ToolOverride {
+            name: Some(draft.name),
+            description: Some(draft.description),
+        };
+
+        self.runtime.spawn(async move {
+            let mgr = manager.lock().await;
+            match mgr
+                .set_tool_override(&draft.plugin_id, &draft.tool, tool_override)
+                .await
+            {
+                Ok(_) => {
+                    tracing::info!(
+                        "Saved override of tool '{}' (plugin '{}')",
+                        draft.tool,
+                        draft.plugin_id
+                    );
+                }
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to save override of tool '{}' (plugin '{}'): {}",
+                        draft.tool,
+                        draft.plugin_id,
+                        e
+                    );
+                }
+            }
+            ctx_clone.request_repaint();
+        });
+        self.trigger_refresh(ctx);
+    }
+
     /// Reload configuration from disk
     fn reload_config(&self, ctx: &egui::Context) {
         let ctx_clone = ctx.clone();
@@ -589,6 +744,210 @@ impl PluginsView {
 // Helper Functions
 // ============================================================================
 
+/// Full metadata of every configured plugin, sorted by name
+async fn load_plugins(manager: &Mutex<McpPluginManager>) -> Vec<PluginMetadata> {
+    let manager = manager.lock().await;
+
+    // Get all plugins using the public list_plugins method
+    let plugin_infos = manager.list_plugins().await;
+
+    // Convert PluginInfo to PluginMetadata by fetching full details
+    let mut plugins = Vec::with_capacity(plugin_infos.len());
+    for info in plugin_infos {
+        if let Some(metadata) = manager.get_plugin(&info.id).await {
+            plugins.push(metadata);
+        }
+    }
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    plugins
+}
+
+/// One tool on the detail page: collapsed to its name and description,
+/// expanding to a table of its parameters and the raw JSON schema
+///
+/// Shows the name and description the agents see, i.e. with the override
+/// from the config applied.
+///
+/// # Returns
+/// Whether "Edit" was clicked to change the override
+fn render_tool(
+    ui: &mut egui::Ui,
+    plugin_id: &str,
+    tool: &ToolInfo,
+    tool_override: Option<&ToolOverride>,
+) -> bool {
+    let colors = theme_colors(ui.ctx());
+    let fields = schema::fields(&tool.input_schema);
+    let name = tool_override.and_then(|o| o.name.as_ref());
+    let description = tool_override
+        .and_then(|o| o.description.as_ref())
+        .or(tool.description.as_ref());
+    let header = match name {
+        Some(name) => format!("{} (server: {})", name, tool.name),
+        None => tool.name.clone(),
+    };
+    let mut edit = false;
+
+    egui::CollapsingHeader::new(egui::RichText::new(header).strong().size(12.0))
+        .id_salt(("plugin_tool", plugin_id, &tool.name))
+        .default_open(false)
+        .show(ui, |ui| {
+            if let Some(desc) = description {
+                ui.label(egui::RichText::new(desc).size(11.0).color(colors.text));
+            }
+            ui.horizontal(|ui| {
+                if tool_override.is_some() {
+                    ui.label(
+                        egui::RichText::new("Name or description overridden in the config")
+                            .size(10.5)
+                            .color(colors.muted),
+                    );
+                }
+                edit = ui
+                    .small_button(format!("{} Edit", icons::PENCIL_SIMPLE))
+                    .on_hover_text("Change the name or description the agents see")
+                    .clicked();
+            });
+            ui.add_space(5.0);
+
+            if fields.is_empty() {
+                ui.label(
+                    egui::RichText::new("No parameters")
+                        .size(11.0)
+                        .color(colors.muted),
+                );
+            } else {
+                egui::Grid::new(("plugin_tool_schema", plugin_id, &tool.name))
+                    .striped(true)
+                    .spacing([12.0, 4.0])
+                    .show(ui, |ui| {
+                        for header in ["Parameter", "Type", "", "Description"] {
+                            ui.label(egui::RichText::new(header).small().color(colors.muted));
+                        }
+                        ui.end_row();
+
+                        for field in &fields {
+                            ui.horizontal(|ui| {
+                                ui.add_space(field.depth as f32 * 12.0);
+                                ui.label(egui::RichText::new(&field.name).monospace())
+                                    .on_hover_text(&field.path);
+                            });
+                            ui.label(
+                                egui::RichText::new(&field.type_name)
+                                    .monospace()
+                                    .color(colors.accent),
+                            );
+                            if field.required {
+                                ui.label(
+                                    egui::RichText::new("required")
+                                        .size(10.0)
+                                        .color(colors.warning),
+                                );
+                            } else {
+                                ui.label("");
+                            }
+                            ui.vertical(|ui| {
+                                if let Some(desc) = &field.description {
+                                    ui.label(egui::RichText::new(desc).size(11.0));
+                                }
+                                if !field.enum_values.is_empty() {
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "One of: {}",
+                                            field.enum_values.join(", ")
+                                        ))
+                                        .size(10.5)
+                                        .color(colors.muted),
+                                    );
+                                }
+                                if let Some(default) = &field.default {
+                                    ui.label(
+                                        egui::RichText::new(format!("Default: {}", default))
+                                            .size(10.5)
+                                            .color(colors.muted),
+                                    );
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+            }
+
+            ui.add_space(5.0);
+            egui::CollapsingHeader::new(egui::RichText::new("JSON schema").small())
+                .id_salt(("plugin_tool_json", plugin_id, &tool.name))
+                .default_open(false)
+                .show(ui, |ui| {
+                    let json = serde_json::to_string_pretty(&tool.input_schema)
+                        .unwrap_or_else(|_| tool.input_schema.to_string());
+                    markdown::code_block(ui, Some("json"), &json);
+                });
+        });
+    edit
+}
+
+/// Form for a tool's name/description override, below the tool
+///
+/// # Returns
+/// Some(true) to save, Some(false) to cancel, None while editing
+fn render_override_form(
+    ui: &mut egui::Ui,
+    draft: &mut OverrideDraft,
+    tool: &ToolInfo,
+) -> Option<bool> {
+    let colors = theme_colors(ui.ctx());
+    let valid_name = !draft.name.contains(':');
+    let mut action = None;
+
+    egui::Frame::group(ui.style()).show(ui, |ui| {
+        ui.set_width(ui.available_width());
+        ui.label(
+            egui::RichText::new(format!(
+                "Override for {} (leave a field empty to use the server's)",
+                tool.name
+            ))
+            .size(11.0)
+            .color(colors.muted),
+        );
+        egui::Grid::new(("tool_override", &draft.plugin_id, &draft.tool))
+            .num_columns(2)
+            .spacing([10.0, 4.0])
+            .show(ui, |ui| {
+                ui.label("Name:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut draft.name)
+                        .hint_text(&tool.name)
+                        .desired_width(300.0),
+                );
+                ui.end_row();
+
+                ui.label("Description:");
+                ui.add(
+                    egui::TextEdit::multiline(&mut draft.description)
+                        .hint_text(tool.description.as_deref().unwrap_or_default())
+                        .desired_rows(3)
+                        .desired_width(f32::INFINITY),
+                );
+                ui.end_row();
+            });
+        if !valid_name {
+            ui.colored_label(colors.error, "Names can't contain ':'");
+        }
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(valid_name, egui::Button::new("Save"))
+                .clicked()
+            {
+                action = Some(true);
+            }
+            if ui.button("Cancel").clicked() {
+                action = Some(false);
+            }
+        });
+    });
+    action
+}
+
 /// Get status icon and color for plugin state
 ///
 /// Returns (icon_text, color) tuple for rendering.
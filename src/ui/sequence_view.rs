@@ -0,0 +1,145 @@
+// Event flow drawn as a sequence diagram
+//
+// Each participant on the event bus gets a vertical swimlane; every event is a
+// row with an arrow from its source lane to its destination lane. Broadcast
+// events are a dot on the sender's lane. Lane order and turn grouping come from
+// `rustbot_core::event_sequence`; this module only paints.
+
+use crate::event_sequence::{self, SequenceStep};
+use crate::ui::theme::colors as theme_colors;
+use crate::ui::VisualEvent;
+use eframe::egui;
+
+/// Lane width at zoom 1.0
+const LANE_WIDTH: f32 = 80.0;
+
+/// Row height per event at zoom 1.0
+const ROW_HEIGHT: f32 = 20.0;
+
+/// Height of the lane name row at zoom 1.0
+const HEADER_HEIGHT: f32 = 16.0;
+
+/// Zoom limits for the buttons and Ctrl+scroll
+pub const MIN_ZOOM: f32 = 0.5;
+pub const MAX_ZOOM: f32 = 3.0;
+
+/// Color for an event kind
+pub fn kind_color(kind: &str) -> egui::Color32 {
+    match kind {
+        "UserMessage" => egui::Color32::from_rgb(100, 150, 255),
+        "AgentMessage" => egui::Color32::from_rgb(100, 255, 150),
+        "ToolCall" => egui::Color32::from_rgb(200, 150, 255),
+        "StatusChange" => egui::Color32::from_rgb(255, 200, 100),
+        "SystemCommand" => egui::Color32::from_rgb(255, 100, 100),
+        _ => egui::Color32::from_rgb(150, 150, 150),
+    }
+}
+
+/// Sequence steps for events, labelled with the event kind
+pub fn steps(events: &[&VisualEvent]) -> Vec<SequenceStep> {
+    events
+        .iter()
+        .map(|event| SequenceStep {
+            source: event.source.clone(),
+            destination: event.destination.clone(),
+            label: event.kind.clone(),
+        })
+        .collect()
+}
+
+/// Draw events (oldest first) as swimlanes
+///
+/// Hovering a row shows the event's time, route and turn; Ctrl+scroll over
+/// the diagram changes `zoom`.
+pub fn show(ui: &mut egui::Ui, events: &[&VisualEvent], zoom: &mut f32) {
+    let colors = theme_colors(ui.ctx());
+    let steps = steps(events);
+    let lanes = event_sequence::participants(&steps);
+    if lanes.is_empty() {
+        return;
+    }
+
+    let lane_width = LANE_WIDTH * *zoom;
+    let row_height = ROW_HEIGHT * *zoom;
+    let header_height = HEADER_HEIGHT * *zoom;
+    let font = egui::FontId::proportional(10.0 * *zoom);
+
+    let size = egui::vec2(
+        lane_width * lanes.len() as f32,
+        header_height + row_height * steps.len() as f32 + 4.0,
+    );
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let lane_x = |name: &str| {
+        let i = lanes.iter().position(|lane| lane == name).unwrap_or(0);
+        rect.left() + lane_width * (i as f32 + 0.5)
+    };
+
+    for lane in &lanes {
+        let x = lane_x(lane);
+        painter.text(
+            egui::pos2(x, rect.top()),
+            egui::Align2::CENTER_TOP,
+            lane,
+            font.clone(),
+            colors.text,
+        );
+        painter.line_segment(
+            [
+                egui::pos2(x, rect.top() + header_height),
+                egui::pos2(x, rect.bottom()),
+            ],
+            egui::Stroke::new(1.0, colors.border),
+        );
+    }
+
+    for (row, step) in steps.iter().enumerate() {
+        let y = rect.top() + header_height + row_height * (row as f32 + 0.75);
+        let color = kind_color(&step.label);
+        let from = egui::pos2(lane_x(&step.source), y);
+
+        if step.is_broadcast() || step.source == step.destination {
+            painter.circle_filled(from, 3.0 * *zoom, color);
+            painter.text(
+                from + egui::vec2(5.0 * *zoom, 0.0),
+                egui::Align2::LEFT_BOTTOM,
+                &step.label,
+                font.clone(),
+                color,
+            );
+        } else {
+            let to = egui::pos2(lane_x(&step.destination), y);
+            painter.arrow(from, to - from, egui::Stroke::new(1.5, color));
+            painter.text(
+                egui::pos2((from.x + to.x) / 2.0, y - 1.0),
+                egui::Align2::CENTER_BOTTOM,
+                &step.label,
+                font.clone(),
+                color,
+            );
+        }
+    }
+
+    if response.hovered() {
+        let delta = ui.input(|i| i.zoom_delta());
+        if delta != 1.0 {
+            *zoom = (*zoom * delta).clamp(MIN_ZOOM, MAX_ZOOM);
+        }
+    }
+
+    let hovered_row = response.hover_pos().and_then(|pos| {
+        let offset = pos.y - rect.top() - header_height;
+        (offset >= 0.0).then_some((offset / row_height) as usize)
+    });
+    if let Some(event) = hovered_row.and_then(|row| events.get(row)) {
+        let turn = event.correlation_id.as_deref().unwrap_or("no turn");
+        response.on_hover_text(format!(
+            "{}\n{} → {}\n{} · {}",
+            event.kind,
+            event.source,
+            event.destination,
+            event.timestamp.format("%H:%M:%S%.3f"),
+            turn
+        ));
+    }
+}
@@ -0,0 +1,154 @@
+// Applies a core `theme::Palette` to egui
+//
+// Design Decision: Palette colors are stored in the egui context
+//
+// Rationale: Views draw with role colors (muted text, success, error…) in
+// many places and most of them only have a `Ui`. Storing the colors in the
+// context's temp data lets any view call `theme::colors(ui.ctx())` without
+// threading the palette through every function.
+
+use eframe::egui;
+use rustbot_core::theme::{self, FontSizes, Palette, Rgb};
+
+/// Role colors of the active palette, ready for drawing
+#[derive(Debug, Clone, Copy)]
+pub struct ThemeColors {
+    pub dark: bool,
+    pub background: egui::Color32,
+    pub surface: egui::Color32,
+    pub input: egui::Color32,
+    pub frame: egui::Color32,
+    pub border: egui::Color32,
+    pub text: egui::Color32,
+    pub muted: egui::Color32,
+    pub subtle: egui::Color32,
+    pub accent: egui::Color32,
+    pub user: egui::Color32,
+    pub assistant: egui::Color32,
+    pub success: egui::Color32,
+    pub warning: egui::Color32,
+    pub error: egui::Color32,
+}
+
+impl From<&Palette> for ThemeColors {
+    fn from(palette: &Palette) -> Self {
+        Self {
+            dark: palette.dark,
+            background: color(palette.background),
+            surface: color(palette.surface),
+            input: color(palette.input),
+            frame: color(palette.frame),
+            border: color(palette.border),
+            text: color(palette.text),
+            muted: color(palette.muted),
+            subtle: color(palette.subtle),
+            accent: color(palette.accent),
+            user: color(palette.user),
+            assistant: color(palette.assistant),
+            success: color(palette.success),
+            warning: color(palette.warning),
+            error: color(palette.error),
+        }
+    }
+}
+
+fn color(rgb: Rgb) -> egui::Color32 {
+    egui::Color32::from_rgb(rgb.0, rgb.1, rgb.2)
+}
+
+fn colors_id() -> egui::Id {
+    egui::Id::new("rustbot_theme_colors")
+}
+
+/// Apply the text style sizes for a body font size, and the interface zoom
+///
+/// Independent of the palette, so changing either keeps the other.
+pub fn apply_text_size(ctx: &egui::Context, font_size: f32, ui_scale: f32) {
+    let sizes = FontSizes::for_body(font_size);
+    let mut style = (*ctx.style()).clone();
+    style.text_styles = [
+        (
+            egui::TextStyle::Heading,
+            egui::FontId::new(sizes.heading, egui::FontFamily::Proportional),
+        ),
+        (
+            egui::TextStyle::Body,
+            egui::FontId::new(sizes.body, egui::FontFamily::Proportional),
+        ),
+        (
+            egui::TextStyle::Button,
+            egui::FontId::new(sizes.button, egui::FontFamily::Proportional),
+        ),
+        (
+            egui::TextStyle::Small,
+            egui::FontId::new(sizes.small, egui::FontFamily::Proportional),
+        ),
+        (
+            egui::TextStyle::Monospace,
+            egui::FontId::new(sizes.monospace, egui::FontFamily::Proportional),
+        ),
+    ]
+    .into();
+    ctx.set_style(style);
+    ctx.set_zoom_factor(theme::clamp_ui_scale(ui_scale));
+}
+
+/// Apply a palette: egui visuals and the role colors for `colors()`
+pub fn apply(ctx: &egui::Context, palette: &Palette) {
+    let colors = ThemeColors::from(palette);
+    let mut style = (*ctx.style()).clone();
+
+    let mut visuals = if palette.dark {
+        egui::Visuals::dark()
+    } else {
+        egui::Visuals::light()
+    };
+
+    visuals.override_text_color = Some(colors.text);
+
+    // Widget backgrounds
+    visuals.widgets.noninteractive.bg_fill = colors.frame;
+    visuals.widgets.noninteractive.bg_stroke.color = colors.border;
+    visuals.widgets.inactive.bg_fill = color(palette.widget);
+    visuals.widgets.inactive.weak_bg_fill = color(palette.widget);
+    visuals.widgets.hovered.bg_fill = color(palette.widget_hover);
+    visuals.widgets.hovered.weak_bg_fill = color(palette.widget_hover);
+    visuals.widgets.active.bg_fill = colors.accent;
+
+    // Selection, links and status colors
+    let (r, g, b, _) = colors.accent.to_tuple();
+    visuals.selection.bg_fill = egui::Color32::from_rgba_unmultiplied(r, g, b, 80);
+    visuals.selection.stroke.color = colors.accent;
+    visuals.hyperlink_color = colors.accent;
+    visuals.warn_fg_color = colors.warning;
+    visuals.error_fg_color = colors.error;
+
+    // Panel and window backgrounds
+    visuals.panel_fill = colors.background;
+    visuals.window_fill = colors.surface;
+    visuals.extreme_bg_color = colors.input;
+    visuals.code_bg_color = colors.input;
+    visuals.window_stroke.color = colors.border;
+
+    // Outlines that stay visible without relying on fill colors
+    if palette.high_contrast {
+        visuals.widgets.noninteractive.bg_stroke.width = 1.0;
+        visuals.widgets.inactive.bg_stroke = egui::Stroke::new(1.0, colors.border);
+        visuals.widgets.hovered.bg_stroke = egui::Stroke::new(2.0, colors.accent);
+        visuals.widgets.active.bg_stroke = egui::Stroke::new(2.0, colors.accent);
+        visuals.widgets.active.fg_stroke.color = egui::Color32::BLACK;
+        visuals.selection.stroke.width = 2.0;
+        visuals.text_cursor.stroke = egui::Stroke::new(2.0, colors.accent);
+        visuals.window_stroke.width = 2.0;
+    }
+
+    style.visuals = visuals;
+    ctx.set_style(style);
+    ctx.data_mut(|data| data.insert_temp(colors_id(), colors));
+}
+
+/// Role colors of the applied palette (the light palette before `apply`)
+pub fn colors(ctx: &egui::Context) -> ThemeColors {
+    ctx.data(|data| data.get_temp(colors_id()))
+        .unwrap_or_else(|| ThemeColors::from(&Palette::light()))
+}
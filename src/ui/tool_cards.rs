@@ -0,0 +1,102 @@
+// Tool call cards shown in an assistant message
+//
+// Each call the agent makes while answering gets a collapsed card with the
+// tool name, its state and duration; expanding it shows the arguments and the
+// result. Cards update live from `EventKind::ToolCall` events. Results that
+// look like a prompt injection (see `crate::untrusted`) are flagged in the
+// header, so the warning is visible without expanding the card.
+
+use crate::events::ToolCallRecord;
+use crate::ui::markdown;
+use crate::ui::theme::colors as theme_colors;
+use eframe::egui;
+use egui_phosphor::regular as icons;
+use std::time::Duration;
+
+/// Longest result shown in a card, in characters
+const MAX_RESULT_CHARS: usize = 4000;
+
+/// Draw the card for one tool call
+pub fn show(ui: &mut egui::Ui, call: &ToolCallRecord) {
+    let colors = theme_colors(ui.ctx());
+    let (status, color) = match &call.result {
+        None => (format!("{} running…", icons::CIRCLE_NOTCH), colors.muted),
+        Some(Ok(_)) => (
+            format!("{} {}", icons::CHECK_CIRCLE, format_duration(call.duration)),
+            colors.muted,
+        ),
+        Some(Err(_)) => (
+            format!(
+                "{} failed after {}",
+                icons::WARNING_CIRCLE,
+                format_duration(call.duration)
+            ),
+            colors.error,
+        ),
+    };
+    let (status, color) = match &call.warning {
+        Some(_) => (
+            format!("{} · {} possible injection", status, icons::WARNING),
+            colors.warning,
+        ),
+        None => (status, color),
+    };
+    let header = egui::RichText::new(format!("{} {} · {}", icons::WRENCH, call.name, status))
+        .size(12.0)
+        .color(color);
+
+    egui::CollapsingHeader::new(header)
+        .id_salt(("tool_call", &call.id))
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.label(egui::RichText::new("Arguments").small().color(colors.muted));
+            let arguments = serde_json::to_string_pretty(&call.arguments)
+                .unwrap_or_else(|_| call.arguments.to_string());
+            markdown::code_block(ui, Some("json"), &arguments);
+
+            if let Some(warning) = &call.warning {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{} {}. The model was told not to follow it.",
+                        icons::WARNING,
+                        warning
+                    ))
+                    .color(colors.warning),
+                );
+            }
+
+            match &call.result {
+                Some(Ok(output)) => {
+                    ui.label(egui::RichText::new("Result").small().color(colors.muted));
+                    markdown::code_block(ui, None, &truncate(output));
+                }
+                Some(Err(e)) => {
+                    ui.label(egui::RichText::new(e).color(colors.error));
+                }
+                None => {}
+            }
+        });
+}
+
+/// "850 ms" or "2.4 s"
+pub fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{} ms", duration.as_millis())
+    } else {
+        format!("{:.1} s", duration.as_secs_f32())
+    }
+}
+
+/// Cut long results, saying how much was left out
+fn truncate(text: &str) -> String {
+    let total = text.chars().count();
+    if total <= MAX_RESULT_CHARS {
+        return text.to_string();
+    }
+    let shown: String = text.chars().take(MAX_RESULT_CHARS).collect();
+    format!(
+        "{}\n… ({} more characters)",
+        shown,
+        total - MAX_RESULT_CHARS
+    )
+}
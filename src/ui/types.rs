@@ -1,7 +1,24 @@
 // UI type definitions for Rustbot
 // Contains data structures used throughout the UI
 
+use crate::agent::validation::{validate_preset, PresetIssue};
+use crate::agent::AgentConfig;
+use crate::calendar::{CalendarConfig, CALDAV_PASSWORD_SECRET, GOOGLE_CLIENT_SECRET};
+use crate::events::ToolCallRecord;
+use crate::handoff::Handoff;
+use crate::prompt_library::PromptTemplate;
+use crate::prompt_test::{parse_prompts, Judge, PromptTest, PromptVariant, TestReport};
+use crate::quota::UsageQuota;
+use crate::services::{ConversationSession, MessageFeedback};
+use crate::ui::attachments::ImageAttachment;
+use crate::ui::tool_cards::format_duration;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
 
 /// Event visualization structure
 pub struct VisualEvent {
@@ -9,6 +26,8 @@ pub struct VisualEvent {
     pub destination: String,
     pub kind: String,
     pub timestamp: chrono::DateTime<chrono::Local>,
+    /// Correlation ID of the user turn this event belongs to (if any)
+    pub correlation_id: Option<String>,
 }
 
 /// Main application view
@@ -17,6 +36,8 @@ pub enum AppView {
     Chat,
     Settings,
     Events,
+    History,
+    Usage,
     Extensions,
 }
 
@@ -25,14 +46,118 @@ pub enum AppView {
 pub enum SettingsView {
     SystemPrompts,
     Agents,
+    Projects,
     Preferences,
+    Backups,
+    Scripts,
+    WasmTools,
+    NativePlugins,
+    Calendar,
+    Prompts,
+    PromptTests,
+    Audit,
 }
 
-/// Extensions sub-view (Marketplace, Installed)
+/// Steps of the New Agent wizard, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgentWizardStep {
+    Name,
+    Role,
+    Model,
+    Capabilities,
+    Extensions,
+}
+
+impl AgentWizardStep {
+    pub const ALL: [AgentWizardStep; 5] = [
+        AgentWizardStep::Name,
+        AgentWizardStep::Role,
+        AgentWizardStep::Model,
+        AgentWizardStep::Capabilities,
+        AgentWizardStep::Extensions,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            AgentWizardStep::Name => "Name",
+            AgentWizardStep::Role => "Role",
+            AgentWizardStep::Model => "Model",
+            AgentWizardStep::Capabilities => "Capabilities",
+            AgentWizardStep::Extensions => "MCP Extensions",
+        }
+    }
+
+    fn index(&self) -> usize {
+        Self::ALL.iter().position(|step| step == self).unwrap_or(0)
+    }
+
+    pub fn next(&self) -> Option<Self> {
+        Self::ALL.get(self.index() + 1).copied()
+    }
+
+    pub fn previous(&self) -> Option<Self> {
+        self.index().checked_sub(1).map(|i| Self::ALL[i])
+    }
+}
+
+/// State of an open New Agent wizard
+pub struct AgentWizard {
+    pub step: AgentWizardStep,
+    pub draft: crate::agent::templates::AgentDraft,
+
+    /// Installed MCP extensions as (id, name), read when the wizard opens
+    pub extensions: Vec<(String, String)>,
+
+    /// Why the last step or the final save failed
+    pub error: Option<String>,
+}
+
+/// A preset that failed to load, open for fixing in Settings > Agents
+pub struct PresetEditor {
+    pub path: PathBuf,
+    pub json: String,
+    /// Problems in `json`, checked as it's edited
+    pub issues: Vec<PresetIssue>,
+    /// Why the last save failed
+    pub error: Option<String>,
+}
+
+impl PresetEditor {
+    /// Read the preset at `path`
+    ///
+    /// # Errors
+    /// - The file can't be read
+    pub fn open(path: PathBuf) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(&path)?;
+        let issues = validate_preset(&json);
+        Ok(Self {
+            path,
+            json,
+            issues,
+            error: None,
+        })
+    }
+
+    /// Check the edited JSON again
+    pub fn revalidate(&mut self) {
+        self.issues = validate_preset(&self.json);
+    }
+}
+
+/// Value plotted in the Usage view
+#[derive(PartialEq, Clone, Copy)]
+pub enum UsageMetric {
+    InputTokens,
+    OutputTokens,
+    Cost,
+}
+
+/// Extensions sub-view (Marketplace, Installed, Running)
 #[derive(PartialEq, Clone)]
 pub enum ExtensionsView {
     Marketplace, // Browse available MCP servers
     Installed,   // View and manage installed extensions (with filtering)
+    Running,     // Configured plugins, their state, tools and schemas
 }
 
 impl Default for ExtensionsView {
@@ -65,6 +190,300 @@ impl InstallTypeFilter {
     }
 }
 
+/// Time range for exporting persisted events
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum EventExportRange {
+    LastHour,
+    #[default]
+    Last24Hours,
+    Last7Days,
+    All,
+}
+
+impl EventExportRange {
+    pub fn label(&self) -> &str {
+        match self {
+            Self::LastHour => "Last hour",
+            Self::Last24Hours => "Last 24 hours",
+            Self::Last7Days => "Last 7 days",
+            Self::All => "All",
+        }
+    }
+
+    /// Start of the range relative to now (None = unbounded)
+    pub fn start(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        let now = chrono::Local::now();
+        match self {
+            Self::LastHour => Some(now - chrono::Duration::hours(1)),
+            Self::Last24Hours => Some(now - chrono::Duration::hours(24)),
+            Self::Last7Days => Some(now - chrono::Duration::days(7)),
+            Self::All => None,
+        }
+    }
+}
+
+/// Editable fields of Settings > Calendar
+///
+/// Secret fields start empty and are only stored when filled in, so saving
+/// other changes keeps the password or client secret already in the keychain.
+#[derive(Clone)]
+pub struct CalendarForm {
+    pub google: bool,
+    pub caldav_url: String,
+    pub caldav_username: String,
+    pub caldav_password: String,
+    pub google_client_id: String,
+    pub google_client_secret: String,
+    pub google_calendar_id: String,
+}
+
+impl Default for CalendarForm {
+    fn default() -> Self {
+        Self {
+            google: false,
+            caldav_url: String::new(),
+            caldav_username: String::new(),
+            caldav_password: String::new(),
+            google_client_id: String::new(),
+            google_client_secret: String::new(),
+            google_calendar_id: "primary".to_string(),
+        }
+    }
+}
+
+impl CalendarForm {
+    /// Fill the form from a saved configuration
+    pub fn from_config(config: Option<&CalendarConfig>) -> Self {
+        let mut form = Self::default();
+        match config {
+            Some(CalendarConfig::CalDav { url, username }) => {
+                form.caldav_url = url.clone();
+                form.caldav_username = username.clone();
+            }
+            Some(CalendarConfig::Google {
+                client_id,
+                calendar_id,
+            }) => {
+                form.google = true;
+                form.google_client_id = client_id.clone();
+                form.google_calendar_id = calendar_id.clone();
+            }
+            None => {}
+        }
+        form
+    }
+
+    /// Configuration described by the form
+    pub fn to_config(&self) -> CalendarConfig {
+        if self.google {
+            CalendarConfig::Google {
+                client_id: self.google_client_id.trim().to_string(),
+                calendar_id: match self.google_calendar_id.trim() {
+                    "" => "primary".to_string(),
+                    id => id.to_string(),
+                },
+            }
+        } else {
+            CalendarConfig::CalDav {
+                url: self.caldav_url.trim().to_string(),
+                username: self.caldav_username.trim().to_string(),
+            }
+        }
+    }
+
+    /// Name and value of the secret entered in the form (empty if unchanged)
+    pub fn secret(&self) -> (&'static str, &str) {
+        if self.google {
+            (GOOGLE_CLIENT_SECRET, &self.google_client_secret)
+        } else {
+            (CALDAV_PASSWORD_SECRET, &self.caldav_password)
+        }
+    }
+}
+
+/// Editable fields of Settings > Preferences > Daily Limits
+///
+/// Empty limit fields mean unlimited. When the saved limits are locked, the
+/// current PIN has to be entered to change them.
+#[derive(Clone, Default)]
+pub struct QuotaForm {
+    pub messages_per_day: String,
+    pub cost_per_day: String,
+    /// Current PIN, to unlock
+    pub pin: String,
+    /// PIN to lock with (empty keeps the current lock)
+    pub new_pin: String,
+    pub remove_pin: bool,
+    pub message: Option<(String, bool)>, // (message, is_error)
+}
+
+impl QuotaForm {
+    /// Fill the form from saved limits
+    pub fn from_quota(quota: &UsageQuota) -> Self {
+        Self {
+            messages_per_day: quota
+                .messages_per_day
+                .map(|n| n.to_string())
+                .unwrap_or_default(),
+            cost_per_day: quota
+                .cost_per_day
+                .map(|c| format!("{:.2}", c))
+                .unwrap_or_default(),
+            ..Self::default()
+        }
+    }
+
+    /// Limits described by the form
+    ///
+    /// # Errors
+    /// A message for the user if the PIN is wrong or a field isn't a number
+    pub fn to_quota(&self, current: &UsageQuota) -> Result<UsageQuota, String> {
+        if !current.check_pin(&self.pin) {
+            return Err("Wrong PIN".to_string());
+        }
+        let messages_per_day = match self.messages_per_day.trim() {
+            "" => None,
+            text => Some(
+                text.parse::<u32>()
+                    .map_err(|_| "Messages per day must be a whole number".to_string())?,
+            ),
+        };
+        let cost_per_day = match self.cost_per_day.trim().trim_start_matches('$') {
+            "" => None,
+            text => match text.parse::<f64>() {
+                Ok(cost) if cost >= 0.0 => Some(cost),
+                _ => return Err("Spending per day must be an amount in dollars".to_string()),
+            },
+        };
+
+        let mut quota = UsageQuota {
+            messages_per_day,
+            cost_per_day,
+            pin_hash: current.pin_hash.clone(),
+        };
+        if self.remove_pin {
+            quota.set_pin(None);
+        }
+        if !self.new_pin.trim().is_empty() {
+            quota.set_pin(Some(&self.new_pin));
+        }
+        Ok(quota)
+    }
+}
+
+/// Settings > A/B Tests: what to compare, and the run in progress or the
+/// last report (see `rustbot_core::prompt_test`)
+#[derive(Default)]
+pub struct PromptTestForm {
+    /// Compare two agents (true) or two drafts of one agent's instructions
+    pub compare_agents: bool,
+    /// Agent IDs; only the first is used when comparing drafts
+    pub agents: [String; 2],
+    /// Drafts of the first agent's instructions
+    pub instructions: [String; 2],
+    /// Prompt set, prompts separated by `---` lines
+    pub prompts: String,
+    pub judge: bool,
+    /// Model judging (empty = default model)
+    pub judge_model: String,
+    pub criteria: String,
+    /// Prompts finished in the running test, and how many there are
+    pub progress: Option<(Arc<AtomicUsize>, usize)>,
+    pub rx: Option<oneshot::Receiver<TestReport>>,
+    pub report: Option<TestReport>,
+    /// Prompt of the report shown with a diff
+    pub selected_case: usize,
+    pub message: Option<(String, bool)>, // (message, is_error)
+}
+
+impl PromptTestForm {
+    /// Load the first agent's instructions into both drafts
+    pub fn reset_drafts(&mut self, agents: &[AgentConfig]) {
+        let instructions = agents
+            .iter()
+            .find(|agent| agent.id == self.agents[0])
+            .map(|agent| agent.instructions.clone())
+            .unwrap_or_default();
+        self.instructions = [instructions.clone(), instructions];
+    }
+
+    /// Build the test from the form
+    ///
+    /// # Errors
+    /// - No prompts, or an agent that doesn't exist
+    pub fn to_test(
+        &self,
+        system_instructions: &str,
+        agents: &[AgentConfig],
+    ) -> Result<PromptTest, String> {
+        let prompts = parse_prompts(&self.prompts);
+        if prompts.is_empty() {
+            return Err("Enter at least one prompt".to_string());
+        }
+        let agent = |id: &str| {
+            agents
+                .iter()
+                .find(|agent| agent.id == id)
+                .ok_or_else(|| format!("Choose an agent for both variants ({} not found)", id))
+        };
+
+        let variants = if self.compare_agents {
+            let (a, b) = (agent(&self.agents[0])?, agent(&self.agents[1])?);
+            [
+                PromptVariant::from_agent(&format!("A: {}", a.name), system_instructions, a),
+                PromptVariant::from_agent(&format!("B: {}", b.name), system_instructions, b),
+            ]
+        } else {
+            let base = agent(&self.agents[0])?;
+            let draft = |label: &str, instructions: &str| {
+                let mut config = base.clone();
+                config.instructions = instructions.to_string();
+                PromptVariant::from_agent(label, system_instructions, &config)
+            };
+            [
+                draft("A", &self.instructions[0]),
+                draft("B", &self.instructions[1]),
+            ]
+        };
+
+        let judge = self.judge.then(|| Judge {
+            model: Some(self.judge_model.trim().to_string()).filter(|model| !model.is_empty()),
+            criteria: self.criteria.trim().to_string(),
+        });
+        Ok(PromptTest {
+            prompts,
+            variants,
+            judge,
+        })
+    }
+}
+
+/// A prompt from the library whose variables are being filled in
+///
+/// Shown above the chat input; the filled text is inserted into the input
+/// rather than sent, so it can still be edited.
+pub struct PromptForm {
+    pub template: PromptTemplate,
+    /// (variable, value) in order of first use
+    pub values: Vec<(String, String)>,
+}
+
+impl PromptForm {
+    pub fn new(template: PromptTemplate) -> Self {
+        let values = template
+            .variables()
+            .into_iter()
+            .map(|name| (name, String::new()))
+            .collect();
+        Self { template, values }
+    }
+
+    /// Template body with the values entered so far
+    pub fn text(&self) -> String {
+        self.template.fill(&self.values.iter().cloned().collect())
+    }
+}
+
 /// System prompts configuration
 #[derive(Serialize, Deserialize, Clone)]
 pub struct SystemPrompts {
@@ -89,11 +508,191 @@ pub struct ChatMessage {
     pub output_tokens: Option<u32>,
     /// Embedded image data URLs (extracted from markdown for easy access)
     pub embedded_images: Vec<String>,
+    /// Images the user attached (pasted) to this message
+    pub images: Vec<ImageAttachment>,
+    /// Tools the agent called while answering (assistant messages)
+    pub tool_calls: Vec<ToolCallRecord>,
+    /// When the message was sent (for answers: when the request started)
+    pub timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    /// Milliseconds until the first streamed text (assistant messages)
+    pub first_token_ms: Option<u64>,
+    /// Milliseconds until the answer was complete (assistant messages)
+    pub generation_ms: Option<u64>,
+    /// Pinned by the user (listed in the Pinned panel)
+    pub pinned: bool,
+    /// The user's 👍/👎 and comment (assistant messages)
+    pub feedback: Option<MessageFeedback>,
+    /// The request failed and the content is the error (offers Retry)
+    pub failed: bool,
+}
+
+impl ChatMessage {
+    /// What besides the list width decides how tall the message is drawn:
+    /// its text, its tool cards and their results, attached images, the
+    /// rating comment and whether it failed
+    ///
+    /// Keys `MessageHeights`, so a message that changes while off-screen is
+    /// measured again. Tool cards are only expanded or collapsed by clicking
+    /// them, while the message is drawn and measured anyway.
+    pub fn layout_key(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.content.len().hash(&mut hasher);
+        for call in &self.tool_calls {
+            let result_len = call.result.as_ref().map(|r| match r {
+                Ok(output) => output.len(),
+                Err(e) => e.len(),
+            });
+            (result_len, call.warning.is_some()).hash(&mut hasher);
+        }
+        self.embedded_images.len().hash(&mut hasher);
+        self.images.len().hash(&mut hasher);
+        self.feedback
+            .as_ref()
+            .map(|f| f.comment.len())
+            .hash(&mut hasher);
+        self.failed.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Time badge for the message header and the details shown on hover
+    ///
+    /// The badge is the local time, plus the total generation time for
+    /// answers; the details add the date and time to first token.
+    pub fn timing(&self) -> Option<(String, String)> {
+        let local = self.timestamp?.with_timezone(&chrono::Local);
+        let mut badge = local.format("%H:%M").to_string();
+        let verb = match self.role {
+            MessageRole::User => "Sent",
+            MessageRole::Assistant => "Requested",
+        };
+        let mut details = format!("{} {}", verb, local.format("%Y-%m-%d %H:%M:%S"));
+
+        if let Some(ms) = self.first_token_ms {
+            details.push_str(&format!(
+                "\nFirst token after {}",
+                format_duration(Duration::from_millis(ms))
+            ));
+        }
+        if let Some(ms) = self.generation_ms {
+            let total = format_duration(Duration::from_millis(ms));
+            badge.push_str(&format!(" · {}", total));
+            details.push_str(&format!("\nComplete after {}", total));
+        }
+        Some((badge, details))
+    }
+}
+
+/// Measured heights of chat messages, so off-screen ones can be skipped
+///
+/// Entries are keyed by `ChatMessage::layout_key` and list width: streaming,
+/// edits, tool results, rating comments and window resizes invalidate them and
+/// the message is measured again when drawn. A font size or interface scale
+/// change clears every tab's cache (see `RustbotApp::apply_theme`).
+#[derive(Default)]
+pub struct MessageHeights {
+    entries: Vec<Option<(u64, f32, f32)>>, // (layout key, width, height)
+}
+
+impl MessageHeights {
+    /// Height of message `index`, if it was measured with this layout and width
+    pub fn get(&self, index: usize, layout_key: u64, width: f32) -> Option<f32> {
+        match self.entries.get(index)? {
+            Some((key, w, height)) if *key == layout_key && *w == width => Some(*height),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, index: usize, layout_key: u64, width: f32, height: f32) {
+        if self.entries.len() <= index {
+            self.entries.resize(index + 1, None);
+        }
+        self.entries[index] = Some((layout_key, width, height));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Frames kept for the frame time figures in the diagnostics panel
+const FRAME_TIME_SAMPLES: usize = 120;
+
+/// How long recent frames took to build (`update`, without painting)
+#[derive(Default)]
+pub struct FrameTimes {
+    recent: std::collections::VecDeque<Duration>,
+}
+
+impl FrameTimes {
+    pub fn record(&mut self, elapsed: Duration) {
+        if self.recent.len() == FRAME_TIME_SAMPLES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(elapsed);
+    }
+
+    /// Mean and slowest of the recent frames, None before the first frame
+    pub fn summary(&self) -> Option<(Duration, Duration)> {
+        let slowest = *self.recent.iter().max()?;
+        let mean = self.recent.iter().sum::<Duration>() / self.recent.len() as u32;
+        Some((mean, slowest))
+    }
+}
+
+/// Longest wait before retrying a failed message
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Retries of a failed message in a row, and when the next one is sent
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetryState {
+    pub attempts: u32,
+    pub scheduled: Option<std::time::Instant>,
+}
+
+impl RetryState {
+    /// Wait before the next retry: none for the first, then doubling from
+    /// 2 s up to `MAX_RETRY_DELAY`
+    pub fn delay(&self) -> Duration {
+        if self.attempts == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs(1 << self.attempts.min(6)).min(MAX_RETRY_DELAY)
+    }
+}
+
+/// Shortest wait after the provider answers 429 Too Many Requests
+pub const MIN_RATE_LIMIT_WAIT: Duration = Duration::from_secs(2);
+
+/// Automatic retries of a rate-limited message before it's left to the user
+pub const MAX_RATE_LIMIT_RETRIES: u32 = 5;
+
+/// A message waiting to be sent: written while an answer was still coming,
+/// while the provider was rate limiting, or while offline with no local model
+pub struct QueuedMessage {
+    pub text: String,
+    pub images: Vec<ImageAttachment>,
 }
 
-/// Token usage statistics
+/// An answer whose mermaid diagrams are rendering in the background
+pub struct PendingDiagrams {
+    /// Index of the answer in the tab's messages
+    pub index: usize,
+
+    /// Answer text the diagrams were rendered from; the result is dropped if
+    /// the message changed in the meantime
+    pub source: String,
+
+    /// Markdown with the diagrams embedded, None if none rendered
+    pub rx: oneshot::Receiver<Option<String>>,
+}
+
+/// Token usage statistics in the old `rustbot_stats.json` format
+///
+/// Only read once at startup to migrate into the storage service.
 #[derive(Default, Serialize, Deserialize, Clone)]
-pub struct TokenStats {
+pub struct LegacyTokenStats {
     pub daily_input: u32,
     pub daily_output: u32,
     pub total_input: u32,
@@ -163,9 +762,218 @@ impl ContextTracker {
     }
 }
 
+/// Result of `RustbotApi::send_message_in`: the response stream or an error
+pub type AgentResultReceiver =
+    mpsc::UnboundedReceiver<anyhow::Result<mpsc::UnboundedReceiver<String>>>;
+
+/// Time between frames while waiting for or streaming a response (~30 Hz)
+///
+/// Chunks that arrive between two frames are appended to the message
+/// together, so the chat is laid out and its markdown rendered at most this
+/// often, however fast the model streams.
+pub const STREAM_FRAME_INTERVAL: Duration = Duration::from_millis(33);
+
+/// Append every chunk that arrived since the last frame to `response`
+///
+/// However many chunks came in, the message is then updated (and laid out)
+/// once per frame.
+///
+/// # Returns
+/// Whether any chunk arrived
+pub fn drain_stream(rx: &mut mpsc::UnboundedReceiver<String>, response: &mut String) -> bool {
+    let mut received = false;
+    while let Ok(chunk) = rx.try_recv() {
+        response.push_str(&chunk);
+        received = true;
+    }
+    received
+}
+
+/// Rotation speed of the waiting spinner, in radians per second
+pub const SPINNER_SPEED: f32 = 6.0;
+
+/// Shortest time the splash screen shows, so a fast start doesn't flash it
+pub const SPLASH_MIN_DURATION: Duration = Duration::from_millis(600);
+
+/// Longest the window close waits for saves and plugin shutdown
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How often the draft and a streaming answer are saved for crash recovery
+pub const RECOVERY_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Chat state of one tab
+///
+/// The visible tab's state lives directly on the app (`messages`,
+/// `response_rx`, ...) so views keep working on a single chat; switching tabs
+/// swaps it with the stored copy. Each tab has its own session in
+/// `RustbotApi`, so histories, agents and streaming responses stay separate.
+pub struct ChatTab {
+    /// Session in `RustbotApi` that holds this tab's LLM history and agent
+    pub api_session: String,
+    pub message_input: String,
+    pub pending_images: Vec<ImageAttachment>,
+    pub messages: Vec<ChatMessage>,
+    pub session: ConversationSession,
+    pub context_tracker: ContextTracker,
+    pub pending_agent_result: Option<AgentResultReceiver>,
+    pub turn_task: Option<tokio::task::AbortHandle>,
+    pub turn_started: Option<(std::time::Instant, bool)>,
+    pub response_rx: Option<mpsc::UnboundedReceiver<String>>,
+    pub current_response: String,
+    pub is_waiting: bool,
+    pub editing_message: Option<(usize, String)>,
+    pub regenerate_open: bool,
+    pub message_heights: MessageHeights,
+    pub feedback_comment: Option<(usize, String)>,
+    pub retry: RetryState,
+    pub pending_diagrams: Vec<PendingDiagrams>,
+    pub outbox: Vec<QueuedMessage>,
+    pub handoff: Option<Handoff>,
+    pub tool_overrides: HashMap<String, bool>,
+
+    /// Shown in its own OS window instead of the main one. Belongs to the
+    /// tab's slot, so it isn't swapped with the visible state.
+    pub popped_out: bool,
+}
+
+impl ChatTab {
+    pub fn new(api_session: String, agent_id: &str) -> Self {
+        Self {
+            api_session,
+            message_input: String::new(),
+            pending_images: Vec::new(),
+            messages: Vec::new(),
+            session: ConversationSession::new(agent_id),
+            context_tracker: ContextTracker::default(),
+            pending_agent_result: None,
+            turn_task: None,
+            turn_started: None,
+            response_rx: None,
+            current_response: String::new(),
+            is_waiting: false,
+            editing_message: None,
+            regenerate_open: false,
+            message_heights: MessageHeights::default(),
+            feedback_comment: None,
+            retry: RetryState::default(),
+            pending_diagrams: Vec::new(),
+            outbox: Vec::new(),
+            handoff: None,
+            tool_overrides: HashMap::new(),
+            popped_out: false,
+        }
+    }
+
+    /// Tab label: the first line of the first user message
+    pub fn title(messages: &[ChatMessage]) -> String {
+        const MAX_CHARS: usize = 24;
+        let Some(first) = messages.iter().find(|m| m.role == MessageRole::User) else {
+            return "New Chat".to_string();
+        };
+        let line = first.content.lines().next().unwrap_or_default().trim();
+        let mut title: String = line.chars().take(MAX_CHARS).collect();
+        if line.chars().count() > MAX_CHARS {
+            title.push('…');
+        }
+        title
+    }
+}
+
 /// Message role (User or Assistant)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MessageRole {
     User,
     Assistant,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drain_stream() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut response = String::new();
+        assert!(!drain_stream(&mut rx, &mut response));
+
+        // Chunks from between two frames are taken together
+        for chunk in ["Hel", "lo", ", world"] {
+            tx.send(chunk.to_string()).unwrap();
+        }
+        assert!(drain_stream(&mut rx, &mut response));
+        assert_eq!(response, "Hello, world");
+        assert!(!drain_stream(&mut rx, &mut response));
+
+        tx.send("!".to_string()).unwrap();
+        drop(tx);
+        assert!(drain_stream(&mut rx, &mut response));
+        assert_eq!(response, "Hello, world!");
+    }
+
+    fn message(content: &str) -> ChatMessage {
+        ChatMessage {
+            role: MessageRole::Assistant,
+            content: content.to_string(),
+            input_tokens: None,
+            output_tokens: None,
+            embedded_images: Vec::new(),
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+            timestamp: None,
+            first_token_ms: None,
+            generation_ms: None,
+            pinned: false,
+            feedback: None,
+            failed: false,
+        }
+    }
+
+    #[test]
+    fn test_message_heights_invalidation() {
+        let mut heights = MessageHeights::default();
+        let msg = message("Hello");
+        heights.set(1, msg.layout_key(), 400.0, 60.0);
+        assert_eq!(heights.get(1, msg.layout_key(), 400.0), Some(60.0));
+        assert_eq!(heights.get(0, msg.layout_key(), 400.0), None);
+
+        // A resize or more streamed text means measuring again
+        assert_eq!(heights.get(1, msg.layout_key(), 500.0), None);
+        assert_eq!(heights.get(1, message("Hello!").layout_key(), 400.0), None);
+
+        heights.clear();
+        assert_eq!(heights.get(1, msg.layout_key(), 400.0), None);
+    }
+
+    #[test]
+    fn test_layout_key_follows_cards_and_feedback() {
+        let mut msg = message("Done");
+        let plain = msg.layout_key();
+
+        // A tool card appears, then its result arrives
+        msg.tool_calls.push(ToolCallRecord {
+            id: "call_1".to_string(),
+            name: "search".to_string(),
+            arguments: serde_json::json!({}),
+            result: None,
+            duration: Duration::ZERO,
+            warning: None,
+        });
+        let running = msg.layout_key();
+        assert_ne!(running, plain);
+        msg.tool_calls[0].result = Some(Ok("three results".to_string()));
+        let finished = msg.layout_key();
+        assert_ne!(finished, running);
+
+        // A rating comment adds a row; pinning doesn't change the height
+        let mut feedback = MessageFeedback::new(crate::services::Rating::Up);
+        feedback.comment = "Spot on".to_string();
+        msg.feedback = Some(feedback);
+        let commented = msg.layout_key();
+        assert_ne!(commented, finished);
+        msg.pinned = true;
+        assert_eq!(msg.layout_key(), commented);
+
+        msg.failed = true;
+        assert_ne!(msg.layout_key(), commented);
+    }
+}
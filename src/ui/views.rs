@@ -1,16 +1,734 @@
 // UI view rendering methods for Rustbot
 // Contains all the main view rendering functions extracted from RustbotApp
 
-use crate::ui::{ExtensionsView, MessageRole, SettingsView};
+use crate::agent::{self, templates};
+use crate::audit;
+use crate::connectivity;
+use crate::event_sequence;
+use crate::fs_consent;
+use crate::git_tools;
+use crate::instruction_profiles;
+use crate::native_plugins;
+use crate::prompt_library;
+use crate::prompt_test::{self, DiffSpan};
+use crate::quota;
+use crate::services::Rating;
+use crate::system_context;
+use crate::theme;
+use crate::ui::accessibility::{self, AccessibleLabel};
+use crate::ui::theme::colors as theme_colors;
+use crate::ui::{commands, markdown, sequence_view, tool_cards};
+use crate::ui::{
+    AgentWizardStep, ChatTab, ExtensionsView, MessageRole, PresetEditor, SettingsView, UsageMetric,
+};
+use crate::usage;
 use eframe::egui;
-use egui_commonmark::CommonMarkViewer;
 use egui_phosphor::regular as icons;
-use std::sync::Arc;
+use std::collections::BTreeMap;
+
+/// Viewport of the window a popped-out chat tab is shown in
+fn chat_window_id(api_session: &str) -> egui::ViewportId {
+    egui::ViewportId::from_hash_of(("chat_window", api_session))
+}
+
+/// First 12 hex digits of an audit hash, enough to compare by eye
+fn short_hash(hash: &str) -> &str {
+    hash.get(..12).unwrap_or(hash)
+}
+
+/// Problems found in an agent preset, errors in red and warnings in amber
+fn render_preset_issues(ui: &mut egui::Ui, issues: &[agent::validation::PresetIssue]) {
+    let colors = theme_colors(ui.ctx());
+    for issue in issues {
+        let (icon, color) = if issue.warning {
+            (icons::WARNING, colors.warning)
+        } else {
+            (icons::X_CIRCLE, colors.error)
+        };
+        ui.label(
+            egui::RichText::new(format!("{} {}", icon, issue))
+                .size(12.0)
+                .color(color),
+        );
+    }
+}
+
+/// Editor for one native plugin setting
+fn render_plugin_setting(
+    ui: &mut egui::Ui,
+    kind: native_plugins::SettingKind,
+    value: &mut serde_json::Value,
+) {
+    match kind {
+        native_plugins::SettingKind::Text => {
+            let mut text = value.as_str().unwrap_or_default().to_string();
+            if ui.text_edit_singleline(&mut text).changed() {
+                *value = text.into();
+            }
+        }
+        native_plugins::SettingKind::Toggle => {
+            let mut on = value.as_bool().unwrap_or_default();
+            if ui.checkbox(&mut on, "").changed() {
+                *value = on.into();
+            }
+        }
+        native_plugins::SettingKind::Number => {
+            let mut number = value.as_f64().unwrap_or_default();
+            if ui.add(egui::DragValue::new(&mut number)).changed() {
+                *value = number.into();
+            }
+        }
+    }
+}
 
 /// Extension trait to add view rendering methods to RustbotApp
 /// This allows us to define methods on RustbotApp from a separate module
 impl crate::RustbotApp {
-    /// Render the main chat view with message history and input controls
+    /// Render the tab bar above the chat: one tab per conversation
+    ///
+    /// Tabs answering in the background show a spinner icon, tabs open in
+    /// their own window an arrow. The last tab can't be closed.
+    fn render_chat_tabs(&mut self, ui: &mut egui::Ui) {
+        let mut selected = None;
+        let mut closed = None;
+        let mut popped = None;
+        let mut open_new = false;
+        let mut switch_project = None;
+        let mut switch_profile = None;
+
+        ui.horizontal_wrapped(|ui| {
+            if !self.projects.is_empty() {
+                let current = self
+                    .current_project()
+                    .map_or("No project".to_string(), |p| p.name.clone());
+                egui::ComboBox::from_id_salt("project_switcher")
+                    .selected_text(format!("{} {}", icons::FOLDER, current))
+                    .show_ui(ui, |ui| {
+                        if ui
+                            .selectable_label(self.active_project.is_none(), "No project")
+                            .clicked()
+                        {
+                            switch_project = Some(None);
+                        }
+                        for project in &self.projects {
+                            let active = self.active_project.as_ref() == Some(&project.id);
+                            if ui.selectable_label(active, &project.name).clicked() {
+                                switch_project = Some(Some(project.id.clone()));
+                            }
+                        }
+                    })
+                    .response
+                    .on_hover_text("Switching projects starts a new chat in the project");
+                ui.add_space(6.0);
+            }
+
+            if self.instruction_profiles.len() > 1 {
+                egui::ComboBox::from_id_salt("instruction_profile_switcher")
+                    .selected_text(format!("{} {}", icons::NOTE, self.instruction_profile))
+                    .show_ui(ui, |ui| {
+                        for name in &self.instruction_profiles {
+                            if ui
+                                .selectable_label(*name == self.instruction_profile, name)
+                                .clicked()
+                            {
+                                switch_profile = Some(name.clone());
+                            }
+                        }
+                    })
+                    .response
+                    .on_hover_text("System instructions sent with every message");
+                ui.add_space(6.0);
+            }
+
+            for index in 0..self.tabs.len() {
+                let active = index == self.active_tab;
+                let (messages, waiting) = if active {
+                    (&self.messages, self.is_waiting)
+                } else {
+                    (&self.tabs[index].messages, self.tabs[index].is_waiting)
+                };
+
+                let popped_out = self.tabs[index].popped_out;
+                let mut label = ChatTab::title(messages);
+                if waiting {
+                    label = format!("{} {}", icons::CIRCLE_NOTCH, label);
+                } else if popped_out {
+                    label = format!("{} {}", icons::ARROW_SQUARE_OUT, label);
+                }
+                if ui.selectable_label(active, label).clicked() {
+                    selected = Some(index);
+                }
+                if !popped_out
+                    && ui
+                        .small_button(icons::ARROW_SQUARE_OUT)
+                        .accessible_label("Open in a new window")
+                        .clicked()
+                {
+                    popped = Some(index);
+                }
+                if self.tabs.len() > 1
+                    && ui
+                        .small_button(icons::X)
+                        .accessible_label("Close tab (Cmd+W)")
+                        .clicked()
+                {
+                    closed = Some(index);
+                }
+                ui.add_space(6.0);
+            }
+
+            if ui
+                .button(icons::PLUS)
+                .accessible_label("New chat tab (Cmd+T)")
+                .clicked()
+            {
+                open_new = true;
+            }
+
+            let pinned = self.messages.iter().filter(|m| m.pinned).count();
+            if ui
+                .selectable_label(
+                    self.pinned_panel_open,
+                    format!("{} Pinned ({})", icons::PUSH_PIN, pinned),
+                )
+                .on_hover_text("Show the messages pinned in this chat")
+                .clicked()
+            {
+                self.pinned_panel_open = !self.pinned_panel_open;
+            }
+            if ui
+                .selectable_label(
+                    self.folder_panel_open,
+                    format!("{} Folder", icons::FOLDER_OPEN),
+                )
+                .on_hover_text("Chat with folder: answer questions from a folder's files")
+                .clicked()
+            {
+                self.toggle_folder_panel();
+            }
+
+            if !connectivity::is_online() {
+                let (label, answers) = match connectivity::local_model() {
+                    Some(model) => (
+                        format!("{} Offline: using {}", icons::WIFI_SLASH, model),
+                        format!("Answers come from the local model {} until then.", model),
+                    ),
+                    None => (
+                        format!("{} Offline", icons::WIFI_SLASH),
+                        "Messages are queued and sent once the connection is back. Set a \
+                         local model in Settings > Preferences to keep chatting."
+                            .to_string(),
+                    ),
+                };
+                let disabled: Vec<String> = connectivity::OFFLINE_FEATURES
+                    .iter()
+                    .map(|(feature, instead)| format!("• {}: {}", feature, instead))
+                    .collect();
+                ui.add_space(6.0);
+                ui.label(egui::RichText::new(label).color(theme_colors(ui.ctx()).warning))
+                    .on_hover_text(format!(
+                        "The provider can't be reached. {}\n\nOff until back online:\n{}",
+                        answers,
+                        disabled.join("\n")
+                    ));
+            }
+
+            if self.privacy_mode {
+                ui.add_space(6.0);
+                ui.label(
+                    egui::RichText::new(format!("{} Local only", icons::SHIELD_CHECK))
+                        .color(theme_colors(ui.ctx()).success),
+                )
+                .on_hover_text(
+                    "Local-only mode is on: nothing leaves this machine except requests to \
+                     localhost and the endpoints allowed in Settings",
+                );
+            }
+
+            let limits = quota::tracker().limits();
+            if let Some(limit) = limits.messages_per_day {
+                let left = limit.saturating_sub(quota::tracker().today().messages);
+                ui.add_space(6.0);
+                let color = if left == 0 {
+                    theme_colors(ui.ctx()).error
+                } else {
+                    theme_colors(ui.ctx()).muted
+                };
+                ui.label(
+                    egui::RichText::new(format!("{} {} left today", icons::CLOCK, left))
+                        .color(color),
+                )
+                .on_hover_text(format!(
+                    "This account can send {} messages a day. The count resets at midnight.",
+                    limit
+                ));
+            }
+        });
+
+        if let Some(index) = selected {
+            self.select_tab(index);
+        }
+        if let Some(index) = popped {
+            tracing::info!("💬 Chat tab {} popped out into its own window", index);
+            self.tabs[index].popped_out = true;
+        }
+        if let Some(index) = closed {
+            self.close_tab(index);
+        }
+        if open_new {
+            self.new_tab();
+        }
+        if let Some(id) = switch_project {
+            self.switch_project(id);
+        }
+        if let Some(name) = switch_profile {
+            self.switch_instruction_profile(&name);
+        }
+    }
+
+    /// Render the main chat view: the tab bar and the visible conversation
+    ///
+    /// A tab that has been popped out into its own window shows a note here
+    /// instead of a second copy of the conversation.
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    /// * `ctx` - The egui Context for global state and repaints
+    pub fn render_chat_view(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        self.render_chat_tabs(ui);
+        ui.separator();
+
+        if self.tabs[self.active_tab].popped_out {
+            ui.add_space(40.0);
+            ui.vertical_centered(|ui| {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{} This chat is open in its own window",
+                        icons::ARROW_SQUARE_OUT
+                    ))
+                    .size(16.0)
+                    .color(theme_colors(ui.ctx()).muted),
+                );
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Show Window").clicked() {
+                        ctx.send_viewport_cmd_to(
+                            chat_window_id(&self.api_session),
+                            egui::ViewportCommand::Focus,
+                        );
+                    }
+                    if ui.button("Bring Back Here").clicked() {
+                        self.tabs[self.active_tab].popped_out = false;
+                    }
+                });
+            });
+            return;
+        }
+
+        if self.recovery_offer.is_some() {
+            self.render_recovery_banner(ui);
+        }
+        if self.active_handoff().is_some() {
+            self.render_handoff_banner(ui);
+        }
+        self.render_conversation(ui, ctx);
+    }
+
+    /// Say which specialist owns the chat after a handoff, with a way back
+    fn render_handoff_banner(&mut self, ui: &mut egui::Ui) {
+        let Some(handoff) = self.active_handoff() else {
+            return;
+        };
+        let name = |id: &str| {
+            self.agent_configs
+                .iter()
+                .find(|c| c.id == id)
+                .map_or_else(|| id.to_string(), |c| c.name.clone())
+        };
+        let (to, from) = (name(&handoff.to), name(&handoff.from));
+        let summary = handoff.summary.clone();
+
+        let mut back = false;
+        ui.horizontal_wrapped(|ui| {
+            ui.label(format!(
+                "{} {} is answering in this chat; {} handed it over",
+                icons::HANDSHAKE,
+                to,
+                from
+            ))
+            .on_hover_text(summary);
+            back = ui.button(format!("Back to {}", from)).clicked();
+        });
+        ui.separator();
+
+        if back {
+            self.end_handoff();
+        }
+    }
+
+    /// Tool panel above the input: switch the agent's tool sources (a
+    /// plugin, a specialist, the git tools, ...) on or off for this chat only
+    fn render_tool_toggles(&mut self, ui: &mut egui::Ui) {
+        let label = match self.tool_overrides.len() {
+            0 => format!("{} Tools", icons::WRENCH),
+            changed => format!("{} Tools ({} changed in this chat)", icons::WRENCH, changed),
+        };
+        if ui
+            .selectable_label(self.tool_panel_open, label)
+            .on_hover_text("Switch tools on or off for this chat")
+            .clicked()
+        {
+            self.tool_panel_open = !self.tool_panel_open;
+            if self.tool_panel_open {
+                self.refresh_tool_sources();
+            }
+        }
+        if !self.tool_panel_open {
+            return;
+        }
+
+        let mut changed = Vec::new();
+        let mut reset = false;
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            if self.tool_sources.is_empty() {
+                ui.label(
+                    egui::RichText::new("No tools available to this agent")
+                        .color(theme_colors(ui.ctx()).muted),
+                );
+            }
+            ui.horizontal_wrapped(|ui| {
+                for source in &self.tool_sources {
+                    let mut enabled = source.is_enabled(&self.tool_overrides);
+                    let tools: Vec<&str> = source
+                        .tools
+                        .iter()
+                        .map(|tool| tool.function.name.as_str())
+                        .collect();
+                    if ui
+                        .checkbox(&mut enabled, &source.label)
+                        .on_hover_text(tools.join("\n"))
+                        .changed()
+                    {
+                        changed.push((source.id.clone(), enabled, source.default_enabled));
+                    }
+                }
+            });
+            if !self.tool_overrides.is_empty() {
+                reset = ui.small_button("Reset to the agent's defaults").clicked();
+            }
+        });
+        ui.add_space(5.0);
+
+        // Only switches away from the default are kept
+        for (id, enabled, default_enabled) in changed {
+            if enabled == default_enabled {
+                self.tool_overrides.remove(&id);
+            } else {
+                self.tool_overrides.insert(id, enabled);
+            }
+        }
+        if reset {
+            self.tool_overrides.clear();
+        }
+    }
+
+    /// Offer to restore what a crash or force-quit left unsaved
+    fn render_recovery_banner(&mut self, ui: &mut egui::Ui) {
+        let Some(state) = &self.recovery_offer else {
+            return;
+        };
+        let what = match (state.draft.trim().is_empty(), state.interrupted.is_some()) {
+            (false, true) => "your unsent message and the answer that was streaming",
+            (false, false) => "your unsent message",
+            _ => "the answer that was streaming",
+        };
+        let text = format!(
+            "{} Rustbot didn't close cleanly. Restore {} ({})?",
+            icons::WARNING,
+            what,
+            state
+                .saved_at
+                .with_timezone(&chrono::Local)
+                .format("%b %d, %H:%M")
+        );
+
+        let mut restore = false;
+        let mut discard = false;
+        ui.horizontal_wrapped(|ui| {
+            ui.label(egui::RichText::new(text).color(theme_colors(ui.ctx()).warning));
+            restore = ui.button("Restore").clicked();
+            discard = ui.button("Discard").clicked();
+        });
+        ui.separator();
+
+        if restore {
+            self.restore_recovery();
+        } else if discard {
+            self.clear_recovery();
+        }
+    }
+
+    /// Show each popped-out chat tab in its own OS window
+    ///
+    /// A tab is swapped in while its window is drawn, the same way background
+    /// tabs are polled, so the window runs the same conversation view as the
+    /// main one. Closing the window brings the chat back into the main window.
+    pub fn render_chat_windows(&mut self, ctx: &egui::Context) {
+        for index in 0..self.tabs.len() {
+            if !self.tabs[index].popped_out {
+                continue;
+            }
+            let active = index == self.active_tab;
+            if !active {
+                self.swap_tab(index);
+            }
+
+            let viewport_id = chat_window_id(&self.api_session);
+            let builder = egui::ViewportBuilder::default()
+                .with_title(format!("{} - Rustbot", ChatTab::title(&self.messages)))
+                .with_inner_size([520.0, 680.0])
+                .with_min_inner_size([320.0, 360.0]);
+            let closed = ctx.show_viewport_immediate(viewport_id, builder, |ctx, _class| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    // Keeps scroll positions and text edits apart from the
+                    // same conversation's widgets in the main window
+                    ui.push_id(viewport_id, |ui| self.render_conversation(ui, ctx));
+                });
+                ctx.input(|i| i.viewport().close_requested())
+            });
+
+            if !active {
+                self.swap_tab(index);
+            }
+            if closed {
+                tracing::info!(
+                    "💬 Chat tab '{}' back in the main window",
+                    self.tabs[index].api_session
+                );
+                self.tabs[index].popped_out = false;
+            }
+        }
+    }
+
+    /// Render the Chat with folder panel: the folder, its index and whether
+    /// questions are answered from it
+    pub fn render_folder_panel(&mut self, ctx: &egui::Context) {
+        let mut build = false;
+
+        egui::SidePanel::right("folder_panel")
+            .resizable(true)
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                let colors = theme_colors(ui.ctx());
+                ui.horizontal(|ui| {
+                    ui.heading(format!("{} Chat with folder", icons::FOLDER_OPEN));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui
+                            .small_button(icons::X)
+                            .accessible_label("Close chat with folder")
+                            .clicked()
+                        {
+                            self.folder_panel_open = false;
+                        }
+                    });
+                });
+                ui.label(
+                    egui::RichText::new(
+                        "Index a folder, then ask about it: each message is sent with the \
+                         excerpts that match it best, and answers cite file paths and lines.",
+                    )
+                    .color(colors.muted),
+                );
+                ui.add_space(5.0);
+
+                ui.label("Folder:");
+                let path = ui.add(
+                    egui::TextEdit::singleline(&mut self.folder_chat_path)
+                        .hint_text("/path/to/folder")
+                        .desired_width(f32::INFINITY),
+                );
+                // The index belongs to the previous folder; load the new one's
+                // once editing is done
+                if path.changed() {
+                    self.folder_index = None;
+                    self.folder_index_message = None;
+                }
+                if path.lost_focus() && self.folder_index.is_none() {
+                    self.load_folder_index();
+                }
+
+                ui.horizontal(|ui| {
+                    let building = self.folder_index_rx.is_some();
+                    let label = if self.folder_index.is_some() {
+                        "Refresh index"
+                    } else {
+                        "Build index"
+                    };
+                    if ui
+                        .add_enabled(
+                            !building,
+                            egui::Button::new(format!("{} {}", icons::ARROWS_CLOCKWISE, label)),
+                        )
+                        .on_hover_text(
+                            "Files ignored by .gitignore are left out; refreshing only reads \
+                             files that changed",
+                        )
+                        .clicked()
+                    {
+                        build = true;
+                    }
+                    if building {
+                        ui.spinner();
+                    }
+                });
+                if let Some(index) = &self.folder_index {
+                    let built = index.built_at.map_or(String::new(), |at| {
+                        at.with_timezone(&chrono::Local)
+                            .format(", updated %Y-%m-%d %H:%M")
+                            .to_string()
+                    });
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "{} files, {} excerpts{}",
+                            index.files.len(),
+                            index.chunks.len(),
+                            built
+                        ))
+                        .size(12.0)
+                        .color(colors.muted),
+                    );
+                }
+                if let Some((message, is_error)) = &self.folder_index_message {
+                    let color = if *is_error {
+                        colors.error
+                    } else {
+                        colors.success
+                    };
+                    ui.label(egui::RichText::new(message).size(12.0).color(color));
+                }
+                ui.separator();
+
+                ui.add_enabled(
+                    self.folder_index.is_some(),
+                    egui::Checkbox::new(&mut self.folder_chat_enabled, "Answer from this folder"),
+                )
+                .on_hover_text(
+                    "Send the matching excerpts with each message in this window's chats",
+                );
+            });
+
+        if build {
+            self.build_folder_index(ctx);
+        }
+    }
+
+    /// Render the Pinned panel: the visible chat's pinned messages, with
+    /// the option to keep them in the model's context
+    pub fn render_pinned_panel(&mut self, ctx: &egui::Context) {
+        const PREVIEW_CHARS: usize = 200;
+        let mut jump_to = None;
+        let mut unpin = None;
+        let mut keep_changed = false;
+
+        egui::SidePanel::right("pinned_panel")
+            .resizable(true)
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                let colors = theme_colors(ui.ctx());
+                ui.horizontal(|ui| {
+                    ui.heading(format!("{} Pinned", icons::PUSH_PIN));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui
+                            .small_button(icons::X)
+                            .accessible_label("Close pinned messages")
+                            .clicked()
+                        {
+                            self.pinned_panel_open = false;
+                        }
+                    });
+                });
+                keep_changed = ui
+                    .checkbox(
+                        &mut self.session.keep_pinned_in_context,
+                        "Keep in the model's context",
+                    )
+                    .on_hover_text(
+                        "Send pinned messages with every request, even after older \
+                         messages are dropped from the history",
+                    )
+                    .changed();
+                ui.separator();
+
+                if !self.messages.iter().any(|m| m.pinned) {
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "Nothing pinned yet. Use {} on a message to keep it here.",
+                            icons::PUSH_PIN
+                        ))
+                        .color(colors.muted),
+                    );
+                    return;
+                }
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false; 2])
+                    .show(ui, |ui| {
+                        for (index, msg) in self.messages.iter().enumerate() {
+                            if !msg.pinned {
+                                continue;
+                            }
+                            egui::Frame::group(ui.style()).show(ui, |ui| {
+                                ui.set_width(ui.available_width());
+                                let (author, color) = match msg.role {
+                                    MessageRole::User => ("You", colors.user),
+                                    MessageRole::Assistant => ("Assistant", colors.assistant),
+                                };
+                                ui.horizontal(|ui| {
+                                    ui.colored_label(color, egui::RichText::new(author).strong());
+                                    if let Some((badge, details)) = msg.timing() {
+                                        ui.label(
+                                            egui::RichText::new(badge).small().color(colors.subtle),
+                                        )
+                                        .on_hover_text(details);
+                                    }
+                                });
+
+                                let mut preview: String =
+                                    msg.content.chars().take(PREVIEW_CHARS).collect();
+                                if msg.content.chars().count() > PREVIEW_CHARS {
+                                    preview.push('…');
+                                }
+                                ui.label(preview);
+
+                                ui.horizontal(|ui| {
+                                    if ui.small_button("Jump to").clicked() {
+                                        jump_to = Some(index);
+                                    }
+                                    if ui.small_button("Copy").clicked() {
+                                        ui.ctx().copy_text(msg.content.clone());
+                                    }
+                                    if ui.small_button("Unpin").clicked() {
+                                        unpin = Some(index);
+                                    }
+                                });
+                            });
+                            ui.add_space(4.0);
+                        }
+                    });
+            });
+
+        if let Some(index) = jump_to {
+            self.scroll_to_message = Some(index);
+        }
+        if let Some(index) = unpin {
+            self.toggle_pin(index);
+        } else if keep_changed {
+            self.save_session();
+        }
+    }
+
+    /// Render the visible conversation with message history and input controls
     ///
     /// This method handles:
     /// - Scrollable message display area
@@ -22,7 +740,7 @@ impl crate::RustbotApp {
     /// # Arguments
     /// * `ui` - The egui UI context for rendering
     /// * `ctx` - The egui Context for global state and repaints
-    pub fn render_chat_view(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+    fn render_conversation(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
         // Calculate available height for messages
         // Account for all UI elements below the message area:
         // - Status indicator (if waiting): ~35px
@@ -32,49 +750,219 @@ impl crate::RustbotApp {
         // - Context bar: ~25px
         // Total bottom UI: ~180px
         let status_height = if self.is_waiting { 35.0 } else { 0.0 };
-        let bottom_ui_height = status_height + 15.0 + 80.0 + 25.0 + 25.0;
+        let attachments_height = if self.pending_images.is_empty() {
+            0.0
+        } else {
+            80.0
+        };
+        let feedback_height = self
+            .command_feedback
+            .as_ref()
+            .map_or(0.0, |(text, _)| text.lines().count() as f32 * 18.0 + 6.0);
+        let prompt_form_height = self
+            .prompt_form
+            .as_ref()
+            .map_or(0.0, |form| form.values.len() as f32 * 26.0 + 60.0);
+        let token_count_height = if self.message_input.trim().is_empty() {
+            0.0
+        } else {
+            18.0
+        };
+        let outbox_height = if self.outbox.is_empty() {
+            0.0
+        } else {
+            self.outbox.len() as f32 * 22.0 + 40.0
+        };
+        let bottom_ui_height = status_height
+            + attachments_height
+            + outbox_height
+            + prompt_form_height
+            + feedback_height
+            + token_count_height
+            + 15.0
+            + 80.0
+            + 25.0
+            + 25.0;
         let available_height = ui.available_height() - bottom_ui_height - 20.0; // Extra margin
 
+        // Edit-and-resend actions, applied after the message list is drawn
+        let mut start_edit = None;
+        let mut resend_edit = false;
+        let mut cancel_edit = false;
+        // Regenerate actions: toggle the options, or Some(keep previous answer)
+        let mut toggle_regenerate = false;
+        let mut regenerate = None;
+        let mut toggle_pin = None;
+        // Ratings: (message, rating) clicked, and the comment box actions
+        let mut rate = None;
+        let mut save_comment = false;
+        let mut skip_comment = false;
+        // Retry actions for a failed latest message
+        let mut retry = false;
+        let mut cancel_retry = false;
+        // Diagram or table button clicked in a message
+        let mut markdown_action = None;
+        let scroll_to = self.scroll_to_message.take();
+        let last_index = self.messages.len().saturating_sub(1);
+
         // Scrollable message area
         egui::ScrollArea::vertical()
             .max_height(available_height.max(100.0)) // Minimum 100px for messages
             .stick_to_bottom(true)
             .auto_shrink([false; 2])
-            .show(ui, |ui| {
+            .show_viewport(ui, |ui, viewport| {
+                // Messages with a measured height that are off screen only
+                // reserve their space; the latest one is always drawn (streaming)
+                let width = ui.available_width();
+                let content_top = ui.max_rect().top();
+
                 if self.messages.is_empty() {
                     ui.vertical_centered(|ui| {
                         ui.add_space(20.0);
                         ui.label(
                             egui::RichText::new("Welcome! Type a message below to start chatting.")
-                                .color(egui::Color32::from_rgb(100, 100, 100)),
+                                .color(theme_colors(ui.ctx()).muted),
                         );
                     });
                 } else {
-                    for msg in &self.messages {
+                    // Messages next to the focused one are always drawn, so
+                    // Tab can move focus past the edge of the viewport
+                    let list_id = ui.id();
+                    let focused = ui.memory(|m| m.focused());
+                    let near_focus = |index: usize| {
+                        focused.is_some_and(|id| {
+                            (index.saturating_sub(1)..=index + 1)
+                                .any(|i| id == accessibility::message_focus_id(list_id, i))
+                        })
+                    };
+
+                    for (index, msg) in self.messages.iter().enumerate() {
+                        let top = ui.cursor().top() - content_top;
+                        let editing = matches!(self.editing_message, Some((i, _)) if i == index);
+                        let commenting =
+                            matches!(self.feedback_comment, Some((i, _)) if i == index);
+                        if index != last_index
+                            && !editing
+                            && !commenting
+                            && !near_focus(index)
+                            && scroll_to != Some(index)
+                        {
+                            if let Some(height) =
+                                self.message_heights.get(index, msg.layout_key(), width)
+                            {
+                                if top + height < viewport.min.y || top > viewport.max.y {
+                                    ui.add_space(height);
+                                    continue;
+                                }
+                            }
+                        }
+
                         let (label, color) = match msg.role {
-                            MessageRole::User => ("You", egui::Color32::from_rgb(45, 100, 200)),
+                            MessageRole::User => ("You", theme_colors(ui.ctx()).user),
                             MessageRole::Assistant => {
-                                ("Assistant", egui::Color32::from_rgb(60, 150, 60))
+                                ("Assistant", theme_colors(ui.ctx()).assistant)
                             }
                         };
 
-                        // Message header
-                        ui.horizontal(|ui| {
-                            ui.colored_label(
+                        // Message header, which is also the message's focus stop
+                        let focus_id = accessibility::message_focus_id(list_id, index);
+                        let focus = ui.horizontal(|ui| {
+                            let header = ui.colored_label(
                                 color,
                                 egui::RichText::new(format!("{}:", label)).strong(),
                             );
+                            let focus = accessibility::message_focus(
+                                ui,
+                                header.rect,
+                                focus_id,
+                                label,
+                                &msg.content,
+                            );
 
-                            // Copy button for assistant messages (only if message has content)
-                            if msg.role == MessageRole::Assistant && !msg.content.is_empty() {
-                                if ui.button(icons::CLIPBOARD_TEXT)
-                                    .on_hover_text("Copy message to clipboard")
+                            // Send time and answer latency; details on hover
+                            if let Some((badge, details)) = msg.timing() {
+                                ui.label(
+                                    egui::RichText::new(badge)
+                                        .small()
+                                        .color(theme_colors(ui.ctx()).subtle),
+                                )
+                                .on_hover_text(details);
+                            }
+
+                            // Copy button for assistant messages (only if message has content)
+                            if msg.role == MessageRole::Assistant && !msg.content.is_empty() {
+                                if ui.button(icons::CLIPBOARD_TEXT)
+                                    .accessible_label("Copy message to clipboard")
                                     .clicked()
                                 {
                                     ui.ctx().copy_text(msg.content.clone());
                                 }
                             }
 
+                            // Edit button for user messages (drops everything after it)
+                            if msg.role == MessageRole::User
+                                && !self.is_waiting
+                                && self.editing_message.is_none()
+                                && ui
+                                    .small_button(icons::PENCIL_SIMPLE)
+                                    .accessible_label("Edit and resend")
+                                    .clicked()
+                            {
+                                start_edit = Some(index);
+                            }
+
+                            // Regenerate button for the latest answer
+                            if msg.role == MessageRole::Assistant
+                                && index == last_index
+                                && !msg.content.is_empty()
+                                && !msg.failed
+                                && !self.is_waiting
+                                && ui
+                                    .small_button(icons::ARROWS_CLOCKWISE)
+                                    .accessible_label("Regenerate response")
+                                    .clicked()
+                            {
+                                toggle_regenerate = true;
+                            }
+
+                            // Pin button; pinned messages are listed in the Pinned panel
+                            if !msg.content.is_empty() {
+                                let (icon, name) = if msg.pinned {
+                                    (
+                                        egui::RichText::new(icons::PUSH_PIN)
+                                            .color(theme_colors(ui.ctx()).accent),
+                                        "Unpin message",
+                                    )
+                                } else {
+                                    (egui::RichText::new(icons::PUSH_PIN), "Pin message")
+                                };
+                                if ui.small_button(icon).accessible_label(name).clicked() {
+                                    toggle_pin = Some(index);
+                                }
+                            }
+
+                            // Thumbs up/down for finished answers
+                            let streaming = self.is_waiting && index == last_index;
+                            if msg.role == MessageRole::Assistant
+                                && !msg.content.is_empty()
+                                && !msg.failed
+                                && !streaming
+                            {
+                                let rating = msg.feedback.as_ref().map(|f| f.rating);
+                                for (value, icon, name) in [
+                                    (Rating::Up, icons::THUMBS_UP, "Rate as helpful"),
+                                    (Rating::Down, icons::THUMBS_DOWN, "Rate as unhelpful"),
+                                ] {
+                                    let mut text = egui::RichText::new(icon);
+                                    if rating == Some(value) {
+                                        text = text.color(theme_colors(ui.ctx()).accent);
+                                    }
+                                    if ui.small_button(text).accessible_label(name).clicked() {
+                                        rate = Some((index, value));
+                                    }
+                                }
+                            }
+
                             if msg.content.is_empty() && self.is_waiting {
                                 // Draw spinner
                                 let spinner_size = 12.0;
@@ -88,7 +976,7 @@ impl crate::RustbotApp {
                                 painter.circle_stroke(
                                     center,
                                     radius,
-                                    egui::Stroke::new(2.0, egui::Color32::from_rgb(150, 150, 150)),
+                                    egui::Stroke::new(2.0, theme_colors(ui.ctx()).subtle),
                                 );
 
                                 // Draw rotating arc
@@ -99,11 +987,12 @@ impl crate::RustbotApp {
                                     let angle = start_angle + arc_length * t;
                                     let pos = center + egui::vec2(angle.cos(), angle.sin()) * radius;
                                     let alpha = (t * 255.0) as u8;
+                                    let accent = theme_colors(ui.ctx()).accent;
                                     painter.circle_filled(
                                         pos,
                                         1.5,
                                         egui::Color32::from_rgba_premultiplied(
-                                            60, 120, 220, alpha,
+                                            accent.r(), accent.g(), accent.b(), alpha,
                                         ),
                                     );
                                 }
@@ -116,14 +1005,65 @@ impl crate::RustbotApp {
 
                                 ui.label(
                                     egui::RichText::new(status_text)
-                                        .color(egui::Color32::from_rgb(150, 150, 150))
+                                        .color(theme_colors(ui.ctx()).subtle)
                                         .italics(),
                                 );
                             }
-                        });
 
-                        // Display message content with proper wrapping and markdown rendering
-                        if !msg.content.is_empty() {
+                            focus
+                        })
+                        .inner;
+                        if focus.gained_focus() {
+                            focus.scroll_to_me(None);
+                        }
+                        if scroll_to == Some(index) {
+                            focus.scroll_to_me(Some(egui::Align::TOP));
+                        }
+
+                        // Tools called while answering, above the answer
+                        if !msg.tool_calls.is_empty() {
+                            ui.add_space(4.0);
+                            ui.horizontal(|ui| {
+                                ui.add_space(20.0);
+                                ui.vertical(|ui| {
+                                    for call in &msg.tool_calls {
+                                        tool_cards::show(ui, call);
+                                    }
+                                });
+                            });
+                        }
+
+                        // The message being edited shows a text box instead of its content
+                        let draft = match &mut self.editing_message {
+                            Some((edit_index, draft)) if *edit_index == index => Some(draft),
+                            _ => None,
+                        };
+                        if let Some(draft) = draft {
+                            ui.add_space(4.0);
+                            ui.horizontal(|ui| {
+                                ui.add_space(20.0); // Indent like message content
+                                ui.vertical(|ui| {
+                                    ui.add(
+                                        egui::TextEdit::multiline(draft)
+                                            .desired_rows(3)
+                                            .desired_width(f32::INFINITY),
+                                    );
+                                    ui.horizontal(|ui| {
+                                        if ui
+                                            .button(format!("{} Resend", icons::PAPER_PLANE_RIGHT))
+                                            .on_hover_text("Drop later replies and send again")
+                                            .clicked()
+                                        {
+                                            resend_edit = true;
+                                        }
+                                        if ui.button("Cancel").clicked() {
+                                            cancel_edit = true;
+                                        }
+                                    });
+                                });
+                            });
+                        } else if !msg.content.is_empty() {
+                            // Display message content with proper wrapping and markdown rendering
                             ui.add_space(4.0);
                             ui.horizontal(|ui| {
                                 ui.add_space(20.0); // Indent message content
@@ -131,8 +1071,14 @@ impl crate::RustbotApp {
                                 ui.vertical(|ui| {
                                     ui.set_max_width(available_width);
                                     // Render markdown content (mermaid preprocessing happens when content is set)
-                                    CommonMarkViewer::new()
-                                        .show(ui, &mut self.markdown_cache, &msg.content);
+                                    if let Some(action) = markdown::show(
+                                        ui,
+                                        &mut self.markdown_cache,
+                                        egui::Id::new(("message", index)),
+                                        &msg.content,
+                                    ) {
+                                        markdown_action = Some(action);
+                                    }
 
                                     // Add copy buttons for embedded images (Mermaid diagrams)
                                     if !msg.embedded_images.is_empty() {
@@ -150,7 +1096,7 @@ impl crate::RustbotApp {
                                                 if ui.button(
                                                     egui::RichText::new(label)
                                                         .size(10.5)
-                                                        .color(egui::Color32::from_rgb(80, 120, 180))
+                                                        .color(theme_colors(ui.ctx()).accent)
                                                 )
                                                 .on_hover_text("Copy diagram image to clipboard (as data URL)")
                                                 .clicked() {
@@ -167,11 +1113,219 @@ impl crate::RustbotApp {
                                 });
                             });
                         }
+
+                        // Comment on a rating, being written or saved
+                        let comment = match &mut self.feedback_comment {
+                            Some((comment_index, draft)) if *comment_index == index => Some(draft),
+                            _ => None,
+                        };
+                        if let Some(draft) = comment {
+                            ui.add_space(4.0);
+                            ui.horizontal(|ui| {
+                                ui.add_space(20.0);
+                                let response = ui.add(
+                                    egui::TextEdit::singleline(draft)
+                                        .hint_text("What was good or wrong? (optional)")
+                                        .desired_width(ui.available_width() - 140.0),
+                                );
+                                if response.lost_focus()
+                                    && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                                {
+                                    save_comment = true;
+                                }
+                                if ui.button("Save").clicked() {
+                                    save_comment = true;
+                                }
+                                if ui.button("Skip").clicked() {
+                                    skip_comment = true;
+                                }
+                            });
+                        } else if let Some(feedback) =
+                            msg.feedback.as_ref().filter(|f| !f.comment.is_empty())
+                        {
+                            ui.add_space(2.0);
+                            ui.horizontal(|ui| {
+                                ui.add_space(20.0);
+                                ui.label(
+                                    egui::RichText::new(&feedback.comment)
+                                        .small()
+                                        .italics()
+                                        .color(theme_colors(ui.ctx()).muted),
+                                );
+                            });
+                        }
+
+                        // Images the user attached
+                        if !msg.images.is_empty() {
+                            ui.add_space(4.0);
+                            ui.horizontal(|ui| {
+                                ui.add_space(20.0);
+                                for image in &msg.images {
+                                    image.thumbnail(ui);
+                                }
+                            });
+                        }
+
+                        // Retry for a failed answer, waiting longer after each failure
+                        if msg.failed && index == last_index && !self.is_waiting {
+                            ui.add_space(4.0);
+                            ui.horizontal(|ui| {
+                                ui.add_space(20.0);
+                                match self.retry.scheduled {
+                                    Some(scheduled) => {
+                                        let wait = scheduled
+                                            .saturating_duration_since(std::time::Instant::now());
+                                        ui.label(
+                                            egui::RichText::new(format!(
+                                                "Retrying in {} s…",
+                                                wait.as_secs() + 1
+                                            ))
+                                            .color(theme_colors(ui.ctx()).muted),
+                                        );
+                                        if ui.button("Cancel").clicked() {
+                                            cancel_retry = true;
+                                        }
+                                    }
+                                    None => {
+                                        let hint = match self.retry.delay().as_secs() {
+                                            0 => "Send your message again".to_string(),
+                                            secs => format!(
+                                                "Send your message again in {} s",
+                                                secs
+                                            ),
+                                        };
+                                        if ui
+                                            .button(format!("{} Retry", icons::ARROW_CLOCKWISE))
+                                            .on_hover_text(hint)
+                                            .clicked()
+                                        {
+                                            retry = true;
+                                        }
+                                        if self.retry.attempts > 0 {
+                                            ui.label(
+                                                egui::RichText::new(format!(
+                                                    "Failed {} times",
+                                                    self.retry.attempts + 1
+                                                ))
+                                                .small()
+                                                .color(theme_colors(ui.ctx()).subtle),
+                                            );
+                                        }
+                                    }
+                                }
+                            });
+                        }
+
+                        // Regenerate options: agent to ask and what to do with this answer
+                        if self.regenerate_open
+                            && index == last_index
+                            && msg.role == MessageRole::Assistant
+                            && !self.is_waiting
+                        {
+                            ui.add_space(4.0);
+                            ui.horizontal(|ui| {
+                                ui.add_space(20.0);
+                                ui.label("Regenerate with");
+                                let selected = self
+                                    .regenerate_agent
+                                    .as_deref()
+                                    .and_then(|id| self.agent_configs.iter().find(|c| c.id == id))
+                                    .map(|c| c.name.clone())
+                                    .unwrap_or_else(|| "Current agent".to_string());
+                                egui::ComboBox::from_id_salt("regenerate_agent")
+                                    .selected_text(selected)
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(
+                                            &mut self.regenerate_agent,
+                                            None,
+                                            "Current agent",
+                                        );
+                                        for config in &self.agent_configs {
+                                            if !config.enabled {
+                                                continue;
+                                            }
+                                            ui.selectable_value(
+                                                &mut self.regenerate_agent,
+                                                Some(config.id.clone()),
+                                                &config.name,
+                                            );
+                                        }
+                                    });
+                                if ui
+                                    .button("Replace")
+                                    .on_hover_text("Drop this answer and ask again")
+                                    .clicked()
+                                {
+                                    regenerate = Some(false);
+                                }
+                                if ui
+                                    .button("Add Alternative")
+                                    .on_hover_text("Keep this answer and add a new one below")
+                                    .clicked()
+                                {
+                                    regenerate = Some(true);
+                                }
+                                if ui.button("Cancel").clicked() {
+                                    toggle_regenerate = true;
+                                }
+                            });
+                        }
+                        if focus.has_focus() {
+                            let rect = egui::Rect::from_x_y_ranges(
+                                ui.max_rect().x_range(),
+                                (content_top + top)..=ui.cursor().top(),
+                            );
+                            accessibility::focus_ring(ui, rect, theme_colors(ui.ctx()).accent);
+                        }
                         ui.add_space(8.0);
+
+                        let height = ui.cursor().top() - content_top - top;
+                        self.message_heights
+                            .set(index, msg.layout_key(), width, height);
                     }
                 }
             });
 
+        if let Some(index) = start_edit {
+            self.editing_message = Some((index, self.messages[index].content.clone()));
+        }
+        if cancel_edit {
+            self.editing_message = None;
+        }
+        if resend_edit {
+            if let Some((index, content)) = self.editing_message.take() {
+                self.resend_edited_message(index, content);
+            }
+        }
+        if toggle_regenerate {
+            self.regenerate_open = !self.regenerate_open;
+        }
+        if let Some(index) = toggle_pin {
+            self.toggle_pin(index);
+        }
+        if save_comment {
+            self.save_feedback_comment();
+        }
+        if retry {
+            self.retry_failed_message();
+        }
+        if cancel_retry {
+            self.retry.scheduled = None;
+        }
+        if skip_comment {
+            self.feedback_comment = None;
+        }
+        if let Some((index, rating)) = rate {
+            self.rate_message(index, rating);
+        }
+        if let Some(action) = markdown_action {
+            self.handle_markdown_action(action);
+        }
+        if let Some(keep_previous) = regenerate {
+            self.regenerate_open = false;
+            self.regenerate_response(self.regenerate_agent.clone(), keep_previous);
+        }
+
         ui.separator();
 
         // Status indicator when processing
@@ -187,7 +1341,7 @@ impl crate::RustbotApp {
                 ui.painter().circle_stroke(
                     spinner_rect.center(),
                     5.0,
-                    egui::Stroke::new(2.0, egui::Color32::from_rgb(100, 150, 255)),
+                    egui::Stroke::new(2.0, theme_colors(ui.ctx()).accent),
                 );
                 ui.painter().circle_filled(
                     egui::pos2(
@@ -195,7 +1349,7 @@ impl crate::RustbotApp {
                         spinner_rect.center().y + 5.0 * self.spinner_rotation.sin(),
                     ),
                     2.0,
-                    egui::Color32::from_rgb(100, 150, 255),
+                    theme_colors(ui.ctx()).accent,
                 );
 
                 ui.add_space(20.0);
@@ -204,7 +1358,7 @@ impl crate::RustbotApp {
                 ui.label(
                     egui::RichText::new("Processing your message...")
                         .size(12.0)
-                        .color(egui::Color32::from_rgb(100, 150, 255)),
+                        .color(theme_colors(ui.ctx()).accent),
                 );
             });
             ui.add_space(5.0);
@@ -213,37 +1367,329 @@ impl crate::RustbotApp {
         // Add spacing before input area
         ui.add_space(15.0);
 
+        // Pasted images waiting to be sent, each with a remove button
+        let mut remove_image = None;
+        if !self.pending_images.is_empty() {
+            ui.horizontal(|ui| {
+                for (i, image) in self.pending_images.iter().enumerate() {
+                    egui::Frame::group(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            image.thumbnail(ui);
+                            if ui
+                                .small_button(icons::X)
+                                .accessible_label("Remove image")
+                                .clicked()
+                            {
+                                remove_image = Some(i);
+                            }
+                        });
+                    });
+                }
+            });
+            ui.add_space(5.0);
+        }
+        if let Some(i) = remove_image {
+            self.pending_images.remove(i);
+        }
+
+        // Queued messages, sent in order once nothing is in the way
+        let mut unqueue = None;
+        if !self.outbox.is_empty() {
+            let now = std::time::Instant::now();
+            let status = match self.rate_limited_until.filter(|until| *until > now) {
+                Some(until) => format!(
+                    "Queued: the provider is rate limiting, next send in {} s",
+                    (until - now).as_secs() + 1
+                ),
+                None if !connectivity::can_chat() => {
+                    "Queued until Rustbot is back online".to_string()
+                }
+                None => "Queued: sent after the current answer".to_string(),
+            };
+            egui::Frame::group(ui.style()).show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                ui.label(
+                    egui::RichText::new(format!("{} {}", icons::CLOCK, status))
+                        .size(12.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                );
+                for (i, queued) in self.outbox.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .small_button(icons::X)
+                            .accessible_label("Remove queued message")
+                            .clicked()
+                        {
+                            unqueue = Some(i);
+                        }
+                        let line = queued.text.lines().next().unwrap_or_default();
+                        let mut preview: String = line.chars().take(80).collect();
+                        if !queued.images.is_empty() {
+                            preview.push_str(&format!(" (+{} images)", queued.images.len()));
+                        }
+                        ui.label(preview);
+                    });
+                }
+            });
+            ui.add_space(5.0);
+        }
+        if let Some(i) = unqueue {
+            self.outbox.remove(i);
+        }
+
+        // Variables of the prompt picked from the library
+        let mut insert_prompt = false;
+        let mut cancel_prompt = false;
+        if let Some(form) = &mut self.prompt_form {
+            egui::Frame::group(ui.style()).show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{} {}",
+                        icons::BOOKMARK_SIMPLE,
+                        form.template.name
+                    ))
+                    .strong(),
+                );
+                egui::Grid::new("prompt_variables")
+                    .num_columns(2)
+                    .spacing([10.0, 4.0])
+                    .show(ui, |ui| {
+                        for (name, value) in &mut form.values {
+                            ui.label(format!("{}:", name));
+                            ui.add(egui::TextEdit::singleline(value).desired_width(400.0));
+                            ui.end_row();
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    insert_prompt = ui.button("Insert").clicked();
+                    cancel_prompt = ui.button("Cancel").clicked();
+                });
+            });
+            ui.add_space(5.0);
+        }
+        if insert_prompt {
+            if let Some(form) = self.prompt_form.take() {
+                self.insert_prompt_text(form.text());
+            }
+        }
+        if cancel_prompt {
+            self.prompt_form = None;
+        }
+
+        // Tools switched on or off for this chat
+        self.render_tool_toggles(ui);
+
         // Input area with multi-line text box
         ui.horizontal(|ui| {
-            let text_edit_width = ui.available_width() - 70.0;
-            let _response = ui.add_sized(
+            let text_edit_width = ui.available_width() - 150.0;
+            let response = ui.add_sized(
                 [text_edit_width, 80.0],
                 egui::TextEdit::multiline(&mut self.message_input)
-                    .hint_text("Type your message here...\n\nPress Cmd+Enter to send")
+                    .hint_text("Type a message or / for commands...\n\nPress Cmd+Enter to send")
                     .desired_width(text_edit_width),
             );
+            if std::mem::take(&mut self.focus_input_requested) {
+                response.request_focus();
+            }
 
-            let send_button = ui.add_sized(
-                [60.0, 80.0],
-                egui::Button::new(if self.is_waiting { "..." } else { "Send" }),
-            );
+            // Slash command autocomplete, drawn above the input
+            let completions = commands::completions(&self.message_input);
+            if !completions.is_empty() {
+                let mut chosen = None;
+                egui::Area::new(egui::Id::new("slash_command_popup"))
+                    .order(egui::Order::Foreground)
+                    .pivot(egui::Align2::LEFT_BOTTOM)
+                    .fixed_pos(response.rect.left_top())
+                    .show(ui.ctx(), |ui| {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            for command in &completions {
+                                ui.horizontal(|ui| {
+                                    if ui.selectable_label(false, command.usage()).clicked() {
+                                        chosen = Some(command.name);
+                                    }
+                                    ui.label(
+                                        egui::RichText::new(command.description)
+                                            .small()
+                                            .color(theme_colors(ui.ctx()).muted),
+                                    );
+                                });
+                            }
+                        });
+                    });
+
+                if let Some(name) = chosen {
+                    self.message_input = format!("{} ", name);
+                    // Keep typing after the command name
+                    if let Some(mut state) = egui::TextEdit::load_state(ui.ctx(), response.id) {
+                        let end = egui::text::CCursor::new(self.message_input.chars().count());
+                        state
+                            .cursor
+                            .set_char_range(Some(egui::text::CCursorRange::one(end)));
+                        state.store(ui.ctx(), response.id);
+                    }
+                    response.request_focus();
+                }
+            }
+
+            let prompts_button = ui
+                .add_sized([32.0, 80.0], egui::Button::new(icons::BOOKMARK_SIMPLE))
+                .accessible_label("Insert a saved prompt");
+            if prompts_button.clicked() {
+                self.prompt_picker_open = !self.prompt_picker_open;
+            }
+
+            // Prompt library picker, drawn above the button
+            let picker_open = self.prompt_picker_open;
+            if self.prompt_picker_open {
+                let mut chosen = None;
+                egui::Area::new(egui::Id::new("prompt_picker_popup"))
+                    .order(egui::Order::Foreground)
+                    .pivot(egui::Align2::RIGHT_BOTTOM)
+                    .fixed_pos(prompts_button.rect.right_top())
+                    .show(ui.ctx(), |ui| {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            ui.set_max_width(320.0);
+                            if self.prompt_library.prompts.is_empty() {
+                                ui.label(
+                                    egui::RichText::new(
+                                        "No saved prompts yet (Settings > Prompts)",
+                                    )
+                                    .color(theme_colors(ui.ctx()).muted),
+                                );
+                            }
+                            for prompt in &self.prompt_library.prompts {
+                                let item = ui.selectable_label(false, &prompt.name);
+                                let item = if prompt.description.is_empty() {
+                                    item
+                                } else {
+                                    item.on_hover_text(&prompt.description)
+                                };
+                                if item.clicked() {
+                                    chosen = Some(prompt.name.clone());
+                                }
+                            }
+                        });
+                    });
+                if let Some(name) = chosen {
+                    self.use_prompt(&name);
+                    response.request_focus();
+                } else if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    self.prompt_picker_open = false;
+                }
+            }
+
+            let paste_button = ui
+                .add_sized([32.0, 80.0], egui::Button::new(icons::IMAGE))
+                .accessible_label("Paste image from clipboard");
+
+            // Stop replaces Send while an answer is being generated
+            let (label, hover) = if self.is_waiting {
+                (
+                    egui::RichText::new(format!("{}\nStop", icons::STOP))
+                        .color(theme_colors(ui.ctx()).error),
+                    "Stop generating (Esc)",
+                )
+            } else {
+                (egui::RichText::new("Send"), "Send (Cmd+Enter)")
+            };
+            let send_button = ui
+                .add_sized([60.0, 80.0], egui::Button::new(label))
+                .on_hover_text(hover);
+
+            // Pasting into the input also attaches a clipboard image. egui
+            // reports no paste when the clipboard has no text, hence the button.
+            let pasted = response.has_focus()
+                && ui.input(|i| {
+                    i.events.iter().any(|e| matches!(e, egui::Event::Paste(_)))
+                        || (i.modifiers.command && i.key_pressed(egui::Key::V))
+                });
+            if paste_button.clicked() || pasted {
+                self.paste_clipboard_image(ctx);
+            }
 
             // Send on Cmd+Enter or button click
             let cmd_enter = ui.input(|i| {
                 i.key_pressed(egui::Key::Enter) && (i.modifiers.command || i.modifiers.ctrl)
             });
 
-            if (send_button.clicked() || cmd_enter) && !self.is_waiting {
+            // Esc stops only when it's meant for the chat: the input has focus
+            // (a text field gives it up on Esc, hence lost_focus) and no popup
+            // or picker is open for Esc to close instead
+            let escape = (response.has_focus() || response.lost_focus())
+                && !picker_open
+                && !egui::Popup::is_any_open(ui.ctx())
+                && ui.input(|i| i.key_pressed(egui::Key::Escape));
+
+            // Cmd+Enter while an answer is coming queues the message
+            if self.is_waiting {
+                if send_button.clicked() || escape {
+                    self.stop_generation();
+                } else if cmd_enter {
+                    self.send_message(ctx);
+                }
+            } else if send_button.clicked() || cmd_enter {
                 self.send_message(ctx);
             }
         });
 
+        // Live token count of the message, with a warning when the message
+        // and the conversation so far won't fit the model's context window
+        if !self.message_input.trim().is_empty() {
+            let input_tokens = self.input_token_count();
+            let total = self.context_tracker.current_tokens + input_tokens;
+            let colors = theme_colors(ui.ctx());
+            let (text, color) = if total > self.context_tracker.max_tokens {
+                (
+                    format!(
+                        "{} {} tokens · {}k with the conversation, over the model's {}k window",
+                        icons::WARNING,
+                        input_tokens,
+                        total / 1000,
+                        self.context_tracker.max_tokens / 1000
+                    ),
+                    colors.error,
+                )
+            } else {
+                (format!("{} tokens", input_tokens), colors.muted)
+            };
+            ui.label(egui::RichText::new(text).size(11.0).color(color));
+        }
+
+        // Slash command output (/help, unknown agent...)
+        let mut dismiss_feedback = false;
+        if let Some((text, is_error)) = &self.command_feedback {
+            ui.horizontal(|ui| {
+                let colors = theme_colors(ui.ctx());
+                let color = if *is_error {
+                    colors.error
+                } else {
+                    colors.muted
+                };
+                ui.label(egui::RichText::new(text).small().color(color));
+                if ui
+                    .small_button(icons::X)
+                    .accessible_label("Dismiss")
+                    .clicked()
+                {
+                    dismiss_feedback = true;
+                }
+            });
+        }
+        if dismiss_feedback {
+            self.command_feedback = None;
+        }
+
         // Compact token tracker under input box
         ui.horizontal(|ui| {
-            let daily_cost =
-                self.calculate_cost(self.token_stats.daily_input, self.token_stats.daily_output);
-            let total_cost =
-                self.calculate_cost(self.token_stats.total_input, self.token_stats.total_output);
+            let daily_cost = self.calculate_cost(
+                self.token_stats.daily_input_tokens,
+                self.token_stats.daily_output_tokens,
+            );
+            let total_cost = self.calculate_cost(
+                self.token_stats.total_input_tokens,
+                self.token_stats.total_output_tokens,
+            );
 
             // Get current model from primary agent
             let model = self
@@ -261,15 +1707,15 @@ impl crate::RustbotApp {
                     "{} {} • Daily: {}↑ {}↓ (${:.4})  •  Total: {}↑ {}↓ (${:.4})",
                     icons::CHART_LINE,
                     model,
-                    self.token_stats.daily_input,
-                    self.token_stats.daily_output,
+                    self.token_stats.daily_input_tokens,
+                    self.token_stats.daily_output_tokens,
                     daily_cost,
-                    self.token_stats.total_input,
-                    self.token_stats.total_output,
+                    self.token_stats.total_input_tokens,
+                    self.token_stats.total_output_tokens,
                     total_cost
                 ))
                 .size(11.0)
-                .color(egui::Color32::from_rgb(120, 120, 120)),
+                .color(theme_colors(ui.ctx()).muted),
             );
 
             // Add space before buttons
@@ -299,6 +1745,30 @@ impl crate::RustbotApp {
 
             ui.add_space(10.0);
 
+            // Export chat to a file
+            if ui
+                .button(
+                    egui::RichText::new(format!("{} Export", icons::DOWNLOAD_SIMPLE)).size(11.0),
+                )
+                .on_hover_text("Export conversation to Markdown, HTML or JSON")
+                .clicked()
+            {
+                self.open_conversation_export_dialog();
+            }
+
+            ui.add_space(10.0);
+
+            // Share as a self-contained page or a gist
+            if ui
+                .button(egui::RichText::new(format!("{} Share", icons::LINK)).size(11.0))
+                .on_hover_text("Share conversation as an HTML page or a GitHub gist")
+                .clicked()
+            {
+                self.open_share_dialog();
+            }
+
+            ui.add_space(10.0);
+
             // Clear chat button
             if ui
                 .button(egui::RichText::new("🗑 Clear Chat").size(11.0))
@@ -317,14 +1787,20 @@ impl crate::RustbotApp {
             // Draw progress bar
             let available_width = ui.available_width() - 150.0;
             let bar_height = 8.0;
-            let (rect, _response) = ui.allocate_exact_size(
+            let (rect, response) = ui.allocate_exact_size(
                 egui::vec2(available_width, bar_height),
-                egui::Sense::hover(),
+                egui::Sense::click(),
             );
+            if response
+                .on_hover_text("Click to inspect what the next message will send")
+                .clicked()
+            {
+                self.open_context_inspector();
+            }
 
             // Background (gray)
             ui.painter()
-                .rect_filled(rect, 2.0, egui::Color32::from_rgb(200, 200, 200));
+                .rect_filled(rect, 2.0, theme_colors(ui.ctx()).border);
 
             // Filled portion (color-coded)
             let filled_width = (available_width * percentage / 100.0)
@@ -348,1141 +1824,5022 @@ impl crate::RustbotApp {
                 .color(color),
             );
         });
+
+        if self.conversation_export_open {
+            self.render_conversation_export_dialog(ctx);
+        }
+        if self.share_open {
+            self.render_share_dialog(ctx);
+        }
     }
 
-    /// Render the settings view with navigation tabs
+    /// Render the approval dialog for the oldest email waiting to be sent
     ///
-    /// Provides a tabbed interface for:
-    /// - AI Settings (model selection)
-    /// - System Prompts (instructions and personality)
-    /// - Agents (agent configuration)
+    /// The decision is sent back to the waiting `send_email` call over the
+    /// event bus. Closing the window counts as "Don't send".
     ///
     /// # Arguments
-    /// * `ui` - The egui UI context for rendering
-    pub fn render_settings_view(&mut self, ui: &mut egui::Ui) {
-        // Secondary navigation bar under header
-        ui.horizontal(|ui| {
-            let system_prompts_button = ui.add(egui::SelectableLabel::new(
-                self.settings_view == SettingsView::SystemPrompts,
-                "System Prompts",
-            ));
-            if system_prompts_button.clicked() {
-                self.settings_view = SettingsView::SystemPrompts;
-            }
+    /// * `ctx` - The egui Context the dialog window is shown in
+    pub fn render_email_approval_dialog(&mut self, ctx: &egui::Context) {
+        let Some((_, draft)) = self.email_approvals.front() else {
+            return;
+        };
+        let from = self
+            .email
+            .as_ref()
+            .map(|email| email.config().from_address().to_string())
+            .unwrap_or_default();
+        let waiting = self.email_approvals.len() - 1;
+
+        let mut open = true;
+        let mut decision = None;
+
+        egui::Window::new(format!("{} Send Email?", icons::WARNING))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label("An agent wants to send this email on your behalf:");
+                ui.add_space(10.0);
 
-            ui.add_space(10.0);
+                egui::Grid::new("email_approval_headers")
+                    .num_columns(2)
+                    .spacing([10.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.label(egui::RichText::new("From:").strong());
+                        ui.label(&from);
+                        ui.end_row();
+                        ui.label(egui::RichText::new("To:").strong());
+                        ui.label(&draft.to);
+                        ui.end_row();
+                        ui.label(egui::RichText::new("Subject:").strong());
+                        ui.label(&draft.subject);
+                        ui.end_row();
+                        if let Some(id) = &draft.in_reply_to {
+                            ui.label(egui::RichText::new("In reply to:").strong());
+                            ui.label(id);
+                            ui.end_row();
+                        }
+                    });
 
-            let agents_button = ui.add(egui::SelectableLabel::new(
-                self.settings_view == SettingsView::Agents,
-                "Agents",
-            ));
-            if agents_button.clicked() {
-                self.settings_view = SettingsView::Agents;
-            }
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        ui.label(&draft.body);
+                    });
+                ui.separator();
 
-            ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.button(format!("{} Send", icons::CHECK_CIRCLE)).clicked() {
+                        decision = Some(true);
+                    }
+                    if ui.button(format!("{} Don't Send", icons::X)).clicked() {
+                        decision = Some(false);
+                    }
+                });
 
-            let preferences_button = ui.add(egui::SelectableLabel::new(
-                self.settings_view == SettingsView::Preferences,
-                "Preferences",
-            ));
-            if preferences_button.clicked() {
-                self.settings_view = SettingsView::Preferences;
-            }
-        });
-        ui.separator();
+                if waiting > 0 {
+                    ui.label(
+                        egui::RichText::new(format!("{} more waiting", waiting))
+                            .size(12.0)
+                            .color(theme_colors(ui.ctx()).muted),
+                    );
+                }
+            });
 
-        // Render content based on selected settings view
-        match self.settings_view {
-            SettingsView::SystemPrompts => self.render_system_prompts(ui),
-            SettingsView::Agents => self.render_agents_view(ui),
-            SettingsView::Preferences => self.render_preferences_view(ui),
+        if !open {
+            decision = Some(false);
+        }
+        if let Some(approved) = decision {
+            if let Some((request, draft)) = self.email_approvals.pop_front() {
+                tracing::info!(
+                    "📧 Email to {} {}",
+                    draft.to,
+                    if approved { "approved" } else { "declined" }
+                );
+                if let Err(e) = self.deps.event_bus.respond(
+                    &request,
+                    "user",
+                    Ok(serde_json::Value::Bool(approved)),
+                ) {
+                    tracing::warn!("Failed to answer email approval: {}", e);
+                }
+            }
         }
     }
 
-    /// Render the system prompts configuration view
-    ///
-    /// Allows editing of:
-    /// - System Instructions: Core behavioral instructions for the AI (shared across all agents)
-    ///
-    /// Note: Agent personality is now configured per-agent in the Agents settings.
+    /// Render the approval dialog for the oldest commit an agent wants to make
     ///
-    /// Changes can be saved and are backed up automatically.
+    /// The decision is sent back to the waiting `git_commit` call over the
+    /// event bus. Closing the window counts as "Don't Commit".
     ///
     /// # Arguments
-    /// * `ui` - The egui UI context for rendering
-    pub fn render_system_prompts(&mut self, ui: &mut egui::Ui) {
-        // Use scroll area for system prompts
-        egui::ScrollArea::vertical()
-            .auto_shrink([false; 2])
-            .show(ui, |ui| {
-                ui.add_space(20.0);
-                ui.heading("System Instructions");
+    /// * `ctx` - The egui Context the dialog window is shown in
+    pub fn render_git_approval_dialog(&mut self, ctx: &egui::Context) {
+        let Some((_, draft)) = self.git_approvals.front() else {
+            return;
+        };
+        let waiting = self.git_approvals.len() - 1;
+
+        let mut open = true;
+        let mut decision = None;
+
+        egui::Window::new(format!("{} Commit Changes?", icons::WARNING))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "An agent wants to commit the staged changes in {}:",
+                    draft.repository.display()
+                ));
                 ui.add_space(10.0);
 
-                ui.label("These instructions are sent with every chat session (shared across all agents):");
-                ui.add_space(5.0);
-                ui.label(egui::RichText::new("Note: Agent personality is configured per-agent in the Agents tab.")
-                    .size(12.0)
-                    .color(egui::Color32::from_rgb(100, 100, 100)));
-                ui.add_space(10.0);
-
-                // System Instructions
-                ui.label(egui::RichText::new("System Instructions:").strong());
-                ui.add_space(5.0);
-                let system_instructions_response = ui.add_sized(
-                    [ui.available_width() - 20.0, 300.0],
-                    egui::TextEdit::multiline(&mut self.system_prompts.system_instructions)
-                        .hint_text("Enter system instructions for the AI...")
-                        .margin(egui::vec2(8.0, 8.0)),
-                );
-
-                ui.add_space(15.0);
+                egui::ScrollArea::vertical()
+                    .id_salt("git_approval_message")
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        ui.label(egui::RichText::new(&draft.message).monospace());
+                    });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .id_salt("git_approval_changes")
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        ui.label(
+                            egui::RichText::new(&draft.changes)
+                                .monospace()
+                                .size(12.0)
+                                .color(theme_colors(ui.ctx()).muted),
+                        );
+                    });
+                ui.separator();
 
-                // Save button
-                if ui.button("Save Instructions").clicked() {
-                    if let Err(e) = self.save_system_prompts() {
-                        tracing::error!("Failed to save system prompts: {}", e);
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(format!("{} Commit", icons::CHECK_CIRCLE))
+                        .clicked()
+                    {
+                        decision = Some(true);
                     }
-                }
+                    if ui.button(format!("{} Don't Commit", icons::X)).clicked() {
+                        decision = Some(false);
+                    }
+                });
 
-                // Show if any changes were detected
-                if system_instructions_response.changed() {
-                    ui.add_space(5.0);
+                if waiting > 0 {
                     ui.label(
-                        egui::RichText::new("* Unsaved changes")
+                        egui::RichText::new(format!("{} more waiting", waiting))
                             .size(12.0)
-                            .color(egui::Color32::from_rgb(220, 100, 60)),
+                            .color(theme_colors(ui.ctx()).muted),
                     );
                 }
-
-                ui.add_space(20.0); // Bottom padding
             });
+
+        if !open {
+            decision = Some(false);
+        }
+        if let Some(approved) = decision {
+            if let Some((request, draft)) = self.git_approvals.pop_front() {
+                tracing::info!(
+                    "🌿 Commit in {} {}",
+                    draft.repository.display(),
+                    if approved { "approved" } else { "declined" }
+                );
+                if let Err(e) = self.deps.event_bus.respond(
+                    &request,
+                    "user",
+                    Ok(serde_json::Value::Bool(approved)),
+                ) {
+                    tracing::warn!("Failed to answer commit approval: {}", e);
+                }
+            }
+        }
     }
 
-    /// Render the events view showing recent MCP plugin events
+    /// Render the confirmation for a rustbot:// link that asked to send its
+    /// prompt
     ///
-    /// Displays a dedicated view for monitoring MCP plugin events including:
-    /// - Plugin starts/stops
-    /// - Errors and health status changes
-    /// - Tool changes and configuration reloads
+    /// The prompt is already in the message box; "Send" sends it, anything
+    /// else leaves it there to edit or send by hand.
     ///
     /// # Arguments
-    /// * `ui` - The egui UI context for rendering
-    pub fn render_events_view(&self, ui: &mut egui::Ui) {
-        ui.add_space(20.0);
-        ui.heading(format!("{} Recent Events", icons::LIST_BULLETS));
-        ui.add_space(10.0);
-
-        ui.label("Monitor MCP extension activity and events:");
-        ui.add_space(15.0);
+    /// * `ctx` - The egui Context the dialog window is shown in
+    pub fn render_link_send_dialog(&mut self, ctx: &egui::Context) {
+        let Some(prompt) = self.link_send_confirm.clone() else {
+            return;
+        };
+        let agent = self
+            .agent_configs
+            .iter()
+            .find(|c| c.id == self.session.agent_id)
+            .map_or_else(|| self.session.agent_id.clone(), |c| c.name.clone());
+
+        let mut open = true;
+        let mut decision = None;
+
+        egui::Window::new(format!("{} Send Message from Link?", icons::WARNING))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "A rustbot:// link wants to send this message to {}:",
+                    agent
+                ));
+                ui.add_space(10.0);
 
-        if let Some(plugins_view) = &self.plugins_view {
-            plugins_view.render_events_only(ui);
-        } else {
-            ui.vertical_centered(|ui| {
-                ui.add_space(50.0);
+                egui::ScrollArea::vertical()
+                    .id_salt("link_send_prompt")
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        ui.label(egui::RichText::new(&prompt).monospace());
+                    });
                 ui.label(
-                    egui::RichText::new("No event data available")
-                        .size(14.0)
-                        .color(egui::Color32::from_rgb(120, 120, 120)),
+                    egui::RichText::new(
+                        "Links can come from any web page. Send only if you expected this one.",
+                    )
+                    .size(12.0)
+                    .color(theme_colors(ui.ctx()).muted),
                 );
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            !self.is_waiting,
+                            egui::Button::new(format!("{} Send", icons::PAPER_PLANE_RIGHT)),
+                        )
+                        .clicked()
+                    {
+                        decision = Some(true);
+                    }
+                    if ui.button(format!("{} Don't Send", icons::X)).clicked() {
+                        decision = Some(false);
+                    }
+                });
             });
+
+        if !open {
+            decision = Some(false);
+        }
+        if let Some(send) = decision {
+            self.link_send_confirm = None;
+            tracing::info!("🔗 Link prompt {}", if send { "sent" } else { "not sent" });
+            if send {
+                self.message_input = prompt;
+                self.send_message(ctx);
+            }
         }
     }
 
-    /// Render the marketplace view
+    /// Render the consent dialog for the oldest file access request
     ///
-    /// Displays the MCP Marketplace browser for discovering and installing MCP servers.
+    /// The answer is sent back to the waiting tool call over the event bus.
+    /// Closing the window counts as "Deny".
     ///
     /// # Arguments
-    /// * `ui` - The egui UI context for rendering
-    /// * `ctx` - The egui Context for global state and repaints
-    pub fn render_marketplace_view(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
-        if let Some(marketplace_view) = &mut self.extensions_marketplace_view {
-            marketplace_view.render(ui, ctx);
-        } else {
-            ui.vertical_centered(|ui| {
-                ui.add_space(50.0);
+    /// * `ctx` - The egui Context the dialog window is shown in
+    pub fn render_fs_consent_dialog(&mut self, ctx: &egui::Context) {
+        let Some((_, request)) = self.fs_consent_requests.front() else {
+            return;
+        };
+        let waiting = self.fs_consent_requests.len() - 1;
+
+        let mut open = true;
+        let mut answer = None;
+
+        egui::Window::new(format!("{} Allow File Access?", icons::FOLDER_OPEN))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
                 ui.label(
-                    egui::RichText::new("Marketplace view not initialized")
-                        .size(14.0)
-                        .color(egui::Color32::from_rgb(120, 120, 120)),
+                    egui::RichText::new(format!(
+                        "{} wants {} access to",
+                        request.plugin,
+                        request.access.label()
+                    ))
+                    .strong(),
+                );
+                ui.label(
+                    egui::RichText::new(fs_consent::display_path(&request.directory)).monospace(),
                 );
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Requested by the {} tool. Covers everything inside this folder.",
+                        request.tool
+                    ))
+                    .size(12.0)
+                    .color(theme_colors(ui.ctx()).muted),
+                );
+                ui.add_space(10.0);
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(format!("{} Allow for this session", icons::CHECK_CIRCLE))
+                        .clicked()
+                    {
+                        answer = Some(fs_consent::ConsentAnswer::Session);
+                    }
+                    if ui
+                        .button(format!("{} Always allow", icons::SHIELD_CHECK))
+                        .clicked()
+                    {
+                        answer = Some(fs_consent::ConsentAnswer::Always);
+                    }
+                    if ui.button(format!("{} Deny", icons::X)).clicked() {
+                        answer = Some(fs_consent::ConsentAnswer::Deny);
+                    }
+                });
+
+                if waiting > 0 {
+                    ui.label(
+                        egui::RichText::new(format!("{} more waiting", waiting))
+                            .size(12.0)
+                            .color(theme_colors(ui.ctx()).muted),
+                    );
+                }
             });
+
+        if !open {
+            answer = Some(fs_consent::ConsentAnswer::Deny);
+        }
+        if let Some(answer) = answer {
+            if let Some((request, _)) = self.fs_consent_requests.pop_front() {
+                let reply = serde_json::to_value(answer).map_err(|e| e.to_string());
+                if let Err(e) = self.deps.event_bus.respond(&request, "user", reply) {
+                    tracing::warn!("Failed to answer file access request: {}", e);
+                }
+            }
         }
     }
 
-    /// Render the extensions view with tabs for Marketplace and Installed
+    /// Render the context inspector window
     ///
-    /// This view provides a unified interface for managing MCP extensions:
-    /// - Marketplace: Browse and discover available MCP servers
-    /// - Installed: View and manage installed extensions (with filtering)
+    /// Shows what the next request in this tab will send: each part of the
+    /// system message, the history after trimming, the tool definitions and
+    /// the text in the input box, with estimated token counts. The preview is
+    /// rebuilt when the tab or the conversation changes.
     ///
     /// # Arguments
-    /// * `ui` - The egui UI context for rendering
-    /// * `ctx` - The egui Context for global state and repaints
-    pub fn render_extensions_view(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
-        // Secondary navigation bar (tabs) - similar to Settings view pattern
-        ui.horizontal(|ui| {
-            if ui
-                .selectable_label(
-                    self.extensions_view == ExtensionsView::Marketplace,
-                    format!("{} Marketplace", icons::STOREFRONT),
-                )
-                .clicked()
-            {
-                self.extensions_view = ExtensionsView::Marketplace;
-            }
-
-            ui.add_space(10.0);
+    /// * `ctx` - The egui Context the window is shown in
+    pub fn render_context_inspector(&mut self, ctx: &egui::Context) {
+        use crate::request_preview::{estimate_tokens, message_tokens, tool_tokens};
 
-            if ui
-                .selectable_label(
-                    self.extensions_view == ExtensionsView::Installed,
-                    format!("{} Installed", icons::PACKAGE),
-                )
-                .clicked()
-            {
-                self.extensions_view = ExtensionsView::Installed;
-            }
+        let stale = self.context_preview.as_ref().is_some_and(|preview| {
+            preview.session_id != self.api_session
+                || (!self.is_waiting && self.context_preview_at != self.messages.len())
         });
-        ui.separator();
+        if stale {
+            self.refresh_context_preview();
+        }
 
-        // Render active subview
-        match self.extensions_view {
-            ExtensionsView::Marketplace => {
-                // Reuse existing marketplace view
-                self.render_marketplace_view(ui, ctx);
-            }
-            ExtensionsView::Installed => {
-                self.render_installed_extensions(ui);
-            }
+        let mut open = true;
+        let mut refresh = false;
+
+        egui::Window::new(format!("{} Context Inspector", icons::MAGNIFYING_GLASS))
+            .default_width(600.0)
+            .default_height(500.0)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let colors = theme_colors(ui.ctx());
+                ui.horizontal(|ui| {
+                    if let Some(preview) = &self.context_preview {
+                        ui.label(format!(
+                            "Next request: {} · {}",
+                            preview.agent_id, preview.model
+                        ));
+                    }
+                    if ui
+                        .button(format!("{} Refresh", icons::ARROWS_CLOCKWISE))
+                        .clicked()
+                    {
+                        refresh = true;
+                    }
+                });
+                if let Some((message, is_error)) = &self.context_preview_message {
+                    let color = if *is_error {
+                        colors.error
+                    } else {
+                        colors.success
+                    };
+                    ui.label(egui::RichText::new(message).size(12.0).color(color));
+                }
+
+                let Some(preview) = &self.context_preview else {
+                    return;
+                };
+                let input_tokens = estimate_tokens(&self.message_input);
+
+                ui.add_space(5.0);
+                egui::Grid::new("context_inspector_totals")
+                    .num_columns(2)
+                    .spacing([20.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.label("System message");
+                        ui.label(format!("{} tokens", preview.system_tokens()));
+                        ui.end_row();
+                        ui.label(format!(
+                            "History ({} of at most {} messages)",
+                            preview.history.len(),
+                            preview.max_history
+                        ));
+                        ui.label(format!("{} tokens", preview.history_tokens()));
+                        ui.end_row();
+                        ui.label(format!("Tools ({})", preview.tools.len()));
+                        ui.label(format!("{} tokens", preview.tools_tokens()));
+                        ui.end_row();
+                        ui.label("Next message (input box)");
+                        ui.label(format!("{} tokens", input_tokens));
+                        ui.end_row();
+                        ui.label(egui::RichText::new("Total").strong());
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "{} tokens",
+                                preview.total_tokens() + input_tokens
+                            ))
+                            .strong(),
+                        );
+                        ui.end_row();
+                    });
+                ui.label(
+                    egui::RichText::new(
+                        "Counted with the GPT-4o tokenizer; other models differ slightly",
+                    )
+                    .size(11.0)
+                    .color(colors.muted),
+                );
+                ui.add_space(5.0);
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::CollapsingHeader::new(format!(
+                        "System message · {} tokens",
+                        preview.system_tokens()
+                    ))
+                    .id_salt("context_system")
+                    .show(ui, |ui| {
+                        for (title, text) in &preview.system {
+                            egui::CollapsingHeader::new(format!(
+                                "{} · {} tokens",
+                                title,
+                                estimate_tokens(text)
+                            ))
+                            .id_salt(("context_system", *title))
+                            .show(ui, |ui| markdown::code_block(ui, None, text));
+                        }
+                    });
+
+                    egui::CollapsingHeader::new(format!(
+                        "History · {} messages · {} tokens",
+                        preview.history.len(),
+                        preview.history_tokens()
+                    ))
+                    .id_salt("context_history")
+                    .show(ui, |ui| {
+                        for (i, message) in preview.history.iter().enumerate() {
+                            egui::CollapsingHeader::new(format!(
+                                "{}. {} · {} tokens",
+                                i + 1,
+                                message.role,
+                                message_tokens(message)
+                            ))
+                            .id_salt(("context_history", i))
+                            .show(ui, |ui| {
+                                markdown::code_block(ui, None, &message.content);
+                                if let Some(calls) = &message.tool_calls {
+                                    let calls =
+                                        serde_json::to_string_pretty(calls).unwrap_or_default();
+                                    markdown::code_block(ui, Some("json"), &calls);
+                                }
+                            });
+                        }
+                    });
+
+                    egui::CollapsingHeader::new(format!(
+                        "Tools · {} · {} tokens",
+                        preview.tools.len(),
+                        preview.tools_tokens()
+                    ))
+                    .id_salt("context_tools")
+                    .show(ui, |ui| {
+                        for tool in &preview.tools {
+                            egui::CollapsingHeader::new(format!(
+                                "{} · {} tokens",
+                                tool.function.name,
+                                tool_tokens(tool)
+                            ))
+                            .id_salt(("context_tool", &tool.function.name))
+                            .show(ui, |ui| {
+                                let json = serde_json::to_string_pretty(tool).unwrap_or_default();
+                                markdown::code_block(ui, Some("json"), &json);
+                            });
+                        }
+                    });
+                });
+            });
+
+        if refresh {
+            self.refresh_context_preview();
+        }
+        if !open {
+            self.context_inspector_open = false;
         }
     }
 
-    /// Render installed extensions view with filtering
+    /// Render the "Export conversation" dialog
     ///
-    /// Shows all installed extensions with the ability to filter by type:
-    /// - All: Show all installed extensions
-    /// - Remote: Show only remote/cloud extensions
-    /// - Local: Show only local extensions
+    /// Lets the user pick a format and destination file; the extension follows
+    /// the selected format.
     ///
-    /// Each extension displays a badge indicating its type (Remote/Local).
-    fn render_installed_extensions(&mut self, ui: &mut egui::Ui) {
-        // Check if we're showing a configuration dialog
-        if let Some(ext_id) = &self.configuring_extension_id.clone() {
-            self.render_extension_config_dialog(ui, ext_id);
-            return; // Show only the dialog when configuring
-        }
+    /// # Arguments
+    /// * `ctx` - The egui Context the dialog window is shown in
+    fn render_conversation_export_dialog(&mut self, ctx: &egui::Context) {
+        use crate::conversation_export::ConversationFormat;
+
+        let mut open = true;
+        let mut export_clicked = false;
+
+        egui::Window::new("Export Conversation")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Format:");
+                    for format in [
+                        ConversationFormat::Markdown,
+                        ConversationFormat::Html,
+                        ConversationFormat::Json,
+                    ] {
+                        if ui
+                            .radio_value(
+                                &mut self.conversation_export_format,
+                                format,
+                                format.label(),
+                            )
+                            .changed()
+                        {
+                            self.conversation_export_path =
+                                std::path::Path::new(&self.conversation_export_path)
+                                    .with_extension(format.extension())
+                                    .display()
+                                    .to_string();
+                        }
+                    }
+                });
 
-        // Show uninstall confirmation dialog if present
-        if let Some((ext_id, ext_name)) = &self.uninstall_confirmation.clone() {
-            self.render_uninstall_confirmation_dialog(ui, ext_id, ext_name);
-            return; // Show only the dialog when confirming
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    ui.label("File:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.conversation_export_path)
+                            .desired_width(400.0),
+                    );
+                });
+
+                ui.add_space(10.0);
+                if ui
+                    .add_enabled(
+                        self.conversation_export_rx.is_none(),
+                        egui::Button::new(format!("{} Save", icons::FLOPPY_DISK)),
+                    )
+                    .clicked()
+                {
+                    export_clicked = true;
+                }
+
+                if let Some((message, is_error)) = &self.conversation_export_message {
+                    let color = if *is_error {
+                        theme_colors(ui.ctx()).error
+                    } else {
+                        theme_colors(ui.ctx()).success
+                    };
+                    ui.label(egui::RichText::new(message).size(12.0).color(color));
+                }
+            });
+
+        if export_clicked {
+            let path = std::path::PathBuf::from(self.conversation_export_path.trim());
+            self.export_conversation_to_file(path, self.conversation_export_format);
         }
-        egui::ScrollArea::vertical()
-            .auto_shrink([false; 2])
-            .show(ui, |ui| {
-                ui.add_space(20.0);
-                ui.heading("Installed Extensions");
+
+        self.conversation_export_open = open;
+    }
+
+    /// Render the "Share conversation" dialog
+    ///
+    /// Saves the conversation as a self-contained HTML page or uploads it as
+    /// a gist, redacted first unless the user turns that off.
+    ///
+    /// # Arguments
+    /// * `ctx` - The egui Context the dialog window is shown in
+    fn render_share_dialog(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let mut save_clicked = false;
+        let mut upload_clicked = false;
+
+        egui::Window::new("Share Conversation")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.checkbox(
+                    &mut self.share_redact,
+                    "Redact secrets, email addresses and home folder paths",
+                );
+
                 ui.add_space(10.0);
+                ui.label(egui::RichText::new("HTML page").strong());
+                ui.horizontal(|ui| {
+                    ui.label("File:");
+                    ui.add(egui::TextEdit::singleline(&mut self.share_path).desired_width(360.0));
+                    if ui
+                        .add_enabled(
+                            self.share_save_rx.is_none(),
+                            egui::Button::new(format!("{} Save", icons::FLOPPY_DISK)),
+                        )
+                        .clicked()
+                    {
+                        save_clicked = true;
+                    }
+                });
 
-                // Filter dropdown
+                ui.add_space(10.0);
+                ui.label(egui::RichText::new("GitHub gist").strong());
+                if !self.share_has_token {
+                    ui.horizontal(|ui| {
+                        ui.label("Token:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.share_token_input)
+                                .password(true)
+                                .hint_text("GitHub token with the gist scope")
+                                .desired_width(300.0),
+                        );
+                    });
+                }
                 ui.horizontal(|ui| {
-                    ui.label("Filter:");
-                    egui::ComboBox::from_id_source("install_type_filter")
-                        .selected_text(self.installed_extensions_filter.label())
-                        .show_ui(ui, |ui| {
-                            use crate::ui::InstallTypeFilter;
-                            ui.selectable_value(
-                                &mut self.installed_extensions_filter,
-                                InstallTypeFilter::All,
-                                "All",
-                            );
-                            ui.selectable_value(
-                                &mut self.installed_extensions_filter,
-                                InstallTypeFilter::Remote,
-                                "Remote",
-                            );
-                            ui.selectable_value(
-                                &mut self.installed_extensions_filter,
-                                InstallTypeFilter::Local,
-                                "Local",
-                            );
-                        });
+                    ui.checkbox(&mut self.share_public, "Public").on_hover_text(
+                        "Listed on your GitHub profile; secret gists only open with the link",
+                    );
+                    let uploading = self.share_rx.is_some();
+                    if ui
+                        .add_enabled(
+                            !uploading,
+                            egui::Button::new(format!("{} Upload", icons::UPLOAD_SIMPLE)),
+                        )
+                        .clicked()
+                    {
+                        upload_clicked = true;
+                    }
+                    if uploading {
+                        ui.spinner();
+                    }
                 });
+                if let Some(url) = &self.share_url {
+                    ui.horizontal(|ui| {
+                        ui.hyperlink(url);
+                        if ui.small_button(format!("{} Copy", icons::COPY)).clicked() {
+                            ui.ctx().copy_text(url.clone());
+                        }
+                    });
+                }
 
-                ui.add_space(15.0);
+                if let Some((message, is_error)) = &self.share_message {
+                    let color = if *is_error {
+                        theme_colors(ui.ctx()).error
+                    } else {
+                        theme_colors(ui.ctx()).success
+                    };
+                    ui.label(egui::RichText::new(message).size(12.0).color(color));
+                }
+            });
 
-                // Load extension registry
-                use crate::mcp::extensions::ExtensionRegistry;
-                use std::path::PathBuf;
+        if save_clicked {
+            self.save_shared_page();
+        }
+        if upload_clicked {
+            self.start_gist_upload(ctx);
+        }
 
-                let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-                let registry_path = home_dir
-                    .join(".rustbot")
-                    .join("extensions")
-                    .join("registry.json");
+        self.share_open = open;
+    }
 
-                match ExtensionRegistry::load(&registry_path) {
-                    Ok(registry) => {
-                        let all_extensions = registry.list();
+    /// Render the settings view with navigation tabs
+    ///
+    /// Provides a tabbed interface for:
+    /// - AI Settings (model selection)
+    /// - System Prompts (instructions and personality)
+    /// - Agents (agent configuration)
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    pub fn render_settings_view(&mut self, ui: &mut egui::Ui) {
+        // Secondary navigation bar under header
+        ui.horizontal(|ui| {
+            let system_prompts_button = ui.add(egui::SelectableLabel::new(
+                self.settings_view == SettingsView::SystemPrompts,
+                "System Prompts",
+            ));
+            if system_prompts_button.clicked() {
+                self.settings_view = SettingsView::SystemPrompts;
+            }
 
-                        // Apply filter
-                        let filtered_extensions: Vec<_> = all_extensions
-                            .iter()
-                            .filter(|ext| {
-                                use crate::mcp::extensions::InstallationType;
-                                use crate::ui::InstallTypeFilter;
+            ui.add_space(10.0);
 
-                                match self.installed_extensions_filter {
-                                    InstallTypeFilter::All => true,
-                                    InstallTypeFilter::Remote => {
-                                        matches!(ext.install_type, InstallationType::Remote)
-                                    }
-                                    InstallTypeFilter::Local => {
-                                        matches!(ext.install_type, InstallationType::Local)
-                                    }
-                                }
-                            })
-                            .collect();
+            let agents_button = ui.add(egui::SelectableLabel::new(
+                self.settings_view == SettingsView::Agents,
+                "Agents",
+            ));
+            if agents_button.clicked() {
+                self.settings_view = SettingsView::Agents;
+            }
 
-                        if filtered_extensions.is_empty() {
-                            ui.vertical_centered(|ui| {
-                                ui.add_space(40.0);
-                                if all_extensions.is_empty() {
-                                    ui.label(
-                                        egui::RichText::new(format!(
-                                            "{} No extensions installed",
-                                            icons::PACKAGE
-                                        ))
-                                        .size(16.0)
-                                        .color(egui::Color32::from_rgb(120, 120, 120)),
-                                    );
-                                    ui.add_space(10.0);
-                                    ui.label(
-                                        egui::RichText::new(
-                                            "Visit the Marketplace to discover and install MCP servers",
-                                        )
-                                        .size(12.0)
-                                        .color(egui::Color32::from_rgb(150, 150, 150)),
-                                    );
-                                } else {
-                                    ui.label(
-                                        egui::RichText::new(format!(
-                                            "{} No {} extensions installed",
-                                            icons::PACKAGE,
-                                            self.installed_extensions_filter.label().to_lowercase()
-                                        ))
-                                        .size(16.0)
-                                        .color(egui::Color32::from_rgb(120, 120, 120)),
-                                    );
-                                    ui.add_space(10.0);
-                                    ui.label(
-                                        egui::RichText::new("Try selecting a different filter")
-                                            .size(12.0)
-                                            .color(egui::Color32::from_rgb(150, 150, 150)),
-                                    );
-                                }
-                            });
-                        } else {
-                            ui.label(
-                                egui::RichText::new(format!(
-                                    "{} extension(s) shown ({} total installed)",
-                                    filtered_extensions.len(),
-                                    all_extensions.len()
-                                ))
-                                .size(12.0)
-                                .color(egui::Color32::from_rgb(120, 120, 120)),
-                            );
-                            ui.add_space(10.0);
+            ui.add_space(10.0);
 
-                            // Display each filtered extension
-                            for ext in filtered_extensions {
-                                ui.group(|ui| {
-                                    ui.set_min_width(ui.available_width());
-                                    ui.add_space(5.0);
+            let projects_button =
+                ui.selectable_label(self.settings_view == SettingsView::Projects, "Projects");
+            if projects_button.clicked() {
+                self.settings_view = SettingsView::Projects;
+            }
 
-                                    // Extension header with type badge
-                                    ui.horizontal(|ui| {
-                                        // Type badge (Remote or Local)
-                                        use crate::mcp::extensions::InstallationType;
-                                        let (badge_icon, badge_text, badge_color) = match ext.install_type {
-                                            InstallationType::Remote => (
-                                                icons::CLOUD,
-                                                "Remote",
-                                                egui::Color32::from_rgb(80, 150, 220),
-                                            ),
-                                            InstallationType::Local => (
-                                                icons::DESKTOP,
-                                                "Local",
-                                                egui::Color32::from_rgb(100, 180, 100),
-                                            ),
-                                        };
+            ui.add_space(10.0);
 
-                                        // Draw badge with background
-                                        let badge_label = format!("{} {}", badge_icon, badge_text);
-                                        ui.label(
-                                            egui::RichText::new(badge_label)
-                                                .size(11.0)
-                                                .color(badge_color)
-                                                .strong(),
-                                        );
+            let preferences_button = ui.add(egui::SelectableLabel::new(
+                self.settings_view == SettingsView::Preferences,
+                "Preferences",
+            ));
+            if preferences_button.clicked() {
+                self.settings_view = SettingsView::Preferences;
+            }
 
-                                        ui.add_space(10.0);
+            ui.add_space(10.0);
 
-                                        ui.label(
-                                            egui::RichText::new(&ext.name)
-                                                .size(16.0)
-                                                .strong()
-                                                .color(egui::Color32::from_rgb(60, 120, 180)),
-                                        );
-                                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                                            ui.label(
-                                                egui::RichText::new(format!(
-                                                    "{} v{}",
-                                                    icons::TAG,
-                                                    ext.metadata.version
-                                                ))
-                                                .size(11.0)
-                                                .color(egui::Color32::from_rgb(120, 120, 120)),
-                                            );
-                                        });
-                                    });
+            let backups_button =
+                ui.selectable_label(self.settings_view == SettingsView::Backups, "Backups");
+            if backups_button.clicked() {
+                self.settings_view = SettingsView::Backups;
+            }
 
-                                    ui.add_space(5.0);
+            ui.add_space(10.0);
+
+            let scripts_button =
+                ui.selectable_label(self.settings_view == SettingsView::Scripts, "Scripts");
+            if scripts_button.clicked() {
+                self.settings_view = SettingsView::Scripts;
+            }
+
+            ui.add_space(10.0);
+
+            let wasm_tools_button =
+                ui.selectable_label(self.settings_view == SettingsView::WasmTools, "WASM Tools");
+            if wasm_tools_button.clicked() {
+                self.settings_view = SettingsView::WasmTools;
+            }
+
+            ui.add_space(10.0);
+
+            let native_plugins_button = ui.selectable_label(
+                self.settings_view == SettingsView::NativePlugins,
+                "Native Plugins",
+            );
+            if native_plugins_button.clicked() {
+                self.settings_view = SettingsView::NativePlugins;
+            }
+
+            ui.add_space(10.0);
+
+            let calendar_button =
+                ui.selectable_label(self.settings_view == SettingsView::Calendar, "Calendar");
+            if calendar_button.clicked() {
+                self.settings_view = SettingsView::Calendar;
+            }
+
+            ui.add_space(10.0);
+
+            let prompts_button =
+                ui.selectable_label(self.settings_view == SettingsView::Prompts, "Prompts");
+            if prompts_button.clicked() {
+                self.settings_view = SettingsView::Prompts;
+            }
+
+            ui.add_space(10.0);
+
+            let prompt_tests_button =
+                ui.selectable_label(self.settings_view == SettingsView::PromptTests, "A/B Tests");
+            if prompt_tests_button.clicked() {
+                self.settings_view = SettingsView::PromptTests;
+            }
+
+            ui.add_space(10.0);
+
+            let audit_button =
+                ui.selectable_label(self.settings_view == SettingsView::Audit, "Audit Log");
+            if audit_button.clicked() {
+                self.settings_view = SettingsView::Audit;
+                self.refresh_audit_log();
+            }
+        });
+        ui.separator();
+
+        // Render content based on selected settings view
+        match self.settings_view {
+            SettingsView::SystemPrompts => self.render_system_prompts(ui),
+            SettingsView::Agents => self.render_agents_view(ui),
+            SettingsView::Projects => self.render_projects_view(ui),
+            SettingsView::Preferences => self.render_preferences_view(ui),
+            SettingsView::Backups => self.render_backups_view(ui),
+            SettingsView::Scripts => self.render_scripts_view(ui),
+            SettingsView::WasmTools => self.render_wasm_tools_view(ui),
+            SettingsView::NativePlugins => self.render_native_plugins_view(ui),
+            SettingsView::Calendar => self.render_calendar_view(ui),
+            SettingsView::Prompts => self.render_prompts_view(ui),
+            SettingsView::PromptTests => self.render_prompt_tests_view(ui),
+            SettingsView::Audit => self.render_audit_view(ui),
+        }
+    }
+
+    /// Render the system prompts configuration view
+    ///
+    /// Allows editing of:
+    /// - System Instructions: Core behavioral instructions for the AI (shared across all agents)
+    ///
+    /// Note: Agent personality is now configured per-agent in the Agents settings.
+    ///
+    /// Changes can be saved and are backed up automatically.
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    pub fn render_system_prompts(&mut self, ui: &mut egui::Ui) {
+        // Use scroll area for system prompts
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                ui.add_space(20.0);
+                ui.heading("System Instructions");
+                ui.add_space(10.0);
+
+                ui.label("These instructions are sent with every chat session (shared across all agents):");
+                ui.add_space(5.0);
+                ui.label(egui::RichText::new("Note: Agent personality is configured per-agent in the Agents tab.")
+                    .size(12.0)
+                    .color(theme_colors(ui.ctx()).muted));
+                ui.add_space(10.0);
+
+                // Instruction profiles: switch, delete, create
+                let mut switch_to = None;
+                let mut delete = false;
+                let mut create = false;
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new("Profile:").strong());
+                    egui::ComboBox::from_id_salt("settings_instruction_profile")
+                        .selected_text(&self.instruction_profile)
+                        .show_ui(ui, |ui| {
+                            for name in &self.instruction_profiles {
+                                if ui
+                                    .selectable_label(*name == self.instruction_profile, name)
+                                    .clicked()
+                                {
+                                    switch_to = Some(name.clone());
+                                }
+                            }
+                        })
+                        .response
+                        .on_hover_text("Switching drops unsaved changes");
+                    if self.instruction_profile != instruction_profiles::DEFAULT_PROFILE
+                        && ui.button(format!("{} Delete", icons::TRASH)).clicked()
+                    {
+                        delete = true;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("New profile:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.new_profile_name)
+                            .hint_text("e.g. Coding")
+                            .desired_width(150.0),
+                    );
+                    let starter = |choice: Option<usize>| {
+                        choice.map_or("Copy of this profile", |i| {
+                            instruction_profiles::STARTER_PROFILES[i].0
+                        })
+                    };
+                    egui::ComboBox::from_id_salt("new_profile_starter")
+                        .selected_text(starter(self.new_profile_starter))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.new_profile_starter, None, starter(None));
+                            for i in 0..instruction_profiles::STARTER_PROFILES.len() {
+                                ui.selectable_value(
+                                    &mut self.new_profile_starter,
+                                    Some(i),
+                                    format!("{} starter", starter(Some(i))),
+                                );
+                            }
+                        });
+                    if ui.button(format!("{} Create", icons::PLUS)).clicked() {
+                        create = true;
+                    }
+                });
+                if let Some((message, is_error)) = &self.profile_message {
+                    let color = if *is_error {
+                        theme_colors(ui.ctx()).error
+                    } else {
+                        theme_colors(ui.ctx()).success
+                    };
+                    ui.label(egui::RichText::new(message).size(12.0).color(color));
+                }
+                if let Some(name) = switch_to {
+                    self.switch_instruction_profile(&name);
+                }
+                if delete {
+                    self.delete_instruction_profile();
+                }
+                if create {
+                    self.create_instruction_profile();
+                }
+                ui.add_space(10.0);
+
+                // System Instructions
+                ui.label(egui::RichText::new("System Instructions:").strong());
+                ui.add_space(5.0);
+                let system_instructions_response = ui.add_sized(
+                    [ui.available_width() - 20.0, 300.0],
+                    egui::TextEdit::multiline(&mut self.system_prompts.system_instructions)
+                        .hint_text("Enter system instructions for the AI...")
+                        .margin(egui::vec2(8.0, 8.0)),
+                );
+
+                ui.add_space(15.0);
+
+                // Save button
+                if ui.button("Save Instructions").clicked() {
+                    self.profile_message = Some(match self.save_system_prompts() {
+                        Ok(()) => (
+                            format!("Saved the {} profile", self.instruction_profile),
+                            false,
+                        ),
+                        Err(e) => {
+                            tracing::error!("Failed to save system prompts: {}", e);
+                            (format!("Failed to save: {}", e), true)
+                        }
+                    });
+                }
+
+                // Show if any changes were detected
+                if system_instructions_response.changed() {
+                    ui.add_space(5.0);
+                    ui.label(
+                        egui::RichText::new("* Unsaved changes")
+                            .size(12.0)
+                            .color(theme_colors(ui.ctx()).warning),
+                    );
+                }
+
+                ui.add_space(20.0); // Bottom padding
+            });
+    }
+
+    /// Render the events view showing recent MCP plugin events
+    ///
+    /// Displays a dedicated view for monitoring MCP plugin events including:
+    /// - Plugin starts/stops
+    /// - Errors and health status changes
+    /// - Tool changes and configuration reloads
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    pub fn render_events_view(&mut self, ui: &mut egui::Ui) {
+        ui.add_space(20.0);
+        ui.heading(format!("{} Recent Events", icons::LIST_BULLETS));
+        ui.add_space(10.0);
+
+        self.render_diagnostics(ui);
+        ui.add_space(10.0);
+
+        self.render_event_export_controls(ui);
+        ui.add_space(10.0);
+        ui.separator();
+        ui.add_space(10.0);
+
+        ui.label(egui::RichText::new("Event Sequence").strong().size(14.0));
+        ui.add_space(5.0);
+        self.render_event_sequence(ui, 20, true);
+        ui.add_space(10.0);
+        ui.separator();
+        ui.add_space(10.0);
+
+        ui.label("Monitor MCP extension activity and events:");
+        ui.add_space(15.0);
+
+        if let Some(plugins_view) = &self.plugins_view {
+            plugins_view.render_events_only(ui);
+        } else {
+            ui.vertical_centered(|ui| {
+                ui.add_space(50.0);
+                ui.label(
+                    egui::RichText::new("No event data available")
+                        .size(14.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                );
+            });
+        }
+    }
+
+    /// Render the diagnostics panel: memory, event bus, UI frame time and caches
+    ///
+    /// "Copy" puts the figures on the clipboard for performance bug reports.
+    /// The API figures are the last snapshot taken while the API wasn't busy.
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    fn render_diagnostics(&mut self, ui: &mut egui::Ui) {
+        if let Ok(api) = self.api.try_lock() {
+            self.api_metrics = Some(api.metrics());
+        }
+        // Refresh while the view is open
+        ui.ctx()
+            .request_repaint_after(std::time::Duration::from_secs(1));
+
+        // (line, needs attention)
+        let mut lines: Vec<(String, bool)> = Vec::new();
+        if let Some(metrics) = &self.api_metrics {
+            let memory = match metrics.rss_bytes {
+                Some(bytes) => format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0)),
+                None => "unknown".to_string(),
+            };
+            lines.push((format!("Memory (RSS): {}", memory), false));
+
+            // Dropped events mean a subscriber couldn't keep up
+            let bus = &metrics.event_bus;
+            lines.push((
+                format!(
+                    "Event bus: {} queued of {} · {} subscribers · {} published · {} dropped \
+                     ({} lag incidents)",
+                    bus.queued,
+                    bus.capacity,
+                    bus.subscribers,
+                    bus.published,
+                    bus.dropped,
+                    bus.lag_incidents
+                ),
+                bus.dropped > 0,
+            ));
+            lines.push((
+                format!(
+                    "Conversations: {} sessions · {} history messages · {} tools ({} calls)",
+                    metrics.sessions, metrics.history_messages, metrics.tools, metrics.tool_calls
+                ),
+                false,
+            ));
+        }
+        if let Some((mean, slowest)) = self.frame_times.summary() {
+            let ms = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+            lines.push((
+                format!(
+                    "UI frame: {:.1} ms average · {:.1} ms slowest (recent frames)",
+                    ms(mean),
+                    ms(slowest)
+                ),
+                slowest > crate::ui::types::STREAM_FRAME_INTERVAL,
+            ));
+        }
+        let mut caches = format!(
+            "Caches: {} rendered messages",
+            self.markdown_cache.cached_messages()
+        );
+        if let Ok(mermaid) = self.mermaid_renderer.try_lock() {
+            caches.push_str(&format!(
+                " · {} diagrams ({:.1} KB)",
+                mermaid.cache_size(),
+                mermaid.cache_memory_bytes() as f64 / 1024.0
+            ));
+        }
+        if let Ok(math) = self.math_renderer.try_lock() {
+            caches.push_str(&format!(
+                " · {} formulas ({:.1} KB)",
+                math.cache_size(),
+                math.cache_memory_bytes() as f64 / 1024.0
+            ));
+        }
+        lines.push((caches, false));
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Diagnostics").strong().size(14.0));
+            if ui
+                .small_button(format!("{} Copy", icons::COPY))
+                .on_hover_text("Copy these figures for a bug report")
+                .clicked()
+            {
+                let mut report = vec![crate::version::version_string()];
+                report.extend(lines.iter().map(|(line, _)| line.clone()));
+                ui.ctx().copy_text(report.join("\n"));
+            }
+        });
+        let colors = theme_colors(ui.ctx());
+        for (line, attention) in &lines {
+            let color = if *attention {
+                colors.warning
+            } else {
+                colors.muted
+            };
+            ui.label(egui::RichText::new(line).size(12.0).color(color));
+        }
+    }
+
+    /// Render recent bus events as sequence diagrams, one per turn
+    ///
+    /// Events are grouped by correlation ID, newest turn first, with a lane per
+    /// participant. The full version (Events view) can also copy a turn as
+    /// Mermaid source or render it through the mermaid pipeline.
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    /// * `max_turns` - Number of turns shown
+    /// * `full` - Show the Mermaid buttons and rendered diagram
+    pub fn render_event_sequence(&mut self, ui: &mut egui::Ui, max_turns: usize, full: bool) {
+        // Diagram from "Render diagram", once mermaid.ink answered
+        if let Some(rx) = &mut self.event_sequence_render {
+            match rx.try_recv() {
+                Err(tokio::sync::oneshot::error::TryRecvError::Empty) => {}
+                result => {
+                    if let (Ok(Some(rendered)), Some((_, diagram))) =
+                        (result, &mut self.event_sequence_diagram)
+                    {
+                        *diagram = rendered;
+                    }
+                    self.event_sequence_render = None;
+                }
+            }
+        }
+
+        if self.event_history.is_empty() {
+            ui.label(
+                egui::RichText::new("No events yet")
+                    .size(11.0)
+                    .color(theme_colors(ui.ctx()).muted),
+            );
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Zoom").size(11.0));
+            if ui.small_button("−").clicked() {
+                self.event_sequence_zoom =
+                    (self.event_sequence_zoom / 1.25).max(sequence_view::MIN_ZOOM);
+            }
+            if ui.small_button("+").clicked() {
+                self.event_sequence_zoom =
+                    (self.event_sequence_zoom * 1.25).min(sequence_view::MAX_ZOOM);
+            }
+            ui.label(
+                egui::RichText::new(format!("{:.0}%", self.event_sequence_zoom * 100.0))
+                    .size(11.0)
+                    .color(theme_colors(ui.ctx()).muted),
+            );
+        })
+        .response
+        .on_hover_text("Ctrl+scroll over a diagram also zooms");
+
+        let groups = event_sequence::group_by_correlation(self.event_history.iter(), |event| {
+            event.correlation_id.as_deref()
+        });
+        let newest = groups.len();
+        let mut render = None;
+        let mut markdown_action = None;
+
+        for (i, (turn, events)) in groups.iter().enumerate().rev().take(max_turns) {
+            let title = match turn {
+                Some(id) => format!(
+                    "{} · {} · {} events",
+                    events[0].timestamp.format("%H:%M:%S"),
+                    id,
+                    events.len()
+                ),
+                None => format!("Other events · {}", events.len()),
+            };
+
+            egui::CollapsingHeader::new(egui::RichText::new(title).size(11.0))
+                .id_salt(("event_sequence", turn, full))
+                .default_open(i + 1 == newest)
+                .show(ui, |ui| {
+                    egui::ScrollArea::horizontal()
+                        .id_salt(("event_sequence_lanes", turn, full))
+                        .show(ui, |ui| {
+                            sequence_view::show(ui, events, &mut self.event_sequence_zoom);
+                        });
+
+                    if !full {
+                        return;
+                    }
+                    let mermaid = event_sequence::to_mermaid(&sequence_view::steps(events));
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button(format!("{} Copy as Mermaid", icons::COPY))
+                            .clicked()
+                        {
+                            ui.ctx().copy_text(mermaid.clone());
+                        }
+                        if ui
+                            .button(format!("{} Render diagram", icons::IMAGE))
+                            .clicked()
+                        {
+                            render = Some((turn.clone(), mermaid.clone()));
+                        }
+                    });
+                    if let Some((diagram_turn, diagram)) = &self.event_sequence_diagram {
+                        if diagram_turn == turn {
+                            if let Some(action) = markdown::show(
+                                ui,
+                                &mut self.markdown_cache,
+                                egui::Id::new("event_sequence_diagram"),
+                                diagram,
+                            ) {
+                                markdown_action = Some(action);
+                            }
+                        }
+                    }
+                });
+        }
+
+        // Rendering goes through mermaid.ink in the background; the code block
+        // shows until it's done and stays if rendering fails
+        if let Some((turn, mermaid)) = render {
+            let diagram = format!("```mermaid\n{}```\n", mermaid);
+            let theme = markdown::diagram_theme(&ui.style().visuals);
+            self.event_sequence_render = Some(self.render_diagrams(ui.ctx(), &diagram, theme));
+            self.event_sequence_diagram = Some((turn, diagram));
+        }
+        if let Some(action) = markdown_action {
+            self.handle_markdown_action(action);
+        }
+    }
+
+    /// Render the "Export events" controls for the persisted event log
+    ///
+    /// Exports land in ~/.rustbot/exports/ with a timestamped file name.
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    fn render_event_export_controls(&mut self, ui: &mut egui::Ui) {
+        use crate::event_log::ExportFormat;
+        use crate::ui::EventExportRange;
+
+        ui.horizontal(|ui| {
+            ui.label("Export events:");
+
+            egui::ComboBox::from_id_salt("event_export_range")
+                .selected_text(self.event_export_range.label())
+                .show_ui(ui, |ui| {
+                    for range in [
+                        EventExportRange::LastHour,
+                        EventExportRange::Last24Hours,
+                        EventExportRange::Last7Days,
+                        EventExportRange::All,
+                    ] {
+                        ui.selectable_value(&mut self.event_export_range, range, range.label());
+                    }
+                });
+
+            ui.radio_value(&mut self.event_export_format, ExportFormat::Jsonl, "JSONL");
+            ui.radio_value(&mut self.event_export_format, ExportFormat::Csv, "CSV");
+
+            if ui
+                .button(format!("{} Export", icons::DOWNLOAD_SIMPLE))
+                .clicked()
+            {
+                let output = dirs::home_dir()
+                    .unwrap_or_default()
+                    .join(".rustbot")
+                    .join("exports")
+                    .join(format!(
+                        "events-{}.{}",
+                        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+                        self.event_export_format.extension()
+                    ));
+
+                self.event_export_message = Some(
+                    match self.event_log.export(
+                        self.event_export_range.start(),
+                        None,
+                        self.event_export_format,
+                        &output,
+                    ) {
+                        Ok(count) => (
+                            format!("Exported {} events to {}", count, output.display()),
+                            false,
+                        ),
+                        Err(e) => (format!("Export failed: {}", e), true),
+                    },
+                );
+            }
+        });
+
+        if let Some((message, is_error)) = &self.event_export_message {
+            let color = if *is_error {
+                theme_colors(ui.ctx()).error
+            } else {
+                theme_colors(ui.ctx()).success
+            };
+            ui.label(egui::RichText::new(message).size(12.0).color(color));
+        }
+    }
+
+    /// Render the conversation history browser
+    ///
+    /// Lists saved sessions (newest first), filters them with full-text search
+    /// and restores the selected one into the chat view.
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    pub fn render_history_view(&mut self, ui: &mut egui::Ui) {
+        ui.add_space(20.0);
+        ui.heading(format!(
+            "{} Conversation History",
+            icons::CLOCK_COUNTER_CLOCKWISE
+        ));
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            ui.label(icons::MAGNIFYING_GLASS);
+            let search = ui.add(
+                egui::TextEdit::singleline(&mut self.history_query)
+                    .hint_text("Search messages...")
+                    .desired_width(300.0),
+            );
+            if search.changed() {
+                self.refresh_history();
+            }
+        });
+        if let Some(project) = self.current_project() {
+            ui.label(
+                egui::RichText::new(format!(
+                    "{} Showing conversations in {}. Choose \"No project\" above the chat to \
+                     see all of them.",
+                    icons::FOLDER,
+                    project.name
+                ))
+                .size(12.0)
+                .color(theme_colors(ui.ctx()).muted),
+            );
+        }
+        ui.add_space(5.0);
+
+        // Import from other assistants' data exports
+        ui.horizontal(|ui| {
+            ui.label("Import ChatGPT/Claude export:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.history_import_path)
+                    .hint_text("path/to/conversations.json")
+                    .desired_width(300.0),
+            );
+            if ui
+                .add_enabled(
+                    !self.history_import_path.trim().is_empty() && self.history_import_rx.is_none(),
+                    egui::Button::new(format!("{} Import", icons::UPLOAD_SIMPLE)),
+                )
+                .clicked()
+            {
+                let path = std::path::PathBuf::from(self.history_import_path.trim());
+                self.history_import_message = None;
+                self.import_conversations(path);
+            }
+        });
+        if let Some((message, is_error)) = &self.history_import_message {
+            let color = if *is_error {
+                theme_colors(ui.ctx()).error
+            } else {
+                theme_colors(ui.ctx()).success
+            };
+            ui.label(egui::RichText::new(message).size(12.0).color(color));
+        }
+
+        // Ratings given to answers, for evaluating agents
+        ui.horizontal(|ui| {
+            ui.label("Answer ratings:");
+            if ui
+                .add_enabled(
+                    self.feedback_export_rx.is_none(),
+                    egui::Button::new(format!("{} Export Ratings", icons::DOWNLOAD_SIMPLE)),
+                )
+                .on_hover_text("Write every rated answer to a JSONL file")
+                .clicked()
+            {
+                self.feedback_export_message = None;
+                self.export_feedback();
+            }
+        });
+        if let Some((message, is_error)) = &self.feedback_export_message {
+            let color = if *is_error {
+                theme_colors(ui.ctx()).error
+            } else {
+                theme_colors(ui.ctx()).success
+            };
+            ui.label(egui::RichText::new(message).size(12.0).color(color));
+        }
+        ui.add_space(10.0);
+        ui.separator();
+        ui.add_space(10.0);
+
+        if self.history_results.is_empty() {
+            ui.vertical_centered(|ui| {
+                ui.add_space(50.0);
+                let message = if self.history_query.trim().is_empty() {
+                    "No saved conversations yet"
+                } else {
+                    "No conversations match your search"
+                };
+                ui.label(
+                    egui::RichText::new(message)
+                        .size(14.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                );
+            });
+            return;
+        }
+
+        let mut restore_id = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for summary in &self.history_results {
+                let is_current = summary.id == self.session.id;
+                ui.horizontal(|ui| {
+                    ui.vertical(|ui| {
+                        let title = if summary.title.is_empty() {
+                            "(untitled)"
+                        } else {
+                            summary.title.as_str()
+                        };
+                        ui.label(egui::RichText::new(title).strong());
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "{} · {} messages · {}",
+                                summary
+                                    .updated_at
+                                    .with_timezone(&chrono::Local)
+                                    .format("%Y-%m-%d %H:%M"),
+                                summary.message_count,
+                                summary.agent_id
+                            ))
+                            .size(12.0)
+                            .color(theme_colors(ui.ctx()).muted),
+                        );
+                    });
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if is_current {
+                            ui.label("Current");
+                        } else if ui
+                            .add_enabled(
+                                !self.is_waiting,
+                                egui::Button::new(format!(
+                                    "{} Restore",
+                                    icons::ARROW_COUNTER_CLOCKWISE
+                                )),
+                            )
+                            .clicked()
+                        {
+                            restore_id = Some(summary.id.clone());
+                        }
+                    });
+                });
+                ui.separator();
+            }
+        });
+
+        if let Some(id) = restore_id {
+            self.restore_session(&id);
+        }
+    }
+
+    /// Render the usage dashboard
+    ///
+    /// Stacked bars of tokens or cost per day or week, split by model or
+    /// agent, followed by the totals for the whole range.
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    pub fn render_usage_view(&mut self, ui: &mut egui::Ui) {
+        ui.add_space(20.0);
+        ui.heading(format!("{} Usage", icons::CHART_BAR));
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.usage_activity, false, "Tokens");
+            ui.add_space(10.0);
+            ui.selectable_value(
+                &mut self.usage_activity,
+                true,
+                format!("{} Activity", icons::CHART_LINE),
+            );
+        });
+        ui.separator();
+
+        let mut refresh = false;
+        ui.horizontal(|ui| {
+            refresh |= ui
+                .radio_value(&mut self.usage_period, usage::UsagePeriod::Day, "Daily")
+                .changed();
+            refresh |= ui
+                .radio_value(&mut self.usage_period, usage::UsagePeriod::Week, "Weekly")
+                .changed();
+            ui.separator();
+            if !self.usage_activity {
+                ui.radio_value(&mut self.usage_by_model, true, "By model");
+                ui.radio_value(&mut self.usage_by_model, false, "By agent");
+                ui.separator();
+                ui.radio_value(
+                    &mut self.usage_metric,
+                    UsageMetric::InputTokens,
+                    "Input tokens",
+                );
+                ui.radio_value(
+                    &mut self.usage_metric,
+                    UsageMetric::OutputTokens,
+                    "Output tokens",
+                );
+                ui.radio_value(&mut self.usage_metric, UsageMetric::Cost, "Cost");
+                ui.separator();
+            }
+            if ui
+                .button(format!("{} Refresh", icons::ARROWS_CLOCKWISE))
+                .clicked()
+            {
+                refresh = true;
+            }
+        });
+        if refresh {
+            self.refresh_usage();
+        }
+        ui.add_space(10.0);
+
+        // One bar per period, including empty ones
+        let step = match self.usage_period {
+            usage::UsagePeriod::Day => chrono::Duration::days(1),
+            usage::UsagePeriod::Week => chrono::Duration::weeks(1),
+        };
+        let today = chrono::Local::now().date_naive();
+        let mut periods = Vec::new();
+        let mut period = self.usage_since();
+        while period <= today {
+            periods.push(period);
+            period += step;
+        }
+        if self.usage_activity {
+            self.render_usage_activity(ui, &periods);
+            return;
+        }
+
+        // Plotted values per model/agent, and totals for the table
+        let mut series: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+        let mut totals: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+        for entry in &self.usage_entries {
+            let key = if self.usage_by_model {
+                entry.model.as_str()
+            } else {
+                entry.agent_id.as_str()
+            };
+            let value = match self.usage_metric {
+                UsageMetric::InputTokens => entry.input_tokens as f64,
+                UsageMetric::OutputTokens => entry.output_tokens as f64,
+                UsageMetric::Cost => self.calculate_cost(entry.input_tokens, entry.output_tokens),
+            };
+            if let Some(i) = periods.iter().position(|p| *p == entry.period) {
+                series
+                    .entry(key)
+                    .or_insert_with(|| vec![0.0; periods.len()])[i] += value;
+            }
+            let total = totals.entry(key).or_default();
+            total.0 += entry.input_tokens;
+            total.1 += entry.output_tokens;
+        }
+
+        if series.is_empty() {
+            ui.label(
+                egui::RichText::new("No usage recorded in this range")
+                    .color(theme_colors(ui.ctx()).muted),
+            );
+            return;
+        }
+
+        let mut charts: Vec<egui_plot::BarChart> = Vec::new();
+        for (key, values) in &series {
+            let bars = values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| egui_plot::Bar::new(i as f64, *value).width(0.7))
+                .collect();
+            let chart =
+                egui_plot::BarChart::new(*key, bars).stack_on(&charts.iter().collect::<Vec<_>>());
+            charts.push(chart);
+        }
+
+        let labels: Vec<String> = periods
+            .iter()
+            .map(|p| p.format("%b %-d").to_string())
+            .collect();
+        egui_plot::Plot::new("usage_chart")
+            .height(280.0)
+            .legend(egui_plot::Legend::default())
+            .allow_drag(false)
+            .allow_zoom(false)
+            .allow_scroll(false)
+            .include_y(0.0)
+            .x_axis_formatter(move |mark, _range| {
+                if mark.value.fract() != 0.0 || mark.value < 0.0 {
+                    return String::new();
+                }
+                labels.get(mark.value as usize).cloned().unwrap_or_default()
+            })
+            .show(ui, |plot_ui| {
+                for chart in charts {
+                    plot_ui.bar_chart(chart);
+                }
+            });
+        ui.add_space(10.0);
+
+        egui::Grid::new("usage_totals")
+            .num_columns(4)
+            .striped(true)
+            .spacing([20.0, 4.0])
+            .show(ui, |ui| {
+                ui.label(
+                    egui::RichText::new(if self.usage_by_model {
+                        "Model"
+                    } else {
+                        "Agent"
+                    })
+                    .strong(),
+                );
+                ui.label(egui::RichText::new("Input").strong());
+                ui.label(egui::RichText::new("Output").strong());
+                ui.label(egui::RichText::new("Cost").strong());
+                ui.end_row();
+                for (key, (input, output)) in &totals {
+                    ui.label(*key);
+                    ui.label(input.to_string());
+                    ui.label(output.to_string());
+                    ui.label(format!("${:.4}", self.calculate_cost(*input, *output)));
+                    ui.end_row();
+                }
+            });
+        ui.label(
+            egui::RichText::new("Token counts are estimates; cost uses the app's flat rate.")
+                .size(12.0)
+                .color(theme_colors(ui.ctx()).muted),
+        );
+    }
+
+    /// Activity section of the Usage view: the opt-in analytics collected on
+    /// this machine (see `rustbot_core::analytics`)
+    ///
+    /// # Arguments
+    /// * `periods` - First day of each bar, oldest first
+    fn render_usage_activity(&mut self, ui: &mut egui::Ui, periods: &[chrono::NaiveDate]) {
+        let colors = theme_colors(ui.ctx());
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(
+                    &mut self.analytics_enabled,
+                    "Collect usage analytics on this machine",
+                )
+                .changed()
+            {
+                self.save_analytics_enabled();
+            }
+            if ui
+                .add_enabled(
+                    !self.usage_analytics.days.is_empty(),
+                    egui::Button::new(format!("{} Clear", icons::TRASH)),
+                )
+                .on_hover_text("Delete everything collected so far")
+                .clicked()
+            {
+                self.clear_analytics();
+            }
+        });
+        ui.label(
+            egui::RichText::new(
+                "Counts messages per agent, tool calls and errors for each day. Nothing \
+                 leaves this machine, and no message content is kept.",
+            )
+            .size(12.0)
+            .color(colors.muted),
+        );
+        ui.add_space(10.0);
+
+        let Some(since) = periods.first().copied() else {
+            return;
+        };
+        let summary = self.usage_analytics.summary(since);
+        if summary.messages == 0 && summary.errors == 0 && summary.tools.is_empty() {
+            ui.label(
+                egui::RichText::new(if self.analytics_enabled {
+                    "No activity recorded in this range"
+                } else {
+                    "Analytics are off; turn them on to see your activity here"
+                })
+                .color(colors.muted),
+            );
+            return;
+        }
+
+        // Messages and errors per period, side by side
+        let mut messages = vec![0.0; periods.len()];
+        let mut errors = vec![0.0; periods.len()];
+        for (date, day) in self.usage_analytics.days.range(since..) {
+            if let Some(i) = periods.partition_point(|p| p <= date).checked_sub(1) {
+                messages[i] += day.messages as f64;
+                errors[i] += day.errors as f64;
+            }
+        }
+        let bars = |values: &[f64], offset: f64| -> Vec<egui_plot::Bar> {
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| egui_plot::Bar::new(i as f64 + offset, *value).width(0.35))
+                .collect()
+        };
+        let messages = egui_plot::BarChart::new("Messages", bars(&messages, -0.18));
+        let errors = egui_plot::BarChart::new("Errors", bars(&errors, 0.18)).color(colors.error);
+
+        let labels: Vec<String> = periods
+            .iter()
+            .map(|p| p.format("%b %-d").to_string())
+            .collect();
+        egui_plot::Plot::new("activity_chart")
+            .height(220.0)
+            .legend(egui_plot::Legend::default())
+            .allow_drag(false)
+            .allow_zoom(false)
+            .allow_scroll(false)
+            .include_y(0.0)
+            .x_axis_formatter(move |mark, _range| {
+                if mark.value.fract() != 0.0 || mark.value < 0.0 {
+                    return String::new();
+                }
+                labels.get(mark.value as usize).cloned().unwrap_or_default()
+            })
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(messages);
+                plot_ui.bar_chart(errors);
+            });
+        ui.add_space(10.0);
+
+        let error_rate = summary
+            .error_rate()
+            .map(|rate| format!(" ({:.1}% of messages)", rate * 100.0))
+            .unwrap_or_default();
+        ui.label(format!(
+            "{} messages, {} errors{}",
+            summary.messages, summary.errors, error_rate
+        ));
+        ui.add_space(10.0);
+
+        ui.horizontal_top(|ui| {
+            egui::Grid::new("activity_agents")
+                .num_columns(2)
+                .striped(true)
+                .spacing([20.0, 4.0])
+                .show(ui, |ui| {
+                    ui.label(egui::RichText::new("Agent").strong());
+                    ui.label(egui::RichText::new("Messages").strong());
+                    ui.end_row();
+                    for (agent, count) in &summary.agents {
+                        ui.label(agent);
+                        ui.label(count.to_string());
+                        ui.end_row();
+                    }
+                });
+            ui.add_space(40.0);
+            egui::Grid::new("activity_tools")
+                .num_columns(3)
+                .striped(true)
+                .spacing([20.0, 4.0])
+                .show(ui, |ui| {
+                    ui.label(egui::RichText::new("Tool").strong());
+                    ui.label(egui::RichText::new("Calls").strong());
+                    ui.label(egui::RichText::new("Failed").strong());
+                    ui.end_row();
+                    for (name, tool) in &summary.tools {
+                        ui.label(name);
+                        ui.label(tool.calls.to_string());
+                        if tool.errors > 0 {
+                            ui.colored_label(colors.error, tool.errors.to_string());
+                        } else {
+                            ui.label("0");
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+
+    /// Render the marketplace view
+    ///
+    /// Displays the MCP Marketplace browser for discovering and installing MCP servers.
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    /// * `ctx` - The egui Context for global state and repaints
+    pub fn render_marketplace_view(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        if let Some(marketplace_view) = &mut self.extensions_marketplace_view {
+            marketplace_view.render(ui, ctx);
+        } else {
+            ui.vertical_centered(|ui| {
+                ui.add_space(50.0);
+                ui.label(
+                    egui::RichText::new("Marketplace view not initialized")
+                        .size(14.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                );
+            });
+        }
+    }
+
+    /// Render the extensions view with tabs for Marketplace and Installed
+    ///
+    /// This view provides a unified interface for managing MCP extensions:
+    /// - Marketplace: Browse and discover available MCP servers
+    /// - Installed: View and manage installed extensions (with filtering)
+    /// - Running: Configured plugins with a detail page per plugin
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    /// * `ctx` - The egui Context for global state and repaints
+    pub fn render_extensions_view(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        // Secondary navigation bar (tabs) - similar to Settings view pattern
+        ui.horizontal(|ui| {
+            if ui
+                .selectable_label(
+                    self.extensions_view == ExtensionsView::Marketplace,
+                    format!("{} Marketplace", icons::STOREFRONT),
+                )
+                .clicked()
+            {
+                self.extensions_view = ExtensionsView::Marketplace;
+            }
+
+            ui.add_space(10.0);
+
+            if ui
+                .selectable_label(
+                    self.extensions_view == ExtensionsView::Installed,
+                    format!("{} Installed", icons::PACKAGE),
+                )
+                .clicked()
+            {
+                self.extensions_view = ExtensionsView::Installed;
+            }
+
+            ui.add_space(10.0);
+
+            if ui
+                .selectable_label(
+                    self.extensions_view == ExtensionsView::Running,
+                    format!("{} Running", icons::PLUGS_CONNECTED),
+                )
+                .clicked()
+            {
+                self.extensions_view = ExtensionsView::Running;
+            }
+        });
+        ui.separator();
+
+        // Render active subview
+        match self.extensions_view {
+            ExtensionsView::Marketplace => {
+                // Reuse existing marketplace view
+                self.render_marketplace_view(ui, ctx);
+            }
+            ExtensionsView::Installed => {
+                self.render_installed_extensions(ui);
+            }
+            ExtensionsView::Running => {
+                if let Some(plugins_view) = &mut self.plugins_view {
+                    plugins_view.render(ui, ctx);
+                }
+            }
+        }
+    }
+
+    /// Render installed extensions view with filtering
+    ///
+    /// Shows all installed extensions with the ability to filter by type:
+    /// - All: Show all installed extensions
+    /// - Remote: Show only remote/cloud extensions
+    /// - Local: Show only local extensions
+    ///
+    /// Each extension displays a badge indicating its type (Remote/Local).
+    fn render_installed_extensions(&mut self, ui: &mut egui::Ui) {
+        // Check if we're showing a configuration dialog
+        if let Some(ext_id) = &self.configuring_extension_id.clone() {
+            self.render_extension_config_dialog(ui, ext_id);
+            return; // Show only the dialog when configuring
+        }
+
+        // Show uninstall confirmation dialog if present
+        if let Some((ext_id, ext_name)) = &self.uninstall_confirmation.clone() {
+            self.render_uninstall_confirmation_dialog(ui, ext_id, ext_name);
+            return; // Show only the dialog when confirming
+        }
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                ui.add_space(20.0);
+                ui.heading("Installed Extensions");
+                ui.add_space(10.0);
+
+                // Filter dropdown
+                ui.horizontal(|ui| {
+                    ui.label("Filter:");
+                    egui::ComboBox::from_id_source("install_type_filter")
+                        .selected_text(self.installed_extensions_filter.label())
+                        .show_ui(ui, |ui| {
+                            use crate::ui::InstallTypeFilter;
+                            ui.selectable_value(
+                                &mut self.installed_extensions_filter,
+                                InstallTypeFilter::All,
+                                "All",
+                            );
+                            ui.selectable_value(
+                                &mut self.installed_extensions_filter,
+                                InstallTypeFilter::Remote,
+                                "Remote",
+                            );
+                            ui.selectable_value(
+                                &mut self.installed_extensions_filter,
+                                InstallTypeFilter::Local,
+                                "Local",
+                            );
+                        });
+                });
+
+                ui.add_space(15.0);
+
+                // Load extension registry
+                use crate::mcp::extensions::ExtensionRegistry;
+                use std::path::PathBuf;
+
+                let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+                let registry_path = home_dir
+                    .join(".rustbot")
+                    .join("extensions")
+                    .join("registry.json");
+
+                match ExtensionRegistry::load(&registry_path) {
+                    Ok(registry) => {
+                        let all_extensions = registry.list();
+
+                        // Apply filter
+                        let filtered_extensions: Vec<_> = all_extensions
+                            .iter()
+                            .filter(|ext| {
+                                use crate::mcp::extensions::InstallationType;
+                                use crate::ui::InstallTypeFilter;
+
+                                match self.installed_extensions_filter {
+                                    InstallTypeFilter::All => true,
+                                    InstallTypeFilter::Remote => {
+                                        matches!(ext.install_type, InstallationType::Remote)
+                                    }
+                                    InstallTypeFilter::Local => {
+                                        matches!(ext.install_type, InstallationType::Local)
+                                    }
+                                }
+                            })
+                            .collect();
+
+                        if filtered_extensions.is_empty() {
+                            ui.vertical_centered(|ui| {
+                                ui.add_space(40.0);
+                                if all_extensions.is_empty() {
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "{} No extensions installed",
+                                            icons::PACKAGE
+                                        ))
+                                        .size(16.0)
+                                        .color(theme_colors(ui.ctx()).muted),
+                                    );
+                                    ui.add_space(10.0);
+                                    ui.label(
+                                        egui::RichText::new(
+                                            "Visit the Marketplace to discover and install MCP servers",
+                                        )
+                                        .size(12.0)
+                                        .color(theme_colors(ui.ctx()).subtle),
+                                    );
+                                } else {
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "{} No {} extensions installed",
+                                            icons::PACKAGE,
+                                            self.installed_extensions_filter.label().to_lowercase()
+                                        ))
+                                        .size(16.0)
+                                        .color(theme_colors(ui.ctx()).muted),
+                                    );
+                                    ui.add_space(10.0);
+                                    ui.label(
+                                        egui::RichText::new("Try selecting a different filter")
+                                            .size(12.0)
+                                            .color(theme_colors(ui.ctx()).subtle),
+                                    );
+                                }
+                            });
+                        } else {
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "{} extension(s) shown ({} total installed)",
+                                    filtered_extensions.len(),
+                                    all_extensions.len()
+                                ))
+                                .size(12.0)
+                                .color(theme_colors(ui.ctx()).muted),
+                            );
+                            ui.add_space(10.0);
+
+                            // Display each filtered extension
+                            for ext in filtered_extensions {
+                                ui.group(|ui| {
+                                    ui.set_min_width(ui.available_width());
+                                    ui.add_space(5.0);
+
+                                    // Extension header with type badge
+                                    ui.horizontal(|ui| {
+                                        // Type badge (Remote or Local)
+                                        use crate::mcp::extensions::InstallationType;
+                                        let (badge_icon, badge_text, badge_color) = match ext.install_type {
+                                            InstallationType::Remote => (
+                                                icons::CLOUD,
+                                                "Remote",
+                                                theme_colors(ui.ctx()).accent,
+                                            ),
+                                            InstallationType::Local => (
+                                                icons::DESKTOP,
+                                                "Local",
+                                                theme_colors(ui.ctx()).success,
+                                            ),
+                                        };
+
+                                        // Draw badge with background
+                                        let badge_label = format!("{} {}", badge_icon, badge_text);
+                                        ui.label(
+                                            egui::RichText::new(badge_label)
+                                                .size(11.0)
+                                                .color(badge_color)
+                                                .strong(),
+                                        );
+
+                                        ui.add_space(10.0);
+
+                                        ui.label(
+                                            egui::RichText::new(&ext.name)
+                                                .size(16.0)
+                                                .strong()
+                                                .color(theme_colors(ui.ctx()).accent),
+                                        );
+                                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                            ui.label(
+                                                egui::RichText::new(format!(
+                                                    "{} v{}",
+                                                    icons::TAG,
+                                                    ext.metadata.version
+                                                ))
+                                                .size(11.0)
+                                                .color(theme_colors(ui.ctx()).muted),
+                                            );
+                                        });
+                                    });
+
+                                    ui.add_space(5.0);
+
+                                    // Description
+                                    if !ext.description.is_empty() {
+                                        ui.label(
+                                            egui::RichText::new(&ext.description)
+                                                .size(12.0)
+                                                .color(theme_colors(ui.ctx()).muted),
+                                        );
+                                        ui.add_space(5.0);
+                                    }
+
+                                    // Installation date
+                                    ui.horizontal(|ui| {
+                                        ui.label(
+                                            egui::RichText::new(format!(
+                                                "{} Installed: {}",
+                                                icons::CALENDAR,
+                                                ext.metadata.installed_at.split('T').next().unwrap_or("Unknown")
+                                            ))
+                                            .size(11.0)
+                                            .color(theme_colors(ui.ctx()).muted),
+                                        );
+                                    });
+
+                                    ui.add_space(5.0);
+
+                                    // Action buttons
+                                    ui.horizontal(|ui| {
+                                        if !ext.metadata.repository_url.is_empty() {
+                                            if ui.button(format!("{} Repository", icons::GITHUB_LOGO)).clicked() {
+                                                ui.output_mut(|o| o.copied_text = ext.metadata.repository_url.clone());
+                                                tracing::info!("Repository URL copied to clipboard: {}", ext.metadata.repository_url);
+                                            }
+                                        }
+
+                                        if ui.button(format!("{} Configure", icons::GEAR)).clicked() {
+                                            self.configuring_extension_id = Some(ext.id.clone());
+                                        }
+
+                                        // Detail page of the running plugin: tools and schemas
+                                        if ui
+                                            .button(format!("{} Tools", icons::WRENCH))
+                                            .on_hover_text("Show the tools this extension provides")
+                                            .clicked()
+                                        {
+                                            use crate::mcp::extensions::McpConfigEntry;
+                                            let plugin_id = match &ext.mcp_config {
+                                                McpConfigEntry::LocalServer(config) => &config.id,
+                                                McpConfigEntry::CloudService(config) => &config.id,
+                                            };
+                                            if let Some(plugins_view) = &mut self.plugins_view {
+                                                plugins_view.select_plugin(plugin_id);
+                                            }
+                                            self.extensions_view = ExtensionsView::Running;
+                                        }
+
+                                        if ui
+                                            .button(
+                                                egui::RichText::new(format!("{} Uninstall", icons::TRASH))
+                                                    .color(theme_colors(ui.ctx()).error),
+                                            )
+                                            .clicked()
+                                        {
+                                            // Show confirmation dialog
+                                            self.uninstall_confirmation = Some((ext.id.clone(), ext.name.clone()));
+                                        }
+                                    });
+
+                                    ui.add_space(5.0);
+                                });
+
+                                ui.add_space(10.0);
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        ui.vertical_centered(|ui| {
+                            ui.add_space(40.0);
+                            ui.label(
+                                egui::RichText::new(format!("{} Failed to load extensions", icons::WARNING))
+                                    .size(16.0)
+                                    .color(theme_colors(ui.ctx()).error),
+                            );
+                            ui.add_space(10.0);
+                            ui.label(
+                                egui::RichText::new(format!("Error: {}", e))
+                                    .size(12.0)
+                                    .color(theme_colors(ui.ctx()).subtle),
+                            );
+                        });
+                    }
+                }
+            });
+    }
+
+    /// Render extension configuration dialog
+    fn render_extension_config_dialog(&mut self, ui: &mut egui::Ui, ext_id: &str) {
+        use crate::mcp::extensions::ExtensionRegistry;
+        use std::path::PathBuf;
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                ui.add_space(20.0);
+                ui.heading(format!("{} Configure Extension", icons::GEAR));
+                ui.add_space(10.0);
+
+                // Back button
+                if ui.button(format!("{} Back to Extensions", icons::ARROW_LEFT)).clicked() {
+                    self.configuring_extension_id = None;
+                    self.extension_config_message = None;
+                    return;
+                }
+
+                ui.add_space(15.0);
+
+                // Load extension info
+                let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+                let registry_path = home_dir.join(".rustbot").join("extensions").join("registry.json");
+
+                let extension = match ExtensionRegistry::load(&registry_path) {
+                    Ok(registry) => registry.get(ext_id).cloned(),
+                    Err(_) => None,
+                };
+
+                if let Some(ext) = extension {
+                    // Extension header
+                    ui.group(|ui| {
+                        ui.set_min_width(ui.available_width());
+                        ui.add_space(10.0);
+                        ui.label(egui::RichText::new(&ext.name).size(18.0).strong());
+                        ui.label(&ext.description);
+                        ui.add_space(10.0);
+                    });
+
+                    ui.add_space(15.0);
+
+                    // Show configuration message if any
+                    if let Some((message, is_error)) = &self.extension_config_message {
+                        let color = if *is_error {
+                            theme_colors(ui.ctx()).error
+                        } else {
+                            theme_colors(ui.ctx()).success
+                        };
+                        ui.label(egui::RichText::new(message).color(color));
+                        ui.add_space(10.0);
+                    }
+
+                    // Agent configuration section
+                    ui.label(egui::RichText::new("Enable for Agents:").size(16.0).strong());
+                    ui.add_space(10.0);
+
+                    // Iterate through agents and show checkboxes
+                    let mut changes_made = false;
+                    for (idx, agent_config) in self.agent_configs.iter_mut().enumerate() {
+                        let mut is_enabled = agent_config.mcp_extensions.contains(&ext.id);
+
+                        ui.horizontal(|ui| {
+                            if ui.checkbox(&mut is_enabled, "").changed() {
+                                if is_enabled {
+                                    // Add extension to agent
+                                    if !agent_config.mcp_extensions.contains(&ext.id) {
+                                        agent_config.mcp_extensions.push(ext.id.clone());
+                                        changes_made = true;
+                                    }
+                                } else {
+                                    // Remove extension from agent
+                                    agent_config.mcp_extensions.retain(|id| id != &ext.id);
+                                    changes_made = true;
+                                }
+                            }
+
+                            ui.label(egui::RichText::new(&agent_config.name).size(14.0));
+                        });
+
+                        ui.add_space(5.0);
+                    }
+
+                    // Save button
+                    ui.add_space(15.0);
+                    if ui.button(format!("{} Save Configuration", icons::FLOPPY_DISK)).clicked() {
+                        // Save all modified agent configs
+                        let mut save_errors = Vec::new();
+                        let mut saved_count = 0;
+
+                        for agent_config in &self.agent_configs {
+                            // Build path to agent JSON file
+                            let agent_path = PathBuf::from("agents")
+                                .join("presets")
+                                .join(format!("{}.json", agent_config.name));
+
+                            // Save the updated config
+                            match serde_json::to_string_pretty(&agent_config) {
+                                Ok(json) => {
+                                    if let Err(e) = std::fs::write(&agent_path, json) {
+                                        save_errors.push(format!("{}: {}", agent_config.name, e));
+                                    } else {
+                                        saved_count += 1;
+                                    }
+                                }
+                                Err(e) => {
+                                    save_errors.push(format!("{}: {}", agent_config.name, e));
+                                }
+                            }
+                        }
+
+                        if saved_count > 0 {
+                            audit::record(
+                                audit::AuditAction::ConfigChanged,
+                                format!("Extension settings of {} agent(s)", saved_count),
+                            );
+                        }
+                        if save_errors.is_empty() {
+                            self.extension_config_message = Some((
+                                format!("✓ Configuration saved! {} agent(s) updated. Tools will be available instantly.", saved_count),
+                                false,
+                            ));
+                            // Reload agent configs to pick up changes
+                            // This will trigger tool reload in the next update cycle
+                        } else {
+                            self.extension_config_message = Some((
+                                format!("✗ Failed to save some configs: {}", save_errors.join(", ")),
+                                true,
+                            ));
+                        }
+                    }
+
+                } else {
+                    ui.label(
+                        egui::RichText::new("Extension not found")
+                            .size(14.0)
+                            .color(theme_colors(ui.ctx()).error),
+                    );
+                }
+            });
+    }
+
+    /// Render uninstall confirmation dialog
+    fn render_uninstall_confirmation_dialog(
+        &mut self,
+        ui: &mut egui::Ui,
+        ext_id: &str,
+        ext_name: &str,
+    ) {
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                ui.add_space(20.0);
+                ui.heading(format!("{} Uninstall Extension", icons::WARNING));
+                ui.add_space(20.0);
+
+                // Show uninstall message if any
+                if let Some((message, is_error)) = &self.uninstall_message {
+                    let color = if *is_error {
+                        theme_colors(ui.ctx()).error
+                    } else {
+                        theme_colors(ui.ctx()).success
+                    };
+                    ui.label(egui::RichText::new(message).size(14.0).color(color));
+                    ui.add_space(15.0);
+
+                    // If successful uninstall, show back button
+                    if !is_error {
+                        if ui
+                            .button(format!("{} Back to Extensions", icons::ARROW_LEFT))
+                            .clicked()
+                        {
+                            self.uninstall_confirmation = None;
+                            self.uninstall_message = None;
+                        }
+                        return;
+                    }
+                }
+
+                // Warning box
+                ui.group(|ui| {
+                    ui.set_min_width(ui.available_width());
+                    ui.add_space(10.0);
+
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "{} Warning: This action cannot be undone",
+                            icons::WARNING
+                        ))
+                        .size(16.0)
+                        .strong()
+                        .color(theme_colors(ui.ctx()).warning),
+                    );
+
+                    ui.add_space(10.0);
+
+                    ui.label(
+                        egui::RichText::new(format!("Extension: {}", ext_name))
+                            .size(14.0)
+                            .strong(),
+                    );
+                    ui.label(egui::RichText::new(format!("ID: {}", ext_id)).size(12.0));
+
+                    ui.add_space(10.0);
+
+                    ui.label("This will:");
+                    ui.label("  • Remove the extension from your system");
+                    ui.label("  • Remove it from all agent configurations");
+                    ui.label("  • Remove MCP configuration entries");
+
+                    ui.add_space(10.0);
+                });
+
+                ui.add_space(20.0);
+
+                // Action buttons
+                ui.horizontal(|ui| {
+                    if ui.button(format!("{} Cancel", icons::X)).clicked() {
+                        self.uninstall_confirmation = None;
+                        self.uninstall_message = None;
+                    }
+
+                    ui.add_space(10.0);
+
+                    if ui
+                        .button(
+                            egui::RichText::new(format!("{} Uninstall", icons::TRASH))
+                                .color(theme_colors(ui.ctx()).error),
+                        )
+                        .clicked()
+                    {
+                        // Perform uninstall
+                        match self.perform_uninstall(ext_id) {
+                            Ok(()) => {
+                                self.uninstall_message = Some((
+                                    format!("✓ Extension '{}' uninstalled successfully", ext_name),
+                                    false,
+                                ));
+                            }
+                            Err(e) => {
+                                self.uninstall_message =
+                                    Some((format!("✗ Failed to uninstall extension: {}", e), true));
+                            }
+                        }
+                    }
+                });
+            });
+    }
+
+    /// Perform extension uninstall
+    ///
+    /// Removes the extension from:
+    /// 1. Extension registry (~/.rustbot/extensions/registry.json)
+    /// 2. Global MCP config (~/.rustbot/mcp_config.json) if present
+    /// 3. All agent-specific MCP configs (~/.rustbot/mcp_configs/*.json)
+    /// 4. All agent presets (agents/presets/*.json) mcp_extensions field
+    fn perform_uninstall(&mut self, extension_id: &str) -> anyhow::Result<()> {
+        use crate::mcp::extensions::ExtensionRegistry;
+        use std::path::PathBuf;
+
+        let home_dir = dirs::home_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))?;
+
+        // 1. Remove from extension registry
+        let registry_path = home_dir
+            .join(".rustbot")
+            .join("extensions")
+            .join("registry.json");
+
+        let mut registry = ExtensionRegistry::load(&registry_path)?;
+        if registry.uninstall(extension_id).is_none() {
+            return Err(anyhow::anyhow!(
+                "Extension '{}' not found in registry",
+                extension_id
+            ));
+        }
+        registry.save(&registry_path)?;
+        tracing::info!("✓ Removed extension '{}' from registry", extension_id);
+        audit::record(audit::AuditAction::ExtensionUninstalled, extension_id);
+
+        // 2. Remove from global MCP config if it exists
+        let global_config_path = home_dir.join(".rustbot").join("mcp_config.json");
+        if global_config_path.exists() {
+            use crate::mcp::config::McpConfig;
+            let mut config = McpConfig::load_from_file(&global_config_path)?;
+            if config.remove_extension(extension_id) {
+                config.save_to_file(&global_config_path)?;
+                tracing::info!("✓ Removed extension from global MCP config");
+            }
+        }
+
+        // 3. Remove from all agent-specific MCP configs
+        let mcp_configs_dir = home_dir.join(".rustbot").join("mcp_configs");
+        if mcp_configs_dir.exists() {
+            use crate::mcp::config::McpConfig;
+            for entry in std::fs::read_dir(&mcp_configs_dir)? {
+                let path = entry?.path();
+                if path.extension().and_then(|s| s.to_str()) == Some("json") {
+                    let mut config = McpConfig::load_from_file(&path)?;
+                    if config.remove_extension(extension_id) {
+                        config.save_to_file(&path)?;
+                        tracing::info!("✓ Removed extension from {:?}", path.file_name());
+                    }
+                }
+            }
+        }
+
+        // 4. Remove from all agent preset configs (agents/presets/*.json)
+        for agent_config in &mut self.agent_configs {
+            if agent_config
+                .mcp_extensions
+                .contains(&extension_id.to_string())
+            {
+                agent_config.mcp_extensions.retain(|id| id != extension_id);
+
+                // Save the updated agent config
+                let agent_path = PathBuf::from("agents")
+                    .join("presets")
+                    .join(format!("{}.json", agent_config.name));
+
+                let json = serde_json::to_string_pretty(&agent_config)?;
+                std::fs::write(&agent_path, json)?;
+                tracing::info!("✓ Removed extension from agent '{}'", agent_config.name);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Render the agents management view
+    ///
+    /// Displays all configured agents and allows:
+    /// - Viewing agent details (name, ID, model, web search capability)
+    /// - Setting the active agent
+    /// - Editing agent configuration (instructions, personality, model)
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    pub fn render_agents_view(&mut self, ui: &mut egui::Ui) {
+        // Use scroll area for agents
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                ui.add_space(20.0);
+                ui.horizontal(|ui| {
+                    ui.heading("Agents");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui
+                            .add_enabled(
+                                self.agent_wizard.is_none(),
+                                egui::Button::new(format!("{} New Agent", icons::PLUS)),
+                            )
+                            .on_hover_text("Create an agent step by step")
+                            .clicked()
+                        {
+                            self.open_agent_wizard();
+                        }
+                    });
+                });
+                ui.add_space(10.0);
+
+                ui.label("Manage AI agents with specialized capabilities and instructions:");
+                ui.add_space(15.0);
+
+                // Agent list - show in list view
+                ui.label(egui::RichText::new("Available Agents:").strong());
+                ui.add_space(10.0);
+
+                // Display each agent in a compact list format
+                for (index, config) in self.agent_configs.iter_mut().enumerate() {
+                    ui.group(|ui| {
+                        ui.horizontal(|ui| {
+                            // Agent icon and name
+                            let status_color = if config.is_primary {
+                                theme_colors(ui.ctx()).success // Green for primary
+                            } else if config.enabled {
+                                theme_colors(ui.ctx()).accent // Blue for enabled
+                            } else {
+                                theme_colors(ui.ctx()).muted // Gray for disabled
+                            };
+
+                            // Icon based on agent type
+                            let icon = if config.is_primary {
+                                format!("{} {}", icons::STAR, config.name)
+                            } else if config.web_search_enabled {
+                                format!("{} {}", icons::MAGNIFYING_GLASS, config.name)
+                            } else {
+                                format!("{} {}", icons::ROBOT, config.name)
+                            };
+
+                            ui.label(egui::RichText::new(&icon).strong().size(16.0));
+
+                            // Status indicator
+                            if config.is_primary {
+                                ui.label(
+                                    egui::RichText::new("● Primary")
+                                        .size(12.0)
+                                        .color(status_color),
+                                );
+                            } else if config.enabled {
+                                ui.label(
+                                    egui::RichText::new("✓ Enabled")
+                                        .size(12.0)
+                                        .color(status_color),
+                                );
+                            } else {
+                                ui.label(
+                                    egui::RichText::new("○ Disabled")
+                                        .size(12.0)
+                                        .color(status_color),
+                                );
+                            }
+
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    // Edit button (for all agents)
+                                    if ui
+                                        .button(format!("{} Edit", icons::PENCIL_SIMPLE))
+                                        .clicked()
+                                    {
+                                        self.selected_agent_index = Some(index);
+                                        self.agent_issues = None;
+                                    }
+
+                                    // Enable/Disable toggle (only for non-primary agents)
+                                    if !config.is_primary {
+                                        let toggle_text = if config.enabled {
+                                            format!("{} Disable", icons::TOGGLE_RIGHT)
+                                        } else {
+                                            format!("{} Enable", icons::TOGGLE_LEFT)
+                                        };
+
+                                        if ui.button(toggle_text).clicked() {
+                                            config.enabled = !config.enabled;
+                                            // TODO: Persist this change and update the agent in the API
+                                        }
+                                    }
+                                },
+                            );
+                        });
+
+                        ui.add_space(3.0);
+
+                        // Compact info line
+                        ui.horizontal(|ui| {
+                            ui.add_space(20.0); // Indent
+                            let role = if config.is_primary {
+                                "Primary"
+                            } else {
+                                "Specialist"
+                            };
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "{} • Model: {} • Web Search: {} • History: {}",
+                                    role,
+                                    config.model.split('/').last().unwrap_or(&config.model),
+                                    if config.web_search_enabled {
+                                        "✓"
+                                    } else {
+                                        "✗"
+                                    },
+                                    config.context_pruning.strategy.label()
+                                ))
+                                .size(11.0)
+                                .color(theme_colors(ui.ctx()).muted),
+                            );
+                        });
+                    });
+
+                    ui.add_space(10.0);
+                }
+
+                // Presets that were skipped when loading
+                if !self.broken_agents.is_empty() {
+                    ui.add_space(5.0);
+                    ui.label(
+                        egui::RichText::new(format!("{} Broken Agents:", icons::WARNING))
+                            .strong()
+                            .color(theme_colors(ui.ctx()).error),
+                    );
+                    ui.label(
+                        egui::RichText::new(
+                            "These presets weren't loaded. Fix them and save to reload.",
+                        )
+                        .size(12.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                    );
+                    ui.add_space(5.0);
+
+                    let mut open = None;
+                    for broken in &self.broken_agents {
+                        ui.group(|ui| {
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    egui::RichText::new(broken.path.display().to_string())
+                                        .monospace(),
+                                );
+                                ui.with_layout(
+                                    egui::Layout::right_to_left(egui::Align::Center),
+                                    |ui| {
+                                        if ui
+                                            .button(format!("{} Fix", icons::PENCIL_SIMPLE))
+                                            .clicked()
+                                        {
+                                            open = Some(broken.path.clone());
+                                        }
+                                    },
+                                );
+                            });
+                            render_preset_issues(ui, &broken.issues);
+                        });
+                        ui.add_space(5.0);
+                    }
+                    if let Some(path) = open {
+                        match PresetEditor::open(path) {
+                            Ok(editor) => self.preset_editor = Some(editor),
+                            Err(e) => {
+                                self.command_feedback =
+                                    Some((format!("Couldn't open the preset: {}", e), true));
+                            }
+                        }
+                    }
+                }
+
+                // A broken preset being fixed, checked as it's typed
+                let mut save_preset = false;
+                if let Some(editor) = &mut self.preset_editor {
+                    ui.separator();
+                    ui.add_space(10.0);
+                    ui.heading(format!("Fix Preset: {}", editor.path.display()));
+                    ui.add_space(5.0);
+                    let response = ui.add(
+                        egui::TextEdit::multiline(&mut editor.json)
+                            .code_editor()
+                            .desired_rows(16)
+                            .desired_width(f32::INFINITY),
+                    );
+                    if response.changed() {
+                        editor.revalidate();
+                    }
+                    ui.add_space(5.0);
+                    if editor.issues.is_empty() {
+                        ui.label(
+                            egui::RichText::new(format!("{} No problems found", icons::CHECK))
+                                .size(12.0)
+                                .color(theme_colors(ui.ctx()).success),
+                        );
+                    } else {
+                        render_preset_issues(ui, &editor.issues);
+                    }
+                    if let Some(error) = &editor.error {
+                        ui.label(egui::RichText::new(error).color(theme_colors(ui.ctx()).error));
+                    }
+                    ui.add_space(5.0);
+                    let mut cancel = false;
+                    ui.horizontal(|ui| {
+                        let valid = !agent::validation::has_errors(&editor.issues);
+                        if ui
+                            .add_enabled(valid, egui::Button::new("Save and Reload"))
+                            .on_disabled_hover_text("Fix the errors first")
+                            .on_hover_text("Reloading closes open chats")
+                            .clicked()
+                        {
+                            save_preset = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            cancel = true;
+                        }
+                    });
+                    if cancel {
+                        self.preset_editor = None;
+                    }
+                }
+                if save_preset {
+                    self.save_preset_editor();
+                }
+
+                ui.add_space(15.0);
+
+                // Agent editing section
+                if let Some(index) = self.selected_agent_index {
+                    if let Some(config) = self.agent_configs.get_mut(index) {
+                        ui.separator();
+                        ui.add_space(15.0);
+
+                        ui.heading(format!("Edit Agent: {}", config.name));
+                        ui.add_space(10.0);
+
+                        // Agent name
+                        ui.label(egui::RichText::new("Agent Name:").strong());
+                        ui.add_space(5.0);
+                        ui.text_edit_singleline(&mut config.name);
+                        ui.add_space(10.0);
+
+                        // Agent instructions
+                        ui.label(egui::RichText::new("Agent Instructions:").strong());
+                        ui.label("What this agent does and how it should behave:");
+                        ui.add_space(5.0);
+                        ui.add_sized(
+                            [ui.available_width() - 20.0, 150.0],
+                            egui::TextEdit::multiline(&mut config.instructions)
+                                .hint_text("Enter agent-specific instructions...")
+                                .margin(egui::vec2(8.0, 8.0)),
+                        );
+
+                        ui.add_space(15.0);
+
+                        // Agent personality
+                        ui.label(egui::RichText::new("Agent Personality (Optional):").strong());
+                        ui.label("The agent's communication style and personality:");
+                        ui.add_space(5.0);
+
+                        // Convert Option<String> to String for editing
+                        let mut personality_text = config.personality.clone().unwrap_or_default();
+                        let personality_response = ui.add_sized(
+                            [ui.available_width() - 20.0, 150.0],
+                            egui::TextEdit::multiline(&mut personality_text)
+                                .hint_text(
+                                    "Enter agent personality traits (leave empty for none)...",
+                                )
+                                .margin(egui::vec2(8.0, 8.0)),
+                        );
+
+                        // Update config if changed
+                        if personality_response.changed() {
+                            config.personality = if personality_text.trim().is_empty() {
+                                None
+                            } else {
+                                Some(personality_text)
+                            };
+                        }
+
+                        ui.add_space(15.0);
+
+                        // Model selection
+                        ui.label(egui::RichText::new("LLM Model:").strong());
+                        ui.add_space(5.0);
+                        egui::ComboBox::from_label("")
+                            .selected_text(&config.model)
+                            .show_ui(ui, |ui| {
+                                for (model, label) in templates::SUGGESTED_MODELS {
+                                    ui.selectable_value(
+                                        &mut config.model,
+                                        model.to_string(),
+                                        *label,
+                                    );
+                                }
+                            });
+
+                        ui.add_space(15.0);
+
+                        // Action buttons
+                        ui.horizontal(|ui| {
+                            if ui.button("Save Changes").clicked() {
+                                // Apply changes to agent (will implement recreation later)
+                                self.selected_agent_index = None;
+                                self.agent_issues = None;
+                            }
+
+                            if ui.button("Cancel").clicked() {
+                                self.selected_agent_index = None;
+                                self.agent_issues = None;
+                            }
+
+                            if ui
+                                .button(format!("{} Validate", icons::CHECK_CIRCLE))
+                                .on_hover_text("Check the agent for problems")
+                                .clicked()
+                            {
+                                self.agent_issues = Some(agent::validation::validate_agent(config));
+                            }
+                        });
+
+                        match &self.agent_issues {
+                            Some(issues) if issues.is_empty() => {
+                                ui.label(
+                                    egui::RichText::new(format!(
+                                        "{} No problems found",
+                                        icons::CHECK
+                                    ))
+                                    .size(12.0)
+                                    .color(theme_colors(ui.ctx()).success),
+                                );
+                            }
+                            Some(issues) => render_preset_issues(ui, issues),
+                            None => {}
+                        }
+                    }
+                }
+
+                ui.add_space(20.0); // Bottom padding
+            });
+    }
+
+    /// Render the New Agent wizard window
+    ///
+    /// Steps: name → role template → model → capabilities → MCP extensions.
+    /// Creating writes the preset to agents/custom and registers the agent
+    /// without a restart.
+    ///
+    /// # Arguments
+    /// * `ctx` - The egui Context the window is shown in
+    pub fn render_agent_wizard(&mut self, ctx: &egui::Context) {
+        let Some(mut wizard) = self.agent_wizard.take() else {
+            return;
+        };
+        let mut open = true;
+        let mut create = false;
+        let existing: Vec<&str> = self.agent_configs.iter().map(|c| c.id.as_str()).collect();
+
+        egui::Window::new(format!("{} New Agent", icons::ROBOT))
+            .collapsible(false)
+            .default_width(520.0)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let colors = theme_colors(ui.ctx());
+                let step = wizard.step;
+                let number = AgentWizardStep::ALL
+                    .iter()
+                    .position(|s| *s == step)
+                    .unwrap_or(0);
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Step {} of {} · {}",
+                        number + 1,
+                        AgentWizardStep::ALL.len(),
+                        step.title()
+                    ))
+                    .small()
+                    .color(colors.muted),
+                );
+                ui.add_space(10.0);
+
+                let draft = &mut wizard.draft;
+                match step {
+                    AgentWizardStep::Name => {
+                        ui.label("What should the agent be called?");
+                        ui.add_space(5.0);
+                        ui.add(
+                            egui::TextEdit::singleline(&mut draft.name)
+                                .hint_text("e.g. Research Helper")
+                                .desired_width(300.0),
+                        );
+                        let id = templates::agent_id(&draft.name);
+                        if !id.is_empty() {
+                            ui.label(
+                                egui::RichText::new(format!("Agent ID: {}", id))
+                                    .small()
+                                    .color(colors.muted),
+                            );
+                        }
+                    }
+                    AgentWizardStep::Role => {
+                        ui.label("Start from a role:");
+                        ui.add_space(5.0);
+                        for (index, template) in templates::ROLE_TEMPLATES.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                if ui.radio(draft.template == index, template.name).clicked()
+                                    && draft.template != index
+                                {
+                                    draft.apply_template(index);
+                                }
+                                ui.label(
+                                    egui::RichText::new(template.summary)
+                                        .small()
+                                        .color(colors.muted),
+                                );
+                            });
+                        }
+                        ui.add_space(10.0);
+                        ui.label("Instructions:");
+                        ui.add(
+                            egui::TextEdit::multiline(&mut draft.instruction)
+                                .hint_text("What this agent does and how it should behave")
+                                .desired_rows(6)
+                                .desired_width(f32::INFINITY),
+                        );
+                    }
+                    AgentWizardStep::Model => {
+                        ui.label("Which model should the agent use?");
+                        ui.add_space(5.0);
+                        egui::ComboBox::from_id_salt("agent_wizard_model")
+                            .selected_text(&draft.model)
+                            .show_ui(ui, |ui| {
+                                for (model, label) in templates::SUGGESTED_MODELS {
+                                    ui.selectable_value(
+                                        &mut draft.model,
+                                        model.to_string(),
+                                        *label,
+                                    );
+                                }
+                            });
+                        ui.add_space(5.0);
+                        ui.horizontal(|ui| {
+                            ui.label("Or enter an OpenRouter model ID:");
+                            ui.text_edit_singleline(&mut draft.model);
+                        });
+                    }
+                    AgentWizardStep::Capabilities => {
+                        ui.checkbox(&mut draft.web_search, "Web search")
+                            .on_hover_text("Let the agent search the web for current information");
+                        ui.checkbox(&mut draft.image_input, "Image input")
+                            .on_hover_text("Send attached images to the agent's model");
+                    }
+                    AgentWizardStep::Extensions => {
+                        if wizard.extensions.is_empty() {
+                            ui.label(
+                                egui::RichText::new(
+                                    "No MCP extensions are installed. You can add them later \
+                                     from Extensions → Marketplace.",
+                                )
+                                .color(colors.muted),
+                            );
+                        } else {
+                            ui.label("Tools from these extensions are available to the agent:");
+                            ui.add_space(5.0);
+                        }
+                        for (id, name) in &wizard.extensions {
+                            let mut enabled = draft.mcp_extensions.contains(id);
+                            if ui.checkbox(&mut enabled, name).on_hover_text(id).changed() {
+                                if enabled {
+                                    draft.mcp_extensions.push(id.clone());
+                                } else {
+                                    draft.mcp_extensions.retain(|e| e != id);
+                                }
+                            }
+                        }
+                    }
+                }
 
-                                    // Description
-                                    if !ext.description.is_empty() {
-                                        ui.label(
-                                            egui::RichText::new(&ext.description)
-                                                .size(12.0)
-                                                .color(egui::Color32::from_rgb(100, 100, 100)),
-                                        );
-                                        ui.add_space(5.0);
-                                    }
+                if let Some(error) = &wizard.error {
+                    ui.add_space(10.0);
+                    ui.label(egui::RichText::new(error).color(colors.error));
+                }
 
-                                    // Installation date
-                                    ui.horizontal(|ui| {
-                                        ui.label(
-                                            egui::RichText::new(format!(
-                                                "{} Installed: {}",
-                                                icons::CALENDAR,
-                                                ext.metadata.installed_at.split('T').next().unwrap_or("Unknown")
-                                            ))
-                                            .size(11.0)
-                                            .color(egui::Color32::from_rgb(120, 120, 120)),
-                                        );
-                                    });
+                ui.add_space(15.0);
+                ui.horizontal(|ui| {
+                    if let Some(previous) = step.previous() {
+                        if ui.button("Back").clicked() {
+                            wizard.step = previous;
+                            wizard.error = None;
+                        }
+                    }
+
+                    // Check each step before moving on
+                    let problem = match step {
+                        AgentWizardStep::Name => {
+                            let id = templates::agent_id(&wizard.draft.name);
+                            if id.is_empty() {
+                                Some("Enter a name with at least one letter or digit".to_string())
+                            } else if existing.contains(&id.as_str()) {
+                                Some(format!("An agent with the ID '{}' already exists", id))
+                            } else {
+                                None
+                            }
+                        }
+                        AgentWizardStep::Role if wizard.draft.instruction.trim().is_empty() => {
+                            Some("Describe what the agent should do".to_string())
+                        }
+                        AgentWizardStep::Model if wizard.draft.model.trim().is_empty() => {
+                            Some("Choose a model".to_string())
+                        }
+                        _ => None,
+                    };
+
+                    match step.next() {
+                        Some(next) => {
+                            if ui.button("Next").clicked() {
+                                wizard.error = problem;
+                                if wizard.error.is_none() {
+                                    wizard.step = next;
+                                }
+                            }
+                        }
+                        None => {
+                            if ui
+                                .button(format!("{} Create Agent", icons::CHECK))
+                                .clicked()
+                            {
+                                create = true;
+                            }
+                        }
+                    }
+                });
+            });
+
+        if create {
+            let draft = wizard.draft.clone();
+            match self.create_agent(&draft) {
+                // The new agent shows up in the Agents list
+                Ok(_) => return,
+                Err(e) => wizard.error = Some(format!("{:#}", e)),
+            }
+        }
+        if open {
+            self.agent_wizard = Some(wizard);
+        }
+    }
+
+    /// Render the preferences view for UI customization
+    ///
+    /// Allows configuration of:
+    /// - Theme (light/dark mode)
+    /// - Font size and interface scale
+    /// - Notifications, privacy and file access grants
+    /// - Settings transfer
+    ///
+    /// Changes are saved immediately to user profile
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    pub fn render_preferences_view(&mut self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                ui.add_space(20.0);
+                ui.heading("Preferences");
+                ui.add_space(10.0);
+
+                ui.label("Customize the application appearance and behavior:");
+                ui.add_space(15.0);
+
+                // Theme selection
+                ui.group(|ui| {
+                    ui.label(egui::RichText::new("Theme").strong().size(16.0));
+                    ui.add_space(5.0);
+                    ui.label("Choose a color theme:");
+                    ui.add_space(10.0);
+
+                    let mut selected = None;
+                    ui.horizontal_wrapped(|ui| {
+                        let builtins = [
+                            (theme::LIGHT, icons::SUN, "Light"),
+                            (theme::DARK, icons::MOON, "Dark"),
+                            (theme::SYSTEM, icons::MONITOR, "System"),
+                            (theme::HIGH_CONTRAST, icons::CIRCLE_HALF, "High Contrast"),
+                        ];
+                        for (name, icon, label) in builtins {
+                            if ui
+                                .selectable_label(self.theme == name, format!("{} {}", icon, label))
+                                .clicked()
+                            {
+                                selected = Some(name.to_string());
+                            }
+                        }
+                        for palette in &self.custom_themes {
+                            if ui
+                                .selectable_label(
+                                    self.theme == palette.name,
+                                    format!("{} {}", icons::PALETTE, palette.name),
+                                )
+                                .clicked()
+                            {
+                                selected = Some(palette.name.clone());
+                            }
+                        }
+                    });
+
+                    // Save theme preference to user profile
+                    if let Some(theme) = selected.filter(|theme| *theme != self.theme) {
+                        self.theme = theme.clone();
+                        tracing::info!("Theme preference changed: {}", theme);
+                        self.app_state
+                            .update_profile(move |profile| profile.theme = theme);
+                    }
+
+                    ui.add_space(5.0);
+                    let colors = theme_colors(ui.ctx());
+                    let current = match self.theme.as_str() {
+                        theme::LIGHT => "Currently using Light theme".to_string(),
+                        theme::DARK => "Currently using Dark theme".to_string(),
+                        theme::HIGH_CONTRAST => "Currently using High Contrast theme".to_string(),
+                        theme::SYSTEM => format!(
+                            "Following the system setting ({} right now)",
+                            if colors.dark { "dark" } else { "light" }
+                        ),
+                        name if self.custom_themes.iter().any(|p| p.name == name) => {
+                            format!("Currently using {}", name)
+                        }
+                        name => format!("Theme '{}' not found, using Light", name),
+                    };
+                    ui.label(egui::RichText::new(current).size(12.0).color(colors.muted));
+
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button(format!("{} Reload Themes", icons::ARROWS_CLOCKWISE))
+                            .on_hover_text("Re-read theme files after editing them")
+                            .clicked()
+                        {
+                            self.custom_themes = theme::load_palettes(&theme::themes_dir());
+                            // Force re-applying in case the active theme's file changed
+                            self.applied_palette = None;
+                        }
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "Custom themes: {}/*.toml",
+                                theme::themes_dir().display()
+                            ))
+                            .size(12.0)
+                            .color(colors.muted),
+                        );
+                    });
+                });
+
+                ui.add_space(20.0);
+
+                // Text size and interface scale, applied live and saved on release
+                ui.group(|ui| {
+                    ui.label(egui::RichText::new("Text Size").strong().size(16.0));
+                    ui.add_space(5.0);
+
+                    let mut save = false;
+                    ui.horizontal(|ui| {
+                        ui.label("Font size:");
+                        let slider = ui.add(
+                            egui::Slider::new(
+                                &mut self.font_size,
+                                theme::MIN_FONT_SIZE..=theme::MAX_FONT_SIZE,
+                            )
+                            .step_by(1.0)
+                            .suffix(" pt"),
+                        );
+                        save |= slider.drag_stopped() || (slider.changed() && !slider.dragged());
+                    });
+
+                    ui.add_space(5.0);
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("Interface scale:");
+                        // Steps rather than a slider: zooming moves the slider under the pointer
+                        for scale in [0.75, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0] {
+                            let selected = (self.ui_scale - scale).abs() < 0.01;
+                            if ui
+                                .selectable_label(selected, format!("{:.0}%", scale * 100.0))
+                                .clicked()
+                                && !selected
+                            {
+                                self.ui_scale = scale;
+                                save = true;
+                            }
+                        }
+                    });
+
+                    ui.add_space(5.0);
+                    let is_default = self.font_size == theme::DEFAULT_FONT_SIZE
+                        && self.ui_scale == theme::DEFAULT_UI_SCALE;
+                    if ui
+                        .add_enabled(!is_default, egui::Button::new("Reset to defaults"))
+                        .clicked()
+                    {
+                        self.font_size = theme::DEFAULT_FONT_SIZE;
+                        self.ui_scale = theme::DEFAULT_UI_SCALE;
+                        save = true;
+                    }
+                    if save {
+                        self.save_text_size();
+                    }
+
+                    // Preview of each text style at the current size
+                    ui.add_space(10.0);
+                    egui::Frame::group(ui.style()).show(ui, |ui| {
+                        ui.label(egui::RichText::new("Heading").heading());
+                        ui.label("Body text as used in chat messages.");
+                        ui.label(egui::RichText::new("Small text for hints").small());
+                        ui.label(egui::RichText::new("let answer = 42;").monospace());
+                    });
+                });
+
+                ui.add_space(20.0);
+
+                ui.group(|ui| {
+                    ui.label(egui::RichText::new("Notifications").strong().size(16.0));
+                    ui.add_space(5.0);
+                    let toggled = ui
+                        .checkbox(
+                            &mut self.notifications_enabled,
+                            "Notify me when an answer finishes while Rustbot is in the background",
+                        )
+                        .changed();
+                    ui.label(
+                        egui::RichText::new(
+                            "Answers you asked for notify after 10 seconds or more; answers to \
+                             scripts and the control socket always do.",
+                        )
+                        .size(12.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                    );
+
+                    if toggled {
+                        let enabled = self.notifications_enabled;
+                        self.app_state
+                            .update_profile(move |profile| profile.notifications = enabled);
+                    }
+                });
+
+                ui.add_space(20.0);
+
+                ui.group(|ui| {
+                    ui.label(egui::RichText::new("Privacy").strong().size(16.0));
+                    ui.add_space(5.0);
+                    let mut changed = ui
+                        .checkbox(
+                            &mut self.privacy_mode,
+                            format!("{} Local-only mode", icons::SHIELD_CHECK),
+                        )
+                        .changed();
+                    ui.label(
+                        egui::RichText::new(
+                            "Blocks every network request except to localhost and the endpoints \
+                             below: no hosted models, no diagram or formula rendering, no \
+                             marketplace, webhooks or bots.",
+                        )
+                        .size(12.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                    );
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        ui.label("Allowed endpoints:");
+                        let response = ui.add_enabled(
+                            self.privacy_mode,
+                            egui::TextEdit::singleline(&mut self.allowed_hosts_input)
+                                .hint_text("gpu-box.lan, models.lan:8080")
+                                .desired_width(280.0),
+                        );
+                        changed |= response.lost_focus();
+                    });
+
+                    if changed {
+                        self.save_privacy_mode();
+                    }
+                });
+
+                ui.add_space(20.0);
+
+                ui.group(|ui| {
+                    ui.label(egui::RichText::new("Offline").strong().size(16.0));
+                    ui.add_space(5.0);
+                    ui.label(
+                        egui::RichText::new(
+                            "When the provider can't be reached, Rustbot answers with this \
+                             Ollama model on localhost. Leave it empty to queue messages until \
+                             the connection is back.",
+                        )
+                        .size(12.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                    );
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        ui.label("Local model:");
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut self.offline_model)
+                                .hint_text("llama3.2")
+                                .desired_width(200.0),
+                        );
+                        if response.lost_focus() {
+                            self.save_offline_model();
+                        }
+                    });
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "Status: {}",
+                            if connectivity::is_online() {
+                                "online"
+                            } else {
+                                "offline"
+                            }
+                        ))
+                        .size(12.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                    );
+                });
+
+                ui.add_space(20.0);
+
+                ui.group(|ui| {
+                    ui.label(egui::RichText::new("System Context").strong().size(16.0));
+                    ui.add_space(5.0);
+                    ui.label(
+                        egui::RichText::new(
+                            "Facts sent to the model ahead of every conversation. Turn off \
+                             anything you'd rather not share with the provider.",
+                        )
+                        .size(12.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                    );
+                    ui.add_space(5.0);
+
+                    let mut changed = false;
+                    for (label, enabled) in self.system_context.fields_mut() {
+                        changed |= ui.checkbox(enabled, label).changed();
+                    }
+
+                    ui.add_space(5.0);
+                    ui.label("Your own entries:");
+                    let mut remove = None;
+                    for (index, entry) in self.system_context.custom.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            let key = ui.add(
+                                egui::TextEdit::singleline(&mut entry.key)
+                                    .hint_text("Key, e.g. Project")
+                                    .desired_width(140.0),
+                            );
+                            let value = ui.add(
+                                egui::TextEdit::singleline(&mut entry.value)
+                                    .hint_text("Value")
+                                    .desired_width(240.0),
+                            );
+                            changed |= key.lost_focus() || value.lost_focus();
+                            if ui
+                                .small_button(icons::TRASH)
+                                .on_hover_text("Remove this entry")
+                                .clicked()
+                            {
+                                remove = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = remove {
+                        self.system_context.custom.remove(index);
+                        changed = true;
+                    }
+                    if ui.button(format!("{} Add entry", icons::PLUS)).clicked() {
+                        self.system_context
+                            .custom
+                            .push(system_context::ContextEntry::default());
+                    }
+
+                    if changed {
+                        self.save_system_context();
+                    }
+                });
+
+                ui.add_space(20.0);
+
+                ui.group(|ui| {
+                    ui.label(egui::RichText::new("Tool Results").strong().size(16.0));
+                    ui.add_space(5.0);
+                    let toggled = ui
+                        .checkbox(
+                            &mut self.injection_warnings,
+                            "Warn when a tool result contains instructions for the assistant",
+                        )
+                        .changed();
+                    ui.label(
+                        egui::RichText::new(
+                            "Tool results are always marked as untrusted for the model. With \
+                             this on, results that look like a prompt injection are also \
+                             flagged in the tool call card and in the model's context.",
+                        )
+                        .size(12.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                    );
+
+                    if toggled {
+                        let enabled = self.injection_warnings;
+                        rustbot_core::untrusted::set_scan_enabled(enabled);
+                        self.app_state
+                            .update_profile(move |profile| profile.injection_warnings = enabled);
+                    }
+                });
+
+                ui.add_space(20.0);
+
+                ui.group(|ui| {
+                    ui.label(egui::RichText::new("Daily Limits").strong().size(16.0));
+                    ui.add_space(5.0);
+                    ui.label(
+                        egui::RichText::new(
+                            "Cap how much this account can use Rustbot each day. Leave a field \
+                             empty for no limit. Spending is estimated from token counts.",
+                        )
+                        .size(12.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                    );
+                    ui.add_space(5.0);
 
-                                    ui.add_space(5.0);
+                    let limits = quota::tracker().limits();
+                    if limits.is_limited() {
+                        let today = quota::tracker().today();
+                        ui.label(format!(
+                            "Used today: {} messages, ${:.2}",
+                            today.messages, today.cost
+                        ));
+                        ui.add_space(5.0);
+                    }
 
-                                    // Action buttons
-                                    ui.horizontal(|ui| {
-                                        if !ext.metadata.repository_url.is_empty() {
-                                            if ui.button(format!("{} Repository", icons::GITHUB_LOGO)).clicked() {
-                                                ui.output_mut(|o| o.copied_text = ext.metadata.repository_url.clone());
-                                                tracing::info!("Repository URL copied to clipboard: {}", ext.metadata.repository_url);
-                                            }
-                                        }
+                    let mut save = false;
+                    egui::Grid::new("daily_limits_grid")
+                        .num_columns(2)
+                        .spacing([10.0, 6.0])
+                        .show(ui, |ui| {
+                            ui.label("Messages per day:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.quota_form.messages_per_day)
+                                    .hint_text("Unlimited")
+                                    .desired_width(120.0),
+                            );
+                            ui.end_row();
 
-                                        if ui.button(format!("{} Configure", icons::GEAR)).clicked() {
-                                            self.configuring_extension_id = Some(ext.id.clone());
-                                        }
+                            ui.label("Spending per day ($):");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.quota_form.cost_per_day)
+                                    .hint_text("Unlimited")
+                                    .desired_width(120.0),
+                            );
+                            ui.end_row();
+
+                            if limits.is_locked() {
+                                ui.label("Current PIN:");
+                                let response = ui.add(
+                                    egui::TextEdit::singleline(&mut self.quota_form.pin)
+                                        .password(true)
+                                        .desired_width(120.0),
+                                );
+                                save |= response.lost_focus()
+                                    && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                                ui.end_row();
+                            }
 
-                                        if ui
-                                            .button(
-                                                egui::RichText::new(format!("{} Uninstall", icons::TRASH))
-                                                    .color(egui::Color32::from_rgb(180, 60, 60)),
-                                            )
-                                            .clicked()
-                                        {
-                                            // Show confirmation dialog
-                                            self.uninstall_confirmation = Some((ext.id.clone(), ext.name.clone()));
-                                        }
-                                    });
+                            ui.label(if limits.is_locked() {
+                                "Change PIN:"
+                            } else {
+                                "Lock with PIN:"
+                            });
+                            ui.add_enabled(
+                                !self.quota_form.remove_pin,
+                                egui::TextEdit::singleline(&mut self.quota_form.new_pin)
+                                    .password(true)
+                                    .hint_text("Optional")
+                                    .desired_width(120.0),
+                            );
+                            ui.end_row();
+                        });
 
-                                    ui.add_space(5.0);
-                                });
+                    if limits.is_locked() {
+                        ui.checkbox(&mut self.quota_form.remove_pin, "Remove the PIN lock");
+                    }
+                    ui.label(
+                        egui::RichText::new(
+                            "With a PIN, the limits can only be changed by someone who knows it.",
+                        )
+                        .size(12.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                    );
 
-                                ui.add_space(10.0);
-                            }
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button(format!("{} Save limits", icons::FLOPPY_DISK))
+                            .clicked()
+                        {
+                            save = true;
+                        }
+                        if let Some((message, is_error)) = &self.quota_form.message {
+                            let colors = theme_colors(ui.ctx());
+                            ui.label(egui::RichText::new(message.as_str()).color(if *is_error {
+                                colors.error
+                            } else {
+                                colors.success
+                            }));
                         }
+                    });
+                    if save {
+                        self.save_quota();
                     }
-                    Err(e) => {
-                        ui.vertical_centered(|ui| {
-                            ui.add_space(40.0);
+                });
+
+                ui.add_space(20.0);
+
+                ui.group(|ui| {
+                    ui.label(egui::RichText::new("File Access").strong().size(16.0));
+                    ui.add_space(5.0);
+                    ui.label(
+                        egui::RichText::new(
+                            "Plugins ask before reading or writing a folder for the first time. \
+                             Revoke a grant to be asked again.",
+                        )
+                        .size(12.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                    );
+                    ui.add_space(5.0);
+
+                    let grants: Vec<(fs_consent::FsGrant, bool)> = self
+                        .fs_consent
+                        .saved_grants()
+                        .into_iter()
+                        .map(|grant| (grant, true))
+                        .chain(
+                            self.fs_consent
+                                .session_grants()
+                                .into_iter()
+                                .map(|grant| (grant, false)),
+                        )
+                        .collect();
+                    if grants.is_empty() {
+                        ui.label("No folders have been granted.");
+                    }
+
+                    let mut revoke = None;
+                    for (grant, always) in &grants {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{} {}", icons::FOLDER, grant.plugin));
                             ui.label(
-                                egui::RichText::new(format!("{} Failed to load extensions", icons::WARNING))
-                                    .size(16.0)
-                                    .color(egui::Color32::from_rgb(200, 80, 80)),
+                                egui::RichText::new(fs_consent::display_path(&grant.directory))
+                                    .monospace(),
                             );
-                            ui.add_space(10.0);
                             ui.label(
-                                egui::RichText::new(format!("Error: {}", e))
-                                    .size(12.0)
-                                    .color(egui::Color32::from_rgb(150, 150, 150)),
+                                egui::RichText::new(format!(
+                                    "{}, {}",
+                                    grant.access.label(),
+                                    if *always { "always" } else { "this session" }
+                                ))
+                                .size(12.0)
+                                .color(theme_colors(ui.ctx()).muted),
                             );
+                            if ui.small_button("Revoke").clicked() {
+                                revoke = Some(grant.clone());
+                            }
                         });
                     }
-                }
+                    if let Some(grant) = revoke {
+                        if let Err(e) = self.fs_consent.revoke(&grant) {
+                            tracing::warn!("Failed to revoke file access: {}", e);
+                        }
+                    }
+                });
+
+                ui.add_space(20.0);
+
+                // Settings export/import for moving to another machine or sharing with a team
+                ui.group(|ui| {
+                    ui.label(egui::RichText::new("Settings Transfer").strong().size(16.0));
+                    ui.add_space(5.0);
+                    ui.label(
+                        "Export agents, MCP plugins, system instructions and theme to a single \
+                         file. API keys and tokens are replaced with ${VAR} placeholders.",
+                    );
+                    ui.add_space(10.0);
+
+                    ui.horizontal(|ui| {
+                        ui.label("Bundle file:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.settings_bundle_path)
+                                .desired_width(320.0),
+                        );
+                    });
+                    ui.add_space(5.0);
+
+                    // One export or import at a time
+                    let ready = !self.settings_bundle_path.trim().is_empty()
+                        && self.settings_export_rx.is_none()
+                        && self.settings_import_rx.is_none();
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(
+                                ready,
+                                egui::Button::new(format!(
+                                    "{} Export settings",
+                                    icons::DOWNLOAD_SIMPLE
+                                )),
+                            )
+                            .clicked()
+                        {
+                            let path = std::path::PathBuf::from(self.settings_bundle_path.trim());
+                            self.settings_bundle_message = None;
+                            self.export_settings_bundle(path);
+                        }
+                        if ui
+                            .add_enabled(
+                                ready,
+                                egui::Button::new(format!(
+                                    "{} Import settings",
+                                    icons::UPLOAD_SIMPLE
+                                )),
+                            )
+                            .clicked()
+                        {
+                            let path = std::path::PathBuf::from(self.settings_bundle_path.trim());
+                            self.settings_bundle_message = None;
+                            self.import_settings_bundle(path);
+                        }
+                    });
+
+                    if let Some((message, is_error)) = &self.settings_bundle_message {
+                        let color = if *is_error {
+                            theme_colors(ui.ctx()).error
+                        } else {
+                            theme_colors(ui.ctx()).success
+                        };
+                        ui.add_space(5.0);
+                        ui.label(egui::RichText::new(message).size(12.0).color(color));
+                    }
+                });
+
+                ui.add_space(20.0);
+
+                // Future preferences can be added here
+                // Example: animations, message density, etc.
             });
     }
 
-    /// Render extension configuration dialog
-    fn render_extension_config_dialog(&mut self, ui: &mut egui::Ui, ext_id: &str) {
-        use crate::mcp::extensions::ExtensionRegistry;
-        use std::path::PathBuf;
-
+    /// Render the projects editor
+    ///
+    /// Each project is edited in place and written to disk with its Save
+    /// button; switching also works from the chat view.
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    pub fn render_projects_view(&mut self, ui: &mut egui::Ui) {
         egui::ScrollArea::vertical()
             .auto_shrink([false; 2])
             .show(ui, |ui| {
                 ui.add_space(20.0);
-                ui.heading(format!("{} Configure Extension", icons::GEAR));
+                ui.heading("Projects");
                 ui.add_space(10.0);
 
-                // Back button
-                if ui.button(format!("{} Back to Extensions", icons::ARROW_LEFT)).clicked() {
-                    self.configuring_extension_id = None;
-                    self.extension_config_message = None;
-                    return;
+                ui.label(
+                    "A project bundles a folder, the agents you use for it and its own \
+                     conversations. While a project is active, new chats start with its first \
+                     agent, the model is told about the folder, and History only lists the \
+                     project's conversations.",
+                );
+                ui.add_space(10.0);
+
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.new_project_name)
+                            .hint_text("Project name")
+                            .desired_width(250.0),
+                    );
+                    if ui
+                        .add_enabled(
+                            !self.new_project_name.trim().is_empty(),
+                            egui::Button::new(format!("{} Create", icons::PLUS)),
+                        )
+                        .clicked()
+                    {
+                        self.create_project();
+                    }
+                });
+
+                if let Some((message, is_error)) = &self.project_message {
+                    let color = if *is_error {
+                        theme_colors(ui.ctx()).error
+                    } else {
+                        theme_colors(ui.ctx()).success
+                    };
+                    ui.add_space(5.0);
+                    ui.label(egui::RichText::new(message).size(12.0).color(color));
                 }
 
                 ui.add_space(15.0);
 
-                // Load extension info
-                let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-                let registry_path = home_dir.join(".rustbot").join("extensions").join("registry.json");
-
-                let extension = match ExtensionRegistry::load(&registry_path) {
-                    Ok(registry) => registry.get(ext_id).cloned(),
-                    Err(_) => None,
-                };
+                if self.projects.is_empty() {
+                    ui.label(
+                        egui::RichText::new("No projects yet").color(theme_colors(ui.ctx()).muted),
+                    );
+                    return;
+                }
 
-                if let Some(ext) = extension {
-                    // Extension header
+                let agents: Vec<(String, String)> = self
+                    .agent_configs
+                    .iter()
+                    .filter(|c| c.enabled)
+                    .map(|c| (c.id.clone(), c.name.clone()))
+                    .collect();
+                let mut save = None;
+                let mut delete = None;
+                let mut switch = None;
+                for (i, project) in self.projects.iter_mut().enumerate() {
+                    let active = self.active_project.as_ref() == Some(&project.id);
                     ui.group(|ui| {
-                        ui.set_min_width(ui.available_width());
-                        ui.add_space(10.0);
-                        ui.label(egui::RichText::new(&ext.name).size(18.0).strong());
-                        ui.label(&ext.description);
-                        ui.add_space(10.0);
-                    });
-
-                    ui.add_space(15.0);
-
-                    // Show configuration message if any
-                    if let Some((message, is_error)) = &self.extension_config_message {
-                        let color = if *is_error {
-                            egui::Color32::from_rgb(200, 80, 80)
-                        } else {
-                            egui::Color32::from_rgb(60, 150, 60)
-                        };
-                        ui.label(egui::RichText::new(message).color(color));
-                        ui.add_space(10.0);
-                    }
-
-                    // Agent configuration section
-                    ui.label(egui::RichText::new("Enable for Agents:").size(16.0).strong());
-                    ui.add_space(10.0);
-
-                    // Iterate through agents and show checkboxes
-                    let mut changes_made = false;
-                    for (idx, agent_config) in self.agent_configs.iter_mut().enumerate() {
-                        let mut is_enabled = agent_config.mcp_extensions.contains(&ext.id);
-
                         ui.horizontal(|ui| {
-                            if ui.checkbox(&mut is_enabled, "").changed() {
-                                if is_enabled {
-                                    // Add extension to agent
-                                    if !agent_config.mcp_extensions.contains(&ext.id) {
-                                        agent_config.mcp_extensions.push(ext.id.clone());
-                                        changes_made = true;
-                                    }
-                                } else {
-                                    // Remove extension from agent
-                                    agent_config.mcp_extensions.retain(|id| id != &ext.id);
-                                    changes_made = true;
-                                }
+                            ui.label(egui::RichText::new(&project.name).strong());
+                            if active {
+                                ui.label(
+                                    egui::RichText::new("Active")
+                                        .size(12.0)
+                                        .color(theme_colors(ui.ctx()).success),
+                                );
                             }
-
-                            ui.label(egui::RichText::new(&agent_config.name).size(14.0));
                         });
 
-                        ui.add_space(5.0);
-                    }
-
-                    // Save button
-                    ui.add_space(15.0);
-                    if ui.button(format!("{} Save Configuration", icons::FLOPPY_DISK)).clicked() {
-                        // Save all modified agent configs
-                        let mut save_errors = Vec::new();
-                        let mut saved_count = 0;
+                        egui::Grid::new(("project", i))
+                            .num_columns(2)
+                            .spacing([10.0, 6.0])
+                            .show(ui, |ui| {
+                                ui.label("Name:");
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut project.name)
+                                        .desired_width(300.0),
+                                );
+                                ui.end_row();
 
-                        for agent_config in &self.agent_configs {
-                            // Build path to agent JSON file
-                            let agent_path = PathBuf::from("agents")
-                                .join("presets")
-                                .join(format!("{}.json", agent_config.name));
+                                ui.label("Folder:");
+                                let mut folder = project
+                                    .folder
+                                    .as_ref()
+                                    .map(|f| f.display().to_string())
+                                    .unwrap_or_default();
+                                let edit = ui.add(
+                                    egui::TextEdit::singleline(&mut folder)
+                                        .hint_text("~/Documents/thesis")
+                                        .desired_width(400.0),
+                                );
+                                if edit.changed() {
+                                    project.folder = (!folder.trim().is_empty())
+                                        .then(|| std::path::PathBuf::from(folder));
+                                }
+                                ui.end_row();
 
-                            // Save the updated config
-                            match serde_json::to_string_pretty(&agent_config) {
-                                Ok(json) => {
-                                    if let Err(e) = std::fs::write(&agent_path, json) {
-                                        save_errors.push(format!("{}: {}", agent_config.name, e));
+                                ui.label("Git:");
+                                ui.vertical(|ui| {
+                                    let repository = project
+                                        .folder
+                                        .as_ref()
+                                        .is_some_and(|f| git_tools::is_repository(f));
+                                    ui.add_enabled(
+                                        repository,
+                                        egui::Checkbox::new(
+                                            &mut project.allow_git_commits,
+                                            "Let agents commit staged changes",
+                                        ),
+                                    );
+                                    let note = if repository {
+                                        "Agents can read the status, diff and log. You approve \
+                                         every commit; /commit drafts a message."
                                     } else {
-                                        saved_count += 1;
+                                        "The git tools are offered when the folder is a git \
+                                         repository."
+                                    };
+                                    ui.label(
+                                        egui::RichText::new(note)
+                                            .size(12.0)
+                                            .color(theme_colors(ui.ctx()).muted),
+                                    );
+                                });
+                                ui.end_row();
+
+                                ui.label("Agents:");
+                                ui.vertical(|ui| {
+                                    for (id, name) in &agents {
+                                        let mut preferred = project.preferred_agents.contains(id);
+                                        if ui.checkbox(&mut preferred, name).changed() {
+                                            if preferred {
+                                                project.preferred_agents.push(id.clone());
+                                            } else {
+                                                project.preferred_agents.retain(|a| a != id);
+                                            }
+                                        }
                                     }
-                                }
-                                Err(e) => {
-                                    save_errors.push(format!("{}: {}", agent_config.name, e));
-                                }
-                            }
-                        }
+                                    let default = project
+                                        .default_agent()
+                                        .and_then(|id| agents.iter().find(|(a, _)| a == id))
+                                        .map_or("the agent already selected", |(_, name)| {
+                                            name.as_str()
+                                        });
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "New chats start with {}",
+                                            default
+                                        ))
+                                        .size(12.0)
+                                        .color(theme_colors(ui.ctx()).muted),
+                                    );
+                                });
+                                ui.end_row();
+                            });
 
-                        if save_errors.is_empty() {
-                            self.extension_config_message = Some((
-                                format!("✓ Configuration saved! {} agent(s) updated. Tools will be available instantly.", saved_count),
-                                false,
-                            ));
-                            // Reload agent configs to pick up changes
-                            // This will trigger tool reload in the next update cycle
-                        } else {
-                            self.extension_config_message = Some((
-                                format!("✗ Failed to save some configs: {}", save_errors.join(", ")),
-                                true,
-                            ));
-                        }
-                    }
+                        ui.horizontal(|ui| {
+                            if ui.button(format!("{} Save", icons::FLOPPY_DISK)).clicked() {
+                                save = Some(i);
+                            }
+                            if !active
+                                && ui
+                                    .add_enabled(
+                                        !self.is_waiting,
+                                        egui::Button::new(format!(
+                                            "{} Switch to",
+                                            icons::FOLDER_OPEN
+                                        )),
+                                    )
+                                    .clicked()
+                            {
+                                switch = Some(project.id.clone());
+                            }
+                            if ui.button(format!("{} Delete", icons::TRASH)).clicked() {
+                                delete = Some(i);
+                            }
+                        });
+                    });
+                    ui.add_space(5.0);
+                }
 
-                } else {
-                    ui.label(
-                        egui::RichText::new("Extension not found")
-                            .size(14.0)
-                            .color(egui::Color32::from_rgb(200, 80, 80)),
-                    );
+                if let Some(i) = save {
+                    self.save_project(i);
+                }
+                if let Some(id) = switch {
+                    self.switch_project(Some(id));
+                }
+                if let Some(i) = delete {
+                    self.delete_project(i);
                 }
             });
     }
 
-    /// Render uninstall confirmation dialog
-    fn render_uninstall_confirmation_dialog(
-        &mut self,
-        ui: &mut egui::Ui,
-        ext_id: &str,
-        ext_name: &str,
-    ) {
+    /// Render the backups view with manual backup and restore picker
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    pub fn render_backups_view(&mut self, ui: &mut egui::Ui) {
         egui::ScrollArea::vertical()
             .auto_shrink([false; 2])
             .show(ui, |ui| {
                 ui.add_space(20.0);
-                ui.heading(format!("{} Uninstall Extension", icons::WARNING));
-                ui.add_space(20.0);
+                ui.heading("Backups");
+                ui.add_space(10.0);
 
-                // Show uninstall message if any
-                if let Some((message, is_error)) = &self.uninstall_message {
+                ui.label(
+                    "Snapshots of agents, MCP plugins, system instructions, profile and \
+                     conversations. Schedule and retention are set in ~/.rustbot/backup.json.",
+                );
+                ui.add_space(10.0);
+
+                // One backup or restore at a time
+                let idle = self.backup_rx.is_none();
+                if ui
+                    .add_enabled(
+                        idle,
+                        egui::Button::new(format!("{} Back up now", icons::FLOPPY_DISK)),
+                    )
+                    .clicked()
+                {
+                    self.create_backup();
+                }
+
+                if let Some((message, is_error)) = &self.backup_message {
                     let color = if *is_error {
-                        egui::Color32::from_rgb(200, 80, 80)
+                        theme_colors(ui.ctx()).error
                     } else {
-                        egui::Color32::from_rgb(60, 150, 60)
+                        theme_colors(ui.ctx()).success
                     };
-                    ui.label(egui::RichText::new(message).size(14.0).color(color));
-                    ui.add_space(15.0);
-
-                    // If successful uninstall, show back button
-                    if !is_error {
-                        if ui
-                            .button(format!("{} Back to Extensions", icons::ARROW_LEFT))
-                            .clicked()
-                        {
-                            self.uninstall_confirmation = None;
-                            self.uninstall_message = None;
-                        }
-                        return;
-                    }
+                    ui.add_space(5.0);
+                    ui.label(egui::RichText::new(message).size(12.0).color(color));
                 }
 
-                // Warning box
-                ui.group(|ui| {
-                    ui.set_min_width(ui.available_width());
-                    ui.add_space(10.0);
+                ui.add_space(15.0);
 
+                if self.backups.is_empty() {
                     ui.label(
-                        egui::RichText::new(format!(
-                            "{} Warning: This action cannot be undone",
-                            icons::WARNING
-                        ))
-                        .size(16.0)
-                        .strong()
-                        .color(egui::Color32::from_rgb(200, 120, 40)),
+                        egui::RichText::new("No backups yet").color(theme_colors(ui.ctx()).muted),
                     );
+                    return;
+                }
 
-                    ui.add_space(10.0);
+                let mut restore_id = None;
+                for backup in &self.backups {
+                    ui.group(|ui| {
+                        ui.horizontal(|ui| {
+                            ui.vertical(|ui| {
+                                ui.label(
+                                    egui::RichText::new(
+                                        backup
+                                            .created_at
+                                            .with_timezone(&chrono::Local)
+                                            .format("%Y-%m-%d %H:%M:%S")
+                                            .to_string(),
+                                    )
+                                    .strong(),
+                                );
+                                ui.label(
+                                    egui::RichText::new(format!(
+                                        "{} · {} items · {:.1} KB",
+                                        backup.reason,
+                                        backup.items.len(),
+                                        backup.size_bytes as f64 / 1024.0
+                                    ))
+                                    .size(12.0)
+                                    .color(theme_colors(ui.ctx()).muted),
+                                );
+                            });
+
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    if ui
+                                        .add_enabled(
+                                            idle,
+                                            egui::Button::new(format!(
+                                                "{} Restore",
+                                                icons::CLOCK_COUNTER_CLOCKWISE
+                                            )),
+                                        )
+                                        .on_hover_text(
+                                            "Replace current settings and conversations with this \
+                                         backup (the current state is backed up first)",
+                                        )
+                                        .clicked()
+                                    {
+                                        restore_id = Some(backup.id.clone());
+                                    }
+                                },
+                            );
+                        });
+                    });
+                    ui.add_space(5.0);
+                }
+
+                if let Some(id) = restore_id {
+                    self.restore_backup(&id);
+                }
+            });
+    }
+
+    /// Render the audit log of security-relevant changes, newest first
+    ///
+    /// The hash chain is verified when the tab is opened and on demand; a
+    /// broken chain means the file was edited outside Rustbot.
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    pub fn render_audit_view(&mut self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                ui.add_space(20.0);
+                ui.heading("Audit Log");
+                ui.add_space(10.0);
+
+                ui.label(
+                    "Extension installs, configuration changes, key updates and file access \
+                     grants. Each entry is linked to the one before it by a SHA-256 hash.",
+                );
+                ui.add_space(10.0);
+
+                if ui
+                    .button(format!("{} Verify again", icons::SHIELD_CHECK))
+                    .clicked()
+                {
+                    self.refresh_audit_log();
+                }
 
+                let colors = theme_colors(ui.ctx());
+                let (message, color) = match &self.audit_status {
+                    None => (String::new(), colors.muted),
+                    Some(Ok(audit::ChainStatus::Intact { entries })) => (
+                        format!(
+                            "{} Chain intact: {} entries, head {}",
+                            icons::CHECK_CIRCLE,
+                            entries,
+                            self.audit_entries
+                                .last()
+                                .map(|entry| short_hash(&entry.hash))
+                                .unwrap_or("none")
+                        ),
+                        colors.success,
+                    ),
+                    Some(Ok(audit::ChainStatus::Broken { line, reason })) => (
+                        format!(
+                            "{} Chain broken at line {}: {}",
+                            icons::WARNING,
+                            line,
+                            reason
+                        ),
+                        colors.error,
+                    ),
+                    Some(Err(e)) => (format!("Couldn't read the audit log: {}", e), colors.error),
+                };
+                if !message.is_empty() {
+                    ui.add_space(5.0);
+                    ui.label(egui::RichText::new(message).size(12.0).color(color));
+                }
+                if let Some(log) = audit::global() {
                     ui.label(
-                        egui::RichText::new(format!("Extension: {}", ext_name))
-                            .size(14.0)
-                            .strong(),
+                        egui::RichText::new(log.path().display().to_string())
+                            .size(12.0)
+                            .color(colors.muted),
                     );
-                    ui.label(egui::RichText::new(format!("ID: {}", ext_id)).size(12.0));
+                }
 
-                    ui.add_space(10.0);
+                ui.add_space(15.0);
 
-                    ui.label("This will:");
-                    ui.label("  • Remove the extension from your system");
-                    ui.label("  • Remove it from all agent configurations");
-                    ui.label("  • Remove MCP configuration entries");
+                if self.audit_entries.is_empty() {
+                    ui.label(egui::RichText::new("Nothing recorded yet").color(colors.muted));
+                    return;
+                }
 
-                    ui.add_space(10.0);
-                });
+                egui::Grid::new("audit_entries")
+                    .num_columns(4)
+                    .spacing([15.0, 6.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for entry in self.audit_entries.iter().rev() {
+                            ui.label(
+                                entry
+                                    .timestamp
+                                    .with_timezone(&chrono::Local)
+                                    .format("%Y-%m-%d %H:%M:%S")
+                                    .to_string(),
+                            );
+                            ui.label(egui::RichText::new(entry.action.label()).strong());
+                            ui.label(&entry.detail);
+                            ui.label(
+                                egui::RichText::new(short_hash(&entry.hash))
+                                    .monospace()
+                                    .color(colors.muted),
+                            )
+                            .on_hover_text(&entry.hash);
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
 
+    /// Render the script manager with per-script status, hooks and tools
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    pub fn render_scripts_view(&mut self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
                 ui.add_space(20.0);
+                ui.heading("Scripts");
+                ui.add_space(10.0);
 
-                // Action buttons
-                ui.horizontal(|ui| {
-                    if ui.button(format!("{} Cancel", icons::X)).clicked() {
-                        self.uninstall_confirmation = None;
-                        self.uninstall_message = None;
-                    }
+                ui.label(format!(
+                    "Rhai scripts in {} can react to events, send messages and register \
+                     tools for the assistant. Edit the files, then reload.",
+                    self.script_host.dir().display()
+                ));
+                ui.add_space(10.0);
 
-                    ui.add_space(10.0);
+                if ui
+                    .button(format!("{} Reload scripts", icons::ARROWS_CLOCKWISE))
+                    .clicked()
+                {
+                    self.reload_scripts();
+                }
 
-                    if ui
-                        .button(
-                            egui::RichText::new(format!("{} Uninstall", icons::TRASH))
-                                .color(egui::Color32::from_rgb(180, 60, 60)),
+                if let Some((message, is_error)) = &self.script_message {
+                    let color = if *is_error {
+                        theme_colors(ui.ctx()).error
+                    } else {
+                        theme_colors(ui.ctx()).success
+                    };
+                    ui.add_space(5.0);
+                    ui.label(egui::RichText::new(message).size(12.0).color(color));
+                }
+
+                ui.add_space(15.0);
+
+                if self.scripts.is_empty() {
+                    ui.label(
+                        egui::RichText::new(
+                            "No scripts found (add .rhai files to the folder above)",
                         )
-                        .clicked()
-                    {
-                        // Perform uninstall
-                        match self.perform_uninstall(ext_id) {
-                            Ok(()) => {
-                                self.uninstall_message = Some((
-                                    format!("✓ Extension '{}' uninstalled successfully", ext_name),
-                                    false,
-                                ));
-                            }
-                            Err(e) => {
-                                self.uninstall_message =
-                                    Some((format!("✗ Failed to uninstall extension: {}", e), true));
+                        .color(theme_colors(ui.ctx()).muted),
+                    );
+                    return;
+                }
+
+                let mut toggle = None;
+                for script in &self.scripts {
+                    ui.group(|ui| {
+                        ui.horizontal(|ui| {
+                            let mut enabled = script.enabled;
+                            if ui.checkbox(&mut enabled, "").changed() {
+                                toggle = Some((script.name.clone(), enabled));
                             }
-                        }
-                    }
-                });
-            });
-    }
 
-    /// Perform extension uninstall
-    ///
-    /// Removes the extension from:
-    /// 1. Extension registry (~/.rustbot/extensions/registry.json)
-    /// 2. Global MCP config (~/.rustbot/mcp_config.json) if present
-    /// 3. All agent-specific MCP configs (~/.rustbot/mcp_configs/*.json)
-    /// 4. All agent presets (agents/presets/*.json) mcp_extensions field
-    fn perform_uninstall(&mut self, extension_id: &str) -> anyhow::Result<()> {
-        use crate::mcp::extensions::ExtensionRegistry;
-        use std::path::PathBuf;
+                            ui.vertical(|ui| {
+                                ui.label(egui::RichText::new(&script.name).strong());
 
-        let home_dir = dirs::home_dir()
-            .ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))?;
+                                let summary = if !script.enabled {
+                                    "Disabled".to_string()
+                                } else {
+                                    let hooks = if script.hooks.is_empty() {
+                                        "no hooks".to_string()
+                                    } else {
+                                        script.hooks.join(", ")
+                                    };
+                                    format!("{} · {} tools", hooks, script.tools.len())
+                                };
+                                ui.label(
+                                    egui::RichText::new(summary)
+                                        .size(12.0)
+                                        .color(theme_colors(ui.ctx()).muted),
+                                );
 
-        // 1. Remove from extension registry
-        let registry_path = home_dir
-            .join(".rustbot")
-            .join("extensions")
-            .join("registry.json");
+                                for tool in &script.tools {
+                                    ui.label(
+                                        egui::RichText::new(format!("{} {}", icons::WRENCH, tool))
+                                            .size(12.0)
+                                            .monospace(),
+                                    );
+                                }
 
-        let mut registry = ExtensionRegistry::load(&registry_path)?;
-        if registry.uninstall(extension_id).is_none() {
-            return Err(anyhow::anyhow!(
-                "Extension '{}' not found in registry",
-                extension_id
-            ));
-        }
-        registry.save(&registry_path)?;
-        tracing::info!("✓ Removed extension '{}' from registry", extension_id);
+                                if let Some(error) = &script.error {
+                                    ui.label(
+                                        egui::RichText::new(error)
+                                            .size(12.0)
+                                            .color(theme_colors(ui.ctx()).error),
+                                    );
+                                }
+                            });
+                        });
+                    });
+                    ui.add_space(5.0);
+                }
+
+                if let Some((name, enabled)) = toggle {
+                    self.set_script_enabled(&name, enabled);
+                }
+            });
+    }
+
+    /// Render the WASM tool manager with each module's grants and errors
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    pub fn render_wasm_tools_view(&mut self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                ui.add_space(20.0);
+                ui.heading("WASM Tools");
+                ui.add_space(10.0);
 
-        // 2. Remove from global MCP config if it exists
-        let global_config_path = home_dir.join(".rustbot").join("mcp_config.json");
-        if global_config_path.exists() {
-            use crate::mcp::config::McpConfig;
-            let mut config = McpConfig::load_from_file(&global_config_path)?;
-            if config.remove_extension(extension_id) {
-                config.save_to_file(&global_config_path)?;
-                tracing::info!("✓ Removed extension from global MCP config");
-            }
-        }
+                let Some(host) = &self.wasm_tools else {
+                    ui.label(
+                        egui::RichText::new(
+                            "The WebAssembly runtime isn't available on this system.",
+                        )
+                        .color(theme_colors(ui.ctx()).error),
+                    );
+                    return;
+                };
+                ui.label(format!(
+                    "WebAssembly modules in {} become tools for the assistant. Each needs a \
+                     .toml manifest next to it with a description, parameters and the \
+                     capabilities it may use; modules run sandboxed, with no other file or \
+                     network access.",
+                    host.dir().display()
+                ));
+                ui.add_space(10.0);
 
-        // 3. Remove from all agent-specific MCP configs
-        let mcp_configs_dir = home_dir.join(".rustbot").join("mcp_configs");
-        if mcp_configs_dir.exists() {
-            use crate::mcp::config::McpConfig;
-            for entry in std::fs::read_dir(&mcp_configs_dir)? {
-                let path = entry?.path();
-                if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                    let mut config = McpConfig::load_from_file(&path)?;
-                    if config.remove_extension(extension_id) {
-                        config.save_to_file(&path)?;
-                        tracing::info!("✓ Removed extension from {:?}", path.file_name());
-                    }
+                if ui
+                    .button(format!("{} Reload tools", icons::ARROWS_CLOCKWISE))
+                    .clicked()
+                {
+                    self.reload_wasm_tools();
                 }
-            }
-        }
 
-        // 4. Remove from all agent preset configs (agents/presets/*.json)
-        for agent_config in &mut self.agent_configs {
-            if agent_config
-                .mcp_extensions
-                .contains(&extension_id.to_string())
-            {
-                agent_config.mcp_extensions.retain(|id| id != extension_id);
+                if let Some((message, is_error)) = &self.wasm_tool_message {
+                    let color = if *is_error {
+                        theme_colors(ui.ctx()).error
+                    } else {
+                        theme_colors(ui.ctx()).success
+                    };
+                    ui.add_space(5.0);
+                    ui.label(egui::RichText::new(message).size(12.0).color(color));
+                }
 
-                // Save the updated agent config
-                let agent_path = PathBuf::from("agents")
-                    .join("presets")
-                    .join(format!("{}.json", agent_config.name));
+                ui.add_space(15.0);
 
-                let json = serde_json::to_string_pretty(&agent_config)?;
-                std::fs::write(&agent_path, json)?;
-                tracing::info!("✓ Removed extension from agent '{}'", agent_config.name);
-            }
-        }
+                if self.wasm_tool_list.is_empty() {
+                    ui.label(
+                        egui::RichText::new(
+                            "No tools found (add .wasm modules to the folder above)",
+                        )
+                        .color(theme_colors(ui.ctx()).muted),
+                    );
+                    return;
+                }
 
-        Ok(())
+                for tool in &self.wasm_tool_list {
+                    ui.group(|ui| {
+                        ui.set_width(ui.available_width());
+                        ui.label(
+                            egui::RichText::new(format!("{} wasm:{}", icons::WRENCH, tool.name))
+                                .strong()
+                                .monospace(),
+                        );
+                        if !tool.description.is_empty() {
+                            ui.label(egui::RichText::new(&tool.description).size(12.0));
+                        }
+
+                        let grants = if tool.capabilities.is_empty() {
+                            "No capabilities (computation only)".to_string()
+                        } else {
+                            format!("Can: {}", tool.capabilities.join(" · "))
+                        };
+                        ui.label(
+                            egui::RichText::new(grants)
+                                .size(12.0)
+                                .color(theme_colors(ui.ctx()).muted),
+                        );
+
+                        if let Some(error) = &tool.error {
+                            ui.label(
+                                egui::RichText::new(error)
+                                    .size(12.0)
+                                    .color(theme_colors(ui.ctx()).error),
+                            );
+                        }
+                    });
+                    ui.add_space(5.0);
+                }
+            });
     }
 
-    /// Render the agents management view
-    ///
-    /// Displays all configured agents and allows:
-    /// - Viewing agent details (name, ID, model, web search capability)
-    /// - Setting the active agent
-    /// - Editing agent configuration (instructions, personality, model)
+    /// Render the native plugin manager: enable plugins, see their tools and
+    /// edit the settings they declare
     ///
     /// # Arguments
     /// * `ui` - The egui UI context for rendering
-    pub fn render_agents_view(&mut self, ui: &mut egui::Ui) {
-        // Use scroll area for agents
+    pub fn render_native_plugins_view(&mut self, ui: &mut egui::Ui) {
         egui::ScrollArea::vertical()
             .auto_shrink([false; 2])
             .show(ui, |ui| {
                 ui.add_space(20.0);
-                ui.heading("Agents");
+                ui.heading("Native Plugins");
                 ui.add_space(10.0);
 
-                ui.label("Manage AI agents with specialized capabilities and instructions:");
-                ui.add_space(15.0);
-
-                // Agent list - show in list view
-                ui.label(egui::RichText::new("Available Agents:").strong());
+                ui.label(format!(
+                    "Dynamic libraries in {} built with the rustbot-plugin SDK can add tools, \
+                     react to events and bring their own settings.",
+                    self.native_plugins.dir().display()
+                ));
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{} Plugins run inside Rustbot with your permissions. Only enable \
+                         plugins you trust; a disabled plugin stays in memory until restart.",
+                        icons::WARNING
+                    ))
+                    .color(theme_colors(ui.ctx()).warning),
+                );
                 ui.add_space(10.0);
 
-                // Display each agent in a compact list format
-                for (index, config) in self.agent_configs.iter_mut().enumerate() {
-                    ui.group(|ui| {
-                        ui.horizontal(|ui| {
-                            // Agent icon and name
-                            let status_color = if config.is_primary {
-                                egui::Color32::from_rgb(60, 150, 60) // Green for primary
-                            } else if config.enabled {
-                                egui::Color32::from_rgb(100, 150, 200) // Blue for enabled
-                            } else {
-                                egui::Color32::from_rgb(100, 100, 100) // Gray for disabled
-                            };
-
-                            // Icon based on agent type
-                            let icon = if config.is_primary {
-                                format!("{} {}", icons::STAR, config.name)
-                            } else if config.web_search_enabled {
-                                format!("{} {}", icons::MAGNIFYING_GLASS, config.name)
-                            } else {
-                                format!("{} {}", icons::ROBOT, config.name)
-                            };
+                if let Some((message, is_error)) = &self.native_plugin_message {
+                    let color = if *is_error {
+                        theme_colors(ui.ctx()).error
+                    } else {
+                        theme_colors(ui.ctx()).success
+                    };
+                    ui.label(egui::RichText::new(message).size(12.0).color(color));
+                    ui.add_space(5.0);
+                }
 
-                            ui.label(egui::RichText::new(&icon).strong().size(16.0));
+                if self.native_plugin_list.is_empty() {
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "No plugins found (add .{} libraries to the folder above)",
+                            std::env::consts::DLL_EXTENSION
+                        ))
+                        .color(theme_colors(ui.ctx()).muted),
+                    );
+                    return;
+                }
 
-                            // Status indicator
-                            if config.is_primary {
-                                ui.label(
-                                    egui::RichText::new("● Primary")
-                                        .size(12.0)
-                                        .color(status_color),
-                                );
-                            } else if config.enabled {
-                                ui.label(
-                                    egui::RichText::new("✓ Enabled")
-                                        .size(12.0)
-                                        .color(status_color),
-                                );
-                            } else {
+                let mut toggle = None;
+                let mut save = None;
+                for (index, plugin) in self.native_plugin_list.iter_mut().enumerate() {
+                    ui.group(|ui| {
+                        ui.set_width(ui.available_width());
+                        ui.horizontal(|ui| {
+                            let mut enabled = plugin.enabled;
+                            if ui.checkbox(&mut enabled, "").changed() {
+                                toggle = Some((plugin.name.clone(), enabled));
+                            }
+                            ui.label(egui::RichText::new(&plugin.name).strong());
+                            if let Some(manifest) = &plugin.manifest {
                                 ui.label(
-                                    egui::RichText::new("○ Disabled")
+                                    egui::RichText::new(format!("v{}", manifest.version))
                                         .size(12.0)
-                                        .color(status_color),
+                                        .color(theme_colors(ui.ctx()).muted),
                                 );
                             }
+                        });
 
-                            ui.with_layout(
-                                egui::Layout::right_to_left(egui::Align::Center),
-                                |ui| {
-                                    // Edit button (for all agents)
-                                    if ui
-                                        .button(format!("{} Edit", icons::PENCIL_SIMPLE))
-                                        .clicked()
-                                    {
-                                        self.selected_agent_index = Some(index);
+                        if let Some(error) = &plugin.error {
+                            ui.label(
+                                egui::RichText::new(error)
+                                    .size(12.0)
+                                    .color(theme_colors(ui.ctx()).error),
+                            );
+                        }
+                        let Some(manifest) = &plugin.manifest else {
+                            return;
+                        };
+                        ui.label(egui::RichText::new(&manifest.description).size(12.0));
+                        for tool in &manifest.tools {
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "{} native:{}:{}",
+                                    icons::WRENCH,
+                                    plugin.name,
+                                    tool.name
+                                ))
+                                .size(12.0)
+                                .monospace(),
+                            );
+                        }
+                        if !manifest.events.is_empty() {
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "Listens to: {}",
+                                    manifest.events.join(", ")
+                                ))
+                                .size(12.0)
+                                .color(theme_colors(ui.ctx()).muted),
+                            );
+                        }
+
+                        if manifest.settings.is_empty() {
+                            return;
+                        }
+                        ui.add_space(5.0);
+                        egui::Grid::new(format!("native_plugin_settings_{}", index))
+                            .num_columns(2)
+                            .show(ui, |ui| {
+                                for spec in &manifest.settings {
+                                    let label = ui.label(&spec.label);
+                                    if !spec.help.is_empty() {
+                                        label.on_hover_text(&spec.help);
                                     }
+                                    let value = plugin
+                                        .values
+                                        .entry(spec.key.clone())
+                                        .or_insert_with(|| spec.default.clone());
+                                    render_plugin_setting(ui, spec.kind, value);
+                                    ui.end_row();
+                                }
+                            });
+                        if ui.button("Save settings").clicked() {
+                            save = Some(index);
+                        }
+                    });
+                    ui.add_space(5.0);
+                }
 
-                                    // Enable/Disable toggle (only for non-primary agents)
-                                    if !config.is_primary {
-                                        let toggle_text = if config.enabled {
-                                            format!("{} Disable", icons::TOGGLE_RIGHT)
-                                        } else {
-                                            format!("{} Enable", icons::TOGGLE_LEFT)
-                                        };
+                if let Some((name, enabled)) = toggle {
+                    self.set_native_plugin_enabled(&name, enabled);
+                }
+                if let Some(index) = save {
+                    self.save_native_plugin_settings(index);
+                }
+            });
+    }
 
-                                        if ui.button(toggle_text).clicked() {
-                                            config.enabled = !config.enabled;
-                                            // TODO: Persist this change and update the agent in the API
-                                        }
-                                    }
-                                },
-                            );
-                        });
+    /// Render the calendar connection settings
+    ///
+    /// CalDAV accounts are saved directly; Google accounts are connected by
+    /// signing in through the browser.
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    pub fn render_calendar_view(&mut self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                ui.add_space(20.0);
+                ui.heading("Calendar");
+                ui.add_space(10.0);
 
-                        ui.add_space(3.0);
+                ui.label(
+                    "Lets the assistant look up your schedule and create events. \
+                     Passwords and tokens are stored in the system keychain.",
+                );
+                ui.add_space(10.0);
 
-                        // Compact info line
-                        ui.horizontal(|ui| {
-                            ui.add_space(20.0); // Indent
-                            let role = if config.is_primary {
-                                "Primary"
-                            } else {
-                                "Specialist"
-                            };
-                            ui.label(
-                                egui::RichText::new(format!(
-                                    "{} • Model: {} • Web Search: {}",
-                                    role,
-                                    config.model.split('/').last().unwrap_or(&config.model),
-                                    if config.web_search_enabled {
-                                        "✓"
-                                    } else {
-                                        "✗"
-                                    }
-                                ))
-                                .size(11.0)
-                                .color(egui::Color32::from_rgb(100, 100, 100)),
+                match &self.calendar {
+                    Some(calendar) => ui.label(
+                        egui::RichText::new(format!(
+                            "{} Connected: {}",
+                            icons::CHECK_CIRCLE,
+                            calendar.label()
+                        ))
+                        .color(theme_colors(ui.ctx()).success),
+                    ),
+                    None => ui.label(
+                        egui::RichText::new(format!("{} Not connected", icons::CALENDAR))
+                            .color(theme_colors(ui.ctx()).muted),
+                    ),
+                };
+                ui.add_space(15.0);
+
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.calendar_form.google, false, "CalDAV");
+                    ui.radio_value(&mut self.calendar_form.google, true, "Google Calendar");
+                });
+                ui.add_space(10.0);
+
+                let form = &mut self.calendar_form;
+                egui::Grid::new("calendar_settings")
+                    .num_columns(2)
+                    .spacing([10.0, 6.0])
+                    .show(ui, |ui| {
+                        if form.google {
+                            ui.label("Client ID:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut form.google_client_id)
+                                    .desired_width(400.0),
                             );
-                        });
+                            ui.end_row();
+                            ui.label("Client secret:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut form.google_client_secret)
+                                    .password(true)
+                                    .hint_text("leave empty to keep the stored secret")
+                                    .desired_width(400.0),
+                            );
+                            ui.end_row();
+                            ui.label("Calendar ID:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut form.google_calendar_id)
+                                    .desired_width(400.0),
+                            );
+                            ui.end_row();
+                        } else {
+                            ui.label("Calendar URL:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut form.caldav_url)
+                                    .hint_text("https://caldav.example.com/calendars/me/personal/")
+                                    .desired_width(400.0),
+                            );
+                            ui.end_row();
+                            ui.label("Username:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut form.caldav_username)
+                                    .desired_width(400.0),
+                            );
+                            ui.end_row();
+                            ui.label("Password:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut form.caldav_password)
+                                    .password(true)
+                                    .hint_text("leave empty to keep the stored password")
+                                    .desired_width(400.0),
+                            );
+                            ui.end_row();
+                        }
                     });
 
-                    ui.add_space(10.0);
+                if self.calendar_form.google {
+                    ui.add_space(5.0);
+                    ui.label(
+                        egui::RichText::new(
+                            "Create an OAuth client of type \"Desktop app\" in the Google Cloud \
+                             console and enable the Google Calendar API for its project.",
+                        )
+                        .size(12.0)
+                        .color(theme_colors(ui.ctx()).muted),
+                    );
                 }
+                ui.add_space(10.0);
 
-                ui.add_space(15.0);
-
-                // Agent editing section
-                if let Some(index) = self.selected_agent_index {
-                    if let Some(config) = self.agent_configs.get_mut(index) {
-                        ui.separator();
-                        ui.add_space(15.0);
+                ui.horizontal(|ui| {
+                    if self.calendar_form.google {
+                        let waiting = self.calendar_auth_rx.is_some();
+                        if ui
+                            .add_enabled(
+                                !waiting,
+                                egui::Button::new(format!("{} Sign in with Google", icons::GLOBE)),
+                            )
+                            .clicked()
+                        {
+                            self.start_google_sign_in(ui.ctx());
+                        }
+                    } else if ui.button(format!("{} Save", icons::FLOPPY_DISK)).clicked() {
+                        self.save_calendar_settings();
+                    }
 
-                        ui.heading(format!("Edit Agent: {}", config.name));
-                        ui.add_space(10.0);
+                    if self.calendar.is_some()
+                        && ui.button(format!("{} Disconnect", icons::TRASH)).clicked()
+                    {
+                        self.disconnect_calendar();
+                    }
+                });
 
-                        // Agent name
-                        ui.label(egui::RichText::new("Agent Name:").strong());
-                        ui.add_space(5.0);
-                        ui.text_edit_singleline(&mut config.name);
-                        ui.add_space(10.0);
+                if let Some((message, is_error)) = &self.calendar_message {
+                    let color = if *is_error {
+                        theme_colors(ui.ctx()).error
+                    } else {
+                        theme_colors(ui.ctx()).success
+                    };
+                    ui.add_space(5.0);
+                    ui.label(egui::RichText::new(message).size(12.0).color(color));
+                }
+            });
+    }
 
-                        // Agent instructions
-                        ui.label(egui::RichText::new("Agent Instructions:").strong());
-                        ui.label("What this agent does and how it should behave:");
-                        ui.add_space(5.0);
-                        ui.add_sized(
-                            [ui.available_width() - 20.0, 150.0],
-                            egui::TextEdit::multiline(&mut config.instructions)
-                                .hint_text("Enter agent-specific instructions...")
-                                .margin(egui::vec2(8.0, 8.0)),
-                        );
+    /// Render the prompt library editor
+    ///
+    /// Prompts are edited in place and written to disk with Save.
+    ///
+    /// # Arguments
+    /// * `ui` - The egui UI context for rendering
+    pub fn render_prompts_view(&mut self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                ui.add_space(20.0);
+                ui.heading("Prompts");
+                ui.add_space(10.0);
 
-                        ui.add_space(15.0);
+                ui.label(
+                    "Reusable prompts for the chat input. Write {{name}} for parts that change; \
+                     you're asked for them when inserting the prompt with the bookmark button \
+                     or /prompt <name>.",
+                );
+                ui.add_space(10.0);
 
-                        // Agent personality
-                        ui.label(egui::RichText::new("Agent Personality (Optional):").strong());
-                        ui.label("The agent's communication style and personality:");
-                        ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    if ui.button(format!("{} Add prompt", icons::PLUS)).clicked() {
+                        self.prompt_library
+                            .prompts
+                            .push(prompt_library::PromptTemplate::default());
+                    }
+                    if ui.button(format!("{} Save", icons::FLOPPY_DISK)).clicked() {
+                        self.save_prompt_library();
+                    }
+                });
 
-                        // Convert Option<String> to String for editing
-                        let mut personality_text = config.personality.clone().unwrap_or_default();
-                        let personality_response = ui.add_sized(
-                            [ui.available_width() - 20.0, 150.0],
-                            egui::TextEdit::multiline(&mut personality_text)
-                                .hint_text(
-                                    "Enter agent personality traits (leave empty for none)...",
-                                )
-                                .margin(egui::vec2(8.0, 8.0)),
-                        );
+                if let Some((message, is_error)) = &self.prompt_message {
+                    let color = if *is_error {
+                        theme_colors(ui.ctx()).error
+                    } else {
+                        theme_colors(ui.ctx()).success
+                    };
+                    ui.add_space(5.0);
+                    ui.label(egui::RichText::new(message).size(12.0).color(color));
+                }
 
-                        // Update config if changed
-                        if personality_response.changed() {
-                            config.personality = if personality_text.trim().is_empty() {
-                                None
-                            } else {
-                                Some(personality_text)
-                            };
-                        }
+                ui.add_space(15.0);
 
-                        ui.add_space(15.0);
+                if self.prompt_library.prompts.is_empty() {
+                    ui.label(
+                        egui::RichText::new("No saved prompts").color(theme_colors(ui.ctx()).muted),
+                    );
+                    return;
+                }
 
-                        // Model selection
-                        ui.label(egui::RichText::new("LLM Model:").strong());
-                        ui.add_space(5.0);
-                        egui::ComboBox::from_label("")
-                            .selected_text(&config.model)
-                            .show_ui(ui, |ui| {
-                                ui.selectable_value(
-                                    &mut config.model,
-                                    "openai/gpt-5.1-turbo".to_string(),
-                                    "GPT-5.1 Turbo",
-                                );
-                                ui.selectable_value(
-                                    &mut config.model,
-                                    "openai/gpt-4o".to_string(),
-                                    "GPT-4o",
-                                );
-                                ui.selectable_value(
-                                    &mut config.model,
-                                    "anthropic/claude-opus-4".to_string(),
-                                    "Claude Opus 4",
-                                );
-                                ui.selectable_value(
-                                    &mut config.model,
-                                    "anthropic/claude-sonnet-4.5".to_string(),
-                                    "Claude Sonnet 4.5",
+                let mut remove = None;
+                for (i, prompt) in self.prompt_library.prompts.iter_mut().enumerate() {
+                    ui.group(|ui| {
+                        egui::Grid::new(("prompt_template", i))
+                            .num_columns(2)
+                            .spacing([10.0, 6.0])
+                            .show(ui, |ui| {
+                                ui.label("Name:");
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut prompt.name)
+                                        .hint_text("e.g. translate")
+                                        .desired_width(300.0),
                                 );
-                                ui.selectable_value(
-                                    &mut config.model,
-                                    "anthropic/claude-sonnet-4".to_string(),
-                                    "Claude Sonnet 4",
+                                ui.end_row();
+                                ui.label("Description:");
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut prompt.description)
+                                        .desired_width(500.0),
                                 );
-                                ui.selectable_value(
-                                    &mut config.model,
-                                    "openai/gpt-4".to_string(),
-                                    "GPT-4",
+                                ui.end_row();
+                                ui.label("Prompt:");
+                                ui.add(
+                                    egui::TextEdit::multiline(&mut prompt.body)
+                                        .hint_text("Translate this into {{language}}:")
+                                        .desired_rows(4)
+                                        .desired_width(500.0),
                                 );
+                                ui.end_row();
                             });
 
-                        ui.add_space(15.0);
-
-                        // Action buttons
                         ui.horizontal(|ui| {
-                            if ui.button("Save Changes").clicked() {
-                                // Apply changes to agent (will implement recreation later)
-                                self.selected_agent_index = None;
-                            }
-
-                            if ui.button("Cancel").clicked() {
-                                self.selected_agent_index = None;
+                            let variables = prompt.variables();
+                            let summary = if variables.is_empty() {
+                                "No variables".to_string()
+                            } else {
+                                format!("Variables: {}", variables.join(", "))
+                            };
+                            ui.label(
+                                egui::RichText::new(summary)
+                                    .size(12.0)
+                                    .color(theme_colors(ui.ctx()).muted),
+                            );
+                            if ui.button(format!("{} Delete", icons::TRASH)).clicked() {
+                                remove = Some(i);
                             }
                         });
-                    }
+                    });
+                    ui.add_space(5.0);
                 }
 
-                ui.add_space(20.0); // Bottom padding
+                if let Some(i) = remove {
+                    self.prompt_library.prompts.remove(i);
+                }
             });
     }
 
-    /// Render the preferences view for UI customization
-    ///
-    /// Allows configuration of:
-    /// - Theme (light/dark mode)
-    /// - Other UI preferences (future: font size, etc.)
+    /// Render the A/B test harness for system instructions
     ///
-    /// Changes are saved immediately to user profile
+    /// Runs a prompt set against two drafts of an agent's instructions (or two
+    /// agents), optionally scored by a judge model, and shows each pair of
+    /// answers with a word diff.
     ///
     /// # Arguments
     /// * `ui` - The egui UI context for rendering
-    pub fn render_preferences_view(&mut self, ui: &mut egui::Ui) {
+    pub fn render_prompt_tests_view(&mut self, ui: &mut egui::Ui) {
+        // Start from the primary agent's instructions
+        if self.prompt_test_form.agents[0].is_empty() {
+            if let Some(primary) = self
+                .agent_configs
+                .iter()
+                .find(|config| config.is_primary)
+                .or_else(|| self.agent_configs.first())
+            {
+                self.prompt_test_form.agents = [primary.id.clone(), primary.id.clone()];
+                self.prompt_test_form.reset_drafts(&self.agent_configs);
+            }
+        }
+
+        let mut run_clicked = false;
+        let mut save_clicked = false;
         egui::ScrollArea::vertical()
             .auto_shrink([false; 2])
             .show(ui, |ui| {
                 ui.add_space(20.0);
-                ui.heading("Preferences");
+                ui.heading("A/B Tests");
                 ui.add_space(10.0);
 
-                ui.label("Customize the application appearance and behavior:");
-                ui.add_space(15.0);
+                ui.label(
+                    "Send the same prompts with two versions of the instructions and compare \
+                     the answers. Nothing here changes your agents; copy the better draft into \
+                     the agent when you're done.",
+                );
+                ui.add_space(10.0);
 
-                // Theme selection
-                ui.group(|ui| {
-                    ui.label(egui::RichText::new("Theme").strong().size(16.0));
-                    ui.add_space(5.0);
-                    ui.label("Choose between light and dark mode:");
-                    ui.add_space(10.0);
+                let form = &mut self.prompt_test_form;
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut form.compare_agents, false, "Two drafts of an agent");
+                    ui.radio_value(&mut form.compare_agents, true, "Two agents");
+                });
+                ui.add_space(5.0);
 
+                let agent_name = |id: &str| {
+                    self.agent_configs
+                        .iter()
+                        .find(|config| config.id == id)
+                        .map(|config| config.name.clone())
+                        .unwrap_or_else(|| "Choose an agent".to_string())
+                };
+                let slots = if form.compare_agents { 2 } else { 1 };
+                let mut agent_changed = false;
+                for slot in 0..slots {
                     ui.horizontal(|ui| {
-                        let theme_changed = if ui
-                            .selectable_label(!self.dark_mode, format!("{} Light", icons::SUN))
-                            .clicked()
-                        {
-                            self.dark_mode = false;
-                            true
-                        } else if ui
-                            .selectable_label(self.dark_mode, format!("{} Dark", icons::MOON))
-                            .clicked()
-                        {
-                            self.dark_mode = true;
-                            true
+                        ui.label(if form.compare_agents {
+                            ["Agent A:", "Agent B:"][slot]
                         } else {
-                            false
-                        };
-
-                        // Save theme preference to user profile
-                        if theme_changed {
-                            let storage = Arc::clone(&self.deps.storage);
-                            let theme = if self.dark_mode { "dark" } else { "light" }.to_string();
-                            let runtime = self
-                                .deps
-                                .runtime
-                                .as_ref()
-                                .expect("Runtime is required for RustbotApp");
-
-                            runtime.spawn(async move {
-                                // Load current profile
-                                if let Ok(mut profile) = storage.load_user_profile().await {
-                                    // Update theme
-                                    profile.theme = theme.clone();
-
-                                    // Save updated profile
-                                    if let Err(e) = storage.save_user_profile(&profile).await {
-                                        tracing::error!("Failed to save theme preference: {}", e);
-                                    } else {
-                                        tracing::info!("Theme preference saved: {}", theme);
-                                    }
+                            "Agent:"
+                        });
+                        egui::ComboBox::from_id_salt(("prompt_test_agent", slot))
+                            .selected_text(agent_name(&form.agents[slot]))
+                            .show_ui(ui, |ui| {
+                                for config in &self.agent_configs {
+                                    agent_changed |= ui
+                                        .selectable_value(
+                                            &mut form.agents[slot],
+                                            config.id.clone(),
+                                            &config.name,
+                                        )
+                                        .changed();
                                 }
                             });
-                        }
                     });
+                }
 
+                if !form.compare_agents {
+                    if agent_changed {
+                        form.reset_drafts(&self.agent_configs);
+                    }
                     ui.add_space(5.0);
-                    ui.label(
-                        egui::RichText::new(if self.dark_mode {
-                            "Currently using Dark theme"
-                        } else {
-                            "Currently using Light theme"
-                        })
-                        .size(12.0)
-                        .color(egui::Color32::from_rgb(100, 100, 100)),
-                    );
+                    ui.columns(2, |columns| {
+                        for (i, column) in columns.iter_mut().enumerate() {
+                            column.label(["Instructions A:", "Instructions B:"][i]);
+                            column.add(
+                                egui::TextEdit::multiline(&mut form.instructions[i])
+                                    .desired_rows(8)
+                                    .desired_width(f32::INFINITY),
+                            );
+                        }
+                    });
+                    if ui.small_button("Reset drafts to the agent").clicked() {
+                        form.reset_drafts(&self.agent_configs);
+                    }
+                }
+
+                ui.add_space(10.0);
+                ui.label(format!(
+                    "Prompts (separate them with a line of {}):",
+                    prompt_test::PROMPT_SEPARATOR
+                ));
+                ui.add(
+                    egui::TextEdit::multiline(&mut form.prompts)
+                        .hint_text("Summarize this paragraph: …\n---\nWrite a haiku about Rust")
+                        .desired_rows(6)
+                        .desired_width(f32::INFINITY),
+                );
+
+                ui.add_space(10.0);
+                ui.checkbox(&mut form.judge, "Score the answers with a judge model");
+                if form.judge {
+                    egui::Grid::new("prompt_test_judge")
+                        .num_columns(2)
+                        .spacing([10.0, 6.0])
+                        .show(ui, |ui| {
+                            ui.label("Model:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut form.judge_model)
+                                    .hint_text("Default model")
+                                    .desired_width(300.0),
+                            );
+                            ui.end_row();
+                            ui.label("Criteria:");
+                            ui.add(
+                                egui::TextEdit::multiline(&mut form.criteria)
+                                    .hint_text(
+                                        "e.g. accurate, concise, answers in the user's language",
+                                    )
+                                    .desired_rows(2)
+                                    .desired_width(500.0),
+                            );
+                            ui.end_row();
+                        });
+                }
+
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    let running = form.rx.is_some();
+                    if ui
+                        .add_enabled(
+                            !running,
+                            egui::Button::new(format!("{} Run test", icons::PLAY)),
+                        )
+                        .clicked()
+                    {
+                        run_clicked = true;
+                    }
+                    if let Some((done, total)) = &form.progress {
+                        ui.spinner();
+                        ui.label(format!(
+                            "{} of {} prompts",
+                            done.load(std::sync::atomic::Ordering::Relaxed),
+                            total
+                        ));
+                    }
+                    if form.report.is_some()
+                        && ui
+                            .button(format!("{} Save report", icons::FLOPPY_DISK))
+                            .on_hover_text("Write the report as Markdown to ~/.rustbot/exports")
+                            .clicked()
+                    {
+                        save_clicked = true;
+                    }
                 });
 
-                ui.add_space(20.0);
+                if let Some((message, is_error)) = &form.message {
+                    let color = if *is_error {
+                        theme_colors(ui.ctx()).error
+                    } else {
+                        theme_colors(ui.ctx()).success
+                    };
+                    ui.add_space(5.0);
+                    ui.label(egui::RichText::new(message).size(12.0).color(color));
+                }
 
-                // Future preferences can be added here
-                // Example: Font size, animations, etc.
+                if let Some(report) = &form.report {
+                    ui.add_space(15.0);
+                    ui.separator();
+                    Self::render_prompt_test_report(ui, report, &mut form.selected_case);
+                }
+            });
+
+        if run_clicked {
+            let ctx = ui.ctx().clone();
+            self.start_prompt_test(&ctx);
+        }
+        if save_clicked {
+            self.prompt_test_form.message = Some(match self.save_prompt_test_report() {
+                Ok(path) => (format!("Saved the report to {}", path.display()), false),
+                Err(e) => (format!("Couldn't save the report: {}", e), true),
+            });
+        }
+    }
+
+    /// Summary of an A/B test and one prompt's answers side by side with a
+    /// word diff from A to B
+    fn render_prompt_test_report(
+        ui: &mut egui::Ui,
+        report: &prompt_test::TestReport,
+        selected: &mut usize,
+    ) {
+        let colors = theme_colors(ui.ctx());
+        let summary = report.summary();
+        let [a, b] = &report.labels;
+
+        ui.heading("Report");
+        ui.add_space(5.0);
+        egui::Grid::new("prompt_test_summary")
+            .num_columns(3)
+            .spacing([20.0, 4.0])
+            .show(ui, |ui| {
+                ui.label("");
+                ui.strong(a);
+                ui.strong(b);
+                ui.end_row();
+                ui.label("Wins:");
+                ui.label(summary.wins[0].to_string());
+                ui.label(summary.wins[1].to_string());
+                ui.end_row();
+                ui.label("Average score:");
+                for score in summary.mean_scores {
+                    ui.label(score.map_or("–".to_string(), |score| format!("{:.1}", score)));
+                }
+                ui.end_row();
             });
+        ui.label(format!("Ties: {}", summary.ties));
+        if summary.failures > 0 {
+            ui.label(
+                egui::RichText::new(format!("{} failed requests", summary.failures))
+                    .color(colors.error),
+            );
+        }
+
+        if report.cases.is_empty() {
+            return;
+        }
+        *selected = (*selected).min(report.cases.len() - 1);
+
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.label("Prompt:");
+            let preview = |prompt: &str| prompt.lines().next().unwrap_or_default().to_string();
+            egui::ComboBox::from_id_salt("prompt_test_case")
+                .selected_text(format!(
+                    "{}. {}",
+                    *selected + 1,
+                    preview(&report.cases[*selected].prompt)
+                ))
+                .width(400.0)
+                .show_ui(ui, |ui| {
+                    for (i, case) in report.cases.iter().enumerate() {
+                        ui.selectable_value(
+                            selected,
+                            i,
+                            format!("{}. {}", i + 1, preview(&case.prompt)),
+                        );
+                    }
+                });
+        });
+
+        let case = &report.cases[*selected];
+        ui.add_space(5.0);
+        ui.label(egui::RichText::new(&case.prompt).color(colors.muted));
+
+        match &case.verdict {
+            Some(Ok(verdict)) => {
+                ui.label(format!(
+                    "Judge: {} {}/10, {} {}/10. {}",
+                    a, verdict.scores[0], b, verdict.scores[1], verdict.reason
+                ));
+            }
+            Some(Err(e)) => {
+                ui.label(egui::RichText::new(format!("Judge failed: {}", e)).color(colors.error));
+            }
+            None => {}
+        }
+
+        ui.add_space(5.0);
+        ui.columns(2, |columns| {
+            for (i, column) in columns.iter_mut().enumerate() {
+                column.strong(&report.labels[i]);
+                match &case.answers[i] {
+                    Ok(answer) => {
+                        column.label(answer);
+                    }
+                    Err(e) => {
+                        column.label(egui::RichText::new(e).color(colors.error));
+                    }
+                }
+            }
+        });
+
+        if let [Ok(first), Ok(second)] = &case.answers {
+            ui.add_space(10.0);
+            ui.strong(format!("Changes from {} to {}", a, b));
+            let mut diff = egui::text::LayoutJob::default();
+            let font = egui::TextStyle::Body.resolve(ui.style());
+            for span in prompt_test::word_diff(first, second) {
+                let (text, format) = match span {
+                    DiffSpan::Same(text) => {
+                        (text, egui::TextFormat::simple(font.clone(), colors.text))
+                    }
+                    DiffSpan::Removed(text) => (
+                        text,
+                        egui::TextFormat {
+                            font_id: font.clone(),
+                            color: colors.error,
+                            strikethrough: egui::Stroke::new(1.0, colors.error),
+                            ..Default::default()
+                        },
+                    ),
+                    DiffSpan::Added(text) => (
+                        text,
+                        egui::TextFormat {
+                            font_id: font.clone(),
+                            color: colors.success,
+                            underline: egui::Stroke::new(1.0, colors.success),
+                            ..Default::default()
+                        },
+                    ),
+                };
+                diff.append(text, 0.0, format);
+            }
+            diff.wrap.max_width = ui.available_width();
+            ui.label(diff);
+        }
     }
 }